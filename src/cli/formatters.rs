@@ -4,20 +4,28 @@
 //! the concerns of data calculation from presentation.
 
 use crate::db::models::AssetType;
+use crate::pricing::fx::FxConversion;
+use crate::reports::columns::ComputedColumn;
 use crate::reports::PortfolioReport;
 use crate::utils::format_currency;
+use anyhow::Result;
 use colored::Colorize;
 use rust_decimal::Decimal;
 use serde::Serialize;
 use std::collections::BTreeMap;
 use tabled::{
+    builder::Builder,
     settings::{object::Columns, Alignment, Style},
-    Table, Tabled,
+    Tabled,
 };
 
 /// Format a portfolio report for JSON output
 #[allow(dead_code)] // Planned for JSON output support
-pub fn format_portfolio_json(report: &PortfolioReport) -> String {
+pub fn format_portfolio_json(
+    report: &PortfolioReport,
+    columns: &[ComputedColumn],
+    table: &TableArgs,
+) -> String {
     #[derive(Serialize)]
     struct JsonPosition {
         ticker: String,
@@ -29,6 +37,14 @@ pub fn format_portfolio_json(report: &PortfolioReport) -> String {
         current_value: Option<String>,
         unrealized_pl: Option<String>,
         unrealized_pl_pct: Option<String>,
+        /// Date of the manual valuation used as the price
+        #[serde(skip_serializing_if = "Option::is_none")]
+        valued_on: Option<String>,
+        /// Rate a price quoted abroad was converted at
+        #[serde(skip_serializing_if = "Option::is_none")]
+        fx: Option<FxConversion>,
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        columns: BTreeMap<String, Option<String>>,
     }
 
     #[derive(Serialize)]
@@ -40,9 +56,10 @@ pub fn format_portfolio_json(report: &PortfolioReport) -> String {
         total_pl_pct: String,
     }
 
-    let positions = report
-        .positions
-        .iter()
+    let positions = table
+        .paginate(report.positions.iter().collect())
+        .rows
+        .into_iter()
         .map(|p| JsonPosition {
             ticker: p.asset.ticker.clone(),
             asset_type: p.asset.asset_type.as_str().to_string(),
@@ -53,6 +70,12 @@ pub fn format_portfolio_json(report: &PortfolioReport) -> String {
             current_value: p.current_value.map(|v: Decimal| v.to_string()),
             unrealized_pl: p.unrealized_pl.map(|pl: Decimal| pl.to_string()),
             unrealized_pl_pct: p.unrealized_pl_pct.map(|pl: Decimal| pl.to_string()),
+            valued_on: p.valued_on.map(|d| d.to_string()),
+            fx: p.fx.clone(),
+            columns: columns
+                .iter()
+                .map(|c| (c.name.clone(), c.value(p, report).map(|v| v.to_string())))
+                .collect(),
         })
         .collect();
 
@@ -68,8 +91,16 @@ pub fn format_portfolio_json(report: &PortfolioReport) -> String {
         .unwrap_or_else(|e| format!(r#"{{"error": "JSON serialization failed: {}"}}"#, e))
 }
 
-/// Format a portfolio report for terminal table output
-pub fn format_portfolio_table(report: &PortfolioReport, asset_type_filter: Option<&str>) -> String {
+/// Format a portfolio report for terminal table output, with the
+/// user-defined `columns` after the built-in ones. `table` pages through the
+/// positions in display order (asset type, then ticker); subtotals and the
+/// summary still cover the whole portfolio.
+pub fn format_portfolio_table(
+    report: &PortfolioReport,
+    asset_type_filter: Option<&str>,
+    columns: &[ComputedColumn],
+    table: &TableArgs,
+) -> Result<String> {
     let mut output = String::new();
 
     // Display header
@@ -96,6 +127,7 @@ pub fn format_portfolio_table(report: &PortfolioReport, asset_type_filter: Optio
     for positions in grouped.values_mut() {
         positions.sort_by(|a, b| a.asset.ticker.cmp(&b.asset.ticker));
     }
+    let page = table.paginate(grouped.values().flatten().copied().collect());
 
     // Display positions table
     #[derive(Tabled)]
@@ -118,8 +150,23 @@ pub fn format_portfolio_table(report: &PortfolioReport, asset_type_filter: Optio
         return_pct: String,
     }
 
+    let headers: Vec<String> = PositionRow::headers()
+        .into_iter()
+        .map(|h| h.to_string())
+        .chain(columns.iter().map(|c| c.name.clone()))
+        .collect();
+    let selected = select_columns(&headers, &table.columns)?;
+
     // Render each asset type group
     for (asset_type, positions) in &grouped {
+        let shown: Vec<_> = positions
+            .iter()
+            .filter(|p| page.rows.iter().any(|r| std::ptr::eq(*r, **p)))
+            .collect();
+        if shown.is_empty() {
+            continue;
+        }
+
         // Calculate subtotals for this asset type
         let mut subtotal_cost = Decimal::ZERO;
         let mut subtotal_value = Decimal::ZERO;
@@ -145,18 +192,18 @@ pub fn format_portfolio_table(report: &PortfolioReport, asset_type_filter: Optio
             asset_type.as_str()
         ));
 
-        if positions.is_empty() {
-            output.push_str("No positions\n");
-            continue;
-        }
-
-        let rows: Vec<PositionRow> = positions
+        let rows: Vec<PositionRow> = shown
             .iter()
             .map(|p| {
-                let price_str = p
-                    .current_price
-                    .map(|pr: Decimal| format_currency(pr))
-                    .unwrap_or_else(|| "N/A".to_string());
+                let price_str = match (p.current_price, p.valued_on) {
+                    (Some(pr), Some(_)) => format!("{}{}", format_currency(pr), "*".yellow()),
+                    (Some(pr), None) => format_currency(pr),
+                    (None, _) => "N/A".to_string(),
+                };
+                let price_str = match &p.fx {
+                    Some(_) => format!("{}{}", price_str, "†".cyan()),
+                    None => price_str,
+                };
 
                 let value_str = p
                     .current_value
@@ -188,7 +235,12 @@ pub fn format_portfolio_table(report: &PortfolioReport, asset_type_filter: Optio
 
                 PositionRow {
                     ticker: p.asset.ticker.clone(),
-                    quantity: format!("{:.2}", p.quantity),
+                    // Coins are held in fractions down to satoshis
+                    quantity: if p.asset.asset_type == AssetType::Crypto {
+                        p.quantity.normalize().to_string()
+                    } else {
+                        format!("{:.2}", p.quantity)
+                    },
                     avg_cost: format_currency(p.average_cost),
                     total_cost: format_currency(p.total_cost),
                     price: price_str,
@@ -199,12 +251,28 @@ pub fn format_portfolio_table(report: &PortfolioReport, asset_type_filter: Optio
             })
             .collect();
 
-        let mut table = Table::new(&rows);
-        table.with(Style::modern());
-        // Right-align all columns except Ticker (0)
-        table.modify(Columns::new(1..), Alignment::right());
+        let mut builder = Builder::default();
+        builder.push_record(selected.iter().map(|&i| headers[i].clone()));
+        for (row, p) in rows.iter().zip(shown.iter()) {
+            let fields: Vec<String> = row
+                .fields()
+                .into_iter()
+                .map(|f| f.to_string())
+                .chain(columns.iter().map(|c| c.display(c.value(p, report))))
+                .collect();
+            builder.push_record(selected.iter().map(|&i| fields[i].clone()));
+        }
+
+        let mut rendered = builder.build();
+        rendered.with(Style::modern());
+        // Right-align all columns except Ticker
+        for (col, &i) in selected.iter().enumerate() {
+            if i != 0 {
+                rendered.modify(Columns::one(col), Alignment::right());
+            }
+        }
 
-        output.push_str(&table.to_string());
+        output.push_str(&rendered.to_string());
 
         // Display subtotals for this asset type
         output.push_str(&format!("\n{} Subtotal", "─".repeat(40).bright_black()));
@@ -230,6 +298,53 @@ pub fn format_portfolio_table(report: &PortfolioReport, asset_type_filter: Optio
         output.push('\n');
     }
 
+    if let Some(footer) = page.footer() {
+        output.push_str(&format!("\n{}\n", footer.dimmed()));
+    }
+
+    let valued: Vec<String> = report
+        .positions
+        .iter()
+        .filter_map(|p| {
+            p.valued_on
+                .map(|d| format!("{} on {}", p.asset.ticker, d.format("%d/%m/%Y")))
+        })
+        .collect();
+    if !valued.is_empty() {
+        output.push_str(&format!(
+            "\n{} {}\n",
+            "*".yellow(),
+            format!("Manual valuation, no market price: {}", valued.join(", ")).dimmed()
+        ));
+    }
+    let converted: Vec<String> = report
+        .positions
+        .iter()
+        .filter_map(|p| {
+            p.fx.as_ref().map(|fx| {
+                format!(
+                    "{} in {} at {} on {}",
+                    p.asset.ticker,
+                    fx.currency,
+                    fx.rate,
+                    fx.rate_date.format("%d/%m/%Y")
+                )
+            })
+        })
+        .collect();
+    if !converted.is_empty() {
+        output.push_str(&format!(
+            "{}{} {}\n",
+            if valued.is_empty() { "\n" } else { "" },
+            "†".cyan(),
+            format!(
+                "Quoted abroad, converted at the PTAX: {}",
+                converted.join(", ")
+            )
+            .dimmed()
+        ));
+    }
+
     // Display overall summary
     output.push_str(&format!(
         "\n\n{} Portfolio Summary",
@@ -264,7 +379,7 @@ pub fn format_portfolio_table(report: &PortfolioReport, asset_type_filter: Optio
         return_colored
     ));
 
-    output
+    Ok(output)
 }
 
 /// Get friendly name for asset type
@@ -282,6 +397,7 @@ fn asset_type_name(asset_type: &AssetType) -> &'static str {
         AssetType::GovBond => "Government Bonds",
         AssetType::Option => "Options",
         AssetType::TermContract => "Term Contracts",
+        AssetType::Crypto => "Crypto",
         AssetType::Unknown => "Unknown",
     }
 }
@@ -295,6 +411,161 @@ pub fn format_empty_portfolio() -> String {
     )
 }
 
+/// Pagination, column selection and paging shared by table-producing commands
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct TableArgs {
+    /// Show at most N rows
+    #[arg(long)]
+    pub limit: Option<usize>,
+
+    /// Skip the first N rows
+    #[arg(long, default_value_t = 0)]
+    pub offset: usize,
+
+    /// Columns to show, comma-separated (e.g., date,ticker,amount)
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// Print directly instead of through a pager
+    #[arg(long)]
+    pub no_pager: bool,
+}
+
+/// One page of rows, remembering where it sits in the full result
+#[derive(Debug)]
+pub struct Page<T> {
+    pub rows: Vec<T>,
+    pub offset: usize,
+    pub total: usize,
+}
+
+impl TableArgs {
+    /// Apply --offset and --limit
+    pub fn paginate<T>(&self, rows: Vec<T>) -> Page<T> {
+        let total = rows.len();
+        let rows = rows
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .collect();
+        Page {
+            rows,
+            offset: self.offset,
+            total,
+        }
+    }
+}
+
+impl<T> Page<T> {
+    /// "Showing 51-100 of 240" hint, when rows were left out
+    pub fn footer(&self) -> Option<String> {
+        if self.offset == 0 && self.rows.len() == self.total {
+            return None;
+        }
+        if self.rows.is_empty() {
+            return Some(format!(
+                "No rows at offset {} ({} in total)",
+                self.offset, self.total
+            ));
+        }
+        let last = self.offset + self.rows.len();
+        let mut text = format!("Showing {}-{} of {}", self.offset + 1, last, self.total);
+        if last < self.total {
+            text.push_str(&format!(" (next page: --offset {})", last));
+        }
+        Some(text)
+    }
+}
+
+/// Column name accepted by --columns: lowercase words joined by '_'
+fn column_key(header: &str) -> String {
+    header
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Indexes into `headers` of the `columns` requested with --columns, in the
+/// order given (all headers when empty)
+fn select_columns<H: AsRef<str>>(headers: &[H], columns: &[String]) -> Result<Vec<usize>> {
+    if columns.is_empty() {
+        return Ok((0..headers.len()).collect());
+    }
+    let keys: Vec<String> = headers.iter().map(|h| column_key(h.as_ref())).collect();
+    columns
+        .iter()
+        .map(|c| {
+            let wanted = column_key(c);
+            keys.iter().position(|k| *k == wanted).ok_or_else(|| {
+                anyhow::anyhow!("Unknown column '{}'. Available: {}", c, keys.join(", "))
+            })
+        })
+        .collect()
+}
+
+/// Render rows as a rounded table, keeping only the `columns` requested
+/// (all when empty) and right-aligning the headers listed in `right`.
+pub fn render_table<T: Tabled>(rows: &[T], columns: &[String], right: &[&str]) -> Result<String> {
+    let headers = T::headers();
+    let selected = select_columns(&headers, columns)?;
+
+    let mut builder = Builder::default();
+    builder.push_record(selected.iter().map(|&i| headers[i].to_string()));
+    for row in rows {
+        let fields = row.fields();
+        builder.push_record(selected.iter().map(|&i| fields[i].to_string()));
+    }
+
+    let mut table = builder.build();
+    table.with(Style::rounded());
+    for (col, &i) in selected.iter().enumerate() {
+        if right.contains(&headers[i].as_ref()) {
+            table.modify(Columns::one(col), Alignment::right());
+        }
+    }
+    Ok(table.to_string())
+}
+
+/// Print command output, through `$PAGER` (default `less -FRX`) when stdout is
+/// a terminal and the text is taller than it.
+pub fn print_paged(text: &str, no_pager: bool) {
+    use std::io::{IsTerminal, Write};
+
+    let fits = crossterm::terminal::size()
+        .map(|(_, rows)| text.lines().count() < rows as usize)
+        .unwrap_or(true);
+    if no_pager || fits || !std::io::stdout().is_terminal() {
+        print!("{}", text);
+        return;
+    }
+
+    let pager = std::env::var("PAGER").unwrap_or_else(|_| "less -FRX".to_string());
+    let mut parts = pager.split_whitespace();
+    let Some(program) = parts.next() else {
+        print!("{}", text);
+        return;
+    };
+    match std::process::Command::new(program)
+        .args(parts)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+    {
+        Ok(mut child) => {
+            if let Some(mut stdin) = child.stdin.take() {
+                // The pager closing early (e.g. 'q') is not an error
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            let _ = child.wait();
+        }
+        Err(e) => {
+            tracing::debug!("Pager '{}' unavailable: {}", pager, e);
+            print!("{}", text);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -346,6 +617,8 @@ mod tests {
             current_value: Some(current_value),
             unrealized_pl: Some(unrealized_pl),
             unrealized_pl_pct,
+            valued_on: None,
+            fx: None,
         }
     }
 
@@ -399,7 +672,7 @@ mod tests {
             total_pl_pct: ((total_value - total_cost) / total_cost) * Decimal::from(100),
         };
 
-        let output = format_portfolio_table(&report, None);
+        let output = format_portfolio_table(&report, None, &[], &TableArgs::default()).unwrap();
 
         // Verify grouping by asset type
         assert!(output.contains("## Stocks (STOCK)"));
@@ -449,7 +722,7 @@ mod tests {
             total_pl_pct: ((total_value - total_cost) / total_cost) * Decimal::from(100),
         };
 
-        let output = format_portfolio_table(&report, None);
+        let output = format_portfolio_table(&report, None, &[], &TableArgs::default()).unwrap();
 
         // Find positions in output - they should be in alphabetical order
         let bbas_idx = output.find("BBAS3").unwrap();
@@ -498,7 +771,7 @@ mod tests {
             total_pl_pct: ((total_value - total_cost) / total_cost) * Decimal::from(100),
         };
 
-        let output = format_portfolio_table(&report, None);
+        let output = format_portfolio_table(&report, None, &[], &TableArgs::default()).unwrap();
 
         // Verify subtotals are shown
         assert!(
@@ -564,7 +837,8 @@ mod tests {
             total_pl_pct: ((total_value - total_cost) / total_cost) * Decimal::from(100),
         };
 
-        let output = format_portfolio_table(&report, Some("STOCK"));
+        let output =
+            format_portfolio_table(&report, Some("STOCK"), &[], &TableArgs::default()).unwrap();
 
         // Should only show Stocks group
         assert!(
@@ -615,7 +889,7 @@ mod tests {
             total_pl_pct: ((total_value - total_cost) / total_cost) * Decimal::from(100),
         };
 
-        let output = format_portfolio_table(&report, None);
+        let output = format_portfolio_table(&report, None, &[], &TableArgs::default()).unwrap();
 
         // Verify overall summary section
         assert!(
@@ -648,4 +922,136 @@ mod tests {
         assert_eq!(asset_type_name(&AssetType::TermContract), "Term Contracts");
         assert_eq!(asset_type_name(&AssetType::Unknown), "Unknown");
     }
+
+    #[test]
+    fn test_table_args_paginate_and_footer() {
+        let args = TableArgs {
+            limit: Some(2),
+            offset: 1,
+            ..Default::default()
+        };
+        let page = args.paginate(vec![1, 2, 3, 4]);
+        assert_eq!(page.rows, vec![2, 3]);
+        assert_eq!(
+            page.footer().as_deref(),
+            Some("Showing 2-3 of 4 (next page: --offset 3)")
+        );
+
+        let all = TableArgs::default().paginate(vec![1, 2]);
+        assert!(all.footer().is_none());
+
+        let past_end = TableArgs {
+            offset: 5,
+            ..Default::default()
+        }
+        .paginate(vec![1, 2]);
+        assert_eq!(
+            past_end.footer().as_deref(),
+            Some("No rows at offset 5 (2 in total)")
+        );
+    }
+
+    #[test]
+    fn test_render_table_selects_columns() {
+        #[derive(Tabled)]
+        struct Row {
+            #[tabled(rename = "Date")]
+            date: &'static str,
+            #[tabled(rename = "Realized P&L")]
+            realized: &'static str,
+            #[tabled(rename = "Notes")]
+            notes: &'static str,
+        }
+        let rows = vec![Row {
+            date: "2024-01-10",
+            realized: "R$ 10,00",
+            notes: "hidden",
+        }];
+
+        let columns = vec!["realized_p_l".to_string(), "DATE".to_string()];
+        let out = render_table(&rows, &columns, &["Realized P&L"]).unwrap();
+        assert!(out.contains("Realized P&L"));
+        assert!(out.contains("2024-01-10"));
+        assert!(!out.contains("hidden"));
+        // Requested order is kept
+        assert!(out.find("Realized").unwrap() < out.find("Date").unwrap());
+
+        let err = render_table(&rows, &["amount".to_string()], &[]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Available: date, realized_p_l, notes"));
+    }
+
+    #[test]
+    fn test_portfolio_table_pages_positions_in_display_order() {
+        control::set_override(false); // Disable colors for testing
+
+        let positions = vec![
+            create_test_position(
+                "PETR4",
+                AssetType::Stock,
+                Decimal::from(100),
+                Decimal::from(20),
+            ),
+            create_test_position(
+                "VALE3",
+                AssetType::Stock,
+                Decimal::from(50),
+                Decimal::from(15),
+            ),
+            create_test_position(
+                "BBAS3",
+                AssetType::Stock,
+                Decimal::from(75),
+                Decimal::from(25),
+            ),
+            create_test_position(
+                "HFOF11",
+                AssetType::Fii,
+                Decimal::from(10),
+                Decimal::from(100),
+            ),
+            create_test_position(
+                "BRCR11",
+                AssetType::Fii,
+                Decimal::from(20),
+                Decimal::from(110),
+            ),
+        ];
+        let report = PortfolioReport {
+            positions,
+            total_cost: Decimal::ZERO,
+            total_value: Decimal::ZERO,
+            total_pl: Decimal::ZERO,
+            total_pl_pct: Decimal::ZERO,
+        };
+        let args = TableArgs {
+            limit: Some(2),
+            offset: 2,
+            columns: vec!["ticker".to_string(), "value".to_string()],
+            no_pager: true,
+        };
+
+        let output = format_portfolio_table(&report, None, &[], &args).unwrap();
+
+        // Stocks BBAS3, PETR4, VALE3 come before FIIs BRCR11, HFOF11
+        assert!(output.contains("VALE3"));
+        assert!(output.contains("BRCR11"));
+        assert!(!output.contains("PETR4"));
+        assert!(!output.contains("HFOF11"));
+        assert!(!output.contains("Avg Cost"));
+        assert!(output.contains("Showing 3-4 of 5"));
+
+        let err = format_portfolio_table(
+            &report,
+            None,
+            &[],
+            &TableArgs {
+                columns: vec!["weight".to_string()],
+                ..TableArgs::default()
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Unknown column 'weight'"));
+    }
 }
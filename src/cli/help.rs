@@ -29,10 +29,59 @@ pub fn render_help<W: Write>(mut out: W, _opts: &RenderOpts) -> io::Result<()> {
     )?;
     writeln!(
         out,
-        "  {:24} - Show performance (MTD/QTD/YTD/1Y/ALL)",
+        "  {:24} - Prices, income, actions and issues since last run; what's due",
+        "whatsnew"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Show performance (MTD/QTD/YTD/1Y/ALL, --method twr, --vs, --what-if, --strict)",
         "performance show <period>"
     )?;
-    writeln!(out, "  {:24} - Show income by asset", "income show [year]")?;
+    writeln!(
+        out,
+        "  {:24} - Value, invested and income per month since the first trade",
+        "performance history"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Volatility, max drawdown, Sharpe and Sortino vs CDI",
+        "performance risk [--period]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - 2-4 held assets side by side (return, yield, drawdown)",
+        "compare <T1> <T2> [--period]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - What to buy with an aporte to reach the target allocation",
+        "rebalance suggest --amount"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Year in review vs benchmarks; --pdf to share it",
+        "reports annual <year>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Show income and 12-month yield by asset",
+        "income show [year]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Income events vs cash credited",
+        "income reconcile [year]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Next 12 months of income: cadence, trend, 80% ranges",
+        "income forecast [-m N]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Forecast net of tax; --dividend-tax N for a scenario",
+        "income forecast --net"
+    )?;
     writeln!(
         out,
         "  {:24} - Filter by asset type (fii, stock, fiagro)",
@@ -40,9 +89,44 @@ pub fn render_help<W: Write>(mut out: W, _opts: &RenderOpts) -> io::Result<()> {
     )?;
     writeln!(
         out,
-        "  {:24} - Import trades or movimentacao (preview with --dry-run)",
+        "  {:24} - Positions per corretora (XP, Inter, ...)",
+        "portfolio show --by-broker"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Positions table: enter/t/p/i row shortcuts",
+        "portfolio browse"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Value by sector/segment, flags concentration",
+        "portfolio exposure [--by]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Portfolio/performance/income for a tag group",
+        "... show --tag <tag>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Import trades, movimentacao, proventos or broker notes (--dry-run)",
         "import <file> [--dry-run]"
     )?;
+    writeln!(
+        out,
+        "  {:24} - Import any CSV/Excel with a TOML column mapping",
+        "import --format custom --mapping"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Import trades and income from a broker's OFX statement",
+        "import <file.ofx>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - List imports, or revert everything one added",
+        "import list / undo <id>"
+    )?;
 
     writeln!(out)?;
     writeln!(out, "{}", "Import & sync:".bold())?;
@@ -51,11 +135,56 @@ pub fn render_help<W: Write>(mut out: W, _opts: &RenderOpts) -> io::Result<()> {
         "  {:24} - Import opening balances from IRPF PDF",
         "import-irpf <file> <year>"
     )?;
+    writeln!(
+        out,
+        "  {:24} - Auto-import files from watched folder",
+        "watch-imports [--once]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Run price updates and syncs for TUI sessions",
+        "daemon [--status]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Sync from the B3 investor API",
+        "sync-b3 [--from DATE]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Fetch quotes; retries only failed tickers",
+        "prices update [--all]"
+    )?;
     writeln!(
         out,
         "  {:24} - Import COTAHIST yearly prices",
         "prices import-b3 <year>"
     )?;
+    writeln!(
+        out,
+        "  {:24} - Multi-year closes for all traded assets",
+        "prices backfill [--from]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - BCB PTAX rates (USD, EUR...) in reais",
+        "prices update-fx [CUR]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - FII P/VP from CVM NAV reports",
+        "prices update-nav | pvp"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Price providers per held asset",
+        "prices coverage [--live]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Live quotes, day change and P&L",
+        "prices watch [--interval]"
+    )?;
     writeln!(
         out,
         "  {:24} - Sync asset metadata registry",
@@ -69,16 +198,81 @@ pub fn render_help<W: Write>(mut out: W, _opts: &RenderOpts) -> io::Result<()> {
         "  {:24} - Find and resolve import issues",
         "inconsistencies list/resolve"
     )?;
+    writeln!(
+        out,
+        "  {:24} - Month-by-month heatmap of computed vs. statement positions",
+        "inconsistencies positions"
+    )?;
     writeln!(
         out,
         "  {:24} - Resolve unknown tickers",
         "tickers list-unknown/resolve"
     )?;
+    writeln!(
+        out,
+        "  {:24} - Dated B3 instrument files for old trades",
+        "tickers versions"
+    )?;
     writeln!(
         out,
         "  {:24} - Apply unapplied corporate actions",
         "actions apply [ticker]"
     )?;
+    writeln!(
+        out,
+        "  {:24} - Take an applied bonus back out of positions",
+        "actions unapply <id>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Fetch splits and bonuses from B3 for held stocks",
+        "actions detect [ticker]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Review, apply and unapply corporate actions",
+        "actions review"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Rebuild snapshots and tax caches from scratch",
+        "recalculate [--through Y]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Debug an import file (Excel/CSV/PDF)",
+        "inspect <file>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Version, DB stats, cache freshness, provider checks",
+        "version --full"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Own/spouse/corporate accounts (--portfolio NAME)",
+        "portfolios list/add/assign"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Separate IRPF per spouse (--declarant NAME)",
+        "tax report --by-declarant"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - IRPF items by code, with discriminação",
+        "tax report --bens-e-direitos"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Try imports/sales on a copy of the database",
+        "--sandbox, sandbox status"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Replay bundled prices, offline, in the sandbox",
+        "--demo"
+    )?;
 
     writeln!(out)?;
     writeln!(out, "{}", "Manage & maintain:".bold())?;
@@ -87,6 +281,26 @@ pub fn render_help<W: Write>(mut out: W, _opts: &RenderOpts) -> io::Result<()> {
         "  {:24} - Manage asset registry",
         "assets add/set-type/set-name"
     )?;
+    writeln!(
+        out,
+        "  {:24} - Validate issuer CNPJs (razão social, situação)",
+        "assets enrich-cnpj"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Value an asset with no market price (FIP, delisted)",
+        "assets set-value"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Value an asset quoted abroad in BRL at the PTAX",
+        "assets set-currency"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Group assets by goal (aposentadoria, reserva)",
+        "assets tag/untag/tags"
+    )?;
     writeln!(
         out,
         "  {:24} - Manage corporate actions",
@@ -97,20 +311,130 @@ pub fn render_help<W: Write>(mut out: W, _opts: &RenderOpts) -> io::Result<()> {
         "  {:24} - Add manual buy/sell entries",
         "transactions add"
     )?;
+    writeln!(
+        out,
+        "  {:24} - Enter many trades at once in $EDITOR",
+        "transactions add --editor"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Filter, inspect and fix recorded trades",
+        "transactions list/show/edit/delete"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Trade idea journal with realized outcome",
+        "journal add/list/link"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Open term contracts vs exposure ceiling",
+        "terms show | set <id>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Bought calls/puts, exercises and strikes",
+        "options show | process | set"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Subscription rights and receipts held",
+        "subscriptions"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Tesouro Direto marking, custody and IR",
+        "tesouro show | redemptions <year>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - CDB, LCI, LCA, CRI, CRA accrued by indexer",
+        "fixed-income show | set"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Portable JSON backup and restore",
+        "db export-json/import-json"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Encrypt the database at rest",
+        "db encrypt | decrypt"
+    )?;
 
     writeln!(out)?;
     writeln!(out, "{}", "Reports & tax:".bold())?;
     writeln!(
         out,
-        "  {:24} - Generate IRPF report (CSV export available)",
+        "  {:24} - Generate IRPF report, BDR carnê-leão included (CSV export)",
         "tax report <year>"
     )?;
     writeln!(out, "  {:24} - Condensed tax summary", "tax summary <year>")?;
+    writeln!(
+        out,
+        "  {:24} - Income or sales per broker account",
+        "income|tax summary --by-broker"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Monthly stock sales vs R$20k exemption",
+        "tax preview"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Exemption used this month; --sell <amount> to check a sale",
+        "tax exemption"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - IRRF withheld and what can be recovered",
+        "tax withholding <year>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Export sales as GCAP operations (CSV)",
+        "tax gcap --year <year>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Tax rates and exemptions by period",
+        "tax rules [--year <year>]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Stored monthly tax per category and DARF status",
+        "tax ledger [year]"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - DARF fields to pay a month; --plain for banking apps",
+        "tax darf <MM/YYYY>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Record a month's DARF as paid (--on, --amount, --clear)",
+        "tax mark-paid <MM/YYYY>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - What if I sell: gain, exemption and DARF",
+        "tax simulate --ticker <T>"
+    )?;
+    writeln!(
+        out,
+        "  {:24} - Fixed income redemptions, IOF and IR",
+        "tax fixed-income <year>"
+    )?;
 
     writeln!(out)?;
     writeln!(out, "{}", "Utilities & session:".bold())?;
     writeln!(out, "  {:24} - Launch the TUI (default)", "interactive")?;
     writeln!(out, "  {:24} - Show this help", "help")?;
+    writeln!(
+        out,
+        "  {:24} - Refresh prices and tax snapshot in background",
+        "refresh"
+    )?;
     writeln!(out, "  {:24} - Exit the application", "exit")?;
 
     writeln!(out)?;
@@ -21,16 +21,42 @@ pub struct Cli {
     #[arg(long = "json", global = true)]
     pub json: bool,
 
+    /// Work on a copy of the database (see `interest sandbox`)
+    #[arg(long = "sandbox", global = true)]
+    pub sandbox: bool,
+
+    /// Replay bundled provider responses instead of the network, in the sandbox
+    #[arg(long = "demo", global = true)]
+    pub demo: bool,
+
+    /// Scope reports to one portfolio and record new entries in it (default: all portfolios)
+    #[arg(long = "portfolio", global = true, value_name = "NAME")]
+    pub portfolio: Option<String>,
+
+    /// Scope reports to the portfolios of one declarant (see `portfolios set-declarant`)
+    #[arg(
+        long = "declarant",
+        global = true,
+        value_name = "NAME",
+        conflicts_with = "portfolio"
+    )]
+    pub declarant: Option<String>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
-    /// Import transactions from B3/CEI or Movimentação files (auto-detects format)
+    /// Import transactions from B3/CEI, Movimentação, Proventos Recebidos, Posição, brokerage note PDF or OFX files (auto-detects format)
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Import {
-        /// Path to the Excel or CSV file
-        file: String,
+        #[command(subcommand)]
+        action: Option<ImportCommands>,
+
+        /// Path to the Excel, CSV, PDF or OFX file
+        #[arg(required = true)]
+        file: Option<String>,
 
         /// Preview only, don't save to database
         #[arg(short, long)]
@@ -39,6 +65,37 @@ pub enum Commands {
         /// Force reimport: delete existing data from same source starting from earliest date in file
         #[arg(long)]
         force_reimport: bool,
+
+        /// File format: auto-detect a B3/broker file, or custom with --mapping
+        #[arg(long, default_value = "auto", value_parser = ["auto", "custom"])]
+        format: String,
+
+        /// TOML file mapping the columns of a --format custom CSV/Excel file
+        #[arg(long, required_if_eq("format", "custom"))]
+        mapping: Option<String>,
+    },
+
+    /// Watch a downloads folder and import new B3 files automatically
+    ///
+    /// Configured through the [watch] section of ~/.interest/config.toml
+    WatchImports {
+        /// Scan the folder once and exit instead of polling
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Sync trades, movements and positions from the B3 investor API
+    ///
+    /// Configured through the [b3_api] section of ~/.interest/config.toml
+    #[command(name = "sync-b3")]
+    SyncB3 {
+        /// Re-sync from this date (YYYY-MM-DD) instead of the last synced day
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Fetch and count only, don't save to database
+        #[arg(short, long)]
+        dry_run: bool,
     },
 
     /// Import opening positions from IRPF tax declaration PDF
@@ -78,6 +135,37 @@ pub enum Commands {
         action: PerformanceCommands,
     },
 
+    /// Reports to share: the annual review
+    Reports {
+        #[command(subcommand)]
+        action: ReportsCommands,
+    },
+
+    /// Compare 2 to 4 held assets side by side: price return, income, yield and drawdown
+    Compare {
+        /// Tickers to compare (e.g., HGLG11 XPML11 KNRI11)
+        #[arg(required = true, num_args = 2..=4)]
+        tickers: Vec<String>,
+
+        /// Period: MTD, QTD, YTD, 1Y, ALL, YYYY (e.g., 2025), or from:to (YYYY-MM-DD:YYYY-MM-DD)
+        #[arg(long, default_value = "1Y")]
+        period: String,
+    },
+
+    /// Split a contribution (aporte) toward the target allocation
+    Rebalance {
+        #[command(subcommand)]
+        action: RebalanceCommands,
+    },
+
+    /// Daily briefing: prices, income, corporate actions and inconsistencies
+    /// since the last run, plus obligations due in the next 30 days
+    Whatsnew {
+        /// Look back from this date (YYYY-MM-DD) instead; the last run is kept
+        #[arg(long)]
+        since: Option<String>,
+    },
+
     /// Cash flow reporting
     CashFlow {
         #[command(subcommand)]
@@ -117,28 +205,119 @@ pub enum Commands {
     /// Process term contract liquidations
     ProcessTerms,
 
+    /// Wipe cached snapshots and tax state and rebuild them from imported data
+    Recalculate {
+        /// Last tax year to rebuild (default: current year)
+        #[arg(long)]
+        through: Option<i32>,
+    },
+
+    /// Open term contracts (compra a termo) and their exposure
+    Terms {
+        #[command(subcommand)]
+        action: TermCommands,
+    },
+
+    /// Bought options (calls/puts): open series, exercises and strikes
+    Options {
+        #[command(subcommand)]
+        action: OptionCommands,
+    },
+
+    /// Subscription rights and receipts held, with the ticker they convert into
+    Subscriptions,
+
+    /// Tesouro Direto bonds: marked positions, custody fee and IR on redemptions
+    Tesouro {
+        #[command(subcommand)]
+        action: TesouroCommands,
+    },
+
+    /// Private fixed income (CDB, LCI, LCA, CRI, CRA) accrued by its indexer
+    FixedIncome {
+        #[command(subcommand)]
+        action: FixedIncomeCommands,
+    },
+
     /// Manual transaction management
     Transactions {
         #[command(subcommand)]
         action: TransactionCommands,
     },
 
-    /// Inspect Excel/CSV file structure
+    /// Trade idea journal linked to executed transactions
+    Journal {
+        #[command(subcommand)]
+        action: JournalCommands,
+    },
+
+    /// Database maintenance (portable JSON export/import, encryption)
+    Db {
+        #[command(subcommand)]
+        action: DbCommands,
+    },
+
+    /// Manage portfolios (own, spouse, corporate accounts) used by --portfolio
+    Portfolios {
+        #[command(subcommand)]
+        action: PortfoliosCommands,
+    },
+
+    /// Review, promote or discard the --sandbox copy of the database
+    Sandbox {
+        #[command(subcommand)]
+        action: SandboxCommands,
+    },
+
+    /// Show the version; --full adds data, cache and provider diagnostics for bug reports
+    Version {
+        /// Database size and row counts, cache freshness and provider connectivity
+        #[arg(long)]
+        full: bool,
+    },
+
+    /// Inspect Excel/CSV/PDF file structure
     Inspect {
-        /// Path to the Excel or CSV file
+        /// Path to the Excel, CSV or PDF file
         file: String,
 
-        /// Show full data rows, not just headers
+        /// Show full data rows (or full PDF text), not just a preview
         #[arg(short, long)]
         full: bool,
 
         /// Analyze and show unique values in a column (e.g., --column 2 for movement types)
         #[arg(short, long)]
         column: Option<usize>,
+
+        /// PDF pages to extract: N or N-M (e.g., 2-5)
+        #[arg(long)]
+        pages: Option<String>,
     },
 
     /// Launch interactive TUI mode
     Interactive,
+
+    /// Run in the background so interactive sessions hand it price updates and syncs
+    ///
+    /// Listens on ~/.interest/daemon.sock until interrupted; tasks run one at
+    /// a time
+    Daemon {
+        /// Show the daemon's queued and running tasks instead
+        #[arg(long)]
+        status: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ImportCommands {
+    /// List recorded imports, newest first
+    List,
+
+    /// Delete every row an import added and rewind its last-import dates
+    Undo {
+        /// Import session id (see `import list`)
+        session: i64,
+    },
 }
 
 #[derive(Subcommand)]
@@ -152,13 +331,47 @@ pub enum PortfolioCommands {
         /// Show portfolio as of this date (YYYY-MM-DD, YYYY-MM, or YYYY)
         #[arg(long)]
         at: Option<String>,
+
+        /// Only include assets with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Break positions down by broker (corretora)
+        #[arg(long)]
+        by_broker: bool,
+
+        #[command(flatten)]
+        table: formatters::TableArgs,
+    },
+
+    /// Browse positions with row shortcuts: detail, new trade, price, income
+    Browse,
+
+    /// Market value by sector or segment, flagging concentration
+    ///
+    /// FIIs, Fiagros and FI-Infras group by fund segment (logística,
+    /// shoppings, lajes...). Classification comes from the Mais Retorno
+    /// registry (assets sync-maisretorno).
+    Exposure {
+        /// Group stocks, BDRs and ETFs by sector or by segment
+        #[arg(long, default_value = "sector", value_parser = ["sector", "segment"])]
+        by: String,
+
+        /// Flag groups above this % of the portfolio (default: [exposure]
+        /// max_concentration_pct, or 25)
+        #[arg(long)]
+        threshold: Option<String>,
     },
 }
 
 #[derive(Subcommand)]
 pub enum PriceCommands {
-    /// Update all asset prices
-    Update,
+    /// Update all asset prices, or only the ones queued after a failed fetch
+    Update {
+        /// Refetch every asset even while failed tickers are queued for retry
+        #[arg(long)]
+        all: bool,
+    },
 
     /// Import B3 COTAHIST for a specific year
     #[command(name = "import-b3")]
@@ -178,13 +391,81 @@ pub enum PriceCommands {
         path: String,
     },
 
-    /// Clear COTAHIST cache (optionally a specific year)
+    /// Load daily closes of every asset ever traded from COTAHIST files
+    ///
+    /// Downloads one annual file per year (monthly files for a partial first
+    /// year), much faster than fetching ticker by ticker
+    Backfill {
+        /// First year (YYYY) or month (YYYY-MM); default: year of the first trade
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Ignore cache and force download
+        #[arg(long = "no-cache")]
+        no_cache: bool,
+    },
+
+    /// Clear COTAHIST cache (optionally a specific year) and cached live quotes
     #[command(name = "clear-cache")]
     ClearCache {
-        /// Year to clear (omit to clear all)
+        /// Year to clear (omit to clear all, live quotes included)
         year: Option<i32>,
     },
 
+    /// Fetch and store benchmark series (IBOV, IFIX, CDI, SELIC, IPCA)
+    #[command(name = "update-benchmarks")]
+    UpdateBenchmarks {
+        /// Benchmark to update (IBOV, IFIX, CDI, SELIC, IPCA); omit to update all
+        benchmark: Option<String>,
+
+        /// Start date (YYYY-MM-DD); defaults to the day after the last stored value
+        #[arg(short, long)]
+        from: Option<String>,
+    },
+
+    /// Fetch BCB PTAX rates in reais of the currencies assets are quoted in
+    #[command(name = "update-fx")]
+    UpdateFx {
+        /// Currency to update (USD, EUR, GBP, ...); omit for every currency in use and USD
+        currency: Option<String>,
+
+        /// Start date (YYYY-MM-DD); defaults to the day after the last stored rate
+        #[arg(short, long)]
+        from: Option<String>,
+    },
+
+    /// Fetch FII NAV per quota from the CVM monthly reports (for P/VP)
+    #[command(name = "update-nav")]
+    UpdateNav {
+        /// Year of the reports (defaults to the current and previous year)
+        #[arg(long)]
+        year: Option<i32>,
+    },
+
+    /// FII price-to-NAV: held FIIs ranked by discount, or one FII's history
+    Pvp {
+        /// FII ticker to show month by month (omit to rank holdings)
+        ticker: Option<String>,
+    },
+
+    /// Live quotes of held assets with day change and P&L, refreshed on an interval
+    Watch {
+        /// Seconds between refreshes (at least 15)
+        #[arg(long, default_value_t = crate::pricing::watch::DEFAULT_INTERVAL_SECS)]
+        interval: u64,
+
+        /// Fetch one round, print it and exit
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Which providers quote each held asset, and assets nothing prices
+    Coverage {
+        /// Also ask Yahoo for a live quote of every eligible asset
+        #[arg(long)]
+        live: bool,
+    },
+
     /// Fetch historical prices for a specific ticker
     History {
         /// Ticker symbol (e.g., PETR4)
@@ -197,6 +478,9 @@ pub enum PriceCommands {
         /// End date (YYYY-MM-DD)
         #[arg(short, long)]
         to: String,
+
+        #[command(flatten)]
+        table: formatters::TableArgs,
     },
 }
 
@@ -209,19 +493,176 @@ pub enum TaxCommands {
     },
 
     /// Generate annual IRPF tax report
+    ///
+    /// --limit, --offset and --columns select rows and columns of the
+    /// Dividends & JCP table; the other sections are always shown in full.
     Report {
         /// Year (e.g., 2025)
         year: i32,
 
-        /// Export report to CSV (irpf_report_<year>.csv)
+        /// Export report to CSV (irpf_report_<year>.csv, or
+        /// bens_e_direitos_<year>.csv with --bens-e-direitos)
         #[arg(long)]
         export: bool,
+
+        /// Separate report per declarant (see `portfolios set-declarant`)
+        #[arg(long = "by-declarant", conflicts_with = "export")]
+        by_declarant: bool,
+
+        /// List year-end positions as IRPF "Bens e Direitos" items, by code
+        #[arg(long = "bens-e-direitos", conflicts_with = "by_declarant")]
+        bens_e_direitos: bool,
+
+        #[command(flatten)]
+        table: formatters::TableArgs,
     },
 
     /// Show monthly tax summary for a year
     Summary {
         /// Year (e.g., 2025)
         year: i32,
+
+        /// Break sales, gains and IRRF on sales down by broker account
+        #[arg(long = "by-broker")]
+        by_broker: bool,
+    },
+
+    /// Preview stock sales vs the R$20k monthly exemption (last 12 months)
+    Preview {
+        #[command(flatten)]
+        table: formatters::TableArgs,
+    },
+
+    /// This month's sales per category against the monthly exemption
+    Exemption {
+        /// Month in MM/YYYY format (default: current month)
+        #[arg(long)]
+        month: Option<String>,
+
+        /// Amount of a planned sale to check against the exemption
+        #[arg(long)]
+        sell: Option<String>,
+
+        /// Ticker of the planned sale (default: a stock)
+        #[arg(long, requires = "sell")]
+        ticker: Option<String>,
+
+        /// The planned sale is a day trade
+        #[arg(long = "day-trade", requires = "sell")]
+        day_trade: bool,
+    },
+
+    /// Summarize IRRF withheld at source and what can be recovered or compensated
+    Withholding {
+        /// Year (e.g., 2025)
+        year: i32,
+    },
+
+    /// Export the year's sales as GCAP (Programa Ganhos de Capital) operations
+    Gcap {
+        /// Year (e.g., 2025)
+        #[arg(long)]
+        year: i32,
+
+        /// CSV file to write (default: gcap_<year>.csv)
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Fixed income redemptions (Tesouro, CDB, LCI, LCA, CRI, CRA) with IOF and regressive IR
+    FixedIncome {
+        /// Year (e.g., 2025)
+        year: i32,
+    },
+
+    /// Monthly tax per category as stored, with the DARF paid for each month
+    Ledger {
+        /// Year (every stored year when omitted)
+        year: Option<i32>,
+    },
+
+    /// The DARF to pay for a month: code, period, due date and amount
+    Darf {
+        /// Month in MM/YYYY format (e.g., 03/2025)
+        month: String,
+
+        /// Only the fields a banking app asks for, in a copy-friendly block
+        #[arg(long)]
+        plain: bool,
+    },
+
+    /// Record the DARF of a month as paid, keeping it across recalculations
+    MarkPaid {
+        /// Month in MM/YYYY format (e.g., 03/2025)
+        month: String,
+
+        /// Payment date (YYYY-MM-DD, default: today)
+        #[arg(long)]
+        on: Option<String>,
+
+        /// Amount paid (default: the month's tax)
+        #[arg(long)]
+        amount: Option<String>,
+
+        /// Forget the payment recorded for the month
+        #[arg(long, conflicts_with_all = ["on", "amount"])]
+        clear: bool,
+    },
+
+    /// Tax effect of a hypothetical sale, without recording it
+    Simulate {
+        /// Ticker to sell
+        #[arg(long)]
+        ticker: String,
+
+        /// Quantity to sell
+        #[arg(long)]
+        quantity: String,
+
+        /// Sale price per unit
+        #[arg(long)]
+        price: String,
+
+        /// Sale date (YYYY-MM-DD, default: today)
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Brokerage fees of the sale
+        #[arg(long, default_value = "0")]
+        fees: String,
+
+        /// Simulate a day trade (bought and sold the same day)
+        #[arg(long = "day-trade")]
+        day_trade: bool,
+    },
+
+    /// List the dated tax rates, exemptions and IRRF rules
+    Rules {
+        /// Mark the rules in force in this year (default: current year)
+        #[arg(long)]
+        year: Option<i32>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ReportsCommands {
+    /// The year in review: returns vs benchmarks, allocation, income, tax,
+    /// contributions and top contributors/detractors
+    Annual {
+        /// Year (e.g., 2025); the current year runs through today
+        year: i32,
+
+        /// Write the report as a PDF
+        #[arg(long)]
+        pdf: bool,
+
+        /// PDF file to write (default: annual_report_<year>.pdf)
+        #[arg(short, long, requires = "pdf")]
+        output: Option<String>,
+
+        /// Benchmarks to compare with (default: IBOV,IFIX,CDI)
+        #[arg(long, value_delimiter = ',')]
+        vs: Vec<String>,
     },
 }
 
@@ -231,6 +672,50 @@ pub enum PerformanceCommands {
     Show {
         /// Period: MTD, QTD, YTD, 1Y, ALL, YYYY (e.g., 2025), or from:to (YYYY-MM-DD:YYYY-MM-DD)
         period: String,
+
+        /// Split BDR returns into local price vs USD/BRL effect
+        #[arg(long)]
+        fx: bool,
+
+        /// Only include assets with this tag
+        #[arg(long)]
+        tag: Option<String>,
+
+        /// Compare with the same cash flows invested in each benchmark
+        #[arg(long)]
+        what_if: bool,
+
+        /// Return method: simple, or twr to chain returns over daily valuations
+        #[arg(long, default_value = "simple", value_parser = ["simple", "twr"])]
+        method: String,
+
+        /// With --method twr, value the portfolio at month ends instead of daily
+        #[arg(long)]
+        monthly: bool,
+
+        /// Compare the return with benchmarks, e.g. IBOV,CDI,IPCA+6
+        #[arg(long, value_delimiter = ',')]
+        vs: Vec<String>,
+
+        /// Refuse to report when prices or benchmarks stop before the period end
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Volatility, max drawdown, Sharpe and Sortino (vs CDI) of the portfolio
+    /// and of each held asset, from daily prices
+    Risk {
+        /// Period: MTD, QTD, YTD, 1Y, ALL, YYYY (e.g., 2025), or from:to (YYYY-MM-DD:YYYY-MM-DD)
+        #[arg(long, default_value = "1Y")]
+        period: String,
+    },
+
+    /// Market value, invested capital and accumulated income at each month
+    /// (or year) end since the first trade, for charts (--json)
+    History {
+        /// Point per month or per year
+        #[arg(long, default_value = "monthly", value_parser = ["monthly", "yearly"])]
+        granularity: String,
     },
 }
 
@@ -246,6 +731,11 @@ pub enum CashFlowCommands {
         /// Period: MTD, QTD, YTD, 1Y, ALL, YYYY (e.g., 2025), or from:to (YYYY-MM-DD:YYYY-MM-DD)
         period: Option<String>,
     },
+    /// Show deposits, withdrawals, reinvested income and net new capital per year
+    Net {
+        /// Period: MTD, QTD, YTD, 1Y, ALL, YYYY (e.g., 2025), or from:to (YYYY-MM-DD:YYYY-MM-DD)
+        period: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -254,6 +744,10 @@ pub enum IncomeCommands {
     Show {
         /// Year to filter (optional, defaults to current year)
         year: Option<i32>,
+
+        /// Only include assets with this tag
+        #[arg(long)]
+        tag: Option<String>,
     },
 
     /// Manually add an income event
@@ -261,7 +755,7 @@ pub enum IncomeCommands {
         /// Ticker symbol
         ticker: String,
 
-        /// Event type (DIVIDEND, JCP, AMORTIZATION)
+        /// Event type (DIVIDEND, JCP, INTEREST, AMORTIZATION)
         event_type: String,
 
         /// Total amount received
@@ -274,9 +768,13 @@ pub enum IncomeCommands {
         #[arg(long)]
         ex_date: Option<String>,
 
-        /// Optional withholding tax
-        #[arg(long, default_value = "0")]
-        withholding: String,
+        /// Tax withheld at source (defaults to the event type's rate: 15% on JCP)
+        #[arg(long)]
+        withholding: Option<String>,
+
+        /// Tax withheld abroad before payment (BDR dividends)
+        #[arg(long)]
+        foreign_tax: Option<String>,
 
         /// Optional amount per quota
         #[arg(long, default_value = "0")]
@@ -295,12 +793,52 @@ pub enum IncomeCommands {
         /// Filter by asset ticker
         #[arg(short, long)]
         asset: Option<String>,
+
+        #[command(flatten)]
+        table: formatters::TableArgs,
     },
 
     /// Show monthly breakdown (if year given) or yearly totals (if no year)
     Summary {
         /// Year (optional - omit for yearly totals)
         year: Option<i32>,
+
+        /// Break the year down by broker account, as informes de rendimentos arrive
+        #[arg(long = "by-broker", requires = "year")]
+        by_broker: bool,
+    },
+
+    /// Reconcile income events against the cash credited in Movimentação statements
+    Reconcile {
+        /// Year (optional - omit for every year)
+        year: Option<i32>,
+    },
+
+    /// Expected payments with 80% ranges: announced ones, else each asset's cadence and trend
+    Forecast {
+        /// Months ahead to forecast
+        #[arg(short, long, default_value = "12")]
+        months: u32,
+
+        /// Filter by asset ticker
+        #[arg(short, long)]
+        asset: Option<String>,
+
+        /// Show the tax on each payment and the net income, under the law in force when paid
+        #[arg(long)]
+        net: bool,
+
+        /// Scenario: withhold this percentage of stock dividends (implies --net)
+        #[arg(long, value_name = "PERCENT")]
+        dividend_tax: Option<String>,
+
+        /// Scenario: a company's dividends up to this amount a month are not withheld
+        #[arg(long, value_name = "AMOUNT", requires = "dividend_tax")]
+        dividend_threshold: Option<String>,
+
+        /// Scenario: first month the scenario applies to (YYYY-MM, default: this month)
+        #[arg(long, value_name = "YYYY-MM", requires = "dividend_tax")]
+        tax_from: Option<String>,
     },
 }
 
@@ -318,7 +856,7 @@ pub enum ActionCommands {
         action: SplitCommands,
     },
 
-    /// Manage bonus actions (synthetic share grants)
+    /// Manage bonus actions (zero-cost share grants)
     Bonus {
         #[command(subcommand)]
         action: BonusCommands,
@@ -336,11 +874,30 @@ pub enum ActionCommands {
         action: ExchangeCommands,
     },
 
-    /// Apply unapplied corporate actions to transactions
+    /// Apply pending corporate actions (a bonus counts once applied)
     Apply {
         /// Ticker symbol (optional, applies all if not specified)
         ticker: Option<String>,
     },
+
+    /// Return an applied bonus to pending, taking its shares out of positions
+    Unapply {
+        /// Corporate action ID
+        id: i64,
+    },
+
+    /// Fetch splits, reverse splits and bonuses from B3's corporate events for held stocks
+    Detect {
+        /// Ticker symbol (optional, checks every stock held if not specified)
+        ticker: Option<String>,
+
+        /// Preview only, don't save to database
+        #[arg(short, long)]
+        dry_run: bool,
+    },
+
+    /// Review pending and recently applied actions: preview, approve, apply, unapply
+    Review,
 }
 
 #[derive(Subcommand)]
@@ -435,7 +992,8 @@ pub enum ExchangeCommands {
         quantity: String,
         /// Cost basis allocated to new ticker
         allocated_cost: String,
-        /// Cash amortization amount
+        /// Cash paid alongside: a capital return in a spin-off; in a merger, a
+        /// sale of the source against the basis not allocated to the new ticker
         #[arg(long)]
         cash: Option<String>,
         /// Optional notes
@@ -477,6 +1035,9 @@ pub enum InconsistenciesCommands {
         /// Filter by asset ticker
         #[arg(long)]
         asset: Option<String>,
+
+        #[command(flatten)]
+        table: formatters::TableArgs,
     },
 
     /// Show details for a single inconsistency
@@ -509,6 +1070,20 @@ pub enum InconsistenciesCommands {
         #[arg(long)]
         reason: Option<String>,
     },
+    /// Heatmap of computed quantities against imported position statements, month by month
+    Positions {
+        /// First month (YYYY-MM)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Last month (YYYY-MM)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Only this ticker
+        #[arg(long)]
+        asset: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -518,11 +1093,18 @@ pub enum TickersCommands {
         /// Force refresh even if cache is fresh
         #[arg(long)]
         force: bool,
+
+        /// Fetch the instruments file of a past date (YYYY-MM-DD) as a dated version
+        #[arg(long)]
+        date: Option<String>,
     },
 
     /// Show cache status
     Status,
 
+    /// List the dated versions of the instruments file used for older trades
+    Versions,
+
     /// List tickers with UNKNOWN asset type
     ListUnknown,
 
@@ -544,6 +1126,9 @@ pub enum AssetsCommands {
         /// Asset type to filter (STOCK, FII, FIAGRO, FI_INFRA, etc.)
         #[arg(long = "type")]
         asset_type: Option<String>,
+
+        #[command(flatten)]
+        table: formatters::TableArgs,
     },
 
     /// Show details for a single asset
@@ -584,6 +1169,62 @@ pub enum AssetsCommands {
         name: String,
     },
 
+    /// Set the currency an asset is quoted in; it is valued in reais at the PTAX
+    #[command(name = "set-currency")]
+    SetCurrency {
+        /// Ticker symbol
+        ticker: String,
+
+        /// Currency code (USD, EUR, ...); BRL for assets quoted in reais
+        currency: String,
+    },
+
+    /// Set the issuer CNPJ of an asset (check digits are validated)
+    SetCnpj {
+        /// Ticker symbol
+        ticker: String,
+
+        /// CNPJ, with or without punctuation
+        cnpj: String,
+    },
+
+    /// Validate CNPJs and fetch the issuer's razão social and situation (BrasilAPI/minhareceita)
+    EnrichCnpj {
+        /// Ticker symbol (optional - defaults to every asset with a CNPJ)
+        ticker: Option<String>,
+
+        /// Look up again assets that were already enriched
+        #[arg(long)]
+        refresh: bool,
+
+        /// Show what would be stored without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Value an asset with no market price (FIPs, closed funds, delisted shares)
+    #[command(name = "set-value")]
+    SetValue {
+        /// Ticker symbol
+        ticker: String,
+
+        /// Value per share/quota
+        #[arg(required_unless_present = "clear")]
+        value: Option<String>,
+
+        /// Valuation date (YYYY-MM-DD, default: today)
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Optional notes (e.g. where the value came from)
+        #[arg(short, long)]
+        notes: Option<String>,
+
+        /// Remove every valuation of the asset
+        #[arg(long, conflicts_with_all = ["value", "date", "notes"])]
+        clear: bool,
+    },
+
     /// Rename ticker symbol (correction-only)
     Rename {
         /// Old ticker
@@ -599,6 +1240,27 @@ pub enum AssetsCommands {
         ticker: String,
     },
 
+    /// Add an asset to a tag group (e.g., aposentadoria, reserva)
+    Tag {
+        /// Ticker symbol
+        ticker: String,
+
+        /// Tag name
+        tag: String,
+    },
+
+    /// Remove an asset from a tag group
+    Untag {
+        /// Ticker symbol
+        ticker: String,
+
+        /// Tag name
+        tag: String,
+    },
+
+    /// List tag groups and their assets
+    Tags,
+
     /// Sync Mais Retorno asset metadata
     #[command(name = "sync-maisretorno")]
     SyncMaisRetorno {
@@ -617,20 +1279,34 @@ pub enum TransactionCommands {
     /// Manually add a buy or sell transaction
     Add {
         /// Ticker symbol (e.g., PETR4, MXRF11)
-        ticker: String,
+        #[arg(required_unless_present = "editor")]
+        ticker: Option<String>,
 
         /// Transaction type: buy or sell
-        #[arg(value_parser = ["buy", "sell", "BUY", "SELL"])]
-        transaction_type: String,
+        #[arg(
+            value_parser = ["buy", "sell", "BUY", "SELL"],
+            required_unless_present = "editor"
+        )]
+        transaction_type: Option<String>,
 
         /// Quantity of shares/quotas
-        quantity: String,
+        #[arg(required_unless_present = "editor")]
+        quantity: Option<String>,
 
         /// Price per unit
-        price: String,
+        #[arg(required_unless_present = "editor")]
+        price: Option<String>,
 
         /// Trade date (YYYY-MM-DD)
-        date: String,
+        #[arg(required_unless_present = "editor")]
+        date: Option<String>,
+
+        /// Enter many trades at once in $EDITOR, one per line (tab or comma separated)
+        #[arg(
+            long,
+            conflicts_with_all = ["ticker", "transaction_type", "quantity", "price", "date", "day_trade", "notes", "broker"]
+        )]
+        editor: bool,
 
         /// Optional fees/brokerage
         #[arg(short, long, default_value = "0")]
@@ -643,12 +1319,348 @@ pub enum TransactionCommands {
         /// Optional notes
         #[arg(short, long)]
         notes: Option<String>,
+
+        /// Broker (corretora) the trade was made at, e.g. XP or INTER
+        #[arg(long)]
+        broker: Option<String>,
+    },
+
+    /// List transactions (optional filters by ticker, date range and source)
+    List {
+        /// Ticker symbol to filter
+        #[arg(long)]
+        ticker: Option<String>,
+
+        /// First trade date (YYYY-MM-DD)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Last trade date (YYYY-MM-DD)
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Only entries from this import source (e.g. CEI, MOVIMENTACAO, MANUAL)
+        #[arg(long)]
+        source: Option<String>,
+
+        #[command(flatten)]
+        table: formatters::TableArgs,
+    },
+
+    /// Show one transaction with its broker, import and split-adjusted quantity
+    Show {
+        /// Transaction ID (see `transactions list`)
+        id: i64,
+    },
+
+    /// Change fields of a transaction; the total is recomputed unless given
+    Edit {
+        /// Transaction ID (see `transactions list`)
+        id: i64,
+
+        /// Move the trade to another ticker
+        #[arg(long)]
+        ticker: Option<String>,
+
+        /// Transaction type: buy or sell
+        #[arg(long = "type", value_parser = ["buy", "sell", "BUY", "SELL"])]
+        transaction_type: Option<String>,
+
+        /// Trade date (YYYY-MM-DD)
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Quantity of shares/quotas
+        #[arg(long)]
+        quantity: Option<String>,
+
+        /// Price per unit
+        #[arg(long)]
+        price: Option<String>,
+
+        /// Fees/brokerage
+        #[arg(long)]
+        fees: Option<String>,
+
+        /// Total amount (default: quantity × price + fees)
+        #[arg(long)]
+        total: Option<String>,
+
+        /// Mark or unmark as day trade
+        #[arg(long)]
+        day_trade: Option<bool>,
+
+        /// Notes (empty to clear)
+        #[arg(long)]
+        notes: Option<String>,
+
+        /// Broker (corretora) the trade was made at
+        #[arg(long)]
+        broker: Option<String>,
+
+        /// Rescale later splits and bonuses to the new position without asking
+        #[arg(long, conflicts_with = "keep_actions")]
+        rescale_actions: bool,
+
+        /// Leave later splits and bonuses as they are without asking
+        #[arg(long)]
+        keep_actions: bool,
+    },
+
+    /// Delete a transaction
+    Delete {
+        /// Transaction ID (see `transactions list`)
+        id: i64,
+
+        /// Rescale later splits and bonuses to the new position without asking
+        #[arg(long, conflicts_with = "keep_actions")]
+        rescale_actions: bool,
+
+        /// Leave later splits and bonuses as they are without asking
+        #[arg(long)]
+        keep_actions: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum OptionCommands {
+    /// List option series held, with underlying, strike, expiry and premium paid
+    Show,
+
+    /// Match exercises to option positions and move premiums into the underlying
+    ///
+    /// Runs after every CEI import; use it after editing transactions by hand
+    Process,
+
+    /// Record the strike and/or underlying of an option series
+    Set {
+        /// Option ticker (e.g., PETRA123)
+        ticker: String,
+
+        /// Strike price
+        #[arg(long)]
+        strike: Option<String>,
+
+        /// Underlying ticker, when it does not share the option's root (e.g., BOVA11)
+        #[arg(long)]
+        underlying: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TesouroCommands {
+    /// Bonds held at the latest PU, with accrued custody and the tax if redeemed today
+    Show,
+
+    /// Redemptions of a year with IOF and regressive IR per purchase lot
+    Redemptions {
+        /// Year (e.g., 2025)
+        year: i32,
     },
+}
+
+#[derive(Subcommand)]
+pub enum FixedIncomeCommands {
+    /// Bonds held at the accrued PU, with the tax if redeemed today
+    Show,
+
+    /// Record the terms of a bond already bought, so its value is accrued daily
+    Set {
+        /// Bond ticker as recorded in transactions (e.g., CDB_BANCOX_2027)
+        ticker: String,
+
+        /// CDB, LCI, LCA, CRI or CRA (LCI, LCA, CRI and CRA are tax exempt)
+        #[arg(long)]
+        kind: String,
+
+        /// Issue date (YYYY-MM-DD)
+        #[arg(long)]
+        issue: String,
 
-    /// List transactions (optional filter by ticker)
+        /// Maturity date (YYYY-MM-DD)
+        #[arg(long)]
+        maturity: String,
+
+        /// CDI, IPCA or PRE
+        #[arg(long)]
+        indexer: String,
+
+        /// % of the CDI (110), spread over IPCA (6.5) or fixed rate (12.4), a year
+        #[arg(long)]
+        rate: String,
+
+        /// Unit value on the issue date (default: price of the first purchase)
+        #[arg(long)]
+        issue_price: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RebalanceCommands {
+    /// Suggest what to buy with a contribution to approach the target allocation
+    ///
+    /// Targets are read from the [rebalance.targets] section of
+    /// ~/.interest/config.toml, by ticker or asset type
+    Suggest {
+        /// Amount to invest (e.g., 3000)
+        #[arg(long)]
+        amount: String,
+
+        /// Buy only standard lots of 100 (no fractional market)
+        #[arg(long)]
+        lots: bool,
+
+        /// Leave out assets with open blocking inconsistencies
+        #[arg(long)]
+        skip_blocked: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TermCommands {
+    /// List open term contracts with notional, rate, expiry and share of the portfolio
+    ///
+    /// Alerts when the total exceeds max_exposure_pct from the [terms]
+    /// section of ~/.interest/config.toml
+    Show,
+
+    /// Record the expiry and contracted rate of a term purchase
+    Set {
+        /// Transaction id of the TICKERT purchase (shown by `terms show`)
+        transaction_id: i64,
+
+        /// Expiry date (YYYY-MM-DD)
+        #[arg(long)]
+        expiry: Option<String>,
+
+        /// Contracted annual rate in percent (e.g., 12.5)
+        #[arg(long)]
+        rate: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum JournalCommands {
+    /// Record a trade idea before executing it
+    Add {
+        /// Ticker symbol
+        ticker: String,
+
+        /// Thesis: why you are making this trade
+        thesis: String,
+
+        /// Entry date (YYYY-MM-DD, default: today)
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Target price for the thesis
+        #[arg(long)]
+        target: Option<String>,
+    },
+
+    /// List journal entries with their realized outcome
     List {
         /// Ticker symbol to filter
         #[arg(long)]
         ticker: Option<String>,
+
+        #[command(flatten)]
+        table: formatters::TableArgs,
+    },
+
+    /// Link an executed transaction to a journal entry
+    Link {
+        /// Journal entry id
+        entry_id: i64,
+
+        /// Transaction id (see `transactions list --json`)
+        transaction_id: i64,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PortfoliosCommands {
+    /// List portfolios with their transaction and income counts
+    List,
+
+    /// Create a portfolio
+    Add {
+        /// Portfolio name (e.g. spouse, empresa)
+        name: String,
+
+        /// Optional description
+        #[arg(long)]
+        description: Option<String>,
+
+        /// Who declares this portfolio in the IRPF (e.g. for a spouse declaring separately)
+        #[arg(long)]
+        declarant: Option<String>,
+    },
+
+    /// Set who declares a portfolio in the IRPF (omit the declarant to clear it)
+    #[command(name = "set-declarant")]
+    SetDeclarant {
+        /// Portfolio name
+        name: String,
+
+        /// Declarant name
+        declarant: Option<String>,
+    },
+
+    /// Remove an empty portfolio
+    Remove {
+        /// Portfolio name
+        name: String,
+    },
+
+    /// Move existing transactions and income events into a portfolio
+    Assign {
+        /// Target portfolio name
+        name: String,
+
+        /// Only entries of this ticker
+        #[arg(long)]
+        ticker: Option<String>,
+
+        /// Only entries from this import source (e.g. CEI, MOVIMENTACAO, MANUAL)
+        #[arg(long)]
+        source: Option<String>,
     },
 }
+
+#[derive(Subcommand)]
+pub enum SandboxCommands {
+    /// Show the sandbox and how its data differs from the live database
+    Status,
+
+    /// Delete the sandbox, keeping the live database untouched
+    Discard,
+
+    /// Replace the live database with the sandbox (a backup of the live file is kept)
+    Promote {
+        /// Promote even if the live database changed after the sandbox was created
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DbCommands {
+    /// Export all user data to a portable, versioned JSON archive
+    ExportJson {
+        /// Output file path
+        path: String,
+    },
+
+    /// Restore a JSON archive into an empty database
+    ImportJson {
+        /// Archive file path
+        path: String,
+    },
+
+    /// Encrypt the database at rest with a passphrase
+    Encrypt,
+
+    /// Remove the encryption and store the database in plain form again
+    Decrypt,
+}
@@ -170,10 +170,14 @@ pub enum ActionsAction {
     Merger {
         action: ExchangeAction,
     },
-    /// Apply corporate actions (bonus synthetic transactions)
+    /// Apply pending corporate actions (a bonus counts once applied)
     Apply {
         ticker: Option<String>,
     },
+    /// Return an applied bonus to pending
+    Unapply {
+        id: i64,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -1044,6 +1048,21 @@ pub fn parse_command(input: &str) -> Result<Command, CommandParseError> {
                 });
             }
 
+            if action_type == "unapply" {
+                let id = parts
+                    .next()
+                    .ok_or_else(|| CommandParseError {
+                        message: "actions unapply requires an id".to_string(),
+                    })?
+                    .parse::<i64>()
+                    .map_err(|_| CommandParseError {
+                        message: "actions unapply requires a numeric id".to_string(),
+                    })?;
+                return Ok(Command::Actions {
+                    action: ActionsAction::Unapply { id },
+                });
+            }
+
             let verb = parts
                 .next()
                 .ok_or_else(|| CommandParseError {
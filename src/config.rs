@@ -0,0 +1,308 @@
+//! User configuration loaded from `~/.interest/config.toml`.
+//!
+//! The file is optional; a missing file yields the default (empty) config.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Top-level configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Config {
+    /// Watched-downloads automatic import settings
+    pub watch: Option<WatchConfig>,
+
+    /// B3 investor API credentials for `sync-b3`
+    pub b3_api: Option<B3ApiConfig>,
+
+    /// Term contract exposure limits for `terms show`
+    pub terms: Option<TermsConfig>,
+
+    /// Extra columns for `portfolio show`
+    pub portfolio: Option<PortfolioConfig>,
+
+    /// Target allocation for `rebalance suggest`
+    pub rebalance: Option<RebalanceConfig>,
+
+    /// Concentration limit for `portfolio exposure`
+    pub exposure: Option<ExposureConfig>,
+
+    /// Price provider order, retries and rate limits
+    pub prices: Option<PricesConfig>,
+}
+
+/// Price providers (see `pricing::provider`)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PricesConfig {
+    /// Providers tried in order: YAHOO, BRAPI, B3_COTAHIST, TESOURO_CSV,
+    /// MERCADO_BITCOIN, MANUAL
+    pub providers: Option<Vec<String>>,
+
+    /// Extra tries per provider after a transient failure
+    pub retries: Option<u32>,
+
+    /// Most requests per minute, by provider
+    #[serde(default)]
+    pub requests_per_minute: std::collections::BTreeMap<String, u32>,
+
+    /// Most requests in flight at once, by provider (default 5)
+    #[serde(default)]
+    pub max_concurrent: std::collections::BTreeMap<String, u32>,
+
+    /// brapi.dev token; BRAPI joins the default order when set
+    pub brapi_token: Option<String>,
+
+    /// How long a live quote is reused during trading hours (default 15)
+    pub quote_ttl_minutes: Option<u32>,
+}
+
+/// Sector and segment exposure settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExposureConfig {
+    /// Flag sectors or segments above this share of the portfolio (%)
+    pub max_concentration_pct: Option<rust_decimal::Decimal>,
+}
+
+/// Target allocation, in % of the whole portfolio
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RebalanceConfig {
+    /// Keyed by ticker (IVVB11) or asset type (FII, STOCK); a type covers
+    /// its held assets that have no target of their own
+    #[serde(default)]
+    pub targets: std::collections::BTreeMap<String, rust_decimal::Decimal>,
+}
+
+/// `portfolio show` settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct PortfolioConfig {
+    /// Computed columns appended to the positions table, in order
+    #[serde(default)]
+    pub columns: Vec<ColumnConfig>,
+}
+
+/// A computed portfolio column (see `reports::columns` for the fields)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnConfig {
+    /// Column header
+    pub name: String,
+
+    /// Arithmetic expression; defaults to the name, so a field can be shown as is
+    pub expr: Option<String>,
+
+    /// How values are printed (defaults to `number`)
+    pub format: Option<ColumnFormat>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnFormat {
+    #[default]
+    Number,
+    Currency,
+    Percent,
+}
+
+/// Term contract (compra a termo) settings
+#[derive(Debug, Clone, Deserialize)]
+pub struct TermsConfig {
+    /// Alert when open term notional exceeds this share of the portfolio (%)
+    pub max_exposure_pct: Option<rust_decimal::Decimal>,
+}
+
+/// Watched folder settings for `watch-imports`
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchConfig {
+    /// Folder to scan for newly downloaded files
+    pub folder: PathBuf,
+
+    /// Filename glob patterns (`*` and `?`), matched case-insensitively
+    #[serde(default = "default_watch_patterns")]
+    pub patterns: Vec<String>,
+
+    /// Where imported files are moved (defaults to `<folder>/interest-imported`)
+    pub archive_dir: Option<PathBuf>,
+
+    /// Where rejected files are moved (defaults to `<folder>/interest-failed`)
+    pub failed_dir: Option<PathBuf>,
+
+    /// Polling interval in seconds
+    #[serde(default = "default_watch_interval")]
+    pub interval_secs: u64,
+
+    /// Optional command run with the result message as its only argument
+    /// (e.g., `notify-send`)
+    pub notify_command: Option<String>,
+}
+
+/// B3 "Área do Investidor" API access for `sync-b3`
+///
+/// B3 issues the client credentials and the mTLS certificate when the
+/// application is registered; the CPF is the investor whose data is pulled.
+#[derive(Debug, Clone, Deserialize)]
+pub struct B3ApiConfig {
+    pub client_id: String,
+    pub client_secret: String,
+
+    /// Investor CPF (digits only or formatted)
+    pub cpf: String,
+
+    /// PEM certificate issued by B3 for the mutual TLS handshake
+    pub certificate: PathBuf,
+
+    /// PEM private key of the certificate
+    pub private_key: PathBuf,
+
+    /// First day to sync when nothing was imported yet
+    pub start_date: Option<chrono::NaiveDate>,
+
+    /// Overrides for B3's certification environment
+    pub base_url: Option<String>,
+    pub token_url: Option<String>,
+    pub scope: Option<String>,
+}
+
+impl B3ApiConfig {
+    pub fn certificate(&self) -> PathBuf {
+        expand_home(&self.certificate)
+    }
+
+    pub fn private_key(&self) -> PathBuf {
+        expand_home(&self.private_key)
+    }
+
+    /// CPF with the punctuation stripped, as the API expects it
+    pub fn document_number(&self) -> String {
+        self.cpf.chars().filter(|c| c.is_ascii_digit()).collect()
+    }
+}
+
+fn default_watch_patterns() -> Vec<String> {
+    vec!["*.xlsx".to_string()]
+}
+
+fn default_watch_interval() -> u64 {
+    60
+}
+
+impl WatchConfig {
+    pub fn folder(&self) -> PathBuf {
+        expand_home(&self.folder)
+    }
+
+    pub fn archive_dir(&self) -> PathBuf {
+        self.archive_dir
+            .as_deref()
+            .map(expand_home)
+            .unwrap_or_else(|| self.folder().join("interest-imported"))
+    }
+
+    pub fn failed_dir(&self) -> PathBuf {
+        self.failed_dir
+            .as_deref()
+            .map(expand_home)
+            .unwrap_or_else(|| self.folder().join("interest-failed"))
+    }
+}
+
+/// Path of the config file (~/.interest/config.toml)
+pub fn get_config_path() -> Result<PathBuf> {
+    Ok(crate::utils::home_dir()?
+        .join(".interest")
+        .join("config.toml"))
+}
+
+/// Load the config file, returning defaults when it does not exist
+pub fn load_config() -> Result<Config> {
+    let path = get_config_path()?;
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    // Notepad may save it as UTF-16 or with a BOM
+    let raw = crate::importers::inspect::read_text(&path)
+        .with_context(|| format!("Failed to read config file {:?}", path))?;
+    parse_config(&raw).with_context(|| format!("Invalid config file {:?}", path))
+}
+
+fn parse_config(raw: &str) -> Result<Config> {
+    Ok(toml::from_str(raw)?)
+}
+
+/// Expand a leading `~/` to the user's home directory
+fn expand_home(path: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        if let Ok(home) = crate::utils::home_dir() {
+            return home.join(rest);
+        }
+    }
+    path.to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_watch_config_defaults() {
+        let config = parse_config("[watch]\nfolder = \"/tmp/downloads\"\n").unwrap();
+        let watch = config.watch.unwrap();
+        assert_eq!(watch.patterns, vec!["*.xlsx".to_string()]);
+        assert_eq!(watch.interval_secs, 60);
+        assert_eq!(
+            watch.archive_dir(),
+            PathBuf::from("/tmp/downloads/interest-imported")
+        );
+    }
+
+    #[test]
+    fn test_parse_empty_config() {
+        let config = parse_config("").unwrap();
+        assert!(config.watch.is_none());
+        assert!(config.b3_api.is_none());
+    }
+
+    #[test]
+    fn test_parse_b3_api_config() {
+        let config = parse_config(
+            "[b3_api]\nclient_id = \"id\"\nclient_secret = \"secret\"\ncpf = \"123.456.789-00\"\ncertificate = \"/etc/b3.crt\"\nprivate_key = \"/etc/b3.key\"\nstart_date = \"2021-01-04\"\n",
+        )
+        .unwrap();
+        let api = config.b3_api.unwrap();
+        assert_eq!(api.document_number(), "12345678900");
+        assert_eq!(api.start_date, chrono::NaiveDate::from_ymd_opt(2021, 1, 4));
+        assert!(api.base_url.is_none());
+    }
+
+    #[test]
+    fn test_parse_portfolio_columns() {
+        let config = parse_config(
+            "[[portfolio.columns]]\nname = \"Weight\"\nexpr = \"value / total * 100\"\nformat = \"percent\"\n\n[[portfolio.columns]]\nname = \"gain_pct\"\n",
+        )
+        .unwrap();
+        let columns = config.portfolio.unwrap().columns;
+        assert_eq!(columns.len(), 2);
+        assert_eq!(columns[0].format, Some(ColumnFormat::Percent));
+        assert!(columns[1].expr.is_none());
+    }
+
+    #[test]
+    fn test_parse_rebalance_targets() {
+        let config =
+            parse_config("[rebalance.targets]\nFII = 40\nSTOCK = 45.5\nIVVB11 = 14.5\n").unwrap();
+        let targets = config.rebalance.unwrap().targets;
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets["STOCK"], rust_decimal::Decimal::new(455, 1));
+    }
+
+    #[test]
+    fn test_parse_prices_config() {
+        let config = parse_config(
+            "[prices]\nproviders = [\"BRAPI\", \"YAHOO\"]\nbrapi_token = \"abc\"\nquote_ttl_minutes = 5\n\n[prices.requests_per_minute]\nBRAPI = 20\n",
+        )
+        .unwrap();
+        let prices = config.prices.unwrap();
+        assert_eq!(prices.providers.unwrap(), vec!["BRAPI", "YAHOO"]);
+        assert_eq!(prices.requests_per_minute["BRAPI"], 20);
+        assert!(prices.retries.is_none());
+        assert_eq!(prices.quote_ttl_minutes, Some(5));
+    }
+}
@@ -0,0 +1,553 @@
+//! Splits, reverse splits and bonuses as B3 publishes them on each listed
+//! company's page ("Eventos corporativos" > desdobramentos, grupamentos e
+//! bonificações), fetched for the stocks held and recorded as corporate
+//! actions awaiting review.
+//!
+//! B3 states each event as a factor over the shares held at the end of the
+//! last "com" day (`lastDatePrior`); the ex-date is the next business day.
+//! Actions store share counts, so the factor is turned into shares with the
+//! position held that day. An event that disagrees with an action already
+//! recorded for the same asset within `review::CONFLICT_WINDOW_DAYS` is not
+//! recorded: it opens a CORPORATE_ACTION_CONFLICT inconsistency instead.
+//! Ticker changes are not part of this feed.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::{Datelike, Duration, NaiveDate, Utc, Weekday};
+use reqwest::Client;
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::str::FromStr;
+
+use super::review::{Conflict, CONFLICT_WINDOW_DAYS};
+use crate::db::{
+    self, CorporateAction, CorporateActionType, Inconsistency, InconsistencySeverity,
+    InconsistencyStatus, InconsistencyType,
+};
+
+const COMPANY_URL: &str =
+    "https://sistemaswebb3-listados.b3.com.br/listedCompaniesProxy/CompanyCall/GetListedSupplementCompany";
+
+/// `source` of the actions and inconsistencies recorded from B3's events
+pub const SOURCE: &str = "B3";
+
+/// One row of the company's "stockDividends" list
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StockDividend {
+    #[serde(default)]
+    isin_code: String,
+    label: String,
+    factor: String,
+    last_date_prior: String,
+    #[serde(default)]
+    approved_on: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Supplement {
+    #[serde(default)]
+    stock_dividends: Option<Vec<StockDividend>>,
+}
+
+/// A split, reverse split or bonus B3 published for one ticker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct B3Event {
+    pub ticker: String,
+    pub action_type: CorporateActionType,
+    /// As B3 labels it (DESDOBRAMENTO, GRUPAMENTO, BONIFICACAO)
+    pub label: String,
+    /// Percentage added for a split or bonus; ratio of a reverse split
+    pub factor: Decimal,
+    pub last_date_prior: NaiveDate,
+    pub ex_date: NaiveDate,
+    pub approved_on: Option<NaiveDate>,
+}
+
+impl B3Event {
+    /// Shares the event adds to (or, negative, removes from) `held`.
+    /// Fractions are auctioned by the company, so only whole shares count.
+    pub fn adjustment(&self, held: Decimal) -> Decimal {
+        let hundred = Decimal::from(100);
+        match self.action_type {
+            CorporateActionType::Split | CorporateActionType::Bonus => {
+                (held * self.factor / hundred).floor()
+            }
+            CorporateActionType::ReverseSplit => {
+                // Published either as new shares per old one (0,1) or as old
+                // shares per new one (10)
+                let ratio = if self.factor < Decimal::ONE {
+                    self.factor
+                } else {
+                    Decimal::ONE / self.factor
+                };
+                (held * ratio).floor() - held
+            }
+            CorporateActionType::CapitalReturn => Decimal::ZERO,
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} {}{} approved {}",
+            self.label,
+            self.factor.normalize(),
+            if self.action_type == CorporateActionType::ReverseSplit {
+                ""
+            } else {
+                "%"
+            },
+            self.approved_on
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| "-".to_string())
+        )
+    }
+
+    fn into_action(self, asset_id: i64, quantity_adjustment: Decimal) -> CorporateAction {
+        CorporateAction {
+            id: None,
+            asset_id,
+            notes: Some(format!("{} (B3 corporate events)", self.describe())),
+            action_type: self.action_type,
+            event_date: self.approved_on.unwrap_or(self.ex_date),
+            ex_date: self.ex_date,
+            quantity_adjustment,
+            source: SOURCE.to_string(),
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// What detection did with one event
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Outcome {
+    /// Recorded as a pending action (no id on a dry run)
+    Recorded { action_id: Option<i64> },
+    /// Already recorded the same way, or a conflict over it was resolved
+    Known { action_id: i64 },
+    /// Another source recorded it differently (no id on a dry run)
+    Conflict { inconsistency_id: Option<i64> },
+    /// No shares held on the last "com" day
+    NotHeld,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Detection {
+    #[serde(flatten)]
+    pub event: B3Event,
+    pub held: Decimal,
+    pub quantity_adjustment: Decimal,
+    pub outcome: Outcome,
+}
+
+/// Issuer code B3 files a ticker's company under (PETR for PETR4)
+pub fn issuer_code(ticker: &str) -> &str {
+    ticker.get(..4).unwrap_or(ticker)
+}
+
+/// Fetch the stock events B3 lists for an issuer
+pub async fn fetch_company_events(client: &Client, issuer: &str) -> Result<String> {
+    let offline = std::env::var("INTEREST_OFFLINE")
+        .map(|v| v != "0")
+        .unwrap_or(false);
+    if offline {
+        anyhow::bail!("B3 corporate events skipped (INTEREST_OFFLINE is set)");
+    }
+
+    let query = json!({ "issuingCompany": issuer, "language": "pt-br" }).to_string();
+    let url = format!(
+        "{}/{}",
+        COMPANY_URL,
+        base64::engine::general_purpose::STANDARD.encode(query)
+    );
+    client
+        .get(&url)
+        .send()
+        .await
+        .context("B3 corporate events request failed")?
+        .error_for_status()
+        .context("B3 corporate events returned an error status")?
+        .text()
+        .await
+        .context("Failed to read B3 corporate events response")
+}
+
+/// Events of `ticker` in a company response; labels other than splits,
+/// reverse splits and bonuses are left out
+pub fn parse_events(body: &str, ticker: &str) -> Result<Vec<B3Event>> {
+    let body = body.trim();
+    let companies: Vec<Supplement> = if body.starts_with('[') {
+        serde_json::from_str(body)
+    } else {
+        serde_json::from_str(body).map(|s| vec![s])
+    }
+    .context("Failed to parse B3 corporate events")?;
+
+    let mut events = Vec::new();
+    for row in companies
+        .into_iter()
+        .flat_map(|c| c.stock_dividends.unwrap_or_default())
+    {
+        let Ok(action_type) = CorporateActionType::from_str(&row.label) else {
+            continue;
+        };
+        if action_type == CorporateActionType::CapitalReturn
+            || !isin_matches(&row.isin_code, ticker)
+        {
+            continue;
+        }
+        let last_date_prior = parse_br_date(&row.last_date_prior)
+            .with_context(|| format!("Invalid lastDatePrior: {}", row.last_date_prior))?;
+        let factor =
+            parse_factor(&row.factor).with_context(|| format!("Invalid factor: {}", row.factor))?;
+        if factor <= Decimal::ZERO {
+            continue;
+        }
+        events.push(B3Event {
+            ticker: ticker.to_uppercase(),
+            action_type,
+            label: row.label.trim().to_uppercase(),
+            factor,
+            last_date_prior,
+            ex_date: next_weekday(last_date_prior),
+            approved_on: row.approved_on.as_deref().and_then(parse_br_date),
+        });
+    }
+    events.sort_by_key(|e| e.ex_date);
+    Ok(events)
+}
+
+/// Whether an ISIN (BRPETRACNPR6) is the share class of `ticker`; an event
+/// with no ISIN applies to every class of the issuer
+fn isin_matches(isin: &str, ticker: &str) -> bool {
+    let isin = isin.trim().to_uppercase();
+    let ticker = ticker.to_uppercase();
+    if isin.is_empty() {
+        return true;
+    }
+    if isin.len() != 12 || isin.get(2..6) != Some(issuer_code(&ticker)) {
+        return false;
+    }
+    match ticker.get(4..) {
+        Some("11") => isin.get(6..9) == Some("CDA"),
+        Some(class) => {
+            let expected = match class {
+                "3" => "OR",
+                "4" => "PR",
+                "5" => "PA",
+                "6" => "PB",
+                "7" => "PC",
+                "8" => "PD",
+                _ => return false,
+            };
+            isin.get(9..11) == Some(expected)
+        }
+        None => false,
+    }
+}
+
+/// "100,00000000000" (B3 writes a decimal comma)
+fn parse_factor(raw: &str) -> Option<Decimal> {
+    Decimal::from_str(&raw.trim().replace('.', "").replace(',', ".")).ok()
+}
+
+fn parse_br_date(raw: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(raw.trim(), "%d/%m/%Y").ok()
+}
+
+fn next_weekday(date: NaiveDate) -> NaiveDate {
+    let mut next = date.succ_opt().unwrap_or(date);
+    while matches!(next.weekday(), Weekday::Sat | Weekday::Sun) {
+        next = next.succ_opt().unwrap_or(next);
+    }
+    next
+}
+
+/// Non capital-return actions of the asset within the conflict window
+fn nearby_actions(conn: &Connection, asset_id: i64, ex_date: NaiveDate) -> Result<Vec<Conflict>> {
+    let window = Duration::days(CONFLICT_WINDOW_DAYS);
+    let mut stmt = conn.prepare(
+        "SELECT id, action_type, ex_date, quantity_adjustment, source
+         FROM corporate_actions
+         WHERE asset_id = ?1 AND ex_date BETWEEN ?2 AND ?3
+           AND action_type != 'CAPITAL_RETURN'
+         ORDER BY ex_date, id",
+    )?;
+    let actions = stmt
+        .query_map(
+            params![asset_id, ex_date - window, ex_date + window],
+            |row| {
+                Ok(Conflict {
+                    id: row.get(0)?,
+                    action_type: row
+                        .get::<_, String>(1)?
+                        .parse::<CorporateActionType>()
+                        .unwrap_or(CorporateActionType::Split)
+                        .as_str(),
+                    ex_date: row.get(2)?,
+                    quantity_adjustment: db::get_decimal_value(row, 3)?,
+                    source: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(actions)
+}
+
+/// Record one event for the asset: a pending action when nothing is recorded
+/// near its ex-date, nothing when the same action already is, and a
+/// CORPORATE_ACTION_CONFLICT inconsistency when another one is
+pub fn record_event(
+    conn: &Connection,
+    asset_id: i64,
+    event: B3Event,
+    dry_run: bool,
+) -> Result<Detection> {
+    let (held, _) = crate::subscriptions::position_on(conn, asset_id, event.last_date_prior)?;
+    let quantity_adjustment = if held > Decimal::ZERO {
+        event.adjustment(held)
+    } else {
+        Decimal::ZERO
+    };
+    let detection = |outcome| Detection {
+        event: event.clone(),
+        held,
+        quantity_adjustment,
+        outcome,
+    };
+    if quantity_adjustment.is_zero() {
+        return Ok(detection(Outcome::NotHeld));
+    }
+
+    let existing = nearby_actions(conn, asset_id, event.ex_date)?;
+    if let Some(same) = existing.iter().find(|a| {
+        a.action_type == event.action_type.as_str() && a.quantity_adjustment == quantity_adjustment
+    }) {
+        return Ok(detection(Outcome::Known { action_id: same.id }));
+    }
+
+    if existing.is_empty() {
+        let action_id = if dry_run {
+            None
+        } else {
+            Some(db::insert_corporate_action(
+                conn,
+                &event.clone().into_action(asset_id, quantity_adjustment),
+            )?)
+        };
+        return Ok(detection(Outcome::Recorded { action_id }));
+    }
+
+    let source_ref = format!(
+        "b3_event:{}:{}:{}",
+        event.ticker,
+        event.ex_date,
+        event.action_type.as_str()
+    );
+    let flagged = db::list_inconsistencies(
+        conn,
+        None,
+        Some(InconsistencyType::CorporateActionConflict),
+        Some(&event.ticker),
+    )?
+    .into_iter()
+    .find(|i| i.source_ref.as_deref() == Some(source_ref.as_str()));
+    match flagged {
+        Some(issue) if issue.status == InconsistencyStatus::Open => {
+            return Ok(detection(Outcome::Conflict {
+                inconsistency_id: issue.id,
+            }))
+        }
+        // Settled by the user already
+        Some(_) => {
+            return Ok(detection(Outcome::Known {
+                action_id: existing[0].id,
+            }))
+        }
+        None => {}
+    }
+    if dry_run {
+        return Ok(detection(Outcome::Conflict {
+            inconsistency_id: None,
+        }));
+    }
+
+    let id = db::insert_inconsistency(
+        conn,
+        &Inconsistency {
+            id: None,
+            issue_type: InconsistencyType::CorporateActionConflict,
+            status: InconsistencyStatus::Open,
+            severity: InconsistencySeverity::Warn,
+            asset_id: Some(asset_id),
+            transaction_id: None,
+            ticker: Some(event.ticker.clone()),
+            trade_date: Some(event.ex_date),
+            quantity: Some(quantity_adjustment),
+            source: Some(SOURCE.to_string()),
+            source_ref: Some(source_ref),
+            missing_fields_json: None,
+            context_json: Some(
+                json!({
+                    "notes": format!(
+                        "B3 reports {} for {} ({} shares on {} held); another action is recorded",
+                        event.describe(),
+                        event.ticker,
+                        quantity_adjustment,
+                        held
+                    ),
+                    "b3": event,
+                    "quantity_adjustment": quantity_adjustment.to_string(),
+                    "existing": existing,
+                })
+                .to_string(),
+            ),
+            resolution_action: None,
+            resolution_json: None,
+            created_at: None,
+            resolved_at: None,
+        },
+    )?;
+    Ok(detection(Outcome::Conflict {
+        inconsistency_id: Some(id),
+    }))
+}
+
+/// Resolve a conflict in B3's favour: delete the actions it disagreed with
+/// and record B3's. Returns the new action id.
+pub fn replace_with_b3(conn: &Connection, issue: &Inconsistency) -> Result<i64> {
+    let asset_id = issue
+        .asset_id
+        .ok_or_else(|| anyhow::anyhow!("the inconsistency has no asset"))?;
+    let context: serde_json::Value = serde_json::from_str(
+        issue
+            .context_json
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("the B3 event is missing"))?,
+    )?;
+    let event: B3Event =
+        serde_json::from_value(context["b3"].clone()).context("Invalid B3 event")?;
+    let quantity_adjustment = context["quantity_adjustment"]
+        .as_str()
+        .and_then(|q| Decimal::from_str(q).ok())
+        .ok_or_else(|| anyhow::anyhow!("the B3 quantity is missing"))?;
+
+    db::bulk::in_transaction(conn, |conn| {
+        for id in context["existing"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|a| a["id"].as_i64())
+        {
+            conn.execute("DELETE FROM corporate_actions WHERE id = ?1", [id])?;
+        }
+        db::insert_corporate_action(conn, &event.into_action(asset_id, quantity_adjustment))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::AssetType;
+
+    const PETR: &str = r#"[{"code":"PETR","stockDividends":[
+        {"assetIssued":"BRPETRACNOR9","factor":"100,00000000000","approvedOn":"25/04/2024",
+         "isinCode":"BRPETRACNOR9","label":"BONIFICACAO","lastDatePrior":"25/04/2024","remarks":""},
+        {"assetIssued":"BRPETRACNPR6","factor":"100,00000000000","approvedOn":"25/04/2024",
+         "isinCode":"BRPETRACNPR6","label":"BONIFICACAO","lastDatePrior":"25/04/2024","remarks":""},
+        {"assetIssued":"BRPETRACNPR6","factor":"10,00000000000","approvedOn":"01/03/2008",
+         "isinCode":"BRPETRACNPR6","label":"GRUPAMENTO","lastDatePrior":"07/03/2008","remarks":""},
+        {"assetIssued":"BRPETRACNPR6","factor":"5,00000000000","approvedOn":"01/03/2008",
+         "isinCode":"BRPETRACNPR6","label":"RESG TOTAL RV","lastDatePrior":"07/03/2008","remarks":""}
+    ]}]"#;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_parse_events_for_share_class() {
+        let events = parse_events(PETR, "PETR4").unwrap();
+        assert_eq!(events.len(), 2);
+        let reverse = &events[0];
+        assert_eq!(reverse.action_type, CorporateActionType::ReverseSplit);
+        // Friday "com" date, ex-date on Monday
+        assert_eq!(reverse.ex_date, date(2008, 3, 10));
+        assert_eq!(reverse.adjustment(Decimal::from(1005)), Decimal::from(-905));
+
+        let bonus = &events[1];
+        assert_eq!(bonus.action_type, CorporateActionType::Bonus);
+        assert_eq!(bonus.ex_date, date(2024, 4, 26));
+        assert_eq!(bonus.approved_on, Some(date(2024, 4, 25)));
+        assert_eq!(bonus.adjustment(Decimal::from(300)), Decimal::from(300));
+
+        assert_eq!(parse_events(PETR, "PETR3").unwrap().len(), 1);
+        assert!(isin_matches("BRTAEECDAM10", "TAEE11"));
+        assert!(!isin_matches("BRTAEEACNPR1", "TAEE11"));
+        assert!(!isin_matches("BRVALEACNOR0", "PETR3"));
+    }
+
+    #[test]
+    fn test_record_event_flags_a_different_manual_action() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id = db::upsert_asset(&conn, "PETR4", &AssetType::Stock, None).unwrap();
+        conn.execute(
+            "INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source)
+             VALUES (?1, 'BUY', '2024-01-10', '200', '30', '6000', '0', 'MANUAL')",
+            [asset_id],
+        )
+        .unwrap();
+        let events = parse_events(PETR, "PETR4").unwrap();
+        let bonus = events[1].clone();
+
+        // Nothing recorded: a pending B3 bonus, then known on the next run
+        let first = record_event(&conn, asset_id, bonus.clone(), false).unwrap();
+        assert_eq!(first.quantity_adjustment, Decimal::from(200));
+        let Outcome::Recorded {
+            action_id: Some(action_id),
+        } = first.outcome
+        else {
+            panic!("expected a recorded action, got {:?}", first.outcome);
+        };
+        let again = record_event(&conn, asset_id, bonus.clone(), false).unwrap();
+        assert!(matches!(again.outcome, Outcome::Known { action_id: id } if id == action_id));
+        // Not held in 2008
+        let old = record_event(&conn, asset_id, events[0].clone(), false).unwrap();
+        assert!(matches!(old.outcome, Outcome::NotHeld));
+
+        // A manual entry with another quantity is flagged once
+        conn.execute(
+            "UPDATE corporate_actions SET quantity_adjustment = '100', source = 'MANUAL'
+             WHERE id = ?1",
+            [action_id],
+        )
+        .unwrap();
+        let conflict = record_event(&conn, asset_id, bonus.clone(), false).unwrap();
+        let Outcome::Conflict {
+            inconsistency_id: Some(issue_id),
+        } = conflict.outcome
+        else {
+            panic!("expected a conflict, got {:?}", conflict.outcome);
+        };
+        let rerun = record_event(&conn, asset_id, bonus, false).unwrap();
+        assert!(
+            matches!(rerun.outcome, Outcome::Conflict { inconsistency_id: Some(id) } if id == issue_id)
+        );
+
+        let issue = db::get_inconsistency(&conn, issue_id).unwrap().unwrap();
+        let new_id = replace_with_b3(&conn, &issue).unwrap();
+        let (action, _) = db::get_corporate_action(&conn, new_id).unwrap().unwrap();
+        assert_eq!(action.quantity_adjustment, Decimal::from(200));
+        assert_eq!(action.source, SOURCE);
+        assert!(db::get_corporate_action(&conn, action_id)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,93 +1,68 @@
 // Corporate actions module - Split/bonus adjustment engine
 
+pub mod b3_events;
+pub mod review;
+
 use anyhow::Result;
 use rusqlite::Connection;
 use rust_decimal::Decimal;
 use std::str::FromStr;
 use tracing::info;
 
-use crate::db::{Asset, CorporateAction, CorporateActionType, Transaction, TransactionType};
+use crate::db::{Asset, CorporateAction, CorporateActionType};
 use chrono::NaiveDate;
 
-/// Get all corporate actions for an asset that occurred between two dates (inclusive)
-///
-/// Used for query-time adjustment: finds actions that should be applied to
-/// a transaction based on trade_date and as_of_date.
-pub fn get_applicable_actions(
-    conn: &Connection,
-    asset_id: i64,
-    after_date: NaiveDate,
-    up_to_date: NaiveDate,
-) -> Result<Vec<CorporateAction>> {
-    let mut stmt = conn.prepare(
-        "SELECT id, asset_id, action_type, event_date, ex_date, quantity_adjustment,
-                source, notes, created_at
-         FROM corporate_actions
-         WHERE asset_id = ?1 AND ex_date > ?2 AND ex_date <= ?3
-         ORDER BY ex_date ASC",
-    )?;
-
-    let actions = stmt
-        .query_map(rusqlite::params![asset_id, after_date, up_to_date], |row| {
-            Ok(CorporateAction {
-                id: Some(row.get(0)?),
-                asset_id: row.get(1)?,
-                action_type: row
-                    .get::<_, String>(2)?
-                    .parse::<CorporateActionType>()
-                    .unwrap_or(CorporateActionType::Split),
-                event_date: row.get(3)?,
-                ex_date: row.get(4)?,
-                quantity_adjustment: get_decimal_value(row, 5)?,
-                source: row.get(6)?,
-                notes: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
-
-    Ok(actions)
+/// Bonuses count once applied (reviewed); the other actions as soon as recorded
+const IN_EFFECT: &str = "(action_type != 'BONUS' OR applied_at IS NOT NULL)";
+
+fn action_from_row(row: &rusqlite::Row) -> rusqlite::Result<CorporateAction> {
+    Ok(CorporateAction {
+        id: Some(row.get(0)?),
+        asset_id: row.get(1)?,
+        action_type: row
+            .get::<_, String>(2)?
+            .parse::<CorporateActionType>()
+            .unwrap_or(CorporateActionType::Split),
+        event_date: row.get(3)?,
+        ex_date: row.get(4)?,
+        quantity_adjustment: get_decimal_value(row, 5)?,
+        source: row.get(6)?,
+        notes: row.get(7)?,
+        created_at: row.get(8)?,
+    })
 }
 
-/// Get all corporate actions for an asset with `ex_date <= up_to_date` (inclusive), sorted by `ex_date ASC`.
+/// Get the corporate actions in effect for an asset with `ex_date <= up_to_date`
+/// (inclusive), sorted by `ex_date ASC`. Pending bonuses are left out.
 pub fn get_actions_up_to(
     conn: &Connection,
     asset_id: i64,
     up_to_date: chrono::NaiveDate,
 ) -> Result<Vec<CorporateAction>> {
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT id, asset_id, action_type, event_date, ex_date, quantity_adjustment,
                 source, notes, created_at
          FROM corporate_actions
-         WHERE asset_id = ?1 AND ex_date <= ?2
+         WHERE asset_id = ?1 AND ex_date <= ?2 AND {}
          ORDER BY ex_date ASC",
-    )?;
+        IN_EFFECT
+    ))?;
 
     let actions = stmt
-        .query_map(rusqlite::params![asset_id, up_to_date.to_string()], |row| {
-            Ok(CorporateAction {
-                id: Some(row.get(0)?),
-                asset_id: row.get(1)?,
-                action_type: row
-                    .get::<_, String>(2)?
-                    .parse::<CorporateActionType>()
-                    .unwrap_or(CorporateActionType::Split),
-                event_date: row.get(3)?,
-                ex_date: row.get(4)?,
-                quantity_adjustment: get_decimal_value(row, 5)?,
-                source: row.get(6)?,
-                notes: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })?
+        .query_map(
+            rusqlite::params![asset_id, up_to_date.to_string()],
+            action_from_row,
+        )?
         .collect::<Result<Vec<_>, _>>()?;
 
     Ok(actions)
 }
 
-/// Apply forward-only quantity adjustments for splits/reverse splits up to `cutoff_date`.
+/// Apply forward-only quantity adjustments for splits, reverse splits and
+/// bonuses up to `cutoff_date`. Bonus shares come at zero cost, so like a
+/// split they only change the quantity the cost is spread over.
 ///
-/// Advances `action_idx` as actions are applied. Ignores bonus and capital return.
+/// Advances `action_idx` as actions are applied. Ignores capital return.
 pub fn apply_forward_qty_adjustments(
     quantity: &mut Decimal,
     actions: &[CorporateAction],
@@ -97,11 +72,8 @@ pub fn apply_forward_qty_adjustments(
     while *action_idx < actions.len() {
         let action = &actions[*action_idx];
         if action.ex_date <= cutoff_date {
-            match action.action_type {
-                CorporateActionType::Split | CorporateActionType::ReverseSplit => {
-                    *quantity += action.quantity_adjustment;
-                }
-                _ => {}
+            if action.action_type != CorporateActionType::CapitalReturn {
+                *quantity += action.quantity_adjustment;
             }
             *action_idx += 1;
         } else {
@@ -110,72 +82,6 @@ pub fn apply_forward_qty_adjustments(
     }
 }
 
-/// Apply corporate action adjustments to a quantity at query time
-///
-/// Adds/subtracts the adjustment quantity (sign convention: positive = add, negative = subtract)
-/// For bonus: no adjustment (bonus creates separate transaction)
-/// For capital return: no quantity adjustment (affects cost basis only)
-///
-/// Returns the adjusted quantity
-pub fn adjust_quantity_for_actions(quantity: Decimal, actions: &[CorporateAction]) -> Decimal {
-    let mut adjusted = quantity;
-    for action in actions {
-        match action.action_type {
-            CorporateActionType::Split | CorporateActionType::ReverseSplit => {
-                // Apply the quantity adjustment directly (sign convention: positive = add, negative = subtract)
-                adjusted += action.quantity_adjustment;
-            }
-            CorporateActionType::Bonus => {
-                // Bonus creates a separate zero-cost transaction, doesn't adjust existing transactions
-            }
-            CorporateActionType::CapitalReturn => {
-                // Capital return affects cost basis, not quantity
-            }
-        }
-    }
-    adjusted
-}
-
-/// Adjust price per unit for corporate actions at query time
-///
-/// For quantity-based adjustments: recalculate price from adjusted total cost
-/// For capital return: affects total cost, compute new price = new_total / quantity
-///
-/// Returns (adjusted_price, adjusted_total_cost)
-pub fn adjust_price_and_cost_for_actions(
-    quantity: Decimal,
-    _price: Decimal,
-    total_cost: Decimal,
-    actions: &[CorporateAction],
-) -> (Decimal, Decimal) {
-    let mut adjusted_qty = quantity;
-    let adjusted_cost = total_cost;
-
-    for action in actions {
-        match action.action_type {
-            CorporateActionType::Split
-            | CorporateActionType::ReverseSplit
-            | CorporateActionType::Bonus => {
-                // Apply quantity adjustment
-                adjusted_qty += action.quantity_adjustment;
-                // Total cost stays same for splits/bonuses; price per unit adjusts automatically
-            }
-            CorporateActionType::CapitalReturn => {
-                // Capital return handling should be revised for new approach
-            }
-        }
-    }
-
-    // Recompute price per unit based on adjusted quantity
-    let adjusted_price = if adjusted_qty > Decimal::ZERO {
-        adjusted_cost / adjusted_qty
-    } else {
-        Decimal::ZERO
-    };
-
-    (adjusted_price, adjusted_cost)
-}
-
 /// Get unapplied corporate actions for an asset (or all assets if None)
 pub fn get_unapplied_actions(
     conn: &Connection,
@@ -185,133 +91,182 @@ pub fn get_unapplied_actions(
         "SELECT id, asset_id, action_type, event_date, ex_date, quantity_adjustment,
                 source, notes, created_at
          FROM corporate_actions
-         WHERE asset_id = ?1
+         WHERE asset_id = ?1 AND applied_at IS NULL
          ORDER BY ex_date ASC"
     } else {
         "SELECT id, asset_id, action_type, event_date, ex_date, quantity_adjustment,
                 source, notes, created_at
          FROM corporate_actions
+         WHERE applied_at IS NULL
          ORDER BY ex_date ASC"
     };
 
     let mut stmt = conn.prepare(query)?;
 
     let actions = if let Some(asset_id) = asset_id_filter {
-        stmt.query_map([asset_id], |row| {
-            Ok(CorporateAction {
-                id: Some(row.get(0)?),
-                asset_id: row.get(1)?,
-                action_type: row
-                    .get::<_, String>(2)?
-                    .parse::<crate::db::CorporateActionType>()
-                    .unwrap_or(crate::db::CorporateActionType::Split),
-                event_date: row.get(3)?,
-                ex_date: row.get(4)?,
-                quantity_adjustment: get_decimal_value(row, 5)?,
-                source: row.get(6)?,
-                notes: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?
+        stmt.query_map([asset_id], action_from_row)?
+            .collect::<Result<Vec<_>, _>>()?
     } else {
-        stmt.query_map([], |row| {
-            Ok(CorporateAction {
-                id: Some(row.get(0)?),
-                asset_id: row.get(1)?,
-                action_type: row
-                    .get::<_, String>(2)?
-                    .parse::<crate::db::CorporateActionType>()
-                    .unwrap_or(crate::db::CorporateActionType::Split),
-                event_date: row.get(3)?,
-                ex_date: row.get(4)?,
-                quantity_adjustment: get_decimal_value(row, 5)?,
-                source: row.get(6)?,
-                notes: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?
+        stmt.query_map([], action_from_row)?
+            .collect::<Result<Vec<_>, _>>()?
     };
 
     Ok(actions)
 }
 
-/// Apply a corporate action by creating synthetic transactions (for bonus shares)
+/// Apply a corporate action: mark it reviewed. Recorded trades are never
+/// touched; every action takes effect at query time, a bonus only once it is
+/// applied, so applying one adds its shares to the positions computed from
+/// then on and unapplying takes them out again.
 ///
-/// For splits/reverse splits: No mutation - adjustments happen at query time.
-/// For bonus shares: Creates a zero-cost BUY transaction.
-/// For capital return: No synthetic transaction - cost adjustment happens at query time.
-///
-/// Returns the number of transactions created (0 or 1).
+/// Returns 1 when applying changed the position (a bonus), 0 otherwise.
 pub fn apply_corporate_action(
     conn: &Connection,
     action: &CorporateAction,
     asset: &Asset,
 ) -> Result<usize> {
+    if let Some(id) = action.id {
+        conn.execute(
+            "UPDATE corporate_actions SET applied_at = CURRENT_TIMESTAMP WHERE id = ?1",
+            [id],
+        )?;
+    }
+
     info!(
-        "Processing {} for {} (adjustment: {} shares)",
+        "Applied {} for {} (adjustment: {} shares)",
         action.action_type.as_str(),
         asset.ticker,
         action.quantity_adjustment
     );
 
-    // For bonus actions, create a zero-cost BUY transaction
-    if action.action_type == crate::db::CorporateActionType::Bonus {
-        // For bonus with quantity adjustment, the quantity_adjustment represents
-        // the shares to add to each shareholder's position
-        let bonus_qty = action.quantity_adjustment;
-
-        if bonus_qty > Decimal::ZERO {
-            let notes = format!(
-                "Bonus shares from {} ({} shares)",
-                action.action_type.as_str(),
-                bonus_qty
-            );
-
-            let bonus_tx = Transaction {
-                id: None,
-                asset_id: action.asset_id,
-                transaction_type: TransactionType::Buy,
-                trade_date: action.ex_date,
-                settlement_date: Some(action.ex_date),
-                quantity: bonus_qty,
-                price_per_unit: Decimal::ZERO,
-                total_cost: Decimal::ZERO,
-                fees: Decimal::ZERO,
-                is_day_trade: false,
-                quota_issuance_date: None,
-                notes: Some(notes),
-                source: "CORPORATE_ACTION".to_string(),
-                created_at: chrono::Utc::now(),
+    let changes_position = action.action_type == CorporateActionType::Bonus
+        && action.quantity_adjustment > Decimal::ZERO;
+    Ok(usize::from(changes_position))
+}
+
+/// Signed quantities of an asset's trades, by date
+fn traded_quantities(conn: &Connection, asset_id: i64) -> Result<Vec<(NaiveDate, Decimal)>> {
+    let mut stmt = conn.prepare(
+        "SELECT trade_date, transaction_type, quantity FROM transactions WHERE asset_id = ?1",
+    )?;
+    let rows = stmt
+        .query_map([asset_id], |row| {
+            let quantity = get_decimal_value(row, 2)?;
+            let sign = match row.get::<_, String>(1)?.as_str() {
+                "SELL" => -Decimal::ONE,
+                _ => Decimal::ONE,
             };
-            crate::db::insert_transaction(conn, &bonus_tx)?;
+            Ok((row.get(0)?, sign * quantity))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
 
-            info!(
-                "Created bonus transaction: {} shares for {} on {}",
-                bonus_qty, asset.ticker, action.ex_date
-            );
+fn held_before(trades: &[(NaiveDate, Decimal)], ex_date: NaiveDate) -> Decimal {
+    trades
+        .iter()
+        .filter(|(date, _)| *date < ex_date)
+        .map(|(_, quantity)| *quantity)
+        .sum()
+}
 
-            return Ok(1);
-        } else {
-            info!(
-                "Bonus action {} for {} resulted in zero bonus quantity",
-                action.id.unwrap_or(0),
-                asset.ticker
-            );
-            return Ok(0);
-        }
+/// Splits, reverse splits and bonuses (pending ones included) of an asset
+/// with the shares held right before each ex-date, across all portfolios
+pub fn holdings_before_actions(
+    conn: &Connection,
+    asset_id: i64,
+) -> Result<Vec<(CorporateAction, Decimal)>> {
+    let trades = traded_quantities(conn, asset_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT id, asset_id, action_type, event_date, ex_date, quantity_adjustment,
+                source, notes, created_at
+         FROM corporate_actions
+         WHERE asset_id = ?1 AND action_type != 'CAPITAL_RETURN'
+         ORDER BY ex_date ASC",
+    )?;
+    let actions = stmt
+        .query_map([asset_id], action_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut from_actions = Decimal::ZERO;
+    let mut holdings = Vec::new();
+    for action in actions {
+        let held = held_before(&trades, action.ex_date) + from_actions;
+        from_actions += action.quantity_adjustment;
+        holdings.push((action, held));
     }
+    Ok(holdings)
+}
 
-    // For splits, reverse splits, and capital returns: no synthetic transactions
-    // Adjustments are applied at query time
-    info!(
-        "{} for {} will be applied at query time",
-        action.action_type.as_str(),
-        asset.ticker
-    );
+/// A share-count action whose adjustment no longer matches the position
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Rescale {
+    pub action_id: i64,
+    pub action_type: String,
+    pub ex_date: NaiveDate,
+    pub source: String,
+    pub from: Decimal,
+    pub to: Decimal,
+}
 
-    Ok(0)
+/// Adjustments that would keep share-count actions in proportion after
+/// trades before their ex-date changed. Adjustments are absolute share
+/// counts, so each is rescaled to the ratio it had to the position in
+/// `before` (taken with [`holdings_before_actions`] ahead of the change).
+/// Nothing is written; see [`apply_rescales`].
+pub fn propose_rescales(
+    conn: &Connection,
+    before: &[(CorporateAction, Decimal)],
+) -> Result<Vec<Rescale>> {
+    let Some((first, _)) = before.first() else {
+        return Ok(Vec::new());
+    };
+    let trades = traded_quantities(conn, first.asset_id)?;
+    let mut from_actions = Decimal::ZERO;
+    let mut proposed = Vec::new();
+    for (action, old_held) in before {
+        let held = held_before(&trades, action.ex_date) + from_actions;
+        let mut adjustment = action.quantity_adjustment;
+        if *old_held > Decimal::ZERO && held >= Decimal::ZERO && held != *old_held {
+            let old_after = *old_held + action.quantity_adjustment;
+            let mut after = held * old_after / *old_held;
+            // Fractions of whole shares are auctioned off, not credited
+            if old_held.fract().is_zero() && old_after.fract().is_zero() {
+                after = after.floor();
+            }
+            adjustment = after - held;
+        }
+        from_actions += adjustment;
+        let Some(id) = action
+            .id
+            .filter(|_| adjustment != action.quantity_adjustment)
+        else {
+            continue;
+        };
+        proposed.push(Rescale {
+            action_id: id,
+            action_type: action.action_type.as_str().to_string(),
+            ex_date: action.ex_date,
+            source: action.source.clone(),
+            from: action.quantity_adjustment,
+            to: adjustment,
+        });
+    }
+    Ok(proposed)
+}
+
+/// Write the adjustments from [`propose_rescales`]
+pub fn apply_rescales(conn: &Connection, rescales: &[Rescale]) -> Result<()> {
+    for rescale in rescales {
+        conn.execute(
+            "UPDATE corporate_actions SET quantity_adjustment = ?2 WHERE id = ?1",
+            rusqlite::params![rescale.action_id, rescale.to.to_string()],
+        )?;
+        info!(
+            "Rescaled {} {} from {} to {} shares",
+            rescale.action_type, rescale.action_id, rescale.from, rescale.to
+        );
+    }
+    Ok(())
 }
 
 /// Helper to read Decimal from SQLite (handles both INTEGER, REAL and TEXT)
@@ -417,4 +372,63 @@ mod tests {
         // Total cost unchanged
         assert_eq!(quantity * original_price, new_qty * new_price);
     }
+
+    #[test]
+    fn test_rescale_actions_keeps_ratio_to_position() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'ITSA4', 'STOCK');
+             INSERT INTO transactions (id, asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source)
+             VALUES (1, 1, 'BUY', '2024-01-10', '100', '10', '1000', '0', 'MANUAL'),
+                    (2, 1, 'BUY', '2024-02-10', '50', '10', '500', '0', 'MANUAL');
+             -- 2:1 split on 150 shares, then a 10% bonus on 300
+             INSERT INTO corporate_actions (id, asset_id, action_type, event_date, ex_date,
+                 quantity_adjustment, source, applied_at)
+             VALUES (1, 1, 'SPLIT', '2024-03-01', '2024-03-01', '150', 'MANUAL', NULL),
+                    (2, 1, 'BONUS', '2024-04-01', '2024-04-01', '30', 'MANUAL', '2024-04-01');",
+        )
+        .unwrap();
+
+        let before = holdings_before_actions(&conn, 1).unwrap();
+        assert_eq!(
+            before.iter().map(|(_, held)| *held).collect::<Vec<_>>(),
+            vec![Decimal::from(150), Decimal::from(300)]
+        );
+
+        // The second buy was really 55 shares
+        conn.execute("UPDATE transactions SET quantity = '55' WHERE id = 2", [])
+            .unwrap();
+        let proposed = propose_rescales(&conn, &before).unwrap();
+        assert_eq!(
+            proposed.iter().map(|r| r.action_id).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        // Proposing leaves the actions as they were
+        let unchanged: Vec<Decimal> = holdings_before_actions(&conn, 1)
+            .unwrap()
+            .iter()
+            .map(|(action, _)| action.quantity_adjustment)
+            .collect();
+        assert_eq!(unchanged, vec![Decimal::from(150), Decimal::from(30)]);
+        apply_rescales(&conn, &proposed).unwrap();
+
+        let adjustments: Vec<Decimal> = holdings_before_actions(&conn, 1)
+            .unwrap()
+            .iter()
+            .map(|(action, _)| action.quantity_adjustment)
+            .collect();
+        // 155 doubles to 310, and 10% of 310 is 31
+        assert_eq!(adjustments, vec![Decimal::from(155), Decimal::from(31)]);
+        let trades: i64 = conn
+            .query_row("SELECT COUNT(*) FROM transactions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(trades, 2);
+
+        // Nothing left to rescale once in proportion
+        let before = holdings_before_actions(&conn, 1).unwrap();
+        assert!(propose_rescales(&conn, &before).unwrap().is_empty());
+    }
 }
@@ -0,0 +1,300 @@
+//! Review queue for corporate actions: what is pending, what was applied
+//! recently, the effect each one has on the position, and other sources
+//! reporting the same event differently.
+
+// Apply/unapply are driven by the review screen, which needs the tui feature
+#![cfg_attr(not(feature = "tui"), allow(dead_code))]
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::db::{self, CorporateAction, CorporateActionType};
+
+/// Applied actions stay on the review list for this many days
+pub const RECENT_DAYS: i64 = 30;
+/// Actions of one asset this close together are taken as the same event
+pub const CONFLICT_WINDOW_DAYS: i64 = 5;
+
+/// Another source's record of what looks like the same event
+#[derive(Debug, Clone, Serialize)]
+pub struct Conflict {
+    pub id: i64,
+    pub action_type: &'static str,
+    pub ex_date: NaiveDate,
+    pub quantity_adjustment: Decimal,
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReviewEntry {
+    #[serde(skip)]
+    pub action: CorporateAction,
+    pub id: i64,
+    pub ticker: String,
+    pub action_type: &'static str,
+    pub ex_date: NaiveDate,
+    pub quantity_adjustment: Decimal,
+    pub source: String,
+    pub applied_at: Option<DateTime<Utc>>,
+    pub conflicts: Vec<Conflict>,
+}
+
+impl ReviewEntry {
+    pub fn is_applied(&self) -> bool {
+        self.applied_at.is_some()
+    }
+}
+
+/// Position right before the ex-date and what the action turns it into
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectPreview {
+    pub held_before: Decimal,
+    pub cost_before: Decimal,
+    pub held_after: Decimal,
+    /// Only a bonus waits for review; the others already act at query time
+    pub applies_on_review: bool,
+}
+
+impl EffectPreview {
+    pub fn average_before(&self) -> Decimal {
+        average(self.cost_before, self.held_before)
+    }
+
+    pub fn average_after(&self) -> Decimal {
+        average(self.cost_before, self.held_after)
+    }
+}
+
+fn average(cost: Decimal, quantity: Decimal) -> Decimal {
+    if quantity > Decimal::ZERO {
+        (cost / quantity).round_dp(4)
+    } else {
+        Decimal::ZERO
+    }
+}
+
+/// Pending actions (oldest ex-date first), then those applied in the last
+/// `RECENT_DAYS` (most recent first)
+pub fn review_entries(conn: &Connection, now: DateTime<Utc>) -> Result<Vec<ReviewEntry>> {
+    let since = (now - Duration::days(RECENT_DAYS))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string();
+    let mut stmt = conn.prepare(
+        "SELECT ca.id, ca.asset_id, ca.action_type, ca.event_date, ca.ex_date,
+                ca.quantity_adjustment, ca.source, ca.notes, ca.created_at,
+                a.ticker, ca.applied_at
+         FROM corporate_actions ca
+         JOIN assets a ON ca.asset_id = a.id
+         WHERE ca.applied_at IS NULL OR ca.applied_at >= ?1
+         ORDER BY ca.applied_at IS NOT NULL, ca.applied_at DESC, ca.ex_date ASC, ca.id ASC",
+    )?;
+    let rows = stmt
+        .query_map([since], |row| {
+            Ok((
+                super::action_from_row(row)?,
+                row.get::<_, String>(9)?,
+                row.get::<_, Option<DateTime<Utc>>>(10)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    rows.into_iter()
+        .map(|(action, ticker, applied_at)| {
+            let conflicts = conflicts_of(conn, &action)?;
+            Ok(ReviewEntry {
+                id: action.id.unwrap_or_default(),
+                ticker,
+                action_type: action.action_type.as_str(),
+                ex_date: action.ex_date,
+                quantity_adjustment: action.quantity_adjustment,
+                source: action.source.clone(),
+                applied_at,
+                conflicts,
+                action,
+            })
+        })
+        .collect()
+}
+
+/// Actions from other sources for the same asset within `CONFLICT_WINDOW_DAYS`
+pub fn conflicts_of(conn: &Connection, action: &CorporateAction) -> Result<Vec<Conflict>> {
+    let window = Duration::days(CONFLICT_WINDOW_DAYS);
+    let mut stmt = conn.prepare(
+        "SELECT id, action_type, ex_date, quantity_adjustment, source
+         FROM corporate_actions
+         WHERE asset_id = ?1 AND id != ?2 AND ex_date BETWEEN ?3 AND ?4
+           AND COALESCE(source, '') != ?5
+         ORDER BY ex_date, id",
+    )?;
+    let conflicts = stmt
+        .query_map(
+            rusqlite::params![
+                action.asset_id,
+                action.id.unwrap_or_default(),
+                action.ex_date - window,
+                action.ex_date + window,
+                action.source,
+            ],
+            |row| {
+                Ok(Conflict {
+                    id: row.get(0)?,
+                    action_type: row
+                        .get::<_, String>(1)?
+                        .parse::<CorporateActionType>()
+                        .unwrap_or(CorporateActionType::Split)
+                        .as_str(),
+                    ex_date: row.get(2)?,
+                    quantity_adjustment: db::get_decimal_value(row, 3)?,
+                    source: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(conflicts)
+}
+
+pub fn preview(conn: &Connection, action: &CorporateAction) -> Result<EffectPreview> {
+    let day_before = action.ex_date.pred_opt().unwrap_or(action.ex_date);
+    let (held_before, cost_before) =
+        crate::subscriptions::position_on(conn, action.asset_id, day_before)?;
+    let held_after = match action.action_type {
+        CorporateActionType::Split
+        | CorporateActionType::ReverseSplit
+        | CorporateActionType::Bonus => held_before + action.quantity_adjustment,
+        CorporateActionType::CapitalReturn => held_before,
+    };
+    Ok(EffectPreview {
+        held_before,
+        cost_before,
+        held_after,
+        applies_on_review: action.action_type == CorporateActionType::Bonus,
+    })
+}
+
+fn applied_at(conn: &Connection, id: i64) -> Result<Option<String>> {
+    Ok(conn.query_row(
+        "SELECT applied_at FROM corporate_actions WHERE id = ?1",
+        [id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Apply a pending action; returns 1 when it changed the position (a bonus)
+pub fn apply(conn: &Connection, id: i64) -> Result<usize> {
+    let (action, asset) =
+        db::get_corporate_action(conn, id)?.context("Corporate action id not found")?;
+    if applied_at(conn, id)?.is_some() {
+        anyhow::bail!("Corporate action {} is already applied", id);
+    }
+    super::apply_corporate_action(conn, &action, &asset)
+}
+
+/// Return an applied bonus to pending, taking its shares out of the position
+pub fn unapply(conn: &Connection, id: i64) -> Result<()> {
+    let (action, _asset) =
+        db::get_corporate_action(conn, id)?.context("Corporate action id not found")?;
+    if applied_at(conn, id)?.is_none() {
+        anyhow::bail!("Corporate action {} is not applied", id);
+    }
+    match action.action_type {
+        CorporateActionType::Bonus => {}
+        CorporateActionType::Split | CorporateActionType::ReverseSplit => anyhow::bail!(
+            "{} {} takes effect at query time; undo it with: interest actions split remove {}",
+            action.action_type.as_str(),
+            id,
+            id
+        ),
+        CorporateActionType::CapitalReturn => anyhow::bail!(
+            "CAPITAL_RETURN {} takes effect at query time and cannot be unapplied",
+            id
+        ),
+    }
+    conn.execute(
+        "UPDATE corporate_actions SET applied_at = NULL WHERE id = ?1",
+        [id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review_preview_apply_and_unapply() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('ITSA4', 'STOCK');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+                 VALUES (1, 'BUY', '2024-01-10', '100', '10', '1000', '0', 0, 'CEI');
+             INSERT INTO corporate_actions (asset_id, action_type, event_date, ex_date,
+                 quantity_adjustment, source)
+                 VALUES (1, 'BONUS', '2024-03-01', '2024-03-01', '10', 'B3');
+             INSERT INTO corporate_actions (asset_id, action_type, event_date, ex_date,
+                 quantity_adjustment, source)
+                 VALUES (1, 'SPLIT', '2024-03-04', '2024-03-04', '10', 'YAHOO');",
+        )
+        .unwrap();
+        let now = Utc::now();
+
+        let entries = review_entries(&conn, now).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|e| !e.is_applied()));
+        assert_eq!(entries[0].action_type, "BONUS");
+        assert_eq!(entries[0].conflicts.len(), 1);
+        assert_eq!(entries[0].conflicts[0].source, "YAHOO");
+
+        let effect = preview(&conn, &entries[0].action).unwrap();
+        assert_eq!(effect.held_before, Decimal::from(100));
+        assert_eq!(effect.held_after, Decimal::from(110));
+        assert_eq!(effect.average_before(), Decimal::from(10));
+        assert!(effect.applies_on_review);
+        let held_on = |date: &str| {
+            crate::subscriptions::position_on(&conn, 1, date.parse().unwrap())
+                .unwrap()
+                .0
+        };
+        // A pending bonus is not in the position yet
+        assert_eq!(held_on("2024-03-02"), Decimal::from(100));
+
+        assert_eq!(apply(&conn, entries[0].id).unwrap(), 1);
+        assert!(apply(&conn, entries[0].id).is_err());
+        assert_eq!(held_on("2024-03-02"), Decimal::from(110));
+        let entries = review_entries(&conn, now).unwrap();
+        assert_eq!(entries[0].action_type, "SPLIT");
+        assert!(entries[1].is_applied());
+        assert!(
+            super::super::get_unapplied_actions(&conn, None)
+                .unwrap()
+                .len()
+                == 1
+        );
+
+        assert_eq!(apply(&conn, entries[0].id).unwrap(), 0);
+        assert!(unapply(&conn, entries[0].id).is_err());
+
+        unapply(&conn, entries[1].id).unwrap();
+        assert_eq!(held_on("2024-03-02"), Decimal::from(100));
+        // The recorded trade itself was never touched
+        let trades: Vec<(Decimal, Decimal)> = conn
+            .prepare("SELECT quantity, total_cost FROM transactions")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((
+                    db::get_decimal_value(row, 0)?,
+                    db::get_decimal_value(row, 1)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(trades, vec![(Decimal::from(100), Decimal::from(1000))]);
+        assert!(!review_entries(&conn, now).unwrap()[0].is_applied());
+    }
+}
@@ -0,0 +1,442 @@
+//! Background daemon that interactive sessions hand heavy tasks to.
+//!
+//! `interest daemon` listens on a Unix socket next to the database
+//! (`~/.interest/daemon.sock`). While it runs, the interactive mode sends
+//! price updates, B3 syncs and its background refresh there instead of
+//! running them in process. The daemon runs one task at a time, so sessions
+//! never write the database concurrently, and a task requested while the
+//! same one is queued or running joins it instead of starting over. Output
+//! is streamed back line by line as it is produced.
+//!
+//! Requests and replies are JSON, one object per line.
+
+#![cfg_attr(not(feature = "tui"), allow(dead_code))]
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Socket file inside the interest directory
+const SOCKET_NAME: &str = "daemon.sock";
+
+/// Top-level commands the daemon runs on behalf of a session
+const DELEGATED_COMMANDS: &[&str] = &["prices", "sync-b3"];
+
+pub fn socket_path() -> Result<PathBuf> {
+    Ok(crate::db::get_interest_dir()?.join(SOCKET_NAME))
+}
+
+/// Work a session can hand to the daemon
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Task {
+    /// The interactive mode's refresh: today's prices and the tax snapshot
+    Refresh,
+    /// A CLI command, given as its arguments (e.g. `["prices", "update"]`)
+    Command { args: Vec<String> },
+}
+
+impl Task {
+    pub fn describe(&self) -> String {
+        match self {
+            Task::Refresh => "background refresh".to_string(),
+            Task::Command { args } => args.join(" "),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Task::Refresh => Ok(()),
+            Task::Command { args } => match args.first() {
+                Some(cmd) if DELEGATED_COMMANDS.contains(&cmd.as_str()) => Ok(()),
+                _ => Err(anyhow::anyhow!(
+                    "The daemon only runs: {}",
+                    DELEGATED_COMMANDS.join(", ")
+                )),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "snake_case")]
+enum Request {
+    Status,
+    Run { task: Task },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event {
+    Status {
+        tasks: Vec<TaskStatus>,
+    },
+    Output {
+        line: String,
+    },
+    Done {
+        success: bool,
+        message: Option<String>,
+    },
+}
+
+/// A task queued or running in the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskStatus {
+    pub task: String,
+    /// None while waiting for the task ahead of it
+    pub started_at: Option<DateTime<Utc>>,
+    pub last_line: Option<String>,
+}
+
+/// Whether a session may hand its work to the daemon, which always works on
+/// the main database: not from a sandbox, a portfolio scope or an encrypted
+/// working copy
+pub fn can_delegate() -> bool {
+    !crate::db::sandbox::is_active()
+        && !crate::db::portfolio::is_scoped()
+        && crate::db::encryption::working_path().is_none()
+}
+
+#[cfg(unix)]
+pub use unix::{run_task, serve, status};
+
+#[cfg(not(unix))]
+pub fn status() -> Option<Vec<TaskStatus>> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn run_task(_task: &Task, _on_line: impl FnMut(&str)) -> Result<()> {
+    Err(anyhow::anyhow!("The daemon needs Unix domain sockets"))
+}
+
+#[cfg(not(unix))]
+pub async fn serve() -> Result<()> {
+    Err(anyhow::anyhow!("The daemon needs Unix domain sockets"))
+}
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use anyhow::Context;
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+    use tokio::sync::{broadcast, mpsc};
+
+    /// How long a session waits for the daemon to answer a status request
+    const STATUS_TIMEOUT: Duration = Duration::from_millis(500);
+
+    fn connect() -> Option<std::os::unix::net::UnixStream> {
+        let path = socket_path().ok()?;
+        if !path.exists() {
+            return None;
+        }
+        std::os::unix::net::UnixStream::connect(path).ok()
+    }
+
+    fn send(stream: &mut std::os::unix::net::UnixStream, request: &Request) -> Result<()> {
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        stream.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Tasks queued or running; None when no daemon is listening
+    pub fn status() -> Option<Vec<TaskStatus>> {
+        let mut stream = connect()?;
+        stream.set_read_timeout(Some(STATUS_TIMEOUT)).ok()?;
+        send(&mut stream, &Request::Status).ok()?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).ok()?;
+        match serde_json::from_str(&line).ok()? {
+            Event::Status { tasks } => Some(tasks),
+            _ => None,
+        }
+    }
+
+    /// Run `task` in the daemon, passing each line of its output to `on_line`
+    pub fn run_task(task: &Task, mut on_line: impl FnMut(&str)) -> Result<()> {
+        let mut stream = connect().context("The daemon is not running")?;
+        send(&mut stream, &Request::Run { task: task.clone() })?;
+        for line in BufReader::new(stream).lines() {
+            match serde_json::from_str(&line?)? {
+                Event::Output { line } => on_line(&line),
+                Event::Done { success: true, .. } => return Ok(()),
+                Event::Done { message, .. } => {
+                    return Err(anyhow::anyhow!(
+                        "{} failed in the daemon{}",
+                        task.describe(),
+                        message.map(|m| format!(": {}", m)).unwrap_or_default()
+                    ))
+                }
+                Event::Status { .. } => {}
+            }
+        }
+        Err(anyhow::anyhow!("The daemon closed the connection"))
+    }
+
+    struct Entry {
+        events: broadcast::Sender<Event>,
+        status: TaskStatus,
+    }
+
+    #[derive(Default)]
+    struct Daemon {
+        /// Held while a task runs, so tasks run one at a time
+        run_lock: tokio::sync::Mutex<()>,
+        tasks: Mutex<HashMap<Task, Entry>>,
+    }
+
+    impl Daemon {
+        fn tasks(&self) -> std::sync::MutexGuard<'_, HashMap<Task, Entry>> {
+            self.tasks.lock().unwrap_or_else(|e| e.into_inner())
+        }
+
+        fn status(&self) -> Vec<TaskStatus> {
+            let mut tasks: Vec<TaskStatus> =
+                self.tasks().values().map(|e| e.status.clone()).collect();
+            tasks.sort_by_key(|t| t.started_at.is_none());
+            tasks
+        }
+
+        /// Subscribe to `task`, queueing it unless it is already pending
+        fn submit(self: &Arc<Self>, task: Task) -> (broadcast::Receiver<Event>, Option<String>) {
+            let mut tasks = self.tasks();
+            if let Some(entry) = tasks.get(&task) {
+                return (entry.events.subscribe(), entry.status.last_line.clone());
+            }
+            let (events, receiver) = broadcast::channel(256);
+            tasks.insert(
+                task.clone(),
+                Entry {
+                    events,
+                    status: TaskStatus {
+                        task: task.describe(),
+                        started_at: None,
+                        last_line: None,
+                    },
+                },
+            );
+            drop(tasks);
+
+            let daemon = Arc::clone(self);
+            tokio::spawn(async move {
+                let _running = daemon.run_lock.lock().await;
+                if let Some(entry) = daemon.tasks().get_mut(&task) {
+                    entry.status.started_at = Some(Utc::now());
+                }
+                tracing::info!("Daemon running {}", task.describe());
+
+                let (lines, mut output) = mpsc::unbounded_channel();
+                let worker = tokio::spawn(execute(task.clone(), lines));
+                while let Some(line) = output.recv().await {
+                    daemon.publish(&task, Event::Output { line });
+                }
+                let done = match worker.await {
+                    Ok(Ok(())) => Event::Done {
+                        success: true,
+                        message: None,
+                    },
+                    Ok(Err(e)) => Event::Done {
+                        success: false,
+                        message: Some(e.to_string()),
+                    },
+                    Err(e) => Event::Done {
+                        success: false,
+                        message: Some(e.to_string()),
+                    },
+                };
+                if let Some(entry) = daemon.tasks().remove(&task) {
+                    let _ = entry.events.send(done);
+                }
+            });
+            (receiver, None)
+        }
+
+        fn publish(&self, task: &Task, event: Event) {
+            if let Some(entry) = self.tasks().get_mut(task) {
+                if let Event::Output { line } = &event {
+                    entry.status.last_line = Some(line.clone());
+                }
+                let _ = entry.events.send(event);
+            }
+        }
+    }
+
+    /// Run a task, sending its output lines to `lines`
+    async fn execute(task: Task, lines: mpsc::UnboundedSender<String>) -> Result<()> {
+        match task {
+            Task::Refresh => {
+                tokio::task::spawn_blocking(move || {
+                    crate::ui::refresh::refresh_once(|line| {
+                        let _ = lines.send(line);
+                    })
+                })
+                .await?
+            }
+            Task::Command { args } => {
+                let mut child = tokio::process::Command::new(std::env::current_exe()?)
+                    .args(&args)
+                    .env("NO_COLOR", "1")
+                    .stdin(std::process::Stdio::null())
+                    .stdout(std::process::Stdio::piped())
+                    .stderr(std::process::Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .context("Failed to start the command")?;
+                let mut readers = Vec::new();
+                let stdout = child.stdout.take().map(|s| forward(s, lines.clone()));
+                let stderr = child.stderr.take().map(|s| forward(s, lines.clone()));
+                readers.extend(stdout);
+                readers.extend(stderr);
+                let status = child.wait().await?;
+                for reader in readers {
+                    let _ = reader.await;
+                }
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err(anyhow::anyhow!("exited with {}", status))
+                }
+            }
+        }
+    }
+
+    fn forward<R>(reader: R, lines: mpsc::UnboundedSender<String>) -> tokio::task::JoinHandle<()>
+    where
+        R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    {
+        tokio::spawn(async move {
+            let mut reader = tokio::io::BufReader::new(reader).lines();
+            while let Ok(Some(line)) = reader.next_line().await {
+                let _ = lines.send(line);
+            }
+        })
+    }
+
+    async fn handle(daemon: Arc<Daemon>, stream: tokio::net::UnixStream) -> Result<()> {
+        let (read, mut write) = stream.into_split();
+        let mut request = String::new();
+        tokio::io::BufReader::new(read)
+            .read_line(&mut request)
+            .await?;
+        let reply = |event: &Event| -> Result<Vec<u8>> {
+            let mut line = serde_json::to_string(event)?;
+            line.push('\n');
+            Ok(line.into_bytes())
+        };
+
+        match serde_json::from_str(&request)? {
+            Request::Status => {
+                let event = Event::Status {
+                    tasks: daemon.status(),
+                };
+                write.write_all(&reply(&event)?).await?;
+            }
+            Request::Run { task } => {
+                if let Err(e) = task.validate() {
+                    let event = Event::Done {
+                        success: false,
+                        message: Some(e.to_string()),
+                    };
+                    write.write_all(&reply(&event)?).await?;
+                    return Ok(());
+                }
+                let (mut events, last_line) = daemon.submit(task);
+                if let Some(line) = last_line {
+                    write.write_all(&reply(&Event::Output { line })?).await?;
+                }
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            let done = matches!(event, Event::Done { .. });
+                            write.write_all(&reply(&event)?).await?;
+                            if done {
+                                break;
+                            }
+                        }
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Listen for sessions until interrupted
+    pub async fn serve() -> Result<()> {
+        let path = socket_path()?;
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                return Err(anyhow::anyhow!(
+                    "A daemon is already listening on {}",
+                    path.display()
+                ));
+            }
+            // Left behind by a daemon that did not shut down cleanly
+            std::fs::remove_file(&path)?;
+        }
+        let listener = tokio::net::UnixListener::bind(&path)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+        let daemon = Arc::new(Daemon::default());
+        let result = loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, _) = match accepted {
+                        Ok(conn) => conn,
+                        Err(e) => break Err(e.into()),
+                    };
+                    let daemon = Arc::clone(&daemon);
+                    tokio::spawn(async move {
+                        if let Err(e) = handle(daemon, stream).await {
+                            tracing::warn!("Daemon connection failed: {}", e);
+                        }
+                    });
+                }
+                _ = tokio::signal::ctrl_c() => break Ok(()),
+            }
+        };
+        let _ = std::fs::remove_file(&path);
+        result
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_same_task_is_joined_and_tasks_run_in_turn() {
+            let daemon = Arc::new(Daemon::default());
+            // Hold the run lock so submitted tasks stay queued
+            let _running = daemon.run_lock.lock().await;
+
+            let task = Task::Command {
+                args: vec!["prices".to_string(), "--help".to_string()],
+            };
+            let (_first, _) = daemon.submit(task.clone());
+            let (_second, _) = daemon.submit(task.clone());
+            let (_other, _) = daemon.submit(Task::Refresh);
+
+            let status = daemon.status();
+            assert_eq!(status.len(), 2);
+            assert!(status.iter().all(|t| t.started_at.is_none()));
+            assert!(status.iter().any(|t| t.task == "prices --help"));
+
+            assert!(Task::Command {
+                args: vec!["import".to_string(), "x.csv".to_string()],
+            }
+            .validate()
+            .is_err());
+            assert!(task.validate().is_ok());
+        }
+    }
+}
@@ -0,0 +1,1735 @@
+//! Portable JSON archive of all user data.
+//!
+//! The archive refers to assets by ticker and to transactions and income
+//! events by an opaque `ref`, never by SQLite row ids, so it can be restored into a newer schema
+//! or a different backend. Market data (prices, benchmarks, FX rates) and
+//! caches are left out: they can be fetched again.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{get_decimal_value, AssetType};
+
+/// Identifies archive files produced by this tool
+pub const ARCHIVE_FORMAT: &str = "interest-archive";
+
+/// Bumped whenever the archive layout changes incompatibly, or gains data
+/// an older reader would silently drop
+///
+/// 2: portfolios, brokers and the rows placed in them, asset currencies,
+/// brokerage notes, term contracts, options, fixed income terms, cash credits,
+/// position statements, valuations, issuers and DARF payments
+pub const ARCHIVE_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Archive {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    /// Missing from version 1 archives, whose rows restore into the portfolio
+    /// being written to
+    #[serde(default)]
+    pub portfolios: Vec<ArchivedPortfolio>,
+    /// Broker names (version 2)
+    #[serde(default)]
+    pub brokers: Vec<String>,
+    pub assets: Vec<ArchivedAsset>,
+    pub transactions: Vec<ArchivedTransaction>,
+    pub corporate_actions: Vec<ArchivedCorporateAction>,
+    pub asset_renames: Vec<ArchivedRename>,
+    pub asset_exchanges: Vec<ArchivedExchange>,
+    pub income_events: Vec<ArchivedIncomeEvent>,
+    pub inconsistencies: Vec<ArchivedInconsistency>,
+    pub journal_entries: Vec<ArchivedJournalEntry>,
+    /// Missing from archives written before asset tags existed
+    #[serde(default)]
+    pub asset_tags: Vec<ArchivedTag>,
+    pub import_state: Vec<ArchivedImportState>,
+    pub loss_carryforward_snapshots: Vec<ArchivedLossSnapshot>,
+    // Sections below are missing from version 1 archives
+    #[serde(default)]
+    pub broker_notes: Vec<ArchivedBrokerNote>,
+    #[serde(default)]
+    pub term_contracts: Vec<ArchivedTermContract>,
+    #[serde(default)]
+    pub option_contracts: Vec<ArchivedOptionContract>,
+    #[serde(default)]
+    pub option_exercises: Vec<ArchivedOptionExercise>,
+    #[serde(default)]
+    pub fixed_income_terms: Vec<ArchivedFixedIncomeTerms>,
+    #[serde(default)]
+    pub cash_credits: Vec<ArchivedCashCredit>,
+    #[serde(default)]
+    pub position_statements: Vec<ArchivedPositionStatement>,
+    #[serde(default)]
+    pub asset_valuations: Vec<ArchivedValuation>,
+    #[serde(default)]
+    pub asset_issuers: Vec<ArchivedIssuer>,
+    #[serde(default)]
+    pub tax_payments: Vec<ArchivedTaxPayment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedPortfolio {
+    pub name: String,
+    pub description: Option<String>,
+    pub declarant: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedAsset {
+    pub ticker: String,
+    pub asset_type: String,
+    pub name: Option<String>,
+    pub cnpj: Option<String>,
+    /// Quote currency when not BRL (version 2)
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTransaction {
+    /// Archive-local reference used by inconsistencies and journal links
+    #[serde(rename = "ref")]
+    pub reference: i64,
+    pub ticker: String,
+    pub transaction_type: String,
+    pub trade_date: NaiveDate,
+    pub settlement_date: Option<NaiveDate>,
+    pub quantity: Decimal,
+    pub price_per_unit: Decimal,
+    pub total_cost: Decimal,
+    pub fees: Decimal,
+    pub is_day_trade: bool,
+    pub quota_issuance_date: Option<NaiveDate>,
+    pub notes: Option<String>,
+    pub source: Option<String>,
+    /// Portfolio name (version 2)
+    #[serde(default)]
+    pub portfolio: Option<String>,
+    /// Broker name (version 2)
+    #[serde(default)]
+    pub broker: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedCorporateAction {
+    pub ticker: String,
+    pub action_type: String,
+    pub event_date: NaiveDate,
+    pub ex_date: NaiveDate,
+    pub quantity_adjustment: Decimal,
+    pub source: Option<String>,
+    pub notes: Option<String>,
+    /// Missing from archives written before corporate action review existed
+    #[serde(default)]
+    pub applied_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedRename {
+    pub from: String,
+    pub to: String,
+    pub effective_date: NaiveDate,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedExchange {
+    pub event_type: String,
+    pub from: String,
+    pub to: String,
+    pub effective_date: NaiveDate,
+    pub to_quantity: Decimal,
+    pub allocated_cost: Decimal,
+    pub cash_amount: Decimal,
+    pub source: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedIncomeEvent {
+    /// Archive-local reference used by cash credits (version 2)
+    #[serde(rename = "ref", default)]
+    pub reference: Option<i64>,
+    pub ticker: String,
+    pub event_date: NaiveDate,
+    pub ex_date: Option<NaiveDate>,
+    pub event_type: String,
+    pub amount_per_quota: Decimal,
+    pub total_amount: Decimal,
+    pub withholding_tax: Decimal,
+    #[serde(default)]
+    pub foreign_tax_withheld: Option<Decimal>,
+    pub is_quota_pre_2026: Option<bool>,
+    pub source: Option<String>,
+    pub notes: Option<String>,
+    /// Portfolio name (version 2)
+    #[serde(default)]
+    pub portfolio: Option<String>,
+    /// Broker name (version 2)
+    #[serde(default)]
+    pub broker: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedInconsistency {
+    pub issue_type: String,
+    pub status: String,
+    pub severity: String,
+    pub ticker: Option<String>,
+    pub transaction_ref: Option<i64>,
+    pub trade_date: Option<NaiveDate>,
+    pub quantity: Option<Decimal>,
+    pub source: Option<String>,
+    pub source_ref: Option<String>,
+    pub missing_fields: Option<serde_json::Value>,
+    pub context: Option<serde_json::Value>,
+    pub resolution_action: Option<String>,
+    pub resolution: Option<serde_json::Value>,
+    pub resolved_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedJournalEntry {
+    pub ticker: String,
+    pub entry_date: NaiveDate,
+    pub thesis: String,
+    pub target_price: Option<Decimal>,
+    pub transaction_refs: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTag {
+    pub ticker: String,
+    pub tag: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedImportState {
+    pub source: String,
+    pub entry_type: String,
+    pub last_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedLossSnapshot {
+    pub year: i32,
+    pub tax_category: String,
+    pub amount: Decimal,
+    pub fingerprint: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedBrokerNote {
+    pub broker: Option<String>,
+    pub note_number: String,
+    pub trade_date: NaiveDate,
+    pub settlement_date: Option<NaiveDate>,
+    pub settlement_fee: Decimal,
+    pub registration_fee: Decimal,
+    pub emolumentos: Decimal,
+    pub brokerage: Decimal,
+    pub iss: Decimal,
+    pub other_fees: Decimal,
+    pub irrf: Decimal,
+    pub irrf_day_trade: Decimal,
+    pub net_amount: Option<Decimal>,
+    pub portfolio: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTermContract {
+    pub transaction_ref: i64,
+    pub expiry_date: Option<NaiveDate>,
+    pub contracted_rate: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedOptionContract {
+    pub ticker: String,
+    pub underlying: Option<String>,
+    pub strike: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedOptionExercise {
+    pub transaction_ref: i64,
+    pub option: String,
+    pub closing_transaction_ref: Option<i64>,
+    pub quantity: Decimal,
+    pub premium: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedFixedIncomeTerms {
+    pub ticker: String,
+    pub kind: String,
+    pub issue_date: NaiveDate,
+    pub maturity_date: NaiveDate,
+    pub indexer: String,
+    pub rate: Decimal,
+    pub issue_price: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedCashCredit {
+    pub ticker: String,
+    pub credit_date: NaiveDate,
+    pub movement_type: String,
+    pub event_type: String,
+    pub amount: Decimal,
+    pub source: String,
+    pub income_event_ref: Option<i64>,
+    pub broker: Option<String>,
+    pub portfolio: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedPositionStatement {
+    pub ticker: String,
+    pub statement_date: NaiveDate,
+    pub quantity: Decimal,
+    pub source: String,
+    pub portfolio: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedValuation {
+    pub ticker: String,
+    pub valuation_date: NaiveDate,
+    pub value: Decimal,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedIssuer {
+    pub ticker: String,
+    pub cnpj: String,
+    pub legal_name: String,
+    pub trade_name: Option<String>,
+    pub situation: Option<String>,
+    pub situation_date: Option<NaiveDate>,
+    pub source: String,
+    pub fetched_at: Option<String>,
+}
+
+/// A month's DARF payment with the ledger row it was recorded on; the row is
+/// recomputed by the next tax report, the payment kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTaxPayment {
+    pub year: i32,
+    pub month: u32,
+    pub tax_category: String,
+    pub sales: Decimal,
+    pub profit_loss: Decimal,
+    pub loss_offset: Decimal,
+    pub exemption: Decimal,
+    pub tax_due: Decimal,
+    pub paid_on: NaiveDate,
+    pub paid_amount: Option<Decimal>,
+}
+
+/// Number of records per section, for reporting
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ArchiveCounts {
+    pub portfolios: usize,
+    pub brokers: usize,
+    pub assets: usize,
+    pub transactions: usize,
+    pub corporate_actions: usize,
+    pub asset_renames: usize,
+    pub asset_exchanges: usize,
+    pub income_events: usize,
+    pub inconsistencies: usize,
+    pub journal_entries: usize,
+    pub asset_tags: usize,
+    pub import_state: usize,
+    pub loss_carryforward_snapshots: usize,
+    pub broker_notes: usize,
+    pub term_contracts: usize,
+    pub option_contracts: usize,
+    pub option_exercises: usize,
+    pub fixed_income_terms: usize,
+    pub cash_credits: usize,
+    pub position_statements: usize,
+    pub asset_valuations: usize,
+    pub asset_issuers: usize,
+    pub tax_payments: usize,
+}
+
+impl Archive {
+    pub fn counts(&self) -> ArchiveCounts {
+        ArchiveCounts {
+            portfolios: self.portfolios.len(),
+            brokers: self.brokers.len(),
+            assets: self.assets.len(),
+            transactions: self.transactions.len(),
+            corporate_actions: self.corporate_actions.len(),
+            asset_renames: self.asset_renames.len(),
+            asset_exchanges: self.asset_exchanges.len(),
+            income_events: self.income_events.len(),
+            inconsistencies: self.inconsistencies.len(),
+            journal_entries: self.journal_entries.len(),
+            asset_tags: self.asset_tags.len(),
+            import_state: self.import_state.len(),
+            loss_carryforward_snapshots: self.loss_carryforward_snapshots.len(),
+            broker_notes: self.broker_notes.len(),
+            term_contracts: self.term_contracts.len(),
+            option_contracts: self.option_contracts.len(),
+            option_exercises: self.option_exercises.len(),
+            fixed_income_terms: self.fixed_income_terms.len(),
+            cash_credits: self.cash_credits.len(),
+            position_statements: self.position_statements.len(),
+            asset_valuations: self.asset_valuations.len(),
+            asset_issuers: self.asset_issuers.len(),
+            tax_payments: self.tax_payments.len(),
+        }
+    }
+}
+
+fn optional_decimal(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<Option<Decimal>> {
+    match row.get_ref(idx)? {
+        rusqlite::types::ValueRef::Null => Ok(None),
+        _ => get_decimal_value(row, idx).map(Some),
+    }
+}
+
+fn optional_json(raw: Option<String>) -> Option<serde_json::Value> {
+    raw.map(|s| serde_json::from_str(&s).unwrap_or(serde_json::Value::String(s)))
+}
+
+fn json_text(value: &Option<serde_json::Value>) -> Option<String> {
+    value.as_ref().map(|v| match v {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    })
+}
+
+/// Read all user data into an archive
+pub fn export_archive(conn: &Connection) -> Result<Archive> {
+    let portfolios = conn
+        .prepare("SELECT name, description, declarant FROM portfolios ORDER BY id")?
+        .query_map([], |row| {
+            Ok(ArchivedPortfolio {
+                name: row.get(0)?,
+                description: row.get(1)?,
+                declarant: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let brokers = conn
+        .prepare("SELECT name FROM brokers ORDER BY id")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let assets = conn
+        .prepare("SELECT ticker, asset_type, name, cnpj, currency FROM assets ORDER BY ticker")?
+        .query_map([], |row| {
+            Ok(ArchivedAsset {
+                ticker: row.get(0)?,
+                asset_type: row.get(1)?,
+                name: row.get(2)?,
+                cnpj: row.get(3)?,
+                currency: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let transactions = conn
+        .prepare(
+            "SELECT t.id, a.ticker, t.transaction_type, t.trade_date, t.settlement_date,
+                    t.quantity, t.price_per_unit, t.total_cost, t.fees, t.is_day_trade,
+                    t.quota_issuance_date, t.notes, t.source, p.name, b.name
+             FROM transactions t
+             JOIN assets a ON t.asset_id = a.id
+             LEFT JOIN portfolios p ON t.portfolio_id = p.id
+             LEFT JOIN brokers b ON t.broker_id = b.id
+             ORDER BY t.trade_date ASC, t.id ASC",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedTransaction {
+                reference: row.get(0)?,
+                ticker: row.get(1)?,
+                transaction_type: row.get(2)?,
+                trade_date: row.get(3)?,
+                settlement_date: row.get(4)?,
+                quantity: get_decimal_value(row, 5)?,
+                price_per_unit: get_decimal_value(row, 6)?,
+                total_cost: get_decimal_value(row, 7)?,
+                fees: optional_decimal(row, 8)?.unwrap_or(Decimal::ZERO),
+                is_day_trade: row.get::<_, Option<bool>>(9)?.unwrap_or(false),
+                quota_issuance_date: row.get(10)?,
+                notes: row.get(11)?,
+                source: row.get(12)?,
+                portfolio: row.get(13)?,
+                broker: row.get(14)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let corporate_actions = conn
+        .prepare(
+            "SELECT a.ticker, c.action_type, c.event_date, c.ex_date, c.quantity_adjustment,
+                    c.source, c.notes, c.applied_at
+             FROM corporate_actions c
+             JOIN assets a ON c.asset_id = a.id
+             ORDER BY c.ex_date ASC, c.id ASC",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedCorporateAction {
+                ticker: row.get(0)?,
+                action_type: row.get(1)?,
+                event_date: row.get(2)?,
+                ex_date: row.get(3)?,
+                quantity_adjustment: get_decimal_value(row, 4)?,
+                source: row.get(5)?,
+                notes: row.get(6)?,
+                applied_at: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let asset_renames = conn
+        .prepare(
+            "SELECT af.ticker, at.ticker, r.effective_date, r.notes
+             FROM asset_renames r
+             JOIN assets af ON r.from_asset_id = af.id
+             JOIN assets at ON r.to_asset_id = at.id
+             ORDER BY r.effective_date ASC, r.id ASC",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedRename {
+                from: row.get(0)?,
+                to: row.get(1)?,
+                effective_date: row.get(2)?,
+                notes: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let asset_exchanges = conn
+        .prepare(
+            "SELECT e.event_type, af.ticker, at.ticker, e.effective_date, e.to_quantity,
+                    e.allocated_cost, e.cash_amount, e.source, e.notes
+             FROM asset_exchanges e
+             JOIN assets af ON e.from_asset_id = af.id
+             JOIN assets at ON e.to_asset_id = at.id
+             ORDER BY e.effective_date ASC, e.id ASC",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedExchange {
+                event_type: row.get(0)?,
+                from: row.get(1)?,
+                to: row.get(2)?,
+                effective_date: row.get(3)?,
+                to_quantity: get_decimal_value(row, 4)?,
+                allocated_cost: get_decimal_value(row, 5)?,
+                cash_amount: get_decimal_value(row, 6)?,
+                source: row.get(7)?,
+                notes: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let income_events = conn
+        .prepare(
+            "SELECT a.ticker, i.event_date, i.ex_date, i.event_type, i.amount_per_quota,
+                    i.total_amount, i.withholding_tax, i.is_quota_pre_2026, i.source, i.notes,
+                    i.foreign_tax_withheld, p.name, b.name, i.id
+             FROM income_events i
+             JOIN assets a ON i.asset_id = a.id
+             LEFT JOIN portfolios p ON i.portfolio_id = p.id
+             LEFT JOIN brokers b ON i.broker_id = b.id
+             ORDER BY i.event_date ASC, i.id ASC",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedIncomeEvent {
+                reference: row.get(13)?,
+                ticker: row.get(0)?,
+                event_date: row.get(1)?,
+                ex_date: row.get(2)?,
+                event_type: row.get(3)?,
+                amount_per_quota: get_decimal_value(row, 4)?,
+                total_amount: get_decimal_value(row, 5)?,
+                withholding_tax: optional_decimal(row, 6)?.unwrap_or(Decimal::ZERO),
+                foreign_tax_withheld: optional_decimal(row, 10)?,
+                is_quota_pre_2026: row.get(7)?,
+                source: row.get(8)?,
+                notes: row.get(9)?,
+                portfolio: row.get(11)?,
+                broker: row.get(12)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let inconsistencies = conn
+        .prepare(
+            "SELECT i.issue_type, i.status, i.severity, COALESCE(a.ticker, i.ticker),
+                    i.transaction_id, i.trade_date, i.quantity, i.source, i.source_ref,
+                    i.missing_fields_json, i.context_json, i.resolution_action,
+                    i.resolution_json, i.resolved_at
+             FROM inconsistencies i
+             LEFT JOIN assets a ON i.asset_id = a.id
+             ORDER BY i.id ASC",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedInconsistency {
+                issue_type: row.get(0)?,
+                status: row.get(1)?,
+                severity: row.get(2)?,
+                ticker: row.get(3)?,
+                transaction_ref: row.get(4)?,
+                trade_date: row.get(5)?,
+                quantity: optional_decimal(row, 6)?,
+                source: row.get(7)?,
+                source_ref: row.get(8)?,
+                missing_fields: optional_json(row.get(9)?),
+                context: optional_json(row.get(10)?),
+                resolution_action: row.get(11)?,
+                resolution: optional_json(row.get(12)?),
+                resolved_at: row.get(13)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut journal_entries = Vec::new();
+    for (entry, ticker) in super::list_journal_entries(conn, None)? {
+        let transaction_refs = super::get_journal_transactions(conn, entry.id.unwrap_or(0))?
+            .into_iter()
+            .filter_map(|tx| tx.id)
+            .collect();
+        journal_entries.push(ArchivedJournalEntry {
+            ticker,
+            entry_date: entry.entry_date,
+            thesis: entry.thesis,
+            target_price: entry.target_price,
+            transaction_refs,
+        });
+    }
+
+    let asset_tags = super::list_asset_tags(conn)?
+        .into_iter()
+        .flat_map(|(tag, tickers)| {
+            tickers.into_iter().map(move |ticker| ArchivedTag {
+                ticker,
+                tag: tag.clone(),
+            })
+        })
+        .collect();
+
+    let import_state = conn
+        .prepare(
+            "SELECT source, entry_type, last_date FROM import_state ORDER BY source, entry_type",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedImportState {
+                source: row.get(0)?,
+                entry_type: row.get(1)?,
+                last_date: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let loss_carryforward_snapshots = conn
+        .prepare(
+            "SELECT year, tax_category, ending_remaining_amount, tx_fingerprint
+             FROM loss_carryforward_snapshots
+             ORDER BY year, tax_category",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedLossSnapshot {
+                year: row.get(0)?,
+                tax_category: row.get(1)?,
+                amount: get_decimal_value(row, 2)?,
+                fingerprint: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let broker_notes = conn
+        .prepare(
+            "SELECT b.name, n.note_number, n.trade_date, n.settlement_date, n.settlement_fee,
+                    n.registration_fee, n.emolumentos, n.brokerage, n.iss, n.other_fees, n.irrf,
+                    n.irrf_day_trade, n.net_amount, p.name
+             FROM broker_notes n
+             LEFT JOIN brokers b ON n.broker_id = b.id
+             LEFT JOIN portfolios p ON n.portfolio_id = p.id
+             ORDER BY n.trade_date ASC, n.id ASC",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedBrokerNote {
+                broker: row.get(0)?,
+                note_number: row.get(1)?,
+                trade_date: row.get(2)?,
+                settlement_date: row.get(3)?,
+                settlement_fee: get_decimal_value(row, 4)?,
+                registration_fee: get_decimal_value(row, 5)?,
+                emolumentos: get_decimal_value(row, 6)?,
+                brokerage: get_decimal_value(row, 7)?,
+                iss: get_decimal_value(row, 8)?,
+                other_fees: get_decimal_value(row, 9)?,
+                irrf: get_decimal_value(row, 10)?,
+                irrf_day_trade: get_decimal_value(row, 11)?,
+                net_amount: optional_decimal(row, 12)?,
+                portfolio: row.get(13)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let term_contracts = conn
+        .prepare(
+            "SELECT transaction_id, expiry_date, contracted_rate
+             FROM term_contract_details ORDER BY transaction_id",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedTermContract {
+                transaction_ref: row.get(0)?,
+                expiry_date: row.get(1)?,
+                contracted_rate: optional_decimal(row, 2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let option_contracts = conn
+        .prepare(
+            "SELECT a.ticker, o.underlying, o.strike
+             FROM option_contracts o
+             JOIN assets a ON o.asset_id = a.id
+             ORDER BY a.ticker",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedOptionContract {
+                ticker: row.get(0)?,
+                underlying: row.get(1)?,
+                strike: optional_decimal(row, 2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let option_exercises = conn
+        .prepare(
+            "SELECT e.transaction_id, a.ticker, e.closing_transaction_id, e.quantity, e.premium
+             FROM option_exercises e
+             JOIN assets a ON e.option_asset_id = a.id
+             ORDER BY e.transaction_id",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedOptionExercise {
+                transaction_ref: row.get(0)?,
+                option: row.get(1)?,
+                closing_transaction_ref: row.get(2)?,
+                quantity: get_decimal_value(row, 3)?,
+                premium: get_decimal_value(row, 4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let fixed_income_terms = conn
+        .prepare(
+            "SELECT a.ticker, f.kind, f.issue_date, f.maturity_date, f.indexer, f.rate,
+                    f.issue_price
+             FROM fixed_income_terms f
+             JOIN assets a ON f.asset_id = a.id
+             ORDER BY a.ticker",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedFixedIncomeTerms {
+                ticker: row.get(0)?,
+                kind: row.get(1)?,
+                issue_date: row.get(2)?,
+                maturity_date: row.get(3)?,
+                indexer: row.get(4)?,
+                rate: get_decimal_value(row, 5)?,
+                issue_price: get_decimal_value(row, 6)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let cash_credits = conn
+        .prepare(
+            "SELECT a.ticker, c.credit_date, c.movement_type, c.event_type, c.amount, c.source,
+                    c.income_event_id, b.name, p.name
+             FROM cash_credits c
+             JOIN assets a ON c.asset_id = a.id
+             LEFT JOIN brokers b ON c.broker_id = b.id
+             LEFT JOIN portfolios p ON c.portfolio_id = p.id
+             ORDER BY c.credit_date ASC, c.id ASC",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedCashCredit {
+                ticker: row.get(0)?,
+                credit_date: row.get(1)?,
+                movement_type: row.get(2)?,
+                event_type: row.get(3)?,
+                amount: get_decimal_value(row, 4)?,
+                source: row.get(5)?,
+                income_event_ref: row.get(6)?,
+                broker: row.get(7)?,
+                portfolio: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let position_statements = conn
+        .prepare(
+            "SELECT a.ticker, s.statement_date, s.quantity, s.source, p.name
+             FROM position_statements s
+             JOIN assets a ON s.asset_id = a.id
+             LEFT JOIN portfolios p ON s.portfolio_id = p.id
+             ORDER BY s.statement_date ASC, s.id ASC",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedPositionStatement {
+                ticker: row.get(0)?,
+                statement_date: row.get(1)?,
+                quantity: get_decimal_value(row, 2)?,
+                source: row.get(3)?,
+                portfolio: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let asset_valuations = conn
+        .prepare(
+            "SELECT a.ticker, v.valuation_date, v.value, v.notes
+             FROM asset_valuations v
+             JOIN assets a ON v.asset_id = a.id
+             ORDER BY a.ticker, v.valuation_date",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedValuation {
+                ticker: row.get(0)?,
+                valuation_date: row.get(1)?,
+                value: get_decimal_value(row, 2)?,
+                notes: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let asset_issuers = conn
+        .prepare(
+            "SELECT a.ticker, i.cnpj, i.legal_name, i.trade_name, i.situation,
+                    i.situation_date, i.source, i.fetched_at
+             FROM asset_issuers i
+             JOIN assets a ON i.asset_id = a.id
+             ORDER BY a.ticker",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedIssuer {
+                ticker: row.get(0)?,
+                cnpj: row.get(1)?,
+                legal_name: row.get(2)?,
+                trade_name: row.get(3)?,
+                situation: row.get(4)?,
+                situation_date: row.get(5)?,
+                source: row.get(6)?,
+                fetched_at: row.get(7)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let tax_payments = conn
+        .prepare(
+            "SELECT year, month, tax_category, sales, profit_loss, loss_offset, exemption,
+                    tax_due, paid_on, paid_amount
+             FROM tax_ledger
+             WHERE paid_on IS NOT NULL
+             ORDER BY year, month, tax_category",
+        )?
+        .query_map([], |row| {
+            Ok(ArchivedTaxPayment {
+                year: row.get(0)?,
+                month: row.get(1)?,
+                tax_category: row.get(2)?,
+                sales: get_decimal_value(row, 3)?,
+                profit_loss: get_decimal_value(row, 4)?,
+                loss_offset: get_decimal_value(row, 5)?,
+                exemption: get_decimal_value(row, 6)?,
+                tax_due: get_decimal_value(row, 7)?,
+                paid_on: row.get(8)?,
+                paid_amount: optional_decimal(row, 9)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Archive {
+        format: ARCHIVE_FORMAT.to_string(),
+        version: ARCHIVE_VERSION,
+        exported_at: chrono::Utc::now(),
+        portfolios,
+        brokers,
+        assets,
+        transactions,
+        corporate_actions,
+        asset_renames,
+        asset_exchanges,
+        income_events,
+        inconsistencies,
+        journal_entries,
+        asset_tags,
+        import_state,
+        loss_carryforward_snapshots,
+        broker_notes,
+        term_contracts,
+        option_contracts,
+        option_exercises,
+        fixed_income_terms,
+        cash_credits,
+        position_statements,
+        asset_valuations,
+        asset_issuers,
+        tax_payments,
+    })
+}
+
+/// Check the archive header before touching the database
+pub fn validate_archive(archive: &Archive) -> Result<()> {
+    if archive.format != ARCHIVE_FORMAT {
+        anyhow::bail!("Not an interest archive (format '{}')", archive.format);
+    }
+    if archive.version > ARCHIVE_VERSION {
+        anyhow::bail!(
+            "Archive version {} is newer than supported version {}; upgrade interest first",
+            archive.version,
+            ARCHIVE_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// Restore an archive into an empty database, in a single transaction
+pub fn import_archive(conn: &Connection, archive: &Archive) -> Result<ArchiveCounts> {
+    validate_archive(archive)?;
+
+    let existing: i64 = conn.query_row("SELECT COUNT(*) FROM assets", [], |row| row.get(0))?;
+    if existing > 0 {
+        anyhow::bail!(
+            "Database already has {} assets; import-json only restores into an empty database",
+            existing
+        );
+    }
+
+    super::bulk::in_transaction(conn, |conn| {
+        let mut portfolio_ids: HashMap<String, i64> = HashMap::new();
+        for portfolio in &archive.portfolios {
+            let id = match super::portfolio::get_portfolio_by_name(conn, &portfolio.name)? {
+                Some(existing) => {
+                    conn.execute(
+                        "UPDATE portfolios SET description = ?2, declarant = ?3 WHERE id = ?1",
+                        params![existing.id, portfolio.description, portfolio.declarant],
+                    )?;
+                    existing.id
+                }
+                None => super::portfolio::create_portfolio(
+                    conn,
+                    &portfolio.name,
+                    portfolio.description.as_deref(),
+                    portfolio.declarant.as_deref(),
+                )?,
+            };
+            portfolio_ids.insert(portfolio.name.clone(), id);
+        }
+        let portfolio_id = |name: &Option<String>| -> Result<i64> {
+            match name {
+                None => Ok(super::portfolio::write_target()),
+                Some(name) => portfolio_ids
+                    .get(name)
+                    .copied()
+                    .with_context(|| format!("Archive references unknown portfolio {}", name)),
+            }
+        };
+
+        let mut broker_ids: HashMap<String, i64> = HashMap::new();
+        for name in &archive.brokers {
+            broker_ids.insert(name.clone(), super::upsert_broker(conn, name)?);
+        }
+        // Rows may name a broker missing from the list in hand-edited archives
+        let mut broker_id = |name: &Option<String>| -> Result<Option<i64>> {
+            let Some(name) = name else {
+                return Ok(None);
+            };
+            if let Some(id) = broker_ids.get(name) {
+                return Ok(Some(*id));
+            }
+            let id = super::upsert_broker(conn, name)?;
+            broker_ids.insert(name.clone(), id);
+            Ok(Some(id))
+        };
+
+        let mut asset_ids: HashMap<String, i64> = HashMap::new();
+        for asset in &archive.assets {
+            let asset_type = asset
+                .asset_type
+                .parse::<AssetType>()
+                .unwrap_or(AssetType::Unknown);
+            conn.execute(
+                "INSERT INTO assets (ticker, asset_type, name, cnpj, currency)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    asset.ticker,
+                    asset_type.as_str(),
+                    asset.name,
+                    asset.cnpj,
+                    asset.currency
+                ],
+            )?;
+            asset_ids.insert(asset.ticker.clone(), conn.last_insert_rowid());
+        }
+        let asset_id = |ticker: &str| -> Result<i64> {
+            asset_ids
+                .get(ticker)
+                .copied()
+                .with_context(|| format!("Archive references unknown asset {}", ticker))
+        };
+
+        let mut tx_ids: HashMap<i64, i64> = HashMap::new();
+        {
+            let mut stmt = conn.prepare_cached(super::INSERT_TRANSACTION_SQL)?;
+            for tx in &archive.transactions {
+                stmt.execute(params![
+                    asset_id(&tx.ticker)?,
+                    tx.transaction_type,
+                    tx.trade_date,
+                    tx.settlement_date,
+                    tx.quantity.to_string(),
+                    tx.price_per_unit.to_string(),
+                    tx.total_cost.to_string(),
+                    tx.fees.to_string(),
+                    tx.is_day_trade,
+                    tx.quota_issuance_date,
+                    tx.notes,
+                    tx.source,
+                    portfolio_id(&tx.portfolio)?,
+                    None::<i64>,
+                ])?;
+                let id = conn.last_insert_rowid();
+                if let Some(broker) = broker_id(&tx.broker)? {
+                    super::set_transaction_broker(conn, id, broker)?;
+                }
+                tx_ids.insert(tx.reference, id);
+            }
+        }
+        let tx_id = |reference: i64| -> Result<i64> {
+            tx_ids
+                .get(&reference)
+                .copied()
+                .with_context(|| format!("Archive references unknown transaction {}", reference))
+        };
+
+        for action in &archive.corporate_actions {
+            conn.execute(
+                "INSERT INTO corporate_actions
+                 (asset_id, action_type, event_date, ex_date, quantity_adjustment, source, notes,
+                  applied_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    asset_id(&action.ticker)?,
+                    action.action_type,
+                    action.event_date,
+                    action.ex_date,
+                    action.quantity_adjustment.to_string(),
+                    action.source,
+                    action.notes,
+                    action.applied_at,
+                ],
+            )?;
+        }
+
+        for rename in &archive.asset_renames {
+            conn.execute(
+                "INSERT INTO asset_renames (from_asset_id, to_asset_id, effective_date, notes)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    asset_id(&rename.from)?,
+                    asset_id(&rename.to)?,
+                    rename.effective_date,
+                    rename.notes,
+                ],
+            )?;
+        }
+
+        for exchange in &archive.asset_exchanges {
+            conn.execute(
+                "INSERT INTO asset_exchanges (
+                    event_type, from_asset_id, to_asset_id, effective_date,
+                    to_quantity, allocated_cost, cash_amount, source, notes
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    exchange.event_type,
+                    asset_id(&exchange.from)?,
+                    asset_id(&exchange.to)?,
+                    exchange.effective_date,
+                    exchange.to_quantity.to_string(),
+                    exchange.allocated_cost.to_string(),
+                    exchange.cash_amount.to_string(),
+                    exchange.source,
+                    exchange.notes,
+                ],
+            )?;
+        }
+
+        let mut income_ids: HashMap<i64, i64> = HashMap::new();
+        for event in &archive.income_events {
+            conn.execute(
+                "INSERT INTO income_events (
+                    asset_id, event_date, ex_date, event_type, amount_per_quota,
+                    total_amount, withholding_tax, is_quota_pre_2026, source, notes, portfolio_id,
+                    foreign_tax_withheld
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    asset_id(&event.ticker)?,
+                    event.event_date,
+                    event.ex_date,
+                    event.event_type,
+                    event.amount_per_quota.to_string(),
+                    event.total_amount.to_string(),
+                    event.withholding_tax.to_string(),
+                    event.is_quota_pre_2026,
+                    event.source,
+                    event.notes,
+                    portfolio_id(&event.portfolio)?,
+                    event.foreign_tax_withheld.map(|v| v.to_string()),
+                ],
+            )?;
+            let id = conn.last_insert_rowid();
+            if let Some(broker) = broker_id(&event.broker)? {
+                super::set_income_event_broker(conn, id, broker)?;
+            }
+            if let Some(reference) = event.reference {
+                income_ids.insert(reference, id);
+            }
+        }
+
+        for issue in &archive.inconsistencies {
+            let issue_asset = issue
+                .ticker
+                .as_deref()
+                .and_then(|t| asset_ids.get(t).copied());
+            let issue_tx = issue
+                .transaction_ref
+                .and_then(|reference| tx_ids.get(&reference).copied());
+            conn.execute(
+                "INSERT INTO inconsistencies (
+                    issue_type, status, severity, asset_id, transaction_id, ticker,
+                    trade_date, quantity, source, source_ref, missing_fields_json,
+                    context_json, resolution_action, resolution_json, resolved_at
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                params![
+                    issue.issue_type,
+                    issue.status,
+                    issue.severity,
+                    issue_asset,
+                    issue_tx,
+                    issue.ticker,
+                    issue.trade_date,
+                    issue.quantity.map(|q| q.to_string()),
+                    issue.source,
+                    issue.source_ref,
+                    json_text(&issue.missing_fields),
+                    json_text(&issue.context),
+                    issue.resolution_action,
+                    json_text(&issue.resolution),
+                    issue.resolved_at,
+                ],
+            )?;
+        }
+
+        for entry in &archive.journal_entries {
+            conn.execute(
+                "INSERT INTO journal_entries (asset_id, entry_date, thesis, target_price)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    asset_id(&entry.ticker)?,
+                    entry.entry_date,
+                    entry.thesis,
+                    entry.target_price.map(|p| p.to_string()),
+                ],
+            )?;
+            let entry_id = conn.last_insert_rowid();
+            for reference in &entry.transaction_refs {
+                super::link_journal_transaction(conn, entry_id, tx_id(*reference)?)?;
+            }
+        }
+
+        for tag in &archive.asset_tags {
+            super::add_asset_tag(conn, asset_id(&tag.ticker)?, &tag.tag)?;
+        }
+
+        for state in &archive.import_state {
+            super::set_last_import_date(conn, &state.source, &state.entry_type, state.last_date)?;
+        }
+
+        for snapshot in &archive.loss_carryforward_snapshots {
+            conn.execute(
+                "INSERT OR REPLACE INTO loss_carryforward_snapshots
+                 (year, tax_category, ending_remaining_amount, tx_fingerprint)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    snapshot.year,
+                    snapshot.tax_category,
+                    snapshot.amount.to_string(),
+                    snapshot.fingerprint,
+                ],
+            )?;
+        }
+
+        for note in &archive.broker_notes {
+            conn.execute(
+                "INSERT INTO broker_notes (
+                    broker_id, note_number, trade_date, settlement_date, settlement_fee,
+                    registration_fee, emolumentos, brokerage, iss, other_fees, irrf,
+                    irrf_day_trade, net_amount, portfolio_id
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![
+                    broker_id(&note.broker)?,
+                    note.note_number,
+                    note.trade_date,
+                    note.settlement_date,
+                    note.settlement_fee.to_string(),
+                    note.registration_fee.to_string(),
+                    note.emolumentos.to_string(),
+                    note.brokerage.to_string(),
+                    note.iss.to_string(),
+                    note.other_fees.to_string(),
+                    note.irrf.to_string(),
+                    note.irrf_day_trade.to_string(),
+                    note.net_amount.map(|d| d.to_string()),
+                    portfolio_id(&note.portfolio)?,
+                ],
+            )?;
+        }
+
+        for term in &archive.term_contracts {
+            conn.execute(
+                "INSERT INTO term_contract_details (transaction_id, expiry_date, contracted_rate)
+                 VALUES (?1, ?2, ?3)",
+                params![
+                    tx_id(term.transaction_ref)?,
+                    term.expiry_date,
+                    term.contracted_rate.map(|r| r.to_string()),
+                ],
+            )?;
+        }
+
+        for option in &archive.option_contracts {
+            conn.execute(
+                "INSERT INTO option_contracts (asset_id, underlying, strike) VALUES (?1, ?2, ?3)",
+                params![
+                    asset_id(&option.ticker)?,
+                    option.underlying,
+                    option.strike.map(|s| s.to_string()),
+                ],
+            )?;
+        }
+
+        for exercise in &archive.option_exercises {
+            conn.execute(
+                "INSERT INTO option_exercises
+                 (transaction_id, option_asset_id, closing_transaction_id, quantity, premium)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    tx_id(exercise.transaction_ref)?,
+                    asset_id(&exercise.option)?,
+                    exercise.closing_transaction_ref.map(tx_id).transpose()?,
+                    exercise.quantity.to_string(),
+                    exercise.premium.to_string(),
+                ],
+            )?;
+        }
+
+        for terms in &archive.fixed_income_terms {
+            conn.execute(
+                "INSERT INTO fixed_income_terms
+                 (asset_id, kind, issue_date, maturity_date, indexer, rate, issue_price)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    asset_id(&terms.ticker)?,
+                    terms.kind,
+                    terms.issue_date,
+                    terms.maturity_date,
+                    terms.indexer,
+                    terms.rate.to_string(),
+                    terms.issue_price.to_string(),
+                ],
+            )?;
+        }
+
+        for credit in &archive.cash_credits {
+            // A credit whose event is missing from the archive waits to be matched again
+            let event = credit
+                .income_event_ref
+                .and_then(|reference| income_ids.get(&reference).copied());
+            conn.execute(
+                "INSERT INTO cash_credits (
+                    asset_id, credit_date, movement_type, event_type, amount, source,
+                    income_event_id, broker_id, portfolio_id
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    asset_id(&credit.ticker)?,
+                    credit.credit_date,
+                    credit.movement_type,
+                    credit.event_type,
+                    credit.amount.to_string(),
+                    credit.source,
+                    event,
+                    broker_id(&credit.broker)?,
+                    portfolio_id(&credit.portfolio)?,
+                ],
+            )?;
+        }
+
+        for statement in &archive.position_statements {
+            conn.execute(
+                "INSERT INTO position_statements
+                 (asset_id, statement_date, quantity, source, portfolio_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    asset_id(&statement.ticker)?,
+                    statement.statement_date,
+                    statement.quantity.to_string(),
+                    statement.source,
+                    portfolio_id(&statement.portfolio)?,
+                ],
+            )?;
+        }
+
+        for valuation in &archive.asset_valuations {
+            conn.execute(
+                "INSERT INTO asset_valuations (asset_id, valuation_date, value, notes)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    asset_id(&valuation.ticker)?,
+                    valuation.valuation_date,
+                    valuation.value.to_string(),
+                    valuation.notes,
+                ],
+            )?;
+        }
+
+        for issuer in &archive.asset_issuers {
+            conn.execute(
+                "INSERT INTO asset_issuers (
+                    asset_id, cnpj, legal_name, trade_name, situation, situation_date, source,
+                    fetched_at
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, COALESCE(?8, CURRENT_TIMESTAMP))",
+                params![
+                    asset_id(&issuer.ticker)?,
+                    issuer.cnpj,
+                    issuer.legal_name,
+                    issuer.trade_name,
+                    issuer.situation,
+                    issuer.situation_date,
+                    issuer.source,
+                    issuer.fetched_at,
+                ],
+            )?;
+        }
+
+        // An empty fingerprint marks the rows stale so the next tax report
+        // recomputes them around the payment
+        for payment in &archive.tax_payments {
+            conn.execute(
+                "INSERT INTO tax_ledger (
+                    year, month, tax_category, sales, profit_loss, loss_offset, exemption,
+                    tax_due, darf_status, paid_on, paid_amount, tx_fingerprint
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'PAID', ?9, ?10, '')",
+                params![
+                    payment.year,
+                    payment.month,
+                    payment.tax_category,
+                    payment.sales.to_string(),
+                    payment.profit_loss.to_string(),
+                    payment.loss_offset.to_string(),
+                    payment.exemption.to_string(),
+                    payment.tax_due.to_string(),
+                    payment.paid_on,
+                    payment.paid_amount.map(|a| a.to_string()),
+                ],
+            )?;
+        }
+
+        Ok(())
+    })?;
+
+    Ok(archive.counts())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn
+    }
+
+    fn seed(conn: &Connection) {
+        conn.execute_batch(
+            "INSERT INTO assets (ticker, asset_type, name) VALUES ('PETR4', 'STOCK', 'Petrobras');
+             INSERT INTO assets (ticker, asset_type) VALUES ('HGLG11', 'FII');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+                 VALUES (1, 'BUY', '2024-01-10', '100', '30.5', '3050.25', '0.25', 0, 'CEI');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+                 VALUES (2, 'BUY', '2024-02-10', '10', '160', '1600', '0', 0, 'MANUAL');
+             INSERT INTO corporate_actions (asset_id, action_type, event_date, ex_date,
+                 quantity_adjustment, source)
+                 VALUES (1, 'SPLIT', '2024-03-01', '2024-03-01', '100', 'MANUAL');
+             INSERT INTO income_events (asset_id, event_date, event_type, amount_per_quota,
+                 total_amount, withholding_tax, source)
+                 VALUES (2, '2024-03-15', 'DIVIDEND', '1.1', '11', '0', 'MANUAL');
+             INSERT INTO inconsistencies (issue_type, status, severity, asset_id,
+                 transaction_id, ticker, context_json)
+                 VALUES ('MISSING_COST_BASIS', 'OPEN', 'BLOCKING', 2, 2, 'HGLG11', '{\"row\":4}');
+             INSERT INTO journal_entries (asset_id, entry_date, thesis, target_price)
+                 VALUES (1, '2024-01-05', 'Cheap vs peers', '40');
+             INSERT INTO journal_links (entry_id, transaction_id) VALUES (1, 1);
+             INSERT INTO asset_tags (asset_id, tag) VALUES (2, 'renda');
+             INSERT INTO import_state (source, entry_type, last_date)
+                 VALUES ('CEI', 'trades', '2024-02-10');
+             INSERT INTO portfolios (name, description, declarant)
+                 VALUES ('spouse', 'Ana''s account', 'Ana');
+             UPDATE transactions SET portfolio_id = 2 WHERE id = 2;
+             UPDATE income_events SET portfolio_id = 2;
+             INSERT INTO brokers (name) VALUES ('INTER DTVM LTDA');
+             INSERT INTO brokers (name) VALUES ('XP INVESTIMENTOS CCTVM S/A');
+             UPDATE transactions SET broker_id = 2 WHERE id = 1;
+             UPDATE income_events SET broker_id = 1;
+             INSERT INTO assets (ticker, asset_type) VALUES ('PETRA123', 'OPTION');
+             INSERT INTO assets (ticker, asset_type) VALUES ('CDB_BANCOX_2027', 'BOND');
+             INSERT INTO assets (ticker, asset_type) VALUES ('PETR4T', 'TERM');
+             INSERT INTO assets (ticker, asset_type, currency) VALUES ('IVV', 'ETF', 'USD');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, source)
+                 VALUES (5, 'BUY', '2024-04-01', '100', '31', '3100', 'MANUAL'),
+                        (3, 'BUY', '2024-04-02', '100', '0.5', '50', 'MANUAL'),
+                        (3, 'SELL', '2024-05-10', '100', '0', '0', 'MANUAL'),
+                        (1, 'BUY', '2024-05-10', '100', '30.5', '3050', 'MANUAL');
+             INSERT INTO term_contract_details (transaction_id, expiry_date, contracted_rate)
+                 VALUES (3, '2024-06-28', '1.25');
+             INSERT INTO option_contracts (asset_id, underlying, strike)
+                 VALUES (3, 'PETR4', '30');
+             INSERT INTO option_exercises (transaction_id, option_asset_id,
+                 closing_transaction_id, quantity, premium)
+                 VALUES (6, 3, 5, '100', '50');
+             INSERT INTO fixed_income_terms (asset_id, kind, issue_date, maturity_date,
+                 indexer, rate, issue_price)
+                 VALUES (4, 'CDB', '2024-01-02', '2027-01-04', 'CDI', '110', '1000');
+             INSERT INTO broker_notes (broker_id, note_number, trade_date, settlement_date,
+                 settlement_fee, registration_fee, emolumentos, net_amount)
+                 VALUES (2, '12345', '2024-01-10', '2024-01-12', '0.75', '0.1', '0.08',
+                 '-3050.25');
+             INSERT INTO cash_credits (asset_id, credit_date, movement_type, event_type,
+                 amount, source, income_event_id, broker_id, portfolio_id)
+                 VALUES (2, '2024-03-15', 'Rendimento', 'DIVIDEND', '11', 'MOVIMENTACAO',
+                 1, 1, 2);
+             INSERT INTO position_statements (asset_id, statement_date, quantity, source)
+                 VALUES (1, '2024-06-30', '300', 'POSICAO');
+             INSERT INTO asset_valuations (asset_id, valuation_date, value, notes)
+                 VALUES (2, '2024-06-30', '155.5', 'Manager report');
+             INSERT INTO asset_issuers (asset_id, cnpj, legal_name, trade_name, situation,
+                 situation_date, source, fetched_at)
+                 VALUES (1, '33000167000101', 'PETROLEO BRASILEIRO S A PETROBRAS',
+                 'PETROBRAS', 'ATIVA', '2005-11-03', 'BRASILAPI', '2024-07-01 12:00:00');
+             INSERT INTO tax_ledger (year, month, tax_category, sales, profit_loss,
+                 loss_offset, exemption, tax_due, darf_status, paid_on, paid_amount,
+                 tx_fingerprint)
+                 VALUES (2024, 3, 'STOCK_SWING', '30000', '1000', '0', '0', '150', 'PAID',
+                 '2024-04-30', '150', 'abc');",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let source = setup();
+        seed(&source);
+        let archive = export_archive(&source).unwrap();
+        let json = serde_json::to_string(&archive).unwrap();
+
+        let target = setup();
+        // Offset row ids so references must be remapped rather than copied
+        target
+            .execute_batch(
+                "INSERT INTO assets (ticker, asset_type) VALUES ('TMP1', 'STOCK');
+                 INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                     price_per_unit, total_cost) VALUES (1, 'BUY', '2020-01-01', '1', '1', '1');
+                 DELETE FROM transactions;
+                 DELETE FROM assets;",
+            )
+            .unwrap();
+        let parsed: Archive = serde_json::from_str(&json).unwrap();
+        let counts = import_archive(&target, &parsed).unwrap();
+        assert_eq!(counts.transactions, 6);
+        assert_eq!(counts.journal_entries, 1);
+        assert_eq!(counts.asset_tags, 1);
+
+        let restored = export_archive(&target).unwrap();
+        assert_eq!(restored.counts(), archive.counts());
+        assert_eq!(restored.transactions[0].total_cost.to_string(), "3050.25");
+        assert_eq!(
+            restored.inconsistencies[0].context,
+            Some(serde_json::json!({"row": 4}))
+        );
+
+        // Journal links and inconsistencies follow the remapped transactions
+        let petr_tx = restored.transactions[0].reference;
+        assert_eq!(restored.journal_entries[0].transaction_refs, vec![petr_tx]);
+        let hglg_tx = restored.transactions[1].reference;
+        assert_eq!(restored.inconsistencies[0].transaction_ref, Some(hglg_tx));
+        assert_ne!(petr_tx, archive.transactions[0].reference);
+
+        // Rows go back to the portfolio they were exported from
+        assert_eq!(restored.portfolios.len(), 2);
+        assert_eq!(restored.portfolios[1].declarant.as_deref(), Some("Ana"));
+        assert_eq!(
+            restored.transactions[0].portfolio.as_deref(),
+            Some("default")
+        );
+        assert_eq!(
+            restored.transactions[1].portfolio.as_deref(),
+            Some("spouse")
+        );
+        assert_eq!(
+            restored.income_events[0].portfolio.as_deref(),
+            Some("spouse")
+        );
+
+        // So does the broker they were made at
+        assert_eq!(restored.brokers, archive.brokers);
+        assert_eq!(
+            restored.transactions[0].broker.as_deref(),
+            Some("XP INVESTIMENTOS CCTVM S/A")
+        );
+        assert_eq!(restored.transactions[1].broker, None);
+        assert_eq!(
+            restored.income_events[0].broker.as_deref(),
+            Some("INTER DTVM LTDA")
+        );
+    }
+
+    /// Rows of a table by what identifies them rather than by row id
+    fn dump(conn: &Connection, query: &str) -> Vec<Vec<rusqlite::types::Value>> {
+        let mut stmt = conn.prepare(query).unwrap();
+        let columns = stmt.column_count();
+        stmt.query_map([], |row| {
+            (0..columns)
+                .map(|i| row.get(i))
+                .collect::<rusqlite::Result<Vec<_>>>()
+        })
+        .unwrap()
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_restores_every_table() {
+        let source = setup();
+        seed(&source);
+        let json = serde_json::to_string(&export_archive(&source).unwrap()).unwrap();
+
+        let target = setup();
+        // Offset row ids so every reference must be remapped
+        target
+            .execute_batch(
+                "INSERT INTO assets (ticker, asset_type) VALUES ('TMP1', 'STOCK');
+                 INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                     price_per_unit, total_cost) VALUES (1, 'BUY', '2020-01-01', '1', '1', '1');
+                 INSERT INTO income_events (asset_id, event_date, event_type,
+                     amount_per_quota, total_amount) VALUES (1, '2020-01-01', 'DIVIDEND', '1', '1');
+                 INSERT INTO brokers (name) VALUES ('TMP');
+                 DELETE FROM income_events;
+                 DELETE FROM transactions;
+                 DELETE FROM brokers;
+                 DELETE FROM assets;",
+            )
+            .unwrap();
+        import_archive(&target, &serde_json::from_str(&json).unwrap()).unwrap();
+
+        let tables = [
+            (
+                "portfolios",
+                "SELECT name, description, declarant FROM portfolios ORDER BY name",
+            ),
+            ("brokers", "SELECT name FROM brokers ORDER BY name"),
+            (
+                "assets",
+                "SELECT ticker, asset_type, name, cnpj, currency FROM assets ORDER BY ticker",
+            ),
+            (
+                "transactions",
+                "SELECT a.ticker, t.transaction_type, t.trade_date, t.quantity, t.total_cost,
+                        t.fees, t.source, p.name, b.name
+                 FROM transactions t JOIN assets a ON t.asset_id = a.id
+                 JOIN portfolios p ON t.portfolio_id = p.id
+                 LEFT JOIN brokers b ON t.broker_id = b.id
+                 ORDER BY t.trade_date, a.ticker, t.transaction_type",
+            ),
+            (
+                "income_events",
+                "SELECT a.ticker, i.event_date, i.event_type, i.total_amount, p.name, b.name
+                 FROM income_events i JOIN assets a ON i.asset_id = a.id
+                 JOIN portfolios p ON i.portfolio_id = p.id
+                 LEFT JOIN brokers b ON i.broker_id = b.id
+                 ORDER BY i.event_date, a.ticker",
+            ),
+            (
+                "broker_notes",
+                "SELECT b.name, n.note_number, n.trade_date, n.settlement_date, n.settlement_fee,
+                        n.registration_fee, n.emolumentos, n.brokerage, n.iss, n.other_fees,
+                        n.irrf, n.irrf_day_trade, n.net_amount, p.name
+                 FROM broker_notes n LEFT JOIN brokers b ON n.broker_id = b.id
+                 JOIN portfolios p ON n.portfolio_id = p.id
+                 ORDER BY n.trade_date, n.note_number",
+            ),
+            (
+                "term_contract_details",
+                "SELECT a.ticker, t.trade_date, d.expiry_date, d.contracted_rate
+                 FROM term_contract_details d JOIN transactions t ON d.transaction_id = t.id
+                 JOIN assets a ON t.asset_id = a.id
+                 ORDER BY t.trade_date",
+            ),
+            (
+                "option_contracts",
+                "SELECT a.ticker, o.underlying, o.strike
+                 FROM option_contracts o JOIN assets a ON o.asset_id = a.id ORDER BY a.ticker",
+            ),
+            (
+                "option_exercises",
+                "SELECT ua.ticker, t.trade_date, oa.ticker, c.transaction_type, c.trade_date,
+                        e.quantity, e.premium
+                 FROM option_exercises e
+                 JOIN transactions t ON e.transaction_id = t.id
+                 JOIN assets ua ON t.asset_id = ua.id
+                 JOIN assets oa ON e.option_asset_id = oa.id
+                 LEFT JOIN transactions c ON e.closing_transaction_id = c.id
+                 ORDER BY t.trade_date",
+            ),
+            (
+                "fixed_income_terms",
+                "SELECT a.ticker, f.kind, f.issue_date, f.maturity_date, f.indexer, f.rate,
+                        f.issue_price
+                 FROM fixed_income_terms f JOIN assets a ON f.asset_id = a.id ORDER BY a.ticker",
+            ),
+            (
+                "cash_credits",
+                "SELECT a.ticker, c.credit_date, c.movement_type, c.event_type, c.amount,
+                        c.source, i.event_date, i.event_type, b.name, p.name
+                 FROM cash_credits c JOIN assets a ON c.asset_id = a.id
+                 LEFT JOIN income_events i ON c.income_event_id = i.id
+                 LEFT JOIN brokers b ON c.broker_id = b.id
+                 JOIN portfolios p ON c.portfolio_id = p.id
+                 ORDER BY c.credit_date, a.ticker",
+            ),
+            (
+                "position_statements",
+                "SELECT a.ticker, s.statement_date, s.quantity, s.source, p.name
+                 FROM position_statements s JOIN assets a ON s.asset_id = a.id
+                 JOIN portfolios p ON s.portfolio_id = p.id
+                 ORDER BY s.statement_date, a.ticker",
+            ),
+            (
+                "asset_valuations",
+                "SELECT a.ticker, v.valuation_date, v.value, v.notes
+                 FROM asset_valuations v JOIN assets a ON v.asset_id = a.id
+                 ORDER BY a.ticker, v.valuation_date",
+            ),
+            (
+                "asset_issuers",
+                "SELECT a.ticker, i.cnpj, i.legal_name, i.trade_name, i.situation,
+                        i.situation_date, i.source, i.fetched_at
+                 FROM asset_issuers i JOIN assets a ON i.asset_id = a.id ORDER BY a.ticker",
+            ),
+            (
+                "tax_ledger",
+                "SELECT year, month, tax_category, tax_due, darf_status, paid_on, paid_amount
+                 FROM tax_ledger WHERE paid_on IS NOT NULL ORDER BY year, month, tax_category",
+            ),
+        ];
+        for (table, query) in tables {
+            let rows = dump(&source, query);
+            assert!(!rows.is_empty(), "seed has no {} rows", table);
+            assert_eq!(dump(&target, query), rows, "{} differs after import", table);
+        }
+
+        // The restored ledger row is recomputed by the next tax report
+        let stale: String = target
+            .query_row("SELECT tx_fingerprint FROM tax_ledger", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(stale, "");
+    }
+
+    #[test]
+    fn test_version_1_archive_restores_into_the_written_portfolio() {
+        let source = setup();
+        seed(&source);
+        let mut json = serde_json::to_value(export_archive(&source).unwrap()).unwrap();
+        json["version"] = serde_json::json!(1);
+        let archive = json.as_object_mut().unwrap();
+        for section in [
+            "portfolios",
+            "brokers",
+            "broker_notes",
+            "term_contracts",
+            "option_contracts",
+            "option_exercises",
+            "fixed_income_terms",
+            "cash_credits",
+            "position_statements",
+            "asset_valuations",
+            "asset_issuers",
+            "tax_payments",
+        ] {
+            archive.remove(section);
+        }
+        for section in ["transactions", "income_events"] {
+            for row in archive[section].as_array_mut().unwrap() {
+                let row = row.as_object_mut().unwrap();
+                row.remove("portfolio");
+                row.remove("broker");
+            }
+        }
+        let parsed: Archive = serde_json::from_value(json).unwrap();
+
+        let target = setup();
+        import_archive(&target, &parsed).unwrap();
+        let portfolios: Vec<i64> = target
+            .prepare("SELECT DISTINCT portfolio_id FROM transactions")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            portfolios,
+            vec![super::super::portfolio::DEFAULT_PORTFOLIO_ID]
+        );
+    }
+
+    #[test]
+    fn test_import_rejects_non_empty_database_and_newer_versions() {
+        let source = setup();
+        seed(&source);
+        let mut archive = export_archive(&source).unwrap();
+
+        assert!(import_archive(&source, &archive).is_err());
+
+        let target = setup();
+        archive.version = ARCHIVE_VERSION + 1;
+        assert!(import_archive(&target, &archive).is_err());
+        let assets: i64 = target
+            .query_row("SELECT COUNT(*) FROM assets", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(assets, 0);
+    }
+}
@@ -0,0 +1,283 @@
+//! Batched inserts for trade, income and price imports.
+//!
+//! Corporate actions are not batched: each one an import records changes the
+//! position the next is checked against (B3 events read the quantity held
+//! before their ex-date, Movimentação applies a split before reading its next
+//! row), so importers insert them one at a time inside their own transaction.
+//!
+//! Each call runs inside a single SQLite transaction with cached prepared
+//! statements, reporting progress every [`PROGRESS_EVERY`] rows. Per-row
+//! `conn.execute` in autocommit mode forces a journal sync per row, which
+//! dominates import time on large files.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+
+use super::{IncomeEvent, PriceHistory, Transaction};
+
+/// Rows between progress callbacks
+pub const PROGRESS_EVERY: usize = 500;
+
+/// Progress of a bulk insert
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+/// Run `f` inside a transaction, committing on success
+///
+/// When the connection is already inside a transaction, `f` joins it so
+/// bulk helpers can be composed by callers that manage their own.
+pub fn in_transaction<T>(conn: &Connection, f: impl FnOnce(&Connection) -> Result<T>) -> Result<T> {
+    if !conn.is_autocommit() {
+        return f(conn);
+    }
+    let tx = conn.unchecked_transaction()?;
+    let value = f(&tx)?;
+    tx.commit()?;
+    Ok(value)
+}
+
+fn insert_rows<R>(
+    conn: &Connection,
+    sql: &str,
+    rows: &[R],
+    mut on_progress: impl FnMut(BulkProgress),
+    mut execute: impl FnMut(&mut rusqlite::CachedStatement<'_>, &R) -> rusqlite::Result<usize>,
+) -> Result<Vec<i64>> {
+    in_transaction(conn, |conn| {
+        let total = rows.len();
+        let mut ids = Vec::with_capacity(total);
+        let mut stmt = conn.prepare_cached(sql)?;
+        for (idx, row) in rows.iter().enumerate() {
+            execute(&mut stmt, row)?;
+            ids.push(conn.last_insert_rowid());
+            let done = idx + 1;
+            if done % PROGRESS_EVERY == 0 && done < total {
+                on_progress(BulkProgress { done, total });
+            }
+        }
+        on_progress(BulkProgress { done: total, total });
+        Ok(ids)
+    })
+}
+
+/// Insert transactions, returning their ids in input order
+pub fn insert_transactions(
+    conn: &Connection,
+    txs: &[Transaction],
+    on_progress: impl FnMut(BulkProgress),
+) -> Result<Vec<i64>> {
+    let ids = insert_rows(
+        conn,
+        super::INSERT_TRANSACTION_SQL,
+        txs,
+        on_progress,
+        |stmt, tx| {
+            stmt.execute(params![
+                tx.asset_id,
+                tx.transaction_type.as_str(),
+                tx.trade_date,
+                tx.settlement_date,
+                tx.quantity.to_string(),
+                tx.price_per_unit.to_string(),
+                tx.total_cost.to_string(),
+                tx.fees.to_string(),
+                tx.is_day_trade,
+                tx.quota_issuance_date,
+                tx.notes,
+                tx.source,
+                super::portfolio::write_target(),
+                super::import_session::current(),
+            ])
+        },
+    )?;
+    for (id, tx) in ids.iter().zip(txs) {
+        super::lot_size::flag_invalid_quantity(conn, *id, tx)?;
+    }
+    Ok(ids)
+}
+
+/// Insert income events, returning their ids in input order
+pub fn insert_income_events(
+    conn: &Connection,
+    events: &[IncomeEvent],
+    on_progress: impl FnMut(BulkProgress),
+) -> Result<Vec<i64>> {
+    insert_rows(
+        conn,
+        super::INSERT_INCOME_EVENT_SQL,
+        events,
+        on_progress,
+        |stmt, event| {
+            stmt.execute(params![
+                event.asset_id,
+                event.event_date,
+                event.ex_date,
+                event.event_type.as_str(),
+                event.amount_per_quota.to_string(),
+                event.total_amount.to_string(),
+                event.withholding_tax.to_string(),
+                event.is_quota_pre_2026,
+                event.source,
+                event.notes,
+                super::portfolio::write_target(),
+                event.foreign_tax_withheld.map(|v| v.to_string()),
+                super::import_session::current(),
+            ])
+        },
+    )
+}
+
+/// Insert (or replace) price history rows, returning their ids in input order
+pub fn insert_price_history(
+    conn: &Connection,
+    prices: &[PriceHistory],
+    on_progress: impl FnMut(BulkProgress),
+) -> Result<Vec<i64>> {
+    insert_rows(
+        conn,
+        super::INSERT_PRICE_HISTORY_SQL,
+        prices,
+        on_progress,
+        |stmt, price| {
+            stmt.execute(params![
+                price.asset_id,
+                price.price_date,
+                price.close_price.to_string(),
+                price.open_price.as_ref().map(|d| d.to_string()),
+                price.high_price.as_ref().map(|d| d.to_string()),
+                price.low_price.as_ref().map(|d| d.to_string()),
+                price.volume,
+                price.source,
+                price.adjusted_close.as_ref().map(|d| d.to_string()),
+            ])
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{AssetType, IncomeEventType, TransactionType};
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn
+    }
+
+    fn buy(asset_id: i64, day: u32) -> Transaction {
+        let date = NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        Transaction {
+            id: None,
+            asset_id,
+            transaction_type: TransactionType::Buy,
+            trade_date: date,
+            settlement_date: Some(date),
+            quantity: Decimal::from(10),
+            price_per_unit: Decimal::from(5),
+            total_cost: Decimal::from(50),
+            fees: Decimal::ZERO,
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: None,
+            source: "TEST".to_string(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_insert_transactions_reports_progress() {
+        let conn = setup();
+        let asset_id = crate::db::insert_asset(&conn, "PETR4", &AssetType::Stock, None).unwrap();
+        let txs: Vec<_> = (0..1200).map(|i| buy(asset_id, 1 + (i % 28))).collect();
+
+        let mut events = Vec::new();
+        let ids = insert_transactions(&conn, &txs, |p| events.push(p)).unwrap();
+
+        assert_eq!(ids.len(), 1200);
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(
+            events,
+            vec![
+                BulkProgress {
+                    done: 500,
+                    total: 1200
+                },
+                BulkProgress {
+                    done: 1000,
+                    total: 1200
+                },
+                BulkProgress {
+                    done: 1200,
+                    total: 1200
+                },
+            ]
+        );
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 1200);
+    }
+
+    #[test]
+    fn test_insert_income_events_returns_ids_in_order() {
+        let conn = setup();
+        let asset_id = crate::db::insert_asset(&conn, "MXRF11", &AssetType::Fii, None).unwrap();
+        let events: Vec<_> = (1..=3)
+            .map(|day| IncomeEvent {
+                id: None,
+                asset_id,
+                event_date: NaiveDate::from_ymd_opt(2025, 1, day).unwrap(),
+                ex_date: None,
+                event_type: IncomeEventType::Dividend,
+                amount_per_quota: Decimal::ZERO,
+                total_amount: Decimal::from(day),
+                withholding_tax: Decimal::ZERO,
+                foreign_tax_withheld: None,
+                is_quota_pre_2026: None,
+                source: "TEST".to_string(),
+                notes: None,
+                created_at: chrono::Utc::now(),
+            })
+            .collect();
+
+        let mut progress = Vec::new();
+        let ids = insert_income_events(&conn, &events, |p| progress.push(p)).unwrap();
+
+        assert_eq!(ids.len(), 3);
+        assert_eq!(progress, vec![BulkProgress { done: 3, total: 3 }]);
+        for (id, day) in ids.iter().zip(1..) {
+            let total: i64 = conn
+                .query_row(
+                    "SELECT total_amount FROM income_events WHERE id = ?1",
+                    [id],
+                    |r| r.get(0),
+                )
+                .unwrap();
+            assert_eq!(total, day);
+        }
+    }
+
+    #[test]
+    fn test_in_transaction_rolls_back_on_error() {
+        let conn = setup();
+        let asset_id = crate::db::insert_asset(&conn, "PETR4", &AssetType::Stock, None).unwrap();
+
+        let result: Result<()> = in_transaction(&conn, |conn| {
+            crate::db::insert_transaction(conn, &buy(asset_id, 2))?;
+            anyhow::bail!("boom")
+        });
+        assert!(result.is_err());
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 0);
+        assert!(conn.is_autocommit());
+    }
+}
@@ -0,0 +1,489 @@
+//! Optional encryption of the database at rest.
+//!
+//! `db encrypt` replaces `~/.interest/data.db` with `data.db.enc`, sealed
+//! with AES-256-GCM under a key derived from a passphrase (PBKDF2-SHA256).
+//! While a command runs, the database is decrypted into a private working
+//! copy in a fresh 0700 directory under the runtime directory
+//! (`$XDG_RUNTIME_DIR`, usually RAM-backed) and the default database path
+//! points there. When the command ends, or is interrupted by SIGINT/SIGTERM,
+//! the copy is sealed back, only if it changed, and removed. A lock on
+//! `data.db.enc.lock` keeps a second session from decrypting the database
+//! until the first one has sealed it back.
+//!
+//! The passphrase comes from `INTEREST_DB_PASSPHRASE` or a hidden prompt.
+
+use anyhow::{anyhow, Context, Result};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use std::fs::{File, TryLockError};
+use std::io::{IsTerminal, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+pub const PASSPHRASE_ENV: &str = "INTEREST_DB_PASSPHRASE";
+
+const MAGIC: &[u8; 8] = b"INTENC01";
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+struct Session {
+    /// Private directory holding the working copy and its journal
+    dir: PathBuf,
+    working: PathBuf,
+    encrypted: PathBuf,
+    passphrase: String,
+    original: blake3::Hash,
+    /// Held until the session ends
+    _lock: File,
+}
+
+static SESSION: Mutex<Option<Session>> = Mutex::new(None);
+
+/// Path of the encrypted database next to the live one
+pub fn encrypted_path() -> Result<PathBuf> {
+    Ok(super::get_interest_dir()?.join(format!("{}.enc", super::DB_FILENAME)))
+}
+
+fn lock_path() -> Result<PathBuf> {
+    Ok(super::get_interest_dir()?.join(format!("{}.enc.lock", super::DB_FILENAME)))
+}
+
+pub fn is_encrypted() -> Result<bool> {
+    Ok(encrypted_path()?.exists())
+}
+
+/// Working copy used by the current command, when the database is encrypted
+pub fn working_path() -> Option<PathBuf> {
+    SESSION
+        .lock()
+        .ok()
+        .and_then(|s| s.as_ref().map(|s| s.working.clone()))
+}
+
+/// Decrypt the database into a working copy when it is encrypted. Returns
+/// true when a session was opened; `lock` must be called before exiting.
+pub fn unlock() -> Result<bool> {
+    let encrypted = encrypted_path()?;
+    if !encrypted.exists() {
+        return Ok(false);
+    }
+    let live = super::get_interest_dir()?.join(super::DB_FILENAME);
+    if live.exists() {
+        anyhow::bail!(
+            "Both {} and {} exist. Move one of them away before continuing",
+            live.display(),
+            encrypted.display()
+        );
+    }
+
+    let lock = acquire_lock(&lock_path()?)?;
+    let sealed =
+        std::fs::read(&encrypted).with_context(|| format!("Failed to read {:?}", encrypted))?;
+    let passphrase = read_passphrase("Database passphrase: ")?;
+    let plain = decrypt(&sealed, &passphrase)?;
+    open_session(encrypted, &passphrase, &plain, lock)?;
+    Ok(true)
+}
+
+fn open_session(encrypted: PathBuf, passphrase: &str, plain: &[u8], lock: File) -> Result<()> {
+    let dir = create_private_dir()?;
+    let working = dir.join(super::DB_FILENAME);
+    if let Err(e) = write_private(&working, plain) {
+        let _ = std::fs::remove_dir_all(&dir);
+        return Err(e);
+    }
+    *SESSION
+        .lock()
+        .map_err(|_| anyhow!("Encryption state poisoned"))? = Some(Session {
+        dir,
+        working,
+        encrypted,
+        passphrase: passphrase.to_string(),
+        original: blake3::hash(plain),
+        _lock: lock,
+    });
+    watch_signals();
+    Ok(())
+}
+
+/// Exclusive lock held for a whole session, so two sessions never each seal
+/// their own copy over the other's writes
+fn acquire_lock(path: &Path) -> Result<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true).write(true).create(true).truncate(false);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options
+        .open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    match file.try_lock() {
+        Ok(()) => Ok(file),
+        Err(TryLockError::WouldBlock) => anyhow::bail!(
+            "Another interest command has the encrypted database open; \
+             wait for it to finish"
+        ),
+        Err(TryLockError::Error(e)) => Err(e).with_context(|| format!("Failed to lock {:?}", path)),
+    }
+}
+
+/// Seal and remove the working copy on SIGINT/SIGTERM too, not only when
+/// the command returns
+fn watch_signals() {
+    static WATCHING: AtomicBool = AtomicBool::new(false);
+    if WATCHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+        return;
+    };
+    runtime.spawn(async {
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut signal) => {
+                    signal.recv().await;
+                }
+                Err(_) => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+        let code = tokio::select! {
+            _ = tokio::signal::ctrl_c() => 130,
+            _ = terminate => 143,
+        };
+        if let Err(e) = interrupt() {
+            eprintln!("Failed to seal the encrypted database: {:#}", e);
+        }
+        std::process::exit(code);
+    });
+}
+
+/// End the session from a signal handler: the command may be in the middle
+/// of a write, so what it committed is read through SQLite (which waits for
+/// the write to finish or leaves it out) instead of copying the raw file
+fn interrupt() -> Result<()> {
+    let Some(session) = SESSION
+        .lock()
+        .map_err(|_| anyhow!("Encryption state poisoned"))?
+        .take()
+    else {
+        return Ok(());
+    };
+
+    let result = (|| {
+        let plain = std::fs::read(&session.working)
+            .with_context(|| format!("Failed to read {:?}", session.working))?;
+        if blake3::hash(&plain) == session.original {
+            return Ok(());
+        }
+        let snapshot = session.dir.join("snapshot.db");
+        let conn = rusqlite::Connection::open(&session.working)?;
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.execute(
+            "VACUUM INTO ?1",
+            rusqlite::params![snapshot.to_string_lossy()],
+        )?;
+        drop(conn);
+        let plain = std::fs::read(&snapshot)?;
+        write_atomically(&session.encrypted, &encrypt(&plain, &session.passphrase)?)
+    })();
+    remove_working_copy(&session.dir);
+    result
+}
+
+/// Seal the working copy back when it changed, then remove it
+pub fn lock() -> Result<()> {
+    let Some(session) = SESSION
+        .lock()
+        .map_err(|_| anyhow!("Encryption state poisoned"))?
+        .take()
+    else {
+        return Ok(());
+    };
+
+    let result = (|| {
+        let plain = std::fs::read(&session.working)
+            .with_context(|| format!("Failed to read {:?}", session.working))?;
+        if blake3::hash(&plain) != session.original {
+            let sealed = encrypt(&plain, &session.passphrase)?;
+            write_atomically(&session.encrypted, &sealed)?;
+        }
+        Ok(())
+    })();
+    remove_working_copy(&session.dir);
+    result
+}
+
+/// Encrypt the live database in place. The rest of the process (e.g. an
+/// interactive session) keeps working on a decrypted copy.
+pub fn encrypt_database(passphrase: &str) -> Result<PathBuf> {
+    if is_encrypted()? {
+        anyhow::bail!("The database is already encrypted");
+    }
+    if super::sandbox::sandbox_path()?.exists() {
+        anyhow::bail!(
+            "A sandbox exists. Promote or discard it first: interest sandbox promote/discard"
+        );
+    }
+    let lock = acquire_lock(&lock_path()?)?;
+    let live = super::get_interest_dir()?.join(super::DB_FILENAME);
+    let plain = std::fs::read(&live).with_context(|| format!("Failed to read {:?}", live))?;
+    let encrypted = encrypted_path()?;
+    write_atomically(&encrypted, &encrypt(&plain, passphrase)?)?;
+    std::fs::remove_file(&live).with_context(|| format!("Failed to remove {:?}", live))?;
+    open_session(encrypted.clone(), passphrase, &plain, lock)?;
+    Ok(encrypted)
+}
+
+/// Turn the current session's working copy back into a plain database
+pub fn decrypt_database() -> Result<PathBuf> {
+    let session = SESSION
+        .lock()
+        .map_err(|_| anyhow!("Encryption state poisoned"))?
+        .take()
+        .ok_or_else(|| anyhow!("The database is not encrypted"))?;
+    let live = super::get_interest_dir()?.join(super::DB_FILENAME);
+    let plain = std::fs::read(&session.working)
+        .with_context(|| format!("Failed to read {:?}", session.working))?;
+    write_atomically(&live, &plain)?;
+    std::fs::remove_file(&session.encrypted)
+        .with_context(|| format!("Failed to remove {:?}", session.encrypted))?;
+    remove_working_copy(&session.dir);
+    Ok(live)
+}
+
+/// Ask for a new passphrase twice (or take it from the environment)
+pub fn read_new_passphrase() -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return non_empty(passphrase);
+    }
+    let passphrase = non_empty(read_passphrase("New passphrase: ")?)?;
+    if read_passphrase("Repeat passphrase: ")? != passphrase {
+        anyhow::bail!("Passphrases do not match");
+    }
+    Ok(passphrase)
+}
+
+fn non_empty(passphrase: String) -> Result<String> {
+    if passphrase.is_empty() {
+        anyhow::bail!("The passphrase cannot be empty");
+    }
+    Ok(passphrase)
+}
+
+fn read_passphrase(prompt: &str) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    eprint!("{}", prompt);
+    std::io::stderr().flush()?;
+
+    if !std::io::stdin().is_terminal() {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        return Ok(line.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    crossterm::terminal::enable_raw_mode()?;
+    let result = read_hidden_line();
+    crossterm::terminal::disable_raw_mode()?;
+    eprintln!();
+    result
+}
+
+fn read_hidden_line() -> Result<String> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    let mut line = String::new();
+    loop {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Enter => return Ok(line),
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Esc => anyhow::bail!("Cancelled"),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                anyhow::bail!("Cancelled")
+            }
+            KeyCode::Char(c) => line.push(c),
+            _ => {}
+        }
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<LessSafeKey> {
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero iterations"),
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| anyhow!("Invalid key length"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Seal `plain` as MAGIC | salt | nonce | ciphertext+tag
+fn encrypt(plain: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rng.fill(&mut salt)
+        .and_then(|_| rng.fill(&mut nonce))
+        .map_err(|_| anyhow!("No secure random source available"))?;
+
+    let mut sealed = plain.to_vec();
+    derive_key(passphrase, &salt)?
+        .seal_in_place_append_tag(
+            Nonce::assume_unique_for_key(nonce),
+            Aad::from(MAGIC),
+            &mut sealed,
+        )
+        .map_err(|_| anyhow!("Encryption failed"))?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + sealed.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+fn decrypt(sealed: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    let header = MAGIC.len() + SALT_LEN + NONCE_LEN;
+    if sealed.len() < header || &sealed[..MAGIC.len()] != MAGIC {
+        anyhow::bail!("Not an encrypted interest database");
+    }
+    let salt = &sealed[MAGIC.len()..MAGIC.len() + SALT_LEN];
+    let nonce = Nonce::try_assume_unique_for_key(&sealed[MAGIC.len() + SALT_LEN..header])
+        .map_err(|_| anyhow!("Invalid nonce"))?;
+
+    let mut buf = sealed[header..].to_vec();
+    let plain = derive_key(passphrase, salt)?
+        .open_in_place(nonce, Aad::from(MAGIC), &mut buf)
+        .map_err(|_| anyhow!("Wrong passphrase or corrupted database"))?;
+    Ok(plain.to_vec())
+}
+
+/// Fresh directory only this user can enter, in the runtime directory (or
+/// the temp directory without one). It is created, never reused: anything
+/// already at the path, such as a planted symlink, makes this fail.
+fn create_private_dir() -> Result<PathBuf> {
+    let base = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|d| d.is_dir())
+        .unwrap_or_else(std::env::temp_dir);
+    let mut suffix = [0u8; 8];
+    SystemRandom::new()
+        .fill(&mut suffix)
+        .map_err(|_| anyhow!("No secure random source available"))?;
+    let dir = base.join(format!(
+        "interest-{}-{:016x}",
+        std::process::id(),
+        u64::from_le_bytes(suffix)
+    ));
+    let mut builder = std::fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder
+        .create(&dir)
+        .with_context(|| format!("Failed to create {:?}", dir))?;
+    Ok(dir)
+}
+
+/// Write a new 0600 file; fails when the path exists (symlinks included)
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("Failed to create {:?}", path))?;
+    file.write_all(data)
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn write_atomically(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    // Left over by an interrupted write
+    let _ = std::fs::remove_file(&tmp);
+    write_private(&tmp, data)?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {:?}", path))
+}
+
+fn remove_working_copy(dir: &Path) {
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_round_trip_and_wrong_passphrase() {
+        let plain = b"SQLite format 3\0 some pages".to_vec();
+        let sealed = encrypt(&plain, "correct horse").unwrap();
+
+        assert_eq!(&sealed[..MAGIC.len()], MAGIC);
+        assert!(!sealed.windows(plain.len()).any(|w| w == plain.as_slice()));
+        assert_eq!(decrypt(&sealed, "correct horse").unwrap(), plain);
+        assert!(decrypt(&sealed, "wrong").is_err());
+        assert!(decrypt(&plain, "correct horse").is_err());
+    }
+
+    #[test]
+    fn test_working_copy_is_private_and_never_reused() {
+        let dir = create_private_dir().unwrap();
+        let working = dir.join("data.db");
+        write_private(&working, b"plain").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |p: &Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&dir), 0o700);
+            assert_eq!(mode(&working), 0o600);
+        }
+        // An existing file or symlink is not written through
+        assert!(write_private(&working, b"other").is_err());
+        assert_eq!(std::fs::read(&working).unwrap(), b"plain");
+        let other = create_private_dir().unwrap();
+        assert_ne!(other, dir);
+        remove_working_copy(&other);
+        remove_working_copy(&dir);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_session_lock_is_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.db.enc.lock");
+        let held = acquire_lock(&path).unwrap();
+        let err = acquire_lock(&path).unwrap_err();
+        assert!(err.to_string().contains("Another interest command"));
+        drop(held);
+        assert!(acquire_lock(&path).is_ok());
+    }
+}
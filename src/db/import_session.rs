@@ -0,0 +1,357 @@
+//! Import sessions: every file import is recorded with the rows it added.
+//!
+//! While a session is active, new transactions, corporate actions, income
+//! events, brokerage notes, cash credits and position statements carry its
+//! id in `import_session_id`. Undoing the session deletes exactly those rows
+//! in one SQL transaction and rewinds the last-import dates the import
+//! moved, so the corrected file can be imported again. Changes an import
+//! made to rows that already existed (fees from a brokerage note, gross
+//! values from Proventos) are not reverted.
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use super::portfolio;
+
+/// Session new rows are tagged with, None outside an import
+static CURRENT: RwLock<Option<i64>> = RwLock::new(None);
+
+/// Tables whose new rows are tagged with the active session
+const TAGGED_TABLES: [&str; 6] = [
+    "transactions",
+    "corporate_actions",
+    "income_events",
+    "broker_notes",
+    "cash_credits",
+    "position_statements",
+];
+
+/// Session id to record on rows inserted now
+pub fn current() -> Option<i64> {
+    *CURRENT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn set_current(id: Option<i64>) {
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = id;
+}
+
+/// A recorded import
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportSession {
+    pub id: i64,
+    pub file: String,
+    pub format: String,
+    pub started_at: String,
+    pub transactions: i64,
+    pub corporate_actions: i64,
+    pub income_events: i64,
+    pub undone_at: Option<String>,
+}
+
+/// A last-import date moved by the import, with the value it had before
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Watermark {
+    source: String,
+    entry_type: String,
+    before: Option<NaiveDate>,
+    after: NaiveDate,
+}
+
+/// Rows removed by an undo
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UndoResult {
+    pub transactions: usize,
+    pub corporate_actions: usize,
+    pub income_events: usize,
+    /// Earliest date touched, for invalidating snapshots
+    pub earliest: Option<NaiveDate>,
+}
+
+/// An import in progress; rows inserted until `finish` are tagged with it
+pub struct ActiveSession {
+    id: i64,
+    watermarks: BTreeMap<(String, String), NaiveDate>,
+}
+
+/// Open a session for importing `file`, detected as `format`
+pub fn begin(conn: &Connection, file: &str, format: &str) -> Result<ActiveSession> {
+    let file = std::fs::canonicalize(file)
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|_| file.to_string());
+    conn.execute(
+        "INSERT INTO import_sessions (file, format, portfolio_id) VALUES (?1, ?2, ?3)",
+        params![file, format, portfolio::write_target()],
+    )?;
+    let id = conn.last_insert_rowid();
+    set_current(Some(id));
+    Ok(ActiveSession {
+        id,
+        watermarks: watermarks(conn)?,
+    })
+}
+
+fn watermarks(conn: &Connection) -> Result<BTreeMap<(String, String), NaiveDate>> {
+    let mut stmt = conn.prepare("SELECT source, entry_type, last_date FROM import_state")?;
+    let rows = stmt
+        .query_map([], |row| Ok(((row.get(0)?, row.get(1)?), row.get(2)?)))?
+        .collect::<Result<_, _>>()?;
+    Ok(rows)
+}
+
+impl ActiveSession {
+    /// Stop tagging rows and record what the import added. A session that
+    /// added nothing is dropped; None then.
+    pub fn finish(self, conn: &Connection) -> Result<Option<ImportSession>> {
+        set_current(None);
+        let added: i64 = TAGGED_TABLES
+            .iter()
+            .map(|table| {
+                conn.query_row(
+                    &format!(
+                        "SELECT COUNT(*) FROM {} WHERE import_session_id = ?1",
+                        table
+                    ),
+                    params![self.id],
+                    |row| row.get::<_, i64>(0),
+                )
+            })
+            .sum::<rusqlite::Result<i64>>()?;
+        if added == 0 {
+            conn.execute(
+                "DELETE FROM import_sessions WHERE id = ?1",
+                params![self.id],
+            )?;
+            return Ok(None);
+        }
+
+        let moved: Vec<Watermark> = watermarks(conn)?
+            .into_iter()
+            .filter(|(key, after)| self.watermarks.get(key) != Some(after))
+            .map(|((source, entry_type), after)| Watermark {
+                before: self
+                    .watermarks
+                    .get(&(source.clone(), entry_type.clone()))
+                    .copied(),
+                source,
+                entry_type,
+                after,
+            })
+            .collect();
+        conn.execute(
+            "UPDATE import_sessions
+             SET finished_at = CURRENT_TIMESTAMP, import_state_changes = ?2,
+                 transactions = (SELECT COUNT(*) FROM transactions WHERE import_session_id = ?1),
+                 corporate_actions = (SELECT COUNT(*) FROM corporate_actions WHERE import_session_id = ?1),
+                 income_events = (SELECT COUNT(*) FROM income_events WHERE import_session_id = ?1)
+             WHERE id = ?1",
+            params![self.id, serde_json::to_string(&moved)?],
+        )?;
+        get_session(conn, self.id)
+    }
+}
+
+impl Drop for ActiveSession {
+    fn drop(&mut self) {
+        if current() == Some(self.id) {
+            set_current(None);
+        }
+    }
+}
+
+fn map_session(row: &rusqlite::Row) -> rusqlite::Result<ImportSession> {
+    Ok(ImportSession {
+        id: row.get(0)?,
+        file: row.get(1)?,
+        format: row.get(2)?,
+        started_at: row.get(3)?,
+        transactions: row.get(4)?,
+        corporate_actions: row.get(5)?,
+        income_events: row.get(6)?,
+        undone_at: row.get(7)?,
+    })
+}
+
+const SESSION_COLUMNS: &str = "id, file, format, started_at, transactions, corporate_actions,
+     income_events, undone_at";
+
+pub fn get_session(conn: &Connection, id: i64) -> Result<Option<ImportSession>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM import_sessions WHERE id = ?1",
+                SESSION_COLUMNS
+            ),
+            params![id],
+            map_session,
+        )
+        .optional()?)
+}
+
+/// Recorded imports in the scoped portfolios, newest first
+pub fn list_sessions(conn: &Connection) -> Result<Vec<ImportSession>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM import_sessions WHERE 1 = 1{} ORDER BY id DESC",
+        SESSION_COLUMNS,
+        portfolio::scope_filter("portfolio_id")
+    ))?;
+    let rows = stmt
+        .query_map([], map_session)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Delete every row the session added and rewind the last-import dates it
+/// moved (unless a later import moved them again), atomically
+pub fn undo(conn: &Connection, id: i64) -> Result<UndoResult> {
+    let session =
+        get_session(conn, id)?.ok_or_else(|| anyhow!("Import session {} not found", id))?;
+    if let Some(undone_at) = session.undone_at {
+        return Err(anyhow!(
+            "Import session {} was already undone on {}",
+            id,
+            undone_at
+        ));
+    }
+
+    let tx = conn.unchecked_transaction()?;
+    let earliest: Option<NaiveDate> = tx.query_row(
+        "SELECT MIN(d) FROM (
+             SELECT MIN(trade_date) AS d FROM transactions WHERE import_session_id = ?1
+             UNION ALL SELECT MIN(ex_date) FROM corporate_actions WHERE import_session_id = ?1
+             UNION ALL SELECT MIN(event_date) FROM income_events WHERE import_session_id = ?1
+             UNION ALL SELECT MIN(credit_date) FROM cash_credits WHERE import_session_id = ?1
+         )",
+        params![id],
+        |row| row.get(0),
+    )?;
+
+    // Rows derived from the imported transactions that don't cascade
+    for table in ["cash_flows", "inconsistencies"] {
+        tx.execute(
+            &format!(
+                "DELETE FROM {} WHERE transaction_id IN
+                 (SELECT id FROM transactions WHERE import_session_id = ?1)",
+                table
+            ),
+            params![id],
+        )?;
+    }
+    let mut result = UndoResult {
+        earliest,
+        ..Default::default()
+    };
+    for table in TAGGED_TABLES {
+        let deleted = tx.execute(
+            &format!("DELETE FROM {} WHERE import_session_id = ?1", table),
+            params![id],
+        )?;
+        match table {
+            "transactions" => result.transactions = deleted,
+            "corporate_actions" => result.corporate_actions = deleted,
+            "income_events" => result.income_events = deleted,
+            _ => {}
+        }
+    }
+
+    let changes: Option<String> = tx.query_row(
+        "SELECT import_state_changes FROM import_sessions WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )?;
+    let moved: Vec<Watermark> = match changes {
+        Some(json) => serde_json::from_str(&json)?,
+        None => Vec::new(),
+    };
+    for mark in moved {
+        match mark.before {
+            Some(before) => tx.execute(
+                "UPDATE import_state SET last_date = ?3, updated_at = CURRENT_TIMESTAMP
+                 WHERE source = ?1 AND entry_type = ?2 AND last_date = ?4",
+                params![mark.source, mark.entry_type, before, mark.after],
+            )?,
+            None => tx.execute(
+                "DELETE FROM import_state
+                 WHERE source = ?1 AND entry_type = ?2 AND last_date = ?3",
+                params![mark.source, mark.entry_type, mark.after],
+            )?,
+        };
+    }
+
+    tx.execute(
+        "UPDATE import_sessions SET undone_at = CURRENT_TIMESTAMP WHERE id = ?1",
+        params![id],
+    )?;
+    tx.commit()?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_removes_only_the_session_rows() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('PETR4', 'STOCK');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                price_per_unit, total_cost, source)
+             VALUES (1, 'BUY', '2024-01-10', 10, 30, 300, 'CEI');
+             INSERT INTO import_state (source, entry_type, last_date)
+             VALUES ('CEI', 'trades', '2024-01-10');",
+        )
+        .unwrap();
+
+        let session = begin(&conn, "negociacao.xlsx", "CEI").unwrap();
+        assert_eq!(current(), Some(session.id));
+        let tx = crate::db::Transaction {
+            id: None,
+            asset_id: 1,
+            transaction_type: crate::db::TransactionType::Buy,
+            trade_date: NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(),
+            settlement_date: None,
+            quantity: rust_decimal::Decimal::from(5),
+            price_per_unit: rust_decimal::Decimal::from(35),
+            total_cost: rust_decimal::Decimal::from(175),
+            fees: rust_decimal::Decimal::ZERO,
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: None,
+            source: "CEI".to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        crate::db::insert_transaction(&conn, &tx).unwrap();
+        crate::db::set_last_import_date(&conn, "CEI", "trades", tx.trade_date).unwrap();
+        let recorded = session.finish(&conn).unwrap().unwrap();
+        assert_eq!(current(), None);
+        assert_eq!(recorded.transactions, 1);
+
+        // An import that added nothing leaves no session behind
+        let empty = begin(&conn, "empty.xlsx", "CEI").unwrap();
+        assert!(empty.finish(&conn).unwrap().is_none());
+        assert_eq!(list_sessions(&conn).unwrap().len(), 1);
+
+        let undone = undo(&conn, recorded.id).unwrap();
+        assert_eq!(undone.transactions, 1);
+        assert_eq!(undone.earliest, Some(tx.trade_date));
+        let remaining: i64 = conn
+            .query_row("SELECT COUNT(*) FROM transactions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 1);
+        assert_eq!(
+            crate::db::get_last_import_date(&conn, "CEI", "trades").unwrap(),
+            NaiveDate::from_ymd_opt(2024, 1, 10)
+        );
+        assert!(get_session(&conn, recorded.id)
+            .unwrap()
+            .unwrap()
+            .undone_at
+            .is_some());
+        assert!(undo(&conn, recorded.id).is_err());
+    }
+}
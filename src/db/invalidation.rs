@@ -0,0 +1,293 @@
+//! Derived data invalidated by the database itself.
+//!
+//! Valuation snapshots and the tax caches are computed from trades,
+//! corporate actions, renames, exchanges, amortizations, manual valuations,
+//! closes, exchange rates, and the currency and type of assets. Instead of
+//! each command that writes one of those having to remember the caches,
+//! triggers on the input tables invalidate them on every insert, update and
+//! delete, whatever the code path: valuation snapshots from the row's date on are deleted, and the
+//! loss carryforward snapshots and tax ledger rows from its year on lose
+//! their fingerprint, so the next report recomputes them. The carried losses
+//! and DARF payments those rows hold are kept.
+//!
+//! An update counts from the earlier of the old and new dates. Triggers are
+//! recreated when their definition here changes.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, OptionalExtension};
+
+/// A table the derived data is computed from
+struct Input {
+    table: &'static str,
+    /// Date the row takes effect on
+    date_column: &'static str,
+    /// Only rows matching this condition are inputs, `{row}` being OLD or NEW
+    only: Option<&'static str>,
+    /// Capital gains depend on it, besides valuations
+    taxed: bool,
+}
+
+const INPUTS: &[Input] = &[
+    Input {
+        table: "transactions",
+        date_column: "trade_date",
+        only: None,
+        taxed: true,
+    },
+    Input {
+        table: "corporate_actions",
+        date_column: "ex_date",
+        only: None,
+        taxed: true,
+    },
+    Input {
+        table: "asset_renames",
+        date_column: "effective_date",
+        only: None,
+        taxed: true,
+    },
+    Input {
+        table: "asset_exchanges",
+        date_column: "effective_date",
+        only: None,
+        taxed: true,
+    },
+    Input {
+        table: "income_events",
+        date_column: "event_date",
+        only: Some("{row}.event_type = 'AMORTIZATION'"),
+        taxed: true,
+    },
+    Input {
+        table: "asset_valuations",
+        date_column: "valuation_date",
+        only: None,
+        taxed: false,
+    },
+    Input {
+        table: "price_history",
+        date_column: "price_date",
+        only: None,
+        taxed: false,
+    },
+    Input {
+        table: "fx_rates",
+        date_column: "rate_date",
+        only: Some(
+            "{row}.pair IN (SELECT currency || 'BRL' FROM assets WHERE currency IS NOT NULL)",
+        ),
+        taxed: false,
+    },
+];
+
+/// Statements run for a change effective on `date` (an SQL expression)
+fn invalidations(date: &str, taxed: bool) -> String {
+    let mut sql = format!(
+        "DELETE FROM position_snapshots WHERE snapshot_date >= {date};
+    DELETE FROM valuation_snapshots WHERE snapshot_date >= {date};"
+    );
+    if taxed {
+        let year = format!("CAST(strftime('%Y', {date}) AS INTEGER)");
+        sql.push_str(&format!(
+            "
+    UPDATE loss_carryforward_snapshots SET tx_fingerprint = ''
+        WHERE year >= {year} AND tx_fingerprint != '';
+    UPDATE tax_ledger SET tx_fingerprint = ''
+        WHERE year >= {year} AND tx_fingerprint != '';"
+        ));
+    }
+    sql
+}
+
+/// (name, CREATE TRIGGER statement) of every invalidation trigger
+fn triggers() -> Vec<(String, String)> {
+    let mut triggers = Vec::new();
+    for input in INPUTS {
+        let column = input.date_column;
+        for (event, date, when) in [
+            ("insert", format!("NEW.{column}"), vec!["NEW"]),
+            (
+                "update",
+                format!("MIN(OLD.{column}, NEW.{column})"),
+                vec!["OLD", "NEW"],
+            ),
+            ("delete", format!("OLD.{column}"), vec!["OLD"]),
+        ] {
+            let name = format!("invalidate_on_{}_{}", input.table, event);
+            let when = match input.only {
+                Some(condition) => {
+                    let rows: Vec<String> = when
+                        .iter()
+                        .map(|row| condition.replace("{row}", row))
+                        .collect();
+                    format!("\nWHEN {}", rows.join(" OR "))
+                }
+                None => String::new(),
+            };
+            let sql = format!(
+                "CREATE TRIGGER {name} AFTER {} ON {}{when}\nBEGIN\n    {}\nEND",
+                event.to_uppercase(),
+                input.table,
+                invalidations(&date, input.taxed)
+            );
+            triggers.push((name, sql));
+        }
+    }
+    // From an asset's first trade on, its prices read in another currency
+    // once its currency changes, and its gains fall in another tax category
+    // once its type does
+    for (column, taxed) in [("currency", false), ("asset_type", true)] {
+        let name = format!("invalidate_on_assets_{column}");
+        let sql = format!(
+            "CREATE TRIGGER {name} AFTER UPDATE OF {column} ON assets
+WHEN OLD.{column} IS NOT NEW.{column}
+BEGIN
+    {}
+END",
+            invalidations(
+                "(SELECT MIN(trade_date) FROM transactions WHERE asset_id = NEW.id)",
+                taxed
+            )
+        );
+        triggers.push((name, sql));
+    }
+    triggers
+}
+
+/// Create the invalidation triggers, replacing any whose definition changed
+pub fn install(conn: &Connection) -> Result<()> {
+    for (name, sql) in triggers() {
+        let current: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type = 'trigger' AND name = ?1",
+                [&name],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if current.as_deref() == Some(sql.as_str()) {
+            continue;
+        }
+        conn.execute_batch(&format!("DROP TRIGGER IF EXISTS {name};\n{sql};"))
+            .with_context(|| format!("Failed to create trigger {}", name))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_writes_to_inputs_invalidate_derived_data() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.db");
+        crate::db::init_database(Some(path.clone()))?;
+        // Reinstalling leaves unchanged triggers alone
+        crate::db::init_database(Some(path.clone()))?;
+        let conn = crate::db::open_db(Some(path))?;
+        let installed = "SELECT COUNT(*) FROM sqlite_master
+                         WHERE type = 'trigger' AND name LIKE 'invalidate_on_%'";
+        assert_eq!(count(&conn, installed), INPUTS.len() as i64 * 3 + 2);
+
+        let seed = || {
+            conn.execute_batch(
+                "DELETE FROM valuation_snapshots; DELETE FROM loss_carryforward_snapshots;
+                 DELETE FROM tax_ledger;
+                 INSERT INTO valuation_snapshots (snapshot_date, market_value, total_cost,
+                     positions, data_fingerprint)
+                     VALUES ('2023-12-29', '0', '0', 0, 'a'), ('2024-06-28', '0', '0', 0, 'b');
+                 INSERT INTO loss_carryforward_snapshots (year, tax_category,
+                     ending_remaining_amount, tx_fingerprint)
+                     VALUES (2023, 'STOCK_SWING', '100', 'a'), (2024, 'STOCK_SWING', '100', 'b');
+                 INSERT INTO tax_ledger (year, month, tax_category, sales, profit_loss,
+                     loss_offset, exemption, tax_due, darf_status, paid_on, tx_fingerprint)
+                     VALUES (2024, 3, 'STOCK_SWING', '0', '0', '0', '0', '10', 'PAID',
+                     '2024-04-30', 'b');",
+            )
+        };
+        let snapshots = "SELECT COUNT(*) FROM valuation_snapshots";
+        let stale_years =
+            "SELECT COUNT(*) FROM loss_carryforward_snapshots WHERE tx_fingerprint = ''";
+
+        seed()?;
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'PETR4', 'STOCK');
+             INSERT INTO transactions (id, asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source)
+                 VALUES (1, 1, 'BUY', '2024-02-01', '10', '30', '300', '0', 'TEST');",
+        )?;
+        assert_eq!(count(&conn, snapshots), 1);
+        assert_eq!(count(&conn, stale_years), 1);
+        // The ledger row is recomputed on the next report, its payment kept
+        assert_eq!(
+            count(
+                &conn,
+                "SELECT COUNT(*) FROM tax_ledger WHERE tx_fingerprint = '' AND paid_on IS NOT NULL"
+            ),
+            1
+        );
+
+        // Moving a trade back counts from its old date or new, whichever is earlier
+        seed()?;
+        conn.execute(
+            "UPDATE transactions SET trade_date = '2023-11-01' WHERE id = 1",
+            [],
+        )?;
+        assert_eq!(count(&conn, snapshots), 0);
+        assert_eq!(count(&conn, stale_years), 2);
+
+        // Only amortizations among income events change valuations
+        seed()?;
+        conn.execute_batch(
+            "INSERT INTO income_events (asset_id, event_date, event_type, amount_per_quota,
+                 total_amount, source)
+                 VALUES (1, '2024-03-15', 'DIVIDEND', '0', '50', 'TEST');",
+        )?;
+        assert_eq!(count(&conn, snapshots), 2);
+        conn.execute(
+            "UPDATE income_events SET event_type = 'AMORTIZATION' WHERE asset_id = 1",
+            [],
+        )?;
+        assert_eq!(count(&conn, snapshots), 1);
+
+        // Closes and manual valuations only touch valuations
+        seed()?;
+        conn.execute_batch(
+            "INSERT INTO price_history (asset_id, price_date, close_price, source)
+                 VALUES (1, '2024-05-31', '32', 'TEST');",
+        )?;
+        assert_eq!(count(&conn, snapshots), 1);
+        assert_eq!(count(&conn, stale_years), 0);
+
+        // Rates only count for currencies assets are quoted in
+        seed()?;
+        conn.execute_batch(
+            "INSERT INTO fx_rates (pair, rate_date, rate) VALUES ('USDBRL', '2024-05-31', '5.1');",
+        )?;
+        assert_eq!(count(&conn, snapshots), 2);
+        conn.execute("UPDATE assets SET currency = 'USD' WHERE id = 1", [])?;
+        assert_eq!(count(&conn, snapshots), 0);
+        seed()?;
+        conn.execute("UPDATE fx_rates SET rate = '5.2' WHERE pair = 'USDBRL'", [])?;
+        assert_eq!(count(&conn, snapshots), 1);
+
+        // Reclassifying an asset moves its gains to another tax category from
+        // its first trade on, now in 2023
+        seed()?;
+        conn.execute("UPDATE assets SET asset_type = 'FII' WHERE id = 1", [])?;
+        assert_eq!(count(&conn, snapshots), 0);
+        assert_eq!(count(&conn, stale_years), 2);
+
+        // Deleting the asset cascades to its trades, the earliest now in 2023
+        seed()?;
+        conn.execute("DELETE FROM assets WHERE id = 1", [])?;
+        assert_eq!(count(&conn, snapshots), 0);
+        assert_eq!(count(&conn, stale_years), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+//! Quantity precision per instrument.
+//!
+//! Shares, fund quotas, BDRs, options and term contracts trade in whole units
+//! on B3: the standard market in lots of 100, the fractional market (ticker
+//! suffix F) in 1 to 99 units, never in parts of one. Tesouro Direto sells
+//! bonds in hundredths; crypto-assets trade in any fraction. A fractional
+//! quantity where whole units are required almost always means a mis-parsed
+//! decimal separator ("1.500" read as 1.5), so such trades are flagged as
+//! INVALID_QUANTITY inconsistencies when they are inserted. Fractions left by bonuses and splits live on corporate
+//! actions; bonus shares recorded as transactions are not checked.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use serde_json::json;
+
+use super::{
+    AssetType, Inconsistency, InconsistencySeverity, InconsistencyStatus, InconsistencyType,
+    Transaction,
+};
+
+/// Smallest quantity an instrument trades in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    Whole,
+    Decimals(u32),
+    /// Not checked (fixed income recorded by amount, crypto-assets,
+    /// unclassified assets)
+    Free,
+}
+
+/// B3 listed codes: four letters and a one or two digit suffix (PETR4, HGLG11)
+fn is_listed_code(ticker: &str) -> bool {
+    if !ticker.is_ascii() {
+        return false;
+    }
+    let (letters, digits) = ticker.split_at(ticker.len().min(4));
+    letters.len() == 4
+        && letters.chars().all(|c| c.is_ascii_alphanumeric())
+        && (1..=2).contains(&digits.len())
+        && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+pub fn precision(asset_type: &AssetType, ticker: &str) -> Precision {
+    match asset_type {
+        AssetType::GovBond => Precision::Decimals(2),
+        AssetType::Bond | AssetType::Crypto => Precision::Free,
+        AssetType::Unknown if !is_listed_code(ticker) => Precision::Free,
+        _ => Precision::Whole,
+    }
+}
+
+/// Why a quantity is impossible for the instrument, with the quantity a
+/// misread thousands separator suggests
+pub fn check_quantity(
+    asset_type: &AssetType,
+    ticker: &str,
+    quantity: Decimal,
+) -> Option<(String, Option<Decimal>)> {
+    let quantity = quantity.normalize();
+    let precision = precision(asset_type, ticker);
+    let reason = match precision {
+        Precision::Whole if quantity.scale() > 0 => format!(
+            "{} trades in whole units, but the quantity is {}",
+            ticker, quantity
+        ),
+        Precision::Decimals(places) if quantity.scale() > places => format!(
+            "{} trades in steps of {}, but the quantity is {}",
+            ticker,
+            Decimal::new(1, places),
+            quantity
+        ),
+        _ => return None,
+    };
+    let thousands = quantity * Decimal::from(1000);
+    let suggestion = (precision == Precision::Whole && thousands.fract().is_zero())
+        .then(|| thousands.normalize());
+    Some((reason, suggestion))
+}
+
+/// Open an INVALID_QUANTITY inconsistency for a just-inserted transaction
+/// whose quantity its instrument cannot trade in
+pub(crate) fn flag_invalid_quantity(conn: &Connection, id: i64, tx: &Transaction) -> Result<()> {
+    let (ticker, asset_type): (String, String) = conn.query_row(
+        "SELECT ticker, asset_type FROM assets WHERE id = ?1",
+        params![tx.asset_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let asset_type = asset_type.parse().unwrap_or(AssetType::Unknown);
+    let Some((reason, suggestion)) = check_quantity(&asset_type, &ticker, tx.quantity) else {
+        return Ok(());
+    };
+    tracing::warn!("{}", reason);
+    super::insert_inconsistency(
+        conn,
+        &Inconsistency {
+            id: None,
+            issue_type: InconsistencyType::InvalidQuantity,
+            status: InconsistencyStatus::Open,
+            severity: InconsistencySeverity::Warn,
+            asset_id: Some(tx.asset_id),
+            transaction_id: None,
+            ticker: Some(ticker),
+            trade_date: Some(tx.trade_date),
+            quantity: Some(tx.quantity),
+            source: Some(tx.source.clone()),
+            source_ref: Some(format!("transaction:{}", id)),
+            missing_fields_json: None,
+            context_json: Some(
+                json!({
+                    "notes": reason,
+                    "suggested_quantity": suggestion.map(|q| q.to_string()),
+                })
+                .to_string(),
+            ),
+            resolution_action: None,
+            resolution_json: None,
+            created_at: None,
+            resolved_at: None,
+        },
+    )?;
+    Ok(())
+}
+
+/// Check an edited transaction again: its open INVALID_QUANTITY issue is
+/// resolved, and a new one opened if the quantity is still impossible
+pub(crate) fn recheck_quantity(conn: &Connection, id: i64, tx: &Transaction) -> Result<()> {
+    conn.execute(
+        "UPDATE inconsistencies
+         SET status = 'RESOLVED', resolution_action = 'UPDATE_TX', resolved_at = CURRENT_TIMESTAMP
+         WHERE issue_type = 'INVALID_QUANTITY' AND status = 'OPEN'
+           AND source_ref = 'transaction:' || ?1",
+        params![id],
+    )?;
+    flag_invalid_quantity(conn, id, tx)
+}
+
+/// Open INVALID_QUANTITY issues on the transactions an import session added
+pub fn flagged_in_session(conn: &Connection, session_id: i64) -> Result<usize> {
+    Ok(conn.query_row(
+        "SELECT COUNT(*) FROM inconsistencies
+         WHERE issue_type = 'INVALID_QUANTITY' AND status = 'OPEN'
+           AND source_ref IN (SELECT 'transaction:' || id FROM transactions
+                              WHERE import_session_id = ?1)",
+        params![session_id],
+        |row| row.get::<_, i64>(0),
+    )? as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_quantity_precision_by_instrument() {
+        assert!(check_quantity(&AssetType::Stock, "PETR4", dec!(300)).is_none());
+        assert!(check_quantity(&AssetType::GovBond, "TESOURO SELIC 2029", dec!(0.37)).is_none());
+        assert!(check_quantity(&AssetType::Bond, "CDB BANCO X", dec!(1234.5678)).is_none());
+        assert!(check_quantity(&AssetType::Unknown, "BTC", dec!(0.015)).is_none());
+
+        // "1.500" read with a decimal point
+        let (reason, suggestion) = check_quantity(&AssetType::Fii, "HGLG11", dec!(1.500)).unwrap();
+        assert_eq!(
+            reason,
+            "HGLG11 trades in whole units, but the quantity is 1.5"
+        );
+        assert_eq!(suggestion, Some(dec!(1500)));
+
+        let (_, suggestion) = check_quantity(&AssetType::Unknown, "VALE3", dec!(0.3333)).unwrap();
+        assert_eq!(suggestion, None);
+        let (reason, _) =
+            check_quantity(&AssetType::GovBond, "TESOURO IPCA+ 2035", dec!(1.125)).unwrap();
+        assert!(reason.contains("steps of 0.01"));
+    }
+}
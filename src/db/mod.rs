@@ -1,33 +1,59 @@
 // Database module - SQLite connection and models
 
+pub mod archive;
+pub mod bulk;
+pub mod encryption;
+pub mod import_session;
+pub mod invalidation;
+pub mod lot_size;
 pub mod models;
+pub mod portfolio;
+pub mod position_statements;
+pub mod sandbox;
+pub mod valuations;
 
 use anyhow::{Context, Result};
 use chrono::Datelike;
 use chrono::NaiveDate;
 use rusqlite::{params, Connection, OptionalExtension};
 use rust_decimal::Decimal;
+use std::collections::HashSet;
 use std::path::PathBuf;
 use std::str::FromStr;
 use tracing::info;
 
 use crate::term_contracts;
 pub use models::{
-    Asset, AssetExchange, AssetExchangeType, AssetRegistryEntry, AssetRename, AssetType,
-    CorporateAction, CorporateActionType, GovBondRate, IncomeEvent, IncomeEventType, Inconsistency,
-    InconsistencySeverity, InconsistencyStatus, InconsistencyType, PriceHistory, Transaction,
-    TransactionType,
+    Asset, AssetExchange, AssetExchangeType, AssetIssuer, AssetRegistryEntry, AssetRename,
+    AssetType, Benchmark, BenchmarkValue, BrokerNote, CorporateAction, CorporateActionType, FiiNav,
+    GovBondRate, IncomeEvent, IncomeEventType, Inconsistency, InconsistencySeverity,
+    InconsistencyStatus, InconsistencyType, JournalEntry, Portfolio, PriceHistory, PriceSeries,
+    PriceSnapshot, Transaction, TransactionType,
 };
 
-/// Get the default database path (~/.interest/data.db)
-pub fn get_default_db_path() -> Result<PathBuf> {
-    let home = std::env::var("HOME").context("HOME environment variable not set")?;
-    let interest_dir = PathBuf::from(home).join(".interest");
+const DB_FILENAME: &str = "data.db";
+
+/// Get the data directory (~/.interest), creating it if needed
+pub fn get_interest_dir() -> Result<PathBuf> {
+    let interest_dir = crate::utils::home_dir()?.join(".interest");
 
     // Create directory if it doesn't exist
     std::fs::create_dir_all(&interest_dir).context("Failed to create .interest directory")?;
 
-    Ok(interest_dir.join("data.db"))
+    Ok(interest_dir)
+}
+
+/// Get the default database path (~/.interest/data.db, the sandbox copy
+/// when running with --sandbox, or the decrypted working copy when the
+/// database is encrypted)
+pub fn get_default_db_path() -> Result<PathBuf> {
+    if sandbox::is_active() {
+        return sandbox::sandbox_path();
+    }
+    if let Some(working) = encryption::working_path() {
+        return Ok(working);
+    }
+    Ok(get_interest_dir()?.join(DB_FILENAME))
 }
 
 /// Open database connection
@@ -39,6 +65,16 @@ pub fn open_db(db_path: Option<PathBuf>) -> Result<Connection> {
     conn.execute("PRAGMA foreign_keys = ON", [])
         .context("Failed to enable foreign keys")?;
 
+    // The unused realized_gains table gave its name to a view
+    let legacy_table: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'realized_gains')",
+        [],
+        |row| row.get(0),
+    )?;
+    if legacy_table {
+        conn.execute_batch("DROP TABLE realized_gains")?;
+    }
+
     // Apply schema updates (idempotent) to ensure new tables exist.
     let schema_sql = include_str!("schema.sql");
     conn.execute_batch(schema_sql)
@@ -82,16 +118,138 @@ pub fn init_database(db_path: Option<PathBuf>) -> Result<()> {
     conn.execute_batch(schema_sql)
         .context("Failed to execute schema")?;
 
+    // Columns added after a table was first created
+    ensure_column(&conn, "price_history", "adjusted_close", "DECIMAL(15,4)")?;
+    ensure_column(
+        &conn,
+        "transactions",
+        "portfolio_id",
+        "INTEGER NOT NULL DEFAULT 1",
+    )?;
+    ensure_column(
+        &conn,
+        "income_events",
+        "portfolio_id",
+        "INTEGER NOT NULL DEFAULT 1",
+    )?;
+    ensure_column(&conn, "transactions", "broker_id", "INTEGER")?;
+    ensure_column(&conn, "income_events", "broker_id", "INTEGER")?;
+    ensure_column(&conn, "portfolios", "declarant", "TEXT")?;
+    ensure_column(&conn, "position_snapshots", "valued_on", "DATE")?;
+    ensure_column(&conn, "position_snapshots", "total_cost", "DECIMAL(15,4)")?;
+    ensure_column(&conn, "position_snapshots", "fx_currency", "TEXT")?;
+    ensure_column(&conn, "position_snapshots", "fx_rate", "DECIMAL(15,6)")?;
+    ensure_column(&conn, "position_snapshots", "fx_date", "DATE")?;
+    ensure_column(&conn, "assets", "currency", "TEXT")?;
+    ensure_column(
+        &conn,
+        "income_events",
+        "foreign_tax_withheld",
+        "DECIMAL(15,4)",
+    )?;
+    for table in [
+        "transactions",
+        "corporate_actions",
+        "income_events",
+        "broker_notes",
+        "cash_credits",
+    ] {
+        ensure_column(&conn, table, "import_session_id", "INTEGER")?;
+    }
+    if ensure_column(&conn, "corporate_actions", "applied_at", "DATETIME")? {
+        // Actions recorded before review existed were already in effect, except
+        // bonuses never applied into their zero-cost transaction
+        conn.execute(
+            "UPDATE corporate_actions SET applied_at = COALESCE(created_at, CURRENT_TIMESTAMP)
+             WHERE action_type != 'BONUS'
+                OR EXISTS (SELECT 1 FROM transactions t
+                           WHERE t.asset_id = corporate_actions.asset_id
+                             AND t.trade_date = corporate_actions.ex_date
+                             AND t.source = 'CORPORATE_ACTION')",
+            [],
+        )?;
+    }
+    migrate_bonus_transactions(&conn)?;
+    invalidation::install(&conn)?;
+
     info!("Database initialized successfully");
     Ok(())
 }
 
+/// Applying a bonus used to insert its shares as a zero-cost BUY; bonuses now
+/// take effect at query time like splits, so those rows would count the
+/// shares twice. Each is removed once its bonus action is marked applied
+/// (which is what it stood for); rows with no matching action are left as
+/// ordinary trades.
+fn migrate_bonus_transactions(conn: &Connection) -> Result<()> {
+    const MATCHES_BONUS: &str = "ca.action_type = 'BONUS'
+          AND ca.asset_id = t.asset_id
+          AND ca.ex_date = t.trade_date
+          AND CAST(ca.quantity_adjustment AS REAL) = CAST(t.quantity AS REAL)";
+    conn.execute(
+        &format!(
+            "UPDATE corporate_actions AS ca
+             SET applied_at = COALESCE(created_at, CURRENT_TIMESTAMP)
+             WHERE ca.applied_at IS NULL
+               AND EXISTS (SELECT 1 FROM transactions t
+                           WHERE t.source = 'CORPORATE_ACTION'
+                             AND t.transaction_type = 'BUY' AND {MATCHES_BONUS})"
+        ),
+        [],
+    )?;
+    let removed = conn.execute(
+        &format!(
+            "DELETE FROM transactions AS t
+             WHERE t.source = 'CORPORATE_ACTION' AND t.transaction_type = 'BUY'
+               AND EXISTS (SELECT 1 FROM corporate_actions ca
+                           WHERE ca.applied_at IS NOT NULL AND {MATCHES_BONUS})"
+        ),
+        [],
+    )?;
+    if removed > 0 {
+        info!(
+            "Replaced {} bonus transaction(s) with their corporate actions",
+            removed
+        );
+    }
+    Ok(())
+}
+
+/// Add a column to an existing table unless it is already there; true if added
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<bool> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+        params![table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        info!("Adding column {}.{}", table, column);
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, decl
+        ))?;
+    }
+    Ok(!exists)
+}
+
 /// Insert or get asset, returns asset_id
 pub fn upsert_asset(
+    conn: &Connection,
+    ticker: &str,
+    asset_type: &AssetType,
+    name: Option<&str>,
+) -> Result<i64> {
+    upsert_asset_as_of(conn, ticker, asset_type, name, None)
+}
+
+/// Insert or get asset, classifying a new one with the B3 instruments file
+/// current on `as_of` (e.g. the trade date) when given.
+pub fn upsert_asset_as_of(
     conn: &Connection,
     ticker: &str,
     _asset_type: &AssetType,
     name: Option<&str>,
+    as_of: Option<NaiveDate>,
 ) -> Result<i64> {
     // Try to find existing asset
     let mut stmt = conn.prepare("SELECT id FROM assets WHERE ticker = ?1")?;
@@ -101,7 +259,11 @@ pub fn upsert_asset(
         return Ok(id);
     }
 
-    let resolved_type = match crate::tickers::resolve_asset_type_with_name(ticker, name) {
+    let resolved = match as_of {
+        Some(date) => crate::tickers::resolve_asset_type_as_of(conn, ticker, name, date),
+        None => crate::tickers::resolve_asset_type_with_name(conn, ticker, name),
+    };
+    let resolved_type = match resolved {
         Ok(Some(asset_type)) => asset_type,
         Ok(None) => AssetType::Unknown,
         Err(err) => {
@@ -123,6 +285,14 @@ pub fn upsert_asset(
     } else {
         (resolved_type, name.map(|s| s.to_string()), None)
     };
+    // Delisted or renamed tickers still carry their issuer name in the era's file
+    let final_name = match (final_name, as_of) {
+        (None, Some(date)) => crate::tickers::lookup_record_as_of(ticker, None, date)
+            .ok()
+            .flatten()
+            .and_then(|record| record.corporate_name),
+        (name, _) => name,
+    };
 
     // Insert new asset
     conn.execute(
@@ -350,32 +520,146 @@ pub fn count_transactions_for_asset(conn: &Connection, ticker: &str) -> Result<i
     Ok(count)
 }
 
-/// Get earliest transaction date for an asset ticker
-pub fn get_earliest_transaction_date_for_asset(
+pub(crate) const INSERT_TRANSACTION_SQL: &str = "INSERT INTO transactions (
+            asset_id, transaction_type, trade_date, settlement_date,
+            quantity, price_per_unit, total_cost, fees,
+            is_day_trade, quota_issuance_date, notes, source, portfolio_id, import_session_id
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)";
+
+/// Insert transaction
+pub fn insert_transaction(conn: &Connection, tx: &Transaction) -> Result<i64> {
+    conn.prepare_cached(INSERT_TRANSACTION_SQL)?
+        .execute(params![
+            tx.asset_id,
+            tx.transaction_type.as_str(),
+            tx.trade_date,
+            tx.settlement_date,
+            tx.quantity.to_string(),
+            tx.price_per_unit.to_string(),
+            tx.total_cost.to_string(),
+            tx.fees.to_string(),
+            tx.is_day_trade,
+            tx.quota_issuance_date,
+            tx.notes,
+            tx.source,
+            portfolio::write_target(),
+            import_session::current(),
+        ])?;
+
+    let id = conn.last_insert_rowid();
+    lot_size::flag_invalid_quantity(conn, id, tx)?;
+    Ok(id)
+}
+
+/// Broker id for an institution name, registering it on first use
+pub fn upsert_broker(conn: &Connection, name: &str) -> Result<i64> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        anyhow::bail!("Broker name is empty");
+    }
+    conn.execute(
+        "INSERT OR IGNORE INTO brokers (name) VALUES (?1)",
+        params![name],
+    )?;
+    Ok(conn.query_row(
+        "SELECT id FROM brokers WHERE name = ?1",
+        params![name],
+        |row| row.get(0),
+    )?)
+}
+
+/// Whether a brokerage note was already imported
+pub fn broker_note_exists(
     conn: &Connection,
-    ticker: &str,
-) -> Result<Option<NaiveDate>> {
-    let date: Option<NaiveDate> = conn
+    broker_id: Option<i64>,
+    note_number: &str,
+    trade_date: NaiveDate,
+) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM broker_notes
+         WHERE broker_id IS ?1 AND note_number = ?2 AND trade_date = ?3)",
+        params![broker_id, note_number, trade_date],
+        |row| row.get(0),
+    )?)
+}
+
+/// Insert a brokerage note's summary
+pub fn insert_broker_note(conn: &Connection, note: &BrokerNote) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO broker_notes (
+            broker_id, note_number, trade_date, settlement_date, settlement_fee,
+            registration_fee, emolumentos, brokerage, iss, other_fees, irrf,
+            irrf_day_trade, net_amount, portfolio_id, import_session_id
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        params![
+            note.broker_id,
+            note.note_number,
+            note.trade_date,
+            note.settlement_date,
+            note.settlement_fee.to_string(),
+            note.registration_fee.to_string(),
+            note.emolumentos.to_string(),
+            note.brokerage.to_string(),
+            note.iss.to_string(),
+            note.other_fees.to_string(),
+            note.irrf.to_string(),
+            note.irrf_day_trade.to_string(),
+            note.net_amount.map(|d| d.to_string()),
+            portfolio::write_target(),
+            import_session::current(),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Record the broker a transaction was made at
+pub fn set_transaction_broker(
+    conn: &Connection,
+    transaction_id: i64,
+    broker_id: i64,
+) -> Result<()> {
+    conn.execute(
+        "UPDATE transactions SET broker_id = ?1 WHERE id = ?2",
+        params![broker_id, transaction_id],
+    )?;
+    Ok(())
+}
+
+pub fn set_income_event_broker(conn: &Connection, event_id: i64, broker_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE income_events SET broker_id = ?1 WHERE id = ?2",
+        params![broker_id, event_id],
+    )?;
+    Ok(())
+}
+
+/// Get a single transaction by id
+pub fn get_transaction(conn: &Connection, id: i64) -> Result<Option<Transaction>> {
+    let tx = conn
         .query_row(
-            "SELECT MIN(t.trade_date) FROM transactions t
-             JOIN assets a ON t.asset_id = a.id
-             WHERE a.ticker = ?1",
-            [ticker.to_uppercase()],
-            |row| row.get(0),
+            "SELECT id, asset_id, transaction_type, trade_date, settlement_date,
+                    quantity, price_per_unit, total_cost, fees, is_day_trade,
+                    quota_issuance_date, notes, source, created_at
+             FROM transactions
+             WHERE id = ?1",
+            params![id],
+            map_transaction_row,
         )
         .optional()?;
-    Ok(date)
+
+    Ok(tx)
 }
 
-/// Insert transaction
-pub fn insert_transaction(conn: &Connection, tx: &Transaction) -> Result<i64> {
-    conn.execute(
-        "INSERT INTO transactions (
-            asset_id, transaction_type, trade_date, settlement_date,
-            quantity, price_per_unit, total_cost, fees,
-            is_day_trade, quota_issuance_date, notes, source
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+/// Overwrite the editable fields of a transaction; returns the rows changed
+pub fn update_transaction(conn: &Connection, id: i64, tx: &Transaction) -> Result<usize> {
+    let count = conn.execute(
+        "UPDATE transactions SET
+             asset_id = ?2, transaction_type = ?3, trade_date = ?4, settlement_date = ?5,
+             quantity = ?6, price_per_unit = ?7, total_cost = ?8, fees = ?9,
+             is_day_trade = ?10, notes = ?11
+         WHERE id = ?1",
         params![
+            id,
             tx.asset_id,
             tx.transaction_type.as_str(),
             tx.trade_date,
@@ -385,13 +669,53 @@ pub fn insert_transaction(conn: &Connection, tx: &Transaction) -> Result<i64> {
             tx.total_cost.to_string(),
             tx.fees.to_string(),
             tx.is_day_trade,
-            tx.quota_issuance_date,
             tx.notes,
-            tx.source,
         ],
     )?;
+    Ok(count)
+}
 
-    Ok(conn.last_insert_rowid())
+/// Delete a transaction with the cash flows and issues recorded against it;
+/// open issues that name it by reference are resolved as gone
+pub fn delete_transaction(conn: &Connection, id: i64) -> Result<usize> {
+    bulk::in_transaction(conn, |conn| {
+        // Neither table cascades
+        for table in ["cash_flows", "inconsistencies"] {
+            conn.execute(
+                &format!("DELETE FROM {} WHERE transaction_id = ?1", table),
+                params![id],
+            )?;
+        }
+        conn.execute(
+            "UPDATE inconsistencies
+             SET status = 'RESOLVED', resolution_action = 'GONE', resolved_at = CURRENT_TIMESTAMP
+             WHERE status = 'OPEN' AND source_ref = 'transaction:' || ?1",
+            params![id],
+        )?;
+        Ok(conn.execute("DELETE FROM transactions WHERE id = ?1", params![id])?)
+    })
+}
+
+fn map_transaction_row(row: &rusqlite::Row) -> rusqlite::Result<Transaction> {
+    Ok(Transaction {
+        id: Some(row.get(0)?),
+        asset_id: row.get(1)?,
+        transaction_type: row
+            .get::<_, String>(2)?
+            .parse::<TransactionType>()
+            .unwrap_or(TransactionType::Buy),
+        trade_date: row.get(3)?,
+        settlement_date: row.get(4)?,
+        quantity: get_decimal_value(row, 5)?,
+        price_per_unit: get_decimal_value(row, 6)?,
+        total_cost: get_decimal_value(row, 7)?,
+        fees: get_decimal_value(row, 8)?,
+        is_day_trade: row.get(9)?,
+        quota_issuance_date: row.get(10)?,
+        notes: row.get(11)?,
+        source: row.get::<_, Option<String>>(12)?.unwrap_or_default(),
+        created_at: row.get(13)?,
+    })
 }
 
 /// Insert inconsistency record
@@ -659,13 +983,15 @@ pub fn set_last_import_date(
     Ok(())
 }
 
+pub(crate) const INSERT_PRICE_HISTORY_SQL: &str = "INSERT OR REPLACE INTO price_history (
+            asset_id, price_date, close_price, open_price, high_price, low_price, volume, source,
+            adjusted_close
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)";
+
 /// Insert price history
 pub fn insert_price_history(conn: &Connection, price: &PriceHistory) -> Result<i64> {
-    conn.execute(
-        "INSERT OR REPLACE INTO price_history (
-            asset_id, price_date, close_price, open_price, high_price, low_price, volume, source
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![
+    conn.prepare_cached(INSERT_PRICE_HISTORY_SQL)?
+        .execute(params![
             price.asset_id,
             price.price_date,
             price.close_price.to_string(),
@@ -674,10 +1000,74 @@ pub fn insert_price_history(conn: &Connection, price: &PriceHistory) -> Result<i
             price.low_price.as_ref().map(|d| d.to_string()),
             price.volume,
             price.source,
+            price.adjusted_close.as_ref().map(|d| d.to_string()),
+        ])?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+/// Attach adjusted closes to existing daily rows, returning how many were updated.
+///
+/// Raw closes are left untouched: sources that only provide split-adjusted
+/// history must not overwrite the as-traded series used for valuation.
+pub fn set_adjusted_closes(
+    conn: &Connection,
+    asset_id: i64,
+    closes: &[(NaiveDate, Decimal)],
+) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    let mut updated = 0;
+    {
+        let mut stmt = tx.prepare(
+            "UPDATE price_history SET adjusted_close = ?3 WHERE asset_id = ?1 AND price_date = ?2",
+        )?;
+        for (date, adjusted) in closes {
+            updated += stmt.execute(params![asset_id, date, adjusted.to_string()])?;
+        }
+    }
+    tx.commit()?;
+    Ok(updated)
+}
+
+/// Record an intraday quote
+pub fn insert_price_snapshot(conn: &Connection, snapshot: &PriceSnapshot) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO price_snapshots (asset_id, snapshot_at, price, source)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            snapshot.asset_id,
+            snapshot.snapshot_at,
+            snapshot.price.to_string(),
+            snapshot.source,
         ],
     )?;
+    Ok(())
+}
 
-    Ok(conn.last_insert_rowid())
+/// Latest intraday quote for an asset captured on the given (UTC) date
+pub fn get_latest_price_snapshot(
+    conn: &Connection,
+    asset_id: i64,
+    date: NaiveDate,
+) -> Result<Option<PriceSnapshot>> {
+    let snapshot = conn
+        .query_row(
+            "SELECT asset_id, snapshot_at, price, source FROM price_snapshots
+             WHERE asset_id = ?1 AND date(snapshot_at) = ?2
+             ORDER BY snapshot_at DESC
+             LIMIT 1",
+            params![asset_id, date],
+            |row| {
+                Ok(PriceSnapshot {
+                    asset_id: row.get(0)?,
+                    snapshot_at: row.get(1)?,
+                    price: get_decimal_value(row, 2)?,
+                    source: row.get::<_, Option<String>>(3)?.unwrap_or_default(),
+                })
+            },
+        )
+        .optional()?;
+    Ok(snapshot)
 }
 
 /// Insert government bond rate history
@@ -697,9 +1087,497 @@ pub fn insert_gov_bond_rate(conn: &Connection, rate: &GovBondRate) -> Result<i64
     Ok(conn.last_insert_rowid())
 }
 
-/// Filter tickers unsupported in portfolio/tax (e.g., options like ITSAA101).
+/// Latest Tesouro sell rate stored on or before a date
+pub fn get_gov_bond_rate_on_or_before(
+    conn: &Connection,
+    asset_id: i64,
+    as_of_date: NaiveDate,
+) -> Result<Option<GovBondRate>> {
+    let rate = conn
+        .query_row(
+            "SELECT id, asset_id, price_date, sell_rate, source, created_at
+             FROM gov_bond_rates
+             WHERE asset_id = ?1 AND price_date <= ?2
+             ORDER BY price_date DESC
+             LIMIT 1",
+            params![asset_id, as_of_date],
+            |row| {
+                Ok(GovBondRate {
+                    id: Some(row.get(0)?),
+                    asset_id: row.get(1)?,
+                    price_date: row.get(2)?,
+                    sell_rate: get_decimal_value(row, 3)?,
+                    source: row.get(4)?,
+                    created_at: row.get(5)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(rate)
+}
+
+/// Insert or replace a benchmark series value
+pub fn insert_benchmark_value(conn: &Connection, value: &BenchmarkValue) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO benchmark_history (benchmark, value_date, value, source)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            value.benchmark.as_str(),
+            value.value_date,
+            value.value.to_string(),
+            value.source,
+        ],
+    )?;
+
+    Ok(())
+}
+
+/// Get benchmark values within a date range (inclusive), ordered by date
+pub fn get_benchmark_values(
+    conn: &Connection,
+    benchmark: Benchmark,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+) -> Result<Vec<BenchmarkValue>> {
+    let mut stmt = conn.prepare(
+        "SELECT value_date, value, source
+         FROM benchmark_history
+         WHERE benchmark = ?1 AND value_date >= ?2 AND value_date <= ?3
+         ORDER BY value_date ASC",
+    )?;
+
+    let values = stmt
+        .query_map(params![benchmark.as_str(), from_date, to_date], |row| {
+            Ok(BenchmarkValue {
+                benchmark,
+                value_date: row.get(0)?,
+                value: get_decimal_value(row, 1)?,
+                source: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(values)
+}
+
+/// Get the latest benchmark value on or before a given date
+pub fn get_benchmark_value_on_or_before(
+    conn: &Connection,
+    benchmark: Benchmark,
+    as_of_date: NaiveDate,
+) -> Result<Option<BenchmarkValue>> {
+    let mut stmt = conn.prepare(
+        "SELECT value_date, value, source
+         FROM benchmark_history
+         WHERE benchmark = ?1 AND value_date <= ?2
+         ORDER BY value_date DESC
+         LIMIT 1",
+    )?;
+
+    let value = stmt
+        .query_row(params![benchmark.as_str(), as_of_date], |row| {
+            Ok(BenchmarkValue {
+                benchmark,
+                value_date: row.get(0)?,
+                value: get_decimal_value(row, 1)?,
+                source: row.get::<_, Option<String>>(2)?.unwrap_or_default(),
+            })
+        })
+        .optional()?;
+
+    Ok(value)
+}
+
+/// Get the most recent stored date for a benchmark
+pub fn get_latest_benchmark_date(
+    conn: &Connection,
+    benchmark: Benchmark,
+) -> Result<Option<NaiveDate>> {
+    let mut stmt =
+        conn.prepare("SELECT MAX(value_date) FROM benchmark_history WHERE benchmark = ?1")?;
+    let date: Option<NaiveDate> = stmt.query_row([benchmark.as_str()], |row| row.get(0))?;
+    Ok(date)
+}
+
+/// Insert or replace an FX rate for a currency pair
+pub fn insert_fx_rate(
+    conn: &Connection,
+    pair: &str,
+    rate_date: NaiveDate,
+    rate: Decimal,
+    source: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO fx_rates (pair, rate_date, rate, source)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![pair, rate_date, rate.to_string(), source],
+    )?;
+
+    Ok(())
+}
+
+/// Get the latest FX rate on or before a given date, with its actual date
+pub fn get_fx_rate_on_or_before(
+    conn: &Connection,
+    pair: &str,
+    as_of_date: NaiveDate,
+) -> Result<Option<(NaiveDate, Decimal)>> {
+    let mut stmt = conn.prepare(
+        "SELECT rate_date, rate
+         FROM fx_rates
+         WHERE pair = ?1 AND rate_date <= ?2
+         ORDER BY rate_date DESC
+         LIMIT 1",
+    )?;
+
+    let rate = stmt
+        .query_row(params![pair, as_of_date], |row| {
+            Ok((row.get(0)?, get_decimal_value(row, 1)?))
+        })
+        .optional()?;
+
+    Ok(rate)
+}
+
+/// Currency an asset's prices are quoted in, None for BRL
+pub fn get_asset_currency(conn: &Connection, asset_id: i64) -> Result<Option<String>> {
+    let currency: Option<Option<String>> = conn
+        .query_row(
+            "SELECT currency FROM assets WHERE id = ?1",
+            [asset_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(currency.flatten())
+}
+
+/// Set the currency an asset's prices are quoted in (None for BRL)
+pub fn update_asset_currency(
+    conn: &Connection,
+    ticker: &str,
+    currency: Option<&str>,
+) -> Result<()> {
+    let count = conn.execute(
+        "UPDATE assets SET currency = ?1, updated_at = CURRENT_TIMESTAMP WHERE ticker = ?2",
+        params![currency, ticker.to_uppercase()],
+    )?;
+    if count == 0 {
+        return Err(anyhow::anyhow!("Ticker {} not found in assets", ticker));
+    }
+    Ok(())
+}
+
+/// Foreign currencies assets are quoted in
+pub fn get_asset_currencies(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT currency FROM assets WHERE currency IS NOT NULL ORDER BY currency",
+    )?;
+    let currencies = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(currencies)
+}
+
+/// Ids of the assets quoted in a foreign currency, held abroad
+pub fn get_assets_quoted_abroad(conn: &Connection) -> Result<std::collections::HashSet<i64>> {
+    let mut stmt = conn.prepare("SELECT id FROM assets WHERE currency IS NOT NULL")?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    Ok(ids)
+}
+
+/// Get the most recent stored date for a currency pair
+pub fn get_latest_fx_date(conn: &Connection, pair: &str) -> Result<Option<NaiveDate>> {
+    let mut stmt = conn.prepare("SELECT MAX(rate_date) FROM fx_rates WHERE pair = ?1")?;
+    let date: Option<NaiveDate> = stmt.query_row([pair], |row| row.get(0))?;
+    Ok(date)
+}
+
+/// Insert a trade idea journal entry
+pub fn insert_journal_entry(conn: &Connection, entry: &JournalEntry) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO journal_entries (asset_id, entry_date, thesis, target_price)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            entry.asset_id,
+            entry.entry_date,
+            entry.thesis,
+            entry.target_price.map(|p| p.to_string())
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid())
+}
+
+fn map_journal_entry_row(row: &rusqlite::Row) -> rusqlite::Result<JournalEntry> {
+    let target_price = match row.get_ref(4)? {
+        rusqlite::types::ValueRef::Null => None,
+        _ => Some(get_decimal_value(row, 4)?),
+    };
+    Ok(JournalEntry {
+        id: Some(row.get(0)?),
+        asset_id: row.get(1)?,
+        entry_date: row.get(2)?,
+        thesis: row.get(3)?,
+        target_price,
+        created_at: row.get(5)?,
+    })
+}
+
+/// Get a journal entry by id
+pub fn get_journal_entry(conn: &Connection, id: i64) -> Result<Option<JournalEntry>> {
+    let entry = conn
+        .query_row(
+            "SELECT id, asset_id, entry_date, thesis, target_price, created_at
+             FROM journal_entries
+             WHERE id = ?1",
+            params![id],
+            map_journal_entry_row,
+        )
+        .optional()?;
+
+    Ok(entry)
+}
+
+/// List journal entries with their tickers, optionally filtered by ticker
+pub fn list_journal_entries(
+    conn: &Connection,
+    ticker: Option<&str>,
+) -> Result<Vec<(JournalEntry, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT j.id, j.asset_id, j.entry_date, j.thesis, j.target_price, j.created_at, a.ticker
+         FROM journal_entries j
+         JOIN assets a ON j.asset_id = a.id
+         WHERE ?1 IS NULL OR a.ticker = ?1
+         ORDER BY j.entry_date ASC, j.id ASC",
+    )?;
+
+    let entries = stmt
+        .query_map(params![ticker.map(|t| t.to_uppercase())], |row| {
+            Ok((map_journal_entry_row(row)?, row.get(6)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(entries)
+}
+
+/// Link an executed transaction to a journal entry (idempotent)
+pub fn link_journal_transaction(
+    conn: &Connection,
+    entry_id: i64,
+    transaction_id: i64,
+) -> Result<bool> {
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO journal_links (entry_id, transaction_id) VALUES (?1, ?2)",
+        params![entry_id, transaction_id],
+    )?;
+
+    Ok(inserted > 0)
+}
+
+/// Get the transactions linked to a journal entry, in trade order
+pub fn get_journal_transactions(conn: &Connection, entry_id: i64) -> Result<Vec<Transaction>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.asset_id, t.transaction_type, t.trade_date, t.settlement_date,
+                t.quantity, t.price_per_unit, t.total_cost, t.fees, t.is_day_trade,
+                t.quota_issuance_date, t.notes, t.source, t.created_at
+         FROM journal_links l
+         JOIN transactions t ON l.transaction_id = t.id
+         WHERE l.entry_id = ?1
+         ORDER BY t.trade_date ASC, t.id ASC",
+    )?;
+
+    let transactions = stmt
+        .query_map(params![entry_id], map_transaction_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(transactions)
+}
+
+/// Normalize a user tag: trimmed and lowercased so "Reserva" and "reserva" match
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim().to_lowercase();
+    if tag.is_empty() {
+        return Err(anyhow::anyhow!("Tag cannot be empty"));
+    }
+    Ok(tag)
+}
+
+/// Tag an asset (idempotent); returns whether the tag was newly added
+pub fn add_asset_tag(conn: &Connection, asset_id: i64, tag: &str) -> Result<bool> {
+    let tag = normalize_tag(tag)?;
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO asset_tags (asset_id, tag) VALUES (?1, ?2)",
+        params![asset_id, tag],
+    )?;
+
+    Ok(inserted > 0)
+}
+
+/// Remove a tag from an asset; returns whether it was present
+pub fn remove_asset_tag(conn: &Connection, asset_id: i64, tag: &str) -> Result<bool> {
+    let tag = normalize_tag(tag)?;
+    let deleted = conn.execute(
+        "DELETE FROM asset_tags WHERE asset_id = ?1 AND tag = ?2",
+        params![asset_id, tag],
+    )?;
+
+    Ok(deleted > 0)
+}
+
+/// Get the tags of a single asset, sorted
+pub fn get_asset_tags(conn: &Connection, asset_id: i64) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT tag FROM asset_tags WHERE asset_id = ?1 ORDER BY tag")?;
+    let tags = stmt
+        .query_map(params![asset_id], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+
+    Ok(tags)
+}
+
+/// List every tag with the tickers that carry it, sorted by tag and ticker
+pub fn list_asset_tags(conn: &Connection) -> Result<Vec<(String, Vec<String>)>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.tag, a.ticker
+         FROM asset_tags t
+         JOIN assets a ON t.asset_id = a.id
+         ORDER BY t.tag, a.ticker",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tags: Vec<(String, Vec<String>)> = Vec::new();
+    for (tag, ticker) in rows {
+        match tags.last_mut() {
+            Some((last, tickers)) if *last == tag => tickers.push(ticker),
+            _ => tags.push((tag, vec![ticker])),
+        }
+    }
+
+    Ok(tags)
+}
+
+/// Get the ids of the assets carrying a tag; errors if no asset has it
+pub fn get_tagged_asset_ids(conn: &Connection, tag: &str) -> Result<HashSet<i64>> {
+    let tag = normalize_tag(tag)?;
+    let mut stmt = conn.prepare("SELECT asset_id FROM asset_tags WHERE tag = ?1")?;
+    let ids = stmt
+        .query_map(params![tag], |row| row.get(0))?
+        .collect::<Result<HashSet<i64>, _>>()?;
+
+    if ids.is_empty() {
+        return Err(anyhow::anyhow!(
+            "No assets tagged '{}'. Tag assets with: interest assets tag <ticker> {}",
+            tag,
+            tag
+        ));
+    }
+
+    Ok(ids)
+}
+
+/// Store (or replace) the registry data of an asset's issuer
+pub fn upsert_asset_issuer(conn: &Connection, issuer: &AssetIssuer) -> Result<()> {
+    conn.execute(
+        "INSERT INTO asset_issuers
+            (asset_id, cnpj, legal_name, trade_name, situation, situation_date, source, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, CURRENT_TIMESTAMP)
+         ON CONFLICT(asset_id) DO UPDATE SET
+            cnpj = excluded.cnpj,
+            legal_name = excluded.legal_name,
+            trade_name = excluded.trade_name,
+            situation = excluded.situation,
+            situation_date = excluded.situation_date,
+            source = excluded.source,
+            fetched_at = excluded.fetched_at",
+        params![
+            issuer.asset_id,
+            issuer.cnpj,
+            issuer.legal_name,
+            issuer.trade_name,
+            issuer.situation,
+            issuer.situation_date,
+            issuer.source
+        ],
+    )?;
+    Ok(())
+}
+
+/// Get the stored issuer data of an asset
+pub fn get_asset_issuer(conn: &Connection, asset_id: i64) -> Result<Option<AssetIssuer>> {
+    let issuer = conn
+        .query_row(
+            "SELECT asset_id, cnpj, legal_name, trade_name, situation, situation_date, source,
+                    fetched_at
+             FROM asset_issuers WHERE asset_id = ?1",
+            params![asset_id],
+            |row| {
+                Ok(AssetIssuer {
+                    asset_id: row.get(0)?,
+                    cnpj: row.get(1)?,
+                    legal_name: row.get(2)?,
+                    trade_name: row.get(3)?,
+                    situation: row.get(4)?,
+                    situation_date: row.get(5)?,
+                    source: row.get(6)?,
+                    fetched_at: row.get(7)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(issuer)
+}
+
+/// Insert or replace the NAV per quota of an FII for a reference month
+pub fn upsert_fii_nav(conn: &Connection, nav: &FiiNav) -> Result<()> {
+    conn.execute(
+        "INSERT INTO fii_nav_history (asset_id, reference_date, nav_per_quota, net_assets, quotas)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(asset_id, reference_date) DO UPDATE SET
+            nav_per_quota = excluded.nav_per_quota,
+            net_assets = excluded.net_assets,
+            quotas = excluded.quotas,
+            fetched_at = CURRENT_TIMESTAMP",
+        params![
+            nav.asset_id,
+            nav.reference_date,
+            nav.nav_per_quota.to_string(),
+            nav.net_assets.map(|d| d.to_string()),
+            nav.quotas.map(|d| d.to_string()),
+        ],
+    )?;
+    Ok(())
+}
+
+/// NAV history of an FII, oldest first
+pub fn get_fii_nav_history(conn: &Connection, asset_id: i64) -> Result<Vec<FiiNav>> {
+    let mut stmt = conn.prepare(
+        "SELECT asset_id, reference_date, nav_per_quota, net_assets, quotas
+         FROM fii_nav_history
+         WHERE asset_id = ?1
+         ORDER BY reference_date ASC",
+    )?;
+    let history = stmt
+        .query_map(params![asset_id], |row| {
+            Ok(FiiNav {
+                asset_id: row.get(0)?,
+                reference_date: row.get(1)?,
+                nav_per_quota: get_decimal_value(row, 2)?,
+                net_assets: get_optional_decimal_value(row, 3)?,
+                quotas: get_optional_decimal_value(row, 4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(history)
+}
+
+/// Filter tickers unsupported in portfolio/tax (e.g., term contracts like PETR4T).
 pub fn is_supported_portfolio_ticker(ticker: &str) -> bool {
-    ticker.len() <= 6
+    (ticker.len() <= 6 || crate::options::is_option_ticker(ticker))
         && !term_contracts::is_term_contract(ticker)
         && !is_follow_on_option_ticker(ticker)
 }
@@ -1088,7 +1966,7 @@ pub fn get_asset_exchanges_as_target_up_to(
 /// Get latest price for an asset
 pub fn get_latest_price(conn: &Connection, asset_id: i64) -> Result<Option<PriceHistory>> {
     let mut stmt = conn.prepare(
-        "SELECT id, asset_id, price_date, close_price, open_price, high_price, low_price, volume, source, created_at
+        "SELECT id, asset_id, price_date, close_price, open_price, high_price, low_price, volume, source, created_at, adjusted_close
          FROM price_history
          WHERE asset_id = ?1
          ORDER BY price_date DESC
@@ -1108,6 +1986,7 @@ pub fn get_latest_price(conn: &Connection, asset_id: i64) -> Result<Option<Price
                 volume: row.get(7)?,
                 source: row.get(8)?,
                 created_at: row.get(9)?,
+                adjusted_close: get_optional_decimal_value(row, 10)?,
             })
         })
         .optional()?;
@@ -1115,6 +1994,15 @@ pub fn get_latest_price(conn: &Connection, asset_id: i64) -> Result<Option<Price
     Ok(result)
 }
 
+/// When prices were last written, across all assets (UTC)
+pub fn get_latest_price_update(conn: &Connection) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+    let updated: Option<chrono::DateTime<chrono::Utc>> =
+        conn.query_row("SELECT MAX(created_at) FROM price_history", [], |row| {
+            row.get(0)
+        })?;
+    Ok(updated)
+}
+
 /// Get the latest price on or before a given date
 pub fn get_price_on_or_before(
     conn: &Connection,
@@ -1122,7 +2010,7 @@ pub fn get_price_on_or_before(
     as_of_date: NaiveDate,
 ) -> Result<Option<PriceHistory>> {
     let mut stmt = conn.prepare(
-        "SELECT id, asset_id, price_date, close_price, open_price, high_price, low_price, volume, source, created_at
+        "SELECT id, asset_id, price_date, close_price, open_price, high_price, low_price, volume, source, created_at, adjusted_close
          FROM price_history
          WHERE asset_id = ?1 AND price_date <= ?2
          ORDER BY price_date DESC
@@ -1142,6 +2030,7 @@ pub fn get_price_on_or_before(
                 volume: row.get(7)?,
                 source: row.get(8)?,
                 created_at: row.get(9)?,
+                adjusted_close: get_optional_decimal_value(row, 10)?,
             })
         })
         .optional()?;
@@ -1195,13 +2084,15 @@ pub(crate) fn get_optional_decimal_value(
     }
 }
 
+const INSERT_CORPORATE_ACTION_SQL: &str = "INSERT INTO corporate_actions (
+            asset_id, action_type, event_date, ex_date, quantity_adjustment, source, notes,
+            import_session_id
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+
 /// Insert corporate action
 pub fn insert_corporate_action(conn: &Connection, action: &CorporateAction) -> Result<i64> {
-    conn.execute(
-        "INSERT INTO corporate_actions (
-            asset_id, action_type, event_date, ex_date, quantity_adjustment, source, notes
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        params![
+    conn.prepare_cached(INSERT_CORPORATE_ACTION_SQL)?
+        .execute(params![
             action.asset_id,
             action.action_type.as_str(),
             action.event_date,
@@ -1209,8 +2100,8 @@ pub fn insert_corporate_action(conn: &Connection, action: &CorporateAction) -> R
             action.quantity_adjustment.to_string(),
             action.source,
             action.notes,
-        ],
-    )?;
+            import_session::current(),
+        ])?;
 
     Ok(conn.last_insert_rowid())
 }
@@ -1389,14 +2280,16 @@ pub fn delete_income_events_from_source_after_date(
     Ok(count)
 }
 
+pub(crate) const INSERT_INCOME_EVENT_SQL: &str = "INSERT INTO income_events (
+            asset_id, event_date, ex_date, event_type, amount_per_quota, total_amount,
+            withholding_tax, is_quota_pre_2026, source, notes, portfolio_id, foreign_tax_withheld,
+            import_session_id
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)";
+
 /// Insert income event
 pub fn insert_income_event(conn: &Connection, event: &IncomeEvent) -> Result<i64> {
-    conn.execute(
-        "INSERT INTO income_events (
-            asset_id, event_date, ex_date, event_type, amount_per_quota, total_amount,
-            withholding_tax, is_quota_pre_2026, source, notes
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        params![
+    conn.prepare_cached(INSERT_INCOME_EVENT_SQL)?
+        .execute(params![
             event.asset_id,
             event.event_date,
             event.ex_date,
@@ -1407,13 +2300,38 @@ pub fn insert_income_event(conn: &Connection, event: &IncomeEvent) -> Result<i64
             event.is_quota_pre_2026,
             event.source,
             event.notes,
-        ],
-    )?;
+            portfolio::write_target(),
+            event.foreign_tax_withheld.map(|v| v.to_string()),
+            import_session::current(),
+        ])?;
 
     Ok(conn.last_insert_rowid())
 }
 
-/// Check if an income event already exists (for duplicate detection)
+/// Give an income event the amounts and ex-date of a more complete source,
+/// keeping its id so links to cash credits and brokers stay in place
+pub fn complete_income_event(conn: &Connection, id: i64, event: &IncomeEvent) -> Result<()> {
+    conn.execute(
+        "UPDATE income_events
+         SET ex_date = COALESCE(?2, ex_date), amount_per_quota = ?3, total_amount = ?4,
+             withholding_tax = ?5, source = ?6, notes = ?7
+         WHERE id = ?1",
+        params![
+            id,
+            event.ex_date,
+            event.amount_per_quota.to_string(),
+            event.total_amount.to_string(),
+            event.withholding_tax.to_string(),
+            event.source,
+            event.notes,
+        ],
+    )?;
+    Ok(())
+}
+
+/// Check if an income event already exists (for duplicate detection). The
+/// amount may also be the event's value net of withholding, as credited
+/// after the Proventos report filled in the gross value.
 pub fn income_event_exists(
     conn: &Connection,
     asset_id: i64,
@@ -1423,12 +2341,16 @@ pub fn income_event_exists(
 ) -> Result<bool> {
     let count: i64 = conn.query_row(
         "SELECT COUNT(*) FROM income_events
-         WHERE asset_id = ?1 AND event_date = ?2 AND event_type = ?3 AND total_amount = ?4",
+         WHERE asset_id = ?1 AND event_date = ?2 AND event_type = ?3
+           AND (total_amount = ?4
+                OR ABS(CAST(total_amount AS REAL) - CAST(COALESCE(withholding_tax, 0) AS REAL)
+                       - ?5) < 0.005)",
         params![
             asset_id,
             event_date,
             event_type.as_str(),
-            total_amount.to_string()
+            total_amount.to_string(),
+            rust_decimal::prelude::ToPrimitive::to_f64(&total_amount),
         ],
         |row| row.get(0),
     )?;
@@ -1446,11 +2368,13 @@ pub fn get_income_events_with_assets(
         "SELECT ie.id, ie.asset_id, ie.event_date, ie.ex_date, ie.event_type,
                 ie.amount_per_quota, ie.total_amount, ie.withholding_tax,
                 ie.is_quota_pre_2026, ie.source, ie.notes, ie.created_at,
-                a.id, a.ticker, a.asset_type, a.name, a.cnpj, a.created_at, a.updated_at
+                a.id, a.ticker, a.asset_type, a.name, a.cnpj, a.created_at, a.updated_at,
+                ie.foreign_tax_withheld
          FROM income_events ie
          JOIN assets a ON ie.asset_id = a.id
          WHERE 1=1",
     );
+    sql.push_str(&portfolio::scope_filter("ie.portfolio_id"));
 
     let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
@@ -1486,6 +2410,7 @@ pub fn get_income_events_with_assets(
                 amount_per_quota: get_decimal_value(row, 5)?,
                 total_amount: get_decimal_value(row, 6)?,
                 withholding_tax: get_optional_decimal_value(row, 7)?.unwrap_or(Decimal::ZERO),
+                foreign_tax_withheld: get_optional_decimal_value(row, 19)?,
                 is_quota_pre_2026: row.get(8)?,
                 source: row.get(9)?,
                 notes: row.get(10)?,
@@ -1519,8 +2444,9 @@ pub fn get_amortizations_for_asset(
 ) -> Result<Vec<IncomeEvent>> {
     let mut sql = String::from(
         "SELECT id, asset_id, event_date, ex_date, event_type, amount_per_quota, total_amount, \
-                withholding_tax, is_quota_pre_2026, source, notes, created_at\n         FROM income_events\n         WHERE asset_id = ? AND event_type = 'AMORTIZATION'",
+                withholding_tax, is_quota_pre_2026, source, notes, created_at, foreign_tax_withheld\n         FROM income_events\n         WHERE asset_id = ? AND event_type = 'AMORTIZATION'",
     );
+    sql.push_str(&portfolio::scope_filter("portfolio_id"));
 
     let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(asset_id)];
 
@@ -1552,6 +2478,7 @@ pub fn get_amortizations_for_asset(
                 amount_per_quota: get_decimal_value(row, 5)?,
                 total_amount: get_decimal_value(row, 6)?,
                 withholding_tax: get_optional_decimal_value(row, 7)?.unwrap_or(Decimal::ZERO),
+                foreign_tax_withheld: get_optional_decimal_value(row, 12)?,
                 is_quota_pre_2026: row.get(8)?,
                 source: row.get(9)?,
                 notes: row.get(10)?,
@@ -1719,7 +2646,6 @@ pub fn has_any_prices(
 }
 
 /// Get the earliest transaction date in the portfolio
-#[allow(dead_code)]
 pub fn get_earliest_transaction_date(conn: &Connection) -> Result<Option<NaiveDate>> {
     let mut stmt = conn.prepare("SELECT MIN(trade_date) FROM transactions")?;
 
@@ -1789,6 +2715,75 @@ mod tests {
         assert!(table_count > 0);
     }
 
+    #[test]
+    fn test_bonus_transactions_migrate_to_their_actions() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let db_path = tmp.path().join("test.db");
+        init_database(Some(db_path.clone()))?;
+        Connection::open(&db_path)?.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'ITSA4', 'STOCK');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, source)
+                 VALUES (1, 'BUY', '2024-01-10', '100', '10', '1000', 'CEI'),
+                        (1, 'BUY', '2024-03-01', '10', '0', '0', 'CORPORATE_ACTION'),
+                        (1, 'BUY', '2024-05-02', '7', '0', '0', 'CORPORATE_ACTION');
+             INSERT INTO corporate_actions (asset_id, action_type, event_date, ex_date,
+                 quantity_adjustment, source)
+                 VALUES (1, 'BONUS', '2024-03-01', '2024-03-01', '10', 'B3');",
+        )?;
+        init_database(Some(db_path.clone()))?;
+
+        let conn = Connection::open(&db_path)?;
+        let sources: Vec<String> = conn
+            .prepare("SELECT source FROM transactions ORDER BY id")?
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        // The row with no bonus behind it stays a trade
+        assert_eq!(sources, vec!["CEI", "CORPORATE_ACTION"]);
+        let applied: bool = conn.query_row(
+            "SELECT applied_at IS NOT NULL FROM corporate_actions",
+            [],
+            |row| row.get(0),
+        )?;
+        assert!(applied);
+        let held: f64 = conn.query_row(
+            "SELECT quantity FROM current_positions WHERE ticker = 'ITSA4'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(held, 117.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_asset_issuer_round_trip() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(include_str!("schema.sql"))?;
+        let asset_id = insert_asset(&conn, "PETR4", &AssetType::Stock, None)?;
+        assert!(get_asset_issuer(&conn, asset_id)?.is_none());
+
+        let mut issuer = AssetIssuer {
+            asset_id,
+            cnpj: "33000167000101".to_string(),
+            legal_name: "PETROLEO BRASILEIRO S A PETROBRAS".to_string(),
+            trade_name: None,
+            situation: Some("ATIVA".to_string()),
+            situation_date: NaiveDate::from_ymd_opt(2005, 11, 3),
+            source: "BRASILAPI".to_string(),
+            fetched_at: None,
+        };
+        upsert_asset_issuer(&conn, &issuer)?;
+        issuer.situation = Some("BAIXADA".to_string());
+        upsert_asset_issuer(&conn, &issuer)?;
+
+        let stored = get_asset_issuer(&conn, asset_id)?.unwrap();
+        assert_eq!(stored.legal_name, issuer.legal_name);
+        assert_eq!(stored.situation_date, issuer.situation_date);
+        assert!(!stored.is_active());
+        assert!(stored.fetched_at.is_some());
+        Ok(())
+    }
+
     #[test]
     fn test_asset_exists() -> Result<()> {
         let tmp = tempfile::tempdir()?;
@@ -1808,4 +2803,78 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_shared_views_replace_the_legacy_table() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let db_path = tmp.path().join("test.db");
+        // Databases created before the views carry an empty realized_gains table
+        Connection::open(&db_path)?.execute_batch(
+            "CREATE TABLE realized_gains (id INTEGER PRIMARY KEY, sale_date DATE NOT NULL)",
+        )?;
+        init_database(Some(db_path.clone()))?;
+        init_database(Some(db_path.clone()))?;
+
+        let conn = Connection::open(&db_path)?;
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES
+                 (1, 'VIIA3', 'STOCK'), (2, 'BHIA3', 'STOCK'), (3, 'XPLG11', 'FII'), (4, 'OLD3', 'STOCK');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, source)
+                 VALUES (1, 'BUY', '2023-01-10', '100', '3', '300', 'TEST'),
+                        (3, 'BUY', '2023-02-01', '10', '100', '1000', 'TEST'),
+                        (3, 'SELL', '2023-03-01', '4', '110', '440', 'TEST'),
+                        (4, 'BUY', '2023-01-10', '5', '20', '100', 'TEST');
+             -- 100 VIIA3 became 10 after a 10:1 reverse split, then renamed BHIA3
+             INSERT INTO corporate_actions (asset_id, action_type, event_date, ex_date,
+                 quantity_adjustment) VALUES (1, 'REVERSE_SPLIT', '2024-02-01', '2024-02-01', '-90');
+             INSERT INTO asset_renames (from_asset_id, to_asset_id, effective_date)
+                 VALUES (1, 2, '2024-03-01');
+             INSERT INTO asset_exchanges (event_type, from_asset_id, to_asset_id,
+                 effective_date, to_quantity, allocated_cost)
+                 VALUES ('MERGER', 4, 3, '2024-05-01', '2', '100');
+             INSERT INTO price_history (asset_id, price_date, close_price)
+                 VALUES (3, '2024-01-31', '95'), (3, '2024-02-29', '98');
+             INSERT INTO income_events (asset_id, event_date, event_type, amount_per_quota,
+                 total_amount, withholding_tax)
+                 VALUES (3, '2024-02-14', 'DIVIDEND', '0.8', '8', '0'),
+                        (3, '2024-02-28', 'JCP', '1', '10', '1.5');
+             INSERT INTO tax_ledger (year, month, tax_category, sales, profit_loss, loss_offset,
+                 exemption, tax_due, darf_status, tx_fingerprint)
+                 VALUES (2023, 3, 'STOCK_SWING', '440', '40', '0', '40', '0', 'NOT_DUE', 'x');",
+        )?;
+
+        let positions: Vec<(String, f64, Option<f64>)> = conn
+            .prepare(
+                "SELECT ticker, quantity, market_value FROM current_positions ORDER BY ticker",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        assert_eq!(
+            positions,
+            vec![
+                ("BHIA3".to_string(), 10.0, None),
+                ("XPLG11".to_string(), 8.0, Some(784.0)),
+            ]
+        );
+
+        let income: Vec<(String, String, f64)> = conn
+            .prepare("SELECT month, event_type, net FROM monthly_income ORDER BY event_type")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        assert_eq!(
+            income,
+            vec![
+                ("2024-02".to_string(), "DIVIDEND".to_string(), 8.0),
+                ("2024-02".to_string(), "JCP".to_string(), 8.5),
+            ]
+        );
+
+        let (month, profit): (String, f64) =
+            conn.query_row("SELECT month, profit_loss FROM realized_gains", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?;
+        assert_eq!((month.as_str(), profit), ("2023-03", 40.0));
+        Ok(())
+    }
 }
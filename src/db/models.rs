@@ -18,6 +18,7 @@ pub enum AssetType {
     Fip,          // Private equity funds
     Option,       // Options on equities
     TermContract, // Term contracts (e.g., ANIM3T)
+    Crypto,       // Crypto-assets traded on exchanges
     Unknown,      // Unresolved/unknown type
 }
 
@@ -36,6 +37,7 @@ impl AssetType {
             AssetType::Fip => "FIP",
             AssetType::Option => "OPTION",
             AssetType::TermContract => "TERM",
+            AssetType::Crypto => "CRYPTO",
             AssetType::Unknown => "UNKNOWN",
         }
     }
@@ -58,6 +60,7 @@ impl FromStr for AssetType {
             "FIP" => Ok(AssetType::Fip),
             "OPTION" => Ok(AssetType::Option),
             "TERM" => Ok(AssetType::TermContract),
+            "CRYPTO" => Ok(AssetType::Crypto),
             "UNKNOWN" => Ok(AssetType::Unknown),
             _ => Err(()),
         }
@@ -98,6 +101,68 @@ pub struct AssetRegistryEntry {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+/// Issuer registration data for an asset's CNPJ (Receita Federal)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetIssuer {
+    pub asset_id: i64,
+    pub cnpj: String,
+    /// Razão social, as required in the IRPF declaration
+    pub legal_name: String,
+    pub trade_name: Option<String>,
+    /// Situação cadastral (e.g. ATIVA, BAIXADA)
+    pub situation: Option<String>,
+    pub situation_date: Option<NaiveDate>,
+    pub source: String,
+    pub fetched_at: Option<DateTime<Utc>>,
+}
+
+impl AssetIssuer {
+    pub fn is_active(&self) -> bool {
+        self.situation
+            .as_deref()
+            .is_none_or(|s| s.eq_ignore_ascii_case("ATIVA"))
+    }
+}
+
+/// FII net asset value per quota for a reference month (CVM informe mensal)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiiNav {
+    pub asset_id: i64,
+    pub reference_date: NaiveDate,
+    pub nav_per_quota: Decimal,
+    pub net_assets: Option<Decimal>,
+    pub quotas: Option<Decimal>,
+}
+
+/// Fees and withheld IRRF of an imported brokerage note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BrokerNote {
+    pub id: Option<i64>,
+    pub broker_id: Option<i64>,
+    pub note_number: String,
+    pub trade_date: NaiveDate,
+    pub settlement_date: Option<NaiveDate>,
+    pub settlement_fee: Decimal,
+    pub registration_fee: Decimal,
+    pub emolumentos: Decimal,
+    pub brokerage: Decimal,
+    pub iss: Decimal,
+    pub other_fees: Decimal,
+    pub irrf: Decimal,
+    pub irrf_day_trade: Decimal,
+    pub net_amount: Option<Decimal>,
+}
+
+/// A set of accounts reported together (e.g. own, spouse, corporate)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Portfolio {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub declarant: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
 /// Transaction type (buy or sell)
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TransactionType {
@@ -207,6 +272,17 @@ pub struct AssetRename {
     pub created_at: DateTime<Utc>,
 }
 
+/// Trade idea journal entry; transactions are linked after execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: Option<i64>,
+    pub asset_id: i64,
+    pub entry_date: NaiveDate,
+    pub thesis: String,
+    pub target_price: Option<Decimal>,
+    pub created_at: DateTime<Utc>,
+}
+
 /// Exchange action type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum AssetExchangeType {
@@ -317,6 +393,20 @@ pub enum InconsistencyType {
     MissingPurchaseHistory,
     InvalidTicker,
     InvalidDate,
+    /// B3 custody update ("Atualização") awaiting user confirmation
+    PositionAdjustment,
+    /// Income credited to the account that no income event accounts for
+    UnmatchedCashCredit,
+    /// Income event the statements covering its date never credited
+    UncreditedIncome,
+    /// The same trade imported from two sources
+    DuplicateTransaction,
+    /// Quantity the instrument cannot trade in (e.g. fractional shares)
+    InvalidQuantity,
+    /// Corporate event B3 publishes differently from the action recorded
+    CorporateActionConflict,
+    /// Held ticker the B3 instruments file replaced with a successor
+    TickerRename,
 }
 
 impl InconsistencyType {
@@ -326,6 +416,13 @@ impl InconsistencyType {
             InconsistencyType::MissingPurchaseHistory => "MISSING_PURCHASE_HISTORY",
             InconsistencyType::InvalidTicker => "INVALID_TICKER",
             InconsistencyType::InvalidDate => "INVALID_DATE",
+            InconsistencyType::PositionAdjustment => "POSITION_ADJUSTMENT",
+            InconsistencyType::UnmatchedCashCredit => "UNMATCHED_CASH_CREDIT",
+            InconsistencyType::UncreditedIncome => "UNCREDITED_INCOME",
+            InconsistencyType::DuplicateTransaction => "DUPLICATE_TRANSACTION",
+            InconsistencyType::InvalidQuantity => "INVALID_QUANTITY",
+            InconsistencyType::CorporateActionConflict => "CORPORATE_ACTION_CONFLICT",
+            InconsistencyType::TickerRename => "TICKER_RENAME",
         }
     }
 }
@@ -339,6 +436,13 @@ impl FromStr for InconsistencyType {
             "MISSING_PURCHASE_HISTORY" => Ok(InconsistencyType::MissingPurchaseHistory),
             "INVALID_TICKER" => Ok(InconsistencyType::InvalidTicker),
             "INVALID_DATE" => Ok(InconsistencyType::InvalidDate),
+            "POSITION_ADJUSTMENT" => Ok(InconsistencyType::PositionAdjustment),
+            "UNMATCHED_CASH_CREDIT" => Ok(InconsistencyType::UnmatchedCashCredit),
+            "UNCREDITED_INCOME" => Ok(InconsistencyType::UncreditedIncome),
+            "DUPLICATE_TRANSACTION" => Ok(InconsistencyType::DuplicateTransaction),
+            "INVALID_QUANTITY" => Ok(InconsistencyType::InvalidQuantity),
+            "CORPORATE_ACTION_CONFLICT" => Ok(InconsistencyType::CorporateActionConflict),
+            "TICKER_RENAME" => Ok(InconsistencyType::TickerRename),
             _ => Err(()),
         }
     }
@@ -379,6 +483,113 @@ pub struct PriceHistory {
     pub volume: Option<i64>,
     pub source: String,
     pub created_at: DateTime<Utc>,
+    pub adjusted_close: Option<Decimal>,
+}
+
+/// Which daily price series to read from a price history row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceSeries {
+    /// Raw close as traded; pairs with quantities that already reflect corporate actions
+    Close,
+    /// Split/dividend adjusted close; falls back to the raw close when not stored
+    Adjusted,
+}
+
+impl PriceHistory {
+    pub fn price(&self, series: PriceSeries) -> Decimal {
+        match series {
+            PriceSeries::Close => self.close_price,
+            PriceSeries::Adjusted => self.adjusted_close.unwrap_or(self.close_price),
+        }
+    }
+}
+
+/// Intraday quote captured from a live price source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceSnapshot {
+    pub asset_id: i64,
+    pub snapshot_at: DateTime<Utc>,
+    pub price: Decimal,
+    pub source: String,
+}
+
+/// Market benchmark used to compare asset and portfolio returns
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Benchmark {
+    Ibov,  // Ibovespa index level
+    Ifix,  // IFIX (real estate funds) index level
+    Cdi,   // CDI daily rate (% per day)
+    Selic, // SELIC daily rate (% per day)
+    Ipca,  // IPCA monthly inflation (% per month, dated on the 1st)
+}
+
+impl Benchmark {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Benchmark::Ibov => "IBOV",
+            Benchmark::Ifix => "IFIX",
+            Benchmark::Cdi => "CDI",
+            Benchmark::Selic => "SELIC",
+            Benchmark::Ipca => "IPCA",
+        }
+    }
+
+    /// All supported benchmarks, in display order
+    pub fn all() -> [Benchmark; 5] {
+        [
+            Benchmark::Ibov,
+            Benchmark::Ifix,
+            Benchmark::Cdi,
+            Benchmark::Selic,
+            Benchmark::Ipca,
+        ]
+    }
+
+    /// True when stored values are rates that must be compounded, false
+    /// when they are index levels.
+    pub fn is_rate(&self) -> bool {
+        matches!(self, Benchmark::Cdi | Benchmark::Selic | Benchmark::Ipca)
+    }
+
+    /// True when each stored rate covers a whole month rather than a day
+    pub fn is_monthly(&self) -> bool {
+        matches!(self, Benchmark::Ipca)
+    }
+
+    /// Natural benchmark for an asset type (IBOV for equities, IFIX for
+    /// listed funds, CDI for fixed income).
+    pub fn for_asset_type(asset_type: AssetType) -> Option<Benchmark> {
+        match asset_type {
+            AssetType::Stock | AssetType::Etf | AssetType::Bdr => Some(Benchmark::Ibov),
+            AssetType::Fii | AssetType::Fiagro | AssetType::FiInfra => Some(Benchmark::Ifix),
+            AssetType::Bond | AssetType::GovBond | AssetType::Fidc => Some(Benchmark::Cdi),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Benchmark {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "IBOV" | "IBOVESPA" => Ok(Benchmark::Ibov),
+            "IFIX" => Ok(Benchmark::Ifix),
+            "CDI" => Ok(Benchmark::Cdi),
+            "SELIC" => Ok(Benchmark::Selic),
+            "IPCA" => Ok(Benchmark::Ipca),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Benchmark series entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkValue {
+    pub benchmark: Benchmark,
+    pub value_date: NaiveDate,
+    pub value: Decimal,
+    pub source: String,
 }
 
 /// Government bond rate entry (Tesouro Direto)
@@ -411,6 +622,7 @@ pub enum IncomeEventType {
     Dividend,     // Regular dividend (rendimento)
     Amortization, // Capital return (amortização)
     Jcp,          // Juros sobre Capital Próprio
+    Interest,     // Fixed income coupon (juros de debêntures, CRI/CRA, Tesouro)
 }
 
 impl IncomeEventType {
@@ -419,6 +631,7 @@ impl IncomeEventType {
             IncomeEventType::Dividend => "DIVIDEND",
             IncomeEventType::Amortization => "AMORTIZATION",
             IncomeEventType::Jcp => "JCP",
+            IncomeEventType::Interest => "INTEREST",
         }
     }
 }
@@ -431,6 +644,7 @@ impl FromStr for IncomeEventType {
             "DIVIDEND" | "DIVIDENDO" | "RENDIMENTO" => Ok(IncomeEventType::Dividend),
             "AMORTIZATION" | "AMORTIZAÇÃO" | "AMORTIZACAO" => Ok(IncomeEventType::Amortization),
             "JCP" => Ok(IncomeEventType::Jcp),
+            "INTEREST" | "JUROS" => Ok(IncomeEventType::Interest),
             _ => Err(()),
         }
     }
@@ -447,6 +661,9 @@ pub struct IncomeEvent {
     pub amount_per_quota: Decimal,
     pub total_amount: Decimal,
     pub withholding_tax: Decimal,
+    /// Tax withheld abroad before `total_amount` was paid (BDR dividends);
+    /// None when the source does not report it
+    pub foreign_tax_withheld: Option<Decimal>,
     pub is_quota_pre_2026: Option<bool>, // For tax rule tracking
     pub source: String,
     pub notes: Option<String>,
@@ -631,6 +848,7 @@ mod tests {
         assert_eq!(IncomeEventType::Dividend.as_str(), "DIVIDEND");
         assert_eq!(IncomeEventType::Amortization.as_str(), "AMORTIZATION");
         assert_eq!(IncomeEventType::Jcp.as_str(), "JCP");
+        assert_eq!(IncomeEventType::Interest.as_str(), "INTEREST");
 
         // Test English inputs
         assert_eq!(
@@ -0,0 +1,277 @@
+//! Portfolios (accounts) that transactions and income events belong to.
+//!
+//! Every transaction and income event carries a `portfolio_id`; the
+//! `default` portfolio holds everything recorded before portfolios existed.
+//! With `--portfolio NAME`, reports read only that portfolio's rows and new
+//! rows are written to it. Without it, reports aggregate all portfolios.
+//!
+//! Portfolios can name their declarant (the CPF that declares them). Couples
+//! declaring separately use `--declarant NAME` to scope reports to all of
+//! one person's portfolios.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::sync::RwLock;
+
+use super::Portfolio;
+
+pub const DEFAULT_PORTFOLIO_ID: i64 = 1;
+
+/// Scoped portfolio ids, empty when aggregating all portfolios
+static SCOPE: RwLock<Vec<i64>> = RwLock::new(Vec::new());
+
+/// Restrict reports to these portfolios (none: aggregate all)
+pub fn set_scope(portfolio_ids: &[i64]) {
+    *SCOPE.write().unwrap_or_else(|e| e.into_inner()) = portfolio_ids.to_vec();
+}
+
+fn scope() -> Vec<i64> {
+    SCOPE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn is_scoped() -> bool {
+    !scope().is_empty()
+}
+
+/// Portfolio new transactions and income events are recorded in (the first
+/// scoped one when a declarant has several)
+pub fn write_target() -> i64 {
+    scope().first().copied().unwrap_or(DEFAULT_PORTFOLIO_ID)
+}
+
+/// SQL condition (" AND column IN (ids)") restricting a query to the scoped
+/// portfolios; empty when aggregating
+pub fn scope_filter(column: &str) -> String {
+    match scope().as_slice() {
+        [] => String::new(),
+        [id] => format!(" AND {} = {}", column, id),
+        ids => format!(
+            " AND {} IN ({})",
+            column,
+            ids.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn map_portfolio(row: &rusqlite::Row) -> rusqlite::Result<Portfolio> {
+    Ok(Portfolio {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        declarant: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+pub fn create_portfolio(
+    conn: &Connection,
+    name: &str,
+    description: Option<&str>,
+    declarant: Option<&str>,
+) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO portfolios (name, description, declarant) VALUES (?1, ?2, ?3)",
+        params![name, description, declarant],
+    )
+    .with_context(|| format!("Failed to create portfolio '{}'", name))?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn get_portfolio_by_name(conn: &Connection, name: &str) -> Result<Option<Portfolio>> {
+    Ok(conn
+        .query_row(
+            "SELECT id, name, description, declarant, created_at FROM portfolios
+             WHERE name = ?1 COLLATE NOCASE",
+            [name],
+            map_portfolio,
+        )
+        .optional()?)
+}
+
+/// Portfolio by name, with a hint to create it when missing
+pub fn require_portfolio(conn: &Connection, name: &str) -> Result<Portfolio> {
+    get_portfolio_by_name(conn, name)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown portfolio '{}'. Create it with: interest portfolios add {}",
+            name,
+            name
+        )
+    })
+}
+
+/// All portfolios with their transaction and income event counts
+pub fn list_portfolios(conn: &Connection) -> Result<Vec<(Portfolio, i64, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.name, p.description, p.declarant, p.created_at,
+                (SELECT COUNT(*) FROM transactions t WHERE t.portfolio_id = p.id),
+                (SELECT COUNT(*) FROM income_events i WHERE i.portfolio_id = p.id)
+         FROM portfolios p
+         ORDER BY p.id",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((map_portfolio(row)?, row.get(5)?, row.get(6)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Set (or clear) the declarant of a portfolio
+pub fn set_declarant(conn: &Connection, portfolio_id: i64, declarant: Option<&str>) -> Result<()> {
+    conn.execute(
+        "UPDATE portfolios SET declarant = ?1 WHERE id = ?2",
+        params![declarant, portfolio_id],
+    )?;
+    Ok(())
+}
+
+/// Declarants named on portfolios, in order of first portfolio
+pub fn list_declarants(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT declarant FROM portfolios
+         WHERE declarant IS NOT NULL
+         GROUP BY declarant COLLATE NOCASE
+         ORDER BY MIN(id)",
+    )?;
+    let rows = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(rows)
+}
+
+/// Ids of the portfolios declared by `declarant`, or of those with no
+/// declarant when None
+pub fn declarant_portfolio_ids(conn: &Connection, declarant: Option<&str>) -> Result<Vec<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM portfolios
+         WHERE (?1 IS NULL AND declarant IS NULL) OR declarant = ?1 COLLATE NOCASE
+         ORDER BY id",
+    )?;
+    let rows = stmt
+        .query_map(params![declarant], |row| row.get(0))?
+        .collect::<Result<Vec<i64>, _>>()?;
+    Ok(rows)
+}
+
+/// Portfolio ids of a declarant, with a hint when nobody has that name
+pub fn require_declarant(conn: &Connection, declarant: &str) -> Result<Vec<i64>> {
+    let ids = declarant_portfolio_ids(conn, Some(declarant))?;
+    if ids.is_empty() {
+        anyhow::bail!(
+            "No portfolio is declared by '{}'. Set one with: interest portfolios set-declarant <portfolio> {}",
+            declarant,
+            declarant
+        );
+    }
+    Ok(ids)
+}
+
+/// Delete an empty portfolio
+pub fn delete_portfolio(conn: &Connection, portfolio: &Portfolio) -> Result<()> {
+    if portfolio.id == DEFAULT_PORTFOLIO_ID {
+        anyhow::bail!("The default portfolio cannot be removed");
+    }
+    let used: i64 = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM transactions WHERE portfolio_id = ?1)
+              + (SELECT COUNT(*) FROM income_events WHERE portfolio_id = ?1)",
+        [portfolio.id],
+        |row| row.get(0),
+    )?;
+    if used > 0 {
+        anyhow::bail!(
+            "Portfolio '{}' still has {} transaction(s)/income event(s); move them with 'interest portfolios assign' first",
+            portfolio.name,
+            used
+        );
+    }
+    conn.execute("DELETE FROM portfolios WHERE id = ?1", [portfolio.id])?;
+    Ok(())
+}
+
+/// Move transactions and income events into a portfolio, optionally only
+/// those of one asset and/or import source. Returns (transactions, income events).
+pub fn assign_to_portfolio(
+    conn: &Connection,
+    portfolio_id: i64,
+    asset_id: Option<i64>,
+    source: Option<&str>,
+) -> Result<(usize, usize)> {
+    let filter = "(?2 IS NULL OR asset_id = ?2) AND (?3 IS NULL OR source = ?3)";
+    let transactions = conn.execute(
+        &format!("UPDATE transactions SET portfolio_id = ?1 WHERE {}", filter),
+        params![portfolio_id, asset_id, source],
+    )?;
+    let income = conn.execute(
+        &format!(
+            "UPDATE income_events SET portfolio_id = ?1 WHERE {}",
+            filter
+        ),
+        params![portfolio_id, asset_id, source],
+    )?;
+    Ok((transactions, income))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_portfolio_assign_and_delete() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('PETR4', 'STOCK')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                price_per_unit, total_cost, source)
+             VALUES (1, 'BUY', '2024-01-10', 10, 30, 300, 'MANUAL')",
+            [],
+        )
+        .unwrap();
+
+        let spouse = create_portfolio(&conn, "spouse", None, Some("Ana")).unwrap();
+        let default = require_portfolio(&conn, "DEFAULT").unwrap();
+        assert_eq!(default.id, DEFAULT_PORTFOLIO_ID);
+        assert!(require_portfolio(&conn, "corp").is_err());
+
+        assert_eq!(
+            assign_to_portfolio(&conn, spouse, Some(1), Some("CEI")).unwrap(),
+            (0, 0)
+        );
+        assert_eq!(
+            assign_to_portfolio(&conn, spouse, Some(1), None).unwrap(),
+            (1, 0)
+        );
+        let counts: Vec<_> = list_portfolios(&conn)
+            .unwrap()
+            .into_iter()
+            .map(|(p, tx, _)| (p.name, tx))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("default".to_string(), 0), ("spouse".to_string(), 1)]
+        );
+
+        assert_eq!(list_declarants(&conn).unwrap(), vec!["Ana".to_string()]);
+        assert_eq!(require_declarant(&conn, "ana").unwrap(), vec![spouse]);
+        assert!(require_declarant(&conn, "Bruno").is_err());
+        assert_eq!(
+            declarant_portfolio_ids(&conn, None).unwrap(),
+            vec![DEFAULT_PORTFOLIO_ID]
+        );
+        set_declarant(&conn, DEFAULT_PORTFOLIO_ID, Some("Bruno")).unwrap();
+        assert_eq!(list_declarants(&conn).unwrap(), vec!["Bruno", "Ana"]);
+
+        let spouse = require_portfolio(&conn, "spouse").unwrap();
+        assert!(delete_portfolio(&conn, &spouse).is_err());
+        assert!(delete_portfolio(&conn, &default).is_err());
+        assign_to_portfolio(&conn, DEFAULT_PORTFOLIO_ID, None, None).unwrap();
+        delete_portfolio(&conn, &spouse).unwrap();
+    }
+}
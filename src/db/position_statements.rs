@@ -0,0 +1,85 @@
+//! Holdings reported by custodians on a given day.
+//!
+//! B3's Posição export and the investor API positions endpoint list what
+//! the investor held at the end of a day. They are stored per asset, summed
+//! over institutions, so the computed history can be checked against them
+//! month by month (`inconsistencies positions`). Recording a statement again
+//! for the same day and source replaces it.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+
+use super::{import_session, portfolio, AssetType};
+
+/// One asset's reported quantity
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportedPosition {
+    pub asset_id: i64,
+    pub ticker: String,
+    pub statement_date: NaiveDate,
+    pub quantity: Decimal,
+    pub source: String,
+}
+
+/// Replace the statement `source` reported on `date` with `quantities`
+/// (ticker → quantity). Returns the number of assets recorded.
+pub fn record_statement(
+    conn: &Connection,
+    date: NaiveDate,
+    source: &str,
+    quantities: &BTreeMap<String, Decimal>,
+) -> Result<usize> {
+    let portfolio_id = portfolio::write_target();
+    super::bulk::in_transaction(conn, |conn| {
+        conn.execute(
+            "DELETE FROM position_statements
+             WHERE statement_date = ?1 AND source = ?2 AND portfolio_id = ?3",
+            params![date, source, portfolio_id],
+        )?;
+        for (ticker, quantity) in quantities {
+            let asset_id =
+                super::upsert_asset_as_of(conn, ticker, &AssetType::Unknown, None, Some(date))?;
+            conn.execute(
+                "INSERT INTO position_statements
+                    (asset_id, statement_date, quantity, source, portfolio_id, import_session_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    asset_id,
+                    date,
+                    quantity.to_string(),
+                    source,
+                    portfolio_id,
+                    import_session::current()
+                ],
+            )?;
+        }
+        Ok(quantities.len())
+    })
+}
+
+/// Every reported position in the scoped portfolios, oldest first
+pub fn load_statements(conn: &Connection) -> Result<Vec<ReportedPosition>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT s.asset_id, a.ticker, s.statement_date, s.quantity, s.source
+         FROM position_statements s
+         JOIN assets a ON a.id = s.asset_id
+         WHERE 1 = 1{}
+         ORDER BY s.statement_date, a.ticker, s.source",
+        portfolio::scope_filter("s.portfolio_id")
+    ))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(ReportedPosition {
+                asset_id: row.get(0)?,
+                ticker: row.get(1)?,
+                statement_date: row.get(2)?,
+                quantity: super::get_decimal_value(row, 3)?,
+                source: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
@@ -0,0 +1,403 @@
+//! Sandbox database for speculative operations.
+//!
+//! With `--sandbox`, every command works on `~/.interest/sandbox.db`, a copy
+//! of the live database taken the first time the sandbox is used. Imports,
+//! hypothetical sales or corporate actions can be tried and reported on there,
+//! then the sandbox is either discarded or promoted to replace the live data.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const SANDBOX_FILENAME: &str = "sandbox.db";
+const CREATED_AT_KEY: &str = "sandbox_created_at";
+const SOURCE_FINGERPRINT_KEY: &str = "sandbox_source_fingerprint";
+
+/// Caches and derived data left out of `sandbox status` and of the check that
+/// guards promotion: they are rebuilt from user data or refreshed on plain
+/// reads. Every other table is compared.
+const UNTRACKED_TABLES: &[&str] = &[
+    "metadata",
+    "asset_registry",
+    "price_history",
+    "price_snapshots",
+    "quote_cache",
+    "gov_bond_rates",
+    "benchmark_history",
+    "fx_rates",
+    "fii_nav_history",
+    "position_snapshots",
+    "valuation_snapshots",
+    "cash_flows",
+    "positions",
+    "tax_events",
+    "loss_carryforward_snapshots",
+    "price_retry_queue",
+];
+
+/// Tables recomputed on reads that also hold user data: only the rows
+/// selected here are compared (the tax ledger is rewritten by every tax
+/// report, the DARF payments recorded on it are not)
+const PARTLY_TRACKED: &[(&str, &str)] = &[(
+    "tax_ledger",
+    "SELECT year, month, tax_category, paid_on, paid_amount FROM tax_ledger
+     WHERE paid_on IS NOT NULL ORDER BY year, month, tax_category",
+)];
+
+/// User tables of a database, in name order
+fn tracked_tables(conn: &Connection) -> Result<Vec<String>> {
+    let names = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(names
+        .into_iter()
+        .filter(|name| !UNTRACKED_TABLES.contains(&name.as_str()))
+        .collect())
+}
+
+/// Query for the compared rows of a tracked table
+fn tracked_rows(table: &str) -> String {
+    PARTLY_TRACKED
+        .iter()
+        .find(|(name, _)| *name == table)
+        .map(|(_, query)| query.to_string())
+        .unwrap_or_else(|| format!("SELECT * FROM \"{}\" ORDER BY rowid", table))
+}
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Path of the sandbox next to the live database
+pub fn sandbox_path() -> Result<PathBuf> {
+    Ok(super::get_interest_dir()?.join(SANDBOX_FILENAME))
+}
+
+fn live_path_in(dir: &Path) -> PathBuf {
+    dir.join(super::DB_FILENAME)
+}
+
+/// Route the default database to the sandbox, creating it from the live
+/// database when missing. Returns true when a new sandbox was created.
+pub fn activate() -> Result<bool> {
+    let dir = super::get_interest_dir()?;
+    let live = live_path_in(&dir);
+    let sandbox = dir.join(SANDBOX_FILENAME);
+    let created = if sandbox.exists() {
+        false
+    } else {
+        create(&live, &sandbox)?;
+        true
+    };
+    ACTIVE.store(true, Ordering::Relaxed);
+    Ok(created)
+}
+
+fn create(live: &Path, sandbox: &Path) -> Result<()> {
+    if live.exists() {
+        let conn = Connection::open(live)
+            .with_context(|| format!("Failed to open live database at {:?}", live))?;
+        conn.execute("VACUUM INTO ?1", params![sandbox.to_string_lossy()])
+            .context("Failed to copy the live database into the sandbox")?;
+    }
+    super::init_database(Some(sandbox.to_path_buf()))?;
+
+    let conn = super::open_db(Some(sandbox.to_path_buf()))?;
+    super::set_metadata(&conn, CREATED_AT_KEY, &Utc::now().to_rfc3339())?;
+    super::set_metadata(&conn, SOURCE_FINGERPRINT_KEY, &live_fingerprint(live)?)?;
+    Ok(())
+}
+
+/// Hash of the tracked tables of the live database ("" when it does not exist)
+fn live_fingerprint(live: &Path) -> Result<String> {
+    if !live.exists() {
+        return Ok(String::new());
+    }
+    let conn = super::open_db(Some(live.to_path_buf()))?;
+    let mut hasher = blake3::Hasher::new();
+    for table in tracked_tables(&conn)? {
+        let mut stmt = conn.prepare(&tracked_rows(&table))?;
+        let columns = stmt.column_count();
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            for i in 0..columns {
+                hasher.update(format!("{:?}|", row.get_ref(i)?).as_bytes());
+            }
+            hasher.update(b"\n");
+        }
+        hasher.update(table.as_bytes());
+    }
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Row count of a tracked table in the live database and in the sandbox
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TableDiff {
+    pub table: String,
+    pub live: i64,
+    pub sandbox: i64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SandboxStatus {
+    pub path: PathBuf,
+    pub created_at: Option<DateTime<Utc>>,
+    /// The live database was written after the sandbox was copied from it
+    pub live_changed: bool,
+    pub tables: Vec<TableDiff>,
+}
+
+/// Describe the current sandbox, or None when there is none
+pub fn status() -> Result<Option<SandboxStatus>> {
+    status_in(&super::get_interest_dir()?)
+}
+
+fn status_in(dir: &Path) -> Result<Option<SandboxStatus>> {
+    let sandbox = dir.join(SANDBOX_FILENAME);
+    if !sandbox.exists() {
+        return Ok(None);
+    }
+    let live = live_path_in(dir);
+    let sandbox_conn = super::open_db(Some(sandbox.clone()))?;
+    let live_conn = if live.exists() {
+        Some(super::open_db(Some(live.clone()))?)
+    } else {
+        None
+    };
+
+    let created_at = super::get_metadata(&sandbox_conn, CREATED_AT_KEY)?
+        .and_then(|v| DateTime::parse_from_rfc3339(&v).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+    let source_fingerprint =
+        super::get_metadata(&sandbox_conn, SOURCE_FINGERPRINT_KEY)?.unwrap_or_default();
+    let live_changed = live_fingerprint(&live)? != source_fingerprint;
+
+    let count = |conn: &Connection, table: &str| -> Result<i64> {
+        Ok(conn.query_row(
+            &format!("SELECT COUNT(*) FROM ({})", tracked_rows(table)),
+            [],
+            |row| row.get(0),
+        )?)
+    };
+    let live_tables = match &live_conn {
+        Some(conn) => tracked_tables(conn)?,
+        None => Vec::new(),
+    };
+    let mut tables = Vec::new();
+    for table in tracked_tables(&sandbox_conn)? {
+        let live = match &live_conn {
+            Some(conn) if live_tables.contains(&table) => count(conn, &table)?,
+            _ => 0,
+        };
+        tables.push(TableDiff {
+            live,
+            sandbox: count(&sandbox_conn, &table)?,
+            table,
+        });
+    }
+
+    Ok(Some(SandboxStatus {
+        path: sandbox,
+        created_at,
+        live_changed,
+        tables,
+    }))
+}
+
+/// Delete the sandbox; returns false when there was none
+pub fn discard() -> Result<bool> {
+    discard_in(&super::get_interest_dir()?)
+}
+
+fn discard_in(dir: &Path) -> Result<bool> {
+    let sandbox = dir.join(SANDBOX_FILENAME);
+    if !sandbox.exists() {
+        return Ok(false);
+    }
+    remove_db_files(&sandbox)?;
+    Ok(true)
+}
+
+/// Replace the live database with the sandbox, keeping a backup of the live
+/// file. Refuses when the live database changed since the sandbox was made,
+/// unless `force` is set. Returns the backup path.
+pub fn promote(force: bool) -> Result<PathBuf> {
+    promote_in(&super::get_interest_dir()?, force)
+}
+
+fn promote_in(dir: &Path, force: bool) -> Result<PathBuf> {
+    let status = status_in(dir)?.ok_or_else(|| anyhow::anyhow!("No sandbox to promote"))?;
+    if status.live_changed && !force {
+        anyhow::bail!(
+            "The live database changed after the sandbox was created; \
+             promoting would drop those changes. Use --force to promote anyway."
+        );
+    }
+
+    let live = live_path_in(dir);
+    let backup = live.with_file_name(format!(
+        "data.db.bak-{}",
+        chrono::Local::now().format("%Y%m%d%H%M%S")
+    ));
+    if live.exists() {
+        let conn = Connection::open(&live)?;
+        conn.execute("VACUUM INTO ?1", params![backup.to_string_lossy()])
+            .context("Failed to back up the live database")?;
+    }
+
+    let staged = live.with_file_name("data.db.promote");
+    if staged.exists() {
+        std::fs::remove_file(&staged)?;
+    }
+    {
+        let conn = Connection::open(&status.path)?;
+        conn.execute(
+            "DELETE FROM metadata WHERE key IN (?1, ?2)",
+            params![CREATED_AT_KEY, SOURCE_FINGERPRINT_KEY],
+        )?;
+        conn.execute("VACUUM INTO ?1", params![staged.to_string_lossy()])
+            .context("Failed to stage the sandbox for promotion")?;
+    }
+    remove_db_files(&live)?;
+    std::fs::rename(&staged, &live).context("Failed to move the sandbox into place")?;
+    remove_db_files(&status.path)?;
+    Ok(backup)
+}
+
+fn remove_db_files(path: &Path) -> Result<()> {
+    for suffix in ["", "-wal", "-shm", "-journal"] {
+        let file = PathBuf::from(format!("{}{}", path.display(), suffix));
+        if file.exists() {
+            std::fs::remove_file(&file).with_context(|| format!("Failed to remove {:?}", file))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let live = live_path_in(dir.path());
+        let sandbox = dir.path().join(SANDBOX_FILENAME);
+        assert!(status_in(dir.path()).unwrap().is_none());
+
+        super::super::init_database(Some(live.clone())).unwrap();
+        let conn = Connection::open(&live).unwrap();
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('PETR4', 'STOCK')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        create(&live, &sandbox).unwrap();
+        let conn = Connection::open(&sandbox).unwrap();
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('VALE3', 'STOCK')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        let st = status_in(dir.path()).unwrap().unwrap();
+        assert!(!st.live_changed);
+        assert!(st.created_at.is_some());
+        let assets = st.tables.iter().find(|t| t.table == "assets").unwrap();
+        assert_eq!((assets.live, assets.sandbox), (1, 2));
+
+        let backup = promote_in(dir.path(), false).unwrap();
+        assert!(backup.exists());
+        assert!(!sandbox.exists());
+        let conn = Connection::open(&live).unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM assets", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+        assert!(super::super::get_metadata(&conn, CREATED_AT_KEY)
+            .unwrap()
+            .is_none());
+        drop(conn);
+
+        // A sandbox is refused once the live data moved on
+        create(&live, &sandbox).unwrap();
+        let conn = Connection::open(&live).unwrap();
+        conn.execute("DELETE FROM assets WHERE ticker = 'VALE3'", [])
+            .unwrap();
+        drop(conn);
+        assert!(promote_in(dir.path(), false).is_err());
+        assert!(discard_in(dir.path()).unwrap());
+        assert!(!discard_in(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn test_promote_guards_every_user_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let live = live_path_in(dir.path());
+        let sandbox = dir.path().join(SANDBOX_FILENAME);
+        super::super::init_database(Some(live.clone())).unwrap();
+        let conn = Connection::open(&live).unwrap();
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('PETR4', 'STOCK')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+        create(&live, &sandbox).unwrap();
+
+        let conn = Connection::open(&live).unwrap();
+        let tables = tracked_tables(&conn).unwrap();
+        for table in ["asset_tags", "option_contracts", "import_sessions"] {
+            assert!(tables.iter().any(|t| t == table), "{} not tracked", table);
+        }
+        assert!(!tables.iter().any(|t| t == "price_history"));
+
+        // Recomputed ledger rows and prices are not changes
+        conn.execute_batch(
+            "INSERT INTO tax_ledger (year, month, tax_category, sales, profit_loss,
+                 loss_offset, exemption, tax_due, darf_status, tx_fingerprint)
+             VALUES (2025, 3, 'STOCK_SWING_TRADE', '30000', '1000', '0', '0', '150',
+                 'PENDING', 'f');
+             INSERT INTO price_history (asset_id, price_date, close_price, source)
+             VALUES (1, '2025-03-10', '38.5', 'YAHOO');",
+        )
+        .unwrap();
+        assert!(!status_in(dir.path()).unwrap().unwrap().live_changed);
+
+        // A paid DARF is
+        conn.execute(
+            "UPDATE tax_ledger SET paid_on = '2025-04-30', paid_amount = '150'",
+            [],
+        )
+        .unwrap();
+        assert!(status_in(dir.path()).unwrap().unwrap().live_changed);
+        drop(conn);
+        discard_in(dir.path()).unwrap();
+
+        // So is a tag, which the sandbox would otherwise overwrite
+        create(&live, &sandbox).unwrap();
+        let conn = Connection::open(&live).unwrap();
+        conn.execute(
+            "INSERT INTO asset_tags (asset_id, tag) VALUES (1, 'dividends')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+        let st = status_in(dir.path()).unwrap().unwrap();
+        assert!(st.live_changed);
+        let tags = st.tables.iter().find(|t| t.table == "asset_tags").unwrap();
+        assert_eq!((tags.live, tags.sandbox), (1, 0));
+        assert!(promote_in(dir.path(), false).is_err());
+    }
+}
@@ -9,7 +9,8 @@ CREATE TABLE IF NOT EXISTS assets (
     name TEXT,                     -- Full name of the asset
     cnpj TEXT,                     -- Cadastro Nacional da Pessoa Juridica (digits only)
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    currency TEXT                  -- Currency its prices are quoted in ('USD'); NULL for BRL
 );
 
 -- Create index on ticker for fast lookups
@@ -40,6 +41,50 @@ CREATE TABLE IF NOT EXISTS asset_registry (
 
 CREATE INDEX IF NOT EXISTS idx_asset_registry_ticker ON asset_registry(ticker);
 
+-- Portfolios (accounts) that transactions and income events belong to
+CREATE TABLE IF NOT EXISTS portfolios (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    declarant TEXT,                       -- Who declares it (IRPF), for couples declaring separately
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Everything recorded before portfolios existed belongs to 'default'
+INSERT OR IGNORE INTO portfolios (id, name) VALUES (1, 'default');
+
+-- Brokers (corretoras) where positions are held, as named in B3 exports
+CREATE TABLE IF NOT EXISTS brokers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,            -- 'XP INVESTIMENTOS CCTVM S/A'
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Brokerage notes (notas de corretagem) imported from PDF; their trades are
+-- in transactions with source 'NOTA_CORRETAGEM'
+CREATE TABLE IF NOT EXISTS broker_notes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    broker_id INTEGER,                    -- brokers.id
+    note_number TEXT NOT NULL,            -- 'Nr. nota'
+    trade_date DATE NOT NULL,             -- 'Data pregão'
+    settlement_date DATE,                 -- 'Líquido para' date
+    settlement_fee DECIMAL(15,2) NOT NULL DEFAULT 0,    -- Taxa de liquidação
+    registration_fee DECIMAL(15,2) NOT NULL DEFAULT 0,  -- Taxa de registro
+    emolumentos DECIMAL(15,2) NOT NULL DEFAULT 0,
+    brokerage DECIMAL(15,2) NOT NULL DEFAULT 0,         -- Corretagem / taxa operacional
+    iss DECIMAL(15,2) NOT NULL DEFAULT 0,
+    other_fees DECIMAL(15,2) NOT NULL DEFAULT 0,
+    irrf DECIMAL(15,2) NOT NULL DEFAULT 0,              -- IRRF on sales (0,005%)
+    irrf_day_trade DECIMAL(15,2) NOT NULL DEFAULT 0,    -- IRRF on day-trade gains (1%)
+    net_amount DECIMAL(15,2),                           -- Positive when credited
+    portfolio_id INTEGER NOT NULL DEFAULT 1,
+    import_session_id INTEGER,                          -- import_sessions.id
+    imported_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (broker_id) REFERENCES brokers(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_broker_notes_date ON broker_notes(trade_date);
+
 -- Transactions (buys and sells)
 CREATE TABLE IF NOT EXISTS transactions (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -56,6 +101,9 @@ CREATE TABLE IF NOT EXISTS transactions (
     notes TEXT,                         -- Optional notes
     source TEXT,                        -- 'CEI', 'B3_PORTAL', 'MANUAL'
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    portfolio_id INTEGER NOT NULL DEFAULT 1,  -- portfolios.id
+    broker_id INTEGER,                  -- brokers.id, when the source names the institution
+    import_session_id INTEGER,          -- import_sessions.id, when added by a file import
     FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
 );
 
@@ -63,6 +111,46 @@ CREATE INDEX IF NOT EXISTS idx_transactions_asset ON transactions(asset_id);
 CREATE INDEX IF NOT EXISTS idx_transactions_date ON transactions(trade_date);
 CREATE INDEX IF NOT EXISTS idx_transactions_type ON transactions(transaction_type);
 
+-- Term contract (compra a termo) terms, keyed by the TICKERT purchase
+CREATE TABLE IF NOT EXISTS term_contract_details (
+    transaction_id INTEGER PRIMARY KEY,  -- transactions.id of the TICKERT buy
+    expiry_date DATE,                    -- Vencimento
+    contracted_rate DECIMAL(9,4),        -- Annual rate (%) agreed with the broker
+    FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+);
+
+-- Option series details the ticker does not carry (see src/options.rs)
+CREATE TABLE IF NOT EXISTS option_contracts (
+    asset_id INTEGER PRIMARY KEY,        -- assets.id of the option (PETRA123)
+    underlying TEXT,                     -- Underlying ticker (PETR4)
+    strike DECIMAL(15,4),                -- Strike price, learned from exercises or set manually
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
+-- Exercises already matched to an option position
+CREATE TABLE IF NOT EXISTS option_exercises (
+    transaction_id INTEGER PRIMARY KEY,  -- Underlying trade made through the exercise
+    option_asset_id INTEGER NOT NULL,
+    closing_transaction_id INTEGER,      -- Option SELL closing the exercised quantity
+    quantity DECIMAL(15,4) NOT NULL,
+    premium DECIMAL(15,2) NOT NULL,      -- Premium moved into the underlying trade
+    FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
+    FOREIGN KEY (option_asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+    FOREIGN KEY (closing_transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+);
+
+-- Terms of private fixed income bonds, accrued daily (see src/fixed_income.rs)
+CREATE TABLE IF NOT EXISTS fixed_income_terms (
+    asset_id INTEGER PRIMARY KEY,        -- assets.id of the bond (CDB_BANCOX_2027)
+    kind TEXT NOT NULL,                  -- CDB, LCI, LCA, CRI, CRA
+    issue_date DATE NOT NULL,
+    maturity_date DATE NOT NULL,
+    indexer TEXT NOT NULL,               -- CDI, IPCA or PRE
+    rate DECIMAL(9,4) NOT NULL,          -- % of the CDI, spread over IPCA (% a year) or fixed rate (% a year)
+    issue_price DECIMAL(15,6) NOT NULL,  -- Unit value (PU) on the issue date
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
 -- Corporate actions (splits, reverse splits, bonuses)
 -- Query-time adjustment: actions are NOT applied to transactions
 -- Adjustments are computed dynamically when calculating positions
@@ -77,6 +165,8 @@ CREATE TABLE IF NOT EXISTS corporate_actions (
     source TEXT,                     -- 'YAHOO', 'MANUAL', 'B3', 'MOVIMENTACAO'
     notes TEXT,
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    applied_at DATETIME,             -- When it was reviewed; a bonus counts only once applied. NULL = pending review
+    import_session_id INTEGER,       -- import_sessions.id, when added by a file import
     FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
 );
 
@@ -126,13 +216,14 @@ CREATE TABLE IF NOT EXISTS price_history (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     asset_id INTEGER NOT NULL,
     price_date DATE NOT NULL,
-    close_price DECIMAL(15,4) NOT NULL,
+    close_price DECIMAL(15,4) NOT NULL,  -- Raw close as traded
     open_price DECIMAL(15,4),
     high_price DECIMAL(15,4),
     low_price DECIMAL(15,4),
     volume BIGINT,
     source TEXT,                     -- 'YAHOO'
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    adjusted_close DECIMAL(15,4),    -- Split/dividend adjusted close, when the source provides it
     FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE,
     UNIQUE(asset_id, price_date)
 );
@@ -140,6 +231,44 @@ CREATE TABLE IF NOT EXISTS price_history (
 CREATE INDEX IF NOT EXISTS idx_price_history_asset ON price_history(asset_id);
 CREATE INDEX IF NOT EXISTS idx_price_history_date ON price_history(price_date);
 
+-- Intraday quotes (live prices), kept apart from daily closes
+CREATE TABLE IF NOT EXISTS price_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    asset_id INTEGER NOT NULL,
+    snapshot_at DATETIME NOT NULL,   -- UTC timestamp of the quote
+    price DECIMAL(15,4) NOT NULL,
+    source TEXT,
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+    UNIQUE(asset_id, snapshot_at)
+);
+
+CREATE INDEX IF NOT EXISTS idx_price_snapshots_asset ON price_snapshots(asset_id, snapshot_at DESC);
+
+-- Latest live quote per ticker, reused across runs until it expires
+CREATE TABLE IF NOT EXISTS quote_cache (
+    ticker TEXT PRIMARY KEY,
+    price DECIMAL(15,4) NOT NULL,
+    quoted_at DATETIME NOT NULL,     -- UTC time of the quote
+    fetched_at DATETIME NOT NULL,
+    expires_at DATETIME NOT NULL,    -- UTC; fetched again after this
+    source TEXT NOT NULL
+);
+
+-- Manual valuations of assets without a market price (FIPs, closed funds,
+-- delisted shares), per share/quota
+CREATE TABLE IF NOT EXISTS asset_valuations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    asset_id INTEGER NOT NULL,
+    valuation_date DATE NOT NULL,
+    value DECIMAL(15,4) NOT NULL,
+    notes TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+    UNIQUE(asset_id, valuation_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_asset_valuations_asset ON asset_valuations(asset_id, valuation_date DESC);
+
 -- Government bond yield/rate history (Tesouro Direto)
 CREATE TABLE IF NOT EXISTS gov_bond_rates (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -155,7 +284,93 @@ CREATE TABLE IF NOT EXISTS gov_bond_rates (
 CREATE INDEX IF NOT EXISTS idx_gov_bond_rates_asset ON gov_bond_rates(asset_id);
 CREATE INDEX IF NOT EXISTS idx_gov_bond_rates_date ON gov_bond_rates(price_date);
 
--- Portfolio snapshots with fingerprint-based invalidation
+-- Benchmark series (IBOV/IFIX index levels, CDI/SELIC daily rates, IPCA monthly rates)
+CREATE TABLE IF NOT EXISTS benchmark_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    benchmark TEXT NOT NULL,         -- 'IBOV', 'IFIX', 'CDI', 'SELIC', 'IPCA'
+    value_date DATE NOT NULL,
+    value DECIMAL(15,6) NOT NULL,    -- Index level (IBOV/IFIX) or rate in % per day/month
+    source TEXT,                     -- 'YAHOO', 'BCB'
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(benchmark, value_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_benchmark_history_date ON benchmark_history(benchmark, value_date);
+
+-- Currency exchange rates (e.g., USD/BRL PTAX) used for BDR FX attribution
+CREATE TABLE IF NOT EXISTS fx_rates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pair TEXT NOT NULL,              -- 'USDBRL', 'EURBRL'
+    rate_date DATE NOT NULL,
+    rate DECIMAL(15,6) NOT NULL,     -- Units of quote currency per unit of base
+    source TEXT,                     -- 'BCB'
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(pair, rate_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_fx_rates_date ON fx_rates(pair, rate_date);
+
+-- Trade idea journal (thesis written before trading, reviewed afterwards)
+CREATE TABLE IF NOT EXISTS journal_entries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    asset_id INTEGER NOT NULL,
+    entry_date DATE NOT NULL,
+    thesis TEXT NOT NULL,
+    target_price DECIMAL(15,4),
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_journal_entries_asset ON journal_entries(asset_id, entry_date);
+
+-- Transactions executed as part of a journal entry
+CREATE TABLE IF NOT EXISTS journal_links (
+    entry_id INTEGER NOT NULL,
+    transaction_id INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (entry_id, transaction_id),
+    FOREIGN KEY (entry_id) REFERENCES journal_entries(id) ON DELETE CASCADE,
+    FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+);
+
+-- User-defined asset groups (goals/buckets such as "aposentadoria" or "reserva")
+CREATE TABLE IF NOT EXISTS asset_tags (
+    asset_id INTEGER NOT NULL,
+    tag TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (asset_id, tag),
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_asset_tags_tag ON asset_tags(tag);
+
+-- Issuer data from the Receita Federal CNPJ registry (via BrasilAPI/minhareceita)
+CREATE TABLE IF NOT EXISTS asset_issuers (
+    asset_id INTEGER PRIMARY KEY,
+    cnpj TEXT NOT NULL,              -- digits only
+    legal_name TEXT NOT NULL,        -- razão social
+    trade_name TEXT,                 -- nome fantasia
+    situation TEXT,                  -- situação cadastral: 'ATIVA', 'BAIXADA', ...
+    situation_date DATE,
+    source TEXT NOT NULL,            -- 'BRASILAPI', 'MINHARECEITA'
+    fetched_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
+-- FII net asset value per quota from the CVM monthly reports (informe mensal), for P/VP
+CREATE TABLE IF NOT EXISTS fii_nav_history (
+    asset_id INTEGER NOT NULL,
+    reference_date DATE NOT NULL,          -- Month the report refers to (first day)
+    nav_per_quota DECIMAL(15,6) NOT NULL,  -- Valor patrimonial da cota
+    net_assets DECIMAL(20,2),              -- Patrimônio líquido
+    quotas DECIMAL(20,4),                  -- Cotas emitidas
+    source TEXT NOT NULL DEFAULT 'CVM',
+    fetched_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (asset_id, reference_date),
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
+-- Per-asset portfolio snapshots with fingerprint-based invalidation
 CREATE TABLE IF NOT EXISTS position_snapshots (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     snapshot_date DATE NOT NULL,
@@ -168,6 +383,11 @@ CREATE TABLE IF NOT EXISTS position_snapshots (
     tx_fingerprint TEXT NOT NULL,
     label TEXT,
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    valued_on DATE,                  -- Manual valuation used as market_price, if any
+    total_cost DECIMAL(15,4),        -- Cost basis as computed (average_cost may be rounded)
+    fx_currency TEXT,                -- Currency market_price was converted from, if any
+    fx_rate DECIMAL(15,6),           -- PTAX used for the conversion
+    fx_date DATE,                    -- Date of that PTAX
     FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE,
     UNIQUE(snapshot_date, asset_id)
 );
@@ -175,22 +395,18 @@ CREATE TABLE IF NOT EXISTS position_snapshots (
 CREATE INDEX IF NOT EXISTS idx_position_snapshots_date ON position_snapshots(snapshot_date DESC);
 CREATE INDEX IF NOT EXISTS idx_position_snapshots_asset ON position_snapshots(asset_id, snapshot_date DESC);
 
--- Realized gains aggregation
-CREATE TABLE IF NOT EXISTS realized_gains (
-    id INTEGER PRIMARY KEY AUTOINCREMENT,
-    asset_id INTEGER NOT NULL,
-    sale_date DATE NOT NULL,
-    quantity_sold DECIMAL(15,4) NOT NULL,
-    cost_basis DECIMAL(15,4) NOT NULL,
-    sale_price DECIMAL(15,4) NOT NULL,
-    realized_gain DECIMAL(15,4) NOT NULL,
-    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+-- Portfolio total at the end of each valued day (month ends included), reused
+-- while the data it was valued from is unchanged; also marks the day's
+-- position_snapshots rows as valid, empty portfolios included
+CREATE TABLE IF NOT EXISTS valuation_snapshots (
+    snapshot_date DATE PRIMARY KEY,
+    market_value DECIMAL(15,4) NOT NULL,
+    total_cost DECIMAL(15,4) NOT NULL,
+    positions INTEGER NOT NULL,
+    data_fingerprint TEXT NOT NULL,   -- Trades, actions, valuations and closes up to the day
+    computed_at DATETIME DEFAULT CURRENT_TIMESTAMP
 );
 
-CREATE INDEX IF NOT EXISTS idx_realized_gains_date ON realized_gains(sale_date);
-CREATE INDEX IF NOT EXISTS idx_realized_gains_asset ON realized_gains(asset_id, sale_date);
-
 -- Cash flow events for time-weighted return calculation
 CREATE TABLE IF NOT EXISTS cash_flows (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -231,6 +447,22 @@ CREATE TABLE IF NOT EXISTS import_state (
     PRIMARY KEY (source, entry_type)
 );
 
+-- Import sessions: one per imported file; the rows it added carry its id in
+-- import_session_id so `import undo` can delete them together
+CREATE TABLE IF NOT EXISTS import_sessions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file TEXT NOT NULL,
+    format TEXT NOT NULL,                -- Detected format: 'CEI', 'Movimentação', ...
+    portfolio_id INTEGER NOT NULL DEFAULT 1,
+    started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    finished_at DATETIME,
+    transactions INTEGER NOT NULL DEFAULT 0,
+    corporate_actions INTEGER NOT NULL DEFAULT 0,
+    income_events INTEGER NOT NULL DEFAULT 0,
+    import_state_changes TEXT,           -- JSON: last-import dates moved, with their previous value
+    undone_at DATETIME
+);
+
 -- Tax events (monthly tracking for swing trade, day trade)
 CREATE TABLE IF NOT EXISTS tax_events (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -262,10 +494,14 @@ CREATE TABLE IF NOT EXISTS income_events (
     amount_per_quota DECIMAL(15,4) NOT NULL,
     total_amount DECIMAL(15,4) NOT NULL,
     withholding_tax DECIMAL(15,4) DEFAULT 0,  -- Tax withheld at source
+    foreign_tax_withheld DECIMAL(15,4),  -- Withheld abroad before payment (BDRs); NULL when not reported
     is_quota_pre_2026 BOOLEAN,           -- Track quota vintage for tax rules
     source TEXT,                         -- 'YAHOO', 'CEI', 'MANUAL'
     notes TEXT,
     created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    portfolio_id INTEGER NOT NULL DEFAULT 1,  -- portfolios.id
+    broker_id INTEGER,                        -- brokers.id, when the source names the institution
+    import_session_id INTEGER,                -- import_sessions.id, when added by a file import
     FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
 );
 
@@ -273,6 +509,45 @@ CREATE INDEX IF NOT EXISTS idx_income_events_asset ON income_events(asset_id);
 CREATE INDEX IF NOT EXISTS idx_income_events_date ON income_events(event_date);
 CREATE INDEX IF NOT EXISTS idx_income_events_type ON income_events(event_type);
 
+-- Income credited to the account (Movimentação income rows), linked to the
+-- income event each one pays
+CREATE TABLE IF NOT EXISTS cash_credits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    asset_id INTEGER NOT NULL,
+    credit_date DATE NOT NULL,
+    movement_type TEXT NOT NULL,         -- As in the statement: 'Dividendo', 'Rendimento', ...
+    event_type TEXT NOT NULL,            -- 'DIVIDEND', 'AMORTIZATION', 'JCP'
+    amount DECIMAL(15,4) NOT NULL,       -- Cash credited (net of IRRF)
+    source TEXT NOT NULL,                -- 'MOVIMENTACAO'
+    income_event_id INTEGER,             -- NULL while no event matches
+    broker_id INTEGER,
+    portfolio_id INTEGER NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    import_session_id INTEGER,
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE,
+    FOREIGN KEY (income_event_id) REFERENCES income_events(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_cash_credits_asset ON cash_credits(asset_id, credit_date);
+CREATE INDEX IF NOT EXISTS idx_cash_credits_event ON cash_credits(income_event_id);
+
+-- Holdings a custodian reported on a date (B3 Posição export, B3 API),
+-- compared with the computed history by `inconsistencies positions`
+CREATE TABLE IF NOT EXISTS position_statements (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    asset_id INTEGER NOT NULL,
+    statement_date DATE NOT NULL,
+    quantity DECIMAL(15,4) NOT NULL,     -- Summed over the institutions holding it
+    source TEXT NOT NULL,                -- 'POSICAO', 'B3_API'
+    portfolio_id INTEGER NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    import_session_id INTEGER,
+    UNIQUE(asset_id, statement_date, source, portfolio_id),
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_position_statements_date ON position_statements(statement_date);
+
 -- Inconsistencies (missing or invalid data tracked for later resolution)
 CREATE TABLE IF NOT EXISTS inconsistencies (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -327,6 +602,35 @@ CREATE TABLE IF NOT EXISTS loss_carryforward_snapshots (
 
 CREATE INDEX IF NOT EXISTS idx_loss_carryforward_snapshots_year ON loss_carryforward_snapshots(year);
 
+-- Monthly tax ledger: each category's tax as last computed, and the DARF paid for the month
+CREATE TABLE IF NOT EXISTS tax_ledger (
+    year INTEGER NOT NULL,
+    month INTEGER NOT NULL,
+    tax_category TEXT NOT NULL,          -- Same codes as loss_carryforward
+    sales DECIMAL(15,4) NOT NULL,
+    profit_loss DECIMAL(15,4) NOT NULL,  -- Net result before offsets
+    loss_offset DECIMAL(15,4) NOT NULL,  -- Earlier losses deducted
+    exemption DECIMAL(15,4) NOT NULL,    -- Gain exempt under the monthly sales limit
+    tax_due DECIMAL(15,4) NOT NULL,
+    darf_status TEXT NOT NULL,           -- 'NOT_DUE', 'PENDING', 'PAID'
+    paid_on DATE,                        -- Kept when the month is recomputed
+    paid_amount DECIMAL(15,2),
+    tx_fingerprint TEXT NOT NULL,        -- Year fingerprint the row was computed from
+    computed_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (year, month, tax_category)
+);
+
+-- Tickers whose last price fetch failed, retried with backoff instead of
+-- refetching the whole portfolio
+CREATE TABLE IF NOT EXISTS price_retry_queue (
+    asset_id INTEGER PRIMARY KEY,
+    attempts INTEGER NOT NULL,           -- Failed fetches in a row
+    last_error TEXT NOT NULL,
+    first_failed_at DATETIME NOT NULL,
+    next_attempt_at DATETIME NOT NULL,   -- UTC; not retried before this
+    FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+);
+
 -- Metadata table for schema version and app settings
 CREATE TABLE IF NOT EXISTS metadata (
     key TEXT PRIMARY KEY,
@@ -337,3 +641,99 @@ CREATE TABLE IF NOT EXISTS metadata (
 -- Insert initial schema version
 INSERT OR IGNORE INTO metadata (key, value) VALUES ('schema_version', '2');
 INSERT OR IGNORE INTO metadata (key, value) VALUES ('db_created_at', datetime('now'));
+
+-- Shared aggregations, recreated on every open so their definitions follow
+-- the release. Both the reports and external tools
+-- (sqlite3, spreadsheets, BI) read these aggregates from here instead of
+-- re-deriving them. Decimal columns are stored as text, so sums are REAL.
+
+-- Quantity held today per asset across portfolios, under its current ticker:
+-- trades, splits, reverse splits and applied bonuses, renames and spin-off/merger
+-- exchanges.
+-- Cost basis needs the trade-by-trade average cost, see `interest portfolio`.
+DROP VIEW IF EXISTS current_positions;
+CREATE VIEW current_positions AS
+WITH RECURSIVE successor(asset_id, current_id) AS (
+    SELECT id, id FROM assets
+    UNION
+    SELECT s.asset_id, r.to_asset_id
+    FROM successor s
+    JOIN asset_renames r ON r.from_asset_id = s.current_id
+    WHERE r.effective_date <= date('now')
+),
+renamed AS (
+    SELECT s.asset_id, s.current_id
+    FROM successor s
+    WHERE NOT EXISTS (
+        SELECT 1 FROM asset_renames r
+        WHERE r.from_asset_id = s.current_id AND r.effective_date <= date('now'))
+),
+movements(asset_id, quantity) AS (
+    SELECT asset_id,
+           CASE transaction_type WHEN 'SELL' THEN -CAST(quantity AS REAL)
+                                 ELSE CAST(quantity AS REAL) END
+    FROM transactions
+    WHERE trade_date <= date('now')
+    UNION ALL
+    SELECT asset_id, CAST(quantity_adjustment AS REAL)
+    FROM corporate_actions
+    WHERE (action_type IN ('SPLIT', 'REVERSE_SPLIT')
+           OR (action_type = 'BONUS' AND applied_at IS NOT NULL))
+      AND ex_date <= date('now')
+    UNION ALL
+    SELECT to_asset_id, CAST(to_quantity AS REAL)
+    FROM asset_exchanges
+    WHERE effective_date <= date('now')
+),
+held AS (
+    SELECT r.current_id AS asset_id, SUM(m.quantity) AS quantity
+    FROM movements m
+    JOIN renamed r ON r.asset_id = m.asset_id
+    GROUP BY r.current_id
+)
+SELECT a.id AS asset_id,
+       a.ticker,
+       a.asset_type,
+       h.quantity,
+       p.price_date AS last_price_date,
+       CAST(p.close_price AS REAL) AS last_close,
+       h.quantity * CAST(p.close_price AS REAL) AS market_value
+FROM held h
+JOIN assets a ON a.id = h.asset_id
+LEFT JOIN price_history p ON p.asset_id = h.asset_id
+    AND p.price_date = (SELECT MAX(price_date) FROM price_history WHERE asset_id = h.asset_id)
+WHERE ABS(h.quantity) > 0.00005
+  -- Merged assets no longer exist as a position
+  AND NOT EXISTS (
+      SELECT 1 FROM asset_exchanges e
+      WHERE e.from_asset_id = h.asset_id AND e.event_type = 'MERGER'
+        AND e.effective_date <= date('now'));
+
+-- Income paid per month, portfolio and event type
+DROP VIEW IF EXISTS monthly_income;
+CREATE VIEW monthly_income AS
+SELECT strftime('%Y-%m', event_date) AS month,
+       portfolio_id,
+       event_type,
+       COUNT(*) AS payments,
+       SUM(CAST(total_amount AS REAL)) AS gross,
+       SUM(CAST(COALESCE(withholding_tax, 0) AS REAL)) AS withheld,
+       SUM(CAST(total_amount AS REAL) - CAST(COALESCE(withholding_tax, 0) AS REAL)) AS net
+FROM income_events
+GROUP BY month, portfolio_id, event_type;
+
+-- Realized gains per month and tax category as of the last tax computation
+-- (`interest tax report`), from the monthly tax ledger
+DROP VIEW IF EXISTS realized_gains;
+CREATE VIEW realized_gains AS
+SELECT printf('%04d-%02d', year, month) AS month,
+       year,
+       month AS month_number,
+       tax_category,
+       CAST(sales AS REAL) AS sales,
+       CAST(profit_loss AS REAL) AS profit_loss,
+       CAST(loss_offset AS REAL) AS loss_offset,
+       CAST(exemption AS REAL) AS exemption,
+       CAST(tax_due AS REAL) AS tax_due,
+       darf_status
+FROM tax_ledger;
@@ -0,0 +1,73 @@
+//! Manual valuations of assets that have no market price.
+//!
+//! FIPs, closed funds and delisted shares awaiting resolution never get a
+//! close from the price providers. A valuation per share or quota, dated,
+//! stands in for the price from that day on, until a newer valuation or a
+//! newer market close.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// The value of one share or quota on a date
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Valuation {
+    pub asset_id: i64,
+    pub valuation_date: NaiveDate,
+    pub value: Decimal,
+    pub notes: Option<String>,
+}
+
+/// Record the valuation, replacing one already set for the same day
+pub fn set_valuation(conn: &Connection, valuation: &Valuation) -> Result<()> {
+    conn.execute(
+        "INSERT INTO asset_valuations (asset_id, valuation_date, value, notes)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(asset_id, valuation_date)
+         DO UPDATE SET value = excluded.value, notes = excluded.notes",
+        params![
+            valuation.asset_id,
+            valuation.valuation_date,
+            valuation.value.to_string(),
+            valuation.notes
+        ],
+    )?;
+    Ok(())
+}
+
+/// Latest valuation on or before `date`
+pub fn valuation_on_or_before(
+    conn: &Connection,
+    asset_id: i64,
+    date: NaiveDate,
+) -> Result<Option<Valuation>> {
+    let valuation = conn
+        .query_row(
+            "SELECT asset_id, valuation_date, value, notes
+             FROM asset_valuations
+             WHERE asset_id = ?1 AND valuation_date <= ?2
+             ORDER BY valuation_date DESC
+             LIMIT 1",
+            params![asset_id, date],
+            |row| {
+                Ok(Valuation {
+                    asset_id: row.get(0)?,
+                    valuation_date: row.get(1)?,
+                    value: super::get_decimal_value(row, 2)?,
+                    notes: row.get(3)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(valuation)
+}
+
+/// Remove every valuation of an asset; returns how many there were
+pub fn clear_valuations(conn: &Connection, asset_id: i64) -> Result<usize> {
+    Ok(conn.execute(
+        "DELETE FROM asset_valuations WHERE asset_id = ?1",
+        [asset_id],
+    )?)
+}
@@ -6,18 +6,44 @@
 pub mod performance;
 use performance::dispatch_performance;
 mod actions;
+mod archive;
 mod assets;
+mod b3_sync;
+mod brokers;
 mod cashflow;
+mod compare;
+mod daemon;
+mod fixed_income;
 pub mod imports;
 pub mod imports_helpers;
+mod income_forecast;
+mod income_reconcile;
 mod inconsistencies;
 mod inspect;
 mod irpf;
+mod journal;
+mod options;
 mod portfolio;
+mod portfolios;
+mod position_discrepancies;
 mod prices;
+mod rebalance;
+mod recalculate;
+mod reports;
+mod sandbox;
+mod subscriptions;
+mod tax_exemption;
+mod tax_ledger;
+mod tax_rules;
+mod tax_simulate;
 mod terms;
+mod tesouro;
 mod tickers;
+mod transaction_editor;
 mod transactions;
+mod version;
+mod watch;
+mod whatsnew;
 use crate::utils::format_currency;
 use crate::{db, tax};
 use anyhow::Result;
@@ -30,17 +56,45 @@ pub async fn dispatch_command(command: &crate::cli::Commands, json_output: bool)
 
     match command {
         Commands::Import {
+            action: Some(action),
+            ..
+        } => imports::dispatch_import_sessions(action, json_output),
+        Commands::Import {
+            action: None,
             file,
             dry_run,
             force_reimport,
-        } => imports::dispatch_import(file, *dry_run, *force_reimport, json_output).await,
+            format,
+            mapping,
+        } => {
+            let file = file
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("Missing file to import"))?;
+            let mapping = match (format.as_str(), mapping) {
+                ("custom", mapping) => mapping.as_deref(),
+                (_, Some(_)) => return Err(anyhow::anyhow!("--mapping requires --format custom")),
+                _ => None,
+            };
+            imports::dispatch_import(file, *dry_run, *force_reimport, mapping, json_output).await
+        }
         Commands::ImportIrpf {
             file,
             year,
             dry_run,
         } => irpf::dispatch_irpf_import(file, *year, *dry_run).await,
+        Commands::WatchImports { once } => watch::dispatch_watch_imports(*once, json_output).await,
+        Commands::Daemon { status } => daemon::dispatch_daemon(*status, json_output).await,
+        Commands::SyncB3 { from, dry_run } => {
+            b3_sync::dispatch_sync_b3(from.as_deref(), *dry_run, json_output).await
+        }
         Commands::Portfolio { action } => portfolio::dispatch_portfolio(action, json_output).await,
         Commands::Performance { action } => dispatch_performance(action, json_output).await,
+        Commands::Reports { action } => reports::dispatch_reports(action, json_output).await,
+        Commands::Compare { tickers, period } => {
+            compare::dispatch_compare(tickers, period, json_output)
+        }
+        Commands::Rebalance { action } => rebalance::dispatch_rebalance(action, json_output),
+        Commands::Whatsnew { since } => whatsnew::dispatch_whatsnew(since.as_deref(), json_output),
         Commands::CashFlow { action } => cashflow::dispatch_cashflow(action, json_output).await,
         Commands::Tax { action } => dispatch_tax(action, json_output).await,
         Commands::Income { action } => dispatch_income(action, json_output).await,
@@ -49,10 +103,28 @@ pub async fn dispatch_command(command: &crate::cli::Commands, json_output: bool)
         Commands::Transactions { action } => {
             transactions::dispatch_transactions(action, json_output).await
         }
-        Commands::Inspect { file, full, column } => {
-            inspect::dispatch_inspect(file, *full, *column).await
-        }
+        Commands::Journal { action } => journal::dispatch_journal(action, json_output).await,
+        Commands::Db { action } => archive::dispatch_db(action, json_output).await,
+        Commands::Portfolios { action } => portfolios::dispatch_portfolios(action, json_output),
+        Commands::Sandbox { action } => sandbox::dispatch_sandbox(action, json_output),
+        Commands::Version { full } => version::dispatch_version(*full, json_output).await,
+        Commands::Inspect {
+            file,
+            full,
+            column,
+            pages,
+        } => inspect::dispatch_inspect(file, *full, *column, pages.as_deref()).await,
         Commands::ProcessTerms => terms::dispatch_process_terms().await,
+        Commands::Recalculate { through } => {
+            recalculate::dispatch_recalculate(*through, json_output)
+        }
+        Commands::Terms { action } => terms::dispatch_terms(action, json_output).await,
+        Commands::Options { action } => options::dispatch_options(action, json_output),
+        Commands::Subscriptions => subscriptions::dispatch_subscriptions(json_output),
+        Commands::Tesouro { action } => tesouro::dispatch_tesouro(action, json_output),
+        Commands::FixedIncome { action } => {
+            fixed_income::dispatch_fixed_income(action, json_output)
+        }
         Commands::Inconsistencies { action } => {
             inconsistencies::dispatch_inconsistencies(action, json_output).await
         }
@@ -69,23 +141,129 @@ pub async fn dispatch_command(command: &crate::cli::Commands, json_output: bool)
 
 async fn dispatch_tax(action: &crate::cli::TaxCommands, json_output: bool) -> Result<()> {
     match action {
-        crate::cli::TaxCommands::Report { year, export } => {
-            dispatch_tax_report(*year, *export, json_output).await
+        crate::cli::TaxCommands::Report {
+            year,
+            by_declarant: true,
+            ..
+        } => dispatch_tax_by_declarant(*year, json_output),
+        crate::cli::TaxCommands::Report {
+            year,
+            export,
+            bens_e_direitos: true,
+            ..
+        } => dispatch_bens_e_direitos(*year, *export, json_output),
+        crate::cli::TaxCommands::Report {
+            year,
+            export,
+            table,
+            ..
+        } => dispatch_tax_report(*year, *export, table, json_output).await,
+        crate::cli::TaxCommands::Summary {
+            year,
+            by_broker: true,
+        } => brokers::dispatch_broker_statement(*year, brokers::StatementView::Tax, json_output),
+        crate::cli::TaxCommands::Summary { year, .. } => {
+            dispatch_tax_summary(*year, json_output).await
         }
-        crate::cli::TaxCommands::Summary { year } => dispatch_tax_summary(*year, json_output).await,
         crate::cli::TaxCommands::Calculate { month } => dispatch_tax_calculate(month).await,
+        crate::cli::TaxCommands::Preview { table } => {
+            dispatch_tax_preview(table, json_output).await
+        }
+        crate::cli::TaxCommands::Darf { month, plain } => {
+            tax_ledger::dispatch_tax_darf(month, *plain, json_output)
+        }
+        crate::cli::TaxCommands::Exemption {
+            month,
+            sell,
+            ticker,
+            day_trade,
+        } => tax_exemption::dispatch_tax_exemption(
+            month.as_deref(),
+            sell.as_deref(),
+            ticker.as_deref(),
+            *day_trade,
+            json_output,
+        ),
+        crate::cli::TaxCommands::Withholding { year } => {
+            dispatch_tax_withholding(*year, json_output).await
+        }
+        crate::cli::TaxCommands::Gcap { year, output } => {
+            dispatch_tax_gcap(*year, output.as_deref(), json_output)
+        }
+        crate::cli::TaxCommands::FixedIncome { year } => {
+            fixed_income::dispatch_tax_fixed_income(*year, json_output)
+        }
+        crate::cli::TaxCommands::Rules { year } => {
+            tax_rules::dispatch_tax_rules(*year, json_output)
+        }
+        crate::cli::TaxCommands::Simulate {
+            ticker,
+            quantity,
+            price,
+            date,
+            fees,
+            day_trade,
+        } => tax_simulate::dispatch_tax_simulate(
+            ticker,
+            quantity,
+            price,
+            date.as_deref(),
+            fees,
+            *day_trade,
+            json_output,
+        ),
+        crate::cli::TaxCommands::Ledger { year } => {
+            tax_ledger::dispatch_tax_ledger(*year, json_output)
+        }
+        crate::cli::TaxCommands::MarkPaid {
+            month,
+            on,
+            amount,
+            clear,
+        } => tax_ledger::dispatch_tax_mark_paid(
+            month,
+            on.as_deref(),
+            amount.as_deref(),
+            *clear,
+            json_output,
+        ),
     }
 }
 
 async fn dispatch_income(action: &crate::cli::IncomeCommands, json_output: bool) -> Result<()> {
     match action {
-        crate::cli::IncomeCommands::Show { year } => dispatch_income_show(*year, json_output).await,
-        crate::cli::IncomeCommands::Detail { year, asset } => {
-            dispatch_income_detail(*year, asset.as_deref(), json_output).await
+        crate::cli::IncomeCommands::Show { year, tag } => {
+            dispatch_income_show(*year, tag.as_deref(), json_output).await
+        }
+        crate::cli::IncomeCommands::Detail { year, asset, table } => {
+            dispatch_income_detail(*year, asset.as_deref(), table, json_output).await
         }
-        crate::cli::IncomeCommands::Summary { year } => {
+        crate::cli::IncomeCommands::Summary {
+            year: Some(year),
+            by_broker: true,
+        } => brokers::dispatch_broker_statement(*year, brokers::StatementView::Income, json_output),
+        crate::cli::IncomeCommands::Summary { year, .. } => {
             dispatch_income_summary(*year, json_output).await
         }
+        crate::cli::IncomeCommands::Reconcile { year } => {
+            income_reconcile::dispatch_income_reconcile(*year, json_output)
+        }
+        crate::cli::IncomeCommands::Forecast {
+            months,
+            asset,
+            net,
+            dividend_tax,
+            dividend_threshold,
+            tax_from,
+        } => income_forecast::dispatch_income_forecast(
+            *months,
+            asset.as_deref(),
+            *net,
+            dividend_tax.as_deref(),
+            dividend_threshold.as_deref(),
+            tax_from.as_deref(),
+            json_output,
+        ),
         crate::cli::IncomeCommands::Add {
             ticker,
             event_type,
@@ -93,6 +271,7 @@ async fn dispatch_income(action: &crate::cli::IncomeCommands, json_output: bool)
             date,
             ex_date,
             withholding,
+            foreign_tax,
             amount_per_quota,
             notes,
         } => {
@@ -102,7 +281,8 @@ async fn dispatch_income(action: &crate::cli::IncomeCommands, json_output: bool)
                 total_amount,
                 date,
                 ex_date.as_deref(),
-                withholding,
+                withholding.as_deref(),
+                foreign_tax.as_deref(),
                 amount_per_quota,
                 notes.as_deref(),
                 json_output,
@@ -112,13 +292,16 @@ async fn dispatch_income(action: &crate::cli::IncomeCommands, json_output: bool)
     }
 }
 
-async fn dispatch_tax_report(year: i32, export_csv: bool, json_output: bool) -> Result<()> {
+async fn dispatch_tax_report(
+    year: i32,
+    export_csv: bool,
+    table: &crate::cli::formatters::TableArgs,
+    json_output: bool,
+) -> Result<()> {
     use rust_decimal::Decimal;
     use serde::Serialize;
-    use tabled::{
-        settings::{object::Columns, Alignment, Modify, Style},
-        Table, Tabled,
-    };
+    use std::fmt::Write;
+    use tabled::Tabled;
 
     info!("Generating IRPF annual report for {}", year);
 
@@ -138,6 +321,10 @@ async fn dispatch_tax_report(year: i32, export_csv: bool, json_output: bool) ->
     let has_income = income_summary
         .iter()
         .any(|entry| entry.dividends_net > Decimal::ZERO || entry.jcp_net > Decimal::ZERO);
+    let carne_leao = tax::foreign_dividends::carne_leao_year(&conn, year)?;
+    let fund_income = tax::fund_income::fund_income_year(&conn, year)?;
+    let foreign_gains =
+        tax::foreign_gains::foreign_gains_year(&conn, year)?.filter(|gains| !gains.is_empty());
 
     if json_output {
         // Emit concise JSON suitable for tests and scripting
@@ -172,8 +359,10 @@ async fn dispatch_tax_report(year: i32, export_csv: bool, json_output: bool) ->
             total_net: rust_decimal::Decimal,
         }
 
-        let income: Vec<IncomeSummaryJson> = income_summary
-            .iter()
+        let income: Vec<IncomeSummaryJson> = table
+            .paginate(income_summary.iter().collect())
+            .rows
+            .into_iter()
             .map(|entry| IncomeSummaryJson {
                 ticker: entry.ticker.clone(),
                 asset_type: entry.asset_type.as_str().to_string(),
@@ -192,87 +381,119 @@ async fn dispatch_tax_report(year: i32, export_csv: bool, json_output: bool) ->
             "annual_total_tax": report.annual_total_tax,
             "monthly_summaries": monthly,
             "income_summary": income,
+            "foreign_dividends": carne_leao,
+            "fund_income": fund_income,
+            "foreign_gains": foreign_gains,
         });
         println!("{}", serde_json::to_string_pretty(&payload)?);
         return Ok(());
-    } else {
-        println!(
-            "\n{} Annual IRPF Tax Report - {}\n",
-            "📊".cyan().bold(),
-            year
-        );
     }
 
-    if report.monthly_summaries.is_empty() && !has_income {
-        println!(
+    let mut out = String::new();
+    writeln!(
+        out,
+        "\n{} Annual IRPF Tax Report - {}\n",
+        "📊".cyan().bold(),
+        year
+    )?;
+
+    if report.monthly_summaries.is_empty()
+        && !has_income
+        && carne_leao.months.is_empty()
+        && !fund_income.has_taxable()
+        && foreign_gains.is_none()
+    {
+        writeln!(
+            out,
             "\n{} No transactions found for year {}\n",
             "ℹ".blue().bold(),
             year
-        );
+        )?;
+        print!("{}", out);
         return Ok(());
     }
 
     // Show prior-year carryforward losses if any
     if !report.previous_losses_carry_forward.is_empty() {
-        println!("{} Carryover from previous years:", "📦".yellow().bold());
+        writeln!(
+            out,
+            "{} Carryover from previous years:",
+            "📦".yellow().bold()
+        )?;
         for (category, amount) in &report.previous_losses_carry_forward {
-            println!(
+            writeln!(
+                out,
                 "  {}: {}",
                 category.display_name(),
                 format_currency(*amount)
-            );
+            )?;
         }
-        println!();
+        writeln!(out)?;
     }
 
     if !report.monthly_summaries.is_empty() {
         // Monthly breakdown
-        println!("{}", "Monthly Summary:".bold());
+        writeln!(out, "{}", "Monthly Summary:".bold())?;
         for summary in &report.monthly_summaries {
-            println!("\n  {}:", summary.month_name.bold());
-            println!(
+            writeln!(out, "\n  {}:", summary.month_name.bold())?;
+            writeln!(
+                out,
                 "    Sales:  {}",
                 format_currency(summary.total_sales).cyan()
-            );
-            println!(
+            )?;
+            writeln!(
+                out,
                 "    Profit: {}",
                 format_currency(summary.total_profit).green()
-            );
-            println!("    Loss:   {}", format_currency(summary.total_loss).red());
-            println!("    Tax:    {}", format_currency(summary.tax_due).yellow());
+            )?;
+            writeln!(
+                out,
+                "    Loss:   {}",
+                format_currency(summary.total_loss).red()
+            )?;
+            writeln!(
+                out,
+                "    Tax:    {}",
+                format_currency(summary.tax_due).yellow()
+            )?;
         }
 
         // Annual totals
-        println!("\n{} Annual Totals:", "📈".cyan().bold());
-        println!(
+        writeln!(out, "\n{} Annual Totals:", "📈".cyan().bold())?;
+        writeln!(
+            out,
             "  Total Sales:  {}",
             format_currency(report.annual_total_sales).cyan()
-        );
-        println!(
+        )?;
+        writeln!(
+            out,
             "  Total Profit: {}",
             format_currency(report.annual_total_profit).green()
-        );
-        println!(
+        )?;
+        writeln!(
+            out,
             "  Total Loss:   {}",
             format_currency(report.annual_total_loss).red()
-        );
-        println!(
+        )?;
+        writeln!(
+            out,
             "  {} {}\n",
             "Total Tax:".bold(),
             format_currency(report.annual_total_tax).yellow().bold()
-        );
+        )?;
 
         // Losses to carry forward
         if !report.losses_to_carry_forward.is_empty() {
-            println!("{} Losses to Carry Forward:", "📋".yellow().bold());
+            writeln!(out, "{} Losses to Carry Forward:", "📋".yellow().bold())?;
             for (category, loss) in &report.losses_to_carry_forward {
-                println!(
+                writeln!(
+                    out,
                     "  {}: {}",
                     category.display_name(),
                     format_currency(*loss).yellow()
-                );
+                )?;
             }
-            println!();
+            writeln!(out)?;
         }
     }
 
@@ -323,7 +544,9 @@ async fn dispatch_tax_report(year: i32, export_csv: bool, json_output: bool) ->
             let total_dividends: Decimal = income_summary.iter().map(|e| e.dividends_net).sum();
             let total_jcp: Decimal = income_summary.iter().map(|e| e.jcp_net).sum();
             let total_all = total_dividends + total_jcp;
-            let mut table_rows = rows;
+            let page = table.paginate(rows);
+            let footer = page.footer();
+            let mut table_rows = page.rows;
             table_rows.push(IncomeRow {
                 ticker: "TOTAL".to_string(),
                 cnpj: "-".to_string(),
@@ -333,16 +556,44 @@ async fn dispatch_tax_report(year: i32, export_csv: bool, json_output: bool) ->
                 total: format_currency(total_all),
             });
 
-            println!("{} Dividends & JCP Received:", "💵".cyan().bold());
-            let mut table = Table::new(table_rows);
-            let table = table
-                .with(Style::rounded())
-                .with(Modify::new(Columns::new(3..6)).with(Alignment::right()));
-            println!("{table}");
-            println!();
+            writeln!(out, "{} Dividends & JCP Received:", "💵".cyan().bold())?;
+            writeln!(
+                out,
+                "{}",
+                crate::cli::formatters::render_table(
+                    &table_rows,
+                    &table.columns,
+                    &["Dividends (Net)", "JCP (Net)", "Total (Net)"],
+                )?
+            )?;
+            if let Some(footer) = footer {
+                writeln!(out, "{}", footer.dimmed())?;
+            }
+            writeln!(out)?;
         }
     }
 
+    if !carne_leao.months.is_empty() {
+        write_carne_leao(&mut out, &carne_leao)?;
+    }
+
+    if fund_income.has_taxable() {
+        write_fund_income(&mut out, &fund_income)?;
+    }
+
+    if let Some(foreign_gains) = &foreign_gains {
+        write_foreign_gains(&mut out, foreign_gains)?;
+    }
+
+    crate::cli::formatters::print_paged(&out, table.no_pager);
+
+    if !json_output {
+        crate::ui::refresh::print_as_of(
+            crate::ui::refresh::Panel::Tax,
+            tax::loss_carryforward::snapshot_computed_at(&conn, year)?,
+        );
+    }
+
     if export_csv {
         let csv_content = tax::irpf::export_to_csv(&report);
         let csv_path = format!("irpf_report_{}.csv", year);
@@ -354,6 +605,335 @@ async fn dispatch_tax_report(year: i32, export_csv: bool, json_output: bool) ->
     Ok(())
 }
 
+fn write_carne_leao(
+    out: &mut String,
+    year: &tax::foreign_dividends::ForeignDividendYear,
+) -> std::fmt::Result {
+    use std::fmt::Write;
+
+    use tabled::{
+        settings::{object::Columns, Alignment, Modify, Style},
+        Table, Tabled,
+    };
+
+    #[derive(Tabled)]
+    struct CarneLeaoRow {
+        #[tabled(rename = "Month")]
+        month: String,
+        #[tabled(rename = "Gross")]
+        gross: String,
+        #[tabled(rename = "Withheld abroad")]
+        foreign_tax: String,
+        #[tabled(rename = "Table tax")]
+        tax: String,
+        #[tabled(rename = "DARF")]
+        darf: String,
+        #[tabled(rename = "Due")]
+        due: String,
+    }
+
+    let rows: Vec<CarneLeaoRow> = year
+        .months
+        .iter()
+        .map(|m| CarneLeaoRow {
+            month: format!("{:02}/{}", m.month, year.year),
+            gross: format_currency(m.gross),
+            foreign_tax: format!(
+                "{}{}",
+                format_currency(m.foreign_tax),
+                if m.dividends.iter().any(|d| d.estimated) {
+                    " *"
+                } else {
+                    ""
+                }
+            ),
+            tax: format_currency(m.tax),
+            darf: if m.darf.is_zero() {
+                "-".to_string()
+            } else {
+                format_currency(m.darf)
+            },
+            due: if m.darf.is_zero() {
+                "-".to_string()
+            } else {
+                m.due_date.format("%d/%m/%Y").to_string()
+            },
+        })
+        .collect();
+
+    writeln!(out, "{} Carnê-leão: BDR dividends", "🌎".cyan().bold())?;
+    writeln!(
+        out,
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..5)).with(Alignment::right()))
+    )?;
+    writeln!(
+        out,
+        "  Gross: {}   Withheld abroad: {}   DARF {} total: {}",
+        format_currency(year.gross),
+        format_currency(year.foreign_tax),
+        tax::foreign_dividends::DARF_CODE,
+        format_currency(year.darf_total).yellow().bold()
+    )?;
+    if !year.carried_out.is_zero() {
+        writeln!(
+            out,
+            "  Under the R$ 10,00 DARF minimum, left for the annual adjustment: {}",
+            format_currency(year.carried_out)
+        )?;
+    }
+    if year.has_estimates {
+        writeln!(out,
+            "  {}",
+            format!(
+                "* foreign tax not recorded, assumed {}% of the gross (US); record it with 'interest income add --foreign-tax'",
+                (tax::rules::income_withholding_rule(
+                    &db::IncomeEventType::Dividend,
+                    &db::AssetType::Bdr,
+                    year.year,
+                    12
+                )
+                .foreign_rate
+                    * rust_decimal::Decimal::ONE_HUNDRED)
+                    .normalize()
+            )
+            .dimmed()
+        )?;
+    }
+    writeln!(
+        out,
+        "  {}",
+        "Other carnê-leão income of the same months changes the bracket.".dimmed()
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_foreign_gains(
+    out: &mut String,
+    year: &tax::foreign_gains::ForeignGainsYear,
+) -> std::fmt::Result {
+    use std::fmt::Write;
+
+    writeln!(
+        out,
+        "{} Assets abroad: annual adjustment (Lei 14.754/2023)",
+        "🌎".cyan().bold()
+    )?;
+    writeln!(
+        out,
+        "  Sales: {}   Gains: {}   Dividends: {}",
+        format_currency(year.sales),
+        format_currency(year.gains),
+        format_currency(year.dividends)
+    )?;
+    if !year.loss_offset.is_zero() {
+        writeln!(
+            out,
+            "  Losses of earlier years offset: {}",
+            format_currency(year.loss_offset)
+        )?;
+    }
+    writeln!(
+        out,
+        "  Taxable: {}   IR {}%: {}   Withheld abroad: {}   Due: {}",
+        format_currency(year.taxable),
+        (year.rate * rust_decimal::Decimal::ONE_HUNDRED).normalize(),
+        format_currency(year.tax),
+        format_currency(year.credit),
+        format_currency(year.due).yellow().bold()
+    )?;
+    if !year.carried_out.is_zero() {
+        writeln!(
+            out,
+            "  Loss carried to {}: {}",
+            year.year + 1,
+            format_currency(year.carried_out)
+        )?;
+    }
+    writeln!(
+        out,
+        "  {}",
+        "Paid with the annual declaration, not by monthly DARF.".dimmed()
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_fund_income(
+    out: &mut String,
+    year: &tax::fund_income::FundIncomeYear,
+) -> std::fmt::Result {
+    use std::fmt::Write;
+
+    use tabled::{
+        settings::{object::Columns, Alignment, Modify, Style},
+        Table, Tabled,
+    };
+
+    #[derive(Tabled)]
+    struct FundIncomeRow {
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Amount")]
+        amount: String,
+        #[tabled(rename = "Exempt")]
+        exempt: String,
+        #[tabled(rename = "Taxable")]
+        taxable: String,
+        #[tabled(rename = "Projected IR")]
+        tax: String,
+        #[tabled(rename = "Withheld")]
+        withheld: String,
+    }
+
+    let rows: Vec<FundIncomeRow> = year
+        .distributions
+        .iter()
+        .filter(|d| d.taxable > rust_decimal::Decimal::ZERO)
+        .map(|d| FundIncomeRow {
+            date: d.date.format("%d/%m/%Y").to_string(),
+            ticker: format!("{}{}", d.ticker, if d.recorded_vintage { " *" } else { "" }),
+            amount: format_currency(d.amount),
+            exempt: format_currency(d.exempt),
+            taxable: format_currency(d.taxable),
+            tax: format_currency(d.projected_tax),
+            withheld: if d.withheld.is_zero() {
+                "-".to_string()
+            } else {
+                format_currency(d.withheld)
+            },
+        })
+        .collect();
+
+    writeln!(
+        out,
+        "{} FII/Fiagro distributions on quotas acquired from 2026",
+        "🏢".cyan().bold()
+    )?;
+    writeln!(
+        out,
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(2..7)).with(Alignment::right()))
+    )?;
+    writeln!(
+        out,
+        "  Exempt: {}   Taxable: {}   Projected IR: {}   Withheld: {}",
+        format_currency(year.exempt),
+        format_currency(year.taxable),
+        format_currency(year.projected_tax).yellow().bold(),
+        format_currency(year.withheld)
+    )?;
+    if year.distributions.iter().any(|d| d.recorded_vintage) {
+        writeln!(
+            out,
+            "  {}",
+            "* quota vintage recorded on the income event".dimmed()
+        )?;
+    }
+    writeln!(
+        out,
+        "  {}",
+        "Quotas held before 2026 keep the exemption; sales consume the oldest quotas first."
+            .dimmed()
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn dispatch_tax_by_declarant(year: i32, json_output: bool) -> Result<()> {
+    use tabled::{
+        settings::{object::Columns, Alignment, Modify, Style},
+        Table, Tabled,
+    };
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let reports = tax::declarants::declarant_reports(&conn, year)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct HoldingRow {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        asset_type: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Cost on 31/12")]
+        total_cost: String,
+    }
+
+    println!(
+        "\n{} IRPF {} by declarant",
+        "👥".cyan().bold(),
+        year.to_string().bold()
+    );
+    for report in &reports {
+        let title = report
+            .declarant
+            .clone()
+            .unwrap_or_else(|| "(no declarant)".to_string());
+        println!(
+            "\n{} {}",
+            title.bold(),
+            format!("- portfolios: {}", report.portfolios.join(", ")).dimmed()
+        );
+        println!(
+            "  Sales: {}   Profit: {}   Loss: {}   Tax: {}",
+            format_currency(report.total_sales).cyan(),
+            format_currency(report.total_profit).green(),
+            format_currency(report.total_loss).red(),
+            format_currency(report.tax_due).yellow().bold()
+        );
+        println!(
+            "  Dividends: {}   JCP: {}   Interest: {}   IRRF on income: {}",
+            format_currency(report.dividends).green(),
+            format_currency(report.jcp).green(),
+            format_currency(report.interest).green(),
+            format_currency(report.income_withheld)
+        );
+        if report.holdings.is_empty() {
+            println!("  {}", "No positions on 31/12".dimmed());
+            continue;
+        }
+        let rows: Vec<HoldingRow> = report
+            .holdings
+            .iter()
+            .map(|h| HoldingRow {
+                ticker: h.ticker.clone(),
+                asset_type: h.asset_type.as_str().to_string(),
+                quantity: h.quantity.normalize().to_string(),
+                total_cost: format_currency(h.total_cost),
+            })
+            .collect();
+        println!(
+            "{}",
+            Table::new(rows)
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(2..)).with(Alignment::right()))
+        );
+    }
+    if reports.iter().any(|r| r.declarant.is_none()) {
+        println!(
+            "\n  {}",
+            "Portfolios without a declarant are listed apart; assign them with 'interest portfolios set-declarant'."
+                .dimmed()
+        );
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 struct IncomeByType {
     ticker: String,
@@ -378,14 +958,20 @@ fn build_income_summary(conn: &rusqlite::Connection, year: i32) -> Result<Vec<In
         db::AssetType::Fiagro,
         db::AssetType::Stock,
         db::AssetType::Etf,
-        db::AssetType::Bdr,
     ];
+    // BDR dividends are foreign-source and taxed, see `tax::foreign_dividends`
+    // FII and Fiagro quotas acquired from 2026 on are taxed, see `tax::fund_income`
+    // Dividends of assets held abroad are taxed, see `tax::foreign_gains`
+    let fund_taxable = tax::fund_income::fund_income_year(conn, year)?.taxable_by_event();
+    let abroad = db::get_assets_quoted_abroad(conn)?;
 
     let tracked_set: std::collections::HashSet<db::AssetType> =
         tracked_types.iter().copied().collect();
     let mut by_ticker: HashMap<String, IncomeByType> = HashMap::new();
     for (event, asset) in events {
-        if !tracked_set.contains(&asset.asset_type) {
+        if !tracked_set.contains(&asset.asset_type)
+            || asset.id.is_some_and(|id| abroad.contains(&id))
+        {
             continue;
         }
         let entry = by_ticker
@@ -400,7 +986,11 @@ fn build_income_summary(conn: &rusqlite::Connection, year: i32) -> Result<Vec<In
         if entry.cnpj.is_none() {
             entry.cnpj = asset.cnpj.clone();
         }
-        let net_amount = event.total_amount - event.withholding_tax;
+        let net_amount = match event.id.and_then(|id| fund_taxable.get(&id)) {
+            // The withholding falls on the taxed part, left out of the exempt income
+            Some(taxable) if !taxable.is_zero() => event.total_amount - *taxable,
+            _ => event.total_amount - event.withholding_tax,
+        };
         match event.event_type {
             db::IncomeEventType::Dividend => entry.dividends_net += net_amount,
             db::IncomeEventType::Jcp => entry.jcp_net += net_amount,
@@ -414,19 +1004,7 @@ fn build_income_summary(conn: &rusqlite::Connection, year: i32) -> Result<Vec<In
 }
 
 fn format_cnpj(value: Option<&str>) -> Option<String> {
-    let raw = value?;
-    let digits: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
-    if digits.len() != 14 {
-        return Some(raw.to_string());
-    }
-    Some(format!(
-        "{}.{}.{}/{}-{}",
-        &digits[0..2],
-        &digits[2..5],
-        &digits[5..8],
-        &digits[8..12],
-        &digits[12..14]
-    ))
+    value.map(crate::scraping::cnpj::format_cnpj)
 }
 
 async fn dispatch_tax_summary(year: i32, _json_output: bool) -> Result<()> {
@@ -508,12 +1086,20 @@ async fn dispatch_tax_summary(year: i32, _json_output: bool) -> Result<()> {
         "Tax:".bold(),
         format_currency(report.annual_total_tax).yellow().bold()
     );
+    crate::ui::refresh::print_as_of(
+        crate::ui::refresh::Panel::Tax,
+        tax::loss_carryforward::snapshot_computed_at(&conn, year)?,
+    );
 
     Ok(())
 }
 
 /// Show income summary by asset, grouped by asset type
-async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()> {
+async fn dispatch_income_show(
+    year: Option<i32>,
+    tag: Option<&str>,
+    json_output: bool,
+) -> Result<()> {
     use chrono::Datelike;
     use rust_decimal::Decimal;
     use serde::Serialize;
@@ -545,7 +1131,11 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
     };
 
     // Query income events
-    let events = db::get_income_events_with_assets(&conn, from_date, to_date, None)?;
+    let mut events = db::get_income_events_with_assets(&conn, from_date, to_date, None)?;
+    if let Some(tag) = tag {
+        let tagged_assets = db::get_tagged_asset_ids(&conn, tag)?;
+        events.retain(|(event, _)| tagged_assets.contains(&event.asset_id));
+    }
 
     if events.is_empty() {
         println!(
@@ -562,10 +1152,14 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
         asset_type: db::AssetType,
         dividends: Decimal,
         jcp: Decimal,
+        interest: Decimal,
         amortization: Decimal,
+        /// Withheld abroad on BDR dividends (recorded or estimated)
+        foreign_tax: Decimal,
     }
 
     let mut by_ticker: HashMap<String, AssetIncome> = HashMap::new();
+    let mut foreign_estimated = false;
 
     for (event, asset) in &events {
         let entry = by_ticker
@@ -575,12 +1169,26 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
                 asset_type: asset.asset_type,
                 dividends: Decimal::ZERO,
                 jcp: Decimal::ZERO,
+                interest: Decimal::ZERO,
                 amortization: Decimal::ZERO,
+                foreign_tax: Decimal::ZERO,
             });
 
+        if asset.asset_type == db::AssetType::Bdr
+            && event.event_type != db::IncomeEventType::Amortization
+        {
+            let (foreign_tax, estimated) = tax::foreign_dividends::foreign_tax(
+                event.total_amount - event.withholding_tax,
+                event.foreign_tax_withheld,
+                event.event_date,
+            );
+            entry.foreign_tax += foreign_tax;
+            foreign_estimated |= estimated;
+        }
         match event.event_type {
             db::IncomeEventType::Dividend => entry.dividends += event.total_amount,
             db::IncomeEventType::Jcp => entry.jcp += event.total_amount,
+            db::IncomeEventType::Interest => entry.interest += event.total_amount,
             db::IncomeEventType::Amortization => entry.amortization += event.total_amount,
         }
     }
@@ -591,11 +1199,16 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
         by_type.entry(income.asset_type).or_default().push(income);
     }
 
+    // Realized yields of what is still held, over the 12 months up to today
+    let portfolio = crate::reports::calculate_portfolio(&conn, None)?;
+    let yields = crate::reports::income_yield::ltm_yields(&conn, &portfolio, today)?;
+    let yield_of = |ticker: &str| yields.get(ticker);
+
     // Sort each group by total (descending)
     for assets in by_type.values_mut() {
         assets.sort_by(|a, b| {
-            let total_a = a.dividends + a.jcp + a.amortization;
-            let total_b = b.dividends + b.jcp + b.amortization;
+            let total_a = a.dividends + a.jcp + a.interest + a.amortization;
+            let total_b = b.dividends + b.jcp + b.interest + b.amortization;
             total_b.cmp(&total_a)
         });
     }
@@ -607,21 +1220,35 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
             asset_type: String,
             dividends: String,
             jcp: String,
+            interest: String,
             amortization: String,
             total: String,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            foreign_tax: Option<String>,
+            ltm_yield_pct: Option<String>,
+            yield_on_cost_pct: Option<String>,
         }
 
         let mut all_assets: Vec<JsonAssetIncome> = Vec::new();
         for (asset_type, assets) in &by_type {
             for a in assets {
-                let total = a.dividends + a.jcp + a.amortization;
+                let total = a.dividends + a.jcp + a.interest + a.amortization;
                 all_assets.push(JsonAssetIncome {
                     ticker: a.ticker.clone(),
                     asset_type: asset_type.as_str().to_string(),
                     dividends: a.dividends.to_string(),
                     jcp: a.jcp.to_string(),
+                    interest: a.interest.to_string(),
                     amortization: a.amortization.to_string(),
                     total: total.to_string(),
+                    foreign_tax: (*asset_type == db::AssetType::Bdr)
+                        .then(|| a.foreign_tax.to_string()),
+                    ltm_yield_pct: yield_of(&a.ticker)
+                        .and_then(|y| y.ltm_yield_pct)
+                        .map(|p| p.to_string()),
+                    yield_on_cost_pct: yield_of(&a.ticker)
+                        .and_then(|y| y.yield_on_cost_pct)
+                        .map(|p| p.to_string()),
                 });
             }
         }
@@ -629,7 +1256,15 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
         return Ok(());
     }
 
-    println!("\n{} Income Summary - {}\n", "💰".cyan().bold(), year_val);
+    match tag {
+        Some(tag) => println!(
+            "\n{} Income Summary - {} - tag {}\n",
+            "💰".cyan().bold(),
+            year_val,
+            tag.to_lowercase().bold()
+        ),
+        None => println!("\n{} Income Summary - {}\n", "💰".cyan().bold(), year_val),
+    }
 
     // Define display order for asset types
     let type_order = [
@@ -664,16 +1299,23 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
                 dividends: String,
                 #[tabled(rename = "JCP")]
                 jcp: String,
+                #[tabled(rename = "Interest")]
+                interest: String,
                 #[tabled(rename = "Amort")]
                 amort: String,
                 #[tabled(rename = "Total")]
                 total: String,
+                #[tabled(rename = "LTM Yield")]
+                ltm_yield: String,
+                #[tabled(rename = "YoC")]
+                yield_on_cost: String,
             }
 
+            let pct = |p: Option<Decimal>| p.map_or("-".to_string(), |p| format!("{:.2}%", p));
             let rows: Vec<IncomeRow> = assets
                 .iter()
                 .map(|a| {
-                    let total = a.dividends + a.jcp + a.amortization;
+                    let total = a.dividends + a.jcp + a.interest + a.amortization;
                     IncomeRow {
                         ticker: a.ticker.clone(),
                         dividends: if a.dividends > Decimal::ZERO {
@@ -686,19 +1328,26 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
                         } else {
                             "-".to_string()
                         },
+                        interest: if a.interest > Decimal::ZERO {
+                            format_currency(a.interest)
+                        } else {
+                            "-".to_string()
+                        },
                         amort: if a.amortization > Decimal::ZERO {
                             format_currency(a.amortization)
                         } else {
                             "-".to_string()
                         },
                         total: format_currency(total),
+                        ltm_yield: pct(yield_of(&a.ticker).and_then(|y| y.ltm_yield_pct)),
+                        yield_on_cost: pct(yield_of(&a.ticker).and_then(|y| y.yield_on_cost_pct)),
                     }
                 })
                 .collect();
 
             let type_total: Decimal = assets
                 .iter()
-                .map(|a| a.dividends + a.jcp + a.amortization)
+                .map(|a| a.dividends + a.jcp + a.interest + a.amortization)
                 .sum();
             grand_total += type_total;
 
@@ -722,6 +1371,39 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
         "Grand Total:".bold(),
         format_currency(grand_total).green().bold()
     );
+    if !yields.is_empty() {
+        println!(
+            "   {}\n",
+            "LTM Yield and YoC: dividends and JCP per share over the last 12 months, on the current price and on the average cost"
+                .dimmed()
+        );
+    }
+
+    if let Some(bdrs) = by_type.get(&db::AssetType::Bdr) {
+        let foreign_tax: Decimal = bdrs.iter().map(|a| a.foreign_tax).sum();
+        let received: Decimal = bdrs.iter().map(|a| a.dividends + a.jcp).sum();
+        if received > Decimal::ZERO {
+            println!(
+                "{} BDR dividends are foreign income: {} received after {} withheld abroad{}",
+                "🌎".cyan(),
+                format_currency(received),
+                format_currency(foreign_tax),
+                if foreign_estimated {
+                    " (partly estimated at the US rate)"
+                } else {
+                    ""
+                }
+            );
+            println!(
+                "   {}\n",
+                format!(
+                    "Taxed monthly by carnê-leão; see 'interest tax report {}'",
+                    year_val
+                )
+                .dimmed()
+            );
+        }
+    }
 
     Ok(())
 }
@@ -730,15 +1412,13 @@ async fn dispatch_income_show(year: Option<i32>, json_output: bool) -> Result<()
 async fn dispatch_income_detail(
     year: Option<i32>,
     asset: Option<&str>,
+    table: &crate::cli::formatters::TableArgs,
     json_output: bool,
 ) -> Result<()> {
     use chrono::Datelike;
     use rust_decimal::Decimal;
     use serde::Serialize;
-    use tabled::{
-        settings::{object::Columns, Alignment, Modify, Style},
-        Table, Tabled,
-    };
+    use tabled::Tabled;
 
     info!("Showing income events detail");
 
@@ -779,6 +1459,8 @@ async fn dispatch_income_detail(
         return Ok(());
     }
 
+    let page = table.paginate(events.iter().collect());
+
     if json_output {
         #[derive(Serialize)]
         struct IncomeRow {
@@ -790,7 +1472,8 @@ async fn dispatch_income_detail(
             notes: Option<String>,
         }
 
-        let rows: Vec<IncomeRow> = events
+        let rows: Vec<IncomeRow> = page
+            .rows
             .iter()
             .map(|(event, asset)| IncomeRow {
                 date: event.event_date.to_string(),
@@ -832,7 +1515,8 @@ async fn dispatch_income_detail(
         notes: String,
     }
 
-    let rows: Vec<IncomeTableRow> = events
+    let rows: Vec<IncomeTableRow> = page
+        .rows
         .iter()
         .map(|(event, asset)| IncomeTableRow {
             date: event.event_date.format("%Y-%m-%d").to_string(),
@@ -840,6 +1524,7 @@ async fn dispatch_income_detail(
             event_type: match event.event_type {
                 db::IncomeEventType::Dividend => "Dividend",
                 db::IncomeEventType::Jcp => "JCP",
+                db::IncomeEventType::Interest => "Interest",
                 db::IncomeEventType::Amortization => "Amort",
             }
             .to_string(),
@@ -848,11 +1533,12 @@ async fn dispatch_income_detail(
         })
         .collect();
 
-    let table = Table::new(&rows)
-        .with(Style::rounded())
-        .with(Modify::new(Columns::new(3..4)).with(Alignment::right()))
-        .to_string();
-    println!("{}", table);
+    let mut out = crate::cli::formatters::render_table(&rows, &table.columns, &["Amount"])?;
+    out.push('\n');
+    if let Some(footer) = page.footer() {
+        out.push_str(&format!("{}\n", footer.dimmed()));
+    }
+    crate::cli::formatters::print_paged(&out, table.no_pager);
 
     // Summary
     let total: Decimal = events.iter().map(|(e, _)| e.total_amount).sum();
@@ -866,6 +1552,11 @@ async fn dispatch_income_detail(
         .filter(|(e, _)| matches!(e.event_type, db::IncomeEventType::Jcp))
         .map(|(e, _)| e.total_amount)
         .sum();
+    let interest: Decimal = events
+        .iter()
+        .filter(|(e, _)| matches!(e.event_type, db::IncomeEventType::Interest))
+        .map(|(e, _)| e.total_amount)
+        .sum();
     let amort: Decimal = events
         .iter()
         .filter(|(e, _)| matches!(e.event_type, db::IncomeEventType::Amortization))
@@ -879,6 +1570,9 @@ async fn dispatch_income_detail(
     if jcp > Decimal::ZERO {
         println!("  JCP:          {}", format_currency(jcp).green());
     }
+    if interest > Decimal::ZERO {
+        println!("  Interest:     {}", format_currency(interest).green());
+    }
     if amort > Decimal::ZERO {
         println!("  Amortization: {}", format_currency(amort).yellow());
     }
@@ -933,6 +1627,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
             struct MonthlyTotals {
                 dividends: Decimal,
                 jcp: Decimal,
+                interest: Decimal,
                 amortization: Decimal,
             }
 
@@ -940,6 +1635,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 .map(|_| MonthlyTotals {
                     dividends: Decimal::ZERO,
                     jcp: Decimal::ZERO,
+                    interest: Decimal::ZERO,
                     amortization: Decimal::ZERO,
                 })
                 .collect();
@@ -951,6 +1647,9 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                         monthly[month_idx].dividends += event.total_amount
                     }
                     db::IncomeEventType::Jcp => monthly[month_idx].jcp += event.total_amount,
+                    db::IncomeEventType::Interest => {
+                        monthly[month_idx].interest += event.total_amount
+                    }
                     db::IncomeEventType::Amortization => {
                         monthly[month_idx].amortization += event.total_amount
                     }
@@ -959,12 +1658,13 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
 
             let total_dividends: Decimal = monthly.iter().map(|m| m.dividends).sum();
             let total_jcp: Decimal = monthly.iter().map(|m| m.jcp).sum();
+            let total_interest: Decimal = monthly.iter().map(|m| m.interest).sum();
             let total_amortization: Decimal = monthly.iter().map(|m| m.amortization).sum();
-            let grand_total = total_dividends + total_jcp + total_amortization;
+            let grand_total = total_dividends + total_jcp + total_interest + total_amortization;
 
             let months_with_income = monthly
                 .iter()
-                .filter(|m| m.dividends + m.jcp + m.amortization > Decimal::ZERO)
+                .filter(|m| m.dividends + m.jcp + m.interest + m.amortization > Decimal::ZERO)
                 .count();
             let avg_per_month = if months_with_income > 0 {
                 grand_total / Decimal::from(months_with_income)
@@ -982,6 +1682,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
             }
             let mut asset_type_vec: Vec<_> = asset_type_totals.iter().collect();
             asset_type_vec.sort_by(|a, b| b.1.cmp(a.1)); // Sort by amount descending
+            let fund_income = tax::fund_income::fund_income_year(&conn, y)?;
 
             if json_output {
                 #[derive(Serialize)]
@@ -989,6 +1690,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                     month: String,
                     dividends: String,
                     jcp: String,
+                    interest: String,
                     amortization: String,
                     total: String,
                 }
@@ -1000,17 +1702,28 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                     totals: JsonMonthlyRow,
                     months_with_income: usize,
                     avg_per_month: String,
+                    #[serde(skip_serializing_if = "Option::is_none")]
+                    fii_taxable: Option<JsonFundIncome>,
+                }
+
+                #[derive(Serialize)]
+                struct JsonFundIncome {
+                    exempt: String,
+                    taxable: String,
+                    projected_tax: String,
+                    withheld: String,
                 }
 
                 let monthly_rows: Vec<JsonMonthlyRow> = monthly
                     .iter()
                     .enumerate()
                     .map(|(i, m)| {
-                        let total = m.dividends + m.jcp + m.amortization;
+                        let total = m.dividends + m.jcp + m.interest + m.amortization;
                         JsonMonthlyRow {
                             month: month_names[i].to_string(),
                             dividends: m.dividends.to_string(),
                             jcp: m.jcp.to_string(),
+                            interest: m.interest.to_string(),
                             amortization: m.amortization.to_string(),
                             total: total.to_string(),
                         }
@@ -1024,11 +1737,18 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                         month: "TOTAL".to_string(),
                         dividends: total_dividends.to_string(),
                         jcp: total_jcp.to_string(),
+                        interest: total_interest.to_string(),
                         amortization: total_amortization.to_string(),
                         total: grand_total.to_string(),
                     },
                     months_with_income,
                     avg_per_month: avg_per_month.to_string(),
+                    fii_taxable: fund_income.has_taxable().then(|| JsonFundIncome {
+                        exempt: fund_income.exempt.to_string(),
+                        taxable: fund_income.taxable.to_string(),
+                        projected_tax: fund_income.projected_tax.to_string(),
+                        withheld: fund_income.withheld.to_string(),
+                    }),
                 };
 
                 println!("{}", serde_json::to_string_pretty(&summary)?);
@@ -1049,6 +1769,8 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 dividends: String,
                 #[tabled(rename = "JCP")]
                 jcp: String,
+                #[tabled(rename = "Interest")]
+                interest: String,
                 #[tabled(rename = "Amort")]
                 amort: String,
                 #[tabled(rename = "Total")]
@@ -1059,7 +1781,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 .iter()
                 .enumerate()
                 .map(|(i, m)| {
-                    let total = m.dividends + m.jcp + m.amortization;
+                    let total = m.dividends + m.jcp + m.interest + m.amortization;
                     MonthRow {
                         month: month_names[i].to_string(),
                         dividends: if m.dividends > Decimal::ZERO {
@@ -1072,6 +1794,11 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                         } else {
                             "-".to_string()
                         },
+                        interest: if m.interest > Decimal::ZERO {
+                            format_currency(m.interest)
+                        } else {
+                            "-".to_string()
+                        },
                         amort: if m.amortization > Decimal::ZERO {
                             format_currency(m.amortization)
                         } else {
@@ -1090,6 +1817,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 month: "─────".to_string(),
                 dividends: "───────────".to_string(),
                 jcp: "───────────".to_string(),
+                interest: "───────────".to_string(),
                 amort: "───────────".to_string(),
                 total: "───────────".to_string(),
             });
@@ -1097,6 +1825,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 month: "TOTAL".to_string(),
                 dividends: format_currency(total_dividends),
                 jcp: format_currency(total_jcp),
+                interest: format_currency(total_interest),
                 amort: format_currency(total_amortization),
                 total: format_currency(grand_total),
             });
@@ -1117,6 +1846,12 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
             if total_jcp > Decimal::ZERO {
                 println!("  JCP:          {}", format_currency(total_jcp).green());
             }
+            if total_interest > Decimal::ZERO {
+                println!(
+                    "  Interest:     {}",
+                    format_currency(total_interest).green()
+                );
+            }
             if total_amortization > Decimal::ZERO {
                 println!(
                     "  Amortization: {}",
@@ -1138,6 +1873,28 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 );
             }
 
+            if fund_income.has_taxable() {
+                println!("\n{} FII/Fiagro under the 2026 rules:", "🏢".cyan().bold());
+                println!(
+                    "  Exempt (quotas before 2026): {}",
+                    format_currency(fund_income.exempt).green()
+                );
+                println!(
+                    "  Taxable (quotas from 2026):  {}",
+                    format_currency(fund_income.taxable).yellow()
+                );
+                println!(
+                    "  Projected IR:                {}",
+                    format_currency(fund_income.projected_tax).yellow().bold()
+                );
+                if !fund_income.withheld.is_zero() {
+                    println!(
+                        "  Already withheld:            {}",
+                        format_currency(fund_income.withheld)
+                    );
+                }
+            }
+
             println!("\n{} Statistics:", "📈".cyan().bold());
             println!("  Months with income: {}", months_with_income);
             println!(
@@ -1160,6 +1917,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
             struct YearlyTotals {
                 dividends: Decimal,
                 jcp: Decimal,
+                interest: Decimal,
                 amortization: Decimal,
             }
 
@@ -1170,19 +1928,22 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 let entry = yearly.entry(year).or_insert(YearlyTotals {
                     dividends: Decimal::ZERO,
                     jcp: Decimal::ZERO,
+                    interest: Decimal::ZERO,
                     amortization: Decimal::ZERO,
                 });
                 match event.event_type {
                     db::IncomeEventType::Dividend => entry.dividends += event.total_amount,
                     db::IncomeEventType::Jcp => entry.jcp += event.total_amount,
+                    db::IncomeEventType::Interest => entry.interest += event.total_amount,
                     db::IncomeEventType::Amortization => entry.amortization += event.total_amount,
                 }
             }
 
             let total_dividends: Decimal = yearly.values().map(|y| y.dividends).sum();
             let total_jcp: Decimal = yearly.values().map(|y| y.jcp).sum();
+            let total_interest: Decimal = yearly.values().map(|y| y.interest).sum();
             let total_amortization: Decimal = yearly.values().map(|y| y.amortization).sum();
-            let grand_total = total_dividends + total_jcp + total_amortization;
+            let grand_total = total_dividends + total_jcp + total_interest + total_amortization;
 
             let years_with_income = yearly.len();
             let avg_per_year = if years_with_income > 0 {
@@ -1208,6 +1969,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                     year: i32,
                     dividends: String,
                     jcp: String,
+                    interest: String,
                     amortization: String,
                     total: String,
                 }
@@ -1223,11 +1985,12 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 let yearly_rows: Vec<JsonYearlyRow> = yearly
                     .iter()
                     .map(|(yr, y)| {
-                        let total = y.dividends + y.jcp + y.amortization;
+                        let total = y.dividends + y.jcp + y.interest + y.amortization;
                         JsonYearlyRow {
                             year: *yr,
                             dividends: y.dividends.to_string(),
                             jcp: y.jcp.to_string(),
+                            interest: y.interest.to_string(),
                             amortization: y.amortization.to_string(),
                             total: total.to_string(),
                         }
@@ -1240,6 +2003,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                         year: 0,
                         dividends: total_dividends.to_string(),
                         jcp: total_jcp.to_string(),
+                        interest: total_interest.to_string(),
                         amortization: total_amortization.to_string(),
                         total: grand_total.to_string(),
                     },
@@ -1264,6 +2028,8 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 dividends: String,
                 #[tabled(rename = "JCP")]
                 jcp: String,
+                #[tabled(rename = "Interest")]
+                interest: String,
                 #[tabled(rename = "Amort")]
                 amort: String,
                 #[tabled(rename = "Total")]
@@ -1273,7 +2039,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
             let mut rows: Vec<YearRow> = yearly
                 .iter()
                 .map(|(yr, y)| {
-                    let total = y.dividends + y.jcp + y.amortization;
+                    let total = y.dividends + y.jcp + y.interest + y.amortization;
                     YearRow {
                         year: yr.to_string(),
                         dividends: if y.dividends > Decimal::ZERO {
@@ -1286,6 +2052,11 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                         } else {
                             "-".to_string()
                         },
+                        interest: if y.interest > Decimal::ZERO {
+                            format_currency(y.interest)
+                        } else {
+                            "-".to_string()
+                        },
                         amort: if y.amortization > Decimal::ZERO {
                             format_currency(y.amortization)
                         } else {
@@ -1300,6 +2071,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 year: "─────".to_string(),
                 dividends: "───────────".to_string(),
                 jcp: "───────────".to_string(),
+                interest: "───────────".to_string(),
                 amort: "───────────".to_string(),
                 total: "───────────".to_string(),
             });
@@ -1307,6 +2079,7 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
                 year: "TOTAL".to_string(),
                 dividends: format_currency(total_dividends),
                 jcp: format_currency(total_jcp),
+                interest: format_currency(total_interest),
                 amort: format_currency(total_amortization),
                 total: format_currency(grand_total),
             });
@@ -1327,6 +2100,12 @@ pub async fn dispatch_income_summary(year: Option<i32>, json_output: bool) -> Re
             if total_jcp > Decimal::ZERO {
                 println!("  JCP:          {}", format_currency(total_jcp).green());
             }
+            if total_interest > Decimal::ZERO {
+                println!(
+                    "  Interest:     {}",
+                    format_currency(total_interest).green()
+                );
+            }
             if total_amortization > Decimal::ZERO {
                 println!(
                     "  Amortization: {}",
@@ -1368,20 +2147,27 @@ async fn dispatch_income_add(
     total_amount_str: &str,
     date_str: &str,
     ex_date_str: Option<&str>,
-    withholding_str: &str,
+    withholding_str: Option<&str>,
+    foreign_tax_str: Option<&str>,
     amount_per_quota_str: &str,
     notes: Option<&str>,
     json_output: bool,
 ) -> Result<()> {
     use anyhow::Context;
-    use chrono::NaiveDate;
+    use chrono::{Datelike, NaiveDate};
     use rust_decimal::Decimal;
     use std::str::FromStr;
 
     let total_amount = Decimal::from_str(total_amount_str)
         .context("Invalid total amount. Must be a decimal number")?;
-    let withholding = Decimal::from_str(withholding_str)
+    let withholding = withholding_str
+        .map(Decimal::from_str)
+        .transpose()
         .context("Invalid withholding amount. Must be a decimal number")?;
+    let foreign_tax = foreign_tax_str
+        .map(Decimal::from_str)
+        .transpose()
+        .context("Invalid foreign tax. Must be a decimal number")?;
     let amount_per_quota = Decimal::from_str(amount_per_quota_str)
         .context("Invalid amount per quota. Must be a decimal number")?;
     let event_date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
@@ -1401,6 +2187,21 @@ async fn dispatch_income_add(
     let conn = db::open_db(None)?;
     let asset_type = db::AssetType::Unknown;
     let asset_id = db::upsert_asset(&conn, ticker, &asset_type, None)?;
+    // Without --withholding the event type's default IRRF (15% on JCP) is withheld
+    let withholding = match withholding {
+        Some(amount) => amount,
+        None => {
+            let asset_type = db::get_asset_by_ticker(&conn, ticker)?
+                .map_or(db::AssetType::Unknown, |a| a.asset_type);
+            tax::rules::income_withholding_rule(
+                &event_type,
+                &asset_type,
+                event_date.year(),
+                event_date.month(),
+            )
+            .withheld_from(total_amount)
+        }
+    };
 
     let event = db::IncomeEvent {
         id: None,
@@ -1411,6 +2212,7 @@ async fn dispatch_income_add(
         amount_per_quota,
         total_amount,
         withholding_tax: withholding,
+        foreign_tax_withheld: foreign_tax,
         is_quota_pre_2026: None,
         source: "MANUAL".to_string(),
         notes: notes.map(|s| s.to_string()),
@@ -1425,12 +2227,337 @@ async fn dispatch_income_add(
             "ticker": ticker,
             "event_date": event_date.to_string(),
             "total_amount": total_amount.to_string(),
+            "withholding_tax": withholding.to_string(),
         });
         println!("{}", serde_json::to_string_pretty(&payload)?);
     } else {
         println!("Income event added: {} {}", ticker, event_date);
+        if !withholding.is_zero() {
+            println!("  IRRF withheld: {}", format_currency(withholding));
+        }
+    }
+
+    Ok(())
+}
+
+async fn dispatch_tax_preview(
+    table: &crate::cli::formatters::TableArgs,
+    json_output: bool,
+) -> Result<()> {
+    use tabled::Tabled;
+    use tax::sales_monitor::ExemptionStatus;
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let today = chrono::Local::now().date_naive();
+    let page = table.paginate(tax::sales_monitor::rolling_monthly_sales(&conn, today)?);
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({ "months": page.rows }))?
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct PreviewRow {
+        #[tabled(rename = "Month")]
+        month: String,
+        #[tabled(rename = "Stock Sales")]
+        sales: String,
+        #[tabled(rename = "Status")]
+        status: String,
+    }
+
+    let rows: Vec<PreviewRow> = page
+        .rows
+        .iter()
+        .map(|m| PreviewRow {
+            month: format!("{:02}/{}", m.month, m.year),
+            sales: format_currency(m.stock_sales),
+            status: match m.status {
+                ExemptionStatus::Ok => format!("{} left", format_currency(m.remaining())),
+                ExemptionStatus::Approaching => format!(
+                    "⚠ approaching limit ({} left)",
+                    format_currency(m.remaining())
+                )
+                .yellow()
+                .to_string(),
+                ExemptionStatus::Exceeded => {
+                    "✗ exceeded - swing trade gains taxable".red().to_string()
+                }
+            },
+        })
+        .collect();
+
+    let mut out = format!(
+        "\n{} Stock sales vs R$20k exemption (all brokers, last 12 months)\n\n",
+        "🧾".cyan().bold()
+    );
+    out.push_str(&crate::cli::formatters::render_table(
+        &rows,
+        &table.columns,
+        &["Stock Sales"],
+    )?);
+    out.push('\n');
+    if let Some(footer) = page.footer() {
+        out.push_str(&format!("{}\n", footer.dimmed()));
+    }
+    crate::cli::formatters::print_paged(&out, table.no_pager);
+
+    Ok(())
+}
+
+fn dispatch_bens_e_direitos(year: i32, export_csv: bool, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let groups = tax::bens_direitos::bens_e_direitos(&conn, year)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+
+    if groups.is_empty() {
+        println!(
+            "\n{} No positions on 31/12/{} or 31/12/{}\n",
+            "ℹ".blue().bold(),
+            year - 1,
+            year
+        );
+        return Ok(());
+    }
+
+    if export_csv {
+        let path = format!("bens_e_direitos_{}.csv", year);
+        std::fs::write(&path, tax::bens_direitos::export_to_csv(&groups, year)?)?;
+        println!(
+            "\n{} Bens e Direitos exported to: {}\n",
+            "✓".green().bold(),
+            path
+        );
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Bens e Direitos - IRPF {}",
+        "📋".cyan().bold(),
+        year + 1
+    );
+    for group in &groups {
+        println!(
+            "\n{} {}",
+            format!("Código {}", group.code).bold(),
+            group.description
+        );
+        for item in &group.items {
+            println!(
+                "\n  {}  {} {} → {} {}",
+                item.ticker.bold(),
+                format!("31/12/{}:", year - 1).dimmed(),
+                format_currency(item.previous_value),
+                format!("31/12/{}:", year).dimmed(),
+                format_currency(item.current_value)
+            );
+            if let Some(cnpj) = &item.cnpj {
+                println!("  CNPJ: {}", cnpj);
+            }
+            println!("  {}", item.discriminacao);
+        }
+        println!(
+            "\n  Total: {} → {}",
+            format_currency(group.previous_total),
+            format_currency(group.current_total)
+        );
     }
 
+    if groups
+        .iter()
+        .flat_map(|g| &g.items)
+        .any(|i| i.cnpj.is_none())
+    {
+        println!(
+            "\n  {}",
+            "Items without a CNPJ: look the issuers up with 'interest assets enrich-cnpj'".dimmed()
+        );
+    }
+    println!();
+    Ok(())
+}
+
+fn dispatch_tax_gcap(year: i32, output: Option<&str>, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let operations = tax::gcap::gcap_operations(&conn, year)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&operations)?);
+        return Ok(());
+    }
+
+    if operations.is_empty() {
+        println!("\n{} No sales found for {}\n", "ℹ".blue().bold(), year);
+        return Ok(());
+    }
+
+    let path = output
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("gcap_{}.csv", year));
+    std::fs::write(&path, tax::gcap::export_to_csv(&operations)?)?;
+
+    let gain: rust_decimal::Decimal = operations.iter().map(|op| op.gain).sum();
+    println!(
+        "\n{} {} sale(s) of {} exported to: {}",
+        "✓".green().bold(),
+        operations.len(),
+        year,
+        path
+    );
+    println!("  Net capital gain: {}\n", format_currency(gain));
+
+    Ok(())
+}
+
+async fn dispatch_tax_withholding(year: i32, json_output: bool) -> Result<()> {
+    use tabled::{
+        settings::{object::Columns, Alignment, Modify, Style},
+        Table, Tabled,
+    };
+    use tax::withholding::WithholdingTreatment;
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let report = tax::withholding::withholding_report(&conn, year)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    if report.lines.is_empty() {
+        println!(
+            "\n{} No income events or sales found for {}\n",
+            "ℹ".blue().bold(),
+            year
+        );
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Income Tax Withheld at Source (IRRF) - {}\n",
+        "🧾".cyan().bold(),
+        year
+    );
+
+    #[derive(Tabled)]
+    struct WithholdingRow {
+        #[tabled(rename = "Category")]
+        category: String,
+        #[tabled(rename = "Gross")]
+        gross: String,
+        #[tabled(rename = "IRRF")]
+        withheld: String,
+        #[tabled(rename = "Treatment")]
+        treatment: String,
+        #[tabled(rename = "Declaration")]
+        declaration: String,
+    }
+
+    let rows: Vec<WithholdingRow> = report
+        .lines
+        .iter()
+        .map(|l| WithholdingRow {
+            category: if l.estimated {
+                format!("{} *", l.category)
+            } else {
+                l.category.to_string()
+            },
+            gross: format_currency(l.gross_amount),
+            withheld: format_currency(l.withheld),
+            treatment: if l.withheld.is_zero() {
+                "-".to_string()
+            } else {
+                l.treatment.label().to_string()
+            },
+            declaration: l.declaration.to_string(),
+        })
+        .collect();
+
+    let table = Table::new(rows)
+        .with(Style::rounded())
+        .with(Modify::new(Columns::new(1..3)).with(Alignment::right()))
+        .to_string();
+    println!("{}", table);
+    if report.lines.iter().any(|l| l.estimated) {
+        println!(
+            "  {}",
+            "* estimated from sales; check the exact value on your brokerage notes".dimmed()
+        );
+    }
+
+    if !report.sales_months.is_empty() {
+        println!("\n{} Sales IRRF vs monthly DARF", "📅".cyan().bold());
+        for m in &report.sales_months {
+            println!(
+                "  {:02}/{}  withheld {}  tax due {}  deductible {}  balance {}",
+                m.month,
+                year,
+                format_currency(m.withheld),
+                format_currency(m.tax_due),
+                format_currency(m.deducted).green(),
+                format_currency(m.balance)
+            );
+        }
+    }
+
+    println!("\n{} Summary", "📈".cyan().bold());
+    println!(
+        "  Total withheld:               {}",
+        format_currency(report.total_withheld).cyan()
+    );
+    println!(
+        "  Deductible from DARFs:        {}",
+        format_currency(report.deductible_from_darf).green()
+    );
+    println!(
+        "  Credit for annual adjustment: {}",
+        format_currency(report.annual_adjustment_credit).green()
+    );
+    if !report.recoverable.is_zero() {
+        println!(
+            "  {} {}",
+            "Withheld on exempt income:  ".yellow(),
+            format_currency(report.recoverable).yellow().bold()
+        );
+        for l in report
+            .lines
+            .iter()
+            .filter(|l| l.treatment == WithholdingTreatment::Recoverable && !l.withheld.is_zero())
+        {
+            println!(
+                "    {}: {} - ask the payer for a correction/refund",
+                l.category,
+                l.tickers.join(", ")
+            );
+        }
+    }
+    if report.jcp_without_withholding > 0 {
+        println!(
+            "\n  {} {} JCP event(s) have no IRRF recorded. B3 statements list JCP net of the 15% IRRF;",
+            "ℹ".blue().bold(),
+            report.jcp_without_withholding
+        );
+        println!(
+            "    declare the net amount, or re-add them with --withholding for gross figures."
+        );
+    }
+    println!();
+
     Ok(())
 }
 
@@ -1646,29 +2773,27 @@ impl TaxProgressPrinter {
                         }),
                     });
             }
-            tax::ReportProgress::RecomputedYear { year } => {
-                if self.in_progress {
-                    self.completed_years = (self.completed_years + 1).min(self.total_years);
-                    let from = self.from_year.unwrap_or(year);
-                    if Some(year) == self.target_year {
-                        self.printer
-                            .handle_event(&crate::ui::progress::ProgressEvent::Success {
-                                message: format!("Snapshots updated {}→{}", from, year),
-                            });
-                        self.in_progress = false;
-                    } else {
-                        self.printer.handle_event(
-                            &crate::ui::progress::ProgressEvent::Recomputing {
-                                what: format!("snapshots (year {})", year),
-                                progress: Some(crate::ui::progress::ProgressData {
-                                    current: self.completed_years,
-                                    total: Some(self.total_years),
-                                }),
-                            },
-                        );
-                    }
+            tax::ReportProgress::RecomputedYear { year } if self.in_progress => {
+                self.completed_years = (self.completed_years + 1).min(self.total_years);
+                let from = self.from_year.unwrap_or(year);
+                if Some(year) == self.target_year {
+                    self.printer
+                        .handle_event(&crate::ui::progress::ProgressEvent::Success {
+                            message: format!("Snapshots updated {}→{}", from, year),
+                        });
+                    self.in_progress = false;
+                } else {
+                    self.printer
+                        .handle_event(&crate::ui::progress::ProgressEvent::Recomputing {
+                            what: format!("snapshots (year {})", year),
+                            progress: Some(crate::ui::progress::ProgressData {
+                                current: self.completed_years,
+                                total: Some(self.total_years),
+                            }),
+                        });
                 }
             }
+            tax::ReportProgress::RecomputedYear { .. } => {}
             tax::ReportProgress::TargetCacheHit { year } => {
                 self.printer
                     .handle_event(&crate::ui::progress::ProgressEvent::Success {
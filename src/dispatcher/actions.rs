@@ -5,7 +5,7 @@ use rust_decimal::Decimal;
 use std::str::FromStr;
 use tabled::{Table, Tabled};
 
-use crate::{db, reports};
+use crate::db;
 
 pub async fn dispatch_actions(
     action: &crate::cli::ActionCommands,
@@ -24,6 +24,11 @@ pub async fn dispatch_actions(
         crate::cli::ActionCommands::Apply { ticker } => {
             dispatch_apply(ticker.as_deref(), json_output).await
         }
+        crate::cli::ActionCommands::Unapply { id } => dispatch_unapply(*id, json_output),
+        crate::cli::ActionCommands::Detect { ticker, dry_run } => {
+            dispatch_detect(ticker.as_deref(), *dry_run, json_output).await
+        }
+        crate::cli::ActionCommands::Review => dispatch_review(json_output),
     }
 }
 
@@ -163,7 +168,6 @@ fn add_rename(
     };
 
     let rename_id = db::insert_asset_rename(&conn, &rename)?;
-    reports::invalidate_snapshots_after(&conn, effective_date)?;
 
     if json_output {
         let payload = serde_json::json!({
@@ -245,14 +249,12 @@ fn list_renames(ticker: Option<&str>, json_output: bool) -> Result<()> {
 
 fn remove_rename(id: i64, json_output: bool) -> Result<()> {
     let conn = open_conn()?;
-    let rename = db::get_asset_rename(&conn, id)?.context("Rename id not found")?;
-    let effective_date = rename.effective_date;
+    db::get_asset_rename(&conn, id)?.context("Rename id not found")?;
 
     let deleted = db::delete_asset_rename(&conn, id)?;
     if deleted == 0 {
         anyhow::bail!("Rename id not found");
     }
-    reports::invalidate_snapshots_after(&conn, effective_date)?;
 
     if json_output {
         let payload = serde_json::json!({ "deleted": id });
@@ -302,7 +304,6 @@ fn add_split_or_bonus(
     };
 
     let action_id = db::insert_corporate_action(&conn, &action)?;
-    reports::invalidate_snapshots_after(&conn, ex_date)?;
 
     if json_output {
         let payload = serde_json::json!({
@@ -419,7 +420,6 @@ fn remove_corporate_action(
     if deleted == 0 {
         anyhow::bail!("Corporate action id not found");
     }
-    reports::invalidate_snapshots_after(&conn, action.ex_date)?;
 
     if json_output {
         let payload = serde_json::json!({ "deleted": id });
@@ -471,7 +471,6 @@ fn add_exchange(
     };
 
     let exchange_id = db::insert_asset_exchange(&conn, &exchange)?;
-    reports::invalidate_snapshots_after(&conn, effective_date)?;
 
     if json_output {
         let payload = serde_json::json!({
@@ -506,6 +505,15 @@ fn add_exchange(
     if let Some(n) = notes {
         println!("  Notes:          {}", n);
     }
+    if event_type == db::AssetExchangeType::Merger && cash_amount > Decimal::ZERO {
+        println!(
+            "\n  {} The cash is taxed as a sale of {} in {}: its cost is the basis",
+            "ℹ".blue().bold(),
+            from.to_uppercase(),
+            effective_date.format("%m/%Y")
+        );
+        println!("    not allocated to {}.", to.to_uppercase());
+    }
     println!();
 
     Ok(())
@@ -597,7 +605,6 @@ fn remove_exchange(id: i64, json_output: bool, event_type: db::AssetExchangeType
     if deleted == 0 {
         anyhow::bail!("Exchange id not found");
     }
-    reports::invalidate_snapshots_after(&conn, exchange.effective_date)?;
 
     if json_output {
         let payload = serde_json::json!({ "deleted": id });
@@ -640,19 +647,19 @@ async fn dispatch_apply(ticker: Option<&str>, json_output: bool) -> Result<()> {
             .into_iter()
             .find(|a| a.id == Some(action.asset_id))
             .context("Asset not found")?;
-        let adjusted_count = corporate_actions::apply_corporate_action(&conn, &action, &asset)?;
-        applied.push((action, asset, adjusted_count));
+        let bonus = corporate_actions::apply_corporate_action(&conn, &action, &asset)? > 0;
+        applied.push((action, asset, bonus));
     }
 
     if json_output {
         let payload: Vec<_> = applied
             .iter()
-            .map(|(action, asset, adjusted)| {
+            .map(|(action, asset, bonus)| {
                 serde_json::json!({
                     "id": action.id,
                     "ticker": asset.ticker,
                     "type": action.action_type.as_str(),
-                    "adjusted_transactions": adjusted,
+                    "added_to_position": bonus,
                 })
             })
             .collect();
@@ -665,12 +672,16 @@ async fn dispatch_apply(ticker: Option<&str>, json_output: bool) -> Result<()> {
         "✓".green().bold(),
         applied.len()
     );
-    for (action, asset, adjusted) in applied {
+    for (action, asset, bonus) in applied {
         println!(
-            "  • {} {} ({} tx)",
+            "  • {} {} ({})",
             asset.ticker,
             action.action_type.as_str(),
-            adjusted
+            if bonus {
+                format!("{} shares added at zero cost", action.quantity_adjustment)
+            } else {
+                "already in effect at query time".to_string()
+            }
         );
     }
     println!();
@@ -678,6 +689,218 @@ async fn dispatch_apply(ticker: Option<&str>, json_output: bool) -> Result<()> {
     Ok(())
 }
 
+fn dispatch_unapply(id: i64, json_output: bool) -> Result<()> {
+    let conn = open_conn()?;
+    let (action, asset) =
+        db::get_corporate_action(&conn, id)?.context("Corporate action id not found")?;
+    crate::corporate_actions::review::unapply(&conn, id)?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "unapplied": id,
+            "ticker": asset.ticker,
+            "type": action.action_type.as_str(),
+            "quantity_adjustment": action.quantity_adjustment.to_string(),
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} Unapplied {} {} for {}: {} shares left the position; it is pending again",
+        "✓".green().bold(),
+        action.action_type.as_str(),
+        id,
+        asset.ticker.cyan().bold(),
+        action.quantity_adjustment
+    );
+    println!(
+        "  Correct it with: interest actions bonus remove {} and add it again",
+        id
+    );
+    Ok(())
+}
+
+async fn dispatch_detect(ticker: Option<&str>, dry_run: bool, json_output: bool) -> Result<()> {
+    use crate::corporate_actions::b3_events::{self, Outcome};
+    use std::collections::BTreeMap;
+
+    let conn = open_conn()?;
+    let assets = if let Some(ticker) = ticker {
+        vec![db::get_asset_by_ticker(&conn, ticker)?.context("Ticker not found in database")?]
+    } else {
+        let today = chrono::Local::now().date_naive();
+        crate::reports::calculate_portfolio_at_date(&conn, today, Some(&db::AssetType::Stock))?
+            .positions
+            .into_iter()
+            .filter(|p| p.quantity > Decimal::ZERO)
+            .map(|p| p.asset)
+            .collect()
+    };
+
+    // One request per company covers all of its share classes
+    let mut by_issuer: BTreeMap<String, Vec<db::Asset>> = BTreeMap::new();
+    for asset in assets {
+        by_issuer
+            .entry(b3_events::issuer_code(&asset.ticker).to_uppercase())
+            .or_default()
+            .push(asset);
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(20))
+        .user_agent("interest")
+        .build()?;
+    let mut detections = Vec::new();
+    let mut failed = Vec::new();
+    for (issuer, assets) in &by_issuer {
+        let body = match b3_events::fetch_company_events(&client, issuer).await {
+            Ok(body) => body,
+            Err(e) => {
+                failed.push((issuer.clone(), format!("{:#}", e)));
+                continue;
+            }
+        };
+        for asset in assets {
+            let events = match b3_events::parse_events(&body, &asset.ticker) {
+                Ok(events) => events,
+                Err(e) => {
+                    failed.push((asset.ticker.clone(), format!("{:#}", e)));
+                    continue;
+                }
+            };
+            for event in events {
+                detections.push(b3_events::record_event(
+                    &conn,
+                    asset.id.unwrap_or_default(),
+                    event,
+                    dry_run,
+                )?);
+            }
+        }
+    }
+    if !by_issuer.is_empty() && failed.len() >= by_issuer.len() && detections.is_empty() {
+        anyhow::bail!("{}", failed[0].1);
+    }
+    detections.retain(|d| !matches!(d.outcome, Outcome::NotHeld));
+
+    if json_output {
+        let payload = serde_json::json!({
+            "dry_run": dry_run,
+            "events": detections,
+            "failed": failed
+                .iter()
+                .map(|(code, error)| serde_json::json!({ "code": code, "error": error }))
+                .collect::<Vec<_>>(),
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    for (code, error) in &failed {
+        eprintln!("{} {}: {}", "⚠".yellow().bold(), code, error);
+    }
+    if detections.is_empty() {
+        println!(
+            "{} B3 lists no split, reverse split or bonus for the stocks held",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct EventRow {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        action_type: String,
+        #[tabled(rename = "Ex-Date")]
+        ex_date: String,
+        #[tabled(rename = "Factor")]
+        factor: String,
+        #[tabled(rename = "Held")]
+        held: String,
+        #[tabled(rename = "Adj Qty")]
+        quantity_adjustment: String,
+        #[tabled(rename = "Result")]
+        result: String,
+    }
+
+    let rows: Vec<_> = detections
+        .iter()
+        .map(|d| EventRow {
+            ticker: d.event.ticker.clone(),
+            action_type: d.event.action_type.as_str().to_string(),
+            ex_date: d.event.ex_date.to_string(),
+            factor: d.event.factor.normalize().to_string(),
+            held: d.held.to_string(),
+            quantity_adjustment: d.quantity_adjustment.to_string(),
+            result: match &d.outcome {
+                Outcome::Recorded {
+                    action_id: Some(id),
+                } => format!("recorded #{}", id),
+                Outcome::Recorded { action_id: None } => "new".to_string(),
+                Outcome::Known { action_id } => format!("known #{}", action_id),
+                Outcome::Conflict {
+                    inconsistency_id: Some(id),
+                } => format!("conflict, issue #{}", id),
+                Outcome::Conflict {
+                    inconsistency_id: None,
+                } => "conflict".to_string(),
+                Outcome::NotHeld => String::new(),
+            },
+        })
+        .collect();
+    println!("{}", Table::new(rows));
+
+    let count = |f: fn(&Outcome) -> bool| detections.iter().filter(|d| f(&d.outcome)).count();
+    let recorded = count(|o| matches!(o, Outcome::Recorded { .. }));
+    let conflicts = count(|o| matches!(o, Outcome::Conflict { .. }));
+    if dry_run {
+        println!(
+            "\n{} Dry run: {} new, {} conflicting; nothing saved",
+            "ℹ".blue().bold(),
+            recorded,
+            conflicts
+        );
+        return Ok(());
+    }
+    println!(
+        "\n{} {} new action(s) recorded, {} conflict(s) flagged",
+        "✓".green().bold(),
+        recorded,
+        conflicts
+    );
+    if recorded > 0 {
+        println!("  Review them with: interest actions review");
+    }
+    if conflicts > 0 {
+        println!("  Settle conflicts with: interest inconsistencies resolve");
+    }
+    Ok(())
+}
+
+fn dispatch_review(json_output: bool) -> Result<()> {
+    use crate::corporate_actions::review;
+
+    if !json_output {
+        return crate::ui::review_corporate_actions();
+    }
+
+    let conn = open_conn()?;
+    let payload = review::review_entries(&conn, chrono::Utc::now())?
+        .into_iter()
+        .map(|entry| {
+            let preview = review::preview(&conn, &entry.action)?;
+            let mut value = serde_json::to_value(&entry)?;
+            value["preview"] = serde_json::to_value(&preview)?;
+            Ok(value)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    println!("{}", serde_json::to_string_pretty(&payload)?);
+    Ok(())
+}
+
 fn parse_date(date_str: &str) -> Result<NaiveDate> {
     NaiveDate::parse_from_str(date_str, "%Y-%m-%d").context("Invalid date format. Use YYYY-MM-DD")
 }
@@ -0,0 +1,156 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+
+use crate::db;
+use crate::db::archive::{self, Archive, ArchiveCounts};
+
+pub async fn dispatch_db(action: &crate::cli::DbCommands, json_output: bool) -> Result<()> {
+    match action {
+        crate::cli::DbCommands::ExportJson { path } => export_json(path, json_output),
+        crate::cli::DbCommands::ImportJson { path } => import_json(path, json_output),
+        crate::cli::DbCommands::Encrypt => encrypt(json_output),
+        crate::cli::DbCommands::Decrypt => decrypt(json_output),
+    }
+}
+
+fn export_json(path: &str, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let archive = archive::export_archive(&conn)?;
+    let file = std::fs::File::create(path).with_context(|| format!("Failed to create {}", path))?;
+    serde_json::to_writer_pretty(std::io::BufWriter::new(file), &archive)
+        .with_context(|| format!("Failed to write archive to {}", path))?;
+
+    let counts = archive.counts();
+    if json_output {
+        let payload = serde_json::json!({
+            "path": path,
+            "version": archive.version,
+            "counts": counts,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} Exported archive v{} to {}",
+        "✓".green().bold(),
+        archive.version,
+        path
+    );
+    print_counts(&counts);
+    Ok(())
+}
+
+fn import_json(path: &str, json_output: bool) -> Result<()> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    let archive: Archive = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not a valid interest archive", path))?;
+    archive::validate_archive(&archive)?;
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let counts = archive::import_archive(&conn, &archive)?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "path": path,
+            "version": archive.version,
+            "exported_at": archive.exported_at.to_rfc3339(),
+            "counts": counts,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} Restored archive v{} exported at {}",
+        "✓".green().bold(),
+        archive.version,
+        archive.exported_at.format("%Y-%m-%d %H:%M")
+    );
+    print_counts(&counts);
+    println!(
+        "\n  Prices are not part of the archive; run {} to fetch them again.",
+        "interest prices update".cyan()
+    );
+    Ok(())
+}
+
+fn encrypt(json_output: bool) -> Result<()> {
+    if db::encryption::is_encrypted()? {
+        anyhow::bail!("The database is already encrypted");
+    }
+    db::init_database(None)?;
+    let passphrase = db::encryption::read_new_passphrase()?;
+    let path = db::encryption::encrypt_database(&passphrase)?;
+
+    if json_output {
+        let payload = serde_json::json!({ "encrypted": true, "path": path });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+    println!(
+        "{} Database encrypted to {}",
+        "✓".green().bold(),
+        path.display()
+    );
+    println!(
+        "  Every command now asks for the passphrase (or reads {}).",
+        db::encryption::PASSPHRASE_ENV.cyan()
+    );
+    println!(
+        "  {} There is no recovery without it; older backups stay unencrypted.",
+        "⚠".yellow()
+    );
+    Ok(())
+}
+
+fn decrypt(json_output: bool) -> Result<()> {
+    let path = db::encryption::decrypt_database()?;
+
+    if json_output {
+        let payload = serde_json::json!({ "encrypted": false, "path": path });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+    println!(
+        "{} Database decrypted to {}",
+        "✓".green().bold(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn print_counts(counts: &ArchiveCounts) {
+    let rows = [
+        ("Portfolios", counts.portfolios),
+        ("Brokers", counts.brokers),
+        ("Assets", counts.assets),
+        ("Transactions", counts.transactions),
+        ("Corporate actions", counts.corporate_actions),
+        ("Renames", counts.asset_renames),
+        ("Spin-offs/mergers", counts.asset_exchanges),
+        ("Income events", counts.income_events),
+        ("Inconsistencies", counts.inconsistencies),
+        ("Journal entries", counts.journal_entries),
+        ("Asset tags", counts.asset_tags),
+        ("Import cursors", counts.import_state),
+        ("Loss snapshots", counts.loss_carryforward_snapshots),
+        ("Brokerage notes", counts.broker_notes),
+        ("Term contracts", counts.term_contracts),
+        ("Option contracts", counts.option_contracts),
+        ("Option exercises", counts.option_exercises),
+        ("Fixed income terms", counts.fixed_income_terms),
+        ("Cash credits", counts.cash_credits),
+        ("Position statements", counts.position_statements),
+        ("Valuations", counts.asset_valuations),
+        ("Issuers", counts.asset_issuers),
+        ("DARF payments", counts.tax_payments),
+    ];
+    for (label, count) in rows {
+        println!("  {:21} {}", format!("{}:", label), count);
+    }
+}
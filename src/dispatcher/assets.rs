@@ -7,8 +7,8 @@ use crate::{db, reports, scraping};
 
 pub async fn dispatch_assets(action: &crate::cli::AssetsCommands, json_output: bool) -> Result<()> {
     match action {
-        crate::cli::AssetsCommands::List { asset_type } => {
-            list_assets(asset_type.as_deref(), json_output)
+        crate::cli::AssetsCommands::List { asset_type, table } => {
+            list_assets(asset_type.as_deref(), table, json_output)
         }
         crate::cli::AssetsCommands::Show { ticker } => show_asset(ticker, json_output),
         crate::cli::AssetsCommands::Add {
@@ -22,11 +22,39 @@ pub async fn dispatch_assets(action: &crate::cli::AssetsCommands, json_output: b
         crate::cli::AssetsCommands::SetName { ticker, name } => {
             set_asset_name(ticker, name, json_output)
         }
+        crate::cli::AssetsCommands::SetCurrency { ticker, currency } => {
+            set_asset_currency(ticker, currency, json_output)
+        }
+        crate::cli::AssetsCommands::SetCnpj { ticker, cnpj } => {
+            set_asset_cnpj(ticker, cnpj, json_output)
+        }
+        crate::cli::AssetsCommands::EnrichCnpj {
+            ticker,
+            refresh,
+            dry_run,
+        } => enrich_cnpj(ticker.as_deref(), *refresh, *dry_run, json_output).await,
+        crate::cli::AssetsCommands::SetValue {
+            ticker,
+            value,
+            date,
+            notes,
+            clear,
+        } => set_asset_value(
+            ticker,
+            value.as_deref(),
+            date.as_deref(),
+            notes.as_deref(),
+            *clear,
+            json_output,
+        ),
         crate::cli::AssetsCommands::Rename {
             old_ticker,
             new_ticker,
         } => rename_asset(old_ticker, new_ticker, json_output),
         crate::cli::AssetsCommands::Remove { ticker } => remove_asset(ticker, json_output),
+        crate::cli::AssetsCommands::Tag { ticker, tag } => tag_asset(ticker, tag, json_output),
+        crate::cli::AssetsCommands::Untag { ticker, tag } => untag_asset(ticker, tag, json_output),
+        crate::cli::AssetsCommands::Tags => list_tags(json_output),
         crate::cli::AssetsCommands::SyncMaisRetorno {
             asset_type,
             dry_run,
@@ -39,7 +67,11 @@ fn open_conn() -> Result<rusqlite::Connection> {
     db::open_db(None)
 }
 
-fn list_assets(asset_type: Option<&str>, json_output: bool) -> Result<()> {
+fn list_assets(
+    asset_type: Option<&str>,
+    table: &crate::cli::formatters::TableArgs,
+    json_output: bool,
+) -> Result<()> {
     let conn = open_conn()?;
     let assets = if let Some(type_str) = asset_type {
         let parsed = parse_asset_type(type_str)?;
@@ -48,12 +80,13 @@ fn list_assets(asset_type: Option<&str>, json_output: bool) -> Result<()> {
         db::get_all_assets(&conn)?
     };
 
+    let page = table.paginate(assets);
     if json_output {
-        println!("{}", serde_json::to_string_pretty(&assets)?);
+        println!("{}", serde_json::to_string_pretty(&page.rows)?);
         return Ok(());
     }
 
-    if assets.is_empty() {
+    if page.total == 0 {
         println!("{} No assets found.", "ℹ".blue().bold());
         return Ok(());
     }
@@ -68,8 +101,10 @@ fn list_assets(asset_type: Option<&str>, json_output: bool) -> Result<()> {
         name: String,
     }
 
-    let rows: Vec<_> = assets
-        .into_iter()
+    let rows: Vec<_> = page
+        .rows
+        .iter()
+        .cloned()
         .map(|asset| AssetRow {
             ticker: asset.ticker,
             asset_type: asset.asset_type.as_str().to_string(),
@@ -77,8 +112,12 @@ fn list_assets(asset_type: Option<&str>, json_output: bool) -> Result<()> {
         })
         .collect();
 
-    let table = Table::new(rows).to_string();
-    println!("{}", table);
+    let mut out = crate::cli::formatters::render_table(&rows, &table.columns, &[])?;
+    out.push('\n');
+    if let Some(footer) = page.footer() {
+        out.push_str(&format!("{}\n", footer.dimmed()));
+    }
+    crate::cli::formatters::print_paged(&out, table.no_pager);
     Ok(())
 }
 
@@ -86,6 +125,25 @@ fn show_asset(ticker: &str, json_output: bool) -> Result<()> {
     let conn = open_conn()?;
     let asset = db::get_asset_by_ticker(&conn, ticker)?.context("Ticker not found in assets")?;
     let tx_count = db::count_transactions_for_asset(&conn, &asset.ticker)?;
+    let alpha = reports::benchmark::asset_alpha(&conn, &asset)?;
+    let tags = match asset.id {
+        Some(id) => db::get_asset_tags(&conn, id)?,
+        None => Vec::new(),
+    };
+    let issuer = match asset.id {
+        Some(id) => db::get_asset_issuer(&conn, id)?,
+        None => None,
+    };
+    let valuation = match asset.id {
+        Some(id) => {
+            db::valuations::valuation_on_or_before(&conn, id, chrono::Local::now().date_naive())?
+        }
+        None => None,
+    };
+    let currency = match asset.id {
+        Some(id) => db::get_asset_currency(&conn, id)?,
+        None => None,
+    };
 
     if json_output {
         let payload = serde_json::json!({
@@ -93,9 +151,21 @@ fn show_asset(ticker: &str, json_output: bool) -> Result<()> {
             "asset_type": asset.asset_type.as_str(),
             "name": asset.name,
             "cnpj": asset.cnpj,
+            "currency": currency.as_deref().unwrap_or("BRL"),
+            "issuer": issuer,
             "created_at": asset.created_at.to_rfc3339(),
             "updated_at": asset.updated_at.to_rfc3339(),
             "transactions": tx_count,
+            "tags": tags,
+            "valuation": valuation,
+            "benchmark": alpha.as_ref().map(|a| serde_json::json!({
+                "benchmark": a.benchmark.as_str(),
+                "from": a.start_date.to_string(),
+                "to": a.end_date.to_string(),
+                "asset_return_pct": a.asset_return_pct.round_dp(2),
+                "benchmark_return_pct": a.benchmark_return_pct.round_dp(2),
+                "alpha_pct": a.alpha_pct.round_dp(2),
+            })),
         });
         println!("{}", serde_json::to_string_pretty(&payload)?);
         return Ok(());
@@ -104,10 +174,64 @@ fn show_asset(ticker: &str, json_output: bool) -> Result<()> {
     println!("Asset: {}", asset.ticker);
     println!("  Type: {}", asset.asset_type.as_str());
     println!("  Name: {}", asset.name.unwrap_or_else(|| "-".to_string()));
-    println!("  CNPJ: {}", asset.cnpj.unwrap_or_else(|| "-".to_string()));
+    println!(
+        "  CNPJ: {}",
+        super::format_cnpj(asset.cnpj.as_deref()).unwrap_or_else(|| "-".to_string())
+    );
+    if let Some(currency) = &currency {
+        println!("  Currency: {} (valued in BRL at the PTAX)", currency);
+    }
+    if let Some(issuer) = &issuer {
+        println!("  Razão social: {}", issuer.legal_name);
+        println!(
+            "  Situação: {}{}",
+            issuer.situation.as_deref().unwrap_or("-"),
+            issuer
+                .situation_date
+                .map(|d| format!(" (since {})", d))
+                .unwrap_or_default()
+        );
+    }
     println!("  Created: {}", asset.created_at.to_rfc3339());
     println!("  Updated: {}", asset.updated_at.to_rfc3339());
     println!("  Transactions: {}", tx_count);
+    if !tags.is_empty() {
+        println!("  Tags: {}", tags.join(", "));
+    }
+    if let Some(valuation) = &valuation {
+        println!(
+            "  Manual valuation: {} on {}{}",
+            match &currency {
+                Some(code) => format!("{} {}", code, valuation.value),
+                None => super::format_currency(valuation.value),
+            },
+            valuation.valuation_date.format("%d/%m/%Y"),
+            valuation
+                .notes
+                .as_deref()
+                .map(|n| format!(" ({})", n))
+                .unwrap_or_default()
+        );
+    }
+    if let Some(alpha) = alpha {
+        let alpha_str = format!("{:+.2} pp", alpha.alpha_pct);
+        println!(
+            "  vs {} ({} → {}):",
+            alpha.benchmark.as_str(),
+            alpha.start_date,
+            alpha.end_date
+        );
+        println!("    Asset return:     {:.2}%", alpha.asset_return_pct);
+        println!("    Benchmark return: {:.2}%", alpha.benchmark_return_pct);
+        println!(
+            "    Alpha:            {}",
+            if alpha.alpha_pct >= rust_decimal::Decimal::ZERO {
+                alpha_str.green()
+            } else {
+                alpha_str.red()
+            }
+        );
+    }
     Ok(())
 }
 
@@ -187,6 +311,273 @@ fn set_asset_name(ticker: &str, name: &str, json_output: bool) -> Result<()> {
     Ok(())
 }
 
+fn set_asset_cnpj(ticker: &str, cnpj: &str, json_output: bool) -> Result<()> {
+    use crate::scraping::cnpj::{is_valid_cnpj, normalize_cnpj};
+
+    let digits = normalize_cnpj(cnpj)
+        .filter(|d| is_valid_cnpj(d))
+        .ok_or_else(|| anyhow::anyhow!("Invalid CNPJ: {} (check digits do not match)", cnpj))?;
+
+    let conn = open_conn()?;
+    db::get_asset_by_ticker(&conn, ticker)?.context("Ticker not found in assets")?;
+    db::update_asset_cnpj(&conn, ticker, &digits)?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "ticker": ticker.to_uppercase(),
+            "cnpj": digits,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    println!(
+        "Updated {} CNPJ to {}",
+        ticker.to_uppercase(),
+        super::format_cnpj(Some(&digits)).unwrap_or(digits)
+    );
+    Ok(())
+}
+
+fn set_asset_currency(ticker: &str, currency: &str, json_output: bool) -> Result<()> {
+    let currency = match currency.trim().to_uppercase().as_str() {
+        "BRL" => None,
+        code => Some(crate::pricing::fx::parse_currency(code)?),
+    };
+
+    let conn = open_conn()?;
+    db::get_asset_by_ticker(&conn, ticker)?.context("Ticker not found in assets")?;
+    db::update_asset_currency(&conn, ticker, currency.as_deref())?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "ticker": ticker.to_uppercase(),
+            "currency": currency.as_deref().unwrap_or("BRL"),
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    match &currency {
+        Some(code) => {
+            println!(
+                "{} now quoted in {}: its prices and valuations are in {} and converted at the PTAX of their date",
+                ticker.to_uppercase(),
+                code,
+                code
+            );
+            let pair = crate::pricing::fx::pair(code);
+            if db::get_latest_fx_date(&conn, &pair)?.is_none() {
+                println!(
+                    "{} No {}/BRL rates yet: run prices update-fx {}",
+                    "ℹ".blue().bold(),
+                    code,
+                    code
+                );
+            }
+        }
+        None => println!("{} now quoted in BRL", ticker.to_uppercase()),
+    }
+    Ok(())
+}
+
+fn set_asset_value(
+    ticker: &str,
+    value: Option<&str>,
+    date: Option<&str>,
+    notes: Option<&str>,
+    clear: bool,
+    json_output: bool,
+) -> Result<()> {
+    use db::valuations::{self, Valuation};
+    use rust_decimal::Decimal;
+
+    let conn = open_conn()?;
+    let asset = db::get_asset_by_ticker(&conn, ticker)?.context("Ticker not found in assets")?;
+    let asset_id = asset.id.context("Asset missing id")?;
+
+    if clear {
+        let removed = valuations::clear_valuations(&conn, asset_id)?;
+        if json_output {
+            let payload = serde_json::json!({
+                "ticker": asset.ticker,
+                "removed": removed,
+            });
+            println!("{}", serde_json::to_string_pretty(&payload)?);
+            return Ok(());
+        }
+        println!(
+            "Removed {} valuation(s) of {}; market prices apply again",
+            removed, asset.ticker
+        );
+        return Ok(());
+    }
+
+    let value = value.context("A value is required")?;
+    let value: Decimal = value
+        .trim()
+        .replace(',', ".")
+        .parse()
+        .with_context(|| format!("Invalid value '{}'", value))?;
+    if value < Decimal::ZERO {
+        anyhow::bail!("The value cannot be negative");
+    }
+    let valuation_date = match date {
+        Some(d) => chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d")
+            .with_context(|| format!("Invalid date '{}'. Use YYYY-MM-DD", d))?,
+        None => chrono::Local::now().date_naive(),
+    };
+    let valuation = Valuation {
+        asset_id,
+        valuation_date,
+        value,
+        notes: notes.map(str::to_string),
+    };
+    valuations::set_valuation(&conn, &valuation)?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "ticker": asset.ticker,
+            "valuation_date": valuation.valuation_date.to_string(),
+            "value": valuation.value.to_string(),
+            "notes": valuation.notes,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    println!(
+        "{} Valued {} at {} per unit from {}",
+        "✓".green().bold(),
+        asset.ticker.cyan().bold(),
+        super::format_currency(value),
+        valuation_date.format("%d/%m/%Y")
+    );
+    println!(
+        "  {}",
+        "Used by the portfolio until a newer valuation or market close".dimmed()
+    );
+    Ok(())
+}
+
+async fn enrich_cnpj(
+    ticker: Option<&str>,
+    refresh: bool,
+    dry_run: bool,
+    json_output: bool,
+) -> Result<()> {
+    use crate::scraping::cnpj::{is_valid_cnpj, lookup_cnpj, normalize_cnpj};
+
+    let conn = open_conn()?;
+    let assets = match ticker {
+        Some(ticker) => {
+            vec![db::get_asset_by_ticker(&conn, ticker)?.context("Ticker not found in assets")?]
+        }
+        None => db::get_all_assets(&conn)?
+            .into_iter()
+            .filter(|a| a.cnpj.is_some())
+            .collect(),
+    };
+
+    #[derive(Tabled, serde::Serialize)]
+    struct EnrichRow {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "CNPJ")]
+        cnpj: String,
+        #[tabled(rename = "Razão social")]
+        legal_name: String,
+        #[tabled(rename = "Situação")]
+        situation: String,
+        #[tabled(rename = "Status")]
+        status: String,
+    }
+
+    let mut rows = Vec::new();
+    for asset in assets {
+        let Some(asset_id) = asset.id else { continue };
+        let row = |cnpj: &str, legal_name: &str, situation: &str, status: &str| EnrichRow {
+            ticker: asset.ticker.clone(),
+            cnpj: super::format_cnpj(Some(cnpj)).unwrap_or_else(|| "-".to_string()),
+            legal_name: legal_name.to_string(),
+            situation: situation.to_string(),
+            status: status.to_string(),
+        };
+
+        let Some(raw) = asset.cnpj.as_deref() else {
+            rows.push(row("", "-", "-", "no CNPJ (use assets set-cnpj)"));
+            continue;
+        };
+        let Some(digits) = normalize_cnpj(raw).filter(|d| is_valid_cnpj(d)) else {
+            rows.push(row(raw, "-", "-", "invalid CNPJ"));
+            continue;
+        };
+        if !refresh {
+            if let Some(issuer) = db::get_asset_issuer(&conn, asset_id)? {
+                if issuer.cnpj == digits {
+                    let situation = issuer.situation.as_deref().unwrap_or("-");
+                    rows.push(row(&digits, &issuer.legal_name, situation, "cached"));
+                    continue;
+                }
+            }
+        }
+
+        match lookup_cnpj(&digits).await {
+            Ok(record) => {
+                let issuer = record.into_issuer(asset_id);
+                let status = if !issuer.is_active() {
+                    "not active"
+                } else if dry_run {
+                    "found"
+                } else {
+                    "updated"
+                };
+                if !dry_run {
+                    db::upsert_asset_issuer(&conn, &issuer)?;
+                }
+                let situation = issuer.situation.as_deref().unwrap_or("-");
+                rows.push(row(&digits, &issuer.legal_name, situation, status));
+            }
+            Err(err) => {
+                tracing::warn!("CNPJ lookup failed for {}: {}", asset.ticker, err);
+                rows.push(row(&digits, "-", "-", "lookup failed"));
+            }
+        }
+        // Both services are rate limited
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    }
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    if rows.is_empty() {
+        println!(
+            "{} No assets with a CNPJ. Set one with: interest assets set-cnpj <ticker> <cnpj>",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    }
+
+    println!("{}", Table::new(&rows));
+    if dry_run {
+        println!("{}", "Dry run: nothing was stored".dimmed());
+    }
+    let attention = rows
+        .iter()
+        .filter(|r| !matches!(r.status.as_str(), "updated" | "found" | "cached"))
+        .count();
+    if attention > 0 {
+        println!(
+            "{} {} asset(s) need attention before declaring in IRPF",
+            "⚠".yellow().bold(),
+            attention
+        );
+    }
+    Ok(())
+}
+
 fn rename_asset(old_ticker: &str, new_ticker: &str, json_output: bool) -> Result<()> {
     println!(
         "Are you sure you want to rename {} to {}?",
@@ -234,14 +625,10 @@ fn remove_asset(ticker: &str, json_output: bool) -> Result<()> {
         return Ok(());
     }
 
-    let earliest_trade_date = db::get_earliest_transaction_date_for_asset(&conn, &asset.ticker)?;
     let deleted = db::delete_asset(&conn, &asset.ticker)?;
     if deleted == 0 {
         anyhow::bail!("Ticker {} not found in assets", asset.ticker);
     }
-    if let Some(date) = earliest_trade_date {
-        reports::invalidate_snapshots_after(&conn, date)?;
-    }
 
     if json_output {
         let payload = serde_json::json!({
@@ -255,6 +642,119 @@ fn remove_asset(ticker: &str, json_output: bool) -> Result<()> {
     Ok(())
 }
 
+fn tag_asset(ticker: &str, tag: &str, json_output: bool) -> Result<()> {
+    let conn = open_conn()?;
+    let asset = db::get_asset_by_ticker(&conn, ticker)?.context("Ticker not found in assets")?;
+    let asset_id = asset.id.context("Asset has no id")?;
+    let tag = db::normalize_tag(tag)?;
+    let added = db::add_asset_tag(&conn, asset_id, &tag)?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "ticker": asset.ticker,
+            "tag": tag,
+            "added": added,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    if added {
+        println!(
+            "{} Tagged {} as '{}'",
+            "✓".green().bold(),
+            asset.ticker,
+            tag
+        );
+    } else {
+        println!(
+            "{} {} is already tagged '{}'",
+            "ℹ".blue().bold(),
+            asset.ticker,
+            tag
+        );
+    }
+    Ok(())
+}
+
+fn untag_asset(ticker: &str, tag: &str, json_output: bool) -> Result<()> {
+    let conn = open_conn()?;
+    let asset = db::get_asset_by_ticker(&conn, ticker)?.context("Ticker not found in assets")?;
+    let asset_id = asset.id.context("Asset has no id")?;
+    let tag = db::normalize_tag(tag)?;
+    let removed = db::remove_asset_tag(&conn, asset_id, &tag)?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "ticker": asset.ticker,
+            "tag": tag,
+            "removed": removed,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    if removed {
+        println!(
+            "{} Removed tag '{}' from {}",
+            "✓".green().bold(),
+            tag,
+            asset.ticker
+        );
+    } else {
+        println!(
+            "{} {} is not tagged '{}'",
+            "ℹ".blue().bold(),
+            asset.ticker,
+            tag
+        );
+    }
+    Ok(())
+}
+
+fn list_tags(json_output: bool) -> Result<()> {
+    let conn = open_conn()?;
+    let tags = db::list_asset_tags(&conn)?;
+
+    if json_output {
+        let payload: Vec<_> = tags
+            .iter()
+            .map(|(tag, tickers)| serde_json::json!({ "tag": tag, "tickers": tickers }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    if tags.is_empty() {
+        println!(
+            "{} No tags yet. Group assets with: interest assets tag <ticker> <tag>",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct TagRow {
+        #[tabled(rename = "Tag")]
+        tag: String,
+        #[tabled(rename = "Assets")]
+        count: usize,
+        #[tabled(rename = "Tickers")]
+        tickers: String,
+    }
+
+    let rows: Vec<TagRow> = tags
+        .into_iter()
+        .map(|(tag, tickers)| TagRow {
+            tag,
+            count: tickers.len(),
+            tickers: tickers.join(", "),
+        })
+        .collect();
+    println!("{}", Table::new(rows));
+    Ok(())
+}
+
 async fn sync_maisretorno(
     asset_type: Option<&str>,
     dry_run: bool,
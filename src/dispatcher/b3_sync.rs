@@ -0,0 +1,293 @@
+//! `sync-b3`: incremental import from the B3 investor API.
+//!
+//! Trades go through the CEI import path and movements through the
+//! Movimentação one, so API syncs and manual Excel imports share their
+//! `import_state` and never import the same period twice. Movement rows that
+//! are trades are dropped because the negotiations endpoint already has them.
+//!
+//! Each endpoint is synced month by month and its `B3_API` state is saved
+//! after every month, so an interrupted sync resumes where it stopped.
+//! The custody reported at the end is stored as a position statement.
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use colored::Colorize;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::importers::b3_api::B3ApiClient;
+use crate::importers::{ImportResult, ImportStats};
+use crate::{config, db, reports};
+
+const SOURCE: &str = "B3_API";
+
+/// Used when neither the config nor any earlier import gives a start date
+fn default_start_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2019, 11, 1).expect("valid date")
+}
+
+#[derive(Debug, Default, Serialize)]
+struct SyncSummary {
+    synced_until: Option<NaiveDate>,
+    trades_fetched: usize,
+    movements_fetched: usize,
+    trades: ImportStats,
+    movements: ImportStats,
+    position_mismatches: Vec<PositionMismatch>,
+}
+
+#[derive(Debug, Serialize)]
+struct PositionMismatch {
+    ticker: String,
+    b3_quantity: Decimal,
+    computed_quantity: Decimal,
+}
+
+pub async fn dispatch_sync_b3(from: Option<&str>, dry_run: bool, json_output: bool) -> Result<()> {
+    let cfg = config::load_config()?;
+    let api_cfg = cfg.b3_api.ok_or_else(|| {
+        anyhow!(
+            "No [b3_api] section in {:?}. See the README for the required keys",
+            config::get_config_path().unwrap_or_default()
+        )
+    })?;
+    let from = from
+        .map(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d"))
+        .transpose()
+        .map_err(|_| anyhow!("Invalid --from date, expected YYYY-MM-DD"))?;
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    // B3 publishes each day's data on the next business day
+    let until = Local::now().date_naive() - Duration::days(1);
+    let start = api_cfg.start_date.unwrap_or_else(default_start_date);
+    let client = B3ApiClient::connect(&api_cfg).await?;
+    let mut summary = SyncSummary::default();
+
+    let trades_from = from.unwrap_or(next_day(
+        latest_state(
+            &conn,
+            &[
+                (SOURCE, "trades"),
+                ("CEI", "trades"),
+                ("MOVIMENTACAO", "trades"),
+            ],
+        )?,
+        start,
+    ));
+    for (chunk_from, chunk_to) in month_chunks(trades_from, until) {
+        let trades = client.trades(chunk_from, chunk_to).await?;
+        summary.trades_fetched += trades.len();
+        if !dry_run {
+            let raw: Vec<_> = trades.iter().map(|t| t.to_raw_transaction()).collect();
+            let stats = super::imports_helpers::import_parsed(&conn, ImportResult::Cei(raw))?;
+            accumulate(&mut summary.trades, &stats);
+            db::set_last_import_date(&conn, SOURCE, "trades", chunk_to)?;
+        }
+        summary.synced_until = Some(chunk_to);
+    }
+
+    let movements_from = from.unwrap_or(next_day(
+        latest_state(&conn, &[(SOURCE, "movements")])?,
+        start,
+    ));
+    for (chunk_from, chunk_to) in month_chunks(movements_from, until) {
+        let movements = client.movements(chunk_from, chunk_to).await?;
+        summary.movements_fetched += movements.len();
+        if !dry_run {
+            let entries: Vec<_> = movements
+                .iter()
+                .map(|m| m.to_movimentacao_entry())
+                .filter(|e| !e.is_trade())
+                .collect();
+            let stats =
+                super::imports_helpers::import_parsed(&conn, ImportResult::Movimentacao(entries))?;
+            accumulate(&mut summary.movements, &stats);
+            db::set_last_import_date(&conn, SOURCE, "movements", chunk_to)?;
+        }
+        summary.synced_until = Some(chunk_to);
+    }
+
+    let positions_synced = db::get_last_import_date(&conn, SOURCE, "positions")?;
+    if positions_synced.is_none_or(|d| d < until) {
+        let mut b3_quantities: BTreeMap<String, Decimal> = BTreeMap::new();
+        for position in client.positions(until).await? {
+            *b3_quantities
+                .entry(position.ticker_symbol.trim().to_uppercase())
+                .or_default() += position.equities_quantity;
+        }
+        summary.position_mismatches = reconcile(&conn, until, &b3_quantities)?;
+        if !dry_run {
+            db::position_statements::record_statement(&conn, until, SOURCE, &b3_quantities)?;
+            db::set_last_import_date(&conn, SOURCE, "positions", until)?;
+        }
+    }
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "success": true,
+                "dry_run": dry_run,
+                "data": summary,
+            }))?
+        );
+        return Ok(());
+    }
+
+    print_summary(&summary, dry_run);
+    Ok(())
+}
+
+/// Most recent date among the given `import_state` entries
+fn latest_state(conn: &rusqlite::Connection, keys: &[(&str, &str)]) -> Result<Option<NaiveDate>> {
+    let mut latest = None;
+    for (source, entry_type) in keys {
+        latest = latest.max(db::get_last_import_date(conn, source, entry_type)?);
+    }
+    Ok(latest)
+}
+
+fn next_day(last: Option<NaiveDate>, start: NaiveDate) -> NaiveDate {
+    last.map(|d| d + Duration::days(1)).unwrap_or(start)
+}
+
+/// Split [from, until] into calendar-month windows
+fn month_chunks(from: NaiveDate, until: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    let mut chunks = Vec::new();
+    let mut cursor = from;
+    while cursor <= until {
+        let next_month = if cursor.month() == 12 {
+            NaiveDate::from_ymd_opt(cursor.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(cursor.year(), cursor.month() + 1, 1)
+        }
+        .expect("valid date");
+        let end = (next_month - Duration::days(1)).min(until);
+        chunks.push((cursor, end));
+        cursor = next_month;
+    }
+    chunks
+}
+
+/// Compare B3 custody with the holdings computed from the database
+fn reconcile(
+    conn: &rusqlite::Connection,
+    date: NaiveDate,
+    b3_quantities: &BTreeMap<String, Decimal>,
+) -> Result<Vec<PositionMismatch>> {
+    let computed: BTreeMap<String, Decimal> =
+        reports::calculate_portfolio_at_date(conn, date, None)?
+            .positions
+            .into_iter()
+            .filter(|p| !p.quantity.is_zero())
+            .map(|p| (p.asset.ticker, p.quantity))
+            .collect();
+
+    let mut tickers: Vec<&String> = b3_quantities.keys().chain(computed.keys()).collect();
+    tickers.sort();
+    tickers.dedup();
+
+    Ok(tickers
+        .into_iter()
+        .filter_map(|ticker| {
+            let b3_quantity = b3_quantities.get(ticker).copied().unwrap_or_default();
+            let computed_quantity = computed.get(ticker).copied().unwrap_or_default();
+            (b3_quantity != computed_quantity).then(|| PositionMismatch {
+                ticker: ticker.clone(),
+                b3_quantity,
+                computed_quantity,
+            })
+        })
+        .collect())
+}
+
+fn accumulate(total: &mut ImportStats, stats: &ImportStats) {
+    total.imported += stats.imported;
+    total.skipped_old += stats.skipped_old;
+    total.imported_trades += stats.imported_trades;
+    total.imported_actions += stats.imported_actions;
+    total.skipped_actions += stats.skipped_actions;
+    total.imported_income += stats.imported_income;
+    total.skipped_income += stats.skipped_income;
+    total.errors += stats.errors;
+    total.earliest = match (total.earliest, stats.earliest) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    total.latest = total.latest.max(stats.latest);
+}
+
+fn print_summary(summary: &SyncSummary, dry_run: bool) {
+    let Some(until) = summary.synced_until else {
+        println!("{} B3 API data is already up to date", "✓".green().bold());
+        print_mismatches(&summary.position_mismatches);
+        return;
+    };
+
+    if dry_run {
+        println!(
+            "{} Dry run through {}: {} trades and {} movements available, nothing saved",
+            "🔍".cyan().bold(),
+            until.format("%d/%m/%Y"),
+            summary.trades_fetched,
+            summary.movements_fetched
+        );
+    } else {
+        println!(
+            "{} Synced B3 API data through {}",
+            "✓".green().bold(),
+            until.format("%d/%m/%Y")
+        );
+        println!(
+            "  Trades: {} imported, {} already imported",
+            summary.trades.imported, summary.trades.skipped_old
+        );
+        println!(
+            "  Movements: {} trades/redemptions, {} corporate actions, {} income events",
+            summary.movements.imported_trades,
+            summary.movements.imported_actions,
+            summary.movements.imported_income
+        );
+        let errors = summary.trades.errors + summary.movements.errors;
+        if errors > 0 {
+            println!("  {} {} entries failed to import", "⚠".yellow(), errors);
+        }
+    }
+    print_mismatches(&summary.position_mismatches);
+}
+
+fn print_mismatches(mismatches: &[PositionMismatch]) {
+    if mismatches.is_empty() {
+        return;
+    }
+    println!("\n{} Holdings differ from B3 custody:", "⚠".yellow().bold());
+    for m in mismatches {
+        println!(
+            "  {:10} B3 {:>12}  computed {:>12}",
+            m.ticker, m.b3_quantity, m.computed_quantity
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn month_chunks_cover_range_without_gaps() {
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+        let chunks = month_chunks(d(2023, 12, 20), d(2024, 2, 10));
+        assert_eq!(
+            chunks,
+            vec![
+                (d(2023, 12, 20), d(2023, 12, 31)),
+                (d(2024, 1, 1), d(2024, 1, 31)),
+                (d(2024, 2, 1), d(2024, 2, 10)),
+            ]
+        );
+        assert!(month_chunks(d(2024, 3, 1), d(2024, 2, 29)).is_empty());
+    }
+}
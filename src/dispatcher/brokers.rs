@@ -0,0 +1,177 @@
+use anyhow::Result;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::db;
+use crate::reports::broker_statement::{self, BrokerMonth};
+use crate::utils::format_currency;
+
+/// Which half of the statement to show
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StatementView {
+    Income,
+    Tax,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Monthly income or sales of `year`, one table per broker account
+pub fn dispatch_broker_statement(year: i32, view: StatementView, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    if !broker_statement::has_broker_data(&conn)? {
+        anyhow::bail!(
+            "No trade or income names its broker. Import a B3 movimentação file with the \"Instituição\" column first."
+        );
+    }
+
+    let months: Vec<BrokerMonth> = broker_statement::broker_statement(&conn, year)?
+        .into_iter()
+        .filter(|m| match view {
+            StatementView::Income => {
+                !(m.dividends + m.jcp + m.interest + m.amortization + m.income_withheld).is_zero()
+            }
+            StatementView::Tax => !(m.sales + m.sales_irrf).is_zero(),
+        })
+        .collect();
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&months)?);
+        return Ok(());
+    }
+
+    if months.is_empty() {
+        let what = match view {
+            StatementView::Income => "income events",
+            StatementView::Tax => "sales",
+        };
+        println!("\n{} No {} found for {}\n", "ℹ".blue().bold(), what, year);
+        return Ok(());
+    }
+
+    let title = match view {
+        StatementView::Income => "Income by broker",
+        StatementView::Tax => "Sales by broker",
+    };
+    println!("\n{} {} - {}", "🏦".cyan().bold(), title, year);
+
+    let mut start = 0;
+    while start < months.len() {
+        let broker = &months[start].broker;
+        let end = start
+            + months[start..]
+                .iter()
+                .take_while(|m| &m.broker == broker)
+                .count();
+        let group = &months[start..end];
+        start = end;
+
+        println!(
+            "\n{}",
+            broker.as_deref().unwrap_or("(no broker recorded)").bold()
+        );
+        let table = match view {
+            StatementView::Income => income_table(group),
+            StatementView::Tax => sales_table(group),
+        };
+        println!("{}", table);
+    }
+
+    if view == StatementView::Tax {
+        println!(
+            "\n  {}",
+            "Tax due is computed on all brokers together; see 'interest tax summary'".dimmed()
+        );
+    }
+    println!();
+    Ok(())
+}
+
+fn month_name(month: u32) -> String {
+    MONTH_NAMES[(month - 1) as usize].to_string()
+}
+
+fn income_table(months: &[BrokerMonth]) -> String {
+    #[derive(Tabled)]
+    struct IncomeRow {
+        #[tabled(rename = "Month")]
+        month: String,
+        #[tabled(rename = "Dividends")]
+        dividends: String,
+        #[tabled(rename = "JCP")]
+        jcp: String,
+        #[tabled(rename = "Interest")]
+        interest: String,
+        #[tabled(rename = "Amortization")]
+        amortization: String,
+        #[tabled(rename = "IRRF")]
+        withheld: String,
+    }
+
+    let total = |f: fn(&BrokerMonth) -> Decimal| months.iter().map(f).sum::<Decimal>();
+    let mut rows: Vec<IncomeRow> = months
+        .iter()
+        .map(|m| IncomeRow {
+            month: month_name(m.month),
+            dividends: format_currency(m.dividends),
+            jcp: format_currency(m.jcp),
+            interest: format_currency(m.interest),
+            amortization: format_currency(m.amortization),
+            withheld: format_currency(m.income_withheld),
+        })
+        .collect();
+    rows.push(IncomeRow {
+        month: "Total".to_string(),
+        dividends: format_currency(total(|m| m.dividends)),
+        jcp: format_currency(total(|m| m.jcp)),
+        interest: format_currency(total(|m| m.interest)),
+        amortization: format_currency(total(|m| m.amortization)),
+        withheld: format_currency(total(|m| m.income_withheld)),
+    });
+    Table::new(rows)
+        .with(Style::rounded())
+        .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+        .to_string()
+}
+
+fn sales_table(months: &[BrokerMonth]) -> String {
+    #[derive(Tabled)]
+    struct SalesRow {
+        #[tabled(rename = "Month")]
+        month: String,
+        #[tabled(rename = "Sales")]
+        sales: String,
+        #[tabled(rename = "Net Gain")]
+        net_gain: String,
+        #[tabled(rename = "IRRF on Sales")]
+        sales_irrf: String,
+    }
+
+    let total = |f: fn(&BrokerMonth) -> Decimal| months.iter().map(f).sum::<Decimal>();
+    let mut rows: Vec<SalesRow> = months
+        .iter()
+        .map(|m| SalesRow {
+            month: month_name(m.month),
+            sales: format_currency(m.sales),
+            net_gain: format_currency(m.net_gain),
+            sales_irrf: format_currency(m.sales_irrf),
+        })
+        .collect();
+    rows.push(SalesRow {
+        month: "Total".to_string(),
+        sales: format_currency(total(|m| m.sales)),
+        net_gain: format_currency(total(|m| m.net_gain)),
+        sales_irrf: format_currency(total(|m| m.sales_irrf)),
+    });
+    Table::new(rows)
+        .with(Style::rounded())
+        .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+        .to_string()
+}
@@ -22,6 +22,10 @@ pub async fn dispatch_cashflow(
             let period_str = period.as_deref().unwrap_or("ALL");
             dispatch_cashflow_stats(period_str, json_output).await
         }
+        crate::cli::CashFlowCommands::Net { period } => {
+            let period_str = period.as_deref().unwrap_or("ALL");
+            dispatch_cashflow_net(period_str, json_output).await
+        }
     }
 }
 
@@ -481,4 +485,81 @@ async fn dispatch_cashflow_stats(period_str: &str, json_output: bool) -> Result<
 
     Ok(())
 }
+async fn dispatch_cashflow_net(period_str: &str, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let period = parse_period_string(period_str)?;
+    let (from_date, to_date) = crate::reports::performance::get_period_dates(period, Some(&conn))?;
+    let report = cashflow::calculate_cash_flow_report(&conn, from_date, to_date)?;
+    let years = cashflow::net_flow_by_year(&report);
+    let total_net: Decimal = years.iter().map(|y| y.net_new_capital).sum();
+
+    if json_output {
+        let payload = serde_json::json!({
+            "from_date": from_date,
+            "to_date": to_date,
+            "net_new_capital": total_net,
+            "years": years,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    if years.is_empty() {
+        println!(
+            "\n{} No cash flow data found for the selected period.\n",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Net New Capital ({} - {})\n",
+        "🏦".cyan().bold(),
+        from_date,
+        to_date
+    );
+
+    #[derive(Tabled)]
+    struct NetRow {
+        #[tabled(rename = "Year")]
+        year: String,
+        #[tabled(rename = "Deposited")]
+        deposited: String,
+        #[tabled(rename = "Withdrawn")]
+        withdrawn: String,
+        #[tabled(rename = "Reinvested Income")]
+        reinvested_income: String,
+        #[tabled(rename = "Net New Capital")]
+        net_new_capital: String,
+    }
+
+    let rows = years
+        .iter()
+        .map(|y| NetRow {
+            year: y.year.to_string(),
+            deposited: format_currency(y.deposited),
+            withdrawn: format_currency(y.withdrawn),
+            reinvested_income: format_currency(y.reinvested_income),
+            net_new_capital: format_currency(y.net_new_capital),
+        })
+        .collect::<Vec<_>>();
+    let mut table = Table::new(rows);
+    table.with(Style::modern());
+    println!("{}", table);
+
+    println!(
+        "\n{} {}",
+        "Total net new capital:".bold(),
+        format_currency(total_net).cyan()
+    );
+    println!(
+        "{}",
+        "Sale proceeds and income are assumed to fund the same year's buys first.".dimmed()
+    );
+
+    Ok(())
+}
+
 type CashFlowTotals = (Decimal, Decimal, Decimal);
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use std::collections::BTreeSet;
+use tabled::{
+    builder::Builder,
+    settings::{object::Columns, Alignment, Modify, Style},
+};
+
+use crate::db;
+use crate::reports::compare::{compare_assets, AssetComparison};
+use crate::reports::performance::get_period_dates;
+use crate::utils::format_currency;
+
+/// Held assets side by side over a period
+pub fn dispatch_compare(tickers: &[String], period: &str, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let period = super::performance::parse_period_string(period)?;
+    let (from, to) = get_period_dates(period, Some(&conn))?;
+
+    let held = crate::reports::calculate_portfolio(&conn, None)?;
+    let mut assets = Vec::with_capacity(tickers.len());
+    for ticker in tickers {
+        let ticker = ticker.to_uppercase();
+        if assets.iter().any(|a: &db::Asset| a.ticker == ticker) {
+            anyhow::bail!("{} was given twice", ticker);
+        }
+        let Some(position) = held.positions.iter().find(|p| p.asset.ticker == ticker) else {
+            anyhow::bail!(
+                "{} is not in the portfolio. Compare takes 2 to 4 assets you hold (see 'portfolio show')",
+                ticker
+            );
+        };
+        assets.push(position.asset.clone());
+    }
+
+    let comparisons = compare_assets(&conn, &assets, from, to)?;
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "from": from,
+                "to": to,
+                "assets": comparisons,
+            }))?
+        );
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Comparison {} to {}\n",
+        "⚖".cyan().bold(),
+        from.format("%d/%m/%Y"),
+        to.format("%d/%m/%Y")
+    );
+    println!("{}", summary_table(&comparisons));
+
+    if comparisons.iter().any(|c| !c.normalized.is_empty()) {
+        println!("\n{} Price rebased to 100\n", "📈".cyan().bold());
+        println!("{}", normalized_table(&comparisons));
+    }
+
+    let missing: Vec<&str> = comparisons
+        .iter()
+        .filter(|c| c.price_return_pct.is_none())
+        .map(|c| c.ticker.as_str())
+        .collect();
+    if !missing.is_empty() {
+        println!(
+            "\n{} No stored prices covering the period for {}. Run 'prices update' or import COTAHIST.",
+            "⚠".yellow().bold(),
+            missing.join(", ")
+        );
+    }
+    println!();
+    Ok(())
+}
+
+/// Formats one metric of an asset
+type Cell = fn(&AssetComparison) -> String;
+
+fn pct_cell(pct: Option<Decimal>) -> String {
+    pct.map(|p| format!("{:+.2}%", p)).unwrap_or("-".into())
+}
+
+fn summary_table(comparisons: &[AssetComparison]) -> String {
+    let mut builder = Builder::default();
+    builder.push_record(
+        std::iter::once(String::new()).chain(comparisons.iter().map(|c| c.ticker.clone())),
+    );
+
+    let rows: [(&str, Cell); 6] = [
+        ("Start price", |c| {
+            c.start_price.map(format_currency).unwrap_or("-".into())
+        }),
+        ("End price", |c| {
+            c.end_price.map(format_currency).unwrap_or("-".into())
+        }),
+        ("Price return", |c| pct_cell(c.price_return_pct)),
+        ("Income per quota", |c| format_currency(c.income_per_quota)),
+        ("Yield", |c| pct_cell(c.yield_pct)),
+        ("Max drawdown", |c| pct_cell(c.max_drawdown_pct)),
+    ];
+    for (label, cell) in rows {
+        builder.push_record(std::iter::once(label.to_string()).chain(comparisons.iter().map(cell)));
+    }
+
+    builder
+        .build()
+        .with(Style::rounded())
+        .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+        .to_string()
+}
+
+fn normalized_table(comparisons: &[AssetComparison]) -> String {
+    let months: BTreeSet<(i32, u32)> = comparisons
+        .iter()
+        .flat_map(|c| c.normalized.iter().map(|(date, _)| month_of(*date)))
+        .collect();
+
+    let mut builder = Builder::default();
+    builder.push_record(
+        std::iter::once("Month".to_string()).chain(comparisons.iter().map(|c| c.ticker.clone())),
+    );
+    for (year, month) in months {
+        builder.push_record(std::iter::once(format!("{:02}/{}", month, year)).chain(
+            comparisons.iter().map(|c| {
+                c.normalized
+                    .iter()
+                    .find(|(date, _)| month_of(*date) == (year, month))
+                    .map(|(_, value)| format!("{:.2}", value))
+                    .unwrap_or("-".into())
+            }),
+        ));
+    }
+
+    builder
+        .build()
+        .with(Style::rounded())
+        .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+        .to_string()
+}
+
+fn month_of(date: NaiveDate) -> (i32, u32) {
+    use chrono::Datelike;
+    (date.year(), date.month())
+}
@@ -0,0 +1,73 @@
+//! Daemon command: serve interactive sessions, or show what it is running
+
+use anyhow::Result;
+use colored::Colorize;
+
+use crate::daemon;
+use crate::db;
+
+pub async fn dispatch_daemon(status: bool, json_output: bool) -> Result<()> {
+    if status {
+        return print_status(json_output);
+    }
+
+    if db::sandbox::is_active() || db::encryption::is_encrypted()? {
+        return Err(anyhow::anyhow!(
+            "The daemon works on the main database; it cannot run in sandbox mode or with an encrypted database"
+        ));
+    }
+    db::init_database(None)?;
+
+    let path = daemon::socket_path()?;
+    if !json_output {
+        println!(
+            "{} Daemon listening on {} (Ctrl+C to stop)",
+            "⚙".cyan().bold(),
+            path.display()
+        );
+    }
+    daemon::serve().await?;
+    if !json_output {
+        println!("Daemon stopped");
+    }
+    Ok(())
+}
+
+fn print_status(json_output: bool) -> Result<()> {
+    let tasks = daemon::status();
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "running": tasks.is_some(),
+                "tasks": tasks.unwrap_or_default(),
+            }))?
+        );
+        return Ok(());
+    }
+
+    let Some(tasks) = tasks else {
+        println!("{} The daemon is not running", "ℹ".blue().bold());
+        return Ok(());
+    };
+    if tasks.is_empty() {
+        println!("{} The daemon is running and idle", "✓".green().bold());
+        return Ok(());
+    }
+    println!("{} The daemon is running:", "⚙".cyan().bold());
+    for task in tasks {
+        match task.started_at {
+            Some(started) => println!(
+                "  {} {} (since {}){}",
+                "▶".green(),
+                task.task,
+                started.with_timezone(&chrono::Local).format("%H:%M:%S"),
+                task.last_line
+                    .map(|l| format!(" - {}", l).dimmed().to_string())
+                    .unwrap_or_default()
+            ),
+            None => println!("  {} {} (queued)", "…".dimmed(), task.task),
+        }
+    }
+    Ok(())
+}
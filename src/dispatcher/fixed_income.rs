@@ -0,0 +1,279 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::cli::FixedIncomeCommands;
+use crate::db;
+use crate::fixed_income::{self, FixedIncomeKind, Indexer};
+use crate::utils::format_currency;
+
+pub fn dispatch_fixed_income(action: &FixedIncomeCommands, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    match action {
+        FixedIncomeCommands::Show => show_positions(&conn, json_output),
+        FixedIncomeCommands::Set {
+            ticker,
+            kind,
+            issue,
+            maturity,
+            indexer,
+            rate,
+            issue_price,
+        } => {
+            let date = |s: &str| {
+                NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .with_context(|| format!("Invalid date '{}'. Use YYYY-MM-DD format", s))
+            };
+            let rate = Decimal::from_str(rate).context("Invalid rate. Must be a decimal number")?;
+            let issue_price = issue_price
+                .as_deref()
+                .map(Decimal::from_str)
+                .transpose()
+                .context("Invalid issue price. Must be a decimal number")?;
+            let terms = fixed_income::set_terms(
+                &conn,
+                ticker,
+                FixedIncomeKind::from_str(kind)?,
+                date(issue)?,
+                date(maturity)?,
+                Indexer::from_str(indexer)?,
+                rate,
+                issue_price,
+            )?;
+            let today = chrono::Local::now().date_naive();
+            let accrual = fixed_income::store_accrual(&conn, &terms, today);
+
+            if json_output {
+                let accrual = accrual.as_ref().ok();
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&serde_json::json!({
+                        "success": true,
+                        "terms": terms,
+                        "latest": accrual.and_then(|a| a.latest()),
+                        "projected_from": accrual.and_then(|a| a.projected_from),
+                    }))?
+                );
+                return Ok(());
+            }
+
+            println!(
+                "{} {} {} {}, {} to {}",
+                "✓".green().bold(),
+                terms.ticker,
+                terms.kind.as_str(),
+                terms.indexer.describe(terms.rate),
+                terms.issue_date.format("%d/%m/%Y"),
+                terms.maturity_date.format("%d/%m/%Y")
+            );
+            match accrual {
+                Ok(accrual) => {
+                    if let Some((date, pu)) = accrual.latest() {
+                        println!(
+                            "  PU on {}: {}",
+                            date.format("%d/%m/%Y"),
+                            format_currency(pu)
+                        );
+                    }
+                }
+                Err(e) => println!("  {} {}", "⚠".yellow(), e),
+            }
+            Ok(())
+        }
+    }
+}
+
+fn show_positions(conn: &rusqlite::Connection, json_output: bool) -> Result<()> {
+    let today = chrono::Local::now().date_naive();
+    let accruals = fixed_income::accrue_all(conn, today)?;
+    let positions = fixed_income::open_positions(conn, today)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&positions)?);
+        return Ok(());
+    }
+
+    if positions.is_empty() {
+        println!(
+            "{} No fixed income bonds with recorded terms held",
+            "ℹ".blue().bold()
+        );
+        println!("Record the terms with: interest fixed-income set <TICKER> --kind CDB --issue <date> --maturity <date> --indexer CDI --rate 110");
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct BondRow {
+        #[tabled(rename = "Bond")]
+        ticker: String,
+        #[tabled(rename = "Kind")]
+        kind: String,
+        #[tabled(rename = "Rate")]
+        rate: String,
+        #[tabled(rename = "Maturity")]
+        maturity: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Cost")]
+        cost: String,
+        #[tabled(rename = "PU")]
+        pu: String,
+        #[tabled(rename = "Value")]
+        value: String,
+        #[tabled(rename = "IOF + IR")]
+        tax: String,
+        #[tabled(rename = "Net")]
+        net: String,
+    }
+
+    let rows: Vec<BondRow> = positions
+        .iter()
+        .map(|p| BondRow {
+            ticker: p.terms.ticker.clone(),
+            kind: p.terms.kind.as_str().to_string(),
+            rate: p.terms.indexer.describe(p.terms.rate),
+            maturity: p.terms.maturity_date.format("%d/%m/%Y").to_string(),
+            quantity: p.quantity.normalize().to_string(),
+            cost: format_currency(p.cost),
+            pu: p
+                .pu
+                .map(|(date, price)| {
+                    format!("{} ({})", format_currency(price), date.format("%d/%m"))
+                })
+                .unwrap_or("-".into()),
+            value: p.market_value.map(format_currency).unwrap_or("-".into()),
+            tax: if p.terms.kind.is_tax_exempt() {
+                "exempt".to_string()
+            } else {
+                format_currency(p.iof + p.ir)
+            },
+            net: p.net_value.map(format_currency).unwrap_or("-".into()),
+        })
+        .collect();
+
+    println!("\n{} Fixed income\n", "🏦".cyan().bold());
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(4..)).with(Alignment::right()))
+    );
+    if positions.iter().any(|p| p.net_value.is_some()) {
+        let net: Decimal = positions.iter().filter_map(|p| p.net_value).sum();
+        println!("\nNet if redeemed today: {}", format_currency(net).bold());
+    }
+
+    let projected: Vec<String> = accruals
+        .iter()
+        .filter_map(|a| {
+            a.projected_from
+                .map(|date| format!("{} since {}", a.ticker, date.format("%d/%m/%Y")))
+        })
+        .collect();
+    if !projected.is_empty() {
+        println!(
+            "{}",
+            format!(
+                "Projected with the last published CDI/IPCA: {}. Run 'interest prices update-benchmarks' to catch up.",
+                projected.join(", ")
+            )
+            .dimmed()
+        );
+    }
+    if positions.iter().any(|p| p.pu.is_none()) {
+        println!("\nSome bonds could not be accrued. Run 'interest prices update-benchmarks' to fetch the CDI and IPCA.");
+    }
+    Ok(())
+}
+
+pub fn dispatch_tax_fixed_income(year: i32, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let report = crate::tax::fixed_income::fixed_income_year(&conn, year)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    if report.redemptions.is_empty() {
+        println!(
+            "{} No fixed income redemptions in {}",
+            "ℹ".blue().bold(),
+            year
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct RedemptionRow {
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Bond")]
+        ticker: String,
+        #[tabled(rename = "Proceeds")]
+        proceeds: String,
+        #[tabled(rename = "Yield")]
+        gain: String,
+        #[tabled(rename = "IOF")]
+        iof: String,
+        #[tabled(rename = "IR")]
+        ir: String,
+        #[tabled(rename = "Net")]
+        net: String,
+    }
+
+    let rows: Vec<RedemptionRow> = report
+        .redemptions
+        .iter()
+        .map(|r| RedemptionRow {
+            date: r.date.format("%d/%m/%Y").to_string(),
+            ticker: r.ticker.clone(),
+            proceeds: format_currency(r.proceeds),
+            gain: format_currency(r.gain),
+            iof: format_currency(r.iof),
+            ir: if r.exempt {
+                "exempt".to_string()
+            } else {
+                format_currency(r.ir)
+            },
+            net: format_currency(r.net),
+        })
+        .collect();
+
+    println!(
+        "\n{} Fixed income redemptions - {}\n",
+        "🏦".cyan().bold(),
+        year
+    );
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(2..)).with(Alignment::right()))
+    );
+    println!("\nIOF: {}", format_currency(report.iof));
+    println!("IR withheld: {}", format_currency(report.ir).bold());
+    println!(
+        "Rendimentos sujeitos à tributação exclusiva (06): {}",
+        format_currency(report.exclusive_net).bold()
+    );
+    println!(
+        "Rendimentos isentos e não tributáveis (12): {}",
+        format_currency(report.exempt_yield).bold()
+    );
+    println!(
+        "{}",
+        "Per-lot detail of Tesouro Direto redemptions: interest tesouro redemptions <year>"
+            .dimmed()
+    );
+    Ok(())
+}
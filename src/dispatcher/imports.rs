@@ -6,6 +6,7 @@ pub async fn dispatch_import(
     file: &str,
     dry_run: bool,
     force_reimport: bool,
+    mapping: Option<&str>,
     json_output: bool,
 ) -> Result<()> {
     use crate::importers::{self, ImportResult};
@@ -13,59 +14,45 @@ pub async fn dispatch_import(
     let path = file;
     tracing::info!("Importing from: {}", path);
 
-    let import_result = match importers::import_file_auto(path) {
+    let parsed = match mapping {
+        Some(mapping_path) => importers::custom::Mapping::load(mapping_path).and_then(|m| {
+            Ok(ImportResult::Custom {
+                transactions: importers::custom::parse_custom(path, &m)?,
+                source: m.source,
+            })
+        }),
+        None => importers::import_file_auto(path),
+    };
+    let import_result = match parsed {
         Ok(r) => r,
         Err(e) => {
             return Err(anyhow::anyhow!("Error reading import file {}: {}", path, e));
         }
     };
 
-    match import_result {
-        ImportResult::Cei(raw_transactions) => {
-            if !json_output {
-                println!(
-                    "\n{} Found {} transactions\n",
-                    "✓".green().bold(),
-                    raw_transactions.len()
-                );
-            }
-
-            if !json_output {
-                if let Some(table) =
-                    crate::dispatcher::imports_helpers::preview_cei_table(&raw_transactions)
-                {
-                    println!("{}", table);
-                }
-            }
-
-            if dry_run {
-                if !json_output {
-                    println!("\n{} Dry run - no changes saved", "ℹ".blue().bold());
-                }
-                return Ok(());
-            }
-
-            db::init_database(None)?;
-            let conn = db::open_db(None)?;
-
-            let stats = crate::dispatcher::imports_helpers::import_cei(&conn, &raw_transactions)?;
+    // Rows added from here on are tagged with the session, for `import undo`
+    let session = if dry_run {
+        None
+    } else {
+        db::init_database(None)?;
+        let conn = db::open_db(None)?;
+        let session = db::import_session::begin(&conn, path, &import_result.format_name())?;
+        Some((conn, session))
+    };
 
-            if !json_output {
-                println!("\n{} Import complete!", "✓".green().bold());
-                println!("  Imported: {}", stats.imported.to_string().green());
-                if stats.skipped_old > 0 {
-                    println!(
-                        "  Skipped (before last import date): {}",
-                        stats.skipped_old.to_string().yellow()
-                    );
-                }
-                if stats.errors > 0 {
-                    println!("  Errors: {}", stats.errors.to_string().red());
-                }
-            }
+    let result = match import_result {
+        ImportResult::Cei(raw_transactions) => dispatch_trades(
+            &raw_transactions,
+            "CEI",
+            dry_run,
+            force_reimport,
+            json_output,
+        ),
 
-            Ok(())
-        }
+        ImportResult::Custom {
+            source,
+            transactions,
+        } => dispatch_trades(&transactions, &source, dry_run, force_reimport, json_output),
 
         ImportResult::Movimentacao(entries) => {
             if !json_output {
@@ -227,20 +214,9 @@ pub async fn dispatch_import(
             // Always track state - when force_reimport deleted metadata, get_last_import_date returns None
             // This allows importing old dates, then properly updates cutoff dates for future imports
             let stats = importers::import_movimentacao_entries(&conn, entries, true)?;
-            if let Some(date) = stats.earliest {
-                reports::invalidate_snapshots_after(&conn, date)?;
-            }
 
             if json_output {
-                // Use the unified ImportStats returned by the importer
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&serde_json::json!({
-                        "success": true,
-                        "data": stats
-                    }))?
-                );
-                return Ok(());
+                return print_batch_json(&stats);
             }
 
             if !json_output {
@@ -250,6 +226,12 @@ pub async fn dispatch_import(
                     "    Imported: {}",
                     stats.imported_trades.to_string().green()
                 );
+                if stats.day_trades > 0 {
+                    println!(
+                        "    Day trades (same-day buy and sell): {}",
+                        stats.day_trades.to_string().cyan()
+                    );
+                }
                 if stats.skipped_trades_old > 0 {
                     println!(
                         "    Skipped (before last import date): {}",
@@ -340,7 +322,120 @@ pub async fn dispatch_import(
 
             let stats = crate::dispatcher::imports_helpers::import_ofertas(&conn, &entries)?;
 
+            if json_output {
+                print_batch_json(&stats)?;
+            } else {
+                println!("\n{} Import complete!", "✓".green().bold());
+                println!("  Imported: {}", stats.imported.to_string().green());
+                if stats.skipped_old > 0 {
+                    println!(
+                        "  Skipped (before last import date): {}",
+                        stats.skipped_old.to_string().yellow()
+                    );
+                }
+                if stats.errors > 0 {
+                    println!("  Errors: {}", stats.errors.to_string().red());
+                }
+            }
+
+            Ok(())
+        }
+
+        ImportResult::NotaCorretagem(notes) => {
+            let trades: usize = notes.iter().map(|n| n.trades.len()).sum();
+            if !json_output {
+                println!(
+                    "\n{} Found {} brokerage note(s) with {} trades\n",
+                    "✓".green().bold(),
+                    notes.len(),
+                    trades
+                );
+                if let Some(table) = crate::dispatcher::imports_helpers::preview_notas_table(&notes)
+                {
+                    println!("{}", table);
+                }
+                let fees: rust_decimal::Decimal = notes.iter().map(|n| n.total_fees()).sum();
+                let irrf: rust_decimal::Decimal =
+                    notes.iter().map(|n| n.irrf + n.irrf_day_trade).sum();
+                println!(
+                    "  Fees: {}   IRRF withheld: {}",
+                    crate::utils::format_currency(fees),
+                    crate::utils::format_currency(irrf)
+                );
+            }
+
+            if dry_run {
+                if json_output {
+                    println!("{}", serde_json::to_string_pretty(&notes)?);
+                } else {
+                    println!("\n{} Dry run - no changes saved", "ℹ".blue().bold());
+                }
+                return Ok(());
+            }
+
+            db::init_database(None)?;
+            let conn = db::open_db(None)?;
+            let stats = crate::dispatcher::imports_helpers::import_notas(&conn, &notes)?;
+
+            if json_output {
+                print_batch_json(&stats)?;
+            } else {
+                println!("\n{} Import complete!", "✓".green().bold());
+                println!(
+                    "  Imported trades: {}",
+                    stats.imported_trades.to_string().green()
+                );
+                if stats.enriched_trades > 0 {
+                    println!(
+                        "  Fees added to trades already imported: {}",
+                        stats.enriched_trades.to_string().cyan()
+                    );
+                }
+                if stats.skipped_trades > 0 {
+                    println!(
+                        "  Skipped (note already imported): {}",
+                        stats.skipped_trades.to_string().yellow()
+                    );
+                }
+                if stats.errors > 0 {
+                    println!("  Errors: {}", stats.errors.to_string().red());
+                }
+            }
+
+            Ok(())
+        }
+
+        ImportResult::TesouroExtrato(entries) => {
             if !json_output {
+                println!(
+                    "\n{} Found {} Tesouro Direto operations\n",
+                    "✓".green().bold(),
+                    entries.len()
+                );
+                if let Some(table) =
+                    crate::dispatcher::imports_helpers::preview_tesouro_table(&entries)
+                {
+                    println!("{}", table);
+                }
+            }
+
+            if dry_run {
+                if json_output {
+                    println!("{}", serde_json::to_string_pretty(&entries)?);
+                } else {
+                    println!("\n{} Dry run - no changes saved", "ℹ".blue().bold());
+                }
+                return Ok(());
+            }
+
+            db::init_database(None)?;
+            let conn = db::open_db(None)?;
+            let stats =
+                crate::dispatcher::imports_helpers::import_tesouro_extrato(&conn, &entries)?;
+
+            if json_output {
+                print_batch_json(&stats)?;
+            } else {
                 println!("\n{} Import complete!", "✓".green().bold());
                 println!("  Imported: {}", stats.imported.to_string().green());
                 if stats.skipped_old > 0 {
@@ -352,8 +447,570 @@ pub async fn dispatch_import(
                 if stats.errors > 0 {
                     println!("  Errors: {}", stats.errors.to_string().red());
                 }
+                println!(
+                    "  Run 'interest tesouro show' for marked values and 'interest tesouro redemptions <year>' for IR"
+                );
+            }
+
+            Ok(())
+        }
+        ImportResult::Ofx(statement) => {
+            if !json_output {
+                println!(
+                    "\n{} Found {} trades and {} income payments in the OFX statement{}\n",
+                    "✓".green().bold(),
+                    statement.trades.len(),
+                    statement.income.len(),
+                    statement
+                        .institution
+                        .as_deref()
+                        .map(|i| format!(" from {}", i))
+                        .unwrap_or_default()
+                );
+                if let Some(table) =
+                    crate::dispatcher::imports_helpers::preview_cei_table(&statement.trades)
+                {
+                    println!("{}", table);
+                }
+                if let Some(table) =
+                    crate::dispatcher::imports_helpers::preview_proventos_table(&statement.income)
+                {
+                    println!("{}", table);
+                }
+            }
+
+            if dry_run {
+                if json_output {
+                    println!("{}", serde_json::to_string_pretty(&statement.income)?);
+                } else {
+                    println!("\n{} Dry run - no changes saved", "ℹ".blue().bold());
+                }
+                return Ok(());
+            }
+
+            db::init_database(None)?;
+            let conn = db::open_db(None)?;
+            if force_reimport {
+                delete_for_reimport(
+                    &conn,
+                    &statement.trades,
+                    crate::importers::ofx::SOURCE,
+                    json_output,
+                )?;
+            }
+            let stats = crate::dispatcher::imports_helpers::import_ofx(&conn, &statement)?;
+
+            if json_output {
+                print_batch_json(&stats)?;
+            } else {
+                println!("\n{} Import complete!", "✓".green().bold());
+                println!("  Imported trades: {}", stats.imported.to_string().green());
+                println!(
+                    "  Imported income events: {}",
+                    stats.imported_income.to_string().green()
+                );
+                if stats.skipped_old > 0 {
+                    println!(
+                        "  Skipped trades (before last import date): {}",
+                        stats.skipped_old.to_string().yellow()
+                    );
+                }
+                if stats.skipped_income > 0 {
+                    println!(
+                        "  Skipped income (already recorded): {}",
+                        stats.skipped_income.to_string().yellow()
+                    );
+                }
+                if stats.errors > 0 {
+                    println!("  Errors: {}", stats.errors.to_string().red());
+                }
+            }
+
+            Ok(())
+        }
+        ImportResult::Proventos(entries) => {
+            if !json_output {
+                println!(
+                    "\n{} Found {} income payments\n",
+                    "✓".green().bold(),
+                    entries.len()
+                );
+                if let Some(table) =
+                    crate::dispatcher::imports_helpers::preview_proventos_table(&entries)
+                {
+                    println!("{}", table);
+                }
+                let withheld: rust_decimal::Decimal = entries.iter().map(|e| e.withholding).sum();
+                println!(
+                    "  IRRF withheld: {}",
+                    crate::utils::format_currency(withheld)
+                );
+            }
+
+            if dry_run {
+                if json_output {
+                    println!("{}", serde_json::to_string_pretty(&entries)?);
+                } else {
+                    println!("\n{} Dry run - no changes saved", "ℹ".blue().bold());
+                }
+                return Ok(());
+            }
+
+            db::init_database(None)?;
+            let conn = db::open_db(None)?;
+            let stats = crate::dispatcher::imports_helpers::import_proventos(&conn, &entries)?;
+
+            if json_output {
+                print_batch_json(&stats)?;
+            } else {
+                println!("\n{} Import complete!", "✓".green().bold());
+                println!(
+                    "  Imported income events: {}",
+                    stats.imported_income.to_string().green()
+                );
+                if stats.enriched_income > 0 {
+                    println!(
+                        "  Gross value and IRRF added to events already imported: {}",
+                        stats.enriched_income.to_string().cyan()
+                    );
+                }
+                if stats.skipped_income > 0 {
+                    println!(
+                        "  Skipped (already imported): {}",
+                        stats.skipped_income.to_string().yellow()
+                    );
+                }
+                if stats.errors > 0 {
+                    println!("  Errors: {}", stats.errors.to_string().red());
+                }
+            }
+
+            Ok(())
+        }
+        ImportResult::UsBroker(statement) => {
+            if !json_output {
+                println!(
+                    "\n{} Found {} trades and {} dividends in the {} statement\n",
+                    "✓".green().bold(),
+                    statement.trades.len(),
+                    statement.dividends.len(),
+                    statement.broker.as_deref().unwrap_or("US broker")
+                );
+                if let Some(table) =
+                    crate::dispatcher::imports_helpers::preview_us_statement_table(&statement)
+                {
+                    println!("{}", table);
+                }
+            }
+
+            if dry_run {
+                if json_output {
+                    println!("{}", serde_json::to_string_pretty(&statement)?);
+                } else {
+                    println!("\n{} Dry run - no changes saved", "ℹ".blue().bold());
+                }
+                return Ok(());
+            }
+
+            db::init_database(None)?;
+            let conn = db::open_db(None)?;
+            if let Some((from, to)) = statement.rate_dates() {
+                // Rates already stored may cover the statement
+                if let Err(e) = crate::pricing::fx::ensure_rates(
+                    &conn,
+                    crate::importers::avenue_csv::CURRENCY,
+                    from,
+                    to,
+                )
+                .await
+                {
+                    tracing::warn!("Failed to update USD/BRL rates: {}", e);
+                }
+            }
+            if force_reimport {
+                let (trades, _) = statement.to_brl(&conn)?;
+                delete_for_reimport(
+                    &conn,
+                    &trades,
+                    crate::importers::avenue_csv::SOURCE,
+                    json_output,
+                )?;
+            }
+            let stats = crate::dispatcher::imports_helpers::import_us_statement(&conn, &statement)?;
+
+            if json_output {
+                print_batch_json(&stats)?;
+            } else {
+                println!("\n{} Import complete!", "✓".green().bold());
+                println!("  Imported trades: {}", stats.imported.to_string().green());
+                println!(
+                    "  Imported dividends: {}",
+                    stats.imported_income.to_string().green()
+                );
+                if stats.skipped_old > 0 {
+                    println!(
+                        "  Skipped trades (before last import date): {}",
+                        stats.skipped_old.to_string().yellow()
+                    );
+                }
+                if stats.skipped_income > 0 {
+                    println!(
+                        "  Skipped dividends (already recorded): {}",
+                        stats.skipped_income.to_string().yellow()
+                    );
+                }
+                if stats.errors > 0 {
+                    println!("  Errors: {}", stats.errors.to_string().red());
+                }
+                println!(
+                    "  Converted at the PTAX of settlement; taxed as assets abroad: {}",
+                    "interest tax report".cyan()
+                );
+            }
+
+            Ok(())
+        }
+        ImportResult::Crypto(statement) => {
+            if !json_output {
+                println!(
+                    "\n{} Found {} trades of {} coins in the {} export\n",
+                    "✓".green().bold(),
+                    statement.trades.len(),
+                    statement.coins().len(),
+                    statement.exchange
+                );
+                if let Some(table) =
+                    crate::dispatcher::imports_helpers::preview_crypto_table(&statement)
+                {
+                    println!("{}", table);
+                }
+            }
+
+            if dry_run {
+                if json_output {
+                    println!("{}", serde_json::to_string_pretty(&statement)?);
+                } else {
+                    println!("\n{} Dry run - no changes saved", "ℹ".blue().bold());
+                }
+                return Ok(());
+            }
+
+            db::init_database(None)?;
+            let conn = db::open_db(None)?;
+            if let Some((from, to)) = statement.rate_dates() {
+                if let Err(e) = crate::pricing::fx::ensure_usd_brl(&conn, from, to).await {
+                    tracing::warn!("Failed to update USD/BRL rates: {}", e);
+                }
+            }
+            if force_reimport {
+                let trades = statement.to_brl(&conn)?;
+                delete_for_reimport(
+                    &conn,
+                    &trades,
+                    crate::importers::crypto_csv::SOURCE,
+                    json_output,
+                )?;
+            }
+            let stats =
+                crate::dispatcher::imports_helpers::import_crypto_statement(&conn, &statement)?;
+
+            if json_output {
+                print_batch_json(&stats)?;
+            } else {
+                println!("\n{} Import complete!", "✓".green().bold());
+                println!("  Imported trades: {}", stats.imported.to_string().green());
+                if stats.skipped_old > 0 {
+                    println!(
+                        "  Skipped trades (before last import date): {}",
+                        stats.skipped_old.to_string().yellow()
+                    );
+                }
+                if stats.errors > 0 {
+                    println!("  Errors: {}", stats.errors.to_string().red());
+                }
+                println!(
+                    "  Taxed as crypto-assets (R$ 35.000 monthly exemption): {}",
+                    "interest tax report".cyan()
+                );
+            }
+
+            Ok(())
+        }
+        ImportResult::Posicao(statement) => {
+            let quantities = statement.quantities();
+            if !json_output {
+                println!(
+                    "\n{} Found {} assets held on {}\n",
+                    "✓".green().bold(),
+                    quantities.len(),
+                    statement.date.format("%d/%m/%Y")
+                );
+                for (ticker, quantity) in &quantities {
+                    println!("  {:<10} {}", ticker, quantity);
+                }
+            }
+
+            if dry_run {
+                if json_output {
+                    println!("{}", serde_json::to_string_pretty(&statement)?);
+                } else {
+                    println!("\n{} Dry run - no changes saved", "ℹ".blue().bold());
+                }
+                return Ok(());
+            }
+
+            db::init_database(None)?;
+            let conn = db::open_db(None)?;
+            let stats = crate::dispatcher::imports_helpers::import_posicao(&conn, &statement)?;
+
+            if json_output {
+                print_batch_json(&stats)?;
+            } else {
+                println!("\n{} Import complete!", "✓".green().bold());
+                println!(
+                    "  Recorded positions: {}",
+                    stats.imported.to_string().green()
+                );
+                println!(
+                    "  Compare them with the computed history: {}",
+                    "interest inconsistencies positions".cyan()
+                );
             }
 
+            Ok(())
+        }
+    };
+
+    if let Some((conn, session)) = session {
+        if let Some(recorded) = session.finish(&conn)? {
+            if !json_output {
+                println!(
+                    "\n{} Recorded as import session {} (revert with: interest import undo {})",
+                    "ℹ".blue().bold(),
+                    recorded.id,
+                    recorded.id
+                );
+            }
+            let invalid = db::lot_size::flagged_in_session(&conn, recorded.id)?;
+            if invalid > 0 && !json_output {
+                println!(
+                    "{} {} trade(s) with a fractional quantity the instrument cannot trade in (a misread decimal separator?); fix them with: interest inconsistencies resolve",
+                    "⚠".yellow().bold(),
+                    invalid
+                );
+            }
+        }
+        let (duplicates, _) = crate::importers::dedupe::sync_duplicates(&conn)?;
+        if duplicates > 0 && !json_output {
+            println!(
+                "{} {} trade(s) also imported from another source; merge them with: interest inconsistencies resolve",
+                "⚠".yellow().bold(),
+                duplicates
+            );
+        }
+    }
+    result
+}
+
+/// Counters plus one result per imported, skipped or failed item
+/// Preview and import plain buy/sell trades (CEI or a custom mapping)
+fn dispatch_trades(
+    raw_transactions: &[crate::importers::RawTransaction],
+    source: &str,
+    dry_run: bool,
+    force_reimport: bool,
+    json_output: bool,
+) -> Result<()> {
+    if !json_output {
+        println!(
+            "\n{} Found {} transactions\n",
+            "✓".green().bold(),
+            raw_transactions.len()
+        );
+    }
+
+    if !json_output {
+        if let Some(table) = crate::dispatcher::imports_helpers::preview_cei_table(raw_transactions)
+        {
+            println!("{}", table);
+        }
+    }
+
+    if dry_run {
+        if !json_output {
+            println!("\n{} Dry run - no changes saved", "ℹ".blue().bold());
+        }
+        return Ok(());
+    }
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    if force_reimport {
+        delete_for_reimport(&conn, raw_transactions, source, json_output)?;
+    }
+
+    let stats = crate::dispatcher::imports_helpers::import_trades(&conn, raw_transactions, source)?;
+
+    if json_output {
+        print_batch_json(&stats)?;
+    } else {
+        println!("\n{} Import complete!", "✓".green().bold());
+        println!("  Imported: {}", stats.imported.to_string().green());
+        if stats.day_trades > 0 {
+            println!(
+                "  Day trades (same-day buy and sell): {}",
+                stats.day_trades.to_string().cyan()
+            );
+        }
+        if stats.option_exercises > 0 {
+            println!(
+                "  Option exercises (premium added to the underlying): {}",
+                stats.option_exercises.to_string().cyan()
+            );
+        }
+        if stats.skipped_old > 0 {
+            println!(
+                "  Skipped (before last import date): {}",
+                stats.skipped_old.to_string().yellow()
+            );
+        }
+        if stats.errors > 0 {
+            println!("  Errors: {}", stats.errors.to_string().red());
+        }
+    }
+
+    Ok(())
+}
+
+/// `--force-reimport`: delete the trades `source` recorded from the file's
+/// first trade date on, so they are imported again
+fn delete_for_reimport(
+    conn: &rusqlite::Connection,
+    raw_transactions: &[crate::importers::RawTransaction],
+    source: &str,
+    json_output: bool,
+) -> Result<()> {
+    let Some(from_date) = raw_transactions.iter().map(|tx| tx.trade_date).min() else {
+        return Ok(());
+    };
+    if !json_output {
+        println!(
+            "\n{} Force reimport: deleting {} data from {} onwards...",
+            "⚠".yellow().bold(),
+            source,
+            from_date.format("%Y-%m-%d").to_string().yellow()
+        );
+    }
+    let deleted = db::delete_transactions_from_source_after_date(conn, source, from_date)?;
+    conn.execute(
+        "DELETE FROM import_state WHERE source = ?1",
+        rusqlite::params![source],
+    )?;
+    if !json_output {
+        println!(
+            "  {} Deleted: {} transactions",
+            "✓".green(),
+            deleted.to_string().red()
+        );
+    }
+    Ok(())
+}
+
+fn print_batch_json(stats: &crate::importers::ImportStats) -> Result<()> {
+    let payload = crate::dispatcher::imports_helpers::batch_envelope(stats, &stats.items);
+    println!("{}", serde_json::to_string_pretty(&payload)?);
+    Ok(())
+}
+
+/// `import list` and `import undo`
+pub fn dispatch_import_sessions(
+    action: &crate::cli::ImportCommands,
+    json_output: bool,
+) -> Result<()> {
+    use crate::db::import_session;
+    use tabled::{
+        settings::{object::Columns, Alignment, Modify, Style},
+        Table, Tabled,
+    };
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    match action {
+        crate::cli::ImportCommands::List => {
+            let sessions = import_session::list_sessions(&conn)?;
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&sessions)?);
+                return Ok(());
+            }
+            if sessions.is_empty() {
+                println!("{} No imports recorded", "ℹ".blue().bold());
+                return Ok(());
+            }
+
+            #[derive(Tabled)]
+            struct Row {
+                #[tabled(rename = "Session")]
+                id: i64,
+                #[tabled(rename = "Imported at")]
+                started_at: String,
+                #[tabled(rename = "Format")]
+                format: String,
+                #[tabled(rename = "File")]
+                file: String,
+                #[tabled(rename = "Trades")]
+                transactions: i64,
+                #[tabled(rename = "Actions")]
+                corporate_actions: i64,
+                #[tabled(rename = "Income")]
+                income_events: i64,
+                #[tabled(rename = "Status")]
+                status: String,
+            }
+
+            let rows: Vec<Row> = sessions
+                .into_iter()
+                .map(|s| Row {
+                    id: s.id,
+                    started_at: s.started_at,
+                    format: s.format,
+                    file: std::path::Path::new(&s.file)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or(s.file),
+                    transactions: s.transactions,
+                    corporate_actions: s.corporate_actions,
+                    income_events: s.income_events,
+                    status: match s.undone_at {
+                        Some(at) => format!("undone {}", at).dimmed().to_string(),
+                        None => "imported".green().to_string(),
+                    },
+                })
+                .collect();
+            println!(
+                "{}",
+                Table::new(rows)
+                    .with(Style::rounded())
+                    .with(Modify::new(Columns::new(4..7)).with(Alignment::right()))
+            );
+            Ok(())
+        }
+        crate::cli::ImportCommands::Undo { session } => {
+            let undone = import_session::undo(&conn, *session)?;
+            reports::income_reconciliation::sync_inconsistencies(&conn)?;
+            crate::importers::dedupe::sync_duplicates(&conn)?;
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&undone)?);
+            } else {
+                println!("{} Import session {} undone", "✓".green().bold(), session);
+                println!(
+                    "  Deleted: {} transactions, {} corporate actions, {} income events",
+                    undone.transactions.to_string().red(),
+                    undone.corporate_actions.to_string().red(),
+                    undone.income_events.to_string().red()
+                );
+            }
             Ok(())
         }
     }
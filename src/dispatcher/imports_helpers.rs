@@ -11,7 +11,7 @@ use tabled::{
 use crate::{db, importers, reports};
 
 // The helpers expose ImportStats from the `importers` module
-use crate::importers::ImportStats;
+use crate::importers::{ImportStats, ItemResult};
 /// Return a pretty table preview for CEI transactions (up to 10 rows)
 pub(crate) fn preview_cei_table(txs: &[crate::importers::RawTransaction]) -> Option<String> {
     #[derive(Tabled)]
@@ -59,20 +59,37 @@ pub(crate) fn import_cei(
     conn: &Connection,
     raw_transactions: &[crate::importers::RawTransaction],
 ) -> Result<ImportStats> {
-    let mut imported: i64 = 0;
+    import_trades(conn, raw_transactions, "CEI")
+}
+
+/// Import raw trades tagged with `source`, keeping a last import date per source
+pub(crate) fn import_trades(
+    conn: &Connection,
+    raw_transactions: &[crate::importers::RawTransaction],
+    source: &str,
+) -> Result<ImportStats> {
     let mut skipped_old: i64 = 0;
     let mut errors: i64 = 0;
+    let mut items = Vec::new();
     let mut max_imported_date: Option<NaiveDate> = None;
     let mut earliest_imported_date: Option<NaiveDate> = None;
 
-    let last_import_date = db::get_last_import_date(conn, "CEI", "trades")?;
+    let last_import_date = db::get_last_import_date(conn, source, "trades")?;
 
     let asset_exists_closure =
         |ticker: &str| -> anyhow::Result<bool> { crate::db::asset_exists(conn, ticker) };
+    let mut pending = Vec::new();
 
     for raw_tx in raw_transactions {
+        let item = ItemResult::new(
+            "trade",
+            Some(&raw_tx.ticker),
+            Some(raw_tx.trade_date),
+            Some(&raw_tx.transaction_type),
+        );
         if let Some(last_date) = last_import_date {
             if raw_tx.trade_date <= last_date {
+                items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
                 skipped_old += 1;
                 continue;
             }
@@ -83,10 +100,17 @@ pub(crate) fn import_cei(
         let asset_type = db::AssetType::Unknown;
 
         // Upsert asset
-        let asset_id = match db::upsert_asset(conn, &normalized_ticker, &asset_type, None) {
+        let asset_id = match db::upsert_asset_as_of(
+            conn,
+            &normalized_ticker,
+            &asset_type,
+            None,
+            Some(raw_tx.trade_date),
+        ) {
             Ok(id) => id,
             Err(e) => {
                 eprintln!("Error upserting asset: {}", e);
+                items.push(item.failed("ASSET_UPSERT_FAILED", e));
                 errors += 1;
                 continue;
             }
@@ -96,6 +120,7 @@ pub(crate) fn import_cei(
             Ok(tx) => tx,
             Err(e) => {
                 eprintln!("Error converting transaction for {}: {}", raw_tx.ticker, e);
+                items.push(item.failed("INVALID_ENTRY", e));
                 errors += 1;
                 continue;
             }
@@ -104,34 +129,55 @@ pub(crate) fn import_cei(
         if let Some(notes) = notes_override {
             transaction.notes = Some(notes);
         }
+        transaction.source = source.to_string();
 
-        match db::insert_transaction(conn, &transaction) {
-            Ok(_) => {
-                imported += 1;
-                max_imported_date = Some(match max_imported_date {
-                    Some(current) if current >= transaction.trade_date => current,
-                    _ => transaction.trade_date,
-                });
-                earliest_imported_date = Some(match earliest_imported_date {
-                    Some(current) if current <= transaction.trade_date => current,
-                    _ => transaction.trade_date,
-                });
-            }
-            Err(e) => {
-                eprintln!("Error inserting transaction: {}", e);
-                errors += 1;
-            }
-        }
+        pending.push(transaction);
+        items.push(item);
+    }
+
+    // Same-day buys and sells of an asset are day trades; the files do not flag them
+    let mut day_trades = importers::day_trade::DayTrades::detect(pending.iter().map(|tx| {
+        (
+            tx.asset_id,
+            tx.trade_date,
+            tx.transaction_type.clone(),
+            tx.quantity,
+        )
+    }));
+    let imported = pending.len() as i64;
+    let pending: Vec<db::Transaction> = pending
+        .into_iter()
+        .flat_map(|tx| {
+            let asset_id = tx.asset_id;
+            day_trades.split(&asset_id, tx)
+        })
+        .collect();
+    let day_trade_count = pending.iter().filter(|tx| tx.is_day_trade).count();
+
+    db::bulk::insert_transactions(conn, &pending, |p| {
+        tracing::debug!("Inserted {}/{} {} transactions", p.done, p.total, source);
+    })?;
+    for transaction in &pending {
+        max_imported_date = Some(match max_imported_date {
+            Some(current) if current >= transaction.trade_date => current,
+            _ => transaction.trade_date,
+        });
+        earliest_imported_date = Some(match earliest_imported_date {
+            Some(current) if current <= transaction.trade_date => current,
+            _ => transaction.trade_date,
+        });
     }
 
     if let Some(last_date) = max_imported_date {
-        db::set_last_import_date(conn, "CEI", "trades", last_date)?;
+        db::set_last_import_date(conn, source, "trades", last_date)?;
     }
 
-    if imported > 0 {
-        if let Some(date) = earliest_imported_date {
-            reports::invalidate_snapshots_after(conn, date)?;
-        }
+    let exercises = crate::options::process_exercises(conn)?;
+    if exercises.written_skipped > 0 {
+        tracing::warn!(
+            "Skipped {} exercise(s) of written options: short positions are not supported",
+            exercises.written_skipped
+        );
     }
 
     Ok(ImportStats {
@@ -151,6 +197,11 @@ pub(crate) fn import_cei(
         imported_income: 0,
         skipped_income: 0,
         skipped_income_old: 0,
+        enriched_trades: 0,
+        enriched_income: 0,
+        day_trades: day_trade_count,
+        option_exercises: exercises.exercised,
+        items,
     })
 }
 
@@ -243,6 +294,502 @@ pub(crate) fn preview_ofertas_table(
 }
 
 /// Import "Ofertas Públicas" allocations into DB and return (imported, skipped_old, errors, max_date)
+pub(crate) fn preview_tesouro_table(
+    entries: &[crate::importers::TesouroExtratoEntry],
+) -> Option<String> {
+    #[derive(Tabled)]
+    struct TesouroPreview {
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Bond")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        operation: String,
+        #[tabled(rename = "Qty")]
+        quantity: String,
+        #[tabled(rename = "Value")]
+        value: String,
+    }
+
+    let preview: Vec<TesouroPreview> = entries
+        .iter()
+        .take(5)
+        .map(|e| TesouroPreview {
+            date: e.date.format("%d/%m/%Y").to_string(),
+            ticker: e.ticker.clone(),
+            operation: e.operation.as_str().to_string(),
+            quantity: e.quantity.to_string(),
+            value: crate::utils::format_currency(e.value),
+        })
+        .collect();
+
+    if preview.is_empty() {
+        None
+    } else {
+        Some(
+            Table::new(preview)
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(3..5)).with(Alignment::right()))
+                .to_string(),
+        )
+    }
+}
+
+/// Import Tesouro Direto purchases and redemptions as government bond trades
+pub(crate) fn import_tesouro_extrato(
+    conn: &Connection,
+    entries: &[crate::importers::TesouroExtratoEntry],
+) -> Result<ImportStats> {
+    let last_import_date = db::get_last_import_date(conn, "TESOURO_EXTRATO", "operations")?;
+    let mut stats = ImportStats::default();
+    let mut pending = Vec::new();
+
+    for entry in entries {
+        let item = ItemResult::new(
+            "trade",
+            Some(&entry.ticker),
+            Some(entry.date),
+            Some(&entry.title),
+        );
+        if last_import_date.is_some_and(|last| entry.date <= last) {
+            stats
+                .items
+                .push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
+            stats.skipped_old += 1;
+            continue;
+        }
+        let asset_id = match db::upsert_asset(conn, &entry.ticker, &db::AssetType::GovBond, None) {
+            Ok(id) => id,
+            Err(e) => {
+                stats.items.push(item.failed("ASSET_UPSERT_FAILED", e));
+                stats.errors += 1;
+                continue;
+            }
+        };
+        pending.push(entry.to_transaction(asset_id));
+        stats.items.push(item);
+    }
+
+    db::bulk::insert_transactions(conn, &pending, |p| {
+        tracing::debug!("Inserted {}/{} Tesouro transactions", p.done, p.total);
+    })?;
+    stats.imported = pending.len();
+    stats.earliest = pending.iter().map(|t| t.trade_date).min();
+    stats.latest = pending.iter().map(|t| t.trade_date).max();
+
+    if let Some(date) = stats.latest {
+        db::set_last_import_date(conn, "TESOURO_EXTRATO", "operations", date)?;
+    }
+    Ok(stats)
+}
+
+/// Trades and dividends of a US broker statement, in dollars
+pub(crate) fn preview_us_statement_table(
+    statement: &importers::UsBrokerStatement,
+) -> Option<String> {
+    #[derive(Tabled)]
+    struct UsPreview {
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        kind: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Price")]
+        price: String,
+        #[tabled(rename = "Fees / tax")]
+        fees: String,
+    }
+
+    let usd = |value: rust_decimal::Decimal| format!("US$ {:.2}", value);
+    let trades = statement.trades.iter().map(|t| UsPreview {
+        date: t.trade_date.format("%d/%m/%Y").to_string(),
+        ticker: t.ticker.clone(),
+        kind: t.transaction_type.as_str().to_string(),
+        quantity: t.quantity.normalize().to_string(),
+        price: usd(t.price),
+        fees: usd(t.fees),
+    });
+    let dividends = statement.dividends.iter().map(|d| UsPreview {
+        date: d.payment_date.format("%d/%m/%Y").to_string(),
+        ticker: d.ticker.clone(),
+        kind: "DIVIDEND".to_string(),
+        quantity: "-".to_string(),
+        price: usd(d.gross),
+        fees: usd(d.tax),
+    });
+    let preview: Vec<UsPreview> = trades.chain(dividends).take(10).collect();
+
+    if preview.is_empty() {
+        None
+    } else {
+        Some(
+            Table::new(preview)
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(3..)).with(Alignment::right()))
+                .to_string(),
+        )
+    }
+}
+
+/// Trades of a crypto exchange export, in the pair's quote
+pub(crate) fn preview_crypto_table(statement: &importers::CryptoStatement) -> Option<String> {
+    #[derive(Tabled)]
+    struct CryptoPreview {
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Coin")]
+        coin: String,
+        #[tabled(rename = "Type")]
+        kind: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Price")]
+        price: String,
+        #[tabled(rename = "Fees")]
+        fees: String,
+    }
+
+    let preview: Vec<CryptoPreview> = statement
+        .trades
+        .iter()
+        .take(10)
+        .map(|t| CryptoPreview {
+            date: t.trade_date.format("%d/%m/%Y").to_string(),
+            coin: t.coin.clone(),
+            kind: t.transaction_type.as_str().to_string(),
+            quantity: t.quantity.normalize().to_string(),
+            price: format!("{} {:.2}", t.quote, t.price),
+            fees: format!("{} {:.2}", t.quote, t.fees),
+        })
+        .collect();
+
+    if preview.is_empty() {
+        None
+    } else {
+        Some(
+            Table::new(preview)
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(3..)).with(Alignment::right()))
+                .to_string(),
+        )
+    }
+}
+
+pub(crate) fn preview_proventos_table(entries: &[importers::ProventoEntry]) -> Option<String> {
+    #[derive(Tabled)]
+    struct ProventoPreview {
+        #[tabled(rename = "Payment")]
+        payment_date: String,
+        #[tabled(rename = "Ex-date")]
+        ex_date: String,
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        event_type: String,
+        #[tabled(rename = "Gross")]
+        gross: String,
+        #[tabled(rename = "IR")]
+        withholding: String,
+        #[tabled(rename = "Net")]
+        net: String,
+    }
+
+    let preview: Vec<ProventoPreview> = entries
+        .iter()
+        .take(5)
+        .map(|e| ProventoPreview {
+            payment_date: e.payment_date.format("%d/%m/%Y").to_string(),
+            ex_date: e
+                .ex_date
+                .map(|d| d.format("%d/%m/%Y").to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            ticker: e.ticker.clone(),
+            event_type: e.event_type.as_str().to_string(),
+            gross: crate::utils::format_currency(e.gross),
+            withholding: crate::utils::format_currency(e.withholding),
+            net: crate::utils::format_currency(e.net),
+        })
+        .collect();
+
+    if preview.is_empty() {
+        None
+    } else {
+        Some(
+            Table::new(preview)
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(4..7)).with(Alignment::right()))
+                .to_string(),
+        )
+    }
+}
+
+/// Import the Proventos Recebidos report into income events. A payment
+/// another import already recorded (a Movimentação credit line, an
+/// announcement) gets the report's gross value, withholding and ex-date
+/// instead of being recorded twice.
+pub(crate) fn import_proventos(
+    conn: &Connection,
+    entries: &[importers::ProventoEntry],
+) -> Result<ImportStats> {
+    import_income_entries(conn, entries, "PROVENTOS")
+}
+
+/// Record income payments tagged with `source`. Only the Proventos report
+/// completes events recorded by other imports; for any other source a
+/// payment already on record is skipped.
+fn import_income_entries(
+    conn: &Connection,
+    entries: &[importers::ProventoEntry],
+    source: &str,
+) -> Result<ImportStats> {
+    let mut stats = ImportStats::default();
+    // New events with the institution paying them, inserted together below
+    let mut pending: Vec<(db::IncomeEvent, &str)> = Vec::new();
+
+    for entry in entries {
+        let item = ItemResult::new(
+            "income",
+            Some(&entry.ticker),
+            Some(entry.payment_date),
+            Some(&entry.movement_type),
+        );
+        let name = entry.product.split_once(" - ").map(|(_, name)| name.trim());
+        let asset_id = match db::upsert_asset_as_of(
+            conn,
+            &entry.ticker,
+            &db::AssetType::Unknown,
+            name,
+            Some(entry.payment_date),
+        ) {
+            Ok(id) => id,
+            Err(e) => {
+                stats.items.push(item.failed("ASSET_UPSERT_FAILED", e));
+                stats.errors += 1;
+                continue;
+            }
+        };
+        let mut event = entry.to_income_event(asset_id);
+        event.source = source.to_string();
+
+        // A payment listed twice in the file is recorded once; only the
+        // Proventos report completes one another import recorded
+        let queued = pending
+            .iter()
+            .any(|(queued, _)| is_same_income(queued, asset_id, entry));
+        let recorded = if queued {
+            None
+        } else {
+            find_recorded_income(conn, asset_id, entry)?
+        };
+        let duplicate = queued
+            || matches!(&recorded, Some((_, recorded)) if recorded == source || source != "PROVENTOS");
+        if duplicate {
+            stats
+                .items
+                .push(item.skipped("DUPLICATE", "income event already recorded"));
+            stats.skipped_income += 1;
+            continue;
+        }
+        match recorded {
+            Some((id, _)) => {
+                db::complete_income_event(conn, id, &event)?;
+                stats.enriched_income += 1;
+            }
+            None => {
+                pending.push((event, entry.institution.as_str()));
+                stats.imported_income += 1;
+            }
+        }
+        stats.items.push(item);
+        let date = entry.payment_date;
+        stats.earliest = Some(stats.earliest.map_or(date, |d| d.min(date)));
+        stats.latest = Some(stats.latest.map_or(date, |d| d.max(date)));
+    }
+
+    let events: Vec<db::IncomeEvent> = pending.iter().map(|(event, _)| event.clone()).collect();
+    let ids = db::bulk::insert_income_events(conn, &events, |p| {
+        tracing::debug!("Inserted {}/{} {} income events", p.done, p.total, source);
+    })?;
+    let mut brokers: std::collections::HashMap<&str, i64> = std::collections::HashMap::new();
+    for (event_id, (_, institution)) in ids.into_iter().zip(&pending) {
+        if institution.is_empty() {
+            continue;
+        }
+        let broker_id = match brokers.get(institution) {
+            Some(id) => *id,
+            None => {
+                let id = db::upsert_broker(conn, institution)?;
+                brokers.insert(institution, id);
+                id
+            }
+        };
+        db::set_income_event_broker(conn, event_id, broker_id)?;
+    }
+
+    // Movimentação credits now have the events they pay
+    reports::income_reconciliation::link_credits(conn)?;
+    reports::income_reconciliation::sync_inconsistencies(conn)?;
+    Ok(stats)
+}
+
+/// Import an OFX statement: its trades like any other trade file, its
+/// income payments tagged OFX
+pub(crate) fn import_ofx(
+    conn: &Connection,
+    statement: &importers::OfxStatement,
+) -> Result<ImportStats> {
+    let source = importers::ofx::SOURCE;
+    let stats = import_trades(conn, &statement.trades, source)?;
+    let income = import_income_entries(conn, &statement.income, source)?;
+    Ok(with_income(stats, income))
+}
+
+/// Import an Avenue or US broker statement in reais, at the PTAX already
+/// stored for its dates. Its assets are created as stocks quoted in USD.
+pub(crate) fn import_us_statement(
+    conn: &Connection,
+    statement: &importers::UsBrokerStatement,
+) -> Result<ImportStats> {
+    let (trades, income) = statement.to_brl(conn)?;
+    for ticker in statement.tickers() {
+        if !db::asset_exists(conn, &ticker)? {
+            db::insert_asset(conn, &ticker, &db::AssetType::Stock, None)?;
+        }
+        let asset = db::get_asset_by_ticker(conn, &ticker)?
+            .ok_or_else(|| anyhow::anyhow!("Ticker {} not found", ticker))?;
+        if let Some(id) = asset.id {
+            if db::get_asset_currency(conn, id)?.is_none() {
+                db::update_asset_currency(conn, &ticker, Some(importers::avenue_csv::CURRENCY))?;
+            }
+        }
+    }
+
+    let source = importers::avenue_csv::SOURCE;
+    let stats = import_trades(conn, &trades, source)?;
+    let income = import_income_entries(conn, &income, source)?;
+    Ok(with_income(stats, income))
+}
+
+/// Import a crypto exchange export in reais. Its coins are created as
+/// crypto-assets.
+pub(crate) fn import_crypto_statement(
+    conn: &Connection,
+    statement: &importers::CryptoStatement,
+) -> Result<ImportStats> {
+    let trades = statement.to_brl(conn)?;
+    for coin in statement.coins() {
+        match db::get_asset_by_ticker(conn, &coin)? {
+            None => {
+                db::insert_asset(conn, &coin, &db::AssetType::Crypto, None)?;
+            }
+            Some(asset) if asset.asset_type == db::AssetType::Unknown => {
+                db::update_asset_type(conn, &coin, &db::AssetType::Crypto)?;
+            }
+            Some(_) => {}
+        }
+    }
+    import_trades(conn, &trades, importers::crypto_csv::SOURCE)
+}
+
+/// Trade import stats with the counts of the income imported alongside
+fn with_income(mut stats: ImportStats, income: ImportStats) -> ImportStats {
+    stats.imported_income = income.imported_income;
+    stats.skipped_income = income.skipped_income;
+    stats.errors += income.errors;
+    stats.items.extend(income.items);
+    stats.earliest = [stats.earliest, income.earliest]
+        .into_iter()
+        .flatten()
+        .min();
+    stats.latest = [stats.latest, income.latest].into_iter().flatten().max();
+    stats
+}
+
+/// Store a Posição export as the custody of its day, for
+/// `inconsistencies positions`
+pub(crate) fn import_posicao(
+    conn: &Connection,
+    statement: &importers::PosicaoStatement,
+) -> Result<ImportStats> {
+    let recorded = db::position_statements::record_statement(
+        conn,
+        statement.date,
+        importers::posicao_excel::SOURCE,
+        &statement.quantities(),
+    )?;
+    Ok(ImportStats {
+        imported: recorded,
+        earliest: Some(statement.date),
+        latest: Some(statement.date),
+        items: statement
+            .quantities()
+            .keys()
+            .map(|ticker| ItemResult::new("position", Some(ticker), Some(statement.date), None))
+            .collect(),
+        ..Default::default()
+    })
+}
+
+/// An income event of the same asset and type, paid within a few days, that
+/// already stands for this payment: its gross value or its net of IRRF
+/// matches the report's. Returns its id and source.
+/// Whether a queued event is the payment `entry` describes, by the same
+/// rule as [`find_recorded_income`]
+fn is_same_income(
+    event: &db::IncomeEvent,
+    asset_id: i64,
+    entry: &importers::ProventoEntry,
+) -> bool {
+    event.asset_id == asset_id
+        && event.event_type == entry.event_type
+        && (event.event_date - entry.payment_date).num_days().abs()
+            <= reports::income_reconciliation::MATCH_WINDOW_DAYS
+        && (event.total_amount == entry.gross
+            || event.total_amount - event.withholding_tax == entry.net)
+}
+
+fn find_recorded_income(
+    conn: &Connection,
+    asset_id: i64,
+    entry: &importers::ProventoEntry,
+) -> Result<Option<(i64, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, source, total_amount, withholding_tax,
+                ABS(julianday(event_date) - julianday(?3))
+         FROM income_events
+         WHERE asset_id = ?1 AND event_type = ?2 AND portfolio_id = ?4
+           AND ABS(julianday(event_date) - julianday(?3)) <= ?5
+         ORDER BY 5, id",
+    )?;
+    let rows = stmt.query_map(
+        rusqlite::params![
+            asset_id,
+            entry.event_type.as_str(),
+            entry.payment_date,
+            db::portfolio::write_target(),
+            reports::income_reconciliation::MATCH_WINDOW_DAYS,
+        ],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                db::get_decimal_value(row, 2)?,
+                db::get_optional_decimal_value(row, 3)?.unwrap_or_default(),
+            ))
+        },
+    )?;
+    for row in rows {
+        let (id, source, total, withheld) = row?;
+        if total == entry.gross || total - withheld == entry.net {
+            return Ok(Some((id, source)));
+        }
+    }
+    Ok(None)
+}
+
 pub(crate) fn import_ofertas(
     conn: &Connection,
     entries: &[crate::importers::OfertaPublicaEntry],
@@ -250,17 +797,26 @@ pub(crate) fn import_ofertas(
     let mut imported: i64 = 0;
     let mut skipped_old: i64 = 0;
     let mut errors: i64 = 0;
+    let mut items = Vec::new();
     let mut max_date: Option<NaiveDate> = None;
 
     let last_import_date = db::get_last_import_date(conn, "OFERTAS_PUBLICAS", "allocations")?;
+    let mut pending = Vec::new();
 
     for entry in entries {
+        let item = ItemResult::new(
+            "offer",
+            Some(&entry.ticker),
+            Some(entry.date),
+            Some(&entry.offer),
+        );
         let asset_type = db::AssetType::Unknown;
 
         let asset_id = match db::upsert_asset(conn, &entry.ticker, &asset_type, None) {
             Ok(id) => id,
             Err(e) => {
                 eprintln!("Error upserting asset {}: {}", entry.ticker, e);
+                items.push(item.failed("ASSET_UPSERT_FAILED", e));
                 errors += 1;
                 continue;
             }
@@ -268,6 +824,7 @@ pub(crate) fn import_ofertas(
 
         if let Some(last_date) = last_import_date {
             if entry.date <= last_date {
+                items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
                 skipped_old += 1;
                 continue;
             }
@@ -277,24 +834,25 @@ pub(crate) fn import_ofertas(
             Ok(tx) => tx,
             Err(e) => {
                 eprintln!("Error converting offer to transaction: {}", e);
+                items.push(item.failed("INVALID_ENTRY", e));
                 errors += 1;
                 continue;
             }
         };
 
-        match db::insert_transaction(conn, &transaction) {
-            Ok(_) => {
-                imported += 1;
-                max_date = Some(match max_date {
-                    Some(current) if current >= transaction.trade_date => current,
-                    _ => transaction.trade_date,
-                });
-            }
-            Err(e) => {
-                eprintln!("Error inserting offer transaction: {}", e);
-                errors += 1;
-            }
-        }
+        pending.push(transaction);
+        items.push(item);
+    }
+
+    db::bulk::insert_transactions(conn, &pending, |p| {
+        tracing::debug!("Inserted {}/{} offer transactions", p.done, p.total);
+    })?;
+    for transaction in &pending {
+        imported += 1;
+        max_date = Some(match max_date {
+            Some(current) if current >= transaction.trade_date => current,
+            _ => transaction.trade_date,
+        });
     }
 
     if let Some(d) = max_date {
@@ -317,9 +875,332 @@ pub(crate) fn import_ofertas(
         imported_income: 0,
         skipped_income: 0,
         skipped_income_old: 0,
+        enriched_trades: 0,
+        enriched_income: 0,
+        day_trades: 0,
+        option_exercises: 0,
+        items,
     })
 }
 
+/// Return a pretty table preview for brokerage note trades (up to 10 rows)
+pub(crate) fn preview_notas_table(notes: &[importers::NotaCorretagem]) -> Option<String> {
+    #[derive(Tabled)]
+    struct NotaPreview {
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Note")]
+        number: String,
+        #[tabled(rename = "Title")]
+        specification: String,
+        #[tabled(rename = "Type")]
+        tx_type: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Price")]
+        price: String,
+        #[tabled(rename = "Fees")]
+        fees: String,
+    }
+
+    let preview: Vec<NotaPreview> = notes
+        .iter()
+        .flat_map(|note| note.trades.iter().map(move |trade| (note, trade)))
+        .take(10)
+        .map(|(note, trade)| NotaPreview {
+            date: note
+                .trade_date
+                .map(|d| d.format("%d/%m/%Y").to_string())
+                .unwrap_or_default(),
+            number: note.number.clone(),
+            specification: match &trade.ticker {
+                Some(ticker) if !trade.specification.contains(ticker.as_str()) => {
+                    format!("{} ({})", trade.specification, ticker)
+                }
+                _ => trade.specification.clone(),
+            },
+            tx_type: format!(
+                "{}{}",
+                trade.transaction_type.as_str(),
+                if trade.is_day_trade { " (DT)" } else { "" }
+            ),
+            quantity: trade.quantity.to_string(),
+            price: crate::utils::format_currency(trade.price),
+            fees: crate::utils::format_currency(trade.fees),
+        })
+        .collect();
+
+    if preview.is_empty() {
+        None
+    } else {
+        Some(
+            Table::new(preview)
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(4..)).with(Alignment::right()))
+                .to_string(),
+        )
+    }
+}
+
+/// Import brokerage notes: each note's fees and IRRF, plus its trades with
+/// their share of the fees. A trade already imported from a B3 export (same
+/// asset, date, side and quantity, no fees) gets the note's fees and day-trade
+/// flag instead of being duplicated. Notes imported before are skipped.
+pub(crate) fn import_notas(
+    conn: &Connection,
+    notes: &[importers::NotaCorretagem],
+) -> Result<ImportStats> {
+    let mut stats = ImportStats::default();
+
+    for note in notes {
+        let Some(trade_date) = note.trade_date else {
+            eprintln!("Note {} has no trading date; skipped", note.number);
+            stats.items.push(
+                ItemResult::new("note", None, None, Some(&note.number))
+                    .failed("NO_TRADE_DATE", "the note has no trading date"),
+            );
+            stats.errors += 1;
+            continue;
+        };
+        let note_ref = format!("Nota {}", note.number);
+        let trade_item = |trade: &importers::nota_corretagem::NotaTrade, ticker: Option<&str>| {
+            ItemResult::new(
+                "trade",
+                ticker.or(trade.ticker.as_deref()),
+                Some(trade_date),
+                Some(&note_ref),
+            )
+        };
+        let broker_id = note
+            .broker
+            .as_deref()
+            .map(|name| db::upsert_broker(conn, name))
+            .transpose()?;
+        if db::broker_note_exists(conn, broker_id, &note.number, trade_date)? {
+            stats.skipped_trades += note.trades.len();
+            stats.items.extend(note.trades.iter().map(|trade| {
+                trade_item(trade, None).skipped("NOTE_ALREADY_IMPORTED", "note imported before")
+            }));
+            continue;
+        }
+
+        let mut resolved = Vec::new();
+        for trade in &note.trades {
+            let ticker =
+                match &trade.ticker {
+                    Some(ticker) => Some(ticker.clone()),
+                    None => crate::tickers::find_ticker_by_specification(
+                        &trade.specification,
+                        trade_date,
+                    )
+                    .unwrap_or_else(|e| {
+                        tracing::warn!("Ticker lookup failed for '{}': {}", trade.specification, e);
+                        None
+                    }),
+                };
+            match ticker {
+                Some(ticker) => resolved.push((trade, ticker)),
+                None => {
+                    eprintln!(
+                        "Note {} ({}): no ticker found for '{}'; add this trade with 'interest transactions add'",
+                        note.number,
+                        trade_date.format("%d/%m/%Y"),
+                        trade.specification
+                    );
+                    stats.items.push(trade_item(trade, None).failed(
+                        "TICKER_NOT_FOUND",
+                        format!("no ticker found for '{}'", trade.specification),
+                    ));
+                    stats.errors += 1;
+                }
+            }
+        }
+
+        let (imported, enriched) = db::bulk::in_transaction(conn, |conn| {
+            db::insert_broker_note(
+                conn,
+                &db::BrokerNote {
+                    id: None,
+                    broker_id,
+                    note_number: note.number.clone(),
+                    trade_date,
+                    settlement_date: note.settlement_date,
+                    settlement_fee: note.settlement_fee,
+                    registration_fee: note.registration_fee,
+                    emolumentos: note.emolumentos,
+                    brokerage: note.brokerage,
+                    iss: note.iss,
+                    other_fees: note.other_fees,
+                    irrf: note.irrf,
+                    irrf_day_trade: note.irrf_day_trade,
+                    net_amount: note.net_amount,
+                },
+            )?;
+
+            let mut imported = 0;
+            let mut enriched = 0;
+            for (trade, ticker) in &resolved {
+                let asset_id = db::upsert_asset_as_of(
+                    conn,
+                    ticker,
+                    &db::AssetType::Unknown,
+                    None,
+                    Some(trade_date),
+                )?;
+                let total_cost = match trade.transaction_type {
+                    db::TransactionType::Buy => trade.value + trade.fees,
+                    db::TransactionType::Sell => trade.value,
+                };
+                let tx_id = match find_unpriced_trade(conn, asset_id, trade_date, trade)? {
+                    Some(tx_id) => {
+                        conn.execute(
+                            "UPDATE transactions
+                             SET fees = ?1, total_cost = ?2, is_day_trade = ?3,
+                                 settlement_date = COALESCE(settlement_date, ?4)
+                             WHERE id = ?5",
+                            rusqlite::params![
+                                trade.fees.to_string(),
+                                total_cost.to_string(),
+                                trade.is_day_trade,
+                                note.settlement_date,
+                                tx_id
+                            ],
+                        )?;
+                        enriched += 1;
+                        tx_id
+                    }
+                    None => {
+                        imported += 1;
+                        db::insert_transaction(
+                            conn,
+                            &db::Transaction {
+                                id: None,
+                                asset_id,
+                                transaction_type: trade.transaction_type.clone(),
+                                trade_date,
+                                settlement_date: note.settlement_date,
+                                quantity: trade.quantity,
+                                price_per_unit: trade.price,
+                                total_cost,
+                                fees: trade.fees,
+                                is_day_trade: trade.is_day_trade,
+                                quota_issuance_date: None,
+                                notes: Some(format!(
+                                    "Nota {} - {}",
+                                    note.number, trade.specification
+                                )),
+                                source: "NOTA_CORRETAGEM".to_string(),
+                                created_at: chrono::Utc::now(),
+                            },
+                        )?
+                    }
+                };
+                if let Some(broker_id) = broker_id {
+                    db::set_transaction_broker(conn, tx_id, broker_id)?;
+                }
+                stats.items.push(trade_item(trade, Some(ticker)));
+            }
+            Ok((imported, enriched))
+        })?;
+
+        stats.imported_trades += imported;
+        stats.enriched_trades += enriched;
+        stats.earliest = Some(stats.earliest.map_or(trade_date, |d| d.min(trade_date)));
+        stats.latest = Some(stats.latest.map_or(trade_date, |d| d.max(trade_date)));
+    }
+
+    Ok(stats)
+}
+
+/// JSON envelope of a batch operation: overall outcome, the operation's
+/// counters under `data` and one entry per processed item. `partial` is set
+/// when some items failed and others went through.
+pub(crate) fn batch_envelope<T: serde::Serialize>(
+    data: &T,
+    items: &[ItemResult],
+) -> serde_json::Value {
+    use crate::importers::ItemStatus;
+
+    let count = |status: ItemStatus| items.iter().filter(|i| i.status == status).count();
+    let succeeded = count(ItemStatus::Success);
+    let failed = count(ItemStatus::Error);
+    serde_json::json!({
+        "success": failed == 0,
+        "partial": failed > 0 && succeeded > 0,
+        "summary": {
+            "succeeded": succeeded,
+            "skipped": count(ItemStatus::Skipped),
+            "failed": failed,
+        },
+        "data": data,
+        "items": items,
+    })
+}
+
+const BEFORE_LAST_IMPORT: &str = "dated on or before the last import of this file type";
+
+/// A trade of another import with the same asset, date, side and quantity
+/// that has no fees recorded yet
+fn find_unpriced_trade(
+    conn: &Connection,
+    asset_id: i64,
+    trade_date: NaiveDate,
+    trade: &importers::nota_corretagem::NotaTrade,
+) -> Result<Option<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, quantity, COALESCE(fees, 0) FROM transactions
+         WHERE asset_id = ?1 AND trade_date = ?2 AND transaction_type = ?3
+           AND source != 'NOTA_CORRETAGEM'
+         ORDER BY id",
+    )?;
+    let rows = stmt.query_map(
+        rusqlite::params![asset_id, trade_date, trade.transaction_type.as_str()],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                db::get_decimal_value(row, 1)?,
+                db::get_decimal_value(row, 2)?,
+            ))
+        },
+    )?;
+    for row in rows {
+        let (id, quantity, fees) = row?;
+        if quantity == trade.quantity && fees.is_zero() {
+            return Ok(Some(id));
+        }
+    }
+    Ok(None)
+}
+
+/// Persist an auto-detected import result, dispatching on its format
+pub(crate) fn import_parsed(
+    conn: &Connection,
+    result: importers::ImportResult,
+) -> Result<ImportStats> {
+    match result {
+        importers::ImportResult::Cei(txs) => import_cei(conn, &txs),
+        importers::ImportResult::Movimentacao(entries) => {
+            let stats = importers::import_movimentacao_entries(conn, entries, true)?;
+            Ok(stats)
+        }
+        importers::ImportResult::OfertasPublicas(entries) => import_ofertas(conn, &entries),
+        importers::ImportResult::NotaCorretagem(notes) => {
+            let stats = import_notas(conn, &notes)?;
+            Ok(stats)
+        }
+        importers::ImportResult::TesouroExtrato(entries) => import_tesouro_extrato(conn, &entries),
+        importers::ImportResult::Proventos(entries) => import_proventos(conn, &entries),
+        importers::ImportResult::Ofx(statement) => import_ofx(conn, &statement),
+        importers::ImportResult::Posicao(statement) => import_posicao(conn, &statement),
+        importers::ImportResult::UsBroker(statement) => import_us_statement(conn, &statement),
+        importers::ImportResult::Crypto(statement) => import_crypto_statement(conn, &statement),
+        importers::ImportResult::Custom {
+            source,
+            transactions,
+        } => import_trades(conn, &transactions, &source),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -330,4 +1211,169 @@ mod tests {
         let txs: Vec<crate::importers::RawTransaction> = vec![];
         assert!(preview_cei_table(&txs).is_none());
     }
+
+    #[test]
+    fn import_notas_adds_fees_to_existing_trades_once() {
+        use crate::importers::nota_corretagem::{NotaCorretagem, NotaTrade};
+        use rust_decimal_macros::dec;
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let fii = db::upsert_asset(&conn, "HGLG11", &db::AssetType::Fii, None).unwrap();
+        conn.execute(
+            "INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                price_per_unit, total_cost, source)
+             VALUES (?1, 'SELL', ?2, 10, 160, 1600, 'MOVIMENTACAO')",
+            rusqlite::params![fii, date],
+        )
+        .unwrap();
+
+        let trade = |ticker: &str, side, quantity, value, fees| NotaTrade {
+            transaction_type: side,
+            market: "VISTA".to_string(),
+            specification: ticker.to_string(),
+            ticker: Some(ticker.to_string()),
+            quantity,
+            price: value / quantity,
+            value,
+            is_day_trade: false,
+            fees,
+        };
+        let note = NotaCorretagem {
+            number: "123456".to_string(),
+            trade_date: Some(date),
+            broker: Some("XP INVESTIMENTOS CCTVM S/A".to_string()),
+            trades: vec![
+                trade(
+                    "PETR4",
+                    db::TransactionType::Buy,
+                    dec!(100),
+                    dec!(3850),
+                    dec!(5.26),
+                ),
+                trade(
+                    "HGLG11",
+                    db::TransactionType::Sell,
+                    dec!(10),
+                    dec!(1600),
+                    dec!(2.19),
+                ),
+            ],
+            emolumentos: dec!(7.45),
+            irrf: dec!(0.08),
+            ..Default::default()
+        };
+
+        let stats = import_notas(&conn, std::slice::from_ref(&note)).unwrap();
+        assert_eq!((stats.imported_trades, stats.enriched_trades), (1, 1));
+        let (total_cost, fees) = conn
+            .query_row(
+                "SELECT total_cost, fees FROM transactions t JOIN assets a ON a.id = t.asset_id
+                 WHERE a.ticker = 'PETR4'",
+                [],
+                |row| {
+                    Ok((
+                        db::get_decimal_value(row, 0)?,
+                        db::get_decimal_value(row, 1)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!((total_cost, fees), (dec!(3855.26), dec!(5.26)));
+        let count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM transactions WHERE asset_id = ?1 AND fees > 0",
+                [fii],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let again = import_notas(&conn, &[note]).unwrap();
+        assert_eq!((again.imported_trades, again.skipped_trades), (0, 2));
+        assert!(again
+            .items
+            .iter()
+            .all(|i| i.code == Some("NOTE_ALREADY_IMPORTED")));
+    }
+
+    #[test]
+    fn import_proventos_completes_movimentacao_income_once() {
+        use rust_decimal_macros::dec;
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let paid = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+        let itsa = db::upsert_asset(&conn, "ITSA4", &db::AssetType::Stock, None).unwrap();
+        // A Movimentação credit line: the net JCP, no withholding
+        conn.execute(
+            "INSERT INTO income_events (asset_id, event_date, event_type, amount_per_quota,
+                total_amount, withholding_tax, source)
+             VALUES (?1, ?2, 'JCP', '0.17', '170', '0', 'MOVIMENTACAO')",
+            rusqlite::params![itsa, paid],
+        )
+        .unwrap();
+
+        let entry = |ticker: &str, gross, withholding| importers::ProventoEntry {
+            ticker: ticker.to_string(),
+            product: format!("{} - TEST S.A.", ticker),
+            event_type: db::IncomeEventType::Jcp,
+            movement_type: "Juros Sobre Capital Próprio".to_string(),
+            ex_date: NaiveDate::from_ymd_opt(2024, 3, 1),
+            payment_date: paid,
+            institution: "XP INVESTIMENTOS CCTVM S/A".to_string(),
+            quantity: Some(dec!(1000)),
+            gross,
+            withholding,
+            net: gross - withholding,
+            foreign_tax: None,
+        };
+        let entries = vec![
+            entry("ITSA4", dec!(200), dec!(30)),
+            entry("BBAS3", dec!(100), dec!(15)),
+        ];
+
+        let stats = import_proventos(&conn, &entries).unwrap();
+        assert_eq!((stats.enriched_income, stats.imported_income), (1, 1));
+        let events = db::get_income_events_with_assets(&conn, None, None, Some("ITSA4")).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0.total_amount, dec!(200));
+        assert_eq!(events[0].0.withholding_tax, dec!(30));
+        assert_eq!(events[0].0.ex_date, NaiveDate::from_ymd_opt(2024, 3, 1));
+        assert_eq!(events[0].0.source, "PROVENTOS");
+
+        let again = import_proventos(&conn, &entries).unwrap();
+        assert_eq!(
+            (
+                again.imported_income,
+                again.enriched_income,
+                again.skipped_income
+            ),
+            (0, 0, 2)
+        );
+    }
+
+    #[test]
+    fn batch_envelope_reports_partial_success() {
+        let ok = ItemResult::new("price", Some("PETR4"), None, None);
+        let failed =
+            ItemResult::new("price", Some("XPTO3"), None, None).failed("FETCH_FAILED", "timeout");
+        let skipped = ok.clone().skipped("DUPLICATE", "already stored");
+
+        let payload = batch_envelope(&serde_json::json!({}), &[ok.clone(), failed, skipped]);
+        assert_eq!(payload["success"], false);
+        assert_eq!(payload["partial"], true);
+        assert_eq!(payload["summary"]["skipped"], 1);
+        assert_eq!(payload["items"][1]["code"], "FETCH_FAILED");
+        assert_eq!(payload["items"][1]["status"], "error");
+
+        let payload = batch_envelope(&serde_json::json!({}), &[ok]);
+        assert_eq!(
+            (payload["success"].clone(), payload["partial"].clone()),
+            (true.into(), false.into())
+        );
+    }
 }
@@ -0,0 +1,304 @@
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate};
+use colored::Colorize;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Remove, Style},
+    Table, Tabled,
+};
+
+use crate::db;
+use crate::reports::income_forecast;
+use crate::tax::income_scenario::{self, DividendScenario};
+use crate::utils::format_currency;
+
+fn range(low: Decimal, high: Decimal) -> String {
+    format!("{} – {}", format_currency(low), format_currency(high))
+}
+
+/// Scenario from `--dividend-tax`, `--dividend-threshold` and `--tax-from`
+fn parse_scenario(
+    rate: &str,
+    threshold: Option<&str>,
+    from: Option<&str>,
+    today: NaiveDate,
+) -> Result<DividendScenario> {
+    let rate = Decimal::from_str(rate.trim_end_matches('%'))
+        .with_context(|| format!("Invalid dividend tax: {}. Must be a percentage", rate))?;
+    if rate < Decimal::ZERO || rate > Decimal::from(100) {
+        anyhow::bail!("Dividend tax must be between 0 and 100%");
+    }
+    let monthly_threshold = match threshold {
+        Some(value) => Decimal::from_str(value)
+            .with_context(|| format!("Invalid dividend threshold: {}. Must be an amount", value))?,
+        None => Decimal::ZERO,
+    };
+    let since = match from {
+        Some(value) => {
+            let date = NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d")
+                .with_context(|| format!("Invalid month '{}'. Use YYYY-MM format", value))?;
+            (date.year(), date.month())
+        }
+        None => (today.year(), today.month()),
+    };
+    Ok(DividendScenario {
+        since,
+        rate: rate / Decimal::from(100),
+        monthly_threshold,
+    })
+}
+
+pub fn dispatch_income_forecast(
+    months: u32,
+    asset: Option<&str>,
+    net: bool,
+    dividend_tax: Option<&str>,
+    dividend_threshold: Option<&str>,
+    tax_from: Option<&str>,
+    json_output: bool,
+) -> Result<()> {
+    let today = chrono::Local::now().date_naive();
+    let scenario = dividend_tax
+        .map(|rate| parse_scenario(rate, dividend_threshold, tax_from, today))
+        .transpose()?;
+    let net = net || scenario.is_some();
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let mut forecast = income_forecast::forecast(&conn, today, months, asset)?;
+    // Tax under the law alone, to show what the scenario changes
+    let mut law_tax = None;
+    if net {
+        if scenario.is_some() {
+            let mut law = forecast.clone();
+            income_scenario::apply(&conn, &mut law, None)?;
+            law_tax = Some(law.total_tax());
+        }
+        income_scenario::apply(&conn, &mut forecast, scenario.as_ref())?;
+    }
+
+    if json_output {
+        let mut value = serde_json::to_value(&forecast)?;
+        if net {
+            value["tax"] = serde_json::json!({
+                "scenario": scenario,
+                "gross": forecast.total(),
+                "tax": forecast.total_tax(),
+                "net": forecast.total() - forecast.total_tax(),
+                "law_tax": law_tax,
+            });
+        }
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Expected income until {}\n",
+        "📅".cyan().bold(),
+        forecast.until.format("%d/%m/%Y")
+    );
+    if forecast.payments.is_empty() {
+        println!(
+            "{} No payments expected. Cadences are learned from the last {} months of income on assets you hold.",
+            "ℹ".blue().bold(),
+            income_forecast::LOOKBACK_MONTHS
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct Row {
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        event_type: String,
+        #[tabled(rename = "Qty")]
+        quantity: String,
+        #[tabled(rename = "Per share")]
+        per_share: String,
+        #[tabled(rename = "Amount")]
+        amount: String,
+        #[tabled(rename = "Range")]
+        range: String,
+        #[tabled(rename = "Basis")]
+        basis: String,
+        #[tabled(rename = "Tax")]
+        tax: String,
+        #[tabled(rename = "Net")]
+        net: String,
+    }
+
+    let rows: Vec<Row> = forecast
+        .payments
+        .iter()
+        .map(|p| Row {
+            date: if p.announced {
+                p.date.format("%d/%m/%Y").to_string()
+            } else {
+                format!("~{}", p.date.format("%d/%m/%Y"))
+            },
+            ticker: p.ticker.clone(),
+            event_type: p.event_type.as_str().to_string(),
+            quantity: p
+                .quantity
+                .map(|q| q.normalize().to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            per_share: format!("{:.4}", p.amount_per_quota),
+            amount: format_currency(p.amount),
+            range: if p.announced {
+                "-".to_string()
+            } else {
+                range(p.low, p.high)
+            },
+            basis: if p.announced {
+                "announced".green().to_string()
+            } else {
+                p.cadence.clone().unwrap_or_default().dimmed().to_string()
+            },
+            tax: format_currency(p.tax.unwrap_or_default()),
+            net: format_currency(p.net()),
+        })
+        .collect();
+    let mut table = Table::new(rows);
+    table
+        .with(Style::rounded())
+        .with(Modify::new(Columns::new(3..7)).with(Alignment::right()))
+        .with(Modify::new(Columns::new(8..10)).with(Alignment::right()));
+    if !net {
+        table.with(Remove::column(Columns::new(8..10)));
+    }
+    println!("{}", table);
+
+    #[derive(Tabled)]
+    struct OutlookRow {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Last 12 months")]
+        ltm: String,
+        #[tabled(rename = "Trend")]
+        trend: String,
+        #[tabled(rename = "Expected")]
+        expected: String,
+        #[tabled(rename = "Range")]
+        range: String,
+        #[tabled(rename = "Tax")]
+        tax: String,
+        #[tabled(rename = "Net")]
+        net: String,
+    }
+
+    if !forecast.assets.is_empty() {
+        let rows: Vec<OutlookRow> = forecast
+            .assets
+            .iter()
+            .map(|a| OutlookRow {
+                ticker: a.ticker.clone(),
+                ltm: format_currency(a.ltm),
+                trend: match a.trend_pct {
+                    Some(t) if t > Decimal::ZERO => format!("+{}%", t).green().to_string(),
+                    Some(t) if t < Decimal::ZERO => format!("{}%", t).red().to_string(),
+                    Some(_) => "0%".to_string(),
+                    None => "-".to_string(),
+                },
+                expected: format_currency(a.expected),
+                range: range(a.low, a.high),
+                tax: format_currency(a.tax.unwrap_or_default()),
+                net: format_currency(a.expected - a.tax.unwrap_or_default()),
+            })
+            .collect();
+        println!("\nBy asset (expected over the next {} months):", months);
+        let mut table = Table::new(rows);
+        table
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..7)).with(Alignment::right()));
+        if !net {
+            table.with(Remove::column(Columns::new(5..7)));
+        }
+        println!("{}", table);
+    }
+
+    println!();
+    let tax_note = |tax: Decimal| {
+        if net {
+            format!("  tax {}", format_currency(tax))
+        } else {
+            String::new()
+        }
+    };
+    for total in forecast.monthly_totals() {
+        println!(
+            "  {}  {:>16}  {}{}",
+            total.month.format("%m/%Y"),
+            format_currency(total.amount),
+            range(total.low, total.high).dimmed(),
+            tax_note(total.tax).dimmed()
+        );
+    }
+    let (low, high) = forecast.total_range();
+    println!(
+        "  {}  {:>16}  {}{}",
+        "Total  ".bold(),
+        format_currency(forecast.total()).green().bold(),
+        range(low, high).dimmed(),
+        tax_note(forecast.total_tax()).dimmed()
+    );
+    if net {
+        println!(
+            "  {}  {:>16}",
+            "Net    ".bold(),
+            format_currency(forecast.total() - forecast.total_tax())
+                .green()
+                .bold()
+        );
+    }
+    if let (Some(scenario), Some(law_tax)) = (&scenario, law_tax) {
+        let change = forecast.total_tax() - law_tax;
+        let impact = if change.is_zero() {
+            "the same tax as the law in force".to_string()
+        } else {
+            format!(
+                "{} {} in tax than the law in force",
+                format_currency(change.abs()).bold(),
+                if change < Decimal::ZERO {
+                    "less"
+                } else {
+                    "more"
+                }
+            )
+        };
+        println!(
+            "\n{} Scenario: {}% on stock dividends{} from {:02}/{}: {}",
+            "⚖".cyan().bold(),
+            (scenario.rate * Decimal::from(100)).normalize(),
+            if scenario.monthly_threshold.is_zero() {
+                String::new()
+            } else {
+                format!(
+                    " when a company pays over {} a month",
+                    format_currency(scenario.monthly_threshold)
+                )
+            },
+            scenario.since.1,
+            scenario.since.0,
+            impact
+        );
+    }
+    println!(
+        "\n{}",
+        format!(
+            "Projected payments (~) follow each asset's calendar and the trend of its distributions per share over today's position; ranges hold {} of payments. {}",
+            income_forecast::CONFIDENCE,
+            if net {
+                "Tax follows the rules in force on each payment date (see tax rules)."
+            } else {
+                "JCP is gross of IRRF."
+            }
+        )
+        .dimmed()
+    );
+    Ok(())
+}
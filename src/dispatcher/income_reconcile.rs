@@ -0,0 +1,112 @@
+use anyhow::Result;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::db;
+use crate::reports::income_reconciliation::{self, ReconciliationStatus};
+use crate::utils::format_currency;
+
+pub fn dispatch_income_reconcile(year: Option<i32>, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let linked = income_reconciliation::link_credits(&conn)?;
+    let (opened, resolved) = income_reconciliation::sync_inconsistencies(&conn)?;
+    let lines = income_reconciliation::reconcile(&conn, year)?;
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "year": year,
+                "linked": linked,
+                "inconsistencies_opened": opened,
+                "inconsistencies_resolved": resolved,
+                "lines": lines,
+            }))?
+        );
+        return Ok(());
+    }
+
+    if lines.is_empty() {
+        println!(
+            "{} No credited income to reconcile. Import a Movimentação statement first.",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct Row {
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        event_type: String,
+        #[tabled(rename = "Expected")]
+        expected: String,
+        #[tabled(rename = "Credited")]
+        credited: String,
+        #[tabled(rename = "Diff")]
+        difference: String,
+        #[tabled(rename = "Status")]
+        status: String,
+    }
+
+    let money = |v: Option<Decimal>| v.map(format_currency).unwrap_or_else(|| "-".to_string());
+    let rows: Vec<Row> = lines
+        .iter()
+        .filter(|l| l.status != ReconciliationStatus::Matched)
+        .map(|l| Row {
+            date: l.date.format("%d/%m/%Y").to_string(),
+            ticker: l.ticker.clone(),
+            event_type: l.event_type.clone(),
+            expected: money(l.expected),
+            credited: money(l.credited),
+            difference: format_currency(l.difference),
+            status: match l.status {
+                ReconciliationStatus::Matched => "matched".green().to_string(),
+                ReconciliationStatus::AmountDiffers => "amount differs".yellow().to_string(),
+                ReconciliationStatus::Uncredited => "not credited".red().to_string(),
+                ReconciliationStatus::UnmatchedCredit => "no income event".red().to_string(),
+            },
+        })
+        .collect();
+
+    match year {
+        Some(year) => println!(
+            "\n{} Income vs cash credited - {}\n",
+            "🔗".cyan().bold(),
+            year
+        ),
+        None => println!("\n{} Income vs cash credited\n", "🔗".cyan().bold()),
+    }
+    let matched = lines.len() - rows.len();
+    if rows.is_empty() {
+        println!(
+            "{} All {} credits match their income events",
+            "✓".green().bold(),
+            matched
+        );
+    } else {
+        println!(
+            "{}",
+            Table::new(rows)
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(3..6)).with(Alignment::right()))
+        );
+        println!("\n{} matched exactly (not listed)", matched);
+    }
+    if opened > 0 || resolved > 0 {
+        println!(
+            "Inconsistencies: {} opened, {} resolved. See: interest inconsistencies list",
+            opened, resolved
+        );
+    }
+    Ok(())
+}
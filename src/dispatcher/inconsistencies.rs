@@ -1,10 +1,12 @@
 use crate::utils::format_currency;
 use crate::{db, reports};
 use anyhow::Result;
+use colored::Colorize;
 use rust_decimal::Decimal;
 use serde_json::{Map, Value};
 use std::io::{stdin, stdout, BufRead, Write};
 use std::str::FromStr;
+use tabled::Tabled;
 
 pub async fn dispatch_inconsistencies(
     action: &crate::cli::InconsistenciesCommands,
@@ -20,6 +22,7 @@ pub async fn dispatch_inconsistencies(
             status,
             issue_type,
             asset,
+            table,
         } => {
             // Convert open/all flags to status
             let status = if *all {
@@ -39,39 +42,65 @@ pub async fn dispatch_inconsistencies(
             } else {
                 None
             };
-            let issues = db::list_inconsistencies(&conn, status, issue_type, asset.as_deref())?;
+            let page = table.paginate(db::list_inconsistencies(
+                &conn,
+                status,
+                issue_type,
+                asset.as_deref(),
+            )?);
 
             if json_output {
-                println!("{}", serde_json::to_string_pretty(&issues)?);
+                println!("{}", serde_json::to_string_pretty(&page.rows)?);
                 return Ok(());
             }
 
-            if issues.is_empty() {
+            if page.total == 0 {
                 println!("No inconsistencies found.");
                 return Ok(());
             }
 
-            for issue in issues {
-                let qty = issue
-                    .quantity
-                    .as_ref()
-                    .map(|q| q.to_string())
-                    .unwrap_or_else(|| "-".to_string());
-                let date = issue
-                    .trade_date
-                    .map(|d| d.to_string())
-                    .unwrap_or_else(|| "-".to_string());
-                let ticker = issue.ticker.clone().unwrap_or_else(|| "-".to_string());
-                println!(
-                    "#{:<5} {:<9} {:<24} {:<8} {:<10} qty={}",
-                    issue.id.unwrap_or(0),
-                    issue.status.as_str(),
-                    issue.issue_type.as_str(),
-                    ticker,
-                    date,
-                    qty
-                );
+            #[derive(Tabled)]
+            struct IssueRow {
+                #[tabled(rename = "ID")]
+                id: String,
+                #[tabled(rename = "Status")]
+                status: String,
+                #[tabled(rename = "Type")]
+                issue_type: String,
+                #[tabled(rename = "Ticker")]
+                ticker: String,
+                #[tabled(rename = "Date")]
+                date: String,
+                #[tabled(rename = "Quantity")]
+                quantity: String,
             }
+
+            let rows: Vec<IssueRow> = page
+                .rows
+                .iter()
+                .map(|issue| IssueRow {
+                    id: format!("#{}", issue.id.unwrap_or(0)),
+                    status: issue.status.as_str().to_string(),
+                    issue_type: issue.issue_type.as_str().to_string(),
+                    ticker: issue.ticker.clone().unwrap_or_else(|| "-".to_string()),
+                    date: issue
+                        .trade_date
+                        .map(|d| d.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                    quantity: issue
+                        .quantity
+                        .map(|q| q.to_string())
+                        .unwrap_or_else(|| "-".to_string()),
+                })
+                .collect();
+
+            let mut out =
+                crate::cli::formatters::render_table(&rows, &table.columns, &["Quantity"])?;
+            out.push('\n');
+            if let Some(footer) = page.footer() {
+                out.push_str(&format!("{}\n", footer.dimmed()));
+            }
+            crate::cli::formatters::print_paged(&out, table.no_pager);
             Ok(())
         }
         crate::cli::InconsistenciesCommands::Show { id } => {
@@ -181,8 +210,25 @@ pub async fn dispatch_inconsistencies(
                         crate::db::InconsistencyType::MissingPurchaseHistory => {
                             prompt_missing_purchase_history(issue)
                         }
+                        crate::db::InconsistencyType::PositionAdjustment => {
+                            prompt_position_adjustment(&conn, issue)
+                        }
+                        crate::db::InconsistencyType::UnmatchedCashCredit => {
+                            prompt_unmatched_cash_credit(issue)
+                        }
+                        crate::db::InconsistencyType::DuplicateTransaction => {
+                            prompt_duplicate_transaction(issue)
+                        }
+                        crate::db::InconsistencyType::InvalidQuantity => {
+                            prompt_invalid_quantity(&conn, issue)
+                        }
+                        crate::db::InconsistencyType::CorporateActionConflict => {
+                            prompt_corporate_action_conflict(issue)
+                        }
+                        crate::db::InconsistencyType::TickerRename => prompt_ticker_rename(issue),
                         crate::db::InconsistencyType::InvalidTicker
-                        | crate::db::InconsistencyType::InvalidDate => {
+                        | crate::db::InconsistencyType::InvalidDate
+                        | crate::db::InconsistencyType::UncreditedIncome => {
                             println!(
                                 "Skipping #{} - interactive resolution for {} not implemented yet.",
                                 issue_id,
@@ -247,6 +293,15 @@ pub async fn dispatch_inconsistencies(
 
             Ok(())
         }
+        crate::cli::InconsistenciesCommands::Positions { from, to, asset } => {
+            super::position_discrepancies::dispatch_position_discrepancies(
+                &conn,
+                from.as_deref(),
+                to.as_deref(),
+                asset.as_deref(),
+                json_output,
+            )
+        }
         crate::cli::InconsistenciesCommands::Ignore { id, reason } => {
             crate::db::ignore_inconsistency(&conn, *id, reason.as_deref())?;
             if json_output {
@@ -559,7 +614,6 @@ fn apply_inconsistency_resolution(
                 created_at: chrono::Utc::now(),
             };
             db::insert_transaction(conn, &tx)?;
-            reports::invalidate_snapshots_after(conn, trade_date)?;
             db::resolve_inconsistency(
                 conn,
                 issue.id.unwrap_or(0),
@@ -622,7 +676,6 @@ fn apply_inconsistency_resolution(
                 created_at: chrono::Utc::now(),
             };
             db::insert_transaction(conn, &tx)?;
-            reports::invalidate_snapshots_after(conn, trade_date)?;
             db::resolve_inconsistency(
                 conn,
                 issue.id.unwrap_or(0),
@@ -631,8 +684,543 @@ fn apply_inconsistency_resolution(
             )?;
             Ok(())
         }
+        db::InconsistencyType::PositionAdjustment => {
+            let trade_date = issue
+                .trade_date
+                .ok_or_else(|| anyhow::anyhow!("trade_date is required"))?;
+            let asset_id = issue
+                .asset_id
+                .ok_or_else(|| anyhow::anyhow!("asset is required"))?;
+
+            // Either an explicit target position or a signed delta (default: B3 quantity)
+            let adjustment = if let Some(target) = get_decimal_field(resolution, "target_quantity")?
+            {
+                target - held_quantity_at(conn, asset_id, trade_date)?
+            } else {
+                get_decimal_field(resolution, "quantity")?
+                    .or(issue.quantity)
+                    .ok_or_else(|| anyhow::anyhow!("quantity is required"))?
+            };
+
+            // The shares either came from a trade the imports missed, which
+            // has a cost, or from a real split, which does not: never guess
+            let kind = get_string_field(resolution, "kind");
+            let note = format!("B3 Atualização (inconsistency {})", issue.id.unwrap_or(0));
+            let action = match kind.as_deref() {
+                _ if adjustment == Decimal::ZERO => "ACKNOWLEDGE",
+                Some("trade") => {
+                    let quantity = adjustment.abs();
+                    let fees = get_decimal_field(resolution, "fees")?.unwrap_or(Decimal::ZERO);
+                    let (price, total_cost) = match (
+                        get_decimal_field(resolution, "price_per_unit")?,
+                        get_decimal_field(resolution, "total_cost")?,
+                    ) {
+                        (Some(price), total) => (price, total.unwrap_or(price * quantity + fees)),
+                        (None, Some(total)) => ((total - fees) / quantity, total),
+                        (None, None) => {
+                            return Err(anyhow::anyhow!(
+                                "price_per_unit or total_cost is required for a missing trade"
+                            ))
+                        }
+                    };
+                    let tx = db::Transaction {
+                        id: None,
+                        asset_id,
+                        transaction_type: if adjustment > Decimal::ZERO {
+                            db::TransactionType::Buy
+                        } else {
+                            db::TransactionType::Sell
+                        },
+                        trade_date,
+                        settlement_date: Some(trade_date),
+                        quantity,
+                        price_per_unit: price,
+                        total_cost,
+                        fees,
+                        is_day_trade: false,
+                        quota_issuance_date: None,
+                        notes: Some(format!("Missing trade from {}", note)),
+                        source: "INCONSISTENCY".to_string(),
+                        created_at: chrono::Utc::now(),
+                    };
+                    db::insert_transaction(conn, &tx)?;
+                    "ADD_TX"
+                }
+                Some("split") => {
+                    let action = db::CorporateAction {
+                        id: None,
+                        asset_id,
+                        action_type: if adjustment > Decimal::ZERO {
+                            db::CorporateActionType::Split
+                        } else {
+                            db::CorporateActionType::ReverseSplit
+                        },
+                        event_date: trade_date,
+                        ex_date: trade_date,
+                        quantity_adjustment: adjustment,
+                        source: "INCONSISTENCY".to_string(),
+                        notes: Some(format!("Split confirmed from {}", note)),
+                        created_at: chrono::Utc::now(),
+                    };
+                    db::insert_corporate_action(conn, &action)?;
+                    "ADJUST_POSITION"
+                }
+                other => {
+                    return Err(anyhow::anyhow!(
+                        "kind is required{}: 'trade' for a buy or sell missing from the imports \
+                         (with price_per_unit or total_cost), 'split' for a real split or \
+                         reverse split (shares at no cost)",
+                        other.map(|k| format!(", not '{}'", k)).unwrap_or_default()
+                    ))
+                }
+            };
+
+            let mut resolution = resolution.clone();
+            resolution.insert(
+                "quantity_adjustment".to_string(),
+                Value::String(adjustment.to_string()),
+            );
+            db::resolve_inconsistency(
+                conn,
+                issue.id.unwrap_or(0),
+                Some(action),
+                Some(&Value::Object(resolution).to_string()),
+            )?;
+            Ok(())
+        }
+        db::InconsistencyType::UnmatchedCashCredit => {
+            let credit_id = issue
+                .source_ref
+                .as_deref()
+                .and_then(|r| r.strip_prefix("cash_credit:"))
+                .and_then(|id| id.parse::<i64>().ok())
+                .ok_or_else(|| anyhow::anyhow!("cash credit reference is missing"))?;
+            let event_id =
+                reports::income_reconciliation::record_credit_as_income(conn, credit_id)?;
+            let mut resolution = resolution.clone();
+            resolution.insert("income_event_id".to_string(), Value::from(event_id));
+            db::resolve_inconsistency(
+                conn,
+                issue.id.unwrap_or(0),
+                Some("ADD_INCOME"),
+                Some(&Value::Object(resolution).to_string()),
+            )?;
+            Ok(())
+        }
+        db::InconsistencyType::DuplicateTransaction => {
+            let (first, second) = issue
+                .source_ref
+                .as_deref()
+                .and_then(crate::importers::dedupe::pair_ids)
+                .ok_or_else(|| anyhow::anyhow!("transaction pair reference is missing"))?;
+            // Keep the row recorded first unless told otherwise
+            let (keep, drop) = match get_decimal_field(resolution, "keep")? {
+                None => (first, second),
+                Some(keep) if keep == Decimal::from(first) => (first, second),
+                Some(keep) if keep == Decimal::from(second) => (second, first),
+                Some(keep) => {
+                    return Err(anyhow::anyhow!(
+                        "keep must be transaction {} or {}, not {}",
+                        first,
+                        second,
+                        keep
+                    ))
+                }
+            };
+            crate::importers::dedupe::merge(conn, keep, drop)?;
+            let mut resolution = resolution.clone();
+            resolution.insert("kept".to_string(), Value::from(keep));
+            resolution.insert("deleted".to_string(), Value::from(drop));
+            db::resolve_inconsistency(
+                conn,
+                issue.id.unwrap_or(0),
+                Some("MERGE"),
+                Some(&Value::Object(resolution).to_string()),
+            )?;
+            Ok(())
+        }
+        db::InconsistencyType::InvalidQuantity => {
+            let Some(tx) = quantity_issue_transaction(conn, issue)? else {
+                db::resolve_inconsistency(conn, issue.id.unwrap_or(0), Some("GONE"), None)?;
+                return Ok(());
+            };
+            let quantity = get_decimal_field(resolution, "quantity")?
+                .ok_or_else(|| anyhow::anyhow!("quantity is required"))?;
+            let price =
+                get_decimal_field(resolution, "price_per_unit")?.unwrap_or(tx.price_per_unit);
+            let total_cost = get_decimal_field(resolution, "total_cost")?.unwrap_or(tx.total_cost);
+            conn.execute(
+                "UPDATE transactions SET quantity = ?2, price_per_unit = ?3, total_cost = ?4
+                 WHERE id = ?1",
+                rusqlite::params![
+                    tx.id,
+                    quantity.to_string(),
+                    price.to_string(),
+                    total_cost.to_string()
+                ],
+            )?;
+            db::resolve_inconsistency(
+                conn,
+                issue.id.unwrap_or(0),
+                Some("UPDATE_TX"),
+                Some(&Value::Object(resolution.clone()).to_string()),
+            )?;
+            Ok(())
+        }
+        db::InconsistencyType::CorporateActionConflict => {
+            let mut resolution = resolution.clone();
+            // Keep what is recorded unless told to take B3's version
+            let use_b3 = get_string_field(&resolution, "use")
+                .map(|u| u.eq_ignore_ascii_case("b3"))
+                .unwrap_or(false);
+            let action = if use_b3 {
+                let id = crate::corporate_actions::b3_events::replace_with_b3(conn, issue)?;
+                resolution.insert("action_id".to_string(), Value::from(id));
+                "USE_B3"
+            } else {
+                "KEEP"
+            };
+            db::resolve_inconsistency(
+                conn,
+                issue.id.unwrap_or(0),
+                Some(action),
+                Some(&Value::Object(resolution).to_string()),
+            )?;
+            Ok(())
+        }
+        db::InconsistencyType::TickerRename => {
+            let date = get_string_field(resolution, "date")
+                .map(|d| {
+                    chrono::NaiveDate::parse_from_str(&d, "%Y-%m-%d")
+                        .map_err(|_| anyhow::anyhow!("Invalid date: {} (use YYYY-MM-DD)", d))
+                })
+                .transpose()?;
+            let to = get_string_field(resolution, "to");
+            let rename_id = crate::tickers::renames::accept(conn, issue, to.as_deref(), date)?;
+            let mut resolution = resolution.clone();
+            resolution.insert("rename_id".to_string(), Value::from(rename_id));
+            db::resolve_inconsistency(
+                conn,
+                issue.id.unwrap_or(0),
+                Some("RENAME"),
+                Some(&Value::Object(resolution).to_string()),
+            )?;
+            Ok(())
+        }
+        // Cleared by importing the statement that credits it, or ignored
+        db::InconsistencyType::UncreditedIncome => Err(anyhow::anyhow!(
+            "An uncredited income event is resolved by importing the statement that pays it; \
+             otherwise ignore it or delete the event"
+        )),
         db::InconsistencyType::InvalidTicker | db::InconsistencyType::InvalidDate => Err(
             anyhow::anyhow!("Resolution for this inconsistency type is not implemented yet"),
         ),
     }
 }
+
+/// Transaction an INVALID_QUANTITY issue points to, None once deleted
+fn quantity_issue_transaction(
+    conn: &rusqlite::Connection,
+    issue: &db::Inconsistency,
+) -> Result<Option<db::Transaction>> {
+    let id = issue
+        .source_ref
+        .as_deref()
+        .and_then(|r| r.strip_prefix("transaction:"))
+        .and_then(|id| id.parse::<i64>().ok())
+        .ok_or_else(|| anyhow::anyhow!("transaction reference is missing"))?;
+    db::get_transaction(conn, id)
+}
+
+/// Quantity held for an asset at the end of `date`
+fn held_quantity_at(
+    conn: &rusqlite::Connection,
+    asset_id: i64,
+    date: chrono::NaiveDate,
+) -> Result<Decimal> {
+    let report = reports::calculate_portfolio_at_date(conn, date, None)?;
+    Ok(report
+        .positions
+        .iter()
+        .find(|p| p.asset.id == Some(asset_id))
+        .map(|p| p.quantity)
+        .unwrap_or(Decimal::ZERO))
+}
+
+fn prompt_unmatched_cash_credit(issue: &db::Inconsistency) -> Result<Map<String, Value>> {
+    println!(
+        "\nResolving inconsistency #{}: UnmatchedCashCredit",
+        issue.id.unwrap_or(0)
+    );
+    println!("  Ticker: {}", issue.ticker.as_deref().unwrap_or("-"));
+    if let Some(date) = issue.trade_date {
+        println!("  Credited on: {}", date);
+    }
+    let context: Value = issue
+        .context_json
+        .as_deref()
+        .and_then(|c| serde_json::from_str(c).ok())
+        .unwrap_or(Value::Null);
+    if let Some(amount) = context["credited"].as_str() {
+        println!(
+            "  Amount: {} ({})",
+            amount,
+            context["event_type"].as_str().unwrap_or("-")
+        );
+    }
+    println!();
+
+    if !prompt_confirm("Record it as an income event?")? {
+        return Err(anyhow::anyhow!("Resolution cancelled"));
+    }
+    Ok(Map::new())
+}
+
+fn prompt_duplicate_transaction(issue: &db::Inconsistency) -> Result<Map<String, Value>> {
+    println!(
+        "\nResolving inconsistency #{}: DuplicateTransaction",
+        issue.id.unwrap_or(0)
+    );
+    let context: Value = issue
+        .context_json
+        .as_deref()
+        .and_then(|c| serde_json::from_str(c).ok())
+        .unwrap_or(Value::Null);
+    for (label, row) in [("Keep", &context["keep"]), ("Delete", &context["drop"])] {
+        println!(
+            "  {:<6} #{} {} {} {} {} x {} (fees {}) from {}",
+            label,
+            row["id"],
+            row["trade_date"].as_str().unwrap_or("-"),
+            row["transaction_type"].as_str().unwrap_or("-"),
+            row["ticker"].as_str().unwrap_or("-"),
+            row["quantity"].as_str().unwrap_or("-"),
+            row["price_per_unit"].as_str().unwrap_or("-"),
+            row["fees"].as_str().unwrap_or("-"),
+            row["source"].as_str().unwrap_or("-")
+        );
+    }
+    println!();
+
+    if !prompt_confirm("Merge them into one transaction?")? {
+        return Err(anyhow::anyhow!("Resolution cancelled"));
+    }
+    Ok(Map::new())
+}
+
+fn prompt_corporate_action_conflict(issue: &db::Inconsistency) -> Result<Map<String, Value>> {
+    println!(
+        "\nResolving inconsistency #{}: CorporateActionConflict",
+        issue.id.unwrap_or(0)
+    );
+    let context: Value = issue
+        .context_json
+        .as_deref()
+        .and_then(|c| serde_json::from_str(c).ok())
+        .unwrap_or(Value::Null);
+    if let Some(notes) = context["notes"].as_str() {
+        println!("  {}", notes);
+    }
+    println!(
+        "  {:<8} {} {} {}",
+        "B3",
+        context["b3"]["ex_date"].as_str().unwrap_or("-"),
+        context["b3"]["action_type"].as_str().unwrap_or("-"),
+        context["quantity_adjustment"].as_str().unwrap_or("-")
+    );
+    for row in context["existing"].as_array().into_iter().flatten() {
+        println!(
+            "  {:<8} {} {} {} (action #{})",
+            row["source"].as_str().unwrap_or("-"),
+            row["ex_date"].as_str().unwrap_or("-"),
+            row["action_type"].as_str().unwrap_or("-"),
+            row["quantity_adjustment"].as_str().unwrap_or("-"),
+            row["id"]
+        );
+    }
+    println!();
+
+    let mut map = Map::new();
+    let choice = if prompt_confirm("Replace the recorded action(s) with B3's?")? {
+        "b3"
+    } else {
+        "existing"
+    };
+    map.insert("use".to_string(), Value::String(choice.to_string()));
+    Ok(map)
+}
+
+fn prompt_ticker_rename(issue: &db::Inconsistency) -> Result<Map<String, Value>> {
+    println!(
+        "\nResolving inconsistency #{}: TickerRename",
+        issue.id.unwrap_or(0)
+    );
+    let context: Value = issue
+        .context_json
+        .as_deref()
+        .and_then(|c| serde_json::from_str(c).ok())
+        .unwrap_or(Value::Null);
+    if let Some(notes) = context["notes"].as_str() {
+        println!("  {}", notes);
+    }
+    let rename = &context["rename"];
+    println!(
+        "  {} ({}) -> {} ({})",
+        rename["from"].as_str().unwrap_or("-"),
+        rename["from_name"].as_str().unwrap_or("-"),
+        rename["to"].as_str().unwrap_or("-"),
+        rename["to_name"].as_str().unwrap_or("-")
+    );
+    println!();
+
+    if !prompt_confirm("Record the rename?")? {
+        return Err(anyhow::anyhow!("Resolution cancelled"));
+    }
+    let suggested = context["effective_date"]
+        .as_str()
+        .and_then(|d| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        .or(issue.trade_date);
+    let mut map = Map::new();
+    if let Some(date) = prompt_date("Effective date", suggested)? {
+        map.insert("date".to_string(), Value::String(date.to_string()));
+    }
+    Ok(map)
+}
+
+fn prompt_invalid_quantity(
+    conn: &rusqlite::Connection,
+    issue: &db::Inconsistency,
+) -> Result<Map<String, Value>> {
+    println!(
+        "\nResolving inconsistency #{}: InvalidQuantity",
+        issue.id.unwrap_or(0)
+    );
+    let tx = quantity_issue_transaction(conn, issue)?
+        .ok_or_else(|| anyhow::anyhow!("the transaction was deleted; ignore this issue"))?;
+    let context: Value = issue
+        .context_json
+        .as_deref()
+        .and_then(|c| serde_json::from_str(c).ok())
+        .unwrap_or(Value::Null);
+    if let Some(notes) = context["notes"].as_str() {
+        println!("  {}", notes);
+    }
+    println!(
+        "  {} {} x {} = {} on {} ({})",
+        tx.transaction_type.as_str(),
+        tx.quantity,
+        tx.price_per_unit,
+        tx.total_cost,
+        tx.trade_date,
+        tx.source
+    );
+    println!();
+
+    let suggested = context["suggested_quantity"]
+        .as_str()
+        .and_then(|q| Decimal::from_str(q).ok());
+    let quantity = prompt_decimal("Quantity", suggested)?
+        .ok_or_else(|| anyhow::anyhow!("quantity is required"))?;
+    let price =
+        prompt_decimal("Price per unit", Some(tx.price_per_unit))?.unwrap_or(tx.price_per_unit);
+    let total_cost = prompt_decimal("Total", Some(tx.total_cost))?.unwrap_or(tx.total_cost);
+
+    let mut map = Map::new();
+    map.insert("quantity".to_string(), Value::String(quantity.to_string()));
+    map.insert(
+        "price_per_unit".to_string(),
+        Value::String(price.to_string()),
+    );
+    map.insert(
+        "total_cost".to_string(),
+        Value::String(total_cost.to_string()),
+    );
+    Ok(map)
+}
+
+fn prompt_position_adjustment(
+    conn: &rusqlite::Connection,
+    issue: &db::Inconsistency,
+) -> Result<Map<String, Value>> {
+    println!(
+        "\nResolving inconsistency #{}: PositionAdjustment",
+        issue.id.unwrap_or(0)
+    );
+    println!("  Ticker: {}", issue.ticker.as_deref().unwrap_or("-"));
+    let date = issue
+        .trade_date
+        .ok_or_else(|| anyhow::anyhow!("trade_date is required"))?;
+    println!("  Date: {}", date);
+    println!(
+        "  B3 quantity (Atualização): {}",
+        issue
+            .quantity
+            .map(|q| q.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    );
+    if let Some(asset_id) = issue.asset_id {
+        println!(
+            "  Held quantity on that date: {}",
+            held_quantity_at(conn, asset_id, date)?
+        );
+    }
+    println!();
+    println!("Enter the quantity change to apply (0 to acknowledge without changes).");
+
+    let quantity = prompt_decimal("Quantity adjustment", issue.quantity)?
+        .ok_or_else(|| anyhow::anyhow!("quantity is required"))?;
+    let mut map = Map::new();
+    map.insert("quantity".to_string(), Value::String(quantity.to_string()));
+    if quantity.is_zero() {
+        return Ok(map);
+    }
+
+    println!();
+    println!("Where did the change come from?");
+    println!("  1) a buy or sell missing from the imports (recorded with its price)");
+    println!("  2) a split or reverse split (shares at no cost)");
+    let kind = loop {
+        let input = prompt_line("Choice [1/2]: ")?;
+        check_skip_quit(&input)?;
+        match input.as_str() {
+            "1" => break "trade",
+            "2" => break "split",
+            _ => println!("Enter 1 or 2."),
+        }
+    };
+
+    println!();
+    if kind == "trade" {
+        let price = prompt_decimal("Price per unit", None)?
+            .ok_or_else(|| anyhow::anyhow!("price is required"))?;
+        println!(
+            "Recording a {} of {} {} at {} on {}",
+            if quantity > Decimal::ZERO {
+                "buy"
+            } else {
+                "sell"
+            },
+            quantity.abs(),
+            issue.ticker.as_deref().unwrap_or("?"),
+            price,
+            date
+        );
+        map.insert(
+            "price_per_unit".to_string(),
+            Value::String(price.to_string()),
+        );
+    } else {
+        println!(
+            "Adjusting {} by {} shares on {} (cost basis unchanged)",
+            issue.ticker.as_deref().unwrap_or("?"),
+            quantity,
+            date
+        );
+    }
+
+    if !prompt_confirm("Confirm?")? {
+        return Err(anyhow::anyhow!("Resolution cancelled"));
+    }
+    map.insert("kind".to_string(), Value::String(kind.to_string()));
+    Ok(map)
+}
@@ -1,17 +1,50 @@
 use anyhow::Result;
+use colored::Colorize;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
 
-pub async fn dispatch_inspect(file_path: &str, full: bool, column: Option<usize>) -> Result<()> {
-    use anyhow::Context;
-    use calamine::{open_workbook, Data, Reader, Xlsx};
-    use colored::Colorize;
-    use std::collections::HashMap;
+use crate::importers::inspect::{self, CsvInspection};
 
+/// Rows or lines shown when --full is not given
+const PREVIEW_ROWS: usize = 5;
+const PREVIEW_LINES: usize = 40;
+
+pub async fn dispatch_inspect(
+    file_path: &str,
+    full: bool,
+    column: Option<usize>,
+    pages: Option<&str>,
+) -> Result<()> {
     println!(
         "{} Inspecting file: {}\n",
         "📊".cyan().bold(),
         file_path.green()
     );
 
+    let extension = std::path::Path::new(file_path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+
+    if pages.is_some() && extension != "pdf" {
+        return Err(anyhow::anyhow!("--pages only applies to PDF files"));
+    }
+
+    match extension.as_str() {
+        "csv" | "txt" => inspect_csv(file_path, full, column),
+        "pdf" => inspect_pdf(file_path, full, pages),
+        _ => inspect_excel(file_path, full, column),
+    }
+}
+
+fn inspect_excel(file_path: &str, full: bool, column: Option<usize>) -> Result<()> {
+    use anyhow::Context;
+    use calamine::{open_workbook, Data, Reader, Xlsx};
+    use std::collections::HashMap;
+
     let mut workbook: Xlsx<_> = open_workbook(file_path).context("Failed to open Excel file")?;
 
     let sheet_names = workbook.sheet_names().to_vec();
@@ -86,3 +119,171 @@ pub async fn dispatch_inspect(file_path: &str, full: bool, column: Option<usize>
 
     Ok(())
 }
+
+fn inspect_csv(file_path: &str, full: bool, column: Option<usize>) -> Result<()> {
+    let inspection = inspect::inspect_csv(file_path)?;
+
+    println!("  Encoding:  {}", inspection.encoding.yellow());
+    println!(
+        "  Delimiter: {}",
+        inspect::delimiter_name(inspection.delimiter).yellow()
+    );
+    println!("  Rows:      {} (plus header)", inspection.rows.len());
+    println!("  Columns:   {}", inspection.headers.len());
+    if inspection.ragged_rows > 0 {
+        println!(
+            "  {} {} row(s) have a different number of fields than the header",
+            "⚠️".yellow(),
+            inspection.ragged_rows
+        );
+    }
+    println!();
+
+    print_column_stats(&inspection);
+
+    if let Some(col_idx) = column {
+        let stats = inspection.columns.get(col_idx).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Column {} does not exist (file has {} columns)",
+                col_idx,
+                inspection.columns.len()
+            )
+        })?;
+        println!(
+            "\n{} Column {} ({}): {} distinct value(s)",
+            "📌".cyan().bold(),
+            col_idx,
+            stats.name.yellow(),
+            stats.distinct
+        );
+        for (value, count) in &stats.top_values {
+            println!("  {:>6}  {}", count, value);
+        }
+        if stats.distinct > stats.top_values.len() {
+            println!("  ... and {} more", stats.distinct - stats.top_values.len());
+        }
+    }
+
+    let limit = if full {
+        inspection.rows.len()
+    } else {
+        PREVIEW_ROWS
+    };
+    println!("\n{} Rows:", "📄".cyan().bold());
+    for (row_idx, row) in inspection.rows.iter().take(limit).enumerate() {
+        // +2: 1-based and the header is row 1, matching importer warnings
+        println!("  Row {}: {}", row_idx + 2, row.join(" | "));
+    }
+
+    if !full {
+        println!();
+        println!("{}", "Tip: Use --full to see all rows".blue());
+        println!(
+            "{}",
+            "Tip: Use --column <n> to list the values of a column".blue()
+        );
+    }
+
+    Ok(())
+}
+
+fn print_column_stats(inspection: &CsvInspection) {
+    #[derive(Tabled)]
+    struct ColumnRow {
+        #[tabled(rename = "#")]
+        index: usize,
+        #[tabled(rename = "Header")]
+        name: String,
+        #[tabled(rename = "Kind")]
+        kind: &'static str,
+        #[tabled(rename = "Filled")]
+        filled: usize,
+        #[tabled(rename = "Empty")]
+        empty: usize,
+        #[tabled(rename = "Distinct")]
+        distinct: usize,
+        #[tabled(rename = "Sample")]
+        sample: String,
+    }
+
+    let rows: Vec<ColumnRow> = inspection
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(index, c)| ColumnRow {
+            index,
+            name: c.name.clone(),
+            kind: c.kind.as_str(),
+            filled: c.filled,
+            empty: c.empty,
+            distinct: c.distinct,
+            sample: c
+                .top_values
+                .first()
+                .map(|(v, _)| v.clone())
+                .unwrap_or_default(),
+        })
+        .collect();
+
+    let table = Table::new(rows)
+        .with(Style::rounded())
+        .with(Modify::new(Columns::new(3..6)).with(Alignment::right()))
+        .to_string();
+    println!("{}", table);
+}
+
+fn inspect_pdf(file_path: &str, full: bool, pages: Option<&str>) -> Result<()> {
+    let range = pages.map(inspect::parse_page_range).transpose()?;
+    let text = inspect::extract_pdf_text(file_path, range)?;
+
+    println!("  Extractor: {}", text.extractor.yellow());
+    println!("  Pages:     {}", text.pages.len());
+    if text.extractor != "pdftotext" {
+        println!(
+            "  {}",
+            "Install poppler-utils (pdftotext) for layout-preserving output".dimmed()
+        );
+    }
+
+    let mut truncated = false;
+    for (page, page_text) in &text.pages {
+        let lines: Vec<&str> = page_text.lines().collect();
+        println!(
+            "\n{} Page {} ({} lines)",
+            "📌".cyan().bold(),
+            page,
+            lines.len()
+        );
+        if lines.iter().all(|l| l.trim().is_empty()) {
+            println!(
+                "  {}",
+                "No text found (scanned image? imports need a text PDF)".yellow()
+            );
+            continue;
+        }
+        let limit = if full { lines.len() } else { PREVIEW_LINES };
+        for line in lines.iter().take(limit) {
+            println!("  {}", line);
+        }
+        if lines.len() > limit {
+            truncated = true;
+            println!(
+                "  {}",
+                format!("... {} more lines", lines.len() - limit).dimmed()
+            );
+        }
+    }
+
+    if truncated {
+        println!();
+        println!("{}", "Tip: Use --full to see the whole text".blue());
+    }
+    if pages.is_none() {
+        println!(
+            "{}",
+            "Tip: Use --pages N or --pages N-M to select pages".blue()
+        );
+    }
+
+    Ok(())
+}
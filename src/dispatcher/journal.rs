@@ -0,0 +1,250 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use tabled::Tabled;
+
+use crate::db;
+use crate::reports::journal::entry_outcome;
+use crate::utils::format_currency;
+
+pub async fn dispatch_journal(
+    action: &crate::cli::JournalCommands,
+    json_output: bool,
+) -> Result<()> {
+    match action {
+        crate::cli::JournalCommands::Add {
+            ticker,
+            thesis,
+            date,
+            target,
+        } => add_entry(
+            ticker,
+            thesis,
+            date.as_deref(),
+            target.as_deref(),
+            json_output,
+        ),
+        crate::cli::JournalCommands::List { ticker, table } => {
+            list_entries(ticker.as_deref(), table, json_output)
+        }
+        crate::cli::JournalCommands::Link {
+            entry_id,
+            transaction_id,
+        } => link_transaction(*entry_id, *transaction_id, json_output),
+    }
+}
+
+fn open_conn() -> Result<rusqlite::Connection> {
+    db::init_database(None)?;
+    db::open_db(None)
+}
+
+fn add_entry(
+    ticker: &str,
+    thesis: &str,
+    date_str: Option<&str>,
+    target_str: Option<&str>,
+    json_output: bool,
+) -> Result<()> {
+    let entry_date = match date_str {
+        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .context("Invalid date format. Use YYYY-MM-DD")?,
+        None => chrono::Local::now().date_naive(),
+    };
+    let target_price = target_str
+        .map(|s| Decimal::from_str(s).context("Invalid target price. Must be a decimal number"))
+        .transpose()?;
+    if thesis.trim().is_empty() {
+        return Err(anyhow::anyhow!("Thesis cannot be empty"));
+    }
+
+    let conn = open_conn()?;
+    let asset_id = db::upsert_asset(&conn, ticker, &db::AssetType::Unknown, None)?;
+    let entry = db::JournalEntry {
+        id: None,
+        asset_id,
+        entry_date,
+        thesis: thesis.trim().to_string(),
+        target_price,
+        created_at: chrono::Utc::now(),
+    };
+    let entry_id = db::insert_journal_entry(&conn, &entry)?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "id": entry_id,
+            "ticker": ticker.to_uppercase(),
+            "entry_date": entry_date.to_string(),
+            "thesis": entry.thesis,
+            "target_price": target_price.map(|p| p.to_string()),
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    println!("\n{} Journal entry added!", "✓".green().bold());
+    println!("  Entry ID:  {}", entry_id);
+    println!("  Ticker:    {}", ticker.to_uppercase().cyan().bold());
+    println!("  Date:      {}", entry_date.format("%Y-%m-%d"));
+    println!("  Thesis:    {}", entry.thesis);
+    if let Some(target) = target_price {
+        println!("  Target:    {}", format_currency(target));
+    }
+    println!(
+        "\n  Link trades with: interest journal link {} <transaction_id>\n",
+        entry_id
+    );
+
+    Ok(())
+}
+
+fn list_entries(
+    ticker: Option<&str>,
+    table: &crate::cli::formatters::TableArgs,
+    json_output: bool,
+) -> Result<()> {
+    let conn = open_conn()?;
+    let page = table.paginate(db::list_journal_entries(&conn, ticker)?);
+
+    let mut rows = Vec::with_capacity(page.rows.len());
+    for (entry, entry_ticker) in page.rows.iter().cloned() {
+        let outcome = entry_outcome(&conn, &entry)?;
+        rows.push((entry, entry_ticker, outcome));
+    }
+
+    if json_output {
+        let payload: Vec<_> = rows
+            .iter()
+            .map(|(entry, entry_ticker, outcome)| {
+                serde_json::json!({
+                    "id": entry.id,
+                    "ticker": entry_ticker,
+                    "entry_date": entry.entry_date.to_string(),
+                    "thesis": entry.thesis,
+                    "target_price": entry.target_price.map(|p| p.to_string()),
+                    "outcome": outcome,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    if page.total == 0 {
+        println!("{} No journal entries found", "ℹ".blue().bold());
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct JournalRow {
+        #[tabled(rename = "ID")]
+        id: String,
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Thesis")]
+        thesis: String,
+        #[tabled(rename = "Trades")]
+        trades: String,
+        #[tabled(rename = "Realized P&L")]
+        realized: String,
+        #[tabled(rename = "Target")]
+        target: String,
+    }
+
+    let table_rows: Vec<_> = rows
+        .into_iter()
+        .map(|(entry, entry_ticker, outcome)| JournalRow {
+            id: entry.id.unwrap_or(0).to_string(),
+            date: entry.entry_date.format("%Y-%m-%d").to_string(),
+            ticker: entry_ticker,
+            thesis: entry.thesis,
+            trades: outcome.linked_transactions.to_string(),
+            realized: if outcome.sold_quantity > Decimal::ZERO {
+                match outcome.realized_pl_pct {
+                    Some(pct) => format!("{} ({}%)", format_currency(outcome.realized_pl), pct),
+                    None => format_currency(outcome.realized_pl),
+                }
+            } else {
+                "-".to_string()
+            },
+            target: match (entry.target_price, outcome.target_reached) {
+                (Some(target), Some(true)) => format!("{} ✓", format_currency(target)),
+                (Some(target), Some(false)) => format!("{} ✗", format_currency(target)),
+                (Some(target), None) => format_currency(target),
+                (None, _) => "-".to_string(),
+            },
+        })
+        .collect();
+
+    let mut out = crate::cli::formatters::render_table(
+        &table_rows,
+        &table.columns,
+        &["Trades", "Realized P&L", "Target"],
+    )?;
+    out.push('\n');
+    if let Some(footer) = page.footer() {
+        out.push_str(&format!("{}\n", footer.dimmed()));
+    }
+    crate::cli::formatters::print_paged(&out, table.no_pager);
+
+    Ok(())
+}
+
+fn link_transaction(entry_id: i64, transaction_id: i64, json_output: bool) -> Result<()> {
+    let conn = open_conn()?;
+    let entry = db::get_journal_entry(&conn, entry_id)?
+        .ok_or_else(|| anyhow::anyhow!("Journal entry {} not found", entry_id))?;
+    let tx = db::get_transaction(&conn, transaction_id)?
+        .ok_or_else(|| anyhow::anyhow!("Transaction {} not found", transaction_id))?;
+    if tx.asset_id != entry.asset_id {
+        return Err(anyhow::anyhow!(
+            "Transaction {} is for a different asset than journal entry {}",
+            transaction_id,
+            entry_id
+        ));
+    }
+
+    let linked = db::link_journal_transaction(&conn, entry_id, transaction_id)?;
+    let outcome = entry_outcome(&conn, &entry)?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "entry_id": entry_id,
+            "transaction_id": transaction_id,
+            "linked": linked,
+            "outcome": outcome,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    if linked {
+        println!(
+            "{} Linked {} {} on {} to journal entry {}",
+            "✓".green().bold(),
+            tx.transaction_type.as_str(),
+            tx.quantity,
+            tx.trade_date.format("%Y-%m-%d"),
+            entry_id
+        );
+    } else {
+        println!(
+            "{} Transaction {} is already linked to journal entry {}",
+            "ℹ".blue().bold(),
+            transaction_id,
+            entry_id
+        );
+    }
+    if outcome.sold_quantity > Decimal::ZERO {
+        println!(
+            "  Realized P&L: {}",
+            format_currency(outcome.realized_pl).cyan().bold()
+        );
+    }
+
+    Ok(())
+}
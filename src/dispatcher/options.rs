@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::cli::OptionCommands;
+use crate::db;
+use crate::options::{self, ExerciseSummary};
+use crate::utils::format_currency;
+
+pub fn dispatch_options(action: &OptionCommands, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    match action {
+        OptionCommands::Show => show_options(&conn, json_output),
+        OptionCommands::Process => {
+            let summary = options::process_exercises(&conn)?;
+            if json_output {
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                print_exercise_summary(&summary);
+            }
+            Ok(())
+        }
+        OptionCommands::Set {
+            ticker,
+            strike,
+            underlying,
+        } => {
+            if strike.is_none() && underlying.is_none() {
+                anyhow::bail!("Nothing to set: pass --strike and/or --underlying");
+            }
+            let strike = strike
+                .as_deref()
+                .map(Decimal::from_str)
+                .transpose()
+                .context("Invalid strike. Must be a decimal number")?;
+            options::set_option_contract(&conn, ticker, underlying.as_deref(), strike)?;
+
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&serde_json::json!({
+                        "success": true,
+                        "ticker": ticker.to_uppercase(),
+                    }))?
+                );
+            } else {
+                println!(
+                    "{} Updated option {}",
+                    "✓".green().bold(),
+                    ticker.to_uppercase()
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+fn print_exercise_summary(summary: &ExerciseSummary) {
+    if summary.exercised == 0 && summary.written_skipped == 0 && summary.unmatched == 0 {
+        println!("{} No option exercises to process", "ℹ".blue().bold());
+        return;
+    }
+    println!(
+        "{} Option exercises processed: {}",
+        "✓".green().bold(),
+        summary.exercised
+    );
+    if summary.written_skipped > 0 {
+        println!(
+            "  {} Skipped {} exercise(s) of written options (short positions are not supported)",
+            "⚠".yellow().bold(),
+            summary.written_skipped
+        );
+    }
+    if summary.unmatched > 0 {
+        println!(
+            "  {} {} exercise(s) had no bought option position to close",
+            "⚠".yellow().bold(),
+            summary.unmatched
+        );
+    }
+}
+
+fn show_options(conn: &rusqlite::Connection, json_output: bool) -> Result<()> {
+    let positions = options::open_positions(conn)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&positions)?);
+        return Ok(());
+    }
+
+    if positions.is_empty() {
+        println!("{} No open option positions", "ℹ".blue().bold());
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct OptionRow {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        kind: String,
+        #[tabled(rename = "Underlying")]
+        underlying: String,
+        #[tabled(rename = "Expiry")]
+        expiry: String,
+        #[tabled(rename = "Strike")]
+        strike: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Avg Premium")]
+        average_premium: String,
+        #[tabled(rename = "Premium Paid")]
+        total_premium: String,
+    }
+
+    let rows: Vec<OptionRow> = positions
+        .iter()
+        .map(|p| OptionRow {
+            ticker: p.ticker.clone(),
+            kind: p.kind.label().to_string(),
+            underlying: p.underlying.clone().unwrap_or("-".into()),
+            expiry: p
+                .expiry
+                .map(|d| d.format("%d/%m/%Y").to_string())
+                .unwrap_or("-".into()),
+            strike: p.strike.map(format_currency).unwrap_or("-".into()),
+            quantity: p.quantity.to_string(),
+            average_premium: format_currency(p.average_premium),
+            total_premium: format_currency(p.total_premium),
+        })
+        .collect();
+
+    println!("\n{} Open option positions\n", "🎯".cyan().bold());
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(4..)).with(Alignment::right()))
+    );
+    let total: Decimal = positions.iter().map(|p| p.total_premium).sum();
+    println!("\nTotal premium at risk: {}", format_currency(total).bold());
+    println!(
+        "{}",
+        "Series still held after expiry are closed as a total loss".dimmed()
+    );
+    if positions.iter().any(|p| p.strike.is_none()) {
+        println!("\nRecord missing strikes with: interest options set <TICKER> --strike <PRICE>");
+    }
+    Ok(())
+}
@@ -1,6 +1,7 @@
 //! Performance command dispatcher implementation
 
 use crate::ui::progress::{ProgressEvent, ProgressPrinter};
+use crate::ui::refresh::{self, Panel};
 use crate::utils::{format_currency, format_currency_aligned};
 use crate::{db, reports};
 use anyhow::{anyhow, Result};
@@ -9,7 +10,7 @@ use colored::Colorize;
 use tracing;
 
 /// Parse a period string (MTD, QTD, YTD, 1Y, ALL, YYYY, or from:to)
-fn parse_period_string(period: &str) -> Result<reports::Period> {
+pub(super) fn parse_period_string(period: &str) -> Result<reports::Period> {
     let upper = period.to_uppercase();
     match upper.as_str() {
         "MTD" => Ok(reports::Period::Mtd),
@@ -47,7 +48,21 @@ fn parse_period_string(period: &str) -> Result<reports::Period> {
     }
 }
 
-pub async fn dispatch_performance_show(period_str: &str, json_output: bool) -> Result<()> {
+#[allow(clippy::too_many_arguments)]
+pub async fn dispatch_performance_show(
+    period_str: &str,
+    fx_breakdown: bool,
+    tag: Option<&str>,
+    what_if: bool,
+    twr_valuation: Option<reports::twr::Valuation>,
+    vs: &[String],
+    strict: bool,
+    json_output: bool,
+) -> Result<()> {
+    let benchmark_specs = vs
+        .iter()
+        .map(|s| s.parse::<reports::benchmark::BenchmarkSpec>())
+        .collect::<Result<Vec<_>>>()?;
     db::init_database(None)?;
     let mut conn = db::open_db(None)?;
 
@@ -71,6 +86,9 @@ pub async fn dispatch_performance_show(period_str: &str, json_output: bool) -> R
         .map(|v| v != "0")
         .unwrap_or(false);
 
+    // Private fixed income is valued from PUs accrued locally
+    crate::fixed_income::accrue_all(&conn, period_end.min(chrono::Local::now().date_naive()))?;
+
     // Ensure prices are available for the required date range
     // Filter out blocked assets
     let assets = db::get_assets_with_transactions(&conn)?;
@@ -79,8 +97,19 @@ pub async fn dispatch_performance_show(period_str: &str, json_output: bool) -> R
         // Get the date range for prices
         let earliest = db::get_earliest_transaction_date(&conn)?;
         if let Some(earliest_date) = earliest {
-            // Limit price resolution to the end of the requested period
-            let today = period_end;
+            // Limit price resolution to the end of the requested period. Interactive
+            // mode leaves today's quotes to the background refresh.
+            let today = if refresh::cache_first() {
+                std::cmp::min(
+                    period_end,
+                    chrono::Local::now()
+                        .date_naive()
+                        .pred_opt()
+                        .unwrap_or(period_end),
+                )
+            } else {
+                period_end
+            };
             let price_start = std::cmp::max(earliest_date, period_start);
 
             if !json_output && !skip_price_fetch {
@@ -131,10 +160,81 @@ pub async fn dispatch_performance_show(period_str: &str, json_output: bool) -> R
         }
     }
 
-    let report = reports::calculate_performance(&mut conn, period)?;
+    let tagged_assets = tag
+        .map(|t| db::get_tagged_asset_ids(&conn, t))
+        .transpose()?;
+    let report = reports::calculate_performance(&mut conn, period, tagged_assets.as_ref())?;
+
+    let fx_attribution = if fx_breakdown {
+        if let Err(e) = crate::pricing::fx::ensure_usd_brl(&conn, period_start, period_end).await {
+            tracing::warn!("USD/BRL rate update failed: {}", e);
+        }
+        reports::fx_attribution::bdr_fx_attribution(&conn, &report)?
+    } else {
+        None
+    };
+
+    let twr = twr_valuation
+        .map(|valuation| {
+            reports::twr::snapshot_twr(
+                &mut conn,
+                report.start_date,
+                report.end_date,
+                tagged_assets.as_ref(),
+                valuation,
+            )
+        })
+        .transpose()?;
+
+    let comparisons = if benchmark_specs.is_empty() {
+        None
+    } else {
+        let portfolio_return = twr.as_ref().map_or(report.return_pct(), |t| t.twr);
+        Some(reports::benchmark::compare_with_benchmarks(
+            &conn,
+            &benchmark_specs,
+            report.start_date,
+            report.end_date,
+            portfolio_return,
+        )?)
+    };
+
+    let stale_benchmarks: Vec<&reports::benchmark::BenchmarkComparison> =
+        comparisons.iter().flatten().filter(|c| c.stale).collect();
+    if strict && (!report.stale_prices.is_empty() || !stale_benchmarks.is_empty()) {
+        anyhow::bail!(
+            "Refusing to report performance through {} with stale data:\n{}\nRun `interest prices update` and `interest prices update-benchmarks`, or drop --strict.",
+            report.end_date,
+            stale_lines(&report.stale_prices, &stale_benchmarks).join("\n")
+        );
+    }
+
+    let what_if = if what_if {
+        // The start value is an end-of-day snapshot, so flows settled on
+        // the start date are already part of it
+        let flows: Vec<_> = reports::performance::extract_cash_flows(
+            &conn,
+            report.start_date,
+            report.end_date,
+            tagged_assets.as_ref(),
+        )?
+        .into_iter()
+        .filter(|f| f.date > report.start_date)
+        .collect();
+        Some(reports::benchmark::what_if(
+            &conn,
+            report.start_date,
+            report.end_date,
+            report.start_value,
+            &flows,
+            report.end_value,
+        )?)
+    } else {
+        None
+    };
 
     if json_output {
-        let payload = serde_json::json!({
+        let mut payload = serde_json::json!({
             "start_date": report.start_date,
             "end_date": report.end_date,
             "start_value": report.start_value,
@@ -143,11 +243,52 @@ pub async fn dispatch_performance_show(period_str: &str, json_output: bool) -> R
             "total_return_pct": report.return_pct(),
             "realized_gains": report.realized_gains,
             "unrealized_gains": report.unrealized_gains,
+            "money_weighted_return": report.money_weighted_return,
+            "asset_xirr": report.asset_xirr,
         });
+        if let Some(tag) = tag {
+            payload["tag"] = serde_json::json!(tag.to_lowercase());
+        }
+        payload["stale_prices"] = serde_json::to_value(&report.stale_prices)?;
+        if fx_breakdown {
+            payload["bdr_fx_attribution"] = serde_json::to_value(&fx_attribution)?;
+        }
+        if let Some(ref twr) = twr {
+            payload["twr"] = serde_json::to_value(twr)?;
+        }
+        if let Some(ref comparisons) = comparisons {
+            payload["benchmarks"] = serde_json::to_value(comparisons)?;
+        }
+        if let Some(ref what_if) = what_if {
+            payload["what_if"] = serde_json::to_value(what_if)?;
+        }
         println!("{}", serde_json::to_string_pretty(&payload)?);
     } else {
-        println!("\n{} Performance Report", "📈".cyan().bold());
+        match tag {
+            Some(tag) => println!(
+                "\n{} Performance Report - tag {}",
+                "📈".cyan().bold(),
+                tag.to_lowercase().bold()
+            ),
+            None => println!("\n{} Performance Report", "📈".cyan().bold()),
+        }
         println!("  Period: {} → {}", report.start_date, report.end_date);
+        if !report.stale_prices.is_empty() || !stale_benchmarks.is_empty() {
+            println!(
+                "  {} {}",
+                "⚠".yellow().bold(),
+                format!("Stale data: not every value reaches {}", report.end_date)
+                    .yellow()
+                    .bold()
+            );
+            for line in stale_lines(&report.stale_prices, &stale_benchmarks) {
+                println!("    {}", line.yellow());
+            }
+            println!(
+                "    {}",
+                "Run `interest prices update` and `interest prices update-benchmarks`; --strict refuses stale reports".dimmed()
+            );
+        }
         println!();
         println!(
             "  Start Value:      {}",
@@ -212,6 +353,15 @@ pub async fn dispatch_performance_show(period_str: &str, json_output: bool) -> R
             }
         }
 
+        if let Some(mwr) = report.money_weighted_return {
+            let mwr_str = format!("{:.2}% a year", mwr);
+            if mwr >= rust_decimal::Decimal::ZERO {
+                println!("  Money-Weighted:   {}", mwr_str.green());
+            } else {
+                println!("  Money-Weighted:   {}", mwr_str.red());
+            }
+        }
+
         println!(
             "  Realized Gains:   {}",
             format_currency(report.realized_gains).yellow()
@@ -255,7 +405,7 @@ pub async fn dispatch_performance_show(period_str: &str, json_output: bool) -> R
 
             // Sort by start value (largest positions first)
             let mut breakdown_vec: Vec<_> = report.asset_breakdown.iter().collect();
-            breakdown_vec.sort_by(|a, b| b.1.start_value.cmp(&a.1.start_value));
+            breakdown_vec.sort_by_key(|b| std::cmp::Reverse(b.1.start_value));
 
             for (asset_type, perf) in breakdown_vec {
                 let return_display = if perf.return_pct >= rust_decimal::Decimal::ZERO {
@@ -274,21 +424,398 @@ pub async fn dispatch_performance_show(period_str: &str, json_output: bool) -> R
             }
         }
 
+        let asset_xirr: Vec<_> = report
+            .asset_xirr
+            .iter()
+            .filter_map(|a| a.xirr.map(|x| (a, x)))
+            .collect();
+        if !asset_xirr.is_empty() {
+            println!();
+            println!("  {} Money-Weighted by Asset (XIRR)", "📐".cyan().bold());
+            for (asset, xirr) in asset_xirr {
+                let xirr_str = format!("{:>9.2}% a year", xirr);
+                if xirr >= rust_decimal::Decimal::ZERO {
+                    println!("    {:12} {}", asset.ticker, xirr_str.green());
+                } else {
+                    println!("    {:12} {}", asset.ticker, xirr_str.red());
+                }
+            }
+        }
+
+        if fx_breakdown {
+            println!();
+            print_fx_attribution(fx_attribution.as_ref());
+        }
+
+        if let Some(ref twr) = twr {
+            println!();
+            print_twr(twr);
+        }
+
+        if let Some(ref comparisons) = comparisons {
+            println!();
+            print_benchmarks(comparisons);
+        }
+
+        if let Some(ref what_if) = what_if {
+            println!();
+            print_what_if(what_if, report.end_date);
+        }
+
+        refresh::print_as_of(Panel::Prices, db::get_latest_price_update(&conn)?);
         println!();
     }
 
     Ok(())
 }
 
+/// One line per asset priced or benchmark stored short of the period end
+fn stale_lines(
+    prices: &[reports::performance::StalePrice],
+    benchmarks: &[&reports::benchmark::BenchmarkComparison],
+) -> Vec<String> {
+    let prices = prices.iter().map(|p| match p.price_date {
+        Some(date) => format!("{}: price from {}", p.ticker, date),
+        None => format!("{}: no price, valued at cost", p.ticker),
+    });
+    let benchmarks = benchmarks.iter().map(|b| match b.data_until {
+        Some(date) => format!("{}: data until {}", b.benchmark, date),
+        None => format!("{}: no data", b.benchmark),
+    });
+    prices.chain(benchmarks).collect()
+}
+
+fn print_twr(twr: &reports::twr::TwrReport) {
+    let label = match twr.valuation {
+        reports::twr::Valuation::Daily => "daily",
+        reports::twr::Valuation::Monthly => "month-end",
+    };
+    println!(
+        "  {} Time-Weighted Return ({} valuation, {} points)",
+        "⏱".cyan().bold(),
+        label,
+        twr.points.len()
+    );
+    let pct = |v: rust_decimal::Decimal, suffix: &str| {
+        let s = format!("{:.2}%{}", v, suffix);
+        if v >= rust_decimal::Decimal::ZERO {
+            s.green()
+        } else {
+            s.red()
+        }
+    };
+    println!("    TWR:            {}", pct(twr.twr, ""));
+    if let Some(annualized) = twr.annualized {
+        println!("    Annualized:     {}", pct(annualized, " a year"));
+    }
+}
+
+fn print_benchmarks(comparisons: &[reports::benchmark::BenchmarkComparison]) {
+    println!("  {} Versus Benchmarks", "🏁".cyan().bold());
+    for c in comparisons {
+        let (Some(ret), Some(excess)) = (c.return_pct, c.excess_pct) else {
+            println!(
+                "    {:10} {}",
+                c.benchmark,
+                "no data for the period; run `interest prices update-benchmarks`".dimmed()
+            );
+            continue;
+        };
+        let excess_str = format!("{:+.2} p.p.", excess);
+        let excess_str = if excess >= rust_decimal::Decimal::ZERO {
+            excess_str.green()
+        } else {
+            excess_str.red()
+        };
+        let until = match (c.stale, c.data_until) {
+            (true, Some(date)) => format!("  (data until {})", date).yellow().to_string(),
+            _ => String::new(),
+        };
+        println!(
+            "    {:10} {:>8.2}%  {}{}",
+            c.benchmark, ret, excess_str, until
+        );
+    }
+}
+
+fn print_what_if(results: &[reports::benchmark::WhatIf], end_date: chrono::NaiveDate) {
+    println!("  {} Same Cash Flows in Benchmarks", "🔀".cyan().bold());
+    for r in results {
+        let difference = format_currency(r.difference);
+        let difference = if r.difference >= rust_decimal::Decimal::ZERO {
+            format!("+{}", difference).green()
+        } else {
+            difference.red()
+        };
+        print!(
+            "    {:6} {}  {}",
+            r.benchmark.as_str(),
+            format_currency_aligned(r.end_value, 16).cyan(),
+            difference
+        );
+        if r.data_until < end_date {
+            print!("  {}", format!("(data until {})", r.data_until).dimmed());
+        }
+        println!();
+    }
+
+    let missing: Vec<&str> = db::Benchmark::all()
+        .into_iter()
+        .filter(|b| !results.iter().any(|r| r.benchmark == *b))
+        .map(|b| b.as_str())
+        .collect();
+    if !missing.is_empty() {
+        println!(
+            "    {}",
+            format!(
+                "No {} data for the period start; run `interest prices update-benchmarks`",
+                missing.join("/")
+            )
+            .dimmed()
+        );
+    }
+}
+
+fn print_fx_attribution(attribution: Option<&reports::fx_attribution::FxAttribution>) {
+    println!("  {} BDR Return vs USD/BRL", "💱".cyan().bold());
+    let Some(a) = attribution else {
+        println!(
+            "    {}",
+            "No BDR holdings at period start or USD/BRL rates unavailable".dimmed()
+        );
+        return;
+    };
+
+    let pct = |v: rust_decimal::Decimal| {
+        let s = format!("{:>7.2}%", v);
+        if v >= rust_decimal::Decimal::ZERO {
+            s.green()
+        } else {
+            s.red()
+        }
+    };
+
+    println!(
+        "    USD/BRL:        {:.4} → {:.4}",
+        a.start_rate, a.end_rate
+    );
+    println!("    Return (BRL):   {}", pct(a.brl_return_pct));
+    println!("    Local price:    {}", pct(a.local_effect_pct));
+    println!("    Currency:       {}", pct(a.fx_effect_pct));
+    println!("    Cross effect:   {}", pct(a.cross_effect_pct));
+}
+
 pub async fn dispatch_performance(
     action: &crate::cli::PerformanceCommands,
     json_output: bool,
 ) -> Result<()> {
     match action {
-        crate::cli::PerformanceCommands::Show { period } => {
-            dispatch_performance_show(period, json_output).await
+        crate::cli::PerformanceCommands::Show {
+            period,
+            fx,
+            tag,
+            what_if,
+            method,
+            monthly,
+            vs,
+            strict,
+        } => {
+            if *monthly && method != "twr" {
+                anyhow::bail!("--monthly only applies to --method twr");
+            }
+            let twr_valuation = (method == "twr").then_some(if *monthly {
+                reports::twr::Valuation::Monthly
+            } else {
+                reports::twr::Valuation::Daily
+            });
+            dispatch_performance_show(
+                period,
+                *fx,
+                tag.as_deref(),
+                *what_if,
+                twr_valuation,
+                vs,
+                *strict,
+                json_output,
+            )
+            .await
         }
+        crate::cli::PerformanceCommands::Risk { period } => {
+            dispatch_performance_risk(period, json_output)
+        }
+        crate::cli::PerformanceCommands::History { granularity } => {
+            dispatch_performance_history(granularity, json_output)
+        }
+    }
+}
+
+/// Portfolio value, invested capital and income at each period end
+fn dispatch_performance_history(granularity: &str, json_output: bool) -> Result<()> {
+    use reports::history::Granularity;
+
+    let granularity = match granularity {
+        "yearly" => Granularity::Yearly,
+        _ => Granularity::Monthly,
+    };
+    db::init_database(None)?;
+    let mut conn = db::open_db(None)?;
+    let today = chrono::Local::now().date_naive();
+    let history = reports::history::portfolio_history(&mut conn, granularity, today)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&history)?);
+        return Ok(());
+    }
+
+    if history.points.is_empty() {
+        println!("{} No transactions yet", "ℹ".blue().bold());
+        return Ok(());
     }
+
+    println!("\n{} Portfolio history\n", "📈".cyan().bold());
+    let mut builder = tabled::builder::Builder::default();
+    builder.push_record([
+        "Date",
+        "Market value",
+        "Invested",
+        "Income (total)",
+        "Result",
+    ]);
+    for p in &history.points {
+        let result = p.market_value - p.invested + p.accumulated_income;
+        builder.push_record([
+            match granularity {
+                Granularity::Monthly => p.date.format("%m/%Y").to_string(),
+                Granularity::Yearly => p.date.format("%Y").to_string(),
+            },
+            format_currency(p.market_value),
+            format_currency(p.invested),
+            format_currency(p.accumulated_income),
+            format_currency(result),
+        ]);
+    }
+    {
+        use tabled::settings::{object::Columns, Alignment, Modify, Style};
+        println!(
+            "{}",
+            builder
+                .build()
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+        );
+    }
+    println!(
+        "{}",
+        "Invested is the cost of the positions held; result = value - invested + income. The last row is today; use --json to plot"
+            .dimmed()
+    );
+    println!();
+    Ok(())
+}
+
+/// Risk figures of the portfolio and of each held asset over a period
+fn dispatch_performance_risk(period_str: &str, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let mut conn = db::open_db(None)?;
+
+    let blocked_assets = db::get_blocked_assets(&conn)?;
+    if !blocked_assets.is_empty() {
+        let tickers: Vec<&str> = blocked_assets.iter().map(|(_, t)| t.as_str()).collect();
+        anyhow::bail!(
+            "Refusing to measure risk due to open blocking inconsistencies.\nAssets: {}\nResolve with `inconsistencies resolve`.",
+            tickers.join(", ")
+        );
+    }
+
+    let period = parse_period_string(period_str)?;
+    let (from, to) = reports::performance::get_period_dates(period, Some(&conn))?;
+    let report = reports::risk::risk_report(&mut conn, from, to)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Risk {} to {}\n",
+        "📉".cyan().bold(),
+        from.format("%d/%m/%Y"),
+        to.format("%d/%m/%Y")
+    );
+
+    let pct = |v: Option<rust_decimal::Decimal>| {
+        v.map(|v| format!("{:.2}%", v))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let ratio = |v: Option<rust_decimal::Decimal>| {
+        v.map(|v| format!("{:.2}", v))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let mut builder = tabled::builder::Builder::default();
+    builder.push_record([
+        "",
+        "Days",
+        "Return/yr",
+        "Volatility",
+        "Max DD",
+        "Sharpe",
+        "Sortino",
+    ]);
+    let mut push = |name: String, m: &reports::risk::RiskMetrics| {
+        builder.push_record([
+            name,
+            m.observations.to_string(),
+            pct(m.return_pct),
+            pct(m.volatility_pct),
+            pct(m.max_drawdown_pct),
+            ratio(m.sharpe),
+            ratio(m.sortino),
+        ]);
+    };
+    push("Portfolio".to_string(), &report.portfolio);
+    for asset in &report.assets {
+        let name = if asset.adjusted {
+            asset.ticker.clone()
+        } else {
+            format!("{} *", asset.ticker)
+        };
+        push(name, &asset.metrics);
+    }
+    {
+        use tabled::settings::{object::Columns, Alignment, Modify, Style};
+        println!(
+            "{}",
+            builder
+                .build()
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+        );
+    }
+
+    match report.cdi_pct {
+        Some(cdi) => println!("\nCDI over the period: {:.2}% a year", cdi),
+        None => println!(
+            "\n{} No CDI stored for the period: Sharpe and Sortino need it (prices update-benchmarks CDI)",
+            "ℹ".blue().bold()
+        ),
+    }
+    println!(
+        "{}",
+        format!(
+            "Annualized over {} trading days; fewer than {} days of returns show drawdown only",
+            reports::risk::TRADING_DAYS,
+            reports::risk::MIN_OBSERVATIONS
+        )
+        .dimmed()
+    );
+    if report.assets.iter().any(|a| !a.adjusted) {
+        println!(
+            "{}",
+            "* raw closes: no adjusted series, so splits and dividends count as moves".dimmed()
+        );
+    }
+    println!();
+    Ok(())
 }
 
 #[cfg(test)]
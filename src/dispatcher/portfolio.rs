@@ -3,12 +3,16 @@ use colored::Colorize;
 
 use crate::reports::portfolio::calculate_allocation;
 use crate::ui::progress::{ProgressEvent, ProgressPrinter};
+use crate::ui::refresh::{self, Panel};
 use crate::utils::format_currency;
 use crate::{cli, db, reports};
 
 pub async fn dispatch_portfolio_show(
     asset_type: Option<&str>,
     as_of_date: Option<&str>,
+    tag: Option<&str>,
+    by_broker: bool,
+    table: &cli::formatters::TableArgs,
     json_output: bool,
 ) -> Result<()> {
     tracing::info!("Generating portfolio report");
@@ -20,6 +24,7 @@ pub async fn dispatch_portfolio_show(
     // Initialize database
     db::init_database(None)?;
     let mut conn = db::open_db(None)?;
+    let columns = reports::columns::configured()?;
 
     // Get blocked assets (those with open blocking inconsistencies)
     let blocked_assets = db::get_blocked_assets(&conn)?;
@@ -47,10 +52,13 @@ pub async fn dispatch_portfolio_show(
         None
     };
 
-    // Allow disabling live price fetching via env var
-    let skip_price_fetch = std::env::var("INTEREST_SKIP_PRICE_FETCH")
-        .map(|v| v != "0")
-        .unwrap_or(false);
+    // Allow disabling live price fetching via env var; interactive mode shows
+    // stored prices right away and refreshes them in the background
+    let cache_first = refresh::cache_first() && historical_date.is_none();
+    let skip_price_fetch = cache_first
+        || std::env::var("INTEREST_SKIP_PRICE_FETCH")
+            .map(|v| v != "0")
+            .unwrap_or(false);
 
     // Parse asset type filter if provided
     let asset_type_filter = if let Some(type_str) = asset_type {
@@ -63,6 +71,22 @@ pub async fn dispatch_portfolio_show(
         None
     };
 
+    // Restrict to a tag group if requested
+    let tagged_assets = tag
+        .map(|t| db::get_tagged_asset_ids(&conn, t))
+        .transpose()?;
+    let calculate = |conn: &rusqlite::Connection| -> Result<reports::PortfolioReport> {
+        let report = if let Some(date) = historical_date {
+            reports::calculate_portfolio_at_date(conn, date, asset_type_filter.as_ref())?
+        } else {
+            reports::calculate_portfolio(conn, asset_type_filter.as_ref())?
+        };
+        Ok(match &tagged_assets {
+            Some(ids) => reports::portfolio::retain_assets(report, ids),
+            None => report,
+        })
+    };
+
     // Get earliest transaction date to determine price range needed
     let earliest_date = db::get_earliest_transaction_date(&conn)?;
     if earliest_date.is_none() {
@@ -75,14 +99,13 @@ pub async fn dispatch_portfolio_show(
 
     let today = chrono::Local::now().date_naive();
 
+    // Private fixed income is valued from PUs accrued locally
+    crate::fixed_income::accrue_all(&conn, historical_date.unwrap_or(today))?;
+
     // Calculate portfolio positions first (fast, no network calls)
     // Make mutable so we can re-run after fetching current prices to include
     // up-to-date market values in the printed report.
-    let mut report = if let Some(date) = historical_date {
-        reports::calculate_portfolio_at_date(&conn, date, asset_type_filter.as_ref())?
-    } else {
-        reports::calculate_portfolio(&conn, asset_type_filter.as_ref())?
-    };
+    let mut report = calculate(&conn)?;
 
     if report.positions.is_empty() {
         if !json_output {
@@ -142,12 +165,7 @@ pub async fn dispatch_portfolio_show(
 
                 // Recompute the portfolio now that current prices have been fetched
                 // so displayed values (Price, Value, P&L) reflect the latest data.
-                report = if let Some(date) = historical_date {
-                    // Historical view - prices shouldn't have changed, but keep behavior consistent
-                    reports::calculate_portfolio_at_date(&conn, date, asset_type_filter.as_ref())?
-                } else {
-                    reports::calculate_portfolio(&conn, asset_type_filter.as_ref())?
-                };
+                report = calculate(&conn)?;
             }
         } else {
             // JSON mode: no spinner, just fetch silently
@@ -175,56 +193,343 @@ pub async fn dispatch_portfolio_show(
             }
 
             // Recompute for JSON mode as well so JSON output contains updated prices
-            report = if let Some(date) = historical_date {
-                reports::calculate_portfolio_at_date(&conn, date, asset_type_filter.as_ref())?
-            } else {
-                reports::calculate_portfolio(&conn, asset_type_filter.as_ref())?
-            };
+            report = calculate(&conn)?;
         }
     }
 
+    let broker_holdings = if by_broker {
+        Some(reports::portfolio::split_by_broker(
+            &conn,
+            &report,
+            historical_date,
+        )?)
+    } else {
+        None
+    };
+
     if json_output {
-        println!("{}", cli::formatters::format_portfolio_json(&report));
+        let json = cli::formatters::format_portfolio_json(&report, &columns, table);
+        match &broker_holdings {
+            Some(holdings) => {
+                let mut value: serde_json::Value = serde_json::from_str(&json)?;
+                value["by_broker"] = holdings
+                    .iter()
+                    .map(|h| {
+                        serde_json::json!({
+                            "broker": h.broker,
+                            "ticker": h.ticker,
+                            "quantity": h.quantity.to_string(),
+                            "total_cost": h.total_cost.to_string(),
+                            "current_value": h.current_value.map(|v| v.to_string()),
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&value)?);
+            }
+            None => println!("{}", json),
+        }
     } else {
-        println!(
-            "{}",
-            cli::formatters::format_portfolio_table(&report, asset_type)
-        );
+        let mut out = String::new();
+        if let Some(tag) = tag {
+            out.push_str(&format!(
+                "\n{} Tag group: {}\n",
+                "🏷".cyan().bold(),
+                tag.to_lowercase().bold()
+            ));
+        }
+        out.push_str(&cli::formatters::format_portfolio_table(
+            &report, asset_type, &columns, table,
+        )?);
+        out.push('\n');
+
+        if let Some(holdings) = &broker_holdings {
+            out.push_str(&format_broker_breakdown(holdings));
+        }
 
         // Display asset allocation if showing full portfolio
         if asset_type_filter.is_none() {
             let allocation = calculate_allocation(&report);
 
             if allocation.len() > 1 {
-                println!("\n{} Asset Allocation", "🎯".cyan().bold());
+                out.push_str(&format!("\n{} Asset Allocation\n", "🎯".cyan().bold()));
 
                 let mut alloc_vec: Vec<_> = allocation.iter().collect();
-                alloc_vec.sort_by(|a, b| b.1 .0.cmp(&a.1 .0));
+                alloc_vec.sort_by_key(|b| std::cmp::Reverse(b.1 .0));
 
                 for (asset_type, (value, pct)) in alloc_vec {
                     let type_ref: &db::AssetType = asset_type;
-                    println!(
-                        "  {}: {} ({:.2}%)",
+                    out.push_str(&format!(
+                        "  {}: {} ({:.2}%)\n",
                         type_ref.as_str().to_uppercase(),
                         format_currency(*value).cyan(),
                         pct
-                    );
+                    ));
                 }
             }
         }
+
+        // Live move from today's intraday quotes, kept apart from the daily closes above
+        if historical_date.is_none() {
+            let quote_date = chrono::Utc::now().date_naive();
+            if let Some(day) = reports::portfolio::calculate_day_move(&conn, &report, quote_date)? {
+                out.push_str(&format_day_move(&day));
+            }
+        }
+        cli::formatters::print_paged(&out, table.no_pager);
+
+        if cache_first {
+            refresh::print_as_of(Panel::Prices, db::get_latest_price_update(&conn)?);
+        }
     }
 
     Ok(())
 }
 
+fn format_broker_breakdown(holdings: &[reports::portfolio::BrokerHolding]) -> String {
+    use rust_decimal::Decimal;
+    use tabled::{
+        settings::{object::Columns, Alignment, Modify, Style},
+        Table, Tabled,
+    };
+
+    #[derive(Tabled)]
+    struct HoldingRow {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Cost")]
+        cost: String,
+        #[tabled(rename = "Value")]
+        value: String,
+    }
+
+    let mut brokers: Vec<Option<&str>> = Vec::new();
+    for holding in holdings {
+        if !brokers.contains(&holding.broker.as_deref()) {
+            brokers.push(holding.broker.as_deref());
+        }
+    }
+    // Named brokers alphabetically, unassigned shares last
+    brokers.sort_by_key(|b| (b.is_none(), b.map(str::to_string)));
+
+    let mut out = format!("\n{} Positions by Broker\n", "🏦".cyan().bold());
+    for broker in brokers {
+        let mut group: Vec<_> = holdings
+            .iter()
+            .filter(|h| h.broker.as_deref() == broker)
+            .collect();
+        group.sort_by_key(|h| std::cmp::Reverse(h.current_value.unwrap_or(h.total_cost)));
+        let value: Decimal = group.iter().filter_map(|h| h.current_value).sum();
+        let cost: Decimal = group.iter().map(|h| h.total_cost).sum();
+
+        out.push_str(&format!(
+            "\n  {}  {} (cost {})\n",
+            broker.unwrap_or("Unassigned").bold(),
+            format_currency(value).cyan(),
+            format_currency(cost)
+        ));
+        let rows: Vec<HoldingRow> = group
+            .iter()
+            .map(|h| HoldingRow {
+                ticker: h.ticker.clone(),
+                quantity: format!("{:.2}", h.quantity),
+                cost: format_currency(h.total_cost),
+                value: h
+                    .current_value
+                    .map(format_currency)
+                    .unwrap_or_else(|| "N/A".to_string()),
+            })
+            .collect();
+        let table = Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+            .to_string();
+        out.push_str(&format!("{}\n", table));
+    }
+    if holdings.iter().any(|h| h.broker.is_none()) {
+        out.push_str(&format!(
+            "\n  {}\n",
+            "Unassigned: shares from files without the 'Instituição' column, renames or spin-offs"
+                .dimmed()
+        ));
+    }
+    out
+}
+
+fn format_day_move(day: &reports::portfolio::DayMove) -> String {
+    let signed = |v: rust_decimal::Decimal, text: String| {
+        if v >= rust_decimal::Decimal::ZERO {
+            text.green()
+        } else {
+            text.red()
+        }
+    };
+
+    let mut out = format!(
+        "\n{} Today's Move (live as of {})\n",
+        "📡".cyan().bold(),
+        day.quoted_at.with_timezone(&chrono::Local).format("%H:%M")
+    );
+    for p in &day.positions {
+        out.push_str(&format!(
+            "  {:8} {} → {}  {}  {}\n",
+            p.ticker,
+            format_currency(p.previous_close).dimmed(),
+            format_currency(p.live_price),
+            signed(p.change_pct, format!("{:>7.2}%", p.change_pct)),
+            signed(p.value_change, format_currency(p.value_change))
+        ));
+    }
+    out.push_str(&format!(
+        "  {:8} {} ({})\n",
+        "Total:".bold(),
+        signed(day.total_change, format_currency(day.total_change)),
+        signed(
+            day.total_change_pct,
+            format!("{:.2}%", day.total_change_pct)
+        )
+    ));
+    out
+}
+
 // Top-level dispatcher for portfolio sub-commands
 pub async fn dispatch_portfolio(
     action: &crate::cli::PortfolioCommands,
     json_output: bool,
 ) -> Result<()> {
     match action {
-        crate::cli::PortfolioCommands::Show { asset_type, at } => {
-            dispatch_portfolio_show(asset_type.as_deref(), at.as_deref(), json_output).await
+        crate::cli::PortfolioCommands::Show {
+            asset_type,
+            at,
+            tag,
+            by_broker,
+            table,
+        } => {
+            dispatch_portfolio_show(
+                asset_type.as_deref(),
+                at.as_deref(),
+                tag.as_deref(),
+                *by_broker,
+                table,
+                json_output,
+            )
+            .await
+        }
+        crate::cli::PortfolioCommands::Browse => {
+            if json_output {
+                anyhow::bail!("portfolio browse is interactive; use portfolio show --json");
+            }
+            crate::ui::browse_portfolio().await
+        }
+        crate::cli::PortfolioCommands::Exposure { by, threshold } => {
+            dispatch_portfolio_exposure(by, threshold.as_deref(), json_output)
+        }
+    }
+}
+
+fn dispatch_portfolio_exposure(by: &str, threshold: Option<&str>, json_output: bool) -> Result<()> {
+    use anyhow::Context;
+    use reports::exposure::{self, ExposureLevel};
+    use rust_decimal::Decimal;
+    use tabled::settings::{object::Columns, Alignment, Modify, Style};
+    use tabled::{Table, Tabled};
+
+    let level = match by {
+        "segment" => ExposureLevel::Segment,
+        _ => ExposureLevel::Sector,
+    };
+    let threshold_pct = match threshold {
+        Some(raw) => raw
+            .trim()
+            .trim_end_matches('%')
+            .replace(',', ".")
+            .parse::<Decimal>()
+            .with_context(|| format!("Invalid threshold '{}'", raw))?,
+        None => crate::config::load_config()?
+            .exposure
+            .and_then(|e| e.max_concentration_pct)
+            .unwrap_or(exposure::DEFAULT_THRESHOLD_PCT),
+    };
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let report = reports::calculate_portfolio(&conn, None)?;
+    let exposure = exposure::exposure(&conn, &report, level, threshold_pct)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&exposure)?);
+        return Ok(());
+    }
+
+    if exposure.groups.is_empty() {
+        println!("{} No valued positions", "ℹ".blue().bold());
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Exposure by {}\n",
+        "🏭".cyan().bold(),
+        if level == ExposureLevel::Segment {
+            "segment"
+        } else {
+            "sector"
         }
+    );
+
+    #[derive(Tabled)]
+    struct GroupRow {
+        #[tabled(rename = "Sector / Segment")]
+        name: String,
+        #[tabled(rename = "Value")]
+        value: String,
+        #[tabled(rename = "Share")]
+        share: String,
+        #[tabled(rename = "Assets")]
+        tickers: String,
+    }
+    let rows: Vec<GroupRow> = exposure
+        .groups
+        .iter()
+        .map(|g| GroupRow {
+            name: if g.classified {
+                g.name.clone()
+            } else {
+                g.name.dimmed().to_string()
+            },
+            value: format_currency(g.value),
+            share: if g.concentrated {
+                format!("{:.2}% ⚠", g.share_pct).yellow().to_string()
+            } else {
+                format!("{:.2}%", g.share_pct)
+            },
+            tickers: g.tickers.join(", "),
+        })
+        .collect();
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..3)).with(Alignment::right()))
+    );
+    println!("Total: {}", format_currency(exposure.total_value).bold());
+
+    for group in exposure.concentrated() {
+        println!(
+            "{} {} is {:.2}% of the portfolio, above the {}% limit",
+            "⚠".yellow().bold(),
+            group.name,
+            group.share_pct,
+            exposure.threshold_pct.normalize()
+        );
     }
+    if exposure.groups.iter().any(|g| !g.classified) {
+        println!(
+            "{}",
+            "Unclassified assets are missing from the registry; run `interest assets sync-maisretorno`"
+                .dimmed()
+        );
+    }
+    println!();
+    Ok(())
 }
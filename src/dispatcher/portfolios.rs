@@ -0,0 +1,176 @@
+use anyhow::Result;
+use colored::Colorize;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::db::{self, portfolio};
+
+pub fn dispatch_portfolios(
+    action: &crate::cli::PortfoliosCommands,
+    json_output: bool,
+) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    match action {
+        crate::cli::PortfoliosCommands::List => list_portfolios(&conn, json_output),
+        crate::cli::PortfoliosCommands::Add {
+            name,
+            description,
+            declarant,
+        } => {
+            let id = portfolio::create_portfolio(
+                &conn,
+                name.trim(),
+                description.as_deref(),
+                declarant.as_deref().map(str::trim),
+            )?;
+            if json_output {
+                println!("{}", serde_json::json!({ "id": id, "name": name.trim() }));
+            } else {
+                println!("{} Portfolio '{}' created", "✓".green().bold(), name.trim());
+                println!(
+                    "  Record into it with --portfolio {}, e.g. interest --portfolio {} import file.xlsx",
+                    name.trim(),
+                    name.trim()
+                );
+            }
+            Ok(())
+        }
+        crate::cli::PortfoliosCommands::SetDeclarant { name, declarant } => {
+            let target = portfolio::require_portfolio(&conn, name)?;
+            let declarant = declarant
+                .as_deref()
+                .map(str::trim)
+                .filter(|d| !d.is_empty());
+            portfolio::set_declarant(&conn, target.id, declarant)?;
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::json!({ "portfolio": target.name, "declarant": declarant })
+                );
+            } else {
+                match declarant {
+                    Some(declarant) => println!(
+                        "{} Portfolio '{}' is declared by {}",
+                        "✓".green().bold(),
+                        target.name,
+                        declarant
+                    ),
+                    None => println!(
+                        "{} Portfolio '{}' has no declarant",
+                        "✓".green().bold(),
+                        target.name
+                    ),
+                }
+            }
+            Ok(())
+        }
+        crate::cli::PortfoliosCommands::Remove { name } => {
+            let target = portfolio::require_portfolio(&conn, name)?;
+            portfolio::delete_portfolio(&conn, &target)?;
+            if json_output {
+                println!("{}", serde_json::json!({ "removed": target.name }));
+            } else {
+                println!("{} Portfolio '{}' removed", "✓".green().bold(), target.name);
+            }
+            Ok(())
+        }
+        crate::cli::PortfoliosCommands::Assign {
+            name,
+            ticker,
+            source,
+        } => {
+            let target = portfolio::require_portfolio(&conn, name)?;
+            let asset_id = match ticker {
+                Some(ticker) => Some(
+                    db::get_asset_by_ticker(&conn, &ticker.to_uppercase())?
+                        .and_then(|a| a.id)
+                        .ok_or_else(|| anyhow::anyhow!("Ticker {} not found", ticker))?,
+                ),
+                None => None,
+            };
+            let source = source.as_deref().map(str::to_uppercase);
+            let (transactions, income) =
+                portfolio::assign_to_portfolio(&conn, target.id, asset_id, source.as_deref())?;
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "portfolio": target.name,
+                        "transactions": transactions,
+                        "income_events": income,
+                    })
+                );
+            } else {
+                println!(
+                    "{} Moved {} transaction(s) and {} income event(s) to '{}'",
+                    "✓".green().bold(),
+                    transactions,
+                    income,
+                    target.name
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+fn list_portfolios(conn: &rusqlite::Connection, json_output: bool) -> Result<()> {
+    let portfolios = portfolio::list_portfolios(conn)?;
+
+    if json_output {
+        let rows: Vec<_> = portfolios
+            .iter()
+            .map(|(p, transactions, income)| {
+                serde_json::json!({
+                    "id": p.id,
+                    "name": p.name,
+                    "description": p.description,
+                    "declarant": p.declarant,
+                    "transactions": transactions,
+                    "income_events": income,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct PortfolioRow {
+        #[tabled(rename = "Portfolio")]
+        name: String,
+        #[tabled(rename = "Description")]
+        description: String,
+        #[tabled(rename = "Declarant")]
+        declarant: String,
+        #[tabled(rename = "Transactions")]
+        transactions: i64,
+        #[tabled(rename = "Income events")]
+        income: i64,
+    }
+
+    let rows: Vec<PortfolioRow> = portfolios
+        .into_iter()
+        .map(|(p, transactions, income)| PortfolioRow {
+            name: p.name,
+            description: p.description.unwrap_or_default(),
+            declarant: p.declarant.unwrap_or_else(|| "-".to_string()),
+            transactions,
+            income,
+        })
+        .collect();
+    let table = Table::new(rows)
+        .with(Style::rounded())
+        .with(Modify::new(Columns::new(3..)).with(Alignment::right()))
+        .to_string();
+    println!("\n{}", table);
+    println!(
+        "\n  Scope reports with --portfolio NAME (e.g. interest --portfolio spouse tax report 2024); \
+         without it, all portfolios are aggregated. --declarant NAME covers all of one declarant's portfolios."
+    );
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate};
+use colored::Colorize;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+
+use crate::reports::position_discrepancies::{self, PositionCell};
+
+fn parse_month(value: Option<&str>, flag: &str) -> Result<Option<NaiveDate>> {
+    value
+        .map(|v| {
+            NaiveDate::parse_from_str(&format!("{}-01", v), "%Y-%m-%d")
+                .with_context(|| format!("Invalid {} '{}', expected YYYY-MM", flag, v))
+        })
+        .transpose()
+}
+
+/// Two characters per month: dim dot when the statement matches, a yellow
+/// or red block by how far off the computed quantity is, blank without one
+fn heat(cell: Option<&PositionCell>) -> String {
+    match cell {
+        None => "  ".to_string(),
+        Some(c) if c.matches() => "· ".dimmed().to_string(),
+        Some(c) if c.relative_difference() < Decimal::new(1, 1) => "░░".yellow().to_string(),
+        Some(_) => "██".red().to_string(),
+    }
+}
+
+pub fn dispatch_position_discrepancies(
+    conn: &Connection,
+    from: Option<&str>,
+    to: Option<&str>,
+    asset: Option<&str>,
+    json_output: bool,
+) -> Result<()> {
+    let from = parse_month(from, "--from")?;
+    let to = parse_month(to, "--to")?;
+    let grid = position_discrepancies::build(conn, from, to, asset)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&grid)?);
+        return Ok(());
+    }
+
+    if grid.assets.is_empty() {
+        println!(
+            "{} No position statements to compare. Import a B3 Posição export or run: interest sync-b3",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Computed vs. reported positions by month\n",
+        "🔎".cyan().bold()
+    );
+    let width = grid
+        .assets
+        .iter()
+        .map(|a| a.ticker.len())
+        .max()
+        .unwrap_or(0);
+
+    // Year above its first month (or January), then month initials
+    let mut years = String::new();
+    let mut initials = String::new();
+    for (i, month) in grid.months.iter().enumerate() {
+        if (i == 0 && month.month() < 12) || month.month() == 1 {
+            years.push_str(&format!("{:<2}", month.year()));
+        } else if years.chars().count() <= i * 2 {
+            years.push_str("  ");
+        }
+        initials.push_str(&format!(
+            "{} ",
+            &"JFMAMJJASOND"[month.month0() as usize..month.month() as usize]
+        ));
+    }
+    println!("{:width$}  {}", "", years.trim_end(), width = width);
+    println!("{:width$}  {}", "", initials.trim_end(), width = width);
+    for history in &grid.assets {
+        let row: String = history.cells.iter().map(|c| heat(c.as_ref())).collect();
+        println!("{:<width$}  {}", history.ticker, row, width = width);
+    }
+    println!(
+        "\n{} matches  {} off by less than 10%  {} off by more  (blank: no statement)",
+        "·".dimmed(),
+        "░░".yellow(),
+        "██".red()
+    );
+
+    let diverging: Vec<_> = grid.diverging().collect();
+    if diverging.is_empty() {
+        println!(
+            "\n{} Every statement matches the computed history",
+            "✓".green().bold()
+        );
+        return Ok(());
+    }
+    println!("\nFirst divergence per asset:");
+    for history in diverging {
+        let cell = history.first_divergence.as_ref().expect("diverging");
+        let sign = if cell.difference > Decimal::ZERO {
+            "+"
+        } else {
+            ""
+        };
+        println!(
+            "  {:<width$}  {}  reported {}, computed {} ({}{})",
+            history.ticker,
+            cell.statement_date.format("%d/%m/%Y"),
+            cell.reported,
+            cell.computed,
+            sign,
+            cell.difference,
+            width = width
+        );
+    }
+    println!(
+        "\nCheck the trades and corporate actions just before each date: {}",
+        "interest transactions list --ticker <TICKER>".cyan()
+    );
+    Ok(())
+}
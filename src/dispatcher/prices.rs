@@ -7,7 +7,7 @@ pub async fn dispatch_prices(action: &crate::cli::PriceCommands, json_output: bo
     use crate::importers::b3_cotahist;
 
     match action {
-        crate::cli::PriceCommands::Update => dispatch_price_update().await,
+        crate::cli::PriceCommands::Update { all } => dispatch_price_update(*all, json_output).await,
         crate::cli::PriceCommands::ImportB3 { year, no_cache } => {
             let year = *year;
             let no_cache = *no_cache;
@@ -121,20 +121,749 @@ pub async fn dispatch_prices(action: &crate::cli::PriceCommands, json_output: bo
             );
             Ok(())
         }
+        crate::cli::PriceCommands::Backfill { from, no_cache } => {
+            dispatch_backfill(from.as_deref(), *no_cache, json_output).await
+        }
         crate::cli::PriceCommands::ClearCache { year } => {
             tracing::info!("Clearing COTAHIST cache {:?}", year);
             crate::importers::b3_cotahist::clear_cache(*year)?;
+            if year.is_none() {
+                crate::db::init_database(None)?;
+                let conn = crate::db::open_db(None)?;
+                let cleared = crate::pricing::quote_cache::clear(&conn)?;
+                tracing::info!("Cleared {} cached quotes", cleared);
+            }
             Ok(())
         }
-        crate::cli::PriceCommands::History { ticker, from, to } => {
-            dispatch_price_history(ticker, from, to).await
+        crate::cli::PriceCommands::History {
+            ticker,
+            from,
+            to,
+            table,
+        } => dispatch_price_history(ticker, from, to, table).await,
+        crate::cli::PriceCommands::UpdateBenchmarks { benchmark, from } => {
+            dispatch_update_benchmarks(benchmark.as_deref(), from.as_deref(), json_output).await
+        }
+        crate::cli::PriceCommands::UpdateFx { currency, from } => {
+            dispatch_update_fx(currency.as_deref(), from.as_deref(), json_output).await
+        }
+        crate::cli::PriceCommands::UpdateNav { year } => {
+            dispatch_update_nav(*year, json_output).await
+        }
+        crate::cli::PriceCommands::Pvp { ticker } => dispatch_pvp(ticker.as_deref(), json_output),
+        crate::cli::PriceCommands::Coverage { live } => dispatch_coverage(*live, json_output).await,
+        crate::cli::PriceCommands::Watch { interval, once } => {
+            dispatch_price_watch(*interval, *once, json_output).await
         }
     }
 }
 
-async fn dispatch_price_update() -> Result<()> {
-    use crate::pricing::PriceFetcher;
+#[derive(serde::Serialize)]
+struct BackfillFile {
+    period: String,
+    prices: usize,
+    error: Option<String>,
+}
+
+async fn dispatch_backfill(from: Option<&str>, no_cache: bool, json_output: bool) -> Result<()> {
+    use crate::db;
+    use crate::importers::b3_cotahist::{self, CotahistPeriod};
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let (from_year, from_month) = match from {
+        Some(s) => parse_year_or_month(s)?,
+        None => match crate::tax::loss_carryforward::earliest_transaction_year(&conn)? {
+            Some(year) => (year, None),
+            None => {
+                println!(
+                    "{} No transactions yet, nothing to backfill",
+                    "ℹ".blue().bold()
+                );
+                return Ok(());
+            }
+        },
+    };
+    drop(conn);
+
+    let periods =
+        b3_cotahist::backfill_periods(from_year, from_month, chrono::Local::now().date_naive());
+    let mut files = Vec::new();
+    for period in periods {
+        if !json_output {
+            println!("  Loading COTAHIST {}...", period.label());
+        }
+        let result = tokio::task::spawn_blocking(move || -> Result<usize> {
+            let zip_path = match period {
+                CotahistPeriod::Year(year) => {
+                    b3_cotahist::download_cotahist_year(year, no_cache, None)?
+                }
+                CotahistPeriod::Month(year, month) => {
+                    b3_cotahist::download_cotahist_month(year, month, no_cache)?
+                }
+            };
+            let records = b3_cotahist::parse_cotahist_file(&zip_path, None)?;
+            let mut conn = db::open_db(None)?;
+            b3_cotahist::import_held_asset_records(&mut conn, &records)
+        })
+        .await
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+
+        let file = match result {
+            Ok(prices) => BackfillFile {
+                period: period.label(),
+                prices,
+                error: None,
+            },
+            Err(e) => BackfillFile {
+                period: period.label(),
+                prices: 0,
+                error: Some(e.to_string()),
+            },
+        };
+        if !json_output {
+            match &file.error {
+                None => println!(
+                    "{} {}: {} new prices",
+                    "✓".green(),
+                    file.period,
+                    file.prices
+                ),
+                Some(e) => println!("{} {}: {}", "✗".red(), file.period, e),
+            }
+        }
+        files.push(file);
+    }
+
+    let total: usize = files.iter().map(|f| f.prices).sum();
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "total_prices": total,
+                "files": files,
+            }))?
+        );
+    } else {
+        println!("\n{} Backfilled {} daily prices", "✓".green().bold(), total);
+    }
+    Ok(())
+}
+
+/// Parse `YYYY` or `YYYY-MM`
+fn parse_year_or_month(s: &str) -> Result<(i32, Option<u32>)> {
+    let invalid = || anyhow::anyhow!("Invalid --from '{}'. Use YYYY or YYYY-MM", s);
+    let (year, month) = match s.split_once('-') {
+        Some((y, m)) => (y, Some(m.parse::<u32>().map_err(|_| invalid())?)),
+        None => (s, None),
+    };
+    let year = year.parse::<i32>().map_err(|_| invalid())?;
+    if month.is_some_and(|m| !(1..=12).contains(&m)) {
+        return Err(invalid());
+    }
+    Ok((year, month))
+}
+
+async fn dispatch_update_nav(year: Option<i32>, json_output: bool) -> Result<()> {
+    use crate::db;
+    use chrono::Datelike;
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let current = chrono::Local::now().year();
+    // Informes are filed with a delay, so the previous year keeps changing early on
+    let years = match year {
+        Some(y) => vec![y],
+        None => vec![current - 1, current],
+    };
+
+    let mut updates = Vec::new();
+    for y in years {
+        updates.push(crate::pricing::fii_nav::update_fii_nav(&conn, y).await?);
+    }
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&updates)?);
+        return Ok(());
+    }
+    if updates.iter().all(|u| u.funds_with_cnpj == 0) {
+        println!(
+            "{} No FII with a known CNPJ. Set one with 'interest assets set-cnpj' or run 'interest assets enrich-cnpj'.",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    }
+    for update in &updates {
+        println!(
+            "{} {}: {} NAV months stored for {}/{} FIIs",
+            "✓".green(),
+            update.year,
+            update.months_stored,
+            update.funds_matched,
+            update.funds_with_cnpj
+        );
+    }
+    Ok(())
+}
+
+fn dispatch_pvp(ticker: Option<&str>, json_output: bool) -> Result<()> {
+    use crate::db;
+    use crate::reports::fii_discount;
+    use crate::utils::format_currency;
+    use tabled::{
+        settings::{object::Columns, Alignment, Modify, Style},
+        Table, Tabled,
+    };
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let pct = |v: Option<rust_decimal::Decimal>| {
+        v.map(|v| format!("{:+.1}%", v))
+            .unwrap_or_else(|| "N/A".to_string())
+    };
+    let ratio = |v: Option<rust_decimal::Decimal>| {
+        v.map(|v| format!("{:.2}", v))
+            .unwrap_or_else(|| "N/A".to_string())
+    };
+
+    if let Some(ticker) = ticker {
+        let asset = db::get_asset_by_ticker(&conn, &ticker.to_uppercase())?
+            .ok_or_else(|| anyhow::anyhow!("Ticker {} not found", ticker))?;
+        let history = fii_discount::nav_history(&conn, asset.id.expect("asset id"))?;
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&history)?);
+            return Ok(());
+        }
+        if history.is_empty() {
+            println!(
+                "{} No NAV data for {}. Run 'interest prices update-nav' first.",
+                "ℹ".blue().bold(),
+                asset.ticker
+            );
+            return Ok(());
+        }
+
+        #[derive(Tabled)]
+        struct NavRow {
+            #[tabled(rename = "Month")]
+            month: String,
+            #[tabled(rename = "NAV/Quota")]
+            nav: String,
+            #[tabled(rename = "Close")]
+            price: String,
+            #[tabled(rename = "P/VP")]
+            p_vp: String,
+            #[tabled(rename = "Premium")]
+            premium: String,
+        }
+        let rows: Vec<NavRow> = history
+            .iter()
+            .map(|p| NavRow {
+                month: p.reference_date.format("%Y-%m").to_string(),
+                nav: format_currency(p.nav_per_quota),
+                price: p
+                    .price
+                    .map(format_currency)
+                    .unwrap_or_else(|| "N/A".to_string()),
+                p_vp: ratio(p.p_vp),
+                premium: pct(p.premium_pct()),
+            })
+            .collect();
+        println!(
+            "\n{} {} price vs NAV",
+            "🏢".cyan().bold(),
+            asset.ticker.bold()
+        );
+        println!(
+            "{}",
+            Table::new(rows)
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+        );
+        return Ok(());
+    }
+
+    let report = crate::reports::calculate_portfolio(&conn, Some(&db::AssetType::Fii))?;
+    let ranking = fii_discount::rank_fii_discounts(&conn, &report)?;
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&ranking)?);
+        return Ok(());
+    }
+    if ranking.is_empty() {
+        println!("{} No FII positions", "ℹ".blue().bold());
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct RankRow {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Close")]
+        price: String,
+        #[tabled(rename = "NAV/Quota")]
+        nav: String,
+        #[tabled(rename = "NAV Month")]
+        nav_month: String,
+        #[tabled(rename = "P/VP")]
+        p_vp: String,
+        #[tabled(rename = "Premium")]
+        premium: String,
+        #[tabled(rename = "Avg Premium")]
+        avg_premium: String,
+        #[tabled(rename = "Value")]
+        value: String,
+    }
+    let rows: Vec<RankRow> = ranking
+        .iter()
+        .map(|r| RankRow {
+            ticker: r.ticker.clone(),
+            price: r
+                .price
+                .map(format_currency)
+                .unwrap_or_else(|| "N/A".to_string()),
+            nav: r
+                .nav_per_quota
+                .map(format_currency)
+                .unwrap_or_else(|| "N/A".to_string()),
+            nav_month: r
+                .nav_date
+                .map(|d| d.format("%Y-%m").to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            p_vp: ratio(r.p_vp),
+            premium: pct(r.premium_pct),
+            avg_premium: pct(r.avg_premium_pct),
+            value: r
+                .market_value
+                .map(format_currency)
+                .unwrap_or_else(|| "N/A".to_string()),
+        })
+        .collect();
+    println!(
+        "\n{} FII holdings by discount to NAV (deepest first)",
+        "🏢".cyan().bold()
+    );
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+    );
+    if ranking.iter().any(|r| r.nav_per_quota.is_none()) {
+        println!(
+            "\n  {}",
+            "N/A: no CVM NAV stored; set the FII's CNPJ and run 'interest prices update-nav'"
+                .dimmed()
+        );
+    }
+    Ok(())
+}
+
+async fn dispatch_price_watch(interval: u64, once: bool, json_output: bool) -> Result<()> {
+    use crate::pricing::watch;
+    use std::io::IsTerminal;
+
+    if !json_output && !once && cfg!(feature = "tui") && std::io::stdout().is_terminal() {
+        return crate::ui::watch_prices(interval).await;
+    }
+
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let mut rows = watch::held_rows(&conn)?;
+    if rows.is_empty() {
+        if json_output {
+            println!("[]");
+        } else {
+            println!("{} No held assets with live quotes", "ℹ".blue().bold());
+        }
+        return Ok(());
+    }
+
+    loop {
+        let outcome = watch::refresh(&conn, &mut rows).await?;
+        let as_of = chrono::Local::now();
+        if json_output {
+            let payload = serde_json::json!({
+                "as_of": as_of.to_rfc3339(),
+                "quoted": outcome.quoted,
+                "failed": outcome.failed,
+                "rows": rows,
+                "totals": watch::totals(&rows),
+            });
+            println!("{}", serde_json::to_string(&payload)?);
+        } else {
+            print_watch_round(&rows, as_of);
+            if let Some(cooldown) = outcome.cooldown {
+                println!(
+                    "  {} Yahoo Finance is rate limiting; waiting {}s",
+                    "⚠".yellow(),
+                    cooldown.as_secs()
+                );
+            }
+        }
+        if once {
+            return Ok(());
+        }
+        tokio::time::sleep(watch::next_wait(interval, &outcome)).await;
+    }
+}
+
+fn print_watch_round(
+    rows: &[crate::pricing::watch::WatchRow],
+    as_of: chrono::DateTime<chrono::Local>,
+) {
+    use crate::utils::format_currency;
+    use tabled::{
+        settings::{object::Columns, Alignment, Modify, Style},
+        Table, Tabled,
+    };
+
+    #[derive(Tabled)]
+    struct WatchLine {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Price")]
+        price: String,
+        #[tabled(rename = "Day")]
+        day: String,
+        #[tabled(rename = "Value")]
+        value: String,
+        #[tabled(rename = "P&L")]
+        pnl: String,
+    }
+
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let lines: Vec<WatchLine> = rows
+        .iter()
+        .map(|r| WatchLine {
+            ticker: r.ticker.clone(),
+            price: match (&r.error, r.price) {
+                (Some(_), Some(price)) => format!("{} (stale)", format_currency(price)),
+                (Some(_), None) => "failed".to_string(),
+                (None, price) => or_dash(price.map(format_currency)),
+            },
+            day: or_dash(r.day_change_pct().map(|p| format!("{:+.2}%", p))),
+            value: or_dash(r.market_value().map(format_currency)),
+            pnl: or_dash(
+                r.pnl()
+                    .zip(r.pnl_pct())
+                    .map(|(v, p)| format!("{} ({:+.2}%)", format_currency(v), p)),
+            ),
+        })
+        .collect();
+    let totals = crate::pricing::watch::totals(rows);
+    println!(
+        "\n{} Live quotes at {}",
+        "📈".cyan().bold(),
+        as_of.format("%d/%m/%Y %H:%M:%S")
+    );
+    println!(
+        "{}",
+        Table::new(lines)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..5)).with(Alignment::right()))
+    );
+    println!(
+        "  Value: {}   Day: {}   P&L: {}",
+        format_currency(totals.market_value),
+        format_currency(totals.day_change),
+        format_currency(totals.pnl)
+    );
+}
+
+async fn dispatch_coverage(live: bool, json_output: bool) -> Result<()> {
+    use crate::db;
+    use crate::pricing::coverage::{self, YAHOO};
+    use tabled::{settings::Style, Table, Tabled};
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let chain = crate::pricing::provider::ProviderChain::load()?;
+    let held = coverage::held_assets(&conn)?;
+    if held.is_empty() {
+        if json_output {
+            println!("[]");
+        } else {
+            println!("{} No open positions", "ℹ".blue().bold());
+        }
+        return Ok(());
+    }
+    if live && !json_output {
+        println!("  Testing live quotes...");
+    }
+
+    let mut report = Vec::with_capacity(held.len());
+    for (asset, quantity) in &held {
+        let live_yahoo = if live && chain.providers_for(asset).contains(&YAHOO) {
+            Some(crate::pricing::fetch_quote(&asset.ticker).await.is_ok())
+        } else {
+            None
+        };
+        report.push(coverage::asset_coverage(
+            &conn, &chain, asset, *quantity, live_yahoo,
+        )?);
+    }
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct CoverageRow {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        asset_type: String,
+        #[tabled(rename = "Providers")]
+        providers: String,
+        #[tabled(rename = "Authoritative")]
+        authoritative: String,
+        #[tabled(rename = "Last Price")]
+        last_price: String,
+    }
+    let rows: Vec<CoverageRow> = report
+        .iter()
+        .map(|c| CoverageRow {
+            ticker: c.ticker.clone(),
+            asset_type: c.asset_type.to_string(),
+            providers: if c.providers.is_empty() {
+                "none".to_string()
+            } else {
+                c.providers
+                    .iter()
+                    .map(|p| {
+                        let stored = p
+                            .last_price_date
+                            .map(|d| d.to_string())
+                            .unwrap_or_else(|| "never".to_string());
+                        match p.live {
+                            Some(true) => format!("{} ({}, live ✓)", p.provider, stored),
+                            Some(false) => format!("{} ({}, live ✗)", p.provider, stored),
+                            None => format!("{} ({})", p.provider, stored),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            },
+            authoritative: c.authoritative.clone().unwrap_or_else(|| "-".to_string()),
+            last_price: c
+                .last_price_date
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        })
+        .collect();
+    println!("\n{} Price coverage of held assets", "📡".cyan().bold());
+    println!("{}", Table::new(rows).with(Style::rounded()));
+
+    let uncovered: Vec<&str> = report
+        .iter()
+        .filter(|c| c.needs_manual_price())
+        .map(|c| c.ticker.as_str())
+        .collect();
+    if uncovered.is_empty() {
+        println!(
+            "\n{} Every held asset has a price provider",
+            "✓".green().bold()
+        );
+    } else {
+        println!(
+            "\n{} {} asset(s) with no provider need manual prices: {}",
+            "⚠".yellow().bold(),
+            uncovered.len(),
+            uncovered.join(", ")
+        );
+    }
+    Ok(())
+}
+
+async fn dispatch_update_benchmarks(
+    benchmark: Option<&str>,
+    from: Option<&str>,
+    json_output: bool,
+) -> Result<()> {
+    use anyhow::Context;
+    use chrono::NaiveDate;
+
+    let benchmarks = match benchmark {
+        Some(name) => vec![name.parse::<crate::db::Benchmark>().map_err(|_| {
+            anyhow::anyhow!(
+                "Unknown benchmark: {} (use IBOV, IFIX, CDI, SELIC or IPCA)",
+                name
+            )
+        })?],
+        None => crate::db::Benchmark::all().to_vec(),
+    };
+    let explicit_from = from
+        .map(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d"))
+        .transpose()
+        .context("Invalid from date. Use YYYY-MM-DD format")?;
+
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let today = chrono::Local::now().date_naive();
+    let default_from = crate::db::get_earliest_transaction_date(&conn)?
+        .unwrap_or_else(|| today - chrono::Duration::days(365 * 5));
+
+    let mut results = Vec::new();
+    for benchmark in benchmarks {
+        let start = match explicit_from {
+            Some(date) => date,
+            None => crate::db::get_latest_benchmark_date(&conn, benchmark)?
+                .and_then(|d| d.succ_opt())
+                .unwrap_or(default_from),
+        };
+        if start > today {
+            results.push((benchmark, Ok(0)));
+            continue;
+        }
+        let outcome = crate::pricing::benchmarks::update_benchmark(&conn, benchmark, start, today)
+            .await
+            .map_err(|e| e.to_string());
+        results.push((benchmark, outcome));
+    }
+
+    if json_output {
+        let payload: Vec<_> = results
+            .iter()
+            .map(|(benchmark, outcome)| match outcome {
+                Ok(count) => serde_json::json!({
+                    "benchmark": benchmark.as_str(),
+                    "stored": count,
+                }),
+                Err(e) => serde_json::json!({
+                    "benchmark": benchmark.as_str(),
+                    "error": e,
+                }),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    for (benchmark, outcome) in &results {
+        match outcome {
+            Ok(count) => println!(
+                "{} {}: {} values stored",
+                "✓".green(),
+                benchmark.as_str(),
+                count
+            ),
+            Err(e) => println!("{} {}: {}", "✗".red(), benchmark.as_str(), e),
+        }
+    }
+
+    Ok(())
+}
+
+/// Fetch the PTAX of each currency missing since its last stored rate (or
+/// `from`), with the latest rate or the error of each
+async fn update_fx_rates(
+    conn: &rusqlite::Connection,
+    currencies: &[String],
+    from: Option<chrono::NaiveDate>,
+) -> Result<Vec<FxUpdate>> {
+    let today = chrono::Local::now().date_naive();
+    let default_from = crate::db::get_earliest_transaction_date(conn)?
+        .unwrap_or_else(|| today - chrono::Duration::days(365 * 5));
+
+    let mut results = Vec::new();
+    for currency in currencies {
+        let pair = crate::pricing::fx::pair(currency);
+        let start = match from {
+            Some(date) => date,
+            None => crate::db::get_latest_fx_date(conn, &pair)?
+                .and_then(|d| d.succ_opt())
+                .unwrap_or(default_from),
+        };
+        let outcome = if start > today {
+            Ok(0)
+        } else {
+            crate::pricing::fx::update_rates(conn, currency, start, today)
+                .await
+                .map_err(|e| e.to_string())
+        };
+        results.push(FxUpdate {
+            currency: currency.clone(),
+            latest: crate::db::get_fx_rate_on_or_before(conn, &pair, today)?,
+            stored: outcome.as_ref().ok().copied(),
+            error: outcome.err(),
+        });
+    }
+    Ok(results)
+}
+
+#[derive(serde::Serialize)]
+struct FxUpdate {
+    currency: String,
+    /// Latest stored (date, rate)
+    latest: Option<(chrono::NaiveDate, rust_decimal::Decimal)>,
+    stored: Option<usize>,
+    error: Option<String>,
+}
+
+fn print_fx_updates(results: &[FxUpdate]) {
+    for result in results {
+        let latest = result
+            .latest
+            .map(|(date, rate)| format!(", PTAX {} on {}", rate, date.format("%d/%m/%Y")))
+            .unwrap_or_default();
+        match (&result.error, result.stored) {
+            (Some(e), _) => println!("{} {}/BRL: {}{}", "✗".red(), result.currency, e, latest),
+            (None, stored) => println!(
+                "{} {}/BRL: {} rates stored{}",
+                "✓".green(),
+                result.currency,
+                stored.unwrap_or(0),
+                latest
+            ),
+        }
+    }
+}
+
+async fn dispatch_update_fx(
+    currency: Option<&str>,
+    from: Option<&str>,
+    json_output: bool,
+) -> Result<()> {
+    use anyhow::Context;
+    use chrono::NaiveDate;
+
+    let explicit_from = from
+        .map(|s| NaiveDate::parse_from_str(s, "%Y-%m-%d"))
+        .transpose()
+        .context("Invalid from date. Use YYYY-MM-DD format")?;
+
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let currencies = match currency {
+        Some(code) => vec![crate::pricing::fx::parse_currency(code)?],
+        None => {
+            let mut currencies = crate::db::get_asset_currencies(&conn)?;
+            // The dollar splits BDR returns even when no asset is quoted in it
+            if !currencies.iter().any(|c| c == "USD") {
+                currencies.insert(0, "USD".to_string());
+            }
+            currencies
+        }
+    };
+    let results = update_fx_rates(&conn, &currencies, explicit_from).await?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+    print_fx_updates(&results);
+    Ok(())
+}
+
+async fn dispatch_price_update(all: bool, json_output: bool) -> Result<()> {
+    use crate::importers::ItemResult;
+    use crate::pricing::provider::{self, ProviderChain};
+    use crate::pricing::retry_queue;
+    use crate::ui::progress::{ProgressData, ProgressEvent};
     use colored::Colorize;
+    use std::sync::Arc;
 
     tracing::info!("Updating all asset prices");
 
@@ -143,78 +872,265 @@ async fn dispatch_price_update() -> Result<()> {
     let conn = crate::db::open_db(None)?;
 
     // Get all assets
-    let assets = crate::db::get_all_assets(&conn)?;
+    let mut assets = crate::db::get_all_assets(&conn)?;
 
-    if assets.is_empty() {
+    if assets.is_empty() && !json_output {
         println!("{} No assets found in database", "ℹ".blue().bold());
         println!("Import transactions first using: interest import <file>");
         return Ok(());
     }
 
-    println!(
-        "\n{} Updating prices for {} assets\n",
-        "→".cyan().bold(),
-        assets.len()
-    );
+    // The providers quote B3; assets quoted abroad keep their manual prices
+    let mut quoted_abroad = HashSet::new();
+    for asset in &assets {
+        if let Some(id) = asset.id {
+            if crate::db::get_asset_currency(&conn, id)?.is_some() {
+                quoted_abroad.insert(id);
+            }
+        }
+    }
+    assets.retain(|a| a.id.is_none_or(|id| !quoted_abroad.contains(&id)));
 
-    let fetcher = PriceFetcher::new();
-    let mut updated = 0;
-    let mut errors = 0;
+    // While earlier failures are queued, only the due ones are fetched again
+    let now = chrono::Utc::now();
+    let queued = retry_queue::pending(&conn)?;
+    let retry_only = !all && !queued.is_empty();
+    if retry_only {
+        let due: HashSet<i64> = queued
+            .iter()
+            .filter(|q| q.is_due(now))
+            .map(|q| q.asset_id)
+            .collect();
+        assets.retain(|a| a.id.is_some_and(|id| due.contains(&id)));
+        if assets.is_empty() {
+            if json_output {
+                let data = serde_json::json!({
+                    "updated": 0,
+                    "errors": 0,
+                    "queued": queued.len(),
+                    "next_retry_at": queued.first().map(|q| q.next_attempt_at),
+                });
+                let payload = crate::dispatcher::imports_helpers::batch_envelope(&data, &[]);
+                println!("{}", serde_json::to_string_pretty(&payload)?);
+            } else {
+                print_retry_queue(&queued);
+                println!("Use --all to refetch every asset now");
+            }
+            return Ok(());
+        }
+    }
 
-    for asset in &assets {
-        print!("  {} {}... ", asset.ticker, "→".cyan());
-
-        match fetcher.fetch_price(&asset.ticker).await {
-            Ok(price) => {
-                // Store price in database
-                let price_history = crate::db::PriceHistory {
-                    id: None,
-                    asset_id: asset.id.unwrap(),
-                    price_date: chrono::Utc::now().date_naive(),
-                    close_price: price,
-                    open_price: None,
-                    high_price: None,
-                    low_price: None,
-                    volume: None,
-                    source: "YAHOO".to_string(),
-                    created_at: chrono::Utc::now(),
-                };
+    if !json_output {
+        if retry_only {
+            println!(
+                "\n{} Retrying prices for {} assets that failed before (--all refetches everything)\n",
+                "→".cyan().bold(),
+                assets.len()
+            );
+        } else {
+            println!(
+                "\n{} Updating prices for {} assets\n",
+                "→".cyan().bold(),
+                assets.len()
+            );
+        }
+    }
+
+    // Every asset's chain runs at once; each provider bounds its own requests
+    // in flight, and results are stored here as they arrive
+    let chain = Arc::new(ProviderChain::load()?);
+    let mut pending = tokio::task::JoinSet::new();
+    for (index, asset) in assets.iter().cloned().enumerate() {
+        let chain = chain.clone();
+        pending.spawn(async move {
+            let first_provider = chain.providers_for(&asset).first().copied();
+            let result = chain.quote(&asset).await;
+            (index, asset, first_provider, result)
+        });
+    }
+
+    let progress = crate::ui::progress::ProgressPrinter::new(json_output);
+    let total = assets.len();
+    let mut updated = 0;
+    let mut items = Vec::with_capacity(total);
+    let mut fallbacks = Vec::new();
+    let mut failures = Vec::new();
+    let today = now.date_naive();
 
-                match crate::db::insert_price_history(&conn, &price_history) {
-                    Ok(_) => {
-                        println!("{} {}", "✓".green(), crate::utils::format_currency(price));
+    while let Some(joined) = pending.join_next().await {
+        let (index, asset, first_provider, result) = joined?;
+        let asset_id = asset.id.expect("asset from database must have id");
+        let outcome = match result {
+            Ok(quote) => {
+                let item = ItemResult::new("price", Some(&asset.ticker), Some(quote.date), None);
+                match provider::store(&conn, asset_id, &quote) {
+                    Ok(()) => {
+                        retry_queue::clear(&conn, asset_id)?;
+                        // Say where the price came from when it is not the usual live quote
+                        let origin = (quote.stored || Some(quote.source) != first_provider)
+                            .then(|| format!("{} {}", quote.source, quote.date.format("%d/%m/%Y")));
+                        let price = crate::utils::format_currency(quote.price);
+                        if let Some(origin) = &origin {
+                            fallbacks.push(format!("{} {} ({})", asset.ticker, price, origin));
+                        }
+                        items.push((index, item));
                         updated += 1;
+                        Ok(match origin {
+                            Some(origin) => format!("{} ({})", price, origin),
+                            None => price,
+                        })
                     }
                     Err(e) => {
-                        println!("{} {}", "✗".red(), e);
-                        errors += 1;
+                        let reason = e.to_string();
+                        items.push((index, item.failed("INSERT_FAILED", e)));
+                        Err(reason)
                     }
                 }
             }
             Err(e) => {
-                println!("{} {}", "✗".red(), e);
-                errors += 1;
+                let code = if e.rate_limited() {
+                    "YAHOO_UNAVAILABLE"
+                } else {
+                    "FETCH_FAILED"
+                };
+                let reason = e.to_string();
+                retry_queue::record_failure(&conn, asset_id, &reason, chrono::Utc::now())?;
+                let item = ItemResult::new("price", Some(&asset.ticker), Some(today), None);
+                items.push((index, item.failed(code, e)));
+                Err(reason)
+            }
+        };
+        if let Err(reason) = &outcome {
+            failures.push((asset.ticker.clone(), reason.clone()));
+        }
+
+        if progress.is_enabled() {
+            progress.handle_event(&ProgressEvent::Bar {
+                label: "Fetching prices".to_string(),
+                progress: ProgressData {
+                    current: items.len(),
+                    total: Some(total),
+                },
+            });
+        } else if !json_output {
+            match outcome {
+                Ok(price) => println!("  {} {} {}", asset.ticker, "✓".green(), price),
+                Err(reason) => println!("  {} {} {}", asset.ticker, "✗".red(), reason),
             }
         }
     }
+    let streamed = !progress.is_enabled();
+    drop(progress);
+    if !streamed {
+        crate::ui::progress::clear_progress_line();
+    }
 
-    println!("\n{} Price update complete!", "✓".green().bold());
+    // Report in the order the assets were listed, whatever order they finished in
+    items.sort_by_key(|(index, _)| *index);
+    let items: Vec<ItemResult> = items.into_iter().map(|(_, item)| item).collect();
+    let queued = retry_queue::pending(&conn)?;
+    let fx = update_fx_rates(&conn, &crate::db::get_asset_currencies(&conn)?, None).await?;
+    // A ticker that stopped quoting may have been renamed
+    let renames_flagged = if failures.is_empty() {
+        0
+    } else {
+        match crate::tickers::renames::sync_renames(&conn) {
+            Ok((opened, _)) => opened,
+            Err(e) => {
+                tracing::warn!("Rename detection failed: {}", e);
+                0
+            }
+        }
+    };
+    if json_output {
+        let data = serde_json::json!({
+            "updated": updated,
+            "errors": failures.len(),
+            "renames_flagged": renames_flagged,
+            "queued": queued.len(),
+            "next_retry_at": queued.first().map(|q| q.next_attempt_at),
+            "fx": fx,
+        });
+        let payload = crate::dispatcher::imports_helpers::batch_envelope(&data, &items);
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    if streamed {
+        println!();
+    } else if !fallbacks.is_empty() {
+        fallbacks.sort();
+        println!("{} Priced by fallback providers:", "ℹ".blue().bold());
+        for line in &fallbacks {
+            println!("  {}", line);
+        }
+        println!();
+    }
+    print_fx_updates(&fx);
+    println!("{} Price update complete!", "✓".green().bold());
     println!("  Updated: {}", updated.to_string().green());
-    if errors > 0 {
-        println!("  Errors: {}", errors.to_string().red());
+    if !failures.is_empty() {
+        failures.sort();
+        println!("  Errors: {}", failures.len().to_string().red());
+        // Streamed lines already gave each reason
+        if !streamed {
+            for (ticker, reason) in &failures {
+                println!("    {:<10} {}", ticker, reason);
+            }
+        }
+    }
+    if renames_flagged > 0 {
+        println!(
+            "\n{} {} held ticker(s) renamed on B3; see: interest inconsistencies list --type TICKER_RENAME",
+            "ℹ".blue().bold(),
+            renames_flagged
+        );
+    }
+    if let Some(next) = queued.first() {
+        println!(
+            "\n{} {} queued for retry, next try {} (run prices update again to retry)",
+            "ℹ".blue().bold(),
+            queued.len(),
+            next.next_attempt_at
+                .with_timezone(&chrono::Local)
+                .format("%d/%m/%Y %H:%M")
+        );
     }
 
     Ok(())
 }
 
-async fn dispatch_price_history(ticker: &str, from: &str, to: &str) -> Result<()> {
+/// Tickers waiting to be fetched again, with their last error and next attempt
+fn print_retry_queue(queued: &[crate::pricing::retry_queue::QueuedFetch]) {
+    println!(
+        "{} {} queued for retry after failed fetches:",
+        "ℹ".blue().bold(),
+        queued.len()
+    );
+    for entry in queued {
+        println!(
+            "  {:<10} next try {} (failed {}x: {})",
+            entry.ticker,
+            entry
+                .next_attempt_at
+                .with_timezone(&chrono::Local)
+                .format("%d/%m/%Y %H:%M"),
+            entry.attempts,
+            entry.last_error
+        );
+    }
+}
+
+async fn dispatch_price_history(
+    ticker: &str,
+    from: &str,
+    to: &str,
+    table: &crate::cli::formatters::TableArgs,
+) -> Result<()> {
     use anyhow::Context;
     use chrono::NaiveDate;
     use colored::Colorize;
-    use tabled::{
-        settings::{object::Columns, Alignment, Modify, Style},
-        Table, Tabled,
-    };
+    use tabled::Tabled;
 
     tracing::info!(
         "Fetching historical prices for {} from {} to {}",
@@ -254,11 +1170,15 @@ async fn dispatch_price_history(ticker: &str, from: &str, to: &str) -> Result<()
         low: String,
         #[tabled(rename = "Close")]
         close: String,
+        #[tabled(rename = "Adj. Close")]
+        adjusted_close: String,
         #[tabled(rename = "Volume")]
         volume: String,
     }
 
-    let rows: Vec<PriceRow> = prices
+    let page = table.paginate(prices.iter().collect());
+    let rows: Vec<PriceRow> = page
+        .rows
         .iter()
         .map(|p| PriceRow {
             date: p.date.format("%Y-%m-%d").to_string(),
@@ -278,6 +1198,10 @@ async fn dispatch_price_history(ticker: &str, from: &str, to: &str) -> Result<()
                 .map(|l| crate::utils::format_currency(*l))
                 .unwrap_or_else(|| "-".to_string()),
             close: crate::utils::format_currency(p.close),
+            adjusted_close: p
+                .adjusted_close
+                .map(crate::utils::format_currency)
+                .unwrap_or_else(|| "-".to_string()),
             volume: p
                 .volume
                 .map(|v| v.to_string())
@@ -285,16 +1209,53 @@ async fn dispatch_price_history(ticker: &str, from: &str, to: &str) -> Result<()
         })
         .collect();
 
-    let table = Table::new(rows)
-        .with(Style::rounded())
-        .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
-        .to_string();
-    println!("\n{}", table);
+    let mut out = format!(
+        "\n{}\n",
+        crate::cli::formatters::render_table(
+            &rows,
+            &table.columns,
+            &["Open", "High", "Low", "Close", "Adj. Close", "Volume"],
+        )?
+    );
+    if let Some(footer) = page.footer() {
+        out.push_str(&format!("{}\n", footer.dimmed()));
+    }
+    crate::cli::formatters::print_paged(&out, table.no_pager);
     println!(
         "\n{} Total: {} price points",
         "✓".green().bold(),
         prices.len()
     );
 
+    // Keep the adjusted series next to the stored raw closes, so returns can
+    // be computed with or without reinvested dividends
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let Some(asset_id) = crate::db::get_asset_by_ticker(&conn, ticker)?.and_then(|a| a.id) else {
+        return Ok(());
+    };
+    let adjusted: Vec<(NaiveDate, rust_decimal::Decimal)> = prices
+        .iter()
+        .filter_map(|p| p.adjusted_close.map(|a| (p.date, a)))
+        .collect();
+    let updated = crate::db::set_adjusted_closes(&conn, asset_id, &adjusted)?;
+    if updated > 0 {
+        println!(
+            "{} Stored adjusted closes for {} existing daily prices",
+            "✓".green().bold(),
+            updated
+        );
+        use crate::db::PriceSeries;
+        use crate::reports::performance::asset_return;
+        let price = asset_return(&conn, asset_id, from_date, to_date, PriceSeries::Close)?;
+        let total = asset_return(&conn, asset_id, from_date, to_date, PriceSeries::Adjusted)?;
+        if let (Some(price), Some(total)) = (price, total) {
+            println!(
+                "  Price return: {:.2}%  |  Total return (adjusted): {:.2}%",
+                price, total
+            );
+        }
+    }
+
     Ok(())
 }
@@ -0,0 +1,143 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use rust_decimal::Decimal;
+use tabled::settings::{object::Columns, Alignment, Modify, Style};
+use tabled::{Table, Tabled};
+
+use crate::reports::rebalance::{self, SuggestOptions};
+use crate::utils::format_currency;
+
+pub fn dispatch_rebalance(action: &crate::cli::RebalanceCommands, json_output: bool) -> Result<()> {
+    match action {
+        crate::cli::RebalanceCommands::Suggest {
+            amount,
+            lots,
+            skip_blocked,
+        } => suggest(
+            amount,
+            SuggestOptions {
+                round_lots: *lots,
+                skip_blocked: *skip_blocked,
+            },
+            json_output,
+        ),
+    }
+}
+
+fn suggest(amount: &str, options: SuggestOptions, json_output: bool) -> Result<()> {
+    let amount: Decimal = amount
+        .trim()
+        .replace(',', ".")
+        .parse()
+        .with_context(|| format!("Invalid amount '{}'", amount))?;
+
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let targets = crate::config::load_config()?
+        .rebalance
+        .map(|r| r.targets)
+        .unwrap_or_default();
+    let report = crate::reports::calculate_portfolio(&conn, None)?;
+    let plan = rebalance::suggest(&conn, &report, &targets, amount, options)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&plan)?);
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Contribution of {}\n",
+        "🧭".cyan().bold(),
+        format_currency(plan.amount).bold()
+    );
+
+    if plan.buys.is_empty() {
+        println!(
+            "{} Nothing to buy: every target is met or no lot fits the amount",
+            "ℹ".blue().bold()
+        );
+    } else {
+        #[derive(Tabled)]
+        struct BuyRow {
+            #[tabled(rename = "Ticker")]
+            ticker: String,
+            #[tabled(rename = "Target")]
+            group: String,
+            #[tabled(rename = "Quantity")]
+            quantity: String,
+            #[tabled(rename = "Price")]
+            price: String,
+            #[tabled(rename = "Amount")]
+            amount: String,
+        }
+        let rows: Vec<BuyRow> = plan
+            .buys
+            .iter()
+            .map(|b| BuyRow {
+                ticker: b.ticker.clone(),
+                group: b.group.clone(),
+                quantity: b.quantity.to_string(),
+                price: format_currency(b.price),
+                amount: format_currency(b.amount),
+            })
+            .collect();
+        println!(
+            "{}",
+            Table::new(rows)
+                .with(Style::rounded())
+                .with(Modify::new(Columns::new(2..)).with(Alignment::right()))
+        );
+    }
+
+    #[derive(Tabled)]
+    struct GroupRow {
+        #[tabled(rename = "Target")]
+        key: String,
+        #[tabled(rename = "Goal")]
+        target: String,
+        #[tabled(rename = "Now")]
+        now: String,
+        #[tabled(rename = "After")]
+        after: String,
+    }
+    let rows: Vec<GroupRow> = plan
+        .groups
+        .iter()
+        .map(|g| GroupRow {
+            key: g.key.clone(),
+            target: format!("{:.2}%", g.target_pct),
+            now: format!("{:.2}%", g.current_pct),
+            after: format!("{:.2}%", g.after_pct),
+        })
+        .collect();
+    println!(
+        "\n{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+    );
+
+    println!(
+        "\nSpent {}, left over {}",
+        format_currency(plan.spent).green(),
+        format_currency(plan.leftover)
+    );
+    if plan.untargeted_value > Decimal::ZERO {
+        println!(
+            "{}",
+            format!(
+                "{} held outside every target counts in the total but gets no money",
+                format_currency(plan.untargeted_value)
+            )
+            .dimmed()
+        );
+    }
+    if !plan.skipped.is_empty() {
+        println!("\nNot bought:");
+        for s in &plan.skipped {
+            println!("  {:<8} {}", s.ticker, s.reason.dimmed());
+        }
+    }
+    println!();
+    Ok(())
+}
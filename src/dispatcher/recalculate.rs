@@ -0,0 +1,64 @@
+use anyhow::Result;
+use chrono::Datelike;
+use colored::Colorize;
+
+use crate::db;
+use crate::reports::recalculate::recalculate;
+
+/// Clear every cache and rebuild it from transactions and corporate actions
+pub fn dispatch_recalculate(through: Option<i32>, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let mut conn = db::open_db(None)?;
+    let through_year = through.unwrap_or_else(|| chrono::Local::now().year());
+
+    if !json_output {
+        println!(
+            "{} Rebuilding snapshots and tax state through {}...",
+            "🔄".cyan().bold(),
+            through_year
+        );
+    }
+    let summary = recalculate(&mut conn, through_year)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    println!("\n{} Recalculation complete!", "✓".green().bold());
+    println!(
+        "  Cleared: {} portfolio snapshot rows, {} carryforward snapshot rows, {} ledger entries",
+        summary.snapshots_cleared,
+        summary.carryforward_snapshots_cleared,
+        summary.carryforward_entries_cleared
+            + summary.legacy_rows_cleared
+            + summary.tax_ledger_rows_cleared
+    );
+    match summary.tax_years {
+        Some((first, last)) if first == last => {
+            println!("  Tax year recomputed: {}", first.to_string().green())
+        }
+        Some((first, last)) => println!(
+            "  Tax years recomputed: {}",
+            format!("{}-{}", first, last).green()
+        ),
+        None => println!("  No transactions to recompute taxes from"),
+    }
+    println!(
+        "  Portfolio snapshots rebuilt: {}",
+        summary.snapshots_rebuilt.to_string().green()
+    );
+    if !summary.irpf_years_kept.is_empty() {
+        let years: Vec<String> = summary
+            .irpf_years_kept
+            .iter()
+            .map(|y| y.to_string())
+            .collect();
+        println!(
+            "  {} Kept the loss carryforward imported from IRPF for {}",
+            "ℹ".blue().bold(),
+            years.join(", ")
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,218 @@
+//! Reports command dispatcher: the annual review, on screen or as a PDF
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use colored::Colorize;
+use rust_decimal::Decimal;
+
+use crate::db;
+use crate::reports::annual::{self, AnnualReport};
+use crate::reports::benchmark::BenchmarkSpec;
+use crate::ui::progress::{ProgressEvent, ProgressPrinter};
+use crate::utils::format_currency;
+
+pub async fn dispatch_reports(
+    action: &crate::cli::ReportsCommands,
+    json_output: bool,
+) -> Result<()> {
+    match action {
+        crate::cli::ReportsCommands::Annual {
+            year,
+            pdf,
+            output,
+            vs,
+        } => dispatch_annual(*year, *pdf, output.as_deref(), vs, json_output).await,
+    }
+}
+
+async fn dispatch_annual(
+    year: i32,
+    pdf: bool,
+    output: Option<&str>,
+    vs: &[String],
+    json_output: bool,
+) -> Result<()> {
+    let specs = if vs.is_empty() {
+        annual::DEFAULT_BENCHMARKS
+            .iter()
+            .map(|s| s.parse::<BenchmarkSpec>())
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        vs.iter()
+            .map(|s| s.parse::<BenchmarkSpec>())
+            .collect::<Result<Vec<_>>>()?
+    };
+    db::init_database(None)?;
+    let mut conn = db::open_db(None)?;
+
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+    let to = NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?
+        .min(chrono::Local::now().date_naive());
+    if to >= from {
+        ensure_prices(&mut conn, from, to, json_output).await?;
+    }
+
+    let report = annual::annual_report(&mut conn, year, &specs)?;
+
+    if pdf {
+        let path = output
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("annual_report_{}.pdf", year));
+        std::fs::write(&path, annual::render_pdf(&report)?)?;
+        if json_output {
+            println!("{}", serde_json::json!({ "year": year, "pdf": path }));
+        } else {
+            println!(
+                "\n{} Annual report for {} written to: {}\n",
+                "✓".green().bold(),
+                year,
+                path
+            );
+        }
+        return Ok(());
+    }
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print_annual(&report);
+    }
+    Ok(())
+}
+
+/// Fetch the year's prices the way `performance show` does, unless disabled
+async fn ensure_prices(
+    conn: &mut rusqlite::Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+    json_output: bool,
+) -> Result<()> {
+    let skip_price_fetch = std::env::var("INTEREST_SKIP_PRICE_FETCH")
+        .map(|v| v != "0")
+        .unwrap_or(false);
+    // Private fixed income is valued from PUs accrued locally
+    crate::fixed_income::accrue_all(conn, to)?;
+    if skip_price_fetch {
+        return Ok(());
+    }
+    let assets = db::get_assets_with_transactions(conn)?;
+    let Some(earliest) = db::get_earliest_transaction_date(conn)? else {
+        return Ok(());
+    };
+    let printer = ProgressPrinter::new(json_output);
+    printer.handle_event(&ProgressEvent::Spinner {
+        message: "Fetching prices...".to_string(),
+    });
+    if let Err(e) =
+        crate::pricing::resolver::ensure_prices_available(conn, &assets, (earliest.max(from), to))
+            .await
+    {
+        tracing::warn!("Price resolution failed: {}", e);
+    }
+    drop(printer);
+    Ok(())
+}
+
+fn colored_pct(value: Decimal) -> String {
+    let text = format!("{:+.2}%", value);
+    if value < Decimal::ZERO {
+        text.red().to_string()
+    } else {
+        text.green().to_string()
+    }
+}
+
+fn print_annual(report: &AnnualReport) {
+    println!(
+        "\n{} Annual report {} ({} to {})\n",
+        "📘".cyan().bold(),
+        report.year,
+        report.start_date.format("%d/%m/%Y"),
+        report.end_date.format("%d/%m/%Y")
+    );
+    println!(
+        "  Value:        {} → {}",
+        format_currency(report.start_value),
+        format_currency(report.end_value)
+    );
+    println!("  Return (TWR): {}", colored_pct(report.return_pct));
+    if let Some(mwr) = report.money_weighted_return {
+        println!("  Return (MWR): {}", colored_pct(mwr));
+    }
+    for b in &report.benchmarks {
+        match (b.return_pct, b.excess_pct) {
+            (Some(ret), Some(excess)) => println!(
+                "    vs {:8} {:>8.2}%  {:+.2} p.p.",
+                b.benchmark, ret, excess
+            ),
+            _ => println!("    vs {:8} {}", b.benchmark, "no data".dimmed()),
+        }
+    }
+
+    println!("\n  {} Allocation", "🧩".cyan().bold());
+    for slice in report
+        .allocation
+        .iter()
+        .filter(|s| s.end_value > Decimal::ZERO)
+    {
+        println!(
+            "    {:10} {:>16}  {:>6.2}%",
+            slice.asset_type.as_str(),
+            format_currency(slice.end_value),
+            slice.end_pct
+        );
+    }
+
+    println!("\n  {} Income and tax", "💰".cyan().bold());
+    println!(
+        "    Income:   {} net ({} withheld)",
+        format_currency(report.income.net),
+        format_currency(report.income.withheld)
+    );
+    print!("    Tax due on sales: {}", format_currency(report.tax.due));
+    match report.tax.paid {
+        Some(paid) => println!(", DARFs paid: {}", format_currency(paid)),
+        None => println!(),
+    }
+    if let Some(flows) = &report.flows {
+        println!(
+            "    Net new capital: {}",
+            format_currency(flows.net_new_capital)
+        );
+    }
+
+    for (title, assets) in [
+        (
+            "Top contributors",
+            report.contributors().collect::<Vec<_>>(),
+        ),
+        ("Top detractors", report.detractors().collect::<Vec<_>>()),
+    ] {
+        if assets.is_empty() {
+            continue;
+        }
+        println!("\n  {} {}", "🏅".cyan().bold(), title);
+        for a in assets {
+            let result = format_currency(a.result);
+            println!(
+                "    {:10} {:>16}",
+                a.ticker,
+                if a.result < Decimal::ZERO {
+                    result.red()
+                } else {
+                    result.green()
+                }
+            );
+        }
+    }
+    println!(
+        "\n  {}\n",
+        format!(
+            "Share it as a PDF: interest reports annual {} --pdf",
+            report.year
+        )
+        .dimmed()
+    );
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use colored::Colorize;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::db::sandbox;
+
+pub fn dispatch_sandbox(action: &crate::cli::SandboxCommands, json_output: bool) -> Result<()> {
+    match action {
+        crate::cli::SandboxCommands::Status => show_status(json_output),
+        crate::cli::SandboxCommands::Discard => {
+            let discarded = sandbox::discard()?;
+            if json_output {
+                println!("{}", serde_json::json!({ "discarded": discarded }));
+            } else if discarded {
+                println!("{} Sandbox discarded", "✓".green().bold());
+            } else {
+                println!("{} No sandbox to discard", "ℹ".blue().bold());
+            }
+            Ok(())
+        }
+        crate::cli::SandboxCommands::Promote { force } => {
+            let backup = sandbox::promote(*force)?;
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::json!({ "promoted": true, "backup": backup })
+                );
+            } else {
+                println!(
+                    "{} Sandbox promoted to the live database",
+                    "✓".green().bold()
+                );
+                println!("  Previous data backed up to {}", backup.display());
+            }
+            Ok(())
+        }
+    }
+}
+
+fn show_status(json_output: bool) -> Result<()> {
+    let status = sandbox::status()?;
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    let Some(status) = status else {
+        println!(
+            "{} No sandbox. Start one by adding --sandbox to any command.",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    };
+
+    println!("\n{} Sandbox: {}", "🧪".bold(), status.path.display());
+    if let Some(created_at) = status.created_at {
+        println!(
+            "  Created: {}",
+            created_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+        );
+    }
+
+    #[derive(Tabled)]
+    struct DiffRow {
+        #[tabled(rename = "Table")]
+        table: String,
+        #[tabled(rename = "Live")]
+        live: i64,
+        #[tabled(rename = "Sandbox")]
+        sandbox: i64,
+        #[tabled(rename = "Change")]
+        change: String,
+    }
+
+    let rows: Vec<DiffRow> = status
+        .tables
+        .iter()
+        .filter(|t| t.live != 0 || t.sandbox != 0)
+        .map(|t| DiffRow {
+            table: t.table.clone(),
+            live: t.live,
+            sandbox: t.sandbox,
+            change: match t.sandbox - t.live {
+                0 => "-".to_string(),
+                d => format!("{:+}", d),
+            },
+        })
+        .collect();
+    let table = Table::new(rows)
+        .with(Style::rounded())
+        .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+        .to_string();
+    println!("{}", table);
+
+    if status.live_changed {
+        println!(
+            "\n{} The live database changed after the sandbox was created; \
+             promoting requires --force and drops those changes.",
+            "⚠".yellow().bold()
+        );
+    }
+    println!(
+        "\n  Report on it with --sandbox (e.g. interest --sandbox portfolio show), \
+         then run 'interest sandbox promote' or 'interest sandbox discard'."
+    );
+    Ok(())
+}
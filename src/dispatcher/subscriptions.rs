@@ -0,0 +1,66 @@
+use anyhow::Result;
+use colored::Colorize;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::db;
+use crate::utils::format_currency;
+
+/// Rights and receipts held, waiting to be sold, exercised or converted
+pub fn dispatch_subscriptions(json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let positions = crate::subscriptions::open_positions(&conn)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&positions)?);
+        return Ok(());
+    }
+
+    if positions.is_empty() {
+        println!(
+            "{} No subscription rights or receipts held",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct SubscriptionRow {
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        kind: String,
+        #[tabled(rename = "Converts into")]
+        base_ticker: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Cost")]
+        total_cost: String,
+    }
+
+    let rows: Vec<SubscriptionRow> = positions
+        .iter()
+        .map(|p| SubscriptionRow {
+            ticker: p.ticker.clone(),
+            kind: p.kind.to_string(),
+            base_ticker: p.base_ticker.clone(),
+            quantity: p.quantity.to_string(),
+            total_cost: format_currency(p.total_cost),
+        })
+        .collect();
+
+    println!(
+        "\n{} Subscription rights and receipts\n",
+        "🧾".cyan().bold()
+    );
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(3..)).with(Alignment::right()))
+    );
+    Ok(())
+}
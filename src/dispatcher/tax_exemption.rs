@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use chrono::Datelike;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use crate::db;
+use crate::tax::sales_monitor::{self, ExemptionStatus};
+use crate::utils::format_currency;
+
+fn parse_month(month_str: Option<&str>) -> Result<(i32, u32)> {
+    let Some(month_str) = month_str else {
+        let today = chrono::Local::now().date_naive();
+        return Ok((today.year(), today.month()));
+    };
+    let (month, year) = month_str
+        .split_once('/')
+        .ok_or_else(|| anyhow::anyhow!("Invalid month format. Use MM/YYYY (e.g., 01/2025)"))?;
+    let month: u32 = month.parse().context("Invalid month number")?;
+    let year: i32 = year.parse().context("Invalid year")?;
+    if !(1..=12).contains(&month) {
+        anyhow::bail!("Month must be between 01 and 12");
+    }
+    Ok((year, month))
+}
+
+pub fn dispatch_tax_exemption(
+    month: Option<&str>,
+    sell: Option<&str>,
+    ticker: Option<&str>,
+    day_trade: bool,
+    json_output: bool,
+) -> Result<()> {
+    let (year, month) = parse_month(month)?;
+    let amount = sell
+        .map(Decimal::from_str)
+        .transpose()
+        .context("Invalid sale amount. Must be a decimal number")?;
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let usage = sales_monitor::month_usage(&conn, year, month)?;
+
+    let planned = match amount {
+        Some(amount) => {
+            let (asset_type, abroad) = match ticker {
+                Some(ticker) => {
+                    let asset = db::get_asset_by_ticker(&conn, &ticker.to_uppercase())?
+                        .ok_or_else(|| anyhow::anyhow!("Ticker {} not found", ticker))?;
+                    let abroad = match asset.id {
+                        Some(id) => db::get_asset_currency(&conn, id)?.is_some(),
+                        None => false,
+                    };
+                    (asset.asset_type, abroad)
+                }
+                None => (db::AssetType::Stock, false),
+            };
+            Some(sales_monitor::plan_sale(
+                &usage,
+                &asset_type,
+                day_trade,
+                abroad,
+                amount,
+            ))
+        }
+        None => None,
+    };
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "year": usage.year,
+                "month": usage.month,
+                "categories": usage.categories,
+                "planned_sale": planned,
+            }))?
+        );
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Sales vs monthly exemption - {:02}/{} (all brokers)\n",
+        "🧾".cyan().bold(),
+        month,
+        year
+    );
+    for c in &usage.categories {
+        let name = format!("{:22}", c.category.display_name());
+        let sales = crate::utils::format_currency_aligned(c.sales, 16);
+        let status = match c.status {
+            None => "no exemption".dimmed(),
+            Some(status) => {
+                let used = if c.exemption_sales == c.sales {
+                    format!("of {}", format_currency(c.limit))
+                } else {
+                    format!(
+                        "({} count) of {}",
+                        format_currency(c.exemption_sales),
+                        format_currency(c.limit)
+                    )
+                };
+                match status {
+                    ExemptionStatus::Ok => {
+                        format!("{}, {} left", used, format_currency(c.remaining())).normal()
+                    }
+                    ExemptionStatus::Approaching => format!(
+                        "{} ⚠ approaching limit ({} left)",
+                        used,
+                        format_currency(c.remaining())
+                    )
+                    .yellow(),
+                    ExemptionStatus::Exceeded => {
+                        format!("{} ✗ exceeded - gains taxable", used).red()
+                    }
+                }
+            }
+        };
+        println!("  {}  {}  {}", name, sales, status);
+    }
+
+    if let Some(p) = planned {
+        println!();
+        let label = format!(
+            "Selling {} more in {}",
+            format_currency(p.amount),
+            p.category.display_name()
+        );
+        if !p.counts {
+            println!("  {}: does not count towards any exemption", label);
+        } else if p.breaks_exemption() {
+            println!(
+                "  {} {}: month sales reach {}, over {}. The month's gains become taxable.",
+                "✗".red().bold(),
+                label,
+                format_currency(p.exemption_sales_after),
+                format_currency(p.limit)
+            );
+            println!(
+                "  {}",
+                "See the tax due with: interest tax simulate --ticker <T> --quantity <Q> --price <P>"
+                    .dimmed()
+            );
+        } else if p.status_after == Some(ExemptionStatus::Exceeded) {
+            println!("  {}: the exemption is already exceeded this month", label);
+        } else {
+            println!(
+                "  {} {}: stays exempt, {} left after it",
+                "✓".green().bold(),
+                label,
+                format_currency((p.limit - p.exemption_sales_after).max(Decimal::ZERO))
+            );
+        }
+    }
+    println!();
+    Ok(())
+}
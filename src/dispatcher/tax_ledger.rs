@@ -0,0 +1,356 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::db;
+use crate::tax::darf::{self, calculate_darf_due_date};
+use crate::tax::ledger::{self, DarfStatus, LedgerEntry};
+use crate::utils::format_currency;
+
+fn ensure_unscoped() -> Result<()> {
+    if db::portfolio::is_scoped() {
+        anyhow::bail!(
+            "The tax ledger covers all portfolios; run it without --portfolio or --declarant"
+        );
+    }
+    Ok(())
+}
+
+/// DARF column of a month: the payment, or the due date while pending
+fn darf_label(year: i32, month: u32, rows: &[&LedgerEntry]) -> String {
+    let tax: Decimal = rows.iter().map(|r| r.tax_due).sum();
+    if let Some(paid) = rows.iter().find(|r| r.darf_status == DarfStatus::Paid) {
+        let amount = paid.paid_amount.unwrap_or(Decimal::ZERO);
+        let label = format!(
+            "paid {} {}",
+            paid.paid_on
+                .map(|d| d.format("%d/%m/%Y").to_string())
+                .unwrap_or_default(),
+            format_currency(amount)
+        );
+        if amount != tax {
+            return format!(
+                "{} {}",
+                label,
+                format!("(now {})", format_currency(tax)).yellow()
+            );
+        }
+        return label.green().to_string();
+    }
+    if rows.iter().any(|r| r.darf_status == DarfStatus::Pending) {
+        return match calculate_darf_due_date(year, month) {
+            Ok(due) => format!("pending, due {}", due.format("%d/%m/%Y"))
+                .yellow()
+                .to_string(),
+            Err(_) => "pending".yellow().to_string(),
+        };
+    }
+    "-".to_string()
+}
+
+pub fn dispatch_tax_ledger(year: Option<i32>, json_output: bool) -> Result<()> {
+    ensure_unscoped()?;
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    if let Some(year) = year {
+        // Brings the year's rows up to date with the transactions
+        crate::tax::irpf::generate_annual_report(&conn, year)?;
+    }
+    let entries = ledger::entries(&conn, year)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!(
+            "{} No tax computed yet. Run: interest tax ledger <year>",
+            "ℹ".blue().bold()
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct LedgerRow {
+        #[tabled(rename = "Month")]
+        month: String,
+        #[tabled(rename = "Category")]
+        category: String,
+        #[tabled(rename = "Sales")]
+        sales: String,
+        #[tabled(rename = "P&L")]
+        profit_loss: String,
+        #[tabled(rename = "Offset")]
+        offset: String,
+        #[tabled(rename = "Exempt")]
+        exemption: String,
+        #[tabled(rename = "Tax")]
+        tax: String,
+        #[tabled(rename = "DARF")]
+        darf: String,
+    }
+
+    let dash_if_zero = |v: Decimal| {
+        if v.is_zero() {
+            "-".to_string()
+        } else {
+            format_currency(v)
+        }
+    };
+    let mut rows = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let first_of_month =
+            i == 0 || (entries[i - 1].year, entries[i - 1].month) != (entry.year, entry.month);
+        let darf = if first_of_month {
+            let month_rows: Vec<&LedgerEntry> = entries
+                .iter()
+                .filter(|e| (e.year, e.month) == (entry.year, entry.month))
+                .collect();
+            darf_label(entry.year, entry.month, &month_rows)
+        } else {
+            String::new()
+        };
+        rows.push(LedgerRow {
+            month: if first_of_month {
+                format!("{:02}/{}", entry.month, entry.year)
+            } else {
+                String::new()
+            },
+            category: entry.category.display_name().to_string(),
+            sales: dash_if_zero(entry.sales),
+            profit_loss: format_currency(entry.profit_loss),
+            offset: dash_if_zero(entry.loss_offset),
+            exemption: dash_if_zero(entry.exemption),
+            tax: dash_if_zero(entry.tax_due),
+            darf,
+        });
+    }
+
+    match year {
+        Some(year) => println!("\n{} Tax ledger - {}\n", "📒".cyan().bold(), year),
+        None => println!("\n{} Tax ledger\n", "📒".cyan().bold()),
+    }
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(2..7)).with(Alignment::right()))
+    );
+    let tax: Decimal = entries.iter().map(|e| e.tax_due).sum();
+    let paid: Decimal = entries
+        .iter()
+        .filter(|e| e.darf_status == DarfStatus::Paid)
+        .map(|e| (e.year, e.month, e.paid_amount.unwrap_or(Decimal::ZERO)))
+        .collect::<std::collections::BTreeSet<_>>()
+        .iter()
+        .map(|(_, _, amount)| *amount)
+        .sum();
+    println!(
+        "\nTax: {}   Paid: {}",
+        format_currency(tax).bold(),
+        format_currency(paid).bold()
+    );
+    println!(
+        "{}",
+        "Record a payment with: interest tax mark-paid MM/YYYY [--on DATE] [--amount X]".dimmed()
+    );
+    Ok(())
+}
+
+fn parse_month(month_str: &str) -> Result<(i32, u32)> {
+    let (month, year) = month_str
+        .split_once('/')
+        .ok_or_else(|| anyhow::anyhow!("Invalid month format. Use MM/YYYY (e.g., 01/2025)"))?;
+    let month: u32 = month.parse().context("Invalid month number")?;
+    let year: i32 = year.parse().context("Invalid year")?;
+    if !(1..=12).contains(&month) {
+        anyhow::bail!("Month must be between 01 and 12");
+    }
+    Ok((year, month))
+}
+
+pub fn dispatch_tax_darf(month_str: &str, plain: bool, json_output: bool) -> Result<()> {
+    ensure_unscoped()?;
+    let (year, month) = parse_month(month_str)?;
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    crate::tax::irpf::generate_annual_report(&conn, year)?;
+    let entries = ledger::entries(&conn, Some(year))?;
+    let paid = entries
+        .iter()
+        .find(|e| e.month == month && e.darf_status == DarfStatus::Paid);
+    let slips = darf::darf_slips(&entries, year, month)?;
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "year": year,
+                "month": month,
+                "paid_on": paid.and_then(|p| p.paid_on),
+                "darfs": slips,
+            }))?
+        );
+        return Ok(());
+    }
+
+    if slips.is_empty() {
+        if !plain {
+            println!(
+                "{} No DARF due for {:02}/{}",
+                "ℹ".blue().bold(),
+                month,
+                year
+            );
+        }
+        return Ok(());
+    }
+
+    let today = chrono::Local::now().date_naive();
+    if plain {
+        // The PIX QR code is issued by the Receita with each DARF; it cannot
+        // be built from these fields
+        let blocks: Vec<String> = slips
+            .iter()
+            .map(|slip| {
+                let mut block = darf::format_plain(slip);
+                if slip.payable && slip.due_date < today {
+                    block.push_str("\nVencido: o Sicalc Web soma multa e juros");
+                }
+                if slip.payable {
+                    block.push_str("\nPIX: QR code no DARF emitido pelo Sicalc Web");
+                } else {
+                    block.push_str("\nAbaixo de R$ 10,00: somar ao DARF do mês seguinte");
+                }
+                block
+            })
+            .collect();
+        println!("{}", blocks.join("\n\n"));
+        return Ok(());
+    }
+
+    println!("\n{} DARF {:02}/{}\n", "💳".cyan().bold(), month, year);
+    for slip in &slips {
+        println!("  Código da receita:   {}", slip.darf_code.bold());
+        println!("  Período de apuração: {}", slip.period.format("%d/%m/%Y"));
+        println!(
+            "  Vencimento:          {}",
+            slip.due_date.format("%d/%m/%Y").to_string().yellow()
+        );
+        if slip.carried > Decimal::ZERO {
+            println!("  Tax of the month:    {}", format_currency(slip.month_tax));
+            println!("  Carried (under R$10): {}", format_currency(slip.carried));
+        }
+        println!(
+            "  Valor:               {}",
+            format_currency(slip.amount).red().bold()
+        );
+        if !slip.payable {
+            println!(
+                "  {} Under the R$10 minimum: no DARF is issued, it is added to next month's",
+                "ℹ".blue().bold()
+            );
+        } else if slip.due_date < today {
+            println!(
+                "  {} Overdue: Sicalc adds multa and juros when issuing it",
+                "⚠".yellow().bold()
+            );
+        }
+        println!();
+    }
+    if let Some(paid) = paid {
+        println!(
+            "{} Already marked paid{}",
+            "✓".green().bold(),
+            paid.paid_on
+                .map(|d| format!(" on {}", d.format("%d/%m/%Y")))
+                .unwrap_or_default()
+        );
+    }
+    if slips.iter().any(|s| s.payable) {
+        println!(
+            "{}",
+            "To pay by PIX, issue it in Sicalc Web with these fields; the QR code is printed on the DARF"
+                .dimmed()
+        );
+    }
+    println!(
+        "{}",
+        "Copy-friendly fields: interest tax darf MM/YYYY --plain".dimmed()
+    );
+    Ok(())
+}
+
+pub fn dispatch_tax_mark_paid(
+    month_str: &str,
+    on: Option<&str>,
+    amount: Option<&str>,
+    clear: bool,
+    json_output: bool,
+) -> Result<()> {
+    ensure_unscoped()?;
+    let (year, month) = parse_month(month_str)?;
+    let date = match on {
+        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .with_context(|| format!("Invalid date '{}'. Use YYYY-MM-DD format", s))?,
+        None => chrono::Local::now().date_naive(),
+    };
+    let amount = amount
+        .map(Decimal::from_str)
+        .transpose()
+        .context("Invalid amount. Must be a decimal number")?;
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    if clear {
+        ledger::clear_paid(&conn, year, month)?;
+        if json_output {
+            println!(
+                "{}",
+                serde_json::json!({ "success": true, "year": year, "month": month, "cleared": true })
+            );
+        } else {
+            println!(
+                "{} Payment for {:02}/{} cleared",
+                "✓".green().bold(),
+                month,
+                year
+            );
+        }
+        return Ok(());
+    }
+
+    crate::tax::irpf::generate_annual_report(&conn, year)?;
+    let paid = ledger::mark_paid(&conn, year, month, date, amount)?;
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "success": true,
+                "year": year,
+                "month": month,
+                "paid_on": date,
+                "paid_amount": paid,
+            }))?
+        );
+    } else {
+        println!(
+            "{} DARF {:02}/{} paid on {}: {}",
+            "✓".green().bold(),
+            month,
+            year,
+            date.format("%d/%m/%Y"),
+            format_currency(paid)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,482 @@
+use anyhow::Result;
+use chrono::Datelike;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::db::AssetType;
+use crate::tax::rules::{
+    self, CarneLeaoRule, CategoryRule, FixedIncomeRule, FundIncomeRule, IncomeWithholdingRule,
+    PayerDividendRule, WithholdingRule,
+};
+use crate::utils::format_currency;
+
+fn since_label(since: (i32, u32)) -> String {
+    format!("{:02}/{}", since.1, since.0)
+}
+
+/// Dated tax rules, marking the ones in force in December of `year`
+pub fn dispatch_tax_rules(year: Option<i32>, json_output: bool) -> Result<()> {
+    let year = year.unwrap_or_else(|| chrono::Local::now().year());
+    let hundred = Decimal::from(100);
+    let category_in_force =
+        |r: &CategoryRule| std::ptr::eq(rules::category_rule(&r.category, year, 12), r);
+    let withholding_in_force =
+        |r: &WithholdingRule| std::ptr::eq(rules::withholding_rule(year, 12), r);
+    let fixed_income_in_force =
+        |r: &FixedIncomeRule| std::ptr::eq(rules::fixed_income_rule(year, 12), r);
+    let carne_leao_in_force = |r: &CarneLeaoRule| std::ptr::eq(rules::carne_leao_rule(year, 12), r);
+    let fund_income_in_force =
+        |r: &FundIncomeRule| std::ptr::eq(rules::fund_income_rule(year, 12), r);
+    let payer_dividend_in_force =
+        |r: &PayerDividendRule| std::ptr::eq(rules::payer_dividend_rule(year, 12), r);
+    let income_withholding_in_force = |r: &IncomeWithholdingRule| {
+        let asset_type = r.asset_types.first().unwrap_or(&AssetType::Unknown);
+        std::ptr::eq(
+            rules::income_withholding_rule(&r.event_type, asset_type, year, 12),
+            r,
+        )
+    };
+    let brackets = |r: &FixedIncomeRule| {
+        let mut parts: Vec<String> = r
+            .ir_brackets
+            .iter()
+            .map(|(days, rate)| format!("{}% up to {} days", (rate * hundred).normalize(), days))
+            .collect();
+        parts.push(format!(
+            "{}% after",
+            (r.long_term_rate * hundred).normalize()
+        ));
+        parts.join(", ")
+    };
+
+    if json_output {
+        let categories: Vec<_> = rules::category_rules()
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "category": r.category.as_str(),
+                    "since": since_label(r.since),
+                    "rate": r.rate,
+                    "gain_brackets": r.gain_brackets.iter().map(|(up_to, rate)| {
+                        serde_json::json!({ "up_to": up_to, "rate": rate })
+                    }).collect::<Vec<_>>(),
+                    "monthly_exemption": r.monthly_exemption,
+                    "exempt_asset_types": r.exempt_asset_types,
+                    "annual": r.annual,
+                    "legal_basis": r.legal_basis,
+                    "in_force": category_in_force(r),
+                })
+            })
+            .collect();
+        let withholding: Vec<_> = rules::withholding_rules()
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "since": since_label(r.since),
+                    "swing_sale_rate": r.swing_sale_rate,
+                    "day_trade_rate": r.day_trade_rate,
+                    "min_retention": r.min_retention,
+                    "dividends_compensable": r.dividends_compensable,
+                    "legal_basis": r.legal_basis,
+                    "in_force": withholding_in_force(r),
+                })
+            })
+            .collect();
+        let fixed_income: Vec<_> = rules::fixed_income_rules()
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "since": since_label(r.since),
+                    "ir_brackets": r.ir_brackets.iter().map(|(days, rate)| {
+                        serde_json::json!({ "up_to_days": days, "rate": rate })
+                    }).collect::<Vec<_>>(),
+                    "long_term_rate": r.long_term_rate,
+                    "iof_by_day": r.iof_by_day,
+                    "legal_basis": r.legal_basis,
+                    "in_force": fixed_income_in_force(r),
+                })
+            })
+            .collect();
+        let carne_leao: Vec<_> = rules::carne_leao_rules()
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "since": since_label(r.since),
+                    "brackets": r.brackets.iter().map(|(up_to, rate, deduction)| {
+                        serde_json::json!({ "up_to": up_to, "rate": rate, "deduction": deduction })
+                    }).collect::<Vec<_>>(),
+                    "top_rate": r.top_rate,
+                    "top_deduction": r.top_deduction,
+                    "exempt_up_to": r.exempt_up_to(),
+                    "legal_basis": r.legal_basis,
+                    "in_force": carne_leao_in_force(r),
+                })
+            })
+            .collect();
+        let fund_income: Vec<_> = rules::fund_income_rules()
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "since": since_label(r.since),
+                    "rate": r.rate,
+                    "taxed_from": r.taxed_from.map(since_label),
+                    "legal_basis": r.legal_basis,
+                    "in_force": fund_income_in_force(r),
+                })
+            })
+            .collect();
+        let income_withholding: Vec<_> = rules::income_withholding_rules()
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "event_type": r.event_type.as_str(),
+                    "asset_types": r.asset_types,
+                    "since": since_label(r.since),
+                    "rate": r.rate,
+                    "foreign_rate": r.foreign_rate,
+                    "legal_basis": r.legal_basis,
+                    "in_force": income_withholding_in_force(r),
+                })
+            })
+            .collect();
+        let payer_dividends: Vec<_> = rules::payer_dividend_rules()
+            .iter()
+            .map(|r| {
+                serde_json::json!({
+                    "since": since_label(r.since),
+                    "rate": r.rate,
+                    "monthly_threshold": r.monthly_threshold,
+                    "legal_basis": r.legal_basis,
+                    "in_force": payer_dividend_in_force(r),
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "year": year,
+                "categories": categories,
+                "withholding": withholding,
+                "income_withholding": income_withholding,
+                "payer_dividends": payer_dividends,
+                "fixed_income": fixed_income,
+                "carne_leao": carne_leao,
+                "fund_income": fund_income,
+            }))?
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct CategoryRow {
+        #[tabled(rename = "Category")]
+        category: String,
+        #[tabled(rename = "Since")]
+        since: String,
+        #[tabled(rename = "Rate")]
+        rate: String,
+        #[tabled(rename = "Monthly exemption")]
+        exemption: String,
+        #[tabled(rename = "Legal basis")]
+        legal_basis: String,
+    }
+
+    let rows: Vec<CategoryRow> = rules::category_rules()
+        .iter()
+        .map(|r| {
+            let marker = if category_in_force(r) { " *" } else { "" };
+            CategoryRow {
+                category: format!("{}{}", r.category.display_name(), marker),
+                since: since_label(r.since),
+                rate: format!(
+                    "{}{}%{}",
+                    r.gain_brackets
+                        .first()
+                        .map(|(_, rate)| format!("{}% to ", (rate * hundred).normalize()))
+                        .unwrap_or_default(),
+                    (r.rate * hundred).normalize(),
+                    if r.annual { " (annual)" } else { "" }
+                ),
+                exemption: if r.monthly_exemption.is_zero() {
+                    "-".to_string()
+                } else {
+                    let types: Vec<&str> =
+                        r.exempt_asset_types.iter().map(|t| t.as_str()).collect();
+                    format!(
+                        "{} ({})",
+                        format_currency(r.monthly_exemption),
+                        types.join(", ")
+                    )
+                },
+                legal_basis: r.legal_basis.to_string(),
+            }
+        })
+        .collect();
+
+    println!("\n{} Capital gains rules\n", "⚖".cyan().bold());
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(2..4)).with(Alignment::right()))
+    );
+
+    #[derive(Tabled)]
+    struct WithholdingRow {
+        #[tabled(rename = "Since")]
+        since: String,
+        #[tabled(rename = "Swing sales")]
+        swing: String,
+        #[tabled(rename = "Day-trade gains")]
+        day_trade: String,
+        #[tabled(rename = "Dividend IRRF")]
+        dividends: String,
+        #[tabled(rename = "Legal basis")]
+        legal_basis: String,
+    }
+
+    let rows: Vec<WithholdingRow> = rules::withholding_rules()
+        .iter()
+        .map(|r| WithholdingRow {
+            since: format!(
+                "{}{}",
+                since_label(r.since),
+                if withholding_in_force(r) { " *" } else { "" }
+            ),
+            swing: format!("{}%", (r.swing_sale_rate * hundred).normalize()),
+            day_trade: format!("{}%", (r.day_trade_rate * hundred).normalize()),
+            dividends: if r.dividends_compensable {
+                "compensable"
+            } else {
+                "recoverable"
+            }
+            .to_string(),
+            legal_basis: r.legal_basis.to_string(),
+        })
+        .collect();
+
+    println!("\n{} IRRF withheld at source\n", "🧾".cyan().bold());
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..3)).with(Alignment::right()))
+    );
+
+    #[derive(Tabled)]
+    struct IncomeWithholdingRow {
+        #[tabled(rename = "Income")]
+        income: String,
+        #[tabled(rename = "Assets")]
+        assets: String,
+        #[tabled(rename = "Since")]
+        since: String,
+        #[tabled(rename = "IRRF")]
+        rate: String,
+        #[tabled(rename = "Abroad")]
+        foreign_rate: String,
+        #[tabled(rename = "Legal basis")]
+        legal_basis: String,
+    }
+
+    let percent = |rate: Decimal| {
+        if rate.is_zero() {
+            "-".to_string()
+        } else {
+            format!("{}%", (rate * hundred).normalize())
+        }
+    };
+    let rows: Vec<IncomeWithholdingRow> = rules::income_withholding_rules()
+        .iter()
+        .map(|r| IncomeWithholdingRow {
+            income: r.event_type.as_str().to_string(),
+            assets: if r.asset_types.is_empty() {
+                let has_specific = rules::income_withholding_rules()
+                    .iter()
+                    .any(|o| o.event_type == r.event_type && !o.asset_types.is_empty());
+                if has_specific { "others" } else { "all" }.to_string()
+            } else {
+                let types: Vec<&str> = r.asset_types.iter().map(|t| t.as_str()).collect();
+                types.join(", ")
+            },
+            since: format!(
+                "{}{}",
+                since_label(r.since),
+                if income_withholding_in_force(r) {
+                    " *"
+                } else {
+                    ""
+                }
+            ),
+            rate: percent(r.rate),
+            foreign_rate: percent(r.foreign_rate),
+            legal_basis: r.legal_basis.to_string(),
+        })
+        .collect();
+
+    println!(
+        "\n{} Default withholding on income (when the statement has none)\n",
+        "💰".cyan().bold()
+    );
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(3..5)).with(Alignment::right()))
+    );
+
+    #[derive(Tabled)]
+    struct PayerDividendRow {
+        #[tabled(rename = "Since")]
+        since: String,
+        #[tabled(rename = "Rate")]
+        rate: String,
+        #[tabled(rename = "Company's month total")]
+        threshold: String,
+        #[tabled(rename = "Legal basis")]
+        legal_basis: String,
+    }
+
+    let rows: Vec<PayerDividendRow> = rules::payer_dividend_rules()
+        .iter()
+        .map(|r| PayerDividendRow {
+            since: format!(
+                "{}{}",
+                since_label(r.since),
+                if payer_dividend_in_force(r) { " *" } else { "" }
+            ),
+            rate: if r.rate.is_zero() {
+                "exempt".to_string()
+            } else {
+                format!("{}%", (r.rate * hundred).normalize())
+            },
+            threshold: if r.rate.is_zero() {
+                "-".to_string()
+            } else {
+                format!("over {}", format_currency(r.monthly_threshold))
+            },
+            legal_basis: r.legal_basis.to_string(),
+        })
+        .collect();
+
+    println!("\n{} Dividends withheld by payer\n", "🏦".cyan().bold());
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..3)).with(Alignment::right()))
+    );
+
+    #[derive(Tabled)]
+    struct FixedIncomeRow {
+        #[tabled(rename = "Since")]
+        since: String,
+        #[tabled(rename = "Regressive IR")]
+        brackets: String,
+        #[tabled(rename = "IOF")]
+        iof: String,
+        #[tabled(rename = "Legal basis")]
+        legal_basis: String,
+    }
+
+    let rows: Vec<FixedIncomeRow> = rules::fixed_income_rules()
+        .iter()
+        .map(|r| FixedIncomeRow {
+            since: format!(
+                "{}{}",
+                since_label(r.since),
+                if fixed_income_in_force(r) { " *" } else { "" }
+            ),
+            brackets: brackets(r),
+            iof: format!("under {} days", r.iof_by_day.len() + 1),
+            legal_basis: r.legal_basis.to_string(),
+        })
+        .collect();
+
+    println!("\n{} Tesouro Direto redemptions\n", "🏛".cyan().bold());
+    println!("{}", Table::new(rows).with(Style::rounded()));
+
+    #[derive(Tabled)]
+    struct CarneLeaoRow {
+        #[tabled(rename = "Since")]
+        since: String,
+        #[tabled(rename = "Exempt up to")]
+        exempt: String,
+        #[tabled(rename = "Top bracket")]
+        top: String,
+        #[tabled(rename = "Legal basis")]
+        legal_basis: String,
+    }
+
+    let rows: Vec<CarneLeaoRow> = rules::carne_leao_rules()
+        .iter()
+        .map(|r| CarneLeaoRow {
+            since: format!(
+                "{}{}",
+                since_label(r.since),
+                if carne_leao_in_force(r) { " *" } else { "" }
+            ),
+            exempt: format!("{} a month", format_currency(r.exempt_up_to())),
+            top: format!(
+                "{}% less {}",
+                (r.top_rate * hundred).normalize(),
+                format_currency(r.top_deduction)
+            ),
+            legal_basis: r.legal_basis.to_string(),
+        })
+        .collect();
+
+    println!("\n{} Carnê-leão (BDR dividends)\n", "🌎".cyan().bold());
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..3)).with(Alignment::right()))
+    );
+
+    #[derive(Tabled)]
+    struct FundIncomeRow {
+        #[tabled(rename = "Since")]
+        since: String,
+        #[tabled(rename = "Rate")]
+        rate: String,
+        #[tabled(rename = "Taxed quotas")]
+        quotas: String,
+        #[tabled(rename = "Legal basis")]
+        legal_basis: String,
+    }
+
+    let rows: Vec<FundIncomeRow> = rules::fund_income_rules()
+        .iter()
+        .map(|r| FundIncomeRow {
+            since: format!(
+                "{}{}",
+                since_label(r.since),
+                if fund_income_in_force(r) { " *" } else { "" }
+            ),
+            rate: match r.taxed_from {
+                Some(_) => format!("{}%", (r.rate * hundred).normalize()),
+                None => "exempt".to_string(),
+            },
+            quotas: match r.taxed_from {
+                Some(from) => format!("acquired from {}", since_label(from)),
+                None => "-".to_string(),
+            },
+            legal_basis: r.legal_basis.to_string(),
+        })
+        .collect();
+
+    println!("\n{} FII/Fiagro distributions\n", "🏢".cyan().bold());
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..2)).with(Alignment::right()))
+    );
+    println!("\n* in force in {}\n", year);
+    Ok(())
+}
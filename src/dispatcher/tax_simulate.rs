@@ -0,0 +1,146 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use std::str::FromStr;
+
+use crate::db;
+use crate::tax::simulate::{simulate_sale, SaleInput};
+use crate::utils::format_currency;
+
+pub fn dispatch_tax_simulate(
+    ticker: &str,
+    quantity: &str,
+    price: &str,
+    date: Option<&str>,
+    fees: &str,
+    day_trade: bool,
+    json_output: bool,
+) -> Result<()> {
+    let parse = |value: &str, what: &str| {
+        Decimal::from_str(value)
+            .with_context(|| format!("Invalid {}: {}. Must be a decimal number", what, value))
+    };
+    let quantity = parse(quantity, "quantity")?;
+    let price = parse(price, "price")?;
+    let fees = parse(fees, "fees")?;
+    if quantity <= Decimal::ZERO || price <= Decimal::ZERO {
+        anyhow::bail!("Quantity and price must be positive");
+    }
+    let date = match date {
+        Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .with_context(|| format!("Invalid date '{}'. Use YYYY-MM-DD format", s))?,
+        None => chrono::Local::now().date_naive(),
+    };
+
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let sim = simulate_sale(
+        &conn,
+        &SaleInput {
+            ticker: ticker.to_uppercase(),
+            quantity,
+            price,
+            fees,
+            date,
+            day_trade,
+        },
+    )?;
+
+    if json_output {
+        let mut value = serde_json::to_value(&sim)?;
+        value["additional_tax"] = serde_json::json!(sim.additional_tax());
+        println!("{}", serde_json::to_string_pretty(&value)?);
+        return Ok(());
+    }
+
+    println!(
+        "\n{} Selling {} {} at {} on {} · {}\n",
+        "🧮".cyan().bold(),
+        sim.quantity,
+        sim.ticker.bold(),
+        format_currency(sim.price),
+        sim.date.format("%d/%m/%Y"),
+        sim.category.display_name()
+    );
+    println!("  Sale:           {}", format_currency(sim.sale_total));
+    println!(
+        "  Cost basis:     {} (average {})",
+        format_currency(sim.cost_basis),
+        format_currency(sim.average_cost)
+    );
+    let gain = format_currency(sim.profit_loss);
+    println!(
+        "  Gain/loss:      {}",
+        if sim.profit_loss < Decimal::ZERO {
+            gain.red()
+        } else {
+            gain.green()
+        }
+    );
+
+    if sim.exemption_limit.is_zero() {
+        println!("  Exemption:      none for this category");
+    } else if sim.exempt {
+        println!(
+            "  Exemption:      {} of {} sold in the month: {}",
+            format_currency(sim.exemption_sales),
+            format_currency(sim.exemption_limit),
+            "exempt".green()
+        );
+    } else {
+        println!(
+            "  Exemption:      {} of {} sold in the month: {}",
+            format_currency(sim.exemption_sales),
+            format_currency(sim.exemption_limit),
+            if sim.loses_exemption {
+                "this sale ends the month's exemption".yellow()
+            } else {
+                "already over the limit".yellow()
+            }
+        );
+    }
+
+    let offset_used = sim.loss_offset_after - sim.loss_offset_before;
+    if !offset_used.is_zero() {
+        println!(
+            "  Loss offset:    {} of past losses consumed",
+            format_currency(offset_used)
+        );
+    }
+    if sim.carry_forward_before != sim.carry_forward_after {
+        println!(
+            "  Losses carried: {} → {} at year end",
+            format_currency(sim.carry_forward_before),
+            format_currency(sim.carry_forward_after)
+        );
+    }
+
+    let additional = sim.additional_tax();
+    println!(
+        "  Month tax:      {} → {} ({}{})",
+        format_currency(sim.month_tax_before),
+        format_currency(sim.month_tax_after),
+        if additional >= Decimal::ZERO { "+" } else { "" },
+        format_currency(additional)
+    );
+    if !sim.irrf.is_zero() {
+        println!(
+            "  IRRF on sale:   {} (withheld by the broker, deductible)",
+            format_currency(sim.irrf)
+        );
+    }
+    if sim.month_tax_after > Decimal::ZERO {
+        println!(
+            "  DARF:           {} code {}, due {}",
+            format_currency(sim.month_tax_after).bold(),
+            sim.darf_code.unwrap_or("-"),
+            sim.darf_due.format("%d/%m/%Y")
+        );
+    } else {
+        println!("  DARF:           {}", "nothing due for the month".green());
+    }
+
+    println!("\n{}", "Nothing was recorded.".dimmed());
+    Ok(())
+}
@@ -30,3 +30,194 @@ pub async fn dispatch_process_terms() -> Result<()> {
 
     Ok(())
 }
+
+pub async fn dispatch_terms(action: &crate::cli::TermCommands, json_output: bool) -> Result<()> {
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+
+    match action {
+        crate::cli::TermCommands::Show => show_terms(&conn, json_output),
+        crate::cli::TermCommands::Set {
+            transaction_id,
+            expiry,
+            rate,
+        } => set_terms(
+            &conn,
+            *transaction_id,
+            expiry.as_deref(),
+            rate.as_deref(),
+            json_output,
+        ),
+    }
+}
+
+fn show_terms(conn: &rusqlite::Connection, json_output: bool) -> Result<()> {
+    use colored::Colorize;
+    use rust_decimal::Decimal;
+    use tabled::settings::{object::Columns, Alignment, Modify, Style};
+    use tabled::{Table, Tabled};
+
+    use crate::utils::format_currency;
+
+    let contracts = crate::term_contracts::open_term_contracts(conn)?;
+    let portfolio = crate::reports::calculate_portfolio(conn, None)?;
+    let portfolio_value = if portfolio.total_value > Decimal::ZERO {
+        portfolio.total_value
+    } else {
+        portfolio.total_cost
+    };
+    let share = |amount: Decimal| {
+        (portfolio_value > Decimal::ZERO)
+            .then(|| (amount / portfolio_value * Decimal::from(100)).round_dp(2))
+    };
+
+    let total_notional: Decimal = contracts.iter().map(|c| c.notional).sum();
+    let total_pct = share(total_notional);
+    let ceiling = crate::config::load_config()?
+        .terms
+        .and_then(|t| t.max_exposure_pct);
+    let over_ceiling = matches!((total_pct, ceiling), (Some(pct), Some(max)) if pct > max);
+
+    if json_output {
+        let rows: Vec<_> = contracts
+            .iter()
+            .map(|c| {
+                serde_json::json!({
+                    "contract": c,
+                    "portfolio_pct": share(c.notional),
+                })
+            })
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "contracts": rows,
+                "total_notional": total_notional,
+                "portfolio_value": portfolio_value,
+                "portfolio_pct": total_pct,
+                "max_exposure_pct": ceiling,
+                "over_ceiling": over_ceiling,
+            }))?
+        );
+        return Ok(());
+    }
+
+    if contracts.is_empty() {
+        println!("{} No open term contracts", "ℹ".blue().bold());
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct TermRow {
+        #[tabled(rename = "ID")]
+        id: i64,
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Trade date")]
+        trade_date: String,
+        #[tabled(rename = "Expiry")]
+        expiry: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Price")]
+        price: String,
+        #[tabled(rename = "Notional")]
+        notional: String,
+        #[tabled(rename = "Rate (a.a.)")]
+        rate: String,
+        #[tabled(rename = "% Portfolio")]
+        pct: String,
+    }
+
+    let pct_cell = |pct: Option<Decimal>| pct.map(|p| format!("{}%", p)).unwrap_or("-".into());
+    let rows: Vec<TermRow> = contracts
+        .iter()
+        .map(|c| TermRow {
+            id: c.transaction_id,
+            ticker: c.ticker.clone(),
+            trade_date: c.trade_date.format("%d/%m/%Y").to_string(),
+            expiry: c
+                .expiry_date
+                .map(|d| d.format("%d/%m/%Y").to_string())
+                .unwrap_or("-".into()),
+            quantity: c.quantity.to_string(),
+            price: format_currency(c.price),
+            notional: format_currency(c.notional),
+            rate: match c.contracted_rate {
+                Some(rate) if c.rate_implied => format!("{}% (implied)", rate),
+                Some(rate) => format!("{}%", rate),
+                None => "-".into(),
+            },
+            pct: pct_cell(share(c.notional)),
+        })
+        .collect();
+
+    println!("\n{} Open term contracts\n", "📄".cyan().bold());
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(4..)).with(Alignment::right()))
+    );
+    println!(
+        "\nTotal notional: {} ({} of the portfolio)",
+        format_currency(total_notional).bold(),
+        pct_cell(total_pct)
+    );
+
+    if over_ceiling {
+        println!(
+            "{} Term exposure is above the configured ceiling of {}%",
+            "⚠".yellow().bold(),
+            ceiling.unwrap_or_default()
+        );
+    }
+    if contracts.iter().any(|c| c.expiry_date.is_none()) {
+        println!("\nRecord missing expiries with: interest terms set <ID> --expiry YYYY-MM-DD");
+    }
+
+    Ok(())
+}
+
+fn set_terms(
+    conn: &rusqlite::Connection,
+    transaction_id: i64,
+    expiry: Option<&str>,
+    rate: Option<&str>,
+    json_output: bool,
+) -> Result<()> {
+    use anyhow::Context;
+    use colored::Colorize;
+    use std::str::FromStr;
+
+    if expiry.is_none() && rate.is_none() {
+        anyhow::bail!("Nothing to set: pass --expiry and/or --rate");
+    }
+    let expiry = expiry
+        .map(|s| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d"))
+        .transpose()
+        .context("Invalid expiry date. Use YYYY-MM-DD")?;
+    let rate = rate
+        .map(rust_decimal::Decimal::from_str)
+        .transpose()
+        .context("Invalid rate. Must be a decimal number")?;
+
+    crate::term_contracts::set_term_details(conn, transaction_id, expiry, rate)?;
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "success": true,
+                "transaction_id": transaction_id,
+            }))?
+        );
+    } else {
+        println!(
+            "{} Updated term contract {}",
+            "✓".green().bold(),
+            transaction_id
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use anyhow::Result;
+use colored::Colorize;
+use rust_decimal::Decimal;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::cli::TesouroCommands;
+use crate::db;
+use crate::utils::format_currency;
+
+pub fn dispatch_tesouro(action: &TesouroCommands, json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    match action {
+        TesouroCommands::Show => show_positions(&conn, json_output),
+        TesouroCommands::Redemptions { year } => show_redemptions(&conn, *year, json_output),
+    }
+}
+
+fn show_positions(conn: &rusqlite::Connection, json_output: bool) -> Result<()> {
+    let today = chrono::Local::now().date_naive();
+    let positions = crate::tesouro::open_positions(conn, today)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&positions)?);
+        return Ok(());
+    }
+
+    if positions.is_empty() {
+        println!("{} No Tesouro Direto bonds held", "ℹ".blue().bold());
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct BondRow {
+        #[tabled(rename = "Bond")]
+        ticker: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Cost")]
+        cost: String,
+        #[tabled(rename = "PU")]
+        pu: String,
+        #[tabled(rename = "Rate")]
+        rate: String,
+        #[tabled(rename = "Value")]
+        value: String,
+        #[tabled(rename = "Custody")]
+        custody: String,
+        #[tabled(rename = "IOF + IR")]
+        tax: String,
+        #[tabled(rename = "Net")]
+        net: String,
+    }
+
+    let rows: Vec<BondRow> = positions
+        .iter()
+        .map(|p| BondRow {
+            ticker: p.ticker.clone(),
+            quantity: p.quantity.normalize().to_string(),
+            cost: format_currency(p.cost),
+            pu: p
+                .pu
+                .map(|(date, price)| {
+                    format!("{} ({})", format_currency(price), date.format("%d/%m"))
+                })
+                .unwrap_or("-".into()),
+            rate: p
+                .sell_rate
+                .map(|r| format!("{:.2}%", r))
+                .unwrap_or("-".into()),
+            value: p.market_value.map(format_currency).unwrap_or("-".into()),
+            custody: format_currency(p.custody),
+            tax: format_currency(p.iof + p.ir),
+            net: p.net_value.map(format_currency).unwrap_or("-".into()),
+        })
+        .collect();
+
+    println!("\n{} Tesouro Direto\n", "🏛".cyan().bold());
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(1..)).with(Alignment::right()))
+    );
+    if positions.iter().any(|p| p.net_value.is_some()) {
+        let net: Decimal = positions.iter().filter_map(|p| p.net_value).sum();
+        println!("\nNet if redeemed today: {}", format_currency(net).bold());
+    }
+    println!(
+        "{}",
+        "Custody is the B3 fee accrued since purchase; it is deducted from the IR base on redemption"
+            .dimmed()
+    );
+    if positions.iter().any(|p| p.pu.is_none()) {
+        println!("\nNo PU stored for some bonds. Run 'interest prices update' to fetch them.");
+    }
+    Ok(())
+}
+
+fn show_redemptions(conn: &rusqlite::Connection, year: i32, json_output: bool) -> Result<()> {
+    let taxes = crate::tax::tesouro::redemption_taxes(conn, year)?;
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&taxes)?);
+        return Ok(());
+    }
+
+    if taxes.is_empty() {
+        println!(
+            "{} No Tesouro Direto redemptions in {}",
+            "ℹ".blue().bold(),
+            year
+        );
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct LotRow {
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Bond")]
+        ticker: String,
+        #[tabled(rename = "Bought")]
+        purchase: String,
+        #[tabled(rename = "Days")]
+        days: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Proceeds")]
+        proceeds: String,
+        #[tabled(rename = "Yield")]
+        gain: String,
+        #[tabled(rename = "IOF")]
+        iof: String,
+        #[tabled(rename = "Custody")]
+        custody: String,
+        #[tabled(rename = "IR rate")]
+        rate: String,
+        #[tabled(rename = "IR")]
+        ir: String,
+    }
+
+    let rows: Vec<LotRow> = taxes
+        .iter()
+        .flat_map(|t| {
+            t.lots.iter().map(move |lot| LotRow {
+                date: t.date.format("%d/%m/%Y").to_string(),
+                ticker: t.ticker.clone(),
+                purchase: lot.purchase_date.format("%d/%m/%Y").to_string(),
+                days: lot.days_held.to_string(),
+                quantity: lot.quantity.normalize().to_string(),
+                proceeds: format_currency(lot.proceeds),
+                gain: format_currency(lot.gain),
+                iof: format_currency(lot.iof),
+                custody: format_currency(lot.custody),
+                rate: format!("{}%", (lot.ir_rate * Decimal::from(100)).normalize()),
+                ir: format_currency(lot.ir),
+            })
+        })
+        .collect();
+
+    println!(
+        "\n{} Tesouro Direto redemptions - {}\n",
+        "🏛".cyan().bold(),
+        year
+    );
+    println!(
+        "{}",
+        Table::new(rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(3..)).with(Alignment::right()))
+    );
+
+    let sum = |f: fn(&crate::tax::tesouro::RedemptionTax) -> Decimal| -> Decimal {
+        taxes.iter().map(f).sum()
+    };
+    println!("\nYield: {}", format_currency(sum(|t| t.gain)));
+    println!("IOF: {}", format_currency(sum(|t| t.iof)));
+    println!("IR withheld: {}", format_currency(sum(|t| t.ir)).bold());
+    println!(
+        "{}",
+        "Declare the yield net of IR under 'Rendimentos sujeitos à tributação exclusiva' (06)"
+            .dimmed()
+    );
+    Ok(())
+}
@@ -5,7 +5,7 @@ use crate::db::{self, AssetType};
 
 const KNOWN_TYPES: &[&str] = &[
     "STOCK", "BDR", "ETF", "FII", "FIAGRO", "FI_INFRA", "FIDC", "FIP", "BOND", "GOV_BOND",
-    "OPTION", "UNKNOWN",
+    "OPTION", "CRYPTO", "UNKNOWN",
 ];
 
 pub async fn dispatch_tickers(
@@ -13,19 +13,49 @@ pub async fn dispatch_tickers(
     json_output: bool,
 ) -> Result<()> {
     match action {
-        crate::cli::TickersCommands::Refresh { force } => {
+        crate::cli::TickersCommands::Refresh {
+            date: Some(date), ..
+        } => {
+            let date = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| anyhow::anyhow!("Invalid date: {} (use YYYY-MM-DD)", date))?;
+            let (file_date, path) = crate::tickers::fetch_b3_tickers_version(date)?;
+            let renames = sync_renames()?;
+            if json_output {
+                println!(
+                    "{}",
+                    serde_json::json!({
+                        "refreshed": true,
+                        "date": file_date.to_string(),
+                        "path": path,
+                        "renames_flagged": renames,
+                    })
+                );
+            } else {
+                println!(
+                    "Stored tickers version for {}: {}",
+                    file_date,
+                    path.display()
+                );
+                print_renames_flagged(renames);
+            }
+            Ok(())
+        }
+        crate::cli::TickersCommands::Refresh { force, date: None } => {
             let force = *force;
             let path = crate::tickers::refresh_b3_tickers(force)?;
+            let renames = sync_renames()?;
             if json_output {
                 println!(
                     "{}",
                     serde_json::json!({
                         "refreshed": true,
                         "path": path,
+                        "renames_flagged": renames,
                     })
                 );
             } else {
                 println!("Updated tickers cache: {}", path.display());
+                print_renames_flagged(renames);
             }
             Ok(())
         }
@@ -35,6 +65,7 @@ pub async fn dispatch_tickers(
             let cache_dir = crate::tickers::get_tickers_cache_dir()?;
             let csv_path = cache_dir.join("tickers.csv");
             let meta = crate::tickers::read_cache_meta(Some(&cache_dir))?;
+            let versions = crate::tickers::list_versions(Some(&cache_dir))?;
             let unknown_assets = db::list_assets_by_type(&conn, AssetType::Unknown)?;
 
             if json_output {
@@ -43,6 +74,7 @@ pub async fn dispatch_tickers(
                     "cache_exists": csv_path.exists(),
                     "fetched_at": meta.as_ref().map(|m| m.fetched_at.to_rfc3339()),
                     "source_url": meta.as_ref().map(|m| m.source_url.clone()),
+                    "versions": versions.len(),
                     "unknown_count": unknown_assets.len(),
                 });
                 println!("{}", serde_json::to_string_pretty(&payload)?);
@@ -61,9 +93,33 @@ pub async fn dispatch_tickers(
             } else {
                 println!("Last fetch: not available");
             }
+            match (versions.first(), versions.last()) {
+                (Some(first), Some(last)) => {
+                    println!("Dated versions: {} ({} to {})", versions.len(), first, last)
+                }
+                _ => println!("Dated versions: none"),
+            }
             println!("Unknown assets: {}", unknown_assets.len());
             Ok(())
         }
+        crate::cli::TickersCommands::Versions => {
+            let versions = crate::tickers::list_versions(None)?;
+            if json_output {
+                let dates: Vec<String> = versions.iter().map(|d| d.to_string()).collect();
+                println!("{}", serde_json::to_string_pretty(&dates)?);
+                return Ok(());
+            }
+            if versions.is_empty() {
+                println!(
+                    "No dated versions kept yet. Use: interest tickers refresh --date YYYY-MM-DD"
+                );
+                return Ok(());
+            }
+            for date in versions {
+                println!("{}", date);
+            }
+            Ok(())
+        }
         crate::cli::TickersCommands::ListUnknown => {
             db::init_database(None)?;
             let conn = db::open_db(None)?;
@@ -163,6 +219,23 @@ pub async fn dispatch_tickers(
     }
 }
 
+/// Propose renames for held tickers the refreshed file no longer lists
+fn sync_renames() -> Result<usize> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+    let (opened, _) = crate::tickers::renames::sync_renames(&conn)?;
+    Ok(opened)
+}
+
+fn print_renames_flagged(opened: usize) {
+    if opened > 0 {
+        println!(
+            "{} held ticker(s) renamed on B3; see: interest inconsistencies list --type TICKER_RENAME",
+            opened
+        );
+    }
+}
+
 enum PromptSelection {
     Skip,
     Quit,
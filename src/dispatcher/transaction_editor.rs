@@ -0,0 +1,441 @@
+//! `transactions add --editor`: backfill many trades from one text block.
+//!
+//! The user's editor opens a template with one trade per line, tab or comma
+//! separated. On save the block is parsed and previewed; rows with errors
+//! send the user back to the editor with the text kept, and nothing is
+//! written until every row parses. The rows then go in one database
+//! transaction, so a failure halfway leaves the database untouched.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use std::io::{stdin, stdout, BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+use tabled::{
+    settings::{object::Columns, Alignment, Modify, Style},
+    Table, Tabled,
+};
+
+use crate::db::{self, TransactionType};
+use crate::utils::format_currency;
+
+const TEMPLATE: &str = "\
+# One trade per line, columns separated by tabs or commas:
+#   date  ticker  type  quantity  price  [fees]  [broker]  [notes]
+# date: YYYY-MM-DD or DD/MM/YYYY
+# type: buy or sell; buy-dt / sell-dt for day trades
+# Decimals may use a comma when the line is tab separated (28,50).
+# Lines starting with # are ignored. Save and close to continue.
+#
+# 2019-12-31\tPETR4\tbuy\t200\t28.50\t0\tXP\topening balance
+";
+
+/// A parsed row of the editor block
+#[derive(Debug, Clone, PartialEq)]
+pub struct EditorRow {
+    pub line: usize,
+    pub trade_date: NaiveDate,
+    pub ticker: String,
+    pub transaction_type: TransactionType,
+    pub day_trade: bool,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fees: Decimal,
+    pub broker: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl EditorRow {
+    pub fn total_cost(&self) -> Decimal {
+        self.quantity * self.price + self.fees
+    }
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%d/%m/%Y"))
+        .map_err(|_| anyhow::anyhow!("invalid date '{}'", value))
+}
+
+fn parse_decimal(field: &str, value: &str) -> Result<Decimal> {
+    let normalized = if value.contains(',') {
+        value.replace('.', "").replace(',', ".")
+    } else {
+        value.to_string()
+    };
+    Decimal::from_str(&normalized).map_err(|_| anyhow::anyhow!("invalid {} '{}'", field, value))
+}
+
+fn parse_line(line_no: usize, line: &str) -> Result<EditorRow> {
+    let fields: Vec<&str> = if line.contains('\t') {
+        line.split('\t').map(str::trim).collect()
+    } else {
+        line.split(',').map(str::trim).collect()
+    };
+    if fields.len() < 5 {
+        anyhow::bail!(
+            "expected at least 5 columns (date, ticker, type, quantity, price), found {}",
+            fields.len()
+        );
+    }
+    let optional = |i: usize| {
+        fields
+            .get(i)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string())
+    };
+
+    let trade_date = parse_date(fields[0])?;
+    let ticker = fields[1].to_uppercase();
+    if ticker.is_empty() {
+        anyhow::bail!("missing ticker");
+    }
+    let (transaction_type, day_trade) = match fields[2].to_lowercase().as_str() {
+        "buy" | "c" => (TransactionType::Buy, false),
+        "sell" | "v" => (TransactionType::Sell, false),
+        "buy-dt" => (TransactionType::Buy, true),
+        "sell-dt" => (TransactionType::Sell, true),
+        other => anyhow::bail!(
+            "type must be buy, sell, buy-dt or sell-dt, found '{}'",
+            other
+        ),
+    };
+    let quantity = parse_decimal("quantity", fields[3])?;
+    let price = parse_decimal("price", fields[4])?;
+    let fees = match optional(5) {
+        Some(v) => parse_decimal("fees", &v)?,
+        None => Decimal::ZERO,
+    };
+    if quantity <= Decimal::ZERO {
+        anyhow::bail!("quantity must be greater than zero");
+    }
+    if price <= Decimal::ZERO {
+        anyhow::bail!("price must be greater than zero");
+    }
+    if fees < Decimal::ZERO {
+        anyhow::bail!("fees cannot be negative");
+    }
+    // Notes may contain the separator themselves
+    let notes = if fields.len() > 8 {
+        Some(fields[7..].join(if line.contains('\t') { "\t" } else { ", " }))
+    } else {
+        optional(7)
+    };
+
+    Ok(EditorRow {
+        line: line_no,
+        trade_date,
+        ticker,
+        transaction_type,
+        day_trade,
+        quantity,
+        price,
+        fees,
+        broker: optional(6).map(|b| b.to_uppercase()),
+        notes,
+    })
+}
+
+/// Parse the edited block: the rows, and the errors by line number
+pub fn parse_block(text: &str) -> (Vec<EditorRow>, Vec<(usize, String)>) {
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        match parse_line(i + 1, line) {
+            Ok(row) => rows.push(row),
+            Err(e) => errors.push((i + 1, e.to_string())),
+        }
+    }
+    (rows, errors)
+}
+
+/// Rows already recorded with the same ticker, date, type, quantity and price
+fn find_duplicates(conn: &Connection, rows: &[EditorRow]) -> Result<Vec<usize>> {
+    let mut duplicates = Vec::new();
+    for row in rows {
+        let Some(asset) = db::get_asset_by_ticker(conn, &row.ticker)? else {
+            continue;
+        };
+        let mut stmt = conn.prepare_cached(
+            "SELECT quantity, price_per_unit FROM transactions
+             WHERE asset_id = ?1 AND trade_date = ?2 AND transaction_type = ?3",
+        )?;
+        let existing = stmt
+            .query_map(
+                rusqlite::params![
+                    asset.id.expect("asset id"),
+                    row.trade_date,
+                    row.transaction_type.as_str()
+                ],
+                |r| Ok((db::get_decimal_value(r, 0)?, db::get_decimal_value(r, 1)?)),
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        if existing.contains(&(row.quantity, row.price)) {
+            duplicates.push(row.line);
+        }
+    }
+    Ok(duplicates)
+}
+
+/// Insert every row in one database transaction; returns the new ids
+pub fn insert_rows(conn: &Connection, rows: &[EditorRow]) -> Result<Vec<i64>> {
+    let tx = conn.unchecked_transaction()?;
+    let mut ids = Vec::new();
+    for row in rows {
+        let asset_id = db::upsert_asset_as_of(
+            &tx,
+            &row.ticker,
+            &db::AssetType::Unknown,
+            None,
+            Some(row.trade_date),
+        )?;
+        let id = db::insert_transaction(
+            &tx,
+            &db::Transaction {
+                id: None,
+                asset_id,
+                transaction_type: row.transaction_type.clone(),
+                trade_date: row.trade_date,
+                settlement_date: Some(row.trade_date),
+                quantity: row.quantity,
+                price_per_unit: row.price,
+                total_cost: row.total_cost(),
+                fees: row.fees,
+                is_day_trade: row.day_trade,
+                quota_issuance_date: None,
+                notes: row.notes.clone(),
+                source: "MANUAL".to_string(),
+                created_at: chrono::Utc::now(),
+            },
+        )
+        .with_context(|| format!("Failed to insert line {}", row.line))?;
+        if let Some(broker) = &row.broker {
+            let broker_id = db::upsert_broker(&tx, broker)?;
+            db::set_transaction_broker(&tx, id, broker_id)?;
+        }
+        ids.push(id);
+    }
+    tx.commit()?;
+    Ok(ids)
+}
+
+/// Editor used when neither $VISUAL nor $EDITOR is set
+const DEFAULT_EDITOR: &str = if cfg!(windows) { "notepad" } else { "vi" };
+
+/// Open `path` in $VISUAL / $EDITOR (vi, or Notepad on Windows, when unset)
+/// and wait for it to close
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+    // Allows editors configured with arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("$EDITOR is empty"))?;
+    let status = std::process::Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start editor '{}'", editor))?;
+    if !status.success() {
+        anyhow::bail!("Editor exited with {}", status);
+    }
+    Ok(())
+}
+
+fn prompt_yes(msg: &str) -> Result<bool> {
+    print!("{} [Y/n]: ", msg);
+    stdout().flush()?;
+    let mut input = String::new();
+    if stdin().lock().read_line(&mut input)? == 0 {
+        return Ok(false);
+    }
+    let input = input.trim();
+    Ok(input.is_empty() || input.eq_ignore_ascii_case("y") || input.eq_ignore_ascii_case("yes"))
+}
+
+fn print_preview(rows: &[EditorRow], duplicates: &[usize]) {
+    #[derive(Tabled)]
+    struct PreviewRow {
+        #[tabled(rename = "Line")]
+        line: usize,
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Ticker")]
+        ticker: String,
+        #[tabled(rename = "Type")]
+        transaction_type: String,
+        #[tabled(rename = "Quantity")]
+        quantity: String,
+        #[tabled(rename = "Price")]
+        price: String,
+        #[tabled(rename = "Fees")]
+        fees: String,
+        #[tabled(rename = "Total")]
+        total: String,
+        #[tabled(rename = "Broker")]
+        broker: String,
+    }
+
+    let table_rows: Vec<PreviewRow> = rows
+        .iter()
+        .map(|row| PreviewRow {
+            line: row.line,
+            date: row.trade_date.format("%Y-%m-%d").to_string(),
+            ticker: row.ticker.clone(),
+            transaction_type: format!(
+                "{}{}",
+                row.transaction_type.as_str().to_uppercase(),
+                if row.day_trade { " (DT)" } else { "" }
+            ),
+            quantity: row.quantity.to_string(),
+            price: format_currency(row.price),
+            fees: format_currency(row.fees),
+            total: format_currency(row.total_cost()),
+            broker: row.broker.clone().unwrap_or_else(|| "-".to_string()),
+        })
+        .collect();
+    println!(
+        "\n{}",
+        Table::new(table_rows)
+            .with(Style::rounded())
+            .with(Modify::new(Columns::new(4..8)).with(Alignment::right()))
+    );
+    for line in duplicates {
+        println!(
+            "{} Line {}: same trade already recorded",
+            "⚠".yellow().bold(),
+            line
+        );
+    }
+}
+
+pub fn dispatch_transactions_editor(json_output: bool) -> Result<()> {
+    db::init_database(None)?;
+    let conn = db::open_db(None)?;
+
+    let path =
+        std::env::temp_dir().join(format!("interest-transactions-{}.tsv", std::process::id()));
+    std::fs::write(&path, TEMPLATE).context("Failed to write editor template")?;
+    let result = edit_and_insert(&conn, &path, json_output);
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+fn edit_and_insert(conn: &Connection, path: &Path, json_output: bool) -> Result<()> {
+    let rows = loop {
+        run_editor(path)?;
+        let text =
+            crate::importers::inspect::read_text(path).context("Failed to read edited file")?;
+        let (rows, errors) = parse_block(&text);
+        if errors.is_empty() {
+            break rows;
+        }
+        println!(
+            "\n{} {} line(s) with errors:",
+            "✗".red().bold(),
+            errors.len()
+        );
+        for (line, error) in &errors {
+            println!("  Line {}: {}", line, error);
+        }
+        if !prompt_yes("Edit again?")? {
+            println!("Nothing was added.");
+            return Ok(());
+        }
+    };
+
+    if rows.is_empty() {
+        println!("No transactions entered; nothing was added.");
+        return Ok(());
+    }
+
+    let duplicates = find_duplicates(conn, &rows)?;
+    print_preview(&rows, &duplicates);
+    if !prompt_yes(&format!("Add {} transaction(s)?", rows.len()))? {
+        println!("Nothing was added.");
+        return Ok(());
+    }
+
+    let ids = insert_rows(conn, &rows)?;
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({
+                "success": true,
+                "inserted": ids.len(),
+                "ids": ids,
+            }))?
+        );
+    } else {
+        println!(
+            "\n{} {} transaction(s) added",
+            "✓".green().bold(),
+            ids.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_block_and_insert_atomically() {
+        let text = format!(
+            "{}2019-12-31\tPETR4\tbuy\t200\t28,50\t1,20\txp\topening balance\n\
+             31/12/2019, vale3, sell-dt, 10, 52.30\n\
+             2019-12-31, HGLG11, hold, 10, 100\n\
+             2019-13-01, HGLG11, buy, 10, 100\n",
+            TEMPLATE
+        );
+        let (rows, errors) = parse_block(&text);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].price, dec!(28.50));
+        assert_eq!(rows[0].fees, dec!(1.20));
+        assert_eq!(rows[0].broker.as_deref(), Some("XP"));
+        assert_eq!(rows[0].notes.as_deref(), Some("opening balance"));
+        assert_eq!(rows[0].total_cost(), dec!(5701.20));
+        assert_eq!(rows[1].ticker, "VALE3");
+        assert!(rows[1].day_trade);
+        assert_eq!(rows[1].transaction_type, TransactionType::Sell);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].1.contains("type must be"));
+        assert!(errors[1].1.contains("invalid date"));
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        // Known assets: no ticker lookup while inserting
+        db::insert_asset(&conn, "PETR4", &db::AssetType::Stock, None).unwrap();
+        db::insert_asset(&conn, "VALE3", &db::AssetType::Stock, None).unwrap();
+        let ids = insert_rows(&conn, &rows).unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(
+            find_duplicates(&conn, &rows).unwrap(),
+            vec![rows[0].line, rows[1].line]
+        );
+
+        // A failing row rolls back the whole block
+        let mut bad = rows.clone();
+        bad[1].broker = Some(" ".to_string());
+        let before: i64 = conn
+            .query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0))
+            .unwrap();
+        let _ = insert_rows(&conn, &bad);
+        let after: i64 = conn
+            .query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(before, after);
+    }
+}
@@ -14,22 +14,86 @@ pub async fn dispatch_transactions(
             fees,
             day_trade,
             notes,
+            broker,
+            editor,
         } => {
+            if *editor {
+                return super::transaction_editor::dispatch_transactions_editor(json_output);
+            }
+            fn required(value: &Option<String>) -> &str {
+                value.as_deref().unwrap_or_default()
+            }
             dispatch_transaction_add(
-                ticker,
-                transaction_type,
-                quantity,
-                price,
-                date,
+                required(ticker),
+                required(transaction_type),
+                required(quantity),
+                required(price),
+                required(date),
                 fees,
                 *day_trade,
                 notes.as_deref(),
+                broker.as_deref(),
             )
             .await
         }
-        crate::cli::TransactionCommands::List { ticker } => {
-            dispatch_transactions_list(ticker.as_deref(), json_output).await
+        crate::cli::TransactionCommands::List {
+            ticker,
+            from,
+            to,
+            source,
+            table,
+        } => {
+            dispatch_transactions_list(
+                ticker.as_deref(),
+                from.as_deref(),
+                to.as_deref(),
+                source.as_deref(),
+                table,
+                json_output,
+            )
+            .await
         }
+        crate::cli::TransactionCommands::Show { id } => dispatch_transaction_show(*id, json_output),
+        crate::cli::TransactionCommands::Edit {
+            id,
+            ticker,
+            transaction_type,
+            date,
+            quantity,
+            price,
+            fees,
+            total,
+            day_trade,
+            notes,
+            broker,
+            rescale_actions,
+            keep_actions,
+        } => dispatch_transaction_edit(
+            *id,
+            &TransactionEdit {
+                ticker: ticker.as_deref(),
+                transaction_type: transaction_type.as_deref(),
+                date: date.as_deref(),
+                quantity: quantity.as_deref(),
+                price: price.as_deref(),
+                fees: fees.as_deref(),
+                total: total.as_deref(),
+                day_trade: *day_trade,
+                notes: notes.as_deref(),
+                broker: broker.as_deref(),
+            },
+            rescale_choice(*rescale_actions, *keep_actions),
+            json_output,
+        ),
+        crate::cli::TransactionCommands::Delete {
+            id,
+            rescale_actions,
+            keep_actions,
+        } => dispatch_transaction_delete(
+            *id,
+            rescale_choice(*rescale_actions, *keep_actions),
+            json_output,
+        ),
     }
 }
 
@@ -43,6 +107,7 @@ async fn dispatch_transaction_add(
     fees_str: &str,
     day_trade: bool,
     notes: Option<&str>,
+    broker: Option<&str>,
 ) -> Result<()> {
     use anyhow::Context;
     use chrono::NaiveDate;
@@ -94,7 +159,8 @@ async fn dispatch_transaction_add(
     let asset_type = crate::db::AssetType::Unknown;
 
     // Upsert asset
-    let asset_id = crate::db::upsert_asset(&conn, ticker, &asset_type, None)?;
+    let asset_id =
+        crate::db::upsert_asset_as_of(&conn, ticker, &asset_type, None, Some(trade_date))?;
 
     // Create transaction
     let transaction = crate::db::Transaction {
@@ -116,6 +182,10 @@ async fn dispatch_transaction_add(
 
     // Insert transaction
     let tx_id = crate::db::insert_transaction(&conn, &transaction)?;
+    if let Some(broker) = broker {
+        let broker_id = crate::db::upsert_broker(&conn, &broker.to_uppercase())?;
+        crate::db::set_transaction_broker(&conn, tx_id, broker_id)?;
+    }
 
     // Display confirmation
     println!("\n{} Transaction added successfully!", "✓".green().bold());
@@ -136,6 +206,9 @@ async fn dispatch_transaction_add(
         "  Total:          {}",
         crate::utils::format_currency(total_cost).cyan().bold()
     );
+    if let Some(b) = broker {
+        println!("  Broker:         {}", b.to_uppercase());
+    }
     if let Some(n) = notes {
         println!("  Notes:          {}", n);
     }
@@ -145,106 +218,605 @@ async fn dispatch_transaction_add(
     Ok(())
 }
 
-async fn dispatch_transactions_list(ticker: Option<&str>, json_output: bool) -> Result<()> {
-    use serde::Serialize;
+#[derive(serde::Serialize)]
+struct TransactionRow {
+    id: Option<i64>,
+    ticker: String,
+    transaction_type: String,
+    trade_date: String,
+    settlement_date: Option<String>,
+    quantity: String,
+    price_per_unit: String,
+    total_cost: String,
+    fees: String,
+    is_day_trade: bool,
+    notes: Option<String>,
+    source: String,
+}
+
+/// Transactions matching `filter` (a WHERE fragment over `t` and `a`), in
+/// trade order
+fn load_transaction_rows(
+    conn: &rusqlite::Connection,
+    filter: &str,
+    params: &[String],
+) -> Result<Vec<TransactionRow>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.id, a.ticker, t.transaction_type, t.trade_date, t.settlement_date,
+                t.quantity, t.price_per_unit, t.total_cost, t.fees, t.is_day_trade,
+                t.notes, t.source
+         FROM transactions t
+         JOIN assets a ON t.asset_id = a.id
+         WHERE 1=1{}{}
+         ORDER BY t.trade_date ASC, t.id ASC",
+        filter,
+        crate::db::portfolio::scope_filter("t.portfolio_id")
+    ))?;
+    let mut rows = Vec::new();
+    let mut iter = stmt.query(rusqlite::params_from_iter(params))?;
+    while let Some(row) = iter.next()? {
+        rows.push(TransactionRow {
+            id: row.get(0)?,
+            ticker: row.get::<_, String>(1)?,
+            transaction_type: row.get::<_, String>(2)?,
+            trade_date: row.get::<_, String>(3)?,
+            settlement_date: row.get::<_, Option<String>>(4)?,
+            quantity: crate::db::get_decimal_value(row, 5)?.to_string(),
+            price_per_unit: crate::db::get_decimal_value(row, 6)?.to_string(),
+            total_cost: crate::db::get_decimal_value(row, 7)?.to_string(),
+            fees: crate::db::get_decimal_value(row, 8)?.to_string(),
+            is_day_trade: row.get(9)?,
+            notes: row.get(10)?,
+            source: row.get::<_, Option<String>>(11)?.unwrap_or_default(),
+        });
+    }
+    Ok(rows)
+}
+
+fn parse_date_arg(value: &str) -> Result<chrono::NaiveDate> {
+    use anyhow::Context;
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date '{}'. Use YYYY-MM-DD", value))
+}
+
+async fn dispatch_transactions_list(
+    ticker: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    source: Option<&str>,
+    table: &crate::cli::formatters::TableArgs,
+    json_output: bool,
+) -> Result<()> {
+    use crate::cli::formatters;
+    use colored::Colorize;
+    use tabled::Tabled;
 
     crate::db::init_database(None)?;
     let conn = crate::db::open_db(None)?;
 
-    #[derive(Serialize)]
-    struct TransactionRow {
-        id: Option<i64>,
+    let mut filter = String::new();
+    let mut params = Vec::new();
+    if let Some(ticker) = ticker {
+        let asset = crate::db::get_asset_by_ticker(&conn, ticker)?
+            .ok_or_else(|| anyhow::anyhow!("Ticker {} not found", ticker))?;
+        params.push(asset.id.expect("asset id").to_string());
+        filter.push_str(&format!(" AND t.asset_id = ?{}", params.len()));
+    }
+    if let Some(from) = from {
+        params.push(parse_date_arg(from)?.to_string());
+        filter.push_str(&format!(" AND t.trade_date >= ?{}", params.len()));
+    }
+    if let Some(to) = to {
+        params.push(parse_date_arg(to)?.to_string());
+        filter.push_str(&format!(" AND t.trade_date <= ?{}", params.len()));
+    }
+    if let Some(source) = source {
+        params.push(source.to_uppercase());
+        filter.push_str(&format!(
+            " AND UPPER(COALESCE(t.source, '')) = ?{}",
+            params.len()
+        ));
+    }
+    let rows = load_transaction_rows(&conn, &filter, &params)?;
+
+    let page = table.paginate(rows);
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&page.rows)?);
+        return Ok(());
+    }
+
+    if page.total == 0 {
+        println!("No transactions found");
+        return Ok(());
+    }
+
+    #[derive(Tabled)]
+    struct TransactionTableRow {
+        #[tabled(rename = "ID")]
+        id: String,
+        #[tabled(rename = "Date")]
+        date: String,
+        #[tabled(rename = "Ticker")]
         ticker: String,
+        #[tabled(rename = "Type")]
         transaction_type: String,
-        trade_date: String,
-        settlement_date: Option<String>,
+        #[tabled(rename = "Quantity")]
         quantity: String,
-        price_per_unit: String,
-        total_cost: String,
+        #[tabled(rename = "Price")]
+        price: String,
+        #[tabled(rename = "Fees")]
         fees: String,
-        is_day_trade: bool,
-        notes: Option<String>,
+        #[tabled(rename = "Total")]
+        total: String,
+        #[tabled(rename = "Source")]
         source: String,
     }
 
-    let mut rows = Vec::new();
-    if let Some(ticker) = ticker {
-        let asset = crate::db::get_asset_by_ticker(&conn, ticker)?
-            .ok_or_else(|| anyhow::anyhow!("Ticker {} not found", ticker))?;
+    let table_rows: Vec<TransactionTableRow> = page
+        .rows
+        .iter()
+        .map(|row| TransactionTableRow {
+            id: row.id.map(|id| id.to_string()).unwrap_or_default(),
+            date: row.trade_date.clone(),
+            ticker: row.ticker.clone(),
+            transaction_type: row.transaction_type.clone(),
+            quantity: row.quantity.clone(),
+            price: row.price_per_unit.clone(),
+            fees: row.fees.clone(),
+            total: row.total_cost.clone(),
+            source: row.source.clone(),
+        })
+        .collect();
 
-        let mut stmt = conn.prepare(
-            "SELECT id, transaction_type, trade_date, settlement_date, quantity, price_per_unit,
-                    total_cost, fees, is_day_trade, notes, source
-             FROM transactions
-             WHERE asset_id = ?1
-             ORDER BY trade_date ASC, id ASC",
-        )?;
-        let mut iter = stmt.query([asset.id.expect("asset id")])?;
-        while let Some(row) = iter.next()? {
-            rows.push(TransactionRow {
-                id: row.get(0)?,
-                ticker: asset.ticker.clone(),
-                transaction_type: row.get::<_, String>(1)?,
-                trade_date: row.get::<_, String>(2)?,
-                settlement_date: row.get::<_, Option<String>>(3)?,
-                quantity: crate::db::get_decimal_value(row, 4)?.to_string(),
-                price_per_unit: crate::db::get_decimal_value(row, 5)?.to_string(),
-                total_cost: crate::db::get_decimal_value(row, 6)?.to_string(),
-                fees: crate::db::get_decimal_value(row, 7)?.to_string(),
-                is_day_trade: row.get(8)?,
-                notes: row.get(9)?,
-                source: row.get(10)?,
-            });
-        }
-    } else {
-        let mut stmt = conn.prepare(
-            "SELECT t.id, a.ticker, t.transaction_type, t.trade_date, t.settlement_date,
-                    t.quantity, t.price_per_unit, t.total_cost, t.fees, t.is_day_trade,
-                    t.notes, t.source
+    let mut out = formatters::render_table(
+        &table_rows,
+        &table.columns,
+        &["Quantity", "Price", "Fees", "Total"],
+    )?;
+    out.push('\n');
+    if let Some(footer) = page.footer() {
+        out.push_str(&format!("{}\n", footer.dimmed()));
+    }
+    formatters::print_paged(&out, table.no_pager);
+
+    Ok(())
+}
+
+/// A transaction the current portfolio scope can see
+fn scoped_transaction(conn: &rusqlite::Connection, id: i64) -> Result<crate::db::Transaction> {
+    let visible: i64 = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM transactions WHERE id = ?1{}",
+            crate::db::portfolio::scope_filter("portfolio_id")
+        ),
+        [id],
+        |row| row.get(0),
+    )?;
+    let tx = match visible {
+        0 => None,
+        _ => crate::db::get_transaction(conn, id)?,
+    };
+    tx.ok_or_else(|| anyhow::anyhow!("Transaction {} not found", id))
+}
+
+fn transaction_row(conn: &rusqlite::Connection, id: i64) -> Result<TransactionRow> {
+    load_transaction_rows(conn, " AND t.id = ?1", &[id.to_string()])?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Transaction {} not found", id))
+}
+
+fn dispatch_transaction_show(id: i64, json_output: bool) -> Result<()> {
+    use colored::Colorize;
+
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let tx = scoped_transaction(&conn, id)?;
+    let row = transaction_row(&conn, id)?;
+    let (broker, portfolio, import_session): (Option<String>, Option<String>, Option<i64>) = conn
+        .query_row(
+        "SELECT b.name, p.name, t.import_session_id
              FROM transactions t
-             JOIN assets a ON t.asset_id = a.id
-             ORDER BY t.trade_date ASC, t.id ASC",
+             LEFT JOIN brokers b ON t.broker_id = b.id
+             LEFT JOIN portfolios p ON t.portfolio_id = p.id
+             WHERE t.id = ?1",
+        [id],
+        |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)),
+    )?;
+
+    // Splits and bonuses after the trade scale the position it joined; this
+    // trade's part grows in the same proportion
+    let today = chrono::Local::now().date_naive();
+    let in_effect: Vec<_> = crate::corporate_actions::get_actions_up_to(&conn, tx.asset_id, today)?
+        .into_iter()
+        .filter_map(|action| action.id)
+        .collect();
+    let actions_since: Vec<_> =
+        crate::corporate_actions::holdings_before_actions(&conn, tx.asset_id)?
+            .into_iter()
+            .filter(|(action, held)| {
+                action.id.is_some_and(|id| in_effect.contains(&id))
+                    && action.ex_date > tx.trade_date
+                    && *held > rust_decimal::Decimal::ZERO
+            })
+            .collect();
+    let adjusted_quantity = actions_since
+        .iter()
+        .fold(tx.quantity, |quantity, (action, held)| {
+            quantity * (*held + action.quantity_adjustment) / *held
+        })
+        .round_dp(4)
+        .normalize();
+
+    if json_output {
+        let mut payload = serde_json::to_value(&row)?;
+        payload["broker"] = serde_json::json!(broker);
+        payload["portfolio"] = serde_json::json!(portfolio);
+        payload["import_session_id"] = serde_json::json!(import_session);
+        payload["adjusted_quantity"] = serde_json::json!(adjusted_quantity.to_string());
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    println!("\n{} Transaction {}", "→".cyan().bold(), id);
+    println!("  Ticker:         {}", row.ticker.cyan().bold());
+    println!("  Type:           {}", row.transaction_type);
+    println!("  Date:           {}", row.trade_date);
+    if let Some(settlement) = &row.settlement_date {
+        println!("  Settlement:     {}", settlement);
+    }
+    println!("  Quantity:       {}", row.quantity);
+    if !actions_since.is_empty() {
+        println!(
+            "  After actions:  {} {}",
+            adjusted_quantity,
+            format!("({} split(s) or bonus(es) since)", actions_since.len()).dimmed()
+        );
+    }
+    println!(
+        "  Price:          {}",
+        crate::utils::format_currency(tx.price_per_unit).cyan()
+    );
+    println!(
+        "  Fees:           {}",
+        crate::utils::format_currency(tx.fees).cyan()
+    );
+    println!(
+        "  Total:          {}",
+        crate::utils::format_currency(tx.total_cost).cyan().bold()
+    );
+    if tx.is_day_trade {
+        println!("  Day trade:      yes");
+    }
+    println!("  Source:         {}", row.source);
+    if let Some(broker) = broker {
+        println!("  Broker:         {}", broker);
+    }
+    if let Some(portfolio) = portfolio {
+        println!("  Portfolio:      {}", portfolio);
+    }
+    if let Some(session) = import_session {
+        println!("  Import session: {}", session);
+    }
+    if let Some(notes) = &row.notes {
+        println!("  Notes:          {}", notes);
+    }
+    println!();
+    Ok(())
+}
+
+/// Fields given to `transactions edit`; None leaves the field as it is
+struct TransactionEdit<'a> {
+    ticker: Option<&'a str>,
+    transaction_type: Option<&'a str>,
+    date: Option<&'a str>,
+    quantity: Option<&'a str>,
+    price: Option<&'a str>,
+    fees: Option<&'a str>,
+    total: Option<&'a str>,
+    day_trade: Option<bool>,
+    notes: Option<&'a str>,
+    broker: Option<&'a str>,
+}
+
+fn parse_decimal_arg(value: Option<&str>, what: &str) -> Result<Option<rust_decimal::Decimal>> {
+    use anyhow::Context;
+    use std::str::FromStr;
+    value
+        .map(|v| {
+            rust_decimal::Decimal::from_str(v)
+                .with_context(|| format!("Invalid {}. Must be a decimal number", what))
+        })
+        .transpose()
+}
+
+fn dispatch_transaction_edit(
+    id: i64,
+    edit: &TransactionEdit,
+    rescale_actions: Option<bool>,
+    json_output: bool,
+) -> Result<()> {
+    use colored::Colorize;
+    use rust_decimal::Decimal;
+
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let old = scoped_transaction(&conn, id)?;
+    let old_row = transaction_row(&conn, id)?;
+    let mut tx = old.clone();
+
+    if let Some(transaction_type) = edit.transaction_type {
+        tx.transaction_type = match transaction_type.to_uppercase().as_str() {
+            "BUY" => crate::db::TransactionType::Buy,
+            "SELL" => crate::db::TransactionType::Sell,
+            _ => return Err(anyhow::anyhow!("Transaction type must be 'buy' or 'sell'")),
+        };
+    }
+    if let Some(date) = edit.date {
+        let trade_date = parse_date_arg(date)?;
+        // Settlement keeps its distance from the trade
+        tx.settlement_date = old
+            .settlement_date
+            .map(|settlement| settlement + (trade_date - old.trade_date));
+        tx.trade_date = trade_date;
+    }
+    let quantity = parse_decimal_arg(edit.quantity, "quantity")?;
+    let price = parse_decimal_arg(edit.price, "price")?;
+    let fees = parse_decimal_arg(edit.fees, "fees")?;
+    let total = parse_decimal_arg(edit.total, "total")?;
+    if quantity.is_some_and(|q| q <= Decimal::ZERO) {
+        return Err(anyhow::anyhow!("Quantity must be greater than zero"));
+    }
+    if price.is_some_and(|p| p < Decimal::ZERO) {
+        return Err(anyhow::anyhow!("Price cannot be negative"));
+    }
+    if fees.is_some_and(|f| f < Decimal::ZERO) {
+        return Err(anyhow::anyhow!("Fees cannot be negative"));
+    }
+    tx.quantity = quantity.unwrap_or(tx.quantity);
+    tx.price_per_unit = price.unwrap_or(tx.price_per_unit);
+    tx.fees = fees.unwrap_or(tx.fees);
+    if let Some(total) = total {
+        tx.total_cost = total;
+    } else if quantity.is_some() || price.is_some() || fees.is_some() {
+        tx.total_cost = tx.quantity * tx.price_per_unit + tx.fees;
+    }
+    if let Some(day_trade) = edit.day_trade {
+        tx.is_day_trade = day_trade;
+    }
+    if let Some(notes) = edit.notes {
+        tx.notes = (!notes.is_empty()).then(|| notes.to_string());
+    }
+    if let Some(ticker) = edit.ticker {
+        tx.asset_id = crate::db::upsert_asset_as_of(
+            &conn,
+            &ticker.to_uppercase(),
+            &crate::db::AssetType::Unknown,
+            None,
+            Some(tx.trade_date),
         )?;
-        let mut iter = stmt.query([])?;
-        while let Some(row) = iter.next()? {
-            rows.push(TransactionRow {
-                id: row.get(0)?,
-                ticker: row.get::<_, String>(1)?,
-                transaction_type: row.get::<_, String>(2)?,
-                trade_date: row.get::<_, String>(3)?,
-                settlement_date: row.get::<_, Option<String>>(4)?,
-                quantity: crate::db::get_decimal_value(row, 5)?.to_string(),
-                price_per_unit: crate::db::get_decimal_value(row, 6)?.to_string(),
-                total_cost: crate::db::get_decimal_value(row, 7)?.to_string(),
-                fees: crate::db::get_decimal_value(row, 8)?.to_string(),
-                is_day_trade: row.get(9)?,
-                notes: row.get(10)?,
-                source: row.get(11)?,
-            });
-        }
     }
 
+    // Splits and bonuses hold absolute share counts: take the positions they
+    // were computed on before the trade changes
+    let holdings_old = crate::corporate_actions::holdings_before_actions(&conn, old.asset_id)?;
+    let holdings_new = (tx.asset_id != old.asset_id)
+        .then(|| crate::corporate_actions::holdings_before_actions(&conn, tx.asset_id))
+        .transpose()?;
+
+    let (rescaled, kept) = crate::db::bulk::in_transaction(&conn, |conn| {
+        crate::db::update_transaction(conn, id, &tx)?;
+        if let Some(broker) = edit.broker {
+            let broker_id = crate::db::upsert_broker(conn, &broker.to_uppercase())?;
+            crate::db::set_transaction_broker(conn, id, broker_id)?;
+        }
+        let mut proposed = crate::corporate_actions::propose_rescales(conn, &holdings_old)?;
+        if let Some(holdings) = &holdings_new {
+            proposed.extend(crate::corporate_actions::propose_rescales(conn, holdings)?);
+        }
+        let rescales = settle_rescales(conn, proposed, rescale_actions, json_output)?;
+        if tx.quantity != old.quantity || tx.asset_id != old.asset_id {
+            crate::db::lot_size::recheck_quantity(conn, id, &tx)?;
+        }
+        Ok(rescales)
+    })?;
+    crate::importers::dedupe::sync_duplicates(&conn)?;
+    let row = transaction_row(&conn, id)?;
+
     if json_output {
-        println!("{}", serde_json::to_string_pretty(&rows)?);
+        let payload = serde_json::json!({
+            "updated": id,
+            "transaction": row,
+            "rescaled_actions": rescaled,
+            "kept_actions": kept,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
+
+    println!("\n{} Transaction {} updated", "✓".green().bold(), id);
+    let changes = [
+        ("Ticker", &old_row.ticker, &row.ticker),
+        ("Type", &old_row.transaction_type, &row.transaction_type),
+        ("Date", &old_row.trade_date, &row.trade_date),
+        ("Quantity", &old_row.quantity, &row.quantity),
+        ("Price", &old_row.price_per_unit, &row.price_per_unit),
+        ("Fees", &old_row.fees, &row.fees),
+        ("Total", &old_row.total_cost, &row.total_cost),
+    ];
+    for (label, before, after) in changes.iter().filter(|(_, b, a)| b != a) {
+        println!(
+            "  {:15} {} → {}",
+            format!("{}:", label),
+            before.dimmed(),
+            after.cyan()
+        );
+    }
+    if old_row.is_day_trade != row.is_day_trade {
+        println!("  {:15} {}", "Day trade:", row.is_day_trade);
+    }
+    if old_row.notes != row.notes {
+        println!(
+            "  {:15} {}",
+            "Notes:",
+            row.notes.as_deref().unwrap_or("(none)")
+        );
+    }
+    if let Some(broker) = edit.broker {
+        println!("  {:15} {}", "Broker:", broker.to_uppercase());
+    }
+    print_rescales(&rescaled, &kept);
+    println!();
+    Ok(())
+}
+
+/// `--rescale-actions` / `--keep-actions`; None asks
+fn rescale_choice(rescale: bool, keep: bool) -> Option<bool> {
+    if rescale {
+        Some(true)
+    } else if keep {
+        Some(false)
     } else {
-        let mut out = String::new();
-        for row in rows {
-            out.push_str(&format!(
-                "{} {} {} {} @ {} (fees {})\n",
-                row.trade_date,
-                row.ticker,
-                row.transaction_type,
-                row.quantity,
-                row.price_per_unit,
-                row.fees
+        None
+    }
+}
+
+/// Apply the proposed split/bonus rescales if the user agrees, asking when
+/// neither `--rescale-actions` nor `--keep-actions` was given. Without a
+/// terminal to ask on, the change is refused. Returns the (applied, kept as
+/// they were) rescales.
+fn settle_rescales(
+    conn: &rusqlite::Connection,
+    proposed: Vec<crate::corporate_actions::Rescale>,
+    rescale_actions: Option<bool>,
+    json_output: bool,
+) -> Result<(
+    Vec<crate::corporate_actions::Rescale>,
+    Vec<crate::corporate_actions::Rescale>,
+)> {
+    use std::io::IsTerminal;
+
+    if proposed.is_empty() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let apply = match rescale_actions {
+        Some(apply) => apply,
+        None if !json_output && std::io::stdin().is_terminal() => confirm_rescales(&proposed)?,
+        None => {
+            let ids: Vec<String> = proposed.iter().map(|r| r.action_id.to_string()).collect();
+            return Err(anyhow::anyhow!(
+                "This change would rescale corporate action(s) {}; pass --rescale-actions or --keep-actions",
+                ids.join(", ")
             ));
         }
-        if out.is_empty() {
-            println!("No transactions found");
-        } else {
-            print!("{}", out);
+    };
+    if !apply {
+        return Ok((Vec::new(), proposed));
+    }
+    crate::corporate_actions::apply_rescales(conn, &proposed)?;
+    Ok((proposed, Vec::new()))
+}
+
+fn print_rescale(rescale: &crate::corporate_actions::Rescale) {
+    println!(
+        "  #{} {} ex {} ({}): {} → {} shares",
+        rescale.action_id,
+        rescale.action_type,
+        rescale.ex_date,
+        rescale.source,
+        rescale.from,
+        rescale.to
+    );
+}
+
+fn confirm_rescales(proposed: &[crate::corporate_actions::Rescale]) -> Result<bool> {
+    use std::io::{stdin, stdout, Write};
+
+    println!("\nThis change moves the position later splits and bonuses were computed on:");
+    for rescale in proposed {
+        print_rescale(rescale);
+    }
+    print!("Rescale them to keep the same proportion? [y/N]: ");
+    stdout().flush()?;
+    let mut input = String::new();
+    stdin().read_line(&mut input)?;
+    let input = input.trim();
+    Ok(input.eq_ignore_ascii_case("y") || input.eq_ignore_ascii_case("yes"))
+}
+
+fn print_rescales(
+    rescaled: &[crate::corporate_actions::Rescale],
+    kept: &[crate::corporate_actions::Rescale],
+) {
+    use colored::Colorize;
+
+    if !rescaled.is_empty() {
+        println!(
+            "{} Rescaled corporate action(s) to the new position:",
+            "ℹ".blue().bold()
+        );
+        rescaled.iter().for_each(print_rescale);
+    }
+    if !kept.is_empty() {
+        println!(
+            "{} Kept corporate action(s) as they were:",
+            "⚠".yellow().bold()
+        );
+        for rescale in kept {
+            println!(
+                "  #{} {} ex {} ({}): {} shares (in proportion: {})",
+                rescale.action_id,
+                rescale.action_type,
+                rescale.ex_date,
+                rescale.source,
+                rescale.from,
+                rescale.to
+            );
         }
     }
+}
+
+fn dispatch_transaction_delete(
+    id: i64,
+    rescale_actions: Option<bool>,
+    json_output: bool,
+) -> Result<()> {
+    use colored::Colorize;
+
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let tx = scoped_transaction(&conn, id)?;
+    let row = transaction_row(&conn, id)?;
+    let holdings = crate::corporate_actions::holdings_before_actions(&conn, tx.asset_id)?;
+
+    let (rescaled, kept) = crate::db::bulk::in_transaction(&conn, |conn| {
+        crate::db::delete_transaction(conn, id)?;
+        let proposed = crate::corporate_actions::propose_rescales(conn, &holdings)?;
+        settle_rescales(conn, proposed, rescale_actions, json_output)
+    })?;
+    crate::importers::dedupe::sync_duplicates(&conn)?;
+
+    if json_output {
+        let payload = serde_json::json!({
+            "deleted": id,
+            "transaction": row,
+            "rescaled_actions": rescaled,
+            "kept_actions": kept,
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+        return Ok(());
+    }
 
+    println!(
+        "{} Deleted transaction {}: {} {} {} × {} on {}",
+        "✓".green().bold(),
+        id,
+        row.transaction_type,
+        row.ticker.cyan(),
+        row.quantity,
+        crate::utils::format_currency(tx.price_per_unit),
+        row.trade_date
+    );
+    print_rescales(&rescaled, &kept);
     Ok(())
 }
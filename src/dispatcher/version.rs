@@ -0,0 +1,487 @@
+//! `interest version [--full]`: the binary version and, with --full, a
+//! diagnostics report to paste into bug reports. It covers the database
+//! (path, size, schema version and row counts per table), how fresh the
+//! downloaded caches, stored prices and registries are, and whether each
+//! data provider answers.
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use colored::Colorize;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Hosts the providers are reached at; any HTTP answer counts as reachable
+const PROVIDERS: &[(&str, &str)] = &[
+    ("Yahoo Finance", "https://query1.finance.yahoo.com"),
+    ("brapi", "https://brapi.dev"),
+    ("B3 files", "https://arquivos.b3.com.br"),
+    ("B3 COTAHIST", "https://bvmf.bmfbovespa.com.br"),
+    ("Tesouro Direto", "https://www.tesourotransparente.gov.br"),
+    ("CVM", "https://dados.cvm.gov.br"),
+    ("Banco Central", "https://api.bcb.gov.br"),
+    ("Mais Retorno", "https://maisretorno.com"),
+];
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+struct TableRows {
+    table: String,
+    rows: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct DatabaseInfo {
+    path: PathBuf,
+    exists: bool,
+    size_bytes: u64,
+    schema_version: Option<String>,
+    created_at: Option<String>,
+    tables: Vec<TableRows>,
+}
+
+/// A cache directory or stored data set and how old its newest entry is
+#[derive(Debug, Serialize)]
+struct Freshness {
+    name: &'static str,
+    /// Directory for file caches, None for data kept in the database
+    path: Option<PathBuf>,
+    entries: usize,
+    updated_at: Option<DateTime<Utc>>,
+    stale: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct ProviderCheck {
+    provider: &'static str,
+    url: &'static str,
+    /// None when the check was skipped (offline or replaying a cassette)
+    reachable: Option<bool>,
+    detail: String,
+    latency_ms: Option<u128>,
+}
+
+#[derive(Debug, Serialize)]
+struct Diagnostics {
+    version: &'static str,
+    target: String,
+    tui: bool,
+    config_path: Option<PathBuf>,
+    config_exists: bool,
+    database: DatabaseInfo,
+    freshness: Vec<Freshness>,
+    providers: Vec<ProviderCheck>,
+}
+
+pub async fn dispatch_version(full: bool, json_output: bool) -> Result<()> {
+    let version = env!("CARGO_PKG_VERSION");
+    if !full {
+        if json_output {
+            println!("{}", serde_json::json!({ "version": version }));
+        } else {
+            println!("interest {}", version);
+        }
+        return Ok(());
+    }
+
+    let db_path = crate::db::get_default_db_path()?;
+    let conn = if db_path.exists() {
+        Some(crate::db::open_db(Some(db_path.clone()))?)
+    } else {
+        None
+    };
+    let config_path = crate::config::get_config_path().ok();
+    let diagnostics = Diagnostics {
+        version,
+        target: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        tui: cfg!(feature = "tui"),
+        config_exists: config_path.as_ref().is_some_and(|p| p.exists()),
+        config_path,
+        database: database_info(&db_path, conn.as_ref())?,
+        freshness: freshness(conn.as_ref(), Utc::now()),
+        providers: check_providers().await,
+    };
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&diagnostics)?);
+    } else {
+        print_diagnostics(&diagnostics);
+    }
+    Ok(())
+}
+
+fn database_info(path: &Path, conn: Option<&Connection>) -> Result<DatabaseInfo> {
+    let mut info = DatabaseInfo {
+        path: path.to_path_buf(),
+        exists: conn.is_some(),
+        size_bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+        schema_version: None,
+        created_at: None,
+        tables: Vec::new(),
+    };
+    let Some(conn) = conn else {
+        return Ok(info);
+    };
+
+    // A database never initialized has no metadata table yet
+    let metadata = |key: &str| -> Option<String> {
+        conn.query_row("SELECT value FROM metadata WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .ok()
+    };
+    info.schema_version = metadata("schema_version");
+    info.created_at = metadata("db_created_at");
+
+    let names = conn
+        .prepare(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for table in names {
+        let rows = conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", table), [], |row| {
+            row.get(0)
+        })?;
+        info.tables.push(TableRows { table, rows });
+    }
+    Ok(info)
+}
+
+/// Files in a cache directory and the newest modification time among them
+fn dir_freshness(
+    name: &'static str,
+    dir: Result<PathBuf>,
+    now: DateTime<Utc>,
+    max_age: chrono::Duration,
+) -> Freshness {
+    let dir = dir.ok();
+    let mut entries = 0;
+    let mut newest: Option<SystemTime> = None;
+    if let Some(files) = dir.as_ref().and_then(|d| std::fs::read_dir(d).ok()) {
+        for meta in files.flatten().filter_map(|f| f.metadata().ok()) {
+            if !meta.is_file() {
+                continue;
+            }
+            entries += 1;
+            if let Ok(modified) = meta.modified() {
+                newest = newest.max(Some(modified));
+            }
+        }
+    }
+    let updated_at = newest.map(DateTime::<Utc>::from);
+    Freshness {
+        name,
+        path: dir,
+        entries,
+        stale: updated_at.is_none_or(|t| now - t > max_age),
+        updated_at,
+    }
+}
+
+fn freshness(conn: Option<&Connection>, now: DateTime<Utc>) -> Vec<Freshness> {
+    let hours = chrono::Duration::hours;
+    let days = chrono::Duration::days;
+    let mut report = vec![
+        dir_freshness(
+            "Ticker registry (B3)",
+            crate::tickers::get_tickers_cache_dir(),
+            now,
+            hours(24),
+        ),
+        dir_freshness(
+            "COTAHIST files",
+            crate::importers::b3_cotahist::get_cotahist_cache_dir(),
+            now,
+            days(7),
+        ),
+        dir_freshness(
+            "Tesouro Direto CSV",
+            crate::pricing::tesouro::get_tesouro_cache_dir(),
+            now,
+            hours(24),
+        ),
+    ];
+    let Some(conn) = conn else {
+        return report;
+    };
+
+    // Prices are dated by trading day; a long weekend leaves 4 days between closes
+    let (prices, latest): (i64, Option<NaiveDate>) = conn
+        .query_row(
+            "SELECT COUNT(*), MAX(price_date) FROM price_history",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap_or((0, None));
+    let updated_at = latest
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|t| t.and_utc());
+    report.push(Freshness {
+        name: "Stored prices",
+        path: None,
+        entries: prices as usize,
+        stale: updated_at.is_none_or(|t| now - t > days(4)),
+        updated_at,
+    });
+
+    let (snapshots, latest): (i64, Option<DateTime<Utc>>) = conn
+        .query_row(
+            "SELECT COUNT(*), MAX(snapshot_at) FROM price_snapshots",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap_or((0, None));
+    report.push(Freshness {
+        name: "Live quotes",
+        path: None,
+        entries: snapshots as usize,
+        stale: latest.is_none_or(|t| now - t > days(4)),
+        updated_at: latest,
+    });
+
+    let (quotes, latest): (i64, Option<DateTime<Utc>>) = conn
+        .query_row(
+            "SELECT COUNT(*), MAX(fetched_at) FROM quote_cache",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap_or((0, None));
+    report.push(Freshness {
+        name: "Quote cache",
+        path: None,
+        entries: quotes as usize,
+        stale: latest.is_none_or(|t| now - t > days(4)),
+        updated_at: latest,
+    });
+
+    let (registry, latest): (i64, Option<DateTime<Utc>>) = conn
+        .query_row(
+            "SELECT COUNT(*), MAX(updated_at) FROM asset_registry",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap_or((0, None));
+    report.push(Freshness {
+        name: "Asset registry (Mais Retorno)",
+        path: None,
+        entries: registry as usize,
+        stale: latest.is_none_or(|t| now - t > days(30)),
+        updated_at: latest,
+    });
+    report
+}
+
+/// Ask every provider host for an answer, all at once
+async fn check_providers() -> Vec<ProviderCheck> {
+    let offline = std::env::var("INTEREST_OFFLINE")
+        .map(|value| value == "1")
+        .unwrap_or(false);
+    let replay = crate::pricing::cassette::is_replay().unwrap_or(false);
+    if offline || replay {
+        return PROVIDERS
+            .iter()
+            .map(|(provider, url)| ProviderCheck {
+                provider,
+                url,
+                reachable: None,
+                detail: if replay {
+                    "skipped (demo)"
+                } else {
+                    "skipped (offline)"
+                }
+                .to_string(),
+                latency_ms: None,
+            })
+            .collect();
+    }
+
+    let client = match reqwest::Client::builder().timeout(CHECK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            return PROVIDERS
+                .iter()
+                .map(|(provider, url)| ProviderCheck {
+                    provider,
+                    url,
+                    reachable: Some(false),
+                    detail: e.to_string(),
+                    latency_ms: None,
+                })
+                .collect()
+        }
+    };
+    let mut checks = tokio::task::JoinSet::new();
+    for (index, (provider, url)) in PROVIDERS.iter().enumerate() {
+        let client = client.clone();
+        checks.spawn(async move {
+            let started = Instant::now();
+            let result = client.head(*url).send().await;
+            let latency_ms = started.elapsed().as_millis();
+            let check = match result {
+                Ok(response) => ProviderCheck {
+                    provider,
+                    url,
+                    reachable: Some(true),
+                    detail: format!("HTTP {}", response.status().as_u16()),
+                    latency_ms: Some(latency_ms),
+                },
+                Err(e) => ProviderCheck {
+                    provider,
+                    url,
+                    reachable: Some(false),
+                    detail: if e.is_timeout() {
+                        format!("timed out after {}s", CHECK_TIMEOUT.as_secs())
+                    } else {
+                        root_cause(&e)
+                    },
+                    latency_ms: None,
+                },
+            };
+            (index, check)
+        });
+    }
+    let mut results = checks.join_all().await;
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, check)| check).collect()
+}
+
+/// The innermost error, which names what went wrong (DNS, TLS, refused...)
+fn root_cause(err: &(dyn std::error::Error + 'static)) -> String {
+    let mut cause = err;
+    while let Some(source) = cause.source() {
+        cause = source;
+    }
+    cause.to_string()
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} KB", b as f64 / (1 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
+
+fn print_diagnostics(d: &Diagnostics) {
+    println!(
+        "\n{} interest {} ({}{})",
+        "🩺".bold(),
+        d.version,
+        d.target,
+        if d.tui { ", tui" } else { "" }
+    );
+    match &d.config_path {
+        Some(path) if d.config_exists => println!("  Config: {}", path.display()),
+        Some(path) => println!("  Config: {} (not created)", path.display()),
+        None => println!("  Config: unknown location"),
+    }
+
+    let db = &d.database;
+    println!("\n{}", "Database".bold());
+    println!("  Path: {}", db.path.display());
+    if !db.exists {
+        println!("  Not created yet");
+    } else {
+        println!("  Size: {}", format_size(db.size_bytes));
+        println!(
+            "  Schema version: {}",
+            db.schema_version.as_deref().unwrap_or("unknown")
+        );
+        if let Some(created_at) = &db.created_at {
+            println!("  Created: {}", created_at);
+        }
+        let width = db.tables.iter().map(|t| t.table.len()).max().unwrap_or(0);
+        for t in db.tables.iter().filter(|t| t.rows > 0) {
+            println!("    {:<width$} {:>10}", t.table, t.rows, width = width);
+        }
+        let empty = db.tables.iter().filter(|t| t.rows == 0).count();
+        if empty > 0 {
+            println!("    {}", format!("{} empty tables", empty).dimmed());
+        }
+    }
+
+    println!("\n{}", "Caches and stored data".bold());
+    for f in &d.freshness {
+        let age = match f.updated_at {
+            Some(t) => t.with_timezone(&Local).format("%d/%m/%Y %H:%M").to_string(),
+            None => "never".to_string(),
+        };
+        let line = format!("  {:<30} {:>8} entries, updated {}", f.name, f.entries, age);
+        if f.stale {
+            println!("{} {}", line, "(stale)".yellow());
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    println!("\n{}", "Providers".bold());
+    for p in &d.providers {
+        let status = match p.reachable {
+            Some(true) => format!("{} {}", "✓".green(), p.detail),
+            Some(false) => format!("{} {}", "✗".red(), p.detail),
+            None => p.detail.dimmed().to_string(),
+        };
+        let latency = p
+            .latency_ms
+            .map(|ms| format!(" in {} ms", ms))
+            .unwrap_or_default();
+        println!("  {:<16} {}{}", p.provider, status, latency);
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_database_info_and_stored_freshness() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.db");
+        crate::db::init_database(Some(path.clone()))?;
+        let conn = crate::db::open_db(Some(path.clone()))?;
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('PETR4', 'STOCK')",
+            [],
+        )?;
+        conn.execute(
+            "INSERT INTO price_history (asset_id, price_date, close_price, source)
+             VALUES (1, '2024-06-03', '38.42', 'YAHOO')",
+            [],
+        )?;
+        conn.execute(
+            "INSERT INTO asset_registry (source, ticker, asset_type, updated_at)
+             VALUES ('MAIS_RETORNO', 'PETR4', 'STOCK', '2024-06-01 09:00:00')",
+            [],
+        )?;
+
+        let info = database_info(&path, Some(&conn))?;
+        assert!(info.size_bytes > 0);
+        assert_eq!(info.schema_version.as_deref(), Some("2"));
+        let rows = |name: &str| info.tables.iter().find(|t| t.table == name).map(|t| t.rows);
+        assert_eq!(rows("assets"), Some(1));
+        assert_eq!(rows("price_history"), Some(1));
+
+        let now = "2024-06-05T12:00:00Z".parse::<DateTime<Utc>>()?;
+        let report = freshness(Some(&conn), now);
+        let prices = report.iter().find(|f| f.name == "Stored prices").unwrap();
+        assert_eq!(prices.entries, 1);
+        assert!(!prices.stale);
+        let quotes = report.iter().find(|f| f.name == "Live quotes").unwrap();
+        assert!(quotes.stale);
+        let registry = report
+            .iter()
+            .find(|f| f.name.starts_with("Asset registry"))
+            .unwrap();
+        assert_eq!(registry.entries, 1);
+        assert!(!registry.stale);
+
+        let missing = database_info(&dir.path().join("none.db"), None)?;
+        assert!(!missing.exists && missing.tables.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+//! Watched-downloads mode: import B3 files dropped into a configured folder.
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::config::WatchConfig;
+use crate::importers::{self, watch, ImportResult};
+use crate::{config, db};
+
+#[derive(Debug, Serialize)]
+struct WatchOutcome {
+    file: String,
+    success: bool,
+    message: String,
+    moved_to: Option<PathBuf>,
+    stats: Option<importers::ImportStats>,
+}
+
+pub async fn dispatch_watch_imports(once: bool, json_output: bool) -> Result<()> {
+    let cfg = config::load_config()?;
+    let watch_cfg = cfg.watch.ok_or_else(|| {
+        anyhow!(
+            "No [watch] section in {:?}. Add at least `folder = \"...\"`",
+            config::get_config_path().unwrap_or_default()
+        )
+    })?;
+
+    let folder = watch_cfg.folder();
+    if !folder.is_dir() {
+        return Err(anyhow!("Watched folder {:?} does not exist", folder));
+    }
+
+    db::init_database(None)?;
+
+    if !json_output {
+        println!(
+            "{} Watching {} for {}",
+            "👀".cyan().bold(),
+            folder.display(),
+            watch_cfg.patterns.join(", ")
+        );
+    }
+
+    loop {
+        let outcomes = run_scan(&watch_cfg)?;
+
+        if json_output {
+            if !outcomes.is_empty() || once {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&serde_json::json!({
+                        "success": outcomes.iter().all(|o| o.success),
+                        "data": outcomes,
+                    }))?
+                );
+            }
+        } else {
+            for outcome in &outcomes {
+                let icon = if outcome.success {
+                    "✓".green().bold()
+                } else {
+                    "✗".red().bold()
+                };
+                println!("{} {}", icon, outcome.message);
+            }
+            if once && outcomes.is_empty() {
+                println!("No new files to import");
+            }
+        }
+
+        for outcome in &outcomes {
+            notify(&watch_cfg, &outcome.message);
+        }
+
+        if once {
+            return Ok(());
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(
+            watch_cfg.interval_secs.max(1),
+        ))
+        .await;
+    }
+}
+
+/// Process every settled candidate in the watched folder
+fn run_scan(cfg: &WatchConfig) -> Result<Vec<WatchOutcome>> {
+    let candidates = watch::find_candidates(&cfg.folder(), &cfg.patterns)?;
+    let mut outcomes = Vec::new();
+    for path in candidates {
+        outcomes.push(process_file(cfg, &path));
+    }
+    Ok(outcomes)
+}
+
+fn process_file(cfg: &WatchConfig, path: &Path) -> WatchOutcome {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let (success, message, stats, dest_dir) = match import_checked(path) {
+        Ok(stats) => (
+            true,
+            format!(
+                "Imported {}: {} new entries, {} errors",
+                name,
+                imported_count(&stats),
+                stats.errors
+            ),
+            Some(stats),
+            cfg.archive_dir(),
+        ),
+        Err(e) => (
+            false,
+            format!("Rejected {}: {}", name, e),
+            None,
+            cfg.failed_dir(),
+        ),
+    };
+
+    let (moved_to, message) = match watch::move_to(path, &dest_dir) {
+        Ok(dest) => (Some(dest), message),
+        Err(e) => (None, format!("{} (could not move file: {})", message, e)),
+    };
+
+    WatchOutcome {
+        file: path.display().to_string(),
+        success,
+        message,
+        moved_to,
+        stats,
+    }
+}
+
+/// Parse the file first as a dry run and only touch the database when it is
+/// a recognised, non-empty B3 export
+fn import_checked(path: &Path) -> Result<importers::ImportStats> {
+    let parsed = importers::import_file_auto(path)?;
+    let entries = match &parsed {
+        ImportResult::Cei(txs) => txs.len(),
+        ImportResult::Movimentacao(entries) => entries.len(),
+        ImportResult::OfertasPublicas(entries) => entries.len(),
+        ImportResult::NotaCorretagem(notes) => notes.iter().map(|n| n.trades.len()).sum(),
+        ImportResult::TesouroExtrato(entries) => entries.len(),
+        ImportResult::Proventos(entries) => entries.len(),
+        ImportResult::Ofx(statement) => statement.trades.len() + statement.income.len(),
+        ImportResult::Posicao(statement) => statement.entries.len(),
+        ImportResult::UsBroker(statement) => statement.trades.len() + statement.dividends.len(),
+        ImportResult::Crypto(statement) => statement.trades.len(),
+        ImportResult::Custom { transactions, .. } => transactions.len(),
+    };
+    if entries == 0 {
+        return Err(anyhow!("no entries found"));
+    }
+
+    let conn = db::open_db(None)?;
+    let session = db::import_session::begin(&conn, &path.to_string_lossy(), &parsed.format_name())?;
+    let result = crate::dispatcher::imports_helpers::import_parsed(&conn, parsed);
+    session.finish(&conn)?;
+    crate::importers::dedupe::sync_duplicates(&conn)?;
+    result
+}
+
+fn imported_count(stats: &importers::ImportStats) -> usize {
+    stats.imported + stats.imported_trades + stats.imported_actions + stats.imported_income
+}
+
+fn notify(cfg: &WatchConfig, message: &str) {
+    let Some(command) = cfg.notify_command.as_deref() else {
+        return;
+    };
+    let mut parts = command.split_whitespace();
+    let Some(program) = parts.next() else {
+        return;
+    };
+    if let Err(e) = std::process::Command::new(program)
+        .args(parts)
+        .arg(message)
+        .status()
+    {
+        tracing::warn!("Failed to run notify command {}: {}", command, e);
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use colored::Colorize;
+
+use crate::reports::whatsnew::{self, Briefing};
+use crate::utils::format_currency;
+
+/// Show what changed since the last run, then move the marker to now
+pub fn dispatch_whatsnew(since: Option<&str>, json_output: bool) -> Result<()> {
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let now = chrono::Utc::now().naive_utc();
+    let today = chrono::Local::now().date_naive();
+
+    let (start, first_run) = match since {
+        Some(date) => {
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .context("Invalid date format. Use YYYY-MM-DD")?;
+            (date.and_hms_opt(0, 0, 0).unwrap_or_default(), false)
+        }
+        None => whatsnew::last_run(&conn, now)?,
+    };
+    let briefing = whatsnew::briefing(&conn, start, first_run, today)?;
+    if since.is_none() {
+        whatsnew::mark_run(&conn, now)?;
+    }
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&briefing)?);
+        return Ok(());
+    }
+    print_briefing(&briefing);
+    Ok(())
+}
+
+fn print_briefing(briefing: &Briefing) {
+    let since = briefing
+        .since
+        .and_utc()
+        .with_timezone(&chrono::Local)
+        .format("%d/%m/%Y %H:%M");
+    println!(
+        "\n{} What's new since {}{}\n",
+        "📰".cyan().bold(),
+        since.to_string().bold(),
+        if briefing.first_run {
+            " (first run: last 7 days)"
+        } else {
+            ""
+        }
+    );
+
+    if briefing.is_quiet() {
+        println!("{} Nothing new and nothing due", "ℹ".blue().bold());
+        println!();
+        return;
+    }
+
+    let prices = &briefing.prices;
+    if prices.prices > 0 {
+        println!(
+            "{} {} prices for {} assets{}",
+            "Prices:".bold(),
+            prices.prices,
+            prices.assets,
+            prices
+                .latest_date
+                .map(|d| format!(", latest close {}", d.format("%d/%m/%Y")))
+                .unwrap_or_default()
+        );
+    }
+
+    if !briefing.income.is_empty() {
+        println!(
+            "{} {}",
+            "Income:".bold(),
+            format_currency(briefing.income_total).green()
+        );
+        for i in &briefing.income {
+            println!(
+                "  {} {:<8} {:<12} {}",
+                i.payment_date.format("%d/%m/%Y"),
+                i.ticker,
+                i.event_type,
+                format_currency(i.amount)
+            );
+        }
+    }
+
+    if !briefing.corporate_actions.is_empty() {
+        println!("{}", "Corporate actions:".bold());
+        for a in &briefing.corporate_actions {
+            println!(
+                "  ex {} {:<8} {:<14} {}",
+                a.ex_date.format("%d/%m/%Y"),
+                a.ticker,
+                a.action_type,
+                if a.pending_review {
+                    "pending review (actions review)".yellow().to_string()
+                } else {
+                    "applied".dimmed().to_string()
+                }
+            );
+        }
+    }
+
+    if !briefing.inconsistencies.is_empty() {
+        println!(
+            "{} {} new, {} open (see inconsistencies list)",
+            "Inconsistencies:".bold(),
+            briefing.inconsistencies.len(),
+            briefing.open_inconsistencies
+        );
+        for i in &briefing.inconsistencies {
+            let line = format!(
+                "  #{} {:<8} {} {}",
+                i.id,
+                i.ticker.as_deref().unwrap_or("-"),
+                i.issue_type,
+                i.severity
+            );
+            if i.severity == "BLOCKING" {
+                println!("{}", line.red());
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+
+    if !briefing.obligations.is_empty() {
+        println!(
+            "{} next {} days",
+            "Coming up:".bold(),
+            whatsnew::UPCOMING_DAYS
+        );
+        for o in &briefing.obligations {
+            let line = format!(
+                "  {} {:<14} {}{}",
+                o.date.format("%d/%m/%Y"),
+                o.kind.label(),
+                o.subject,
+                o.amount
+                    .map(|a| format!("  {}", format_currency(a)))
+                    .unwrap_or_default()
+            );
+            if o.overdue {
+                println!("{} {}", line.red(), "(overdue)".red());
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+    println!();
+}
@@ -0,0 +1,586 @@
+//! Private fixed income (CDB, LCI, LCA, CRI, CRA) marked on the curve.
+//!
+//! Bank and credit bonds are not traded on an exchange, so their value is
+//! accrued from the terms of the issue: a share of the CDI (110% do CDI),
+//! IPCA plus a yearly spread (IPCA + 6%) or a fixed yearly rate
+//! (prefixado). `fixed-income set` records the terms and the accrued unit
+//! value (PU) of every business day is written to the price history, so
+//! positions, snapshots and performance value the bond like any other asset.
+//!
+//! The CDI and IPCA come from the BCB series stored by
+//! `prices update-benchmarks`. Business days are the days with a published
+//! CDI, and weekdays after the last one; the CDI share and the yearly rates
+//! compound over them (252 a year), while each month's IPCA is spread over
+//! the calendar days of the month. Days past the last published value repeat
+//! it, so the latest PUs are a projection until the series catch up.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate, Weekday};
+use rusqlite::Connection;
+use rust_decimal::{Decimal, MathematicalOps};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use tracing::warn;
+
+use crate::db::{self, AssetType, Benchmark, PriceHistory};
+use crate::tesouro::LotRedemption;
+
+/// Source of the PUs written by the accrual
+pub const ACCRUAL_SOURCE: &str = "FIXED_INCOME_ACCRUAL";
+
+/// Kind of bond, which decides whether its yield is taxed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FixedIncomeKind {
+    Cdb,
+    Lci,
+    Lca,
+    Cri,
+    Cra,
+}
+
+impl FixedIncomeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FixedIncomeKind::Cdb => "CDB",
+            FixedIncomeKind::Lci => "LCI",
+            FixedIncomeKind::Lca => "LCA",
+            FixedIncomeKind::Cri => "CRI",
+            FixedIncomeKind::Cra => "CRA",
+        }
+    }
+
+    /// LCI, LCA, CRI and CRA yields are exempt for individuals
+    /// (Lei 11.033/2004, art. 3º)
+    pub fn is_tax_exempt(&self) -> bool {
+        !matches!(self, FixedIncomeKind::Cdb)
+    }
+}
+
+impl FromStr for FixedIncomeKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "CDB" => Ok(FixedIncomeKind::Cdb),
+            "LCI" => Ok(FixedIncomeKind::Lci),
+            "LCA" => Ok(FixedIncomeKind::Lca),
+            "CRI" => Ok(FixedIncomeKind::Cri),
+            "CRA" => Ok(FixedIncomeKind::Cra),
+            _ => anyhow::bail!(
+                "Unknown fixed income kind '{}' (use CDB, LCI, LCA, CRI or CRA)",
+                s
+            ),
+        }
+    }
+}
+
+/// What the rate of a bond is applied to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Indexer {
+    /// Rate is a percentage of the CDI
+    Cdi,
+    /// Rate is a yearly spread over IPCA
+    Ipca,
+    /// Rate is a fixed yearly rate
+    Prefixado,
+}
+
+impl Indexer {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Indexer::Cdi => "CDI",
+            Indexer::Ipca => "IPCA",
+            Indexer::Prefixado => "PRE",
+        }
+    }
+
+    /// How the rate reads on a statement (110% CDI, IPCA + 6%, 12.5% a.a.)
+    pub fn describe(&self, rate: Decimal) -> String {
+        let rate = rate.normalize();
+        match self {
+            Indexer::Cdi => format!("{}% CDI", rate),
+            Indexer::Ipca => format!("IPCA + {}%", rate),
+            Indexer::Prefixado => format!("{}% a.a.", rate),
+        }
+    }
+}
+
+impl FromStr for Indexer {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "CDI" | "DI" => Ok(Indexer::Cdi),
+            "IPCA" | "IPCA+" => Ok(Indexer::Ipca),
+            "PRE" | "PREFIXADO" => Ok(Indexer::Prefixado),
+            _ => anyhow::bail!("Unknown indexer '{}' (use CDI, IPCA or PRE)", s),
+        }
+    }
+}
+
+/// Terms of a private bond
+#[derive(Debug, Clone, Serialize)]
+pub struct FixedIncomeTerms {
+    pub asset_id: i64,
+    pub ticker: String,
+    pub kind: FixedIncomeKind,
+    pub issue_date: NaiveDate,
+    pub maturity_date: NaiveDate,
+    pub indexer: Indexer,
+    pub rate: Decimal,
+    /// PU on the issue date
+    pub issue_price: Decimal,
+}
+
+/// Record the terms of a bond already bought (see `transactions add`).
+///
+/// Without `issue_price` the unit price of the first purchase is used, which
+/// is right for bonds bought at issue. The accrued PUs are rewritten.
+#[allow(clippy::too_many_arguments)]
+pub fn set_terms(
+    conn: &Connection,
+    ticker: &str,
+    kind: FixedIncomeKind,
+    issue_date: NaiveDate,
+    maturity_date: NaiveDate,
+    indexer: Indexer,
+    rate: Decimal,
+    issue_price: Option<Decimal>,
+) -> Result<FixedIncomeTerms> {
+    if maturity_date <= issue_date {
+        anyhow::bail!("Maturity must be after the issue date");
+    }
+    if rate < Decimal::ZERO {
+        anyhow::bail!("Rate cannot be negative");
+    }
+    let asset = db::get_asset_by_ticker(conn, ticker)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "Asset {} not found. Record its purchase first with 'interest transactions add'",
+            ticker
+        )
+    })?;
+    let asset_id = asset.id.expect("asset from database must have id");
+    match asset.asset_type {
+        AssetType::Bond => {}
+        AssetType::GovBond => anyhow::bail!(
+            "{} is a Tesouro Direto bond, priced from the Tesouro CSV",
+            asset.ticker
+        ),
+        _ => db::update_asset_type(conn, &asset.ticker, &AssetType::Bond)?,
+    }
+
+    let issue_price = match issue_price {
+        Some(price) => price,
+        None => conn
+            .query_row(
+                "SELECT price_per_unit FROM transactions
+                 WHERE asset_id = ?1 AND transaction_type = 'BUY'
+                 ORDER BY trade_date, id LIMIT 1",
+                [asset_id],
+                |row| db::get_decimal_value(row, 0),
+            )
+            .context("No purchase recorded; pass --issue-price")?,
+    };
+    if issue_price <= Decimal::ZERO {
+        anyhow::bail!("Issue price must be positive");
+    }
+
+    conn.execute(
+        "INSERT OR REPLACE INTO fixed_income_terms
+             (asset_id, kind, issue_date, maturity_date, indexer, rate, issue_price)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            asset_id,
+            kind.as_str(),
+            issue_date,
+            maturity_date,
+            indexer.as_str(),
+            rate.to_string(),
+            issue_price.to_string()
+        ],
+    )?;
+    conn.execute(
+        "DELETE FROM price_history WHERE asset_id = ?1 AND source = ?2",
+        rusqlite::params![asset_id, ACCRUAL_SOURCE],
+    )?;
+
+    Ok(FixedIncomeTerms {
+        asset_id,
+        ticker: asset.ticker,
+        kind,
+        issue_date,
+        maturity_date,
+        indexer,
+        rate,
+        issue_price,
+    })
+}
+
+/// Every bond with recorded terms, by ticker
+pub fn list_terms(conn: &Connection) -> Result<Vec<FixedIncomeTerms>> {
+    let mut stmt = conn.prepare(
+        "SELECT f.asset_id, a.ticker, f.kind, f.issue_date, f.maturity_date, f.indexer,
+                f.rate, f.issue_price
+         FROM fixed_income_terms f
+         JOIN assets a ON a.id = f.asset_id
+         ORDER BY a.ticker",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, NaiveDate>(3)?,
+                row.get::<_, NaiveDate>(4)?,
+                row.get::<_, String>(5)?,
+                db::get_decimal_value(row, 6)?,
+                db::get_decimal_value(row, 7)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    rows.into_iter()
+        .map(
+            |(asset_id, ticker, kind, issue_date, maturity_date, indexer, rate, issue_price)| {
+                Ok(FixedIncomeTerms {
+                    asset_id,
+                    ticker,
+                    kind: kind.parse()?,
+                    issue_date,
+                    maturity_date,
+                    indexer: indexer.parse()?,
+                    rate,
+                    issue_price,
+                })
+            },
+        )
+        .collect()
+}
+
+/// PUs of a bond up to a date
+#[derive(Debug, Clone, Serialize)]
+pub struct Accrual {
+    pub ticker: String,
+    /// PU at the end of each business day, from the issue date
+    pub values: Vec<(NaiveDate, Decimal)>,
+    /// First day accrued with a repeated (not yet published) CDI or IPCA
+    pub projected_from: Option<NaiveDate>,
+}
+
+impl Accrual {
+    pub fn latest(&self) -> Option<(NaiveDate, Decimal)> {
+        self.values.last().copied()
+    }
+}
+
+/// Benchmark rates by date, with the last one published before `from`
+fn rate_series(
+    conn: &Connection,
+    benchmark: Benchmark,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<BTreeMap<NaiveDate, Decimal>> {
+    let mut series: BTreeMap<NaiveDate, Decimal> =
+        db::get_benchmark_values(conn, benchmark, from, to)?
+            .into_iter()
+            .map(|v| (v.value_date, v.value))
+            .collect();
+    if let Some(before) = db::get_benchmark_value_on_or_before(conn, benchmark, from)? {
+        series.entry(before.value_date).or_insert(before.value);
+    }
+    Ok(series)
+}
+
+/// Latest rate on or before `date`, and whether it was published for it
+fn rate_on(series: &BTreeMap<NaiveDate, Decimal>, date: NaiveDate) -> Option<(Decimal, bool)> {
+    series
+        .range(..=date)
+        .next_back()
+        .map(|(published, rate)| (*rate, *published == date))
+}
+
+fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Accrue the PU of a bond day by day through `through` (or its maturity)
+pub fn accrue(conn: &Connection, terms: &FixedIncomeTerms, through: NaiveDate) -> Result<Accrual> {
+    let end = through.min(terms.maturity_date);
+    let mut accrual = Accrual {
+        ticker: terms.ticker.clone(),
+        values: Vec::new(),
+        projected_from: None,
+    };
+    if end < terms.issue_date {
+        return Ok(accrual);
+    }
+
+    let cdi = rate_series(conn, Benchmark::Cdi, terms.issue_date, end)?;
+    let last_cdi = cdi.keys().next_back().copied();
+    if terms.indexer == Indexer::Cdi && cdi.is_empty() {
+        anyhow::bail!(
+            "{}: no CDI stored. Run 'interest prices update-benchmarks CDI'",
+            terms.ticker
+        );
+    }
+    let ipca = if terms.indexer == Indexer::Ipca {
+        let ipca = rate_series(conn, Benchmark::Ipca, first_of_month(terms.issue_date), end)?;
+        if ipca.is_empty() {
+            anyhow::bail!(
+                "{}: no IPCA stored. Run 'interest prices update-benchmarks IPCA'",
+                terms.ticker
+            );
+        }
+        ipca
+    } else {
+        BTreeMap::new()
+    };
+
+    let hundred = Decimal::from(100);
+    let per_business_day = |yearly: Decimal| {
+        (Decimal::ONE + yearly / hundred)
+            .checked_powd(Decimal::ONE / Decimal::from(252))
+            .unwrap_or(Decimal::ONE)
+    };
+    let fixed_daily = match terms.indexer {
+        Indexer::Cdi => Decimal::ONE,
+        Indexer::Ipca | Indexer::Prefixado => per_business_day(terms.rate),
+    };
+    let is_business_day = |date: NaiveDate| match last_cdi {
+        Some(last) if date <= last => cdi.contains_key(&date),
+        _ => !matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
+    };
+    let mut ipca_daily: HashMap<NaiveDate, (Decimal, bool)> = HashMap::new();
+
+    let mut factor = Decimal::ONE;
+    let mut day = terms.issue_date;
+    accrual.values.push((day, terms.issue_price));
+    while day < end {
+        let mut published = true;
+        if terms.indexer == Indexer::Ipca {
+            let month = first_of_month(day);
+            let (daily, month_published) = match ipca_daily.get(&month) {
+                Some(cached) => *cached,
+                None => {
+                    let (rate, month_published) =
+                        rate_on(&ipca, month).unwrap_or((Decimal::ZERO, false));
+                    let next_month = month
+                        .checked_add_months(chrono::Months::new(1))
+                        .unwrap_or(month);
+                    let days = (next_month - month).num_days().max(1);
+                    let daily = (Decimal::ONE + rate / hundred)
+                        .checked_powd(Decimal::ONE / Decimal::from(days))
+                        .unwrap_or(Decimal::ONE);
+                    *ipca_daily.entry(month).or_insert((daily, month_published))
+                }
+            };
+            factor = (factor * daily).round_dp(18);
+            published &= month_published;
+        }
+        if is_business_day(day) {
+            let daily = match terms.indexer {
+                Indexer::Cdi => {
+                    let (cdi_rate, day_published) =
+                        rate_on(&cdi, day).unwrap_or((Decimal::ZERO, false));
+                    published &= day_published;
+                    Decimal::ONE + cdi_rate / hundred * terms.rate / hundred
+                }
+                _ => fixed_daily,
+            };
+            factor = (factor * daily).round_dp(18);
+        }
+        if !published && accrual.projected_from.is_none() {
+            accrual.projected_from = Some(day);
+        }
+
+        day = day.succ_opt().unwrap_or(end);
+        if is_business_day(day) || day == end {
+            accrual
+                .values
+                .push((day, (terms.issue_price * factor).round_dp(6)));
+        }
+    }
+    Ok(accrual)
+}
+
+/// Write the PUs of a bond through `through` to the price history
+pub fn store_accrual(
+    conn: &Connection,
+    terms: &FixedIncomeTerms,
+    through: NaiveDate,
+) -> Result<Accrual> {
+    let accrual = accrue(conn, terms, through)?;
+    let prices: Vec<PriceHistory> = accrual
+        .values
+        .iter()
+        .map(|(date, pu)| PriceHistory {
+            id: None,
+            asset_id: terms.asset_id,
+            price_date: *date,
+            close_price: *pu,
+            open_price: None,
+            high_price: None,
+            low_price: None,
+            volume: None,
+            source: ACCRUAL_SOURCE.to_string(),
+            created_at: chrono::Utc::now(),
+            adjusted_close: None,
+        })
+        .collect();
+    db::bulk::insert_price_history(conn, &prices, |_| {})?;
+    Ok(accrual)
+}
+
+/// Accrue every bond with recorded terms through `through`. Bonds whose
+/// index series is missing are skipped with a warning.
+pub fn accrue_all(conn: &Connection, through: NaiveDate) -> Result<Vec<Accrual>> {
+    let mut accruals = Vec::new();
+    for terms in list_terms(conn)? {
+        match store_accrual(conn, &terms, through) {
+            Ok(accrual) => accruals.push(accrual),
+            Err(e) => warn!("Skipping accrual of {}: {}", terms.ticker, e),
+        }
+    }
+    Ok(accruals)
+}
+
+/// A private bond held, marked on the curve
+#[derive(Debug, Clone, Serialize)]
+pub struct FixedIncomePosition {
+    pub terms: FixedIncomeTerms,
+    pub quantity: Decimal,
+    pub cost: Decimal,
+    /// Latest accrued PU and its date
+    pub pu: Option<(NaiveDate, Decimal)>,
+    pub market_value: Option<Decimal>,
+    /// IOF and IR if every lot were redeemed at the PU on `as_of`
+    pub iof: Decimal,
+    pub ir: Decimal,
+    pub net_value: Option<Decimal>,
+}
+
+/// Private bonds with recorded terms held on `as_of`, at the stored PUs
+pub fn open_positions(conn: &Connection, as_of: NaiveDate) -> Result<Vec<FixedIncomePosition>> {
+    let mut positions = Vec::new();
+    for terms in list_terms(conn)? {
+        let history = crate::tesouro::replay_lots(conn, terms.asset_id, as_of)?;
+        if history.lots.is_empty() {
+            continue;
+        }
+        let quantity: Decimal = history.lots.iter().map(|l| l.quantity).sum();
+        let cost: Decimal = history.lots.iter().map(|l| l.unit_cost * l.quantity).sum();
+        let pu = db::get_price_on_or_before(conn, terms.asset_id, as_of)?
+            .map(|p| (p.price_date, p.close_price));
+
+        let (iof, ir) = match pu {
+            Some((date, price)) => {
+                let lots: Vec<LotRedemption> = history
+                    .lots
+                    .iter()
+                    .map(|lot| LotRedemption {
+                        purchase_date: lot.purchase_date,
+                        quantity: lot.quantity,
+                        cost: lot.unit_cost * lot.quantity,
+                        proceeds: price * lot.quantity,
+                        custody: Decimal::ZERO,
+                    })
+                    .collect();
+                let tax = crate::tax::fixed_income::redemption_tax(&terms, date, &lots);
+                (tax.iof, tax.ir)
+            }
+            None => (Decimal::ZERO, Decimal::ZERO),
+        };
+        let market_value = pu.map(|(_, price)| (price * quantity).round_dp(2));
+
+        positions.push(FixedIncomePosition {
+            terms,
+            quantity,
+            cost: cost.round_dp(2),
+            pu,
+            market_value,
+            iof,
+            ir,
+            net_value: market_value.map(|value| value - iof - ir),
+        });
+    }
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::BenchmarkValue;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_cdi_bond_accrues_share_of_published_rates() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("db/schema.sql")).unwrap();
+        let d = |day| NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        db::insert_asset(&conn, "CDB_BANCOX_2027", &AssetType::Unknown, None).unwrap();
+        conn.execute(
+            "INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity, price_per_unit, total_cost)
+             VALUES (1, 'BUY', '2025-01-06', '10', '1000', '10000')",
+            [],
+        )
+        .unwrap();
+        // Mon 6 to Fri 10, with Wednesday 8 a holiday (no CDI published)
+        for day in [6, 7, 9, 10] {
+            db::insert_benchmark_value(
+                &conn,
+                &BenchmarkValue {
+                    benchmark: Benchmark::Cdi,
+                    value_date: d(day),
+                    value: dec!(0.05),
+                    source: "BCB".to_string(),
+                },
+            )
+            .unwrap();
+        }
+
+        let terms = set_terms(
+            &conn,
+            "CDB_BANCOX_2027",
+            FixedIncomeKind::Cdb,
+            d(6),
+            NaiveDate::from_ymd_opt(2027, 1, 6).unwrap(),
+            Indexer::Cdi,
+            dec!(120),
+            None,
+        )
+        .unwrap();
+        assert_eq!(terms.issue_price, dec!(1000));
+        assert_eq!(
+            db::get_asset_by_ticker(&conn, "CDB_BANCOX_2027")
+                .unwrap()
+                .unwrap()
+                .asset_type,
+            AssetType::Bond
+        );
+
+        let accrual = store_accrual(&conn, &terms, d(14)).unwrap();
+        let dates: Vec<_> = accrual.values.iter().map(|(date, _)| *date).collect();
+        assert_eq!(dates, vec![d(6), d(7), d(9), d(10), d(13), d(14)]);
+        // 120% of 0.05% a day over the 6th, 7th and 9th
+        let daily = dec!(1.0006);
+        assert_eq!(
+            accrual.values[3].1,
+            (dec!(1000) * daily * daily * daily).round_dp(6)
+        );
+        // Past the 10th the last CDI is repeated
+        assert_eq!(accrual.projected_from, Some(d(13)));
+        let stored = db::get_price_on_or_before(&conn, terms.asset_id, d(8))
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.price_date, d(7));
+        assert_eq!(stored.source, ACCRUAL_SOURCE);
+
+        let positions = open_positions(&conn, d(14)).unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_eq!(positions[0].quantity, dec!(10));
+        // Held under 30 days: IOF takes most of the yield
+        assert!(positions[0].iof > Decimal::ZERO);
+    }
+}
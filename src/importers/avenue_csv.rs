@@ -0,0 +1,614 @@
+//! Avenue and US broker statement importer.
+//!
+//! Trades and dividends of US stocks and ETFs held at a foreign broker, in
+//! dollars. Two CSV layouts are read:
+//!
+//! - the Avenue statement (`Data`, `Liquidação`, `Descrição`, `Valor (U$)`),
+//!   where the operation, quantity, ticker and price are spelled out in the
+//!   description ("Compra de 2 AAPL a $ 150,00 cada", "Dividendos de AAPL",
+//!   "Impostos sobre dividendos de AAPL");
+//! - the transaction history of US brokers (`Date`, `Action`, `Symbol`,
+//!   `Quantity`, `Price`, `Fees & Comm`, `Amount`, optionally
+//!   `Settlement Date`).
+//!
+//! Tax withheld in the US is a separate row, attached to the dividend of the
+//! same symbol paid within a few days. Deposits, transfers and interest on
+//! the cash balance are ignored.
+//!
+//! Values are converted to reais at the PTAX of the settlement date (the
+//! payment date for dividends) when the statement is imported, and the
+//! assets are flagged as quoted in USD, so their sales fall in the `Foreign`
+//! tax category (see `tax::foreign_gains`).
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{Datelike, NaiveDate, Weekday};
+use regex::Regex;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tracing::{debug, info, warn};
+
+use super::cei_excel::RawTransaction;
+use super::proventos_excel::ProventoEntry;
+use crate::db::models::{IncomeEventType, TransactionType};
+use crate::pricing::fx;
+
+/// Source recorded on trades and income read from US broker statements
+pub const SOURCE: &str = "US_BROKER";
+
+/// Currency of every value in the statement
+pub const CURRENCY: &str = "USD";
+
+/// Days between a dividend and the tax row withheld on it
+const TAX_WINDOW_DAYS: i64 = 5;
+
+/// A trade in dollars
+#[derive(Debug, Clone, Serialize)]
+pub struct UsTrade {
+    pub ticker: String,
+    pub transaction_type: TransactionType,
+    pub trade_date: NaiveDate,
+    pub settlement_date: NaiveDate,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fees: Decimal,
+}
+
+/// A dividend in dollars with the tax withheld in the US
+#[derive(Debug, Clone, Serialize)]
+pub struct UsDividend {
+    pub ticker: String,
+    pub payment_date: NaiveDate,
+    pub gross: Decimal,
+    pub tax: Decimal,
+}
+
+/// Trades and dividends of one statement
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UsBrokerStatement {
+    /// Avenue, or None for other brokers
+    pub broker: Option<String>,
+    pub trades: Vec<UsTrade>,
+    pub dividends: Vec<UsDividend>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Layout {
+    Avenue,
+    Generic,
+}
+
+fn layout(header: &str) -> Option<Layout> {
+    let header = header.to_lowercase();
+    if (header.contains("descrição") || header.contains("descricao"))
+        && (header.contains("u$") || header.contains("liquidação") || header.contains("liquidacao"))
+    {
+        Some(Layout::Avenue)
+    } else if header.contains("symbol") && header.contains("action") {
+        Some(Layout::Generic)
+    } else {
+        None
+    }
+}
+
+/// Whether a CSV header line looks like an Avenue or US broker statement
+pub fn is_us_broker_header(line: &str) -> bool {
+    layout(line).is_some()
+}
+
+impl UsBrokerStatement {
+    /// Tickers traded or paying dividends, sorted
+    pub fn tickers(&self) -> Vec<String> {
+        let mut tickers: Vec<String> = self
+            .trades
+            .iter()
+            .map(|t| t.ticker.clone())
+            .chain(self.dividends.iter().map(|d| d.ticker.clone()))
+            .collect();
+        tickers.sort();
+        tickers.dedup();
+        tickers
+    }
+
+    /// First and last dates whose PTAX is needed
+    pub fn rate_dates(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let dates = self
+            .trades
+            .iter()
+            .map(|t| t.settlement_date)
+            .chain(self.dividends.iter().map(|d| d.payment_date));
+        let (mut first, mut last) = (None::<NaiveDate>, None::<NaiveDate>);
+        for date in dates {
+            first = Some(first.map_or(date, |d| d.min(date)));
+            last = Some(last.map_or(date, |d| d.max(date)));
+        }
+        Some((first?, last?))
+    }
+
+    /// Trades and income in reais, at the PTAX on or before each settlement
+    /// or payment date
+    pub fn to_brl(&self, conn: &Connection) -> Result<(Vec<RawTransaction>, Vec<ProventoEntry>)> {
+        let rate = |date: NaiveDate| -> Result<(NaiveDate, Decimal)> {
+            crate::db::get_fx_rate_on_or_before(conn, fx::USD_BRL, date)?.ok_or_else(|| {
+                anyhow!(
+                    "No USD/BRL PTAX on or before {}; run 'interest prices update-fx USD --from {}'",
+                    date,
+                    date
+                )
+            })
+        };
+
+        let mut trades = Vec::new();
+        for trade in &self.trades {
+            let (rate_date, rate) = rate(trade.settlement_date)?;
+            trades.push(RawTransaction {
+                ticker: trade.ticker.clone(),
+                transaction_type: trade.transaction_type.as_str().to_string(),
+                trade_date: trade.trade_date,
+                quantity: trade.quantity,
+                price: (trade.price * rate).round_dp(6),
+                fees: (trade.fees * rate).round_dp(2),
+                total: (trade.quantity * trade.price * rate).round_dp(2),
+                market: Some(format!(
+                    "US$ {} a PTAX {} de {}",
+                    trade.price.normalize(),
+                    rate,
+                    rate_date.format("%d/%m/%Y")
+                )),
+            });
+        }
+
+        let mut income = Vec::new();
+        for dividend in &self.dividends {
+            let (rate_date, rate) = rate(dividend.payment_date)?;
+            let received = ((dividend.gross - dividend.tax) * rate).round_dp(2);
+            income.push(ProventoEntry {
+                ticker: dividend.ticker.clone(),
+                product: dividend.ticker.clone(),
+                event_type: IncomeEventType::Dividend,
+                movement_type: format!(
+                    "Dividendo US$ {} a PTAX {} de {}",
+                    dividend.gross.normalize(),
+                    rate,
+                    rate_date.format("%d/%m/%Y")
+                ),
+                ex_date: None,
+                payment_date: dividend.payment_date,
+                institution: self.broker.clone().unwrap_or_default(),
+                quantity: None,
+                gross: received,
+                withholding: Decimal::ZERO,
+                net: received,
+                foreign_tax: Some((dividend.tax * rate).round_dp(2)),
+            });
+        }
+        Ok((trades, income))
+    }
+}
+
+/// Amounts may carry `$`/`US$`, parentheses for negatives and either
+/// separator; with both, the last one is the decimal point
+pub(crate) fn parse_amount(raw: &str, decimal_comma: bool) -> Result<Decimal> {
+    let trimmed = raw.trim();
+    let negative = trimmed.starts_with('(') || trimmed.contains('-');
+    let digits: String = trimmed
+        .chars()
+        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .collect();
+    let normalized = match (digits.rfind(','), digits.rfind('.')) {
+        (Some(comma), Some(dot)) if comma > dot => digits.replace('.', "").replace(',', "."),
+        (Some(_), Some(_)) => digits.replace(',', ""),
+        (Some(_), None) if decimal_comma => digits.replace(',', "."),
+        (Some(_), None) => digits.replace(',', ""),
+        // "1.500" is fifteen hundred in a statement written with commas
+        (None, Some(dot)) if decimal_comma && digits.len() - dot == 4 => digits.replace('.', ""),
+        _ => digits,
+    };
+    let value = Decimal::from_str(&normalized)
+        .with_context(|| format!("Invalid amount '{}'", raw.trim()))?;
+    Ok(if negative { -value } else { value })
+}
+
+fn parse_date(raw: &str, layout: Layout) -> Result<NaiveDate> {
+    // "04/15/2024 as of 04/12/2024": the first date is the posting date
+    let raw = raw.trim();
+    let date = raw.get(..10).unwrap_or(raw);
+    let formats: &[&str] = match layout {
+        Layout::Avenue => &["%d/%m/%Y", "%Y-%m-%d"],
+        Layout::Generic => &["%m/%d/%Y", "%Y-%m-%d"],
+    };
+    formats
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+        .ok_or_else(|| anyhow!("Invalid date '{}'", raw))
+}
+
+/// US trades settle one business day after the trade (T+1) since
+/// 2024-05-28, two before
+fn settlement_after(trade_date: NaiveDate) -> NaiveDate {
+    let t_plus_one = NaiveDate::from_ymd_opt(2024, 5, 28).expect("valid date");
+    let mut days = if trade_date >= t_plus_one { 1 } else { 2 };
+    let mut date = trade_date;
+    while days > 0 {
+        date = date.succ_opt().unwrap_or(date);
+        if !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            days -= 1;
+        }
+    }
+    date
+}
+
+fn find(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    headers.iter().position(|h| {
+        let h = h.trim().to_lowercase();
+        names.iter().any(|name| h == *name)
+    })
+}
+
+fn normalize_ticker(raw: &str) -> String {
+    raw.trim().to_uppercase().replace('/', ".")
+}
+
+/// A row of either layout, before taxes are attached to dividends
+enum Row {
+    Trade(UsTrade),
+    Dividend(UsDividend),
+    Tax {
+        ticker: String,
+        date: NaiveDate,
+        amount: Decimal,
+    },
+}
+
+fn avenue_trade_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?i)^(compra|venda)\s+de\s+([\d.,]+)\s+(?:a[cç][oõ]es\s+de\s+)?([A-Z][A-Z0-9./-]*)\s+a\s+(?:US)?\$\s*([\d.,]+)")
+            .expect("valid pattern")
+    })
+}
+
+/// Ticker named last in a description ("Dividendos de AAPL")
+fn trailing_ticker(description: &str) -> Option<String> {
+    description
+        .split_whitespace()
+        .rev()
+        .map(|word| word.trim_matches(|c: char| !c.is_ascii_alphanumeric()))
+        .find(|word| {
+            !word.is_empty()
+                && word.len() <= 6
+                && word.chars().all(|c| c.is_ascii_uppercase() || c == '.')
+        })
+        .map(normalize_ticker)
+}
+
+fn parse_avenue_row(record: &csv::StringRecord, columns: &AvenueColumns) -> Result<Option<Row>> {
+    let field = |idx: usize| record.get(idx).unwrap_or("").trim();
+    let description = field(columns.description);
+    if description.is_empty() {
+        return Ok(None);
+    }
+    let date = parse_date(field(columns.date), Layout::Avenue)?;
+    let amount = parse_amount(field(columns.amount), true)?;
+    let lower = description.to_lowercase();
+
+    if let Some(captures) = avenue_trade_pattern().captures(description) {
+        let transaction_type = if captures[1].to_lowercase() == "compra" {
+            TransactionType::Buy
+        } else {
+            TransactionType::Sell
+        };
+        let quantity = parse_amount(&captures[2], true)?;
+        let price = parse_amount(&captures[4], true)?;
+        let settlement_date = match columns.settlement.map(field).filter(|s| !s.is_empty()) {
+            Some(raw) => parse_date(raw, Layout::Avenue)?,
+            None => settlement_after(date),
+        };
+        // The amount moved includes the commission
+        let fees = match transaction_type {
+            TransactionType::Buy => amount.abs() - quantity * price,
+            _ => quantity * price - amount.abs(),
+        }
+        .max(Decimal::ZERO);
+        return Ok(Some(Row::Trade(UsTrade {
+            ticker: normalize_ticker(&captures[3]),
+            transaction_type,
+            trade_date: date,
+            settlement_date,
+            quantity,
+            price,
+            fees,
+        })));
+    }
+
+    let Some(ticker) = trailing_ticker(description) else {
+        debug!("Ignoring Avenue row '{}'", description);
+        return Ok(None);
+    };
+    if lower.starts_with("imposto") {
+        return Ok(Some(Row::Tax {
+            ticker,
+            date,
+            amount: amount.abs(),
+        }));
+    }
+    if lower.starts_with("dividendo") || lower.starts_with("provento") {
+        return Ok(Some(Row::Dividend(UsDividend {
+            ticker,
+            payment_date: date,
+            gross: amount.abs(),
+            tax: Decimal::ZERO,
+        })));
+    }
+    debug!("Ignoring Avenue row '{}'", description);
+    Ok(None)
+}
+
+fn parse_generic_row(record: &csv::StringRecord, columns: &GenericColumns) -> Result<Option<Row>> {
+    let field = |idx: usize| record.get(idx).unwrap_or("").trim();
+    let optional = |idx: Option<usize>| -> Result<Decimal> {
+        match idx.map(field).filter(|v| !v.is_empty()) {
+            Some(raw) => parse_amount(raw, false),
+            None => Ok(Decimal::ZERO),
+        }
+    };
+    let action = field(columns.action).to_lowercase();
+    let ticker = normalize_ticker(field(columns.symbol));
+    if ticker.is_empty() || action.is_empty() {
+        return Ok(None);
+    }
+    let date = parse_date(field(columns.date), Layout::Generic)?;
+    let amount = optional(columns.amount)?;
+
+    let transaction_type = match action.as_str() {
+        "buy" | "bought" | "reinvest shares" | "buy to open" => Some(TransactionType::Buy),
+        "sell" | "sold" | "sell to close" => Some(TransactionType::Sell),
+        _ => None,
+    };
+    if let Some(transaction_type) = transaction_type {
+        let quantity = optional(Some(columns.quantity))?.abs();
+        let mut price = optional(columns.price)?.abs();
+        if price.is_zero() && !quantity.is_zero() {
+            price = amount.abs() / quantity;
+        }
+        let settlement_date = match columns.settlement.map(field).filter(|s| !s.is_empty()) {
+            Some(raw) => parse_date(raw, Layout::Generic)?,
+            None => settlement_after(date),
+        };
+        return Ok(Some(Row::Trade(UsTrade {
+            ticker,
+            transaction_type,
+            trade_date: date,
+            settlement_date,
+            quantity,
+            price,
+            fees: optional(columns.fees)?.abs(),
+        })));
+    }
+    if action.contains("tax") {
+        return Ok(Some(Row::Tax {
+            ticker,
+            date,
+            amount: amount.abs(),
+        }));
+    }
+    if action.contains("dividend") || action.contains(" div") {
+        return Ok(Some(Row::Dividend(UsDividend {
+            ticker,
+            payment_date: date,
+            gross: amount.abs(),
+            tax: Decimal::ZERO,
+        })));
+    }
+    debug!("Ignoring '{}' row of {}", action, ticker);
+    Ok(None)
+}
+
+struct AvenueColumns {
+    date: usize,
+    settlement: Option<usize>,
+    description: usize,
+    amount: usize,
+}
+
+struct GenericColumns {
+    date: usize,
+    settlement: Option<usize>,
+    action: usize,
+    symbol: usize,
+    quantity: usize,
+    price: Option<usize>,
+    fees: Option<usize>,
+    amount: Option<usize>,
+}
+
+/// Parse an Avenue or US broker CSV statement
+pub fn parse_us_broker_csv<P: AsRef<Path>>(path: P) -> Result<UsBrokerStatement> {
+    let path = path.as_ref();
+    info!("Parsing US broker statement: {:?}", path);
+    let content = super::inspect::read_text(path).context("Failed to read statement")?;
+    parse_us_broker_content(&content)
+}
+
+fn parse_us_broker_content(content: &str) -> Result<UsBrokerStatement> {
+    let content = content.trim_start_matches('\u{feff}');
+    let header = content.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let layout = layout(header)
+        .ok_or_else(|| anyhow!("Not an Avenue or US broker statement: {}", header))?;
+    let delimiter = if header.matches(';').count() > header.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    let required = |names: &[&str]| {
+        find(&headers, names).ok_or_else(|| {
+            anyhow!(
+                "Statement is missing a '{}' column (found: {:?})",
+                names[0],
+                headers
+            )
+        })
+    };
+
+    let mut rows = Vec::new();
+    match layout {
+        Layout::Avenue => {
+            let columns = AvenueColumns {
+                date: required(&["data", "data da operação", "data da operacao"])?,
+                settlement: find(
+                    &headers,
+                    &["liquidação", "liquidacao", "data de liquidação"],
+                ),
+                description: required(&["descrição", "descricao"])?,
+                amount: required(&["valor (u$)", "valor (us$)", "valor"])?,
+            };
+            for (row, record) in reader.records().enumerate() {
+                match parse_avenue_row(&record?, &columns) {
+                    Ok(Some(parsed)) => rows.push(parsed),
+                    Ok(None) => {}
+                    Err(e) => warn!("Skipping row {}: {}", row + 2, e),
+                }
+            }
+        }
+        Layout::Generic => {
+            let columns = GenericColumns {
+                date: required(&["date", "trade date", "run date"])?,
+                settlement: find(&headers, &["settlement date", "settle date"]),
+                action: required(&["action", "activity", "type"])?,
+                symbol: required(&["symbol", "ticker"])?,
+                quantity: required(&["quantity", "qty", "shares"])?,
+                price: find(&headers, &["price", "price ($)"]),
+                fees: find(&headers, &["fees & comm", "fees", "commission"]),
+                amount: find(&headers, &["amount", "amount ($)", "net amount"]),
+            };
+            for (row, record) in reader.records().enumerate() {
+                match parse_generic_row(&record?, &columns) {
+                    Ok(Some(parsed)) => rows.push(parsed),
+                    Ok(None) => {}
+                    Err(e) => warn!("Skipping row {}: {}", row + 2, e),
+                }
+            }
+        }
+    }
+
+    let mut statement = UsBrokerStatement {
+        broker: (layout == Layout::Avenue).then(|| "Avenue".to_string()),
+        ..Default::default()
+    };
+    let mut taxes = Vec::new();
+    for row in rows {
+        match row {
+            Row::Trade(trade) => statement.trades.push(trade),
+            Row::Dividend(dividend) => statement.dividends.push(dividend),
+            Row::Tax {
+                ticker,
+                date,
+                amount,
+            } => taxes.push((ticker, date, amount)),
+        }
+    }
+    for (ticker, date, amount) in taxes {
+        let dividend = statement
+            .dividends
+            .iter_mut()
+            .filter(|d| {
+                d.ticker == ticker && (d.payment_date - date).num_days().abs() <= TAX_WINDOW_DAYS
+            })
+            .min_by_key(|d| (d.payment_date - date).num_days().abs());
+        match dividend {
+            Some(dividend) => dividend.tax += amount,
+            None => warn!("Tax of {} on {} without a dividend, skipped", ticker, date),
+        }
+    }
+    statement.trades.sort_by_key(|t| t.trade_date);
+    statement.dividends.sort_by_key(|d| d.payment_date);
+
+    info!(
+        "Parsed {} trades and {} dividends from the US broker statement",
+        statement.trades.len(),
+        statement.dividends.len()
+    );
+    Ok(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_avenue_and_generic_statements() {
+        let avenue = "Data,Hora,Liquidação,Descrição,Valor (U$),Saldo da conta (U$)\n\
+02/01/2024,10:31,04/01/2024,\"Compra de 2 AAPL a $ 150,00 cada\",\"-301,00\",\"699,00\"\n\
+15/02/2024,09:00,15/02/2024,Dividendos de AAPL,\"0,48\",\"699,48\"\n\
+15/02/2024,09:00,15/02/2024,Impostos sobre dividendos de AAPL,\"-0,14\",\"699,34\"\n\
+01/03/2024,09:00,01/03/2024,Depósito via câmbio,\"1.000,00\",\"1.699,34\"\n\
+10/06/2024,14:02,11/06/2024,\"Venda de 1 AAPL a $ 190,50 cada\",\"190,50\",\"1.889,84\"\n";
+        assert!(is_us_broker_header(avenue.lines().next().unwrap()));
+        let statement = parse_us_broker_content(avenue).unwrap();
+        assert_eq!(statement.broker.as_deref(), Some("Avenue"));
+        assert_eq!(statement.trades.len(), 2);
+        let buy = &statement.trades[0];
+        assert_eq!(buy.ticker, "AAPL");
+        assert_eq!(buy.transaction_type, TransactionType::Buy);
+        assert_eq!(
+            (buy.quantity, buy.price, buy.fees),
+            (dec!(2), dec!(150), dec!(1))
+        );
+        assert_eq!(
+            buy.settlement_date,
+            NaiveDate::from_ymd_opt(2024, 1, 4).unwrap()
+        );
+        assert_eq!(statement.trades[1].transaction_type, TransactionType::Sell);
+        assert_eq!(statement.dividends.len(), 1);
+        assert_eq!(
+            (statement.dividends[0].gross, statement.dividends[0].tax),
+            (dec!(0.48), dec!(0.14))
+        );
+
+        let generic = "Date,Action,Symbol,Description,Quantity,Price,Fees & Comm,Amount\n\
+05/24/2024,Buy,VOO,VANGUARD S&P 500 ETF,3,$480.10,$0.00,\"-$1,440.30\"\n\
+06/28/2024 as of 06/27/2024,Qualified Dividend,VOO,VANGUARD S&P 500 ETF,,,,$5.34\n\
+06/28/2024,NRA Tax Adj,VOO,VANGUARD S&P 500 ETF,,,,($1.60)\n\
+07/01/2024,MoneyLink Transfer,,Tfr BANK,,,,\"$2,000.00\"\n";
+        let statement = parse_us_broker_content(generic).unwrap();
+        assert_eq!(statement.broker, None);
+        let buy = &statement.trades[0];
+        assert_eq!((buy.ticker.as_str(), buy.price), ("VOO", dec!(480.10)));
+        // Traded on a Friday, before the switch to T+1
+        assert_eq!(
+            buy.settlement_date,
+            NaiveDate::from_ymd_opt(2024, 5, 28).unwrap()
+        );
+        assert_eq!(
+            (statement.dividends[0].gross, statement.dividends[0].tax),
+            (dec!(5.34), dec!(1.60))
+        );
+
+        // Converted at the PTAX on or before settlement
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        crate::db::insert_fx_rate(
+            &conn,
+            fx::USD_BRL,
+            NaiveDate::from_ymd_opt(2024, 5, 28).unwrap(),
+            dec!(5.20),
+            "BCB",
+        )
+        .unwrap();
+        let (trades, income) = statement.to_brl(&conn).unwrap();
+        assert_eq!(trades[0].total, dec!(7489.56));
+        assert_eq!(trades[0].price, dec!(2496.52));
+        assert_eq!(income[0].gross, dec!(19.45));
+        assert_eq!(income[0].foreign_tax, Some(dec!(8.32)));
+    }
+}
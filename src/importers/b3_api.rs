@@ -0,0 +1,457 @@
+//! B3 "Área do Investidor" API client
+//!
+//! Pulls the same data as the manual Excel downloads straight from B3:
+//! - **Negociações**: trades, mapped to [`RawTransaction`] (the CEI layout)
+//! - **Movimentações**: account movements, mapped to [`MovimentacaoEntry`]
+//! - **Posições**: end-of-day custody, used to reconcile computed holdings
+//!
+//! Authentication is OAuth2 client credentials over mutual TLS, using the
+//! certificate B3 issues when the application is registered. The access
+//! token is cached in `~/.interest/b3_api_token.json` until it expires.
+//!
+//! B3 publishes data with one business day of delay, and every endpoint is
+//! paginated through `links.next`.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::{debug, info};
+
+use crate::config::B3ApiConfig;
+use crate::importers::movimentacao_layout;
+use crate::importers::{MovimentacaoEntry, RawTransaction};
+
+const DEFAULT_BASE_URL: &str = "https://investidor.b3.com.br:2443";
+const DEFAULT_TOKEN_URL: &str =
+    "https://login.microsoftonline.com/4bee639f-5388-44c7-bbac-cb92a93911e6/oauth2/v2.0/token";
+const DEFAULT_SCOPE: &str = "0c991613-4c90-454d-8685-d466a47669cb/.default";
+
+const TRADES_PATH: &str = "/api/assets-trading/v2/equities/investors";
+const MOVEMENTS_PATH: &str = "/api/movement/v2/equities/investors";
+const POSITIONS_PATH: &str = "/api/position/v3/equities/investors";
+
+const TOKEN_FILENAME: &str = "b3_api_token.json";
+
+/// Seconds shaved off the token lifetime so it never expires mid-sync
+const TOKEN_EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// One trade from the negotiations endpoint
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTrade {
+    pub reference_date: NaiveDate,
+    pub ticker_symbol: String,
+    /// "Compra" or "Venda"
+    pub side_name: String,
+    /// "Mercado à Vista", "Mercado Fracionário", "Termo", ...
+    #[serde(default)]
+    pub market_name: Option<String>,
+    pub trade_quantity: Decimal,
+    pub price_value: Decimal,
+    #[serde(default)]
+    pub gross_amount: Option<Decimal>,
+}
+
+/// One entry from the movements endpoint
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiMovement {
+    pub reference_date: NaiveDate,
+    /// "Credito" or "Debito"
+    pub operation_type: String,
+    pub movement_type: String,
+    #[serde(default)]
+    pub ticker_symbol: Option<String>,
+    #[serde(default)]
+    pub corporation_name: Option<String>,
+    #[serde(default)]
+    pub participant_name: Option<String>,
+    #[serde(default)]
+    pub equities_quantity: Option<Decimal>,
+    #[serde(default)]
+    pub unit_price: Option<Decimal>,
+    #[serde(default)]
+    pub operation_value: Option<Decimal>,
+}
+
+/// One custody position from the positions endpoint
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiPosition {
+    pub ticker_symbol: String,
+    pub equities_quantity: Decimal,
+    #[serde(default)]
+    pub participant_name: Option<String>,
+}
+
+impl ApiTrade {
+    /// Convert to the CEI trade layout so it shares the CEI import path
+    pub fn to_raw_transaction(&self) -> RawTransaction {
+        let total = self
+            .gross_amount
+            .unwrap_or(self.trade_quantity * self.price_value);
+        RawTransaction {
+            ticker: self.ticker_symbol.trim().to_uppercase(),
+            transaction_type: self.side_name.trim().to_string(),
+            trade_date: self.reference_date,
+            quantity: self.trade_quantity,
+            price: self.price_value,
+            fees: Decimal::ZERO,
+            total,
+            market: self.market_name.clone(),
+        }
+    }
+}
+
+impl ApiMovement {
+    /// Convert to a Movimentação row so it shares the Excel import path
+    pub fn to_movimentacao_entry(&self) -> MovimentacaoEntry {
+        let product = match (&self.ticker_symbol, &self.corporation_name) {
+            (Some(ticker), Some(name)) => format!("{} - {}", ticker.trim(), name.trim()),
+            (Some(ticker), None) => ticker.trim().to_string(),
+            (None, Some(name)) => name.trim().to_string(),
+            (None, None) => String::new(),
+        };
+        let ticker = self
+            .ticker_symbol
+            .as_deref()
+            .map(|t| t.trim().to_uppercase())
+            .filter(|t| !t.is_empty())
+            .or_else(|| MovimentacaoEntry::extract_ticker(&product));
+
+        MovimentacaoEntry {
+            direction: self.operation_type.trim().to_string(),
+            date: self.reference_date,
+            movement_type: movimentacao_layout::normalize_movement_type(&self.movement_type),
+            product,
+            ticker,
+            institution: self.participant_name.clone().unwrap_or_default(),
+            quantity: self.equities_quantity.filter(|q| *q > Decimal::ZERO),
+            unit_price: self.unit_price.filter(|p| *p > Decimal::ZERO),
+            operation_value: self.operation_value,
+        }
+    }
+}
+
+/// Paginated response envelope shared by all endpoints
+#[derive(Debug, Deserialize)]
+struct Envelope<D> {
+    data: Option<D>,
+    #[serde(alias = "Links")]
+    links: Option<Links>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Links {
+    next: Option<String>,
+}
+
+/// Endpoint payloads nest their list under different keys
+trait PageItems {
+    type Item;
+    fn into_items(self) -> Vec<Self::Item>;
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TradeData {
+    #[serde(default)]
+    equities_trades: Vec<ApiTrade>,
+}
+
+impl PageItems for TradeData {
+    type Item = ApiTrade;
+    fn into_items(self) -> Vec<ApiTrade> {
+        self.equities_trades
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MovementData {
+    #[serde(default)]
+    equities_movements: Vec<ApiMovement>,
+}
+
+impl PageItems for MovementData {
+    type Item = ApiMovement;
+    fn into_items(self) -> Vec<ApiMovement> {
+        self.equities_movements
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PositionData {
+    #[serde(default)]
+    equities_positions: Vec<ApiPosition>,
+}
+
+impl PageItems for PositionData {
+    type Item = ApiPosition;
+    fn into_items(self) -> Vec<ApiPosition> {
+        self.equities_positions
+    }
+}
+
+/// Parse one page, returning its items and the next page URL
+fn parse_page<D: PageItems + DeserializeOwned>(
+    body: &str,
+) -> Result<(Vec<D::Item>, Option<String>)> {
+    let envelope: Envelope<D> = serde_json::from_str(body).context("Unexpected B3 API response")?;
+    let items = envelope.data.map(PageItems::into_items).unwrap_or_default();
+    let next = envelope
+        .links
+        .and_then(|l| l.next)
+        .filter(|n| !n.trim().is_empty());
+    Ok((items, next))
+}
+
+/// Access token cached between runs
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedToken {
+    client_id: String,
+    access_token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: i64,
+}
+
+fn token_path() -> Result<PathBuf> {
+    Ok(crate::db::get_interest_dir()?.join(TOKEN_FILENAME))
+}
+
+fn load_cached_token(client_id: &str) -> Option<String> {
+    let path = token_path().ok()?;
+    let raw = std::fs::read_to_string(path).ok()?;
+    let cached: CachedToken = serde_json::from_str(&raw).ok()?;
+    (cached.client_id == client_id && cached.expires_at > Utc::now()).then_some(cached.access_token)
+}
+
+fn store_token(token: &CachedToken) -> Result<()> {
+    let path = token_path()?;
+    std::fs::write(&path, serde_json::to_string_pretty(token)?)
+        .with_context(|| format!("Failed to write {:?}", path))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+/// Authenticated B3 API session
+pub struct B3ApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    document_number: String,
+    token: String,
+}
+
+impl B3ApiClient {
+    /// Build the mTLS client and obtain an access token (cached or fresh)
+    pub async fn connect(cfg: &B3ApiConfig) -> Result<Self> {
+        let offline = std::env::var("INTEREST_OFFLINE")
+            .map(|v| v != "0")
+            .unwrap_or(false);
+        if offline {
+            anyhow::bail!("B3 API sync skipped (INTEREST_OFFLINE is set)");
+        }
+
+        let mut pem = std::fs::read(cfg.certificate())
+            .with_context(|| format!("Failed to read certificate {:?}", cfg.certificate()))?;
+        pem.push(b'\n');
+        pem.extend(
+            std::fs::read(cfg.private_key())
+                .with_context(|| format!("Failed to read private key {:?}", cfg.private_key()))?,
+        );
+        let identity =
+            reqwest::Identity::from_pem(&pem).context("Invalid B3 certificate or key")?;
+
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(60))
+            .user_agent("interest")
+            .identity(identity)
+            .build()?;
+
+        let token = match load_cached_token(&cfg.client_id) {
+            Some(token) => {
+                debug!("Using cached B3 API token");
+                token
+            }
+            None => request_token(&http, cfg).await?,
+        };
+
+        Ok(Self {
+            http,
+            base_url: cfg
+                .base_url
+                .clone()
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            document_number: cfg.document_number(),
+            token,
+        })
+    }
+
+    /// Trades between two dates (inclusive)
+    pub async fn trades(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<ApiTrade>> {
+        self.fetch_all::<TradeData>(TRADES_PATH, &period_query(from, to))
+            .await
+    }
+
+    /// Account movements between two dates (inclusive)
+    pub async fn movements(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<ApiMovement>> {
+        self.fetch_all::<MovementData>(MOVEMENTS_PATH, &period_query(from, to))
+            .await
+    }
+
+    /// Custody positions at the end of a day
+    pub async fn positions(&self, date: NaiveDate) -> Result<Vec<ApiPosition>> {
+        let query = vec![("referenceDate", date.format("%Y-%m-%d").to_string())];
+        self.fetch_all::<PositionData>(POSITIONS_PATH, &query).await
+    }
+
+    async fn fetch_all<D: PageItems + DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<Vec<D::Item>> {
+        let mut items = Vec::new();
+        let first = reqwest::Url::parse_with_params(
+            &format!("{}{}/{}", self.base_url, path, self.document_number),
+            query,
+        )?;
+        let mut request = self.http.get(first);
+        loop {
+            let body = request
+                .bearer_auth(&self.token)
+                .send()
+                .await
+                .context("Failed to reach the B3 API")?
+                .error_for_status()
+                .context("B3 API request failed")?
+                .text()
+                .await?;
+            let (page, next) = parse_page::<D>(&body)?;
+            items.extend(page);
+            match next {
+                Some(url) => {
+                    let url = if url.starts_with('/') {
+                        format!("{}{}", self.base_url, url)
+                    } else {
+                        url
+                    };
+                    request = self.http.get(url);
+                }
+                None => break,
+            }
+        }
+        debug!("{}: {} items", path, items.len());
+        Ok(items)
+    }
+}
+
+fn period_query(from: NaiveDate, to: NaiveDate) -> Vec<(&'static str, String)> {
+    vec![
+        ("referenceStartDate", from.format("%Y-%m-%d").to_string()),
+        ("referenceEndDate", to.format("%Y-%m-%d").to_string()),
+    ]
+}
+
+async fn request_token(http: &reqwest::Client, cfg: &B3ApiConfig) -> Result<String> {
+    info!("Requesting B3 API access token");
+    let url = cfg.token_url.as_deref().unwrap_or(DEFAULT_TOKEN_URL);
+    let scope = cfg.scope.as_deref().unwrap_or(DEFAULT_SCOPE);
+    let body = form_urlencoded::Serializer::new(String::new())
+        .append_pair("grant_type", "client_credentials")
+        .append_pair("client_id", &cfg.client_id)
+        .append_pair("client_secret", &cfg.client_secret)
+        .append_pair("scope", scope)
+        .finish();
+    let response: TokenResponse = http
+        .post(url)
+        .header(
+            reqwest::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(body)
+        .send()
+        .await
+        .context("Failed to reach the B3 token endpoint")?
+        .error_for_status()
+        .map_err(|e| anyhow!("B3 API authentication failed: {}", e))?
+        .json()
+        .await
+        .context("Unexpected B3 token response")?;
+
+    store_token(&CachedToken {
+        client_id: cfg.client_id.clone(),
+        access_token: response.access_token.clone(),
+        expires_at: Utc::now() + Duration::seconds(response.expires_in - TOKEN_EXPIRY_MARGIN_SECS),
+    })?;
+    Ok(response.access_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_trade_page_and_convert() {
+        let body = r#"{
+            "data": {"equitiesTrades": [{
+                "referenceDate": "2024-03-05",
+                "tickerSymbol": "PETR4F",
+                "sideName": "Compra",
+                "marketName": "Mercado Fracionário",
+                "tradeQuantity": 7,
+                "priceValue": 38.12
+            }]},
+            "Links": {"self": "/page=1", "next": "/api/assets-trading/v2/equities/investors/1?page=2"}
+        }"#;
+        let (trades, next) = parse_page::<TradeData>(body).unwrap();
+        assert_eq!(trades.len(), 1);
+        assert!(next.unwrap().ends_with("page=2"));
+
+        let raw = trades[0].to_raw_transaction();
+        assert_eq!(raw.normalized_ticker(), "PETR4");
+        assert_eq!(raw.total, dec!(266.84));
+        assert_eq!(raw.trade_date, NaiveDate::from_ymd_opt(2024, 3, 5).unwrap());
+    }
+
+    #[test]
+    fn test_parse_movement_page_and_convert() {
+        let body = r#"{
+            "data": {"equitiesMovements": [{
+                "referenceDate": "2024-05-15",
+                "operationType": "Credito",
+                "movementType": "Rendimento",
+                "tickerSymbol": "HGLG11",
+                "corporationName": "CSHG LOGISTICA FII",
+                "participantName": "XP INVESTIMENTOS CCTVM S/A",
+                "equitiesQuantity": 10,
+                "unitPrice": 1.1,
+                "operationValue": 11.0
+            }]},
+            "links": {"next": null}
+        }"#;
+        let (movements, next) = parse_page::<MovementData>(body).unwrap();
+        assert!(next.is_none());
+
+        let entry = movements[0].to_movimentacao_entry();
+        assert_eq!(entry.ticker.as_deref(), Some("HGLG11"));
+        assert_eq!(entry.product, "HGLG11 - CSHG LOGISTICA FII");
+        assert!(entry.is_income_event());
+        assert_eq!(entry.operation_value, Some(dec!(11)));
+    }
+}
@@ -10,7 +10,7 @@
 //! - Graceful error handling with fallback strategies
 
 use anyhow::{anyhow, Context, Result};
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
 use reqwest::blocking::Client;
 use rusqlite::Connection;
 use rust_decimal::Decimal;
@@ -50,6 +50,52 @@ pub enum DownloadStage {
     Complete,
 }
 
+/// One COTAHIST file: a whole year or a single month
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CotahistPeriod {
+    Year(i32),
+    Month(i32, u32),
+}
+
+impl CotahistPeriod {
+    pub fn file_name(&self) -> String {
+        match self {
+            CotahistPeriod::Year(year) => format!("COTAHIST_A{}.ZIP", year),
+            CotahistPeriod::Month(year, month) => format!("COTAHIST_M{:02}{}.ZIP", month, year),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            CotahistPeriod::Year(year) => year.to_string(),
+            CotahistPeriod::Month(year, month) => format!("{:02}/{}", month, year),
+        }
+    }
+}
+
+/// Files needed to backfill from a year (or a month of it) through today
+///
+/// Past years use the annual file. A month-precise start loads that year's
+/// remaining months from monthly files instead, which are much smaller. The
+/// current year always uses the annual file, the only one with the current
+/// month.
+pub fn backfill_periods(
+    from_year: i32,
+    from_month: Option<u32>,
+    today: NaiveDate,
+) -> Vec<CotahistPeriod> {
+    let mut periods = Vec::new();
+    for year in from_year..=today.year() {
+        match from_month {
+            Some(month) if year == from_year && year < today.year() && month > 1 => {
+                periods.extend((month..=12).map(|m| CotahistPeriod::Month(year, m)));
+            }
+            _ => periods.push(CotahistPeriod::Year(year)),
+        }
+    }
+    periods
+}
+
 /// Get the platform-specific cache directory for COTAHIST files
 pub fn get_cotahist_cache_dir() -> Result<PathBuf> {
     let cache_dir = std::env::var_os("XDG_CACHE_HOME")
@@ -76,6 +122,12 @@ pub fn clear_cache(year: Option<i32>) -> Result<()> {
                 std::fs::remove_file(&file).context("Failed to delete cache file")?;
                 tracing::info!("Deleted cache for COTAHIST {}", y);
             }
+            for month in 1..=12 {
+                let file = cache_dir.join(CotahistPeriod::Month(y, month).file_name());
+                if file.exists() {
+                    std::fs::remove_file(&file).context("Failed to delete cache file")?;
+                }
+            }
         }
         None => {
             // Delete entire cache directory
@@ -413,6 +465,53 @@ pub fn download_cotahist_year(
     Ok(zip_path)
 }
 
+/// Download a monthly COTAHIST file unless it is already cached
+///
+/// Closed months never change, so a cached file is used as is.
+pub fn download_cotahist_month(year: i32, month: u32, force_redownload: bool) -> Result<PathBuf> {
+    let cache_dir = get_cotahist_cache_dir()?;
+    let file_name = CotahistPeriod::Month(year, month).file_name();
+    let zip_path = cache_dir.join(&file_name);
+    if zip_path.exists() && !force_redownload {
+        return Ok(zip_path);
+    }
+
+    let offline = std::env::var("INTEREST_OFFLINE")
+        .map(|value| value == "1")
+        .unwrap_or(false);
+    if offline {
+        return Err(anyhow!(
+            "COTAHIST cache missing for {:02}/{} while offline mode is enabled",
+            month,
+            year
+        ));
+    }
+    std::fs::create_dir_all(&cache_dir).context("Failed to create cache directory")?;
+
+    tracing::info!("Downloading {}", file_name);
+    let client = Client::builder()
+        .timeout(std::time::Duration::from_secs(300))
+        .build()
+        .context("Failed to create HTTP client")?;
+    let response = client
+        .get(format!("{}/{}", B3_COTAHIST_BASE_URL, file_name))
+        .send()
+        .context("Failed to download COTAHIST file")?;
+    if !response.status().is_success() {
+        return Err(anyhow!(
+            "Download failed with status: {}. {:02}/{} may not be available.",
+            response.status(),
+            month,
+            year
+        ));
+    }
+    let bytes = response
+        .bytes()
+        .context("Failed to read download response")?;
+    std::fs::write(&zip_path, bytes).context("Failed to write COTAHIST to cache")?;
+    Ok(zip_path)
+}
+
 /// Parse a COTAHIST record line according to B3 specification
 ///
 /// COTAHIST format is fixed-width, 245 bytes per line:
@@ -505,10 +604,9 @@ pub fn parse_cotahist_file<P: AsRef<Path>>(
         .and_then(|s| s.to_str())
         .ok_or_else(|| anyhow!("Invalid file path"))?;
 
-    let year: i32 = filename
-        .chars()
-        .filter(|c| c.is_ascii_digit())
-        .collect::<String>()
+    // COTAHIST_A2024.ZIP or COTAHIST_M032024.ZIP: the year is the last 4 digits
+    let digits: String = filename.chars().filter(|c| c.is_ascii_digit()).collect();
+    let year: i32 = digits[digits.len().saturating_sub(4)..]
         .parse()
         .context("Could not extract year from filename")?;
 
@@ -599,7 +697,7 @@ pub fn import_records_to_db(
     progress_callback: Option<&dyn Fn(&DownloadProgress)>,
     year: i32,
 ) -> Result<usize> {
-    use std::collections::{HashMap, HashSet};
+    use std::collections::HashMap;
 
     // Pre-fetch all existing assets to avoid repeated lookups
     let mut asset_map: HashMap<String, i64> = HashMap::new();
@@ -619,6 +717,43 @@ pub fn import_records_to_db(
         return Ok(0);
     }
 
+    insert_records_for_assets(conn, records, &asset_map, progress_callback, year)
+}
+
+/// Import COTAHIST records only for assets that ever had a transaction
+pub fn import_held_asset_records(
+    conn: &mut Connection,
+    records: &[CotahistRecord],
+) -> Result<usize> {
+    let mut asset_map: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT a.id, a.ticker FROM assets a
+             JOIN transactions t ON t.asset_id = a.id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(1)?, row.get::<_, i64>(0)?))
+        })?;
+        for row in rows {
+            let (ticker, id) = row?;
+            asset_map.insert(ticker, id);
+        }
+    }
+    if asset_map.is_empty() {
+        return Ok(0);
+    }
+    insert_records_for_assets(conn, records, &asset_map, None, 0)
+}
+
+fn insert_records_for_assets(
+    conn: &mut Connection,
+    records: &[CotahistRecord],
+    asset_map: &std::collections::HashMap<String, i64>,
+    progress_callback: Option<&dyn Fn(&DownloadProgress)>,
+    year: i32,
+) -> Result<usize> {
+    use std::collections::HashSet;
+
     tracing::info!(
         "Filtering COTAHIST records for {} portfolio assets",
         asset_map.len()
@@ -674,41 +809,37 @@ pub fn import_records_to_db(
     tracing::debug!("Found {} existing price records", existing_prices.len());
 
     // Batch insert with single transaction (much faster)
-    let tx = conn.transaction()?;
-    let mut inserted = 0;
-
-    {
-        let mut stmt = tx.prepare(
-            "INSERT OR IGNORE INTO price_history (asset_id, price_date, close_price, open_price, high_price, low_price, volume, source)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'B3_COTAHIST')"
-        )?;
-
-        for record in relevant_records.iter() {
-            let asset_id = match asset_map.get(&record.ticker) {
-                Some(id) => *id,
-                None => continue, // Should never happen due to filter above
-            };
-
-            // Skip if exists
-            if existing_prices.contains(&(asset_id, record.date)) {
-                continue;
-            }
-
-            stmt.execute(rusqlite::params![
-                asset_id,
-                record.date.format("%Y-%m-%d").to_string(),
-                record.close_price.to_string(),
-                record.open_price.to_string(),
-                record.high_price.to_string(),
-                record.low_price.to_string(),
-                record.volume,
-            ])?;
-
-            inserted += 1;
+    let mut new_prices = Vec::new();
+    for record in relevant_records.iter() {
+        let asset_id = match asset_map.get(&record.ticker) {
+            Some(id) => *id,
+            None => continue, // Should never happen due to filter above
+        };
+
+        // Skip if exists (also drops duplicate rows within the file)
+        if !existing_prices.insert((asset_id, record.date)) {
+            continue;
         }
+
+        new_prices.push(crate::db::PriceHistory {
+            id: None,
+            asset_id,
+            price_date: record.date,
+            close_price: record.close_price,
+            open_price: Some(record.open_price),
+            high_price: Some(record.high_price),
+            low_price: Some(record.low_price),
+            volume: Some(record.volume),
+            source: "B3_COTAHIST".to_string(),
+            created_at: chrono::Utc::now(),
+            adjusted_close: None,
+        });
     }
 
-    tx.commit()?;
+    crate::db::bulk::insert_price_history(conn, &new_prices, |p| {
+        tracing::debug!("Inserted {}/{} COTAHIST prices", p.done, p.total);
+    })?;
+    let inserted = new_prices.len();
     tracing::info!("Imported {} new price records", inserted);
 
     // Report import completion with fun emoji
@@ -785,6 +916,36 @@ mod tests {
         result
     }
 
+    #[test]
+    fn test_backfill_periods() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 10).unwrap();
+        assert_eq!(
+            backfill_periods(2023, None, today),
+            vec![
+                CotahistPeriod::Year(2023),
+                CotahistPeriod::Year(2024),
+                CotahistPeriod::Year(2025)
+            ]
+        );
+
+        let periods = backfill_periods(2024, Some(11), today);
+        assert_eq!(
+            periods,
+            vec![
+                CotahistPeriod::Month(2024, 11),
+                CotahistPeriod::Month(2024, 12),
+                CotahistPeriod::Year(2025)
+            ]
+        );
+        assert_eq!(periods[0].file_name(), "COTAHIST_M112024.ZIP");
+
+        // The current year only has an annual file with the running month
+        assert_eq!(
+            backfill_periods(2025, Some(3), today),
+            vec![CotahistPeriod::Year(2025)]
+        );
+    }
+
     #[test]
     fn test_parse_cotahist_line_valid() {
         // Sample line from B3's official demo file (DemoCotacoesHistoricas12022003.txt)
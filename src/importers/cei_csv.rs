@@ -13,11 +13,11 @@ pub fn parse_cei_csv<P: AsRef<Path>>(file_path: P) -> Result<Vec<RawTransaction>
     let path = file_path.as_ref();
     info!("Parsing CEI CSV file: {:?}", path);
 
+    let text = super::inspect::read_text(path).context("Failed to open CSV file")?;
     let mut reader = ReaderBuilder::new()
         .delimiter(b';') // Brazilian CSV often uses semicolon
         .flexible(true) // Allow variable number of columns
-        .from_path(path)
-        .context("Failed to open CSV file")?;
+        .from_reader(text.as_bytes());
 
     let headers = reader
         .headers()
@@ -221,7 +221,7 @@ fn parse_csv_row(
     }))
 }
 
-fn parse_csv_date(date_str: &str) -> Result<NaiveDate> {
+pub(crate) fn parse_csv_date(date_str: &str) -> Result<NaiveDate> {
     // Try common Brazilian formats
     if let Ok(date) = NaiveDate::parse_from_str(date_str, "%d/%m/%Y") {
         return Ok(date);
@@ -239,7 +239,7 @@ fn parse_csv_date(date_str: &str) -> Result<NaiveDate> {
     Err(anyhow!("Could not parse date: {}", date_str))
 }
 
-fn parse_csv_decimal(text: &str) -> Result<Decimal> {
+pub(crate) fn parse_csv_decimal(text: &str) -> Result<Decimal> {
     let cleaned = text
         .replace("R$", "")
         .replace(" ", "")
@@ -0,0 +1,485 @@
+//! Mercado Bitcoin and Binance trade history importer.
+//!
+//! Trades of crypto-assets read from the exchanges' CSV exports:
+//!
+//! - Mercado Bitcoin (`Data`, `Tipo`, `Moeda` or `Par`, `Quantidade`,
+//!   `Preço`, `Total`, `Taxa`), `;`-separated with Brazilian numbers and
+//!   local times;
+//! - Binance (`Date(UTC)`, `Pair`, `Side`, `Price`, `Executed`, `Amount`,
+//!   `Fee`, or the older `Market`, `Type`, `Amount`, `Total`, `Fee Coin`),
+//!   with quantities suffixed by their coin ("0.0015BTC") and UTC times.
+//!
+//! Trades against reais are taken as they are; trades against dollars and
+//! dollar stablecoins (USDT, USDC, ...) are converted to reais at the USD
+//! PTAX on or before the trade date when the file is imported. Trades of one
+//! crypto-asset for another are skipped with a warning.
+//!
+//! A fee charged in the coin bought reduces the quantity received; other
+//! fees are valued in the pair's quote. The assets are created as `Crypto`,
+//! so their sales fall in the `Crypto` tax category.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::path::Path;
+use tracing::{debug, info, warn};
+
+use super::avenue_csv::parse_amount;
+use super::cei_excel::RawTransaction;
+use crate::db::models::TransactionType;
+use crate::pricing::fx;
+
+/// Source recorded on trades read from crypto exchange exports
+pub const SOURCE: &str = "CRYPTO_EXCHANGE";
+
+/// Quotes traded against: reais, or dollars and stablecoins valued as dollars
+const QUOTES: &[&str] = &["BRL", "USDT", "USDC", "FDUSD", "BUSD", "USD"];
+
+/// Brasília is three hours behind the UTC times of Binance exports
+const BRT_OFFSET_HOURS: i64 = 3;
+
+/// A trade of one coin against reais or dollars
+#[derive(Debug, Clone, Serialize)]
+pub struct CryptoTrade {
+    pub coin: String,
+    /// BRL, USD or a dollar stablecoin
+    pub quote: String,
+    pub transaction_type: TransactionType,
+    pub trade_date: NaiveDate,
+    pub quantity: Decimal,
+    /// Price per coin in the quote
+    pub price: Decimal,
+    /// Amount paid or received in the quote, before fees
+    pub total: Decimal,
+    /// Fees in the quote
+    pub fees: Decimal,
+}
+
+/// Trades of one export
+#[derive(Debug, Clone, Serialize)]
+pub struct CryptoStatement {
+    pub exchange: String,
+    pub trades: Vec<CryptoTrade>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Exchange {
+    MercadoBitcoin,
+    Binance,
+}
+
+impl Exchange {
+    fn name(self) -> &'static str {
+        match self {
+            Exchange::MercadoBitcoin => "Mercado Bitcoin",
+            Exchange::Binance => "Binance",
+        }
+    }
+}
+
+fn exchange(header: &str) -> Option<Exchange> {
+    let columns: Vec<String> = header
+        .split([';', ','])
+        .map(|c| c.trim().trim_matches('"').to_lowercase())
+        .collect();
+    let has = |names: &[&str]| columns.iter().any(|c| names.contains(&c.as_str()));
+    if has(&["date(utc)"]) && has(&["pair", "market"]) {
+        Some(Exchange::Binance)
+    } else if has(&["moeda", "criptomoeda", "criptoativo", "par"]) && has(&["tipo", "operação"]) {
+        Some(Exchange::MercadoBitcoin)
+    } else {
+        None
+    }
+}
+
+/// Whether a CSV header line looks like a Mercado Bitcoin or Binance export
+pub fn is_crypto_exchange_header(line: &str) -> bool {
+    exchange(line).is_some()
+}
+
+impl CryptoStatement {
+    /// Coins traded, sorted
+    pub fn coins(&self) -> Vec<String> {
+        let mut coins: Vec<String> = self.trades.iter().map(|t| t.coin.clone()).collect();
+        coins.sort();
+        coins.dedup();
+        coins
+    }
+
+    /// First and last dates of trades against dollars, whose PTAX is needed
+    pub fn rate_dates(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let dates = self
+            .trades
+            .iter()
+            .filter(|t| t.quote != "BRL")
+            .map(|t| t.trade_date);
+        let (mut first, mut last) = (None::<NaiveDate>, None::<NaiveDate>);
+        for date in dates {
+            first = Some(first.map_or(date, |d| d.min(date)));
+            last = Some(last.map_or(date, |d| d.max(date)));
+        }
+        Some((first?, last?))
+    }
+
+    /// Trades in reais, those against dollars at the PTAX on or before the
+    /// trade date
+    pub fn to_brl(&self, conn: &Connection) -> Result<Vec<RawTransaction>> {
+        let mut trades = Vec::new();
+        for trade in &self.trades {
+            let (rate, market) = if trade.quote == "BRL" {
+                (Decimal::ONE, None)
+            } else {
+                let (rate_date, rate) =
+                    crate::db::get_fx_rate_on_or_before(conn, fx::USD_BRL, trade.trade_date)?
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "No USD/BRL PTAX on or before {}; run 'interest prices update-fx USD --from {}'",
+                                trade.trade_date,
+                                trade.trade_date
+                            )
+                        })?;
+                let market = format!(
+                    "{} {} a PTAX {} de {}",
+                    trade.quote,
+                    trade.price.normalize(),
+                    rate,
+                    rate_date.format("%d/%m/%Y")
+                );
+                (rate, Some(market))
+            };
+            trades.push(RawTransaction {
+                ticker: trade.coin.clone(),
+                transaction_type: trade.transaction_type.as_str().to_string(),
+                trade_date: trade.trade_date,
+                quantity: trade.quantity,
+                price: (trade.price * rate).round_dp(6),
+                fees: (trade.fees * rate).round_dp(2),
+                total: (trade.total * rate).round_dp(2),
+                market,
+            });
+        }
+        Ok(trades)
+    }
+}
+
+/// Coin and quote of a pair ("BTC-BRL", "BTC/BRL", "BTCUSDT"), or of a lone
+/// coin traded against `default_quote`; None when the quote is not reais or
+/// dollars
+fn split_pair(raw: &str, default_quote: Option<&str>) -> Option<(String, String)> {
+    let pair = raw.trim().to_uppercase();
+    let known = |quote: &str| QUOTES.contains(&quote);
+    if let Some((coin, quote)) = pair.split_once(['-', '/', '_']) {
+        return known(quote).then(|| (coin.to_string(), quote.to_string()));
+    }
+    if let Some(quote) = QUOTES
+        .iter()
+        .find(|quote| pair.len() > quote.len() && pair.ends_with(*quote))
+    {
+        return Some((
+            pair[..pair.len() - quote.len()].to_string(),
+            quote.to_string(),
+        ));
+    }
+    default_quote.map(|quote| (pair, quote.to_string()))
+}
+
+/// Amount and the coin it is in ("0.0015BTC", "R$ 0,30"), if named
+fn split_unit(raw: &str, decimal_comma: bool) -> Result<(Decimal, Option<String>)> {
+    let raw = raw.trim();
+    if let Some(reais) = raw.strip_prefix("R$") {
+        return Ok((parse_amount(reais, decimal_comma)?, Some("BRL".to_string())));
+    }
+    let end = raw
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(raw.len());
+    let unit = raw[end..].trim().to_uppercase();
+    Ok((
+        parse_amount(&raw[..end], decimal_comma)?,
+        (!unit.is_empty()).then_some(unit),
+    ))
+}
+
+fn parse_date(raw: &str, exchange: Exchange) -> Result<NaiveDate> {
+    let raw = raw.trim();
+    let with_time = ["%Y-%m-%d %H:%M:%S", "%d/%m/%Y %H:%M:%S", "%d/%m/%Y %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(raw, format).ok());
+    if let Some(time) = with_time {
+        return Ok(match exchange {
+            Exchange::Binance => (time - Duration::hours(BRT_OFFSET_HOURS)).date(),
+            Exchange::MercadoBitcoin => time.date(),
+        });
+    }
+    let date = raw.get(..10).unwrap_or(raw);
+    ["%Y-%m-%d", "%d/%m/%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+        .ok_or_else(|| anyhow!("Invalid date '{}'", raw))
+}
+
+fn find(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    headers.iter().position(|h| {
+        let h = h.trim().to_lowercase();
+        names.iter().any(|name| h == *name)
+    })
+}
+
+struct Columns {
+    date: usize,
+    pair: usize,
+    side: usize,
+    price: Option<usize>,
+    quantity: usize,
+    total: Option<usize>,
+    fee: Option<usize>,
+    fee_coin: Option<usize>,
+}
+
+fn parse_row(
+    record: &csv::StringRecord,
+    columns: &Columns,
+    exchange: Exchange,
+) -> Result<Option<CryptoTrade>> {
+    let decimal_comma = exchange == Exchange::MercadoBitcoin;
+    let field = |idx: usize| record.get(idx).unwrap_or("").trim();
+    let optional = |idx: Option<usize>| -> Result<(Decimal, Option<String>)> {
+        match idx.map(field).filter(|v| !v.is_empty()) {
+            Some(raw) => split_unit(raw, decimal_comma),
+            None => Ok((Decimal::ZERO, None)),
+        }
+    };
+
+    let side = field(columns.side).to_lowercase();
+    let transaction_type = match side.as_str() {
+        "buy" | "compra" | "c" => TransactionType::Buy,
+        "sell" | "venda" | "v" => TransactionType::Sell,
+        "" => return Ok(None),
+        _ => {
+            debug!("Ignoring '{}' row", side);
+            return Ok(None);
+        }
+    };
+    let raw_pair = field(columns.pair);
+    let default_quote = (exchange == Exchange::MercadoBitcoin).then_some("BRL");
+    let Some((coin, quote)) = split_pair(raw_pair, default_quote) else {
+        warn!("Skipping {} trade: not against reais or dollars", raw_pair);
+        return Ok(None);
+    };
+    let trade_date = parse_date(field(columns.date), exchange)?;
+    let mut quantity = optional(Some(columns.quantity))?.0.abs();
+    let mut total = optional(columns.total)?.0.abs();
+    let mut price = optional(columns.price)?.0.abs();
+    if quantity.is_zero() {
+        return Err(anyhow!("No quantity"));
+    }
+    if price.is_zero() {
+        price = total / quantity;
+    }
+    if total.is_zero() {
+        total = quantity * price;
+    }
+
+    let (fee, fee_unit) = optional(columns.fee)?;
+    let fee_unit = columns
+        .fee_coin
+        .map(field)
+        .filter(|c| !c.is_empty())
+        .map(|c| c.to_uppercase())
+        .or(fee_unit);
+    let fee = fee.abs();
+    let fees = match fee_unit.as_deref() {
+        _ if fee.is_zero() => Decimal::ZERO,
+        Some(unit) if unit == coin => {
+            if transaction_type == TransactionType::Buy {
+                // Received net of the fee, for the same amount paid
+                quantity -= fee;
+                Decimal::ZERO
+            } else {
+                fee * price
+            }
+        }
+        None => fee,
+        Some(unit) if unit == quote => fee,
+        Some(unit) => {
+            debug!("Ignoring fee of {} {} on a {} trade", fee, unit, coin);
+            Decimal::ZERO
+        }
+    };
+
+    Ok(Some(CryptoTrade {
+        coin,
+        quote,
+        transaction_type,
+        trade_date,
+        quantity,
+        price,
+        total,
+        fees,
+    }))
+}
+
+/// Parse a Mercado Bitcoin or Binance trade history CSV
+pub fn parse_crypto_csv<P: AsRef<Path>>(path: P) -> Result<CryptoStatement> {
+    let path = path.as_ref();
+    info!("Parsing crypto exchange export: {:?}", path);
+    let content = super::inspect::read_text(path).context("Failed to read export")?;
+    parse_crypto_content(&content)
+}
+
+fn parse_crypto_content(content: &str) -> Result<CryptoStatement> {
+    let content = content.trim_start_matches('\u{feff}');
+    let header = content.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let exchange = exchange(header)
+        .ok_or_else(|| anyhow!("Not a Mercado Bitcoin or Binance export: {}", header))?;
+    let delimiter = if header.matches(';').count() > header.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers = reader.headers()?.clone();
+    let required = |names: &[&str]| {
+        find(&headers, names).ok_or_else(|| {
+            anyhow!(
+                "Export is missing a '{}' column (found: {:?})",
+                names[0],
+                headers
+            )
+        })
+    };
+
+    // Binance names the quantity "Executed" and the quote total "Amount";
+    // older exports and Mercado Bitcoin call the quantity "Amount"/"Quantidade"
+    let executed = find(&headers, &["executed"]);
+    let columns = Columns {
+        date: required(&["date(utc)", "data", "data/hora", "data e hora", "date"])?,
+        pair: required(&[
+            "pair",
+            "market",
+            "par",
+            "moeda",
+            "criptomoeda",
+            "criptoativo",
+        ])?,
+        side: required(&["side", "type", "tipo", "operação"])?,
+        price: find(
+            &headers,
+            &[
+                "price",
+                "preço",
+                "preco",
+                "preço unitário",
+                "preco unitario",
+                "cotação",
+            ],
+        ),
+        quantity: match executed {
+            Some(idx) => idx,
+            None => required(&["amount", "quantidade", "qtd"])?,
+        },
+        total: if executed.is_some() {
+            find(&headers, &["amount"])
+        } else {
+            find(&headers, &["total", "valor", "valor total"])
+        },
+        fee: find(&headers, &["fee", "taxa", "tarifa", "comissão"]),
+        fee_coin: find(&headers, &["fee coin", "moeda da taxa"]),
+    };
+
+    let mut trades = Vec::new();
+    for (row, record) in reader.records().enumerate() {
+        match parse_row(&record?, &columns, exchange) {
+            Ok(Some(trade)) => trades.push(trade),
+            Ok(None) => {}
+            Err(e) => warn!("Skipping row {}: {}", row + 2, e),
+        }
+    }
+    trades.sort_by_key(|t| t.trade_date);
+
+    info!(
+        "Parsed {} trades from the {} export",
+        trades.len(),
+        exchange.name()
+    );
+    Ok(CryptoStatement {
+        exchange: exchange.name().to_string(),
+        trades,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_mercado_bitcoin_and_binance_exports() {
+        let mercado = "Data;Tipo;Moeda;Quantidade;Preço;Total;Taxa\n\
+05/03/2024 10:15;Compra;BTC;0,01000000;310.000,00;3.100,00;0,00003000 BTC\n\
+20/03/2024 16:40;Venda;BTC;0,00500000;350.000,00;1.750,00;R$ 12,25\n\
+21/03/2024 09:00;Depósito;BRL;1.000,00;;;\n";
+        assert!(is_crypto_exchange_header(mercado.lines().next().unwrap()));
+        let statement = parse_crypto_content(mercado).unwrap();
+        assert_eq!(statement.exchange, "Mercado Bitcoin");
+        assert_eq!(statement.trades.len(), 2);
+        let buy = &statement.trades[0];
+        assert_eq!((buy.coin.as_str(), buy.quote.as_str()), ("BTC", "BRL"));
+        // The fee in bitcoin came out of the coins received
+        assert_eq!(
+            (buy.quantity, buy.price, buy.fees),
+            (dec!(0.00997), dec!(310000), Decimal::ZERO)
+        );
+        let sell = &statement.trades[1];
+        assert_eq!(sell.transaction_type, TransactionType::Sell);
+        assert_eq!(sell.fees, dec!(12.25));
+
+        let binance = "Date(UTC),Pair,Side,Price,Executed,Amount,Fee\n\
+2024-04-01 01:30:00,ETHUSDT,BUY,3500.00,0.5000ETH,1750.00USDT,1.75USDT\n\
+2024-04-02 12:00:00,ETHBTC,SELL,0.05,0.1000ETH,0.005BTC,0.000005BTC\n\
+2024-04-03 12:00:00,SOLBRL,BUY,900.00,2.00SOL,1800.00BRL,0.002SOL\n";
+        assert!(is_crypto_exchange_header(binance.lines().next().unwrap()));
+        assert!(!is_crypto_exchange_header(
+            "Data do Negócio;Tipo de Movimentação;Código;Quantidade;Preço"
+        ));
+        let statement = parse_crypto_content(binance).unwrap();
+        assert_eq!(statement.exchange, "Binance");
+        // The ETH/BTC trade is skipped
+        assert_eq!(statement.coins(), vec!["ETH", "SOL"]);
+        let eth = &statement.trades[0];
+        // 01:30 UTC is still March 31st in Brasília
+        assert_eq!(
+            eth.trade_date,
+            NaiveDate::from_ymd_opt(2024, 3, 31).unwrap()
+        );
+        assert_eq!((eth.quantity, eth.fees), (dec!(0.5), dec!(1.75)));
+
+        // Dollars converted at the PTAX, reais as they are
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        assert!(statement.to_brl(&conn).is_err());
+        crate::db::insert_fx_rate(
+            &conn,
+            fx::USD_BRL,
+            NaiveDate::from_ymd_opt(2024, 3, 28).unwrap(),
+            dec!(5.00),
+            "BCB",
+        )
+        .unwrap();
+        let trades = statement.to_brl(&conn).unwrap();
+        assert_eq!(
+            (trades[0].price, trades[0].total, trades[0].fees),
+            (dec!(17500), dec!(8750), dec!(8.75))
+        );
+        assert_eq!(
+            (trades[1].quantity, trades[1].total),
+            (dec!(1.998), dec!(1800))
+        );
+        assert_eq!(trades[1].market, None);
+    }
+}
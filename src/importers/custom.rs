@@ -0,0 +1,483 @@
+//! Generic CSV/Excel trade importer driven by a user-written column mapping
+//!
+//! For brokers and spreadsheets that have no dedicated parser, the user
+//! describes the layout in a TOML file and runs
+//! `interest import trades.csv --format custom --mapping mapping.toml`:
+//!
+//! ```toml
+//! source = "MYBROKER"        # transactions are tagged with this source
+//! delimiter = ";"            # CSV only; detected from the header when omitted
+//! sheet = "Trades"           # Excel only; defaults to the first sheet
+//! skip_rows = 0              # lines above the header row
+//! date_format = "%d/%m/%Y"   # tried before the usual Brazilian/ISO formats
+//! decimal_separator = ","    # for numbers stored as text
+//!
+//! [columns]                  # header names, matched case-insensitively
+//! date = "Data"
+//! ticker = "Ativo"
+//! type = "Operação"          # optional: a negative quantity means a sale
+//! quantity = "Quantidade"
+//! price = "Preço"            # optional when total is mapped
+//! fees = "Taxas"             # optional
+//! total = "Valor"            # optional
+//!
+//! [types]                    # extra labels besides C/V, COMPRA/VENDA, BUY/SELL
+//! buy = ["Aplicação"]
+//! sell = ["Resgate"]
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use calamine::{open_workbook_auto, Data, Reader};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{info, warn};
+
+use super::cei_excel::RawTransaction;
+use crate::db::models::TransactionType;
+
+/// Sources written by the built-in importers; a custom mapping may not reuse
+/// them, or `--force-reimport` would delete the other importer's data
+const RESERVED_SOURCES: &[&str] = &[
+    "CEI",
+    "MOVIMENTACAO",
+    "OFERTAS_PUBLICAS",
+    "NOTA_CORRETAGEM",
+    "TESOURO_EXTRATO",
+    "TESOURO_CSV",
+    "PROVENTOS",
+    "IRPF_PDF",
+    "OFX",
+    "MANUAL",
+];
+
+/// Column mapping loaded from the user's TOML file
+#[derive(Debug, Clone, Deserialize)]
+pub struct Mapping {
+    /// Source recorded on every imported transaction
+    #[serde(default = "default_source")]
+    pub source: String,
+
+    /// CSV field delimiter
+    pub delimiter: Option<char>,
+
+    /// Excel worksheet name
+    pub sheet: Option<String>,
+
+    /// Lines to skip before the header row
+    #[serde(default)]
+    pub skip_rows: usize,
+
+    /// chrono format for text dates (e.g. `%m/%d/%Y`)
+    pub date_format: Option<String>,
+
+    /// Decimal separator of numbers stored as text
+    #[serde(default = "default_decimal_separator")]
+    pub decimal_separator: char,
+
+    pub columns: ColumnNames,
+
+    #[serde(default)]
+    pub types: TypeLabels,
+}
+
+/// Header names of the mapped columns
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnNames {
+    pub date: String,
+    pub ticker: String,
+    #[serde(rename = "type")]
+    pub transaction_type: Option<String>,
+    pub quantity: String,
+    pub price: Option<String>,
+    pub fees: Option<String>,
+    pub total: Option<String>,
+}
+
+/// Broker-specific labels of the type column
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TypeLabels {
+    #[serde(default)]
+    pub buy: Vec<String>,
+    #[serde(default)]
+    pub sell: Vec<String>,
+}
+
+fn default_source() -> String {
+    "CUSTOM".to_string()
+}
+
+fn default_decimal_separator() -> char {
+    ','
+}
+
+impl Mapping {
+    /// Read and validate a mapping file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = super::inspect::read_text(path)
+            .with_context(|| format!("Failed to read mapping file {}", path.display()))?;
+        let mut mapping: Mapping = toml::from_str(&text)
+            .with_context(|| format!("Invalid mapping file {}", path.display()))?;
+        mapping.source = mapping.source.trim().to_uppercase();
+        if mapping.source.is_empty() {
+            return Err(anyhow!("Mapping source cannot be empty"));
+        }
+        if RESERVED_SOURCES.contains(&mapping.source.as_str()) {
+            return Err(anyhow!(
+                "Mapping source {} is used by a built-in importer; pick another name",
+                mapping.source
+            ));
+        }
+        if mapping.columns.price.is_none() && mapping.columns.total.is_none() {
+            return Err(anyhow!(
+                "Mapping needs a price or a total column to value the trades"
+            ));
+        }
+        if !matches!(mapping.decimal_separator, ',' | '.') {
+            return Err(anyhow!("decimal_separator must be ',' or '.'"));
+        }
+        Ok(mapping)
+    }
+
+    fn transaction_type(&self, label: &str) -> Option<TransactionType> {
+        let matches =
+            |labels: &[String]| labels.iter().any(|l| l.trim().eq_ignore_ascii_case(label));
+        if matches(&self.types.buy) {
+            Some(TransactionType::Buy)
+        } else if matches(&self.types.sell) {
+            Some(TransactionType::Sell)
+        } else {
+            label.to_uppercase().parse().ok()
+        }
+    }
+}
+
+/// Resolved column positions
+#[derive(Debug)]
+struct Columns {
+    date: usize,
+    ticker: usize,
+    transaction_type: Option<usize>,
+    quantity: usize,
+    price: Option<usize>,
+    fees: Option<usize>,
+    total: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &[Data], names: &ColumnNames) -> Result<Self> {
+        let headers: Vec<String> = header
+            .iter()
+            .map(|c| c.to_string().trim().to_string())
+            .collect();
+        let find = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.to_lowercase() == name.trim().to_lowercase())
+        };
+        let required = |name: &str| {
+            find(name).ok_or_else(|| {
+                anyhow!(
+                    "Column '{}' not found; the header has: {}",
+                    name,
+                    headers.join(", ")
+                )
+            })
+        };
+        let optional = |name: &Option<String>| name.as_deref().map(required).transpose();
+
+        Ok(Columns {
+            date: required(&names.date)?,
+            ticker: required(&names.ticker)?,
+            transaction_type: optional(&names.transaction_type)?,
+            quantity: required(&names.quantity)?,
+            price: optional(&names.price)?,
+            fees: optional(&names.fees)?,
+            total: optional(&names.total)?,
+        })
+    }
+}
+
+/// Parse a CSV or Excel file with the given mapping
+pub fn parse_custom<P: AsRef<Path>>(
+    file_path: P,
+    mapping: &Mapping,
+) -> Result<Vec<RawTransaction>> {
+    let path = file_path.as_ref();
+    info!(
+        "Parsing {:?} with custom mapping ({})",
+        path, mapping.source
+    );
+
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let rows = match extension.as_str() {
+        "xlsx" | "xls" | "xlsm" | "ods" => read_sheet(path, mapping)?,
+        _ => read_csv(path, mapping)?,
+    };
+    parse_rows(&rows, mapping)
+}
+
+fn read_sheet(path: &Path, mapping: &Mapping) -> Result<Vec<Vec<Data>>> {
+    let mut workbook = open_workbook_auto(path).context("Failed to open spreadsheet")?;
+    let sheet = match &mapping.sheet {
+        Some(name) => name.clone(),
+        None => workbook
+            .sheet_names()
+            .first()
+            .cloned()
+            .ok_or_else(|| anyhow!("Spreadsheet has no sheets"))?,
+    };
+    let range = workbook
+        .worksheet_range(&sheet)
+        .with_context(|| format!("Failed to read sheet {}", sheet))?;
+    Ok(range.rows().map(|r| r.to_vec()).collect())
+}
+
+fn read_csv(path: &Path, mapping: &Mapping) -> Result<Vec<Vec<Data>>> {
+    let text = super::inspect::read_text(path).context("Failed to read CSV file")?;
+    let delimiter = match mapping.delimiter {
+        Some(d) => d,
+        // Brazilian exports use ';' because ',' is the decimal separator
+        None => {
+            let header = text.lines().nth(mapping.skip_rows).unwrap_or("");
+            if header.matches(';').count() >= header.matches(',').count() {
+                ';'
+            } else {
+                ','
+            }
+        }
+    };
+    let delimiter = u8::try_from(delimiter).map_err(|_| anyhow!("Delimiter must be ASCII"))?;
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    reader
+        .records()
+        .map(|record| {
+            let record = record.context("Failed to read CSV record")?;
+            Ok(record
+                .iter()
+                .map(|field| Data::String(field.to_string()))
+                .collect())
+        })
+        .collect()
+}
+
+fn parse_rows(rows: &[Vec<Data>], mapping: &Mapping) -> Result<Vec<RawTransaction>> {
+    let header = rows
+        .get(mapping.skip_rows)
+        .ok_or_else(|| anyhow!("Header row {} not found", mapping.skip_rows + 1))?;
+    let columns = Columns::from_header(header, &mapping.columns)?;
+
+    let mut transactions = Vec::new();
+    for (idx, row) in rows.iter().enumerate().skip(mapping.skip_rows + 1) {
+        match parse_row(row, &columns, mapping) {
+            Ok(Some(tx)) => transactions.push(tx),
+            Ok(None) => continue,
+            Err(e) => warn!("Skipping row {}: {}", idx + 1, e),
+        }
+    }
+
+    info!(
+        "Parsed {} transactions with custom mapping",
+        transactions.len()
+    );
+    Ok(transactions)
+}
+
+fn parse_row(row: &[Data], columns: &Columns, mapping: &Mapping) -> Result<Option<RawTransaction>> {
+    let cell = |idx: usize| row.get(idx).unwrap_or(&Data::Empty);
+    let optional = |idx: Option<usize>| -> Result<Option<Decimal>> {
+        idx.map(|i| parse_decimal(cell(i), mapping.decimal_separator))
+            .transpose()
+            .map(Option::flatten)
+    };
+
+    // Blank and totals rows have no ticker
+    let ticker = cell(columns.ticker).to_string().trim().to_uppercase();
+    if ticker.is_empty() {
+        return Ok(None);
+    }
+
+    let trade_date = parse_date(cell(columns.date), mapping.date_format.as_deref())?;
+    let signed_quantity = parse_decimal(cell(columns.quantity), mapping.decimal_separator)?
+        .ok_or_else(|| anyhow!("Missing quantity"))?;
+    let transaction_type = match columns.transaction_type {
+        Some(idx) => {
+            let label = cell(idx).to_string();
+            mapping
+                .transaction_type(label.trim())
+                .ok_or_else(|| anyhow!("Unknown transaction type: {}", label.trim()))?
+        }
+        None if signed_quantity < Decimal::ZERO => TransactionType::Sell,
+        None => TransactionType::Buy,
+    };
+    let quantity = signed_quantity.abs();
+    if quantity.is_zero() {
+        return Err(anyhow!("Zero quantity"));
+    }
+
+    let total = optional(columns.total)?.map(|t| t.abs());
+    let price = match (optional(columns.price)?, total) {
+        (Some(price), _) => price.abs(),
+        (None, Some(total)) => total / quantity,
+        (None, None) => return Err(anyhow!("Missing price")),
+    };
+    let fees = optional(columns.fees)?.map(|f| f.abs()).unwrap_or_default();
+
+    Ok(Some(RawTransaction {
+        ticker,
+        transaction_type: transaction_type.as_str().to_string(),
+        trade_date,
+        quantity,
+        price,
+        fees,
+        total: total.unwrap_or(quantity * price),
+        market: None,
+    }))
+}
+
+fn parse_date(cell: &Data, format: Option<&str>) -> Result<NaiveDate> {
+    if let Data::DateTime(dt) = cell {
+        let days_since_epoch = dt.as_f64().floor() as i64;
+        let excel_epoch =
+            NaiveDate::from_ymd_opt(1899, 12, 30).ok_or_else(|| anyhow!("Invalid Excel epoch"))?;
+        return excel_epoch
+            .checked_add_signed(chrono::Duration::days(days_since_epoch))
+            .ok_or_else(|| anyhow!("Date overflow"));
+    }
+    let text = cell.to_string();
+    let text = text.trim();
+    // Timestamps such as "2025-03-15 10:32:00" keep only the date
+    let text = text.split([' ', 'T']).next().unwrap_or(text);
+    if let Some(format) = format {
+        if let Ok(date) = NaiveDate::parse_from_str(text, format) {
+            return Ok(date);
+        }
+    }
+    super::cei_csv::parse_csv_date(text)
+}
+
+/// Parse a number cell; None when it is empty or a dash
+fn parse_decimal(cell: &Data, decimal_separator: char) -> Result<Option<Decimal>> {
+    match cell {
+        Data::Empty => Ok(None),
+        Data::Int(i) => Ok(Some(Decimal::from(*i))),
+        Data::Float(f) => Decimal::from_f64_retain(*f)
+            .map(|d| Some(d.round_dp(8).normalize()))
+            .ok_or_else(|| anyhow!("Invalid decimal")),
+        Data::String(s) => {
+            let thousands = if decimal_separator == ',' { '.' } else { ',' };
+            let cleaned: String = s
+                .replace("R$", "")
+                .replace("US$", "")
+                .chars()
+                .filter(|c| !c.is_whitespace() && *c != thousands)
+                .map(|c| if c == decimal_separator { '.' } else { c })
+                .collect();
+            if cleaned.is_empty() || cleaned == "-" {
+                return Ok(None);
+            }
+            // Accounting notation for negatives: (1.234,56)
+            let (cleaned, negative) =
+                match cleaned.strip_prefix('(').and_then(|c| c.strip_suffix(')')) {
+                    Some(inner) => (inner.to_string(), true),
+                    None => (cleaned, false),
+                };
+            let value = Decimal::from_str(&cleaned)
+                .with_context(|| format!("Invalid number: {}", s.trim()))?;
+            Ok(Some(if negative { -value } else { value }))
+        }
+        _ => Err(anyhow!("Unsupported cell: {}", cell)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_rows_with_mapping() {
+        let mapping: Mapping = toml::from_str(
+            r#"
+            source = "mybroker"
+            skip_rows = 1
+            date_format = "%m/%d/%Y"
+
+            [columns]
+            date = "Trade Date"
+            ticker = "symbol"
+            quantity = "Qty"
+            total = "Amount"
+            fees = "Fees"
+
+            [types]
+            sell = ["Resgate"]
+            "#,
+        )
+        .unwrap();
+        let text = |s: &str| Data::String(s.to_string());
+        let rows = vec![
+            vec![text("Exported by MyBroker")],
+            vec![
+                text("Trade Date"),
+                text("Symbol"),
+                text("Qty"),
+                text("Amount"),
+                text("Fees"),
+            ],
+            vec![
+                text("03/15/2025"),
+                text("petr4"),
+                text("100"),
+                text("3.250,00"),
+                text("4,90"),
+            ],
+            vec![
+                text("2025-04-02"),
+                text("ITSA4"),
+                text("-50"),
+                text("(525,00)"),
+                Data::Empty,
+            ],
+            vec![text("Total"), Data::Empty, Data::Empty, text("3.775,00")],
+            vec![text("bad date"), text("VALE3"), text("1"), text("60,00")],
+        ];
+
+        let txs = parse_rows(&rows, &mapping).unwrap();
+        assert_eq!(txs.len(), 2);
+
+        assert_eq!(txs[0].ticker, "PETR4");
+        assert_eq!(txs[0].transaction_type, "BUY");
+        assert_eq!(
+            txs[0].trade_date,
+            NaiveDate::from_ymd_opt(2025, 3, 15).unwrap()
+        );
+        assert_eq!(txs[0].quantity, dec!(100));
+        assert_eq!(txs[0].price, dec!(32.50));
+        assert_eq!(txs[0].fees, dec!(4.90));
+
+        // Without a type column a negative quantity is a sale
+        assert_eq!(txs[1].transaction_type, "SELL");
+        assert_eq!(txs[1].quantity, dec!(50));
+        assert_eq!(txs[1].total, dec!(525));
+        assert_eq!(txs[1].price, dec!(10.50));
+        assert_eq!(txs[1].fees, Decimal::ZERO);
+
+        assert_eq!(
+            mapping.transaction_type("resgate"),
+            Some(TransactionType::Sell)
+        );
+        assert_eq!(mapping.transaction_type("C"), Some(TransactionType::Buy));
+    }
+}
@@ -0,0 +1,143 @@
+//! Day trade detection for imports that do not flag it.
+//!
+//! CEI and Movimentação files list every buy and sell but not whether they
+//! closed on the same day. A day trade is the quantity of an asset bought and
+//! sold on the same date: min(bought, sold). That quantity of each side is
+//! marked `is_day_trade`, so the sale lands in the 20% day-trade categories
+//! and the 1% IRRF is estimated on its gain; any remainder is split off as a
+//! regular trade, with total and fees prorated by quantity.
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::db::{Transaction, TransactionType};
+
+/// Day-trade quantity still to assign per (asset, date), for each side
+pub struct DayTrades<K> {
+    remaining: HashMap<(K, NaiveDate), (Decimal, Decimal)>,
+}
+
+impl<K: Hash + Eq + Clone> DayTrades<K> {
+    /// Find same-day buys and sells among `trades` (key, date, side, quantity)
+    pub fn detect(
+        trades: impl IntoIterator<Item = (K, NaiveDate, TransactionType, Decimal)>,
+    ) -> Self {
+        let mut totals: HashMap<(K, NaiveDate), (Decimal, Decimal)> = HashMap::new();
+        for (key, date, side, quantity) in trades {
+            let (bought, sold) = totals.entry((key, date)).or_default();
+            match side {
+                TransactionType::Buy => *bought += quantity,
+                TransactionType::Sell => *sold += quantity,
+            }
+        }
+        let remaining = totals
+            .into_iter()
+            .filter_map(|(day, (bought, sold))| {
+                let quantity = bought.min(sold);
+                (quantity > Decimal::ZERO).then_some((day, (quantity, quantity)))
+            })
+            .collect();
+        Self { remaining }
+    }
+
+    /// Mark `tx` as a day trade, splitting off the part beyond the day's
+    /// day-trade quantity as a regular trade
+    pub fn split(&mut self, key: &K, mut tx: Transaction) -> Vec<Transaction> {
+        if tx.is_day_trade || tx.quantity <= Decimal::ZERO {
+            return vec![tx];
+        }
+        let Some((buys, sells)) = self.remaining.get_mut(&(key.clone(), tx.trade_date)) else {
+            return vec![tx];
+        };
+        let left = match tx.transaction_type {
+            TransactionType::Buy => buys,
+            TransactionType::Sell => sells,
+        };
+        if left.is_zero() {
+            return vec![tx];
+        }
+
+        let day_quantity = tx.quantity.min(*left);
+        *left -= day_quantity;
+        if day_quantity == tx.quantity {
+            tx.is_day_trade = true;
+            return vec![tx];
+        }
+
+        let share = day_quantity / tx.quantity;
+        let mut day = tx.clone();
+        day.is_day_trade = true;
+        day.quantity = day_quantity;
+        day.total_cost = (tx.total_cost * share).round_dp(2);
+        day.fees = (tx.fees * share).round_dp(2);
+        tx.quantity -= day.quantity;
+        tx.total_cost -= day.total_cost;
+        tx.fees -= day.fees;
+        vec![day, tx]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn trade(transaction_type: TransactionType, date: NaiveDate, quantity: Decimal) -> Transaction {
+        Transaction {
+            id: None,
+            asset_id: 1,
+            transaction_type,
+            trade_date: date,
+            settlement_date: None,
+            quantity,
+            price_per_unit: dec!(10),
+            total_cost: quantity * dec!(10),
+            fees: dec!(0.30),
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: None,
+            source: "TEST".to_string(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_same_day_pairs_split_into_day_and_swing_trades() {
+        let d = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        let trades = [
+            ("PETR4", trade(TransactionType::Buy, d(4), dec!(300))),
+            ("PETR4", trade(TransactionType::Sell, d(4), dec!(100))),
+            ("PETR4", trade(TransactionType::Sell, d(5), dec!(100))),
+            ("VALE3", trade(TransactionType::Sell, d(4), dec!(50))),
+        ];
+        let mut day_trades = DayTrades::detect(
+            trades
+                .iter()
+                .map(|(k, tx)| (*k, tx.trade_date, tx.transaction_type.clone(), tx.quantity)),
+        );
+
+        let result: Vec<Transaction> = trades
+            .into_iter()
+            .flat_map(|(k, tx)| day_trades.split(&k, tx))
+            .collect();
+        assert_eq!(result.len(), 5);
+
+        let buy_day = &result[0];
+        assert!(buy_day.is_day_trade);
+        assert_eq!(buy_day.quantity, dec!(100));
+        assert_eq!(buy_day.total_cost, dec!(1000));
+        assert_eq!(buy_day.fees, dec!(0.10));
+        let buy_swing = &result[1];
+        assert!(!buy_swing.is_day_trade);
+        assert_eq!(buy_swing.quantity, dec!(200));
+        assert_eq!(buy_swing.fees, dec!(0.20));
+
+        assert!(result[2].is_day_trade);
+        assert_eq!(result[2].quantity, dec!(100));
+        // Other day, other ticker
+        assert!(!result[3].is_day_trade);
+        assert!(!result[4].is_day_trade);
+    }
+}
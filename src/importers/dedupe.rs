@@ -0,0 +1,303 @@
+//! Duplicate trades across import sources.
+//!
+//! Each importer only skips what it imported itself, so the same purchase can
+//! reach the database twice: from the B3 trade export and from a Movimentação
+//! statement, a custom spreadsheet or a manual entry. After each import, trades
+//! are fingerprinted (portfolio, asset, date, side, quantity and price to the
+//! cent) and rows from different sources sharing a fingerprint are flagged as
+//! DUPLICATE_TRANSACTION inconsistencies. Rows from the same source are never
+//! paired: two identical fills on one day are real trades. Resolving an issue
+//! merges the pair into one transaction.
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::db::{
+    self, portfolio, Inconsistency, InconsistencySeverity, InconsistencyStatus, InconsistencyType,
+};
+
+/// A trade as compared for duplicates
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeRow {
+    pub id: i64,
+    pub ticker: String,
+    pub trade_date: NaiveDate,
+    pub transaction_type: String,
+    pub quantity: Decimal,
+    pub price_per_unit: Decimal,
+    pub fees: Decimal,
+    pub source: String,
+    #[serde(skip)]
+    portfolio_id: i64,
+    #[serde(skip)]
+    asset_id: i64,
+}
+
+impl TradeRow {
+    /// Same portfolio, asset, date, side, quantity and price to the cent
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "{}|{}|{}|{}|{}|{}",
+            self.portfolio_id,
+            self.asset_id,
+            self.trade_date,
+            self.transaction_type,
+            self.quantity.normalize(),
+            self.price_per_unit.round_dp(2).normalize()
+        )
+    }
+}
+
+/// Two rows for the same trade; `keep` is the one recorded first
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicatePair {
+    pub keep: TradeRow,
+    pub drop: TradeRow,
+}
+
+impl DuplicatePair {
+    fn source_ref(&self) -> String {
+        format!("transactions:{}:{}", self.keep.id, self.drop.id)
+    }
+}
+
+fn map_trade(row: &rusqlite::Row) -> rusqlite::Result<TradeRow> {
+    Ok(TradeRow {
+        id: row.get(0)?,
+        ticker: row.get(1)?,
+        trade_date: row.get(2)?,
+        transaction_type: row.get(3)?,
+        quantity: db::get_decimal_value(row, 4)?,
+        price_per_unit: db::get_decimal_value(row, 5)?,
+        fees: db::get_decimal_value(row, 6)?,
+        source: row.get(7)?,
+        portfolio_id: row.get(8)?,
+        asset_id: row.get(9)?,
+    })
+}
+
+const TRADE_COLUMNS: &str = "t.id, a.ticker, t.trade_date, t.transaction_type, t.quantity,
+     t.price_per_unit, COALESCE(t.fees, 0), COALESCE(t.source, 'MANUAL'), t.portfolio_id, t.asset_id";
+
+fn get_trade(conn: &Connection, id: i64) -> Result<Option<TradeRow>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM transactions t JOIN assets a ON t.asset_id = a.id WHERE t.id = ?1",
+                TRADE_COLUMNS
+            ),
+            params![id],
+            map_trade,
+        )
+        .optional()?)
+}
+
+/// Pairs of rows from different sources with the same fingerprint
+pub fn find_duplicates(conn: &Connection) -> Result<Vec<DuplicatePair>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM transactions t JOIN assets a ON t.asset_id = a.id
+         WHERE 1 = 1{}
+         ORDER BY t.id",
+        TRADE_COLUMNS,
+        portfolio::scope_filter("t.portfolio_id")
+    ))?;
+    let mut groups: BTreeMap<String, Vec<TradeRow>> = BTreeMap::new();
+    for row in stmt.query_map([], map_trade)? {
+        let row = row?;
+        groups.entry(row.fingerprint()).or_default().push(row);
+    }
+
+    let mut pairs = Vec::new();
+    for rows in groups.into_values().filter(|rows| rows.len() > 1) {
+        let mut paired = vec![false; rows.len()];
+        for i in 0..rows.len() {
+            if paired[i] {
+                continue;
+            }
+            let Some(j) =
+                (i + 1..rows.len()).find(|&j| !paired[j] && rows[j].source != rows[i].source)
+            else {
+                continue;
+            };
+            paired[i] = true;
+            paired[j] = true;
+            pairs.push(DuplicatePair {
+                keep: rows[i].clone(),
+                drop: rows[j].clone(),
+            });
+        }
+    }
+    Ok(pairs)
+}
+
+/// The two transaction ids an issue's source_ref names
+pub fn pair_ids(source_ref: &str) -> Option<(i64, i64)> {
+    let (keep, drop) = source_ref.strip_prefix("transactions:")?.split_once(':')?;
+    Some((keep.parse().ok()?, drop.parse().ok()?))
+}
+
+/// Open an inconsistency for each new duplicate pair, and resolve the open
+/// ones whose rows were deleted or merged since. Returns (opened, resolved).
+pub fn sync_duplicates(conn: &Connection) -> Result<(usize, usize)> {
+    let mut known: HashSet<String> = HashSet::new();
+    let mut resolved = 0;
+    for issue in db::list_inconsistencies(
+        conn,
+        None,
+        Some(InconsistencyType::DuplicateTransaction),
+        None,
+    )? {
+        let Some(source_ref) = issue.source_ref else {
+            continue;
+        };
+        if issue.status == InconsistencyStatus::Open {
+            let (keep, drop) = pair_ids(&source_ref).unwrap_or_default();
+            if get_trade(conn, keep)?.is_none() || get_trade(conn, drop)?.is_none() {
+                db::resolve_inconsistency(conn, issue.id.unwrap_or(0), Some("GONE"), None)?;
+                resolved += 1;
+                continue;
+            }
+        }
+        known.insert(source_ref);
+    }
+
+    let mut opened = 0;
+    for pair in find_duplicates(conn)? {
+        let source_ref = pair.source_ref();
+        if known.contains(&source_ref) {
+            continue;
+        }
+        db::insert_inconsistency(
+            conn,
+            &Inconsistency {
+                id: None,
+                issue_type: InconsistencyType::DuplicateTransaction,
+                status: InconsistencyStatus::Open,
+                severity: InconsistencySeverity::Warn,
+                asset_id: Some(pair.keep.asset_id),
+                transaction_id: None,
+                ticker: Some(pair.keep.ticker.clone()),
+                trade_date: Some(pair.keep.trade_date),
+                quantity: Some(pair.keep.quantity),
+                source: Some(pair.drop.source.clone()),
+                source_ref: Some(source_ref),
+                missing_fields_json: None,
+                context_json: Some(
+                    json!({
+                        "notes": format!(
+                            "Same trade imported from {} and {}",
+                            pair.keep.source, pair.drop.source
+                        ),
+                        "keep": pair.keep,
+                        "drop": pair.drop,
+                    })
+                    .to_string(),
+                ),
+                resolution_action: None,
+                resolution_json: None,
+                created_at: None,
+                resolved_at: None,
+            },
+        )?;
+        opened += 1;
+    }
+    Ok((opened, resolved))
+}
+
+/// Merge a duplicate into the row kept: the kept row takes the fees, broker,
+/// settlement date and day-trade flag it lacks, journal links and cash flows
+/// move to it, and the duplicate is deleted.
+pub fn merge(conn: &Connection, keep_id: i64, drop_id: i64) -> Result<()> {
+    let keep =
+        get_trade(conn, keep_id)?.ok_or_else(|| anyhow!("Transaction {} not found", keep_id))?;
+    let drop =
+        get_trade(conn, drop_id)?.ok_or_else(|| anyhow!("Transaction {} not found", drop_id))?;
+    if keep.fingerprint() != drop.fingerprint() {
+        return Err(anyhow!(
+            "Transactions {} and {} are not the same trade",
+            keep_id,
+            drop_id
+        ));
+    }
+
+    db::bulk::in_transaction(conn, |conn| {
+        if keep.fees.is_zero() && !drop.fees.is_zero() {
+            conn.execute(
+                "UPDATE transactions SET fees = (SELECT fees FROM transactions WHERE id = ?2),
+                     total_cost = (SELECT total_cost FROM transactions WHERE id = ?2)
+                 WHERE id = ?1",
+                params![keep_id, drop_id],
+            )?;
+        }
+        conn.execute(
+            "UPDATE transactions SET
+                 broker_id = COALESCE(broker_id, (SELECT broker_id FROM transactions WHERE id = ?2)),
+                 settlement_date = COALESCE(settlement_date,
+                     (SELECT settlement_date FROM transactions WHERE id = ?2)),
+                 is_day_trade = is_day_trade OR (SELECT is_day_trade FROM transactions WHERE id = ?2)
+             WHERE id = ?1",
+            params![keep_id, drop_id],
+        )?;
+        conn.execute(
+            "UPDATE OR IGNORE journal_links SET transaction_id = ?1 WHERE transaction_id = ?2",
+            params![keep_id, drop_id],
+        )?;
+        for table in ["cash_flows", "inconsistencies"] {
+            conn.execute(
+                &format!(
+                    "UPDATE {} SET transaction_id = ?1 WHERE transaction_id = ?2",
+                    table
+                ),
+                params![keep_id, drop_id],
+            )?;
+        }
+        conn.execute("DELETE FROM transactions WHERE id = ?1", params![drop_id])?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cross_source_duplicates_are_flagged_and_merged() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'PETR4', 'STOCK');
+             INSERT INTO transactions (id, asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source)
+             VALUES (1, 1, 'BUY', '2024-01-10', '100', '30.5', '3050', '0', 'CEI'),
+                    (2, 1, 'BUY', '2024-01-10', '100', '30.5', '3050', '0', 'CEI'),
+                    (3, 1, 'BUY', '2024-01-10', '100', '30.50', '3052.10', '2.10', 'MOVIMENTACAO'),
+                    (4, 1, 'BUY', '2024-01-10', '100', '31', '3100', '0', 'MOVIMENTACAO');",
+        )
+        .unwrap();
+
+        // Two identical CEI fills are real; only one of them pairs with the
+        // Movimentação row, and a different price is another trade
+        let pairs = find_duplicates(&conn).unwrap();
+        assert_eq!(pairs.len(), 1);
+        assert_eq!((pairs[0].keep.id, pairs[0].drop.id), (1, 3));
+
+        assert_eq!(sync_duplicates(&conn).unwrap(), (1, 0));
+        assert_eq!(sync_duplicates(&conn).unwrap(), (0, 0));
+
+        merge(&conn, 1, 3).unwrap();
+        let kept = get_trade(&conn, 1).unwrap().unwrap();
+        assert_eq!(kept.fees, Decimal::new(210, 2));
+        assert_eq!(kept.source, "CEI");
+        assert!(find_duplicates(&conn).unwrap().is_empty());
+
+        // The open issue is closed once its pair is gone
+        assert_eq!(sync_duplicates(&conn).unwrap(), (0, 1));
+        assert!(merge(&conn, 1, 4).is_err());
+    }
+}
@@ -9,14 +9,29 @@ pub enum FileType {
     Cei,
     Movimentacao,
     OfertasPublicas,
+    NotaCorretagem,
+    TesouroExtrato,
+    Proventos,
+    Ofx,
+    Posicao,
+    UsBroker,
+    Crypto,
 }
 
 /// Detect the type of import file based on its contents
 ///
 /// Detection strategy:
-/// - CSV/TXT files → Always CEI format (Movimentacao only supports Excel)
+/// - CSV/TXT files → Tesouro Direto extract when the header names a "Título"
+///   column, Avenue/US broker statement when it names a "Descrição" and
+///   dollar value or a "Symbol" and "Action", Mercado Bitcoin or Binance
+///   trade history when it names a coin or pair, otherwise CEI format
+///   (Movimentacao only supports Excel)
+/// - PDF files → Brokerage notes (notas de corretagem)
+/// - OFX/QFX files → Investment statements from other brokers and banks
 /// - Excel files → Check sheet names:
 ///   - "Movimentação" → Movimentacao format
+///   - "Proventos Recebidos" → income received report
+///   - "Acoes", "Fundo de Investimento", "BDR", "ETF" → Posição (custody) export
 ///   - "negociação", "ativos", "trading", etc → CEI format
 ///   - Unknown → Error with helpful message
 pub fn detect_file_type<P: AsRef<Path>>(path: P) -> Result<FileType> {
@@ -27,12 +42,36 @@ pub fn detect_file_type<P: AsRef<Path>>(path: P) -> Result<FileType> {
         .ok_or_else(|| anyhow!("File has no extension"))?
         .to_lowercase();
 
-    // CSV/TXT files are always CEI format
     if matches!(extension.as_str(), "csv" | "txt") {
+        let header =
+            super::inspect::read_text(path).context("Failed to read file for type detection")?;
+        let first_line = header.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+        if super::tesouro_extrato::is_extrato_header(first_line) {
+            info!("Detected Tesouro Direto extract (CSV with Título column)");
+            return Ok(FileType::TesouroExtrato);
+        }
+        if super::avenue_csv::is_us_broker_header(first_line) {
+            info!("Detected Avenue/US broker statement (CSV in dollars)");
+            return Ok(FileType::UsBroker);
+        }
+        if super::crypto_csv::is_crypto_exchange_header(first_line) {
+            info!("Detected crypto exchange trade history (CSV)");
+            return Ok(FileType::Crypto);
+        }
         info!("Detected CEI format (CSV/TXT file)");
         return Ok(FileType::Cei);
     }
 
+    if matches!(extension.as_str(), "ofx" | "qfx") {
+        info!("Detected OFX statement");
+        return Ok(FileType::Ofx);
+    }
+
+    if extension == "pdf" {
+        info!("Detected brokerage note (PDF file)");
+        return Ok(FileType::NotaCorretagem);
+    }
+
     // For Excel files, check sheet names
     if matches!(extension.as_str(), "xlsx" | "xls") {
         let workbook: Xlsx<_> =
@@ -73,6 +112,22 @@ pub fn detect_file_type<P: AsRef<Path>>(path: P) -> Result<FileType> {
             return Ok(FileType::Movimentacao);
         }
 
+        if let Some(name) = sheet_names
+            .iter()
+            .find(|name| super::proventos_excel::is_proventos_sheet(name))
+        {
+            info!(
+                "Detected Proventos Recebidos report (found '{}' sheet)",
+                name
+            );
+            return Ok(FileType::Proventos);
+        }
+
+        if super::posicao_excel::is_posicao_workbook(&sheet_names) {
+            info!("Detected B3 Posição export (found per-product custody sheets)");
+            return Ok(FileType::Posicao);
+        }
+
         // Check for CEI trading sheets (case-insensitive pattern matching)
         let cei_patterns = ["negociação", "negociacao", "ativos", "trading", "trades"];
         for sheet_name in &sheet_names {
@@ -95,6 +150,8 @@ pub fn detect_file_type<P: AsRef<Path>>(path: P) -> Result<FileType> {
              Expected either:\n  \
              - CEI format with sheets matching: negociação, ativos, trading\n  \
              - Movimentacao format with sheet: Movimentação\n  \
+             - Proventos Recebidos report with sheet: Proventos Recebidos\n  \
+             - Posição export with sheets: Acoes, Fundo de Investimento, BDR, ETF\n  \
              - Ofertas Públicas format with sheet: Movimentação + oferta headers",
             sheet_names
         ));
@@ -0,0 +1,401 @@
+// File inspection helpers - explain what the importers will see in a CSV or PDF
+//
+// CSV values are classified with the same date/decimal parsers the CEI CSV
+// importer uses, so a column shown as "text" is one the importer would reject.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::cei_csv::{parse_csv_date, parse_csv_decimal};
+
+/// Delimiters tried when sniffing a CSV, in order of preference on ties
+const CANDIDATE_DELIMITERS: [u8; 4] = [b';', b',', b'\t', b'|'];
+
+/// Number of lines used to sniff the delimiter
+const SNIFF_LINES: usize = 20;
+
+/// What kind of values a column holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Empty,
+    Date,
+    Number,
+    Text,
+    Mixed,
+}
+
+impl ColumnKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColumnKind::Empty => "empty",
+            ColumnKind::Date => "date",
+            ColumnKind::Number => "number",
+            ColumnKind::Text => "text",
+            ColumnKind::Mixed => "mixed",
+        }
+    }
+}
+
+/// Per-column statistics of a CSV file
+#[derive(Debug, Clone)]
+pub struct ColumnStats {
+    pub name: String,
+    pub filled: usize,
+    pub empty: usize,
+    pub distinct: usize,
+    pub kind: ColumnKind,
+    /// Most frequent values with their counts, most frequent first
+    pub top_values: Vec<(String, usize)>,
+}
+
+/// Result of sniffing and scanning a CSV file
+#[derive(Debug, Clone)]
+pub struct CsvInspection {
+    pub encoding: &'static str,
+    pub delimiter: u8,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Rows whose field count differs from the header
+    pub ragged_rows: usize,
+    pub columns: Vec<ColumnStats>,
+}
+
+/// Human-readable delimiter name
+pub fn delimiter_name(delimiter: u8) -> &'static str {
+    match delimiter {
+        b';' => "semicolon (;)",
+        b',' => "comma (,)",
+        b'\t' => "tab",
+        b'|' => "pipe (|)",
+        _ => "unknown",
+    }
+}
+
+/// Decode raw bytes, returning the detected encoding and the text
+///
+/// A BOM wins; otherwise valid UTF-8 is taken as is and anything else is
+/// decoded as Windows-1252, which is what B3 and most brokers export.
+pub fn decode_text(bytes: &[u8]) -> (&'static str, String) {
+    if let Some((encoding, bom_len)) = encoding_rs::Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_len..]);
+        let name = match encoding.name() {
+            "UTF-8" => "UTF-8 (with BOM)",
+            "UTF-16LE" => "UTF-16LE",
+            _ => "UTF-16BE",
+        };
+        return (name, text.into_owned());
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) => ("UTF-8", text.to_string()),
+        Err(_) => {
+            let (text, _, _) = encoding_rs::WINDOWS_1252.decode(bytes);
+            ("Windows-1252 (Latin-1)", text.into_owned())
+        }
+    }
+}
+
+/// Read a text file in any encoding `decode_text` knows: Excel and Notepad on
+/// Windows save UTF-16 or UTF-8 with a BOM, older exports Windows-1252.
+/// Lines may end in CRLF; `str::lines` and the CSV reader accept both.
+pub fn read_text<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(decode_text(&bytes).1)
+}
+
+/// Pick the delimiter that splits the first lines into a consistent field count
+pub fn detect_delimiter(text: &str) -> u8 {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+
+    let mut best = (b';', 0usize, 0usize);
+    for delimiter in CANDIDATE_DELIMITERS {
+        let counts: Vec<usize> = lines
+            .iter()
+            .map(|l| l.bytes().filter(|b| *b == delimiter).count())
+            .collect();
+        let Some(&first) = counts.first() else {
+            continue;
+        };
+        if first == 0 {
+            continue;
+        }
+        let consistent = counts.iter().filter(|c| **c == first).count();
+        if (consistent, first) > (best.1, best.2) {
+            best = (delimiter, consistent, first);
+        }
+    }
+
+    best.0
+}
+
+fn classify(value: &str) -> ColumnKind {
+    if parse_csv_date(value).is_ok() {
+        ColumnKind::Date
+    } else if parse_csv_decimal(value).is_ok() {
+        ColumnKind::Number
+    } else {
+        ColumnKind::Text
+    }
+}
+
+fn column_stats(name: &str, values: &[&str]) -> ColumnStats {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut kind = ColumnKind::Empty;
+    let mut empty = 0;
+
+    for value in values {
+        if value.is_empty() {
+            empty += 1;
+            continue;
+        }
+        *counts.entry(value).or_insert(0) += 1;
+        kind = match (kind, classify(value)) {
+            (ColumnKind::Empty, k) => k,
+            (current, k) if current == k => current,
+            _ => ColumnKind::Mixed,
+        };
+    }
+
+    let mut top_values: Vec<(String, usize)> = counts
+        .iter()
+        .map(|(value, count)| (value.to_string(), *count))
+        .collect();
+    top_values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top_values.truncate(5);
+
+    ColumnStats {
+        name: name.to_string(),
+        filled: values.len() - empty,
+        empty,
+        distinct: counts.len(),
+        kind,
+        top_values,
+    }
+}
+
+/// Sniff encoding and delimiter of a CSV and compute per-column statistics
+pub fn inspect_csv_bytes(bytes: &[u8]) -> Result<CsvInspection> {
+    let (encoding, text) = decode_text(bytes);
+    let delimiter = detect_delimiter(&text);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(text.as_bytes());
+
+    let mut records = reader.records();
+    let headers: Vec<String> = match records.next() {
+        Some(record) => record
+            .context("Failed to read CSV header")?
+            .iter()
+            .map(|h| h.trim().to_string())
+            .collect(),
+        None => return Err(anyhow!("CSV file is empty")),
+    };
+
+    let mut rows = Vec::new();
+    for (idx, record) in records.enumerate() {
+        let record = record.with_context(|| format!("Failed to read CSV row {}", idx + 2))?;
+        if record.iter().all(|f| f.trim().is_empty()) {
+            continue;
+        }
+        rows.push(record.iter().map(|f| f.trim().to_string()).collect());
+    }
+
+    let ragged_rows = rows
+        .iter()
+        .filter(|r: &&Vec<String>| r.len() != headers.len())
+        .count();
+    let width = rows.iter().map(Vec::len).fold(headers.len(), usize::max);
+    let columns = (0..width)
+        .map(|col| {
+            let values: Vec<&str> = rows
+                .iter()
+                .map(|r| r.get(col).map(String::as_str).unwrap_or(""))
+                .collect();
+            let name = headers.get(col).map(String::as_str).unwrap_or("");
+            column_stats(name, &values)
+        })
+        .collect();
+
+    Ok(CsvInspection {
+        encoding,
+        delimiter,
+        headers,
+        rows,
+        ragged_rows,
+        columns,
+    })
+}
+
+/// Inspect a CSV file on disk
+pub fn inspect_csv<P: AsRef<Path>>(path: P) -> Result<CsvInspection> {
+    let bytes = std::fs::read(path.as_ref()).context("Failed to read CSV file")?;
+    inspect_csv_bytes(&bytes)
+}
+
+/// Parse a page selection such as "3" or "2-5" into an inclusive 1-based range
+pub fn parse_page_range(pages: &str) -> Result<(u32, u32)> {
+    let parse = |s: &str| -> Result<u32> {
+        let page: u32 = s
+            .trim()
+            .parse()
+            .map_err(|_| anyhow!("Invalid page '{}'. Use N or N-M (e.g., 2-5)", s.trim()))?;
+        if page == 0 {
+            return Err(anyhow!("Pages start at 1"));
+        }
+        Ok(page)
+    };
+
+    let (first, last) = match pages.split_once('-') {
+        Some((first, last)) => (parse(first)?, parse(last)?),
+        None => {
+            let page = parse(pages)?;
+            (page, page)
+        }
+    };
+    if first > last {
+        return Err(anyhow!("Invalid page range {}: start is after end", pages));
+    }
+    Ok((first, last))
+}
+
+/// Extracted text of a PDF, one entry per page
+#[derive(Debug, Clone)]
+pub struct PdfText {
+    /// "pdftotext" or "pdf-extract" (fallback when poppler is not installed)
+    pub extractor: &'static str,
+    /// (page number, text)
+    pub pages: Vec<(u32, String)>,
+}
+
+/// Extract the text of a PDF, optionally restricted to a page range
+///
+/// Uses `pdftotext -layout` when available since it keeps table columns
+/// aligned, and falls back to the built-in extractor used by `import-irpf`.
+pub fn extract_pdf_text<P: AsRef<Path>>(path: P, pages: Option<(u32, u32)>) -> Result<PdfText> {
+    let path = path.as_ref();
+    match pdftotext_pages(path, pages) {
+        Ok(Some(pages)) => {
+            return Ok(PdfText {
+                extractor: "pdftotext",
+                pages,
+            })
+        }
+        Ok(None) => tracing::debug!("pdftotext not found, using pdf-extract"),
+        Err(e) => tracing::warn!("pdftotext failed, using pdf-extract: {}", e),
+    }
+
+    let all_pages =
+        pdf_extract::extract_text_by_pages(path).context("Failed to extract text from PDF")?;
+    let (first, last) = pages.unwrap_or((1, all_pages.len().max(1) as u32));
+    let pages = all_pages
+        .into_iter()
+        .zip(1u32..)
+        .filter(|(_, n)| (first..=last).contains(n))
+        .map(|(text, n)| (n, text))
+        .collect();
+
+    Ok(PdfText {
+        extractor: "pdf-extract",
+        pages,
+    })
+}
+
+/// Run pdftotext page by page; `Ok(None)` when the binary is not installed
+fn pdftotext_pages(path: &Path, pages: Option<(u32, u32)>) -> Result<Option<Vec<(u32, String)>>> {
+    let mut command = std::process::Command::new("pdftotext");
+    command.arg("-layout");
+    if let Some((first, last)) = pages {
+        command
+            .args(["-f", &first.to_string()])
+            .args(["-l", &last.to_string()]);
+    }
+    command.arg(path).arg("-");
+
+    let output = match command.output() {
+        Ok(output) => output,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).context("Failed to run pdftotext"),
+    };
+    if !output.status.success() {
+        return Err(anyhow!(
+            "pdftotext exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    // pdftotext separates pages with form feeds
+    let text = String::from_utf8_lossy(&output.stdout);
+    let first = pages.map(|(first, _)| first).unwrap_or(1);
+    let mut split: Vec<&str> = text.split('\x0c').collect();
+    if split.last().is_some_and(|p| p.trim().is_empty()) {
+        split.pop();
+    }
+    Ok(Some(
+        split
+            .into_iter()
+            .zip(first..)
+            .map(|(text, n)| (n, text.to_string()))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect_latin1_semicolon_csv() {
+        let csv = "Data do Negócio;Código;Quantidade;Preço\n\
+                   15/03/2025;PETR4;100;38,50\n\
+                   16/03/2025;VALE3;;61,20\n\
+                   17/03/2025;PETR4;50;abc\n";
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode(csv);
+
+        let inspection = inspect_csv_bytes(&bytes).unwrap();
+        assert_eq!(inspection.encoding, "Windows-1252 (Latin-1)");
+        assert_eq!(inspection.delimiter, b';');
+        assert_eq!(inspection.headers[0], "Data do Negócio");
+        assert_eq!(inspection.rows.len(), 3);
+        assert_eq!(inspection.ragged_rows, 0);
+
+        let cols = &inspection.columns;
+        assert_eq!(cols[0].kind, ColumnKind::Date);
+        assert_eq!(cols[1].kind, ColumnKind::Text);
+        assert_eq!(cols[1].distinct, 2);
+        assert_eq!(cols[1].top_values[0], ("PETR4".to_string(), 2));
+        assert_eq!(cols[2].kind, ColumnKind::Number);
+        assert_eq!(cols[2].empty, 1);
+        assert_eq!(cols[3].kind, ColumnKind::Mixed);
+    }
+
+    #[test]
+    fn test_detect_delimiter_and_bom() {
+        let (encoding, text) = decode_text(b"\xEF\xBB\xBFa,b,c\n1,2,3\n");
+        assert_eq!(encoding, "UTF-8 (with BOM)");
+        assert_eq!(text, "a,b,c\n1,2,3\n");
+        assert_eq!(detect_delimiter(&text), b',');
+        // Decimal commas inside a semicolon file do not win
+        assert_eq!(detect_delimiter("a;b\n1,5;2,5\n3,1;4\n"), b';');
+        assert_eq!(detect_delimiter("a\tb\n1\t2\n"), b'\t');
+    }
+
+    #[test]
+    fn test_parse_page_range() {
+        assert_eq!(parse_page_range("3").unwrap(), (3, 3));
+        assert_eq!(parse_page_range("2-5").unwrap(), (2, 5));
+        assert!(parse_page_range("0").is_err());
+        assert!(parse_page_range("5-2").is_err());
+        assert!(parse_page_range("x").is_err());
+    }
+}
@@ -1,24 +1,45 @@
 // Import module - B3/CEI Excel and CSV parsers
 
+pub mod avenue_csv;
+pub mod b3_api;
 pub mod b3_cotahist;
 pub mod cei_csv;
 pub mod cei_excel;
+pub mod crypto_csv;
+pub mod custom;
+pub mod day_trade;
+pub mod dedupe;
 mod file_detector;
+pub mod inspect;
 pub mod irpf_pdf;
 pub mod movimentacao_excel;
 pub mod movimentacao_import;
+pub mod movimentacao_layout;
+pub mod nota_corretagem;
 pub mod ofertas_publicas_excel;
+pub mod ofx;
+pub mod posicao_excel;
+pub mod proventos_excel;
+pub mod tesouro_extrato;
 pub mod validation;
+pub mod watch;
 
 use anyhow::{anyhow, Result};
 use std::path::Path;
 use tracing::info;
 
+pub use avenue_csv::UsBrokerStatement;
 pub use cei_excel::RawTransaction;
+pub use crypto_csv::CryptoStatement;
 pub use file_detector::FileType;
 pub use movimentacao_excel::MovimentacaoEntry;
 pub use movimentacao_import::import_movimentacao_entries;
+pub use nota_corretagem::NotaCorretagem;
 pub use ofertas_publicas_excel::OfertaPublicaEntry;
+pub use ofx::OfxStatement;
+pub use posicao_excel::PosicaoStatement;
+pub use proventos_excel::ProventoEntry;
+pub use tesouro_extrato::TesouroExtratoEntry;
 
 use chrono::NaiveDate;
 use serde::Serialize;
@@ -42,10 +63,90 @@ pub struct ImportStats {
     pub skipped_income: usize,
     pub skipped_income_old: usize,
 
+    // Brokerage notes: trades already imported from B3 that got the note's fees
+    pub enriched_trades: usize,
+
+    // Proventos: income events recorded by another import that got the
+    // report's gross value, withholding and ex-date
+    pub enriched_income: usize,
+
+    // CEI / Movimentação: trades marked as day trades (same-day buy and sell)
+    pub day_trades: usize,
+
+    // CEI: option exercises whose premium was moved into the underlying trade
+    pub option_exercises: usize,
+
     pub errors: usize,
 
     pub earliest: Option<NaiveDate>,
     pub latest: Option<NaiveDate>,
+
+    /// Outcome of each processed item; printed next to the counters in JSON
+    #[serde(skip)]
+    pub items: Vec<ItemResult>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemStatus {
+    Success,
+    Skipped,
+    Error,
+}
+
+/// Outcome of one item of a batch operation (a file row, a brokerage note,
+/// a ticker whose price was fetched), so automation can retry exactly the
+/// items that failed
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ItemResult {
+    /// trade, corporate_action, income, offer, note or price
+    pub kind: &'static str,
+    pub ticker: Option<String>,
+    pub date: Option<NaiveDate>,
+    /// Movement type, note number or other detail that identifies the item
+    pub reference: Option<String>,
+    pub status: ItemStatus,
+    /// Stable reason for skipped and failed items (e.g. BEFORE_LAST_IMPORT)
+    pub code: Option<&'static str>,
+    pub message: Option<String>,
+}
+
+impl ItemResult {
+    /// A successful item; turn it into a skip or an error with the builders
+    pub fn new(
+        kind: &'static str,
+        ticker: Option<&str>,
+        date: Option<NaiveDate>,
+        reference: Option<&str>,
+    ) -> Self {
+        Self {
+            kind,
+            ticker: ticker.map(str::to_string),
+            date,
+            reference: reference.map(str::to_string),
+            status: ItemStatus::Success,
+            code: None,
+            message: None,
+        }
+    }
+
+    pub fn skipped(self, code: &'static str, message: impl ToString) -> Self {
+        Self {
+            status: ItemStatus::Skipped,
+            code: Some(code),
+            message: Some(message.to_string()),
+            ..self
+        }
+    }
+
+    pub fn failed(self, code: &'static str, message: impl ToString) -> Self {
+        Self {
+            status: ItemStatus::Error,
+            code: Some(code),
+            message: Some(message.to_string()),
+            ..self
+        }
+    }
 }
 
 /// Result of importing a file with auto-detection
@@ -54,13 +155,54 @@ pub enum ImportResult {
     Cei(Vec<RawTransaction>),
     Movimentacao(Vec<MovimentacaoEntry>),
     OfertasPublicas(Vec<OfertaPublicaEntry>),
+    NotaCorretagem(Vec<NotaCorretagem>),
+    TesouroExtrato(Vec<TesouroExtratoEntry>),
+    Proventos(Vec<ProventoEntry>),
+    /// Trades and income of an OFX investment statement
+    Ofx(OfxStatement),
+    /// Custody held at the end of a day, from the B3 Posição export
+    Posicao(PosicaoStatement),
+    /// Trades and dividends in dollars from Avenue or another US broker
+    UsBroker(UsBrokerStatement),
+    /// Crypto trades from Mercado Bitcoin or Binance
+    Crypto(CryptoStatement),
+    /// Trades read with a user-supplied column mapping, tagged with its source
+    Custom {
+        source: String,
+        transactions: Vec<RawTransaction>,
+    },
+}
+
+impl ImportResult {
+    /// Name of the detected format, as recorded on the import session
+    pub fn format_name(&self) -> String {
+        match self {
+            ImportResult::Cei(_) => "CEI".to_string(),
+            ImportResult::Movimentacao(_) => "Movimentação".to_string(),
+            ImportResult::OfertasPublicas(_) => "Ofertas Públicas".to_string(),
+            ImportResult::NotaCorretagem(_) => "Nota de corretagem".to_string(),
+            ImportResult::TesouroExtrato(_) => "Tesouro Direto".to_string(),
+            ImportResult::Proventos(_) => "Proventos Recebidos".to_string(),
+            ImportResult::Ofx(_) => "OFX".to_string(),
+            ImportResult::Posicao(_) => "Posição".to_string(),
+            ImportResult::UsBroker(statement) => statement
+                .broker
+                .clone()
+                .unwrap_or_else(|| "US broker".to_string()),
+            ImportResult::Crypto(statement) => statement.exchange.clone(),
+            ImportResult::Custom { source, .. } => source.clone(),
+        }
+    }
 }
 
 /// Import file with automatic format detection
 ///
-/// Detects whether the file is CEI or Movimentacao format based on content,
-/// then parses accordingly. Returns an ImportResult indicating which format
-/// was detected and the parsed data.
+/// Detects whether the file is CEI, Movimentacao, a brokerage note PDF, a
+/// Tesouro Direto extract, a Proventos Recebidos report, a Posição export,
+/// an OFX statement, an Avenue/US broker statement or a Mercado Bitcoin or
+/// Binance trade history, then parses
+/// accordingly. Returns an ImportResult
+/// indicating which format was detected and the parsed data.
 pub fn import_file_auto<P: AsRef<Path>>(path: P) -> Result<ImportResult> {
     let path_ref = path.as_ref();
 
@@ -80,6 +222,28 @@ pub fn import_file_auto<P: AsRef<Path>>(path: P) -> Result<ImportResult> {
             let entries = ofertas_publicas_excel::parse_ofertas_publicas_excel(path_ref)?;
             Ok(ImportResult::OfertasPublicas(entries))
         }
+        FileType::NotaCorretagem => {
+            let notes = nota_corretagem::parse_nota_corretagem_pdf(path_ref)?;
+            Ok(ImportResult::NotaCorretagem(notes))
+        }
+        FileType::TesouroExtrato => {
+            let entries = tesouro_extrato::parse_tesouro_extrato(path_ref)?;
+            Ok(ImportResult::TesouroExtrato(entries))
+        }
+        FileType::Proventos => {
+            let entries = proventos_excel::parse_proventos_excel(path_ref)?;
+            Ok(ImportResult::Proventos(entries))
+        }
+        FileType::Ofx => Ok(ImportResult::Ofx(ofx::parse_ofx(path_ref)?)),
+        FileType::Posicao => Ok(ImportResult::Posicao(posicao_excel::parse_posicao_excel(
+            path_ref,
+        )?)),
+        FileType::UsBroker => Ok(ImportResult::UsBroker(avenue_csv::parse_us_broker_csv(
+            path_ref,
+        )?)),
+        FileType::Crypto => Ok(ImportResult::Crypto(crypto_csv::parse_crypto_csv(
+            path_ref,
+        )?)),
     }
 }
 
@@ -19,14 +19,16 @@
 
 use anyhow::{anyhow, Context, Result};
 use calamine::{open_workbook, Data, DataType, Reader, Xlsx};
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
 use rust_decimal::Decimal;
 use std::path::Path;
 use std::str::FromStr;
 use tracing::{debug, info, warn};
 
-use crate::db::models::{IncomeEvent, IncomeEventType, Transaction, TransactionType};
+use crate::db::models::{AssetType, IncomeEvent, IncomeEventType, Transaction, TransactionType};
 use crate::db::{CorporateAction, CorporateActionType};
+use crate::importers::movimentacao_layout::{self, ColumnMap};
+use crate::tax::rules::income_withholding_rule;
 
 /// Parsed movimentacao entry
 #[derive(Debug, Clone)]
@@ -36,7 +38,6 @@ pub struct MovimentacaoEntry {
     pub movement_type: String, // Compra, Venda, Liquidação Termo, Desdobro, etc.
     pub product: String,       // Full product name with ticker
     pub ticker: Option<String>, // Extracted ticker
-    #[allow(dead_code)]
     pub institution: String,
     pub quantity: Option<Decimal>,
     pub unit_price: Option<Decimal>,
@@ -51,7 +52,7 @@ impl MovimentacaoEntry {
     /// - Debentures: "DEB - ELET23 - COMPANY" -> "ELET23"
     /// - CDB: "CDB - CDB92576XY3 - ITAU" -> "CDB92576XY3"
     /// - Tesouro Direto: "Tesouro IPCA+ 2035" -> "TESOURO_IPCA_2035"
-    fn extract_ticker(product: &str) -> Option<String> {
+    pub(crate) fn extract_ticker(product: &str) -> Option<String> {
         // Handle CDBs: "CDB - CODE" or "CDB - CODE - BANK"
         if product.starts_with("CDB ") {
             let parts: Vec<&str> = product.split(" - ").collect();
@@ -166,55 +167,54 @@ impl MovimentacaoEntry {
         None
     }
 
-    /// Parse a movimentacao entry from a row
-    pub fn from_row(row: &[Data]) -> Result<Self> {
-        // Column indices based on header:
-        // [0] Entrada/Saída, [1] Data, [2] Movimentação, [3] Produto
-        // [4] Instituição, [5] Quantidade, [6] Preço unitário, [7] Valor da Operação
-
+    /// Parse a movimentacao entry from a row using a detected column layout
+    pub fn from_row_with_columns(row: &[Data], columns: &ColumnMap) -> Result<Self> {
         let direction = row
-            .first()
+            .get(columns.direction)
             .and_then(|d| d.get_string())
             .ok_or_else(|| anyhow!("Missing direction (Entrada/Saída)"))?
             .to_string();
 
         let date_str = row
-            .get(1)
+            .get(columns.date)
             .ok_or_else(|| anyhow!("Missing date"))?
             .to_string();
         let date = parse_date(&date_str)?;
 
         let movement_type = row
-            .get(2)
+            .get(columns.movement_type)
             .and_then(|d| d.get_string())
-            .ok_or_else(|| anyhow!("Missing movement type"))?
-            .to_string();
+            .map(movimentacao_layout::normalize_movement_type)
+            .ok_or_else(|| anyhow!("Missing movement type"))?;
 
         let product = row
-            .get(3)
+            .get(columns.product)
             .and_then(|d| d.get_string())
             .ok_or_else(|| anyhow!("Missing product"))?
             .to_string();
 
         let ticker = Self::extract_ticker(&product);
 
-        let institution = row
-            .get(4)
+        let institution = columns
+            .institution
+            .and_then(|idx| row.get(idx))
             .and_then(|d| d.get_string())
             .unwrap_or("")
             .to_string();
 
         let quantity = row
-            .get(5)
+            .get(columns.quantity)
             .and_then(|d| parse_decimal(d).ok())
             .filter(|q| *q > Decimal::ZERO);
 
         let unit_price = row
-            .get(6)
+            .get(columns.unit_price)
             .and_then(|d| parse_decimal(d).ok())
             .filter(|p| *p > Decimal::ZERO);
 
-        let operation_value = row.get(7).and_then(|d| parse_decimal(d).ok());
+        let operation_value = row
+            .get(columns.operation_value)
+            .and_then(|d| parse_decimal(d).ok());
 
         Ok(MovimentacaoEntry {
             direction,
@@ -242,6 +242,15 @@ impl MovimentacaoEntry {
         )
     }
 
+    /// Side of an exchange buy or sell, the only trades that can be day trades
+    pub fn exchange_side(&self) -> Option<TransactionType> {
+        match self.movement_type.as_str() {
+            "Compra" => Some(TransactionType::Buy),
+            "Venda" => Some(TransactionType::Sell),
+            _ => None,
+        }
+    }
+
     /// Determine if this is a bond redemption entry
     pub fn is_resgate(&self) -> bool {
         matches!(
@@ -275,7 +284,12 @@ impl MovimentacaoEntry {
                 | "Bonificação em Ativos"
                 | "Incorporação"
                 | "Atualização"
-        )
+        ) || self.subscription_event().is_some()
+    }
+
+    /// Step of a subscription (rights grant, exercise, receipt or expiry)
+    pub fn subscription_event(&self) -> Option<crate::subscriptions::SubscriptionEvent> {
+        crate::subscriptions::classify_movement(&self.movement_type, &self.direction)
     }
 
     /// Determine if this is an income event
@@ -294,11 +308,22 @@ impl MovimentacaoEntry {
                 | "Rendimento - Transferido"
                 | "Dividendo - Transferido"
                 | "Juros Sobre Capital Próprio - Transferido"
+                | "Bonificação em Dinheiro"
+                | "Leilão de Fração"
         )
     }
 
-    /// Convert to IncomeEvent
-    pub fn to_income_event(&self, asset_id: i64) -> Result<IncomeEvent> {
+    /// Cash from the auction of fractional shares left over by a bonus,
+    /// split or reverse split
+    pub fn is_fraction_auction(&self) -> bool {
+        self.movement_type == "Leilão de Fração"
+    }
+
+    /// Convert to IncomeEvent. The statement only has the net cash, so the
+    /// withholding is the default for the event and asset type (see
+    /// `tax::rules`): JCP is grossed up at 15%, bond coupons are taken as
+    /// paid.
+    pub fn to_income_event(&self, asset_id: i64, asset_type: &AssetType) -> Result<IncomeEvent> {
         // Determine event type and notes from movement_type
         let (event_type, notes) = match self.movement_type.as_str() {
             "Rendimento" | "Dividendo" => (IncomeEventType::Dividend, None),
@@ -314,16 +339,35 @@ impl MovimentacaoEntry {
             "Juros Sobre Capital Próprio - Transferido" => {
                 (IncomeEventType::Jcp, Some("Transferido".to_string()))
             }
+            // Coupons of debentures and CRI/CRA, not JCP
+            "Juros" | "PAGAMENTO DE JUROS" if *asset_type == AssetType::Bond => {
+                (IncomeEventType::Interest, None)
+            }
+            "INCORPORAÇÃO DE JUROS" if *asset_type == AssetType::Bond => {
+                (IncomeEventType::Interest, Some("Incorporação".to_string()))
+            }
             "Juros" | "PAGAMENTO DE JUROS" => (IncomeEventType::Jcp, None),
             "INCORPORAÇÃO DE JUROS" => (IncomeEventType::Jcp, Some("Incorporação".to_string())),
+            "Bonificação em Dinheiro" => (
+                IncomeEventType::Dividend,
+                Some("Bonificação em dinheiro".to_string()),
+            ),
+            // Classified against the bonus remainders when imported
+            "Leilão de Fração" => (
+                IncomeEventType::Dividend,
+                Some("Leilão de fração".to_string()),
+            ),
             _ => return Err(anyhow!("Not an income event: {}", self.movement_type)),
         };
 
-        // Get total amount - prefer operation_value, fall back to quantity * unit_price
-        let total_amount = self
+        // Get net amount - prefer operation_value, fall back to quantity * unit_price
+        let net_amount = self
             .operation_value
             .or_else(|| self.quantity.zip(self.unit_price).map(|(q, p)| q * p))
             .ok_or_else(|| anyhow!("No value for income event"))?;
+        let (total_amount, withholding_tax) =
+            income_withholding_rule(&event_type, asset_type, self.date.year(), self.date.month())
+                .gross_up(net_amount);
 
         // Calculate amount per quota if we have quantity
         let amount_per_quota = if let Some(qty) = self.quantity {
@@ -345,8 +389,9 @@ impl MovimentacaoEntry {
             event_type,
             amount_per_quota,
             total_amount,
-            withholding_tax: Decimal::ZERO, // Not available in movimentação file
-            is_quota_pre_2026: None,        // Will be determined later if needed
+            withholding_tax,
+            foreign_tax_withheld: None,
+            is_quota_pre_2026: None, // Will be determined later if needed
             source: "MOVIMENTACAO".to_string(),
             notes,
             created_at: chrono::Utc::now(),
@@ -461,12 +506,13 @@ pub fn parse_movimentacao_excel<P: AsRef<Path>>(path: P) -> Result<Vec<Movimenta
         return Err(anyhow!("Empty sheet"));
     }
 
-    // Skip header row
+    // Header row selects the column layout
+    let (_, columns) = movimentacao_layout::detect_layout(rows[0]);
     let mut entries = Vec::new();
     let mut errors = 0;
 
     for (idx, row) in rows.iter().enumerate().skip(1) {
-        match MovimentacaoEntry::from_row(row) {
+        match MovimentacaoEntry::from_row_with_columns(row, &columns) {
             Ok(entry) => entries.push(entry),
             Err(e) => {
                 debug!("Failed to parse row {}: {}", idx + 1, e);
@@ -674,6 +720,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_from_row_without_institution_column() {
+        let row = vec![
+            Data::String("Credito".to_string()),
+            Data::String("15/03/2020".to_string()),
+            Data::String("Dividendos".to_string()),
+            Data::String("ITSA4 - ITAUSA S/A".to_string()),
+            Data::Float(100.0),
+            Data::Float(0.02),
+            Data::Float(2.0),
+        ];
+        let (_, columns) = movimentacao_layout::detect_layout(&[
+            Data::String("Entrada/Saída".to_string()),
+            Data::String("Data".to_string()),
+            Data::String("Movimentação".to_string()),
+            Data::String("Produto".to_string()),
+            Data::String("Quantidade".to_string()),
+            Data::String("Preço unitário".to_string()),
+            Data::String("Valor da Operação".to_string()),
+        ]);
+        let entry = MovimentacaoEntry::from_row_with_columns(&row, &columns).unwrap();
+
+        assert_eq!(entry.movement_type, "Dividendo");
+        assert_eq!(entry.ticker.as_deref(), Some("ITSA4"));
+        assert_eq!(entry.institution, "");
+        assert_eq!(entry.quantity, Some(Decimal::from(100)));
+        assert_eq!(entry.operation_value, Some(Decimal::from(2)));
+        assert!(entry.is_income_event());
+    }
+
     #[test]
     fn test_resgate_is_trade() {
         use chrono::NaiveDate;
@@ -7,28 +7,49 @@ use tracing::{info, warn};
 
 use crate::corporate_actions;
 use crate::db;
-use crate::importers::MovimentacaoEntry;
+use crate::importers::{ItemResult, MovimentacaoEntry};
+use crate::reports::income_reconciliation;
 use serde_json::json;
 
+/// Import parsed Movimentação entries in a single database transaction
 pub fn import_movimentacao_entries(
     conn: &Connection,
     entries: Vec<MovimentacaoEntry>,
     track_state: bool,
+) -> Result<crate::importers::ImportStats> {
+    db::bulk::in_transaction(conn, |conn| import_entries(conn, entries, track_state))
+}
+
+fn import_entries(
+    conn: &Connection,
+    entries: Vec<MovimentacaoEntry>,
+    track_state: bool,
 ) -> Result<crate::importers::ImportStats> {
     let receipt_index = build_subscription_receipts_index(&entries);
     let trades: Vec<_> = entries
         .iter()
         .filter(|e| e.is_trade() || e.is_resgate())
         .collect();
+    let mut day_trades = crate::importers::day_trade::DayTrades::detect(
+        trades
+            .iter()
+            .filter_map(|e| Some((e.ticker.clone()?, e.date, e.exchange_side()?, e.quantity?))),
+    );
     let mut actions: Vec<_> = entries.iter().filter(|e| e.is_corporate_action()).collect();
-    actions.sort_by_key(|e| e.date);
+    // Exercised rights are recorded before the receipt of the same day
+    actions.sort_by_key(|e| (e.date, e.subscription_event()));
+    let mut subscriptions = crate::subscriptions::SubscriptionRecorder::default();
 
     let mut imported_trades = 0;
     let mut skipped_trades = 0;
     let mut skipped_trades_old = 0;
+    let mut day_trade_count = 0;
     let mut errors = 0;
+    let mut items = Vec::new();
     let mut max_trade_date: Option<chrono::NaiveDate> = None;
     let mut earliest_trade_date: Option<chrono::NaiveDate> = None;
+    // "Instituição" column (current layout) -> brokers.id
+    let mut brokers: HashMap<String, i64> = HashMap::new();
 
     let last_trade_date = if track_state {
         db::get_last_import_date(conn, "MOVIMENTACAO", "trades")?
@@ -37,8 +58,10 @@ pub fn import_movimentacao_entries(
     };
 
     for entry in trades {
+        let item = entry_item("trade", entry);
         if entry.ticker.is_none() {
             warn!("Skipping trade with no ticker: {:?}", entry.product);
+            items.push(item.skipped("NO_TICKER", &entry.product));
             skipped_trades += 1;
             continue;
         }
@@ -46,10 +69,17 @@ pub fn import_movimentacao_entries(
         let ticker = entry.ticker.as_ref().unwrap();
         let asset_type = db::AssetType::Unknown;
         let asset_name = extract_asset_name(&entry.product);
-        let asset_id = match db::upsert_asset(conn, ticker, &asset_type, asset_name.as_deref()) {
+        let asset_id = match db::upsert_asset_as_of(
+            conn,
+            ticker,
+            &asset_type,
+            asset_name.as_deref(),
+            Some(entry.date),
+        ) {
             Ok(id) => id,
             Err(e) => {
                 warn!("Error upserting asset {}: {}", ticker, e);
+                items.push(item.failed("ASSET_UPSERT_FAILED", e));
                 errors += 1;
                 continue;
             }
@@ -60,11 +90,13 @@ pub fn import_movimentacao_entries(
                 Ok(Some(asset)) => asset,
                 Ok(None) => {
                     warn!("Asset {} not found after upsert", ticker);
+                    items.push(item.failed("ASSET_NOT_FOUND", "asset not found after upsert"));
                     errors += 1;
                     continue;
                 }
                 Err(e) => {
                     warn!("Error loading asset {}: {}", ticker, e);
+                    items.push(item.failed("DATABASE_ERROR", e));
                     errors += 1;
                     continue;
                 }
@@ -78,6 +110,7 @@ pub fn import_movimentacao_entries(
                     "Skipping resgate for non-bond asset {} ({:?})",
                     ticker, asset.asset_type
                 );
+                items.push(item.skipped("NOT_A_BOND", "resgate of a non-bond asset"));
                 skipped_trades += 1;
                 continue;
             }
@@ -87,19 +120,38 @@ pub fn import_movimentacao_entries(
             Ok(tx) => tx,
             Err(e) => {
                 warn!("Failed to convert entry to transaction: {}", e);
+                items.push(item.failed("INVALID_ENTRY", e));
                 errors += 1;
                 continue;
             }
         };
         if let Some(last_date) = last_trade_date {
             if transaction.trade_date <= last_date {
+                items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
                 skipped_trades_old += 1;
                 continue;
             }
         }
 
-        match db::insert_transaction(conn, &transaction) {
-            Ok(_) => {
+        let parts = if entry.exchange_side().is_some() {
+            day_trades.split(ticker, transaction)
+        } else {
+            vec![transaction]
+        };
+        let transaction = &parts[0];
+        match parts
+            .iter()
+            .map(|tx| db::insert_transaction(conn, tx))
+            .collect::<Result<Vec<i64>>>()
+        {
+            Ok(tx_ids) => {
+                if let Some(broker_id) = entry_broker(conn, &mut brokers, entry)? {
+                    for tx_id in tx_ids {
+                        db::set_transaction_broker(conn, tx_id, broker_id)?;
+                    }
+                }
+                day_trade_count += parts.iter().filter(|tx| tx.is_day_trade).count();
+                items.push(item);
                 imported_trades += 1;
                 max_trade_date = Some(match max_trade_date {
                     Some(current) if current >= transaction.trade_date => current,
@@ -112,6 +164,7 @@ pub fn import_movimentacao_entries(
             }
             Err(e) => {
                 warn!("Error inserting transaction: {}", e);
+                items.push(item.failed("INSERT_FAILED", e));
                 errors += 1;
             }
         }
@@ -127,6 +180,8 @@ pub fn import_movimentacao_entries(
     let mut skipped_actions = 0;
     let mut skipped_actions_old = 0;
     let mut auto_applied_actions = 0;
+    // Fractional bonus remainders of this file, matched to fraction auctions
+    let mut bonus_remainders: HashMap<String, Vec<(chrono::NaiveDate, Decimal)>> = HashMap::new();
     let mut max_action_date: Option<chrono::NaiveDate> = None;
     let mut earliest_action_date: Option<chrono::NaiveDate> = None;
 
@@ -137,11 +192,13 @@ pub fn import_movimentacao_entries(
     };
 
     for entry in actions {
+        let item = entry_item("corporate_action", entry);
         if entry.ticker.is_none() {
             warn!(
                 "Skipping corporate action with no ticker: {:?}",
                 entry.product
             );
+            items.push(item.skipped("NO_TICKER", &entry.product));
             skipped_actions += 1;
             continue;
         }
@@ -149,15 +206,73 @@ pub fn import_movimentacao_entries(
         let ticker = entry.ticker.as_ref().unwrap();
         let asset_type = db::AssetType::Unknown;
         let asset_name = extract_asset_name(&entry.product);
-        let asset_id = match db::upsert_asset(conn, ticker, &asset_type, asset_name.as_deref()) {
+        let asset_id = match db::upsert_asset_as_of(
+            conn,
+            ticker,
+            &asset_type,
+            asset_name.as_deref(),
+            Some(entry.date),
+        ) {
             Ok(id) => id,
             Err(e) => {
                 warn!("Error upserting asset {}: {}", ticker, e);
+                items.push(item.failed("ASSET_UPSERT_FAILED", e));
                 errors += 1;
                 continue;
             }
         };
 
+        if let Some(event) = entry.subscription_event() {
+            let qty = match entry.quantity {
+                Some(qty) if qty > Decimal::ZERO => qty,
+                _ => {
+                    items.push(item.skipped("NO_QUANTITY", "no positive quantity"));
+                    skipped_actions += 1;
+                    continue;
+                }
+            };
+            if let Some(last_date) = last_action_date {
+                if entry.date <= last_date {
+                    items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
+                    skipped_actions_old += 1;
+                    continue;
+                }
+            }
+
+            let value = entry
+                .operation_value
+                .or_else(|| entry.unit_price.map(|price| price * qty));
+            match subscriptions.record(
+                conn,
+                event,
+                asset_id,
+                ticker,
+                entry.date,
+                qty,
+                value,
+                &entry.product,
+            ) {
+                Ok(_) => {
+                    items.push(item);
+                    imported_actions += 1;
+                    max_action_date = Some(match max_action_date {
+                        Some(current) if current >= entry.date => current,
+                        _ => entry.date,
+                    });
+                    earliest_action_date = Some(match earliest_action_date {
+                        Some(current) if current <= entry.date => current,
+                        _ => entry.date,
+                    });
+                }
+                Err(e) => {
+                    warn!("Error recording subscription {:?}: {}", event, e);
+                    items.push(item.failed("INSERT_FAILED", e));
+                    errors += 1;
+                }
+            }
+            continue;
+        }
+
         if entry.movement_type == "Bonificação em Ativos" || entry.movement_type == "Bonificação"
         {
             use rust_decimal::RoundingStrategy;
@@ -165,19 +280,28 @@ pub fn import_movimentacao_entries(
             let qty = match entry.quantity {
                 Some(qty) if qty > Decimal::ZERO => qty,
                 _ => {
+                    items.push(item.skipped("NO_QUANTITY", "no positive quantity"));
                     skipped_actions += 1;
                     continue;
                 }
             };
+            let integer_qty = qty.round_dp_with_strategy(0, RoundingStrategy::ToZero);
+            let fractional_qty = qty - integer_qty;
+            if fractional_qty > Decimal::ZERO {
+                bonus_remainders
+                    .entry(ticker.clone())
+                    .or_default()
+                    .push((entry.date, fractional_qty));
+            }
+
             if let Some(last_date) = last_action_date {
                 if entry.date <= last_date {
+                    items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
                     skipped_actions_old += 1;
                     continue;
                 }
             }
 
-            let integer_qty = qty.round_dp_with_strategy(0, RoundingStrategy::ToZero);
-            let fractional_qty = qty - integer_qty;
             if integer_qty > Decimal::ZERO {
                 let mut notes = format!(
                     "Bonificação em Ativos credit from movimentacao: {}",
@@ -204,6 +328,7 @@ pub fn import_movimentacao_entries(
                 };
                 match db::insert_transaction(conn, &bonus_tx) {
                     Ok(_) => {
+                        items.push(item);
                         imported_actions += 1;
                         max_action_date = Some(match max_action_date {
                             Some(current) if current >= entry.date => current,
@@ -216,10 +341,12 @@ pub fn import_movimentacao_entries(
                     }
                     Err(e) => {
                         warn!("Error inserting Bonificação em Ativos transaction: {}", e);
+                        items.push(item.failed("INSERT_FAILED", e));
                         errors += 1;
                     }
                 }
             } else {
+                items.push(item.skipped("FRACTIONAL_ONLY", "only a fractional quantity"));
                 skipped_actions += 1;
             }
             continue;
@@ -229,12 +356,14 @@ pub fn import_movimentacao_entries(
             let qty = match entry.quantity {
                 Some(qty) if qty > Decimal::ZERO => qty,
                 _ => {
+                    items.push(item.skipped("NO_QUANTITY", "no positive quantity"));
                     skipped_actions += 1;
                     continue;
                 }
             };
             if let Some(last_date) = last_action_date {
                 if entry.date <= last_date {
+                    items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
                     skipped_actions_old += 1;
                     continue;
                 }
@@ -244,6 +373,7 @@ pub fn import_movimentacao_entries(
                 Ok(a) => a,
                 Err(e) => {
                     warn!("Failed to convert entry to corporate action: {}", e);
+                    items.push(item.failed("INVALID_ENTRY", e));
                     errors += 1;
                     continue;
                 }
@@ -269,6 +399,7 @@ pub fn import_movimentacao_entries(
                 Ok(id) => id,
                 Err(e) => {
                     warn!("Error inserting corporate action: {}", e);
+                    items.push(item.failed("INSERT_FAILED", e));
                     errors += 1;
                     continue;
                 }
@@ -298,12 +429,14 @@ pub fn import_movimentacao_entries(
                     if adjusted > 0 {
                         auto_applied_actions += 1;
                     }
+                    items.push(item);
                 }
                 Err(e) => {
                     warn!(
                         "Failed to auto-apply corporate action for {} on {}: {}",
                         ticker, action.event_date, e
                     );
+                    items.push(item.failed("AUTO_APPLY_FAILED", auto_apply_message(e)));
                     errors += 1;
                 }
             }
@@ -314,12 +447,48 @@ pub fn import_movimentacao_entries(
             let qty = match entry.quantity {
                 Some(qty) if qty > Decimal::ZERO => qty,
                 _ => {
+                    items.push(item.skipped("NO_QUANTITY", "no positive quantity"));
                     skipped_actions += 1;
                     continue;
                 }
             };
 
             let receipt_match = match_subscription_receipt(&receipt_index, entry, qty);
+            if let Some(receipt_match) = &receipt_match {
+                if last_action_date.is_some_and(|last_date| entry.date <= last_date) {
+                    items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
+                    skipped_actions_old += 1;
+                    continue;
+                }
+                // Receipts recorded with their cost move into the base ticker
+                match crate::subscriptions::convert_receipts(
+                    conn,
+                    &receipt_match.tickers,
+                    asset_id,
+                    entry.date,
+                ) {
+                    Ok(0) => {}
+                    Ok(_) => {
+                        items.push(item);
+                        imported_actions += 1;
+                        max_action_date = Some(match max_action_date {
+                            Some(current) if current >= entry.date => current,
+                            _ => entry.date,
+                        });
+                        earliest_action_date = Some(match earliest_action_date {
+                            Some(current) if current <= entry.date => current,
+                            _ => entry.date,
+                        });
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!("Error converting subscription receipts: {}", e);
+                        items.push(item.failed("INSERT_FAILED", e));
+                        errors += 1;
+                        continue;
+                    }
+                }
+            }
             if let Some(receipt_match) = receipt_match {
                 let notes = format!(
                     "Subscription receipt conversion from {} ({})",
@@ -362,6 +531,10 @@ pub fn import_movimentacao_entries(
                 };
                 match db::insert_inconsistency(conn, &issue) {
                     Ok(_) => {
+                        items.push(item.skipped(
+                            "PENDING_CONFIRMATION",
+                            "recorded as an inconsistency to resolve",
+                        ));
                         skipped_actions += 1;
                         max_action_date = Some(match max_action_date {
                             Some(current) if current >= entry.date => current,
@@ -370,11 +543,70 @@ pub fn import_movimentacao_entries(
                     }
                     Err(e) => {
                         warn!("Error inserting Atualização inconsistency: {}", e);
+                        items.push(item.failed("INSERT_FAILED", e));
                         errors += 1;
                     }
                 }
             } else {
-                skipped_actions += 1;
+                if let Some(last_date) = last_action_date {
+                    if entry.date <= last_date {
+                        items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
+                        skipped_actions_old += 1;
+                        continue;
+                    }
+                }
+
+                // B3 custody adjustment: ask the user to confirm before touching quantities
+                let signed_qty = if entry.direction == "Debito" {
+                    -qty
+                } else {
+                    qty
+                };
+                let issue = db::Inconsistency {
+                    id: None,
+                    issue_type: db::InconsistencyType::PositionAdjustment,
+                    status: db::InconsistencyStatus::Open,
+                    severity: db::InconsistencySeverity::Warn,
+                    asset_id: Some(asset_id),
+                    transaction_id: None,
+                    ticker: Some(ticker.to_string()),
+                    trade_date: Some(entry.date),
+                    quantity: Some(signed_qty),
+                    source: Some("MOVIMENTACAO".to_string()),
+                    source_ref: None,
+                    missing_fields_json: None,
+                    context_json: Some(
+                        json!({
+                            "notes": "B3 position update (Atualização) pending confirmation",
+                            "movement_type": entry.movement_type,
+                            "direction": entry.direction,
+                            "product": entry.product,
+                        })
+                        .to_string(),
+                    ),
+                    resolution_action: None,
+                    resolution_json: None,
+                    created_at: None,
+                    resolved_at: None,
+                };
+                match db::insert_inconsistency(conn, &issue) {
+                    Ok(_) => {
+                        items.push(item.skipped(
+                            "PENDING_CONFIRMATION",
+                            "recorded as an inconsistency to resolve",
+                        ));
+                        skipped_actions += 1;
+                        max_action_date = Some(match max_action_date {
+                            Some(current) if current >= entry.date => current,
+                            _ => entry.date,
+                        });
+                    }
+                    Err(e) => {
+                        warn!("Error inserting Atualização inconsistency: {}", e);
+                        items.push(item.failed("INSERT_FAILED", e));
+                        errors += 1;
+                    }
+                }
             }
             continue;
         }
@@ -383,6 +615,7 @@ pub fn import_movimentacao_entries(
             Ok(a) => a,
             Err(e) => {
                 warn!("Failed to convert entry to corporate action: {}", e);
+                items.push(item.failed("INVALID_ENTRY", e));
                 errors += 1;
                 continue;
             }
@@ -390,6 +623,7 @@ pub fn import_movimentacao_entries(
 
         if let Some(last_date) = last_action_date {
             if action.event_date <= last_date {
+                items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
                 skipped_actions_old += 1;
                 continue;
             }
@@ -399,6 +633,7 @@ pub fn import_movimentacao_entries(
             Ok(id) => id,
             Err(e) => {
                 warn!("Error inserting corporate action: {}", e);
+                items.push(item.failed("INSERT_FAILED", e));
                 errors += 1;
                 continue;
             }
@@ -425,16 +660,19 @@ pub fn import_movimentacao_entries(
                     if adjusted > 0 {
                         auto_applied_actions += 1;
                     }
+                    items.push(item);
                 }
                 Err(e) => {
                     warn!(
                         "Failed to auto-apply corporate action for {} on {}: {}",
                         ticker, action.event_date, e
                     );
+                    items.push(item.failed("AUTO_APPLY_FAILED", auto_apply_message(e)));
                     errors += 1;
                 }
             }
         } else {
+            items.push(item);
             info!(
                 "Skipping auto-apply for {} on {} (ratio 1:1)",
                 ticker, action.event_date
@@ -464,8 +702,10 @@ pub fn import_movimentacao_entries(
     };
 
     for entry in income_events {
+        let item = entry_item("income", entry);
         if entry.ticker.is_none() {
             warn!("Skipping income event with no ticker: {:?}", entry.product);
+            items.push(item.skipped("NO_TICKER", &entry.product));
             skipped_income += 1;
             continue;
         }
@@ -473,10 +713,17 @@ pub fn import_movimentacao_entries(
         let ticker = entry.ticker.as_ref().unwrap();
         let asset_type = db::AssetType::Unknown;
         let asset_name = extract_asset_name(&entry.product);
-        let asset_id = match db::upsert_asset(conn, ticker, &asset_type, asset_name.as_deref()) {
+        let asset_id = match db::upsert_asset_as_of(
+            conn,
+            ticker,
+            &asset_type,
+            asset_name.as_deref(),
+            Some(entry.date),
+        ) {
             Ok(id) => id,
             Err(e) => {
                 warn!("Error upserting asset {} for income event: {}", ticker, e);
+                items.push(item.failed("ASSET_UPSERT_FAILED", e));
                 errors += 1;
                 continue;
             }
@@ -485,42 +732,100 @@ pub fn import_movimentacao_entries(
         // Skip if older than last import date
         if let Some(last_date) = last_income_date {
             if entry.date <= last_date {
+                items.push(item.skipped("BEFORE_LAST_IMPORT", BEFORE_LAST_IMPORT));
                 skipped_income_old += 1;
                 continue;
             }
         }
 
-        let income_event = match entry.to_income_event(asset_id) {
+        let resolved_type = match db::get_asset_by_ticker(conn, ticker) {
+            Ok(asset) => asset.map_or(db::AssetType::Unknown, |a| a.asset_type),
+            Err(e) => {
+                warn!("Error looking up asset {} for income event: {}", ticker, e);
+                items.push(item.failed("DATABASE_ERROR", e));
+                errors += 1;
+                continue;
+            }
+        };
+        let mut income_event = match entry.to_income_event(asset_id, &resolved_type) {
             Ok(ie) => ie,
             Err(e) => {
                 warn!("Failed to convert entry to income event: {}", e);
+                items.push(item.failed("INVALID_ENTRY", e));
                 errors += 1;
                 continue;
             }
         };
+        if entry.is_fraction_auction() {
+            let in_file = bonus_remainders.get(ticker).map(Vec::as_slice);
+            if let Err(e) =
+                classify_fraction_auction(conn, asset_id, in_file.unwrap_or(&[]), &mut income_event)
+            {
+                warn!("Error looking up bonus remainders for {}: {}", ticker, e);
+                items.push(item.failed("DATABASE_ERROR", e));
+                errors += 1;
+                continue;
+            }
+        }
 
-        // Check for duplicate (same asset, date, type, amount)
+        // Cash that reached the account; an income event recorded beforehand
+        // (announced or added by hand) is what it pays
+        let credit_id = if entry.direction == "Debito" {
+            None
+        } else {
+            income_reconciliation::record_credit(
+                conn,
+                &income_reconciliation::NewCredit {
+                    asset_id,
+                    date: income_event.event_date,
+                    movement_type: &entry.movement_type,
+                    event_type: &income_event.event_type,
+                    amount: income_event.total_amount - income_event.withholding_tax,
+                    broker_id: entry_broker(conn, &mut brokers, entry)?,
+                },
+            )?
+        };
+        if let Some(credit_id) = credit_id {
+            if let Some(event_id) = income_reconciliation::matching_event(conn, credit_id)? {
+                income_reconciliation::link(conn, credit_id, event_id)?;
+                items.push(item.skipped("CREDITED", "credits an income event already recorded"));
+                skipped_income += 1;
+                continue;
+            }
+        }
+
+        // Check for duplicate (same asset, date, type, amount); the net cash
+        // also matches events recorded before the default IRRF was applied
         match db::income_event_exists(
             conn,
             asset_id,
             income_event.event_date,
             &income_event.event_type,
-            income_event.total_amount,
+            income_event.total_amount - income_event.withholding_tax,
         ) {
             Ok(true) => {
+                items.push(item.skipped("DUPLICATE", "income event already recorded"));
                 skipped_income += 1;
                 continue;
             }
             Ok(false) => {}
             Err(e) => {
                 warn!("Error checking for duplicate income event: {}", e);
+                items.push(item.failed("DATABASE_ERROR", e));
                 errors += 1;
                 continue;
             }
         }
 
         match db::insert_income_event(conn, &income_event) {
-            Ok(_) => {
+            Ok(event_id) => {
+                if let Some(broker_id) = entry_broker(conn, &mut brokers, entry)? {
+                    db::set_income_event_broker(conn, event_id, broker_id)?;
+                }
+                if let Some(credit_id) = credit_id {
+                    income_reconciliation::link(conn, credit_id, event_id)?;
+                }
+                items.push(item);
                 imported_income += 1;
                 max_income_date = Some(match max_income_date {
                     Some(current) if current >= income_event.event_date => current,
@@ -533,6 +838,7 @@ pub fn import_movimentacao_entries(
             }
             Err(e) => {
                 warn!("Error inserting income event: {}", e);
+                items.push(item.failed("INSERT_FAILED", e));
                 errors += 1;
             }
         }
@@ -543,6 +849,7 @@ pub fn import_movimentacao_entries(
             db::set_last_import_date(conn, "MOVIMENTACAO", "income", last_date)?;
         }
     }
+    income_reconciliation::sync_inconsistencies(conn)?;
 
     // Determine overall min/max dates across sections
     let earliest = [
@@ -571,12 +878,119 @@ pub fn import_movimentacao_entries(
         imported_income,
         skipped_income,
         skipped_income_old,
+        enriched_trades: 0,
+        enriched_income: 0,
+        day_trades: day_trade_count,
+        option_exercises: 0,
         errors,
         earliest,
         latest,
+        items,
     })
 }
 
+/// How long after a bonus its fractional remainder may still be auctioned
+const FRACTION_AUCTION_WINDOW_DAYS: i64 = 180;
+
+/// Cash from a fraction auction is income when it pays for a bonus remainder
+/// (bonus shares are recorded at zero cost, so all of it is gain); otherwise
+/// the fraction came from a split or reverse split and had a cost basis, so
+/// the cash is booked as a capital return
+fn classify_fraction_auction(
+    conn: &Connection,
+    asset_id: i64,
+    in_file: &[(chrono::NaiveDate, Decimal)],
+    event: &mut db::IncomeEvent,
+) -> Result<()> {
+    let since = event.event_date - chrono::Duration::days(FRACTION_AUCTION_WINDOW_DAYS);
+    let in_window = |date: &chrono::NaiveDate| *date >= since && *date <= event.event_date;
+    let remainder = match in_file
+        .iter()
+        .filter(|(date, _)| in_window(date))
+        .max_by_key(|(date, _)| *date)
+    {
+        Some(found) => Some(*found),
+        None => recorded_bonus_remainder(conn, asset_id, since, event.event_date)?,
+    };
+
+    match remainder {
+        Some((date, quantity)) => {
+            event.notes = Some(format!(
+                "Leilão de fração: remainder of {} from the {} bonus",
+                quantity,
+                date.format("%d/%m/%Y")
+            ));
+        }
+        None => {
+            event.event_type = db::IncomeEventType::Amortization;
+            event.notes = Some(
+                "Leilão de fração: no bonus remainder found, booked as capital return".to_string(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Latest remainder noted on a bonus credit of an earlier import
+fn recorded_bonus_remainder(
+    conn: &Connection,
+    asset_id: i64,
+    from: chrono::NaiveDate,
+    to: chrono::NaiveDate,
+) -> Result<Option<(chrono::NaiveDate, Decimal)>> {
+    let mut stmt = conn.prepare(
+        "SELECT trade_date, notes FROM transactions
+         WHERE asset_id = ?1 AND trade_date >= ?2 AND trade_date <= ?3
+           AND notes LIKE '%fractional remainder: %'
+         ORDER BY trade_date DESC",
+    )?;
+    let mut rows = stmt.query(rusqlite::params![asset_id, from, to])?;
+    while let Some(row) = rows.next()? {
+        let notes: String = row.get(1)?;
+        let quantity = notes
+            .rsplit("fractional remainder: ")
+            .next()
+            .and_then(|q| q.trim().parse::<Decimal>().ok());
+        if let Some(quantity) = quantity {
+            return Ok(Some((row.get(0)?, quantity)));
+        }
+    }
+    Ok(None)
+}
+
+const BEFORE_LAST_IMPORT: &str = "dated on or before the last import of this section";
+
+fn entry_item(kind: &'static str, entry: &MovimentacaoEntry) -> ItemResult {
+    ItemResult::new(
+        kind,
+        entry.ticker.as_deref(),
+        Some(entry.date),
+        Some(&entry.movement_type),
+    )
+}
+
+/// Broker named in the entry's "Instituição" column, registered on first use
+fn entry_broker(
+    conn: &Connection,
+    brokers: &mut HashMap<String, i64>,
+    entry: &MovimentacaoEntry,
+) -> Result<Option<i64>> {
+    if entry.institution.trim().is_empty() {
+        return Ok(None);
+    }
+    if let Some(id) = brokers.get(&entry.institution) {
+        return Ok(Some(*id));
+    }
+    let id = db::upsert_broker(conn, &entry.institution)?;
+    brokers.insert(entry.institution.clone(), id);
+    Ok(Some(id))
+}
+
+/// The action itself was stored; only adjusting the positions failed
+fn auto_apply_message(e: anyhow::Error) -> String {
+    format!("stored, but applying it to positions failed: {}", e)
+}
+
 #[derive(Clone)]
 struct ReceiptEntry {
     date: chrono::NaiveDate,
@@ -870,4 +1284,125 @@ mod tests {
 
         assert!(match_result.is_none());
     }
+
+    #[test]
+    fn books_fraction_auction_as_income_only_for_bonus_remainders() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+
+        let mut bonus = entry(
+            (2024, 5, 2),
+            "Bonificação em Ativos",
+            "ITSA4 - ITAUSA S.A.",
+            "ITSA4",
+            "Credito",
+            0,
+        );
+        bonus.quantity = Some(Decimal::new(104, 1));
+        let auction = |ticker: &str, product: &str| {
+            let mut e = entry(
+                (2024, 6, 10),
+                "Leilão de Fração",
+                product,
+                ticker,
+                "Credito",
+                0,
+            );
+            e.quantity = None;
+            e.operation_value = Some(Decimal::new(321, 2));
+            e
+        };
+        let entries = vec![
+            bonus,
+            auction("ITSA4", "ITSA4 - ITAUSA S.A."),
+            auction("MGLU3", "MGLU3 - MAGAZINE LUIZA S.A."),
+        ];
+
+        let stats = import_movimentacao_entries(&conn, entries, false).unwrap();
+        assert_eq!((stats.imported_actions, stats.imported_income), (1, 2));
+
+        let events = db::get_income_events_with_assets(&conn, None, None, None).unwrap();
+        let by_ticker = |ticker: &str| {
+            events
+                .iter()
+                .find(|(_, a)| a.ticker == ticker)
+                .map(|(e, _)| e.clone())
+                .unwrap()
+        };
+        let itsa = by_ticker("ITSA4");
+        assert_eq!(itsa.event_type, db::IncomeEventType::Dividend);
+        assert!(itsa.notes.unwrap().contains("remainder of 0.4"));
+        assert_eq!(
+            by_ticker("MGLU3").event_type,
+            db::IncomeEventType::Amortization
+        );
+        // A later file only has the auction: the remainder noted on the
+        // imported bonus credit is found in the database
+        let mut later = auction("ITSA4", "ITSA4 - ITAUSA S.A.");
+        later.date = NaiveDate::from_ymd_opt(2024, 7, 1).unwrap();
+        import_movimentacao_entries(&conn, vec![later], false).unwrap();
+        let events = db::get_income_events_with_assets(&conn, None, None, None).unwrap();
+        assert!(events
+            .iter()
+            .filter(|(_, a)| a.ticker == "ITSA4")
+            .all(|(e, _)| e.event_type == db::IncomeEventType::Dividend));
+    }
+
+    #[test]
+    fn grosses_up_jcp_and_keeps_bond_interest_as_paid() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('VALE18', 'BOND')",
+            [],
+        )
+        .unwrap();
+
+        let mut jcp = entry(
+            (2024, 5, 2),
+            "Juros Sobre Capital Próprio",
+            "ITSA4 - ITAUSA S.A.",
+            "ITSA4",
+            "Credito",
+            100,
+        );
+        jcp.operation_value = Some(Decimal::from(85));
+        let mut coupon = entry(
+            (2024, 5, 15),
+            "PAGAMENTO DE JUROS",
+            "VALE18 - VALE S.A.",
+            "VALE18",
+            "Credito",
+            10,
+        );
+        coupon.operation_value = Some(Decimal::from(50));
+
+        let stats = import_movimentacao_entries(&conn, vec![jcp.clone(), coupon], false).unwrap();
+        assert_eq!(stats.imported_income, 2);
+
+        let events = db::get_income_events_with_assets(&conn, None, None, None).unwrap();
+        let by_ticker = |ticker: &str| {
+            events
+                .iter()
+                .find(|(_, a)| a.ticker == ticker)
+                .map(|(e, _)| e.clone())
+                .unwrap()
+        };
+        // The statement shows JCP net of the 15% IRRF
+        let itsa = by_ticker("ITSA4");
+        assert_eq!(itsa.event_type, db::IncomeEventType::Jcp);
+        assert_eq!(itsa.total_amount, Decimal::from(100));
+        assert_eq!(itsa.withholding_tax, Decimal::from(15));
+        assert_eq!(itsa.amount_per_quota, Decimal::ONE);
+        let vale = by_ticker("VALE18");
+        assert_eq!(vale.event_type, db::IncomeEventType::Interest);
+        assert_eq!(vale.total_amount, Decimal::from(50));
+        assert_eq!(vale.withholding_tax, Decimal::ZERO);
+
+        // Importing the file again matches the net cash of the grossed-up event
+        let stats = import_movimentacao_entries(&conn, vec![jcp], false).unwrap();
+        assert_eq!(stats.imported_income, 0);
+    }
 }
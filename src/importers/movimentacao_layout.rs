@@ -0,0 +1,259 @@
+//! Movimentação layout versions
+//!
+//! B3 has changed the Movimentação column set over the years. Each known
+//! header set maps to a [`ColumnMap`] so rows from older exports land in the
+//! same fields as current ones. Older movement type names are normalized to
+//! the current spelling before classification.
+
+use calamine::{Data, DataType};
+use tracing::{info, warn};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Known Movimentação layouts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutVersion {
+    /// Current export (2022+), with "Instituição"
+    Current,
+    /// 2020–2021 exports without the "Instituição" column
+    NoInstitution,
+    /// Early exports using "Crédito/Débito", "Tipo de Movimentação" and "Preço"/"Valor"
+    Legacy,
+}
+
+impl LayoutVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LayoutVersion::Current => "current",
+            LayoutVersion::NoInstitution => "no-institution",
+            LayoutVersion::Legacy => "legacy",
+        }
+    }
+}
+
+/// Column indices for each Movimentação field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnMap {
+    pub direction: usize,
+    pub date: usize,
+    pub movement_type: usize,
+    pub product: usize,
+    pub institution: Option<usize>,
+    pub quantity: usize,
+    pub unit_price: usize,
+    pub operation_value: usize,
+}
+
+impl ColumnMap {
+    pub const CURRENT: ColumnMap = ColumnMap {
+        direction: 0,
+        date: 1,
+        movement_type: 2,
+        product: 3,
+        institution: Some(4),
+        quantity: 5,
+        unit_price: 6,
+        operation_value: 7,
+    };
+
+    const NO_INSTITUTION: ColumnMap = ColumnMap {
+        direction: 0,
+        date: 1,
+        movement_type: 2,
+        product: 3,
+        institution: None,
+        quantity: 4,
+        unit_price: 5,
+        operation_value: 6,
+    };
+}
+
+/// Normalized header sets for each known layout
+const LAYOUTS: &[(LayoutVersion, &[&str], ColumnMap)] = &[
+    (
+        LayoutVersion::Current,
+        &[
+            "entrada/saida",
+            "data",
+            "movimentacao",
+            "produto",
+            "instituicao",
+            "quantidade",
+            "preco unitario",
+            "valor da operacao",
+        ],
+        ColumnMap::CURRENT,
+    ),
+    (
+        LayoutVersion::NoInstitution,
+        &[
+            "entrada/saida",
+            "data",
+            "movimentacao",
+            "produto",
+            "quantidade",
+            "preco unitario",
+            "valor da operacao",
+        ],
+        ColumnMap::NO_INSTITUTION,
+    ),
+    (
+        LayoutVersion::Legacy,
+        &[
+            "credito/debito",
+            "data",
+            "tipo de movimentacao",
+            "produto",
+            "quantidade",
+            "preco",
+            "valor",
+        ],
+        ColumnMap::NO_INSTITUTION,
+    ),
+];
+
+/// Older movement type names and their current equivalents
+const MOVEMENT_TYPE_ALIASES: &[(&str, &str)] = &[
+    ("Juros sobre Capital Próprio", "Juros Sobre Capital Próprio"),
+    ("JUROS SOBRE CAPITAL PROPRIO", "Juros Sobre Capital Próprio"),
+    ("Dividendos", "Dividendo"),
+    ("DIVIDENDO", "Dividendo"),
+    ("Rendimentos", "Rendimento"),
+    ("RENDIMENTO", "Rendimento"),
+    ("Desdobramento", "Desdobro"),
+    ("DESDOBRAMENTO", "Desdobro"),
+    ("Agrupamento", "Grupamento"),
+    ("GRUPAMENTO", "Grupamento"),
+    ("Bonificação em Ações", "Bonificação em Ativos"),
+    ("BONIFICACAO EM ATIVOS", "Bonificação em Ativos"),
+    ("Liquidação de Termo", "Liquidação Termo"),
+];
+
+fn normalize_header(cell: &str) -> String {
+    cell.trim()
+        .to_lowercase()
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Detect the layout from a header row
+///
+/// Unknown header sets fall back to the current layout (the historical
+/// behavior) with a warning, so new cosmetic changes don't block imports.
+pub fn detect_layout(header: &[Data]) -> (LayoutVersion, ColumnMap) {
+    let mut headers: Vec<String> = header
+        .iter()
+        .map(|cell| cell.get_string().map(normalize_header).unwrap_or_default())
+        .collect();
+    while headers.last().is_some_and(|h| h.is_empty()) {
+        headers.pop();
+    }
+
+    for (version, expected, map) in LAYOUTS {
+        if headers.len() == expected.len()
+            && headers.iter().zip(expected.iter()).all(|(h, e)| h == e)
+        {
+            info!("Detected Movimentação layout: {}", version.as_str());
+            return (*version, *map);
+        }
+    }
+
+    warn!(
+        "Unrecognized Movimentação header {:?}; assuming current layout",
+        headers
+    );
+    (LayoutVersion::Current, ColumnMap::CURRENT)
+}
+
+/// Map an older movement type name to its current spelling
+pub fn normalize_movement_type(raw: &str) -> String {
+    let trimmed = raw.trim();
+    MOVEMENT_TYPE_ALIASES
+        .iter()
+        .find(|(old, _)| *old == trimmed)
+        .map(|(_, current)| current.to_string())
+        .unwrap_or_else(|| trimmed.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(cells: &[&str]) -> Vec<Data> {
+        cells.iter().map(|c| Data::String(c.to_string())).collect()
+    }
+
+    #[test]
+    fn test_detect_current_layout() {
+        let row = header(&[
+            "Entrada/Saída",
+            "Data",
+            "Movimentação",
+            "Produto",
+            "Instituição",
+            "Quantidade",
+            "Preço unitário",
+            "Valor da Operação",
+        ]);
+        assert_eq!(
+            detect_layout(&row),
+            (LayoutVersion::Current, ColumnMap::CURRENT)
+        );
+    }
+
+    #[test]
+    fn test_detect_no_institution_layout() {
+        let mut row = header(&[
+            "Entrada/Saída",
+            "Data",
+            "Movimentação",
+            "Produto",
+            "Quantidade",
+            "Preço unitário",
+            "Valor da Operação",
+        ]);
+        row.push(Data::Empty);
+        let (version, map) = detect_layout(&row);
+        assert_eq!(version, LayoutVersion::NoInstitution);
+        assert_eq!(map.institution, None);
+        assert_eq!(map.operation_value, 6);
+    }
+
+    #[test]
+    fn test_detect_legacy_layout() {
+        let row = header(&[
+            "Crédito/Débito",
+            "Data",
+            "Tipo de Movimentação",
+            "Produto",
+            "Quantidade",
+            "Preço",
+            "Valor",
+        ]);
+        let (version, map) = detect_layout(&row);
+        assert_eq!(version, LayoutVersion::Legacy);
+        assert_eq!(map.quantity, 4);
+    }
+
+    #[test]
+    fn test_unknown_layout_falls_back_to_current() {
+        let row = header(&["Foo", "Bar"]);
+        assert_eq!(
+            detect_layout(&row),
+            (LayoutVersion::Current, ColumnMap::CURRENT)
+        );
+    }
+
+    #[test]
+    fn test_normalize_movement_type() {
+        assert_eq!(
+            normalize_movement_type("Juros sobre Capital Próprio"),
+            "Juros Sobre Capital Próprio"
+        );
+        assert_eq!(normalize_movement_type("Desdobramento"), "Desdobro");
+        assert_eq!(normalize_movement_type(" Compra "), "Compra");
+    }
+}
@@ -0,0 +1,501 @@
+//! Brokerage notes (notas de corretagem) in the SINACOR layout.
+//!
+//! Most brokers print their Bovespa notes from SINACOR: a header with the
+//! note number and trading date, one "1-BOVESPA" line per trade and a
+//! "Resumo Financeiro" block with the clearing and exchange fees, brokerage,
+//! ISS and the IRRF withheld on sales ("dedo-duro"). The text comes from
+//! `pdftotext -layout`, which keeps those columns on one line.
+//!
+//! Fees are spread over the note's trades in proportion to their value, so
+//! each buy carries its share in the cost basis and each sale in its result.
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tracing::info;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+use crate::db::TransactionType;
+
+/// One "1-BOVESPA" line of a note
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct NotaTrade {
+    pub transaction_type: TransactionType,
+    /// "VISTA", "FRACIONARIO", "OPCAO DE COMPRA", ...
+    pub market: String,
+    /// "Especificação do título" as printed, e.g. "PETROBRAS PN N2"
+    pub specification: String,
+    /// Ticker when the specification carries one (FIIs, options, some brokers)
+    pub ticker: Option<String>,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub value: Decimal,
+    /// Marked "D" in the Obs. column
+    pub is_day_trade: bool,
+    /// Share of the note's fees, by trade value
+    pub fees: Decimal,
+}
+
+/// A brokerage note with its trades and the fees of its financial summary
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct NotaCorretagem {
+    pub number: String,
+    pub trade_date: Option<NaiveDate>,
+    pub settlement_date: Option<NaiveDate>,
+    pub broker: Option<String>,
+    pub trades: Vec<NotaTrade>,
+    pub settlement_fee: Decimal,
+    pub registration_fee: Decimal,
+    pub emolumentos: Decimal,
+    pub brokerage: Decimal,
+    pub iss: Decimal,
+    /// Termo/opções, A.N.A., execução, custódia and "outras"
+    pub other_fees: Decimal,
+    /// IRRF withheld on swing-trade sales (0,005%)
+    pub irrf: Decimal,
+    /// IRRF withheld on day-trade gains (1%)
+    pub irrf_day_trade: Decimal,
+    /// "Líquido para" amount; positive when credited to the investor
+    pub net_amount: Option<Decimal>,
+}
+
+impl NotaCorretagem {
+    /// Fees added to buys and deducted from sales (IRRF excluded: it is an
+    /// advance of the capital gains tax, not a cost)
+    pub fn total_fees(&self) -> Decimal {
+        self.settlement_fee
+            + self.registration_fee
+            + self.emolumentos
+            + self.brokerage
+            + self.iss
+            + self.other_fees
+    }
+
+    /// Spread the note's fees over its trades by value, the rounding
+    /// remainder going to the last trade
+    fn allocate_fees(&mut self) {
+        let total_fees = self.total_fees();
+        let total_value: Decimal = self.trades.iter().map(|t| t.value).sum();
+        if total_value.is_zero() {
+            return;
+        }
+        let mut allocated = Decimal::ZERO;
+        let count = self.trades.len();
+        for (i, trade) in self.trades.iter_mut().enumerate() {
+            trade.fees = if i + 1 == count {
+                total_fees - allocated
+            } else {
+                (total_fees * trade.value / total_value).round_dp(2)
+            };
+            allocated += trade.fees;
+        }
+    }
+}
+
+/// Parse every note in a PDF (a file often holds a month of notes)
+pub fn parse_nota_corretagem_pdf<P: AsRef<Path>>(path: P) -> Result<Vec<NotaCorretagem>> {
+    let path = path.as_ref();
+    let text = super::inspect::extract_pdf_text(path, None)?;
+    info!(
+        "Extracted {} page(s) from {:?} with {}",
+        text.pages.len(),
+        path,
+        text.extractor
+    );
+    let pages: Vec<String> = text.pages.into_iter().map(|(_, text)| text).collect();
+    let notes = parse_nota_pages(&pages);
+    if notes.is_empty() {
+        return Err(anyhow!(
+            "No SINACOR brokerage note found in {:?} (expected 'Nr. nota' and '1-BOVESPA' lines)",
+            path
+        ));
+    }
+    Ok(notes)
+}
+
+/// Parse the text of each PDF page; pages of the same note (same number
+/// and date) are merged, the summary coming from the page that prints it
+pub fn parse_nota_pages(pages: &[String]) -> Vec<NotaCorretagem> {
+    let mut notes: Vec<NotaCorretagem> = Vec::new();
+    for page in pages {
+        let Some(parsed) = parse_page(page) else {
+            continue;
+        };
+        match notes
+            .iter_mut()
+            .find(|n| n.number == parsed.number && n.trade_date == parsed.trade_date)
+        {
+            Some(existing) => merge_page(existing, parsed),
+            None => notes.push(parsed),
+        }
+    }
+    for note in &mut notes {
+        note.allocate_fees();
+    }
+    notes
+}
+
+fn merge_page(note: &mut NotaCorretagem, page: NotaCorretagem) {
+    note.trades.extend(page.trades);
+    note.broker = note.broker.take().or(page.broker);
+    note.settlement_date = page.settlement_date.or(note.settlement_date);
+    note.net_amount = page.net_amount.or(note.net_amount);
+    let keep = |current: &mut Decimal, value: Decimal| {
+        if !value.is_zero() {
+            *current = value;
+        }
+    };
+    keep(&mut note.settlement_fee, page.settlement_fee);
+    keep(&mut note.registration_fee, page.registration_fee);
+    keep(&mut note.emolumentos, page.emolumentos);
+    keep(&mut note.brokerage, page.brokerage);
+    keep(&mut note.iss, page.iss);
+    keep(&mut note.other_fees, page.other_fees);
+    keep(&mut note.irrf, page.irrf);
+    keep(&mut note.irrf_day_trade, page.irrf_day_trade);
+}
+
+fn money_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"-?\b\d[\d.]*,\d{2}\b").unwrap())
+}
+
+fn date_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b(\d{2}/\d{2}/\d{4})\b").unwrap())
+}
+
+fn trade_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r"(?i)^\s*(?:\d-)?BOVESPA\s+([CV])\s+(.+?)\s+(\d[\d.]*)\s+(\d[\d.]*,\d+)\s+(\d[\d.]*,\d{2})\s+([DC])\s*$",
+        )
+        .unwrap()
+    })
+}
+
+/// Lowercase without accents, for label matching
+fn fold(text: &str) -> String {
+    text.to_lowercase()
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .collect()
+}
+
+fn parse_brl(raw: &str) -> Option<Decimal> {
+    Decimal::from_str(&raw.replace('.', "").replace(',', ".")).ok()
+}
+
+fn parse_date(raw: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(raw, "%d/%m/%Y").ok()
+}
+
+fn parse_page(page: &str) -> Option<NotaCorretagem> {
+    let lines: Vec<&str> = page.lines().collect();
+    let folded: Vec<String> = lines.iter().map(|l| fold(l)).collect();
+
+    // "Nr. nota   Folha   Data pregão" followed by the values
+    let header = folded
+        .iter()
+        .position(|l| l.contains("nr. nota") && l.contains("data pregao"))?;
+    let (values_idx, values) = lines
+        .iter()
+        .enumerate()
+        .skip(header + 1)
+        .take(4)
+        .find(|(_, l)| date_re().is_match(l))?;
+    let trade_date = date_re().captures(values).and_then(|c| parse_date(&c[1]));
+    let number = values
+        .split_whitespace()
+        .next()
+        .map(|n| n.replace('.', ""))
+        .filter(|n| n.chars().all(|c| c.is_ascii_digit()))?;
+
+    let mut note = NotaCorretagem {
+        number,
+        trade_date,
+        broker: find_broker(&lines[values_idx + 1..]),
+        ..Default::default()
+    };
+
+    for (line, folded_line) in lines.iter().zip(&folded) {
+        if let Some(trade) = parse_trade(line) {
+            note.trades.push(trade);
+            continue;
+        }
+        parse_summary_line(&mut note, folded_line);
+    }
+    Some(note)
+}
+
+/// The broker's name is printed in the first lines under the header
+fn find_broker(lines: &[&str]) -> Option<String> {
+    const MARKERS: &[&str] = &["CCTVM", "DTVM", "CTVM", "CORRETORA", "S/A", "S.A"];
+    lines.iter().take(8).find_map(|line| {
+        let first_column = line.trim().split("  ").next()?.trim();
+        let upper = first_column.to_uppercase();
+        MARKERS
+            .iter()
+            .any(|m| upper.contains(m))
+            .then(|| first_column.to_string())
+    })
+}
+
+fn parse_trade(line: &str) -> Option<NotaTrade> {
+    const MARKETS: &[&str] = &[
+        "OPCAO DE COMPRA",
+        "OPCAO DE VENDA",
+        "EXERC OPC COMPRA",
+        "EXERC OPC VENDA",
+        "FRACIONARIO",
+        "VISTA",
+        "TERMO",
+        "LEILAO",
+    ];
+    const OBS_CODES: &str = "#DFBATIHXPYL";
+
+    let caps = trade_re().captures(line)?;
+    let transaction_type = if caps[1].eq_ignore_ascii_case("C") {
+        TransactionType::Buy
+    } else {
+        TransactionType::Sell
+    };
+
+    let middle = caps[2].trim();
+    let folded = fold(middle).to_uppercase();
+    let market = MARKETS.iter().find(|m| folded.starts_with(*m))?;
+    let mut tokens: Vec<&str> = middle
+        .split_whitespace()
+        .skip(market.split_whitespace().count())
+        .collect();
+    // Options carry a "Prazo" (expiry month) column before the title
+    if tokens.first().is_some_and(|t| {
+        t.len() == 5 && t.as_bytes()[2] == b'/' && t.replace('/', "").parse::<u32>().is_ok()
+    }) {
+        tokens.remove(0);
+    }
+    let is_day_trade = match tokens.last() {
+        Some(obs)
+            if tokens.len() > 1 && obs.len() <= 2 && obs.chars().all(|c| OBS_CODES.contains(c)) =>
+        {
+            let day_trade = obs.contains('D');
+            tokens.pop();
+            day_trade
+        }
+        _ => false,
+    };
+    let specification = tokens.join(" ");
+    if specification.is_empty() {
+        return None;
+    }
+
+    let is_option = market.starts_with("OPCAO");
+    Some(NotaTrade {
+        transaction_type,
+        market: market.to_string(),
+        ticker: ticker_in_specification(&specification, is_option),
+        specification,
+        quantity: parse_brl(&caps[3])?,
+        price: parse_brl(&caps[4])?,
+        value: parse_brl(&caps[5])?,
+        is_day_trade,
+        fees: Decimal::ZERO,
+    })
+}
+
+/// A ticker printed within the title ("FII HGLG HGLG11 CI", "PETRC400 PN"),
+/// with the fractional "F" suffix removed
+fn ticker_in_specification(specification: &str, is_option: bool) -> Option<String> {
+    static STOCK: OnceLock<Regex> = OnceLock::new();
+    static OPTION: OnceLock<Regex> = OnceLock::new();
+    let re = if is_option {
+        OPTION.get_or_init(|| Regex::new(r"^[A-Z]{4}[A-X]\d{1,3}[A-Z]?$").unwrap())
+    } else {
+        STOCK.get_or_init(|| Regex::new(r"^[A-Z]{4}\d{1,2}F?$").unwrap())
+    };
+    specification
+        .split_whitespace()
+        .find(|t| re.is_match(t))
+        .map(|t| {
+            if is_option {
+                t.to_string()
+            } else {
+                t.trim_end_matches('F').to_string()
+            }
+        })
+}
+
+/// Fill fees, IRRF and the net amount from a "Resumo Financeiro" line; with
+/// `-layout` a line may also carry a "Resumo dos Negócios" column on its left
+fn parse_summary_line(note: &mut NotaCorretagem, line: &str) {
+    let amounts_after = |label: &str| -> Option<Vec<Decimal>> {
+        let idx = line.find(label)?;
+        Some(
+            money_re()
+                .find_iter(&line[idx + label.len()..])
+                .filter_map(|m| parse_brl(m.as_str()))
+                .map(|v| v.abs())
+                .collect(),
+        )
+    };
+    let first_after = |label: &str| amounts_after(label).and_then(|a| a.first().copied());
+
+    if let Some(idx) = line.find("liquido para") {
+        let rest = &line[idx..];
+        note.settlement_date = date_re().captures(rest).and_then(|c| parse_date(&c[1]));
+        if let Some(m) = money_re().find_iter(rest).last() {
+            let value = parse_brl(m.as_str()).map(|v| v.abs());
+            let debit = rest[m.end()..].trim_start().starts_with('d');
+            note.net_amount = value.map(|v| if debit { -v } else { v });
+        }
+        return;
+    }
+    if line.contains("i.r.r.f.") || line.contains("irrf") {
+        // "I.R.R.F. s/ operações, base R$ 3.850,00   0,19"
+        let label = if line.contains("i.r.r.f.") {
+            "i.r.r.f."
+        } else {
+            "irrf"
+        };
+        let amounts = amounts_after(label).unwrap_or_default();
+        let withheld = if line.contains("base") {
+            amounts.get(1).copied()
+        } else {
+            amounts.first().copied()
+        };
+        if let Some(withheld) = withheld {
+            if line.contains("day trade") {
+                note.irrf_day_trade = withheld;
+            } else {
+                note.irrf = withheld;
+            }
+        }
+        return;
+    }
+
+    type Field = fn(&mut NotaCorretagem) -> &mut Decimal;
+    let fields: [(&str, Field); 7] = [
+        ("taxa de liquidacao", |n| &mut n.settlement_fee),
+        ("taxa de registro", |n| &mut n.registration_fee),
+        ("emolumentos", |n| &mut n.emolumentos),
+        ("taxa operacional", |n| &mut n.brokerage),
+        ("corretagem", |n| &mut n.brokerage),
+        ("iss", |n| &mut n.iss),
+        ("impostos", |n| &mut n.iss),
+    ];
+    for (label, field) in fields {
+        if label == "iss" && !(line.trim_start().starts_with("iss") || line.contains(" iss")) {
+            continue;
+        }
+        if let Some(value) = first_after(label) {
+            *field(note) = value;
+        }
+    }
+    let mut other = Decimal::ZERO;
+    for label in [
+        "taxa de termo/opcoes",
+        "taxa a.n.a.",
+        "execucao",
+        "taxa de custodia",
+        "outras",
+    ] {
+        if let Some(value) = first_after(label) {
+            other += value;
+        }
+    }
+    if !other.is_zero() {
+        note.other_fees += other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const PAGE: &str = "\
+                                   NOTA DE NEGOCIAÇÃO
+                                                            Nr. nota      Folha      Data pregão
+                                                            123456        1          15/03/2024
+ XP INVESTIMENTOS CCTVM S/A
+ Av. Ataulfo de Paiva, 153
+
+                                          Negócios realizados
+ Q Negociação  C/V  Tipo mercado   Prazo  Especificação do título          Obs. (*)   Quantidade   Preço / Ajuste   Valor Operação / Ajuste D/C
+ 1-BOVESPA     C    VISTA                 PETROBRAS PN N2                               100           38,50            3.850,00 D
+ 1-BOVESPA     V    FRACIONARIO           FII CSHG LOG HGLG11F CI          D            10           160,00           1.600,00 C
+ 1-BOVESPA     C    OPCAO DE COMPRA 04/24 PETRD400 PN 38,00 PETR                       1.000         0,50             500,00 D
+
+ Resumo dos Negócios                                  Resumo Financeiro
+ Vendas à vista                      1.600,00         Valor líquido das operações               2.750,00 D
+ Compras à vista                     3.850,00         Taxa de liquidação                            1,64 D
+ Opções - compras                      500,00         Taxa de Registro                              0,00 D
+                                                      Emolumentos                                   0,32 D
+                                                      Taxa Operacional                              4,90 D
+                                                      ISS (SÃO PAULO)                               0,24 D
+                                                      I.R.R.F. s/ operações, base R$ 0,00           0,00
+                                                      IRRF Day Trade: Base R$ 50,00 Projeção R$     0,50
+                                                      Outras                                        0,10 D
+                                                      Líquido para 19/03/2024                   2.757,20 D
+";
+
+    #[test]
+    fn test_parse_sinacor_page() {
+        let notes = parse_nota_pages(&[PAGE.to_string()]);
+        assert_eq!(notes.len(), 1);
+        let note = &notes[0];
+        assert_eq!(note.number, "123456");
+        assert_eq!(note.trade_date, NaiveDate::from_ymd_opt(2024, 3, 15));
+        assert_eq!(note.settlement_date, NaiveDate::from_ymd_opt(2024, 3, 19));
+        assert_eq!(note.broker.as_deref(), Some("XP INVESTIMENTOS CCTVM S/A"));
+        assert_eq!(note.net_amount, Some(dec!(-2757.20)));
+        assert_eq!(note.settlement_fee, dec!(1.64));
+        assert_eq!(note.emolumentos, dec!(0.32));
+        assert_eq!(note.brokerage, dec!(4.90));
+        assert_eq!(note.iss, dec!(0.24));
+        assert_eq!(note.other_fees, dec!(0.10));
+        assert_eq!(note.irrf, Decimal::ZERO);
+        assert_eq!(note.irrf_day_trade, dec!(0.50));
+
+        assert_eq!(note.trades.len(), 3);
+        let petr = &note.trades[0];
+        assert_eq!(petr.transaction_type, TransactionType::Buy);
+        assert_eq!(petr.specification, "PETROBRAS PN N2");
+        assert_eq!(petr.ticker, None);
+        assert_eq!(petr.quantity, dec!(100));
+        assert_eq!(petr.value, dec!(3850.00));
+        assert!(!petr.is_day_trade);
+
+        let fii = &note.trades[1];
+        assert_eq!(fii.transaction_type, TransactionType::Sell);
+        assert_eq!(fii.ticker.as_deref(), Some("HGLG11"));
+        assert!(fii.is_day_trade);
+
+        let option = &note.trades[2];
+        assert_eq!(option.market, "OPCAO DE COMPRA");
+        assert_eq!(option.ticker.as_deref(), Some("PETRD400"));
+        assert_eq!(option.quantity, dec!(1000));
+
+        // 7,20 of fees spread by value (3.850 / 1.600 / 500 of 5.950)
+        let fees: Vec<Decimal> = note.trades.iter().map(|t| t.fees).collect();
+        assert_eq!(fees, vec![dec!(4.66), dec!(1.94), dec!(0.60)]);
+        assert_eq!(fees.iter().sum::<Decimal>(), note.total_fees());
+    }
+
+    #[test]
+    fn test_continued_pages_merge_into_one_note() {
+        let (first, summary) = PAGE.split_once(" Resumo dos Negócios").unwrap();
+        let header: String = first.lines().take(4).collect::<Vec<_>>().join("\n");
+        let second = format!("{}\n Resumo dos Negócios{}", header, summary);
+        let notes = parse_nota_pages(&[format!("{}\nCONTINUA...", first), second]);
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].trades.len(), 3);
+        assert_eq!(notes[0].total_fees(), dec!(7.20));
+    }
+}
@@ -0,0 +1,453 @@
+//! OFX investment statement importer.
+//!
+//! Brokers and banks outside B3's own exports often offer an OFX download
+//! (`.ofx`/`.qfx`). Both OFX 1.x (SGML, leaf elements without closing tags)
+//! and 2.x (XML) are read. From the investment transaction list:
+//!
+//! - `BUY*`/`SELL*` records (their `INVBUY`/`INVSELL` aggregates) become
+//!   trades, like a CEI or custom-mapping file;
+//! - `INCOME` records become income events, like a Proventos Recebidos row.
+//!
+//! Securities are named by the `SECLIST` entry their `SECID` points to; one
+//! without a `TICKER` cannot be matched to a B3 asset and its records are
+//! skipped. Reinvestments, transfers and cash movements are ignored.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+use super::cei_excel::RawTransaction;
+use super::proventos_excel::ProventoEntry;
+use crate::db::models::{IncomeEventType, TransactionType};
+
+/// Source recorded on trades and income read from OFX files
+pub const SOURCE: &str = "OFX";
+
+/// Trades and income of one OFX statement
+#[derive(Debug, Clone, Default)]
+pub struct OfxStatement {
+    /// Institution that issued the file (`FI/ORG`, else the broker id)
+    pub institution: Option<String>,
+    pub trades: Vec<RawTransaction>,
+    pub income: Vec<ProventoEntry>,
+}
+
+/// An OFX element: a leaf with a value or an aggregate with children
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    value: Option<String>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    fn text(&self, name: &str) -> Option<&str> {
+        self.child(name).and_then(|c| c.value.as_deref())
+    }
+
+    /// Every element named `name` below this one, depth first
+    fn descendants<'a>(&'a self, name: &str, found: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            } else {
+                child.descendants(name, found);
+            }
+        }
+    }
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Build the element tree from the body of the file (from `<OFX>` on).
+/// An opening tag followed by text is a leaf, closed or not; one followed
+/// by another tag opens an aggregate.
+fn parse_elements(content: &str) -> Result<Element> {
+    let start = content
+        .find("<OFX>")
+        .ok_or_else(|| anyhow!("Not an OFX file: no <OFX> element"))?;
+    let mut stack = vec![Element::default()];
+    let mut rest = &content[start..];
+
+    while let Some(open) = rest.find('<') {
+        let close = rest[open..]
+            .find('>')
+            .map(|i| open + i)
+            .ok_or_else(|| anyhow!("Unterminated tag in OFX file"))?;
+        let tag = rest[open + 1..close].trim();
+        rest = &rest[close + 1..];
+        let text_end = rest.find('<').unwrap_or(rest.len());
+        let text = rest[..text_end].trim();
+
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim().to_uppercase();
+            // Leaf closing tags of OFX 2.x were already consumed with the leaf
+            if let Some(depth) = stack.iter().rposition(|e| e.name == name) {
+                while stack.len() > depth {
+                    let done = stack.pop().expect("depth is within the stack");
+                    stack
+                        .last_mut()
+                        .ok_or_else(|| anyhow!("Unbalanced </{}> in OFX file", name))?
+                        .children
+                        .push(done);
+                }
+            }
+            continue;
+        }
+
+        let name = tag.to_uppercase();
+        if text.is_empty() {
+            stack.push(Element {
+                name,
+                ..Default::default()
+            });
+        } else {
+            stack
+                .last_mut()
+                .expect("root is never popped")
+                .children
+                .push(Element {
+                    name: name.clone(),
+                    value: Some(unescape(text)),
+                    children: Vec::new(),
+                });
+            rest = &rest[text_end..];
+            if let Some(after) = rest.strip_prefix(&format!("</{}>", name)) {
+                rest = after;
+            }
+        }
+    }
+
+    // Aggregates left open at the end of the file (truncated SGML)
+    while stack.len() > 1 {
+        let done = stack.pop().expect("checked length");
+        stack
+            .last_mut()
+            .expect("checked length")
+            .children
+            .push(done);
+    }
+    stack
+        .pop()
+        .and_then(|root| root.children.into_iter().find(|e| e.name == "OFX"))
+        .ok_or_else(|| anyhow!("Not an OFX file: no <OFX> element"))
+}
+
+/// OFX dates are `YYYYMMDD`, optionally followed by time and zone
+fn parse_date(raw: &str) -> Result<NaiveDate> {
+    let digits = raw.get(..8).unwrap_or(raw);
+    NaiveDate::parse_from_str(digits, "%Y%m%d")
+        .with_context(|| format!("Invalid OFX date '{}'", raw))
+}
+
+/// Amounts use a decimal point; some Brazilian banks write a comma instead
+fn parse_amount(raw: &str) -> Result<Decimal> {
+    let raw = raw.trim();
+    let normalized = if raw.contains('.') {
+        raw.replace(',', "")
+    } else {
+        raw.replace(',', ".")
+    };
+    Decimal::from_str(&normalized).with_context(|| format!("Invalid OFX amount '{}'", raw))
+}
+
+fn amount(element: &Element, name: &str) -> Result<Option<Decimal>> {
+    element.text(name).map(parse_amount).transpose()
+}
+
+fn security_key(secid: &Element) -> Option<String> {
+    Some(format!(
+        "{}:{}",
+        secid.text("UNIQUEIDTYPE").unwrap_or(""),
+        secid.text("UNIQUEID")?
+    ))
+}
+
+/// Ticker and name of each security in the statement's `SECLIST`
+fn securities(ofx: &Element) -> HashMap<String, (Option<String>, Option<String>)> {
+    let mut infos = Vec::new();
+    ofx.descendants("SECINFO", &mut infos);
+    infos
+        .into_iter()
+        .filter_map(|info| {
+            let key = security_key(info.child("SECID")?)?;
+            let ticker = info.text("TICKER").map(|t| {
+                let t = t.trim().to_uppercase();
+                t.strip_suffix(".SA").map(str::to_string).unwrap_or(t)
+            });
+            Some((key, (ticker, info.text("SECNAME").map(str::to_string))))
+        })
+        .collect()
+}
+
+fn income_type(income_type: &str, memo: &str) -> IncomeEventType {
+    let memo = memo.to_uppercase();
+    if memo.contains("JCP") || memo.contains("JUROS") {
+        IncomeEventType::Jcp
+    } else if memo.contains("AMORT") {
+        IncomeEventType::Amortization
+    } else if memo.contains("DIVID") || memo.contains("RENDIMENTO") {
+        IncomeEventType::Dividend
+    } else if income_type.eq_ignore_ascii_case("INTEREST") {
+        // Listed shares and funds pay no interest other than JCP
+        IncomeEventType::Jcp
+    } else {
+        IncomeEventType::Dividend
+    }
+}
+
+/// Parse an OFX file
+pub fn parse_ofx<P: AsRef<Path>>(path: P) -> Result<OfxStatement> {
+    let path = path.as_ref();
+    info!("Parsing OFX statement: {:?}", path);
+    let bytes = std::fs::read(path).context("Failed to read OFX file")?;
+    let (_, content) = super::inspect::decode_text(&bytes);
+    parse_ofx_content(&content)
+}
+
+fn parse_ofx_content(content: &str) -> Result<OfxStatement> {
+    let ofx = parse_elements(content)?;
+    let securities = securities(&ofx);
+
+    let mut statements = Vec::new();
+    ofx.descendants("INVSTMTRS", &mut statements);
+    if statements.is_empty() {
+        return Err(anyhow!(
+            "OFX file has no investment statement (INVSTMTRS); bank and card statements are not supported"
+        ));
+    }
+
+    let institution = ofx
+        .child("SIGNONMSGSRSV1")
+        .and_then(|s| s.child("SONRS"))
+        .and_then(|s| s.child("FI"))
+        .and_then(|fi| fi.text("ORG"))
+        .or_else(|| {
+            statements[0]
+                .child("INVACCTFROM")
+                .and_then(|a| a.text("BROKERID"))
+        })
+        .map(str::to_string);
+    let mut statement = OfxStatement {
+        institution: institution.clone(),
+        ..Default::default()
+    };
+
+    let security = |record: &Element| -> Option<(String, Option<String>)> {
+        let key = security_key(record.child("SECID")?)?;
+        match securities.get(&key) {
+            Some((Some(ticker), name)) => Some((ticker.clone(), name.clone())),
+            _ => {
+                warn!("Skipping OFX record for security {} without a ticker", key);
+                None
+            }
+        }
+    };
+
+    for list in statements.iter().filter_map(|s| s.child("INVTRANLIST")) {
+        for record in &list.children {
+            let (trade, transaction_type) = match record.name.as_str() {
+                name if name.starts_with("BUY") => (record.child("INVBUY"), TransactionType::Buy),
+                name if name.starts_with("SELL") => {
+                    (record.child("INVSELL"), TransactionType::Sell)
+                }
+                "INCOME" => {
+                    let Some((ticker, name)) = security(record) else {
+                        continue;
+                    };
+                    let tran = record
+                        .child("INVTRAN")
+                        .ok_or_else(|| anyhow!("OFX INCOME record without INVTRAN"))?;
+                    let memo = tran.text("MEMO").unwrap_or("");
+                    let kind = record.text("INCOMETYPE").unwrap_or("");
+                    let paid = tran
+                        .text("DTSETTLE")
+                        .or(tran.text("DTTRADE"))
+                        .ok_or_else(|| {
+                            anyhow!("OFX INCOME record for {} without a date", ticker)
+                        })?;
+                    let gross = amount(record, "TOTAL")?
+                        .ok_or_else(|| anyhow!("OFX INCOME record for {} without TOTAL", ticker))?
+                        .abs();
+                    let withholding = amount(record, "WITHHOLDING")?.unwrap_or_default().abs();
+                    statement.income.push(ProventoEntry {
+                        product: format!("{} - {}", ticker, name.unwrap_or_default()),
+                        ticker,
+                        event_type: income_type(kind, memo),
+                        movement_type: if memo.is_empty() { kind } else { memo }.to_string(),
+                        ex_date: None,
+                        payment_date: parse_date(paid)?,
+                        institution: institution.clone().unwrap_or_default(),
+                        quantity: None,
+                        gross,
+                        withholding,
+                        net: gross - withholding,
+                        foreign_tax: None,
+                    });
+                    continue;
+                }
+                other => {
+                    debug!("Ignoring OFX {} record", other);
+                    continue;
+                }
+            };
+
+            let trade = trade
+                .ok_or_else(|| anyhow!("OFX {} record without its INVBUY/INVSELL", record.name))?;
+            let Some((ticker, _)) = security(trade) else {
+                continue;
+            };
+            let tran = trade
+                .child("INVTRAN")
+                .ok_or_else(|| anyhow!("OFX {} record without INVTRAN", record.name))?;
+            let trade_date = parse_date(
+                tran.text("DTTRADE")
+                    .ok_or_else(|| anyhow!("OFX trade of {} without DTTRADE", ticker))?,
+            )?;
+            let quantity = amount(trade, "UNITS")?
+                .ok_or_else(|| anyhow!("OFX trade of {} without UNITS", ticker))?
+                .abs();
+            let price = amount(trade, "UNITPRICE")?
+                .ok_or_else(|| anyhow!("OFX trade of {} without UNITPRICE", ticker))?
+                .abs();
+            let mut fees = Decimal::ZERO;
+            for name in ["COMMISSION", "FEES", "TAXES"] {
+                fees += amount(trade, name)?.unwrap_or_default().abs();
+            }
+
+            statement.trades.push(RawTransaction {
+                ticker,
+                transaction_type: transaction_type.as_str().to_string(),
+                trade_date,
+                quantity,
+                price,
+                fees,
+                total: quantity * price,
+                market: None,
+            });
+        }
+    }
+
+    info!(
+        "Parsed {} trades and {} income payments from OFX",
+        statement.trades.len(),
+        statement.income.len()
+    );
+    Ok(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const SGML: &str = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+CHARSET:1252
+
+<OFX>
+<SIGNONMSGSRSV1><SONRS>
+<STATUS><CODE>0<SEVERITY>INFO</STATUS>
+<DTSERVER>20240701120000[-3:BRT]
+<FI><ORG>CORRETORA X<FID>123</FI>
+</SONRS></SIGNONMSGSRSV1>
+<INVSTMTMSGSRSV1><INVSTMTTRNRS><INVSTMTRS>
+<INVACCTFROM><BROKERID>corretorax.com.br<ACCTID>98765</INVACCTFROM>
+<INVTRANLIST>
+<BUYSTOCK><INVBUY>
+<INVTRAN><FITID>1<DTTRADE>20240102<DTSETTLE>20240104</INVTRAN>
+<SECID><UNIQUEID>BRPETRACNPR6<UNIQUEIDTYPE>ISIN</SECID>
+<UNITS>100<UNITPRICE>38,50<COMMISSION>4.90<FEES>1.15<TOTAL>-3856.05
+<SUBACCTSEC>CASH<SUBACCTFUND>CASH
+</INVBUY><BUYTYPE>BUY</BUYSTOCK>
+<SELLSTOCK><INVSELL>
+<INVTRAN><FITID>2<DTTRADE>20240315</INVTRAN>
+<SECID><UNIQUEID>BRPETRACNPR6<UNIQUEIDTYPE>ISIN</SECID>
+<UNITS>-40<UNITPRICE>41.00<TOTAL>1640.00
+</INVSELL><SELLTYPE>SELL</SELLSTOCK>
+<INCOME>
+<INVTRAN><FITID>3<DTTRADE>20240220<MEMO>JUROS S/ CAPITAL</INVTRAN>
+<SECID><UNIQUEID>BRPETRACNPR6<UNIQUEIDTYPE>ISIN</SECID>
+<INCOMETYPE>DIV<TOTAL>50.00<WITHHOLDING>7.50
+</INCOME>
+<BUYSTOCK><INVBUY>
+<INVTRAN><FITID>4<DTTRADE>20240105</INVTRAN>
+<SECID><UNIQUEID>US0000000001<UNIQUEIDTYPE>ISIN</SECID>
+<UNITS>1<UNITPRICE>10<TOTAL>-10
+</INVBUY><BUYTYPE>BUY</BUYSTOCK>
+<INVBANKTRAN><STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20240101<TRNAMT>1000</STMTTRN></INVBANKTRAN>
+</INVTRANLIST>
+</INVSTMTRS></INVSTMTTRNRS></INVSTMTMSGSRSV1>
+<SECLISTMSGSRSV1><SECLIST>
+<STOCKINFO><SECINFO><SECID><UNIQUEID>BRPETRACNPR6<UNIQUEIDTYPE>ISIN</SECID>
+<SECNAME>PETROBRAS PN &amp; CIA<TICKER>PETR4.SA</SECINFO></STOCKINFO>
+<STOCKINFO><SECINFO><SECID><UNIQUEID>US0000000001<UNIQUEIDTYPE>ISIN</SECID>
+<SECNAME>NO TICKER INC</SECINFO></STOCKINFO>
+</SECLIST></SECLISTMSGSRSV1>
+</OFX>
+";
+
+    #[test]
+    fn test_parse_ofx_trades_and_income() {
+        let statement = parse_ofx_content(SGML).unwrap();
+        assert_eq!(statement.institution.as_deref(), Some("CORRETORA X"));
+
+        // The security without a ticker and the cash credit are skipped
+        assert_eq!(statement.trades.len(), 2);
+        let buy = &statement.trades[0];
+        assert_eq!(buy.ticker, "PETR4");
+        assert_eq!(buy.transaction_type, "BUY");
+        assert_eq!(buy.trade_date, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!((buy.quantity, buy.price), (dec!(100), dec!(38.50)));
+        assert_eq!(buy.fees, dec!(6.05));
+        assert_eq!(buy.total, dec!(3850));
+        let sell = &statement.trades[1];
+        assert_eq!(sell.transaction_type, "SELL");
+        assert_eq!(sell.quantity, dec!(40));
+
+        assert_eq!(statement.income.len(), 1);
+        let jcp = &statement.income[0];
+        assert_eq!(jcp.event_type, IncomeEventType::Jcp);
+        assert_eq!(jcp.product, "PETR4 - PETROBRAS PN & CIA");
+        assert_eq!(
+            (jcp.gross, jcp.withholding, jcp.net),
+            (dec!(50), dec!(7.50), dec!(42.50))
+        );
+        assert_eq!(jcp.institution, "CORRETORA X");
+
+        // OFX 2.x closes every element
+        let xml = "<?xml version=\"1.0\"?><?OFX OFXHEADER=\"200\"?><OFX><INVSTMTMSGSRSV1><INVSTMTTRNRS><INVSTMTRS>\
+            <INVTRANLIST><BUYMF><INVBUY><INVTRAN><FITID>9</FITID><DTTRADE>20240610</DTTRADE></INVTRAN>\
+            <SECID><UNIQUEID>BRHGLGCTF004</UNIQUEID><UNIQUEIDTYPE>ISIN</UNIQUEIDTYPE></SECID>\
+            <UNITS>10</UNITS><UNITPRICE>160.25</UNITPRICE><TOTAL>-1602.50</TOTAL></INVBUY><BUYTYPE>BUY</BUYTYPE></BUYMF>\
+            </INVTRANLIST></INVSTMTRS></INVSTMTTRNRS></INVSTMTMSGSRSV1><SECLISTMSGSRSV1><SECLIST><MFINFO><SECINFO>\
+            <SECID><UNIQUEID>BRHGLGCTF004</UNIQUEID><UNIQUEIDTYPE>ISIN</UNIQUEIDTYPE></SECID>\
+            <SECNAME>CSHG LOGISTICA</SECNAME><TICKER>HGLG11</TICKER></SECINFO></MFINFO></SECLIST></SECLISTMSGSRSV1></OFX>";
+        let statement = parse_ofx_content(xml).unwrap();
+        assert_eq!(statement.trades.len(), 1);
+        assert_eq!(statement.trades[0].ticker, "HGLG11");
+        assert_eq!(statement.trades[0].price, dec!(160.25));
+
+        assert!(parse_ofx_content("<OFX><BANKMSGSRSV1></BANKMSGSRSV1></OFX>").is_err());
+    }
+}
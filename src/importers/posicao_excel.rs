@@ -0,0 +1,230 @@
+//! B3 "Posição" Excel importer
+//!
+//! The investor area of the B3 site exports the custody held at the end of a
+//! day, one sheet per product (Acoes, BDR, ETF, Fundo de Investimento, Renda
+//! Fixa, Tesouro Direto) and one row per institution holding it. The file has
+//! no date inside: B3 names it `posicao-YYYY-MM-DD-...xlsx`, so the statement
+//! date is taken from the file name.
+//!
+//! Only sheets with a "Código de Negociação" column are read; fixed income
+//! and Tesouro rows have no B3 ticker to match the computed holdings.
+
+use anyhow::{anyhow, Context, Result};
+use calamine::{open_workbook, Data, Reader, Xlsx};
+use chrono::NaiveDate;
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{debug, info};
+
+/// Source recorded on the stored statements
+pub const SOURCE: &str = "POSICAO";
+
+/// Sheets of the Posição export
+const SHEETS: &[&str] = &["acoes", "bdr", "etf", "fundo de investimento"];
+
+/// One row: a ticker held at one institution
+#[derive(Debug, Clone, Serialize)]
+pub struct PosicaoEntry {
+    pub ticker: String,
+    pub institution: String,
+    pub quantity: Decimal,
+}
+
+/// The custody of one day
+#[derive(Debug, Clone, Serialize)]
+pub struct PosicaoStatement {
+    pub date: NaiveDate,
+    pub entries: Vec<PosicaoEntry>,
+}
+
+impl PosicaoStatement {
+    /// Quantity per ticker, summed over institutions
+    pub fn quantities(&self) -> BTreeMap<String, Decimal> {
+        let mut quantities = BTreeMap::new();
+        for entry in &self.entries {
+            *quantities.entry(entry.ticker.clone()).or_default() += entry.quantity;
+        }
+        quantities
+    }
+}
+
+fn normalize(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ã' => 'a',
+            'é' | 'ê' => 'e',
+            'í' => 'i',
+            'ó' | 'ô' | 'õ' => 'o',
+            'ú' => 'u',
+            'ç' => 'c',
+            c => c,
+        })
+        .collect()
+}
+
+/// Whether the workbook's sheets look like the Posição export
+pub fn is_posicao_workbook(sheet_names: &[String]) -> bool {
+    sheet_names
+        .iter()
+        .any(|name| SHEETS.contains(&normalize(name).as_str()))
+}
+
+/// Statement date from a B3 file name such as `posicao-2024-06-28-10-15-00.xlsx`
+pub fn statement_date(path: &Path) -> Result<NaiveDate> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let re = Regex::new(r"(\d{4})-(\d{2})-(\d{2})").expect("valid regex");
+    re.captures(&name)
+        .and_then(|c| NaiveDate::parse_from_str(&c[0], "%Y-%m-%d").ok())
+        .ok_or_else(|| {
+            anyhow!(
+                "Could not tell the position date from the file name '{}'; rename it like posicao-2024-06-28.xlsx",
+                name
+            )
+        })
+}
+
+/// Parse the Posição workbook
+pub fn parse_posicao_excel<P: AsRef<Path>>(path: P) -> Result<PosicaoStatement> {
+    let path = path.as_ref();
+    info!("Parsing B3 Posição file: {:?}", path);
+    let date = statement_date(path)?;
+    let mut workbook: Xlsx<_> = open_workbook(path).context("Failed to open Posição Excel file")?;
+
+    let mut entries = Vec::new();
+    for sheet_name in workbook.sheet_names() {
+        let range = workbook
+            .worksheet_range(&sheet_name)
+            .context(format!("Sheet '{}' not found", sheet_name))?;
+        let rows: Vec<Vec<Data>> = range.rows().map(|r| r.to_vec()).collect();
+        let parsed = parse_rows(&rows)?;
+        debug!("Sheet '{}': {} positions", sheet_name, parsed.len());
+        entries.extend(parsed);
+    }
+    if entries.is_empty() {
+        return Err(anyhow!(
+            "No positions with a 'Código de Negociação' found in the Posição file"
+        ));
+    }
+    info!("Parsed {} positions held on {}", entries.len(), date);
+    Ok(PosicaoStatement { date, entries })
+}
+
+/// Rows of one sheet; sheets without a ticker column yield nothing
+fn parse_rows(rows: &[Vec<Data>]) -> Result<Vec<PosicaoEntry>> {
+    let Some((header, rows)) = rows.split_first() else {
+        return Ok(Vec::new());
+    };
+    let names: Vec<String> = header.iter().map(|c| normalize(&c.to_string())).collect();
+    let find = |name: &str| names.iter().position(|h| h == name);
+    let (Some(ticker_idx), Some(quantity_idx)) = (find("codigo de negociacao"), find("quantidade"))
+    else {
+        return Ok(Vec::new());
+    };
+    let institution_idx = find("instituicao");
+
+    let mut entries = Vec::new();
+    for row in rows {
+        let text = |idx: Option<usize>| {
+            idx.and_then(|i| row.get(i))
+                .map(|c| c.to_string().trim().to_string())
+                .unwrap_or_default()
+        };
+        let ticker = text(Some(ticker_idx)).to_uppercase();
+        // Blank lines and the totals row at the end
+        if ticker.is_empty() || ticker == "-" {
+            continue;
+        }
+        let quantity = parse_quantity(row.get(quantity_idx).unwrap_or(&Data::Empty))
+            .with_context(|| format!("Invalid quantity for {}", ticker))?;
+        entries.push(PosicaoEntry {
+            ticker,
+            institution: text(institution_idx),
+            quantity,
+        });
+    }
+    Ok(entries)
+}
+
+fn parse_quantity(data: &Data) -> Result<Decimal> {
+    match data {
+        Data::Int(i) => Ok(Decimal::from(*i)),
+        Data::Float(f) => Decimal::from_f64_retain(*f)
+            .map(|d| d.round_dp(8).normalize())
+            .ok_or_else(|| anyhow!("Invalid decimal")),
+        Data::String(s) => {
+            Decimal::from_str(&s.trim().replace('.', "").replace(',', ".")).context("Not a number")
+        }
+        _ => Err(anyhow!("Empty quantity")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_posicao_rows_and_date() {
+        let s = |v: &str| Data::String(v.to_string());
+        let rows = vec![
+            vec![
+                s("Produto"),
+                s("Instituição"),
+                s("Conta"),
+                s("Código de Negociação"),
+                s("Quantidade"),
+            ],
+            vec![
+                s("PETR4 - PETROLEO BRASILEIRO S.A."),
+                s("XP INVESTIMENTOS CCTVM S/A"),
+                s("123"),
+                s("PETR4"),
+                Data::Float(300.0),
+            ],
+            vec![
+                s("PETR4 - PETROLEO BRASILEIRO S.A."),
+                s("NU INVEST CORRETORA DE VALORES S.A."),
+                s("456"),
+                s("PETR4"),
+                s("1.000"),
+            ],
+            vec![
+                s("Total"),
+                Data::Empty,
+                Data::Empty,
+                Data::Empty,
+                Data::Float(1300.0),
+            ],
+        ];
+        let statement = PosicaoStatement {
+            date: NaiveDate::from_ymd_opt(2024, 6, 28).unwrap(),
+            entries: parse_rows(&rows).unwrap(),
+        };
+        assert_eq!(statement.entries.len(), 2);
+        assert_eq!(statement.quantities()["PETR4"], dec!(1300));
+
+        // Tesouro Direto rows have no ticker column
+        let tesouro = vec![vec![s("Produto"), s("Código ISIN"), s("Quantidade")]];
+        assert!(parse_rows(&tesouro).unwrap().is_empty());
+
+        assert_eq!(
+            statement_date(Path::new("/tmp/posicao-2024-06-28-10-15-00.xlsx")).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 6, 28).unwrap()
+        );
+        assert!(statement_date(Path::new("posicao.xlsx")).is_err());
+        assert!(is_posicao_workbook(&[
+            "Acoes".to_string(),
+            "Tesouro Direto".to_string()
+        ]));
+        assert!(!is_posicao_workbook(&["Negociação".to_string()]));
+    }
+}
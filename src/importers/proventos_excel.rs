@@ -0,0 +1,447 @@
+//! B3 "Proventos Recebidos" Excel importer
+//!
+//! The investor area of the B3 site exports the income received over a
+//! period, one payment per row: product, event type, ex-date, payment date,
+//! institution, quantity and the gross, withheld and net values. Unlike the
+//! credit lines of a Movimentação statement, which only carry the net cash,
+//! it says how much IRRF was withheld, so JCP (and dividends taxed from 2026)
+//! are recorded gross with their withholding.
+//!
+//! Columns are found by name. When the report has no IR column the
+//! withholding is gross minus net; when it has only one of them, the
+//! withholding is the event type's default (15% on JCP, see `tax::rules`).
+
+use anyhow::{anyhow, Context, Result};
+use calamine::{open_workbook, Data, Reader, Xlsx};
+use chrono::{Datelike, NaiveDate, Weekday};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::path::Path;
+use std::str::FromStr;
+use tracing::{debug, info, warn};
+
+use crate::db::models::{AssetType, IncomeEvent, IncomeEventType};
+use crate::importers::movimentacao_excel::MovimentacaoEntry;
+use crate::tax::rules::income_withholding_rule;
+
+/// One payment of the Proventos Recebidos report
+#[derive(Debug, Clone, Serialize)]
+pub struct ProventoEntry {
+    pub ticker: String,
+    pub product: String,
+    pub event_type: IncomeEventType,
+    /// Event as named in the report (Dividendo, Rendimento, ...)
+    pub movement_type: String,
+    pub ex_date: Option<NaiveDate>,
+    pub payment_date: NaiveDate,
+    pub institution: String,
+    pub quantity: Option<Decimal>,
+    pub gross: Decimal,
+    pub withholding: Decimal,
+    pub net: Decimal,
+    /// Tax withheld abroad, for income of assets held abroad
+    pub foreign_tax: Option<Decimal>,
+}
+
+impl ProventoEntry {
+    pub fn to_income_event(&self, asset_id: i64) -> IncomeEvent {
+        let amount_per_quota = match self.quantity {
+            Some(quantity) if quantity > Decimal::ZERO => (self.gross / quantity).round_dp(8),
+            _ => self.gross,
+        };
+        IncomeEvent {
+            id: None,
+            asset_id,
+            event_date: self.payment_date,
+            ex_date: self.ex_date,
+            event_type: self.event_type.clone(),
+            amount_per_quota,
+            total_amount: self.gross,
+            withholding_tax: self.withholding,
+            foreign_tax_withheld: self.foreign_tax,
+            is_quota_pre_2026: None,
+            source: "PROVENTOS".to_string(),
+            notes: Some(format!("{} - {}", self.movement_type, self.product)),
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Whether a sheet name looks like the Proventos Recebidos report
+pub fn is_proventos_sheet(name: &str) -> bool {
+    name.to_lowercase().contains("provento")
+}
+
+fn event_type(raw: &str) -> Option<IncomeEventType> {
+    let raw = normalize(raw);
+    if raw.starts_with("juros") || raw == "jcp" {
+        Some(IncomeEventType::Jcp)
+    } else if raw.starts_with("dividendo")
+        || raw.starts_with("rendimento")
+        || raw.starts_with("reembolso")
+        || raw.starts_with("bonificacao em dinheiro")
+    {
+        Some(IncomeEventType::Dividend)
+    } else if raw.starts_with("amortizacao") || raw.starts_with("restituicao de capital") {
+        Some(IncomeEventType::Amortization)
+    } else {
+        None
+    }
+}
+
+/// Lowercase without accents or a currency suffix, for header matching
+fn normalize(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .trim_end_matches("(r$)")
+        .trim()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'â' | 'ã' => 'a',
+            'é' | 'ê' => 'e',
+            'í' => 'i',
+            'ó' | 'ô' | 'õ' => 'o',
+            'ú' => 'u',
+            'ç' => 'c',
+            '-' => ' ',
+            c => c,
+        })
+        .collect()
+}
+
+/// Column indexes found in the header row
+struct Columns {
+    product: usize,
+    event_type: usize,
+    payment_date: usize,
+    ex_date: Option<usize>,
+    /// "Data com": the last day with rights, a business day before the ex-date
+    record_date: Option<usize>,
+    institution: Option<usize>,
+    quantity: Option<usize>,
+    gross: Option<usize>,
+    withholding: Option<usize>,
+    net: Option<usize>,
+}
+
+impl Columns {
+    fn from_header(header: &[Data]) -> Result<Self> {
+        let names: Vec<String> = header.iter().map(|c| normalize(&c.to_string())).collect();
+        let find =
+            |candidates: &[&str]| names.iter().position(|h| candidates.contains(&h.as_str()));
+        let required = |candidates: &[&str]| {
+            find(candidates).ok_or_else(|| {
+                anyhow!(
+                    "Proventos report is missing a '{}' column (found: {:?})",
+                    candidates[0],
+                    names
+                )
+            })
+        };
+
+        let columns = Self {
+            product: required(&["produto", "ativo"])?,
+            event_type: required(&["tipo de evento", "tipo", "evento", "tipo de provento"])?,
+            payment_date: required(&[
+                "pagamento",
+                "data de pagamento",
+                "data do pagamento",
+                "data pagamento",
+            ])?,
+            ex_date: find(&["data ex", "data ex direito", "ex data", "data de ex"]),
+            record_date: find(&["data com", "data base"]),
+            institution: find(&["instituicao"]),
+            quantity: find(&["quantidade"]),
+            gross: find(&["valor bruto", "bruto"]),
+            withholding: find(&["ir", "irrf", "imposto de renda", "valor ir", "valor do ir"]),
+            net: find(&["valor liquido", "liquido", "valor"]),
+        };
+        if columns.gross.is_none() && columns.net.is_none() {
+            return Err(anyhow!(
+                "Proventos report has neither a gross nor a net value column (found: {:?})",
+                names
+            ));
+        }
+        Ok(columns)
+    }
+}
+
+/// Parse the Proventos Recebidos workbook
+pub fn parse_proventos_excel<P: AsRef<Path>>(path: P) -> Result<Vec<ProventoEntry>> {
+    info!("Parsing Proventos Recebidos file: {:?}", path.as_ref());
+    let mut workbook: Xlsx<_> =
+        open_workbook(path.as_ref()).context("Failed to open Proventos Excel file")?;
+    let sheet_name = workbook
+        .sheet_names()
+        .into_iter()
+        .find(|name| is_proventos_sheet(name))
+        .ok_or_else(|| anyhow!("No 'Proventos Recebidos' sheet found"))?;
+    let range = workbook
+        .worksheet_range(&sheet_name)
+        .context(format!("Sheet '{}' not found", sheet_name))?;
+    let rows: Vec<Vec<Data>> = range.rows().map(|r| r.to_vec()).collect();
+    parse_rows(&rows)
+}
+
+fn parse_rows(rows: &[Vec<Data>]) -> Result<Vec<ProventoEntry>> {
+    let Some((header, rows)) = rows.split_first() else {
+        return Err(anyhow!("Empty sheet"));
+    };
+    let columns = Columns::from_header(header)?;
+
+    let mut entries = Vec::new();
+    let mut errors = 0;
+    for (idx, row) in rows.iter().enumerate() {
+        let product = cell_text(row, Some(columns.product));
+        // Blank lines and the totals row at the end
+        if product.is_empty() || normalize(&product).starts_with("total") {
+            continue;
+        }
+        match parse_row(row, &columns, product) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => {
+                debug!("Failed to parse row {}: {}", idx + 2, e);
+                errors += 1;
+            }
+        }
+    }
+    if errors > 0 {
+        warn!("Failed to parse {} rows out of {}", errors, rows.len());
+    }
+    info!(
+        "Parsed {} proventos from {} rows",
+        entries.len(),
+        rows.len()
+    );
+    Ok(entries)
+}
+
+fn parse_row(row: &[Data], columns: &Columns, product: String) -> Result<ProventoEntry> {
+    let ticker = MovimentacaoEntry::extract_ticker(&product)
+        .ok_or_else(|| anyhow!("No ticker in product: {}", product))?;
+    let movement_type = cell_text(row, Some(columns.event_type));
+    let event_type = event_type(&movement_type)
+        .ok_or_else(|| anyhow!("Not an income event: {}", movement_type))?;
+    let payment_date = parse_date(cell(row, Some(columns.payment_date)))?;
+    let ex_date = match optional_date(cell(row, columns.ex_date))? {
+        Some(date) => Some(date),
+        None => optional_date(cell(row, columns.record_date))?.map(next_weekday),
+    };
+
+    let amount = |idx: Option<usize>| -> Result<Option<Decimal>> {
+        match idx {
+            Some(_) => parse_decimal(cell(row, idx)),
+            None => Ok(None),
+        }
+    };
+    let (gross, withholding, net) = amounts(
+        &event_type,
+        payment_date,
+        amount(columns.gross)?,
+        amount(columns.withholding)?,
+        amount(columns.net)?,
+    )?;
+
+    Ok(ProventoEntry {
+        ticker,
+        product,
+        event_type,
+        movement_type,
+        ex_date,
+        payment_date,
+        institution: cell_text(row, columns.institution),
+        quantity: amount(columns.quantity)?,
+        gross,
+        withholding,
+        net,
+        foreign_tax: None,
+    })
+}
+
+/// Gross, withheld and net values from whichever of them the row has; a
+/// missing IRRF is the event type's default (see `tax::rules`)
+fn amounts(
+    event_type: &IncomeEventType,
+    paid: NaiveDate,
+    gross: Option<Decimal>,
+    withholding: Option<Decimal>,
+    net: Option<Decimal>,
+) -> Result<(Decimal, Decimal, Decimal)> {
+    // The report does not say the asset type; the defaults it needs apply to any
+    let rule = income_withholding_rule(event_type, &AssetType::Unknown, paid.year(), paid.month());
+    let (gross, withholding) = match (gross, withholding, net) {
+        (Some(gross), Some(ir), _) => (gross, ir),
+        (Some(gross), None, Some(net)) => (gross, gross - net),
+        (Some(gross), None, None) => (gross, rule.withheld_from(gross)),
+        (None, Some(ir), Some(net)) => (net + ir, ir),
+        (None, None, Some(net)) => rule.gross_up(net),
+        (None, _, None) => return Err(anyhow!("No value for the payment")),
+    };
+    if gross <= Decimal::ZERO || withholding < Decimal::ZERO || withholding > gross {
+        return Err(anyhow!(
+            "Inconsistent values: gross {}, IR {}",
+            gross,
+            withholding
+        ));
+    }
+    Ok((gross, withholding, gross - withholding))
+}
+
+fn cell(row: &[Data], idx: Option<usize>) -> &Data {
+    idx.and_then(|i| row.get(i)).unwrap_or(&Data::Empty)
+}
+
+fn cell_text(row: &[Data], idx: Option<usize>) -> String {
+    match cell(row, idx) {
+        Data::Empty => String::new(),
+        data => data.to_string().trim().to_string(),
+    }
+}
+
+fn next_weekday(date: NaiveDate) -> NaiveDate {
+    let mut next = date.succ_opt().unwrap_or(date);
+    while matches!(next.weekday(), Weekday::Sat | Weekday::Sun) {
+        next = next.succ_opt().unwrap_or(next);
+    }
+    next
+}
+
+/// Parse a date cell: an Excel date or DD/MM/YYYY text
+fn parse_date(cell: &Data) -> Result<NaiveDate> {
+    match cell {
+        Data::DateTime(dt) => {
+            let days_since_epoch = dt.as_f64().floor() as i64;
+            let excel_epoch = NaiveDate::from_ymd_opt(1899, 12, 30)
+                .ok_or_else(|| anyhow!("Invalid Excel epoch"))?;
+            excel_epoch
+                .checked_add_signed(chrono::Duration::days(days_since_epoch))
+                .ok_or_else(|| anyhow!("Date overflow"))
+        }
+        _ => {
+            let date_str = cell.to_string();
+            let date_str = date_str.trim();
+            NaiveDate::parse_from_str(date_str, "%d/%m/%Y")
+                .or_else(|_| NaiveDate::parse_from_str(date_str, "%Y-%m-%d"))
+                .map_err(|_| anyhow!("Invalid date format: {}", date_str))
+        }
+    }
+}
+
+/// Parse a date cell that may be blank or a dash
+fn optional_date(cell: &Data) -> Result<Option<NaiveDate>> {
+    match cell {
+        Data::Empty => Ok(None),
+        Data::String(s) if matches!(s.trim(), "" | "-") => Ok(None),
+        _ => parse_date(cell).map(Some),
+    }
+}
+
+/// Parse a value cell; None when it is empty or a dash
+fn parse_decimal(data: &Data) -> Result<Option<Decimal>> {
+    match data {
+        Data::Empty => Ok(None),
+        Data::Int(i) => Ok(Some(Decimal::from(*i))),
+        Data::Float(f) => Decimal::from_f64_retain(*f)
+            .map(|d| Some(d.round_dp(8).normalize()))
+            .ok_or_else(|| anyhow!("Invalid decimal")),
+        Data::String(s) => {
+            let cleaned = s
+                .replace("R$", "")
+                .replace('.', "")
+                .replace(',', ".")
+                .trim()
+                .to_string();
+            if cleaned == "-" || cleaned.is_empty() {
+                return Ok(None);
+            }
+            Decimal::from_str(&cleaned)
+                .map(Some)
+                .context("Failed to parse decimal")
+        }
+        _ => Err(anyhow!("Unsupported data type")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn text(s: &str) -> Data {
+        Data::String(s.to_string())
+    }
+
+    #[test]
+    fn test_parse_rows_with_withholding() {
+        let header = [
+            "Produto",
+            "Pagamento",
+            "Data Ex",
+            "Tipo de Evento",
+            "Instituição",
+            "Quantidade",
+            "Valor Bruto (R$)",
+            "IR (R$)",
+            "Valor Líquido (R$)",
+        ]
+        .map(text)
+        .to_vec();
+        let rows = vec![
+            header,
+            vec![
+                text("ITSA4 - ITAUSA S.A."),
+                text("01/04/2024"),
+                text("01/03/2024"),
+                text("Juros Sobre Capital Próprio"),
+                text("XP INVESTIMENTOS CCTVM S/A"),
+                Data::Int(1000),
+                Data::Float(200.0),
+                Data::Float(30.0),
+                Data::Float(170.0),
+            ],
+            vec![
+                text("MXRF11 - MAXI RENDA FII"),
+                text("14/06/2024"),
+                text("-"),
+                text("Rendimento"),
+                text("XP INVESTIMENTOS CCTVM S/A"),
+                Data::Int(100),
+                text("10,00"),
+                text("-"),
+                text("10,00"),
+            ],
+            vec![
+                text("PETR4 - PETROBRAS"),
+                text("20/06/2024"),
+                Data::Empty,
+                text("Atualização"),
+            ],
+            vec![text("Total"), Data::Empty, Data::Empty, Data::Empty],
+        ];
+
+        let entries = parse_rows(&rows).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].ticker, "ITSA4");
+        assert_eq!(entries[0].event_type, IncomeEventType::Jcp);
+        assert_eq!(entries[0].ex_date, NaiveDate::from_ymd_opt(2024, 3, 1));
+        assert_eq!(entries[0].gross, dec!(200));
+        assert_eq!(entries[0].withholding, dec!(30));
+        assert_eq!(entries[0].net, dec!(170));
+        assert_eq!(entries[0].to_income_event(1).amount_per_quota, dec!(0.2));
+        assert_eq!(entries[1].event_type, IncomeEventType::Dividend);
+        assert_eq!(entries[1].ex_date, None);
+        assert_eq!(entries[1].withholding, Decimal::ZERO);
+
+        // Only the net value: JCP is grossed up at 15%
+        let paid = NaiveDate::from_ymd_opt(2024, 6, 20).unwrap();
+        assert_eq!(
+            amounts(&IncomeEventType::Jcp, paid, None, None, Some(dec!(85))).unwrap(),
+            (dec!(100), dec!(15), dec!(85))
+        );
+        // Only the gross value: the default IRRF is withheld from it
+        assert_eq!(
+            amounts(&IncomeEventType::Jcp, paid, Some(dec!(100)), None, None).unwrap(),
+            (dec!(100), dec!(15), dec!(85))
+        );
+    }
+}
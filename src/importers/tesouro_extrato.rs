@@ -0,0 +1,242 @@
+//! Tesouro Direto extract (extrato de operações) CSV parser.
+//!
+//! The extract exported from the Tesouro Direto site lists one operation per
+//! row with the bond title, date, operation, quantity, unit price and value,
+//! separated by `;` with Brazilian number formatting. Columns are found by
+//! name, so the optional ones (IR, IOF, custody fee, maturity) may be absent
+//! or in any order.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::db::{Transaction, TransactionType};
+use crate::tesouro;
+
+/// One operation of the Tesouro Direto extract
+#[derive(Debug, Clone, Serialize)]
+pub struct TesouroExtratoEntry {
+    pub date: NaiveDate,
+    pub title: String,
+    pub ticker: String,
+    /// Buy for purchases, Sell for sales, early redemptions and maturities
+    pub operation: TransactionType,
+    pub quantity: Decimal,
+    pub unit_price: Decimal,
+    pub value: Decimal,
+    pub ir: Option<Decimal>,
+    pub iof: Option<Decimal>,
+    pub custody_fee: Option<Decimal>,
+}
+
+impl TesouroExtratoEntry {
+    pub fn to_transaction(&self, asset_id: i64) -> Transaction {
+        let mut notes = format!("Tesouro Direto: {}", self.title);
+        for (label, value) in [("IR", self.ir), ("IOF", self.iof)] {
+            if let Some(value) = value.filter(|v| !v.is_zero()) {
+                notes.push_str(&format!("; {} retido {}", label, value));
+            }
+        }
+        Transaction {
+            id: None,
+            asset_id,
+            transaction_type: self.operation.clone(),
+            trade_date: self.date,
+            settlement_date: Some(self.date),
+            quantity: self.quantity,
+            price_per_unit: self.unit_price,
+            total_cost: self.value,
+            fees: self.custody_fee.unwrap_or_default(),
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: Some(notes),
+            source: "TESOURO_EXTRATO".to_string(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Whether a CSV header line looks like the Tesouro Direto extract
+pub fn is_extrato_header(line: &str) -> bool {
+    let line = line.to_lowercase();
+    (line.contains("título") || line.contains("titulo")) && line.contains("quantidade")
+}
+
+fn find(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    headers.iter().position(|h| {
+        let h = h.trim().to_lowercase();
+        names.iter().any(|name| h == *name)
+    })
+}
+
+fn operation_type(raw: &str) -> Option<TransactionType> {
+    let raw = raw.trim().to_lowercase();
+    if raw.starts_with("compra") || raw.starts_with("investimento") || raw.starts_with("aplica") {
+        Some(TransactionType::Buy)
+    } else if raw.starts_with("venda")
+        || raw.starts_with("resgate")
+        || raw.starts_with("vencimento")
+    {
+        Some(TransactionType::Sell)
+    } else {
+        None
+    }
+}
+
+fn parse_amount(raw: &str) -> Result<Decimal> {
+    tesouro::parse_decimal_br(raw.trim().trim_start_matches("R$").trim())
+}
+
+/// Parse the extract CSV
+pub fn parse_tesouro_extrato<P: AsRef<Path>>(path: P) -> Result<Vec<TesouroExtratoEntry>> {
+    let path = path.as_ref();
+    info!("Parsing Tesouro Direto extract: {:?}", path);
+    let content = super::inspect::read_text(path).context("Failed to read Tesouro extract")?;
+    parse_tesouro_extrato_content(&content)
+}
+
+fn parse_tesouro_extrato_content(content: &str) -> Result<Vec<TesouroExtratoEntry>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .flexible(true)
+        .from_reader(content.trim_start_matches('\u{feff}').as_bytes());
+    let headers = reader.headers()?.clone();
+
+    let required = |names: &[&str]| {
+        find(&headers, names).ok_or_else(|| {
+            anyhow!(
+                "Tesouro extract is missing a '{}' column (found: {:?})",
+                names[0],
+                headers
+            )
+        })
+    };
+    let title_idx = required(&["título", "titulo", "título do tesouro"])?;
+    let date_idx = required(&[
+        "data",
+        "data da operação",
+        "data operação",
+        "data da operacao",
+    ])?;
+    let op_idx = required(&["operação", "operacao", "tipo", "tipo de operação"])?;
+    let qty_idx = required(&["quantidade", "qtd"])?;
+    let price_idx = find(
+        &headers,
+        &["preço unitário", "preco unitario", "pu", "valor unitário"],
+    );
+    let value_idx = find(
+        &headers,
+        &[
+            "valor",
+            "valor bruto",
+            "valor da operação",
+            "valor operação",
+        ],
+    );
+    let maturity_idx = find(&headers, &["vencimento", "data de vencimento"]);
+    let ir_idx = find(&headers, &["ir", "imposto de renda", "irrf"]);
+    let iof_idx = find(&headers, &["iof"]);
+    let custody_idx = find(
+        &headers,
+        &["taxa de custódia", "taxa de custodia", "taxa b3"],
+    );
+    if price_idx.is_none() && value_idx.is_none() {
+        return Err(anyhow!(
+            "Tesouro extract needs a unit price or a value column"
+        ));
+    }
+
+    let mut entries = Vec::new();
+    for (row, record) in reader.records().enumerate() {
+        let record = record?;
+        let field = |idx: usize| record.get(idx).unwrap_or("").trim();
+        let optional = |idx: Option<usize>| {
+            idx.map(field)
+                .filter(|v| !v.is_empty())
+                .and_then(|v| parse_amount(v).ok())
+        };
+
+        let title = field(title_idx);
+        if title.is_empty() {
+            continue;
+        }
+        let Some(operation) = operation_type(field(op_idx)) else {
+            warn!("Row {}: skipping operation '{}'", row + 2, field(op_idx));
+            continue;
+        };
+        let date = NaiveDate::parse_from_str(field(date_idx), "%d/%m/%Y")
+            .with_context(|| format!("Row {}: invalid date '{}'", row + 2, field(date_idx)))?;
+        let maturity =
+            maturity_idx.and_then(|idx| NaiveDate::parse_from_str(field(idx), "%d/%m/%Y").ok());
+        let ticker = tesouro::ticker_from_name(title)
+            .or_else(|| maturity.and_then(|m| tesouro::ticker_from_type_and_maturity(title, m)))
+            .ok_or_else(|| anyhow!("Row {}: unknown Tesouro title '{}'", row + 2, title))?;
+
+        let quantity = parse_amount(field(qty_idx))
+            .with_context(|| format!("Row {}: invalid quantity", row + 2))?;
+        if quantity <= Decimal::ZERO {
+            continue;
+        }
+        let unit_price = optional(price_idx);
+        let value = optional(value_idx);
+        let (unit_price, value) = match (unit_price, value) {
+            (Some(price), Some(value)) => (price, value),
+            (Some(price), None) => (price, (price * quantity).round_dp(2)),
+            (None, Some(value)) => (value / quantity, value),
+            (None, None) => {
+                return Err(anyhow!("Row {}: missing unit price and value", row + 2));
+            }
+        };
+
+        entries.push(TesouroExtratoEntry {
+            date,
+            title: title.to_string(),
+            ticker,
+            operation,
+            quantity,
+            unit_price,
+            value,
+            ir: optional(ir_idx),
+            iof: optional(iof_idx),
+            custody_fee: optional(custody_idx),
+        });
+    }
+
+    entries.sort_by_key(|e| e.date);
+    info!("Parsed {} Tesouro Direto operations", entries.len());
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_extrato_purchases_and_redemptions() {
+        let csv = "Título;Vencimento;Data da Operação;Operação;Quantidade;Preço Unitário;Valor;IR;Taxa de Custódia\n\
+Tesouro IPCA+ 2035;15/05/2035;10/03/2025;Resgate antecipado;0,50;R$ 2.100,00;R$ 1.050,00;R$ 12,34;R$ 1,20\n\
+Tesouro IPCA+;15/05/2035;02/01/2024;Compra;1,00;1.980,55;1.980,55;;\n\
+Tesouro Selic 2029;01/03/2029;05/02/2024;Agendamento;1,00;15.000,00;;;\n";
+
+        let entries = parse_tesouro_extrato_content(csv).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(is_extrato_header(csv.lines().next().unwrap()));
+
+        let buy = &entries[0];
+        assert_eq!(buy.ticker, "TESOURO_IPCA_2035");
+        assert_eq!(buy.operation, TransactionType::Buy);
+        assert_eq!(buy.value, dec!(1980.55));
+
+        let sell = &entries[1];
+        assert_eq!(sell.operation, TransactionType::Sell);
+        assert_eq!(sell.quantity, dec!(0.5));
+        assert_eq!(sell.ir, Some(dec!(12.34)));
+        let tx = sell.to_transaction(1);
+        assert_eq!(tx.fees, dec!(1.20));
+        assert_eq!(tx.total_cost, dec!(1050));
+    }
+}
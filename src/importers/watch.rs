@@ -0,0 +1,127 @@
+//! Watched-folder scanning for automatic imports.
+//!
+//! Finds settled files matching the configured glob patterns and moves them
+//! to the archive or failed folders once processed.
+
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Files modified more recently than this are assumed to still be downloading
+const SETTLE_TIME: Duration = Duration::from_secs(5);
+
+/// Case-insensitive glob match supporting `*` and `?` on a file name
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+
+    let (mut p, mut n) = (0usize, 0usize);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// List settled files in `folder` matching any of `patterns`, oldest first
+pub fn find_candidates(folder: &Path, patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let now = SystemTime::now();
+    let mut candidates = Vec::new();
+
+    let entries =
+        std::fs::read_dir(folder).with_context(|| format!("Failed to read {:?}", folder))?;
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !patterns.iter().any(|p| glob_match(p, &name)) {
+            continue;
+        }
+        let modified = metadata.modified().unwrap_or(now);
+        if now.duration_since(modified).unwrap_or_default() < SETTLE_TIME {
+            continue;
+        }
+        candidates.push((modified, entry.path()));
+    }
+
+    candidates.sort();
+    Ok(candidates.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Move a processed file into `dest_dir`, avoiding name collisions
+pub fn move_to(path: &Path, dest_dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dest_dir)
+        .with_context(|| format!("Failed to create {:?}", dest_dir))?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid file path {:?}", path))?;
+
+    let mut dest = dest_dir.join(file_name);
+    let mut counter = 1;
+    while dest.exists() {
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ext = path
+            .extension()
+            .map(|e| format!(".{}", e.to_string_lossy()))
+            .unwrap_or_default();
+        dest = dest_dir.join(format!("{}-{}{}", stem, counter, ext));
+        counter += 1;
+    }
+
+    // rename fails across filesystems; fall back to copy + remove
+    if std::fs::rename(path, &dest).is_err() {
+        std::fs::copy(path, &dest).with_context(|| format!("Failed to archive {:?}", path))?;
+        std::fs::remove_file(path)?;
+    }
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.xlsx", "movimentacao-2024.XLSX"));
+        assert!(glob_match(
+            "movimentacao-*.xlsx",
+            "movimentacao-2024-01.xlsx"
+        ));
+        assert!(glob_match("negociacao-????.xlsx", "negociacao-2024.xlsx"));
+        assert!(!glob_match("*.xlsx", "movimentacao.xlsx.crdownload"));
+        assert!(!glob_match("movimentacao-*.xlsx", "negociacao-2024.xlsx"));
+    }
+
+    #[test]
+    fn test_move_to_avoids_collisions() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let archive = dir.path().join("archive");
+        std::fs::create_dir_all(&archive).unwrap();
+        std::fs::write(archive.join("file.xlsx"), b"old").unwrap();
+
+        let src = dir.path().join("file.xlsx");
+        std::fs::write(&src, b"new").unwrap();
+        let dest = move_to(&src, &archive).unwrap();
+
+        assert_eq!(dest, archive.join("file-1.xlsx"));
+        assert!(!src.exists());
+    }
+}
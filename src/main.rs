@@ -1,12 +1,17 @@
 mod cli;
 mod commands;
+mod config;
 mod corporate_actions;
+mod daemon;
 mod db;
 mod dispatcher;
+mod fixed_income;
 mod importers;
+mod options;
 mod pricing;
 mod reports;
 mod scraping;
+mod subscriptions;
 mod tax;
 mod term_contracts;
 mod tesouro;
@@ -41,11 +46,15 @@ async fn main() -> Result<()> {
     }
 
     // Parse CLI first to configure logging and color
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
 
     // Determine color usage: disable when requested or when stdout is not a TTY (piped)
     let stdout_is_tty = std::io::stdout().is_terminal();
     let disable_color = cli.no_color || !stdout_is_tty || cli.json;
+    // Windows consoles render ANSI colors only once virtual terminal
+    // processing is on; consoles too old for it get plain text
+    #[cfg(windows)]
+    let disable_color = disable_color || colored::control::set_virtual_terminal(true).is_err();
 
     // Initialize logging - always write to stderr to keep stdout clean
     let env_filter = EnvFilter::try_from_default_env()
@@ -65,7 +74,7 @@ async fn main() -> Result<()> {
 
     // If no command is given, print the top-level help instead of
     // automatically launching the interactive TUI.
-    let command = match cli.command {
+    let command = match cli.command.take() {
         Some(cmd) => cmd,
         None => {
             let opts = crate::cli::help::RenderOpts::default();
@@ -74,6 +83,70 @@ async fn main() -> Result<()> {
         }
     };
 
+    let sandboxed = cli.sandbox || cli.demo || matches!(command, Commands::Sandbox { .. });
+    if db::encryption::unlock()? && sandboxed {
+        db::encryption::lock()?;
+        anyhow::bail!("Sandbox mode is not available while the database is encrypted");
+    }
+    // Always seal the working copy back, whatever the command's outcome
+    let result = run(&cli, command).await;
+    db::encryption::lock()?;
+
+    // With --json, failures are reported on stdout as well, in the same
+    // envelope batch commands use, so scripts only parse one stream
+    if let (true, Err(e)) = (cli.json, &result) {
+        let payload = serde_json::json!({
+            "success": false,
+            "error": {
+                "message": e.to_string(),
+                "causes": e.chain().skip(1).map(|c| c.to_string()).collect::<Vec<_>>(),
+            },
+        });
+        println!("{}", serde_json::to_string_pretty(&payload)?);
+    }
+    result
+}
+
+async fn run(cli: &Cli, command: Commands) -> Result<()> {
+    if cli.demo {
+        // Prices come from the bundled cassette; downloads it does not cover
+        // (COTAHIST, Tesouro, CVM) are skipped
+        pricing::cassette::use_demo()?;
+        std::env::set_var("INTEREST_OFFLINE", "1");
+    }
+    if (cli.sandbox || cli.demo) && !matches!(command, Commands::Sandbox { .. }) {
+        let created = db::sandbox::activate()?;
+        // stderr keeps --json output clean
+        eprintln!(
+            "{}",
+            colored::Colorize::dimmed(
+                format!(
+                    "🧪 Sandbox{}: changes go to {} (interest sandbox promote/discard)",
+                    if created { " created" } else { "" },
+                    db::sandbox::sandbox_path()?.display()
+                )
+                .as_str()
+            )
+        );
+    }
+
+    if let Some(name) = &cli.portfolio {
+        if !matches!(command, Commands::Portfolios { .. }) {
+            db::init_database(None)?;
+            let conn = db::open_db(None)?;
+            let portfolio = db::portfolio::require_portfolio(&conn, name)?;
+            db::portfolio::set_scope(&[portfolio.id]);
+        }
+    }
+    if let Some(declarant) = &cli.declarant {
+        if !matches!(command, Commands::Portfolios { .. }) {
+            db::init_database(None)?;
+            let conn = db::open_db(None)?;
+            let ids = db::portfolio::require_declarant(&conn, declarant)?;
+            db::portfolio::set_scope(&ids);
+        }
+    }
+
     if matches!(command, Commands::Interactive) {
         return crate::ui::launch_tui().await;
     }
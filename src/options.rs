@@ -0,0 +1,631 @@
+//! Equity Options (Opções) Handling
+//!
+//! B3 option tickers encode most of the contract:
+//! - PETR + A + 123: underlying root, series letter, strike code
+//! - Letters A-L are calls expiring January to December, M-X are puts
+//! - Weekly series add W1-W5, and "E" marks a strike adjusted for dividends
+//!
+//! The strike code is not the strike price, so strikes are recorded with
+//! `options set` or learned from an exercise (it trades at the strike).
+//! Series expire on the third Friday of their month; a bought option still
+//! held after that expires worthless and its whole premium becomes a loss.
+//! An exercise closes the option at its average premium (no gain or loss)
+//! and moves the premium into the underlying trade: it adds to the cost of
+//! shares bought through a call and comes off the proceeds of shares sold
+//! through a put. Only bought (long) options are supported; written options
+//! need short positions, which average cost does not track.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, Weekday};
+use rusqlite::{Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::db::{self, AssetType, Transaction, TransactionType};
+
+/// Call (direito de compra) or put (direito de venda)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum OptionKind {
+    Call,
+    Put,
+}
+
+impl OptionKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            OptionKind::Call => "Call",
+            OptionKind::Put => "Put",
+        }
+    }
+}
+
+/// What a B3 option ticker says about its series
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OptionTicker {
+    /// First four letters, shared with the underlying (PETR for PETR4)
+    pub root: String,
+    pub kind: OptionKind,
+    /// Month the series expires (1-12); the year is not in the ticker
+    pub expiry_month: u32,
+    /// Strike code and suffix (123, 123E, 123W2)
+    pub series: String,
+    pub weekly: bool,
+}
+
+/// Decode an option ticker such as PETRA123 or BOVAX120W2
+pub fn decode_option_ticker(ticker: &str) -> Option<OptionTicker> {
+    let ticker = ticker.trim().to_ascii_uppercase();
+    if !(7..=11).contains(&ticker.len()) || !ticker.is_ascii() {
+        return None;
+    }
+    let (root, rest) = ticker.split_at(4);
+    if !root.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+
+    let letter = rest.chars().next()?;
+    let (kind, expiry_month) = match letter {
+        'A'..='L' => (OptionKind::Call, letter as u32 - 'A' as u32 + 1),
+        'M'..='X' => (OptionKind::Put, letter as u32 - 'M' as u32 + 1),
+        _ => return None,
+    };
+
+    let series = &rest[1..];
+    let digits = series.chars().take_while(|c| c.is_ascii_digit()).count();
+    if !(2..=3).contains(&digits) {
+        return None;
+    }
+    let weekly = match &series[digits..] {
+        "" | "E" => false,
+        "W1" | "W2" | "W3" | "W4" | "W5" => true,
+        _ => return None,
+    };
+
+    Some(OptionTicker {
+        root: root.to_string(),
+        kind,
+        expiry_month,
+        series: series.to_string(),
+        weekly,
+    })
+}
+
+pub fn is_option_ticker(ticker: &str) -> bool {
+    decode_option_ticker(ticker).is_some()
+}
+
+/// Third Friday of a month, when monthly series expire
+pub fn third_friday(year: i32, month: u32) -> Option<NaiveDate> {
+    NaiveDate::from_weekday_of_month_opt(year, month, Weekday::Fri, 3)
+}
+
+/// Expiry of a series bought or sold on `trade_date`: the first expiry of
+/// its month on or after that day
+pub fn expiry_on_or_after(expiry_month: u32, trade_date: NaiveDate) -> Option<NaiveDate> {
+    let this_year = third_friday(trade_date.year(), expiry_month)?;
+    if this_year >= trade_date {
+        Some(this_year)
+    } else {
+        third_friday(trade_date.year() + 1, expiry_month)
+    }
+}
+
+/// Add a worthless sale at each expiry where bought options were still held.
+///
+/// `transactions` must be in date order; each sale is placed after the other
+/// trades of its day (an exercise on expiry day closes the position first).
+/// B3 reuses tickers every year, so each position gets the expiry following
+/// the purchase that opened it. Other assets are returned unchanged.
+pub fn add_expiry_sales(
+    ticker: &str,
+    asset_id: i64,
+    transactions: Vec<Transaction>,
+    as_of: NaiveDate,
+) -> Vec<Transaction> {
+    let Some(option) = decode_option_ticker(ticker) else {
+        return transactions;
+    };
+
+    let mut result = Vec::with_capacity(transactions.len() + 1);
+    let mut open = Decimal::ZERO;
+    let mut expiry: Option<NaiveDate> = None;
+    for tx in transactions {
+        if let Some(date) = expiry {
+            if open > Decimal::ZERO && tx.trade_date > date {
+                result.push(expiry_sale(asset_id, date, open));
+                open = Decimal::ZERO;
+            }
+        }
+        match tx.transaction_type {
+            TransactionType::Buy => {
+                if open <= Decimal::ZERO {
+                    expiry = expiry_on_or_after(option.expiry_month, tx.trade_date);
+                }
+                open += tx.quantity;
+            }
+            TransactionType::Sell => open -= tx.quantity,
+        }
+        result.push(tx);
+    }
+    if let Some(date) = expiry {
+        if open > Decimal::ZERO && date <= as_of {
+            result.push(expiry_sale(asset_id, date, open));
+        }
+    }
+    result
+}
+
+fn expiry_sale(asset_id: i64, date: NaiveDate, quantity: Decimal) -> Transaction {
+    Transaction {
+        id: None,
+        asset_id,
+        transaction_type: TransactionType::Sell,
+        trade_date: date,
+        settlement_date: Some(date),
+        quantity,
+        price_per_unit: Decimal::ZERO,
+        total_cost: Decimal::ZERO,
+        fees: Decimal::ZERO,
+        is_day_trade: false,
+        quota_issuance_date: None,
+        notes: Some("Option expired worthless".to_string()),
+        source: "OPTION_EXPIRY".to_string(),
+        created_at: chrono::Utc::now(),
+    }
+}
+
+/// Underlying ticker of an option: the one recorded with `options set` or by
+/// an exercise, otherwise the first known asset sharing its root
+pub fn underlying_ticker(
+    conn: &Connection,
+    asset_id: i64,
+    option: &OptionTicker,
+) -> Result<Option<String>> {
+    let recorded: Option<String> = conn
+        .query_row(
+            "SELECT underlying FROM option_contracts WHERE asset_id = ?1",
+            [asset_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    if recorded.is_some() {
+        return Ok(recorded);
+    }
+    for class in ["4", "3", "11", "5", "6"] {
+        let candidate = format!("{}{}", option.root, class);
+        if db::asset_exists(conn, &candidate)? {
+            return Ok(Some(candidate));
+        }
+    }
+    Ok(None)
+}
+
+/// Strike recorded for an option series
+pub fn strike(conn: &Connection, asset_id: i64) -> Result<Option<Decimal>> {
+    let strike = conn
+        .query_row(
+            "SELECT strike FROM option_contracts WHERE asset_id = ?1",
+            [asset_id],
+            |row| db::get_optional_decimal_value(row, 0),
+        )
+        .optional()?
+        .flatten();
+    Ok(strike)
+}
+
+/// Record the strike and/or underlying of an option series
+pub fn set_option_contract(
+    conn: &Connection,
+    ticker: &str,
+    underlying: Option<&str>,
+    strike: Option<Decimal>,
+) -> Result<()> {
+    if !is_option_ticker(ticker) {
+        anyhow::bail!("{} is not a B3 option ticker", ticker);
+    }
+    let asset = db::get_asset_by_ticker(conn, ticker)?
+        .ok_or_else(|| anyhow::anyhow!("Asset {} not found", ticker))?;
+    let asset_id = asset.id.expect("asset from database must have id");
+
+    conn.execute(
+        "INSERT INTO option_contracts (asset_id, underlying, strike)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(asset_id) DO UPDATE SET
+             underlying = COALESCE(excluded.underlying, underlying),
+             strike = COALESCE(excluded.strike, strike)",
+        rusqlite::params![
+            asset_id,
+            underlying.map(|u| u.trim().to_ascii_uppercase()),
+            strike.map(|s| s.to_string())
+        ],
+    )?;
+    Ok(())
+}
+
+/// Outcome of matching exercises to option positions
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExerciseSummary {
+    /// Option assets whose type was corrected to OPTION
+    pub reclassified: usize,
+    pub exercised: usize,
+    /// Exercises of written options, which are not supported
+    pub written_skipped: usize,
+    /// Exercises with no bought option position to close
+    pub unmatched: usize,
+}
+
+/// Close exercised options and move their premium into the underlying trade.
+///
+/// Exercises come from the negociação export, whose exercise trades are
+/// recorded on the underlying with "exercício de opção via TICKER" in their
+/// notes. Each one is processed once (see `option_exercises`).
+pub fn process_exercises(conn: &Connection) -> Result<ExerciseSummary> {
+    let mut summary = ExerciseSummary::default();
+
+    for asset in db::get_all_assets(conn)? {
+        if is_option_ticker(&asset.ticker) && asset.asset_type != AssetType::Option {
+            db::update_asset_type(conn, &asset.ticker, &AssetType::Option)?;
+            summary.reclassified += 1;
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.notes FROM transactions t
+         WHERE t.notes LIKE '%exercício de opção via %'
+           AND NOT EXISTS (SELECT 1 FROM option_exercises e WHERE e.transaction_id = t.id)
+         ORDER BY t.trade_date, t.id",
+    )?;
+    let pending = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(i64, String)>>>()?;
+
+    for (tx_id, notes) in pending {
+        let Some(exercise) = db::get_transaction(conn, tx_id)? else {
+            continue;
+        };
+        let Some(option_ticker) = exercised_option_ticker(&notes) else {
+            continue;
+        };
+        let Some(option) = decode_option_ticker(&option_ticker) else {
+            summary.unmatched += 1;
+            continue;
+        };
+
+        let holder = matches!(
+            (option.kind, &exercise.transaction_type),
+            (OptionKind::Call, TransactionType::Buy) | (OptionKind::Put, TransactionType::Sell)
+        );
+        if !holder {
+            warn!(
+                "Exercise of written option {} on {} is not supported",
+                option_ticker, exercise.trade_date
+            );
+            summary.written_skipped += 1;
+            continue;
+        }
+
+        let Some(option_asset) = db::get_asset_by_ticker(conn, &option_ticker)? else {
+            warn!("No purchase of option {} to exercise", option_ticker);
+            summary.unmatched += 1;
+            continue;
+        };
+        let option_id = option_asset.id.expect("asset from database must have id");
+        let (open, average_premium) =
+            position_before(conn, &option_ticker, option_id, exercise.trade_date)?;
+        let quantity = open.min(exercise.quantity);
+        if quantity <= Decimal::ZERO {
+            warn!(
+                "No open position in {} on {} to exercise",
+                option_ticker, exercise.trade_date
+            );
+            summary.unmatched += 1;
+            continue;
+        }
+        let premium = (average_premium * quantity).round_dp(2);
+
+        let closing_id = db::insert_transaction(
+            conn,
+            &Transaction {
+                id: None,
+                asset_id: option_id,
+                transaction_type: TransactionType::Sell,
+                trade_date: exercise.trade_date,
+                settlement_date: exercise.settlement_date,
+                quantity,
+                price_per_unit: average_premium,
+                total_cost: premium,
+                fees: Decimal::ZERO,
+                is_day_trade: false,
+                quota_issuance_date: None,
+                notes: Some(format!(
+                    "Option exercised (premium moved to transaction {})",
+                    tx_id
+                )),
+                source: "OPTION_EXERCISE".to_string(),
+                created_at: chrono::Utc::now(),
+            },
+        )?;
+        // The closing trade belongs to the same account as the exercise
+        conn.execute(
+            "UPDATE transactions SET
+                 portfolio_id = (SELECT portfolio_id FROM transactions WHERE id = ?2),
+                 broker_id = (SELECT broker_id FROM transactions WHERE id = ?2)
+             WHERE id = ?1",
+            [closing_id, tx_id],
+        )?;
+
+        let adjusted_total = match exercise.transaction_type {
+            TransactionType::Buy => exercise.total_cost + premium,
+            TransactionType::Sell => exercise.total_cost - premium,
+        };
+        conn.execute(
+            "UPDATE transactions SET total_cost = ?1, notes = ?2 WHERE id = ?3",
+            rusqlite::params![
+                adjusted_total.to_string(),
+                format!("{}; prêmio de {} incluído", notes, premium),
+                tx_id
+            ],
+        )?;
+
+        let underlying: String = conn.query_row(
+            "SELECT ticker FROM assets WHERE id = ?1",
+            [exercise.asset_id],
+            |row| row.get(0),
+        )?;
+        conn.execute(
+            "INSERT INTO option_contracts (asset_id, underlying, strike)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(asset_id) DO UPDATE SET
+                 underlying = COALESCE(underlying, excluded.underlying),
+                 strike = excluded.strike",
+            rusqlite::params![option_id, underlying, exercise.price_per_unit.to_string()],
+        )?;
+        conn.execute(
+            "INSERT INTO option_exercises
+                 (transaction_id, option_asset_id, closing_transaction_id, quantity, premium)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                tx_id,
+                option_id,
+                closing_id,
+                quantity.to_string(),
+                premium.to_string()
+            ],
+        )?;
+
+        info!(
+            "Exercised {} {} on {}: premium {} moved to transaction {}",
+            quantity, option_ticker, exercise.trade_date, premium, tx_id
+        );
+        summary.exercised += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Option ticker in the notes of an exercise trade
+fn exercised_option_ticker(notes: &str) -> Option<String> {
+    let (_, rest) = notes.split_once("exercício de opção via ")?;
+    let ticker: String = rest
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    (!ticker.is_empty()).then(|| ticker.to_ascii_uppercase())
+}
+
+/// Quantity held and average premium paid up to `date`, expiries included
+fn position_before(
+    conn: &Connection,
+    ticker: &str,
+    asset_id: i64,
+    date: NaiveDate,
+) -> Result<(Decimal, Decimal)> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM transactions WHERE asset_id = ?1 AND trade_date <= ?2
+         ORDER BY trade_date, id",
+    )?;
+    let ids = stmt
+        .query_map(rusqlite::params![asset_id, date], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+    let mut transactions = Vec::with_capacity(ids.len());
+    for id in ids {
+        transactions.extend(db::get_transaction(conn, id)?);
+    }
+
+    let mut quantity = Decimal::ZERO;
+    let mut cost = Decimal::ZERO;
+    // Exercises happen up to expiry day, so only earlier expiries count
+    let before = date.pred_opt().unwrap_or(date);
+    for tx in add_expiry_sales(ticker, asset_id, transactions, before) {
+        match tx.transaction_type {
+            TransactionType::Buy => {
+                quantity += tx.quantity;
+                cost += tx.total_cost;
+            }
+            TransactionType::Sell if quantity > Decimal::ZERO => {
+                let sold = tx.quantity.min(quantity);
+                cost -= cost / quantity * sold;
+                quantity -= sold;
+            }
+            TransactionType::Sell => {}
+        }
+    }
+    let average = if quantity > Decimal::ZERO {
+        cost / quantity
+    } else {
+        Decimal::ZERO
+    };
+    Ok((quantity, average))
+}
+
+/// An option series currently held
+#[derive(Debug, Clone, Serialize)]
+pub struct OptionPosition {
+    pub ticker: String,
+    pub kind: OptionKind,
+    pub underlying: Option<String>,
+    pub strike: Option<Decimal>,
+    pub expiry: Option<NaiveDate>,
+    pub quantity: Decimal,
+    pub average_premium: Decimal,
+    pub total_premium: Decimal,
+}
+
+/// Bought options held today, soonest expiry first
+pub fn open_positions(conn: &Connection) -> Result<Vec<OptionPosition>> {
+    let mut positions = Vec::new();
+    for position in crate::reports::calculate_portfolio(conn, None)?.positions {
+        let Some(option) = decode_option_ticker(&position.asset.ticker) else {
+            continue;
+        };
+        let Some(asset_id) = position.asset.id else {
+            continue;
+        };
+        let last_buy: Option<NaiveDate> = conn.query_row(
+            "SELECT MAX(trade_date) FROM transactions
+             WHERE asset_id = ?1 AND transaction_type = 'BUY'",
+            [asset_id],
+            |row| row.get(0),
+        )?;
+        positions.push(OptionPosition {
+            underlying: underlying_ticker(conn, asset_id, &option)?,
+            strike: strike(conn, asset_id)?,
+            expiry: last_buy.and_then(|d| expiry_on_or_after(option.expiry_month, d)),
+            ticker: position.asset.ticker,
+            kind: option.kind,
+            quantity: position.quantity,
+            average_premium: position.average_cost,
+            total_premium: position.total_cost,
+        });
+    }
+    positions.sort_by_key(|p| (p.expiry, p.ticker.clone()));
+    Ok(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn trade(
+        asset_id: i64,
+        transaction_type: TransactionType,
+        date: NaiveDate,
+        quantity: Decimal,
+        price: Decimal,
+    ) -> Transaction {
+        Transaction {
+            id: None,
+            asset_id,
+            transaction_type,
+            trade_date: date,
+            settlement_date: None,
+            quantity,
+            price_per_unit: price,
+            total_cost: quantity * price,
+            fees: Decimal::ZERO,
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: None,
+            source: "TEST".to_string(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_decode_option_tickers() {
+        let call = decode_option_ticker("PETRA123").unwrap();
+        assert_eq!(call.root, "PETR");
+        assert_eq!(call.kind, OptionKind::Call);
+        assert_eq!(call.expiry_month, 1);
+        assert_eq!(call.series, "123");
+
+        let put = decode_option_ticker("bovax120w2").unwrap();
+        assert_eq!(put.kind, OptionKind::Put);
+        assert_eq!(put.expiry_month, 12);
+        assert!(put.weekly);
+        assert!(decode_option_ticker("VALEF75E").is_some());
+
+        for ticker in ["PETR4", "TAEE11", "ANIM3T", "PETR4F", "VALEB1", "XPML11"] {
+            assert!(!is_option_ticker(ticker), "{}", ticker);
+        }
+        assert_eq!(
+            expiry_on_or_after(1, NaiveDate::from_ymd_opt(2024, 1, 20).unwrap()),
+            NaiveDate::from_ymd_opt(2025, 1, 17)
+        );
+    }
+
+    #[test]
+    fn test_exercise_moves_premium_and_expiry_is_total_loss() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("db/schema.sql")).unwrap();
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+        let petr4 = db::insert_asset(&conn, "PETR4", &AssetType::Stock, None).unwrap();
+        let call = db::insert_asset(&conn, "PETRB300", &AssetType::Unknown, None).unwrap();
+        let put = db::insert_asset(&conn, "PETRN280", &AssetType::Option, None).unwrap();
+
+        // Call bought in January, exercised on February's expiry (16/02/2024)
+        db::insert_transaction(
+            &conn,
+            &trade(
+                call,
+                TransactionType::Buy,
+                d(2024, 1, 10),
+                dec!(100),
+                dec!(1.5),
+            ),
+        )
+        .unwrap();
+        let mut exercise = trade(
+            petr4,
+            TransactionType::Buy,
+            d(2024, 2, 16),
+            dec!(100),
+            dec!(30),
+        );
+        exercise.notes = Some("Exercício de Opção (exercício de opção via PETRB300)".into());
+        let exercise_id = db::insert_transaction(&conn, &exercise).unwrap();
+        // Put bought and left to expire
+        db::insert_transaction(
+            &conn,
+            &trade(
+                put,
+                TransactionType::Buy,
+                d(2024, 1, 12),
+                dec!(200),
+                dec!(0.4),
+            ),
+        )
+        .unwrap();
+
+        let summary = process_exercises(&conn).unwrap();
+        assert_eq!((summary.exercised, summary.reclassified), (1, 1));
+        assert_eq!(process_exercises(&conn).unwrap().exercised, 0);
+
+        let shares = db::get_transaction(&conn, exercise_id).unwrap().unwrap();
+        assert_eq!(shares.total_cost, dec!(3150));
+        assert_eq!(strike(&conn, call).unwrap(), Some(dec!(30)));
+
+        let sales = crate::tax::swing_trade::realized_sales_for_year(&conn, 2024).unwrap();
+        let result = |asset_id: i64| {
+            sales
+                .iter()
+                .find(|(_, s)| s.asset_id == asset_id)
+                .map(|(_, s)| (s.sale_date, s.profit_loss))
+                .unwrap()
+        };
+        assert_eq!(result(call), (d(2024, 2, 16), Decimal::ZERO));
+        assert_eq!(result(put), (d(2024, 2, 16), dec!(-80)));
+
+        let portfolio =
+            crate::reports::calculate_portfolio_at_date(&conn, d(2024, 3, 1), None).unwrap();
+        let held: Vec<&str> = portfolio
+            .positions
+            .iter()
+            .map(|p| p.asset.ticker.as_str())
+            .collect();
+        assert_eq!(held, vec!["PETR4"]);
+    }
+}
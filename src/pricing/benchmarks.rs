@@ -0,0 +1,180 @@
+//! Benchmark series fetchers (IBOV, IFIX, CDI, SELIC, IPCA).
+//!
+//! Index levels come from Yahoo Finance; the CDI and SELIC daily rates and
+//! the IPCA monthly rate come from the Banco Central SGS API (exchange rates
+//! in `pricing::fx` use the separate Olinda PTAX service). Values are stored
+//! in `benchmark_history` and consumed by `reports::benchmark`.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::db::{self, Benchmark, BenchmarkValue};
+
+const BCB_SGS_URL: &str = "https://api.bcb.gov.br/dados/serie";
+
+/// SGS series code of a rate benchmark (CDI and SELIC daily, IPCA monthly)
+fn sgs_series(benchmark: Benchmark) -> Option<u32> {
+    match benchmark {
+        Benchmark::Cdi => Some(12),
+        Benchmark::Selic => Some(11),
+        Benchmark::Ipca => Some(433),
+        Benchmark::Ibov | Benchmark::Ifix => None,
+    }
+}
+
+/// SGS limits daily series queries to 10 years per request
+const BCB_MAX_YEARS_PER_REQUEST: i32 = 10;
+
+#[derive(Debug, Deserialize)]
+struct SgsEntry {
+    data: String,
+    valor: String,
+}
+
+/// Yahoo symbol used for index benchmarks
+fn yahoo_symbol(benchmark: Benchmark) -> Option<&'static str> {
+    match benchmark {
+        Benchmark::Ibov => Some("^BVSP"),
+        Benchmark::Ifix => Some("IFIX.SA"),
+        Benchmark::Cdi | Benchmark::Selic | Benchmark::Ipca => None,
+    }
+}
+
+/// Fetch a benchmark series for the given date range
+pub async fn fetch_benchmark_history(
+    benchmark: Benchmark,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<BenchmarkValue>> {
+    if from > to {
+        anyhow::bail!("Benchmark range start must be <= end");
+    }
+
+    if let Some(symbol) = yahoo_symbol(benchmark) {
+        let prices = crate::pricing::yahoo::fetch_historical_prices_for_symbol(symbol, from, to)
+            .await
+            .with_context(|| {
+                format!("Failed to fetch {} from Yahoo Finance", benchmark.as_str())
+            })?;
+        return Ok(prices
+            .into_iter()
+            .map(|p| BenchmarkValue {
+                benchmark,
+                value_date: p.date,
+                value: p.close,
+                source: "YAHOO".to_string(),
+            })
+            .collect());
+    }
+
+    let series = sgs_series(benchmark)
+        .ok_or_else(|| anyhow!("No source for benchmark {}", benchmark.as_str()))?;
+    Ok(fetch_sgs_series(series, from, to)
+        .await?
+        .into_iter()
+        .map(|(value_date, value)| BenchmarkValue {
+            benchmark,
+            value_date,
+            value,
+            source: "BCB".to_string(),
+        })
+        .collect())
+}
+
+/// Fetch a daily BCB SGS series as (date, value) pairs
+pub(crate) async fn fetch_sgs_series(
+    series: u32,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<(NaiveDate, Decimal)>> {
+    let client = reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (compatible; InterestBot/1.0)")
+        .build()?;
+
+    let mut values = Vec::new();
+    let mut chunk_start = from;
+    while chunk_start <= to {
+        let chunk_end =
+            NaiveDate::from_ymd_opt(chunk_start.year() + BCB_MAX_YEARS_PER_REQUEST - 1, 12, 31)
+                .map(|d| d.min(to))
+                .unwrap_or(to);
+
+        info!(
+            "Fetching SGS series {} from {} to {}",
+            series, chunk_start, chunk_end
+        );
+        let url = format!(
+            "{}/bcdata.sgs.{}/dados?formato=json&dataInicial={}&dataFinal={}",
+            BCB_SGS_URL,
+            series,
+            chunk_start.format("%d/%m/%Y"),
+            chunk_end.format("%d/%m/%Y")
+        );
+        let body = super::cassette::get(&client, &url, "BCB SGS").await?;
+        values.extend(parse_sgs_response(&body)?);
+
+        chunk_start = match chunk_end.succ_opt() {
+            Some(next) => next,
+            None => break,
+        };
+    }
+
+    Ok(values)
+}
+
+fn parse_sgs_response(body: &str) -> Result<Vec<(NaiveDate, Decimal)>> {
+    let entries: Vec<SgsEntry> =
+        serde_json::from_str(body).context("Failed to parse BCB SGS response")?;
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let value_date = NaiveDate::parse_from_str(&entry.data, "%d/%m/%Y")
+                .with_context(|| format!("Invalid SGS date: {}", entry.data))?;
+            let value = Decimal::from_str(entry.valor.trim())
+                .with_context(|| format!("Invalid SGS value: {}", entry.valor))?;
+            Ok((value_date, value))
+        })
+        .collect()
+}
+
+/// Fetch and store a benchmark series, returning the number of stored values
+pub async fn update_benchmark(
+    conn: &Connection,
+    benchmark: Benchmark,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<usize> {
+    let values = fetch_benchmark_history(benchmark, from, to).await?;
+    for value in &values {
+        db::insert_benchmark_value(conn, value)?;
+    }
+    Ok(values.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sgs_response() {
+        let body = r#"[{"data":"02/01/2024","valor":"0.043739"},{"data":"03/01/2024","valor":"0.043739"}]"#;
+        let values = parse_sgs_response(body).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].0, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(values[0].1, Decimal::from_str("0.043739").unwrap());
+    }
+
+    #[test]
+    fn test_yahoo_symbols() {
+        assert_eq!(yahoo_symbol(Benchmark::Ibov), Some("^BVSP"));
+        assert_eq!(yahoo_symbol(Benchmark::Cdi), None);
+        assert_eq!(sgs_series(Benchmark::Ipca), Some(433));
+        assert_eq!(sgs_series(Benchmark::Ibov), None);
+    }
+}
@@ -0,0 +1,227 @@
+//! Recorded provider responses (VCR-style cassettes).
+//!
+//! Yahoo Finance and BCB SGS requests go through [`get`]. With
+//! `INTEREST_CASSETTE=<file>` set they are answered from the file instead of
+//! the network, so tests and demos get the same prices on every run; adding
+//! `INTEREST_CASSETTE_RECORD=1` sends them for real and saves each response to
+//! the file. A request whose exact URL was not recorded replays the latest
+//! recording of the same endpoint (a chart for another date range), and one
+//! with no recording at all fails rather than reaching the network.
+//!
+//! `--demo` replays the cassette bundled with the binary.
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tracing::debug;
+
+const DEMO_CASSETTE: &str = include_str!("demo_cassette.json");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    url: String,
+    status: u16,
+    body: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Cassette {
+    interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    fn parse(raw: &str) -> Result<Self> {
+        serde_json::from_str(raw).context("Invalid cassette file")
+    }
+
+    fn find(&self, url: &str) -> Option<&Interaction> {
+        self.interactions
+            .iter()
+            .rev()
+            .find(|i| i.url == url)
+            .or_else(|| {
+                let wanted = endpoint(url);
+                self.interactions
+                    .iter()
+                    .rev()
+                    .find(|i| endpoint(&i.url) == wanted)
+            })
+    }
+}
+
+/// The URL without its query string
+fn endpoint(url: &str) -> &str {
+    url.split_once('?').map_or(url, |(path, _)| path)
+}
+
+enum Mode {
+    Live,
+    Replay(Cassette),
+    Record(PathBuf, Mutex<Cassette>),
+}
+
+static MODE: OnceLock<Mode> = OnceLock::new();
+
+fn mode_from_env() -> Result<Mode> {
+    let Some(path) = std::env::var_os("INTEREST_CASSETTE").map(PathBuf::from) else {
+        return Ok(Mode::Live);
+    };
+    let record = std::env::var("INTEREST_CASSETTE_RECORD")
+        .map(|v| v != "0")
+        .unwrap_or(false);
+    let cassette = match std::fs::read_to_string(&path) {
+        Ok(raw) => Cassette::parse(&raw).with_context(|| format!("Reading {:?}", path))?,
+        Err(e) if record && e.kind() == std::io::ErrorKind::NotFound => Cassette::default(),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read cassette {:?}", path)),
+    };
+    Ok(if record {
+        Mode::Record(path, Mutex::new(cassette))
+    } else {
+        Mode::Replay(cassette)
+    })
+}
+
+fn mode() -> Result<&'static Mode> {
+    if let Some(mode) = MODE.get() {
+        return Ok(mode);
+    }
+    let mode = mode_from_env()?;
+    Ok(MODE.get_or_init(|| mode))
+}
+
+/// Replay the bundled demo cassette for the rest of the process
+pub fn use_demo() -> Result<()> {
+    MODE.set(Mode::Replay(Cassette::parse(DEMO_CASSETTE)?))
+        .map_err(|_| anyhow!("Provider responses were already set up"))
+}
+
+/// A provider response, error statuses included
+#[derive(Debug, Clone)]
+pub struct Reply {
+    pub status: StatusCode,
+    /// The `Retry-After` delay the server asked for; never set on replay
+    pub retry_after: Option<Duration>,
+    pub body: String,
+}
+
+/// Whether requests are answered from a cassette instead of the network
+pub fn is_replay() -> Result<bool> {
+    Ok(matches!(mode()?, Mode::Replay(_)))
+}
+
+/// Whether requests go to the network without a cassette in between
+pub fn is_live() -> Result<bool> {
+    Ok(matches!(mode()?, Mode::Live))
+}
+
+/// GET `url` and return the response body, failing on error statuses.
+/// `provider` names the service in error messages.
+pub async fn get(client: &Client, url: &str, provider: &str) -> Result<String> {
+    let reply = get_reply(client, url, provider, str::to_string).await?;
+    if !reply.status.is_success() {
+        return Err(anyhow!(
+            "{} returned error status: {}",
+            provider,
+            reply.status
+        ));
+    }
+    Ok(reply.body)
+}
+
+/// GET `url` and return the response whatever its status.
+///
+/// `live_url` rewrites the URL of requests that reach the network, to add a
+/// session token; recordings keep the plain `url`.
+pub async fn get_reply(
+    client: &Client,
+    url: &str,
+    provider: &str,
+    live_url: impl FnOnce(&str) -> String,
+) -> Result<Reply> {
+    match mode()? {
+        Mode::Replay(cassette) => {
+            let recorded = cassette
+                .find(url)
+                .ok_or_else(|| anyhow!("No recorded {} response for {}", provider, url))?;
+            debug!("Replaying {} from cassette", url);
+            Ok(Reply {
+                status: StatusCode::from_u16(recorded.status)?,
+                retry_after: None,
+                body: recorded.body.clone(),
+            })
+        }
+        Mode::Live => send(client.get(live_url(url)), provider).await,
+        Mode::Record(path, cassette) => {
+            let reply = send(client.get(live_url(url)), provider).await?;
+            let mut cassette = cassette.lock().unwrap_or_else(|e| e.into_inner());
+            cassette.interactions.retain(|i| i.url != url);
+            cassette.interactions.push(Interaction {
+                url: url.to_string(),
+                status: reply.status.as_u16(),
+                body: reply.body.clone(),
+            });
+            std::fs::write(path, serde_json::to_string_pretty(&*cassette)?)
+                .with_context(|| format!("Failed to write cassette {:?}", path))?;
+            Ok(reply)
+        }
+    }
+}
+
+async fn send(request: RequestBuilder, provider: &str) -> Result<Reply> {
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to send request to {}", provider))?;
+    let status = response.status();
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let body = response
+        .text()
+        .await
+        .with_context(|| format!("Failed to read {} response", provider))?;
+    Ok(Reply {
+        status,
+        retry_after,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cassette_matches_exact_url_then_endpoint() {
+        let cassette = Cassette::parse(
+            r#"{"interactions": [
+                {"url": "https://example.com/chart/A?from=1", "status": 200, "body": "a1"},
+                {"url": "https://example.com/chart/A?from=2", "status": 200, "body": "a2"},
+                {"url": "https://example.com/chart/B", "status": 404, "body": ""}
+            ]}"#,
+        )
+        .unwrap();
+
+        let body = |url| cassette.find(url).map(|i| i.body.as_str());
+        assert_eq!(body("https://example.com/chart/A?from=1"), Some("a1"));
+        // Another date range replays the latest recording of the endpoint
+        assert_eq!(body("https://example.com/chart/A?from=9"), Some("a2"));
+        assert_eq!(
+            cassette.find("https://example.com/chart/B").unwrap().status,
+            404
+        );
+        assert!(cassette.find("https://example.com/chart/C").is_none());
+
+        // The bundled demo cassette loads
+        assert!(!Cassette::parse(DEMO_CASSETTE)
+            .unwrap()
+            .interactions
+            .is_empty());
+    }
+}
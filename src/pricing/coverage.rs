@@ -0,0 +1,235 @@
+//! Which price providers cover each held asset.
+//!
+//! Yahoo and the B3 COTAHIST files quote listed assets, the Tesouro Direto
+//! CSV quotes government bonds, and nothing quotes private bonds, FIDCs,
+//! FIPs or derivatives. For every held asset this lists the providers the
+//! configured chain would try (see [`crate::pricing::provider`]), when each
+//! one last stored a price and, on request, whether Yahoo answers a live
+//! quote right now. The authoritative provider is the one whose price
+//! valuation actually uses (the latest stored close); assets no provider
+//! covers need prices entered by hand.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::db::{Asset, AssetType};
+use crate::pricing::provider::{ProviderChain, MANUAL};
+use crate::pricing::resolver::is_priceable_asset;
+
+pub use crate::pricing::provider::{B3_COTAHIST, TESOURO_CSV, YAHOO};
+
+/// One provider's standing for an asset
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderCoverage {
+    pub provider: String,
+    /// Whether the resolver tries this provider for the asset
+    pub eligible: bool,
+    /// Latest price stored from this provider
+    pub last_price_date: Option<NaiveDate>,
+    /// Result of a live quote, when one was attempted
+    pub live: Option<bool>,
+}
+
+impl ProviderCoverage {
+    pub fn covers(&self) -> bool {
+        self.last_price_date.is_some() || self.live == Some(true)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetCoverage {
+    pub ticker: String,
+    pub asset_type: &'static str,
+    pub quantity: Decimal,
+    pub providers: Vec<ProviderCoverage>,
+    /// Provider whose price is used for valuation; None means zero coverage
+    pub authoritative: Option<String>,
+    pub last_price_date: Option<NaiveDate>,
+}
+
+impl AssetCoverage {
+    pub fn needs_manual_price(&self) -> bool {
+        self.authoritative.is_none()
+    }
+}
+
+/// Providers with live or bulk prices for an asset in the default chain
+pub fn eligible_providers(asset: &Asset) -> &'static [&'static str] {
+    if asset.asset_type == AssetType::GovBond {
+        &[TESOURO_CSV]
+    } else if is_priceable_asset(asset) {
+        &[YAHOO, B3_COTAHIST]
+    } else {
+        &[]
+    }
+}
+
+/// Assets with a position today and their quantities
+pub fn held_assets(conn: &Connection) -> Result<Vec<(Asset, Decimal)>> {
+    Ok(crate::reports::calculate_portfolio(conn, None)?
+        .positions
+        .into_iter()
+        .map(|p| (p.asset, p.quantity))
+        .collect())
+}
+
+/// Coverage of one asset from its stored prices and an optional live Yahoo result
+pub fn asset_coverage(
+    conn: &Connection,
+    chain: &ProviderChain,
+    asset: &Asset,
+    quantity: Decimal,
+    live_yahoo: Option<bool>,
+) -> Result<AssetCoverage> {
+    let asset_id = asset.id.expect("asset from database must have id");
+    let mut stmt = conn.prepare(
+        "SELECT source, MAX(price_date) FROM price_history
+         WHERE asset_id = ?1 GROUP BY source",
+    )?;
+    let history = stmt
+        .query_map([asset_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(String, NaiveDate)>>>()?;
+    // Manual values live apart from price_history and are reported as such
+    let eligible: Vec<&str> = chain
+        .providers_for(asset)
+        .into_iter()
+        .filter(|p| *p != MANUAL)
+        .collect();
+    Ok(classify(asset, &eligible, quantity, &history, live_yahoo))
+}
+
+fn classify(
+    asset: &Asset,
+    eligible: &[&str],
+    quantity: Decimal,
+    history: &[(String, NaiveDate)],
+    live_yahoo: Option<bool>,
+) -> AssetCoverage {
+    let last_stored = |provider: &str| {
+        history
+            .iter()
+            .find(|(source, _)| source == provider)
+            .map(|(_, date)| *date)
+    };
+
+    let mut providers: Vec<ProviderCoverage> = eligible
+        .iter()
+        .map(|provider| ProviderCoverage {
+            provider: provider.to_string(),
+            eligible: true,
+            last_price_date: last_stored(provider),
+            live: (*provider == YAHOO).then_some(live_yahoo).flatten(),
+        })
+        .collect();
+    // Prices from sources the resolver no longer uses still value the asset
+    for (source, date) in history {
+        if !eligible.contains(&source.as_str()) {
+            providers.push(ProviderCoverage {
+                provider: source.clone(),
+                eligible: false,
+                last_price_date: Some(*date),
+                live: None,
+            });
+        }
+    }
+
+    // Valuation takes the latest close; a tie goes to the provider listed first
+    let mut latest: Option<(NaiveDate, &str)> = None;
+    for p in &providers {
+        if let Some(d) = p.last_price_date {
+            if latest.is_none_or(|(best, _)| d > best) {
+                latest = Some((d, &p.provider));
+            }
+        }
+    }
+    let authoritative = match latest {
+        Some((_, provider)) => Some(provider.to_string()),
+        None => providers
+            .iter()
+            .find(|p| p.covers())
+            .map(|p| p.provider.clone()),
+    };
+    let last_price_date = latest.map(|(d, _)| d);
+
+    AssetCoverage {
+        ticker: asset.ticker.clone(),
+        asset_type: asset.asset_type.as_str(),
+        quantity,
+        providers,
+        authoritative,
+        last_price_date,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn asset(ticker: &str, asset_type: AssetType) -> Asset {
+        Asset {
+            id: Some(1),
+            ticker: ticker.to_string(),
+            asset_type,
+            name: None,
+            cnpj: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_coverage_authoritative_and_zero_coverage() {
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+
+        let stock = [YAHOO, B3_COTAHIST];
+
+        let petr = classify(
+            &asset("PETR4", AssetType::Stock),
+            &stock,
+            dec!(100),
+            &[
+                (B3_COTAHIST.to_string(), d(5, 31)),
+                (YAHOO.to_string(), d(6, 3)),
+            ],
+            None,
+        );
+        assert_eq!(petr.authoritative.as_deref(), Some(YAHOO));
+        assert_eq!(petr.last_price_date, Some(d(6, 3)));
+        assert_eq!(petr.providers.len(), 2);
+
+        // Never stored, but Yahoo answers live
+        let wege = asset("WEGE3", AssetType::Stock);
+        let new = classify(&wege, &stock, dec!(10), &[], Some(true));
+        assert_eq!(new.authoritative.as_deref(), Some(YAHOO));
+        assert!(new.last_price_date.is_none());
+
+        // Eligible but never priced and not tested: not covered
+        let untested = classify(&wege, &stock, dec!(10), &[], None);
+        assert!(untested.needs_manual_price());
+
+        let bond = classify(
+            &asset("CDB-XP-2026", AssetType::Bond),
+            &[],
+            dec!(1),
+            &[],
+            None,
+        );
+        assert!(bond.providers.is_empty());
+        assert!(bond.needs_manual_price());
+
+        // A provider dropped from the chain still counts through its stored prices
+        let tesouro = classify(
+            &asset("TESOURO IPCA+ 2035", AssetType::GovBond),
+            &[],
+            dec!(2),
+            &[(TESOURO_CSV.to_string(), d(6, 3))],
+            None,
+        );
+        assert!(!tesouro.providers[0].eligible);
+        assert_eq!(tesouro.authoritative.as_deref(), Some(TESOURO_CSV));
+    }
+}
@@ -0,0 +1,246 @@
+//! FII net asset value per quota from the CVM monthly reports.
+//!
+//! Every FII files an "informe mensal estruturado" with its patrimônio
+//! líquido, cotas emitidas and valor patrimonial da cota. CVM publishes them
+//! as one ZIP per year; the `complemento` CSV inside carries the NAV fields.
+//! Funds are matched to assets by CNPJ (`assets.cnpj`, or the issuer data
+//! from `assets enrich-cnpj`) and stored in `fii_nav_history` for P/VP.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::db::{self, AssetType, FiiNav};
+use crate::scraping::cnpj::normalize_cnpj;
+
+const CVM_INF_MENSAL_URL: &str = "https://dados.cvm.gov.br/dados/FII/DOC/INF_MENSAL/DADOS";
+
+/// One fund's NAV for a reference month, as published by CVM
+#[derive(Debug, Clone, PartialEq)]
+pub struct CvmNavRecord {
+    pub cnpj: String,
+    pub reference_date: NaiveDate,
+    pub version: u32,
+    pub nav_per_quota: Decimal,
+    pub net_assets: Option<Decimal>,
+    pub quotas: Option<Decimal>,
+}
+
+/// Outcome of a yearly NAV update
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct NavUpdate {
+    pub year: i32,
+    /// FIIs in the database with a CNPJ to match
+    pub funds_with_cnpj: usize,
+    pub funds_matched: usize,
+    pub months_stored: usize,
+}
+
+/// Download the CVM informes for a year and store the NAV of known FIIs
+pub async fn update_fii_nav(conn: &Connection, year: i32) -> Result<NavUpdate> {
+    let by_cnpj = fii_assets_by_cnpj(conn)?;
+    let mut update = NavUpdate {
+        year,
+        funds_with_cnpj: by_cnpj.len(),
+        ..Default::default()
+    };
+    if by_cnpj.is_empty() {
+        return Ok(update);
+    }
+
+    let records = fetch_year(year).await?;
+    let mut matched = std::collections::HashSet::new();
+    for record in records {
+        let Some(&asset_id) = by_cnpj.get(&record.cnpj) else {
+            continue;
+        };
+        db::upsert_fii_nav(
+            conn,
+            &FiiNav {
+                asset_id,
+                reference_date: record.reference_date,
+                nav_per_quota: record.nav_per_quota,
+                net_assets: record.net_assets,
+                quotas: record.quotas,
+            },
+        )?;
+        matched.insert(asset_id);
+        update.months_stored += 1;
+    }
+    update.funds_matched = matched.len();
+    info!(
+        "Stored {} NAV months for {} FIIs ({})",
+        update.months_stored, update.funds_matched, year
+    );
+    Ok(update)
+}
+
+/// FII assets keyed by CNPJ digits
+fn fii_assets_by_cnpj(conn: &Connection) -> Result<HashMap<String, i64>> {
+    let mut map = HashMap::new();
+    for asset in db::get_all_assets(conn)? {
+        let Some(asset_id) = asset.id else {
+            continue;
+        };
+        if asset.asset_type != AssetType::Fii {
+            continue;
+        }
+        let cnpj = match asset.cnpj.as_deref().and_then(normalize_cnpj) {
+            Some(cnpj) => Some(cnpj),
+            None => db::get_asset_issuer(conn, asset_id)?.map(|i| i.cnpj),
+        };
+        if let Some(cnpj) = cnpj {
+            map.insert(cnpj, asset_id);
+        }
+    }
+    Ok(map)
+}
+
+async fn fetch_year(year: i32) -> Result<Vec<CvmNavRecord>> {
+    let offline = std::env::var("INTEREST_OFFLINE")
+        .map(|v| v != "0")
+        .unwrap_or(false);
+    if offline {
+        anyhow::bail!("CVM download skipped (INTEREST_OFFLINE is set)");
+    }
+
+    let url = format!("{}/inf_mensal_fii_{}.zip", CVM_INF_MENSAL_URL, year);
+    info!("Downloading CVM FII monthly reports: {}", url);
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(120))
+        .user_agent("interest")
+        .build()?;
+    let bytes = client
+        .get(&url)
+        .send()
+        .await
+        .context("Failed to download CVM FII monthly reports")?
+        .error_for_status()
+        .with_context(|| format!("CVM has no FII monthly reports for {}", year))?
+        .bytes()
+        .await?;
+
+    let text = read_complemento(&bytes)?;
+    parse_complemento_csv(&text)
+}
+
+/// Extract the `complemento` CSV (Latin-1) from the yearly ZIP
+fn read_complemento(zip_bytes: &[u8]) -> Result<String> {
+    let mut archive =
+        zip::ZipArchive::new(std::io::Cursor::new(zip_bytes)).context("Invalid CVM ZIP archive")?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if !file.name().to_lowercase().contains("complemento") {
+            continue;
+        }
+        let mut raw = Vec::new();
+        file.read_to_end(&mut raw)?;
+        let (text, _, _) = encoding_rs::WINDOWS_1252.decode(&raw);
+        return Ok(text.into_owned());
+    }
+    anyhow::bail!("CVM archive has no 'complemento' file")
+}
+
+/// Parse the `complemento` CSV, keeping the latest version of each fund/month
+pub fn parse_complemento_csv(text: &str) -> Result<Vec<CvmNavRecord>> {
+    let mut lines = text.lines();
+    let header: Vec<String> = lines
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Empty CVM file"))?
+        .trim_start_matches('\u{feff}')
+        .split(';')
+        .map(|h| h.trim().to_lowercase())
+        .collect();
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+
+    let cnpj_col = column(&["cnpj_fundo_classe", "cnpj_fundo"])
+        .ok_or_else(|| anyhow::anyhow!("CVM file has no CNPJ column"))?;
+    let date_col = column(&["data_referencia"])
+        .ok_or_else(|| anyhow::anyhow!("CVM file has no Data_Referencia column"))?;
+    let nav_col = column(&["valor_patrimonial_cotas"])
+        .ok_or_else(|| anyhow::anyhow!("CVM file has no Valor_Patrimonial_Cotas column"))?;
+    let version_col = column(&["versao"]);
+    let net_assets_col = column(&["patrimonio_liquido"]);
+    let quotas_col = column(&["cotas_emitidas"]);
+
+    let mut latest: HashMap<(String, NaiveDate), CvmNavRecord> = HashMap::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split(';').map(|f| f.trim()).collect();
+        let field = |idx: Option<usize>| idx.and_then(|i| fields.get(i)).copied();
+        let decimal = |idx: Option<usize>| field(idx).and_then(parse_cvm_decimal);
+
+        let Some(cnpj) = field(Some(cnpj_col)).and_then(normalize_cnpj) else {
+            continue;
+        };
+        let Some(reference_date) =
+            field(Some(date_col)).and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        else {
+            continue;
+        };
+        let Some(nav_per_quota) = decimal(Some(nav_col)).filter(|v| *v > Decimal::ZERO) else {
+            continue;
+        };
+
+        let record = CvmNavRecord {
+            cnpj,
+            reference_date,
+            version: field(version_col).and_then(|v| v.parse().ok()).unwrap_or(1),
+            nav_per_quota,
+            net_assets: decimal(net_assets_col),
+            quotas: decimal(quotas_col),
+        };
+        let key = (record.cnpj.clone(), record.reference_date);
+        match latest.get(&key) {
+            Some(existing) if existing.version >= record.version => {}
+            _ => {
+                latest.insert(key, record);
+            }
+        }
+    }
+
+    let mut records: Vec<_> = latest.into_values().collect();
+    records.sort_by(|a, b| (&a.cnpj, a.reference_date).cmp(&(&b.cnpj, b.reference_date)));
+    Ok(records)
+}
+
+/// CVM numbers use '.' for decimals; accept Brazilian ',' too
+fn parse_cvm_decimal(raw: &str) -> Option<Decimal> {
+    if raw.is_empty() {
+        return None;
+    }
+    let normalized = if raw.contains(',') {
+        raw.replace('.', "").replace(',', ".")
+    } else {
+        raw.to_string()
+    };
+    Decimal::from_str(&normalized)
+        .or_else(|_| Decimal::from_scientific(&normalized))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_complemento_keeps_latest_version() {
+        let csv = "CNPJ_Fundo_Classe;Data_Referencia;Versao;Nome_Fundo_Classe;Patrimonio_Liquido;Cotas_Emitidas;Valor_Patrimonial_Cotas\n\
+            11.728.688/0001-47;2024-01-01;1;FUNDO A;1000000.00;10000;100.00\n\
+            11.728.688/0001-47;2024-01-01;2;FUNDO A;1010000.00;10000;101.00\n\
+            11.728.688/0001-47;2024-02-01;1;FUNDO A;990000.00;10000;99,5\n\
+            bad;2024-02-01;1;FUNDO B;1;1;1\n\
+            97.521.225/0001-25;2024-01-01;1;FUNDO C;;;\n";
+        let records = parse_complemento_csv(csv).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].cnpj, "11728688000147");
+        assert_eq!(records[0].version, 2);
+        assert_eq!(records[0].nav_per_quota, Decimal::from(101));
+        assert_eq!(records[0].quotas, Some(Decimal::from(10000)));
+        assert_eq!(records[1].nav_per_quota, Decimal::from_str("99.5").unwrap());
+    }
+}
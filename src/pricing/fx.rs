@@ -0,0 +1,212 @@
+//! Currency exchange rates.
+//!
+//! Rates of each foreign currency in reais come from the Banco Central PTAX
+//! closing bulletin (selling rate) and are stored in `fx_rates` under the pair
+//! `<CURRENCY>BRL`. Assets flagged with a trading currency (BDR underlyings,
+//! foreign brokers, crypto) have their closes and valuations in it, and are
+//! valued in BRL at the PTAX of the price's date. USD/BRL is also used to split
+//! BDR returns into local price and currency effects.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use tracing::info;
+
+use crate::db;
+
+/// Pair identifier for US dollar priced in reais
+pub const USD_BRL: &str = "USDBRL";
+
+/// Currencies with a PTAX bulletin
+pub const CURRENCIES: &[&str] = &[
+    "USD", "EUR", "GBP", "JPY", "CHF", "CAD", "AUD", "DKK", "NOK", "SEK",
+];
+
+const PTAX_URL: &str = "https://olinda.bcb.gov.br/olinda/servico/PTAX/versao/v1/odata";
+
+/// A price in a foreign currency converted to reais
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FxConversion {
+    pub currency: String,
+    /// Reais per unit of the currency
+    pub rate: Decimal,
+    /// Date of the PTAX used
+    pub rate_date: NaiveDate,
+}
+
+/// Pair identifier of `currency` priced in reais
+pub fn pair(currency: &str) -> String {
+    format!("{}BRL", currency)
+}
+
+/// Validate a currency code, uppercased
+pub fn parse_currency(code: &str) -> Result<String> {
+    let code = code.trim().to_uppercase();
+    if CURRENCIES.contains(&code.as_str()) {
+        Ok(code)
+    } else {
+        anyhow::bail!(
+            "Unsupported currency: {} (use {})",
+            code,
+            CURRENCIES.join(", ")
+        )
+    }
+}
+
+/// Convert `price` in the asset's currency to reais at the PTAX on or before
+/// `date`; prices of BRL assets pass through. None when no rate is stored.
+pub fn to_brl(
+    conn: &Connection,
+    currency: Option<&str>,
+    price: Decimal,
+    date: NaiveDate,
+) -> Result<Option<(Decimal, Option<FxConversion>)>> {
+    let Some(currency) = currency else {
+        return Ok(Some((price, None)));
+    };
+    let Some((rate_date, rate)) = db::get_fx_rate_on_or_before(conn, &pair(currency), date)? else {
+        tracing::warn!("No {}/BRL rate on or before {}", currency, date);
+        return Ok(None);
+    };
+    Ok(Some((
+        price * rate,
+        Some(FxConversion {
+            currency: currency.to_string(),
+            rate,
+            rate_date,
+        }),
+    )))
+}
+
+#[derive(Deserialize)]
+struct PtaxResponse {
+    value: Vec<PtaxQuote>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PtaxQuote {
+    cotacao_venda: serde_json::Number,
+    data_hora_cotacao: String,
+    tipo_boletim: String,
+}
+
+/// Fetch the PTAX closing selling rates of `currency` as (date, rate) pairs
+pub async fn fetch_ptax(
+    currency: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<(NaiveDate, Decimal)>> {
+    let client = reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (compatible; InterestBot/1.0)")
+        .build()?;
+    info!("Fetching PTAX {} from {} to {}", currency, from, to);
+    let url = format!(
+        "{}/CotacaoMoedaPeriodo(moeda=@moeda,dataInicial=@dataInicial,dataFinalCotacao=@dataFinalCotacao)?@moeda='{}'&@dataInicial='{}'&@dataFinalCotacao='{}'&$format=json",
+        PTAX_URL,
+        currency,
+        from.format("%m-%d-%Y"),
+        to.format("%m-%d-%Y")
+    );
+    let body = super::cassette::get(&client, &url, "BCB PTAX").await?;
+    parse_ptax_response(&body)
+}
+
+fn parse_ptax_response(body: &str) -> Result<Vec<(NaiveDate, Decimal)>> {
+    let response: PtaxResponse =
+        serde_json::from_str(body).context("Failed to parse BCB PTAX response")?;
+    response
+        .value
+        .into_iter()
+        // Opening and intermediate bulletins come first on the same day
+        .filter(|quote| quote.tipo_boletim == "Fechamento")
+        .map(|quote| {
+            let date = quote.data_hora_cotacao.get(..10).unwrap_or_default();
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .with_context(|| format!("Invalid PTAX date: {}", quote.data_hora_cotacao))?;
+            let rate = Decimal::from_str(&quote.cotacao_venda.to_string())
+                .with_context(|| format!("Invalid PTAX rate: {}", quote.cotacao_venda))?;
+            Ok((date, rate))
+        })
+        .collect()
+}
+
+/// Fetch and store rates of `currency`, returning the number of stored values
+pub async fn update_rates(
+    conn: &Connection,
+    currency: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<usize> {
+    if from > to {
+        return Ok(0);
+    }
+    let rates = fetch_ptax(currency, from, to).await?;
+    let pair = pair(currency);
+    for (date, rate) in &rates {
+        db::insert_fx_rate(conn, &pair, *date, *rate, "BCB")?;
+    }
+    Ok(rates.len())
+}
+
+/// Make sure rates of `currency` cover `[from, to]`, fetching only the
+/// missing tail
+///
+/// Honors `INTEREST_OFFLINE`; failures are left to the caller to report.
+pub async fn ensure_rates(
+    conn: &Connection,
+    currency: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<()> {
+    let offline = std::env::var("INTEREST_OFFLINE")
+        .map(|v| v != "0")
+        .unwrap_or(false);
+    if offline {
+        return Ok(());
+    }
+
+    let pair = pair(currency);
+    let start = match db::get_latest_fx_date(conn, &pair)? {
+        Some(latest) if latest >= to => return Ok(()),
+        Some(latest) if db::get_fx_rate_on_or_before(conn, &pair, from)?.is_some() => {
+            latest.succ_opt().unwrap_or(latest)
+        }
+        _ => from,
+    };
+    update_rates(conn, currency, start, to).await?;
+    Ok(())
+}
+
+/// Make sure USD/BRL rates cover `[from, to]`
+pub async fn ensure_usd_brl(conn: &Connection, from: NaiveDate, to: NaiveDate) -> Result<()> {
+    ensure_rates(conn, "USD", from, to).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_parse_ptax_keeps_closing_bulletin() {
+        let body = r#"{"@odata.context":"x","value":[
+            {"paridadeCompra":1.0,"paridadeVenda":1.0,"cotacaoCompra":4.8630,"cotacaoVenda":4.8636,"dataHoraCotacao":"2024-01-02 10:08:24.613","tipoBoletim":"Abertura"},
+            {"paridadeCompra":1.0,"paridadeVenda":1.0,"cotacaoCompra":4.8907,"cotacaoVenda":4.8913,"dataHoraCotacao":"2024-01-02 13:03:31.419","tipoBoletim":"Fechamento"},
+            {"paridadeCompra":1.0,"paridadeVenda":1.0,"cotacaoCompra":4.9206,"cotacaoVenda":4.9212,"dataHoraCotacao":"2024-01-03 13:06:27.36","tipoBoletim":"Fechamento"}
+        ]}"#;
+        let rates = parse_ptax_response(body).unwrap();
+        assert_eq!(
+            rates,
+            vec![
+                (NaiveDate::from_ymd_opt(2024, 1, 2).unwrap(), dec!(4.8913)),
+                (NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(), dec!(4.9212)),
+            ]
+        );
+        assert!(parse_currency("btc").is_err());
+        assert_eq!(parse_currency("usd").unwrap(), "USD");
+    }
+}
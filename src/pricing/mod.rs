@@ -1,7 +1,16 @@
 // Pricing module - Yahoo Finance API client
 
+pub mod benchmarks;
+pub mod cassette;
+pub mod coverage;
+pub mod fii_nav;
+pub mod fx;
+pub mod provider;
+pub mod quote_cache;
 pub mod resolver;
+pub mod retry_queue;
 pub mod tesouro;
+pub mod watch;
 pub mod yahoo;
 
 use anyhow::{Context, Result};
@@ -11,21 +20,29 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use tracing::{debug, info};
 
-/// Global singleton price fetcher with 24-hour cache.
-/// This ensures cache is shared across all calls within a process.
+/// Global singleton price fetcher.
+/// This ensures the in-memory layer is shared across all calls within a process.
 static GLOBAL_FETCHER: Lazy<PriceFetcher> = Lazy::new(PriceFetcher::new);
 
 /// Price cache entry
 #[derive(Debug, Clone)]
 struct CacheEntry {
-    price: rust_decimal::Decimal,
-    timestamp: chrono::DateTime<chrono::Utc>,
+    quote: LiveQuote,
+    expires_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// Price fetcher with caching (24hr TTL)
+/// Current price with the time it was quoted
+#[derive(Debug, Clone, Copy)]
+pub struct LiveQuote {
+    pub price: rust_decimal::Decimal,
+    pub quoted_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Price fetcher with caching in memory and in the database's quote cache,
+/// so later runs reuse a quote until it expires (see [`quote_cache`])
 pub struct PriceFetcher {
     cache: Arc<Mutex<HashMap<String, CacheEntry>>>,
-    cache_ttl_hours: i64,
+    ttl: Duration,
 }
 
 impl Default for PriceFetcher {
@@ -38,44 +55,71 @@ impl PriceFetcher {
     pub fn new() -> Self {
         Self {
             cache: Arc::new(Mutex::new(HashMap::new())),
-            cache_ttl_hours: 24,
+            ttl: quote_cache::ttl(),
         }
     }
 
     /// Fetch current price with caching
     pub async fn fetch_price(&self, ticker: &str) -> Result<rust_decimal::Decimal> {
+        Ok(self.fetch_quote(ticker).await?.price)
+    }
+
+    /// Fetch current price and quote time with caching
+    pub async fn fetch_quote(&self, ticker: &str) -> Result<LiveQuote> {
+        let now = Utc::now();
         // Check cache first
         {
             let cache = self.cache.lock().unwrap();
             if let Some(entry) = cache.get(ticker) {
-                let age = Utc::now().signed_duration_since(entry.timestamp);
-                if age < Duration::hours(self.cache_ttl_hours) {
-                    debug!(
-                        "Using cached price for {} (age: {}h)",
-                        ticker,
-                        age.num_hours()
-                    );
-                    return Ok(entry.price);
+                if entry.expires_at > now {
+                    debug!("Using cached price for {}", ticker);
+                    return Ok(entry.quote);
                 }
             }
         }
 
+        let conn = cache_db();
+        let cached = conn
+            .as_ref()
+            .and_then(|conn| quote_cache::get(conn, ticker, now).ok().flatten());
+        if let Some((quote, expires_at)) = cached {
+            debug!("Using stored quote for {} (until {})", ticker, expires_at);
+            self.remember(ticker, quote, expires_at);
+            return Ok(quote);
+        }
+
         // Fetch from Yahoo Finance (primary)
         info!("Fetching fresh price for {} from Yahoo Finance", ticker);
         let price_data = yahoo::fetch_current_price(ticker)
             .await
             .context("Yahoo Finance price fetch failed")?;
 
-        // Cache the price
+        let quote = LiveQuote {
+            price: price_data.price,
+            quoted_at: price_data.timestamp,
+        };
+
+        let fetched_at = Utc::now();
+        let expires_at = quote_cache::expires_at(fetched_at, self.ttl);
+        if let Some(conn) = &conn {
+            if let Err(e) = quote_cache::put(
+                conn,
+                ticker,
+                &quote,
+                provider::YAHOO,
+                fetched_at,
+                expires_at,
+            ) {
+                debug!("Could not store quote for {}: {}", ticker, e);
+            }
+        }
+        self.remember(ticker, quote, expires_at);
+        Ok(quote)
+    }
+
+    fn remember(&self, ticker: &str, quote: LiveQuote, expires_at: chrono::DateTime<Utc>) {
         let mut cache = self.cache.lock().unwrap();
-        cache.insert(
-            ticker.to_string(),
-            CacheEntry {
-                price: price_data.price,
-                timestamp: Utc::now(),
-            },
-        );
-        Ok(price_data.price)
+        cache.insert(ticker.to_string(), CacheEntry { quote, expires_at });
     }
 
     /// Clear cache
@@ -94,12 +138,77 @@ impl PriceFetcher {
     }
 }
 
+/// Database holding the quote cache; None while replaying or recording a
+/// cassette, whose quotes must come from the cassette, or before the
+/// database exists
+fn cache_db() -> Option<rusqlite::Connection> {
+    if !cassette::is_live().unwrap_or(false) {
+        return None;
+    }
+    let path = crate::db::get_default_db_path().ok()?;
+    if !path.exists() {
+        return None;
+    }
+    crate::db::open_db(Some(path)).ok()
+}
+
 /// Convenience function to fetch a price using the global shared fetcher.
 /// This uses a singleton cache that persists for the lifetime of the process.
+#[allow(dead_code)]
 pub async fn fetch_price(ticker: &str) -> Result<rust_decimal::Decimal> {
     GLOBAL_FETCHER.fetch_price(ticker).await
 }
 
+/// Like [`fetch_price`], keeping the quote time
+pub async fn fetch_quote(ticker: &str) -> Result<LiveQuote> {
+    GLOBAL_FETCHER.fetch_quote(ticker).await
+}
+
+/// Put a quote fetched outside the fetcher into the quote cache, so the
+/// next reads in this run and later ones reuse it
+pub fn cache_quote(conn: &rusqlite::Connection, ticker: &str, quote: &LiveQuote) -> Result<()> {
+    let fetched_at = Utc::now();
+    let expires_at = quote_cache::expires_at(fetched_at, GLOBAL_FETCHER.ttl);
+    quote_cache::put(conn, ticker, quote, provider::YAHOO, fetched_at, expires_at)?;
+    GLOBAL_FETCHER.remember(ticker, *quote, expires_at);
+    Ok(())
+}
+
+/// Keep a live quote as an intraday snapshot and as `date`'s daily close
+pub fn store_quote(
+    conn: &rusqlite::Connection,
+    asset_id: i64,
+    quote: &LiveQuote,
+    date: chrono::NaiveDate,
+) -> Result<()> {
+    crate::db::insert_price_snapshot(
+        conn,
+        &crate::db::PriceSnapshot {
+            asset_id,
+            snapshot_at: quote.quoted_at,
+            price: quote.price,
+            source: provider::YAHOO.to_string(),
+        },
+    )?;
+    crate::db::insert_price_history(
+        conn,
+        &crate::db::PriceHistory {
+            id: None,
+            asset_id,
+            price_date: date,
+            close_price: quote.price,
+            open_price: None,
+            high_price: None,
+            low_price: None,
+            volume: None,
+            source: provider::YAHOO.to_string(),
+            created_at: Utc::now(),
+            adjusted_close: None,
+        },
+    )?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,8 +264,9 @@ mod tests {
     }
 
     #[test]
-    fn test_cache_ttl_default() {
-        // Default TTL should be 24 hours
-        assert_eq!(GLOBAL_FETCHER.cache_ttl_hours, 24);
+    fn test_cache_ttl_from_config() {
+        // The TTL comes from [prices] quote_ttl_minutes, 15 minutes by default
+        assert_eq!(GLOBAL_FETCHER.ttl, quote_cache::ttl());
+        assert!(GLOBAL_FETCHER.ttl > Duration::zero());
     }
 }
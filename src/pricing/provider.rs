@@ -0,0 +1,731 @@
+//! Price providers behind one interface, tried in a configurable order.
+//!
+//! Each provider quotes the assets it covers: Yahoo and brapi.dev give live
+//! quotes for listed assets, the B3 COTAHIST file the latest official close,
+//! the Tesouro Direto CSV government bond PUs, Mercado Bitcoin the last
+//! trade of crypto-assets in reais, and manual valuations
+//! (`assets set-value`) anything else. A [`ProviderChain`] asks them in the
+//! order set under `[prices]` in the config and stops at the first answer.
+//! Every provider can have its own requests-per-minute limit, and a transient
+//! failure is tried again with backoff before the chain moves on; a provider
+//! that has no price for the asset, or Yahoo with its circuit open, is passed
+//! over at once. The provider that answered is what lands in
+//! `price_history.source`.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, Utc};
+use rusqlite::{Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::sync::{Mutex, OnceCell, Semaphore};
+use tokio::time::Instant;
+
+use crate::config::PricesConfig;
+use crate::db::{Asset, AssetType};
+use crate::importers::b3_cotahist;
+
+pub const YAHOO: &str = "YAHOO";
+pub const BRAPI: &str = "BRAPI";
+pub const B3_COTAHIST: &str = "B3_COTAHIST";
+pub const TESOURO_CSV: &str = "TESOURO_CSV";
+pub const MERCADO_BITCOIN: &str = "MERCADO_BITCOIN";
+pub const MANUAL: &str = "MANUAL";
+
+/// Tries per provider after the first when the config sets none
+const DEFAULT_RETRIES: u32 = 1;
+/// Wait before the first retry, doubled on each one that follows
+const RETRY_BASE: Duration = Duration::from_millis(500);
+const RETRY_MAX: Duration = Duration::from_secs(8);
+/// Requests in flight per provider when the config sets no limit
+const DEFAULT_CONCURRENCY: u32 = 5;
+/// Oldest stored close a bulk provider still answers with
+const MAX_CLOSE_AGE_DAYS: i64 = 10;
+const BRAPI_URL: &str = "https://brapi.dev/api/quote";
+const MERCADO_BITCOIN_URL: &str = "https://api.mercadobitcoin.net/api/v4/tickers";
+
+pub type QuoteFuture<'a> = Pin<Box<dyn Future<Output = Result<ProviderQuote>> + Send + 'a>>;
+
+/// A price one provider answered with
+#[derive(Debug, Clone)]
+pub struct ProviderQuote {
+    pub price: Decimal,
+    /// Day the price is stored under
+    pub date: NaiveDate,
+    /// Time of a live quote; None for closes and valuations
+    pub quoted_at: Option<DateTime<Utc>>,
+    /// Provider that answered, kept as `price_history.source`
+    pub source: &'static str,
+    /// Already in the database (bulk imports, manual valuations)
+    pub stored: bool,
+}
+
+impl ProviderQuote {
+    fn live(source: &'static str, price: Decimal, quoted_at: DateTime<Utc>) -> Self {
+        Self {
+            price,
+            date: Local::now().date_naive(),
+            quoted_at: Some(quoted_at),
+            source,
+            stored: false,
+        }
+    }
+
+    fn stored(source: &'static str, (date, price): (NaiveDate, Decimal)) -> Self {
+        Self {
+            price,
+            date,
+            quoted_at: None,
+            source,
+            stored: true,
+        }
+    }
+}
+
+/// The provider has no price for the asset; trying again will not help
+#[derive(Debug)]
+pub struct NoQuote(pub String);
+
+impl std::fmt::Display for NoQuote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for NoQuote {}
+
+/// Whether trying the same provider again may succeed
+fn is_transient(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<NoQuote>().is_none() && !super::yahoo::is_unavailable(err)
+}
+
+/// A source of prices
+pub trait PriceProvider: Send + Sync {
+    /// Name used in the config and written to `price_history.source`
+    fn id(&self) -> &'static str;
+
+    /// Whether the provider prices this kind of asset at all
+    fn covers(&self, asset: &Asset) -> bool;
+
+    fn quote<'a>(&'a self, asset: &'a Asset) -> QuoteFuture<'a>;
+}
+
+/// Yahoo Finance live quotes, through the shared cached fetcher
+struct Yahoo;
+
+impl PriceProvider for Yahoo {
+    fn id(&self) -> &'static str {
+        YAHOO
+    }
+
+    fn covers(&self, asset: &Asset) -> bool {
+        super::resolver::is_priceable_asset(asset)
+    }
+
+    fn quote<'a>(&'a self, asset: &'a Asset) -> QuoteFuture<'a> {
+        Box::pin(async move {
+            let quote = super::fetch_quote(&asset.ticker).await?;
+            Ok(ProviderQuote::live(YAHOO, quote.price, quote.quoted_at))
+        })
+    }
+}
+
+/// brapi.dev live quotes; without a token only a few tickers answer
+struct Brapi {
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BrapiResponse {
+    results: Vec<BrapiQuote>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BrapiQuote {
+    regular_market_price: Option<f64>,
+    regular_market_time: Option<String>,
+}
+
+fn parse_brapi(ticker: &str, body: &str) -> Result<(Decimal, DateTime<Utc>)> {
+    let response: BrapiResponse =
+        serde_json::from_str(body).context("Failed to parse brapi response")?;
+    let quote = response
+        .results
+        .into_iter()
+        .next()
+        .ok_or_else(|| NoQuote(format!("brapi returned no quote for {}", ticker)))?;
+    let price = quote
+        .regular_market_price
+        .and_then(Decimal::from_f64_retain)
+        .ok_or_else(|| NoQuote(format!("brapi has no price for {}", ticker)))?;
+    let quoted_at = quote
+        .regular_market_time
+        .and_then(|t| DateTime::parse_from_rfc3339(&t).ok())
+        .map_or_else(Utc::now, |t| t.with_timezone(&Utc));
+    Ok((price.round_dp(6), quoted_at))
+}
+
+impl PriceProvider for Brapi {
+    fn id(&self) -> &'static str {
+        BRAPI
+    }
+
+    fn covers(&self, asset: &Asset) -> bool {
+        super::resolver::is_priceable_asset(asset)
+    }
+
+    fn quote<'a>(&'a self, asset: &'a Asset) -> QuoteFuture<'a> {
+        Box::pin(async move {
+            let client = reqwest::Client::builder().build()?;
+            let url = format!("{}/{}", BRAPI_URL, asset.ticker);
+            let reply =
+                super::cassette::get_reply(&client, &url, "brapi", |url| match &self.token {
+                    Some(token) => format!("{}?token={}", url, token),
+                    None => url.to_string(),
+                })
+                .await?;
+            match reply.status {
+                reqwest::StatusCode::NOT_FOUND => {
+                    Err(NoQuote(format!("brapi does not know {}", asset.ticker)).into())
+                }
+                status if !status.is_success() => {
+                    Err(anyhow!("brapi returned error status: {}", status))
+                }
+                _ => {
+                    let (price, quoted_at) = parse_brapi(&asset.ticker, &reply.body)?;
+                    Ok(ProviderQuote::live(BRAPI, price, quoted_at))
+                }
+            }
+        })
+    }
+}
+
+/// Mercado Bitcoin tickers: the last trade of a crypto-asset against reais
+struct MercadoBitcoin;
+
+#[derive(Debug, Deserialize)]
+struct MercadoBitcoinTicker {
+    last: String,
+    /// Unix time of the ticker
+    date: Option<i64>,
+}
+
+fn parse_mercado_bitcoin(coin: &str, body: &str) -> Result<(Decimal, DateTime<Utc>)> {
+    let tickers: Vec<MercadoBitcoinTicker> =
+        serde_json::from_str(body).context("Failed to parse Mercado Bitcoin response")?;
+    let ticker = tickers
+        .into_iter()
+        .next()
+        .ok_or_else(|| NoQuote(format!("Mercado Bitcoin returned no ticker for {}", coin)))?;
+    let price = ticker
+        .last
+        .parse::<Decimal>()
+        .with_context(|| format!("Invalid Mercado Bitcoin price: {}", ticker.last))?;
+    if price.is_zero() {
+        return Err(NoQuote(format!("Mercado Bitcoin has no trades of {}", coin)).into());
+    }
+    let quoted_at = ticker
+        .date
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .unwrap_or_else(Utc::now);
+    Ok((price.round_dp(8), quoted_at))
+}
+
+impl PriceProvider for MercadoBitcoin {
+    fn id(&self) -> &'static str {
+        MERCADO_BITCOIN
+    }
+
+    fn covers(&self, asset: &Asset) -> bool {
+        asset.asset_type == AssetType::Crypto
+    }
+
+    fn quote<'a>(&'a self, asset: &'a Asset) -> QuoteFuture<'a> {
+        Box::pin(async move {
+            let client = reqwest::Client::builder().build()?;
+            let url = format!("{}?symbols={}-BRL", MERCADO_BITCOIN_URL, asset.ticker);
+            let reply =
+                super::cassette::get_reply(&client, &url, "Mercado Bitcoin", |url| url.to_string())
+                    .await?;
+            match reply.status {
+                status if status.is_client_error() => {
+                    Err(NoQuote(format!("Mercado Bitcoin does not list {}", asset.ticker)).into())
+                }
+                status if !status.is_success() => {
+                    Err(anyhow!("Mercado Bitcoin returned error status: {}", status))
+                }
+                _ => {
+                    let (price, quoted_at) = parse_mercado_bitcoin(&asset.ticker, &reply.body)?;
+                    Ok(ProviderQuote::live(MERCADO_BITCOIN, price, quoted_at))
+                }
+            }
+        })
+    }
+}
+
+/// Latest close stored from `source`, if recent enough to stand for today
+fn recent_close(conn: &Connection, asset: &Asset, source: &'static str) -> Result<ProviderQuote> {
+    let asset_id = asset.id.context("asset from database must have id")?;
+    let latest: Option<(NaiveDate, Decimal)> = conn
+        .query_row(
+            "SELECT price_date, close_price FROM price_history
+             WHERE asset_id = ?1 AND source = ?2
+             ORDER BY price_date DESC LIMIT 1",
+            rusqlite::params![asset_id, source],
+            |row| Ok((row.get(0)?, crate::db::get_decimal_value(row, 1)?)),
+        )
+        .optional()?;
+    let oldest = Local::now().date_naive() - ChronoDuration::days(MAX_CLOSE_AGE_DAYS);
+    match latest {
+        Some(close) if close.0 >= oldest => Ok(ProviderQuote::stored(source, close)),
+        Some((date, _)) => Err(NoQuote(format!(
+            "latest {} close for {} is from {}",
+            source,
+            asset.ticker,
+            date.format("%d/%m/%Y")
+        ))
+        .into()),
+        None => Err(NoQuote(format!("no {} close for {}", source, asset.ticker)).into()),
+    }
+}
+
+/// Run `read` on a fresh connection off the async runtime
+async fn with_db<T, F>(read: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || read(&mut crate::db::open_db(None)?))
+        .await
+        .map_err(|e| anyhow!("Price lookup failed: {}", e))?
+}
+
+/// The current year's B3 COTAHIST file, imported once per chain
+struct Cotahist {
+    imported: OnceCell<()>,
+}
+
+impl PriceProvider for Cotahist {
+    fn id(&self) -> &'static str {
+        B3_COTAHIST
+    }
+
+    fn covers(&self, asset: &Asset) -> bool {
+        super::resolver::is_priceable_asset(asset)
+    }
+
+    fn quote<'a>(&'a self, asset: &'a Asset) -> QuoteFuture<'a> {
+        Box::pin(async move {
+            self.imported
+                .get_or_init(|| async {
+                    let year = Local::now().year();
+                    let imported = with_db(move |conn| {
+                        b3_cotahist::download_cotahist_year(year, false, None)?;
+                        if !b3_cotahist::has_cotahist_been_imported_with_conn(year, Some(conn))? {
+                            b3_cotahist::import_cotahist_year(conn, year, false, None)?;
+                        }
+                        Ok(())
+                    })
+                    .await;
+                    // Closes imported earlier still answer
+                    if let Err(e) = imported {
+                        tracing::warn!("COTAHIST {} import failed: {}", year, e);
+                    }
+                })
+                .await;
+            let asset = asset.clone();
+            with_db(move |conn| recent_close(conn, &asset, B3_COTAHIST)).await
+        })
+    }
+}
+
+/// Government bond PUs from the Tesouro Direto CSV, imported once per chain
+struct Tesouro {
+    imported: OnceCell<()>,
+}
+
+impl PriceProvider for Tesouro {
+    fn id(&self) -> &'static str {
+        TESOURO_CSV
+    }
+
+    fn covers(&self, asset: &Asset) -> bool {
+        asset.asset_type == AssetType::GovBond
+    }
+
+    fn quote<'a>(&'a self, asset: &'a Asset) -> QuoteFuture<'a> {
+        Box::pin(async move {
+            self.imported
+                .get_or_init(|| async {
+                    let imported = with_db(|conn| {
+                        if super::tesouro::has_tesouro_been_imported_with_conn(Some(conn))? {
+                            return Ok(());
+                        }
+                        let bonds: Vec<Asset> = crate::db::get_all_assets(conn)?
+                            .into_iter()
+                            .filter(|a| a.asset_type == AssetType::GovBond)
+                            .collect();
+                        let today = Local::now().date_naive();
+                        let from = today - ChronoDuration::days(MAX_CLOSE_AGE_DAYS * 3);
+                        super::tesouro::import_tesouro_csv(conn, &bonds, from, today)?;
+                        Ok(())
+                    })
+                    .await;
+                    if let Err(e) = imported {
+                        tracing::warn!("Tesouro CSV import failed: {}", e);
+                    }
+                })
+                .await;
+            let asset = asset.clone();
+            with_db(move |conn| recent_close(conn, &asset, TESOURO_CSV)).await
+        })
+    }
+}
+
+/// Valuations entered with `assets set-value`
+struct Manual;
+
+impl PriceProvider for Manual {
+    fn id(&self) -> &'static str {
+        MANUAL
+    }
+
+    fn covers(&self, _asset: &Asset) -> bool {
+        true
+    }
+
+    fn quote<'a>(&'a self, asset: &'a Asset) -> QuoteFuture<'a> {
+        Box::pin(async move {
+            let asset = asset.clone();
+            with_db(move |conn| {
+                let asset_id = asset.id.context("asset from database must have id")?;
+                let today = Local::now().date_naive();
+                match crate::db::valuations::valuation_on_or_before(conn, asset_id, today)? {
+                    Some(v) => Ok(ProviderQuote::stored(MANUAL, (v.valuation_date, v.value))),
+                    None => Err(NoQuote(format!("no manual value for {}", asset.ticker)).into()),
+                }
+            })
+            .await
+        })
+    }
+}
+
+/// Spaces a provider's requests to stay under its per-minute limit
+struct RateLimiter {
+    interval: Duration,
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn per_minute(requests: u32) -> Self {
+        Self {
+            interval: Duration::from_secs(60) / requests.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for this request's slot
+    async fn acquire(&self) {
+        let mut next = self.next.lock().await;
+        tokio::time::sleep_until(*next).await;
+        *next = Instant::now() + self.interval;
+    }
+}
+
+fn retry_backoff(attempt: u32) -> Duration {
+    RETRY_BASE
+        .saturating_mul(1 << attempt.min(16))
+        .min(RETRY_MAX)
+}
+
+struct Link {
+    provider: Box<dyn PriceProvider>,
+    limiter: Option<RateLimiter>,
+    /// Bounds the provider's requests in flight across concurrent quotes
+    permits: Semaphore,
+}
+
+impl Link {
+    async fn quote(&self, asset: &Asset, retries: u32) -> Result<ProviderQuote> {
+        let mut attempt = 0;
+        loop {
+            let permit = self.permits.acquire().await?;
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+            let result = self.provider.quote(asset).await;
+            // Backoff waits without holding up the provider's other requests
+            drop(permit);
+            match result {
+                Ok(quote) => return Ok(quote),
+                Err(e) if attempt < retries && is_transient(&e) => {
+                    tracing::debug!(
+                        "{} failed for {} (try {}): {}",
+                        self.provider.id(),
+                        asset.ticker,
+                        attempt + 1,
+                        e
+                    );
+                    tokio::time::sleep(retry_backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Every provider in the chain failed or none covers the asset
+#[derive(Debug)]
+pub struct ChainError {
+    pub ticker: String,
+    pub failures: Vec<(&'static str, anyhow::Error)>,
+}
+
+impl ChainError {
+    /// Whether Yahoo was set aside for rate limiting along the way
+    pub fn rate_limited(&self) -> bool {
+        self.failures
+            .iter()
+            .any(|(_, e)| super::yahoo::is_unavailable(e))
+    }
+}
+
+impl std::fmt::Display for ChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.failures.is_empty() {
+            return write!(f, "No price provider covers {}", self.ticker);
+        }
+        let reasons: Vec<String> = self
+            .failures
+            .iter()
+            .map(|(id, e)| format!("{}: {}", id, e))
+            .collect();
+        f.write_str(&reasons.join("; "))
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+/// Providers in priority order, each with its limiters
+pub struct ProviderChain {
+    links: Vec<Link>,
+    retries: u32,
+}
+
+impl ProviderChain {
+    pub fn from_config(config: &PricesConfig) -> Result<Self> {
+        let ids: Vec<String> = match &config.providers {
+            Some(ids) => ids.iter().map(|id| id.to_uppercase()).collect(),
+            None => [
+                YAHOO,
+                BRAPI,
+                B3_COTAHIST,
+                TESOURO_CSV,
+                MERCADO_BITCOIN,
+                MANUAL,
+            ]
+            .into_iter()
+            .filter(|id| *id != BRAPI || config.brapi_token.is_some())
+            .map(str::to_string)
+            .collect(),
+        };
+        let mut links = Vec::new();
+        for id in ids {
+            let provider: Box<dyn PriceProvider> = match id.as_str() {
+                YAHOO => Box::new(Yahoo),
+                BRAPI => Box::new(Brapi {
+                    token: config.brapi_token.clone(),
+                }),
+                B3_COTAHIST => Box::new(Cotahist {
+                    imported: OnceCell::new(),
+                }),
+                TESOURO_CSV => Box::new(Tesouro {
+                    imported: OnceCell::new(),
+                }),
+                MERCADO_BITCOIN => Box::new(MercadoBitcoin),
+                MANUAL => Box::new(Manual),
+                other => anyhow::bail!(
+                    "Unknown price provider {} in [prices] providers (use YAHOO, BRAPI, B3_COTAHIST, TESOURO_CSV, MERCADO_BITCOIN or MANUAL)",
+                    other
+                ),
+            };
+            let setting = |map: &std::collections::BTreeMap<String, u32>| {
+                map.iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(&id))
+                    .map(|(_, value)| *value)
+            };
+            let limiter = setting(&config.requests_per_minute).map(RateLimiter::per_minute);
+            let concurrency = setting(&config.max_concurrent).unwrap_or(DEFAULT_CONCURRENCY);
+            links.push(Link {
+                provider,
+                limiter,
+                permits: Semaphore::new(concurrency.max(1) as usize),
+            });
+        }
+        Ok(Self {
+            links,
+            retries: config.retries.unwrap_or(DEFAULT_RETRIES),
+        })
+    }
+
+    /// The chain set under `[prices]` in the config, or the default one
+    pub fn load() -> Result<Self> {
+        Self::from_config(&crate::config::load_config()?.prices.unwrap_or_default())
+    }
+
+    /// Providers tried for `asset`, in order
+    pub fn providers_for(&self, asset: &Asset) -> Vec<&'static str> {
+        self.links
+            .iter()
+            .filter(|l| l.provider.covers(asset))
+            .map(|l| l.provider.id())
+            .collect()
+    }
+
+    /// Ask each provider covering `asset` in turn; the first price wins
+    pub async fn quote(&self, asset: &Asset) -> Result<ProviderQuote, ChainError> {
+        let mut failures = Vec::new();
+        for link in self.links.iter().filter(|l| l.provider.covers(asset)) {
+            match link.quote(asset, self.retries).await {
+                Ok(quote) => return Ok(quote),
+                Err(e) => {
+                    tracing::debug!(
+                        "{} has no price for {}: {}",
+                        link.provider.id(),
+                        asset.ticker,
+                        e
+                    );
+                    failures.push((link.provider.id(), e));
+                }
+            }
+        }
+        Err(ChainError {
+            ticker: asset.ticker.clone(),
+            failures,
+        })
+    }
+}
+
+/// Store a quote under its provider; bulk and manual prices are already stored
+pub fn store(conn: &Connection, asset_id: i64, quote: &ProviderQuote) -> Result<()> {
+    if quote.stored {
+        return Ok(());
+    }
+    if let Some(snapshot_at) = quote.quoted_at {
+        crate::db::insert_price_snapshot(
+            conn,
+            &crate::db::PriceSnapshot {
+                asset_id,
+                snapshot_at,
+                price: quote.price,
+                source: quote.source.to_string(),
+            },
+        )?;
+    }
+    crate::db::insert_price_history(
+        conn,
+        &crate::db::PriceHistory {
+            id: None,
+            asset_id,
+            price_date: quote.date,
+            close_price: quote.price,
+            open_price: None,
+            high_price: None,
+            low_price: None,
+            volume: None,
+            source: quote.source.to_string(),
+            created_at: Utc::now(),
+            adjusted_close: None,
+        },
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn asset(ticker: &str, asset_type: AssetType) -> Asset {
+        Asset {
+            id: Some(1),
+            ticker: ticker.to_string(),
+            asset_type,
+            name: None,
+            cnpj: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_chain_order_coverage_and_brapi_parsing() {
+        let default = ProviderChain::from_config(&PricesConfig::default()).unwrap();
+        let stock = asset("PETR4", AssetType::Stock);
+        assert_eq!(
+            default.providers_for(&stock),
+            vec![YAHOO, B3_COTAHIST, MANUAL]
+        );
+        assert_eq!(
+            default.providers_for(&asset("TESOURO IPCA+ 2035", AssetType::GovBond)),
+            vec![TESOURO_CSV, MANUAL]
+        );
+        assert_eq!(
+            default.providers_for(&asset("CDB-XP-2026", AssetType::Bond)),
+            vec![MANUAL]
+        );
+        assert_eq!(
+            default.providers_for(&asset("BTC", AssetType::Crypto)),
+            vec![MERCADO_BITCOIN, MANUAL]
+        );
+
+        // A token brings brapi into the default order; a configured order wins
+        let config = PricesConfig {
+            brapi_token: Some("token".to_string()),
+            ..Default::default()
+        };
+        let with_brapi = ProviderChain::from_config(&config).unwrap();
+        assert_eq!(with_brapi.providers_for(&stock)[..2], [YAHOO, BRAPI]);
+        let config = PricesConfig {
+            providers: Some(vec!["brapi".to_string(), "yahoo".to_string()]),
+            ..Default::default()
+        };
+        let custom = ProviderChain::from_config(&config).unwrap();
+        assert_eq!(custom.providers_for(&stock), vec![BRAPI, YAHOO]);
+        let config = PricesConfig {
+            providers: Some(vec!["GOOGLE".to_string()]),
+            ..Default::default()
+        };
+        assert!(ProviderChain::from_config(&config).is_err());
+
+        assert_eq!(retry_backoff(0), Duration::from_millis(500));
+        assert_eq!(retry_backoff(10), RETRY_MAX);
+        let no_quote: anyhow::Error = NoQuote("none".to_string()).into();
+        assert!(!is_transient(&no_quote));
+
+        let (price, quoted_at) = parse_brapi(
+            "PETR4",
+            r#"{"results":[{"symbol":"PETR4","regularMarketPrice":38.42,
+                "regularMarketTime":"2024-06-03T20:07:00.000Z"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(price, dec!(38.42));
+        assert_eq!(quoted_at.to_rfc3339(), "2024-06-03T20:07:00+00:00");
+        let missing = parse_brapi("XXXX3", r#"{"results":[]}"#).unwrap_err();
+        assert!(missing.downcast_ref::<NoQuote>().is_some());
+
+        let (price, quoted_at) = parse_mercado_bitcoin(
+            "BTC",
+            r#"[{"pair":"BTC-BRL","high":"352000.00000000","low":"340100.00000000",
+                "vol":"48.51230000","last":"350123.45000000","buy":"350100.00000000",
+                "sell":"350123.45000000","open":"341000.00000000","date":1717430400}]"#,
+        )
+        .unwrap();
+        assert_eq!(price, dec!(350123.45));
+        assert_eq!(quoted_at.to_rfc3339(), "2024-06-03T16:00:00+00:00");
+    }
+}
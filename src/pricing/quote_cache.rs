@@ -0,0 +1,174 @@
+//! Live quotes kept in the database so separate runs reuse them.
+//!
+//! Each entry expires a TTL after it was fetched while B3 is trading. A
+//! quote fetched with the market closed cannot change before the next
+//! session, so it lasts until the next opening instead.
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveTime, Utc, Weekday};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::LiveQuote;
+
+pub const DEFAULT_TTL_MINUTES: u32 = 15;
+
+/// B3 regular session in Brasília time (UTC-3, no daylight saving)
+const UTC_OFFSET_SECS: i32 = -3 * 3600;
+const SESSION_OPEN: (u32, u32) = (10, 0);
+const SESSION_CLOSE: (u32, u32) = (18, 0);
+
+/// Reuse window during trading hours, from `[prices] quote_ttl_minutes`
+pub fn ttl() -> Duration {
+    let minutes = crate::config::load_config()
+        .ok()
+        .and_then(|c| c.prices)
+        .and_then(|p| p.quote_ttl_minutes)
+        .unwrap_or(DEFAULT_TTL_MINUTES);
+    Duration::minutes(minutes.into())
+}
+
+/// When a quote fetched at `fetched_at` stops being reused
+pub fn expires_at(fetched_at: DateTime<Utc>, ttl: Duration) -> DateTime<Utc> {
+    let brt = FixedOffset::east_opt(UTC_OFFSET_SECS).expect("valid offset");
+    let local = fetched_at.with_timezone(&brt);
+    let open = NaiveTime::from_hms_opt(SESSION_OPEN.0, SESSION_OPEN.1, 0).expect("valid time");
+    let close = NaiveTime::from_hms_opt(SESSION_CLOSE.0, SESSION_CLOSE.1, 0).expect("valid time");
+    let weekday = !matches!(local.weekday(), Weekday::Sat | Weekday::Sun);
+    if weekday && local.time() >= open && local.time() < close {
+        return fetched_at + ttl;
+    }
+
+    // Closed: good until the next weekday opening
+    let mut day = local.date_naive();
+    if local.time() >= open || !weekday {
+        day = day.succ_opt().expect("date in range");
+    }
+    while matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+        day = day.succ_opt().expect("date in range");
+    }
+    day.and_time(open)
+        .and_local_timezone(brt)
+        .single()
+        .expect("fixed offset is unambiguous")
+        .with_timezone(&Utc)
+}
+
+/// The cached quote for `ticker` and when it expires, unless it expired by `now`
+pub fn get(
+    conn: &Connection,
+    ticker: &str,
+    now: DateTime<Utc>,
+) -> Result<Option<(LiveQuote, DateTime<Utc>)>> {
+    let row = conn
+        .query_row(
+            "SELECT price, quoted_at, expires_at FROM quote_cache WHERE ticker = ?1",
+            [ticker],
+            |row| {
+                Ok((
+                    crate::db::get_decimal_value(row, 0)?,
+                    row.get::<_, DateTime<Utc>>(1)?,
+                    row.get::<_, DateTime<Utc>>(2)?,
+                ))
+            },
+        )
+        .optional()?;
+    Ok(row
+        .filter(|(_, _, expires_at)| *expires_at > now)
+        .map(|(price, quoted_at, expires_at)| (LiveQuote { price, quoted_at }, expires_at)))
+}
+
+/// Keep `quote` for `ticker` until `expires_at`
+pub fn put(
+    conn: &Connection,
+    ticker: &str,
+    quote: &LiveQuote,
+    source: &str,
+    fetched_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO quote_cache (ticker, price, quoted_at, fetched_at, expires_at, source)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            ticker,
+            quote.price.to_string(),
+            quote.quoted_at,
+            fetched_at,
+            expires_at,
+            source
+        ],
+    )?;
+    Ok(())
+}
+
+/// Forget every cached quote, returning how many there were
+pub fn clear(conn: &Connection) -> Result<usize> {
+    Ok(conn.execute("DELETE FROM quote_cache", [])?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+    use tempfile::TempDir;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_quote_cache_expiry_and_market_hours() -> Result<()> {
+        let ttl = Duration::minutes(15);
+        // Wednesday 14:00 BRT: trading, so the TTL applies
+        assert_eq!(
+            expires_at(utc("2024-06-05T17:00:00Z"), ttl),
+            utc("2024-06-05T17:15:00Z")
+        );
+        // Wednesday 19:00 BRT: closed until Thursday 10:00 BRT
+        assert_eq!(
+            expires_at(utc("2024-06-05T22:00:00Z"), ttl),
+            utc("2024-06-06T13:00:00Z")
+        );
+        // Thursday 08:00 BRT: before the same day's opening
+        assert_eq!(
+            expires_at(utc("2024-06-06T11:00:00Z"), ttl),
+            utc("2024-06-06T13:00:00Z")
+        );
+        // Friday evening and Saturday both last until Monday's opening
+        assert_eq!(
+            expires_at(utc("2024-06-07T23:00:00Z"), ttl),
+            utc("2024-06-10T13:00:00Z")
+        );
+        assert_eq!(
+            expires_at(utc("2024-06-08T15:00:00Z"), ttl),
+            utc("2024-06-10T13:00:00Z")
+        );
+
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.db");
+        crate::db::init_database(Some(path.clone()))?;
+        let conn = crate::db::open_db(Some(path))?;
+        let fetched_at = utc("2024-06-05T17:00:00Z");
+        let quote = LiveQuote {
+            price: dec!(38.42),
+            quoted_at: utc("2024-06-05T16:59:00Z"),
+        };
+        put(
+            &conn,
+            "PETR4",
+            &quote,
+            "YAHOO",
+            fetched_at,
+            expires_at(fetched_at, ttl),
+        )?;
+
+        let (cached, until) = get(&conn, "PETR4", utc("2024-06-05T17:10:00Z"))?.unwrap();
+        assert_eq!(until, utc("2024-06-05T17:15:00Z"));
+        assert_eq!(cached.price, dec!(38.42));
+        assert_eq!(cached.quoted_at, quote.quoted_at);
+        assert!(get(&conn, "PETR4", utc("2024-06-05T17:20:00Z"))?.is_none());
+        assert!(get(&conn, "VALE3", fetched_at)?.is_none());
+        assert_eq!(clear(&conn)?, 1);
+        Ok(())
+    }
+}
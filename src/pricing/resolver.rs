@@ -12,7 +12,6 @@
 use anyhow::{anyhow, Result};
 use chrono::{Datelike, Local, NaiveDate};
 use rusqlite::{Connection, OptionalExtension};
-use rust_decimal::Decimal;
 use std::collections::HashSet;
 use std::sync::Arc;
 use tokio::sync::Semaphore;
@@ -22,6 +21,7 @@ use crate::utils::format_currency;
 
 use crate::db::models::{Asset, AssetType};
 use crate::importers::b3_cotahist;
+use crate::pricing::provider::{ProviderChain, ProviderQuote};
 use crate::pricing::tesouro;
 
 /// Maximum concurrent API requests to avoid rate limiting
@@ -603,6 +603,7 @@ pub(crate) fn is_priceable_asset(asset: &Asset) -> bool {
         | AssetType::Fip
         | AssetType::Option
         | AssetType::TermContract
+        | AssetType::Crypto
         | AssetType::Unknown => false,
     }
 }
@@ -625,67 +626,104 @@ async fn fetch_current_prices_with_progress<F>(
 where
     F: FnMut(&crate::ui::progress::ProgressEvent),
 {
-    let today = Local::now().date_naive();
     let total = assets.len();
     let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_REQUESTS));
+    let chain = Arc::new(ProviderChain::load()?);
 
     // Use JoinSet to get results as they complete (not in spawn order)
     let mut join_set = JoinSet::new();
 
     for asset in assets {
         let sem = semaphore.clone();
-        let ticker = asset.ticker.clone();
-        let asset_id = asset.id.expect("Asset from database must have id");
+        let chain = chain.clone();
+        let asset = asset.clone();
 
         join_set.spawn(async move {
             // Acquire semaphore permit (limits concurrent requests)
             let _permit = sem.acquire().await.unwrap();
 
-            let result = crate::pricing::fetch_price(&ticker).await;
-            (asset_id, ticker, result)
+            let result = chain.quote(&asset).await;
+            (asset, result)
         });
     }
 
     // Collect results as they complete (whichever finishes first)
-    let mut successful_prices: Vec<(i64, Decimal)> = Vec::new();
+    let mut successful_prices: Vec<(i64, ProviderQuote)> = Vec::new();
     let mut completed = 0;
 
     while let Some(result) = join_set.join_next().await {
-        let (asset_id, ticker, fetch_result) = result?;
+        let (asset, fetch_result) = result?;
         completed += 1;
 
         match fetch_result {
-            Ok(price) => {
-                successful_prices.push((asset_id, price));
+            Ok(quote) => {
                 progress(&ProgressEvent::TickerResult {
-                    ticker: ticker.clone(),
-                    price: Ok(format_currency(price)),
+                    ticker: asset.ticker.clone(),
+                    price: Ok(format_currency(quote.price)),
                     current: completed,
                     total,
                 });
-                tracing::debug!("Fetched price for {}: {}", ticker, price);
+                tracing::debug!(
+                    "Fetched price for {} from {}: {}",
+                    asset.ticker,
+                    quote.source,
+                    quote.price
+                );
+                successful_prices
+                    .push((asset.id.expect("Asset from database must have id"), quote));
             }
             Err(e) => {
                 progress(&ProgressEvent::TickerResult {
-                    ticker: ticker.clone(),
+                    ticker: asset.ticker.clone(),
                     price: Err("failed".to_string()),
                     current: completed,
                     total,
                 });
-                tracing::warn!("Failed to fetch price for {}: {}", ticker, e);
+                tracing::warn!("Failed to fetch price for {}: {}", asset.ticker, e);
             }
         }
     }
 
-    // Batch insert all successful prices
-    for (asset_id, price) in successful_prices {
-        conn.execute(
-            "INSERT OR REPLACE INTO price_history (asset_id, price_date, close_price, source)
-             VALUES (?1, ?2, ?3, 'YAHOO')",
-            rusqlite::params![asset_id, today, &price.to_string()],
-        )?;
+    // Keep each live quote as an intraday snapshot; today's daily row holds the
+    // latest quote until a closing price replaces it. Closes and valuations the
+    // providers answered from the database are already stored.
+    let fresh: Vec<_> = successful_prices
+        .into_iter()
+        .filter(|(_, quote)| !quote.stored)
+        .collect();
+    for (asset_id, quote) in &fresh {
+        if let Some(snapshot_at) = quote.quoted_at {
+            crate::db::insert_price_snapshot(
+                conn,
+                &crate::db::PriceSnapshot {
+                    asset_id: *asset_id,
+                    snapshot_at,
+                    price: quote.price,
+                    source: quote.source.to_string(),
+                },
+            )?;
+        }
     }
 
+    // Batch insert all successful prices
+    let prices: Vec<_> = fresh
+        .into_iter()
+        .map(|(asset_id, quote)| crate::db::PriceHistory {
+            id: None,
+            asset_id,
+            price_date: quote.date,
+            close_price: quote.price,
+            open_price: None,
+            high_price: None,
+            low_price: None,
+            volume: None,
+            source: quote.source.to_string(),
+            created_at: chrono::Utc::now(),
+            adjusted_close: None,
+        })
+        .collect();
+    crate::db::bulk::insert_price_history(conn, &prices, |_| {})?;
+
     Ok(())
 }
 
@@ -786,6 +824,7 @@ mod tests {
             volume: None,
             source: "TEST".to_string(),
             created_at: Utc::now(),
+            adjusted_close: None,
         };
         crate::db::insert_price_history(conn, &price)?;
         Ok(())
@@ -0,0 +1,189 @@
+//! Retry queue for price fetches that failed.
+//!
+//! When `prices update` cannot quote a ticker (rate limits, timeouts, a
+//! provider hiccup) the asset is queued with the error and a time before
+//! which it is not tried again. Each failure in a row doubles the wait, from
+//! 5 minutes up to 6 hours. While the queue has entries, `prices update`
+//! retries only the queued tickers that are due, and the background refresh
+//! (in process or in the daemon) does the same; a successful quote removes
+//! the entry.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// Wait after the first failure
+const BASE_DELAY_MINUTES: i64 = 5;
+/// Longest wait between attempts
+const MAX_DELAY_MINUTES: i64 = 6 * 60;
+
+/// A ticker waiting to be fetched again
+#[derive(Debug, Clone)]
+pub struct QueuedFetch {
+    pub asset_id: i64,
+    pub ticker: String,
+    pub attempts: i64,
+    pub last_error: String,
+    pub next_attempt_at: DateTime<Utc>,
+}
+
+impl QueuedFetch {
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_attempt_at <= now
+    }
+}
+
+/// Outcome of retrying the due entries
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RetryOutcome {
+    pub retried: usize,
+    pub recovered: usize,
+    /// Entries still queued, due or not
+    pub waiting: usize,
+}
+
+/// Wait before the next attempt after `attempts` failures in a row
+pub fn backoff(attempts: i64) -> Duration {
+    let doublings = (attempts - 1).clamp(0, 16) as u32;
+    Duration::minutes((BASE_DELAY_MINUTES << doublings).min(MAX_DELAY_MINUTES))
+}
+
+/// Queue `asset_id` after a failed fetch; returns when it is next tried
+pub fn record_failure(
+    conn: &Connection,
+    asset_id: i64,
+    error: &str,
+    now: DateTime<Utc>,
+) -> Result<DateTime<Utc>> {
+    let attempts: i64 = conn
+        .query_row(
+            "SELECT attempts FROM price_retry_queue WHERE asset_id = ?1",
+            [asset_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .unwrap_or(0)
+        + 1;
+    let next_attempt_at = now + backoff(attempts);
+    conn.execute(
+        "INSERT INTO price_retry_queue
+             (asset_id, attempts, last_error, first_failed_at, next_attempt_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(asset_id) DO UPDATE SET
+             attempts = excluded.attempts,
+             last_error = excluded.last_error,
+             next_attempt_at = excluded.next_attempt_at",
+        params![asset_id, attempts, error, now, next_attempt_at],
+    )?;
+    Ok(next_attempt_at)
+}
+
+/// Drop `asset_id` from the queue after a successful fetch
+pub fn clear(conn: &Connection, asset_id: i64) -> Result<()> {
+    conn.execute(
+        "DELETE FROM price_retry_queue WHERE asset_id = ?1",
+        [asset_id],
+    )?;
+    Ok(())
+}
+
+/// Every queued ticker, soonest retry first
+pub fn pending(conn: &Connection) -> Result<Vec<QueuedFetch>> {
+    let mut stmt = conn.prepare(
+        "SELECT q.asset_id, a.ticker, q.attempts, q.last_error, q.next_attempt_at
+         FROM price_retry_queue q
+         JOIN assets a ON a.id = q.asset_id
+         ORDER BY q.next_attempt_at, a.ticker",
+    )?;
+    let queued = stmt
+        .query_map([], |row| {
+            Ok(QueuedFetch {
+                asset_id: row.get(0)?,
+                ticker: row.get(1)?,
+                attempts: row.get(2)?,
+                last_error: row.get(3)?,
+                next_attempt_at: row.get(4)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(queued)
+}
+
+/// Fetch the queued tickers that are due through the provider chain
+pub async fn retry_due(conn: &Connection) -> Result<RetryOutcome> {
+    let now = Utc::now();
+    let queued = pending(conn)?;
+    if !queued.iter().any(|q| q.is_due(now)) {
+        return Ok(RetryOutcome {
+            waiting: queued.len(),
+            ..Default::default()
+        });
+    }
+    let chain = super::provider::ProviderChain::load()?;
+    let mut outcome = RetryOutcome {
+        waiting: queued.len(),
+        ..Default::default()
+    };
+    for entry in queued.iter().filter(|q| q.is_due(now)) {
+        let Some(asset) = crate::db::get_asset_by_ticker(conn, &entry.ticker)? else {
+            clear(conn, entry.asset_id)?;
+            outcome.waiting -= 1;
+            continue;
+        };
+        outcome.retried += 1;
+        match chain.quote(&asset).await {
+            Ok(quote) => {
+                super::provider::store(conn, entry.asset_id, &quote)?;
+                clear(conn, entry.asset_id)?;
+                outcome.recovered += 1;
+                outcome.waiting -= 1;
+            }
+            Err(e) => {
+                let next = record_failure(conn, entry.asset_id, &e.to_string(), Utc::now())?;
+                tracing::warn!(
+                    "Retry {} for {} failed, next attempt at {}: {}",
+                    entry.attempts + 1,
+                    entry.ticker,
+                    next,
+                    e
+                );
+            }
+        }
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failures_back_off_until_a_fetch_succeeds() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'PETR4', 'STOCK'),
+                                                                (2, 'VALE3', 'STOCK');",
+        )
+        .unwrap();
+        let now = Utc::now();
+
+        record_failure(&conn, 1, "429 Too Many Requests", now).unwrap();
+        let next = record_failure(&conn, 1, "timeout", now).unwrap();
+        assert_eq!(next, now + Duration::minutes(10));
+        record_failure(&conn, 2, "timeout", now - Duration::hours(1)).unwrap();
+
+        let queued = pending(&conn).unwrap();
+        let tickers: Vec<&str> = queued.iter().map(|q| q.ticker.as_str()).collect();
+        assert_eq!(tickers, vec!["VALE3", "PETR4"]);
+        assert_eq!(queued[1].attempts, 2);
+        assert_eq!(queued[1].last_error, "timeout");
+        let due: Vec<bool> = queued.iter().map(|q| q.is_due(now)).collect();
+        assert_eq!(due, vec![true, false]);
+
+        clear(&conn, 2).unwrap();
+        assert_eq!(pending(&conn).unwrap().len(), 1);
+        assert_eq!(backoff(30), Duration::hours(6));
+    }
+}
@@ -226,6 +226,7 @@ fn import_tesouro_csv_from_content(
             volume: None,
             source: "TESOURO_CSV".to_string(),
             created_at: chrono::Utc::now(),
+            adjusted_close: None,
         };
         crate::db::insert_price_history(conn, &price)?;
 
@@ -0,0 +1,231 @@
+//! Live quotes for held assets, refreshed on an interval.
+//!
+//! `prices watch` asks Yahoo for a fresh quote of every held asset each
+//! round and stores it the way `prices update` does, so reports opened in the
+//! meantime see it. Tickers are fetched one at a time and rounds never start
+//! closer than [`MIN_INTERVAL_SECS`] apart. When Yahoo's circuit opens (see
+//! `yahoo.rs`) the round stops where it is and the next one waits out the
+//! cooldown instead of piling more requests on the provider.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Local, NaiveDate, Utc};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::time::Duration;
+
+use super::coverage::{self, YAHOO};
+use super::{retry_queue, yahoo, LiveQuote};
+
+pub const DEFAULT_INTERVAL_SECS: u64 = 60;
+/// Shortest wait between rounds, whatever the interval asked for
+pub const MIN_INTERVAL_SECS: u64 = 15;
+
+/// One held asset on the watch board
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchRow {
+    #[serde(skip)]
+    pub asset_id: i64,
+    pub ticker: String,
+    pub quantity: Decimal,
+    pub average_cost: Decimal,
+    /// Latest quote, or the latest stored price until one arrives
+    pub price: Option<Decimal>,
+    /// When `price` was quoted; None while it is a stored close
+    pub quoted_at: Option<DateTime<Utc>>,
+    /// Last close stored before today
+    pub previous_close: Option<Decimal>,
+    /// Why the last fetch failed; cleared by the next quote
+    pub error: Option<String>,
+}
+
+impl WatchRow {
+    pub fn day_change(&self) -> Option<Decimal> {
+        Some(self.price? - self.previous_close?)
+    }
+
+    pub fn day_change_pct(&self) -> Option<Decimal> {
+        let previous = self.previous_close.filter(|p| !p.is_zero())?;
+        Some((self.day_change()? / previous * Decimal::ONE_HUNDRED).round_dp(2))
+    }
+
+    pub fn market_value(&self) -> Option<Decimal> {
+        Some(self.price? * self.quantity)
+    }
+
+    pub fn cost(&self) -> Decimal {
+        self.average_cost * self.quantity
+    }
+
+    /// Unrealized gain of the position at `price`
+    pub fn pnl(&self) -> Option<Decimal> {
+        Some(self.market_value()? - self.cost())
+    }
+
+    pub fn pnl_pct(&self) -> Option<Decimal> {
+        let cost = self.cost();
+        if cost.is_zero() {
+            return None;
+        }
+        Some((self.pnl()? / cost * Decimal::ONE_HUNDRED).round_dp(2))
+    }
+}
+
+/// Sums over the rows that have a price
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WatchTotals {
+    pub market_value: Decimal,
+    pub day_change: Decimal,
+    pub pnl: Decimal,
+}
+
+pub fn totals(rows: &[WatchRow]) -> WatchTotals {
+    let mut totals = WatchTotals::default();
+    for row in rows {
+        totals.market_value += row.market_value().unwrap_or_default();
+        totals.day_change += row.day_change().unwrap_or_default() * row.quantity;
+        totals.pnl += row.pnl().unwrap_or_default();
+    }
+    totals
+}
+
+/// What a round fetched
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RoundOutcome {
+    pub quoted: usize,
+    pub failed: usize,
+    /// Set when Yahoo was set aside and the round stopped early
+    pub cooldown: Option<Duration>,
+}
+
+/// Held assets Yahoo quotes, priced from what is stored
+pub fn held_rows(conn: &Connection) -> Result<Vec<WatchRow>> {
+    let yesterday = Local::now().date_naive() - ChronoDuration::days(1);
+    let mut rows = Vec::new();
+    for position in crate::reports::calculate_portfolio(conn, None)?.positions {
+        let Some(asset_id) = position.asset.id else {
+            continue;
+        };
+        if !coverage::eligible_providers(&position.asset).contains(&YAHOO) {
+            continue;
+        }
+        rows.push(WatchRow {
+            asset_id,
+            ticker: position.asset.ticker,
+            quantity: position.quantity,
+            average_cost: position.average_cost,
+            price: crate::db::get_latest_price(conn, asset_id)?.map(|p| p.close_price),
+            quoted_at: None,
+            previous_close: crate::db::get_price_on_or_before(conn, asset_id, yesterday)?
+                .map(|p| p.close_price),
+            error: None,
+        });
+    }
+    rows.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+    Ok(rows)
+}
+
+/// Ask Yahoo for `ticker` right now, skipping the quote cache, and store the
+/// quote as `date`'s price and as the cached quote
+pub async fn quote_now(
+    conn: &Connection,
+    asset_id: i64,
+    ticker: &str,
+    date: NaiveDate,
+) -> Result<LiveQuote> {
+    let data = yahoo::fetch_current_price(ticker).await?;
+    let quote = LiveQuote {
+        price: data.price,
+        quoted_at: data.timestamp,
+    };
+    super::store_quote(conn, asset_id, &quote, date)?;
+    super::cache_quote(conn, ticker, &quote)?;
+    retry_queue::clear(conn, asset_id)?;
+    Ok(quote)
+}
+
+/// Fetch a fresh quote for every row, storing each as today's price
+pub async fn refresh(conn: &Connection, rows: &mut [WatchRow]) -> Result<RoundOutcome> {
+    let today = Local::now().date_naive();
+    let mut outcome = RoundOutcome::default();
+    for row in rows.iter_mut() {
+        match quote_now(conn, row.asset_id, &row.ticker, today).await {
+            Ok(quote) => {
+                row.price = Some(quote.price);
+                row.quoted_at = Some(quote.quoted_at);
+                row.error = None;
+                outcome.quoted += 1;
+            }
+            Err(e) => {
+                outcome.failed += 1;
+                if let Some(unavailable) = e.downcast_ref::<yahoo::YahooUnavailable>() {
+                    outcome.cooldown = Some(unavailable.retry_in);
+                    row.error = Some(unavailable.to_string());
+                    break;
+                }
+                row.error = Some(e.to_string());
+            }
+        }
+    }
+    Ok(outcome)
+}
+
+/// Wait before the next round: the interval, never under the minimum nor
+/// before an open circuit closes
+pub fn next_wait(interval_secs: u64, outcome: &RoundOutcome) -> Duration {
+    let interval = Duration::from_secs(interval_secs.max(MIN_INTERVAL_SECS));
+    outcome.cooldown.map_or(interval, |c| c.max(interval))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_held_rows_start_from_stored_prices() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let yesterday = Local::now().date_naive() - ChronoDuration::days(1);
+        let today = Local::now().date_naive();
+        conn.execute_batch(&format!(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'PETR4', 'STOCK'),
+                                                                (2, 'CDB-XYZ', 'BOND');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source)
+                 VALUES (1, 'BUY', '2024-01-10', '100', '30', '3000', '0', 'TEST'),
+                        (2, 'BUY', '2024-01-10', '1', '1000', '1000', '0', 'TEST');
+             INSERT INTO price_history (asset_id, price_date, close_price, source)
+                 VALUES (1, '{yesterday}', '32', 'TEST'), (1, '{today}', '33.60', 'TEST');"
+        ))
+        .unwrap();
+
+        // Assets Yahoo does not quote stay off the board
+        let rows = held_rows(&conn).unwrap();
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(
+            (row.price, row.previous_close),
+            (Some(dec!(33.60)), Some(dec!(32)))
+        );
+        assert_eq!(row.day_change(), Some(dec!(1.60)));
+        assert_eq!(row.day_change_pct(), Some(dec!(5)));
+        assert_eq!(row.pnl(), Some(dec!(360)));
+        assert_eq!(row.pnl_pct(), Some(dec!(12)));
+
+        let totals = totals(&rows);
+        assert_eq!(totals.market_value, dec!(3360));
+        assert_eq!(totals.day_change, dec!(160));
+
+        let throttled = RoundOutcome {
+            cooldown: Some(Duration::from_secs(120)),
+            ..Default::default()
+        };
+        assert_eq!(
+            next_wait(5, &RoundOutcome::default()),
+            Duration::from_secs(15)
+        );
+        assert_eq!(next_wait(60, &throttled), Duration::from_secs(120));
+    }
+}
@@ -1,9 +1,257 @@
+//! Yahoo Finance chart client.
+//!
+//! Every request shares one session: the cookie Yahoo sets on its consent
+//! domain and the crumb issued for it, fetched on first use and refreshed when
+//! a request is turned away. Throttled requests (429) back off exponentially,
+//! or for as long as `Retry-After` asks, and hold back the other requests in
+//! flight too. After [`BREAKER_THRESHOLD`] requests in a row are throttled or
+//! fail, the circuit opens: for [`BREAKER_COOLDOWN`] every call fails at once
+//! with [`YahooUnavailable`], so callers move on to their fallback instead of
+//! waiting on each ticker.
+
 use anyhow::{anyhow, Context, Result};
 use chrono::NaiveDate;
-use reqwest::Client;
+use once_cell::sync::Lazy;
+use reqwest::cookie::Jar;
+use reqwest::{Client, StatusCode};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, info};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+const USER_AGENT: &str = "Mozilla/5.0 (compatible; InterestBot/1.0)";
+const CONSENT_URL: &str = "https://fc.yahoo.com";
+const CRUMB_URL: &str = "https://query1.finance.yahoo.com/v1/test/getcrumb";
+
+/// Tries per chart request, throttled and rejected ones included
+const MAX_ATTEMPTS: u32 = 4;
+/// Wait after the first 429, doubled on each one that follows
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Requests in a row throttled or failed that open the circuit
+pub const BREAKER_THRESHOLD: u32 = 3;
+/// How long an open circuit keeps Yahoo out of the way
+pub const BREAKER_COOLDOWN: Duration = Duration::from_secs(120);
+
+/// Yahoo is throttling or failing and was set aside for a while
+#[derive(Debug, Clone, Copy)]
+pub struct YahooUnavailable {
+    pub retry_in: Duration,
+}
+
+impl std::fmt::Display for YahooUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Yahoo Finance is rate limiting or failing; skipped for the next {}s",
+            self.retry_in.as_secs().max(1)
+        )
+    }
+}
+
+impl std::error::Error for YahooUnavailable {}
+
+/// Whether `err` comes from the open circuit rather than from the ticker
+pub fn is_unavailable(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<YahooUnavailable>().is_some()
+}
+
+/// What a request has to do before going out
+#[derive(Debug, PartialEq, Eq)]
+enum Gate {
+    Go,
+    Wait(Duration),
+    Open(Duration),
+}
+
+/// Throttling and failure history shared by all requests
+#[derive(Debug, Default)]
+struct Health {
+    /// Requests in a row that were throttled or failed
+    failures: u32,
+    /// 429s in a row, which set the backoff
+    throttles: u32,
+    paused_until: Option<Instant>,
+    open_until: Option<Instant>,
+}
+
+impl Health {
+    fn gate(&self, now: Instant) -> Gate {
+        if let Some(until) = self.open_until.filter(|&until| until > now) {
+            return Gate::Open(until - now);
+        }
+        match self.paused_until.filter(|&until| until > now) {
+            Some(until) => Gate::Wait(until - now),
+            None => Gate::Go,
+        }
+    }
+
+    fn succeeded(&mut self) {
+        self.failures = 0;
+        self.throttles = 0;
+    }
+
+    /// Count a failure. Past the threshold the circuit opens, and once the
+    /// cooldown ends a single failure opens it again.
+    fn failed(&mut self, now: Instant) {
+        self.failures += 1;
+        if self.failures >= BREAKER_THRESHOLD {
+            let until = now + BREAKER_COOLDOWN;
+            self.open_until = Some(self.open_until.map_or(until, |open| open.max(until)));
+        }
+    }
+
+    /// Count a 429 and pause every request for the backoff
+    fn throttled(&mut self, retry_after: Option<Duration>, now: Instant) {
+        self.throttles += 1;
+        let backoff = BASE_BACKOFF
+            .saturating_mul(1 << (self.throttles - 1).min(16))
+            .min(MAX_BACKOFF);
+        let wait = retry_after.unwrap_or(backoff);
+        if wait > MAX_BACKOFF {
+            // Asked to stay away longer than any backoff: open right away
+            self.open_until = Some(now + wait);
+        }
+        self.paused_until = Some(now + wait.min(MAX_BACKOFF));
+        self.failed(now);
+    }
+}
+
+/// Cookies, crumb and health of the Yahoo session
+struct Session {
+    jar: Arc<Jar>,
+    crumb: tokio::sync::Mutex<Option<String>>,
+    health: Mutex<Health>,
+}
+
+static SESSION: Lazy<Session> = Lazy::new(|| Session {
+    jar: Arc::new(Jar::default()),
+    crumb: tokio::sync::Mutex::new(None),
+    health: Mutex::new(Health::default()),
+});
+
+impl Session {
+    /// A client sharing the session cookies. Built per call so it never
+    /// outlives the runtime it was used on.
+    fn client(&self) -> Result<Client> {
+        Ok(Client::builder()
+            .user_agent(USER_AGENT)
+            .cookie_provider(self.jar.clone())
+            .build()?)
+    }
+
+    fn health(&self) -> std::sync::MutexGuard<'_, Health> {
+        self.health.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The crumb for this session, fetched on first use. Yahoo still answers
+    /// many requests without one, so failing to get it is not an error.
+    async fn crumb(&self, client: &Client, refresh: bool) -> Option<String> {
+        if super::cassette::is_replay().unwrap_or(false) {
+            return None;
+        }
+        let mut crumb = self.crumb.lock().await;
+        if refresh {
+            *crumb = None;
+        }
+        if crumb.is_none() {
+            *crumb = fetch_crumb(client).await;
+        }
+        crumb.clone()
+    }
+}
+
+async fn fetch_crumb(client: &Client) -> Option<String> {
+    // Only the cookie matters; the consent domain answers 404
+    if let Err(e) = client.get(CONSENT_URL).send().await {
+        debug!("Yahoo consent cookie request failed: {}", e);
+    }
+    let response = client.get(CRUMB_URL).send().await.ok()?;
+    if !response.status().is_success() {
+        debug!("Yahoo crumb request returned {}", response.status());
+        return None;
+    }
+    let crumb = response.text().await.ok()?.trim().to_string();
+    (!crumb.is_empty() && !crumb.contains('<')).then_some(crumb)
+}
+
+fn with_crumb(url: &str, crumb: Option<&str>) -> String {
+    match (crumb, reqwest::Url::parse(url)) {
+        (Some(crumb), Ok(mut parsed)) => {
+            parsed.query_pairs_mut().append_pair("crumb", crumb);
+            parsed.into()
+        }
+        _ => url.to_string(),
+    }
+}
+
+/// GET a chart URL through the session, backing off on 429s and refreshing
+/// the crumb when Yahoo rejects it
+async fn get_chart(url: &str) -> Result<String> {
+    let session = &*SESSION;
+    let client = session.client()?;
+    let mut refresh_crumb = false;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        loop {
+            let gate = session.health().gate(Instant::now());
+            match gate {
+                Gate::Go => break,
+                Gate::Wait(wait) => tokio::time::sleep(wait).await,
+                Gate::Open(retry_in) => return Err(YahooUnavailable { retry_in }.into()),
+            }
+        }
+
+        let crumb = session.crumb(&client, refresh_crumb).await;
+        let reply = super::cassette::get_reply(&client, url, "Yahoo Finance", |url| {
+            with_crumb(url, crumb.as_deref())
+        })
+        .await;
+
+        let reply = match reply {
+            Ok(reply) => reply,
+            Err(e) => {
+                session.health().failed(Instant::now());
+                return Err(e);
+            }
+        };
+
+        match reply.status {
+            StatusCode::TOO_MANY_REQUESTS => {
+                warn!("Yahoo Finance throttled attempt {} for {}", attempt, url);
+                session
+                    .health()
+                    .throttled(reply.retry_after, Instant::now());
+            }
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN if !refresh_crumb => {
+                debug!("Yahoo Finance rejected the session; refreshing the crumb");
+                refresh_crumb = true;
+            }
+            status @ (StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => {
+                session.health().failed(Instant::now());
+                return Err(anyhow!("Yahoo Finance refused the session: {}", status));
+            }
+            status if status.is_server_error() => {
+                session.health().failed(Instant::now());
+                return Err(anyhow!("Yahoo Finance returned error status: {}", status));
+            }
+            status => {
+                // An unknown ticker (404) says nothing about Yahoo's health
+                session.health().succeeded();
+                if !status.is_success() {
+                    return Err(anyhow!("Yahoo Finance returned error status: {}", status));
+                }
+                return Ok(reply.body);
+            }
+        }
+    }
+
+    Err(anyhow!(
+        "Yahoo Finance kept throttling after {} attempts",
+        MAX_ATTEMPTS
+    ))
+}
 
 /// Yahoo Finance quote response
 #[derive(Debug, Deserialize)]
@@ -28,6 +276,8 @@ struct ChartResult {
 struct Meta {
     #[serde(rename = "regularMarketPrice")]
     regular_market_price: Option<f64>,
+    #[serde(rename = "regularMarketTime")]
+    regular_market_time: Option<i64>,
     currency: Option<String>,
     #[allow(dead_code)]
     symbol: String,
@@ -36,6 +286,12 @@ struct Meta {
 #[derive(Debug, Deserialize)]
 struct Indicators {
     quote: Vec<Quote>,
+    adjclose: Option<Vec<AdjClose>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AdjClose {
+    adjclose: Option<Vec<Option<f64>>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -70,6 +326,8 @@ pub struct HistoricalPrice {
     pub high: Option<Decimal>,
     pub low: Option<Decimal>,
     pub close: Decimal,
+    /// Close adjusted for splits and dividends
+    pub adjusted_close: Option<Decimal>,
     pub volume: Option<i64>,
 }
 
@@ -78,32 +336,14 @@ pub async fn fetch_current_price(ticker: &str) -> Result<PriceData> {
     let symbol = format!("{}.SA", ticker);
     info!("Fetching current price for {} from Yahoo Finance", symbol);
 
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (compatible; InterestBot/1.0)")
-        .build()?;
-
     let url = format!(
         "https://query1.finance.yahoo.com/v8/finance/chart/{}",
         symbol
     );
 
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .context("Failed to send request to Yahoo Finance")?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!(
-            "Yahoo Finance returned error status: {}",
-            response.status()
-        ));
-    }
-
-    let data: YahooQuoteResponse = response
-        .json()
-        .await
-        .context("Failed to parse Yahoo Finance response")?;
+    let body = get_chart(&url).await?;
+    let data: YahooQuoteResponse =
+        serde_json::from_str(&body).context("Failed to parse Yahoo Finance response")?;
     parse_current_price_response(ticker, data)
 }
 
@@ -118,16 +358,20 @@ pub async fn fetch_historical_prices(
     from: NaiveDate,
     to: NaiveDate,
 ) -> Result<Vec<HistoricalPrice>> {
-    let symbol = format!("{}.SA", ticker);
+    fetch_historical_prices_for_symbol(&format!("{}.SA", ticker), from, to).await
+}
+
+/// Fetch historical prices for a raw Yahoo symbol (e.g., `^BVSP`)
+pub async fn fetch_historical_prices_for_symbol(
+    symbol: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<HistoricalPrice>> {
     info!(
         "Fetching historical prices for {} from {} to {}",
         symbol, from, to
     );
 
-    let client = Client::builder()
-        .user_agent("Mozilla/5.0 (compatible; InterestBot/1.0)")
-        .build()?;
-
     // Convert dates to Unix timestamps
     let from_timestamp = from
         .and_hms_opt(0, 0, 0)
@@ -143,26 +387,14 @@ pub async fn fetch_historical_prices(
 
     let url = format!(
         "https://query1.finance.yahoo.com/v8/finance/chart/{}?period1={}&period2={}&interval=1d",
-        symbol, from_timestamp, to_timestamp
+        symbol.replace('^', "%5E"),
+        from_timestamp,
+        to_timestamp
     );
 
-    let response = client
-        .get(&url)
-        .send()
-        .await
-        .context("Failed to send request to Yahoo Finance")?;
-
-    if !response.status().is_success() {
-        return Err(anyhow!(
-            "Yahoo Finance returned error status: {}",
-            response.status()
-        ));
-    }
-
-    let data: YahooQuoteResponse = response
-        .json()
-        .await
-        .context("Failed to parse Yahoo Finance response")?;
+    let body = get_chart(&url).await?;
+    let data: YahooQuoteResponse =
+        serde_json::from_str(&body).context("Failed to parse Yahoo Finance response")?;
     parse_historical_prices_response(data)
 }
 
@@ -188,11 +420,18 @@ fn parse_current_price_response(ticker: &str, data: YahooQuoteResponse) -> Resul
 
     let currency = result.meta.currency.unwrap_or_else(|| "BRL".to_string());
 
+    // Time of the quote itself; during trading hours this is an intraday price
+    let timestamp = result
+        .meta
+        .regular_market_time
+        .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+        .unwrap_or_else(chrono::Utc::now);
+
     Ok(PriceData {
         ticker: ticker.to_string(),
         price: Decimal::from_f64_retain(price).ok_or_else(|| anyhow!("Invalid price value"))?,
         currency,
-        timestamp: chrono::Utc::now(),
+        timestamp,
     })
 }
 
@@ -227,6 +466,12 @@ fn parse_historical_prices_response(data: YahooQuoteResponse) -> Result<Vec<Hist
     let lows = quote.low.unwrap_or_default();
     let closes = quote.close.ok_or_else(|| anyhow!("No close prices"))?;
     let volumes = quote.volume.unwrap_or_default();
+    let adjusted = result
+        .indicators
+        .adjclose
+        .and_then(|a| a.into_iter().next())
+        .and_then(|a| a.adjclose)
+        .unwrap_or_default();
 
     let mut prices = Vec::new();
 
@@ -255,6 +500,10 @@ fn parse_historical_prices_response(data: YahooQuoteResponse) -> Result<Vec<Hist
                 .and_then(|&v| v)
                 .and_then(Decimal::from_f64_retain),
             close: Decimal::from_f64_retain(close).ok_or_else(|| anyhow!("Invalid close price"))?,
+            adjusted_close: adjusted
+                .get(i)
+                .and_then(|&v| v)
+                .and_then(Decimal::from_f64_retain),
             volume: volumes.get(i).and_then(|&v| v),
         });
     }
@@ -314,6 +563,67 @@ mod tests {
         println!("Fetched {} historical prices", prices.len());
     }
 
+    #[test]
+    fn test_backoff_and_circuit_breaker() {
+        let now = Instant::now();
+        let mut health = Health::default();
+        assert_eq!(health.gate(now), Gate::Go);
+
+        // Each 429 pauses everyone for twice as long as the one before
+        health.throttled(None, now);
+        assert_eq!(health.gate(now), Gate::Wait(BASE_BACKOFF));
+        health.throttled(None, now);
+        assert_eq!(health.gate(now), Gate::Wait(BASE_BACKOFF * 2));
+        assert_eq!(health.gate(now + BASE_BACKOFF * 2), Gate::Go);
+
+        // A success resets the count, so the next 429 starts over
+        health.succeeded();
+        health.throttled(Some(Duration::from_secs(5)), now);
+        assert_eq!(health.gate(now), Gate::Wait(Duration::from_secs(5)));
+        health.succeeded();
+        let now = now + Duration::from_secs(5);
+
+        // Three failures in a row open the circuit for the cooldown
+        health.failed(now);
+        health.failed(now);
+        assert_eq!(health.gate(now), Gate::Go);
+        health.failed(now);
+        assert_eq!(health.gate(now), Gate::Open(BREAKER_COOLDOWN));
+        let later = now + BREAKER_COOLDOWN;
+        assert_eq!(health.gate(later), Gate::Go);
+        // Half-open: one more failure after the cooldown reopens it
+        health.failed(later);
+        assert_eq!(health.gate(later), Gate::Open(BREAKER_COOLDOWN));
+
+        // A Retry-After longer than any backoff opens the circuit that long
+        let now = Instant::now();
+        let mut health = Health::default();
+        health.throttled(Some(Duration::from_secs(600)), now);
+        assert_eq!(health.gate(now), Gate::Open(Duration::from_secs(600)));
+
+        let err: anyhow::Error = YahooUnavailable {
+            retry_in: Duration::from_secs(60),
+        }
+        .into();
+        assert!(is_unavailable(
+            &err.context("Yahoo Finance price fetch failed")
+        ));
+        assert!(!is_unavailable(&anyhow!("No price data available")));
+    }
+
+    #[test]
+    fn test_crumb_is_appended_to_the_query() {
+        assert_eq!(
+            with_crumb(
+                "https://query1.finance.yahoo.com/v8/finance/chart/PETR4.SA?interval=1d",
+                Some("a/b.c")
+            ),
+            "https://query1.finance.yahoo.com/v8/finance/chart/PETR4.SA?interval=1d&crumb=a%2Fb.c"
+        );
+        let url = "https://query1.finance.yahoo.com/v8/finance/chart/PETR4.SA";
+        assert_eq!(with_crumb(url, None), url);
+    }
+
     #[test]
     fn test_parse_current_price_from_fixture() {
         let raw = include_str!(concat!(
@@ -324,6 +634,7 @@ mod tests {
         let parsed = parse_current_price_response("PETR4", data).unwrap();
         assert_eq!(parsed.ticker, "PETR4");
         assert_eq!(parsed.price, Decimal::from_str("34.75").unwrap());
+        assert_eq!(parsed.timestamp.timestamp(), 1769190958);
     }
 
     #[test]
@@ -335,9 +646,9 @@ mod tests {
         let data: YahooQuoteResponse = serde_json::from_str(raw).unwrap();
         let parsed = parse_historical_prices_response(data).unwrap();
         assert_eq!(parsed.len(), 7);
-        assert_eq!(
-            parsed.first().unwrap().date,
-            NaiveDate::from_ymd_opt(2025, 1, 2).unwrap()
-        );
+        let first = parsed.first().unwrap();
+        assert_eq!(first.date, NaiveDate::from_ymd_opt(2025, 1, 2).unwrap());
+        // Yahoo's adjusted series sits below the raw close after later dividends
+        assert!(first.adjusted_close.unwrap() < first.close);
     }
 }
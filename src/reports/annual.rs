@@ -0,0 +1,1025 @@
+//! Annual performance report: the year's return against benchmarks,
+//! allocation, income, tax, contributions and the assets that moved the
+//! result most, printable as a PDF to share with an advisor or partner.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::benchmark::{BenchmarkComparison, BenchmarkSpec};
+use super::cashflow::YearlyNetFlow;
+use super::pdf::{text_width, Color, Page, PdfDocument, MARGIN, PAGE_HEIGHT, PAGE_WIDTH};
+use super::{snapshots, PortfolioReport};
+use crate::db::{self, AssetType, TransactionType};
+use crate::utils::format_currency;
+
+/// Benchmarks shown when none are chosen
+pub const DEFAULT_BENCHMARKS: [&str; 3] = ["IBOV", "IFIX", "CDI"];
+
+/// Contributors and detractors listed on each side
+const TOP_ASSETS: usize = 10;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AllocationSlice {
+    pub asset_type: AssetType,
+    pub start_value: Decimal,
+    pub end_value: Decimal,
+    /// Share of the year-end value, in %
+    pub end_pct: Decimal,
+}
+
+/// What one asset added to the year: value change net of money put in,
+/// plus income
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetResult {
+    pub ticker: String,
+    pub asset_type: AssetType,
+    pub start_value: Decimal,
+    pub end_value: Decimal,
+    pub bought: Decimal,
+    pub sold: Decimal,
+    pub income: Decimal,
+    pub result: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IncomeTypeTotal {
+    pub event_type: String,
+    pub gross: Decimal,
+    pub withheld: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IncomeSummary {
+    /// Net income paid in each month, January first
+    pub by_month: Vec<Decimal>,
+    pub by_type: Vec<IncomeTypeTotal>,
+    pub gross: Decimal,
+    pub withheld: Decimal,
+    pub net: Decimal,
+    /// Largest payers by net income
+    pub top_payers: Vec<(String, Decimal)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaxSummary {
+    /// Tax due on sales per month (months with tax only)
+    pub by_month: Vec<(u32, Decimal)>,
+    pub due: Decimal,
+    /// DARFs marked as paid; None when reports are scoped to a portfolio,
+    /// since payments are kept for the aggregate
+    pub paid: Option<Decimal>,
+    /// IR withheld at source on income (JCP, 2026 dividends)
+    pub income_withheld: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnualReport {
+    pub year: i32,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub start_value: Decimal,
+    pub end_value: Decimal,
+    /// Time-weighted return, in %
+    pub return_pct: Decimal,
+    /// Money-weighted return (XIRR, annualized), in %
+    pub money_weighted_return: Option<Decimal>,
+    pub benchmarks: Vec<BenchmarkComparison>,
+    pub allocation: Vec<AllocationSlice>,
+    pub flows: Option<YearlyNetFlow>,
+    pub income: IncomeSummary,
+    pub tax: TaxSummary,
+    /// Every asset held or traded in the year, best result first
+    pub assets: Vec<AssetResult>,
+}
+
+impl AnnualReport {
+    pub fn contributors(&self) -> impl Iterator<Item = &AssetResult> {
+        self.assets
+            .iter()
+            .filter(|a| a.result > Decimal::ZERO)
+            .take(TOP_ASSETS)
+    }
+
+    pub fn detractors(&self) -> impl Iterator<Item = &AssetResult> {
+        self.assets
+            .iter()
+            .rev()
+            .filter(|a| a.result < Decimal::ZERO)
+            .take(TOP_ASSETS)
+    }
+}
+
+/// Build the report for `year`, through today while the year is running.
+/// Prices should already be stored for the period.
+pub fn annual_report(
+    conn: &mut Connection,
+    year: i32,
+    benchmarks: &[BenchmarkSpec],
+) -> Result<AnnualReport> {
+    let today = chrono::Local::now().date_naive();
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+    if from > today {
+        anyhow::bail!("{} has not started yet", year);
+    }
+    let to = NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?
+        .min(today);
+
+    let performance = super::calculate_performance(conn, super::Period::Custom { from, to }, None)?;
+    let benchmarks = super::benchmark::compare_with_benchmarks(
+        conn,
+        benchmarks,
+        performance.start_date,
+        performance.end_date,
+        performance.return_pct(),
+    )?;
+
+    let start = snapshots::report(conn, from)?;
+    let end = snapshots::report(conn, to)?;
+    let events = db::get_income_events_with_assets(conn, Some(from), Some(to), None)?;
+
+    let cash_flows = super::cashflow::calculate_cash_flow_report(conn, from, to)?;
+    let flows = super::cashflow::net_flow_by_year(&cash_flows)
+        .into_iter()
+        .find(|f| f.year == year);
+
+    Ok(AnnualReport {
+        year,
+        start_date: performance.start_date,
+        end_date: performance.end_date,
+        start_value: performance.start_value,
+        end_value: performance.end_value,
+        return_pct: performance.return_pct().round_dp(2),
+        money_weighted_return: performance.money_weighted_return,
+        benchmarks,
+        allocation: allocation(&start, &end),
+        flows,
+        income: income_summary(&events),
+        tax: tax_summary(conn, year, &events)?,
+        assets: asset_results(conn, from, to, &start, &end, &events)?,
+    })
+}
+
+fn position_value(p: &super::portfolio::PositionSummary) -> Decimal {
+    p.current_value.unwrap_or(p.total_cost)
+}
+
+fn allocation(start: &PortfolioReport, end: &PortfolioReport) -> Vec<AllocationSlice> {
+    let mut by_type: HashMap<AssetType, (Decimal, Decimal)> = HashMap::new();
+    for p in &start.positions {
+        by_type.entry(p.asset.asset_type).or_default().0 += position_value(p);
+    }
+    for p in &end.positions {
+        by_type.entry(p.asset.asset_type).or_default().1 += position_value(p);
+    }
+    let total: Decimal = by_type.values().map(|(_, end)| *end).sum();
+    let mut slices: Vec<AllocationSlice> = by_type
+        .into_iter()
+        .map(|(asset_type, (start_value, end_value))| AllocationSlice {
+            asset_type,
+            start_value,
+            end_value,
+            end_pct: if total > Decimal::ZERO {
+                (end_value / total * Decimal::from(100)).round_dp(2)
+            } else {
+                Decimal::ZERO
+            },
+        })
+        .collect();
+    slices.sort_by(|a, b| {
+        b.end_value
+            .cmp(&a.end_value)
+            .then(b.start_value.cmp(&a.start_value))
+    });
+    slices
+}
+
+fn income_summary(events: &[(db::IncomeEvent, db::Asset)]) -> IncomeSummary {
+    let mut by_month = vec![Decimal::ZERO; 12];
+    let mut by_type: Vec<IncomeTypeTotal> = Vec::new();
+    let mut by_payer: HashMap<String, Decimal> = HashMap::new();
+    for (event, asset) in events {
+        let net = event.total_amount - event.withholding_tax;
+        by_month[event.event_date.month0() as usize] += net;
+        *by_payer.entry(asset.ticker.clone()).or_default() += net;
+        let label = event.event_type.as_str();
+        match by_type.iter_mut().find(|t| t.event_type == label) {
+            Some(total) => {
+                total.gross += event.total_amount;
+                total.withheld += event.withholding_tax;
+            }
+            None => by_type.push(IncomeTypeTotal {
+                event_type: label.to_string(),
+                gross: event.total_amount,
+                withheld: event.withholding_tax,
+            }),
+        }
+    }
+    by_type.sort_by_key(|t| std::cmp::Reverse(t.gross));
+    let mut top_payers: Vec<(String, Decimal)> = by_payer.into_iter().collect();
+    top_payers.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    top_payers.truncate(5);
+
+    let gross = by_type.iter().map(|t| t.gross).sum();
+    let withheld = by_type.iter().map(|t| t.withheld).sum();
+    IncomeSummary {
+        by_month,
+        by_type,
+        gross,
+        withheld,
+        net: gross - withheld,
+        top_payers,
+    }
+}
+
+fn tax_summary(
+    conn: &Connection,
+    year: i32,
+    events: &[(db::IncomeEvent, db::Asset)],
+) -> Result<TaxSummary> {
+    let report = crate::tax::irpf::generate_annual_report(conn, year)?;
+    let by_month: Vec<(u32, Decimal)> = report
+        .monthly_summaries
+        .iter()
+        .filter(|m| m.tax_due > Decimal::ZERO)
+        .map(|m| (m.month, m.tax_due))
+        .collect();
+
+    // A payment is recorded on every category row of its month
+    let paid = if db::portfolio::is_scoped() {
+        None
+    } else {
+        let mut months: HashMap<u32, Decimal> = HashMap::new();
+        for entry in crate::tax::ledger::entries(conn, Some(year))? {
+            if let Some(amount) = entry.paid_amount {
+                months.insert(entry.month, amount);
+            }
+        }
+        Some(months.values().sum())
+    };
+
+    Ok(TaxSummary {
+        due: by_month.iter().map(|(_, tax)| *tax).sum(),
+        by_month,
+        paid,
+        income_withheld: events.iter().map(|(e, _)| e.withholding_tax).sum(),
+    })
+}
+
+fn asset_results(
+    conn: &Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+    start: &PortfolioReport,
+    end: &PortfolioReport,
+    events: &[(db::IncomeEvent, db::Asset)],
+) -> Result<Vec<AssetResult>> {
+    let mut results: HashMap<i64, AssetResult> = HashMap::new();
+
+    for p in &start.positions {
+        asset_entry(&mut results, &p.asset).start_value += position_value(p);
+    }
+    for p in &end.positions {
+        asset_entry(&mut results, &p.asset).end_value += position_value(p);
+    }
+    for (event, asset) in events {
+        asset_entry(&mut results, asset).income += event.total_amount - event.withholding_tax;
+    }
+
+    // The start value is an end-of-day snapshot, so its trades are already in it
+    let sql = format!(
+        "SELECT a.id, a.ticker, a.asset_type, a.name, a.cnpj, a.created_at, a.updated_at,
+                t.transaction_type, t.quantity, t.price_per_unit, t.fees
+         FROM transactions t
+         JOIN assets a ON t.asset_id = a.id
+         WHERE t.trade_date > ?1 AND t.trade_date <= ?2{}",
+        db::portfolio::scope_filter("t.portfolio_id")
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let trades = stmt
+        .query_map([from, to], |row| {
+            Ok((
+                db::Asset {
+                    id: Some(row.get(0)?),
+                    ticker: row.get(1)?,
+                    asset_type: row
+                        .get::<_, String>(2)?
+                        .parse::<AssetType>()
+                        .unwrap_or(AssetType::Unknown),
+                    name: row.get(3)?,
+                    cnpj: row.get(4)?,
+                    created_at: row.get(5)?,
+                    updated_at: row.get(6)?,
+                },
+                row.get::<_, String>(7)?
+                    .parse::<TransactionType>()
+                    .unwrap_or(TransactionType::Buy),
+                db::get_decimal_value(row, 8)? * db::get_decimal_value(row, 9)?,
+                db::get_optional_decimal_value(row, 10)?.unwrap_or_default(),
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (asset, transaction_type, gross, fees) in trades {
+        let result = asset_entry(&mut results, &asset);
+        match transaction_type {
+            TransactionType::Buy => result.bought += gross + fees,
+            TransactionType::Sell => result.sold += gross - fees,
+        }
+    }
+
+    let mut results: Vec<AssetResult> = results
+        .into_values()
+        .map(|mut r| {
+            r.result = r.end_value - r.start_value - r.bought + r.sold + r.income;
+            r
+        })
+        .collect();
+    results.sort_by(|a, b| b.result.cmp(&a.result).then(a.ticker.cmp(&b.ticker)));
+    Ok(results)
+}
+
+fn asset_entry<'a>(
+    results: &'a mut HashMap<i64, AssetResult>,
+    asset: &db::Asset,
+) -> &'a mut AssetResult {
+    results
+        .entry(asset.id.unwrap_or_default())
+        .or_insert_with(|| AssetResult {
+            ticker: asset.ticker.clone(),
+            asset_type: asset.asset_type,
+            start_value: Decimal::ZERO,
+            end_value: Decimal::ZERO,
+            bought: Decimal::ZERO,
+            sold: Decimal::ZERO,
+            income: Decimal::ZERO,
+            result: Decimal::ZERO,
+        })
+}
+
+const TOP: f32 = PAGE_HEIGHT - MARGIN;
+const BOTTOM: f32 = 60.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+const ROW_HEIGHT: f32 = 15.0;
+
+/// Allocation segment colors, cycled
+const PALETTE: [Color; 6] = [
+    Color(0.12, 0.35, 0.60),
+    Color(0.95, 0.60, 0.20),
+    Color(0.30, 0.65, 0.45),
+    Color(0.60, 0.40, 0.70),
+    Color(0.85, 0.35, 0.35),
+    Color(0.50, 0.70, 0.85),
+];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+fn signed_pct(value: Decimal) -> String {
+    format!("{:+.2}%", value)
+}
+
+fn signed_color(value: Decimal) -> Color {
+    if value < Decimal::ZERO {
+        Color::LOSS
+    } else {
+        Color::GAIN
+    }
+}
+
+/// Currency without cents, for chart labels
+fn whole_currency(value: Decimal) -> String {
+    format_currency(value.round())
+        .trim_end_matches(",00")
+        .to_string()
+}
+
+/// A table column; `x` is the right edge for right-aligned columns
+struct Column {
+    title: &'static str,
+    x: f32,
+    right: bool,
+}
+
+const fn left(title: &'static str, x: f32) -> Column {
+    Column {
+        title,
+        x,
+        right: false,
+    }
+}
+
+const fn right(title: &'static str, x: f32) -> Column {
+    Column {
+        title,
+        x,
+        right: true,
+    }
+}
+
+type Cell = (String, Color);
+
+fn cell(text: impl Into<String>) -> Cell {
+    (text.into(), Color::TEXT)
+}
+
+fn money_cell(value: Decimal) -> Cell {
+    cell(format_currency(value))
+}
+
+fn result_cell(value: Decimal) -> Cell {
+    (format_currency(value), signed_color(value))
+}
+
+/// Lays content out top to bottom, starting a new page when it runs out
+struct Writer {
+    doc: PdfDocument,
+    y: f32,
+}
+
+impl Writer {
+    fn new(title: &str) -> Self {
+        let mut doc = PdfDocument::new(title);
+        doc.add_page();
+        Self { doc, y: TOP }
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.doc.current_page()
+    }
+
+    fn new_page(&mut self) {
+        self.doc.add_page();
+        self.y = TOP;
+    }
+
+    fn reserve(&mut self, height: f32) {
+        if self.y - height < BOTTOM {
+            self.new_page();
+        }
+    }
+
+    fn heading(&mut self, text: &str) {
+        // Keep a heading together with the start of its content
+        self.reserve(90.0);
+        if self.y < TOP {
+            self.y -= 14.0;
+        }
+        let y = self.y;
+        let page = self.page();
+        page.text(MARGIN, y - 14.0, 13.0, true, Color::ACCENT, text);
+        page.line(
+            (MARGIN, y - 20.0),
+            (PAGE_WIDTH - MARGIN, y - 20.0),
+            0.75,
+            Color::ACCENT,
+        );
+        self.y -= 34.0;
+    }
+
+    fn note(&mut self, text: &str) {
+        self.reserve(14.0);
+        let y = self.y;
+        self.page()
+            .text(MARGIN, y - 9.0, 8.5, false, Color::MUTED, text);
+        self.y -= 14.0;
+    }
+
+    fn gap(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn table_header(&mut self, columns: &[Column]) {
+        let y = self.y;
+        let page = self.page();
+        for column in columns {
+            if column.right {
+                page.text_right(column.x, y - 10.0, 8.0, true, Color::MUTED, column.title);
+            } else {
+                page.text(column.x, y - 10.0, 8.0, true, Color::MUTED, column.title);
+            }
+        }
+        page.line(
+            (MARGIN, y - 14.0),
+            (PAGE_WIDTH - MARGIN, y - 14.0),
+            0.5,
+            Color::RULE,
+        );
+        self.y -= 16.0;
+    }
+
+    fn table(&mut self, columns: &[Column], rows: &[Vec<Cell>]) {
+        self.reserve(16.0 + ROW_HEIGHT);
+        self.table_header(columns);
+        for (i, row) in rows.iter().enumerate() {
+            if self.y - ROW_HEIGHT < BOTTOM {
+                self.new_page();
+                self.table_header(columns);
+            }
+            let y = self.y;
+            let page = self.page();
+            if i % 2 == 1 {
+                page.rect(
+                    MARGIN,
+                    y - ROW_HEIGHT,
+                    CONTENT_WIDTH,
+                    ROW_HEIGHT,
+                    Color::PANEL,
+                );
+            }
+            for (column, (text, color)) in columns.iter().zip(row) {
+                if column.right {
+                    page.text_right(column.x, y - 10.5, 9.0, false, *color, text);
+                } else {
+                    page.text(column.x, y - 10.5, 9.0, false, *color, text);
+                }
+            }
+            self.y -= ROW_HEIGHT;
+        }
+        self.gap(6.0);
+    }
+
+    /// Boxes of label and value, four per row
+    fn figures(&mut self, figures: &[(&str, String, Color)]) {
+        const PER_ROW: usize = 4;
+        const HEIGHT: f32 = 46.0;
+        const SPACING: f32 = 10.0;
+        let width = (CONTENT_WIDTH - SPACING * (PER_ROW - 1) as f32) / PER_ROW as f32;
+        for row in figures.chunks(PER_ROW) {
+            self.reserve(HEIGHT + SPACING);
+            let y = self.y;
+            let page = self.page();
+            for (i, (label, value, color)) in row.iter().enumerate() {
+                let x = MARGIN + i as f32 * (width + SPACING);
+                page.rect(x, y - HEIGHT, width, HEIGHT, Color::PANEL);
+                page.text(x + 8.0, y - 15.0, 8.0, false, Color::MUTED, label);
+                let size = if text_width(value, 13.0) > width - 16.0 {
+                    10.0
+                } else {
+                    13.0
+                };
+                page.text(x + 8.0, y - 35.0, size, true, *color, value);
+            }
+            self.y -= HEIGHT + SPACING;
+        }
+    }
+
+    /// Horizontal bars from a zero axis, with the value at the right
+    fn bar_chart(&mut self, bars: &[(String, Option<Decimal>, Color)]) {
+        const LABEL_WIDTH: f32 = 120.0;
+        const VALUE_WIDTH: f32 = 70.0;
+        const BAR_ROW: f32 = 20.0;
+        let area_left = MARGIN + LABEL_WIDTH;
+        let area_width = CONTENT_WIDTH - LABEL_WIDTH - VALUE_WIDTH;
+        let values: Vec<f32> = bars
+            .iter()
+            .filter_map(|(_, v, _)| v.and_then(|v| v.to_f32()))
+            .collect();
+        let max = values.iter().fold(1.0_f32, |max, v| max.max(v.abs()));
+        let has_negative = values.iter().any(|v| *v < 0.0);
+        let (zero, scale) = if has_negative {
+            (area_left + area_width / 2.0, area_width / 2.0 / max)
+        } else {
+            (area_left, area_width / max)
+        };
+
+        self.reserve(BAR_ROW * bars.len() as f32 + 6.0);
+        let top = self.y;
+        for (label, value, color) in bars {
+            let y = self.y;
+            let page = self.page();
+            page.text(MARGIN, y - 13.0, 9.0, false, Color::TEXT, label);
+            match value {
+                Some(value) => {
+                    let length = value.to_f32().unwrap_or_default() * scale;
+                    let x = if length < 0.0 { zero + length } else { zero };
+                    page.rect(x, y - 16.0, length.abs().max(0.5), 11.0, *color);
+                    page.text_right(
+                        PAGE_WIDTH - MARGIN,
+                        y - 13.0,
+                        9.0,
+                        true,
+                        signed_color(*value),
+                        &signed_pct(*value),
+                    );
+                }
+                None => page.text_right(
+                    PAGE_WIDTH - MARGIN,
+                    y - 13.0,
+                    9.0,
+                    false,
+                    Color::MUTED,
+                    "no data",
+                ),
+            }
+            self.y -= BAR_ROW;
+        }
+        let bottom = self.y;
+        self.page()
+            .line((zero, top - 2.0), (zero, bottom - 2.0), 0.5, Color::MUTED);
+        self.gap(8.0);
+    }
+
+    /// Twelve monthly columns with their values on top
+    fn month_chart(&mut self, values: &[Decimal], color: Color) {
+        const HEIGHT: f32 = 110.0;
+        let slot = CONTENT_WIDTH / values.len() as f32;
+        let max = values
+            .iter()
+            .filter_map(|v| v.to_f32())
+            .fold(0.0_f32, f32::max);
+        self.reserve(HEIGHT + 40.0);
+        let base = self.y - HEIGHT - 14.0;
+        let page = self.page();
+        page.line(
+            (MARGIN, base),
+            (PAGE_WIDTH - MARGIN, base),
+            0.5,
+            Color::MUTED,
+        );
+        for (i, value) in values.iter().enumerate() {
+            let x = MARGIN + i as f32 * slot;
+            let center = x + slot / 2.0;
+            let label = MONTHS[i % 12];
+            page.text(
+                center - text_width(label, 8.0) / 2.0,
+                base - 11.0,
+                8.0,
+                false,
+                Color::MUTED,
+                label,
+            );
+            let amount = value.to_f32().unwrap_or_default();
+            if amount <= 0.0 || max <= 0.0 {
+                continue;
+            }
+            let height = amount / max * HEIGHT;
+            page.rect(x + slot * 0.2, base, slot * 0.6, height, color);
+            let text = whole_currency(*value);
+            page.text(
+                center - text_width(&text, 6.5) / 2.0,
+                base + height + 3.0,
+                6.5,
+                false,
+                Color::TEXT,
+                &text,
+            );
+        }
+        self.y = base - 24.0;
+    }
+
+    /// One bar split by share, with a legend below
+    fn stacked_bar(&mut self, segments: &[(String, Decimal)]) {
+        let total: Decimal = segments.iter().map(|(_, v)| *v).sum();
+        if total <= Decimal::ZERO {
+            return;
+        }
+        self.reserve(60.0);
+        let y = self.y;
+        let page = self.page();
+        let mut x = MARGIN;
+        for (i, (_, value)) in segments.iter().enumerate() {
+            let width = (*value / total).to_f32().unwrap_or_default() * CONTENT_WIDTH;
+            page.rect(x, y - 18.0, width, 18.0, PALETTE[i % PALETTE.len()]);
+            x += width;
+        }
+
+        let mut x = MARGIN;
+        let mut line_y = y - 34.0;
+        for (i, (label, value)) in segments.iter().enumerate() {
+            let text = format!("{} {:.1}%", label, *value / total * Decimal::from(100));
+            let width = 14.0 + text_width(&text, 8.5) + 16.0;
+            if x + width > PAGE_WIDTH - MARGIN {
+                x = MARGIN;
+                line_y -= 14.0;
+            }
+            page.rect(x, line_y - 1.0, 9.0, 9.0, PALETTE[i % PALETTE.len()]);
+            page.text(x + 14.0, line_y, 8.5, false, Color::TEXT, &text);
+            x += width;
+        }
+        self.y = line_y - 16.0;
+    }
+}
+
+/// Lay the report out as an A4 PDF
+pub fn render_pdf(report: &AnnualReport) -> Result<Vec<u8>> {
+    let mut w = Writer::new(&format!("Annual report {}", report.year));
+    let today = chrono::Local::now().date_naive();
+
+    let y = w.y;
+    let page = w.page();
+    page.text(
+        MARGIN,
+        y - 24.0,
+        22.0,
+        true,
+        Color::ACCENT,
+        &format!("Annual report {}", report.year),
+    );
+    page.text(
+        MARGIN,
+        y - 42.0,
+        10.0,
+        false,
+        Color::MUTED,
+        &format!(
+            "{} to {} · generated on {}",
+            report.start_date.format("%d/%m/%Y"),
+            report.end_date.format("%d/%m/%Y"),
+            today.format("%d/%m/%Y")
+        ),
+    );
+    w.gap(62.0);
+
+    let net_new_capital = report
+        .flows
+        .as_ref()
+        .map(|f| f.net_new_capital)
+        .unwrap_or_default();
+    let result = report.assets.iter().map(|a| a.result).sum::<Decimal>();
+    w.figures(&[
+        (
+            "Start value",
+            format_currency(report.start_value),
+            Color::TEXT,
+        ),
+        ("End value", format_currency(report.end_value), Color::TEXT),
+        (
+            "Return (time-weighted)",
+            signed_pct(report.return_pct),
+            signed_color(report.return_pct),
+        ),
+        (
+            "Return (money-weighted)",
+            report
+                .money_weighted_return
+                .map(signed_pct)
+                .unwrap_or_else(|| "-".to_string()),
+            report
+                .money_weighted_return
+                .map(signed_color)
+                .unwrap_or(Color::MUTED),
+        ),
+        ("Result", format_currency(result), signed_color(result)),
+        (
+            "Net new capital",
+            format_currency(net_new_capital),
+            Color::TEXT,
+        ),
+        (
+            "Income (net)",
+            format_currency(report.income.net),
+            Color::TEXT,
+        ),
+        (
+            "Tax due on sales",
+            format_currency(report.tax.due),
+            Color::TEXT,
+        ),
+    ]);
+
+    w.heading("Returns vs benchmarks");
+    let mut bars = vec![(
+        "Portfolio".to_string(),
+        Some(report.return_pct),
+        Color::ACCENT,
+    )];
+    bars.extend(
+        report
+            .benchmarks
+            .iter()
+            .map(|b| (b.benchmark.clone(), b.return_pct, Color::MUTED)),
+    );
+    w.bar_chart(&bars);
+    for b in report.benchmarks.iter().filter(|b| b.stale) {
+        w.note(&format!(
+            "{} data only until {}",
+            b.benchmark,
+            b.data_until
+                .map(|d| d.format("%d/%m/%Y").to_string())
+                .unwrap_or_else(|| "-".to_string())
+        ));
+    }
+
+    w.heading("Allocation");
+    let segments: Vec<(String, Decimal)> = report
+        .allocation
+        .iter()
+        .filter(|s| s.end_value > Decimal::ZERO)
+        .map(|s| (s.asset_type.as_str().to_string(), s.end_value))
+        .collect();
+    w.stacked_bar(&segments);
+    let rows: Vec<Vec<Cell>> = report
+        .allocation
+        .iter()
+        .map(|s| {
+            vec![
+                cell(s.asset_type.as_str()),
+                money_cell(s.start_value),
+                money_cell(s.end_value),
+                cell(format!("{:.2}%", s.end_pct)),
+            ]
+        })
+        .collect();
+    w.table(
+        &[
+            left("Type", MARGIN + 4.0),
+            right("Start of year", 300.0),
+            right("End of period", 420.0),
+            right("Share", PAGE_WIDTH - MARGIN - 4.0),
+        ],
+        &rows,
+    );
+
+    w.new_page();
+    w.heading("Income");
+    w.month_chart(&report.income.by_month, Color::GAIN);
+    let mut rows: Vec<Vec<Cell>> = report
+        .income
+        .by_type
+        .iter()
+        .map(|t| {
+            vec![
+                cell(t.event_type.clone()),
+                money_cell(t.gross),
+                money_cell(t.withheld),
+                money_cell(t.gross - t.withheld),
+            ]
+        })
+        .collect();
+    rows.push(vec![
+        cell("Total"),
+        money_cell(report.income.gross),
+        money_cell(report.income.withheld),
+        money_cell(report.income.net),
+    ]);
+    let income_columns = [
+        left("Type", MARGIN + 4.0),
+        right("Gross", 300.0),
+        right("Withheld", 420.0),
+        right("Net", PAGE_WIDTH - MARGIN - 4.0),
+    ];
+    w.table(&income_columns, &rows);
+    if !report.income.top_payers.is_empty() {
+        let rows: Vec<Vec<Cell>> = report
+            .income
+            .top_payers
+            .iter()
+            .map(|(ticker, net)| vec![cell(ticker.clone()), money_cell(*net)])
+            .collect();
+        w.table(
+            &[
+                left("Top payers", MARGIN + 4.0),
+                right("Net", PAGE_WIDTH - MARGIN - 4.0),
+            ],
+            &rows,
+        );
+    }
+
+    w.heading("Tax");
+    let mut rows: Vec<Vec<Cell>> = report
+        .tax
+        .by_month
+        .iter()
+        .map(|(month, tax)| {
+            vec![
+                cell(MONTHS[(*month as usize).saturating_sub(1) % 12]),
+                money_cell(*tax),
+            ]
+        })
+        .collect();
+    rows.push(vec![cell("Due on sales"), money_cell(report.tax.due)]);
+    if let Some(paid) = report.tax.paid {
+        rows.push(vec![cell("DARFs paid"), money_cell(paid)]);
+    }
+    rows.push(vec![
+        cell("Withheld on income"),
+        money_cell(report.tax.income_withheld),
+    ]);
+    w.table(
+        &[
+            left("Month", MARGIN + 4.0),
+            right("Amount", PAGE_WIDTH - MARGIN - 4.0),
+        ],
+        &rows,
+    );
+    if report.tax.by_month.is_empty() {
+        w.note("No tax due on sales this year");
+    }
+
+    w.new_page();
+    w.heading("Contributions");
+    match &report.flows {
+        Some(f) => {
+            let rows = vec![
+                vec![cell("Bought"), money_cell(f.bought)],
+                vec![cell("Sold"), money_cell(f.sold)],
+                vec![cell("Income received"), money_cell(f.income)],
+                vec![cell("Income reinvested"), money_cell(f.reinvested_income)],
+                vec![cell("Deposited"), money_cell(f.deposited)],
+                vec![cell("Withdrawn"), money_cell(f.withdrawn)],
+                vec![cell("Net new capital"), result_cell(f.net_new_capital)],
+            ];
+            w.table(
+                &[
+                    left("Flow", MARGIN + 4.0),
+                    right("Amount", PAGE_WIDTH - MARGIN - 4.0),
+                ],
+                &rows,
+            );
+        }
+        None => w.note("No trades or cash movements this year"),
+    }
+
+    let asset_columns = [
+        left("Asset", MARGIN + 4.0),
+        left("Type", 115.0),
+        right("Start", 235.0),
+        right("End", 315.0),
+        right("Net bought", 395.0),
+        right("Income", 470.0),
+        right("Result", PAGE_WIDTH - MARGIN - 4.0),
+    ];
+    let asset_row = |a: &AssetResult| {
+        vec![
+            cell(a.ticker.clone()),
+            cell(a.asset_type.as_str()),
+            money_cell(a.start_value),
+            money_cell(a.end_value),
+            money_cell(a.bought - a.sold),
+            money_cell(a.income),
+            result_cell(a.result),
+        ]
+    };
+    for (title, rows) in [
+        (
+            "Top contributors",
+            report.contributors().map(asset_row).collect::<Vec<_>>(),
+        ),
+        (
+            "Top detractors",
+            report.detractors().map(asset_row).collect::<Vec<_>>(),
+        ),
+    ] {
+        w.heading(title);
+        if rows.is_empty() {
+            w.note("None");
+        } else {
+            w.table(&asset_columns, &rows);
+        }
+    }
+    w.note("Result = end value - start value - bought + sold + net income");
+
+    w.doc.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_results_and_pdf() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('ITSA4', 'STOCK');
+             INSERT INTO assets (ticker, asset_type) VALUES ('MXRF11', 'FII');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+                 VALUES (1, 'BUY', '2023-06-01', '100', '10', '1000', '0', 0, 'CEI');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+                 VALUES (2, 'BUY', '2024-03-01', '100', '10', '1000', '0', 0, 'CEI');
+             INSERT INTO price_history (asset_id, price_date, close_price, source)
+                 VALUES (1, '2024-01-01', '10', 'TEST'), (1, '2024-12-31', '12', 'TEST'),
+                        (2, '2024-03-01', '10', 'TEST'), (2, '2024-12-31', '9', 'TEST');
+             INSERT INTO income_events (asset_id, event_date, ex_date, event_type,
+                 amount_per_quota, total_amount, withholding_tax, is_quota_pre_2026, source)
+                 VALUES (2, '2024-06-14', '2024-05-31', 'DIVIDEND', '0.5', '50', '0', 0, 'CEI');",
+        )
+        .unwrap();
+
+        let report = annual_report(&mut conn, 2024, &[]).unwrap();
+        let itsa = report.assets.iter().find(|a| a.ticker == "ITSA4").unwrap();
+        assert_eq!(itsa.start_value, Decimal::from(1000));
+        assert_eq!(itsa.result, Decimal::from(200));
+        let mxrf = report.assets.iter().find(|a| a.ticker == "MXRF11").unwrap();
+        assert_eq!(mxrf.bought, Decimal::from(1000));
+        assert_eq!(mxrf.result, Decimal::from(-50));
+        assert_eq!(report.contributors().count(), 1);
+        assert_eq!(report.detractors().next().unwrap().ticker, "MXRF11");
+        assert_eq!(report.income.by_month[5], Decimal::from(50));
+
+        let bytes = render_pdf(&report).unwrap();
+        assert!(bytes.starts_with(b"%PDF-1.5"));
+        let doc = lopdf::Document::load_mem(&bytes).unwrap();
+        assert_eq!(doc.get_pages().len(), 3);
+    }
+}
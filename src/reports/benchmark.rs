@@ -0,0 +1,581 @@
+//! Benchmark-relative returns computed from stored benchmark series.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::{Decimal, MathematicalOps};
+use serde::Serialize;
+use std::str::FromStr;
+
+use crate::db::{self, Asset, Benchmark, BenchmarkValue};
+use crate::reports::performance::{is_stale, CashFlow, FlowType};
+use crate::reports::portfolio::calculate_position;
+
+/// Asset return compared to its natural benchmark over the holding period
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetAlpha {
+    pub benchmark: Benchmark,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub asset_return_pct: Decimal,
+    pub benchmark_return_pct: Decimal,
+    pub alpha_pct: Decimal, // Percentage points (asset - benchmark)
+}
+
+/// Compute a benchmark's return (in %) between two dates.
+///
+/// Index benchmarks use the ratio of the last stored levels on or before each
+/// date. Rate benchmarks compound every stored rate accrued in `[from, to)`;
+/// a monthly rate (IPCA) only partly inside the range counts pro rata.
+/// Returns `None` when the stored series does not cover the range.
+pub fn benchmark_return(
+    conn: &Connection,
+    benchmark: Benchmark,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Option<Decimal>> {
+    if from >= to {
+        return Ok(None);
+    }
+
+    if benchmark.is_rate() {
+        let accrued: Vec<_> = rate_values(conn, benchmark, from, to)?
+            .into_iter()
+            .filter(|v| v.value_date < to)
+            .collect();
+        if accrued.is_empty() {
+            return Ok(None);
+        }
+        let factor = accrued.iter().fold(Decimal::ONE, |acc, v| {
+            acc * accrued_factor(benchmark, v, from, to)
+        });
+        return Ok(Some((factor - Decimal::ONE) * Decimal::from(100)));
+    }
+
+    let start = db::get_benchmark_value_on_or_before(conn, benchmark, from)?;
+    let end = db::get_benchmark_value_on_or_before(conn, benchmark, to)?;
+    match (start, end) {
+        (Some(start), Some(end)) if start.value > Decimal::ZERO => Ok(Some(
+            (end.value / start.value - Decimal::ONE) * Decimal::from(100),
+        )),
+        _ => Ok(None),
+    }
+}
+
+/// Stored rates whose accrual period overlaps `[from, to]`
+fn rate_values(
+    conn: &Connection,
+    benchmark: Benchmark,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<BenchmarkValue>> {
+    // A monthly rate is dated on the 1st and covers the month containing `from`
+    let query_from = if benchmark.is_monthly() {
+        from.with_day(1).unwrap_or(from)
+    } else {
+        from
+    };
+    db::get_benchmark_values(conn, benchmark, query_from, to)
+}
+
+/// First day after the period a stored rate accrues over
+fn accrual_end(benchmark: Benchmark, value: &BenchmarkValue) -> NaiveDate {
+    let date = value.value_date;
+    if !benchmark.is_monthly() {
+        return date.succ_opt().unwrap_or(date);
+    }
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(date)
+}
+
+/// Growth factor of one stored rate over its overlap with `[from, to)`
+fn accrued_factor(
+    benchmark: Benchmark,
+    value: &BenchmarkValue,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Decimal {
+    let start = value.value_date;
+    let end = accrual_end(benchmark, value);
+    let full = Decimal::ONE + value.value / Decimal::from(100);
+    let overlap = (end.min(to) - start.max(from)).num_days();
+    let length = (end - start).num_days();
+    if overlap <= 0 {
+        Decimal::ONE
+    } else if overlap >= length {
+        full
+    } else {
+        full.checked_powd(Decimal::from(overlap) / Decimal::from(length))
+            .unwrap_or(full)
+    }
+}
+
+/// A benchmark selected for comparison, optionally with a yearly spread on
+/// top (e.g. IPCA+6 for inflation plus 6% a year)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchmarkSpec {
+    pub benchmark: Benchmark,
+    pub spread_pct: Option<Decimal>,
+}
+
+impl BenchmarkSpec {
+    pub fn label(&self) -> String {
+        match self.spread_pct {
+            Some(spread) => format!("{}+{}%", self.benchmark.as_str(), spread.normalize()),
+            None => self.benchmark.as_str().to_string(),
+        }
+    }
+}
+
+impl FromStr for BenchmarkSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let unknown = || {
+            anyhow::anyhow!(
+                "Unknown benchmark '{}' (use IBOV, IFIX, CDI, SELIC, IPCA or a rate plus spread like IPCA+6)",
+                s
+            )
+        };
+        let (name, spread) = match s.split_once('+') {
+            Some((name, spread)) => {
+                let spread = spread
+                    .trim()
+                    .trim_end_matches('%')
+                    .parse::<Decimal>()
+                    .map_err(|_| unknown())?;
+                (name, Some(spread))
+            }
+            None => (s, None),
+        };
+        let benchmark = name.parse::<Benchmark>().map_err(|_| unknown())?;
+        if spread.is_some() && !benchmark.is_rate() {
+            anyhow::bail!("A spread only applies to rate benchmarks (CDI, SELIC, IPCA)");
+        }
+        Ok(BenchmarkSpec {
+            benchmark,
+            spread_pct: spread,
+        })
+    }
+}
+
+/// A benchmark's return over a report period
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkComparison {
+    pub benchmark: String,
+    /// None when the stored series does not cover the period
+    pub return_pct: Option<Decimal>,
+    /// Portfolio return minus the benchmark's, in percentage points
+    pub excess_pct: Option<Decimal>,
+    /// Last day the stored series covers
+    pub data_until: Option<NaiveDate>,
+    /// The series stops before the period end
+    pub stale: bool,
+}
+
+/// Last day a benchmark's stored series covers, up to `to`; a monthly rate
+/// covers its whole month
+pub fn data_until(
+    conn: &Connection,
+    benchmark: Benchmark,
+    to: NaiveDate,
+) -> Result<Option<NaiveDate>> {
+    Ok(
+        db::get_benchmark_value_on_or_before(conn, benchmark, to)?.map(|value| {
+            if benchmark.is_monthly() {
+                let end = accrual_end(benchmark, &value);
+                end.pred_opt().unwrap_or(end).min(to)
+            } else {
+                value.value_date
+            }
+        }),
+    )
+}
+
+/// Compare a portfolio return (in %) with each selected benchmark over the
+/// same period
+pub fn compare_with_benchmarks(
+    conn: &Connection,
+    specs: &[BenchmarkSpec],
+    from: NaiveDate,
+    to: NaiveDate,
+    portfolio_return_pct: Decimal,
+) -> Result<Vec<BenchmarkComparison>> {
+    let years = Decimal::from((to - from).num_days()) / Decimal::from(365);
+    specs
+        .iter()
+        .map(|spec| {
+            let return_pct = benchmark_return(conn, spec.benchmark, from, to)?.map(|ret| {
+                let Some(spread) = spec.spread_pct else {
+                    return ret.round_dp(2);
+                };
+                let spread_factor = (Decimal::ONE + spread / Decimal::from(100))
+                    .checked_powd(years)
+                    .unwrap_or(Decimal::ONE);
+                (((Decimal::ONE + ret / Decimal::from(100)) * spread_factor - Decimal::ONE)
+                    * Decimal::from(100))
+                .round_dp(2)
+            });
+            let data_until = data_until(conn, spec.benchmark, to)?;
+            Ok(BenchmarkComparison {
+                benchmark: spec.label(),
+                return_pct,
+                excess_pct: return_pct.map(|r| (portfolio_return_pct - r).round_dp(2)),
+                data_until,
+                stale: data_until.is_none_or(|date| is_stale(date, to)),
+            })
+        })
+        .collect()
+}
+
+/// Compare an asset's return on cost to its natural benchmark.
+///
+/// The benchmark side is cost-weighted: each buy's cash is compounded at the
+/// benchmark from its own date, and sales, amortizations and spin-offs take
+/// out the same share of it that they take out of the average cost. Both
+/// returns are then on the same money over the same time. The period runs
+/// from the purchase that opened the current position to its latest price.
+/// Returns `None` when the asset has no open position, no natural benchmark,
+/// no price, or no benchmark data for the period.
+pub fn asset_alpha(conn: &Connection, asset: &Asset) -> Result<Option<AssetAlpha>> {
+    let Some(benchmark) = Benchmark::for_asset_type(asset.asset_type) else {
+        return Ok(None);
+    };
+    let Some(asset_id) = asset.id else {
+        return Ok(None);
+    };
+    let Some((position, cost_history)) = calculate_position(conn, asset)? else {
+        return Ok(None);
+    };
+    let Some(asset_return_pct) = position.unrealized_pl_pct else {
+        return Ok(None);
+    };
+    let end_date = match position.valued_on {
+        Some(date) => date,
+        None => match db::get_latest_price(conn, asset_id)? {
+            Some(price) => price.price_date,
+            None => return Ok(None),
+        },
+    };
+    // The current position opened after the last time it was sold out
+    let opened = cost_history
+        .iter()
+        .rposition(|(_, cost)| cost.is_zero())
+        .map_or(0, |i| i + 1);
+    let Some(&(start_date, _)) = cost_history.get(opened) else {
+        return Ok(None);
+    };
+    if start_date >= end_date {
+        return Ok(None);
+    }
+
+    let Some(benchmark_return_pct) =
+        cost_weighted_return(conn, benchmark, &cost_history[opened..], end_date)?
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(AssetAlpha {
+        benchmark,
+        start_date,
+        end_date,
+        asset_return_pct,
+        benchmark_return_pct,
+        alpha_pct: asset_return_pct - benchmark_return_pct,
+    }))
+}
+
+/// Return (in %) of a cost basis replayed into a benchmark: increases are
+/// invested on their date, decreases withdraw the same share of the
+/// benchmark holding
+fn cost_weighted_return(
+    conn: &Connection,
+    benchmark: Benchmark,
+    cost_history: &[(NaiveDate, Decimal)],
+    end: NaiveDate,
+) -> Result<Option<Decimal>> {
+    let Some(&(start, _)) = cost_history.first() else {
+        return Ok(None);
+    };
+    let Some(curve) = growth_curve(conn, benchmark, start, end)? else {
+        return Ok(None);
+    };
+    let factor_at = |date: NaiveDate| {
+        let idx = curve.partition_point(|(d, _)| *d <= date);
+        curve[idx.saturating_sub(1)].1
+    };
+
+    let mut units = Decimal::ZERO;
+    let mut cost = Decimal::ZERO;
+    for &(date, after) in cost_history {
+        if after > cost {
+            units += (after - cost) / factor_at(date);
+        } else if cost > Decimal::ZERO {
+            units *= after / cost;
+        }
+        cost = after;
+    }
+    if cost <= Decimal::ZERO {
+        return Ok(None);
+    }
+    Ok(Some(
+        (units * factor_at(end) / cost - Decimal::ONE) * Decimal::from(100),
+    ))
+}
+
+/// The portfolio's own cash flows replayed into a benchmark
+#[derive(Debug, Clone, Serialize)]
+pub struct WhatIf {
+    pub benchmark: Benchmark,
+    /// What the start value plus every contribution, minus every withdrawal,
+    /// would be worth at the end of the period
+    pub end_value: Decimal,
+    /// Actual end value minus the benchmark's (positive = you beat it)
+    pub difference: Decimal,
+    /// Last benchmark value used; earlier than the period end when the
+    /// stored series is behind
+    pub data_until: NaiveDate,
+}
+
+/// Replay the period's start value and cash flows into each benchmark.
+///
+/// Buys are invested on their settlement date, sales and income are taken
+/// out on theirs, so both sides see exactly the same money movements and the
+/// end values compare directly. Benchmarks without stored data for the start
+/// of the period are left out.
+pub fn what_if(
+    conn: &Connection,
+    start: NaiveDate,
+    end: NaiveDate,
+    start_value: Decimal,
+    flows: &[CashFlow],
+    actual_end_value: Decimal,
+) -> Result<Vec<WhatIf>> {
+    let mut flows: Vec<&CashFlow> = flows.iter().collect();
+    flows.sort_by_key(|f| f.date);
+
+    let mut results = Vec::new();
+    for benchmark in Benchmark::all() {
+        let Some(curve) = growth_curve(conn, benchmark, start, end)? else {
+            continue;
+        };
+        let factor_at = |date: NaiveDate| {
+            let idx = curve.partition_point(|(d, _)| *d <= date);
+            curve[idx.saturating_sub(1)].1
+        };
+
+        let mut units = start_value;
+        for flow in &flows {
+            let amount = flow.amount / factor_at(flow.date);
+            match flow.flow_type {
+                FlowType::Contribution => units += amount,
+                FlowType::Withdrawal => units -= amount,
+            }
+        }
+        let end_value = (units * factor_at(end)).round_dp(2);
+        results.push(WhatIf {
+            benchmark,
+            end_value,
+            difference: actual_end_value - end_value,
+            data_until: curve.last().map(|(d, _)| *d).unwrap_or(start),
+        });
+    }
+    Ok(results)
+}
+
+/// Growth of 1 invested at `start`, as (date it applies from, factor) points
+fn growth_curve(
+    conn: &Connection,
+    benchmark: Benchmark,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Option<Vec<(NaiveDate, Decimal)>>> {
+    let mut curve = vec![(start, Decimal::ONE)];
+    if benchmark.is_rate() {
+        let values = rate_values(conn, benchmark, start, end)?;
+        if values.is_empty() {
+            return Ok(None);
+        }
+        // A rate counts once its day (or month) has accrued
+        let mut factor = Decimal::ONE;
+        for v in values.iter().filter(|v| v.value_date < end) {
+            factor *= accrued_factor(benchmark, v, start, end);
+            curve.push((accrual_end(benchmark, v).min(end), factor));
+        }
+        return Ok(Some(curve));
+    }
+
+    let values = db::get_benchmark_values(conn, benchmark, start, end)?;
+    let Some(base) = db::get_benchmark_value_on_or_before(conn, benchmark, start)? else {
+        return Ok(None);
+    };
+    if base.value <= Decimal::ZERO {
+        return Ok(None);
+    }
+    curve.extend(
+        values
+            .iter()
+            .filter(|v| v.value_date > start)
+            .map(|v| (v.value_date, v.value / base.value)),
+    );
+    Ok(Some(curve))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn
+    }
+
+    fn insert(conn: &Connection, benchmark: Benchmark, date: NaiveDate, value: Decimal) {
+        db::insert_benchmark_value(
+            conn,
+            &BenchmarkValue {
+                benchmark,
+                value_date: date,
+                value,
+                source: "TEST".to_string(),
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_what_if_replays_cash_flows() {
+        let conn = setup();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        insert(&conn, Benchmark::Ibov, d(1, 2), dec!(100000));
+        insert(&conn, Benchmark::Ibov, d(3, 1), dec!(125000));
+        insert(&conn, Benchmark::Ibov, d(6, 28), dec!(150000));
+
+        let flows = vec![
+            CashFlow {
+                date: d(3, 1),
+                flow_type: FlowType::Contribution,
+                amount: dec!(1000),
+            },
+            CashFlow {
+                date: d(6, 28),
+                flow_type: FlowType::Withdrawal,
+                amount: dec!(300),
+            },
+        ];
+        let results = what_if(&conn, d(1, 2), d(6, 28), dec!(1000), &flows, dec!(2500)).unwrap();
+
+        // IFIX and CDI have no data and are left out
+        assert_eq!(results.len(), 1);
+        let ibov = &results[0];
+        // 1000 grows 50%, 1000 added at +25% grows 20%, then 300 withdrawn
+        assert_eq!(ibov.end_value, dec!(2400));
+        assert_eq!(ibov.difference, dec!(100));
+        assert_eq!(ibov.data_until, d(6, 28));
+    }
+
+    #[test]
+    fn test_asset_alpha_weights_benchmark_by_cost() {
+        let conn = setup();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        insert(&conn, Benchmark::Ibov, d(1, 2), dec!(100000));
+        insert(&conn, Benchmark::Ibov, d(3, 1), dec!(125000));
+        insert(&conn, Benchmark::Ibov, d(4, 1), dec!(130000));
+        insert(&conn, Benchmark::Ibov, d(6, 28), dec!(150000));
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'ITSA4', 'STOCK');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source)
+             VALUES (1, 'BUY', '2024-01-02', '100', '10', '1000', '0', 'MANUAL'),
+                    (1, 'BUY', '2024-03-01', '100', '20', '2000', '0', 'MANUAL'),
+                    (1, 'SELL', '2024-04-01', '100', '21', '2100', '0', 'MANUAL');
+             INSERT INTO price_history (asset_id, price_date, close_price, source)
+             VALUES (1, '2024-06-28', '18', 'TEST');",
+        )
+        .unwrap();
+        let asset = db::get_asset_by_ticker(&conn, "ITSA4").unwrap().unwrap();
+
+        let alpha = asset_alpha(&conn, &asset).unwrap().unwrap();
+        assert_eq!(alpha.start_date, d(1, 2));
+        assert_eq!(alpha.end_date, d(6, 28));
+        // 100 shares at an average cost of 15 are worth 18
+        assert_eq!(alpha.asset_return_pct, dec!(20));
+        // 1000 grows 50% and 2000 grows 20%; the sale takes out half of each
+        assert_eq!(alpha.benchmark_return_pct.round_dp(6), dec!(30));
+        assert_eq!(alpha.alpha_pct.round_dp(6), dec!(-10));
+    }
+
+    #[test]
+    fn test_index_benchmark_return_uses_levels() {
+        let conn = setup();
+        let d1 = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let d2 = NaiveDate::from_ymd_opt(2024, 6, 28).unwrap();
+        insert(&conn, Benchmark::Ibov, d1, dec!(100000));
+        insert(&conn, Benchmark::Ibov, d2, dec!(110000));
+
+        // Start on a weekend before d1 falls back to no data
+        let weekend = NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+        assert_eq!(
+            benchmark_return(&conn, Benchmark::Ibov, weekend, d2).unwrap(),
+            None
+        );
+
+        let ret = benchmark_return(&conn, Benchmark::Ibov, d1, d2)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ret, dec!(10));
+    }
+
+    #[test]
+    fn test_rate_benchmark_compounds_daily_rates() {
+        let conn = setup();
+        let d1 = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let d2 = NaiveDate::from_ymd_opt(2024, 1, 3).unwrap();
+        let d3 = NaiveDate::from_ymd_opt(2024, 1, 4).unwrap();
+        insert(&conn, Benchmark::Cdi, d1, dec!(1));
+        insert(&conn, Benchmark::Cdi, d2, dec!(1));
+        insert(&conn, Benchmark::Cdi, d3, dec!(1));
+
+        // Rates for d1 and d2 accrue; d3 is the end date and does not
+        let ret = benchmark_return(&conn, Benchmark::Cdi, d1, d3)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ret, dec!(2.01));
+    }
+
+    #[test]
+    fn test_monthly_rate_counts_pro_rata_with_spread() {
+        let conn = setup();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        insert(&conn, Benchmark::Ipca, d(1, 1), dec!(1));
+        insert(&conn, Benchmark::Ipca, d(2, 1), dec!(2));
+
+        // All of January, none of February
+        let ret = benchmark_return(&conn, Benchmark::Ipca, d(1, 1), d(2, 1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(ret, dec!(1));
+
+        // Half of January (16 of 31 days) from the 16th on
+        let ret = benchmark_return(&conn, Benchmark::Ipca, d(1, 16), d(2, 1))
+            .unwrap()
+            .unwrap();
+        assert!((ret - dec!(0.5147)).abs() < dec!(0.001), "{}", ret);
+
+        let spec: BenchmarkSpec = "ipca+6".parse().unwrap();
+        assert_eq!(spec.label(), "IPCA+6%");
+        assert!("IBOV+2".parse::<BenchmarkSpec>().is_err());
+        let cmp = compare_with_benchmarks(&conn, &[spec], d(1, 1), d(2, 1), dec!(3)).unwrap();
+        // 1% inflation plus 31 days of 6% a year
+        let ret = cmp[0].return_pct.unwrap();
+        assert!(ret > dec!(1.49) && ret < dec!(1.51), "{}", ret);
+        assert_eq!(cmp[0].excess_pct, Some(dec!(3) - ret));
+    }
+}
@@ -0,0 +1,219 @@
+//! Income and sales month by month for each broker account.
+//!
+//! Informes de rendimentos arrive one per broker, so reconciling the year is
+//! easier with the same split: dividends, JCP, amortizations and the IRRF
+//! withheld on them, plus sales, their net gain and the IRRF on sales taken
+//! from the brokerage notes. Gains use the consolidated average cost, the
+//! way they are taxed; only the attribution to brokers is new. Income and
+//! trades from imports without an "Instituição" column have no broker.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::db::{self, portfolio::scope_filter, IncomeEventType};
+use crate::tax::swing_trade::realized_sales_for_year;
+
+/// One broker's figures for one month
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BrokerMonth {
+    /// None for income and trades no broker is known for
+    pub broker: Option<String>,
+    pub month: u32,
+    pub dividends: Decimal,
+    pub jcp: Decimal,
+    pub interest: Decimal,
+    pub amortization: Decimal,
+    /// IRRF withheld on income
+    pub income_withheld: Decimal,
+    pub sales: Decimal,
+    /// Profit minus loss of the month's sales
+    pub net_gain: Decimal,
+    /// IRRF on sales (0.005% and 1% on day trades) from brokerage notes
+    pub sales_irrf: Decimal,
+}
+
+/// Whether any trade or income event names its broker
+pub fn has_broker_data(conn: &Connection) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM transactions WHERE broker_id IS NOT NULL)
+             OR EXISTS(SELECT 1 FROM income_events WHERE broker_id IS NOT NULL)",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+/// Months of `year` with any activity, by broker (named brokers first)
+pub fn broker_statement(conn: &Connection, year: i32) -> Result<Vec<BrokerMonth>> {
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+    let to = NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+
+    let mut months: Months = BTreeMap::new();
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT b.name, e.event_date, e.event_type, e.total_amount, e.withholding_tax
+         FROM income_events e
+         LEFT JOIN brokers b ON b.id = e.broker_id
+         WHERE e.event_date BETWEEN ?1 AND ?2{}",
+        scope_filter("e.portfolio_id")
+    ))?;
+    let mut rows = stmt.query([from, to])?;
+    while let Some(row) = rows.next()? {
+        let month = month_entry(&mut months, row.get(0)?, row.get(1)?);
+        let amount = db::get_decimal_value(row, 3)?;
+        match row.get::<_, String>(2)?.parse::<IncomeEventType>() {
+            Ok(IncomeEventType::Dividend) => month.dividends += amount,
+            Ok(IncomeEventType::Jcp) => month.jcp += amount,
+            Ok(IncomeEventType::Interest) => month.interest += amount,
+            Ok(IncomeEventType::Amortization) => month.amortization += amount,
+            Err(_) => continue,
+        }
+        month.income_withheld += db::get_optional_decimal_value(row, 4)?.unwrap_or_default();
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.id, b.name FROM transactions t
+         JOIN brokers b ON b.id = t.broker_id
+         WHERE t.transaction_type = 'SELL' AND t.trade_date BETWEEN ?1 AND ?2{}",
+        scope_filter("t.portfolio_id")
+    ))?;
+    let sale_brokers: HashMap<i64, String> = stmt
+        .query_map([from, to], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (_, sale) in realized_sales_for_year(conn, year)? {
+        let broker = sale
+            .transaction_id
+            .and_then(|id| sale_brokers.get(&id).cloned());
+        let month = month_entry(&mut months, broker, sale.sale_date);
+        month.sales += sale.sale_total;
+        month.net_gain += sale.profit_loss;
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT b.name, n.trade_date, n.irrf, n.irrf_day_trade
+         FROM broker_notes n
+         LEFT JOIN brokers b ON b.id = n.broker_id
+         WHERE n.trade_date BETWEEN ?1 AND ?2{}",
+        scope_filter("n.portfolio_id")
+    ))?;
+    let mut rows = stmt.query([from, to])?;
+    while let Some(row) = rows.next()? {
+        let irrf = db::get_decimal_value(row, 2)? + db::get_decimal_value(row, 3)?;
+        if !irrf.is_zero() {
+            month_entry(&mut months, row.get(0)?, row.get(1)?).sales_irrf += irrf;
+        }
+    }
+
+    Ok(months.into_values().collect())
+}
+
+/// Keyed so named brokers sort first, then by name and month
+type Months = BTreeMap<(bool, Option<String>, u32), BrokerMonth>;
+
+fn month_entry(months: &mut Months, broker: Option<String>, date: NaiveDate) -> &mut BrokerMonth {
+    months
+        .entry((broker.is_none(), broker.clone(), date.month()))
+        .or_insert_with(|| BrokerMonth {
+            broker,
+            month: date.month(),
+            ..Default::default()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{AssetType, IncomeEvent, Transaction, TransactionType};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_statement_splits_income_and_sales_by_broker() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        let asset_id = db::insert_asset(&conn, "ITSA4", &AssetType::Stock, None).unwrap();
+        let xp = db::upsert_broker(&conn, "XP INVESTIMENTOS CCTVM S/A").unwrap();
+        let inter = db::upsert_broker(&conn, "BANCO INTER S/A").unwrap();
+        assert!(!has_broker_data(&conn).unwrap());
+
+        for (date, tx_type, quantity, price, broker) in [
+            (d(1, 10), TransactionType::Buy, dec!(100), dec!(10), xp),
+            (d(1, 11), TransactionType::Buy, dec!(100), dec!(10), inter),
+            (d(3, 5), TransactionType::Sell, dec!(50), dec!(12), inter),
+        ] {
+            let tx_id = db::insert_transaction(
+                &conn,
+                &Transaction {
+                    id: None,
+                    asset_id,
+                    transaction_type: tx_type,
+                    trade_date: date,
+                    settlement_date: None,
+                    quantity,
+                    price_per_unit: price,
+                    total_cost: quantity * price,
+                    fees: Decimal::ZERO,
+                    is_day_trade: false,
+                    quota_issuance_date: None,
+                    notes: None,
+                    source: "TEST".to_string(),
+                    created_at: chrono::Utc::now(),
+                },
+            )
+            .unwrap();
+            db::set_transaction_broker(&conn, tx_id, broker).unwrap();
+        }
+
+        for (broker, event_type, amount, withheld) in [
+            (Some(xp), IncomeEventType::Jcp, dec!(20), dec!(3)),
+            (Some(inter), IncomeEventType::Jcp, dec!(10), dec!(1.5)),
+            (None, IncomeEventType::Dividend, dec!(5), dec!(0)),
+        ] {
+            let event_id = db::insert_income_event(
+                &conn,
+                &IncomeEvent {
+                    id: None,
+                    asset_id,
+                    event_date: d(4, 1),
+                    ex_date: None,
+                    event_type,
+                    amount_per_quota: dec!(0.1),
+                    total_amount: amount,
+                    withholding_tax: withheld,
+                    foreign_tax_withheld: None,
+                    is_quota_pre_2026: None,
+                    source: "TEST".to_string(),
+                    notes: None,
+                    created_at: chrono::Utc::now(),
+                },
+            )
+            .unwrap();
+            if let Some(broker) = broker {
+                db::set_income_event_broker(&conn, event_id, broker).unwrap();
+            }
+        }
+        assert!(has_broker_data(&conn).unwrap());
+
+        let statement = broker_statement(&conn, 2024).unwrap();
+        let find = |broker: Option<&str>, month: u32| {
+            statement
+                .iter()
+                .find(|m| m.broker.as_deref() == broker && m.month == month)
+                .unwrap()
+        };
+
+        let inter_sale = find(Some("BANCO INTER S/A"), 3);
+        assert_eq!(inter_sale.sales, dec!(600));
+        assert_eq!(inter_sale.net_gain, dec!(100));
+        assert_eq!(find(Some("BANCO INTER S/A"), 4).income_withheld, dec!(1.5));
+        assert_eq!(find(Some("XP INVESTIMENTOS CCTVM S/A"), 4).jcp, dec!(20));
+        assert_eq!(find(None, 4).dividends, dec!(5));
+        assert!(statement.last().unwrap().broker.is_none());
+    }
+}
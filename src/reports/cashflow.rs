@@ -1,6 +1,7 @@
 use anyhow::Result;
 use chrono::{Datelike, NaiveDate};
 use rust_decimal::Decimal;
+use serde::Serialize;
 use std::collections::HashMap;
 
 use crate::db::{self, AssetType, TransactionType};
@@ -34,6 +35,24 @@ pub struct AssetTypeCashFlow {
     pub net_flow: Decimal,
 }
 
+/// New capital for one year, inferred from trades and income. There is no
+/// cash account, so sale proceeds and income are assumed to fund that
+/// year's buys first: only what they do not cover counts as a deposit, and
+/// only what is left over counts as a withdrawal.
+#[derive(Debug, Clone, Serialize)]
+pub struct YearlyNetFlow {
+    pub year: i32,
+    pub bought: Decimal,
+    pub sold: Decimal,
+    pub income: Decimal,
+    /// Income that went back into buys
+    pub reinvested_income: Decimal,
+    pub deposited: Decimal,
+    pub withdrawn: Decimal,
+    /// Deposited minus withdrawn
+    pub net_new_capital: Decimal,
+}
+
 #[derive(Debug, Clone)]
 pub struct CashFlowStats {
     pub avg_monthly_in: Decimal,
@@ -144,6 +163,37 @@ pub fn calculate_cash_flow_report(
     })
 }
 
+/// Per-year deposits, withdrawals and reinvested income of a report
+pub fn net_flow_by_year(report: &CashFlowReport) -> Vec<YearlyNetFlow> {
+    report
+        .years
+        .iter()
+        .map(|y| {
+            let sum = |f: fn(&AssetTypeCashFlow) -> Decimal| -> Decimal {
+                y.by_asset_type.values().map(f).sum()
+            };
+            let bought = sum(|a| a.money_in);
+            let sold = sum(|a| a.money_out_sells);
+            let income = sum(|a| a.money_out_income);
+
+            // Sale proceeds are redeployed first, then income
+            let uncovered = (bought - sold).max(Decimal::ZERO);
+            let reinvested_income = income.min(uncovered);
+            let net_new_capital = bought - sold - income;
+            YearlyNetFlow {
+                year: y.year,
+                bought,
+                sold,
+                income,
+                reinvested_income,
+                deposited: net_new_capital.max(Decimal::ZERO),
+                withdrawn: (-net_new_capital).max(Decimal::ZERO),
+                net_new_capital,
+            }
+        })
+        .collect()
+}
+
 pub fn calculate_cash_flow_stats(
     conn: &rusqlite::Connection,
     from_date: NaiveDate,
@@ -421,6 +471,7 @@ mod tests {
             amount_per_quota: Decimal::ZERO,
             total_amount: Decimal::from(20),
             withholding_tax: Decimal::from(3),
+            foreign_tax_withheld: None,
             is_quota_pre_2026: None,
             source: "TEST".to_string(),
             notes: None,
@@ -496,6 +547,7 @@ mod tests {
             amount_per_quota: Decimal::ZERO,
             total_amount: Decimal::from(10),
             withholding_tax: Decimal::from(2),
+            foreign_tax_withheld: None,
             is_quota_pre_2026: None,
             source: "TEST".to_string(),
             notes: None,
@@ -525,5 +577,12 @@ mod tests {
         assert_eq!(asset.money_in, Decimal::from(101));
         assert_eq!(asset.money_out_income, Decimal::from(8));
         assert_eq!(asset.net_flow, Decimal::from(93));
+
+        let net = net_flow_by_year(&report);
+        assert_eq!(net.len(), 1);
+        assert_eq!(net[0].reinvested_income, Decimal::from(8));
+        assert_eq!(net[0].deposited, Decimal::from(93));
+        assert_eq!(net[0].withdrawn, Decimal::ZERO);
+        assert_eq!(net[0].net_new_capital, Decimal::from(93));
     }
 }
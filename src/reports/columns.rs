@@ -0,0 +1,334 @@
+//! User-defined columns for `portfolio show`.
+//!
+//! Each `[[portfolio.columns]]` entry in the config names a column and the
+//! arithmetic expression that fills it, written over the position fields
+//! below with `+ - * /`, parentheses and decimal literals:
+//!
+//! ```toml
+//! [[portfolio.columns]]
+//! name = "Weight"
+//! expr = "value / total * 100"
+//! format = "percent"
+//! ```
+//!
+//! A column whose expression needs a missing value (no price yet) or divides
+//! by zero shows N/A for that position.
+
+use anyhow::{anyhow, bail, Context, Result};
+use rust_decimal::Decimal;
+
+use super::portfolio::PositionSummary;
+use super::PortfolioReport;
+use crate::config::{ColumnConfig, ColumnFormat};
+use crate::utils::format_currency;
+
+/// Names usable in expressions, with their aliases
+const FIELDS: &[(&str, &[&str])] = &[
+    ("quantity", &["qty"]),
+    ("average_cost", &["avg_cost"]),
+    ("total_cost", &["cost"]),
+    ("price", &["current_price"]),
+    ("value", &["market_value", "current_value"]),
+    ("gain", &["pl", "unrealized_pl"]),
+    ("gain_pct", &["pl_pct", "return_pct", "unrealized_pl_pct"]),
+    ("total", &["total_value", "portfolio_value"]),
+    ("portfolio_cost", &[]),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(Decimal),
+    Field(&'static str),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+}
+
+/// A configured column, ready to evaluate
+#[derive(Debug, Clone)]
+pub struct ComputedColumn {
+    pub name: String,
+    format: ColumnFormat,
+    expr: Expr,
+}
+
+impl ComputedColumn {
+    pub fn value(&self, position: &PositionSummary, report: &PortfolioReport) -> Option<Decimal> {
+        eval(&self.expr, position, report)
+    }
+
+    pub fn display(&self, value: Option<Decimal>) -> String {
+        match value {
+            None => "N/A".to_string(),
+            Some(v) => match self.format {
+                ColumnFormat::Number => format!("{:.2}", v.round_dp(2)),
+                ColumnFormat::Currency => format_currency(v),
+                ColumnFormat::Percent => format!("{:.2}%", v.round_dp(2)),
+            },
+        }
+    }
+}
+
+/// Parse the configured columns, failing on the first invalid expression
+pub fn compile(columns: &[ColumnConfig]) -> Result<Vec<ComputedColumn>> {
+    columns
+        .iter()
+        .map(|c| {
+            let source = c.expr.as_deref().unwrap_or(&c.name);
+            let expr =
+                parse(source).with_context(|| format!("Invalid portfolio column '{}'", c.name))?;
+            Ok(ComputedColumn {
+                name: c.name.clone(),
+                format: c.format.unwrap_or_default(),
+                expr,
+            })
+        })
+        .collect()
+}
+
+/// Columns from `~/.interest/config.toml`, none when unset
+pub fn configured() -> Result<Vec<ComputedColumn>> {
+    let config = crate::config::load_config()?;
+    match config.portfolio {
+        Some(portfolio) => compile(&portfolio.columns),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn field(position: &PositionSummary, report: &PortfolioReport, name: &str) -> Option<Decimal> {
+    match name {
+        "quantity" => Some(position.quantity),
+        "average_cost" => Some(position.average_cost),
+        "total_cost" => Some(position.total_cost),
+        "price" => position.current_price,
+        "value" => position.current_value,
+        "gain" => position.unrealized_pl,
+        "gain_pct" => position.unrealized_pl_pct,
+        "total" => Some(report.total_value),
+        "portfolio_cost" => Some(report.total_cost),
+        _ => None,
+    }
+}
+
+fn eval(expr: &Expr, position: &PositionSummary, report: &PortfolioReport) -> Option<Decimal> {
+    match expr {
+        Expr::Number(n) => Some(*n),
+        Expr::Field(name) => field(position, report, name),
+        Expr::Neg(inner) => eval(inner, position, report).map(|v| -v),
+        Expr::Binary(lhs, op, rhs) => {
+            let a = eval(lhs, position, report)?;
+            let b = eval(rhs, position, report)?;
+            match op {
+                '+' => a.checked_add(b),
+                '-' => a.checked_sub(b),
+                '*' => a.checked_mul(b),
+                _ => a.checked_div(b),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Decimal),
+    Ident(String),
+    Op(char),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if "+-*/()".contains(c) {
+            tokens.push(Token::Op(c));
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut end = start;
+            while let Some(&(i, d)) = chars.peek() {
+                if !(d.is_ascii_digit() || d == '.') {
+                    break;
+                }
+                end = i + d.len_utf8();
+                chars.next();
+            }
+            let text = &source[start..end];
+            let number = text
+                .parse()
+                .map_err(|_| anyhow!("Invalid number '{}'", text))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, d)) = chars.peek() {
+                if !(d.is_alphanumeric() || d == '_') {
+                    break;
+                }
+                end = i + d.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Ident(source[start..end].to_lowercase()));
+        } else {
+            bail!("Unexpected character '{}'", c);
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse(source: &str) -> Result<Expr> {
+    let tokens = tokenize(source)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.sum()?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        bail!("Unexpected {:?} in '{}'", token, source);
+    }
+    Ok(expr)
+}
+
+/// Recursive descent over `sum := product (('+'|'-') product)*`,
+/// `product := unary (('*'|'/') unary)*`, `unary := '-' unary | atom`
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next_op(&mut self, ops: &str) -> Option<char> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(c)) if ops.contains(*c) => {
+                self.pos += 1;
+                Some(*c)
+            }
+            _ => None,
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        let mut expr = self.product()?;
+        while let Some(op) = self.next_op("+-") {
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while let Some(op) = self.next_op("*/") {
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.next_op("-").is_some() {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow!("Expression ends too early"))?;
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Ident(name) => FIELDS
+                .iter()
+                .find(|(field, aliases)| *field == name || aliases.contains(&name.as_str()))
+                .map(|(field, _)| Expr::Field(field))
+                .ok_or_else(|| {
+                    let known: Vec<&str> = FIELDS.iter().map(|(f, _)| *f).collect();
+                    anyhow!("Unknown field '{}'. Available: {}", name, known.join(", "))
+                }),
+            Token::Op('(') => {
+                let expr = self.sum()?;
+                if self.next_op(")").is_none() {
+                    bail!("Missing ')'");
+                }
+                Ok(expr)
+            }
+            Token::Op(c) => bail!("Unexpected '{}'", c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Asset, AssetType};
+    use rust_decimal_macros::dec;
+
+    fn column(name: &str, expr: Option<&str>) -> ColumnConfig {
+        ColumnConfig {
+            name: name.to_string(),
+            expr: expr.map(str::to_string),
+            format: None,
+        }
+    }
+
+    #[test]
+    fn test_computed_columns_evaluate_over_position() {
+        let position = PositionSummary {
+            asset: Asset {
+                id: Some(1),
+                ticker: "PETR4".to_string(),
+                asset_type: AssetType::Stock,
+                name: None,
+                cnpj: None,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            },
+            quantity: dec!(100),
+            average_cost: dec!(30),
+            total_cost: dec!(3000),
+            current_price: None,
+            current_value: None,
+            unrealized_pl: None,
+            unrealized_pl_pct: None,
+            valued_on: None,
+            fx: None,
+        };
+        let mut report = PortfolioReport {
+            positions: Vec::new(),
+            total_cost: dec!(6000),
+            total_value: dec!(8000),
+            total_pl: dec!(2000),
+            total_pl_pct: dec!(33.33),
+        };
+
+        let columns = compile(&[
+            column("weight", Some("market_value / total * 100")),
+            column("gain_pct", None),
+            column("break_even", Some("-(avg_cost * -1) + 2 * (1 - 0.5)")),
+        ])
+        .unwrap();
+        // No price yet: columns needing it are N/A
+        assert_eq!(columns[0].value(&position, &report), None);
+        assert_eq!(columns[0].display(None), "N/A");
+        assert_eq!(columns[2].value(&position, &report), Some(dec!(31)));
+
+        let position = PositionSummary {
+            current_price: Some(dec!(40)),
+            current_value: Some(dec!(4000)),
+            unrealized_pl: Some(dec!(1000)),
+            unrealized_pl_pct: Some(dec!(33.3366)),
+            ..position
+        };
+        assert_eq!(columns[0].value(&position, &report), Some(dec!(50)));
+        assert_eq!(
+            columns[1].display(columns[1].value(&position, &report)),
+            "33.34"
+        );
+
+        // Dividing by zero leaves the cell empty instead of failing
+        report.total_value = Decimal::ZERO;
+        assert_eq!(columns[0].value(&position, &report), None);
+
+        let err = compile(&[column("bad", Some("value / dividends"))]).unwrap_err();
+        assert!(format!("{:#}", err).contains("Unknown field 'dividends'"));
+        assert!(compile(&[column("bad", Some("(value + 1"))]).is_err());
+        assert!(compile(&[column("bad", Some("value 2"))]).is_err());
+    }
+}
@@ -0,0 +1,215 @@
+//! Side-by-side comparison of a few held assets over one period.
+//!
+//! Prices come from the stored daily closes, so the comparison is as good as
+//! the price history (see `prices coverage`). Returns are price-only; income
+//! is shown separately as the amount paid per quota and its yield on the
+//! price at the start of the period.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::db::{self, Asset, IncomeEventType};
+
+/// How one asset did over the period
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetComparison {
+    pub ticker: String,
+    pub start_price: Option<Decimal>,
+    pub end_price: Option<Decimal>,
+    pub price_return_pct: Option<Decimal>,
+    /// Dividends and JCP paid per quota in the period (amortization is capital)
+    pub income_per_quota: Decimal,
+    /// Income per quota over the start price
+    pub yield_pct: Option<Decimal>,
+    /// Largest fall from a previous close in the period (negative or zero)
+    pub max_drawdown_pct: Option<Decimal>,
+    /// Close at each month end rebased to 100 at the start of the period
+    pub normalized: Vec<(NaiveDate, Decimal)>,
+}
+
+/// Compare `assets` between `from` and `to`, in the order given
+pub fn compare_assets(
+    conn: &Connection,
+    assets: &[Asset],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<AssetComparison>> {
+    let hundred = Decimal::from(100);
+    let mut comparisons = Vec::with_capacity(assets.len());
+
+    for asset in assets {
+        let asset_id = asset.id.expect("asset from database must have id");
+        // The period starts at the last close on or before `from`
+        let start = db::get_price_on_or_before(conn, asset_id, from)?;
+        let mut closes = match &start {
+            Some(p) => vec![(p.price_date, p.close_price)],
+            None => Vec::new(),
+        };
+        closes.extend(closes_between(conn, asset_id, from, to)?);
+        closes.dedup_by_key(|(date, _)| *date);
+
+        let start_price = closes
+            .first()
+            .map(|(_, c)| *c)
+            .filter(|c| *c > Decimal::ZERO);
+        let end_price = closes.last().map(|(_, c)| *c);
+
+        let price_return_pct = match (start_price, end_price) {
+            (Some(start), Some(end)) if closes.len() > 1 => {
+                Some(((end / start - Decimal::ONE) * hundred).round_dp(2))
+            }
+            _ => None,
+        };
+
+        let income_per_quota: Decimal =
+            db::get_income_events_with_assets(conn, Some(from), Some(to), Some(&asset.ticker))?
+                .iter()
+                .filter(|(event, _)| event.event_type != IncomeEventType::Amortization)
+                .map(|(event, _)| event.amount_per_quota)
+                .sum();
+        let yield_pct = start_price.map(|start| (income_per_quota / start * hundred).round_dp(2));
+
+        comparisons.push(AssetComparison {
+            ticker: asset.ticker.clone(),
+            start_price,
+            end_price,
+            price_return_pct,
+            income_per_quota,
+            yield_pct,
+            max_drawdown_pct: max_drawdown_pct(&closes),
+            normalized: start_price
+                .map(|start| normalized_month_ends(&closes, start))
+                .unwrap_or_default(),
+        });
+    }
+
+    Ok(comparisons)
+}
+
+fn closes_between(
+    conn: &Connection,
+    asset_id: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<(NaiveDate, Decimal)>> {
+    let mut stmt = conn.prepare(
+        "SELECT price_date, close_price FROM price_history
+         WHERE asset_id = ?1 AND price_date > ?2 AND price_date <= ?3
+         ORDER BY price_date",
+    )?;
+    let closes = stmt
+        .query_map(rusqlite::params![asset_id, from, to], |row| {
+            Ok((row.get(0)?, db::get_decimal_value(row, 1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(closes)
+}
+
+/// Worst peak-to-trough fall, in percent
+pub(crate) fn max_drawdown_pct(closes: &[(NaiveDate, Decimal)]) -> Option<Decimal> {
+    if closes.len() < 2 {
+        return None;
+    }
+    let mut peak = Decimal::ZERO;
+    let mut worst = Decimal::ZERO;
+    for (_, close) in closes {
+        peak = peak.max(*close);
+        if peak > Decimal::ZERO {
+            worst = worst.min((*close / peak - Decimal::ONE) * Decimal::from(100));
+        }
+    }
+    Some(worst.round_dp(2))
+}
+
+/// Last close of each month, rebased to 100 at `start`
+fn normalized_month_ends(
+    closes: &[(NaiveDate, Decimal)],
+    start: Decimal,
+) -> Vec<(NaiveDate, Decimal)> {
+    let mut points: Vec<(NaiveDate, Decimal)> = Vec::new();
+    for (date, close) in closes {
+        let value = (*close / start * Decimal::from(100)).round_dp(2);
+        match points.last_mut() {
+            Some((last, v)) if (last.year(), last.month()) == (date.year(), date.month()) => {
+                *last = *date;
+                *v = value;
+            }
+            _ => points.push((*date, value)),
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{AssetType, IncomeEvent, PriceHistory};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_compare_returns_income_and_drawdown() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        let asset_id = db::insert_asset(&conn, "HGLG11", &AssetType::Fii, None).unwrap();
+        for (date, close) in [
+            (d(1, 2), dec!(100)),
+            (d(1, 31), dec!(120)),
+            (d(2, 15), dec!(90)),
+            (d(2, 29), dec!(110)),
+        ] {
+            db::insert_price_history(
+                &conn,
+                &PriceHistory {
+                    id: None,
+                    asset_id,
+                    price_date: date,
+                    close_price: close,
+                    open_price: None,
+                    high_price: None,
+                    low_price: None,
+                    volume: None,
+                    source: "TEST".to_string(),
+                    created_at: chrono::Utc::now(),
+                    adjusted_close: None,
+                },
+            )
+            .unwrap();
+        }
+        db::insert_income_event(
+            &conn,
+            &IncomeEvent {
+                id: None,
+                asset_id,
+                event_date: d(2, 14),
+                ex_date: None,
+                event_type: IncomeEventType::Dividend,
+                amount_per_quota: dec!(1.10),
+                total_amount: dec!(110),
+                withholding_tax: Decimal::ZERO,
+                foreign_tax_withheld: None,
+                is_quota_pre_2026: None,
+                source: "TEST".to_string(),
+                notes: None,
+                created_at: chrono::Utc::now(),
+            },
+        )
+        .unwrap();
+
+        let asset = db::get_asset_by_ticker(&conn, "HGLG11").unwrap().unwrap();
+        let result = compare_assets(&conn, &[asset], d(1, 5), d(2, 29)).unwrap();
+        let hglg = &result[0];
+        assert_eq!(hglg.start_price, Some(dec!(100)));
+        assert_eq!(hglg.price_return_pct, Some(dec!(10)));
+        assert_eq!(hglg.yield_pct, Some(dec!(1.10)));
+        assert_eq!(hglg.max_drawdown_pct, Some(dec!(-25)));
+        assert_eq!(
+            hglg.normalized,
+            vec![(d(1, 31), dec!(120)), (d(2, 29), dec!(110))]
+        );
+    }
+}
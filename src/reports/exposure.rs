@@ -0,0 +1,287 @@
+//! Portfolio exposure by economic sector and segment.
+//!
+//! Positions are grouped by the sector or segment Mais Retorno reports for
+//! the asset (`assets sync-maisretorno`). Real estate, agro and
+//! infrastructure funds always group by their fund segment (logística,
+//! shoppings, lajes corporativas...), since their sector is the fund type
+//! itself. Assets missing from the registry fall into an unclassified group
+//! per asset type. Classified groups holding more than the threshold share of
+//! the portfolio are flagged as concentrated.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::db::{self, AssetType};
+use crate::reports::PortfolioReport;
+
+const REGISTRY_SOURCE: &str = "MAIS_RETORNO";
+
+/// Flag groups above this share of the portfolio when none is configured (%)
+pub const DEFAULT_THRESHOLD_PCT: Decimal = Decimal::from_parts(25, 0, 0, false, 0);
+
+/// Which registry field groups stocks, BDRs and ETFs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExposureLevel {
+    Sector,
+    Segment,
+}
+
+/// Market value held in one sector or segment
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureGroup {
+    pub name: String,
+    /// False for assets missing from the registry
+    pub classified: bool,
+    pub value: Decimal,
+    /// Share of the valued portfolio, in %
+    pub share_pct: Decimal,
+    pub tickers: Vec<String>,
+    pub concentrated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExposureReport {
+    pub level: ExposureLevel,
+    pub threshold_pct: Decimal,
+    pub total_value: Decimal,
+    /// Largest first
+    pub groups: Vec<ExposureGroup>,
+}
+
+impl ExposureReport {
+    pub fn concentrated(&self) -> impl Iterator<Item = &ExposureGroup> {
+        self.groups.iter().filter(|g| g.concentrated)
+    }
+}
+
+fn is_fund(asset_type: &AssetType) -> bool {
+    matches!(
+        asset_type,
+        AssetType::Fii | AssetType::Fiagro | AssetType::FiInfra
+    )
+}
+
+/// Group name of a position, None when the registry does not classify it
+fn group_name(
+    asset_type: &AssetType,
+    sector: Option<&str>,
+    segment: Option<&str>,
+    level: ExposureLevel,
+) -> Option<String> {
+    if is_fund(asset_type) {
+        return segment.map(|s| format!("{}: {}", asset_type.as_str(), s));
+    }
+    match level {
+        ExposureLevel::Sector => sector.or(segment),
+        ExposureLevel::Segment => segment.or(sector),
+    }
+    .map(str::to_string)
+}
+
+/// Exposure of the valued positions in `report`
+pub fn exposure(
+    conn: &Connection,
+    report: &PortfolioReport,
+    level: ExposureLevel,
+    threshold_pct: Decimal,
+) -> Result<ExposureReport> {
+    let mut groups: BTreeMap<(bool, String), (Decimal, Vec<String>)> = BTreeMap::new();
+    for position in &report.positions {
+        let Some(value) = position.current_value.filter(|v| *v > Decimal::ZERO) else {
+            continue;
+        };
+        let ticker = &position.asset.ticker;
+        let entry = db::get_asset_registry_by_ticker(conn, REGISTRY_SOURCE, ticker)?;
+        let (sector, segment) = entry
+            .as_ref()
+            .map(|e| {
+                (
+                    e.actuation_sector.as_deref(),
+                    e.actuation_segment.as_deref(),
+                )
+            })
+            .unwrap_or_default();
+        // The registry knows the type of assets the ticker lookup could not resolve
+        let asset_type = match (&position.asset.asset_type, &entry) {
+            (AssetType::Unknown, Some(e)) => &e.asset_type,
+            (asset_type, _) => asset_type,
+        };
+        let key = match group_name(asset_type, sector, segment, level) {
+            Some(name) => (true, name),
+            None => (false, format!("Unclassified {}", asset_type.as_str())),
+        };
+        let group = groups.entry(key).or_default();
+        group.0 += value;
+        group.1.push(ticker.clone());
+    }
+
+    let total_value: Decimal = groups.values().map(|(value, _)| *value).sum();
+    let mut groups: Vec<ExposureGroup> = groups
+        .into_iter()
+        .map(|((classified, name), (value, tickers))| {
+            let share_pct = if total_value > Decimal::ZERO {
+                (value / total_value * Decimal::from(100)).round_dp(2)
+            } else {
+                Decimal::ZERO
+            };
+            ExposureGroup {
+                name,
+                classified,
+                value,
+                share_pct,
+                tickers,
+                concentrated: classified && share_pct > threshold_pct,
+            }
+        })
+        .collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.value));
+
+    Ok(ExposureReport {
+        level,
+        threshold_pct,
+        total_value,
+        groups,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Asset, AssetRegistryEntry};
+    use crate::reports::portfolio::PositionSummary;
+    use rust_decimal_macros::dec;
+
+    fn register(
+        conn: &Connection,
+        ticker: &str,
+        asset_type: AssetType,
+        sector: &str,
+        segment: &str,
+    ) {
+        db::upsert_asset_registry(
+            conn,
+            &AssetRegistryEntry {
+                source: REGISTRY_SOURCE.to_string(),
+                ticker: ticker.to_string(),
+                asset_type,
+                name: None,
+                cnpj: None,
+                actuation_segment: Some(segment.to_string()),
+                actuation_sector: Some(sector.to_string()),
+                issue: None,
+                situation: None,
+                indexer: None,
+                security_type: None,
+                codigo: None,
+                data_emissao: None,
+                data_vencimento: None,
+                source_url: None,
+                raw_json: None,
+                updated_at: None,
+            },
+        )
+        .unwrap();
+    }
+
+    fn position(ticker: &str, asset_type: AssetType, value: Decimal) -> PositionSummary {
+        PositionSummary {
+            asset: Asset {
+                id: None,
+                ticker: ticker.to_string(),
+                asset_type,
+                name: None,
+                cnpj: None,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            },
+            quantity: dec!(1),
+            average_cost: value,
+            total_cost: value,
+            current_price: Some(value),
+            current_value: Some(value),
+            unrealized_pl: None,
+            unrealized_pl_pct: None,
+            valued_on: None,
+            fx: None,
+        }
+    }
+
+    #[test]
+    fn test_exposure_by_sector_and_fii_segment() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        register(&conn, "ITUB4", AssetType::Stock, "Financeiro", "Bancos");
+        register(&conn, "BBAS3", AssetType::Stock, "Financeiro", "Bancos");
+        register(
+            &conn,
+            "BBSE3",
+            AssetType::Stock,
+            "Financeiro",
+            "Seguradoras",
+        );
+        register(
+            &conn,
+            "XPLG11",
+            AssetType::Fii,
+            "Fundos Imobiliários",
+            "Logística",
+        );
+        register(
+            &conn,
+            "HGLG11",
+            AssetType::Fii,
+            "Fundos Imobiliários",
+            "Logística",
+        );
+
+        let positions = vec![
+            position("ITUB4", AssetType::Stock, dec!(3000)),
+            position("BBAS3", AssetType::Stock, dec!(1000)),
+            position("BBSE3", AssetType::Stock, dec!(1000)),
+            position("XPLG11", AssetType::Fii, dec!(2000)),
+            position("HGLG11", AssetType::Fii, dec!(1000)),
+            position("WEGE3", AssetType::Stock, dec!(2000)),
+        ];
+        let report = PortfolioReport {
+            positions,
+            total_cost: dec!(10000),
+            total_value: dec!(10000),
+            total_pl: Decimal::ZERO,
+            total_pl_pct: Decimal::ZERO,
+        };
+
+        let by_sector = exposure(&conn, &report, ExposureLevel::Sector, dec!(25)).unwrap();
+        let shares: Vec<(&str, Decimal, bool)> = by_sector
+            .groups
+            .iter()
+            .map(|g| (g.name.as_str(), g.share_pct, g.concentrated))
+            .collect();
+        assert_eq!(
+            shares,
+            vec![
+                ("Financeiro", dec!(50), true),
+                ("FII: Logística", dec!(30), true),
+                // Missing from the registry: shown, never flagged
+                ("Unclassified STOCK", dec!(20), false),
+            ]
+        );
+
+        let by_segment = exposure(&conn, &report, ExposureLevel::Segment, dec!(35)).unwrap();
+        let bancos = &by_segment.groups[0];
+        assert_eq!(bancos.name, "Bancos");
+        assert_eq!(bancos.share_pct, dec!(40));
+        assert_eq!(bancos.tickers, vec!["ITUB4", "BBAS3"]);
+        assert_eq!(
+            by_segment
+                .concentrated()
+                .map(|g| g.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Bancos"]
+        );
+    }
+}
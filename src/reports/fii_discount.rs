@@ -0,0 +1,168 @@
+//! FII price-to-NAV (P/VP) and premium/discount over time.
+//!
+//! NAV per quota comes from the CVM monthly reports (`prices update-nav`).
+//! Each month is compared with the last close on or before the end of that
+//! month; the current ratio uses the latest close against the latest NAV.
+
+use anyhow::Result;
+use chrono::{Months, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::db::{self, AssetType, PriceSeries};
+use crate::reports::PortfolioReport;
+
+/// Price against NAV for one reference month
+#[derive(Debug, Clone, Serialize)]
+pub struct NavPoint {
+    pub reference_date: NaiveDate,
+    pub nav_per_quota: Decimal,
+    pub price: Option<Decimal>,
+    pub p_vp: Option<Decimal>,
+}
+
+impl NavPoint {
+    /// Premium (positive) or discount (negative) to NAV, in %
+    pub fn premium_pct(&self) -> Option<Decimal> {
+        self.p_vp.map(premium_pct)
+    }
+}
+
+fn premium_pct(p_vp: Decimal) -> Decimal {
+    (p_vp - Decimal::ONE) * Decimal::from(100)
+}
+
+/// Current discount of a held FII, with its own history for context
+#[derive(Debug, Clone, Serialize)]
+pub struct FiiDiscount {
+    pub ticker: String,
+    pub market_value: Option<Decimal>,
+    pub price: Option<Decimal>,
+    pub nav_per_quota: Option<Decimal>,
+    pub nav_date: Option<NaiveDate>,
+    pub p_vp: Option<Decimal>,
+    pub premium_pct: Option<Decimal>,
+    /// Average premium over the stored NAV history
+    pub avg_premium_pct: Option<Decimal>,
+}
+
+/// Month-by-month P/VP of an FII, oldest first
+pub fn nav_history(conn: &Connection, asset_id: i64) -> Result<Vec<NavPoint>> {
+    let mut points = Vec::new();
+    for nav in db::get_fii_nav_history(conn, asset_id)? {
+        let month_end = nav
+            .reference_date
+            .checked_add_months(Months::new(1))
+            .and_then(|d| d.pred_opt())
+            .unwrap_or(nav.reference_date);
+        let price = db::get_price_on_or_before(conn, asset_id, month_end)?
+            .filter(|p| (month_end - p.price_date).num_days() <= 31)
+            .map(|p| p.price(PriceSeries::Close));
+        points.push(NavPoint {
+            reference_date: nav.reference_date,
+            nav_per_quota: nav.nav_per_quota,
+            price,
+            p_vp: price.map(|p| p / nav.nav_per_quota),
+        });
+    }
+    Ok(points)
+}
+
+/// Held FIIs ranked by current premium, deepest discount first; FIIs without
+/// NAV data are listed last
+pub fn rank_fii_discounts(conn: &Connection, report: &PortfolioReport) -> Result<Vec<FiiDiscount>> {
+    let mut rows = Vec::new();
+    for position in &report.positions {
+        if position.asset.asset_type != AssetType::Fii {
+            continue;
+        }
+        let Some(asset_id) = position.asset.id else {
+            continue;
+        };
+
+        let history = nav_history(conn, asset_id)?;
+        let premiums: Vec<Decimal> = history.iter().filter_map(|p| p.premium_pct()).collect();
+        let avg_premium_pct = (!premiums.is_empty())
+            .then(|| premiums.iter().sum::<Decimal>() / Decimal::from(premiums.len()));
+
+        let latest = history.last();
+        let price = position.current_price;
+        let p_vp = match (price, latest) {
+            (Some(price), Some(nav)) => Some(price / nav.nav_per_quota),
+            _ => None,
+        };
+        rows.push(FiiDiscount {
+            ticker: position.asset.ticker.clone(),
+            market_value: position.current_value,
+            price,
+            nav_per_quota: latest.map(|n| n.nav_per_quota),
+            nav_date: latest.map(|n| n.reference_date),
+            p_vp,
+            premium_pct: p_vp.map(premium_pct),
+            avg_premium_pct,
+        });
+    }
+
+    rows.sort_by(|a, b| match (a.premium_pct, b.premium_pct) {
+        (Some(x), Some(y)) => x.cmp(&y),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.ticker.cmp(&b.ticker),
+    });
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{FiiNav, PriceHistory};
+    use chrono::Utc;
+
+    #[test]
+    fn test_nav_history_uses_month_end_close() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id = db::upsert_asset(&conn, "HGLG11", &AssetType::Fii, None).unwrap();
+
+        for (month, nav) in [(1, 160), (2, 162)] {
+            db::upsert_fii_nav(
+                &conn,
+                &FiiNav {
+                    asset_id,
+                    reference_date: NaiveDate::from_ymd_opt(2024, month, 1).unwrap(),
+                    nav_per_quota: Decimal::from(nav),
+                    net_assets: None,
+                    quotas: None,
+                },
+            )
+            .unwrap();
+        }
+        // February has no close of its own and falls back to January's
+        db::insert_price_history(
+            &conn,
+            &PriceHistory {
+                id: None,
+                asset_id,
+                price_date: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+                close_price: Decimal::from(144),
+                open_price: None,
+                high_price: None,
+                low_price: None,
+                volume: None,
+                source: "TEST".to_string(),
+                created_at: Utc::now(),
+                adjusted_close: None,
+            },
+        )
+        .unwrap();
+
+        let history = nav_history(&conn, asset_id).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].p_vp, Some(Decimal::new(9, 1)));
+        assert_eq!(history[0].premium_pct(), Some(Decimal::from(-10)));
+        assert!(history[1].p_vp.is_some());
+        assert_eq!(history[1].price, Some(Decimal::from(144)));
+    }
+}
@@ -0,0 +1,124 @@
+//! Split BDR returns into local price and USD/BRL currency effects.
+//!
+//! A BDR's return in reais compounds the underlying's return in dollars with
+//! the change in USD/BRL: `(1 + r_brl) = (1 + r_local) * (1 + r_fx)`. The
+//! cross term is reported separately so the three parts add up to `r_brl`.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::db::{self, AssetType};
+use crate::pricing::fx::USD_BRL;
+use crate::reports::performance::PerformanceReport;
+
+/// BDR return attribution for a period (all values in %)
+#[derive(Debug, Clone, Serialize)]
+pub struct FxAttribution {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub start_rate: Decimal,
+    pub end_rate: Decimal,
+    pub brl_return_pct: Decimal,
+    pub local_effect_pct: Decimal,
+    pub fx_effect_pct: Decimal,
+    pub cross_effect_pct: Decimal,
+}
+
+/// Decompose a BRL return (%) given the FX rates at both ends
+///
+/// Returns (local_effect_pct, fx_effect_pct, cross_effect_pct).
+pub fn decompose_return(
+    brl_return_pct: Decimal,
+    start_rate: Decimal,
+    end_rate: Decimal,
+) -> Option<(Decimal, Decimal, Decimal)> {
+    if start_rate <= Decimal::ZERO {
+        return None;
+    }
+    let hundred = Decimal::from(100);
+    let fx = end_rate / start_rate - Decimal::ONE;
+    let brl = brl_return_pct / hundred;
+    let local = (Decimal::ONE + brl) / (Decimal::ONE + fx) - Decimal::ONE;
+    let cross = brl - local - fx;
+    Some((
+        (local * hundred).round_dp(4),
+        (fx * hundred).round_dp(4),
+        (cross * hundred).round_dp(4),
+    ))
+}
+
+/// Attribute the BDR segment return of a performance report
+///
+/// Returns None when the report has no BDR holdings at the start of the
+/// period or when USD/BRL rates are missing for either end.
+pub fn bdr_fx_attribution(
+    conn: &Connection,
+    report: &PerformanceReport,
+) -> Result<Option<FxAttribution>> {
+    let Some(bdr) = report.asset_breakdown.get(&AssetType::Bdr) else {
+        return Ok(None);
+    };
+    if bdr.start_value <= Decimal::ZERO {
+        return Ok(None);
+    }
+
+    let Some((_, start_rate)) = db::get_fx_rate_on_or_before(conn, USD_BRL, report.start_date)?
+    else {
+        return Ok(None);
+    };
+    let Some((_, end_rate)) = db::get_fx_rate_on_or_before(conn, USD_BRL, report.end_date)? else {
+        return Ok(None);
+    };
+
+    let Some((local, fx, cross)) = decompose_return(bdr.return_pct, start_rate, end_rate) else {
+        return Ok(None);
+    };
+
+    Ok(Some(FxAttribution {
+        start_date: report.start_date,
+        end_date: report.end_date,
+        start_rate,
+        end_rate,
+        brl_return_pct: bdr.return_pct,
+        local_effect_pct: local,
+        fx_effect_pct: fx,
+        cross_effect_pct: cross,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_decompose_return_sums_to_total() {
+        // Underlying +10% in USD, dollar +5% vs BRL => +15.5% in BRL
+        let (local, fx, cross) = decompose_return(
+            Decimal::from_str("15.5").unwrap(),
+            Decimal::from_str("5.00").unwrap(),
+            Decimal::from_str("5.25").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(local, Decimal::from(10));
+        assert_eq!(fx, Decimal::from(5));
+        assert_eq!(cross, Decimal::from_str("0.5").unwrap());
+    }
+
+    #[test]
+    fn test_decompose_return_currency_only() {
+        // Flat underlying, dollar -4%: whole BRL loss is currency
+        let (local, fx, cross) = decompose_return(
+            Decimal::from(-4),
+            Decimal::from(5),
+            Decimal::from_str("4.8").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(local, Decimal::ZERO);
+        assert_eq!(fx, Decimal::from(-4));
+        assert_eq!(cross, Decimal::ZERO);
+    }
+}
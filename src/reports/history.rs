@@ -0,0 +1,178 @@
+//! Portfolio evolution since the first trade, for charts.
+//!
+//! The portfolio is valued at the end of every month (or year) from the
+//! stored valuation snapshots, next to the capital invested in the positions
+//! held then (their cost basis) and the income received up to that day, net
+//! of withholding. The current period ends today.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::db;
+use crate::reports::snapshots::day_values;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Monthly,
+    Yearly,
+}
+
+/// The portfolio at the end of one month or year
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryPoint {
+    pub date: NaiveDate,
+    pub market_value: Decimal,
+    /// Cost basis of the positions held
+    pub invested: Decimal,
+    /// Income received since the first trade, net of withholding
+    pub accumulated_income: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioHistory {
+    pub granularity: Granularity,
+    pub points: Vec<HistoryPoint>,
+}
+
+/// Period ends from the one containing `start` through `today`, which closes
+/// the last period
+pub fn period_ends(start: NaiveDate, today: NaiveDate, granularity: Granularity) -> Vec<NaiveDate> {
+    let mut ends = Vec::new();
+    let (mut year, mut month) = (start.year(), start.month());
+    loop {
+        let end = match granularity {
+            Granularity::Monthly => last_day_of_month(year, month),
+            Granularity::Yearly => NaiveDate::from_ymd_opt(year, 12, 31),
+        };
+        let Some(end) = end else {
+            break;
+        };
+        if end >= today {
+            ends.push(today);
+            break;
+        }
+        ends.push(end);
+        match granularity {
+            Granularity::Monthly if month == 12 => (year, month) = (year + 1, 1),
+            Granularity::Monthly => month += 1,
+            Granularity::Yearly => year += 1,
+        }
+    }
+    ends
+}
+
+fn last_day_of_month(year: i32, month: u32) -> Option<NaiveDate> {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)?.pred_opt()
+}
+
+/// Value, invested capital and accumulated income at each period end since
+/// the first trade
+pub fn portfolio_history(
+    conn: &mut Connection,
+    granularity: Granularity,
+    today: NaiveDate,
+) -> Result<PortfolioHistory> {
+    let Some(inception) = db::get_earliest_transaction_date(conn)? else {
+        return Ok(PortfolioHistory {
+            granularity,
+            points: Vec::new(),
+        });
+    };
+
+    let mut income: Vec<(NaiveDate, Decimal)> =
+        db::get_income_events_with_assets(conn, None, Some(today), None)?
+            .into_iter()
+            .map(|(event, _)| (event.event_date, event.total_amount - event.withholding_tax))
+            .collect();
+    income.sort_by_key(|(date, _)| *date);
+
+    let mut points = Vec::new();
+    let mut accumulated_income = Decimal::ZERO;
+    let mut next_income = income.iter().peekable();
+    let ends = period_ends(inception, today, granularity);
+    for day in day_values(conn, &ends, None)? {
+        while let Some((_, amount)) = next_income.next_if(|(paid, _)| *paid <= day.date) {
+            accumulated_income += amount;
+        }
+        points.push(HistoryPoint {
+            date: day.date,
+            market_value: day.market_value,
+            invested: day.total_cost,
+            accumulated_income,
+        });
+    }
+
+    Ok(PortfolioHistory {
+        granularity,
+        points,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_monthly_history_values_invested_and_income() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'XPLG11', 'FII');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source)
+                 VALUES (1, 'BUY', '2024-01-10', '10', '100', '1000', '0', 'TEST'),
+                        (1, 'BUY', '2024-03-05', '10', '110', '1100', '0', 'TEST');
+             INSERT INTO price_history (asset_id, price_date, close_price, source)
+                 VALUES (1, '2024-01-31', '105', 'TEST'), (1, '2024-02-29', '108', 'TEST'),
+                        (1, '2024-03-15', '112', 'TEST');
+             INSERT INTO income_events (asset_id, event_date, event_type, amount_per_quota,
+                 total_amount, withholding_tax, source)
+                 VALUES (1, '2024-02-14', 'DIVIDEND', '0.8', '8', '0', 'TEST'),
+                        (1, '2024-03-14', 'DIVIDEND', '0.8', '16', '0', 'TEST');",
+        )
+        .unwrap();
+
+        let today = NaiveDate::from_ymd_opt(2024, 3, 20).unwrap();
+        let history = portfolio_history(&mut conn, Granularity::Monthly, today).unwrap();
+        let rows: Vec<(NaiveDate, Decimal, Decimal, Decimal)> = history
+            .points
+            .iter()
+            .map(|p| (p.date, p.market_value, p.invested, p.accumulated_income))
+            .collect();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (d(1, 31), dec!(1050), dec!(1000), dec!(0)),
+                (d(2, 29), dec!(1080), dec!(1000), dec!(8)),
+                // The current month ends today
+                (d(3, 20), dec!(2240), dec!(2100), dec!(24)),
+            ]
+        );
+
+        let years = period_ends(
+            d(1, 10),
+            NaiveDate::from_ymd_opt(2026, 5, 1).unwrap(),
+            Granularity::Yearly,
+        );
+        assert_eq!(
+            years,
+            vec![
+                NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+                NaiveDate::from_ymd_opt(2025, 12, 31).unwrap(),
+                NaiveDate::from_ymd_opt(2026, 5, 1).unwrap(),
+            ]
+        );
+    }
+}
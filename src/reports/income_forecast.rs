@@ -0,0 +1,710 @@
+//! Expected income payments, learned from each asset's payment history.
+//!
+//! Dividends and JCP follow a calendar the company or fund keeps from year to
+//! year: most FIIs pay every month around the same day, many stocks pay in
+//! the same two or four months. The cadence of each asset and income type is
+//! learned from the last two years of payments and projected forward over
+//! the current holdings. Events already recorded with a future payment date
+//! are announcements and take the place of the projection for their month.
+//!
+//! A projected payment starts from the amount per share of the reference
+//! payment (the same month a year earlier for a seasonal payer, the latest
+//! one for a monthly payer) and carries the yearly trend of the
+//! distributions: the last 12 months against the 12 before. How far single
+//! payments strayed from that pattern gives the 80% range around it, wider
+//! the further the payment is from its reference.
+
+use anyhow::Result;
+use chrono::{Datelike, Months, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::{Decimal, MathematicalOps};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use crate::db::{self, IncomeEvent, IncomeEventType};
+use crate::reports::calculate_portfolio;
+
+/// Months of payment history the cadence is learned from
+pub const LOOKBACK_MONTHS: u32 = 24;
+
+/// Payments in this many of the last 12 months make a monthly payer
+const MONTHLY_MIN_MONTHS: usize = 10;
+
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Months of the year an asset pays in, and the usual day of payment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Cadence {
+    pub months: Vec<u32>,
+    pub day: u32,
+}
+
+impl Cadence {
+    /// Learn the cadence from payment dates on or before `as_of`
+    ///
+    /// A calendar month counts when it was paid in both of the last two
+    /// years, or once if the history is shorter than a year; one-off
+    /// payments outside the usual months are ignored. None with fewer than
+    /// two payments.
+    pub fn learn(dates: &[NaiveDate], as_of: NaiveDate) -> Option<Cadence> {
+        let start = as_of.checked_sub_months(Months::new(LOOKBACK_MONTHS))?;
+        let dates: Vec<NaiveDate> = dates
+            .iter()
+            .copied()
+            .filter(|d| *d > start && *d <= as_of)
+            .collect();
+        let paid: BTreeSet<(i32, u32)> = dates.iter().map(|d| (d.year(), d.month())).collect();
+        if paid.len() < 2 {
+            return None;
+        }
+
+        let year_ago = as_of.checked_sub_months(Months::new(12))?;
+        let first = *dates.iter().min()?;
+        let recent: Vec<&(i32, u32)> = paid
+            .iter()
+            .filter(|(y, m)| NaiveDate::from_ymd_opt(*y, *m, 1).is_some_and(|d| d > year_ago))
+            .collect();
+        // A short history paid every month since it began is monthly too
+        let months_since_first = ((as_of.year() - first.year()) * 12 + as_of.month() as i32
+            - first.month() as i32
+            + 1) as usize;
+        let monthly = recent.len() >= MONTHLY_MIN_MONTHS
+            || (first > year_ago && paid.len() >= 3 && paid.len() + 1 >= months_since_first);
+
+        let months: Vec<u32> = if monthly {
+            (1..=12).collect()
+        } else {
+            let required = if first <= year_ago { 2 } else { 1 };
+            let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+            for (_, month) in &paid {
+                *counts.entry(*month).or_default() += 1;
+            }
+            counts
+                .into_iter()
+                .filter(|(_, n)| *n >= required)
+                .map(|(m, _)| m)
+                .collect()
+        };
+        if months.is_empty() {
+            return None;
+        }
+
+        let mut days: Vec<u32> = dates
+            .iter()
+            .filter(|d| months.contains(&d.month()))
+            .map(|d| d.day())
+            .collect();
+        days.sort_unstable();
+        let day = days[days.len() / 2];
+        Some(Cadence { months, day })
+    }
+
+    pub fn is_monthly(&self) -> bool {
+        self.months.len() == 12
+    }
+
+    /// e.g. "monthly, ~14th" or "May, Nov ~25th"
+    pub fn describe(&self) -> String {
+        if self.is_monthly() {
+            return format!("monthly, ~{}", ordinal(self.day));
+        }
+        let months: Vec<&str> = self
+            .months
+            .iter()
+            .map(|m| MONTH_NAMES[*m as usize - 1])
+            .collect();
+        format!("{} ~{}", months.join(", "), ordinal(self.day))
+    }
+
+    /// Expected payment dates after `after`, up to `until`
+    pub fn dates_between(&self, after: NaiveDate, until: NaiveDate) -> Vec<NaiveDate> {
+        let mut dates = Vec::new();
+        let mut month_start = after.with_day(1).unwrap_or(after);
+        while month_start <= until {
+            if self.months.contains(&month_start.month()) {
+                let date = (1..=self.day)
+                    .rev()
+                    .find_map(|d| month_start.with_day(d))
+                    .unwrap_or(month_start);
+                if date > after && date <= until {
+                    dates.push(date);
+                }
+            }
+            match month_start.checked_add_months(Months::new(1)) {
+                Some(next) => month_start = next,
+                None => break,
+            }
+        }
+        dates
+    }
+}
+
+fn ordinal(day: u32) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{}{}", day, suffix)
+}
+
+/// Share of projected payments expected to land inside their range
+pub const CONFIDENCE: &str = "80%";
+
+/// z-score of the two-sided 80% range
+const Z_80: Decimal = Decimal::from_parts(128, 0, 0, false, 2);
+
+/// Largest yearly change the trend may carry into the projection, either way
+const MAX_TREND: Decimal = Decimal::from_parts(5, 0, 0, false, 1);
+
+/// Relative spread assumed when too few payments tell the real one
+const UNKNOWN_SPREAD: Decimal = Decimal::from_parts(25, 0, 0, false, 2);
+
+/// Smallest relative spread, even for a fund paying the same every month
+const MIN_SPREAD: Decimal = Decimal::from_parts(5, 0, 0, false, 2);
+
+/// One payment expected in the forecast window
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpectedPayment {
+    pub date: NaiveDate,
+    pub ticker: String,
+    pub event_type: IncomeEventType,
+    /// Quantity held now; None for announcements, recorded with their total
+    pub quantity: Option<Decimal>,
+    pub amount_per_quota: Decimal,
+    /// Gross amount (JCP before the 15% IRRF)
+    pub amount: Decimal,
+    /// Range the amount is expected in; both equal the amount when announced
+    pub low: Decimal,
+    pub high: Decimal,
+    /// Recorded with a future payment date rather than projected
+    pub announced: bool,
+    /// Learned cadence behind a projected payment
+    pub cadence: Option<String>,
+    /// Tax on the payment under the scenario asked for; None when gross
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax: Option<Decimal>,
+}
+
+impl ExpectedPayment {
+    /// Amount left after the scenario's tax
+    pub fn net(&self) -> Decimal {
+        self.amount - self.tax.unwrap_or_default()
+    }
+}
+
+/// Trailing and expected income of one asset held
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetOutlook {
+    pub ticker: String,
+    /// Received over the last 12 months
+    pub ltm: Decimal,
+    /// Change of the distributions per share over the last 12 months against
+    /// the 12 before, in percent; None without a full earlier year
+    pub trend_pct: Option<Decimal>,
+    /// Expected over the forecast window
+    pub expected: Decimal,
+    pub low: Decimal,
+    pub high: Decimal,
+    /// Tax on the expected payments under the scenario asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax: Option<Decimal>,
+}
+
+/// Expected income of one calendar month
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthlyTotal {
+    /// First day of the month
+    pub month: NaiveDate,
+    pub amount: Decimal,
+    pub low: Decimal,
+    pub high: Decimal,
+    pub tax: Decimal,
+}
+
+/// Expected payments over the next months
+#[derive(Debug, Clone, Serialize)]
+pub struct IncomeForecast {
+    pub as_of: NaiveDate,
+    pub until: NaiveDate,
+    pub payments: Vec<ExpectedPayment>,
+    pub assets: Vec<AssetOutlook>,
+}
+
+impl IncomeForecast {
+    /// Total expected per calendar month, with the payments' ranges added up
+    pub fn monthly_totals(&self) -> Vec<MonthlyTotal> {
+        let mut totals: BTreeMap<NaiveDate, MonthlyTotal> = BTreeMap::new();
+        for p in &self.payments {
+            let month = p.date.with_day(1).unwrap_or(p.date);
+            let total = totals.entry(month).or_insert(MonthlyTotal {
+                month,
+                amount: Decimal::ZERO,
+                low: Decimal::ZERO,
+                high: Decimal::ZERO,
+                tax: Decimal::ZERO,
+            });
+            total.amount += p.amount;
+            total.low += p.low;
+            total.high += p.high;
+            total.tax += p.tax.unwrap_or_default();
+        }
+        totals.into_values().collect()
+    }
+
+    pub fn total(&self) -> Decimal {
+        self.payments.iter().map(|p| p.amount).sum()
+    }
+
+    /// Tax on every payment under the scenario asked for
+    pub fn total_tax(&self) -> Decimal {
+        self.payments.iter().filter_map(|p| p.tax).sum()
+    }
+
+    /// Lower and upper end of the total, each payment at its own end
+    pub fn total_range(&self) -> (Decimal, Decimal) {
+        (
+            self.payments.iter().map(|p| p.low).sum(),
+            self.payments.iter().map(|p| p.high).sum(),
+        )
+    }
+}
+
+fn months_between(from: NaiveDate, to: NaiveDate) -> i32 {
+    (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32
+}
+
+/// How one asset's payments of one type move: the yearly trend of the
+/// distributions per share and how much single payments stray from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct PayoutModel {
+    /// Last 12 months over the 12 before; 1 without a full earlier year
+    pub growth: Decimal,
+    /// Relative standard deviation of the last 12 months' payments
+    pub spread: Decimal,
+    /// Distributions per share of the last 12 months
+    pub recent: Decimal,
+    /// Of the 12 before, scaled to as many payments as the last 12 months
+    /// had; None unless that year was paid in full
+    pub earlier: Option<Decimal>,
+}
+
+impl PayoutModel {
+    /// Fit on (payment date, amount per share) pairs on or before `as_of`.
+    /// The trend compares the average payment of both years, so a payment
+    /// still to come this month does not read as a cut; it needs the earlier
+    /// year paid at least as often as the last one, so a payer that started
+    /// mid-year does not look like it grew.
+    pub fn fit(series: &[(NaiveDate, Decimal)], cadence: &Cadence, as_of: NaiveDate) -> Self {
+        let year_ago = as_of.checked_sub_months(Months::new(12)).unwrap_or(as_of);
+        let two_years_ago = as_of
+            .checked_sub_months(Months::new(24))
+            .unwrap_or(year_ago);
+        let recent: Vec<Decimal> = series
+            .iter()
+            .filter(|(d, _)| *d > year_ago && *d <= as_of)
+            .map(|(_, a)| *a)
+            .collect();
+        let earlier: Vec<Decimal> = series
+            .iter()
+            .filter(|(d, _)| *d > two_years_ago && *d <= year_ago)
+            .map(|(_, a)| *a)
+            .collect();
+
+        let recent_sum: Decimal = recent.iter().sum();
+        let earlier_sum: Decimal = earlier.iter().sum();
+        let comparable =
+            !recent.is_empty() && earlier.len() >= recent.len() && earlier_sum > Decimal::ZERO;
+        // Earlier year at the last one's number of payments
+        let earlier_scaled =
+            earlier_sum * Decimal::from(recent.len()) / Decimal::from(earlier.len().max(1));
+        let growth = if comparable {
+            (recent_sum / earlier_scaled).clamp(Decimal::ONE - MAX_TREND, Decimal::ONE + MAX_TREND)
+        } else {
+            Decimal::ONE
+        };
+
+        // Seasonal payers spread by season, not around one mean: compare
+        // each payment with the same month a year earlier instead
+        let deviations: Vec<Decimal> = if cadence.is_monthly() {
+            let mean = recent_sum / Decimal::from(recent.len().max(1));
+            if mean.is_zero() {
+                Vec::new()
+            } else {
+                recent.iter().map(|a| *a / mean - Decimal::ONE).collect()
+            }
+        } else {
+            series
+                .iter()
+                .filter(|(d, _)| *d > year_ago && *d <= as_of)
+                .filter_map(|(d, a)| {
+                    series
+                        .iter()
+                        .find(|(p, _)| months_between(*p, *d) == 12)
+                        .filter(|(_, prior)| *prior > Decimal::ZERO)
+                        .map(|(_, prior)| *a / (*prior * growth) - Decimal::ONE)
+                })
+                .collect()
+        };
+        let spread = if deviations.len() < 2 && !(cadence.is_monthly() && recent.len() >= 3) {
+            UNKNOWN_SPREAD
+        } else {
+            let n = Decimal::from(deviations.len().max(2) - 1);
+            let variance: Decimal = deviations.iter().map(|d| d * d).sum::<Decimal>() / n;
+            variance
+                .sqrt()
+                .unwrap_or(UNKNOWN_SPREAD)
+                .clamp(MIN_SPREAD, Decimal::ONE)
+        };
+        PayoutModel {
+            growth,
+            spread,
+            recent: recent_sum,
+            earlier: comparable.then_some(earlier_scaled),
+        }
+    }
+
+    /// Amount per share expected on `date` from one paid on `reference`,
+    /// with the low and high ends of its range. The range widens with the
+    /// distance from the reference payment.
+    pub fn project(
+        &self,
+        reference: NaiveDate,
+        per_share: Decimal,
+        date: NaiveDate,
+    ) -> (Decimal, Decimal, Decimal) {
+        let years = Decimal::from(months_between(reference, date).max(1)) / Decimal::from(12);
+        let expected = per_share * self.growth.powd(years);
+        let width = Z_80 * self.spread * (Decimal::ONE + years).sqrt().unwrap_or(Decimal::ONE);
+        (
+            expected,
+            expected * (Decimal::ONE - width).max(Decimal::ZERO),
+            expected * (Decimal::ONE + width),
+        )
+    }
+}
+
+/// Quantities held at the end of each day asked for, computed once per day
+pub(crate) struct HeldOn<'a> {
+    conn: &'a Connection,
+    days: HashMap<NaiveDate, HashMap<i64, Decimal>>,
+}
+
+impl<'a> HeldOn<'a> {
+    pub(crate) fn new(conn: &'a Connection) -> Self {
+        HeldOn {
+            conn,
+            days: HashMap::new(),
+        }
+    }
+
+    fn quantity(&mut self, asset_id: i64, date: NaiveDate) -> Result<Decimal> {
+        if !self.days.contains_key(&date) {
+            let held = crate::reports::calculate_portfolio_at_date(self.conn, date, None)?
+                .positions
+                .into_iter()
+                .filter_map(|p| p.asset.id.map(|id| (id, p.quantity)))
+                .collect();
+            self.days.insert(date, held);
+        }
+        Ok(self.days[&date].get(&asset_id).copied().unwrap_or_default())
+    }
+
+    /// Amount per share of a paid event. Statements record the total
+    /// credited; divide it by the position held on the ex-date (or the
+    /// payment date when it is unknown).
+    pub(crate) fn per_share(&mut self, event: &IncomeEvent) -> Result<Option<Decimal>> {
+        if event.amount_per_quota > Decimal::ZERO {
+            return Ok(Some(event.amount_per_quota));
+        }
+        let held = self.quantity(event.asset_id, event.ex_date.unwrap_or(event.event_date))?;
+        Ok((held > Decimal::ZERO).then(|| (event.total_amount / held).round_dp(6)))
+    }
+}
+
+/// Forecast dividend and JCP payments from `as_of` to `months` months ahead
+pub fn forecast(
+    conn: &Connection,
+    as_of: NaiveDate,
+    months: u32,
+    asset: Option<&str>,
+) -> Result<IncomeForecast> {
+    let until = as_of
+        .checked_add_months(Months::new(months))
+        .ok_or_else(|| anyhow::anyhow!("Forecast window out of range"))?;
+    let start = as_of
+        .checked_sub_months(Months::new(LOOKBACK_MONTHS))
+        .unwrap_or(as_of);
+    let year_ago = as_of.checked_sub_months(Months::new(12)).unwrap_or(as_of);
+
+    let holdings: HashMap<i64, Decimal> = calculate_portfolio(conn, None)?
+        .positions
+        .into_iter()
+        .filter(|p| p.quantity > Decimal::ZERO)
+        .filter_map(|p| p.asset.id.map(|id| (id, p.quantity)))
+        .collect();
+
+    let mut history: HashMap<(i64, String), (String, Vec<IncomeEvent>)> = HashMap::new();
+    let mut payments = Vec::new();
+    // Months an announcement or an earlier payment already covers
+    let mut covered: HashSet<(i64, String, i32, u32)> = HashSet::new();
+    // Per asset held, with the distributions per share of the last 12 months
+    // and the 12 before, over the income types paid in both years
+    let mut outlooks: BTreeMap<String, (AssetOutlook, Decimal, Decimal)> = BTreeMap::new();
+
+    for (event, a) in db::get_income_events_with_assets(conn, Some(start), Some(until), asset)? {
+        if event.event_type == IncomeEventType::Amortization {
+            continue;
+        }
+        if holdings.contains_key(&event.asset_id) {
+            let (outlook, _, _) = outlooks.entry(a.ticker.clone()).or_insert_with(|| {
+                let empty = AssetOutlook {
+                    ticker: a.ticker.clone(),
+                    ltm: Decimal::ZERO,
+                    trend_pct: None,
+                    expected: Decimal::ZERO,
+                    low: Decimal::ZERO,
+                    high: Decimal::ZERO,
+                    tax: None,
+                };
+                (empty, Decimal::ZERO, Decimal::ZERO)
+            });
+            if event.event_date > year_ago && event.event_date <= as_of {
+                outlook.ltm += event.total_amount;
+            }
+        }
+        let type_key = event.event_type.as_str().to_string();
+        covered.insert((
+            event.asset_id,
+            type_key.clone(),
+            event.event_date.year(),
+            event.event_date.month(),
+        ));
+        if event.event_date > as_of {
+            payments.push(ExpectedPayment {
+                date: event.event_date,
+                ticker: a.ticker.clone(),
+                event_type: event.event_type.clone(),
+                quantity: None,
+                amount_per_quota: event.amount_per_quota,
+                amount: event.total_amount,
+                low: event.total_amount,
+                high: event.total_amount,
+                announced: true,
+                cadence: None,
+                tax: None,
+            });
+        } else {
+            history
+                .entry((event.asset_id, type_key))
+                .or_insert_with(|| (a.ticker.clone(), Vec::new()))
+                .1
+                .push(event);
+        }
+    }
+
+    let mut held_on = HeldOn::new(conn);
+    for ((asset_id, type_key), (ticker, events)) in &history {
+        let Some(quantity) = holdings.get(asset_id).copied() else {
+            continue;
+        };
+        let mut series = Vec::new();
+        for event in events {
+            if let Some(per_share) = held_on.per_share(event)? {
+                series.push((event.event_date, per_share));
+            }
+        }
+        let dates: Vec<NaiveDate> = series.iter().map(|(d, _)| *d).collect();
+        let Some(cadence) = Cadence::learn(&dates, as_of) else {
+            continue;
+        };
+        let model = PayoutModel::fit(&series, &cadence, as_of);
+        if let (Some((_, recent, earlier)), Some(model_earlier)) =
+            (outlooks.get_mut(ticker), model.earlier)
+        {
+            *recent += model.recent;
+            *earlier += model_earlier;
+        }
+
+        let event_type = events[0].event_type.clone();
+        for date in cadence.dates_between(as_of, until) {
+            if covered.contains(&(*asset_id, type_key.clone(), date.year(), date.month())) {
+                continue;
+            }
+            // The same month a year earlier sets the amount of a seasonal
+            // payer; otherwise the latest payment does
+            let reference = series
+                .iter()
+                .rev()
+                .find(|(d, _)| !cadence.is_monthly() && d.month() == date.month())
+                .or_else(|| series.last());
+            let Some((paid_on, per_share)) = reference else {
+                continue;
+            };
+            let (per_share, low, high) = model.project(*paid_on, *per_share, date);
+            payments.push(ExpectedPayment {
+                date,
+                ticker: ticker.clone(),
+                event_type: event_type.clone(),
+                quantity: Some(quantity),
+                amount_per_quota: per_share.round_dp(6),
+                amount: (per_share * quantity).round_dp(2),
+                low: (low * quantity).round_dp(2),
+                high: (high * quantity).round_dp(2),
+                announced: false,
+                cadence: Some(cadence.describe()),
+                tax: None,
+            });
+        }
+    }
+
+    payments.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.ticker.cmp(&b.ticker)));
+    for p in &payments {
+        if let Some((outlook, _, _)) = outlooks.get_mut(&p.ticker) {
+            outlook.expected += p.amount;
+            outlook.low += p.low;
+            outlook.high += p.high;
+        }
+    }
+    let assets = outlooks
+        .into_values()
+        .map(|(mut outlook, recent, earlier)| {
+            outlook.trend_pct = (earlier > Decimal::ZERO)
+                .then(|| ((recent / earlier - Decimal::ONE) * Decimal::from(100)).round_dp(1));
+            outlook
+        })
+        .collect();
+    Ok(IncomeForecast {
+        as_of,
+        until,
+        payments,
+        assets,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+
+    #[test]
+    fn test_learn_monthly_and_semiannual_cadence() {
+        let as_of = d(2025, 6, 20);
+
+        // FII paying around the 14th every month, one payment on the 16th
+        let fii: Vec<NaiveDate> = (0..18)
+            .map(|i| {
+                let month = d(2024, 1, 1).checked_add_months(Months::new(i)).unwrap();
+                month.with_day(if i == 5 { 16 } else { 14 }).unwrap()
+            })
+            .collect();
+        let cadence = Cadence::learn(&fii, as_of).unwrap();
+        assert!(cadence.is_monthly());
+        assert_eq!(cadence.day, 14);
+        assert_eq!(cadence.describe(), "monthly, ~14th");
+        assert_eq!(
+            cadence.dates_between(as_of, d(2025, 9, 20)),
+            vec![d(2025, 7, 14), d(2025, 8, 14), d(2025, 9, 14)]
+        );
+
+        // Stock paying in May and November, plus a one-off extraordinary dividend
+        let stock = vec![
+            d(2023, 11, 24),
+            d(2024, 5, 22),
+            d(2024, 8, 2),
+            d(2024, 11, 25),
+            d(2025, 5, 23),
+        ];
+        let cadence = Cadence::learn(&stock, as_of).unwrap();
+        assert_eq!(cadence.months, vec![5, 11]);
+        assert_eq!(cadence.day, 24);
+        assert_eq!(cadence.describe(), "May, Nov ~24th");
+        assert_eq!(
+            cadence.dates_between(as_of, d(2026, 6, 20)),
+            vec![d(2025, 11, 24), d(2026, 5, 24)]
+        );
+
+        // A single payment has no cadence
+        assert_eq!(Cadence::learn(&[d(2025, 3, 10)], as_of), None);
+    }
+
+    #[test]
+    fn test_forecast_carries_trend_with_range() {
+        use crate::db::{AssetType, Transaction, TransactionType};
+        use rust_decimal_macros::dec;
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let fii = db::insert_asset(&conn, "HGLG11", &AssetType::Fii, None).unwrap();
+        db::insert_transaction(
+            &conn,
+            &Transaction {
+                id: None,
+                asset_id: fii,
+                transaction_type: TransactionType::Buy,
+                trade_date: d(2023, 1, 10),
+                settlement_date: None,
+                quantity: dec!(100),
+                price_per_unit: dec!(160),
+                total_cost: dec!(16000),
+                fees: Decimal::ZERO,
+                is_day_trade: false,
+                quota_issuance_date: None,
+                notes: None,
+                source: "TEST".to_string(),
+                created_at: chrono::Utc::now(),
+            },
+        )
+        .unwrap();
+        // 1.00 a quota a month until June 2024, 1.10 since: 10% a year up
+        for i in 0..24 {
+            let date = d(2023, 7, 14).checked_add_months(Months::new(i)).unwrap();
+            let per_quota = if i < 12 { dec!(1.00) } else { dec!(1.10) };
+            db::insert_income_event(
+                &conn,
+                &IncomeEvent {
+                    id: None,
+                    asset_id: fii,
+                    event_date: date,
+                    ex_date: None,
+                    event_type: IncomeEventType::Dividend,
+                    amount_per_quota: per_quota,
+                    total_amount: per_quota * dec!(100),
+                    withholding_tax: Decimal::ZERO,
+                    foreign_tax_withheld: None,
+                    is_quota_pre_2026: None,
+                    source: "TEST".to_string(),
+                    notes: None,
+                    created_at: chrono::Utc::now(),
+                },
+            )
+            .unwrap();
+        }
+
+        let forecast = forecast(&conn, d(2025, 6, 20), 12, None).unwrap();
+        assert_eq!(forecast.payments.len(), 12);
+        let july = &forecast.payments[0];
+        assert_eq!(july.date, d(2025, 7, 14));
+        // A month of the 10% yearly trend on top of the June payment
+        assert!(july.amount > dec!(110.80) && july.amount < dec!(110.95));
+        assert!(july.low < july.amount && july.amount < july.high);
+        // Later payments drift further and their ranges widen
+        let june = &forecast.payments[11];
+        assert!(june.amount > dec!(120));
+        assert!(june.high - june.low > july.high - july.low);
+
+        let outlook = &forecast.assets[0];
+        assert_eq!(outlook.ltm, dec!(1320));
+        assert_eq!(outlook.trend_pct, Some(dec!(10.0)));
+        assert_eq!(outlook.expected, forecast.total());
+        assert_eq!((outlook.low, outlook.high), forecast.total_range());
+    }
+}
@@ -0,0 +1,474 @@
+//! Income events reconciled against the cash credited for them.
+//!
+//! Each income row of a Movimentação statement is cash that reached the
+//! account. It is kept in `cash_credits` and linked to the income event it
+//! pays: the event imported from the same row, or one recorded beforehand
+//! (announced, added by hand) for the same asset and type, paid within a few
+//! days. The link compares what was announced, net of IRRF, with what was
+//! credited.
+//!
+//! Credits no event accounts for, and events the statements never credited
+//! although they cover the payment date, are opened as inconsistencies; they
+//! are closed again once linked.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashSet;
+
+use crate::db::{
+    self, get_decimal_value, get_optional_decimal_value, portfolio, IncomeEventType, Inconsistency,
+    InconsistencySeverity, InconsistencyStatus, InconsistencyType,
+};
+
+/// Days between an event's payment date and the credit that pays it
+pub const MATCH_WINDOW_DAYS: i64 = 5;
+
+/// A statement row crediting income
+#[derive(Debug, Clone)]
+pub struct NewCredit<'a> {
+    pub asset_id: i64,
+    pub date: NaiveDate,
+    pub movement_type: &'a str,
+    pub event_type: &'a IncomeEventType,
+    pub amount: Decimal,
+    pub broker_id: Option<i64>,
+}
+
+/// Record a credit; None when the same row was recorded before
+pub fn record_credit(conn: &Connection, credit: &NewCredit) -> Result<Option<i64>> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM cash_credits
+         WHERE asset_id = ?1 AND credit_date = ?2 AND movement_type = ?3 AND amount = ?4
+           AND broker_id IS ?5 AND portfolio_id = ?6)",
+        params![
+            credit.asset_id,
+            credit.date,
+            credit.movement_type,
+            credit.amount.to_string(),
+            credit.broker_id,
+            portfolio::write_target(),
+        ],
+        |row| row.get(0),
+    )?;
+    if exists {
+        return Ok(None);
+    }
+    conn.execute(
+        "INSERT INTO cash_credits (asset_id, credit_date, movement_type, event_type, amount,
+             source, broker_id, portfolio_id, import_session_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, 'MOVIMENTACAO', ?6, ?7, ?8)",
+        params![
+            credit.asset_id,
+            credit.date,
+            credit.movement_type,
+            credit.event_type.as_str(),
+            credit.amount.to_string(),
+            credit.broker_id,
+            portfolio::write_target(),
+            crate::db::import_session::current(),
+        ],
+    )?;
+    Ok(Some(conn.last_insert_rowid()))
+}
+
+/// Income event a credit pays: same asset, type and portfolio, paid within
+/// the window and not credited yet. An exact amount wins, then the nearest date.
+pub fn matching_event(conn: &Connection, credit_id: i64) -> Result<Option<i64>> {
+    let mut stmt = conn.prepare(
+        "SELECT e.id, e.total_amount, e.withholding_tax, c.amount,
+                ABS(julianday(e.event_date) - julianday(c.credit_date))
+         FROM cash_credits c
+         JOIN income_events e ON e.asset_id = c.asset_id AND e.event_type = c.event_type
+              AND e.portfolio_id = c.portfolio_id
+         WHERE c.id = ?1
+           AND ABS(julianday(e.event_date) - julianday(c.credit_date)) <= ?2
+           AND NOT EXISTS (SELECT 1 FROM cash_credits o WHERE o.income_event_id = e.id)",
+    )?;
+    let mut candidates = stmt
+        .query_map(params![credit_id, MATCH_WINDOW_DAYS], |row| {
+            let net = get_decimal_value(row, 1)?
+                - get_optional_decimal_value(row, 2)?.unwrap_or_default();
+            let credited = get_decimal_value(row, 3)?;
+            Ok((
+                net != credited,
+                row.get::<_, f64>(4)? as i64,
+                row.get::<_, i64>(0)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    candidates.sort();
+    Ok(candidates.first().map(|(_, _, id)| *id))
+}
+
+pub fn link(conn: &Connection, credit_id: i64, event_id: i64) -> Result<()> {
+    conn.execute(
+        "UPDATE cash_credits SET income_event_id = ?2 WHERE id = ?1",
+        params![credit_id, event_id],
+    )?;
+    Ok(())
+}
+
+/// Link every unlinked credit in scope that now has a matching event
+pub fn link_credits(conn: &Connection) -> Result<usize> {
+    let ids: Vec<i64> = conn
+        .prepare(&format!(
+            "SELECT id FROM cash_credits WHERE income_event_id IS NULL{} ORDER BY credit_date, id",
+            portfolio::scope_filter("portfolio_id")
+        ))?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut linked = 0;
+    for id in ids {
+        if let Some(event_id) = matching_event(conn, id)? {
+            link(conn, id, event_id)?;
+            linked += 1;
+        }
+    }
+    Ok(linked)
+}
+
+/// Record an unmatched credit as the income event it stands for
+pub fn record_credit_as_income(conn: &Connection, credit_id: i64) -> Result<i64> {
+    let (asset_id, date, event_type, amount): (i64, NaiveDate, String, Decimal) = conn
+        .query_row(
+            "SELECT asset_id, credit_date, event_type, amount FROM cash_credits WHERE id = ?1",
+            [credit_id],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    get_decimal_value(row, 3)?,
+                ))
+            },
+        )
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("Cash credit {} not found", credit_id))?;
+    let event = db::IncomeEvent {
+        id: None,
+        asset_id,
+        event_date: date,
+        ex_date: None,
+        event_type: event_type
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Unknown income type: {}", event_type))?,
+        amount_per_quota: amount,
+        total_amount: amount,
+        withholding_tax: Decimal::ZERO,
+        foreign_tax_withheld: None,
+        is_quota_pre_2026: None,
+        source: "MOVIMENTACAO".to_string(),
+        notes: Some(format!("Recorded from cash credit {}", credit_id)),
+        created_at: chrono::Utc::now(),
+    };
+    let event_id = db::insert_income_event(conn, &event)?;
+    link(conn, credit_id, event_id)?;
+    Ok(event_id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReconciliationStatus {
+    Matched,
+    AmountDiffers,
+    /// Event within the statements' period that no credit pays
+    Uncredited,
+    /// Credit no event accounts for
+    UnmatchedCredit,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconciliationLine {
+    pub date: NaiveDate,
+    pub ticker: String,
+    pub asset_id: i64,
+    pub event_type: String,
+    pub income_event_id: Option<i64>,
+    pub cash_credit_id: Option<i64>,
+    pub event_source: Option<String>,
+    /// Announced amount net of IRRF
+    pub expected: Option<Decimal>,
+    pub credited: Option<Decimal>,
+    /// Credited minus expected
+    pub difference: Decimal,
+    pub status: ReconciliationStatus,
+}
+
+fn in_year(date: NaiveDate, year: Option<i32>) -> bool {
+    year.is_none_or(|y| date.year() == y)
+}
+
+/// Credits and events of `year` (every year when None), by date
+pub fn reconcile(conn: &Connection, year: Option<i32>) -> Result<Vec<ReconciliationLine>> {
+    let mut lines = Vec::new();
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT c.id, c.credit_date, a.ticker, c.asset_id, c.event_type, c.amount,
+                e.id, e.source, e.total_amount, e.withholding_tax
+         FROM cash_credits c
+         JOIN assets a ON a.id = c.asset_id
+         LEFT JOIN income_events e ON e.id = c.income_event_id
+         WHERE 1=1{}",
+        portfolio::scope_filter("c.portfolio_id")
+    ))?;
+    let credits = stmt
+        .query_map([], |row| {
+            let credited = get_decimal_value(row, 5)?;
+            let event_id: Option<i64> = row.get(6)?;
+            let expected = match get_optional_decimal_value(row, 8)? {
+                Some(total) => {
+                    Some(total - get_optional_decimal_value(row, 9)?.unwrap_or_default())
+                }
+                None => None,
+            };
+            let (difference, status) = match (event_id, expected) {
+                (Some(_), Some(expected)) if expected == credited => {
+                    (Decimal::ZERO, ReconciliationStatus::Matched)
+                }
+                (Some(_), Some(expected)) => {
+                    (credited - expected, ReconciliationStatus::AmountDiffers)
+                }
+                _ => (credited, ReconciliationStatus::UnmatchedCredit),
+            };
+            Ok(ReconciliationLine {
+                date: row.get(1)?,
+                ticker: row.get(2)?,
+                asset_id: row.get(3)?,
+                event_type: row.get(4)?,
+                income_event_id: event_id,
+                cash_credit_id: Some(row.get(0)?),
+                event_source: row.get(7)?,
+                expected,
+                credited: Some(credited),
+                difference,
+                status,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    lines.extend(credits.into_iter().filter(|l| in_year(l.date, year)));
+
+    // Statements only speak for the period they cover
+    let covered: Option<(NaiveDate, NaiveDate)> = conn.query_row(
+        &format!(
+            "SELECT MIN(credit_date), MAX(credit_date) FROM cash_credits WHERE 1=1{}",
+            portfolio::scope_filter("portfolio_id")
+        ),
+        [],
+        |row| Ok(row.get::<_, Option<NaiveDate>>(0)?.zip(row.get(1)?)),
+    )?;
+    if let Some((from, to)) = covered {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT e.id, e.event_date, a.ticker, e.asset_id, e.event_type, e.source,
+                    e.total_amount, e.withholding_tax
+             FROM income_events e
+             JOIN assets a ON a.id = e.asset_id
+             WHERE e.event_date BETWEEN ?1 AND ?2
+               AND COALESCE(e.source, '') != 'MOVIMENTACAO'
+               AND NOT EXISTS (SELECT 1 FROM cash_credits c WHERE c.income_event_id = e.id){}",
+            portfolio::scope_filter("e.portfolio_id")
+        ))?;
+        let uncredited = stmt
+            .query_map([from, to], |row| {
+                let expected = get_decimal_value(row, 6)?
+                    - get_optional_decimal_value(row, 7)?.unwrap_or_default();
+                Ok(ReconciliationLine {
+                    date: row.get(1)?,
+                    ticker: row.get(2)?,
+                    asset_id: row.get(3)?,
+                    event_type: row.get(4)?,
+                    income_event_id: Some(row.get(0)?),
+                    cash_credit_id: None,
+                    event_source: row.get(5)?,
+                    expected: Some(expected),
+                    credited: None,
+                    difference: -expected,
+                    status: ReconciliationStatus::Uncredited,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        lines.extend(uncredited.into_iter().filter(|l| in_year(l.date, year)));
+    }
+
+    lines.sort_by(|a, b| (a.date, &a.ticker).cmp(&(b.date, &b.ticker)));
+    Ok(lines)
+}
+
+fn issue_ref(line: &ReconciliationLine) -> Option<(InconsistencyType, String)> {
+    match line.status {
+        ReconciliationStatus::UnmatchedCredit => Some((
+            InconsistencyType::UnmatchedCashCredit,
+            format!("cash_credit:{}", line.cash_credit_id?),
+        )),
+        ReconciliationStatus::Uncredited => Some((
+            InconsistencyType::UncreditedIncome,
+            format!("income_event:{}", line.income_event_id?),
+        )),
+        _ => None,
+    }
+}
+
+/// Whether the row an issue points to is reconciled now (or gone)
+fn reconciled(conn: &Connection, source_ref: &str) -> Result<bool> {
+    let open = match source_ref.split_once(':') {
+        Some(("cash_credit", id)) => conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM cash_credits WHERE id = ?1 AND income_event_id IS NULL)",
+            [id],
+            |row| row.get::<_, bool>(0),
+        )?,
+        Some(("income_event", id)) => conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM income_events e WHERE e.id = ?1
+                 AND NOT EXISTS (SELECT 1 FROM cash_credits c WHERE c.income_event_id = e.id))",
+            [id],
+            |row| row.get::<_, bool>(0),
+        )?,
+        _ => true,
+    };
+    Ok(!open)
+}
+
+/// Open an inconsistency for each unmatched credit and uncredited event,
+/// and resolve the open ones reconciled since. Returns (opened, resolved).
+pub fn sync_inconsistencies(conn: &Connection) -> Result<(usize, usize)> {
+    let mut known: HashSet<String> = HashSet::new();
+    let mut resolved = 0;
+    for issue_type in [
+        InconsistencyType::UnmatchedCashCredit,
+        InconsistencyType::UncreditedIncome,
+    ] {
+        for issue in db::list_inconsistencies(conn, None, Some(issue_type), None)? {
+            let Some(source_ref) = issue.source_ref else {
+                continue;
+            };
+            if issue.status == InconsistencyStatus::Open && reconciled(conn, &source_ref)? {
+                db::resolve_inconsistency(conn, issue.id.unwrap_or(0), Some("RECONCILED"), None)?;
+                resolved += 1;
+                continue;
+            }
+            known.insert(source_ref);
+        }
+    }
+
+    let mut opened = 0;
+    for line in reconcile(conn, None)? {
+        let Some((issue_type, source_ref)) = issue_ref(&line) else {
+            continue;
+        };
+        if known.contains(&source_ref) {
+            continue;
+        }
+        let notes = match issue_type {
+            InconsistencyType::UnmatchedCashCredit => {
+                "Income credited with no matching income event"
+            }
+            _ => "Income event never credited in the imported statements",
+        };
+        db::insert_inconsistency(
+            conn,
+            &Inconsistency {
+                id: None,
+                issue_type,
+                status: InconsistencyStatus::Open,
+                severity: InconsistencySeverity::Warn,
+                asset_id: Some(line.asset_id),
+                transaction_id: None,
+                ticker: Some(line.ticker.clone()),
+                trade_date: Some(line.date),
+                quantity: None,
+                source: line
+                    .event_source
+                    .clone()
+                    .or(Some("MOVIMENTACAO".to_string())),
+                source_ref: Some(source_ref),
+                missing_fields_json: None,
+                context_json: Some(
+                    json!({
+                        "notes": notes,
+                        "event_type": line.event_type,
+                        "expected": line.expected,
+                        "credited": line.credited,
+                    })
+                    .to_string(),
+                ),
+                resolution_action: None,
+                resolution_json: None,
+                created_at: None,
+                resolved_at: None,
+            },
+        )?;
+        opened += 1;
+    }
+    Ok((opened, resolved))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_credits_link_to_announced_income() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'ITSA4', 'STOCK');
+             INSERT INTO income_events (id, asset_id, event_date, event_type, amount_per_quota,
+                 total_amount, withholding_tax, source)
+             VALUES (1, 1, '2025-03-03', 'JCP', '0.10', '100', '15', 'MANUAL'),
+                    (2, 1, '2025-04-01', 'DIVIDEND', '0.02', '20', '0', 'MANUAL'),
+                    (3, 1, '2025-05-02', 'DIVIDEND', '0.03', '30', '0', 'MANUAL'),
+                    (4, 1, '2025-08-01', 'DIVIDEND', '0.03', '30', '0', 'MANUAL');",
+        )
+        .unwrap();
+        let d = |m, day| NaiveDate::from_ymd_opt(2025, m, day).unwrap();
+        let credit = |date, event_type: &IncomeEventType, amount| {
+            record_credit(
+                &conn,
+                &NewCredit {
+                    asset_id: 1,
+                    date,
+                    movement_type: "Dividendo",
+                    event_type,
+                    amount,
+                    broker_id: None,
+                },
+            )
+            .unwrap()
+        };
+
+        // JCP credited net of IRRF, one business day late
+        let jcp = credit(d(3, 4), &IncomeEventType::Jcp, dec!(85)).unwrap();
+        assert_eq!(matching_event(&conn, jcp).unwrap(), Some(1));
+        let short = credit(d(4, 1), &IncomeEventType::Dividend, dec!(19)).unwrap();
+        credit(d(6, 2), &IncomeEventType::Dividend, dec!(7)).unwrap();
+        assert!(credit(d(6, 2), &IncomeEventType::Dividend, dec!(7)).is_none());
+        assert_eq!(link_credits(&conn).unwrap(), 2);
+        assert_eq!(matching_event(&conn, short).unwrap(), None);
+
+        let lines = reconcile(&conn, Some(2025)).unwrap();
+        let status: Vec<_> = lines.iter().map(|l| l.status).collect();
+        // August falls after the last statement
+        assert_eq!(
+            status,
+            vec![
+                ReconciliationStatus::Matched,
+                ReconciliationStatus::AmountDiffers,
+                ReconciliationStatus::Uncredited,
+                ReconciliationStatus::UnmatchedCredit,
+            ]
+        );
+        assert_eq!(lines[1].difference, dec!(-1));
+
+        assert_eq!(sync_inconsistencies(&conn).unwrap(), (2, 0));
+        assert_eq!(sync_inconsistencies(&conn).unwrap(), (0, 0));
+
+        // Recording the stray credit as income closes its issue
+        let stray = lines[3].cash_credit_id.unwrap();
+        record_credit_as_income(&conn, stray).unwrap();
+        assert_eq!(sync_inconsistencies(&conn).unwrap(), (0, 1));
+    }
+}
@@ -0,0 +1,171 @@
+//! Realized income yields of the current holdings.
+//!
+//! The dividends and JCP paid per share over the trailing 12 months are
+//! divided by the current price (LTM yield) and by the average cost of the
+//! position (yield on cost). Amortizations return capital and are left out.
+//! Events recorded only with the total credited are turned into an amount
+//! per share with the position held on the ex-date.
+
+use anyhow::Result;
+use chrono::{Months, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::{self, IncomeEventType};
+use crate::reports::income_forecast::HeldOn;
+use crate::reports::PortfolioReport;
+
+/// Trailing-12-month income of one held asset
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IncomeYield {
+    pub ticker: String,
+    /// Dividends and JCP paid per share in the 12 months up to the report date
+    pub ltm_per_share: Decimal,
+    /// On the current price, in %; None without a price
+    pub ltm_yield_pct: Option<Decimal>,
+    /// On the average cost, in %; None without a cost
+    pub yield_on_cost_pct: Option<Decimal>,
+}
+
+fn pct(per_share: Decimal, base: Decimal) -> Option<Decimal> {
+    (base > Decimal::ZERO).then(|| (per_share / base * Decimal::from(100)).round_dp(2))
+}
+
+/// Yields of the positions in `report`, keyed by ticker, for the 12 months
+/// ending on `as_of`
+pub fn ltm_yields(
+    conn: &Connection,
+    report: &PortfolioReport,
+    as_of: NaiveDate,
+) -> Result<HashMap<String, IncomeYield>> {
+    let year_ago = as_of.checked_sub_months(Months::new(12)).unwrap_or(as_of);
+    let mut per_share: HashMap<i64, Decimal> = HashMap::new();
+    let mut held_on = HeldOn::new(conn);
+    for (event, _) in
+        db::get_income_events_with_assets(conn, year_ago.succ_opt(), Some(as_of), None)?
+    {
+        if event.event_type == IncomeEventType::Amortization {
+            continue;
+        }
+        if let Some(amount) = held_on.per_share(&event)? {
+            *per_share.entry(event.asset_id).or_default() += amount;
+        }
+    }
+
+    Ok(report
+        .positions
+        .iter()
+        .filter(|p| p.quantity > Decimal::ZERO)
+        .filter_map(|p| {
+            let ltm_per_share = per_share.get(&p.asset.id?).copied()?;
+            Some((
+                p.asset.ticker.clone(),
+                IncomeYield {
+                    ticker: p.asset.ticker.clone(),
+                    ltm_per_share,
+                    ltm_yield_pct: p.current_price.and_then(|price| pct(ltm_per_share, price)),
+                    yield_on_cost_pct: pct(ltm_per_share, p.average_cost),
+                },
+            ))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{AssetType, IncomeEvent, Transaction, TransactionType};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_ltm_yield_and_yield_on_cost() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+        let fii = db::insert_asset(&conn, "XPLG11", &AssetType::Fii, None).unwrap();
+        db::insert_transaction(
+            &conn,
+            &Transaction {
+                id: None,
+                asset_id: fii,
+                transaction_type: TransactionType::Buy,
+                trade_date: d(2023, 1, 10),
+                settlement_date: None,
+                quantity: dec!(100),
+                price_per_unit: dec!(100),
+                total_cost: dec!(10000),
+                fees: Decimal::ZERO,
+                is_day_trade: false,
+                quota_issuance_date: None,
+                notes: None,
+                source: "TEST".to_string(),
+                created_at: chrono::Utc::now(),
+            },
+        )
+        .unwrap();
+        let income = |date, event_type, per_quota, total| {
+            db::insert_income_event(
+                &conn,
+                &IncomeEvent {
+                    id: None,
+                    asset_id: fii,
+                    event_date: date,
+                    ex_date: None,
+                    event_type,
+                    amount_per_quota: per_quota,
+                    total_amount: total,
+                    withholding_tax: Decimal::ZERO,
+                    foreign_tax_withheld: None,
+                    is_quota_pre_2026: None,
+                    source: "TEST".to_string(),
+                    notes: None,
+                    created_at: chrono::Utc::now(),
+                },
+            )
+            .unwrap();
+        };
+        // Older than 12 months
+        income(
+            d(2023, 5, 14),
+            IncomeEventType::Dividend,
+            dec!(5),
+            dec!(500),
+        );
+        income(
+            d(2024, 1, 15),
+            IncomeEventType::Dividend,
+            dec!(4),
+            dec!(400),
+        );
+        // Only the total credited: 500 over the 100 quotas held
+        income(
+            d(2024, 4, 15),
+            IncomeEventType::Dividend,
+            Decimal::ZERO,
+            dec!(500),
+        );
+        // Capital returned, not income
+        income(
+            d(2024, 5, 15),
+            IncomeEventType::Amortization,
+            dec!(20),
+            dec!(2000),
+        );
+
+        let mut report = crate::reports::calculate_portfolio(&conn, None).unwrap();
+        report.positions[0].current_price = Some(dec!(75));
+        let yields = ltm_yields(&conn, &report, d(2024, 6, 1)).unwrap();
+        let xplg = &yields["XPLG11"];
+        assert_eq!(xplg.ltm_per_share, dec!(9));
+        assert_eq!(xplg.ltm_yield_pct, Some(dec!(12)));
+        // The amortization brought the average cost down to 80
+        assert_eq!(xplg.yield_on_cost_pct, Some(dec!(11.25)));
+
+        report.positions[0].current_price = None;
+        let yields = ltm_yields(&conn, &report, d(2024, 6, 1)).unwrap();
+        assert_eq!(yields["XPLG11"].ltm_yield_pct, None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Realized outcome of trade idea journal entries.
+//!
+//! Linked SELL transactions are matched against the asset's average cost by
+//! the same replay used for swing trade taxes, so the journal never keeps its
+//! own copy of P&L numbers.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::db::{self, JournalEntry, TransactionType};
+use crate::tax::swing_trade::realized_sale;
+
+/// What happened to a journal entry's linked transactions
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct JournalOutcome {
+    pub linked_transactions: usize,
+    pub bought_quantity: Decimal,
+    pub bought_total: Decimal,
+    pub sold_quantity: Decimal,
+    pub sale_total: Decimal,
+    pub cost_basis: Decimal,
+    pub realized_pl: Decimal,
+    pub realized_pl_pct: Option<Decimal>,
+    pub avg_sale_price: Option<Decimal>,
+    /// Whether the average sale price reached the entry's target price
+    pub target_reached: Option<bool>,
+}
+
+/// Compute the realized outcome of a journal entry from its linked transactions
+pub fn entry_outcome(conn: &Connection, entry: &JournalEntry) -> Result<JournalOutcome> {
+    let entry_id = entry
+        .id
+        .ok_or_else(|| anyhow::anyhow!("Journal entry has no id"))?;
+    let transactions = db::get_journal_transactions(conn, entry_id)?;
+
+    let mut outcome = JournalOutcome {
+        linked_transactions: transactions.len(),
+        ..Default::default()
+    };
+
+    for tx in &transactions {
+        match tx.transaction_type {
+            TransactionType::Buy => {
+                outcome.bought_quantity += tx.quantity;
+                outcome.bought_total += tx.total_cost;
+            }
+            TransactionType::Sell => {
+                let sale = realized_sale(conn, tx)?.ok_or_else(|| {
+                    anyhow::anyhow!("Could not match sale {:?} against its position", tx.id)
+                })?;
+                outcome.sold_quantity += sale.quantity;
+                outcome.sale_total += sale.sale_total;
+                outcome.cost_basis += sale.cost_basis;
+                outcome.realized_pl += sale.profit_loss;
+            }
+        }
+    }
+
+    if outcome.cost_basis > Decimal::ZERO {
+        outcome.realized_pl_pct =
+            Some((outcome.realized_pl / outcome.cost_basis * Decimal::from(100)).round_dp(2));
+    }
+    if outcome.sold_quantity > Decimal::ZERO {
+        let avg = outcome.sale_total / outcome.sold_quantity;
+        outcome.avg_sale_price = Some(avg.round_dp(4));
+        outcome.target_reached = entry.target_price.map(|target| avg >= target);
+    }
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('VALE3', 'STOCK')",
+            [],
+        )
+        .unwrap();
+        conn
+    }
+
+    fn insert_trade(conn: &Connection, tx_type: &str, date: &str, qty: &str, total: &str) -> i64 {
+        conn.execute(
+            "INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                price_per_unit, total_cost, fees, is_day_trade, source)
+             VALUES (1, ?1, ?2, ?3, '0', ?4, '0', 0, 'TEST')",
+            rusqlite::params![tx_type, date, qty, total],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    #[test]
+    fn test_entry_outcome_uses_position_average_cost() {
+        let conn = setup();
+        // Earlier lot outside the idea still drives the average cost
+        insert_trade(&conn, "BUY", "2024-01-10", "100", "5000");
+        let buy = insert_trade(&conn, "BUY", "2024-03-10", "100", "7000");
+        let sell = insert_trade(&conn, "SELL", "2024-06-10", "100", "8000");
+
+        let entry = JournalEntry {
+            id: None,
+            asset_id: 1,
+            entry_date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            thesis: "Iron ore rebound".to_string(),
+            target_price: Some(Decimal::from(75)),
+            created_at: chrono::Utc::now(),
+        };
+        let entry_id = db::insert_journal_entry(&conn, &entry).unwrap();
+        let entry = db::get_journal_entry(&conn, entry_id).unwrap().unwrap();
+
+        let empty = entry_outcome(&conn, &entry).unwrap();
+        assert_eq!(empty.linked_transactions, 0);
+        assert_eq!(empty.realized_pl, Decimal::ZERO);
+        assert_eq!(empty.target_reached, None);
+
+        assert!(db::link_journal_transaction(&conn, entry_id, buy).unwrap());
+        assert!(db::link_journal_transaction(&conn, entry_id, sell).unwrap());
+        assert!(!db::link_journal_transaction(&conn, entry_id, sell).unwrap());
+
+        let outcome = entry_outcome(&conn, &entry).unwrap();
+        assert_eq!(outcome.linked_transactions, 2);
+        assert_eq!(outcome.bought_quantity, Decimal::from(100));
+        assert_eq!(outcome.sold_quantity, Decimal::from(100));
+        assert_eq!(outcome.cost_basis, Decimal::from(6000));
+        assert_eq!(outcome.realized_pl, Decimal::from(2000));
+        assert_eq!(outcome.target_reached, Some(true));
+    }
+}
@@ -1,10 +1,30 @@
 // Reports module - Portfolio and tax report generators
 
+pub mod annual;
+pub mod benchmark;
+pub mod broker_statement;
 pub mod cashflow;
+pub mod columns;
+pub mod compare;
+pub mod exposure;
+pub mod fii_discount;
+pub mod fx_attribution;
+pub mod history;
+pub mod income_forecast;
+pub mod income_reconciliation;
+pub mod income_yield;
+pub mod journal;
+pub mod pdf;
 pub mod performance;
 pub mod portfolio;
+pub mod position_discrepancies;
+pub mod rebalance;
+pub mod recalculate;
+pub mod risk;
+pub mod snapshots;
+pub mod twr;
+pub mod whatsnew;
+pub mod xirr;
 
 pub use performance::{calculate_performance, Period};
-pub use portfolio::{
-    calculate_portfolio, calculate_portfolio_at_date, invalidate_snapshots_after, PortfolioReport,
-};
+pub use portfolio::{calculate_portfolio, calculate_portfolio_at_date, PortfolioReport};
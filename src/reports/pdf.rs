@@ -0,0 +1,250 @@
+//! Minimal PDF writer for shareable reports: A4 pages with text in the
+//! standard Helvetica fonts, lines and filled rectangles. Coordinates are in
+//! points from the bottom-left corner, as in PDF itself.
+
+use anyhow::Result;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream};
+
+pub const PAGE_WIDTH: f32 = 595.0;
+pub const PAGE_HEIGHT: f32 = 842.0;
+pub const MARGIN: f32 = 50.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color(pub f32, pub f32, pub f32);
+
+impl Color {
+    pub const TEXT: Color = Color(0.13, 0.13, 0.13);
+    pub const MUTED: Color = Color(0.45, 0.45, 0.45);
+    pub const RULE: Color = Color(0.82, 0.82, 0.82);
+    pub const PANEL: Color = Color(0.95, 0.96, 0.97);
+    pub const ACCENT: Color = Color(0.12, 0.35, 0.60);
+    pub const GAIN: Color = Color(0.13, 0.55, 0.30);
+    pub const LOSS: Color = Color(0.75, 0.20, 0.18);
+}
+
+/// Helvetica advance width of a character, in thousandths of the font size
+fn char_width(c: char) -> u16 {
+    const ASCII: [u16; 95] = [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+        278, // ' '../
+        556, 556, 556, 556, 556, 556, 556, 556, 556, 556, // 0..9
+        278, 278, 584, 584, 584, 556, 1015, // :..@
+        667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, // A..M
+        722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, // N..Z
+        278, 278, 278, 469, 556, 333, // [..`
+        556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, // a..m
+        556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, // n..z
+        334, 260, 334, 584, // {..~
+    ];
+    match c {
+        ' '..='~' => ASCII[c as usize - 32],
+        'À'..='Å' => 667,
+        'Ç' => 722,
+        'È'..='Ë' => 667,
+        'Ì'..='Ï' => 278,
+        'Ò'..='Ö' => 778,
+        'Ù'..='Ü' => 722,
+        'à'..='å' | 'è'..='ë' | 'ò'..='ö' | 'ù'..='ü' | 'ñ' => 556,
+        'ç' => 500,
+        'ì'..='ï' => 278,
+        '·' | '•' => 350,
+        _ => 556,
+    }
+}
+
+/// Width of `text` set at `size` points
+pub fn text_width(text: &str, size: f32) -> f32 {
+    text.chars().map(|c| char_width(c) as f32).sum::<f32>() * size / 1000.0
+}
+
+/// Encode text for the standard fonts' WinAnsiEncoding
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '€' => 0x80,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Drawing operations of one page
+#[derive(Debug, Default)]
+pub struct Page {
+    ops: Vec<Operation>,
+}
+
+impl Page {
+    fn fill(&mut self, color: Color) {
+        self.ops.push(Operation::new(
+            "rg",
+            vec![color.0.into(), color.1.into(), color.2.into()],
+        ));
+    }
+
+    pub fn text(&mut self, x: f32, y: f32, size: f32, bold: bool, color: Color, text: &str) {
+        self.fill(color);
+        self.ops.push(Operation::new("BT", vec![]));
+        self.ops.push(Operation::new(
+            "Tf",
+            vec![if bold { "F2" } else { "F1" }.into(), size.into()],
+        ));
+        self.ops
+            .push(Operation::new("Td", vec![x.into(), y.into()]));
+        self.ops.push(Operation::new(
+            "Tj",
+            vec![Object::string_literal(win_ansi(text))],
+        ));
+        self.ops.push(Operation::new("ET", vec![]));
+    }
+
+    /// Text ending at `right`
+    pub fn text_right(
+        &mut self,
+        right: f32,
+        y: f32,
+        size: f32,
+        bold: bool,
+        color: Color,
+        text: &str,
+    ) {
+        self.text(right - text_width(text, size), y, size, bold, color, text);
+    }
+
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        self.fill(color);
+        self.ops.push(Operation::new(
+            "re",
+            vec![x.into(), y.into(), width.into(), height.into()],
+        ));
+        self.ops.push(Operation::new("f", vec![]));
+    }
+
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, color: Color) {
+        self.ops.push(Operation::new(
+            "RG",
+            vec![color.0.into(), color.1.into(), color.2.into()],
+        ));
+        self.ops.push(Operation::new("w", vec![width.into()]));
+        self.ops
+            .push(Operation::new("m", vec![from.0.into(), from.1.into()]));
+        self.ops
+            .push(Operation::new("l", vec![to.0.into(), to.1.into()]));
+        self.ops.push(Operation::new("S", vec![]));
+    }
+}
+
+/// A4 document whose pages get a "title · page N of M" footer
+pub struct PdfDocument {
+    title: String,
+    pages: Vec<Page>,
+}
+
+impl PdfDocument {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            pages: Vec::new(),
+        }
+    }
+
+    pub fn add_page(&mut self) -> &mut Page {
+        self.pages.push(Page::default());
+        self.pages.last_mut().expect("page just added")
+    }
+
+    /// Last page, adding one to an empty document
+    pub fn current_page(&mut self) -> &mut Page {
+        if self.pages.is_empty() {
+            self.add_page();
+        }
+        self.pages.last_mut().expect("document has a page")
+    }
+
+    pub fn into_bytes(mut self) -> Result<Vec<u8>> {
+        let count = self.pages.len();
+        for (i, page) in self.pages.iter_mut().enumerate() {
+            page.line(
+                (MARGIN, 40.0),
+                (PAGE_WIDTH - MARGIN, 40.0),
+                0.5,
+                Color::RULE,
+            );
+            page.text(MARGIN, 28.0, 8.0, false, Color::MUTED, &self.title);
+            page.text_right(
+                PAGE_WIDTH - MARGIN,
+                28.0,
+                8.0,
+                false,
+                Color::MUTED,
+                &format!("Page {} of {}", i + 1, count),
+            );
+        }
+
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let font = |name: &str| {
+            dictionary! {
+                "Type" => "Font",
+                "Subtype" => "Type1",
+                "BaseFont" => name.to_string(),
+                "Encoding" => "WinAnsiEncoding",
+            }
+        };
+        let regular_id = doc.add_object(font("Helvetica"));
+        let bold_id = doc.add_object(font("Helvetica-Bold"));
+        let resources_id = doc.add_object(dictionary! {
+            "Font" => dictionary! {
+                "F1" => regular_id,
+                "F2" => bold_id,
+            },
+        });
+
+        let mut kids = Vec::new();
+        for page in self.pages {
+            let content = Content {
+                operations: page.ops,
+            };
+            let content_id = doc.add_object(Stream::new(dictionary! {}, content.encode()?));
+            kids.push(Object::from(doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "Contents" => content_id,
+            })));
+        }
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => count as i64,
+                "Resources" => resources_id,
+                "MediaBox" => vec![0.into(), 0.into(), PAGE_WIDTH.into(), PAGE_HEIGHT.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        let info_id = doc.add_object(dictionary! {
+            "Title" => Object::string_literal(win_ansi(&self.title)),
+            "Producer" => Object::string_literal("interest"),
+        });
+        doc.trailer.set("Root", catalog_id);
+        doc.trailer.set("Info", info_id);
+        doc.compress();
+
+        let mut bytes = Vec::new();
+        doc.save_to(&mut bytes)?;
+        Ok(bytes)
+    }
+}
@@ -2,12 +2,13 @@ use anyhow::Result;
 use chrono::{Datelike, Local, NaiveDate};
 use rusqlite::Connection;
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 
-use crate::db::{self, AssetType};
-use crate::reports::portfolio::{
-    calculate_portfolio_at_date, get_valid_snapshot, save_portfolio_snapshot, PositionSummary,
-};
+use crate::db::{self, AssetType, PriceSeries};
+use crate::reports::portfolio::{retain_assets, PositionSummary};
+use crate::reports::snapshots;
+use crate::reports::xirr::{period_xirr, AssetXirr};
 
 #[derive(Debug, Clone)]
 pub struct PerformanceReport {
@@ -19,10 +20,14 @@ pub struct PerformanceReport {
     pub end_value: Decimal,
     pub total_return: Decimal,         // Absolute return (end - start)
     pub time_weighted_return: Decimal, // Percentage return
-    pub realized_gains: Decimal,       // Placeholder (0 until realized_gains populated)
+    pub realized_gains: Decimal,       // From the realized_gains view (whole portfolio only)
     pub unrealized_gains: Decimal,     // From snapshot end unrealized sum
     pub asset_breakdown: HashMap<AssetType, AssetPerformance>,
     pub cash_flows: Option<CashFlowSummary>, // Cash flow summary if available
+    pub money_weighted_return: Option<Decimal>, // Annualized XIRR percentage
+    pub asset_xirr: Vec<AssetXirr>,
+    /// Assets held at the end valued with a price older than the period end
+    pub stale_prices: Vec<StalePrice>,
 }
 
 impl PerformanceReport {
@@ -131,30 +136,92 @@ pub fn get_period_dates(
     Ok((start, end))
 }
 
-/// Ensure a valid snapshot exists for the given date; create it if missing/stale.
-fn ensure_snapshot(conn: &mut Connection, date: NaiveDate) -> Result<()> {
-    if get_valid_snapshot(conn, date)?.is_none() {
-        // Compute snapshot using as-of portfolio and persist
-        save_portfolio_snapshot(conn, date, None)?;
+/// Calendar days data may trail the period end before it counts as stale
+/// (a weekend plus a holiday)
+pub const STALE_AFTER_DAYS: i64 = 4;
+
+/// Whether data dated `data_date` is too old to value `end_date`. Periods
+/// ending in the future are checked against today.
+pub fn is_stale(data_date: NaiveDate, end_date: NaiveDate) -> bool {
+    let end_date = end_date.min(Local::now().date_naive());
+    (end_date - data_date).num_days() > STALE_AFTER_DAYS
+}
+
+/// An asset held at the period end valued with an older price
+#[derive(Debug, Clone, Serialize)]
+pub struct StalePrice {
+    pub ticker: String,
+    /// Date of the price used; None when the position is valued at cost
+    pub price_date: Option<NaiveDate>,
+}
+
+/// Positions held on `end_date` whose latest price is stale
+fn stale_prices(
+    conn: &Connection,
+    end_date: NaiveDate,
+    positions: &[PositionSummary],
+) -> Result<Vec<StalePrice>> {
+    let mut stale = Vec::new();
+    for position in positions {
+        let Some(asset_id) = position.asset.id else {
+            continue;
+        };
+        if position.quantity <= Decimal::ZERO {
+            continue;
+        }
+        let price_date =
+            db::get_price_on_or_before(conn, asset_id, end_date)?.map(|p| p.price_date);
+        if price_date.is_none_or(|date| is_stale(date, end_date)) {
+            stale.push(StalePrice {
+                ticker: position.asset.ticker.clone(),
+                price_date,
+            });
+        }
     }
-    Ok(())
+    stale.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+    Ok(stale)
 }
 
-pub fn calculate_performance(conn: &mut Connection, period: Period) -> Result<PerformanceReport> {
-    let (start_date, end_date) = get_period_dates(period.clone(), Some(conn))?;
+/// Net result of the sales in the months from `start_date` through
+/// `end_date`, as of the last tax computation (the ledger is monthly)
+fn realized_gains_between(
+    conn: &Connection,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Result<Decimal> {
+    let total = conn.query_row(
+        "SELECT COALESCE(SUM(profit_loss), 0) FROM realized_gains
+         WHERE month BETWEEN ?1 AND ?2",
+        rusqlite::params![
+            start_date.format("%Y-%m").to_string(),
+            end_date.format("%Y-%m").to_string()
+        ],
+        |row| db::get_decimal_value(row, 0),
+    )?;
+    Ok(total.round_dp(2))
+}
 
-    // Ensure snapshots exist
-    ensure_snapshot(conn, start_date)?;
-    ensure_snapshot(conn, end_date)?;
+/// Performance of the whole portfolio, or of a subset of assets (e.g. a tag
+/// group) when `asset_ids` is given.
+///
+/// Snapshots, cash flows and daily values are all restricted to the subset, so
+/// buying or selling assets outside the group never shows up as its return.
+pub fn calculate_performance(
+    conn: &mut Connection,
+    period: Period,
+    asset_ids: Option<&HashSet<i64>>,
+) -> Result<PerformanceReport> {
+    let (start_date, end_date) = get_period_dates(period.clone(), Some(conn))?;
 
-    // Load snapshots, with graceful fallback to on-the-fly portfolio calculation
-    let start_snapshot = match get_valid_snapshot(conn, start_date)? {
-        Some(s) => s,
-        None => calculate_portfolio_at_date(conn, start_date, None)?,
-    };
-    let end_snapshot = match get_valid_snapshot(conn, end_date)? {
-        Some(s) => s,
-        None => calculate_portfolio_at_date(conn, end_date, None)?,
+    // Valuation snapshots, valued and stored when missing or stale
+    let start_snapshot = snapshots::report(conn, start_date)?;
+    let end_snapshot = snapshots::report(conn, end_date)?;
+    let (start_snapshot, end_snapshot) = match asset_ids {
+        Some(ids) => (
+            retain_assets(start_snapshot, ids),
+            retain_assets(end_snapshot, ids),
+        ),
+        None => (start_snapshot, end_snapshot),
     };
 
     // Aggregate values
@@ -163,7 +230,7 @@ pub fn calculate_performance(conn: &mut Connection, period: Period) -> Result<Pe
     let total_return = end_value - start_value; // Absolute return in currency
 
     // Extract cash flows in period
-    let cash_flows = extract_cash_flows(conn, start_date, end_date)?;
+    let cash_flows = extract_cash_flows(conn, start_date, end_date, asset_ids)?;
     let cash_flow_summary = if !cash_flows.is_empty() {
         Some(summarize_cash_flows(&cash_flows))
     } else {
@@ -174,9 +241,21 @@ pub fn calculate_performance(conn: &mut Connection, period: Period) -> Result<Pe
     // If we have cash flows, use proper TWR calculation
     // Otherwise fall back to simple percentage return
     let twr = if !cash_flows.is_empty() {
-        // Load daily snapshots for sub-period calculations
-        let snapshots = load_daily_snapshots(conn, start_date, end_date)?;
-        calculate_time_weighted_return(start_value, end_value, &cash_flows, &snapshots)?
+        // Value on each flow date before that day's flows
+        let mut net_flows: HashMap<NaiveDate, Decimal> = HashMap::new();
+        for flow in &cash_flows {
+            *net_flows.entry(flow.date).or_default() += match flow.flow_type {
+                FlowType::Contribution => flow.amount,
+                FlowType::Withdrawal => -flow.amount,
+            };
+        }
+        let flow_dates: Vec<NaiveDate> = net_flows.keys().copied().collect();
+        let before_flows: HashMap<NaiveDate, Decimal> =
+            snapshots::day_values(conn, &flow_dates, asset_ids)?
+                .into_iter()
+                .map(|day| (day.date, day.market_value - net_flows[&day.date]))
+                .collect();
+        calculate_time_weighted_return(start_value, end_value, &cash_flows, &before_flows)?
     } else {
         // Simple percentage return when no cash flows
         if start_value > Decimal::ZERO {
@@ -200,8 +279,23 @@ pub fn calculate_performance(conn: &mut Connection, period: Period) -> Result<Pe
         })
         .fold(Decimal::ZERO, |acc, x| acc + x);
 
-    // Realized gains: 0 until realized_gains table is populated by sell processing
-    let realized_gains = Decimal::ZERO;
+    // The tax ledger behind realized gains covers every asset and portfolio
+    let realized_gains = if asset_ids.is_none() && !db::portfolio::is_scoped() {
+        realized_gains_between(conn, start_date, end_date)?
+    } else {
+        Decimal::ZERO
+    };
+
+    let (money_weighted_return, asset_xirr) = period_xirr(
+        conn,
+        start_date,
+        end_date,
+        &start_snapshot.positions,
+        &end_snapshot.positions,
+        asset_ids,
+    )?;
+
+    let stale_prices = stale_prices(conn, end_date, &end_snapshot.positions)?;
 
     // Asset breakdown
     let breakdown = build_asset_breakdown(
@@ -222,6 +316,9 @@ pub fn calculate_performance(conn: &mut Connection, period: Period) -> Result<Pe
         unrealized_gains: unrealized_sum,
         asset_breakdown: breakdown,
         cash_flows: cash_flow_summary,
+        money_weighted_return,
+        asset_xirr,
+        stale_prices,
     })
 }
 
@@ -276,13 +373,17 @@ pub fn extract_cash_flows(
     conn: &Connection,
     from_date: NaiveDate,
     to_date: NaiveDate,
+    asset_ids: Option<&HashSet<i64>>,
 ) -> Result<Vec<CashFlow>> {
+    let in_scope = |asset_id: i64| asset_ids.is_none_or(|ids| ids.contains(&asset_id));
+
     let mut stmt = conn.prepare(
         "SELECT COALESCE(settlement_date, trade_date) as flow_date,
                 transaction_type,
                 quantity,
                 price_per_unit,
-                fees
+                fees,
+                asset_id
          FROM transactions
          WHERE COALESCE(settlement_date, trade_date) >= ?1
            AND COALESCE(settlement_date, trade_date) <= ?2
@@ -298,6 +399,10 @@ pub fn extract_cash_flows(
             let quantity = db::get_decimal_value(row, 2)?;
             let price = db::get_decimal_value(row, 3)?;
             let fees = db::get_optional_decimal_value(row, 4)?.unwrap_or(Decimal::ZERO);
+            let asset_id: i64 = row.get(5)?;
+            if !in_scope(asset_id) {
+                return Ok(None);
+            }
 
             let gross = quantity * price;
             let amount = match tx_type.as_str() {
@@ -326,6 +431,9 @@ pub fn extract_cash_flows(
     let income_events =
         db::get_income_events_with_assets(conn, Some(from_date), Some(to_date), None)?;
     for (event, _asset) in income_events {
+        if !in_scope(event.asset_id) {
+            continue;
+        }
         let net_income = event.total_amount - event.withholding_tax;
         flows.push(CashFlow {
             date: event.event_date,
@@ -357,41 +465,6 @@ pub fn summarize_cash_flows(flows: &[CashFlow]) -> CashFlowSummary {
     }
 }
 
-/// Load daily portfolio values from snapshots table
-pub fn load_daily_snapshots(
-    conn: &Connection,
-    from_date: NaiveDate,
-    to_date: NaiveDate,
-) -> Result<HashMap<NaiveDate, Decimal>> {
-    let mut stmt = conn.prepare(
-        "SELECT snapshot_date, SUM(market_value) as total_value
-         FROM position_snapshots
-         WHERE snapshot_date >= ?1 AND snapshot_date <= ?2
-         GROUP BY snapshot_date
-         ORDER BY snapshot_date",
-    )?;
-
-    let mut snapshots = HashMap::new();
-    let rows = stmt.query_map([from_date, to_date], |row| {
-        let date: NaiveDate = row.get(0)?;
-        let value_val: rusqlite::types::Value = row.get(1)?;
-        Ok((date, value_val))
-    })?;
-
-    for row in rows {
-        let (date, value_val) = row?;
-        let value = match value_val {
-            rusqlite::types::Value::Text(s) => s.parse::<Decimal>().unwrap_or(Decimal::ZERO),
-            rusqlite::types::Value::Real(f) => Decimal::try_from(f).unwrap_or(Decimal::ZERO),
-            rusqlite::types::Value::Integer(i) => Decimal::from(i),
-            _ => Decimal::ZERO,
-        };
-        snapshots.insert(date, value);
-    }
-
-    Ok(snapshots)
-}
-
 /// Calculate time-weighted return (TWR) accounting for cash flows
 ///
 /// TWR breaks the period into sub-periods at each cash flow date and chains
@@ -471,37 +544,39 @@ pub fn calculate_time_weighted_return(
     Ok(twr_pct)
 }
 
-#[allow(dead_code)] // Kept for Phase 6: Performance Tracking (see PERFORMANCE_TRACKING_PLAN.md)
-pub fn backfill_daily_snapshots(
-    conn: &mut Connection,
-    from_date: NaiveDate,
-    to_date: NaiveDate,
-    progress_callback: impl Fn(usize, usize),
-) -> Result<()> {
-    if from_date > to_date {
-        anyhow::bail!("from_date must be <= to_date");
-    }
-
-    let mut dates = Vec::new();
-    let mut d = from_date;
-    while d <= to_date {
-        dates.push(d);
-        d = d
-            .succ_opt()
-            .ok_or_else(|| anyhow::anyhow!("Failed to increment date"))?;
-    }
-
-    let total = dates.len();
-    for (idx, date) in dates.into_iter().enumerate() {
-        if get_valid_snapshot(conn, date)?.is_none() {
-            // Compute using historical portfolio as-of date and persist
-            let _ = calculate_portfolio_at_date(conn, date, None)?;
-            save_portfolio_snapshot(conn, date, None)?;
+/// Return (in %) of a single asset's stored prices between two dates.
+///
+/// `PriceSeries::Close` gives the price return; `PriceSeries::Adjusted` gives
+/// the total return including reinvested dividends, and requires an adjusted
+/// close on both ends. Portfolio valuation always uses raw closes since income
+/// and corporate actions are accounted for separately.
+pub fn asset_return(
+    conn: &Connection,
+    asset_id: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+    series: PriceSeries,
+) -> Result<Option<Decimal>> {
+    let start = db::get_price_on_or_before(conn, asset_id, from)?;
+    let end = db::get_price_on_or_before(conn, asset_id, to)?;
+    match (start, end) {
+        (Some(start), Some(end)) if start.price_date < end.price_date => {
+            // Mixing adjusted and raw ends would misstate the return
+            if series == PriceSeries::Adjusted
+                && (start.adjusted_close.is_none() || end.adjusted_close.is_none())
+            {
+                return Ok(None);
+            }
+            let start_price = start.price(series);
+            if start_price <= Decimal::ZERO {
+                return Ok(None);
+            }
+            Ok(Some(
+                (end.price(series) / start_price - Decimal::ONE) * Decimal::from(100),
+            ))
         }
-        progress_callback(idx + 1, total);
+        _ => Ok(None),
     }
-
-    Ok(())
 }
 
 #[cfg(test)]
@@ -555,6 +630,7 @@ mod tests {
             volume: Some(1_000),
             source: "TEST".to_string(),
             created_at: chrono::Utc::now(),
+            adjusted_close: None,
         };
         let end_price = crate::db::PriceHistory {
             id: None,
@@ -567,6 +643,7 @@ mod tests {
             volume: Some(1_000),
             source: "TEST".to_string(),
             created_at: chrono::Utc::now(),
+            adjusted_close: None,
         };
         db::insert_price_history(&conn, &start_price).unwrap();
         db::insert_price_history(&conn, &end_price).unwrap();
@@ -575,11 +652,27 @@ mod tests {
             from: NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
             to: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
         };
-        let report = calculate_performance(&mut conn, period).unwrap();
+        let report = calculate_performance(&mut conn, period, None).unwrap();
         assert_eq!(report.start_value, Decimal::from(120));
         assert_eq!(report.end_value, Decimal::from(150));
         assert!(report.total_return > Decimal::ZERO);
         assert!(report.unrealized_gains >= Decimal::ZERO);
+        // +25% in 29 days, annualized
+        assert!(report.money_weighted_return.unwrap() > Decimal::from(1000));
+        assert_eq!(report.asset_xirr.len(), 1);
+        assert!(report.stale_prices.is_empty());
+
+        // Three weeks past the last price the valuation is flagged
+        let period = Period::Custom {
+            from: NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            to: NaiveDate::from_ymd_opt(2024, 3, 22).unwrap(),
+        };
+        let report = calculate_performance(&mut conn, period, None).unwrap();
+        assert_eq!(report.stale_prices.len(), 1);
+        assert_eq!(
+            report.stale_prices[0].price_date,
+            NaiveDate::from_ymd_opt(2024, 3, 1)
+        );
     }
 
     #[test]
@@ -632,6 +725,7 @@ mod tests {
             &conn,
             NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(),
             NaiveDate::from_ymd_opt(2023, 12, 31).unwrap(),
+            None,
         )
         .unwrap();
         assert_eq!(flows.len(), 1); // only the regular buy counted
@@ -640,4 +734,92 @@ mod tests {
         assert_eq!(summary.total_contributions, Decimal::from(1000));
         assert_eq!(summary.total_withdrawals, Decimal::ZERO);
     }
+
+    #[test]
+    fn test_performance_restricted_to_tag_group() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('ITSA4', 'STOCK');
+             INSERT INTO assets (ticker, asset_type) VALUES ('MXRF11', 'FII');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+                 VALUES (1, 'BUY', '2024-01-02', '10', '10', '100', '0', 0, 'TEST');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+                 VALUES (2, 'BUY', '2024-01-02', '100', '10', '1000', '0', 0, 'TEST');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+                 VALUES (2, 'BUY', '2024-02-15', '100', '10', '1000', '0', 0, 'TEST');
+             INSERT INTO price_history (asset_id, price_date, close_price, source)
+                 VALUES (1, '2024-02-01', '10', 'TEST'), (1, '2024-03-01', '12', 'TEST'),
+                        (2, '2024-02-01', '10', 'TEST'), (2, '2024-03-01', '9', 'TEST');",
+        )
+        .unwrap();
+        db::add_asset_tag(&conn, 1, "Aposentadoria").unwrap();
+        let tagged = db::get_tagged_asset_ids(&conn, "aposentadoria").unwrap();
+
+        let period = Period::Custom {
+            from: NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            to: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+        };
+        let group = calculate_performance(&mut conn, period.clone(), Some(&tagged)).unwrap();
+        assert_eq!(group.start_value, Decimal::from(100));
+        assert_eq!(group.end_value, Decimal::from(120));
+        // The FII purchase outside the group is not a cash flow of the group
+        assert!(group.cash_flows.is_none());
+        assert_eq!(group.time_weighted_return, Decimal::from(20));
+
+        let whole = calculate_performance(&mut conn, period, None).unwrap();
+        assert_eq!(whole.start_value, Decimal::from(1100));
+        assert_eq!(whole.end_value, Decimal::from(1920));
+        assert!(whole.cash_flows.is_some());
+
+        assert!(db::get_tagged_asset_ids(&conn, "reserva").is_err());
+    }
+
+    #[test]
+    fn test_asset_return_by_series() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id = db::upsert_asset(&conn, "TEST6", &AssetType::Stock, None).unwrap();
+
+        let d1 = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        let d2 = NaiveDate::from_ymd_opt(2024, 12, 30).unwrap();
+        for (date, close) in [(d1, 100), (d2, 110)] {
+            conn.execute(
+                "INSERT INTO price_history (asset_id, price_date, close_price, source)
+                 VALUES (?1, ?2, ?3, 'TEST')",
+                rusqlite::params![asset_id, date, close.to_string()],
+            )
+            .unwrap();
+        }
+
+        let price = asset_return(&conn, asset_id, d1, d2, PriceSeries::Close).unwrap();
+        assert_eq!(price, Some(Decimal::from(10)));
+        // No adjusted series stored yet
+        assert_eq!(
+            asset_return(&conn, asset_id, d1, d2, PriceSeries::Adjusted).unwrap(),
+            None
+        );
+
+        // A dividend paid in between lowers the earlier adjusted close
+        let updated = db::set_adjusted_closes(
+            &conn,
+            asset_id,
+            &[(d1, Decimal::from(88)), (d2, Decimal::from(110))],
+        )
+        .unwrap();
+        assert_eq!(updated, 2);
+        let total = asset_return(&conn, asset_id, d1, d2, PriceSeries::Adjusted)
+            .unwrap()
+            .unwrap();
+        assert_eq!(total, Decimal::from(25));
+        assert_eq!(
+            asset_return(&conn, asset_id, d1, d2, PriceSeries::Close).unwrap(),
+            Some(Decimal::from(10))
+        );
+    }
 }
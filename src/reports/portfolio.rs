@@ -1,12 +1,13 @@
 use anyhow::Result;
-use blake3::Hasher;
 use chrono::NaiveDate;
 use rusqlite::Connection;
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
-use crate::db::{Asset, AssetType, Transaction, TransactionType};
+use crate::db::{Asset, AssetType, PriceSeries, Transaction, TransactionType};
+use crate::pricing::fx::{self, FxConversion};
+use crate::reports::snapshots::{self, Fingerprints};
 
 /// Summary of a single position
 #[derive(Debug, Clone)]
@@ -19,6 +20,10 @@ pub struct PositionSummary {
     pub current_value: Option<Decimal>,
     pub unrealized_pl: Option<Decimal>,
     pub unrealized_pl_pct: Option<Decimal>,
+    /// Date of the manual valuation used as the price (`assets set-value`)
+    pub valued_on: Option<NaiveDate>,
+    /// Rate the price was converted to reais at, for assets quoted abroad
+    pub fx: Option<FxConversion>,
 }
 
 /// Complete portfolio report
@@ -128,6 +133,32 @@ pub fn calculate_portfolio_at_date(
     calculate_portfolio_with_cutoff(conn, asset_type_filter, Some(as_of_date))
 }
 
+/// Restrict a portfolio report to a set of assets (e.g. a tag group), recomputing totals
+pub fn retain_assets(report: PortfolioReport, asset_ids: &HashSet<i64>) -> PortfolioReport {
+    let positions: Vec<PositionSummary> = report
+        .positions
+        .into_iter()
+        .filter(|p| p.asset.id.is_some_and(|id| asset_ids.contains(&id)))
+        .collect();
+
+    let total_cost: Decimal = positions.iter().map(|p| p.total_cost).sum();
+    let total_value: Decimal = positions.iter().filter_map(|p| p.current_value).sum();
+    let total_pl = total_value - total_cost;
+    let total_pl_pct = if total_cost > Decimal::ZERO {
+        (total_pl / total_cost) * Decimal::from(100)
+    } else {
+        Decimal::ZERO
+    };
+
+    PortfolioReport {
+        positions,
+        total_cost,
+        total_value,
+        total_pl,
+        total_pl_pct,
+    }
+}
+
 fn calculate_portfolio_with_cutoff(
     conn: &Connection,
     asset_type_filter: Option<&AssetType>,
@@ -178,173 +209,268 @@ fn calculate_portfolio_with_cutoff(
     let mut total_value = Decimal::ZERO;
 
     for asset in filtered_assets {
-        let asset_id = asset.id.unwrap();
-
-        // Get all transactions for this asset, ordered by date
-        let mut transactions = match as_of_date {
-            Some(cutoff) => get_asset_transactions_until(conn, asset_id, cutoff)?,
-            None => get_asset_transactions(conn, asset_id)?,
+        let Some((position, _)) = asset_position(conn, asset, &assets_by_id, as_of_date)? else {
+            continue;
         };
-
-        let renames = crate::db::get_asset_renames_as_target_up_to(conn, asset_id, as_of)?;
-        for rename in renames {
-            if let Some(source_asset) = assets_by_id.get(&rename.from_asset_id) {
-                if let Some(carryover) = build_rename_carryover_transaction(
-                    conn,
-                    source_asset,
-                    asset_id,
-                    rename.effective_date,
-                )? {
-                    transactions.push(carryover);
-                }
-            }
+        total_cost += position.total_cost;
+        if let Some(value) = position.current_value {
+            total_value += value;
         }
+        positions.push(position);
+    }
 
-        let exchanges = crate::db::get_asset_exchanges_as_target_up_to(conn, asset_id, as_of)?;
-        for exchange in exchanges {
-            if exchange.to_quantity <= Decimal::ZERO {
-                continue;
-            }
+    // Sort positions by total value (descending)
+    positions.sort_by(|a, b| {
+        let a_val = a.current_value.unwrap_or(a.total_cost);
+        let b_val = b.current_value.unwrap_or(b.total_cost);
+        b_val.cmp(&a_val)
+    });
 
-            let source_ticker = assets_by_id
-                .get(&exchange.from_asset_id)
-                .map(|a| a.ticker.as_str())
-                .unwrap_or("UNKNOWN");
-            let notes = match exchange.event_type {
-                crate::db::AssetExchangeType::Spinoff => {
-                    format!("Spin-off from {}", source_ticker)
-                }
-                crate::db::AssetExchangeType::Merger => {
-                    format!("Merger from {}", source_ticker)
-                }
-            };
+    let total_pl = total_value - total_cost;
+    let total_pl_pct = if total_cost > Decimal::ZERO {
+        (total_pl / total_cost) * Decimal::from(100)
+    } else {
+        Decimal::ZERO
+    };
 
-            let price_per_unit = if exchange.to_quantity > Decimal::ZERO {
-                exchange.allocated_cost / exchange.to_quantity
-            } else {
-                Decimal::ZERO
-            };
+    Ok(PortfolioReport {
+        positions,
+        total_cost,
+        total_value,
+        total_pl,
+        total_pl_pct,
+    })
+}
 
-            transactions.push(Transaction {
-                id: None,
-                asset_id,
-                transaction_type: TransactionType::Buy,
-                trade_date: exchange.effective_date,
-                settlement_date: Some(exchange.effective_date),
-                quantity: exchange.to_quantity,
-                price_per_unit,
-                total_cost: exchange.allocated_cost,
-                fees: Decimal::ZERO,
-                is_day_trade: false,
-                quota_issuance_date: None,
-                notes: Some(notes),
-                source: "EXCHANGE".to_string(),
-                created_at: chrono::Utc::now(),
-            });
-        }
+/// Cost basis of a position after each change, in date order
+pub type CostHistory = Vec<(NaiveDate, Decimal)>;
 
-        transactions.sort_by(|a, b| (a.trade_date, a.id).cmp(&(b.trade_date, b.id)));
+/// Current position of one asset, with the cost basis history it was built
+/// from. `None` when nothing is held.
+pub fn calculate_position(
+    conn: &Connection,
+    asset: &Asset,
+) -> Result<Option<(PositionSummary, CostHistory)>> {
+    let assets_by_id: HashMap<i64, Asset> = crate::db::get_all_assets(conn)?
+        .into_iter()
+        .filter_map(|a| a.id.map(|id| (id, a)))
+        .collect();
+    asset_position(conn, asset.clone(), &assets_by_id, None)
+}
 
-        // Calculate average-cost position
-        let mut position = AvgCostPosition::new(asset_id);
+/// Average-cost position of one asset as of `as_of_date` (today when None)
+fn asset_position(
+    conn: &Connection,
+    asset: Asset,
+    assets_by_id: &HashMap<i64, Asset>,
+    as_of_date: Option<NaiveDate>,
+) -> Result<Option<(PositionSummary, CostHistory)>> {
+    let Some(asset_id) = asset.id else {
+        return Ok(None);
+    };
+    let as_of = as_of_date.unwrap_or_else(|| chrono::Local::now().date_naive());
 
-        // Apply fixed split adjustments forward-only, at the time they occur
-        let amortizations =
-            crate::db::get_amortizations_for_asset(conn, asset_id, None, Some(as_of))?;
-        let mut amort_idx = 0usize;
-        let exchanges_as_source =
-            crate::db::get_asset_exchanges_as_source_up_to(conn, asset_id, as_of)?;
-        let mut exchange_idx = 0usize;
-        let actions = crate::corporate_actions::get_actions_up_to(conn, asset_id, as_of)?;
+    // Get all transactions for this asset, ordered by date
+    let mut transactions = match as_of_date {
+        Some(cutoff) => get_asset_transactions_until(conn, asset_id, cutoff)?,
+        None => get_asset_transactions(conn, asset_id)?,
+    };
 
-        let mut action_idx = 0usize;
-        for tx in transactions {
-            while amort_idx < amortizations.len()
-                && amortizations[amort_idx].event_date <= tx.trade_date
-            {
-                position.apply_amortization(amortizations[amort_idx].total_amount);
-                amort_idx += 1;
+    let renames = crate::db::get_asset_renames_as_target_up_to(conn, asset_id, as_of)?;
+    for rename in renames {
+        if let Some(source_asset) = assets_by_id.get(&rename.from_asset_id) {
+            if let Some(carryover) = build_rename_carryover_transaction(
+                conn,
+                source_asset,
+                asset_id,
+                rename.effective_date,
+            )? {
+                transactions.push(carryover);
             }
+        }
+    }
+
+    let exchanges = crate::db::get_asset_exchanges_as_target_up_to(conn, asset_id, as_of)?;
+    for exchange in exchanges {
+        if exchange.to_quantity <= Decimal::ZERO {
+            continue;
+        }
 
-            while exchange_idx < exchanges_as_source.len()
-                && exchanges_as_source[exchange_idx].effective_date <= tx.trade_date
-            {
-                apply_exchange_source_effect(&mut position, &exchanges_as_source[exchange_idx]);
-                exchange_idx += 1;
+        let source_ticker = assets_by_id
+            .get(&exchange.from_asset_id)
+            .map(|a| a.ticker.as_str())
+            .unwrap_or("UNKNOWN");
+        let notes = match exchange.event_type {
+            crate::db::AssetExchangeType::Spinoff => {
+                format!("Spin-off from {}", source_ticker)
+            }
+            crate::db::AssetExchangeType::Merger => {
+                format!("Merger from {}", source_ticker)
             }
+        };
 
-            // Apply any corporate actions effective up to this transaction's date
-            crate::corporate_actions::apply_forward_qty_adjustments(
-                &mut position.quantity,
-                &actions,
-                &mut action_idx,
-                tx.trade_date,
-            );
+        let price_per_unit = if exchange.to_quantity > Decimal::ZERO {
+            exchange.allocated_cost / exchange.to_quantity
+        } else {
+            Decimal::ZERO
+        };
 
-            // Build raw position for this transaction
-            match tx.transaction_type {
-                TransactionType::Buy => {
-                    position.add_buy(tx.quantity, tx.total_cost);
-                }
-                TransactionType::Sell => {
-                    position.remove_sell(tx.quantity, &asset.ticker)?;
-                }
-            }
-        }
+        transactions.push(Transaction {
+            id: None,
+            asset_id,
+            transaction_type: TransactionType::Buy,
+            trade_date: exchange.effective_date,
+            settlement_date: Some(exchange.effective_date),
+            quantity: exchange.to_quantity,
+            price_per_unit,
+            total_cost: exchange.allocated_cost,
+            fees: Decimal::ZERO,
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: Some(notes),
+            source: "EXCHANGE".to_string(),
+            created_at: chrono::Utc::now(),
+        });
+    }
+
+    transactions.sort_by_key(|a| (a.trade_date, a.id));
+    let transactions =
+        crate::options::add_expiry_sales(&asset.ticker, asset_id, transactions, as_of);
+
+    // Calculate average-cost position
+    let mut position = AvgCostPosition::new(asset_id);
+
+    // Apply fixed split adjustments forward-only, at the time they occur
+    let amortizations = crate::db::get_amortizations_for_asset(conn, asset_id, None, Some(as_of))?;
+    let mut amort_idx = 0usize;
+    let exchanges_as_source =
+        crate::db::get_asset_exchanges_as_source_up_to(conn, asset_id, as_of)?;
+    let mut exchange_idx = 0usize;
+    let actions = crate::corporate_actions::get_actions_up_to(conn, asset_id, as_of)?;
 
-        while amort_idx < amortizations.len() && amortizations[amort_idx].event_date <= as_of {
+    let mut action_idx = 0usize;
+    let mut cost_history = CostHistory::new();
+    for tx in transactions {
+        while amort_idx < amortizations.len()
+            && amortizations[amort_idx].event_date <= tx.trade_date
+        {
             position.apply_amortization(amortizations[amort_idx].total_amount);
+            cost_history.push((amortizations[amort_idx].event_date, position.total_cost));
             amort_idx += 1;
         }
 
         while exchange_idx < exchanges_as_source.len()
-            && exchanges_as_source[exchange_idx].effective_date <= as_of
+            && exchanges_as_source[exchange_idx].effective_date <= tx.trade_date
         {
             apply_exchange_source_effect(&mut position, &exchanges_as_source[exchange_idx]);
+            cost_history.push((
+                exchanges_as_source[exchange_idx].effective_date,
+                position.total_cost,
+            ));
             exchange_idx += 1;
         }
 
-        // Apply any remaining actions after the last transaction but before as_of
+        // Apply any corporate actions effective up to this transaction's date
         crate::corporate_actions::apply_forward_qty_adjustments(
             &mut position.quantity,
             &actions,
             &mut action_idx,
-            as_of,
+            tx.trade_date,
         );
 
-        // Skip assets with zero quantity
-        if position.quantity <= Decimal::ZERO {
-            continue;
+        // Build raw position for this transaction
+        match tx.transaction_type {
+            TransactionType::Buy => {
+                position.add_buy(tx.quantity, tx.total_cost);
+            }
+            TransactionType::Sell => {
+                position.remove_sell(tx.quantity, &asset.ticker)?;
+            }
         }
+        cost_history.push((tx.trade_date, position.total_cost));
+    }
 
-        // Get current price
-        let latest_price = if let Some(cutoff) = as_of_date {
-            crate::db::get_price_on_or_before(conn, asset_id, cutoff)?
-        } else {
-            crate::db::get_latest_price(conn, asset_id)?
-        };
-        let current_price = latest_price.as_ref().map(|p| p.close_price);
-
-        // Calculate current value and P&L
-        let (current_value, unrealized_pl, unrealized_pl_pct) = if let Some(price) = current_price {
-            let value = price * position.quantity;
-            let pl = value - position.total_cost;
-            let pl_pct = if position.total_cost > Decimal::ZERO {
-                (pl / position.total_cost) * Decimal::from(100)
-            } else {
-                Decimal::ZERO
-            };
-            (Some(value), Some(pl), Some(pl_pct))
+    while amort_idx < amortizations.len() && amortizations[amort_idx].event_date <= as_of {
+        position.apply_amortization(amortizations[amort_idx].total_amount);
+        cost_history.push((amortizations[amort_idx].event_date, position.total_cost));
+        amort_idx += 1;
+    }
+
+    while exchange_idx < exchanges_as_source.len()
+        && exchanges_as_source[exchange_idx].effective_date <= as_of
+    {
+        apply_exchange_source_effect(&mut position, &exchanges_as_source[exchange_idx]);
+        cost_history.push((
+            exchanges_as_source[exchange_idx].effective_date,
+            position.total_cost,
+        ));
+        exchange_idx += 1;
+    }
+
+    // Apply any remaining actions after the last transaction but before as_of
+    crate::corporate_actions::apply_forward_qty_adjustments(
+        &mut position.quantity,
+        &actions,
+        &mut action_idx,
+        as_of,
+    );
+
+    // Skip assets with zero quantity
+    if position.quantity <= Decimal::ZERO {
+        return Ok(None);
+    }
+
+    // Get current price
+    let latest_price = if let Some(cutoff) = as_of_date {
+        crate::db::get_price_on_or_before(conn, asset_id, cutoff)?
+    } else {
+        crate::db::get_latest_price(conn, asset_id)?
+    };
+    // A manual valuation stands in until a newer market close exists
+    let valuation = crate::db::valuations::valuation_on_or_before(
+        conn,
+        asset_id,
+        as_of_date.unwrap_or_else(|| chrono::Local::now().date_naive()),
+    )?
+    .filter(|v| {
+        latest_price
+            .as_ref()
+            .is_none_or(|p| v.valuation_date >= p.price_date)
+    });
+    let valued_on = valuation.as_ref().map(|v| v.valuation_date);
+    // Raw closes: quantities already reflect splits and income is tracked separately
+    let quoted = match valuation {
+        Some(valuation) => Some((valuation.value, valuation.valuation_date)),
+        None => latest_price
+            .as_ref()
+            .map(|p| (p.price(PriceSeries::Close), p.price_date)),
+    };
+    // Prices of assets quoted abroad are in their currency
+    let currency = crate::db::get_asset_currency(conn, asset_id)?;
+    let (current_price, fx) = match quoted {
+        Some((price, date)) => match fx::to_brl(conn, currency.as_deref(), price, date)? {
+            Some((price, fx)) => (Some(price), fx),
+            None => (None, None),
+        },
+        None => (None, None),
+    };
+
+    // Calculate current value and P&L
+    let (current_value, unrealized_pl, unrealized_pl_pct) = if let Some(price) = current_price {
+        let value = price * position.quantity;
+        let pl = value - position.total_cost;
+        let pl_pct = if position.total_cost > Decimal::ZERO {
+            (pl / position.total_cost) * Decimal::from(100)
         } else {
-            (None, None, None)
+            Decimal::ZERO
         };
+        (Some(value), Some(pl), Some(pl_pct))
+    } else {
+        (None, None, None)
+    };
 
-        total_cost += position.total_cost;
-        if let Some(value) = current_value {
-            total_value += value;
-        }
-
-        positions.push(PositionSummary {
+    Ok(Some((
+        PositionSummary {
             asset,
             quantity: position.quantity,
             average_cost: position.average_cost(),
@@ -353,30 +479,11 @@ fn calculate_portfolio_with_cutoff(
             current_value,
             unrealized_pl,
             unrealized_pl_pct,
-        });
-    }
-
-    // Sort positions by total value (descending)
-    positions.sort_by(|a, b| {
-        let a_val = a.current_value.unwrap_or(a.total_cost);
-        let b_val = b.current_value.unwrap_or(b.total_cost);
-        b_val.cmp(&a_val)
-    });
-
-    let total_pl = total_value - total_cost;
-    let total_pl_pct = if total_cost > Decimal::ZERO {
-        (total_pl / total_cost) * Decimal::from(100)
-    } else {
-        Decimal::ZERO
-    };
-
-    Ok(PortfolioReport {
-        positions,
-        total_cost,
-        total_value,
-        total_pl,
-        total_pl_pct,
-    })
+            valued_on,
+            fx,
+        },
+        cost_history,
+    )))
 }
 
 fn map_transaction(row: &rusqlite::Row) -> Result<Transaction, rusqlite::Error> {
@@ -403,14 +510,15 @@ fn map_transaction(row: &rusqlite::Row) -> Result<Transaction, rusqlite::Error>
 
 /// Get all transactions for an asset, ordered by trade date
 fn get_asset_transactions(conn: &Connection, asset_id: i64) -> Result<Vec<Transaction>> {
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT id, asset_id, transaction_type, trade_date, settlement_date,
                 quantity, price_per_unit, total_cost, fees, is_day_trade,
                 quota_issuance_date, notes, source, created_at
          FROM transactions
-         WHERE asset_id = ?1
+         WHERE asset_id = ?1{}
          ORDER BY trade_date ASC, id ASC",
-    )?;
+        crate::db::portfolio::scope_filter("portfolio_id")
+    ))?;
 
     let transactions = stmt
         .query_map([asset_id], map_transaction)?
@@ -425,14 +533,15 @@ fn get_asset_transactions_before(
     asset_id: i64,
     before_date: NaiveDate,
 ) -> Result<Vec<Transaction>> {
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT id, asset_id, transaction_type, trade_date, settlement_date,
                 quantity, price_per_unit, total_cost, fees, is_day_trade,
                 quota_issuance_date, notes, source, created_at
          FROM transactions
-         WHERE asset_id = ?1 AND trade_date < ?2
+         WHERE asset_id = ?1 AND trade_date < ?2{}
          ORDER BY trade_date ASC, id ASC",
-    )?;
+        crate::db::portfolio::scope_filter("portfolio_id")
+    ))?;
 
     let transactions = stmt
         .query_map(rusqlite::params![asset_id, before_date], map_transaction)?
@@ -447,14 +556,15 @@ fn get_asset_transactions_until(
     asset_id: i64,
     cutoff_date: NaiveDate,
 ) -> Result<Vec<Transaction>> {
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT id, asset_id, transaction_type, trade_date, settlement_date,
                 quantity, price_per_unit, total_cost, fees, is_day_trade,
                 quota_issuance_date, notes, source, created_at
          FROM transactions
-         WHERE asset_id = ?1 AND trade_date <= ?2
+         WHERE asset_id = ?1 AND trade_date <= ?2{}
          ORDER BY trade_date ASC, id ASC",
-    )?;
+        crate::db::portfolio::scope_filter("portfolio_id")
+    ))?;
 
     let transactions = stmt
         .query_map(rusqlite::params![asset_id, cutoff_date], map_transaction)?
@@ -594,218 +704,216 @@ pub fn calculate_allocation(report: &PortfolioReport) -> HashMap<AssetType, (Dec
     allocation
 }
 
-/// Compute a fingerprint for all transactions up to and including a date.
-/// Includes corporate actions to detect when adjustments change.
-pub fn compute_snapshot_fingerprint(conn: &Connection, as_of_date: NaiveDate) -> Result<String> {
-    let mut hasher = Hasher::new();
-
-    // Hash transactions
-    let mut stmt = conn.prepare(
-        "SELECT id, asset_id, transaction_type, trade_date, quantity, price_per_unit, total_cost
-         FROM transactions
-         WHERE trade_date <= ?1
-         ORDER BY trade_date ASC, id ASC",
-    )?;
-
-    let mut rows = stmt.query([as_of_date])?;
-
-    while let Some(row) = rows.next()? {
-        let id: i64 = row.get(0)?;
-        let asset_id: i64 = row.get(1)?;
-        let tx_type: String = row.get(2)?;
-        let trade_date: NaiveDate = row.get(3)?;
-        let quantity = get_decimal_value(row, 4)?;
-        let price_per_unit = get_decimal_value(row, 5)?;
-        let total_cost = get_decimal_value(row, 6)?;
-
-        let line = format!(
-            "{}|{}|{}|{}|{}|{}|{}\n",
-            id, asset_id, tx_type, trade_date, quantity, price_per_unit, total_cost
-        );
-        hasher.update(line.as_bytes());
-    }
-
-    // Hash corporate actions that apply up to this date
-    let mut ca_stmt = conn.prepare(
-        "SELECT id, asset_id, action_type, ex_date, quantity_adjustment
-         FROM corporate_actions
-         WHERE ex_date <= ?1
-         ORDER BY ex_date ASC, id ASC",
-    )?;
-
-    let mut ca_rows = ca_stmt.query([as_of_date])?;
-
-    while let Some(row) = ca_rows.next()? {
-        let id: i64 = row.get(0)?;
-        let asset_id: i64 = row.get(1)?;
-        let action_type: String = row.get(2)?;
-        let ex_date: NaiveDate = row.get(3)?;
-        let quantity_adjustment = crate::db::get_decimal_value(row, 4)?;
-
-        let line = format!(
-            "CA|{}|{}|{}|{}|{}\n",
-            id, asset_id, action_type, ex_date, quantity_adjustment
-        );
-        hasher.update(line.as_bytes());
-    }
-
-    Ok(hasher.finalize().to_hex().to_string())
+/// Live move of one position against its last daily close before today
+#[derive(Debug, Clone)]
+pub struct PositionDayMove {
+    pub ticker: String,
+    pub previous_close: Decimal,
+    pub live_price: Decimal,
+    pub change_pct: Decimal,
+    pub value_change: Decimal,
 }
 
-/// Save a portfolio snapshot for a specific date, replacing any existing rows for that date.
-pub fn save_portfolio_snapshot(
-    conn: &mut Connection,
-    date: NaiveDate,
-    label: Option<String>,
-) -> Result<()> {
-    let report = calculate_portfolio_at_date(conn, date, None)?;
-    let fingerprint = compute_snapshot_fingerprint(conn, date)?;
-
-    let tx = conn.transaction()?;
-    tx.execute(
-        "DELETE FROM position_snapshots WHERE snapshot_date = ?1",
-        [date],
-    )?;
-
-    for position in report.positions {
-        let asset_id = position
-            .asset
-            .id
-            .ok_or_else(|| anyhow::anyhow!("Asset missing id for snapshot"))?;
-
-        let market_price = position.current_price.unwrap_or(position.average_cost);
-        let market_value = position
-            .current_value
-            .unwrap_or_else(|| market_price * position.quantity);
-        let unrealized_pl = position
-            .unrealized_pl
-            .unwrap_or_else(|| market_value - position.total_cost);
-
-        tx.execute(
-            "INSERT INTO position_snapshots (
-                snapshot_date, asset_id, quantity, average_cost, market_price,
-                market_value, unrealized_pl, tx_fingerprint, label
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            rusqlite::params![
-                date,
-                asset_id,
-                position.quantity.to_string(),
-                position.average_cost.to_string(),
-                market_price.to_string(),
-                market_value.to_string(),
-                unrealized_pl.to_string(),
-                &fingerprint,
-                label.clone(),
-            ],
-        )?;
-    }
-
-    tx.commit()?;
-    Ok(())
+/// Today's live move across positions that have an intraday quote
+#[derive(Debug, Clone)]
+pub struct DayMove {
+    pub positions: Vec<PositionDayMove>,
+    pub total_change: Decimal,
+    pub total_change_pct: Decimal,
+    pub quoted_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// Load a snapshot if the stored fingerprint matches the current transaction state.
-pub fn get_valid_snapshot(conn: &Connection, date: NaiveDate) -> Result<Option<PortfolioReport>> {
-    let mut stmt = conn.prepare(
-        "SELECT ps.asset_id, ps.quantity, ps.average_cost, ps.market_price, ps.market_value,
-                ps.unrealized_pl, ps.tx_fingerprint, a.ticker, a.asset_type, a.name, a.cnpj,
-                a.created_at, a.updated_at
-         FROM position_snapshots ps
-         JOIN assets a ON ps.asset_id = a.id
-         WHERE ps.snapshot_date = ?1
-         ORDER BY ps.market_value DESC",
-    )?;
-
-    let rows = stmt
-        .query_map([date], |row| {
-            let asset_type: AssetType = row
-                .get::<_, String>(8)?
-                .parse()
-                .unwrap_or(AssetType::Unknown);
-
-            Ok((
-                Asset {
-                    id: Some(row.get(0)?),
-                    ticker: row.get(7)?,
-                    asset_type,
-                    name: row.get(9)?,
-                    cnpj: row.get(10)?,
-                    created_at: row.get(11)?,
-                    updated_at: row.get(12)?,
-                },
-                get_decimal_value(row, 1)?,
-                get_decimal_value(row, 2)?,
-                get_decimal_value(row, 3)?,
-                get_decimal_value(row, 4)?,
-                get_decimal_value(row, 5)?,
-                row.get::<_, String>(6)?,
-            ))
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
-
-    if rows.is_empty() {
-        return Ok(None);
-    }
-
-    let stored_fingerprint = rows[0].6.clone();
-    let current_fingerprint = compute_snapshot_fingerprint(conn, date)?;
-    if stored_fingerprint != current_fingerprint {
+/// Compare today's intraday quotes with the previous daily closes.
+///
+/// `today` is the UTC date of the quotes. Returns `None` when no position has
+/// both an intraday quote today and an earlier close.
+pub fn calculate_day_move(
+    conn: &Connection,
+    report: &PortfolioReport,
+    today: NaiveDate,
+) -> Result<Option<DayMove>> {
+    let Some(yesterday) = today.pred_opt() else {
         return Ok(None);
-    }
+    };
 
     let mut positions = Vec::new();
-    let mut total_cost = Decimal::ZERO;
-    let mut total_value = Decimal::ZERO;
+    let mut total_change = Decimal::ZERO;
+    let mut previous_value = Decimal::ZERO;
+    let mut quoted_at = None;
 
-    for (asset, quantity, average_cost, market_price, market_value, unrealized_pl, _) in rows {
-        let position_cost = average_cost * quantity;
-        let unrealized_pl_pct = if position_cost > Decimal::ZERO {
-            (unrealized_pl / position_cost) * Decimal::from(100)
-        } else {
-            Decimal::ZERO
+    for position in &report.positions {
+        let Some(asset_id) = position.asset.id else {
+            continue;
         };
+        let Some(snapshot) = crate::db::get_latest_price_snapshot(conn, asset_id, today)? else {
+            continue;
+        };
+        let Some(previous) = crate::db::get_price_on_or_before(conn, asset_id, yesterday)? else {
+            continue;
+        };
+        let previous_close = previous.price(PriceSeries::Close);
+        if previous_close <= Decimal::ZERO {
+            continue;
+        }
 
-        total_cost += position_cost;
-        total_value += market_value;
-
-        positions.push(PositionSummary {
-            asset,
-            quantity,
-            average_cost,
-            total_cost: position_cost,
-            current_price: Some(market_price),
-            current_value: Some(market_value),
-            unrealized_pl: Some(unrealized_pl),
-            unrealized_pl_pct: Some(unrealized_pl_pct),
+        let value_change = (snapshot.price - previous_close) * position.quantity;
+        total_change += value_change;
+        previous_value += previous_close * position.quantity;
+        quoted_at = quoted_at.max(Some(snapshot.snapshot_at));
+        positions.push(PositionDayMove {
+            ticker: position.asset.ticker.clone(),
+            previous_close,
+            live_price: snapshot.price,
+            change_pct: (snapshot.price / previous_close - Decimal::ONE) * Decimal::from(100),
+            value_change,
         });
     }
 
-    let total_pl = total_value - total_cost;
-    let total_pl_pct = if total_cost > Decimal::ZERO {
-        (total_pl / total_cost) * Decimal::from(100)
+    let Some(quoted_at) = quoted_at else {
+        return Ok(None);
+    };
+    let total_change_pct = if previous_value > Decimal::ZERO {
+        total_change / previous_value * Decimal::from(100)
     } else {
         Decimal::ZERO
     };
 
-    Ok(Some(PortfolioReport {
+    Ok(Some(DayMove {
         positions,
-        total_cost,
-        total_value,
-        total_pl,
-        total_pl_pct,
+        total_change,
+        total_change_pct,
+        quoted_at,
     }))
 }
 
-/// Delete snapshots on or after a given date to force recomputation.
-pub fn invalidate_snapshots_after(
+/// Part of a position held at one broker
+#[derive(Debug, Clone)]
+pub struct BrokerHolding {
+    /// None for shares no broker-tagged trade accounts for
+    pub broker: Option<String>,
+    pub ticker: String,
+    pub quantity: Decimal,
+    pub total_cost: Decimal,
+    pub current_value: Option<Decimal>,
+}
+
+/// Split each position across the brokers its trades were made at.
+///
+/// Quantities are replayed per broker and scaled on splits by the same ratio
+/// as the whole position. Cost uses the consolidated average cost, which is
+/// what IRPF asks for regardless of broker. Shares that come from renames,
+/// spin-offs or imports without an "Instituição" column are left unassigned.
+pub fn split_by_broker(
     conn: &Connection,
-    earliest_changed_date: NaiveDate,
+    report: &PortfolioReport,
+    as_of_date: Option<NaiveDate>,
+) -> Result<Vec<BrokerHolding>> {
+    let as_of = as_of_date.unwrap_or_else(|| chrono::Local::now().date_naive());
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.transaction_type, t.trade_date, t.quantity, b.name
+         FROM transactions t
+         LEFT JOIN brokers b ON b.id = t.broker_id
+         WHERE t.asset_id = ?1 AND t.trade_date <= ?2{}
+         ORDER BY t.trade_date ASC, t.id ASC",
+        crate::db::portfolio::scope_filter("t.portfolio_id")
+    ))?;
+
+    let mut holdings = Vec::new();
+    for position in &report.positions {
+        let Some(asset_id) = position.asset.id else {
+            continue;
+        };
+        let actions = crate::corporate_actions::get_actions_up_to(conn, asset_id, as_of)?;
+        let mut action_idx = 0usize;
+        let mut total = Decimal::ZERO;
+        let mut by_broker: Vec<(Option<String>, Decimal)> = Vec::new();
+
+        let mut apply_splits = |total: &mut Decimal,
+                                by_broker: &mut Vec<(Option<String>, Decimal)>,
+                                cutoff: NaiveDate| {
+            let before = *total;
+            crate::corporate_actions::apply_forward_qty_adjustments(
+                total,
+                &actions,
+                &mut action_idx,
+                cutoff,
+            );
+            if before > Decimal::ZERO && *total != before {
+                let ratio = *total / before;
+                for (_, quantity) in by_broker.iter_mut() {
+                    *quantity *= ratio;
+                }
+            }
+        };
+
+        let mut rows = stmt.query(rusqlite::params![asset_id, as_of])?;
+        while let Some(row) = rows.next()? {
+            let tx_type: String = row.get(0)?;
+            let trade_date: NaiveDate = row.get(1)?;
+            let quantity = get_decimal_value(row, 2)?;
+            let broker: Option<String> = row.get(3)?;
+
+            apply_splits(&mut total, &mut by_broker, trade_date);
+            let signed = match tx_type.parse::<TransactionType>() {
+                Ok(TransactionType::Sell) => -quantity,
+                _ => quantity,
+            };
+            total += signed;
+            match by_broker.iter_mut().find(|(b, _)| *b == broker) {
+                Some((_, q)) => *q += signed,
+                None => by_broker.push((broker, signed)),
+            }
+        }
+        apply_splits(&mut total, &mut by_broker, as_of);
+
+        let mut assigned = Decimal::ZERO;
+        let mut split: Vec<(Option<String>, Decimal)> = by_broker
+            .into_iter()
+            .filter(|(broker, quantity)| broker.is_some() && quantity.round_dp(4) > Decimal::ZERO)
+            .map(|(broker, quantity)| (broker, quantity.round_dp(4)))
+            .inspect(|(_, quantity)| assigned += *quantity)
+            .collect();
+        let unassigned = position.quantity - assigned;
+        if unassigned.round_dp(4) != Decimal::ZERO {
+            split.push((None, unassigned));
+        }
+
+        for (broker, quantity) in split {
+            holdings.push(BrokerHolding {
+                broker,
+                ticker: position.asset.ticker.clone(),
+                quantity,
+                total_cost: quantity * position.average_cost,
+                current_value: position.current_price.map(|price| price * quantity),
+            });
+        }
+    }
+
+    Ok(holdings)
+}
+
+/// Fingerprint of the data behind the valuation at the end of a date: trades,
+/// corporate actions, renames, exchanges, amortizations, manual valuations
+/// and closes up to and including it.
+pub fn compute_snapshot_fingerprint(conn: &Connection, as_of_date: NaiveDate) -> Result<String> {
+    Ok(Fingerprints::load(conn, as_of_date)?
+        .at(as_of_date)
+        .to_string())
+}
+
+/// Save a portfolio snapshot for a specific date, replacing any existing rows for that date.
+pub fn save_portfolio_snapshot(
+    conn: &mut Connection,
+    date: NaiveDate,
+    label: Option<String>,
 ) -> Result<()> {
-    conn.execute(
-        "DELETE FROM position_snapshots WHERE snapshot_date >= ?1",
-        [earliest_changed_date],
-    )?;
-    Ok(())
+    // Snapshots cache the aggregate of all portfolios
+    if crate::db::portfolio::is_scoped() {
+        return Ok(());
+    }
+    let report = calculate_portfolio_at_date(conn, date, None)?;
+    let fingerprint = compute_snapshot_fingerprint(conn, date)?;
+    snapshots::store(conn, date, &report, &fingerprint, label)
 }
 
 #[cfg(test)]
@@ -815,6 +923,12 @@ mod tests {
     use chrono::{NaiveDate, Utc};
     use rusqlite::Connection;
 
+    /// The stored snapshot of `date`, if still valid
+    fn get_valid_snapshot(conn: &Connection, date: NaiveDate) -> Result<Option<PortfolioReport>> {
+        let fingerprint = compute_snapshot_fingerprint(conn, date)?;
+        snapshots::load(conn, date, &fingerprint)
+    }
+
     #[test]
     fn test_avg_position_buy_and_sell() {
         let mut position = AvgCostPosition::new(1);
@@ -935,6 +1049,7 @@ mod tests {
             volume: Some(1_000),
             source: "TEST".to_string(),
             created_at: Utc::now(),
+            adjusted_close: None,
         };
 
         db::insert_price_history(&conn, &price).unwrap();
@@ -954,4 +1069,305 @@ mod tests {
         assert_eq!(position.current_value, Some(Decimal::from(60)));
         assert_eq!(position.unrealized_pl, Some(Decimal::from(10)));
     }
+
+    #[test]
+    fn test_manual_valuation_until_newer_close() {
+        use crate::db::valuations::{set_valuation, Valuation};
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        let asset_id = db::upsert_asset(&conn, "FIPX11", &AssetType::Fip, None).unwrap();
+        db::insert_transaction(
+            &conn,
+            &Transaction {
+                id: None,
+                asset_id,
+                transaction_type: TransactionType::Buy,
+                trade_date: d(1, 5),
+                settlement_date: None,
+                quantity: Decimal::from(10),
+                price_per_unit: Decimal::from(100),
+                total_cost: Decimal::from(1000),
+                fees: Decimal::ZERO,
+                is_day_trade: false,
+                quota_issuance_date: None,
+                notes: None,
+                source: "TEST".to_string(),
+                created_at: Utc::now(),
+            },
+        )
+        .unwrap();
+        set_valuation(
+            &conn,
+            &Valuation {
+                asset_id,
+                valuation_date: d(3, 31),
+                value: Decimal::from(130),
+                notes: None,
+            },
+        )
+        .unwrap();
+
+        // No price before the valuation date
+        let before = calculate_portfolio_at_date(&conn, d(3, 30), None).unwrap();
+        assert_eq!(before.positions[0].current_price, None);
+
+        let valued = calculate_portfolio_at_date(&conn, d(4, 30), None).unwrap();
+        assert_eq!(valued.positions[0].current_price, Some(Decimal::from(130)));
+        assert_eq!(valued.positions[0].valued_on, Some(d(3, 31)));
+        assert_eq!(valued.total_value, Decimal::from(1300));
+
+        // Snapshots keep the marker
+        save_portfolio_snapshot(&mut conn, d(4, 30), None).unwrap();
+        let snapshot = get_valid_snapshot(&conn, d(4, 30)).unwrap().unwrap();
+        assert_eq!(snapshot.positions[0].valued_on, Some(d(3, 31)));
+
+        // A newer valuation invalidates the snapshot
+        set_valuation(
+            &conn,
+            &Valuation {
+                asset_id,
+                valuation_date: d(4, 15),
+                value: Decimal::from(140),
+                notes: None,
+            },
+        )
+        .unwrap();
+        assert!(get_valid_snapshot(&conn, d(4, 30)).unwrap().is_none());
+
+        // Once listed, a newer market close takes over
+        db::insert_price_history(
+            &conn,
+            &PriceHistory {
+                id: None,
+                asset_id,
+                price_date: d(4, 20),
+                close_price: Decimal::from(90),
+                open_price: None,
+                high_price: None,
+                low_price: None,
+                volume: None,
+                source: "TEST".to_string(),
+                created_at: Utc::now(),
+                adjusted_close: None,
+            },
+        )
+        .unwrap();
+        let listed = calculate_portfolio_at_date(&conn, d(4, 30), None).unwrap();
+        assert_eq!(listed.positions[0].current_price, Some(Decimal::from(90)));
+        assert_eq!(listed.positions[0].valued_on, None);
+    }
+
+    #[test]
+    fn test_asset_quoted_abroad_valued_at_ptax() {
+        use crate::db::valuations::{set_valuation, Valuation};
+
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        let asset_id = db::insert_asset(&conn, "AAPL", &AssetType::Stock, None).unwrap();
+        db::update_asset_currency(&conn, "AAPL", Some("USD")).unwrap();
+        db::insert_transaction(
+            &conn,
+            &Transaction {
+                id: None,
+                asset_id,
+                transaction_type: TransactionType::Buy,
+                trade_date: d(1, 5),
+                settlement_date: None,
+                quantity: Decimal::from(10),
+                price_per_unit: Decimal::from(900),
+                total_cost: Decimal::from(9000),
+                fees: Decimal::ZERO,
+                is_day_trade: false,
+                quota_issuance_date: None,
+                notes: None,
+                source: "TEST".to_string(),
+                created_at: Utc::now(),
+            },
+        )
+        .unwrap();
+        set_valuation(
+            &conn,
+            &Valuation {
+                asset_id,
+                valuation_date: d(3, 29),
+                value: Decimal::from(200),
+                notes: None,
+            },
+        )
+        .unwrap();
+
+        // No rate yet: the price cannot be read in reais
+        let unrated = calculate_portfolio_at_date(&conn, d(4, 30), None).unwrap();
+        assert_eq!(unrated.positions[0].current_price, None);
+
+        // The PTAX on or before the price's date, not the report's
+        db::insert_fx_rate(&conn, "USDBRL", d(3, 28), Decimal::new(5, 0), "BCB").unwrap();
+        db::insert_fx_rate(&conn, "USDBRL", d(4, 30), Decimal::new(6, 0), "BCB").unwrap();
+        let report = calculate_portfolio_at_date(&conn, d(4, 30), None).unwrap();
+        let position = &report.positions[0];
+        assert_eq!(position.current_price, Some(Decimal::from(1000)));
+        assert_eq!(report.total_value, Decimal::from(10000));
+        assert_eq!(position.fx.as_ref().unwrap().rate_date, d(3, 28));
+
+        // Snapshots keep the conversion
+        save_portfolio_snapshot(&mut conn, d(4, 30), None).unwrap();
+        let snapshot = get_valid_snapshot(&conn, d(4, 30)).unwrap().unwrap();
+        assert_eq!(snapshot.positions[0].fx, position.fx);
+    }
+
+    #[test]
+    fn test_split_by_broker_scales_on_splits() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id = db::upsert_asset(&conn, "ITSA4", &AssetType::Stock, None).unwrap();
+
+        let trade =
+            |date: (i32, u32, u32), kind: TransactionType, qty: i64, broker: Option<&str>| {
+                let tx = Transaction {
+                    id: None,
+                    asset_id,
+                    transaction_type: kind,
+                    trade_date: NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap(),
+                    settlement_date: None,
+                    quantity: Decimal::from(qty),
+                    price_per_unit: Decimal::from(10),
+                    total_cost: Decimal::from(qty * 10),
+                    fees: Decimal::ZERO,
+                    is_day_trade: false,
+                    quota_issuance_date: None,
+                    notes: None,
+                    source: "TEST".to_string(),
+                    created_at: Utc::now(),
+                };
+                let id = db::insert_transaction(&conn, &tx).unwrap();
+                if let Some(name) = broker {
+                    let broker_id = db::upsert_broker(&conn, name).unwrap();
+                    db::set_transaction_broker(&conn, id, broker_id).unwrap();
+                }
+            };
+        trade(
+            (2024, 1, 5),
+            TransactionType::Buy,
+            100,
+            Some("XP INVESTIMENTOS"),
+        );
+        trade(
+            (2024, 1, 6),
+            TransactionType::Buy,
+            100,
+            Some("BANCO  INTER S/A"),
+        );
+        trade((2024, 1, 7), TransactionType::Buy, 50, None);
+        trade(
+            (2024, 2, 1),
+            TransactionType::Sell,
+            50,
+            Some("XP INVESTIMENTOS"),
+        );
+        // 2:1 split on the 200 shares held
+        conn.execute(
+            "INSERT INTO corporate_actions (asset_id, action_type, event_date, ex_date,
+                quantity_adjustment, source)
+             VALUES (?1, 'SPLIT', '2024-03-01', '2024-03-01', 200, 'TEST')",
+            [asset_id],
+        )
+        .unwrap();
+
+        let as_of = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let report = calculate_portfolio_at_date(&conn, as_of, None).unwrap();
+        assert_eq!(report.positions[0].quantity, Decimal::from(400));
+
+        let holdings = split_by_broker(&conn, &report, Some(as_of)).unwrap();
+        let quantities: Vec<_> = holdings
+            .iter()
+            .map(|h| (h.broker.as_deref(), h.quantity))
+            .collect();
+        assert_eq!(
+            quantities,
+            vec![
+                (Some("XP INVESTIMENTOS"), Decimal::from(100)),
+                (Some("BANCO INTER S/A"), Decimal::from(200)),
+                (None, Decimal::from(100)),
+            ]
+        );
+        let cost: Decimal = holdings.iter().map(|h| h.total_cost).sum();
+        assert_eq!(cost, report.positions[0].total_cost);
+    }
+
+    #[test]
+    fn test_day_move_compares_live_quote_with_previous_close() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+
+        let asset_id = db::upsert_asset(&conn, "TEST4", &AssetType::Stock, None).unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        db::insert_transaction(
+            &conn,
+            &Transaction {
+                id: None,
+                asset_id,
+                transaction_type: TransactionType::Buy,
+                trade_date: NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(),
+                settlement_date: None,
+                quantity: Decimal::from(10),
+                price_per_unit: Decimal::from(10),
+                total_cost: Decimal::from(100),
+                fees: Decimal::ZERO,
+                is_day_trade: false,
+                quota_issuance_date: None,
+                notes: None,
+                source: "TEST".to_string(),
+                created_at: Utc::now(),
+            },
+        )
+        .unwrap();
+
+        let close = |date: NaiveDate, price: i64| PriceHistory {
+            id: None,
+            asset_id,
+            price_date: date,
+            close_price: Decimal::from(price),
+            open_price: None,
+            high_price: None,
+            low_price: None,
+            volume: None,
+            source: "TEST".to_string(),
+            created_at: Utc::now(),
+            adjusted_close: Some(Decimal::from(price - 1)),
+        };
+        db::insert_price_history(&conn, &close(today.pred_opt().unwrap(), 20)).unwrap();
+        // Today's daily row already holds the live quote; the move must use yesterday's close
+        db::insert_price_history(&conn, &close(today, 22)).unwrap();
+
+        let report = calculate_portfolio(&conn, None).unwrap();
+        assert!(calculate_day_move(&conn, &report, today).unwrap().is_none());
+
+        let quoted_at = today.and_hms_opt(15, 0, 0).unwrap().and_utc();
+        db::insert_price_snapshot(
+            &conn,
+            &db::PriceSnapshot {
+                asset_id,
+                snapshot_at: quoted_at,
+                price: Decimal::from(22),
+                source: "TEST".to_string(),
+            },
+        )
+        .unwrap();
+
+        let day = calculate_day_move(&conn, &report, today).unwrap().unwrap();
+        assert_eq!(day.quoted_at, quoted_at);
+        assert_eq!(day.positions.len(), 1);
+        assert_eq!(day.positions[0].previous_close, Decimal::from(20));
+        assert_eq!(day.positions[0].change_pct, Decimal::from(10));
+        assert_eq!(day.total_change, Decimal::from(20));
+        // Valuation uses the raw close, not the adjusted series
+        assert_eq!(report.positions[0].current_price, Some(Decimal::from(22)));
+    }
 }
@@ -0,0 +1,255 @@
+//! Computed holdings checked against the custody statements, month by month.
+//!
+//! For every month with a stored position statement (a Posição export or the
+//! positions `sync-b3` fetched), the latest statement of the month is compared
+//! with the quantities the transactions give on that day. Only assets that
+//! appear in some statement are compared; an asset missing from a statement
+//! was reported as zero. When the Posição export and the API both report the
+//! same day, the export wins.
+//!
+//! The first month an asset diverges is usually where its history went wrong:
+//! a missing trade, an unapplied split or a transfer between brokers.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::db::position_statements;
+
+/// One asset in one month
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PositionCell {
+    pub statement_date: NaiveDate,
+    pub reported: Decimal,
+    pub computed: Decimal,
+    /// Computed minus reported
+    pub difference: Decimal,
+}
+
+impl PositionCell {
+    pub fn matches(&self) -> bool {
+        self.difference.is_zero()
+    }
+
+    /// Difference relative to the larger of both quantities, 0 to 1
+    pub fn relative_difference(&self) -> Decimal {
+        let base = self.reported.abs().max(self.computed.abs());
+        if base.is_zero() {
+            Decimal::ZERO
+        } else {
+            (self.difference.abs() / base).min(Decimal::ONE)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetHistory {
+    pub ticker: String,
+    /// One per month of the grid; None when that month has no statement
+    pub cells: Vec<Option<PositionCell>>,
+    /// Earliest month whose statement disagrees with the computed quantity
+    pub first_divergence: Option<PositionCell>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiscrepancyGrid {
+    /// First day of each month, oldest first
+    pub months: Vec<NaiveDate>,
+    pub assets: Vec<AssetHistory>,
+}
+
+impl DiscrepancyGrid {
+    pub fn diverging(&self) -> impl Iterator<Item = &AssetHistory> {
+        self.assets.iter().filter(|a| a.first_divergence.is_some())
+    }
+}
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).expect("valid date")
+}
+
+/// Compare the statements of the months in [from, to] (first days of month),
+/// optionally for one ticker
+pub fn build(
+    conn: &Connection,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    asset: Option<&str>,
+) -> Result<DiscrepancyGrid> {
+    let asset = asset.map(str::to_uppercase);
+    let statements: Vec<_> = position_statements::load_statements(conn)?
+        .into_iter()
+        .filter(|s| from.is_none_or(|f| month_start(s.statement_date) >= f))
+        .filter(|s| to.is_none_or(|t| month_start(s.statement_date) <= t))
+        .collect();
+
+    // Latest statement day of each month, and what it reported per ticker
+    let mut latest: BTreeMap<NaiveDate, NaiveDate> = BTreeMap::new();
+    for s in &statements {
+        let day = latest.entry(month_start(s.statement_date)).or_default();
+        *day = (*day).max(s.statement_date);
+    }
+    let mut reported: BTreeMap<NaiveDate, BTreeMap<&str, (&str, Decimal)>> = BTreeMap::new();
+    for s in &statements {
+        if latest[&month_start(s.statement_date)] != s.statement_date {
+            continue;
+        }
+        let day = reported.entry(s.statement_date).or_default();
+        let keep = match day.get(s.ticker.as_str()) {
+            Some((source, _)) => *source != "POSICAO",
+            None => true,
+        };
+        if keep {
+            day.insert(&s.ticker, (&s.source, s.quantity));
+        }
+    }
+
+    let tickers: BTreeSet<&str> = statements
+        .iter()
+        .map(|s| s.ticker.as_str())
+        .filter(|t| asset.as_deref().is_none_or(|a| a == *t))
+        .collect();
+    // Every month between the first and last statement, gaps included
+    let mut months = Vec::new();
+    if let (Some(first), Some(last)) = (latest.keys().next(), latest.keys().next_back()) {
+        let mut month = *first;
+        while month <= *last {
+            months.push(month);
+            month = month
+                .checked_add_months(chrono::Months::new(1))
+                .expect("valid date");
+        }
+    }
+
+    let mut assets: BTreeMap<&str, AssetHistory> = tickers
+        .iter()
+        .map(|t| {
+            (
+                *t,
+                AssetHistory {
+                    ticker: t.to_string(),
+                    cells: Vec::with_capacity(months.len()),
+                    first_divergence: None,
+                },
+            )
+        })
+        .collect();
+
+    for month in &months {
+        let Some(&date) = latest.get(month) else {
+            for history in assets.values_mut() {
+                history.cells.push(None);
+            }
+            continue;
+        };
+        let computed: BTreeMap<String, Decimal> =
+            super::calculate_portfolio_at_date(conn, date, None)?
+                .positions
+                .into_iter()
+                .map(|p| (p.asset.ticker, p.quantity))
+                .collect();
+        let day = &reported[&date];
+        for (ticker, history) in assets.iter_mut() {
+            let reported = day.get(ticker).map(|(_, q)| *q).unwrap_or_default();
+            let computed = computed.get(*ticker).copied().unwrap_or_default();
+            let cell = PositionCell {
+                statement_date: date,
+                reported,
+                computed,
+                difference: computed - reported,
+            };
+            if !cell.matches() && history.first_divergence.is_none() {
+                history.first_divergence = Some(cell.clone());
+            }
+            history.cells.push(Some(cell));
+        }
+    }
+
+    Ok(DiscrepancyGrid {
+        months,
+        assets: assets.into_values().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, AssetType, Transaction, TransactionType};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_first_divergent_month_per_asset() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        let buy = |asset_id, date, quantity| {
+            db::insert_transaction(
+                &conn,
+                &Transaction {
+                    id: None,
+                    asset_id,
+                    transaction_type: TransactionType::Buy,
+                    trade_date: date,
+                    settlement_date: None,
+                    quantity,
+                    price_per_unit: dec!(10),
+                    total_cost: quantity * dec!(10),
+                    fees: Decimal::ZERO,
+                    is_day_trade: false,
+                    quota_issuance_date: None,
+                    notes: None,
+                    source: "TEST".to_string(),
+                    created_at: chrono::Utc::now(),
+                },
+            )
+            .unwrap();
+        };
+        let petr = db::insert_asset(&conn, "PETR4", &AssetType::Stock, None).unwrap();
+        let vale = db::insert_asset(&conn, "VALE3", &AssetType::Stock, None).unwrap();
+        buy(petr, d(1, 10), dec!(100));
+        buy(vale, d(1, 10), dec!(50));
+        // The February buy of PETR4 was never imported
+        buy(vale, d(3, 5), dec!(50));
+
+        let statement = |date, petr_qty, vale_qty, source| {
+            let mut quantities = BTreeMap::new();
+            quantities.insert("PETR4".to_string(), petr_qty);
+            quantities.insert("VALE3".to_string(), vale_qty);
+            position_statements::record_statement(&conn, date, source, &quantities).unwrap();
+        };
+        statement(d(1, 31), dec!(100), dec!(50), "POSICAO");
+        // A stale mid-month statement is superseded by the month's last one
+        statement(d(2, 15), dec!(100), dec!(50), "POSICAO");
+        statement(d(2, 29), dec!(200), dec!(50), "B3_API");
+        statement(d(3, 28), dec!(200), dec!(100), "POSICAO");
+        statement(d(3, 28), dec!(999), dec!(999), "B3_API");
+        statement(d(5, 31), dec!(200), dec!(100), "POSICAO");
+
+        let grid = build(&conn, None, None, None).unwrap();
+        assert_eq!(
+            grid.months,
+            vec![d(1, 1), d(2, 1), d(3, 1), d(4, 1), d(5, 1)]
+        );
+        let petr4 = &grid.assets[0];
+        assert_eq!(petr4.ticker, "PETR4");
+        let first = petr4.first_divergence.as_ref().unwrap();
+        assert_eq!(first.statement_date, d(2, 29));
+        assert_eq!(first.difference, dec!(-100));
+        assert_eq!(first.relative_difference(), dec!(0.5));
+        assert!(petr4.cells[0].as_ref().unwrap().matches());
+        // No statement in April
+        assert!(petr4.cells[3].is_none());
+        // The Posição export wins over the API on the same day
+        assert!(grid.assets[1].first_divergence.is_none());
+        assert_eq!(grid.diverging().count(), 1);
+
+        let march = build(&conn, Some(d(3, 1)), Some(d(4, 1)), Some("vale3")).unwrap();
+        assert_eq!(march.months, vec![d(3, 1)]);
+        assert_eq!(march.assets.len(), 1);
+        assert_eq!(march.assets[0].ticker, "VALE3");
+    }
+}
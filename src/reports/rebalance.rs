@@ -0,0 +1,494 @@
+//! What to buy with a new contribution (aporte) to approach the target
+//! allocation.
+//!
+//! Targets come from `[rebalance.targets]` in the config, in % of the whole
+//! portfolio after the contribution. A target key is a ticker or an asset
+//! type; a type covers its held assets that have no target of their own, and
+//! within it the smallest position is bought first. Nothing is sold: the
+//! contribution goes, one lot at a time, to the group furthest below its
+//! target, and a lot is only bought when it brings the group closer to the
+//! target than it was. Assets held outside every target count in the total
+//! but never receive money.
+
+use anyhow::{bail, Result};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::db::lot_size::{self, Precision};
+use crate::db::{self, AssetType, InconsistencySeverity, InconsistencyStatus};
+use crate::reports::PortfolioReport;
+
+/// Standard B3 lot; smaller quantities trade on the fractional market
+const STANDARD_LOT: i64 = 100;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SuggestOptions {
+    /// Buy only standard lots of 100 (no fractional market)
+    pub round_lots: bool,
+    /// Leave out assets with open blocking inconsistencies
+    pub skip_blocked: bool,
+}
+
+/// One target and where the portfolio stands against it
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupAllocation {
+    pub key: String,
+    pub target_pct: Decimal,
+    pub current_value: Decimal,
+    pub current_pct: Decimal,
+    pub after_value: Decimal,
+    pub after_pct: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SuggestedBuy {
+    pub ticker: String,
+    pub group: String,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub amount: Decimal,
+}
+
+/// An asset of some target that cannot receive money, and why
+#[derive(Debug, Clone, Serialize)]
+pub struct Skipped {
+    pub ticker: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ContributionPlan {
+    pub amount: Decimal,
+    pub spent: Decimal,
+    pub leftover: Decimal,
+    pub buys: Vec<SuggestedBuy>,
+    pub groups: Vec<GroupAllocation>,
+    /// Value held in assets outside every target
+    pub untargeted_value: Decimal,
+    pub skipped: Vec<Skipped>,
+}
+
+/// An asset money can go to
+struct Candidate {
+    ticker: String,
+    price: Decimal,
+    /// Quantity of one purchase
+    step: Decimal,
+    /// Held value plus what the plan bought so far
+    value: Decimal,
+    bought: Decimal,
+}
+
+impl Candidate {
+    fn lot_cost(&self) -> Decimal {
+        self.price * self.step
+    }
+}
+
+struct Group {
+    key: String,
+    target_pct: Decimal,
+    current_value: Decimal,
+    added: Decimal,
+    candidates: Vec<Candidate>,
+}
+
+impl Group {
+    fn deficit(&self, total_after: Decimal) -> Decimal {
+        self.target_pct / Decimal::from(100) * total_after - self.current_value - self.added
+    }
+
+    /// Smallest position whose lot is affordable and brings the group
+    /// closer to its target
+    fn next_candidate(&self, cash: Decimal, deficit: Decimal) -> Option<usize> {
+        self.candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.lot_cost() <= cash && c.lot_cost() < deficit * Decimal::TWO)
+            .min_by(|(_, a), (_, b)| a.value.cmp(&b.value))
+            .map(|(i, _)| i)
+    }
+}
+
+fn pct(value: Decimal, total: Decimal) -> Decimal {
+    if total > Decimal::ZERO {
+        (value / total * Decimal::from(100)).round_dp(2)
+    } else {
+        Decimal::ZERO
+    }
+}
+
+/// Quantity of one purchase, or why the asset cannot be bought in units
+fn step(asset_type: &AssetType, ticker: &str, round_lots: bool) -> Result<Decimal, String> {
+    if matches!(asset_type, AssetType::Option | AssetType::TermContract) {
+        return Err("not bought with contributions".to_string());
+    }
+    match lot_size::precision(asset_type, ticker) {
+        Precision::Whole if round_lots => Ok(Decimal::from(STANDARD_LOT)),
+        Precision::Whole => Ok(Decimal::ONE),
+        Precision::Decimals(places) => Ok(Decimal::new(1, places)),
+        Precision::Free => Err("bought by amount, not in units".to_string()),
+    }
+}
+
+/// Open blocking inconsistencies, by ticker
+fn blocked_tickers(conn: &Connection) -> Result<HashSet<String>> {
+    Ok(
+        db::list_inconsistencies(conn, Some(InconsistencyStatus::Open), None, None)?
+            .into_iter()
+            .filter(|i| i.severity == InconsistencySeverity::Blocking)
+            .filter_map(|i| i.ticker)
+            .map(|t| t.to_uppercase())
+            .collect(),
+    )
+}
+
+/// Split `amount` over the assets of `targets` (key → %)
+pub fn suggest(
+    conn: &Connection,
+    report: &PortfolioReport,
+    targets: &BTreeMap<String, Decimal>,
+    amount: Decimal,
+    options: SuggestOptions,
+) -> Result<ContributionPlan> {
+    if amount <= Decimal::ZERO {
+        bail!("The contribution must be positive");
+    }
+    if targets.is_empty() {
+        bail!("No target allocation; add a [rebalance.targets] section to ~/.interest/config.toml");
+    }
+    let target_sum: Decimal = targets.values().sum();
+    if target_sum > Decimal::from(100) {
+        bail!("Targets add up to {}%, more than 100%", target_sum);
+    }
+    if let Some((key, _)) = targets.iter().find(|(_, pct)| **pct < Decimal::ZERO) {
+        bail!("Negative target for {}", key);
+    }
+
+    let blocked = if options.skip_blocked {
+        blocked_tickers(conn)?
+    } else {
+        HashSet::new()
+    };
+    let targets: BTreeMap<String, Decimal> = targets
+        .iter()
+        .map(|(k, v)| (k.trim().to_uppercase(), *v))
+        .collect();
+    let ticker_targets: HashSet<&str> = targets
+        .keys()
+        .filter(|k| k.parse::<AssetType>().is_err())
+        .map(String::as_str)
+        .collect();
+
+    let mut skipped = Vec::new();
+    let mut candidate = |ticker: &str,
+                         asset_type: &AssetType,
+                         price: Option<Decimal>,
+                         valued: bool,
+                         value: Decimal|
+     -> Option<Candidate> {
+        let reason = if blocked.contains(ticker) {
+            Some("open blocking inconsistency".to_string())
+        } else if valued {
+            Some("valued manually, no market price".to_string())
+        } else if price.is_none_or(|p| p <= Decimal::ZERO) {
+            Some("no price; run: interest prices update".to_string())
+        } else {
+            step(asset_type, ticker, options.round_lots).err()
+        };
+        if let Some(reason) = reason {
+            skipped.push(Skipped {
+                ticker: ticker.to_string(),
+                reason,
+            });
+            return None;
+        }
+        Some(Candidate {
+            ticker: ticker.to_string(),
+            price: price.expect("checked above"),
+            step: step(asset_type, ticker, options.round_lots).expect("checked above"),
+            value,
+            bought: Decimal::ZERO,
+        })
+    };
+
+    let mut groups = Vec::new();
+    let mut targeted = HashSet::new();
+    for (key, target_pct) in &targets {
+        let mut group = Group {
+            key: key.clone(),
+            target_pct: *target_pct,
+            current_value: Decimal::ZERO,
+            added: Decimal::ZERO,
+            candidates: Vec::new(),
+        };
+        match key.parse::<AssetType>() {
+            Ok(asset_type) => {
+                for p in report.positions.iter().filter(|p| {
+                    p.asset.asset_type == asset_type
+                        && !ticker_targets.contains(p.asset.ticker.as_str())
+                }) {
+                    let value = p.current_value.unwrap_or(p.total_cost);
+                    group.current_value += value;
+                    targeted.insert(p.asset.ticker.clone());
+                    group.candidates.extend(candidate(
+                        &p.asset.ticker,
+                        &p.asset.asset_type,
+                        p.current_price,
+                        p.valued_on.is_some(),
+                        value,
+                    ));
+                }
+            }
+            Err(()) => {
+                targeted.insert(key.clone());
+                if let Some(p) = report.positions.iter().find(|p| &p.asset.ticker == key) {
+                    let value = p.current_value.unwrap_or(p.total_cost);
+                    group.current_value = value;
+                    group.candidates.extend(candidate(
+                        key,
+                        &p.asset.asset_type,
+                        p.current_price,
+                        p.valued_on.is_some(),
+                        value,
+                    ));
+                } else {
+                    // A new asset: its latest close is the price
+                    let asset = db::get_asset_by_ticker(conn, key)?;
+                    let asset_type = asset.as_ref().map_or(AssetType::Unknown, |a| a.asset_type);
+                    let price = match asset.as_ref().and_then(|a| a.id) {
+                        Some(id) => {
+                            db::get_latest_price(conn, id)?.map(|p| p.price(db::PriceSeries::Close))
+                        }
+                        None => None,
+                    };
+                    group.candidates.extend(candidate(
+                        key,
+                        &asset_type,
+                        price,
+                        false,
+                        Decimal::ZERO,
+                    ));
+                }
+            }
+        }
+        groups.push(group);
+    }
+
+    let value_of =
+        |p: &crate::reports::portfolio::PositionSummary| p.current_value.unwrap_or(p.total_cost);
+    let total_value: Decimal = report.positions.iter().map(value_of).sum();
+    let untargeted_value: Decimal = report
+        .positions
+        .iter()
+        .filter(|p| !targeted.contains(&p.asset.ticker))
+        .map(value_of)
+        .sum();
+    let total_after = total_value + amount;
+
+    let mut cash = amount;
+    loop {
+        // The group furthest below target that can still take a lot
+        let mut ranked: Vec<(usize, Decimal)> = groups
+            .iter()
+            .enumerate()
+            .map(|(i, g)| (i, g.deficit(total_after)))
+            .filter(|(_, d)| *d > Decimal::ZERO)
+            .collect();
+        ranked.sort_by_key(|(_, d)| std::cmp::Reverse(*d));
+        let Some((position, (g, c))) = ranked
+            .iter()
+            .enumerate()
+            .find_map(|(pos, (g, d))| groups[*g].next_candidate(cash, *d).map(|c| (pos, (*g, c))))
+        else {
+            break;
+        };
+        // Fill in one go down to the next group's deficit, and up to the
+        // next smallest position of the group
+        let deficit = ranked[position].1;
+        let next = ranked.get(position + 1).map_or(Decimal::ZERO, |(_, d)| *d);
+        let value = groups[g].candidates[c].value;
+        let gap = groups[g]
+            .candidates
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != c)
+            .map(|(_, other)| other.value - value)
+            .filter(|gap| *gap >= Decimal::ZERO)
+            .min();
+        let lot_cost = groups[g].candidates[c].lot_cost();
+        let mut room = (deficit - next).min(cash);
+        if let Some(gap) = gap {
+            room = room.min(gap);
+        }
+        let lots = (room / lot_cost).floor().max(Decimal::ONE);
+        let spent = lots * lot_cost;
+        let group = &mut groups[g];
+        let candidate = &mut group.candidates[c];
+        candidate.bought += lots * candidate.step;
+        candidate.value += spent;
+        group.added += spent;
+        cash -= spent;
+    }
+
+    let mut buys = Vec::new();
+    for group in &groups {
+        for c in group.candidates.iter().filter(|c| c.bought > Decimal::ZERO) {
+            buys.push(SuggestedBuy {
+                ticker: c.ticker.clone(),
+                group: group.key.clone(),
+                quantity: c.bought.normalize(),
+                price: c.price,
+                amount: (c.bought * c.price).round_dp(2),
+            });
+        }
+    }
+    buys.sort_by_key(|b| std::cmp::Reverse(b.amount));
+
+    let spent = amount - cash;
+    Ok(ContributionPlan {
+        amount,
+        spent,
+        leftover: cash,
+        buys,
+        groups: groups
+            .iter()
+            .map(|g| GroupAllocation {
+                key: g.key.clone(),
+                target_pct: g.target_pct,
+                current_value: g.current_value,
+                current_pct: pct(g.current_value, total_value),
+                after_value: g.current_value + g.added,
+                after_pct: pct(g.current_value + g.added, total_value + spent),
+            })
+            .collect(),
+        untargeted_value,
+        skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Asset, Inconsistency, InconsistencyType};
+    use crate::reports::portfolio::PositionSummary;
+    use rust_decimal_macros::dec;
+
+    fn position(
+        ticker: &str,
+        asset_type: AssetType,
+        quantity: Decimal,
+        price: Decimal,
+    ) -> PositionSummary {
+        PositionSummary {
+            asset: Asset {
+                id: None,
+                ticker: ticker.to_string(),
+                asset_type,
+                name: None,
+                cnpj: None,
+                created_at: chrono::Utc::now(),
+                updated_at: chrono::Utc::now(),
+            },
+            quantity,
+            average_cost: price,
+            total_cost: quantity * price,
+            current_price: Some(price),
+            current_value: Some(quantity * price),
+            unrealized_pl: Some(Decimal::ZERO),
+            unrealized_pl_pct: Some(Decimal::ZERO),
+            valued_on: None,
+            fx: None,
+        }
+    }
+
+    #[test]
+    fn test_contribution_goes_to_groups_below_target() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        // 7000 in FIIs, 3600 in stocks; PETR4 has a blocking issue
+        let positions = vec![
+            position("XPLG11", AssetType::Fii, dec!(40), dec!(100)),
+            position("HGLG11", AssetType::Fii, dec!(20), dec!(150)),
+            position("PETR4", AssetType::Stock, dec!(100), dec!(30)),
+            position("VALE3", AssetType::Stock, dec!(10), dec!(60)),
+            position("BOVA11", AssetType::Etf, dec!(4), dec!(100)),
+        ];
+        let report = PortfolioReport {
+            total_cost: dec!(11000),
+            total_value: dec!(11000),
+            total_pl: Decimal::ZERO,
+            total_pl_pct: Decimal::ZERO,
+            positions,
+        };
+        db::insert_inconsistency(
+            &conn,
+            &Inconsistency {
+                id: None,
+                issue_type: InconsistencyType::MissingCostBasis,
+                status: InconsistencyStatus::Open,
+                severity: InconsistencySeverity::Blocking,
+                asset_id: None,
+                transaction_id: None,
+                ticker: Some("PETR4".to_string()),
+                trade_date: None,
+                quantity: None,
+                source: None,
+                source_ref: None,
+                missing_fields_json: None,
+                context_json: None,
+                resolution_action: None,
+                resolution_json: None,
+                created_at: None,
+                resolved_at: None,
+            },
+        )
+        .unwrap();
+        let mut targets = BTreeMap::new();
+        targets.insert("fii".to_string(), dec!(50));
+        targets.insert("STOCK".to_string(), dec!(50));
+
+        // After 3000: 7000 each. Stocks are 3400 short, FIIs on target
+        let plan = suggest(
+            &conn,
+            &report,
+            &targets,
+            dec!(3000),
+            SuggestOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(plan.untargeted_value, dec!(400));
+        assert!(plan.buys.iter().all(|b| b.group == "STOCK"));
+        // The smaller stock position is filled first
+        assert_eq!(plan.buys[0].ticker, "VALE3");
+        assert!(plan.leftover < dec!(60));
+        let stocks = &plan.groups[1];
+        assert_eq!(stocks.current_pct, dec!(32.73));
+        assert_eq!(stocks.after_value, stocks.current_value + plan.spent);
+
+        let skip = SuggestOptions {
+            skip_blocked: true,
+            ..Default::default()
+        };
+        let plan = suggest(&conn, &report, &targets, dec!(3000), skip).unwrap();
+        assert_eq!(plan.buys.len(), 1);
+        assert_eq!(plan.buys[0].ticker, "VALE3");
+        assert_eq!(plan.buys[0].quantity, dec!(50));
+        assert_eq!(plan.skipped[0].ticker, "PETR4");
+
+        // Standard lots of 100 VALE3 cost 6000: more than the contribution
+        let lots = SuggestOptions {
+            round_lots: true,
+            skip_blocked: true,
+        };
+        let plan = suggest(&conn, &report, &targets, dec!(3000), lots).unwrap();
+        assert!(plan.buys.is_empty());
+        assert_eq!(plan.leftover, dec!(3000));
+
+        targets.insert("BOVA11".to_string(), dec!(10));
+        assert!(suggest(&conn, &report, &targets, dec!(3000), skip).is_err());
+    }
+}
@@ -0,0 +1,188 @@
+//! Full rebuild of derived state from the imported facts.
+//!
+//! Portfolio snapshots and the loss carryforward ledger are caches keyed by
+//! fingerprints of the transactions. A manual fix that does not move the
+//! fingerprint (an asset type, a corporate action on an old date, a deleted
+//! income event) can leave them stale. `recalculate` drops every cache and
+//! recomputes it in order from transactions and recorded corporate actions:
+//! first the tax years from the earliest trade, then each portfolio snapshot
+//! date that existed before. Carryforward snapshots of years whose opening
+//! positions came from an IRPF import are facts, not caches, and are kept, as
+//! are the DARF payments recorded in the tax ledger.
+//!
+//! Everything cleared is recomputed lazily anyway, so a rebuild interrupted
+//! halfway leaves the database consistent, only slower on the next report.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::reports::portfolio::save_portfolio_snapshot;
+use crate::tax::irpf::generate_annual_report;
+use crate::tax::loss_carryforward::earliest_transaction_year;
+
+/// What was cleared and rebuilt
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RecalculateSummary {
+    pub snapshots_cleared: usize,
+    pub snapshots_rebuilt: usize,
+    pub carryforward_entries_cleared: usize,
+    pub carryforward_snapshots_cleared: usize,
+    /// Legacy cache tables (tax_events, positions)
+    pub legacy_rows_cleared: usize,
+    /// Monthly tax ledger rows without a recorded DARF payment
+    pub tax_ledger_rows_cleared: usize,
+    /// Years whose loss carryforward was recomputed, first and last
+    pub tax_years: Option<(i32, i32)>,
+    /// Years keeping the carryforward declared in an IRPF import
+    pub irpf_years_kept: Vec<i32>,
+}
+
+/// Wipe derived state and rebuild it through `through_year`
+pub fn recalculate(conn: &mut Connection, through_year: i32) -> Result<RecalculateSummary> {
+    if crate::db::portfolio::is_scoped() {
+        anyhow::bail!(
+            "recalculate rebuilds the state shared by all portfolios; run it without --portfolio or --declarant"
+        );
+    }
+
+    let mut summary = RecalculateSummary {
+        irpf_years_kept: irpf_import_years(conn)?,
+        ..Default::default()
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT snapshot_date, MAX(label) FROM position_snapshots
+         GROUP BY snapshot_date ORDER BY snapshot_date",
+    )?;
+    let snapshot_dates = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<Vec<(NaiveDate, Option<String>)>>>()?;
+    drop(stmt);
+
+    let tx = conn.transaction()?;
+    summary.snapshots_cleared = tx.execute("DELETE FROM position_snapshots", [])?;
+    tx.execute("DELETE FROM valuation_snapshots", [])?;
+    summary.carryforward_entries_cleared = tx.execute("DELETE FROM loss_carryforward", [])?;
+    let kept = summary
+        .irpf_years_kept
+        .iter()
+        .map(|y| y.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    summary.carryforward_snapshots_cleared = tx.execute(
+        &format!(
+            "DELETE FROM loss_carryforward_snapshots WHERE year NOT IN ({})",
+            kept
+        ),
+        [],
+    )?;
+    summary.tax_ledger_rows_cleared =
+        tx.execute("DELETE FROM tax_ledger WHERE paid_on IS NULL", [])?;
+    for table in ["tax_events", "positions"] {
+        summary.legacy_rows_cleared += tx.execute(&format!("DELETE FROM {}", table), [])?;
+    }
+    tx.commit()?;
+
+    if let Some(first) = earliest_transaction_year(conn)? {
+        if first <= through_year {
+            // Recomputes every year from the first trade and stores its snapshot
+            generate_annual_report(conn, through_year)?;
+            summary.tax_years = Some((first, through_year));
+        }
+    }
+
+    for (date, label) in snapshot_dates {
+        save_portfolio_snapshot(conn, date, label)?;
+        summary.snapshots_rebuilt += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Years of IRPF imports, whose carryforward snapshot holds declared losses
+fn irpf_import_years(conn: &Connection) -> Result<Vec<i32>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT trade_date FROM transactions WHERE source = 'IRPF_PDF'
+         ORDER BY trade_date",
+    )?;
+    let mut years: Vec<i32> = stmt
+        .query_map([], |row| row.get::<_, NaiveDate>(0))?
+        .map(|d| d.map(|d| d.year()))
+        .collect::<rusqlite::Result<_>>()?;
+    years.dedup();
+    Ok(years)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, AssetType, Transaction, TransactionType};
+    use crate::tax::loss_carryforward::load_snapshots;
+    use crate::tax::swing_trade::TaxCategory;
+    use rust_decimal::Decimal;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_recalculate_replaces_stale_caches() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id = db::insert_asset(&conn, "PETR4", &AssetType::Stock, None).unwrap();
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+        for (date, tx_type, price) in [
+            (d(2023, 2, 1), TransactionType::Buy, dec!(40)),
+            (d(2023, 5, 2), TransactionType::Sell, dec!(30)),
+        ] {
+            db::insert_transaction(
+                &conn,
+                &Transaction {
+                    id: None,
+                    asset_id,
+                    transaction_type: tx_type,
+                    trade_date: date,
+                    settlement_date: None,
+                    quantity: dec!(100),
+                    price_per_unit: price,
+                    total_cost: dec!(100) * price,
+                    fees: Decimal::ZERO,
+                    is_day_trade: false,
+                    quota_issuance_date: None,
+                    notes: None,
+                    source: "TEST".to_string(),
+                    created_at: chrono::Utc::now(),
+                },
+            )
+            .unwrap();
+        }
+
+        generate_annual_report(&conn, 2023).unwrap();
+        save_portfolio_snapshot(&mut conn, d(2023, 3, 1), Some("march".to_string())).unwrap();
+        // A stale cache the fingerprints cannot catch
+        conn.execute(
+            "UPDATE loss_carryforward_snapshots SET ending_remaining_amount = '999'",
+            [],
+        )
+        .unwrap();
+        conn.execute("UPDATE position_snapshots SET quantity = '1'", [])
+            .unwrap();
+
+        let summary = recalculate(&mut conn, 2023).unwrap();
+        assert_eq!(summary.tax_years, Some((2023, 2023)));
+        assert_eq!(summary.snapshots_rebuilt, 1);
+        assert!(summary.irpf_years_kept.is_empty());
+
+        let carry = &load_snapshots(&conn).unwrap()[&2023].ending_carry;
+        assert_eq!(carry[&TaxCategory::StockSwingTrade], dec!(1000));
+        let (quantity, label): (Decimal, Option<String>) = conn
+            .query_row(
+                "SELECT quantity, label FROM position_snapshots WHERE snapshot_date = ?1",
+                [d(2023, 3, 1)],
+                |row| Ok((db::get_decimal_value(row, 0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(quantity, dec!(100));
+        assert_eq!(label.as_deref(), Some("march"));
+    }
+}
@@ -0,0 +1,279 @@
+//! Historical risk of the holdings: volatility, drawdown, Sharpe and Sortino.
+//!
+//! Each held asset is measured on its daily closes in `price_history`, using
+//! the adjusted series when every close in the period has one, so splits
+//! and dividends do not show up as falls. The portfolio is measured on the
+//! daily sub-period returns of the time-weighted return, which leave
+//! contributions and withdrawals out. Figures are annualized over 252
+//! trading days, and Sharpe and Sortino use the CDI accrued over the same
+//! days as the risk-free rate.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::{Decimal, MathematicalOps};
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::db::{self, Benchmark};
+use crate::reports::compare::max_drawdown_pct;
+use crate::reports::twr::{snapshot_twr, Valuation};
+
+/// Trading days in a year, for annualizing daily figures
+pub const TRADING_DAYS: i64 = 252;
+
+/// Fewer daily returns than this say too little about risk
+pub const MIN_OBSERVATIONS: usize = 20;
+
+/// Risk figures of one daily return series
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RiskMetrics {
+    /// Daily returns measured
+    pub observations: usize,
+    /// Annualized return, in %
+    pub return_pct: Option<Decimal>,
+    /// Annualized standard deviation of daily returns, in %
+    pub volatility_pct: Option<Decimal>,
+    /// Largest fall from a previous high (negative or zero), in %
+    pub max_drawdown_pct: Option<Decimal>,
+    /// Excess return over the CDI per unit of volatility
+    pub sharpe: Option<Decimal>,
+    /// Excess return over the CDI per unit of downside deviation
+    pub sortino: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetRisk {
+    pub ticker: String,
+    /// Measured on adjusted closes rather than raw ones
+    pub adjusted: bool,
+    pub metrics: RiskMetrics,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskReport {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    /// Annualized CDI over the period, in %; None without stored rates
+    pub cdi_pct: Option<Decimal>,
+    pub portfolio: RiskMetrics,
+    pub assets: Vec<AssetRisk>,
+}
+
+/// Risk of the portfolio and of each asset held at `to`, between `from` and `to`
+pub fn risk_report(conn: &mut Connection, from: NaiveDate, to: NaiveDate) -> Result<RiskReport> {
+    let cdi = annual_cdi(conn, from, to)?;
+
+    // Weekday holidays have no closes: no trading, no return
+    let trading_days: HashSet<NaiveDate> = conn
+        .prepare(
+            "SELECT DISTINCT price_date FROM price_history
+             WHERE price_date > ?1 AND price_date <= ?2",
+        )?
+        .query_map(rusqlite::params![from, to], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    let twr = snapshot_twr(conn, from, to, None, Valuation::Daily)?;
+    let mut returns = Vec::with_capacity(twr.points.len());
+    let mut prev_value = twr.start_value;
+    for point in &twr.points {
+        let traded = trading_days.contains(&point.date) || point.net_flow != Decimal::ZERO;
+        // Days before the first purchase hold nothing to measure
+        if traded && (prev_value > Decimal::ZERO || point.net_flow > Decimal::ZERO) {
+            returns.push(point.period_return / Decimal::ONE_HUNDRED);
+        }
+        prev_value = point.value;
+    }
+    let portfolio = metrics(&returns, cdi);
+
+    let held = crate::reports::calculate_portfolio_at_date(conn, to, None)?;
+    let mut assets = Vec::new();
+    for position in held.positions.iter().filter(|p| p.quantity > Decimal::ZERO) {
+        let Some(asset_id) = position.asset.id else {
+            continue;
+        };
+        let (closes, adjusted) = daily_closes(conn, asset_id, from, to)?;
+        let returns: Vec<Decimal> = closes
+            .windows(2)
+            .filter(|w| w[0].1 > Decimal::ZERO)
+            .map(|w| w[1].1 / w[0].1 - Decimal::ONE)
+            .collect();
+        assets.push(AssetRisk {
+            ticker: position.asset.ticker.clone(),
+            adjusted,
+            metrics: metrics(&returns, cdi),
+        });
+    }
+    assets.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+
+    Ok(RiskReport {
+        start_date: from,
+        end_date: to,
+        cdi_pct: cdi.map(|c| (c * Decimal::ONE_HUNDRED).round_dp(2)),
+        portfolio,
+        assets,
+    })
+}
+
+/// Closes from the last one on or before `from` up to `to`; adjusted when
+/// every row has an adjusted close
+fn daily_closes(
+    conn: &Connection,
+    asset_id: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<(Vec<(NaiveDate, Decimal)>, bool)> {
+    let mut stmt = conn.prepare(
+        "SELECT price_date, close_price, adjusted_close FROM price_history
+         WHERE asset_id = ?1 AND price_date <= ?3
+           AND price_date >= COALESCE(
+               (SELECT MAX(price_date) FROM price_history WHERE asset_id = ?1 AND price_date <= ?2),
+               ?2)
+         ORDER BY price_date",
+    )?;
+    let rows = stmt
+        .query_map(rusqlite::params![asset_id, from, to], |row| {
+            Ok((
+                row.get::<_, NaiveDate>(0)?,
+                db::get_decimal_value(row, 1)?,
+                db::get_optional_decimal_value(row, 2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let adjusted = !rows.is_empty() && rows.iter().all(|(_, _, adj)| adj.is_some());
+    let closes = rows
+        .into_iter()
+        .map(|(date, close, adj)| {
+            (
+                date,
+                if adjusted {
+                    adj.unwrap_or(close)
+                } else {
+                    close
+                },
+            )
+        })
+        .collect();
+    Ok((closes, adjusted))
+}
+
+/// CDI compounded over the stored days in `[from, to)`, as a yearly rate
+fn annual_cdi(conn: &Connection, from: NaiveDate, to: NaiveDate) -> Result<Option<Decimal>> {
+    let rates: Vec<Decimal> = db::get_benchmark_values(conn, Benchmark::Cdi, from, to)?
+        .into_iter()
+        .filter(|v| v.value_date < to)
+        .map(|v| v.value)
+        .collect();
+    if rates.is_empty() {
+        return Ok(None);
+    }
+    let factor = rates.iter().fold(Decimal::ONE, |acc, r| {
+        acc * (Decimal::ONE + r / Decimal::ONE_HUNDRED)
+    });
+    Ok(annualize(factor, rates.len()))
+}
+
+/// Yearly rate from a growth factor over `days` trading days
+fn annualize(factor: Decimal, days: usize) -> Option<Decimal> {
+    if factor <= Decimal::ZERO || days == 0 {
+        return None;
+    }
+    factor
+        .checked_powd(Decimal::from(TRADING_DAYS) / Decimal::from(days))
+        .map(|f| f - Decimal::ONE)
+}
+
+/// Risk figures of daily returns (fractions, not %), with `cdi` as the
+/// yearly risk-free rate
+pub fn metrics(returns: &[Decimal], cdi: Option<Decimal>) -> RiskMetrics {
+    let n = returns.len();
+    let mut level = Decimal::ONE_HUNDRED;
+    let mut index = vec![(NaiveDate::MIN, level)];
+    for r in returns {
+        level *= Decimal::ONE + r;
+        index.push((NaiveDate::MIN, level));
+    }
+    let mut result = RiskMetrics {
+        observations: n,
+        max_drawdown_pct: max_drawdown_pct(&index),
+        ..Default::default()
+    };
+    if n < MIN_OBSERVATIONS {
+        return result;
+    }
+
+    let pct = |v: Decimal| (v * Decimal::ONE_HUNDRED).round_dp(2);
+    let year = Decimal::from(TRADING_DAYS).sqrt().unwrap_or_default();
+    let count = Decimal::from(n);
+    let mean = returns.iter().sum::<Decimal>() / count;
+    let variance = returns
+        .iter()
+        .map(|r| (*r - mean) * (*r - mean))
+        .sum::<Decimal>()
+        / Decimal::from(n - 1);
+    let volatility = variance.sqrt().map(|sd| sd * year);
+    let annual_return = annualize(level / Decimal::ONE_HUNDRED, n);
+
+    result.return_pct = annual_return.map(pct);
+    result.volatility_pct = volatility.map(pct);
+
+    let (Some(cdi), Some(annual_return)) = (cdi, annual_return) else {
+        return result;
+    };
+    let excess = annual_return - cdi;
+    result.sharpe = volatility
+        .filter(|v| *v > Decimal::ZERO)
+        .map(|v| (excess / v).round_dp(2));
+
+    // Only the days that fell short of the daily CDI count as risk
+    let daily_cdi = (Decimal::ONE + cdi)
+        .checked_powd(Decimal::ONE / Decimal::from(TRADING_DAYS))
+        .map(|f| f - Decimal::ONE)
+        .unwrap_or_default();
+    let downside = returns
+        .iter()
+        .map(|r| (*r - daily_cdi).min(Decimal::ZERO))
+        .map(|d| d * d)
+        .sum::<Decimal>()
+        / count;
+    result.sortino = downside
+        .sqrt()
+        .map(|d| d * year)
+        .filter(|d| *d > Decimal::ZERO)
+        .map(|d| (excess / d).round_dp(2));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_metrics_of_alternating_returns() {
+        // +2%, -1% repeated: steady gains with some downside days
+        let returns: Vec<Decimal> = (0..40)
+            .map(|i| if i % 2 == 0 { dec!(0.02) } else { dec!(-0.01) })
+            .collect();
+        let m = metrics(&returns, Some(dec!(0.10)));
+        assert_eq!(m.observations, 40);
+        // Daily standard deviation of 1.519%, times the square root of 252
+        assert_eq!(m.volatility_pct, Some(dec!(24.12)));
+        assert_eq!(m.max_drawdown_pct, Some(dec!(-1)));
+        // 1.02 × 0.99 compounded 126 times in a year
+        assert_eq!(m.return_pct, Some(dec!(241.71)));
+        assert_eq!(m.sharpe, Some(dec!(9.61)));
+        // Only the falls count against Sortino, so it beats Sharpe
+        assert!(m.sortino.unwrap() > m.sharpe.unwrap());
+
+        // Without the CDI there is nothing to measure the excess against
+        let m = metrics(&returns, None);
+        assert_eq!(m.sharpe, None);
+        assert_eq!(m.sortino, None);
+
+        // Too few days: drawdown only
+        let m = metrics(&returns[..5], Some(dec!(0.10)));
+        assert_eq!(m.volatility_pct, None);
+        assert_eq!(m.max_drawdown_pct, Some(dec!(-1)));
+    }
+}
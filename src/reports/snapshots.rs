@@ -0,0 +1,524 @@
+//! Persistent valuation snapshots with fingerprint-based invalidation.
+//!
+//! Valuing a past day replays every trade up to it, so each valued day is
+//! stored: the portfolio total in `valuation_snapshots` and the per-asset
+//! rows in `position_snapshots`, next to a fingerprint of the data the day
+//! was valued from (trades, corporate actions, renames, exchanges,
+//! amortizations, manual valuations, closes and the exchange rates of assets
+//! quoted abroad dated up to it). Like the tax snapshots, a stored day is
+//! reused while its fingerprint holds. An import only changes the
+//! fingerprints from its earliest date on, so only those days are valued
+//! again.
+//!
+//! The fingerprints of every day up to a date come from one ordered pass over
+//! those tables, which keeps checking a few years of daily values cheap.
+//! Snapshots hold the aggregate of all portfolios and are bypassed when the
+//! session is scoped to some of them.
+
+use anyhow::Result;
+use blake3::Hasher;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::db::{self, get_decimal_value, get_optional_decimal_value, Asset, AssetType};
+use crate::pricing::fx::FxConversion;
+use crate::reports::portfolio::{calculate_portfolio_at_date, PortfolioReport, PositionSummary};
+
+/// Everything a valuation depends on, as (date it takes effect, description)
+const INPUTS: &[&str] = &[
+    "SELECT trade_date, 'T|' || id || '|' || asset_id || '|' || transaction_type || '|'
+            || quantity || '|' || price_per_unit || '|' || total_cost
+     FROM transactions WHERE trade_date <= ?1",
+    "SELECT ex_date, 'CA|' || id || '|' || asset_id || '|' || action_type || '|'
+            || quantity_adjustment || '|' || (applied_at IS NOT NULL)
+     FROM corporate_actions WHERE ex_date <= ?1",
+    "SELECT effective_date, 'RN|' || id || '|' || from_asset_id || '|' || to_asset_id
+     FROM asset_renames WHERE effective_date <= ?1",
+    "SELECT effective_date, 'EX|' || id || '|' || event_type || '|' || from_asset_id || '|'
+            || to_asset_id || '|' || to_quantity || '|' || allocated_cost || '|' || cash_amount
+     FROM asset_exchanges WHERE effective_date <= ?1",
+    "SELECT event_date, 'AM|' || id || '|' || asset_id || '|' || total_amount
+     FROM income_events WHERE event_type = 'AMORTIZATION' AND event_date <= ?1",
+    "SELECT valuation_date, 'VAL|' || asset_id || '|' || value
+     FROM asset_valuations WHERE valuation_date <= ?1",
+    // Rates of the currencies assets are quoted in, so flagging one re-values
+    "SELECT rate_date, 'FX|' || pair || '|' || rate
+     FROM fx_rates WHERE rate_date <= ?1
+       AND pair IN (SELECT currency || 'BRL' FROM assets WHERE currency IS NOT NULL)",
+    // Closes are summed up per day: a new or re-fetched close changes its day
+    "SELECT price_date, 'P|' || COUNT(*) || '|' || TOTAL(close_price) || '|'
+            || COALESCE(MAX(created_at), '')
+     FROM price_history WHERE price_date <= ?1 GROUP BY price_date",
+];
+
+/// Cumulative hash of the valuation inputs dated up to each day
+pub struct Fingerprints {
+    through: NaiveDate,
+    /// Hash after the inputs of each date that has any, in date order
+    checkpoints: Vec<(NaiveDate, String)>,
+    empty: String,
+}
+
+impl Fingerprints {
+    /// Fingerprints of every day up to `through`
+    pub fn load(conn: &Connection, through: NaiveDate) -> Result<Self> {
+        let mut inputs: Vec<(NaiveDate, String)> = Vec::new();
+        for sql in INPUTS {
+            let mut stmt = conn.prepare(sql)?;
+            let rows = stmt.query_map([through], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            for row in rows {
+                inputs.push(row?);
+            }
+        }
+        inputs.sort();
+
+        let mut hasher = Hasher::new();
+        let empty = hasher.finalize().to_hex().to_string();
+        let mut checkpoints: Vec<(NaiveDate, String)> = Vec::new();
+        for (i, (date, line)) in inputs.iter().enumerate() {
+            hasher.update(line.as_bytes());
+            hasher.update(b"\n");
+            if inputs.get(i + 1).is_none_or(|(next, _)| next != date) {
+                checkpoints.push((*date, hasher.finalize().to_hex().to_string()));
+            }
+        }
+        Ok(Self {
+            through,
+            checkpoints,
+            empty,
+        })
+    }
+
+    /// Fingerprint of the valuation at the end of `date` (up to `through`)
+    pub fn at(&self, date: NaiveDate) -> &str {
+        debug_assert!(
+            date <= self.through,
+            "fingerprints loaded up to {}",
+            self.through
+        );
+        match self.checkpoints.partition_point(|(d, _)| *d <= date) {
+            0 => &self.empty,
+            n => &self.checkpoints[n - 1].1,
+        }
+    }
+}
+
+/// Portfolio totals at the end of one day
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DayValue {
+    pub date: NaiveDate,
+    pub market_value: Decimal,
+    /// Cost basis of the positions held
+    pub total_cost: Decimal,
+}
+
+/// End-of-day portfolio, from a valid snapshot or valued and stored
+pub fn report(conn: &Connection, date: NaiveDate) -> Result<PortfolioReport> {
+    if db::portfolio::is_scoped() {
+        return calculate_portfolio_at_date(conn, date, None);
+    }
+    let fingerprint = Fingerprints::load(conn, date)?.at(date).to_string();
+    if let Some(report) = load(conn, date, &fingerprint)? {
+        return Ok(report);
+    }
+    let report = calculate_portfolio_at_date(conn, date, None)?;
+    store(conn, date, &report, &fingerprint, None)?;
+    // Read back, so a day reads the same whether valued now or earlier
+    Ok(load(conn, date, &fingerprint)?.unwrap_or(report))
+}
+
+/// Totals at each of `dates`, restricted to `asset_ids` when given. Days
+/// without a valid snapshot are valued and stored first.
+pub fn day_values(
+    conn: &Connection,
+    dates: &[NaiveDate],
+    asset_ids: Option<&HashSet<i64>>,
+) -> Result<Vec<DayValue>> {
+    let (Some(&first), Some(&last)) = (dates.iter().min(), dates.iter().max()) else {
+        return Ok(Vec::new());
+    };
+    if db::portfolio::is_scoped() {
+        return dates
+            .iter()
+            .map(|&date| {
+                let report = calculate_portfolio_at_date(conn, date, None)?;
+                Ok(totals(date, retained(&report.positions, asset_ids)))
+            })
+            .collect();
+    }
+
+    let fingerprints = Fingerprints::load(conn, last)?;
+    let stored = stored_fingerprints(conn, first, last)?;
+    for &date in dates {
+        let fingerprint = fingerprints.at(date);
+        if stored.get(&date).map(String::as_str) != Some(fingerprint) {
+            let report = calculate_portfolio_at_date(conn, date, None)?;
+            store(conn, date, &report, fingerprint, None)?;
+        }
+    }
+
+    let values = match asset_ids {
+        None => stored_totals(conn, first, last)?,
+        Some(ids) => stored_asset_totals(conn, first, last, ids)?,
+    };
+    Ok(dates
+        .iter()
+        .map(|&date| {
+            values.get(&date).copied().unwrap_or(DayValue {
+                date,
+                market_value: Decimal::ZERO,
+                total_cost: Decimal::ZERO,
+            })
+        })
+        .collect())
+}
+
+fn retained<'a>(
+    positions: &'a [PositionSummary],
+    asset_ids: Option<&'a HashSet<i64>>,
+) -> impl Iterator<Item = &'a PositionSummary> {
+    positions
+        .iter()
+        .filter(move |p| asset_ids.is_none_or(|ids| p.asset.id.is_some_and(|id| ids.contains(&id))))
+}
+
+/// Price a position is stored at: its close, or its average cost when unpriced
+fn market_price(position: &PositionSummary) -> Decimal {
+    position.current_price.unwrap_or(position.average_cost)
+}
+
+fn market_value(position: &PositionSummary) -> Decimal {
+    position
+        .current_value
+        .unwrap_or_else(|| market_price(position) * position.quantity)
+}
+
+fn totals<'a>(date: NaiveDate, positions: impl Iterator<Item = &'a PositionSummary>) -> DayValue {
+    let mut value = DayValue {
+        date,
+        market_value: Decimal::ZERO,
+        total_cost: Decimal::ZERO,
+    };
+    for position in positions {
+        value.market_value += market_value(position);
+        value.total_cost += position.total_cost;
+    }
+    value
+}
+
+/// Store the valuation of `date` under `fingerprint`, replacing the day's
+/// earlier snapshot (whose label is kept unless a new one is given)
+pub fn store(
+    conn: &Connection,
+    date: NaiveDate,
+    report: &PortfolioReport,
+    fingerprint: &str,
+    label: Option<String>,
+) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    let label = match label {
+        Some(label) => Some(label),
+        None => tx.query_row(
+            "SELECT MAX(label) FROM position_snapshots WHERE snapshot_date = ?1",
+            [date],
+            |row| row.get(0),
+        )?,
+    };
+    tx.execute(
+        "DELETE FROM position_snapshots WHERE snapshot_date = ?1",
+        [date],
+    )?;
+
+    for position in &report.positions {
+        let asset_id = position
+            .asset
+            .id
+            .ok_or_else(|| anyhow::anyhow!("Asset missing id for snapshot"))?;
+        let market_value = market_value(position);
+        let unrealized_pl = position
+            .unrealized_pl
+            .unwrap_or_else(|| market_value - position.total_cost);
+
+        tx.execute(
+            "INSERT INTO position_snapshots (
+                snapshot_date, asset_id, quantity, average_cost, market_price,
+                market_value, unrealized_pl, tx_fingerprint, label, valued_on, total_cost,
+                fx_currency, fx_rate, fx_date
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                date,
+                asset_id,
+                position.quantity.to_string(),
+                position.average_cost.to_string(),
+                market_price(position).to_string(),
+                market_value.to_string(),
+                unrealized_pl.to_string(),
+                fingerprint,
+                label,
+                position.valued_on,
+                position.total_cost.to_string(),
+                position.fx.as_ref().map(|fx| fx.currency.as_str()),
+                position.fx.as_ref().map(|fx| fx.rate.to_string()),
+                position.fx.as_ref().map(|fx| fx.rate_date),
+            ],
+        )?;
+    }
+
+    let day = totals(date, report.positions.iter());
+    tx.execute(
+        "INSERT OR REPLACE INTO valuation_snapshots (
+            snapshot_date, market_value, total_cost, positions, data_fingerprint
+        ) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            date,
+            day.market_value.to_string(),
+            day.total_cost.to_string(),
+            report.positions.len() as i64,
+            fingerprint,
+        ],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Stored snapshot of `date` when it was valued from data matching `fingerprint`
+pub fn load(
+    conn: &Connection,
+    date: NaiveDate,
+    fingerprint: &str,
+) -> Result<Option<PortfolioReport>> {
+    let stored = stored_fingerprints(conn, date, date)?;
+    if stored.get(&date).map(String::as_str) != Some(fingerprint) {
+        return Ok(None);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT ps.asset_id, ps.quantity, ps.average_cost, ps.market_price, ps.market_value,
+                ps.unrealized_pl, a.ticker, a.asset_type, a.name, a.cnpj,
+                a.created_at, a.updated_at, ps.valued_on, ps.total_cost,
+                ps.fx_currency, ps.fx_rate, ps.fx_date
+         FROM position_snapshots ps
+         JOIN assets a ON ps.asset_id = a.id
+         WHERE ps.snapshot_date = ?1
+         ORDER BY ps.market_value DESC",
+    )?;
+    let positions = stmt
+        .query_map([date], |row| {
+            let asset_type: AssetType = row
+                .get::<_, String>(7)?
+                .parse()
+                .unwrap_or(AssetType::Unknown);
+            let quantity = get_decimal_value(row, 1)?;
+            let average_cost = get_decimal_value(row, 2)?;
+            let unrealized_pl = get_decimal_value(row, 5)?;
+            let position_cost =
+                get_optional_decimal_value(row, 13)?.unwrap_or_else(|| average_cost * quantity);
+            let unrealized_pl_pct = if position_cost > Decimal::ZERO {
+                (unrealized_pl / position_cost) * Decimal::from(100)
+            } else {
+                Decimal::ZERO
+            };
+            Ok(PositionSummary {
+                asset: Asset {
+                    id: Some(row.get(0)?),
+                    ticker: row.get(6)?,
+                    asset_type,
+                    name: row.get(8)?,
+                    cnpj: row.get(9)?,
+                    created_at: row.get(10)?,
+                    updated_at: row.get(11)?,
+                },
+                quantity,
+                average_cost,
+                total_cost: position_cost,
+                current_price: Some(get_decimal_value(row, 3)?),
+                current_value: Some(get_decimal_value(row, 4)?),
+                unrealized_pl: Some(unrealized_pl),
+                unrealized_pl_pct: Some(unrealized_pl_pct),
+                valued_on: row.get(12)?,
+                fx: match (
+                    row.get(14)?,
+                    get_optional_decimal_value(row, 15)?,
+                    row.get(16)?,
+                ) {
+                    (Some(currency), Some(rate), Some(rate_date)) => Some(FxConversion {
+                        currency,
+                        rate,
+                        rate_date,
+                    }),
+                    _ => None,
+                },
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let total_cost: Decimal = positions.iter().map(|p| p.total_cost).sum();
+    let total_value: Decimal = positions.iter().filter_map(|p| p.current_value).sum();
+    let total_pl = total_value - total_cost;
+    let total_pl_pct = if total_cost > Decimal::ZERO {
+        (total_pl / total_cost) * Decimal::from(100)
+    } else {
+        Decimal::ZERO
+    };
+
+    Ok(Some(PortfolioReport {
+        positions,
+        total_cost,
+        total_value,
+        total_pl,
+        total_pl_pct,
+    }))
+}
+
+fn stored_fingerprints(
+    conn: &Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<HashMap<NaiveDate, String>> {
+    let mut stmt = conn.prepare(
+        "SELECT snapshot_date, data_fingerprint FROM valuation_snapshots
+         WHERE snapshot_date BETWEEN ?1 AND ?2",
+    )?;
+    let rows = stmt
+        .query_map([from, to], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(rows)
+}
+
+fn stored_totals(
+    conn: &Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<HashMap<NaiveDate, DayValue>> {
+    let mut stmt = conn.prepare(
+        "SELECT snapshot_date, market_value, total_cost FROM valuation_snapshots
+         WHERE snapshot_date BETWEEN ?1 AND ?2",
+    )?;
+    let rows = stmt
+        .query_map([from, to], |row| {
+            let date = row.get(0)?;
+            Ok((
+                date,
+                DayValue {
+                    date,
+                    market_value: get_decimal_value(row, 1)?,
+                    total_cost: get_decimal_value(row, 2)?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok(rows)
+}
+
+fn stored_asset_totals(
+    conn: &Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+    asset_ids: &HashSet<i64>,
+) -> Result<HashMap<NaiveDate, DayValue>> {
+    let mut stmt = conn.prepare(
+        "SELECT snapshot_date, asset_id, market_value,
+                COALESCE(total_cost, CAST(average_cost AS REAL) * CAST(quantity AS REAL))
+         FROM position_snapshots
+         WHERE snapshot_date BETWEEN ?1 AND ?2",
+    )?;
+    let mut values: HashMap<NaiveDate, DayValue> = HashMap::new();
+    let rows = stmt.query_map([from, to], |row| {
+        Ok((
+            row.get::<_, NaiveDate>(0)?,
+            row.get::<_, i64>(1)?,
+            get_decimal_value(row, 2)?,
+            get_decimal_value(row, 3)?,
+        ))
+    })?;
+    for row in rows {
+        let (date, asset_id, market_value, cost) = row?;
+        if !asset_ids.contains(&asset_id) {
+            continue;
+        }
+        let value = values.entry(date).or_insert(DayValue {
+            date,
+            market_value: Decimal::ZERO,
+            total_cost: Decimal::ZERO,
+        });
+        value.market_value += market_value;
+        value.total_cost += cost;
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn d(m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, m, day).unwrap()
+    }
+
+    /// Days valued since their `computed_at` was reset
+    fn revalued(conn: &Connection) -> Vec<NaiveDate> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT snapshot_date FROM valuation_snapshots
+                 WHERE computed_at != '2000-01-01 00:00:00' ORDER BY snapshot_date",
+            )
+            .unwrap();
+        stmt.query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_day_values_revalue_only_days_whose_data_changed() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'XPLG11', 'FII');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source)
+                 VALUES (1, 'BUY', '2024-01-10', '10', '100', '1000', '0', 'TEST');
+             INSERT INTO price_history (asset_id, price_date, close_price, source, created_at)
+                 VALUES (1, '2024-01-31', '105', 'TEST', '2024-02-01 10:00:00'),
+                        (1, '2024-02-29', '108', 'TEST', '2024-03-01 10:00:00');",
+        )
+        .unwrap();
+
+        // The empty portfolio before the first trade is stored too
+        let dates = [d(1, 5), d(1, 31), d(2, 29)];
+        let values = day_values(&conn, &dates, None).unwrap();
+        let market: Vec<Decimal> = values.iter().map(|v| v.market_value).collect();
+        assert_eq!(market, vec![dec!(0), dec!(1050), dec!(1080)]);
+        assert_eq!(values[2].total_cost, dec!(1000));
+        assert_eq!(revalued(&conn), dates.to_vec());
+        conn.execute(
+            "UPDATE valuation_snapshots SET computed_at = '2000-01-01 00:00:00'",
+            [],
+        )
+        .unwrap();
+        day_values(&conn, &dates, None).unwrap();
+        assert!(revalued(&conn).is_empty());
+
+        // A re-fetched February close only changes the days from then on
+        conn.execute(
+            "INSERT OR REPLACE INTO price_history (asset_id, price_date, close_price, source, created_at)
+             VALUES (1, '2024-02-29', '110', 'TEST', '2024-03-02 10:00:00')",
+            [],
+        )
+        .unwrap();
+        let values = day_values(&conn, &dates, None).unwrap();
+        assert_eq!(values[2].market_value, dec!(1100));
+        assert_eq!(revalued(&conn), vec![d(2, 29)]);
+
+        // Restricted to a group, the per-asset rows of the same days are used
+        let none = HashSet::from([2]);
+        let values = day_values(&conn, &dates, Some(&none)).unwrap();
+        assert!(values.iter().all(|v| v.market_value == Decimal::ZERO));
+        assert_eq!(report(&conn, d(2, 29)).unwrap().total_value, dec!(1100));
+    }
+}
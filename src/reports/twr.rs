@@ -0,0 +1,227 @@
+//! Time-weighted return chained over valuation snapshots.
+//!
+//! The portfolio is valued on every weekday of the period (or at each month
+//! end) from `price_history`, through the stored valuation snapshots. Each
+//! sub-period return removes that sub-period's cash flows with Modified
+//! Dietz weighting, and the returns are chained, so contributions and
+//! withdrawals never count as performance. With daily valuation every flow
+//! falls on a valuation date and the result is an exact TWR.
+
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use rusqlite::Connection;
+use rust_decimal::prelude::*;
+use rust_decimal::MathematicalOps;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::reports::snapshots::day_values;
+use crate::reports::xirr::trade_and_income_flows;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Valuation {
+    Daily,
+    Monthly,
+}
+
+/// Portfolio value at the end of one sub-period
+#[derive(Debug, Clone, Serialize)]
+pub struct TwrPoint {
+    pub date: NaiveDate,
+    pub value: Decimal,
+    /// Money put in (positive) or taken out (negative) during the sub-period
+    pub net_flow: Decimal,
+    /// Sub-period return, percentage
+    pub period_return: Decimal,
+    /// Return chained from the start of the period, percentage
+    pub cumulative_return: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TwrReport {
+    pub valuation: Valuation,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub start_value: Decimal,
+    /// Chained return over the whole period, percentage
+    pub twr: Decimal,
+    /// Annualized TWR, only for periods of at least a year
+    pub annualized: Option<Decimal>,
+    pub points: Vec<TwrPoint>,
+}
+
+/// Chain sub-period returns between `start` and `end`, restricted to
+/// `asset_ids` when given
+pub fn snapshot_twr(
+    conn: &mut Connection,
+    start: NaiveDate,
+    end: NaiveDate,
+    asset_ids: Option<&HashSet<i64>>,
+    valuation: Valuation,
+) -> Result<TwrReport> {
+    let mut flows: BTreeMap<NaiveDate, Decimal> = BTreeMap::new();
+    for (_, date, amount) in trade_and_income_flows(conn, start, end, asset_ids)? {
+        // Investor-signed (negative = money in); the portfolio sees the opposite
+        *flows.entry(date).or_default() -= amount;
+    }
+
+    let mut dates = vec![start];
+    dates.extend(valuation_dates(
+        start,
+        end,
+        valuation,
+        flows.keys().copied(),
+    ));
+    let values = day_values(conn, &dates, asset_ids)?;
+    let start_value = values[0].market_value;
+    let mut prev_date = start;
+    let mut prev_value = start_value;
+    let mut factor = Decimal::ONE;
+    let mut points = Vec::new();
+
+    for day in &values[1..] {
+        let (date, value) = (day.date, day.market_value);
+        let days = Decimal::from((date - prev_date).num_days());
+        let mut net_flow = Decimal::ZERO;
+        let mut weighted_flow = Decimal::ZERO;
+        for (flow_date, amount) in flows.range(prev_date.succ_opt().unwrap_or(prev_date)..=date) {
+            net_flow += amount;
+            // A flow at the end of the sub-period has no time to earn anything
+            weighted_flow += *amount * Decimal::from((date - *flow_date).num_days()) / days;
+        }
+
+        let base = prev_value + weighted_flow;
+        let period_return = if base > Decimal::ZERO {
+            (value - prev_value - net_flow) / base
+        } else {
+            Decimal::ZERO
+        };
+        factor *= Decimal::ONE + period_return;
+        points.push(TwrPoint {
+            date,
+            value,
+            net_flow,
+            period_return: (period_return * Decimal::ONE_HUNDRED).round_dp(4),
+            cumulative_return: ((factor - Decimal::ONE) * Decimal::ONE_HUNDRED).round_dp(4),
+        });
+        prev_date = date;
+        prev_value = value;
+    }
+
+    let days = (end - start).num_days();
+    let annualized = (days >= 365 && factor > Decimal::ZERO)
+        .then(|| factor.checked_powd(Decimal::from(365) / Decimal::from(days)))
+        .flatten()
+        .map(|f| ((f - Decimal::ONE) * Decimal::ONE_HUNDRED).round_dp(2));
+
+    Ok(TwrReport {
+        valuation,
+        start_date: start,
+        end_date: end,
+        start_value,
+        twr: ((factor - Decimal::ONE) * Decimal::ONE_HUNDRED).round_dp(2),
+        annualized,
+        points,
+    })
+}
+
+/// Valuation dates after `start` up to and including `end`: weekdays and
+/// flow dates for daily valuation, month ends for monthly
+fn valuation_dates(
+    start: NaiveDate,
+    end: NaiveDate,
+    valuation: Valuation,
+    flow_dates: impl Iterator<Item = NaiveDate>,
+) -> Vec<NaiveDate> {
+    let mut dates: Vec<NaiveDate> = Vec::new();
+    let mut day = start + Duration::days(1);
+    while day <= end {
+        let keep = match valuation {
+            Valuation::Daily => !matches!(day.weekday(), Weekday::Sat | Weekday::Sun),
+            Valuation::Monthly => (day + Duration::days(1)).month() != day.month(),
+        };
+        if keep || day == end {
+            dates.push(day);
+        }
+        day += Duration::days(1);
+    }
+    if valuation == Valuation::Daily {
+        dates.extend(flow_dates.filter(|d| *d > start && *d <= end));
+        dates.sort();
+        dates.dedup();
+    }
+    dates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, AssetType, PriceHistory, Transaction, TransactionType};
+
+    fn d(m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, m, day).unwrap()
+    }
+
+    #[test]
+    fn test_daily_twr_ignores_contributions() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id = db::upsert_asset(&conn, "TEST5", &AssetType::Stock, None).unwrap();
+
+        for (date, price) in [(d(1, 2), 10), (d(2, 1), 12), (d(3, 1), 15)] {
+            let buy = Transaction {
+                id: None,
+                asset_id,
+                transaction_type: TransactionType::Buy,
+                trade_date: date,
+                settlement_date: None,
+                quantity: Decimal::from(10),
+                price_per_unit: Decimal::from(price),
+                total_cost: Decimal::from(10 * price),
+                fees: Decimal::ZERO,
+                is_day_trade: false,
+                quota_issuance_date: None,
+                notes: None,
+                source: "TEST".to_string(),
+                created_at: chrono::Utc::now(),
+            };
+            // The last date only carries a price
+            if date != d(3, 1) {
+                db::insert_transaction(&conn, &buy).unwrap();
+            }
+            db::insert_price_history(
+                &conn,
+                &PriceHistory {
+                    id: None,
+                    asset_id,
+                    price_date: date,
+                    close_price: Decimal::from(price),
+                    open_price: None,
+                    high_price: None,
+                    low_price: None,
+                    volume: None,
+                    source: "TEST".to_string(),
+                    created_at: chrono::Utc::now(),
+                    adjusted_close: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // 100 -> 120 (+20%), then 120 more bought -> 240 -> 300 (+25%)
+        let report = snapshot_twr(&mut conn, d(1, 2), d(3, 1), None, Valuation::Daily).unwrap();
+        assert_eq!(report.start_value, Decimal::from(100));
+        assert_eq!(report.twr, Decimal::from(50));
+        assert_eq!(report.points.last().unwrap().value, Decimal::from(300));
+        assert!(report.annualized.is_none());
+
+        let monthly = snapshot_twr(&mut conn, d(1, 2), d(3, 1), None, Valuation::Monthly).unwrap();
+        assert_eq!(
+            monthly.points.iter().map(|p| p.date).collect::<Vec<_>>(),
+            vec![d(1, 31), d(2, 29), d(3, 1)]
+        );
+        assert!(monthly.twr > Decimal::ZERO);
+    }
+}
@@ -0,0 +1,391 @@
+//! Daily briefing: what changed since the previous run.
+//!
+//! The time of the last briefing is kept in the metadata table. Everything
+//! recorded after it is summarized: prices fetched, income paid, corporate
+//! actions detected and inconsistencies opened. Obligations falling due in
+//! the next days (DARFs, option expiries, term contracts, bond maturities)
+//! are listed along with overdue ones, whatever the last run.
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+use crate::db::{self, get_decimal_value};
+use crate::tax::ledger::{self, DarfStatus};
+
+/// Metadata key holding the UTC time of the last briefing
+pub const LAST_RUN_KEY: &str = "whatsnew_last_run";
+
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// How far back the first briefing looks
+pub const FIRST_RUN_DAYS: i64 = 7;
+
+/// Obligations due within this many days are listed
+pub const UPCOMING_DAYS: i64 = 30;
+
+/// Prices stored since the last run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PriceActivity {
+    pub prices: usize,
+    pub assets: usize,
+    /// Most recent trading day among them
+    pub latest_date: Option<NaiveDate>,
+}
+
+/// An income payment recorded or paid since the last run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IncomeCredit {
+    pub ticker: String,
+    pub event_type: String,
+    pub payment_date: NaiveDate,
+    /// Net of withholding
+    pub amount: Decimal,
+}
+
+/// A corporate action recorded since the last run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DetectedAction {
+    pub ticker: String,
+    pub action_type: String,
+    pub ex_date: NaiveDate,
+    /// Not yet accepted (see `actions review`)
+    pub pending_review: bool,
+}
+
+/// An inconsistency opened since the last run and still open
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpenedIssue {
+    pub id: i64,
+    pub issue_type: String,
+    pub severity: String,
+    pub ticker: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObligationKind {
+    Darf,
+    OptionExpiry,
+    TermContract,
+    Maturity,
+}
+
+impl ObligationKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ObligationKind::Darf => "DARF",
+            ObligationKind::OptionExpiry => "Option expiry",
+            ObligationKind::TermContract => "Term contract",
+            ObligationKind::Maturity => "Maturity",
+        }
+    }
+}
+
+/// Something due on a date: a payment, an expiry or a maturity
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Obligation {
+    pub kind: ObligationKind,
+    pub date: NaiveDate,
+    /// Ticker, or the DARF code and period
+    pub subject: String,
+    /// Amount to pay (DARF, term contract), when known
+    pub amount: Option<Decimal>,
+    pub overdue: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Briefing {
+    /// UTC time the briefing starts from
+    pub since: NaiveDateTime,
+    /// No earlier run recorded: `since` is [`FIRST_RUN_DAYS`] ago
+    pub first_run: bool,
+    pub prices: PriceActivity,
+    pub income: Vec<IncomeCredit>,
+    pub income_total: Decimal,
+    pub corporate_actions: Vec<DetectedAction>,
+    pub inconsistencies: Vec<OpenedIssue>,
+    /// All open inconsistencies, new or not
+    pub open_inconsistencies: usize,
+    /// Overdue first, then by date
+    pub obligations: Vec<Obligation>,
+}
+
+impl Briefing {
+    /// Nothing happened and nothing is due
+    pub fn is_quiet(&self) -> bool {
+        self.prices.prices == 0
+            && self.income.is_empty()
+            && self.corporate_actions.is_empty()
+            && self.inconsistencies.is_empty()
+            && self.obligations.is_empty()
+    }
+}
+
+/// Start of the next briefing: the last run, or [`FIRST_RUN_DAYS`] before `now`
+pub fn last_run(conn: &Connection, now: NaiveDateTime) -> Result<(NaiveDateTime, bool)> {
+    let stored = db::get_metadata(conn, LAST_RUN_KEY)?
+        .and_then(|v| NaiveDateTime::parse_from_str(&v, TIMESTAMP_FORMAT).ok());
+    Ok(match stored {
+        Some(since) => (since, false),
+        None => (now - Duration::days(FIRST_RUN_DAYS), true),
+    })
+}
+
+/// Record `now` as the start of the next briefing
+pub fn mark_run(conn: &Connection, now: NaiveDateTime) -> Result<()> {
+    db::set_metadata(
+        conn,
+        LAST_RUN_KEY,
+        &now.format(TIMESTAMP_FORMAT).to_string(),
+    )
+}
+
+/// Briefing of what was recorded after `since` (UTC), with obligations due
+/// up to [`UPCOMING_DAYS`] after `today`
+pub fn briefing(
+    conn: &Connection,
+    since: NaiveDateTime,
+    first_run: bool,
+    today: NaiveDate,
+) -> Result<Briefing> {
+    let since_text = since.format(TIMESTAMP_FORMAT).to_string();
+
+    let prices = conn.query_row(
+        "SELECT COUNT(*), COUNT(DISTINCT asset_id), MAX(price_date)
+         FROM price_history
+         WHERE datetime(created_at) >= datetime(?1)",
+        [&since_text],
+        |row| {
+            Ok(PriceActivity {
+                prices: row.get::<_, i64>(0)? as usize,
+                assets: row.get::<_, i64>(1)? as usize,
+                latest_date: row.get(2)?,
+            })
+        },
+    )?;
+
+    // Paid since the last run, or recorded since then with a past payment date
+    let scope = db::portfolio::scope_filter("i.portfolio_id");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT a.ticker, i.event_type, i.event_date, i.total_amount, i.withholding_tax
+         FROM income_events i
+         JOIN assets a ON i.asset_id = a.id
+         WHERE i.event_date <= ?2
+           AND (datetime(i.created_at) >= datetime(?1) OR i.event_date > date(?1)){}
+         ORDER BY i.event_date, a.ticker",
+        scope
+    ))?;
+    let income = stmt
+        .query_map(params![since_text, today], |row| {
+            Ok(IncomeCredit {
+                ticker: row.get(0)?,
+                event_type: row.get(1)?,
+                payment_date: row.get(2)?,
+                amount: get_decimal_value(row, 3)? - get_decimal_value(row, 4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let income_total = income.iter().map(|i| i.amount).sum();
+
+    let mut stmt = conn.prepare(
+        "SELECT a.ticker, c.action_type, c.ex_date, c.applied_at IS NULL
+         FROM corporate_actions c
+         JOIN assets a ON c.asset_id = a.id
+         WHERE datetime(c.created_at) >= datetime(?1)
+         ORDER BY c.ex_date, a.ticker",
+    )?;
+    let corporate_actions = stmt
+        .query_map([&since_text], |row| {
+            Ok(DetectedAction {
+                ticker: row.get(0)?,
+                action_type: row.get(1)?,
+                ex_date: row.get(2)?,
+                pending_review: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT id, issue_type, severity, ticker
+         FROM inconsistencies
+         WHERE status = 'OPEN' AND datetime(created_at) >= datetime(?1)
+         ORDER BY id",
+    )?;
+    let inconsistencies = stmt
+        .query_map([&since_text], |row| {
+            Ok(OpenedIssue {
+                id: row.get(0)?,
+                issue_type: row.get(1)?,
+                severity: row.get(2)?,
+                ticker: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let open_inconsistencies = conn.query_row(
+        "SELECT COUNT(*) FROM inconsistencies WHERE status = 'OPEN'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )? as usize;
+
+    Ok(Briefing {
+        since,
+        first_run,
+        prices,
+        income,
+        income_total,
+        corporate_actions,
+        inconsistencies,
+        open_inconsistencies,
+        obligations: obligations(conn, today)?,
+    })
+}
+
+/// Obligations due by [`UPCOMING_DAYS`] from `today`, overdue ones included
+fn obligations(conn: &Connection, today: NaiveDate) -> Result<Vec<Obligation>> {
+    let horizon = today + Duration::days(UPCOMING_DAYS);
+    let mut obligations = Vec::new();
+    let mut push = |kind, date: NaiveDate, subject: String, amount| {
+        if date <= horizon {
+            obligations.push(Obligation {
+                kind,
+                date,
+                subject,
+                amount,
+                overdue: date < today,
+            });
+        }
+    };
+
+    // From the ledger as last computed (see `tax ledger`)
+    let entries = ledger::entries(conn, None)?;
+    let pending: BTreeSet<(i32, u32)> = entries
+        .iter()
+        .filter(|e| e.darf_status == DarfStatus::Pending)
+        .map(|e| (e.year, e.month))
+        .collect();
+    for (year, month) in pending {
+        let year_entries: Vec<_> = entries.iter().filter(|e| e.year == year).cloned().collect();
+        for slip in crate::tax::darf::darf_slips(&year_entries, year, month)? {
+            if slip.payable {
+                push(
+                    ObligationKind::Darf,
+                    slip.due_date,
+                    format!("{} {:02}/{}", slip.darf_code, month, year),
+                    Some(slip.amount),
+                );
+            }
+        }
+    }
+
+    // Expired options stay open until the exercise or the expiry is recorded
+    for option in crate::options::open_positions(conn)? {
+        if let Some(expiry) = option.expiry.filter(|d| *d >= today) {
+            push(ObligationKind::OptionExpiry, expiry, option.ticker, None);
+        }
+    }
+
+    for contract in crate::term_contracts::open_term_contracts(conn)? {
+        if let Some(expiry) = contract.expiry_date {
+            push(
+                ObligationKind::TermContract,
+                expiry,
+                contract.ticker,
+                Some(contract.notional),
+            );
+        }
+    }
+
+    for position in crate::fixed_income::open_positions(conn, today)? {
+        let maturity = position.terms.maturity_date;
+        if maturity >= today {
+            push(
+                ObligationKind::Maturity,
+                maturity,
+                position.terms.ticker,
+                None,
+            );
+        }
+    }
+
+    obligations.sort_by_key(|o| (!o.overdue, o.date));
+    Ok(obligations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_briefing_counts_what_was_recorded_since_the_last_run() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'XPLG11', 'FII'), (2, 'PETR4', 'STOCK');
+             INSERT INTO price_history (asset_id, price_date, close_price, source, created_at)
+                 VALUES (1, '2024-06-06', '100', 'YAHOO', '2024-06-06 20:00:00'),
+                        (1, '2024-06-07', '101', 'YAHOO', '2024-06-07 20:00:00'),
+                        (2, '2024-06-07', '38', 'YAHOO', '2024-06-07 20:00:00');
+             INSERT INTO income_events (asset_id, event_date, event_type, amount_per_quota,
+                 total_amount, withholding_tax, source, created_at)
+                 VALUES (1, '2024-06-07', 'DIVIDEND', '0.8', '80', '0', 'CEI', '2024-05-31 12:00:00'),
+                        (2, '2024-06-05', 'JCP', '0.5', '50', '7.5', 'CEI', '2024-06-07 21:00:00'),
+                        (2, '2024-06-20', 'DIVIDEND', '1', '100', '0', 'CEI', '2024-06-07 21:00:00'),
+                        (1, '2024-06-03', 'DIVIDEND', '0.8', '80', '0', 'CEI', '2024-06-01 12:00:00');
+             INSERT INTO corporate_actions (asset_id, action_type, event_date, ex_date,
+                 quantity_adjustment, source, created_at)
+                 VALUES (2, 'BONUS', '2024-06-01', '2024-06-10', '10', 'YAHOO', '2024-06-07 21:00:00');
+             INSERT INTO inconsistencies (issue_type, status, severity, ticker, created_at)
+                 VALUES ('MISSING_COST_BASIS', 'OPEN', 'BLOCKING', 'PETR4', '2024-06-07 21:00:00'),
+                        ('MISSING_COST_BASIS', 'RESOLVED', 'BLOCKING', 'PETR4', '2024-06-07 21:00:00'),
+                        ('MISSING_PURCHASE_HISTORY', 'OPEN', 'WARN', 'XPLG11', '2024-06-01 10:00:00');
+             INSERT INTO tax_ledger (year, month, tax_category, sales, profit_loss, loss_offset,
+                 exemption, tax_due, darf_status, tx_fingerprint)
+                 VALUES (2024, 5, 'STOCK_SWING', '30000', '2000', '0', '0', '300', 'PENDING', 'x');",
+        )
+        .unwrap();
+
+        let since = NaiveDateTime::parse_from_str("2024-06-06 21:00:00", TIMESTAMP_FORMAT).unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 6, 8).unwrap();
+        let briefing = briefing(&conn, since, false, today).unwrap();
+
+        assert_eq!(
+            briefing.prices,
+            PriceActivity {
+                prices: 2,
+                assets: 2,
+                latest_date: NaiveDate::from_ymd_opt(2024, 6, 7),
+            }
+        );
+        // Paid since the last run, or recorded late; not the one paid later on
+        let income: Vec<(&str, Decimal)> = briefing
+            .income
+            .iter()
+            .map(|i| (i.ticker.as_str(), i.amount))
+            .collect();
+        assert_eq!(
+            income,
+            vec![
+                ("PETR4", Decimal::new(425, 1)),
+                ("XPLG11", Decimal::from(80))
+            ]
+        );
+        assert_eq!(briefing.income_total, Decimal::new(1225, 1));
+        assert_eq!(briefing.corporate_actions.len(), 1);
+        assert!(briefing.corporate_actions[0].pending_review);
+        assert_eq!(briefing.inconsistencies.len(), 1);
+        assert_eq!(briefing.open_inconsistencies, 2);
+
+        // May's DARF is due at the end of June
+        assert_eq!(briefing.obligations.len(), 1);
+        let darf = &briefing.obligations[0];
+        assert_eq!(darf.kind, ObligationKind::Darf);
+        assert_eq!(darf.amount, Some(Decimal::from(300)));
+        assert!(!darf.overdue);
+        assert!(!briefing.is_quiet());
+    }
+}
@@ -0,0 +1,217 @@
+//! Money-weighted return (XIRR) for the portfolio and for each asset.
+//!
+//! Unlike the time-weighted return, XIRR weighs each period by the money
+//! that was invested during it, so it answers "what annual rate did my own
+//! contributions earn". The value held at the start of the period counts as
+//! money put in on the start date and the value at the end as money taken
+//! out on the end date; buys (with fees), sales (net of fees) and income
+//! (net of withholding) in between are placed on their own dates.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::prelude::*;
+use rust_decimal::MathematicalOps;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::db;
+use crate::reports::portfolio::PositionSummary;
+
+/// Bounds for the annual rate search (-99% a year up to a rate that only
+/// annualizing a few days of a big move can reach)
+const MIN_RATE: Decimal = Decimal::from_parts(99, 0, 0, true, 2);
+const MAX_RATE: Decimal = Decimal::from_parts(1_000_000, 0, 0, false, 0);
+
+/// Annualized money-weighted return of one asset over the period
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetXirr {
+    pub ticker: String,
+    /// Percentage; None when the flows have no solution in range
+    pub xirr: Option<Decimal>,
+}
+
+/// Annual rate `r` (as a fraction) that brings the flows to zero, where
+/// negative amounts are money put in and positive ones money taken out.
+/// None when the flows do not change sign or no rate in range solves them.
+pub fn xirr(flows: &[(NaiveDate, Decimal)]) -> Option<Decimal> {
+    let has_in = flows.iter().any(|(_, a)| *a < Decimal::ZERO);
+    let has_out = flows.iter().any(|(_, a)| *a > Decimal::ZERO);
+    if !has_in || !has_out {
+        return None;
+    }
+    let last = flows.iter().map(|(d, _)| *d).max()?;
+
+    // Future value at the last date; decreasing in the rate when money goes
+    // in before it comes out, so bisection converges
+    let future_value = |rate: Decimal| -> Option<Decimal> {
+        let ln_growth = (Decimal::ONE + rate).checked_ln()?;
+        flows.iter().try_fold(Decimal::ZERO, |acc, (date, amount)| {
+            let years = Decimal::from((last - *date).num_days()) / Decimal::from(365);
+            let factor = (ln_growth * years).checked_exp()?;
+            acc.checked_add(amount.checked_mul(factor)?)
+        })
+    };
+
+    let mut lo = MIN_RATE;
+    let f_lo = future_value(lo)?;
+    if f_lo.is_zero() {
+        return Some(lo);
+    }
+    // Widen the upper bound gradually: long horizons overflow at huge rates
+    // that their solution never needs
+    let mut hi = Decimal::ONE;
+    while future_value(hi)?.is_sign_negative() == f_lo.is_sign_negative() {
+        if hi >= MAX_RATE {
+            return None;
+        }
+        hi *= Decimal::TEN;
+    }
+
+    let tolerance = Decimal::new(1, 7);
+    while hi - lo > tolerance {
+        let mid = (lo + hi) / Decimal::TWO;
+        let f_mid = future_value(mid)?;
+        if f_mid.is_zero() {
+            return Some(mid);
+        }
+        if f_mid.is_sign_negative() == f_lo.is_sign_negative() {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Some((lo + hi) / Decimal::TWO)
+}
+
+/// Portfolio XIRR and per-asset XIRRs (both as percentages) for a period,
+/// given the positions at its start and end.
+pub fn period_xirr(
+    conn: &Connection,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    start_positions: &[PositionSummary],
+    end_positions: &[PositionSummary],
+    asset_ids: Option<&HashSet<i64>>,
+) -> Result<(Option<Decimal>, Vec<AssetXirr>)> {
+    let mut by_asset: BTreeMap<String, Vec<(NaiveDate, Decimal)>> = BTreeMap::new();
+
+    let position_value = |p: &PositionSummary| p.current_value.unwrap_or_default();
+    for p in start_positions {
+        if !position_value(p).is_zero() {
+            by_asset
+                .entry(p.asset.ticker.clone())
+                .or_default()
+                .push((start_date, -position_value(p)));
+        }
+    }
+
+    for (ticker, date, amount) in trade_and_income_flows(conn, start_date, end_date, asset_ids)? {
+        by_asset.entry(ticker).or_default().push((date, amount));
+    }
+
+    for p in end_positions {
+        if !position_value(p).is_zero() {
+            by_asset
+                .entry(p.asset.ticker.clone())
+                .or_default()
+                .push((end_date, position_value(p)));
+        }
+    }
+
+    let as_pct = |rate: Decimal| (rate * Decimal::ONE_HUNDRED).round_dp(2);
+    let all_flows: Vec<(NaiveDate, Decimal)> = by_asset.values().flatten().copied().collect();
+    let portfolio = xirr(&all_flows).map(as_pct);
+    let assets = by_asset
+        .into_iter()
+        .map(|(ticker, flows)| AssetXirr {
+            ticker,
+            xirr: xirr(&flows).map(as_pct),
+        })
+        .collect();
+    Ok((portfolio, assets))
+}
+
+/// Buys (negative, fees added), sales (net of fees) and income (net of
+/// withholding) of a period, by ticker. Start-of-period positions are
+/// end-of-day snapshots that already include trades made on the start date,
+/// so only later trades are returned; income from the start date on is.
+pub(crate) fn trade_and_income_flows(
+    conn: &Connection,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    asset_ids: Option<&HashSet<i64>>,
+) -> Result<Vec<(String, NaiveDate, Decimal)>> {
+    let in_scope = |asset_id: i64| asset_ids.is_none_or(|ids| ids.contains(&asset_id));
+    let mut flows = Vec::new();
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.asset_id, a.ticker, t.trade_date, t.transaction_type,
+                t.quantity, t.price_per_unit, t.fees
+         FROM transactions t
+         JOIN assets a ON a.id = t.asset_id
+         WHERE t.trade_date > ?1 AND t.trade_date <= ?2
+           AND t.transaction_type IN ('BUY', 'SELL')
+           AND (t.notes IS NULL OR t.notes NOT LIKE '%Term contract liquidation%'){}",
+        db::portfolio::scope_filter("t.portfolio_id")
+    ))?;
+    let mut rows = stmt.query([start_date, end_date])?;
+    while let Some(row) = rows.next()? {
+        if !in_scope(row.get(0)?) {
+            continue;
+        }
+        let ticker: String = row.get(1)?;
+        let date: NaiveDate = row.get(2)?;
+        let tx_type: String = row.get(3)?;
+        let gross = db::get_decimal_value(row, 4)? * db::get_decimal_value(row, 5)?;
+        let fees = db::get_optional_decimal_value(row, 6)?.unwrap_or_default();
+        let amount = if tx_type == "BUY" {
+            -(gross + fees)
+        } else {
+            gross - fees
+        };
+        flows.push((ticker, date, amount));
+    }
+
+    for (event, asset) in
+        db::get_income_events_with_assets(conn, Some(start_date), Some(end_date), None)?
+    {
+        if in_scope(event.asset_id) {
+            flows.push((
+                asset.ticker,
+                event.event_date,
+                event.total_amount - event.withholding_tax,
+            ));
+        }
+    }
+    Ok(flows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn d(y: i32, m: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, day).unwrap()
+    }
+
+    #[test]
+    fn test_xirr_single_year_and_staggered_flows() {
+        // 1000 in, 1100 out one year later: 10% a year
+        let rate = xirr(&[(d(2023, 1, 1), dec!(-1000)), (d(2024, 1, 1), dec!(1100))]).unwrap();
+        assert!((rate - dec!(0.10)).abs() < dec!(0.0001), "{}", rate);
+
+        // A second contribution halfway through earns only half a year
+        let rate = xirr(&[
+            (d(2023, 1, 1), dec!(-1000)),
+            (d(2023, 7, 2), dec!(-1000)),
+            (d(2024, 1, 1), dec!(2148.95)),
+        ])
+        .unwrap();
+        assert!((rate - dec!(0.10)).abs() < dec!(0.001), "{}", rate);
+
+        // No money taken out: no rate
+        assert!(xirr(&[(d(2023, 1, 1), dec!(-1000))]).is_none());
+    }
+}
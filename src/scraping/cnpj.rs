@@ -0,0 +1,202 @@
+//! CNPJ registry lookup (Receita Federal data via BrasilAPI, with minhareceita
+//! as fallback). Used to confirm the issuer's razão social and registration
+//! situation, which the IRPF declaration expects verbatim.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use reqwest::Client;
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::db::AssetIssuer;
+
+const BRASILAPI_URL: &str = "https://brasilapi.com.br/api/cnpj/v1";
+const MINHARECEITA_URL: &str = "https://minhareceita.org";
+
+/// Issuer data as published in the CNPJ registry
+#[derive(Debug, Clone, PartialEq)]
+pub struct CnpjRecord {
+    pub cnpj: String,
+    pub legal_name: String,
+    pub trade_name: Option<String>,
+    pub situation: Option<String>,
+    pub situation_date: Option<NaiveDate>,
+    pub source: &'static str,
+}
+
+impl CnpjRecord {
+    pub fn into_issuer(self, asset_id: i64) -> AssetIssuer {
+        AssetIssuer {
+            asset_id,
+            cnpj: self.cnpj,
+            legal_name: self.legal_name,
+            trade_name: self.trade_name,
+            situation: self.situation,
+            situation_date: self.situation_date,
+            source: self.source.to_string(),
+            fetched_at: None,
+        }
+    }
+}
+
+/// Digits of a CNPJ, if it has exactly 14 of them
+pub fn normalize_cnpj(raw: &str) -> Option<String> {
+    let digits: String = raw.chars().filter(|c| c.is_ascii_digit()).collect();
+    (digits.len() == 14).then_some(digits)
+}
+
+/// CNPJ in the usual XX.XXX.XXX/XXXX-XX mask; anything that is not 14 digits
+/// is returned as given
+pub fn format_cnpj(raw: &str) -> String {
+    match normalize_cnpj(raw) {
+        Some(d) => format!(
+            "{}.{}.{}/{}-{}",
+            &d[0..2],
+            &d[2..5],
+            &d[5..8],
+            &d[8..12],
+            &d[12..14]
+        ),
+        None => raw.to_string(),
+    }
+}
+
+/// Check a 14-digit CNPJ against its two verification digits
+pub fn is_valid_cnpj(digits: &str) -> bool {
+    let nums: Vec<u32> = digits.chars().filter_map(|c| c.to_digit(10)).collect();
+    if nums.len() != 14 || digits.len() != 14 || nums.iter().all(|d| *d == nums[0]) {
+        return false;
+    }
+
+    let check = |len: usize| {
+        let sum: u32 = nums[..len]
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, d)| d * (2 + (i as u32 % 8)))
+            .sum();
+        match sum % 11 {
+            0 | 1 => 0,
+            r => 11 - r,
+        }
+    };
+    check(12) == nums[12] && check(13) == nums[13]
+}
+
+/// Look a CNPJ up, trying BrasilAPI first and minhareceita when it fails
+pub async fn lookup_cnpj(cnpj: &str) -> Result<CnpjRecord> {
+    let offline = std::env::var("INTEREST_OFFLINE")
+        .map(|v| v != "0")
+        .unwrap_or(false);
+    if offline {
+        anyhow::bail!("CNPJ lookup skipped (INTEREST_OFFLINE is set)");
+    }
+
+    let digits = normalize_cnpj(cnpj)
+        .filter(|d| is_valid_cnpj(d))
+        .ok_or_else(|| anyhow::anyhow!("Invalid CNPJ: {}", cnpj))?;
+
+    let client = Client::builder()
+        .timeout(Duration::from_secs(20))
+        .user_agent("interest")
+        .build()?;
+
+    let primary = fetch_json(&client, &format!("{}/{}", BRASILAPI_URL, digits)).await;
+    match primary.and_then(|json| parse_registry_json(&json, "BRASILAPI")) {
+        Ok(record) => Ok(record),
+        Err(err) => {
+            tracing::debug!("BrasilAPI lookup failed for {}: {}", digits, err);
+            let json = fetch_json(&client, &format!("{}/{}", MINHARECEITA_URL, digits))
+                .await
+                .context(format!(
+                    "CNPJ {} not found in BrasilAPI or minhareceita",
+                    digits
+                ))?;
+            parse_registry_json(&json, "MINHARECEITA")
+        }
+    }
+}
+
+async fn fetch_json(client: &Client, url: &str) -> Result<Value> {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .context("CNPJ lookup request failed")?
+        .error_for_status()
+        .context("CNPJ lookup returned an error status")?;
+    response
+        .json()
+        .await
+        .context("Failed to parse CNPJ lookup response")
+}
+
+/// Both services publish the Receita dataset with the same field names
+fn parse_registry_json(json: &Value, source: &'static str) -> Result<CnpjRecord> {
+    let text = |key: &str| {
+        json.get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    let cnpj = text("cnpj")
+        .and_then(|c| normalize_cnpj(&c))
+        .or_else(|| {
+            json.get("cnpj")
+                .and_then(|v| v.as_u64())
+                .map(|n| format!("{:014}", n))
+        })
+        .ok_or_else(|| anyhow::anyhow!("Response has no CNPJ"))?;
+    let legal_name =
+        text("razao_social").ok_or_else(|| anyhow::anyhow!("Response has no razão social"))?;
+
+    Ok(CnpjRecord {
+        cnpj,
+        legal_name,
+        trade_name: text("nome_fantasia"),
+        situation: text("descricao_situacao_cadastral").map(|s| s.to_uppercase()),
+        situation_date: text("data_situacao_cadastral")
+            .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cnpj_check_digits() {
+        // Petrobras
+        assert!(is_valid_cnpj("33000167000101"));
+        assert_eq!(
+            normalize_cnpj("33.000.167/0001-01").as_deref(),
+            Some("33000167000101")
+        );
+        assert!(!is_valid_cnpj("33000167000102"));
+        assert!(!is_valid_cnpj("11111111111111"));
+        assert!(normalize_cnpj("123").is_none());
+    }
+
+    #[test]
+    fn test_parse_registry_json() {
+        let json = serde_json::json!({
+            "cnpj": "33000167000101",
+            "razao_social": "PETROLEO BRASILEIRO S A PETROBRAS",
+            "nome_fantasia": "PETROBRAS",
+            "descricao_situacao_cadastral": "Ativa",
+            "data_situacao_cadastral": "2005-11-03",
+        });
+        let record = parse_registry_json(&json, "BRASILAPI").unwrap();
+        assert_eq!(record.legal_name, "PETROLEO BRASILEIRO S A PETROBRAS");
+        assert_eq!(record.situation.as_deref(), Some("ATIVA"));
+        assert_eq!(record.situation_date, NaiveDate::from_ymd_opt(2005, 11, 3));
+
+        let issuer = record.into_issuer(7);
+        assert!(issuer.is_active());
+
+        let missing = serde_json::json!({ "cnpj": "33000167000101", "nome_fantasia": "" });
+        assert!(parse_registry_json(&missing, "MINHARECEITA").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 // Web scraping module for extracting data from websites
 // Uses headless Chrome to bypass Cloudflare protection
 
+pub mod cnpj;
 pub mod maisretorno;
@@ -0,0 +1,360 @@
+//! Subscription Rights (Direitos e Recibos de Subscrição) Handling
+//!
+//! A follow-on offer of a fund or unit goes through separate tickers:
+//! - HGLG12: the right (direito), credited to holders at no cost and tradable
+//! - HGLG13, HGLG14, HGLG15: the receipt (recibo) of a paid subscription
+//! - HGLG11: the base ticker the receipts convert into once the offer closes
+//!
+//! The Movimentação file reports each step. Granted rights are recorded as a
+//! zero-cost purchase, so selling them is all gain; rights bought on the
+//! exchange keep their price. Exercised rights leave at their average cost,
+//! which is carried into the receipt together with the subscription payment.
+//! Rights left unexercised leave at zero, losing whatever they cost. The
+//! conversion moves the receipt position and its cost into the base ticker as
+//! an asset exchange (the same record a merger uses).
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::{self, AssetExchange, AssetExchangeType, Transaction, TransactionType};
+
+/// A step of the subscription lifecycle reported by Movimentação
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SubscriptionEvent {
+    /// Rights credited to holders of the base ticker
+    Grant,
+    /// Rights used to subscribe
+    Exercise,
+    /// Receipt credited for the subscribed quantity
+    Receipt,
+    /// Rights neither sold nor exercised by the deadline
+    Expiry,
+}
+
+/// Lifecycle step of a Movimentação entry, if it is one
+pub fn classify_movement(movement_type: &str, direction: &str) -> Option<SubscriptionEvent> {
+    let movement = movement_type.replace("Direitos de", "Direito de");
+    let credit = direction == "Credito";
+    match movement.as_str() {
+        "Direito de Subscrição" if credit => Some(SubscriptionEvent::Grant),
+        "Direito de Subscrição - Exercido" if !credit => Some(SubscriptionEvent::Exercise),
+        "Direito de Subscrição - Não Exercido" if !credit => Some(SubscriptionEvent::Expiry),
+        "Recibo de Subscrição" if credit => Some(SubscriptionEvent::Receipt),
+        _ => None,
+    }
+}
+
+pub fn is_right_ticker(ticker: &str) -> bool {
+    ticker.len() == 6 && ticker.ends_with("12")
+}
+
+pub fn is_receipt_ticker(ticker: &str) -> bool {
+    ticker.len() == 6
+        && (ticker.ends_with("13") || ticker.ends_with("14") || ticker.ends_with("15"))
+}
+
+/// Ticker that rights and receipts convert into (HGLG12 -> HGLG11)
+pub fn base_ticker(ticker: &str) -> Option<String> {
+    (is_right_ticker(ticker) || is_receipt_ticker(ticker)).then(|| format!("{}11", &ticker[..4]))
+}
+
+/// Quantity held and its total cost at the end of `date`
+pub fn position_on(
+    conn: &Connection,
+    asset_id: i64,
+    date: NaiveDate,
+) -> Result<(Decimal, Decimal)> {
+    let report = crate::reports::calculate_portfolio_at_date(conn, date, None)?;
+    Ok(report
+        .positions
+        .iter()
+        .find(|p| p.asset.id == Some(asset_id))
+        .map(|p| (p.quantity, p.total_cost))
+        .unwrap_or_default())
+}
+
+/// Records the lifecycle of one import, carrying the cost of exercised
+/// rights into the receipts subscribed with them
+#[derive(Default)]
+pub struct SubscriptionRecorder {
+    /// Cost of rights exercised and not yet credited as a receipt, by root
+    exercised_cost: HashMap<String, Decimal>,
+}
+
+impl SubscriptionRecorder {
+    /// Insert the transaction for `event` and return its id. Entries must be
+    /// given in date order, with exercises before the receipt of the same day.
+    #[allow(clippy::too_many_arguments)]
+    pub fn record(
+        &mut self,
+        conn: &Connection,
+        event: SubscriptionEvent,
+        asset_id: i64,
+        ticker: &str,
+        date: NaiveDate,
+        quantity: Decimal,
+        value: Option<Decimal>,
+        product: &str,
+    ) -> Result<i64> {
+        let root: String = ticker.chars().take(4).collect();
+        let (transaction_type, total, notes) = match event {
+            SubscriptionEvent::Grant => (
+                TransactionType::Buy,
+                Decimal::ZERO,
+                format!("Subscription right grant: {}", product),
+            ),
+            SubscriptionEvent::Exercise => {
+                let (held, cost) = position_on(conn, asset_id, date)?;
+                let cost = if held > Decimal::ZERO {
+                    (cost / held * quantity.min(held)).round_dp(2)
+                } else {
+                    Decimal::ZERO
+                };
+                *self.exercised_cost.entry(root).or_default() += cost;
+                (
+                    TransactionType::Sell,
+                    cost,
+                    format!(
+                        "Subscription right exercised (cost moved to the receipt): {}",
+                        product
+                    ),
+                )
+            }
+            SubscriptionEvent::Receipt => {
+                let paid = value.unwrap_or_default();
+                let rights = self.exercised_cost.remove(&root).unwrap_or_default();
+                let notes = if rights > Decimal::ZERO {
+                    format!(
+                        "Subscription receipt: paid {} plus {} of exercised rights: {}",
+                        paid, rights, product
+                    )
+                } else {
+                    format!("Subscription receipt: {}", product)
+                };
+                (TransactionType::Buy, paid + rights, notes)
+            }
+            SubscriptionEvent::Expiry => (
+                TransactionType::Sell,
+                Decimal::ZERO,
+                format!("Subscription right expired unexercised: {}", product),
+            ),
+        };
+
+        db::insert_transaction(
+            conn,
+            &Transaction {
+                id: None,
+                asset_id,
+                transaction_type,
+                trade_date: date,
+                settlement_date: Some(date),
+                quantity,
+                price_per_unit: if quantity > Decimal::ZERO {
+                    total / quantity
+                } else {
+                    Decimal::ZERO
+                },
+                total_cost: total,
+                fees: Decimal::ZERO,
+                is_day_trade: false,
+                quota_issuance_date: None,
+                notes: Some(notes),
+                source: "MOVIMENTACAO".to_string(),
+                created_at: chrono::Utc::now(),
+            },
+        )
+    }
+}
+
+/// Move receipt positions into the base ticker on `date`. Returns how many
+/// receipts were converted; receipts with no recorded position are left out.
+pub fn convert_receipts(
+    conn: &Connection,
+    receipt_tickers: &[String],
+    base_asset_id: i64,
+    date: NaiveDate,
+) -> Result<usize> {
+    let mut converted = 0;
+    for ticker in receipt_tickers {
+        // Rights credited in the same offer are not converted
+        if is_right_ticker(ticker) {
+            continue;
+        }
+        let Some(receipt) = db::get_asset_by_ticker(conn, ticker)? else {
+            continue;
+        };
+        let receipt_id = receipt.id.expect("asset from database must have id");
+        let (quantity, cost) = position_on(conn, receipt_id, date)?;
+        if quantity <= Decimal::ZERO {
+            continue;
+        }
+        db::insert_asset_exchange(
+            conn,
+            &AssetExchange {
+                id: None,
+                event_type: AssetExchangeType::Merger,
+                from_asset_id: receipt_id,
+                to_asset_id: base_asset_id,
+                effective_date: date,
+                to_quantity: quantity,
+                allocated_cost: cost,
+                cash_amount: Decimal::ZERO,
+                source: "MOVIMENTACAO".to_string(),
+                notes: Some(format!("Subscription receipt conversion from {}", ticker)),
+                created_at: chrono::Utc::now(),
+            },
+        )?;
+        converted += 1;
+    }
+    Ok(converted)
+}
+
+/// A right or receipt currently held
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionPosition {
+    pub ticker: String,
+    /// "Right" or "Receipt"
+    pub kind: &'static str,
+    pub base_ticker: String,
+    pub quantity: Decimal,
+    pub total_cost: Decimal,
+}
+
+/// Rights and receipts held today
+pub fn open_positions(conn: &Connection) -> Result<Vec<SubscriptionPosition>> {
+    let report = crate::reports::calculate_portfolio(conn, None)?;
+    Ok(report
+        .positions
+        .into_iter()
+        .filter(|p| p.asset.asset_type != db::AssetType::Bond)
+        .filter_map(|p| {
+            let base_ticker = base_ticker(&p.asset.ticker)?;
+            Some(SubscriptionPosition {
+                kind: if is_right_ticker(&p.asset.ticker) {
+                    "Right"
+                } else {
+                    "Receipt"
+                },
+                ticker: p.asset.ticker,
+                base_ticker,
+                quantity: p.quantity,
+                total_cost: p.total_cost,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::AssetType;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_rights_exercise_and_conversion_carry_cost() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("db/schema.sql")).unwrap();
+        let d = |m, day| NaiveDate::from_ymd_opt(2024, m, day).unwrap();
+        let base = db::insert_asset(&conn, "HGLG11", &AssetType::Fii, None).unwrap();
+        let right = db::insert_asset(&conn, "HGLG12", &AssetType::Fii, None).unwrap();
+        let receipt = db::insert_asset(&conn, "HGLG13", &AssetType::Fii, None).unwrap();
+
+        assert_eq!(
+            classify_movement("Direitos de Subscrição - Não Exercido", "Debito"),
+            Some(SubscriptionEvent::Expiry)
+        );
+        assert_eq!(classify_movement("Recibo de Subscrição", "Debito"), None);
+        assert_eq!(base_ticker("HGLG13").as_deref(), Some("HGLG11"));
+
+        let mut recorder = SubscriptionRecorder::default();
+        let mut record = |event, asset_id, ticker, date, quantity, value| {
+            recorder
+                .record(
+                    &conn, event, asset_id, ticker, date, quantity, value, "test",
+                )
+                .unwrap()
+        };
+        record(
+            SubscriptionEvent::Grant,
+            right,
+            "HGLG12",
+            d(3, 1),
+            dec!(10),
+            None,
+        );
+        // Ten more rights bought on the exchange at 2.00
+        db::insert_transaction(
+            &conn,
+            &Transaction {
+                id: None,
+                asset_id: right,
+                transaction_type: TransactionType::Buy,
+                trade_date: d(3, 5),
+                settlement_date: None,
+                quantity: dec!(10),
+                price_per_unit: dec!(2),
+                total_cost: dec!(20),
+                fees: Decimal::ZERO,
+                is_day_trade: false,
+                quota_issuance_date: None,
+                notes: None,
+                source: "TEST".to_string(),
+                created_at: chrono::Utc::now(),
+            },
+        )
+        .unwrap();
+        record(
+            SubscriptionEvent::Exercise,
+            right,
+            "HGLG12",
+            d(3, 20),
+            dec!(15),
+            None,
+        );
+        record(
+            SubscriptionEvent::Receipt,
+            receipt,
+            "HGLG13",
+            d(3, 20),
+            dec!(15),
+            Some(dec!(1500)),
+        );
+        record(
+            SubscriptionEvent::Expiry,
+            right,
+            "HGLG12",
+            d(3, 25),
+            dec!(5),
+            None,
+        );
+
+        assert_eq!(
+            position_on(&conn, receipt, d(3, 31)).unwrap(),
+            (dec!(15), dec!(1515))
+        );
+        assert_eq!(
+            position_on(&conn, right, d(3, 31)).unwrap().0,
+            Decimal::ZERO
+        );
+
+        let converted = convert_receipts(&conn, &["HGLG13".to_string()], base, d(5, 2)).unwrap();
+        assert_eq!(converted, 1);
+        assert_eq!(
+            position_on(&conn, base, d(5, 2)).unwrap(),
+            (dec!(15), dec!(1515))
+        );
+        assert_eq!(
+            position_on(&conn, receipt, d(5, 2)).unwrap().0,
+            Decimal::ZERO
+        );
+
+        // The unexercised rights lose their share of the cost
+        let sales = crate::tax::swing_trade::realized_sales_for_year(&conn, 2024).unwrap();
+        let losses: Decimal = sales.iter().map(|(_, s)| s.profit_loss).sum();
+        assert_eq!(losses, dec!(-5));
+    }
+}
@@ -0,0 +1,340 @@
+//! Year-end positions as IRPF "Bens e Direitos" items.
+//!
+//! Each asset held on 31/12 of the year or of the year before becomes one
+//! item, with its code, the issuer's CNPJ, and the "situação" on both dates
+//! at acquisition cost, as the declaration requires (never market value).
+//! The discriminação follows the wording the IRPF program expects: quantity,
+//! ticker, razão social, CNPJ and average cost. Items are grouped by code;
+//! positions sold out during the year stay listed with a zero situação so the
+//! previous year's value is carried over.
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::db::{self, Asset, AssetType};
+use crate::reports::{self, portfolio::PositionSummary};
+use crate::scraping::cnpj::format_cnpj;
+use crate::utils::format_currency;
+
+/// One "Bens e Direitos" item
+#[derive(Debug, Clone, Serialize)]
+pub struct BensDireitosItem {
+    pub code: &'static str,
+    pub ticker: String,
+    /// Razão social when the issuer was looked up, otherwise the asset name
+    pub issuer: Option<String>,
+    pub cnpj: Option<String>,
+    /// Quantity on 31/12 of the year
+    pub quantity: Decimal,
+    pub average_cost: Decimal,
+    /// Situação em 31/12 of the previous year
+    pub previous_value: Decimal,
+    /// Situação em 31/12 of the year
+    pub current_value: Decimal,
+    pub discriminacao: String,
+}
+
+/// Items sharing a code
+#[derive(Debug, Clone, Serialize)]
+pub struct BensDireitosGroup {
+    pub code: &'static str,
+    pub description: &'static str,
+    pub items: Vec<BensDireitosItem>,
+    pub previous_total: Decimal,
+    pub current_total: Decimal,
+}
+
+/// Code and description of an asset type in the declaration
+pub fn irpf_code(asset_type: &AssetType) -> (&'static str, &'static str) {
+    match asset_type {
+        AssetType::Stock => ("31", "Ações (inclusive as listadas em bolsa)"),
+        AssetType::GovBond | AssetType::Bond => {
+            ("45", "Aplicação de renda fixa (CDB, RDB e outros)")
+        }
+        AssetType::Bdr | AssetType::Option | AssetType::TermContract => {
+            ("49", "Outras aplicações e investimentos")
+        }
+        AssetType::Fii => ("73", "Fundo de Investimento Imobiliário"),
+        AssetType::Etf => (
+            "74",
+            "Fundo de Ações, Mútuos de Privatização e Fundos de Índice",
+        ),
+        AssetType::Fiagro | AssetType::FiInfra | AssetType::Fidc | AssetType::Fip => {
+            ("79", "Outros fundos")
+        }
+        AssetType::Crypto => ("89", "Outros criptoativos"),
+        AssetType::Unknown => ("99", "Outros bens e direitos"),
+    }
+}
+
+/// Plural noun for the units of an asset type
+fn unit_label(asset_type: &AssetType) -> &'static str {
+    match asset_type {
+        AssetType::Stock => "ações",
+        AssetType::Bdr => "BDRs",
+        AssetType::GovBond | AssetType::Bond => "títulos",
+        AssetType::Option => "opções",
+        AssetType::TermContract => "contratos a termo",
+        AssetType::Crypto | AssetType::Unknown => "unidades",
+        _ => "cotas",
+    }
+}
+
+/// Bens e Direitos for `year`, one group per code in code order. Honors the
+/// portfolio scope, so `--declarant` yields that declarant's items.
+pub fn bens_e_direitos(conn: &Connection, year: i32) -> Result<Vec<BensDireitosGroup>> {
+    let year_end =
+        NaiveDate::from_ymd_opt(year, 12, 31).ok_or_else(|| anyhow!("Invalid year: {}", year))?;
+    let previous_end = NaiveDate::from_ymd_opt(year - 1, 12, 31)
+        .ok_or_else(|| anyhow!("Invalid year: {}", year))?;
+
+    let by_asset = |positions: Vec<PositionSummary>| -> BTreeMap<String, PositionSummary> {
+        positions
+            .into_iter()
+            .filter(|p| !p.quantity.is_zero())
+            .map(|p| (p.asset.ticker.clone(), p))
+            .collect()
+    };
+    let current = by_asset(reports::calculate_portfolio_at_date(conn, year_end, None)?.positions);
+    let previous =
+        by_asset(reports::calculate_portfolio_at_date(conn, previous_end, None)?.positions);
+
+    let mut groups: BTreeMap<&'static str, BensDireitosGroup> = BTreeMap::new();
+    let tickers: std::collections::BTreeSet<&String> =
+        current.keys().chain(previous.keys()).collect();
+    for ticker in tickers {
+        let now = current.get(ticker);
+        let before = previous.get(ticker);
+        let Some(asset) = now.or(before).map(|p| &p.asset) else {
+            continue;
+        };
+        let issuer = match asset.id {
+            Some(id) => db::get_asset_issuer(conn, id)?,
+            None => None,
+        };
+        let item = build_item(
+            asset,
+            issuer
+                .as_ref()
+                .map(|i| i.legal_name.clone())
+                .or_else(|| asset.name.clone()),
+            issuer.map(|i| i.cnpj).or_else(|| asset.cnpj.clone()),
+            now,
+            before,
+            year,
+        );
+
+        let (code, description) = irpf_code(&asset.asset_type);
+        let group = groups.entry(code).or_insert_with(|| BensDireitosGroup {
+            code,
+            description,
+            items: Vec::new(),
+            previous_total: Decimal::ZERO,
+            current_total: Decimal::ZERO,
+        });
+        group.previous_total += item.previous_value;
+        group.current_total += item.current_value;
+        group.items.push(item);
+    }
+    Ok(groups.into_values().collect())
+}
+
+fn build_item(
+    asset: &Asset,
+    issuer: Option<String>,
+    cnpj: Option<String>,
+    now: Option<&PositionSummary>,
+    before: Option<&PositionSummary>,
+    year: i32,
+) -> BensDireitosItem {
+    let cnpj = cnpj.map(|c| format_cnpj(&c));
+    let mut identification = asset.ticker.clone();
+    if let Some(name) = &issuer {
+        identification.push_str(&format!(" - {}", name));
+    }
+    if let Some(cnpj) = &cnpj {
+        identification.push_str(&format!(", CNPJ {}", cnpj));
+    }
+
+    let units = unit_label(&asset.asset_type);
+    let discriminacao = match now {
+        Some(p) => format!(
+            "{} {} de {}. Custo médio de {} por unidade.",
+            p.quantity.normalize(),
+            units,
+            identification,
+            format_currency(p.average_cost)
+        ),
+        None => format!(
+            "{} {} de {}, integralmente alienadas em {}.",
+            before.map(|p| p.quantity).unwrap_or_default().normalize(),
+            units,
+            identification,
+            year
+        ),
+    };
+
+    BensDireitosItem {
+        code: irpf_code(&asset.asset_type).0,
+        ticker: asset.ticker.clone(),
+        issuer,
+        cnpj,
+        quantity: now.map(|p| p.quantity).unwrap_or_default(),
+        average_cost: now.map(|p| p.average_cost).unwrap_or_default(),
+        previous_value: before.map(|p| p.total_cost.round_dp(2)).unwrap_or_default(),
+        current_value: now.map(|p| p.total_cost.round_dp(2)).unwrap_or_default(),
+        discriminacao,
+    }
+}
+
+/// CSV with one line per item, values with the Brazilian decimal comma
+pub fn export_to_csv(groups: &[BensDireitosGroup], year: i32) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b';')
+        .from_writer(Vec::new());
+    writer.write_record([
+        "Código".to_string(),
+        "Ticker".to_string(),
+        "CNPJ".to_string(),
+        "Discriminação".to_string(),
+        format!("Situação em 31/12/{}", year - 1),
+        format!("Situação em 31/12/{}", year),
+    ])?;
+
+    let money = |v: Decimal| format!("{:.2}", v.round_dp(2)).replace('.', ",");
+    for item in groups.iter().flat_map(|g| &g.items) {
+        writer.write_record([
+            item.code.to_string(),
+            item.ticker.clone(),
+            item.cnpj.clone().unwrap_or_default(),
+            item.discriminacao.clone(),
+            money(item.previous_value),
+            money(item.current_value),
+        ])?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Transaction, TransactionType};
+    use rust_decimal_macros::dec;
+
+    fn trade(
+        asset_id: i64,
+        transaction_type: TransactionType,
+        date: NaiveDate,
+        quantity: Decimal,
+        price: Decimal,
+    ) -> Transaction {
+        Transaction {
+            id: None,
+            asset_id,
+            transaction_type,
+            trade_date: date,
+            settlement_date: None,
+            quantity,
+            price_per_unit: price,
+            total_cost: quantity * price,
+            fees: Decimal::ZERO,
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: None,
+            source: "TEST".to_string(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_bens_e_direitos_groups_and_discriminacao() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+        let petr = db::insert_asset(&conn, "PETR4", &AssetType::Stock, None).unwrap();
+        let hglg = db::insert_asset(&conn, "HGLG11", &AssetType::Fii, None).unwrap();
+        let vale = db::insert_asset(&conn, "VALE3", &AssetType::Stock, None).unwrap();
+        conn.execute(
+            "INSERT INTO asset_issuers (asset_id, cnpj, legal_name, source)
+             VALUES (?1, '33000167000101', 'PETROLEO BRASILEIRO S.A. PETROBRAS', 'TEST')",
+            [petr],
+        )
+        .unwrap();
+
+        for tx in [
+            trade(
+                petr,
+                TransactionType::Buy,
+                d(2023, 5, 2),
+                dec!(100),
+                dec!(25),
+            ),
+            trade(
+                petr,
+                TransactionType::Buy,
+                d(2024, 3, 1),
+                dec!(100),
+                dec!(35),
+            ),
+            trade(
+                hglg,
+                TransactionType::Buy,
+                d(2024, 2, 1),
+                dec!(10),
+                dec!(160),
+            ),
+            trade(
+                vale,
+                TransactionType::Buy,
+                d(2023, 6, 1),
+                dec!(50),
+                dec!(70),
+            ),
+            trade(
+                vale,
+                TransactionType::Sell,
+                d(2024, 8, 1),
+                dec!(50),
+                dec!(60),
+            ),
+        ] {
+            db::insert_transaction(&conn, &tx).unwrap();
+        }
+
+        let groups = bens_e_direitos(&conn, 2024).unwrap();
+        assert_eq!(
+            groups.iter().map(|g| g.code).collect::<Vec<_>>(),
+            vec!["31", "73"]
+        );
+
+        let stocks = &groups[0];
+        assert_eq!(stocks.previous_total, dec!(6000));
+        assert_eq!(stocks.current_total, dec!(6000));
+        let petr4 = &stocks.items[0];
+        assert_eq!(petr4.previous_value, dec!(2500));
+        assert_eq!(petr4.current_value, dec!(6000));
+        assert_eq!(
+            petr4.discriminacao,
+            "200 ações de PETR4 - PETROLEO BRASILEIRO S.A. PETROBRAS, CNPJ 33.000.167/0001-01. Custo médio de R$ 30,00 por unidade."
+        );
+
+        let vale3 = &stocks.items[1];
+        assert_eq!(vale3.current_value, Decimal::ZERO);
+        assert_eq!(
+            vale3.discriminacao,
+            "50 ações de VALE3, integralmente alienadas em 2024."
+        );
+
+        assert_eq!(groups[1].items[0].previous_value, Decimal::ZERO);
+        assert_eq!(groups[1].current_total, dec!(1600));
+
+        let csv = export_to_csv(&groups, 2024).unwrap();
+        assert!(csv.starts_with("Código;Ticker;CNPJ;Discriminação;Situação em 31/12/2023;"));
+        assert!(csv.contains(";2500,00;6000,00\n"));
+    }
+}
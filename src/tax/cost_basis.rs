@@ -20,6 +20,9 @@ pub struct SaleCostBasis {
     #[allow(dead_code)]
     pub matched_lots: Vec<MatchedLot>,
     pub asset_type: AssetType,
+    pub asset_id: i64,
+    /// Id of the SELL transaction this sale came from (None for synthetic sales)
+    pub transaction_id: Option<i64>,
 }
 
 /// A matched lot from average cost calculation
@@ -35,6 +38,8 @@ pub struct MatchedLot {
 pub struct AverageCostMatcher {
     total_quantity: Decimal,
     total_cost: Decimal,
+    /// First purchase since the position was last zeroed
+    opened_on: Option<NaiveDate>,
 }
 
 impl AverageCostMatcher {
@@ -42,6 +47,7 @@ impl AverageCostMatcher {
         Self {
             total_quantity: Decimal::ZERO,
             total_cost: Decimal::ZERO,
+            opened_on: None,
         }
     }
 
@@ -68,6 +74,9 @@ impl AverageCostMatcher {
         let quantity = adjusted_quantity.unwrap_or(tx.quantity);
         let cost = adjusted_cost.unwrap_or(tx.total_cost);
 
+        if self.total_quantity <= Decimal::ZERO {
+            self.opened_on = Some(tx.trade_date);
+        }
         self.total_quantity += quantity;
         self.total_cost += cost;
     }
@@ -89,6 +98,45 @@ impl AverageCostMatcher {
     pub fn clear_position(&mut self) {
         self.total_quantity = Decimal::ZERO;
         self.total_cost = Decimal::ZERO;
+        self.opened_on = None;
+    }
+
+    /// Close the position in a merger paying `cash` alongside the new shares.
+    ///
+    /// The basis not carried to the new ticker (`carried_cost`) is the cost of
+    /// the cash portion, which is a sale of the delivered shares: returns it
+    /// when there is cash and a position to deliver.
+    pub fn settle_merger(
+        &mut self,
+        date: NaiveDate,
+        asset_id: i64,
+        carried_cost: Decimal,
+        cash: Decimal,
+    ) -> Option<SaleCostBasis> {
+        let quantity = self.total_quantity;
+        let cost_basis = (self.total_cost - carried_cost).max(Decimal::ZERO);
+        let opened_on = self.opened_on.unwrap_or(date);
+        self.clear_position();
+        if cash <= Decimal::ZERO || quantity <= Decimal::ZERO {
+            return None;
+        }
+
+        Some(SaleCostBasis {
+            sale_date: date,
+            quantity,
+            sale_price: cash / quantity,
+            sale_total: cash,
+            cost_basis,
+            profit_loss: cash - cost_basis,
+            matched_lots: vec![MatchedLot {
+                purchase_date: opened_on,
+                quantity,
+                cost: cost_basis,
+            }],
+            asset_type: AssetType::Stock,
+            asset_id,
+            transaction_id: None,
+        })
     }
 
     /// Match a sale using average cost up to that point, with optional adjusted quantity
@@ -139,11 +187,13 @@ impl AverageCostMatcher {
             cost_basis,
             profit_loss,
             matched_lots: vec![MatchedLot {
-                purchase_date: tx.trade_date,
+                purchase_date: self.opened_on.unwrap_or(tx.trade_date),
                 quantity,
                 cost: cost_basis,
             }],
             asset_type: AssetType::Stock,
+            asset_id: tx.asset_id,
+            transaction_id: tx.id,
         })
     }
 
@@ -1,10 +1,17 @@
 use anyhow::Result;
 use chrono::NaiveDate;
 use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
 
+use super::ledger::{DarfStatus, LedgerEntry};
 use super::swing_trade::{MonthlyTaxCalculation, TaxCategory};
 use crate::utils::format_currency;
 
+/// The Receita does not issue a DARF under R$10; the tax is added to the
+/// next month's DARF until the total reaches it
+pub const MIN_DARF: Decimal = Decimal::TEN;
+
 /// DARF payment information
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -54,7 +61,7 @@ pub fn generate_darf_payments(
 /// Calculate DARF due date
 /// Tax is due on the last business day of the month following the transaction month
 /// For simplicity, we use the last day of the month (business day check can be added later)
-fn calculate_darf_due_date(year: i32, month: u32) -> Result<NaiveDate> {
+pub fn calculate_darf_due_date(year: i32, month: u32) -> Result<NaiveDate> {
     // Get the following month
     let (due_year, due_month) = if month == 12 {
         (year + 1, 1)
@@ -110,6 +117,99 @@ pub fn format_monthly_darf_summary(payments: &[DarfPayment], year: i32, month: u
     output
 }
 
+/// One DARF of a month, with the fields a banking app asks for
+#[derive(Debug, Clone, Serialize)]
+pub struct DarfSlip {
+    pub darf_code: String,
+    /// Período de apuração: last day of the month the tax refers to
+    pub period: NaiveDate,
+    pub due_date: NaiveDate,
+    /// Tax of the month itself
+    pub month_tax: Decimal,
+    /// Earlier months of the year left under the minimum and not paid
+    pub carried: Decimal,
+    pub amount: Decimal,
+    /// Reaches the minimum, so it can be issued (and paid by PIX)
+    pub payable: bool,
+}
+
+/// DARFs of `month` from the year's ledger rows, one per code. Earlier
+/// months whose DARF stayed under [`MIN_DARF`] and was not paid are added in.
+pub fn darf_slips(entries: &[LedgerEntry], year: i32, month: u32) -> Result<Vec<DarfSlip>> {
+    let mut by_code: BTreeMap<&str, (Decimal, Decimal)> = BTreeMap::new();
+    for m in 1..=month {
+        let rows: Vec<&LedgerEntry> = entries
+            .iter()
+            .filter(|e| (e.year, e.month) == (year, m))
+            .collect();
+        let paid = rows.iter().any(|e| e.darf_status == DarfStatus::Paid);
+        let mut month_tax: BTreeMap<&str, Decimal> = BTreeMap::new();
+        for row in &rows {
+            if let Some(code) = row.category.darf_code() {
+                *month_tax.entry(code).or_default() += row.tax_due;
+            }
+        }
+        if m == month {
+            for (code, tax) in month_tax {
+                by_code.entry(code).or_default().1 = tax;
+            }
+            break;
+        }
+        for (code, tax) in month_tax {
+            let carried = &mut by_code.entry(code).or_default().0;
+            *carried += tax;
+            if paid || *carried >= MIN_DARF {
+                *carried = Decimal::ZERO;
+            }
+        }
+    }
+
+    let period = last_day_of_month(year, month);
+    let due_date = calculate_darf_due_date(year, month)?;
+    Ok(by_code
+        .into_iter()
+        .map(|(code, (carried, month_tax))| DarfSlip {
+            darf_code: code.to_string(),
+            period,
+            due_date,
+            month_tax,
+            carried,
+            amount: carried + month_tax,
+            payable: carried + month_tax >= MIN_DARF,
+        })
+        .filter(|slip| slip.amount > Decimal::ZERO)
+        .collect())
+}
+
+fn last_day_of_month(year: i32, month: u32) -> NaiveDate {
+    let (next_year, next_month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    NaiveDate::from_ymd_opt(next_year, next_month, 1)
+        .and_then(|d| d.pred_opt())
+        .expect("valid month")
+}
+
+/// Amount as banking apps take it: digits and a decimal comma, no R$ or
+/// thousands separator
+pub fn amount_digits(amount: Decimal) -> String {
+    format!("{:.2}", amount.round_dp(2)).replace('.', ",")
+}
+
+/// Copy-friendly block with one field per line, the way "DARF sem código de
+/// barras" forms ask for them
+pub fn format_plain(slip: &DarfSlip) -> String {
+    format!(
+        "Código da receita: {code}\nPeríodo de apuração: {period}\nVencimento: {due}\nValor principal: {amount}",
+        code = slip.darf_code,
+        period = slip.period.format("%d/%m/%Y"),
+        due = slip.due_date.format("%d/%m/%Y"),
+        amount = amount_digits(slip.amount),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,4 +256,67 @@ mod tests {
         assert!(formatted.contains("29/02/2024"));
         assert!(formatted.contains("R$ 1.500,00")); // Brazilian locale format
     }
+
+    #[test]
+    fn test_darf_slips_carry_amounts_under_the_minimum() {
+        let entry = |month, category, tax_due, status| LedgerEntry {
+            year: 2025,
+            month,
+            category,
+            sales: dec!(30000),
+            profit_loss: dec!(100),
+            loss_offset: Decimal::ZERO,
+            exemption: Decimal::ZERO,
+            tax_due,
+            darf_status: status,
+            paid_on: None,
+            paid_amount: None,
+            tx_fingerprint: String::new(),
+            computed_at: chrono::Utc::now(),
+        };
+        let entries = vec![
+            // Under the minimum but paid anyway: not carried
+            entry(1, TaxCategory::StockSwingTrade, dec!(3), DarfStatus::Paid),
+            // Under the minimum: carried into March
+            entry(
+                2,
+                TaxCategory::StockSwingTrade,
+                dec!(4),
+                DarfStatus::Pending,
+            ),
+            entry(
+                3,
+                TaxCategory::StockSwingTrade,
+                dec!(3.5),
+                DarfStatus::Pending,
+            ),
+            entry(
+                3,
+                TaxCategory::FiiSwingTrade,
+                dec!(1000.5),
+                DarfStatus::Pending,
+            ),
+            // Exempt category, no DARF
+            entry(3, TaxCategory::FiInfra, dec!(50), DarfStatus::Pending),
+        ];
+
+        let slips = darf_slips(&entries, 2025, 3).unwrap();
+        assert_eq!(slips.len(), 1);
+        let slip = &slips[0];
+        assert_eq!(slip.darf_code, "6015");
+        assert_eq!(slip.month_tax, dec!(1004));
+        assert_eq!(slip.carried, dec!(4));
+        assert_eq!(slip.amount, dec!(1008));
+        assert!(slip.payable);
+
+        let plain = format_plain(slip);
+        assert!(plain.contains("Período de apuração: 31/03/2025"));
+        assert!(plain.contains("Vencimento: 30/04/2025"));
+        assert!(plain.ends_with("Valor principal: 1008,00"));
+
+        // February alone stays under the minimum
+        let february = darf_slips(&entries, 2025, 2).unwrap();
+        assert!(!february[0].payable);
+        assert_eq!(amount_digits(dec!(1234567.891)), "1234567,89");
+    }
 }
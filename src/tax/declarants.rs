@@ -0,0 +1,154 @@
+//! Tax figures split by declarant, for couples declaring separately.
+//!
+//! Each declarant's portfolios are reported on their own: capital gains and
+//! tax due, dividends and JCP received, and the year-end holdings that go to
+//! their "Bens e Direitos". Portfolios without a declarant form a separate
+//! group so nothing is silently left out.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::db::{self, portfolio, AssetType, IncomeEventType};
+use crate::reports;
+
+/// Year-end position of one asset for a declarant
+#[derive(Debug, Clone, Serialize)]
+pub struct DeclarantHolding {
+    pub ticker: String,
+    pub asset_type: AssetType,
+    pub quantity: Decimal,
+    pub total_cost: Decimal,
+}
+
+/// One declarant's share of the database for a tax year
+#[derive(Debug, Clone, Serialize)]
+pub struct DeclarantReport {
+    /// None for the portfolios nobody declares
+    pub declarant: Option<String>,
+    pub portfolios: Vec<String>,
+    pub total_sales: Decimal,
+    pub total_profit: Decimal,
+    pub total_loss: Decimal,
+    pub tax_due: Decimal,
+    pub dividends: Decimal,
+    pub jcp: Decimal,
+    /// Fixed income coupons (debentures, CRI/CRA)
+    pub interest: Decimal,
+    pub income_withheld: Decimal,
+    /// Positions on 31/12, cost basis included
+    pub holdings: Vec<DeclarantHolding>,
+}
+
+impl DeclarantReport {
+    fn is_empty(&self) -> bool {
+        self.total_sales.is_zero()
+            && self.dividends.is_zero()
+            && self.jcp.is_zero()
+            && self.interest.is_zero()
+            && self.holdings.is_empty()
+    }
+}
+
+/// Build one report per declarant, plus one for the portfolios without a
+/// declarant when they hold anything. Uses the portfolio scope, so it must
+/// be called unscoped; the scope is cleared again on return.
+pub fn declarant_reports(conn: &Connection, year: i32) -> Result<Vec<DeclarantReport>> {
+    if portfolio::is_scoped() {
+        anyhow::bail!("A report by declarant covers all portfolios; drop --portfolio/--declarant");
+    }
+    let declarants = portfolio::list_declarants(conn)?;
+    if declarants.is_empty() {
+        anyhow::bail!(
+            "No portfolio has a declarant. Set them with: interest portfolios set-declarant <portfolio> <name>"
+        );
+    }
+
+    let names: std::collections::HashMap<i64, String> = portfolio::list_portfolios(conn)?
+        .into_iter()
+        .map(|(p, _, _)| (p.id, p.name))
+        .collect();
+    let mut groups: Vec<(Option<String>, Vec<i64>)> = Vec::new();
+    for declarant in declarants {
+        let ids = portfolio::declarant_portfolio_ids(conn, Some(&declarant))?;
+        groups.push((Some(declarant), ids));
+    }
+    let unassigned = portfolio::declarant_portfolio_ids(conn, None)?;
+    if !unassigned.is_empty() {
+        groups.push((None, unassigned));
+    }
+
+    let mut reports = Vec::new();
+    for (declarant, ids) in groups {
+        portfolio::set_scope(&ids);
+        let report = scoped_report(conn, year, declarant.clone(), &ids, &names);
+        portfolio::set_scope(&[]);
+        let report = report?;
+        if report.declarant.is_some() || !report.is_empty() {
+            reports.push(report);
+        }
+    }
+    Ok(reports)
+}
+
+fn scoped_report(
+    conn: &Connection,
+    year: i32,
+    declarant: Option<String>,
+    portfolio_ids: &[i64],
+    names: &std::collections::HashMap<i64, String>,
+) -> Result<DeclarantReport> {
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+    let to = NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+
+    let annual = crate::tax::irpf::generate_annual_report(conn, year)?;
+
+    let mut dividends = Decimal::ZERO;
+    let mut jcp = Decimal::ZERO;
+    let mut interest = Decimal::ZERO;
+    let mut income_withheld = Decimal::ZERO;
+    for (event, _) in db::get_income_events_with_assets(conn, Some(from), Some(to), None)? {
+        match event.event_type {
+            IncomeEventType::Dividend => dividends += event.total_amount,
+            IncomeEventType::Jcp => jcp += event.total_amount,
+            IncomeEventType::Interest => interest += event.total_amount,
+            IncomeEventType::Amortization => continue,
+        }
+        income_withheld += event.withholding_tax;
+    }
+
+    let year_end = reports::calculate_portfolio_at_date(conn, to, None)?;
+    let mut holdings: Vec<DeclarantHolding> = year_end
+        .positions
+        .into_iter()
+        .filter(|p| !p.quantity.is_zero())
+        .map(|p| DeclarantHolding {
+            ticker: p.asset.ticker,
+            asset_type: p.asset.asset_type,
+            quantity: p.quantity,
+            total_cost: p.total_cost,
+        })
+        .collect();
+    holdings.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+
+    Ok(DeclarantReport {
+        declarant,
+        portfolios: portfolio_ids
+            .iter()
+            .filter_map(|id| names.get(id).cloned())
+            .collect(),
+        total_sales: annual.annual_total_sales,
+        total_profit: annual.annual_total_profit,
+        total_loss: annual.annual_total_loss,
+        tax_due: annual.annual_total_tax,
+        dividends,
+        jcp,
+        interest,
+        income_withheld,
+        holdings,
+    })
+}
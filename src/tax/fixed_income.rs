@@ -0,0 +1,146 @@
+//! IR and IOF on fixed income redemptions.
+//!
+//! Private bonds follow the same regressive table as Tesouro Direto (see
+//! `tesouro.rs`), lot by lot, without custody. LCI, LCA, CRI and CRA are
+//! exempt for individuals, so their yield is only reported. The year view
+//! adds Tesouro Direto redemptions to give the two IRPF totals: yield net of
+//! IR under "Rendimentos sujeitos à tributação exclusiva" (06) and exempt
+//! yield under "Rendimentos isentos e não tributáveis" (12).
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::tesouro::{self, RedemptionTax};
+use crate::fixed_income::{self, FixedIncomeTerms};
+use crate::tesouro::LotRedemption;
+
+/// IOF and IR due on redeeming `lots` of a private bond on `date`
+pub fn redemption_tax(
+    terms: &FixedIncomeTerms,
+    date: NaiveDate,
+    lots: &[LotRedemption],
+) -> RedemptionTax {
+    let mut tax = tesouro::redemption_tax(&terms.ticker, date, lots, Decimal::ZERO);
+    if terms.kind.is_tax_exempt() {
+        for lot in tax.lots.iter_mut() {
+            lot.iof = Decimal::ZERO;
+            lot.taxable = Decimal::ZERO;
+            lot.ir_rate = Decimal::ZERO;
+            lot.ir = Decimal::ZERO;
+        }
+        tax.iof = Decimal::ZERO;
+        tax.ir = Decimal::ZERO;
+        tax.net = tax.proceeds;
+        tax.exempt = true;
+    }
+    tax
+}
+
+/// Private bond redemptions of `year` with their IOF and IR
+pub fn redemption_taxes(conn: &Connection, year: i32) -> Result<Vec<RedemptionTax>> {
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+    let to = NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+
+    let mut taxes = Vec::new();
+    for terms in fixed_income::list_terms(conn)? {
+        let history = crate::tesouro::replay_lots(conn, terms.asset_id, to)?;
+        for redemption in history.redemptions {
+            if redemption.date < from || redemption.lots.is_empty() {
+                continue;
+            }
+            taxes.push(redemption_tax(&terms, redemption.date, &redemption.lots));
+        }
+    }
+    taxes.sort_by(|a, b| (a.date, &a.ticker).cmp(&(b.date, &b.ticker)));
+    Ok(taxes)
+}
+
+/// Fixed income redemptions of a year, Tesouro Direto included
+#[derive(Debug, Clone, Serialize)]
+pub struct FixedIncomeYear {
+    pub year: i32,
+    pub redemptions: Vec<RedemptionTax>,
+    /// Taxed yield less IOF and IR withheld (IRPF code 06)
+    pub exclusive_net: Decimal,
+    /// Yield of exempt bonds (IRPF code 12)
+    pub exempt_yield: Decimal,
+    pub iof: Decimal,
+    pub ir: Decimal,
+}
+
+pub fn fixed_income_year(conn: &Connection, year: i32) -> Result<FixedIncomeYear> {
+    let mut redemptions = tesouro::redemption_taxes(conn, year)?;
+    redemptions.extend(redemption_taxes(conn, year)?);
+    redemptions.sort_by(|a, b| (a.date, &a.ticker).cmp(&(b.date, &b.ticker)));
+
+    let (exempt, taxed): (Vec<_>, Vec<_>) = redemptions.iter().partition(|r| r.exempt);
+    let positive_yield = |r: &RedemptionTax| r.gain.max(Decimal::ZERO);
+    let iof: Decimal = taxed.iter().map(|r| r.iof).sum();
+    let ir: Decimal = taxed.iter().map(|r| r.ir).sum();
+    Ok(FixedIncomeYear {
+        year,
+        exclusive_net: taxed.iter().map(|r| positive_yield(r)).sum::<Decimal>() - iof - ir,
+        exempt_yield: exempt.iter().map(|r| positive_yield(r)).sum(),
+        iof,
+        ir,
+        redemptions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixed_income::{FixedIncomeKind, Indexer};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_exempt_kinds_report_yield_without_tax() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES
+                 (1, 'CDB_BANCOX_2026', 'BOND'), (2, 'LCA_BANCOY_2026', 'BOND');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity, price_per_unit, total_cost)
+             VALUES (1, 'BUY', '2024-03-01', '1', '1000', '1000'),
+                    (1, 'SELL', '2025-03-01', '1', '1120', '1120'),
+                    (2, 'BUY', '2024-03-01', '2', '1000', '2000'),
+                    (2, 'SELL', '2025-03-01', '2', '1100', '2200');",
+        )
+        .unwrap();
+        let d = |y, m| NaiveDate::from_ymd_opt(y, m, 1).unwrap();
+        for (ticker, kind) in [
+            ("CDB_BANCOX_2026", FixedIncomeKind::Cdb),
+            ("LCA_BANCOY_2026", FixedIncomeKind::Lca),
+        ] {
+            fixed_income::set_terms(
+                &conn,
+                ticker,
+                kind,
+                d(2024, 3),
+                d(2026, 3),
+                Indexer::Prefixado,
+                dec!(12),
+                None,
+            )
+            .unwrap();
+        }
+
+        let year = fixed_income_year(&conn, 2025).unwrap();
+        assert_eq!(year.redemptions.len(), 2);
+        let cdb = &year.redemptions[0];
+        assert!(!cdb.exempt);
+        // 365 days held: over 360, so 17.5%
+        assert_eq!(cdb.lots[0].ir_rate, dec!(0.175));
+        assert_eq!(cdb.ir, dec!(21));
+        assert!(year.redemptions[1].exempt);
+        assert_eq!(year.redemptions[1].ir, Decimal::ZERO);
+        assert_eq!(year.exclusive_net, dec!(99));
+        assert_eq!(year.exempt_yield, dec!(200));
+    }
+}
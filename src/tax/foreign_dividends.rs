@@ -0,0 +1,230 @@
+//! Carnê-leão on BDR dividends.
+//!
+//! Dividends of BDRs are foreign-source income: unlike Brazilian dividends
+//! they are taxed every month by the progressive table (see `rules.rs`),
+//! paid by the investor on DARF 0190 by the last business day of the next
+//! month. The tax withheld abroad (30% in the US for investors without a
+//! treaty) can be deducted from the Brazilian tax on the same income, never
+//! beyond it.
+//!
+//! The amount credited by the custodian is already net of the foreign tax.
+//! When an event does not record `foreign_tax_withheld`, it is estimated by
+//! grossing the credit up at the US rate and flagged as estimated.
+//!
+//! The month's table is applied to the BDR dividends alone: other carnê-leão
+//! income (rent, foreign salary) of the same month raises the bracket.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use super::darf::calculate_darf_due_date;
+use super::rules::{carne_leao_rule, income_withholding_rule};
+use crate::db::{self, AssetType, IncomeEventType};
+
+/// Carnê-leão DARF code for individuals
+pub const DARF_CODE: &str = "0190";
+
+/// DARFs under this amount are added to the next month's
+const MIN_DARF: Decimal = Decimal::TEN;
+
+/// One BDR dividend with the tax withheld abroad
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignDividend {
+    pub date: NaiveDate,
+    pub ticker: String,
+    /// Amount credited in Brazil, net of the foreign tax
+    pub received: Decimal,
+    pub foreign_tax: Decimal,
+    /// True when `foreign_tax` was not recorded and assumes the US rate
+    pub estimated: bool,
+}
+
+impl ForeignDividend {
+    pub fn gross(&self) -> Decimal {
+        self.received + self.foreign_tax
+    }
+}
+
+/// Carnê-leão of one month
+#[derive(Debug, Clone, Serialize)]
+pub struct CarneLeaoMonth {
+    pub month: u32,
+    pub dividends: Vec<ForeignDividend>,
+    pub gross: Decimal,
+    /// Tax by the month's progressive table
+    pub tax: Decimal,
+    pub foreign_tax: Decimal,
+    /// Foreign tax deducted, up to `tax`
+    pub credit: Decimal,
+    /// Tax left under the DARF minimum in earlier months
+    pub carried_in: Decimal,
+    /// Amount of DARF 0190 to pay (zero when carried to the next month)
+    pub darf: Decimal,
+    pub due_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignDividendYear {
+    pub year: i32,
+    pub months: Vec<CarneLeaoMonth>,
+    pub gross: Decimal,
+    pub foreign_tax: Decimal,
+    pub darf_total: Decimal,
+    /// Under the DARF minimum at year end, paid in the annual adjustment
+    pub carried_out: Decimal,
+    pub has_estimates: bool,
+}
+
+/// Foreign tax of a BDR dividend paid on `date`: the recorded one, or the
+/// default foreign rate (US, see `rules.rs`) on the grossed-up credit
+pub fn foreign_tax(
+    received: Decimal,
+    recorded: Option<Decimal>,
+    date: NaiveDate,
+) -> (Decimal, bool) {
+    match recorded {
+        Some(tax) => (tax, false),
+        None => (
+            income_withholding_rule(
+                &IncomeEventType::Dividend,
+                &AssetType::Bdr,
+                date.year(),
+                date.month(),
+            )
+            .foreign_tax_on(received),
+            true,
+        ),
+    }
+}
+
+/// BDR dividends paid between `from` and `to`, by date
+pub fn foreign_dividends(
+    conn: &Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<ForeignDividend>> {
+    Ok(
+        db::get_income_events_with_assets(conn, Some(from), Some(to), None)?
+            .into_iter()
+            .filter(|(event, asset)| {
+                asset.asset_type == AssetType::Bdr
+                    && event.event_type != IncomeEventType::Amortization
+            })
+            .map(|(event, asset)| {
+                let received = event.total_amount - event.withholding_tax;
+                let (foreign_tax, estimated) =
+                    foreign_tax(received, event.foreign_tax_withheld, event.event_date);
+                ForeignDividend {
+                    date: event.event_date,
+                    ticker: asset.ticker,
+                    received,
+                    foreign_tax,
+                    estimated,
+                }
+            })
+            .collect(),
+    )
+}
+
+/// Monthly carnê-leão on the BDR dividends of `year`
+pub fn carne_leao_year(conn: &Connection, year: i32) -> Result<ForeignDividendYear> {
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+    let to = NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+
+    let mut by_month: BTreeMap<u32, Vec<ForeignDividend>> = BTreeMap::new();
+    for dividend in foreign_dividends(conn, from, to)? {
+        by_month
+            .entry(dividend.date.month())
+            .or_default()
+            .push(dividend);
+    }
+
+    let mut months = Vec::new();
+    let mut carried = Decimal::ZERO;
+    for (month, dividends) in by_month {
+        let gross: Decimal = dividends.iter().map(|d| d.gross()).sum();
+        let foreign_tax: Decimal = dividends.iter().map(|d| d.foreign_tax).sum();
+        let tax = carne_leao_rule(year, month).tax(gross);
+        let credit = foreign_tax.min(tax);
+        let carried_in = carried;
+        let owed = tax - credit + carried_in;
+        let darf = if owed >= MIN_DARF {
+            owed
+        } else {
+            Decimal::ZERO
+        };
+        carried = owed - darf;
+        months.push(CarneLeaoMonth {
+            month,
+            gross,
+            tax,
+            foreign_tax,
+            credit,
+            carried_in,
+            darf,
+            due_date: calculate_darf_due_date(year, month)?,
+            dividends,
+        });
+    }
+
+    Ok(ForeignDividendYear {
+        year,
+        gross: months.iter().map(|m| m.gross).sum(),
+        foreign_tax: months.iter().map(|m| m.foreign_tax).sum(),
+        darf_total: months.iter().map(|m| m.darf).sum(),
+        carried_out: carried,
+        has_estimates: months
+            .iter()
+            .flat_map(|m| &m.dividends)
+            .any(|d| d.estimated),
+        months,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_carne_leao_deducts_foreign_tax() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES
+                 (1, 'AAPL34', 'BDR'), (2, 'MSFT34', 'BDR'), (3, 'ITSA4', 'STOCK');
+             INSERT INTO income_events (asset_id, event_date, event_type, amount_per_quota,
+                 total_amount, foreign_tax_withheld, source)
+             VALUES (1, '2025-06-10', 'DIVIDEND', '1', '7000', '3000', 'MANUAL'),
+                    (2, '2025-06-20', 'DIVIDEND', '1', '700', NULL, 'MOVIMENTACAO'),
+                    (3, '2025-06-20', 'DIVIDEND', '1', '500', NULL, 'MOVIMENTACAO'),
+                    (1, '2025-08-10', 'DIVIDEND', '1', '14000', '0', 'MANUAL');",
+        )
+        .unwrap();
+
+        let year = carne_leao_year(&conn, 2025).unwrap();
+        assert_eq!(year.months.len(), 2);
+        let june = &year.months[0];
+        assert_eq!(june.dividends.len(), 2);
+        // 700 credited grosses up to 1000 with 300 estimated abroad
+        assert!(june.dividends[1].estimated);
+        assert_eq!(june.dividends[1].foreign_tax, dec!(300));
+        assert_eq!(june.gross, dec!(11000));
+        // 27.5% table on 11.000: 3025 - 908.73, all covered by the 3.300 withheld
+        assert_eq!(june.tax, dec!(2116.27));
+        assert_eq!(june.credit, dec!(2116.27));
+        assert_eq!(june.darf, Decimal::ZERO);
+        assert_eq!(june.due_date, NaiveDate::from_ymd_opt(2025, 7, 31).unwrap());
+        // Nothing withheld abroad: the whole table tax goes to the DARF
+        assert_eq!(year.months[1].darf, dec!(2941.27));
+        assert_eq!(year.darf_total, dec!(2941.27));
+        assert!(year.has_estimates);
+    }
+}
@@ -0,0 +1,173 @@
+//! Annual tax on assets held abroad.
+//!
+//! From 2024 (Lei 14.754/2023) gains on sales of assets held abroad and the
+//! dividends they pay are taxed once a year, at a flat rate, in the annual
+//! declaration (ficha Aplicações Financeiras no Exterior): the year's gains,
+//! losses and dividends are netted, losses of earlier years (2024 on) offset
+//! a positive result, and tax withheld abroad on the dividends is credited up
+//! to the Brazilian tax. Nothing is due monthly. Before 2024 sales were
+//! settled month by month in GCAP under the `Foreign` category.
+//!
+//! Assets held abroad are the ones quoted in a foreign currency; their
+//! trades and income are recorded in reais at the PTAX of settlement.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::rules::{category_rule, category_rules};
+use super::swing_trade::{realized_sales_for_year, TaxCategory};
+use crate::db::{self, IncomeEventType};
+
+/// The year's result abroad and the tax due in the annual declaration
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignGainsYear {
+    pub year: i32,
+    pub sales: Decimal,
+    /// Realized gains net of losses
+    pub gains: Decimal,
+    /// Gross dividends, before tax withheld abroad
+    pub dividends: Decimal,
+    pub foreign_tax: Decimal,
+    /// Losses of earlier years
+    pub carried_in: Decimal,
+    pub loss_offset: Decimal,
+    pub taxable: Decimal,
+    pub rate: Decimal,
+    pub tax: Decimal,
+    /// Tax withheld abroad deducted, up to `tax`
+    pub credit: Decimal,
+    pub due: Decimal,
+    /// Losses left for later years
+    pub carried_out: Decimal,
+    pub legal_basis: &'static str,
+}
+
+impl ForeignGainsYear {
+    pub fn is_empty(&self) -> bool {
+        self.sales.is_zero() && self.dividends.is_zero() && self.carried_in.is_zero()
+    }
+}
+
+/// First year settled annually
+fn first_annual_year() -> Option<i32> {
+    category_rules()
+        .iter()
+        .find(|r| r.category == TaxCategory::Foreign && r.annual)
+        .map(|r| r.since.0)
+}
+
+/// The year's gains and dividends abroad; None before the annual regime
+pub fn foreign_gains_year(conn: &Connection, year: i32) -> Result<Option<ForeignGainsYear>> {
+    let Some(first) = first_annual_year().filter(|first| year >= *first) else {
+        return Ok(None);
+    };
+    let mut carried = Decimal::ZERO;
+    for earlier in first..year {
+        carried = settle(conn, earlier, carried)?.carried_out;
+    }
+    settle(conn, year, carried).map(Some)
+}
+
+fn settle(conn: &Connection, year: i32, carried_in: Decimal) -> Result<ForeignGainsYear> {
+    let (mut sales, mut gains) = (Decimal::ZERO, Decimal::ZERO);
+    for (category, sale) in realized_sales_for_year(conn, year)? {
+        if category == TaxCategory::Foreign {
+            sales += sale.sale_total;
+            gains += sale.profit_loss;
+        }
+    }
+
+    let abroad = db::get_assets_quoted_abroad(conn)?;
+    let from = NaiveDate::from_ymd_opt(year, 1, 1).expect("valid year");
+    let to = NaiveDate::from_ymd_opt(year, 12, 31).expect("valid year");
+    let (mut dividends, mut foreign_tax) = (Decimal::ZERO, Decimal::ZERO);
+    for (event, asset) in db::get_income_events_with_assets(conn, Some(from), Some(to), None)? {
+        if event.event_type != IncomeEventType::Amortization
+            && asset.id.is_some_and(|id| abroad.contains(&id))
+        {
+            // Credited net of the tax withheld abroad, like BDR dividends
+            let withheld_abroad = event.foreign_tax_withheld.unwrap_or(Decimal::ZERO);
+            dividends += event.total_amount - event.withholding_tax + withheld_abroad;
+            foreign_tax += withheld_abroad;
+        }
+    }
+
+    let result = gains + dividends;
+    let loss_offset = if result > Decimal::ZERO {
+        result.min(carried_in)
+    } else {
+        Decimal::ZERO
+    };
+    let taxable = (result - loss_offset).max(Decimal::ZERO);
+    let carried_out = carried_in - loss_offset + (-result).max(Decimal::ZERO);
+    let rule = category_rule(&TaxCategory::Foreign, year, 12);
+    let tax = (taxable * rule.rate).round_dp(2);
+    let credit = foreign_tax.min(tax);
+    Ok(ForeignGainsYear {
+        year,
+        sales,
+        gains,
+        dividends,
+        foreign_tax,
+        carried_in,
+        loss_offset,
+        taxable,
+        rate: rule.rate,
+        tax,
+        credit,
+        due: tax - credit,
+        carried_out,
+        legal_basis: rule.legal_basis,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_annual_result_nets_years_and_credits_foreign_tax() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type, currency) VALUES (1, 'AAPL', 'STOCK', 'USD');
+             INSERT INTO assets (id, ticker, asset_type) VALUES (2, 'PETR4', 'STOCK');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source) VALUES
+                 (1, 'BUY', '2023-06-01', '10', '1000', '10000', '0', 'TEST'),
+                 (1, 'SELL', '2023-08-01', '2', '1200', '2400', '0', 'TEST'),
+                 (1, 'SELL', '2024-03-01', '4', '700', '2800', '0', 'TEST'),
+                 (1, 'SELL', '2025-05-02', '4', '1500', '6000', '0', 'TEST'),
+                 (2, 'BUY', '2025-01-02', '100', '30', '3000', '0', 'TEST'),
+                 (2, 'SELL', '2025-02-03', '100', '40', '4000', '0', 'TEST');
+             INSERT INTO income_events (asset_id, event_date, event_type, amount_per_quota,
+                 total_amount, withholding_tax, foreign_tax_withheld, source) VALUES
+                 (1, '2025-02-15', 'DIVIDEND', '0', '70', '0', '30', 'TEST'),
+                 (2, '2025-02-15', 'DIVIDEND', '0', '500', '0', NULL, 'TEST');",
+        )
+        .unwrap();
+
+        // 2023 was settled in GCAP
+        assert!(foreign_gains_year(&conn, 2023).unwrap().is_none());
+
+        let y2024 = foreign_gains_year(&conn, 2024).unwrap().unwrap();
+        assert_eq!(y2024.gains, dec!(-1200));
+        assert_eq!(y2024.tax, Decimal::ZERO);
+        assert_eq!(y2024.carried_out, dec!(1200));
+
+        // R$ 2.000 gain and R$ 100 dividends, less the 2024 loss: 15% of 900
+        let y2025 = foreign_gains_year(&conn, 2025).unwrap().unwrap();
+        assert_eq!(y2025.gains, dec!(2000));
+        assert_eq!(y2025.dividends, dec!(100));
+        assert_eq!(y2025.loss_offset, dec!(1200));
+        assert_eq!(y2025.tax, dec!(135));
+        assert_eq!(y2025.credit, dec!(30));
+        assert_eq!(y2025.due, dec!(105));
+        assert_eq!(y2025.carried_out, Decimal::ZERO);
+    }
+}
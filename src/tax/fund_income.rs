@@ -0,0 +1,268 @@
+//! FII and Fiagro distributions under the 2026 rules.
+//!
+//! Distributions of listed FIIs and Fiagros were exempt for individuals. The
+//! 2025 reform taxes them at source from 2026, but only for quotas acquired
+//! from 2026 on: quotas already held keep the exemption for good (see
+//! `rules.rs`). One distribution can therefore be part exempt, part taxed,
+//! in proportion to the quotas of each vintage held when the fund went ex.
+//!
+//! Vintages follow the trades: a purchase is a lot acquired on its trade
+//! date (or `quota_issuance_date`, for subscriptions), and sales consume the
+//! oldest lots first. An income event whose `is_quota_pre_2026` is set
+//! overrides the trades: true makes it all exempt, false all taxed.
+//!
+//! The tax is a projection: the administrator withholds it, and an event
+//! may already carry the amount in `withholding_tax`.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+use super::rules::{fund_income_rule, FundIncomeRule};
+use crate::db::{self, AssetType, IncomeEventType};
+
+/// One FII or Fiagro distribution split by quota vintage
+#[derive(Debug, Clone, Serialize)]
+pub struct FundDistribution {
+    pub event_id: Option<i64>,
+    pub date: NaiveDate,
+    pub ticker: String,
+    pub asset_type: AssetType,
+    pub amount: Decimal,
+    /// Share of the quotas held on the ex-date whose distributions are taxed
+    pub taxed_share: Decimal,
+    pub exempt: Decimal,
+    pub taxable: Decimal,
+    pub projected_tax: Decimal,
+    /// Tax the event records as already withheld
+    pub withheld: Decimal,
+    /// True when the vintage came from `is_quota_pre_2026` instead of the trades
+    pub recorded_vintage: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FundIncomeYear {
+    pub year: i32,
+    pub distributions: Vec<FundDistribution>,
+    pub exempt: Decimal,
+    pub taxable: Decimal,
+    pub projected_tax: Decimal,
+    pub withheld: Decimal,
+}
+
+impl FundIncomeYear {
+    /// Whether any distribution of the year falls under the new tax
+    pub fn has_taxable(&self) -> bool {
+        !self.taxable.is_zero()
+    }
+
+    /// Taxed part of each event, by event id
+    pub fn taxable_by_event(&self) -> HashMap<i64, Decimal> {
+        self.distributions
+            .iter()
+            .filter_map(|d| d.event_id.map(|id| (id, d.taxable)))
+            .collect()
+    }
+}
+
+struct Trade {
+    trade_date: NaiveDate,
+    acquired: NaiveDate,
+    is_buy: bool,
+    quantity: Decimal,
+}
+
+fn fund_trades(conn: &Connection, asset_id: i64) -> Result<Vec<Trade>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT trade_date, quota_issuance_date, transaction_type, quantity
+         FROM transactions
+         WHERE asset_id = ?1{}
+         ORDER BY trade_date ASC, id ASC",
+        db::portfolio::scope_filter("portfolio_id")
+    ))?;
+    let trades = stmt
+        .query_map([asset_id], |row| {
+            let trade_date: NaiveDate = row.get(0)?;
+            let issued: Option<NaiveDate> = row.get(1)?;
+            Ok(Trade {
+                trade_date,
+                acquired: issued.unwrap_or(trade_date),
+                is_buy: row.get::<_, String>(2)? == "BUY",
+                quantity: db::get_decimal_value(row, 3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(trades)
+}
+
+/// Quotas held before `cutoff` as (acquired, quantity) lots, oldest first
+fn lots_before(trades: &[Trade], cutoff: NaiveDate) -> VecDeque<(NaiveDate, Decimal)> {
+    let mut lots: VecDeque<(NaiveDate, Decimal)> = VecDeque::new();
+    for trade in trades.iter().filter(|t| t.trade_date < cutoff) {
+        if trade.is_buy {
+            lots.push_back((trade.acquired, trade.quantity));
+            continue;
+        }
+        let mut left = trade.quantity;
+        while left > Decimal::ZERO {
+            let Some(front) = lots.front_mut() else {
+                break;
+            };
+            let used = front.1.min(left);
+            front.1 -= used;
+            left -= used;
+            if front.1.is_zero() {
+                lots.pop_front();
+            }
+        }
+    }
+    lots
+}
+
+/// Share of the quotas held before `cutoff` whose distributions `rule` taxes
+fn taxed_share(trades: &[Trade], rule: &FundIncomeRule, cutoff: NaiveDate) -> Decimal {
+    let lots = lots_before(trades, cutoff);
+    let held: Decimal = lots.iter().map(|(_, q)| *q).sum();
+    let taxed: Decimal = lots
+        .iter()
+        .filter(|(acquired, _)| rule.taxes_quota((acquired.year(), acquired.month())))
+        .map(|(_, q)| *q)
+        .sum();
+    if held.is_zero() {
+        Decimal::ZERO
+    } else {
+        taxed / held
+    }
+}
+
+/// Share of the quotas of a fund held today whose distributions `rule` taxes
+pub fn held_taxed_share(
+    conn: &Connection,
+    asset_id: i64,
+    rule: &FundIncomeRule,
+) -> Result<Decimal> {
+    if rule.taxed_from.is_none() {
+        return Ok(Decimal::ZERO);
+    }
+    Ok(taxed_share(
+        &fund_trades(conn, asset_id)?,
+        rule,
+        NaiveDate::MAX,
+    ))
+}
+
+/// FII and Fiagro distributions paid in `year`, split by vintage
+pub fn fund_income_year(conn: &Connection, year: i32) -> Result<FundIncomeYear> {
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+    let to = NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+
+    let mut trades_by_asset: HashMap<i64, Vec<Trade>> = HashMap::new();
+    let mut distributions = Vec::new();
+    for (event, asset) in db::get_income_events_with_assets(conn, Some(from), Some(to), None)? {
+        if !matches!(asset.asset_type, AssetType::Fii | AssetType::Fiagro)
+            || event.event_type == IncomeEventType::Amortization
+        {
+            continue;
+        }
+        let rule = fund_income_rule(event.event_date.year(), event.event_date.month());
+        let (taxed_share, recorded_vintage) = match event.is_quota_pre_2026 {
+            Some(pre_2026) => {
+                let acquired = if pre_2026 { (2025, 12) } else { (2026, 1) };
+                let share = if rule.taxes_quota(acquired) {
+                    Decimal::ONE
+                } else {
+                    Decimal::ZERO
+                };
+                (share, true)
+            }
+            None if rule.taxed_from.is_none() => (Decimal::ZERO, false),
+            None => {
+                let trades = match trades_by_asset.entry(event.asset_id) {
+                    std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+                    std::collections::hash_map::Entry::Vacant(e) => {
+                        e.insert(fund_trades(conn, event.asset_id)?)
+                    }
+                };
+                // Holders on the day before the ex-date receive the distribution
+                let cutoff = event
+                    .ex_date
+                    .unwrap_or_else(|| event.event_date.succ_opt().unwrap_or(event.event_date));
+                (taxed_share(trades, rule, cutoff), false)
+            }
+        };
+        let taxable = (event.total_amount * taxed_share).round_dp(2);
+        distributions.push(FundDistribution {
+            event_id: event.id,
+            date: event.event_date,
+            ticker: asset.ticker,
+            asset_type: asset.asset_type,
+            amount: event.total_amount,
+            taxed_share,
+            exempt: event.total_amount - taxable,
+            taxable,
+            projected_tax: (taxable * rule.rate).round_dp(2),
+            withheld: event.withholding_tax,
+            recorded_vintage,
+        });
+    }
+
+    Ok(FundIncomeYear {
+        year,
+        exempt: distributions.iter().map(|d| d.exempt).sum(),
+        taxable: distributions.iter().map(|d| d.taxable).sum(),
+        projected_tax: distributions.iter().map(|d| d.projected_tax).sum(),
+        withheld: distributions.iter().map(|d| d.withheld).sum(),
+        distributions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_distributions_split_by_quota_vintage() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES
+                 (1, 'HGLG11', 'FII'), (2, 'RZAG11', 'FIAGRO');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, source)
+             VALUES (1, 'BUY', '2025-06-02', '100', '160', '16000', 'MANUAL'),
+                    (1, 'BUY', '2026-02-02', '100', '160', '16000', 'MANUAL'),
+                    (1, 'SELL', '2026-03-02', '50', '165', '8250', 'MANUAL'),
+                    (2, 'BUY', '2026-01-05', '10', '9', '90', 'MANUAL');
+             INSERT INTO income_events (asset_id, event_date, ex_date, event_type,
+                 amount_per_quota, total_amount, is_quota_pre_2026, source)
+             VALUES (1, '2026-02-13', '2026-02-01', 'DIVIDEND', '1.10', '110', NULL, 'MANUAL'),
+                    (1, '2026-03-13', '2026-03-05', 'DIVIDEND', '1.10', '165', NULL, 'MANUAL'),
+                    (2, '2026-03-13', NULL, 'DIVIDEND', '0.10', '1', true, 'MANUAL');",
+        )
+        .unwrap();
+
+        let year = fund_income_year(&conn, 2026).unwrap();
+        assert_eq!(year.distributions.len(), 3);
+        // Before the February purchase every quota is from 2025
+        assert_eq!(year.distributions[0].taxable, Decimal::ZERO);
+        // The sale consumed 2025 quotas first: 50 old, 100 new
+        let march = &year.distributions[1];
+        assert_eq!(march.taxable, dec!(110));
+        assert_eq!(march.exempt, dec!(55));
+        assert_eq!(march.projected_tax, dec!(5.50));
+        // A recorded vintage wins over the trades
+        assert!(year.distributions[2].recorded_vintage);
+        assert_eq!(year.distributions[2].taxable, Decimal::ZERO);
+        assert_eq!(year.projected_tax, dec!(5.50));
+
+        // 2025 distributions are exempt whatever the vintage
+        assert!(!fund_income_year(&conn, 2025).unwrap().has_taxable());
+    }
+}
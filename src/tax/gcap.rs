@@ -0,0 +1,233 @@
+//! Realized sales in the fields of the Receita Federal GCAP program
+//! (Programa de Apuração dos Ganhos de Capital).
+//!
+//! Each sale of the year becomes one operation: what was sold (with the
+//! issuer's CNPJ), acquisition and sale dates, sale value, selling expenses,
+//! acquisition cost and the resulting gain. The acquisition date is the first
+//! purchase of the position that was being sold, since average cost does not
+//! track lots. Values use the Brazilian decimal comma and `;` as separator,
+//! the way GCAP and Brazilian spreadsheets read them.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::{self, AssetType};
+use crate::tax::swing_trade::realized_sales_for_year;
+
+/// One sale, as typed into a GCAP operation
+#[derive(Debug, Clone, Serialize)]
+pub struct GcapOperation {
+    pub ticker: String,
+    /// Razão social when the issuer was looked up, otherwise the asset name
+    pub issuer: Option<String>,
+    pub cnpj: Option<String>,
+    /// Kind of asset, in GCAP's words (Ações, Cotas de FII, ...)
+    pub kind: &'static str,
+    /// Tax category (swing trade, day trade, FII...)
+    pub category: &'static str,
+    pub quantity: Decimal,
+    pub acquisition_date: NaiveDate,
+    pub sale_date: NaiveDate,
+    pub sale_value: Decimal,
+    /// Brokerage and exchange fees of the sale
+    pub expenses: Decimal,
+    pub acquisition_cost: Decimal,
+    /// Negative for a loss
+    pub gain: Decimal,
+}
+
+/// Every sale realized during `year`
+pub fn gcap_operations(conn: &Connection, year: i32) -> Result<Vec<GcapOperation>> {
+    let assets: HashMap<i64, db::Asset> = db::get_all_assets(conn)?
+        .into_iter()
+        .filter_map(|a| a.id.map(|id| (id, a)))
+        .collect();
+    let mut issuers: HashMap<i64, Option<db::AssetIssuer>> = HashMap::new();
+
+    let mut operations = Vec::new();
+    for (category, sale) in realized_sales_for_year(conn, year)? {
+        let Some(asset) = assets.get(&sale.asset_id) else {
+            continue;
+        };
+        let issuer = match issuers.get(&sale.asset_id) {
+            Some(issuer) => issuer.clone(),
+            None => {
+                let issuer = db::get_asset_issuer(conn, sale.asset_id)?;
+                issuers.insert(sale.asset_id, issuer.clone());
+                issuer
+            }
+        };
+
+        operations.push(GcapOperation {
+            ticker: asset.ticker.clone(),
+            issuer: issuer
+                .as_ref()
+                .map(|i| i.legal_name.clone())
+                .or_else(|| asset.name.clone()),
+            cnpj: issuer.map(|i| i.cnpj).or_else(|| asset.cnpj.clone()),
+            kind: kind_label(&asset.asset_type),
+            category: category.display_name(),
+            quantity: sale.quantity,
+            acquisition_date: sale
+                .matched_lots
+                .first()
+                .map(|lot| lot.purchase_date)
+                .unwrap_or(sale.sale_date),
+            sale_date: sale.sale_date,
+            sale_value: sale.sale_total,
+            expenses: sale.sale_total - sale.cost_basis - sale.profit_loss,
+            acquisition_cost: sale.cost_basis,
+            gain: sale.profit_loss,
+        });
+    }
+    Ok(operations)
+}
+
+fn kind_label(asset_type: &AssetType) -> &'static str {
+    match asset_type {
+        AssetType::Fii => "Cotas de FII",
+        AssetType::Fiagro => "Cotas de Fiagro",
+        AssetType::FiInfra => "Cotas de FI-Infra",
+        AssetType::Etf => "Cotas de ETF",
+        AssetType::Bdr => "BDR",
+        AssetType::Option => "Opções",
+        AssetType::TermContract => "Termo",
+        _ => "Ações",
+    }
+}
+
+/// CSV with one line per operation, in GCAP's field order
+pub fn export_to_csv(operations: &[GcapOperation]) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b';')
+        .from_writer(Vec::new());
+    writer.write_record([
+        "Especificação",
+        "CNPJ",
+        "Tipo",
+        "Categoria",
+        "Quantidade",
+        "Data de aquisição",
+        "Data de alienação",
+        "Valor de alienação",
+        "Despesas de alienação",
+        "Custo de aquisição",
+        "Ganho de capital",
+    ])?;
+
+    let date = |d: NaiveDate| d.format("%d/%m/%Y").to_string();
+    let number = |v: Decimal| v.normalize().to_string().replace('.', ",");
+    let money = |v: Decimal| format!("{:.2}", v.round_dp(2)).replace('.', ",");
+    for op in operations {
+        let specification = match &op.issuer {
+            Some(issuer) => format!("{} {} - {}", number(op.quantity), op.ticker, issuer),
+            None => format!("{} {}", number(op.quantity), op.ticker),
+        };
+        writer.write_record([
+            specification,
+            op.cnpj.clone().unwrap_or_default(),
+            op.kind.to_string(),
+            op.category.to_string(),
+            number(op.quantity),
+            date(op.acquisition_date),
+            date(op.sale_date),
+            money(op.sale_value),
+            money(op.expenses),
+            money(op.acquisition_cost),
+            money(op.gain),
+        ])?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Transaction, TransactionType};
+    use rust_decimal_macros::dec;
+
+    fn trade(
+        asset_id: i64,
+        transaction_type: TransactionType,
+        date: NaiveDate,
+        quantity: Decimal,
+        price: Decimal,
+        fees: Decimal,
+    ) -> Transaction {
+        Transaction {
+            id: None,
+            asset_id,
+            transaction_type,
+            trade_date: date,
+            settlement_date: None,
+            quantity,
+            price_per_unit: price,
+            total_cost: quantity * price,
+            fees,
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: None,
+            source: "TEST".to_string(),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_gcap_operation_dates_and_csv() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id =
+            db::upsert_asset(&conn, "PETR4", &AssetType::Stock, Some("PETROBRAS PN")).unwrap();
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+
+        for tx in [
+            trade(
+                asset_id,
+                TransactionType::Buy,
+                d(2022, 3, 10),
+                dec!(100),
+                dec!(20),
+                dec!(0),
+            ),
+            trade(
+                asset_id,
+                TransactionType::Buy,
+                d(2023, 1, 5),
+                dec!(100),
+                dec!(30),
+                dec!(0),
+            ),
+            trade(
+                asset_id,
+                TransactionType::Sell,
+                d(2024, 6, 3),
+                dec!(50),
+                dec!(40),
+                dec!(1.5),
+            ),
+        ] {
+            db::insert_transaction(&conn, &tx).unwrap();
+        }
+
+        let operations = gcap_operations(&conn, 2024).unwrap();
+        assert_eq!(operations.len(), 1);
+        let op = &operations[0];
+        assert_eq!(op.acquisition_date, d(2022, 3, 10));
+        assert_eq!(op.acquisition_cost, dec!(1250));
+        assert_eq!(op.expenses, dec!(1.5));
+        assert_eq!(op.gain, dec!(748.5));
+
+        let csv = export_to_csv(&operations).unwrap();
+        let line = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            line,
+            "50 PETR4 - PETROBRAS PN;;Ações;Ações (Swing Trade);50;10/03/2022;03/06/2024;2000,00;1,50;1250,00;748,50"
+        );
+        assert!(gcap_operations(&conn, 2023).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+//! Expected income net of tax, under the law or a dividend tax scenario.
+//!
+//! Each expected payment is taxed by the rules of the month it is paid in:
+//! JCP at its IRRF, FII and Fiagro distributions in proportion to the quotas
+//! held that the fund tax reaches, and stock dividends by the total each
+//! company pays in the month (withheld in full once it passes the
+//! threshold). Payers are told apart by CNPJ, or by the ticker's first four
+//! letters when the asset has none, so PETR3 and PETR4 add up.
+//!
+//! A scenario replaces the dividend rule from a chosen month on, to weigh a
+//! proposal before it takes effect; the rest of the law stays as it is.
+
+use anyhow::Result;
+use chrono::Datelike;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use super::fund_income::held_taxed_share;
+use super::rules::{
+    fund_income_rule, income_withholding_rule, payer_dividend_rule, PayerDividendRule,
+};
+use crate::db::{self, Asset, AssetType, IncomeEventType};
+use crate::reports::income_forecast::IncomeForecast;
+
+/// Dividend tax to project instead of the law from `since` on
+#[derive(Debug, Clone, Serialize)]
+pub struct DividendScenario {
+    /// First (year, month) the scenario applies to
+    pub since: (i32, u32),
+    pub rate: Decimal,
+    /// A company's dividends in the month up to this are not taxed
+    pub monthly_threshold: Decimal,
+}
+
+impl DividendScenario {
+    fn rule(&self) -> PayerDividendRule {
+        PayerDividendRule {
+            since: self.since,
+            rate: self.rate,
+            monthly_threshold: self.monthly_threshold,
+            legal_basis: "Scenario",
+        }
+    }
+}
+
+fn payer_key(asset: &Asset) -> String {
+    match &asset.cnpj {
+        Some(cnpj) => cnpj.clone(),
+        None => asset.ticker.chars().take(4).collect(),
+    }
+}
+
+/// Fill in the tax on each payment and asset of `forecast` under the law in
+/// force when it is paid, or `scenario` for dividends from its start on
+pub fn apply(
+    conn: &Connection,
+    forecast: &mut IncomeForecast,
+    scenario: Option<&DividendScenario>,
+) -> Result<()> {
+    let scenario_rule = scenario.map(DividendScenario::rule);
+    let dividend_rule = |year: i32, month: u32| match &scenario_rule {
+        Some(rule) if (year, month) >= rule.since => rule,
+        _ => payer_dividend_rule(year, month),
+    };
+
+    let mut assets: HashMap<String, Option<Asset>> = HashMap::new();
+    for p in &forecast.payments {
+        if !assets.contains_key(&p.ticker) {
+            assets.insert(p.ticker.clone(), db::get_asset_by_ticker(conn, &p.ticker)?);
+        }
+    }
+
+    // Stock dividends of each company and month, to test against the threshold
+    let mut payer_months: HashMap<(String, i32, u32), Decimal> = HashMap::new();
+    for p in &forecast.payments {
+        if let (IncomeEventType::Dividend, Some(asset)) = (&p.event_type, &assets[&p.ticker]) {
+            if asset.asset_type == AssetType::Stock {
+                *payer_months
+                    .entry((payer_key(asset), p.date.year(), p.date.month()))
+                    .or_default() += p.amount;
+            }
+        }
+    }
+    let taxed_months: HashSet<(String, i32, u32)> = payer_months
+        .into_iter()
+        .filter(|((_, year, month), total)| !dividend_rule(*year, *month).tax(*total).is_zero())
+        .map(|(key, _)| key)
+        .collect();
+
+    for p in &mut forecast.payments {
+        let (year, month) = (p.date.year(), p.date.month());
+        let Some(asset) = &assets[&p.ticker] else {
+            p.tax = Some(Decimal::ZERO);
+            continue;
+        };
+        let tax = match (&p.event_type, &asset.asset_type) {
+            (IncomeEventType::Dividend, AssetType::Stock) => {
+                if taxed_months.contains(&(payer_key(asset), year, month)) {
+                    (p.amount * dividend_rule(year, month).rate).round_dp(2)
+                } else {
+                    Decimal::ZERO
+                }
+            }
+            (IncomeEventType::Dividend, AssetType::Fii | AssetType::Fiagro) => {
+                let rule = fund_income_rule(year, month);
+                let share = match asset.id {
+                    Some(id) => held_taxed_share(conn, id, rule)?,
+                    None => Decimal::ZERO,
+                };
+                (p.amount * share * rule.rate).round_dp(2)
+            }
+            (event_type, asset_type) => {
+                income_withholding_rule(event_type, asset_type, year, month).withheld_from(p.amount)
+            }
+        };
+        p.tax = Some(tax);
+    }
+
+    for outlook in &mut forecast.assets {
+        outlook.tax = Some(
+            forecast
+                .payments
+                .iter()
+                .filter(|p| p.ticker == outlook.ticker)
+                .filter_map(|p| p.tax)
+                .sum(),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reports::income_forecast::ExpectedPayment;
+    use chrono::NaiveDate;
+    use rust_decimal_macros::dec;
+    use tempfile::TempDir;
+
+    fn payment(
+        ticker: &str,
+        date: &str,
+        event_type: IncomeEventType,
+        amount: Decimal,
+    ) -> ExpectedPayment {
+        ExpectedPayment {
+            date: date.parse().unwrap(),
+            ticker: ticker.to_string(),
+            event_type,
+            quantity: None,
+            amount_per_quota: Decimal::ZERO,
+            amount,
+            low: amount,
+            high: amount,
+            announced: true,
+            cadence: None,
+            tax: None,
+        }
+    }
+
+    #[test]
+    fn test_forecast_taxed_by_law_and_scenario() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.db");
+        db::init_database(Some(path.clone()))?;
+        let conn = db::open_db(Some(path))?;
+        for ticker in ["PETR3", "PETR4", "ITSA4"] {
+            db::insert_asset(&conn, ticker, &AssetType::Stock, None)?;
+        }
+
+        let forecast = IncomeForecast {
+            as_of: NaiveDate::from_ymd_opt(2026, 10, 1).unwrap(),
+            until: NaiveDate::from_ymd_opt(2027, 10, 1).unwrap(),
+            payments: vec![
+                // PETR3 and PETR4 share a payer: R$ 60.000,00 in November
+                payment(
+                    "PETR3",
+                    "2026-11-20",
+                    IncomeEventType::Dividend,
+                    dec!(20000),
+                ),
+                payment(
+                    "PETR4",
+                    "2026-11-20",
+                    IncomeEventType::Dividend,
+                    dec!(40000),
+                ),
+                payment("ITSA4", "2026-11-20", IncomeEventType::Dividend, dec!(1000)),
+                payment("ITSA4", "2027-03-10", IncomeEventType::Jcp, dec!(1000)),
+                payment("ITSA4", "2027-04-10", IncomeEventType::Dividend, dec!(1000)),
+            ],
+            assets: Vec::new(),
+        };
+
+        let mut law = forecast.clone();
+        apply(&conn, &mut law, None)?;
+        let taxes: Vec<_> = law.payments.iter().map(|p| p.tax.unwrap()).collect();
+        assert_eq!(
+            taxes,
+            vec![
+                dec!(2000),
+                dec!(4000),
+                Decimal::ZERO,
+                dec!(150),
+                Decimal::ZERO
+            ]
+        );
+        assert_eq!(law.total_tax(), dec!(6150));
+
+        // 15% on every dividend from 2027: November keeps the law
+        let mut scenario = forecast.clone();
+        apply(
+            &conn,
+            &mut scenario,
+            Some(&DividendScenario {
+                since: (2027, 1),
+                rate: dec!(0.15),
+                monthly_threshold: Decimal::ZERO,
+            }),
+        )?;
+        let taxes: Vec<_> = scenario.payments.iter().map(|p| p.tax.unwrap()).collect();
+        assert_eq!(
+            taxes,
+            vec![dec!(2000), dec!(4000), Decimal::ZERO, dec!(150), dec!(150)]
+        );
+        assert_eq!(scenario.payments[4].net(), dec!(850));
+        Ok(())
+    }
+}
@@ -3,24 +3,25 @@ use rusqlite::Connection;
 use rust_decimal::Decimal;
 use std::collections::HashMap;
 
+use super::ledger::{self, LedgerEntry};
 use super::loss_carryforward::{
     clear_year_losses, compute_year_fingerprint, earliest_transaction_year, load_snapshots,
     record_loss, upsert_snapshot,
 };
-use super::swing_trade::{calculate_monthly_tax, TaxCategory};
+use super::swing_trade::{calculate_annual_tax, TaxCategory};
 use tracing::debug;
 
 /// Monthly summary for IRPF
 ///
 #[derive(Debug, Clone)]
 pub struct MonthlyIrpfSummary {
+    pub month: u32,
     pub month_name: &'static str,
     pub total_sales: Decimal,
     pub total_profit: Decimal,
     pub total_loss: Decimal,
     pub total_loss_offset_applied: Decimal,
     pub tax_due: Decimal,
-    #[allow(dead_code)]
     pub by_category: HashMap<TaxCategory, CategoryMonthSummary>,
 }
 
@@ -96,10 +97,10 @@ fn compute_annual_report_with_carry(
 
     let mut carryforward = starting_carry.clone();
 
-    // Process each month
-    for month in 1..=12 {
-        let month_calculations = calculate_monthly_tax(conn, year, month, &mut carryforward)?;
+    // Positions are replayed once; months come back in order with carry applied
+    let annual_calculations = calculate_annual_tax(conn, year, &mut carryforward)?;
 
+    for (month_calculations, month) in annual_calculations.into_iter().zip(1u32..) {
         if month_calculations.is_empty() {
             continue;
         }
@@ -146,6 +147,7 @@ fn compute_annual_report_with_carry(
         annual_total_tax += month_tax;
 
         monthly_summaries.push(MonthlyIrpfSummary {
+            month,
             month_name: get_month_name(month),
             total_sales: month_sales,
             total_profit: month_profit,
@@ -191,7 +193,6 @@ fn compute_annual_report_with_carry(
 }
 
 /// Generate annual IRPF report for a year (deterministic, snapshot-aware)
-#[allow(dead_code)]
 pub fn generate_annual_report(conn: &Connection, year: i32) -> Result<AnnualTaxReport> {
     generate_annual_report_with_progress(conn, year, |_| {})
 }
@@ -207,7 +208,14 @@ where
 {
     let earliest_year = earliest_transaction_year(conn)?.unwrap_or(year);
 
-    let snapshots = load_snapshots(conn)?;
+    // The carryforward ledger and snapshots belong to the aggregate of all
+    // portfolios; a scoped report recomputes its own carry and stores nothing
+    let scoped = crate::db::portfolio::is_scoped();
+    let snapshots = if scoped {
+        HashMap::new()
+    } else {
+        load_snapshots(conn)?
+    };
 
     debug!(
         target_year = year,
@@ -227,6 +235,7 @@ where
     let mut carry: HashMap<TaxCategory, Decimal> = HashMap::new();
     let mut carry_before_target: HashMap<TaxCategory, Decimal> = HashMap::new();
     let mut target_snapshot_valid = false;
+    let mut target_fingerprint = String::new();
 
     // Find the latest consecutive snapshot chain with matching fingerprints before target
     for y in earliest_year..=year {
@@ -245,6 +254,7 @@ where
                     // For the target year, we want the starting carry BEFORE applying this snapshot
                     carry_before_target = carry.clone();
                     target_snapshot_valid = true;
+                    target_fingerprint = fingerprint;
                 }
                 // snapshot matches; we can use its ending carry for next year
                 carry = snapshot.ending_carry.clone();
@@ -273,7 +283,18 @@ where
             "Using cached carry for target year; skipping recomputation"
         );
         progress(ReportProgress::TargetCacheHit { year });
+        if let Some(entries) = ledger::load_year(conn, year, &target_fingerprint)? {
+            return Ok(report_from_ledger(
+                year,
+                &entries,
+                carry_before_target,
+                carry,
+            ));
+        }
         let (report, _) = compute_annual_report_with_carry(conn, year, carry_before_target, false)?;
+        if !scoped {
+            ledger::store_year(conn, year, &target_fingerprint, &report)?;
+        }
         return Ok(report);
     }
 
@@ -287,16 +308,21 @@ where
     });
 
     // Clear loss ledger for all years being recomputed to avoid stale data
-    for y in recompute_start..=year {
-        clear_year_losses(conn, y)?;
+    if !scoped {
+        for y in recompute_start..=year {
+            clear_year_losses(conn, y)?;
+        }
     }
 
     let mut last_report = None;
     for y in recompute_start..=year {
-        let fingerprint = compute_year_fingerprint(conn, y)?;
         let (report, ending_carry) =
-            compute_annual_report_with_carry(conn, y, carry.clone(), true)?;
-        upsert_snapshot(conn, y, &fingerprint, &ending_carry)?;
+            compute_annual_report_with_carry(conn, y, carry.clone(), !scoped)?;
+        if !scoped {
+            let fingerprint = compute_year_fingerprint(conn, y)?;
+            upsert_snapshot(conn, y, &fingerprint, &ending_carry)?;
+            ledger::store_year(conn, y, &fingerprint, &report)?;
+        }
         debug!(
             target_year = year,
             recomputed_year = y,
@@ -320,6 +346,66 @@ where
     last_report.ok_or_else(|| anyhow::anyhow!("Failed to compute annual report for {year}"))
 }
 
+/// Annual report rebuilt from ledger rows stored under a valid snapshot
+fn report_from_ledger(
+    year: i32,
+    entries: &[LedgerEntry],
+    starting_carry: HashMap<TaxCategory, Decimal>,
+    ending_carry: HashMap<TaxCategory, Decimal>,
+) -> AnnualTaxReport {
+    let mut monthly_summaries: Vec<MonthlyIrpfSummary> = Vec::new();
+    for entry in entries.iter().filter(|e| !e.is_empty()) {
+        if monthly_summaries.last().map(|m| m.month) != Some(entry.month) {
+            monthly_summaries.push(MonthlyIrpfSummary {
+                month: entry.month,
+                month_name: get_month_name(entry.month),
+                total_sales: Decimal::ZERO,
+                total_profit: Decimal::ZERO,
+                total_loss: Decimal::ZERO,
+                total_loss_offset_applied: Decimal::ZERO,
+                tax_due: Decimal::ZERO,
+                by_category: HashMap::new(),
+            });
+        }
+        let summary = monthly_summaries.last_mut().expect("pushed above");
+        summary.total_sales += entry.sales;
+        if entry.profit_loss > Decimal::ZERO {
+            summary.total_profit += entry.profit_loss;
+        } else {
+            summary.total_loss += entry.profit_loss.abs();
+        }
+        summary.total_loss_offset_applied += entry.loss_offset;
+        summary.tax_due += entry.tax_due;
+        summary.by_category.insert(
+            entry.category.clone(),
+            CategoryMonthSummary {
+                sales: entry.sales,
+                profit_loss: entry.profit_loss,
+                loss_offset_applied: entry.loss_offset,
+                exemption_applied: entry.exemption,
+                tax_due: entry.tax_due,
+            },
+        );
+    }
+
+    let non_zero = |carry: HashMap<TaxCategory, Decimal>| {
+        carry
+            .into_iter()
+            .filter(|(_, v)| !v.is_zero())
+            .collect::<HashMap<_, _>>()
+    };
+    AnnualTaxReport {
+        year,
+        annual_total_sales: monthly_summaries.iter().map(|m| m.total_sales).sum(),
+        annual_total_profit: monthly_summaries.iter().map(|m| m.total_profit).sum(),
+        annual_total_loss: monthly_summaries.iter().map(|m| m.total_loss).sum(),
+        annual_total_tax: monthly_summaries.iter().map(|m| m.tax_due).sum(),
+        monthly_summaries,
+        previous_losses_carry_forward: non_zero(starting_carry),
+        losses_to_carry_forward: non_zero(ending_carry),
+    }
+}
+
 /// Get month name in Portuguese
 fn get_month_name(month: u32) -> &'static str {
     match month {
@@ -387,6 +473,8 @@ pub fn export_to_csv(report: &AnnualTaxReport) -> String {
                 TaxCategory::FiagroSwingTrade => "FIAGRO (Swing Trade)",
                 TaxCategory::FiagroDayTrade => "FIAGRO (Day Trade)",
                 TaxCategory::FiInfra => "FI-Infra (Isento)",
+                TaxCategory::Foreign => "Exterior",
+                TaxCategory::Crypto => "Criptoativos",
             };
             csv.push_str(&format!("{},{:.2}\n", category_name, loss));
         }
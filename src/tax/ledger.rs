@@ -0,0 +1,309 @@
+//! Monthly tax ledger.
+//!
+//! Every time a tax year is computed for the whole database, each month's
+//! result per category is stored in `tax_ledger` next to the loss
+//! carryforward snapshot, under the same transaction fingerprint. While the
+//! fingerprint holds, annual reports are rebuilt from these rows instead of
+//! replaying the year's trades.
+//!
+//! The ledger also keeps what was filed: marking a month's DARF as paid
+//! records the date and amount, which survive later recomputations. A paid
+//! month whose tax changed afterwards shows the difference to settle.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::str::FromStr;
+
+use super::irpf::AnnualTaxReport;
+use super::swing_trade::TaxCategory;
+use crate::db::{get_decimal_value, get_optional_decimal_value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DarfStatus {
+    NotDue,
+    Pending,
+    Paid,
+}
+
+impl FromStr for DarfStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "NOT_DUE" => Ok(DarfStatus::NotDue),
+            "PENDING" => Ok(DarfStatus::Pending),
+            "PAID" => Ok(DarfStatus::Paid),
+            _ => Err(anyhow::anyhow!("Unknown DARF status: {}", s)),
+        }
+    }
+}
+
+/// One category of one month, as last computed
+#[derive(Debug, Clone, Serialize)]
+pub struct LedgerEntry {
+    pub year: i32,
+    pub month: u32,
+    #[serde(serialize_with = "serialize_category")]
+    pub category: TaxCategory,
+    pub sales: Decimal,
+    pub profit_loss: Decimal,
+    pub loss_offset: Decimal,
+    pub exemption: Decimal,
+    pub tax_due: Decimal,
+    pub darf_status: DarfStatus,
+    pub paid_on: Option<NaiveDate>,
+    pub paid_amount: Option<Decimal>,
+    #[serde(skip)]
+    pub tx_fingerprint: String,
+    pub computed_at: chrono::DateTime<chrono::Utc>,
+}
+
+fn serialize_category<S: serde::Serializer>(
+    category: &TaxCategory,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(category.as_str())
+}
+
+impl LedgerEntry {
+    /// Whether the row only keeps a payment for a month no longer taxed
+    pub fn is_empty(&self) -> bool {
+        self.sales.is_zero() && self.profit_loss.is_zero() && self.tax_due.is_zero()
+    }
+}
+
+/// Replace the computed rows of `year`, keeping recorded payments
+pub fn store_year(
+    conn: &Connection,
+    year: i32,
+    fingerprint: &str,
+    report: &AnnualTaxReport,
+) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "DELETE FROM tax_ledger WHERE year = ?1 AND paid_on IS NULL",
+        [year],
+    )?;
+    // Paid months stay listed even when their sales are gone
+    tx.execute(
+        "UPDATE tax_ledger SET sales = '0', profit_loss = '0', loss_offset = '0',
+                exemption = '0', tax_due = '0', tx_fingerprint = ?2,
+                computed_at = CURRENT_TIMESTAMP
+         WHERE year = ?1",
+        params![year, fingerprint],
+    )?;
+    for summary in &report.monthly_summaries {
+        for (category, calc) in &summary.by_category {
+            tx.execute(
+                "INSERT INTO tax_ledger (year, month, tax_category, sales, profit_loss,
+                     loss_offset, exemption, tax_due, darf_status, tx_fingerprint)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 'NOT_DUE', ?9)
+                 ON CONFLICT(year, month, tax_category) DO UPDATE SET
+                     sales = excluded.sales, profit_loss = excluded.profit_loss,
+                     loss_offset = excluded.loss_offset, exemption = excluded.exemption,
+                     tax_due = excluded.tax_due, tx_fingerprint = excluded.tx_fingerprint,
+                     computed_at = CURRENT_TIMESTAMP",
+                params![
+                    year,
+                    summary.month,
+                    category.as_str(),
+                    calc.sales.to_string(),
+                    calc.profit_loss.to_string(),
+                    calc.loss_offset_applied.to_string(),
+                    calc.exemption_applied.to_string(),
+                    calc.tax_due.to_string(),
+                    fingerprint,
+                ],
+            )?;
+        }
+    }
+    refresh_status(&tx, year)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn refresh_status(conn: &Connection, year: i32) -> Result<()> {
+    conn.execute(
+        "UPDATE tax_ledger SET darf_status = CASE
+             WHEN paid_on IS NOT NULL THEN 'PAID'
+             WHEN CAST(tax_due AS REAL) > 0 THEN 'PENDING'
+             ELSE 'NOT_DUE' END
+         WHERE year = ?1",
+        [year],
+    )?;
+    Ok(())
+}
+
+/// Rows of `year` (every year when None), by month and category
+pub fn entries(conn: &Connection, year: Option<i32>) -> Result<Vec<LedgerEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT year, month, tax_category, sales, profit_loss, loss_offset, exemption,
+                tax_due, darf_status, paid_on, paid_amount, tx_fingerprint, computed_at
+         FROM tax_ledger
+         WHERE ?1 IS NULL OR year = ?1
+         ORDER BY year, month, tax_category",
+    )?;
+    let rows = stmt
+        .query_map([year], |row| {
+            Ok((
+                LedgerEntry {
+                    year: row.get(0)?,
+                    month: row.get(1)?,
+                    category: TaxCategory::StockSwingTrade,
+                    sales: get_decimal_value(row, 3)?,
+                    profit_loss: get_decimal_value(row, 4)?,
+                    loss_offset: get_decimal_value(row, 5)?,
+                    exemption: get_decimal_value(row, 6)?,
+                    tax_due: get_decimal_value(row, 7)?,
+                    darf_status: DarfStatus::NotDue,
+                    paid_on: row.get(9)?,
+                    paid_amount: get_optional_decimal_value(row, 10)?,
+                    tx_fingerprint: row.get(11)?,
+                    computed_at: row.get(12)?,
+                },
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(8)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    rows.into_iter()
+        .map(|(mut entry, category, status)| {
+            entry.category = category
+                .parse()
+                .map_err(|_| anyhow::anyhow!("Unknown tax category in ledger: {}", category))?;
+            entry.darf_status = status.parse()?;
+            Ok(entry)
+        })
+        .collect()
+}
+
+/// Rows of `year` when all of them were computed from `fingerprint`
+pub fn load_year(
+    conn: &Connection,
+    year: i32,
+    fingerprint: &str,
+) -> Result<Option<Vec<LedgerEntry>>> {
+    let rows = entries(conn, Some(year))?;
+    if rows.is_empty() || rows.iter().any(|r| r.tx_fingerprint != fingerprint) {
+        return Ok(None);
+    }
+    Ok(Some(rows))
+}
+
+/// Record the DARF of a month as paid on `date`; `amount` defaults to the
+/// month's tax. Returns the amount recorded.
+pub fn mark_paid(
+    conn: &Connection,
+    year: i32,
+    month: u32,
+    date: NaiveDate,
+    amount: Option<Decimal>,
+) -> Result<Decimal> {
+    let rows: Vec<LedgerEntry> = entries(conn, Some(year))?
+        .into_iter()
+        .filter(|r| r.month == month)
+        .collect();
+    if rows.is_empty() {
+        anyhow::bail!("No tax computed for {:02}/{}", month, year);
+    }
+    let amount = amount.unwrap_or_else(|| rows.iter().map(|r| r.tax_due).sum());
+    conn.execute(
+        "UPDATE tax_ledger SET paid_on = ?3, paid_amount = ?4 WHERE year = ?1 AND month = ?2",
+        params![year, month, date, amount.to_string()],
+    )?;
+    refresh_status(conn, year)?;
+    Ok(amount)
+}
+
+/// Forget the payment recorded for a month
+pub fn clear_paid(conn: &Connection, year: i32, month: u32) -> Result<()> {
+    conn.execute(
+        "UPDATE tax_ledger SET paid_on = NULL, paid_amount = NULL WHERE year = ?1 AND month = ?2",
+        params![year, month],
+    )?;
+    // Rows kept only for their payment go with it
+    conn.execute(
+        "DELETE FROM tax_ledger WHERE year = ?1 AND month = ?2
+         AND CAST(sales AS REAL) = 0 AND CAST(profit_loss AS REAL) = 0
+         AND CAST(tax_due AS REAL) = 0",
+        params![year, month],
+    )?;
+    refresh_status(conn, year)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{self, AssetType, Transaction, TransactionType};
+    use crate::tax::irpf::generate_annual_report;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_ledger_serves_reports_and_keeps_payments() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id = db::insert_asset(&conn, "PETR4", &AssetType::Stock, None).unwrap();
+        let d = |m, day| NaiveDate::from_ymd_opt(2025, m, day).unwrap();
+        let trade = |date, tx_type, quantity, price: Decimal| Transaction {
+            id: None,
+            asset_id,
+            transaction_type: tx_type,
+            trade_date: date,
+            settlement_date: None,
+            quantity,
+            price_per_unit: price,
+            total_cost: quantity * price,
+            fees: Decimal::ZERO,
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: None,
+            source: "TEST".to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        db::insert_transaction(
+            &conn,
+            &trade(d(1, 10), TransactionType::Buy, dec!(2000), dec!(20)),
+        )
+        .unwrap();
+        db::insert_transaction(
+            &conn,
+            &trade(d(3, 10), TransactionType::Sell, dec!(1000), dec!(30)),
+        )
+        .unwrap();
+
+        let report = generate_annual_report(&conn, 2025).unwrap();
+        assert_eq!(report.annual_total_tax, dec!(1500));
+        let rows = entries(&conn, Some(2025)).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].darf_status, DarfStatus::Pending);
+
+        // A cache hit is rebuilt from the ledger rows
+        conn.execute("UPDATE tax_ledger SET tax_due = '1499'", [])
+            .unwrap();
+        let cached = generate_annual_report(&conn, 2025).unwrap();
+        assert_eq!(cached.annual_total_tax, dec!(1499));
+        assert_eq!(cached.monthly_summaries[0].month, 3);
+
+        mark_paid(&conn, 2025, 3, d(4, 30), None).unwrap();
+        // A new trade changes the fingerprint: the month is recomputed, the payment stays
+        db::insert_transaction(
+            &conn,
+            &trade(d(6, 10), TransactionType::Sell, dec!(1000), dec!(25)),
+        )
+        .unwrap();
+        generate_annual_report(&conn, 2025).unwrap();
+        let rows = entries(&conn, Some(2025)).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].tax_due, dec!(1500));
+        assert_eq!(rows[0].darf_status, DarfStatus::Paid);
+        assert_eq!(rows[0].paid_amount, Some(dec!(1499)));
+        assert_eq!(rows[1].darf_status, DarfStatus::Pending);
+    }
+}
@@ -182,7 +182,8 @@ pub fn get_remaining_losses_before_year(
     Ok(losses)
 }
 
-/// Compute a lightweight fingerprint of tax-relevant transactions for a year.
+/// Compute a lightweight fingerprint of tax-relevant transactions and
+/// exchanges for a year.
 pub fn compute_year_fingerprint(conn: &Connection, year: i32) -> Result<String> {
     let mut stmt = conn.prepare(
         "SELECT COUNT(*) as cnt,
@@ -202,7 +203,25 @@ pub fn compute_year_fingerprint(conn: &Connection, year: i32) -> Result<String>
         ))
     })?;
 
-    Ok(format!("{}:{}:{}:{}", row.0, row.1, row.2, row.3))
+    // Spin-offs and mergers change the year's sales and carried basis too
+    let exchanges = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(allocated_cost), 0), COALESCE(SUM(cash_amount), 0)
+         FROM asset_exchanges
+         WHERE strftime('%Y', effective_date) = ?1",
+        [year.to_string()],
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                get_decimal_value(row, 1)?,
+                get_decimal_value(row, 2)?,
+            ))
+        },
+    )?;
+
+    Ok(format!(
+        "{}:{}:{}:{}:{}:{}:{}",
+        row.0, row.1, row.2, row.3, exchanges.0, exchanges.1, exchanges.2
+    ))
 }
 
 pub fn load_snapshots(conn: &Connection) -> Result<HashMap<i32, LossSnapshot>> {
@@ -245,6 +264,19 @@ pub fn load_snapshots(conn: &Connection) -> Result<HashMap<i32, LossSnapshot>> {
     Ok(by_year)
 }
 
+/// When the carryforward snapshot for a year was last computed
+pub fn snapshot_computed_at(
+    conn: &Connection,
+    year: i32,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+    let computed: Option<chrono::DateTime<chrono::Utc>> = conn.query_row(
+        "SELECT MAX(computed_at) FROM loss_carryforward_snapshots WHERE year = ?1",
+        [year],
+        |row| row.get(0),
+    )?;
+    Ok(computed)
+}
+
 pub fn upsert_snapshot(
     conn: &Connection,
     year: i32,
@@ -285,8 +317,8 @@ pub fn earliest_transaction_year(conn: &Connection) -> Result<Option<i32>> {
     let mut stmt =
         conn.prepare("SELECT MIN(CAST(strftime('%Y', trade_date) AS INTEGER)) FROM transactions")?;
 
-    let year: Option<i32> = stmt.query_row([], |row| row.get(0)).optional()?;
-    Ok(year)
+    let year: Option<Option<i32>> = stmt.query_row([], |row| row.get(0)).optional()?;
+    Ok(year.flatten())
 }
 
 /// Clear all loss_carryforward entries for a given year.
@@ -1,10 +1,24 @@
 // Tax module - Brazilian tax calculations (average cost, swing trade, IRPF)
 
+pub mod bens_direitos;
 pub mod cost_basis;
 pub mod darf;
+pub mod declarants;
+pub mod fixed_income;
+pub mod foreign_dividends;
+pub mod foreign_gains;
+pub mod fund_income;
+pub mod gcap;
+pub mod income_scenario;
 pub mod irpf;
+pub mod ledger;
 pub mod loss_carryforward;
+pub mod rules;
+pub mod sales_monitor;
+pub mod simulate;
 pub mod swing_trade;
+pub mod tesouro;
+pub mod withholding;
 
 #[allow(unused_imports)]
 pub use darf::{format_monthly_darf_summary, generate_darf_payments, DarfPayment};
@@ -0,0 +1,767 @@
+//! Tax law in force by month.
+//!
+//! Rates, the monthly sales exemption and the IRRF withheld on sales changed
+//! over the years, so each rule carries the first month (of apuração) it
+//! applies to and stays in force until the next rule for the same category.
+//! A month before the first rule of a category uses that first rule.
+//!
+//! A change in the law is a new row here: calculations for earlier months
+//! keep using the rule that was in force then.
+
+use rust_decimal::Decimal;
+
+use super::swing_trade::TaxCategory;
+use crate::db::{AssetType, IncomeEventType};
+
+/// How gains of one tax category are taxed from `since` on
+#[derive(Debug)]
+pub struct CategoryRule {
+    pub category: TaxCategory,
+    /// First (year, month) the rule applies to
+    pub since: (i32, u32),
+    /// Rate on gains above the last of `gain_brackets`, or on all of them
+    pub rate: Decimal,
+    /// (gains up to, rate): progressive rates on each part of the month's
+    /// gains, as in capital gains (GCAP); empty: `rate` on all of them
+    pub gain_brackets: &'static [(Decimal, Decimal)],
+    /// Monthly sales up to this amount are exempt (zero: no exemption)
+    pub monthly_exemption: Decimal,
+    /// Asset types whose sales count towards the exemption and benefit from it
+    pub exempt_asset_types: &'static [AssetType],
+    /// Settled in the annual declaration: the year's gains and losses are
+    /// netted and nothing is due monthly
+    pub annual: bool,
+    pub legal_basis: &'static str,
+}
+
+impl CategoryRule {
+    /// Tax on the month's `taxable` gains
+    pub fn tax(&self, taxable: Decimal) -> Decimal {
+        let mut tax = Decimal::ZERO;
+        let mut taxed = Decimal::ZERO;
+        for (up_to, rate) in self.gain_brackets {
+            if taxable <= taxed {
+                break;
+            }
+            tax += (taxable.min(*up_to) - taxed) * rate;
+            taxed = *up_to;
+        }
+        if taxable > taxed {
+            tax += (taxable - taxed) * self.rate;
+        }
+        tax
+    }
+}
+
+/// IRRF withheld at source from `since` on
+#[derive(Debug)]
+pub struct WithholdingRule {
+    pub since: (i32, u32),
+    /// Share of swing-trade sale value ("dedo-duro")
+    pub swing_sale_rate: Decimal,
+    /// Share of day-trade gains
+    pub day_trade_rate: Decimal,
+    /// Brokers skip the retention when the month's amount is this or less
+    pub min_retention: Decimal,
+    /// Withholding on dividends is an advance of the annual tax instead of an error
+    pub dividends_compensable: bool,
+    pub legal_basis: &'static str,
+}
+
+/// Regressive IR and IOF on fixed income (Tesouro Direto) from `since` on
+#[derive(Debug)]
+pub struct FixedIncomeRule {
+    pub since: (i32, u32),
+    /// (days held up to, rate); holdings past the last bracket pay `long_term_rate`
+    pub ir_brackets: &'static [(i64, Decimal)],
+    pub long_term_rate: Decimal,
+    /// IOF share of the yield for redemptions on day 1, 2, ... 29 after purchase
+    pub iof_by_day: &'static [Decimal],
+    pub legal_basis: &'static str,
+}
+
+impl FixedIncomeRule {
+    pub fn ir_rate(&self, days_held: i64) -> Decimal {
+        self.ir_brackets
+            .iter()
+            .find(|(up_to, _)| days_held <= *up_to)
+            .map(|(_, rate)| *rate)
+            .unwrap_or(self.long_term_rate)
+    }
+
+    pub fn iof_rate(&self, days_held: i64) -> Decimal {
+        let day = days_held.max(1) as usize;
+        self.iof_by_day
+            .get(day - 1)
+            .copied()
+            .unwrap_or(Decimal::ZERO)
+    }
+}
+
+/// Monthly progressive table for income taxed through carnê-leão from `since` on
+#[derive(Debug)]
+pub struct CarneLeaoRule {
+    pub since: (i32, u32),
+    /// (monthly income up to, rate, deduction); income above the last bracket
+    /// pays `top_rate` less `top_deduction`
+    pub brackets: &'static [(Decimal, Decimal, Decimal)],
+    pub top_rate: Decimal,
+    pub top_deduction: Decimal,
+    /// Reduction of the tax on low incomes, when the law grants one
+    pub reduction: Option<TaxReduction>,
+    pub legal_basis: &'static str,
+}
+
+/// Tax on FII and Fiagro distributions to individuals from `since` on
+#[derive(Debug)]
+pub struct FundIncomeRule {
+    pub since: (i32, u32),
+    /// Rate withheld on distributions of taxed quotas
+    pub rate: Decimal,
+    /// Quotas acquired from this month on are taxed; earlier ones keep the
+    /// exemption. None: every quota is exempt
+    pub taxed_from: Option<(i32, u32)>,
+    pub legal_basis: &'static str,
+}
+
+impl FundIncomeRule {
+    /// Whether distributions of a quota acquired in (year, month) are taxed
+    pub fn taxes_quota(&self, acquired: (i32, u32)) -> bool {
+        self.taxed_from.is_some_and(|from| acquired >= from)
+    }
+}
+
+/// Withholding on the dividends one company pays an individual in a month,
+/// from `since` on
+#[derive(Debug, Clone)]
+pub struct PayerDividendRule {
+    pub since: (i32, u32),
+    pub rate: Decimal,
+    /// A company's dividends in the month up to this are not withheld; above
+    /// it the whole month's total is
+    pub monthly_threshold: Decimal,
+    pub legal_basis: &'static str,
+}
+
+impl PayerDividendRule {
+    /// Tax withheld on a company's dividends totalling `month_total` in a month
+    pub fn tax(&self, month_total: Decimal) -> Decimal {
+        if month_total > self.monthly_threshold {
+            (month_total * self.rate).round_dp(2)
+        } else {
+            Decimal::ZERO
+        }
+    }
+}
+
+/// Tax withheld by default on one kind of income from `since` on, for
+/// statements and manual entries that do not say how much was withheld
+#[derive(Debug)]
+pub struct IncomeWithholdingRule {
+    pub event_type: IncomeEventType,
+    /// Asset types the rule is for; empty: every type without a rule of its own
+    pub asset_types: &'static [AssetType],
+    pub since: (i32, u32),
+    /// IRRF withheld in Brazil, share of the gross amount
+    pub rate: Decimal,
+    /// Withheld abroad before the amount reaches Brazil, share of the gross
+    pub foreign_rate: Decimal,
+    pub legal_basis: &'static str,
+}
+
+impl IncomeWithholdingRule {
+    /// IRRF on a gross amount
+    pub fn withheld_from(&self, gross: Decimal) -> Decimal {
+        (gross * self.rate).round_dp(2)
+    }
+
+    /// Gross amount and IRRF of an amount credited net of the IRRF
+    pub fn gross_up(&self, net: Decimal) -> (Decimal, Decimal) {
+        if self.rate.is_zero() {
+            return (net, Decimal::ZERO);
+        }
+        let gross = (net / (Decimal::ONE - self.rate)).round_dp(2);
+        (gross, gross - net)
+    }
+
+    /// Tax withheld abroad on an amount received net of it
+    pub fn foreign_tax_on(&self, received: Decimal) -> Decimal {
+        (received * self.foreign_rate / (Decimal::ONE - self.foreign_rate)).round_dp(2)
+    }
+}
+
+/// Reduction zeroing the tax up to `exempt_up_to` a month, then worth
+/// `base` - `slope` × income until `phase_out_until`
+#[derive(Debug)]
+pub struct TaxReduction {
+    pub exempt_up_to: Decimal,
+    pub phase_out_until: Decimal,
+    pub base: Decimal,
+    pub slope: Decimal,
+}
+
+impl CarneLeaoRule {
+    /// Tax due on `income` received in one month
+    pub fn tax(&self, income: Decimal) -> Decimal {
+        let (rate, deduction) = self
+            .brackets
+            .iter()
+            .find(|(up_to, _, _)| income <= *up_to)
+            .map(|(_, rate, deduction)| (*rate, *deduction))
+            .unwrap_or((self.top_rate, self.top_deduction));
+        let tax = (income * rate - deduction).max(Decimal::ZERO);
+        let reduction = match &self.reduction {
+            Some(r) if income <= r.exempt_up_to => tax,
+            Some(r) if income <= r.phase_out_until => {
+                (r.base - r.slope * income).max(Decimal::ZERO)
+            }
+            _ => Decimal::ZERO,
+        };
+        (tax - reduction.min(tax)).round_dp(2)
+    }
+
+    /// Monthly income taxed at zero
+    pub fn exempt_up_to(&self) -> Decimal {
+        let table = self
+            .brackets
+            .first()
+            .map(|(up_to, _, _)| *up_to)
+            .unwrap_or(Decimal::ZERO);
+        match &self.reduction {
+            Some(r) => r.exempt_up_to.max(table),
+            None => table,
+        }
+    }
+}
+
+/// `units` × 10^-`scale`
+const fn decimal(units: u32, scale: u32) -> Decimal {
+    Decimal::from_parts(units, 0, 0, false, scale)
+}
+
+static CATEGORY_RULES: &[CategoryRule] = &[
+    CategoryRule {
+        category: TaxCategory::StockSwingTrade,
+        since: (2005, 1),
+        rate: decimal(15, 2),
+        gain_brackets: &[],
+        monthly_exemption: decimal(20000, 0),
+        exempt_asset_types: &[AssetType::Stock],
+        annual: false,
+        legal_basis: "Lei 11.033/2004, arts. 2º e 3º",
+    },
+    CategoryRule {
+        category: TaxCategory::StockDayTrade,
+        since: (2005, 1),
+        rate: decimal(20, 2),
+        gain_brackets: &[],
+        monthly_exemption: Decimal::ZERO,
+        exempt_asset_types: &[],
+        annual: false,
+        legal_basis: "Lei 8.981/1995; IN RFB 1.585/2015",
+    },
+    CategoryRule {
+        category: TaxCategory::FiiSwingTrade,
+        since: (2005, 1),
+        rate: decimal(20, 2),
+        gain_brackets: &[],
+        monthly_exemption: Decimal::ZERO,
+        exempt_asset_types: &[],
+        annual: false,
+        legal_basis: "Lei 8.668/1993, art. 18",
+    },
+    CategoryRule {
+        category: TaxCategory::FiiDayTrade,
+        since: (2005, 1),
+        rate: decimal(20, 2),
+        gain_brackets: &[],
+        monthly_exemption: Decimal::ZERO,
+        exempt_asset_types: &[],
+        annual: false,
+        legal_basis: "Lei 8.668/1993, art. 18",
+    },
+    CategoryRule {
+        category: TaxCategory::FiagroSwingTrade,
+        since: (2021, 3),
+        rate: decimal(20, 2),
+        gain_brackets: &[],
+        monthly_exemption: Decimal::ZERO,
+        exempt_asset_types: &[],
+        annual: false,
+        legal_basis: "Lei 14.130/2021",
+    },
+    CategoryRule {
+        category: TaxCategory::FiagroDayTrade,
+        since: (2021, 3),
+        rate: decimal(20, 2),
+        gain_brackets: &[],
+        monthly_exemption: Decimal::ZERO,
+        exempt_asset_types: &[],
+        annual: false,
+        legal_basis: "Lei 14.130/2021",
+    },
+    CategoryRule {
+        category: TaxCategory::FiInfra,
+        since: (2011, 6),
+        rate: Decimal::ZERO,
+        gain_brackets: &[],
+        monthly_exemption: Decimal::ZERO,
+        exempt_asset_types: &[],
+        annual: false,
+        legal_basis: "Lei 12.431/2011, art. 3º",
+    },
+    CategoryRule {
+        category: TaxCategory::Foreign,
+        since: (2005, 1),
+        rate: decimal(15, 2),
+        gain_brackets: &[],
+        monthly_exemption: decimal(35000, 0),
+        exempt_asset_types: &[AssetType::Stock, AssetType::Etf, AssetType::Unknown],
+        annual: false,
+        legal_basis: "Lei 8.981/1995, art. 21; IN SRF 118/2000, art. 1º (GCAP)",
+    },
+    CategoryRule {
+        category: TaxCategory::Foreign,
+        since: (2024, 1),
+        rate: decimal(15, 2),
+        gain_brackets: &[],
+        monthly_exemption: Decimal::ZERO,
+        exempt_asset_types: &[],
+        annual: true,
+        legal_basis: "Lei 14.754/2023, arts. 2º a 4º",
+    },
+    CategoryRule {
+        category: TaxCategory::Crypto,
+        since: (2017, 1),
+        rate: decimal(225, 3),
+        gain_brackets: &[
+            (decimal(5_000_000, 0), decimal(15, 2)),
+            (decimal(10_000_000, 0), decimal(175, 3)),
+            (decimal(30_000_000, 0), decimal(20, 2)),
+        ],
+        monthly_exemption: decimal(35000, 0),
+        exempt_asset_types: &[AssetType::Crypto],
+        annual: false,
+        legal_basis:
+            "Lei 8.981/1995, art. 21 (Lei 13.259/2016); Lei 9.250/1995, art. 22; IN RFB 1.888/2019",
+    },
+];
+
+static WITHHOLDING_RULES: &[WithholdingRule] = &[
+    WithholdingRule {
+        since: (2005, 1),
+        swing_sale_rate: decimal(5, 5),
+        day_trade_rate: decimal(1, 2),
+        min_retention: Decimal::ONE,
+        dividends_compensable: false,
+        legal_basis: "Lei 11.033/2004, art. 2º",
+    },
+    WithholdingRule {
+        since: (2026, 1),
+        swing_sale_rate: decimal(5, 5),
+        day_trade_rate: decimal(1, 2),
+        min_retention: Decimal::ONE,
+        dividends_compensable: true,
+        legal_basis: "Lei 11.033/2004, art. 2º; Lei 15.270/2025",
+    },
+];
+
+static FIXED_INCOME_RULES: &[FixedIncomeRule] = &[FixedIncomeRule {
+    since: (2005, 1),
+    ir_brackets: &[
+        (180, decimal(225, 3)),
+        (360, decimal(20, 2)),
+        (720, decimal(175, 3)),
+    ],
+    long_term_rate: decimal(15, 2),
+    iof_by_day: &[
+        decimal(96, 2),
+        decimal(93, 2),
+        decimal(90, 2),
+        decimal(86, 2),
+        decimal(83, 2),
+        decimal(80, 2),
+        decimal(76, 2),
+        decimal(73, 2),
+        decimal(70, 2),
+        decimal(66, 2),
+        decimal(63, 2),
+        decimal(60, 2),
+        decimal(56, 2),
+        decimal(53, 2),
+        decimal(50, 2),
+        decimal(46, 2),
+        decimal(43, 2),
+        decimal(40, 2),
+        decimal(36, 2),
+        decimal(33, 2),
+        decimal(30, 2),
+        decimal(26, 2),
+        decimal(23, 2),
+        decimal(20, 2),
+        decimal(16, 2),
+        decimal(13, 2),
+        decimal(10, 2),
+        decimal(6, 2),
+        decimal(3, 2),
+    ],
+    legal_basis: "Lei 11.033/2004, art. 1º; Decreto 6.306/2007",
+}];
+
+static CARNE_LEAO_RULES: &[CarneLeaoRule] = &[
+    CarneLeaoRule {
+        since: (2015, 4),
+        brackets: &[
+            (decimal(190398, 2), Decimal::ZERO, Decimal::ZERO),
+            (decimal(282665, 2), decimal(75, 3), decimal(14280, 2)),
+            (decimal(375105, 2), decimal(15, 2), decimal(35480, 2)),
+            (decimal(466468, 2), decimal(225, 3), decimal(63613, 2)),
+        ],
+        top_rate: decimal(275, 3),
+        top_deduction: decimal(86936, 2),
+        reduction: None,
+        legal_basis: "Lei 13.149/2015",
+    },
+    CarneLeaoRule {
+        since: (2023, 5),
+        brackets: &[
+            (decimal(211200, 2), Decimal::ZERO, Decimal::ZERO),
+            (decimal(282665, 2), decimal(75, 3), decimal(15840, 2)),
+            (decimal(375105, 2), decimal(15, 2), decimal(37040, 2)),
+            (decimal(466468, 2), decimal(225, 3), decimal(65173, 2)),
+        ],
+        top_rate: decimal(275, 3),
+        top_deduction: decimal(88496, 2),
+        reduction: None,
+        legal_basis: "Lei 14.663/2023",
+    },
+    CarneLeaoRule {
+        since: (2024, 2),
+        brackets: &[
+            (decimal(225920, 2), Decimal::ZERO, Decimal::ZERO),
+            (decimal(282665, 2), decimal(75, 3), decimal(16944, 2)),
+            (decimal(375105, 2), decimal(15, 2), decimal(38144, 2)),
+            (decimal(466468, 2), decimal(225, 3), decimal(66277, 2)),
+        ],
+        top_rate: decimal(275, 3),
+        top_deduction: decimal(89600, 2),
+        reduction: None,
+        legal_basis: "Lei 14.848/2024",
+    },
+    CarneLeaoRule {
+        since: (2025, 5),
+        brackets: &[
+            (decimal(242880, 2), Decimal::ZERO, Decimal::ZERO),
+            (decimal(282665, 2), decimal(75, 3), decimal(18216, 2)),
+            (decimal(375105, 2), decimal(15, 2), decimal(39416, 2)),
+            (decimal(466468, 2), decimal(225, 3), decimal(67549, 2)),
+        ],
+        top_rate: decimal(275, 3),
+        top_deduction: decimal(90873, 2),
+        reduction: None,
+        legal_basis: "Lei 15.191/2025",
+    },
+    CarneLeaoRule {
+        since: (2026, 1),
+        brackets: &[
+            (decimal(242880, 2), Decimal::ZERO, Decimal::ZERO),
+            (decimal(282665, 2), decimal(75, 3), decimal(18216, 2)),
+            (decimal(375105, 2), decimal(15, 2), decimal(39416, 2)),
+            (decimal(466468, 2), decimal(225, 3), decimal(67549, 2)),
+        ],
+        top_rate: decimal(275, 3),
+        top_deduction: decimal(90873, 2),
+        reduction: Some(TaxReduction {
+            exempt_up_to: decimal(5000, 0),
+            phase_out_until: decimal(7350, 0),
+            base: decimal(97862, 2),
+            slope: decimal(133145, 6),
+        }),
+        legal_basis: "Lei 15.191/2025; Lei 15.270/2025",
+    },
+];
+
+static FUND_INCOME_RULES: &[FundIncomeRule] = &[
+    FundIncomeRule {
+        since: (2005, 1),
+        rate: Decimal::ZERO,
+        taxed_from: None,
+        legal_basis: "Lei 11.033/2004, art. 3º, III; Lei 14.130/2021",
+    },
+    FundIncomeRule {
+        since: (2026, 1),
+        rate: decimal(5, 2),
+        taxed_from: Some((2026, 1)),
+        legal_basis: "MP 1.303/2025, art. 17",
+    },
+];
+
+static PAYER_DIVIDEND_RULES: &[PayerDividendRule] = &[
+    PayerDividendRule {
+        since: (2005, 1),
+        rate: Decimal::ZERO,
+        monthly_threshold: Decimal::ZERO,
+        legal_basis: "Lei 9.249/1995, art. 10",
+    },
+    PayerDividendRule {
+        since: (2026, 1),
+        rate: decimal(10, 2),
+        monthly_threshold: decimal(50000, 0),
+        legal_basis: "Lei 15.270/2025",
+    },
+];
+
+// The 10% on dividends from 2026 depends on the payer's month total (see
+// PAYER_DIVIDEND_RULES), so the per-event default stays at zero.
+static INCOME_WITHHOLDING_RULES: &[IncomeWithholdingRule] = &[
+    IncomeWithholdingRule {
+        event_type: IncomeEventType::Dividend,
+        asset_types: &[],
+        since: (2005, 1),
+        rate: Decimal::ZERO,
+        foreign_rate: Decimal::ZERO,
+        legal_basis: "Lei 9.249/1995, art. 10",
+    },
+    IncomeWithholdingRule {
+        event_type: IncomeEventType::Dividend,
+        asset_types: &[AssetType::Fii, AssetType::Fiagro],
+        since: (2005, 1),
+        rate: Decimal::ZERO,
+        foreign_rate: Decimal::ZERO,
+        legal_basis: "Lei 11.033/2004, art. 3º, III; Lei 14.130/2021",
+    },
+    IncomeWithholdingRule {
+        event_type: IncomeEventType::Dividend,
+        asset_types: &[AssetType::Bdr],
+        since: (2005, 1),
+        rate: Decimal::ZERO,
+        foreign_rate: decimal(30, 2),
+        legal_basis: "US IRC § 1441 (no treaty); carnê-leão in Brazil",
+    },
+    IncomeWithholdingRule {
+        event_type: IncomeEventType::Jcp,
+        asset_types: &[],
+        since: (2005, 1),
+        rate: decimal(15, 2),
+        foreign_rate: Decimal::ZERO,
+        legal_basis: "Lei 9.249/1995, art. 9º, § 2º",
+    },
+    IncomeWithholdingRule {
+        event_type: IncomeEventType::Interest,
+        asset_types: &[],
+        since: (2011, 6),
+        rate: Decimal::ZERO,
+        foreign_rate: Decimal::ZERO,
+        legal_basis: "Lei 12.431/2011, art. 2º (debêntures incentivadas)",
+    },
+    IncomeWithholdingRule {
+        event_type: IncomeEventType::Amortization,
+        asset_types: &[],
+        since: (2005, 1),
+        rate: Decimal::ZERO,
+        foreign_rate: Decimal::ZERO,
+        legal_basis: "Return of capital, deducted from the cost",
+    },
+];
+
+/// Rules of a list in force in (year, month): the latest one already started,
+/// or the first one for months before any of them
+fn in_force<'a, T>(
+    rules: impl Iterator<Item = &'a T>,
+    since: impl Fn(&T) -> (i32, u32),
+    year: i32,
+    month: u32,
+) -> &'a T {
+    let mut first = None;
+    let mut current = None;
+    for rule in rules {
+        first.get_or_insert(rule);
+        if since(rule) <= (year, month) {
+            current = Some(rule);
+        }
+    }
+    current
+        .or(first)
+        .expect("every tax category has at least one rule")
+}
+
+/// Rule for `category` in force in (year, month)
+pub fn category_rule(category: &TaxCategory, year: i32, month: u32) -> &'static CategoryRule {
+    in_force(
+        CATEGORY_RULES.iter().filter(|r| r.category == *category),
+        |r| r.since,
+        year,
+        month,
+    )
+}
+
+/// Sales IRRF rule in force in (year, month)
+pub fn withholding_rule(year: i32, month: u32) -> &'static WithholdingRule {
+    in_force(WITHHOLDING_RULES.iter(), |r| r.since, year, month)
+}
+
+/// Fixed income rule in force in (year, month)
+pub fn fixed_income_rule(year: i32, month: u32) -> &'static FixedIncomeRule {
+    in_force(FIXED_INCOME_RULES.iter(), |r| r.since, year, month)
+}
+
+/// Carnê-leão table in force in (year, month)
+pub fn carne_leao_rule(year: i32, month: u32) -> &'static CarneLeaoRule {
+    in_force(CARNE_LEAO_RULES.iter(), |r| r.since, year, month)
+}
+
+/// FII and Fiagro distribution rule in force in (year, month)
+pub fn fund_income_rule(year: i32, month: u32) -> &'static FundIncomeRule {
+    in_force(FUND_INCOME_RULES.iter(), |r| r.since, year, month)
+}
+
+/// Dividend withholding by payer in force in (year, month)
+pub fn payer_dividend_rule(year: i32, month: u32) -> &'static PayerDividendRule {
+    in_force(PAYER_DIVIDEND_RULES.iter(), |r| r.since, year, month)
+}
+
+/// Default withholding on `event_type` income of an `asset_type` asset in
+/// (year, month): the asset type's own rule, or the event type's general one
+pub fn income_withholding_rule(
+    event_type: &IncomeEventType,
+    asset_type: &AssetType,
+    year: i32,
+    month: u32,
+) -> &'static IncomeWithholdingRule {
+    let of_event = || {
+        INCOME_WITHHOLDING_RULES
+            .iter()
+            .filter(move |r| r.event_type == *event_type)
+    };
+    let specific = of_event().any(|r| r.asset_types.contains(asset_type));
+    in_force(
+        of_event().filter(|r| {
+            if specific {
+                r.asset_types.contains(asset_type)
+            } else {
+                r.asset_types.is_empty()
+            }
+        }),
+        |r| r.since,
+        year,
+        month,
+    )
+}
+
+/// Every FII and Fiagro distribution rule, by start
+pub fn fund_income_rules() -> &'static [FundIncomeRule] {
+    FUND_INCOME_RULES
+}
+
+/// Every carnê-leão table, by start
+pub fn carne_leao_rules() -> &'static [CarneLeaoRule] {
+    CARNE_LEAO_RULES
+}
+
+/// Every category rule, by category then start
+pub fn category_rules() -> &'static [CategoryRule] {
+    CATEGORY_RULES
+}
+
+/// Every fixed income rule, by start
+pub fn fixed_income_rules() -> &'static [FixedIncomeRule] {
+    FIXED_INCOME_RULES
+}
+
+/// Every withholding rule, by start
+pub fn withholding_rules() -> &'static [WithholdingRule] {
+    WITHHOLDING_RULES
+}
+
+/// Every dividend withholding rule by payer, by start
+pub fn payer_dividend_rules() -> &'static [PayerDividendRule] {
+    PAYER_DIVIDEND_RULES
+}
+
+/// Every default income withholding rule, by event type
+pub fn income_withholding_rules() -> &'static [IncomeWithholdingRule] {
+    INCOME_WITHHOLDING_RULES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_rules_follow_the_month_in_force() {
+        let stock = category_rule(&TaxCategory::StockSwingTrade, 2024, 6);
+        assert_eq!(stock.rate, dec!(0.15));
+        assert_eq!(stock.monthly_exemption, dec!(20000));
+        assert_eq!(stock.exempt_asset_types, &[AssetType::Stock]);
+
+        // Before the first rule of a category its first rule applies
+        let fiagro = category_rule(&TaxCategory::FiagroSwingTrade, 2019, 1);
+        assert_eq!(fiagro.since, (2021, 3));
+
+        assert_eq!(withholding_rule(2025, 12).swing_sale_rate, dec!(0.00005));
+        assert!(!withholding_rule(2025, 12).dividends_compensable);
+        assert!(withholding_rule(2026, 1).dividends_compensable);
+
+        let fixed = fixed_income_rule(2025, 6);
+        assert_eq!(fixed.ir_rate(180), dec!(0.225));
+        assert_eq!(fixed.ir_rate(181), dec!(0.20));
+        assert_eq!(fixed.ir_rate(721), dec!(0.15));
+        assert_eq!(fixed.iof_rate(1), dec!(0.96));
+        assert_eq!(fixed.iof_rate(29), dec!(0.03));
+        assert_eq!(fixed.iof_rate(30), Decimal::ZERO);
+
+        assert_eq!(carne_leao_rule(2025, 4).tax(dec!(2300)), dec!(3.06));
+        assert_eq!(carne_leao_rule(2025, 6).tax(dec!(2300)), Decimal::ZERO);
+        assert_eq!(carne_leao_rule(2025, 6).tax(dec!(10000)), dec!(1841.27));
+        // From 2026 the reduction zeroes the tax up to R$ 5.000,00 a month
+        assert_eq!(carne_leao_rule(2026, 1).tax(dec!(5000)), Decimal::ZERO);
+        assert_eq!(carne_leao_rule(2026, 1).tax(dec!(6000)), dec!(561.52));
+        assert_eq!(carne_leao_rule(2026, 1).exempt_up_to(), dec!(5000));
+
+        // Quotas bought before 2026 keep the exemption on later distributions
+        assert!(!fund_income_rule(2025, 12).taxes_quota((2025, 12)));
+        assert!(!fund_income_rule(2026, 3).taxes_quota((2025, 12)));
+        assert!(fund_income_rule(2026, 3).taxes_quota((2026, 1)));
+        assert_eq!(fund_income_rule(2026, 3).rate, dec!(0.05));
+
+        // From 2026 a company's dividends over R$ 50.000,00 a month are
+        // withheld in full, not just the excess
+        assert_eq!(
+            payer_dividend_rule(2025, 12).tax(dec!(80000)),
+            Decimal::ZERO
+        );
+        assert_eq!(payer_dividend_rule(2026, 1).tax(dec!(50000)), Decimal::ZERO);
+        assert_eq!(payer_dividend_rule(2026, 1).tax(dec!(50000.01)), dec!(5000));
+
+        // Income withholding falls back to the event type's general rule
+        let jcp = income_withholding_rule(&IncomeEventType::Jcp, &AssetType::Unknown, 2024, 6);
+        assert_eq!(jcp.withheld_from(dec!(1000)), dec!(150));
+        assert_eq!(jcp.gross_up(dec!(85)), (dec!(100), dec!(15)));
+        let fii = income_withholding_rule(&IncomeEventType::Dividend, &AssetType::Fii, 2024, 6);
+        assert_eq!(fii.rate, Decimal::ZERO);
+        let bdr = income_withholding_rule(&IncomeEventType::Dividend, &AssetType::Bdr, 2024, 6);
+        assert_eq!(bdr.foreign_tax_on(dec!(70)), dec!(30));
+        let stock = income_withholding_rule(&IncomeEventType::Dividend, &AssetType::Stock, 2024, 6);
+        assert!(stock.asset_types.is_empty());
+
+        // Crypto gains are taxed progressively, each part at its bracket's rate
+        let crypto = category_rule(&TaxCategory::Crypto, 2024, 6);
+        assert_eq!(crypto.tax(dec!(10000)), dec!(1500));
+        assert_eq!(crypto.tax(dec!(6000000)), dec!(925000));
+        assert_eq!(
+            category_rule(&TaxCategory::StockDayTrade, 2024, 6).tax(dec!(1000)),
+            dec!(200)
+        );
+
+        // Rules of a category are listed in start order
+        for category in CATEGORY_RULES.iter().map(|r| &r.category) {
+            let starts: Vec<_> = CATEGORY_RULES
+                .iter()
+                .filter(|r| r.category == *category)
+                .map(|r| r.since)
+                .collect();
+            assert!(starts.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+}
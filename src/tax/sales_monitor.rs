@@ -0,0 +1,442 @@
+//! Monthly stock sales monitor for the R$20k swing-trade exemption.
+//!
+//! The exemption applies to the sum of stock sales in a calendar month across
+//! all brokers, so sales are aggregated from every imported account.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::db;
+use crate::tax::swing_trade::TaxCategory;
+
+/// Share of the threshold at which a month is flagged as approaching it
+const APPROACHING_RATIO: Decimal = Decimal::from_parts(8, 0, 0, false, 1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExemptionStatus {
+    Ok,
+    Approaching,
+    Exceeded,
+}
+
+/// Stock swing-trade sales for one calendar month
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthlySales {
+    pub year: i32,
+    pub month: u32,
+    pub stock_sales: Decimal,
+    pub threshold: Decimal,
+    pub status: ExemptionStatus,
+}
+
+impl MonthlySales {
+    /// Remaining sales room before losing the exemption
+    pub fn remaining(&self) -> Decimal {
+        (self.threshold - self.stock_sales).max(Decimal::ZERO)
+    }
+}
+
+fn exemption_status(sales: Decimal, threshold: Decimal) -> ExemptionStatus {
+    if sales > threshold {
+        ExemptionStatus::Exceeded
+    } else if sales >= threshold * APPROACHING_RATIO {
+        ExemptionStatus::Approaching
+    } else {
+        ExemptionStatus::Ok
+    }
+}
+
+fn month_start(date: NaiveDate) -> NaiveDate {
+    NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap_or(date)
+}
+
+/// Stock swing-trade sales per month for the 12 months ending at `as_of`
+///
+/// Months without sales are included so callers can render a full window.
+pub fn rolling_monthly_sales(conn: &Connection, as_of: NaiveDate) -> Result<Vec<MonthlySales>> {
+    let end = month_start(as_of);
+    let start = end
+        .checked_sub_months(chrono::Months::new(11))
+        .unwrap_or(end);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.trade_date, t.total_cost
+         FROM transactions t
+         JOIN assets a ON a.id = t.asset_id
+         WHERE t.transaction_type = 'SELL'
+           AND a.asset_type = ?1
+           AND a.currency IS NULL
+           AND COALESCE(t.is_day_trade, 0) = 0
+           AND t.trade_date >= ?2 AND t.trade_date <= ?3{}",
+        db::portfolio::scope_filter("t.portfolio_id")
+    ))?;
+    let rows = stmt
+        .query_map(
+            rusqlite::params![db::AssetType::Stock.as_str(), start, as_of],
+            |row| {
+                let date: NaiveDate = row.get(0)?;
+                Ok((date, db::get_decimal_value(row, 1)?))
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut by_month: BTreeMap<NaiveDate, Decimal> = BTreeMap::new();
+    let mut month = start;
+    while month <= end {
+        by_month.insert(month, Decimal::ZERO);
+        month = match month.checked_add_months(chrono::Months::new(1)) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    for (date, total) in rows {
+        *by_month.entry(month_start(date)).or_insert(Decimal::ZERO) += total.abs();
+    }
+
+    Ok(by_month
+        .into_iter()
+        .map(|(month, stock_sales)| {
+            let threshold =
+                TaxCategory::StockSwingTrade.monthly_exemption_in(month.year(), month.month());
+            MonthlySales {
+                year: month.year(),
+                month: month.month(),
+                stock_sales,
+                threshold,
+                status: exemption_status(stock_sales, threshold),
+            }
+        })
+        .collect())
+}
+
+/// Categories in the order they are listed
+const CATEGORIES: [TaxCategory; 9] = [
+    TaxCategory::StockSwingTrade,
+    TaxCategory::StockDayTrade,
+    TaxCategory::FiiSwingTrade,
+    TaxCategory::FiiDayTrade,
+    TaxCategory::FiagroSwingTrade,
+    TaxCategory::FiagroDayTrade,
+    TaxCategory::FiInfra,
+    TaxCategory::Foreign,
+    TaxCategory::Crypto,
+];
+
+fn serialize_category<S: serde::Serializer>(
+    category: &TaxCategory,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(category.as_str())
+}
+
+/// One tax category's sales in a month against its exemption
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryUsage {
+    #[serde(serialize_with = "serialize_category")]
+    pub category: TaxCategory,
+    /// Every sale of the category in the month
+    pub sales: Decimal,
+    /// Sales of the asset types the exemption covers (stocks, for stocks)
+    pub exemption_sales: Decimal,
+    /// Monthly exemption limit, zero when the category has none
+    pub limit: Decimal,
+    /// None when the category has no exemption
+    pub status: Option<ExemptionStatus>,
+}
+
+impl CategoryUsage {
+    pub fn remaining(&self) -> Decimal {
+        (self.limit - self.exemption_sales).max(Decimal::ZERO)
+    }
+}
+
+/// Sales of one month per category, stock swing trades always included
+#[derive(Debug, Clone, Serialize)]
+pub struct MonthUsage {
+    pub year: i32,
+    pub month: u32,
+    pub categories: Vec<CategoryUsage>,
+}
+
+impl MonthUsage {
+    pub fn category(&self, category: &TaxCategory) -> Option<&CategoryUsage> {
+        self.categories.iter().find(|c| c.category == *category)
+    }
+}
+
+fn usage_of(
+    categories: &mut Vec<CategoryUsage>,
+    category: TaxCategory,
+    year: i32,
+    month: u32,
+) -> &mut CategoryUsage {
+    let i = match categories.iter().position(|c| c.category == category) {
+        Some(i) => i,
+        None => {
+            categories.push(CategoryUsage {
+                limit: category.monthly_exemption_in(year, month),
+                category,
+                sales: Decimal::ZERO,
+                exemption_sales: Decimal::ZERO,
+                status: None,
+            });
+            categories.len() - 1
+        }
+    };
+    &mut categories[i]
+}
+
+/// Sales of (year, month) per tax category, against each one's exemption
+pub fn month_usage(conn: &Connection, year: i32, month: u32) -> Result<MonthUsage> {
+    let start = NaiveDate::from_ymd_opt(year, month, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid month: {:02}/{}", month, year))?;
+    let end = start
+        .checked_add_months(chrono::Months::new(1))
+        .and_then(|d| d.pred_opt())
+        .unwrap_or(start);
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT a.asset_type, COALESCE(t.is_day_trade, 0), t.total_cost, a.currency IS NOT NULL
+         FROM transactions t
+         JOIN assets a ON a.id = t.asset_id
+         WHERE t.transaction_type = 'SELL'
+           AND t.trade_date >= ?1 AND t.trade_date <= ?2{}",
+        db::portfolio::scope_filter("t.portfolio_id")
+    ))?;
+    let rows = stmt
+        .query_map(rusqlite::params![start, end], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, bool>(1)?,
+                db::get_decimal_value(row, 2)?,
+                row.get::<_, bool>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut categories: Vec<CategoryUsage> = Vec::new();
+    usage_of(&mut categories, TaxCategory::StockSwingTrade, year, month);
+    for (asset_type, is_day_trade, total, abroad) in rows {
+        let asset_type = asset_type.parse().unwrap_or(db::AssetType::Unknown);
+        let category = TaxCategory::of_sale(&asset_type, is_day_trade, abroad);
+        let exempt_types = super::rules::category_rule(&category, year, month).exempt_asset_types;
+        let monitored = category == TaxCategory::StockSwingTrade;
+        let usage = usage_of(&mut categories, category, year, month);
+        usage.sales += total.abs();
+        // Stock swing-trade exemption sales come from the monthly monitor below
+        if !monitored && exempt_types.contains(&asset_type) {
+            usage.exemption_sales += total.abs();
+        }
+    }
+    for c in categories.iter_mut().filter(|c| !c.limit.is_zero()) {
+        c.status = Some(exemption_status(c.exemption_sales, c.limit));
+    }
+    if let Some(monitored) = rolling_monthly_sales(conn, end)?.pop() {
+        let stock = usage_of(&mut categories, TaxCategory::StockSwingTrade, year, month);
+        stock.exemption_sales = monitored.stock_sales;
+        stock.limit = monitored.threshold;
+        stock.status = Some(monitored.status);
+    }
+    categories.sort_by_key(|c| CATEGORIES.iter().position(|k| *k == c.category));
+
+    Ok(MonthUsage {
+        year,
+        month,
+        categories,
+    })
+}
+
+/// The month's exemption after one more sale
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedSale {
+    #[serde(serialize_with = "serialize_category")]
+    pub category: TaxCategory,
+    pub amount: Decimal,
+    /// Whether the sale counts towards the category's exemption
+    pub counts: bool,
+    pub exemption_sales_after: Decimal,
+    pub limit: Decimal,
+    pub status_after: Option<ExemptionStatus>,
+}
+
+impl PlannedSale {
+    /// The month was exempt and this sale takes it over the limit
+    pub fn breaks_exemption(&self) -> bool {
+        self.counts
+            && self.status_after == Some(ExemptionStatus::Exceeded)
+            && self.exemption_sales_after - self.amount <= self.limit
+    }
+}
+
+/// Where selling `amount` of `asset_type` (held `abroad` or not) would leave
+/// the month's exemption
+pub fn plan_sale(
+    usage: &MonthUsage,
+    asset_type: &db::AssetType,
+    is_day_trade: bool,
+    abroad: bool,
+    amount: Decimal,
+) -> PlannedSale {
+    let category = TaxCategory::of_sale(asset_type, is_day_trade, abroad);
+    let rule = super::rules::category_rule(&category, usage.year, usage.month);
+    let counts = !rule.monthly_exemption.is_zero() && rule.exempt_asset_types.contains(asset_type);
+    let before = usage
+        .category(&category)
+        .map(|c| c.exemption_sales)
+        .unwrap_or(Decimal::ZERO);
+    let after = if counts { before + amount } else { before };
+    PlannedSale {
+        category,
+        amount,
+        counts,
+        exemption_sales_after: after,
+        limit: rule.monthly_exemption,
+        status_after: (!rule.monthly_exemption.is_zero())
+            .then(|| exemption_status(after, rule.monthly_exemption)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn
+    }
+
+    fn insert_asset(conn: &Connection, ticker: &str, asset_type: db::AssetType) -> i64 {
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES (?1, ?2)",
+            rusqlite::params![ticker, asset_type.as_str()],
+        )
+        .unwrap();
+        conn.last_insert_rowid()
+    }
+
+    fn insert_sale(conn: &Connection, asset_id: i64, date: &str, total: &str, day_trade: bool) {
+        conn.execute(
+            "INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                price_per_unit, total_cost, fees, is_day_trade)
+             VALUES (?1, 'SELL', ?2, '100', '1', ?3, '0', ?4)",
+            rusqlite::params![asset_id, date, total, day_trade],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_rolling_monthly_sales_flags_threshold() {
+        let conn = setup();
+        let stock = insert_asset(&conn, "PETR4", db::AssetType::Stock);
+        let fii = insert_asset(&conn, "HGLG11", db::AssetType::Fii);
+
+        insert_sale(&conn, stock, "2025-01-10", "9000", false);
+        insert_sale(&conn, stock, "2025-01-20", "8000", false);
+        insert_sale(&conn, stock, "2025-02-05", "25000", false);
+        insert_sale(&conn, stock, "2025-03-05", "50000", true); // day trade
+        insert_sale(&conn, fii, "2025-03-06", "50000", false); // not a stock
+
+        let as_of = NaiveDate::from_ymd_opt(2025, 3, 15).unwrap();
+        let months = rolling_monthly_sales(&conn, as_of).unwrap();
+        assert_eq!(months.len(), 12);
+
+        let jan = &months[9];
+        assert_eq!((jan.year, jan.month), (2025, 1));
+        assert_eq!(jan.stock_sales, Decimal::from(17000));
+        assert_eq!(jan.status, ExemptionStatus::Approaching);
+        assert_eq!(jan.remaining(), Decimal::from(3000));
+
+        assert_eq!(months[10].status, ExemptionStatus::Exceeded);
+
+        let mar = months.last().unwrap();
+        assert_eq!(mar.stock_sales, Decimal::ZERO);
+        assert_eq!(mar.status, ExemptionStatus::Ok);
+    }
+
+    #[test]
+    fn test_month_usage_by_category_and_planned_sale() {
+        let conn = setup();
+        let stock = insert_asset(&conn, "PETR4", db::AssetType::Stock);
+        let etf = insert_asset(&conn, "BOVA11", db::AssetType::Etf);
+        let fii = insert_asset(&conn, "HGLG11", db::AssetType::Fii);
+
+        insert_sale(&conn, stock, "2025-03-03", "12000", false);
+        insert_sale(&conn, etf, "2025-03-04", "30000", false); // same category, no exemption
+        insert_sale(&conn, stock, "2025-03-05", "5000", true);
+        insert_sale(&conn, fii, "2025-03-06", "7000", false);
+
+        let usage = month_usage(&conn, 2025, 3).unwrap();
+        let categories: Vec<_> = usage
+            .categories
+            .iter()
+            .map(|c| c.category.clone())
+            .collect();
+        assert_eq!(
+            categories,
+            vec![
+                TaxCategory::StockSwingTrade,
+                TaxCategory::StockDayTrade,
+                TaxCategory::FiiSwingTrade
+            ]
+        );
+        let swing = &usage.categories[0];
+        assert_eq!(swing.sales, Decimal::from(42000));
+        assert_eq!(swing.exemption_sales, Decimal::from(12000));
+        assert_eq!(swing.remaining(), Decimal::from(8000));
+        assert_eq!(swing.status, Some(ExemptionStatus::Ok));
+        assert_eq!(usage.categories[2].status, None);
+
+        // Same total the monthly monitor reports for March
+        let as_of = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+        let monitored = rolling_monthly_sales(&conn, as_of).unwrap().pop().unwrap();
+        assert_eq!(monitored.stock_sales, swing.exemption_sales);
+        assert_eq!(monitored.status, ExemptionStatus::Ok);
+
+        let planned = plan_sale(
+            &usage,
+            &db::AssetType::Stock,
+            false,
+            false,
+            Decimal::from(9000),
+        );
+        assert!(planned.breaks_exemption());
+        let etf_sale = plan_sale(
+            &usage,
+            &db::AssetType::Etf,
+            false,
+            false,
+            Decimal::from(9000),
+        );
+        assert!(!etf_sale.counts);
+        assert_eq!(etf_sale.status_after, Some(ExemptionStatus::Ok));
+    }
+
+    #[test]
+    fn test_exemption_status_boundaries() {
+        let threshold = Decimal::from(20000);
+        assert_eq!(
+            exemption_status(Decimal::from(15999), threshold),
+            ExemptionStatus::Ok
+        );
+        assert_eq!(
+            exemption_status(Decimal::from(16000), threshold),
+            ExemptionStatus::Approaching
+        );
+        assert_eq!(
+            exemption_status(Decimal::from(20000), threshold),
+            ExemptionStatus::Approaching
+        );
+        assert_eq!(
+            exemption_status(Decimal::from_str("20000.01").unwrap(), threshold),
+            ExemptionStatus::Exceeded
+        );
+    }
+}
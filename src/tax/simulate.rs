@@ -0,0 +1,284 @@
+//! "What if I sell": the tax effect of a hypothetical sale.
+//!
+//! The sale runs through the same engine as the reports, on a scratch copy
+//! of the database (like the sandbox, made with `VACUUM INTO`) that is
+//! deleted afterwards. The month is computed with and without the sale, so
+//! the result accounts for the month's other sales, the R$ 20k exemption and
+//! the losses carried into the month.
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate};
+use rusqlite::{params, Connection};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::path::PathBuf;
+
+use super::darf::calculate_darf_due_date;
+use super::irpf::{generate_annual_report, AnnualTaxReport};
+use super::rules::{category_rule, withholding_rule};
+use super::swing_trade::{realized_sale, realized_sales_for_year, TaxCategory};
+use crate::db::{self, Transaction, TransactionType};
+
+/// The sale to simulate
+#[derive(Debug, Clone)]
+pub struct SaleInput {
+    pub ticker: String,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fees: Decimal,
+    pub date: NaiveDate,
+    pub day_trade: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SaleSimulation {
+    pub ticker: String,
+    pub date: NaiveDate,
+    #[serde(serialize_with = "serialize_category")]
+    pub category: TaxCategory,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub sale_total: Decimal,
+    pub cost_basis: Decimal,
+    pub average_cost: Decimal,
+    pub profit_loss: Decimal,
+    /// Monthly sales limit of the exemption (zero: the category has none)
+    pub exemption_limit: Decimal,
+    /// Sales counting towards the exemption in the month, this one included
+    pub exemption_sales: Decimal,
+    pub exempt: bool,
+    /// The month was exempt and this sale takes it over the limit
+    pub loses_exemption: bool,
+    pub loss_offset_before: Decimal,
+    pub loss_offset_after: Decimal,
+    pub carry_forward_before: Decimal,
+    pub carry_forward_after: Decimal,
+    pub month_tax_before: Decimal,
+    pub month_tax_after: Decimal,
+    /// IRRF the broker withholds on this sale, deductible from the DARF
+    pub irrf: Decimal,
+    pub darf_code: Option<&'static str>,
+    pub darf_due: NaiveDate,
+}
+
+fn serialize_category<S: serde::Serializer>(
+    category: &TaxCategory,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(category.as_str())
+}
+
+impl SaleSimulation {
+    pub fn additional_tax(&self) -> Decimal {
+        self.month_tax_after - self.month_tax_before
+    }
+}
+
+/// Copy of the database that disappears with the value
+struct Scratch {
+    path: PathBuf,
+    conn: Option<Connection>,
+}
+
+impl Scratch {
+    fn copy_of(conn: &Connection) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "interest-simulate-{}-{}.db",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let _ = std::fs::remove_file(&path);
+        conn.execute("VACUUM INTO ?1", params![path.to_string_lossy()])
+            .context("Failed to copy the database for the simulation")?;
+        let copy = Connection::open(&path)?;
+        Ok(Self {
+            path,
+            conn: Some(copy),
+        })
+    }
+
+    fn conn(&self) -> &Connection {
+        self.conn.as_ref().expect("scratch connection")
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        drop(self.conn.take());
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn month_category(
+    report: &AnnualTaxReport,
+    month: u32,
+    category: &TaxCategory,
+) -> (Decimal, Decimal) {
+    let summary = report.monthly_summaries.iter().find(|s| s.month == month);
+    let loss_offset = summary
+        .and_then(|s| s.by_category.get(category))
+        .map(|c| c.loss_offset_applied)
+        .unwrap_or(Decimal::ZERO);
+    let tax = summary.map(|s| s.tax_due).unwrap_or(Decimal::ZERO);
+    (loss_offset, tax)
+}
+
+/// Tax effect of selling `input` on top of the recorded trades; the
+/// database is left untouched
+pub fn simulate_sale(conn: &Connection, input: &SaleInput) -> Result<SaleSimulation> {
+    let asset = db::get_asset_by_ticker(conn, &input.ticker)?
+        .ok_or_else(|| anyhow::anyhow!("Ticker {} not found", input.ticker))?;
+    let asset_id = asset.id.expect("asset id");
+    let (year, month) = (input.date.year(), input.date.month());
+    let abroad = db::get_asset_currency(conn, asset_id)?.is_some();
+    let category = TaxCategory::of_sale(&asset.asset_type, input.day_trade, abroad);
+
+    let scratch = Scratch::copy_of(conn)?;
+    let conn = scratch.conn();
+    let before = generate_annual_report(conn, year)?;
+
+    let sale_total = input.quantity * input.price;
+    let mut sell = Transaction {
+        id: None,
+        asset_id,
+        transaction_type: TransactionType::Sell,
+        trade_date: input.date,
+        settlement_date: Some(input.date),
+        quantity: input.quantity,
+        price_per_unit: input.price,
+        total_cost: sale_total,
+        fees: input.fees,
+        is_day_trade: input.day_trade,
+        quota_issuance_date: None,
+        notes: None,
+        source: "SIMULATION".to_string(),
+        created_at: chrono::Utc::now(),
+    };
+    sell.id = Some(db::insert_transaction(conn, &sell)?);
+    let sale = realized_sale(conn, &sell)
+        .with_context(|| format!("Cannot sell {} {}", input.quantity, input.ticker))?
+        .ok_or_else(|| anyhow::anyhow!("The sale was not matched to a position"))?;
+    let after = generate_annual_report(conn, year)?;
+
+    let rule = category_rule(&category, year, month);
+    let eligible =
+        !rule.monthly_exemption.is_zero() && rule.exempt_asset_types.contains(&asset.asset_type);
+    let exemption_sales: Decimal = if eligible {
+        realized_sales_for_year(conn, year)?
+            .into_iter()
+            .filter(|(c, s)| {
+                *c == category
+                    && s.sale_date.month() == month
+                    && rule.exempt_asset_types.contains(&s.asset_type)
+            })
+            .map(|(_, s)| s.sale_total)
+            .sum()
+    } else {
+        Decimal::ZERO
+    };
+    let exempt = eligible && exemption_sales <= rule.monthly_exemption;
+    let loses_exemption =
+        eligible && !exempt && exemption_sales - sale_total <= rule.monthly_exemption;
+
+    let (loss_offset_before, month_tax_before) = month_category(&before, month, &category);
+    let (loss_offset_after, month_tax_after) = month_category(&after, month, &category);
+    let carry = |report: &AnnualTaxReport| {
+        report
+            .losses_to_carry_forward
+            .get(&category)
+            .copied()
+            .unwrap_or(Decimal::ZERO)
+    };
+    let (carry_forward_before, carry_forward_after) = (carry(&before), carry(&after));
+    let withholding = withholding_rule(year, month);
+    let irrf = if input.day_trade {
+        (sale.profit_loss.max(Decimal::ZERO) * withholding.day_trade_rate).round_dp(2)
+    } else {
+        (sale_total * withholding.swing_sale_rate).round_dp(2)
+    };
+
+    Ok(SaleSimulation {
+        ticker: asset.ticker,
+        date: input.date,
+        darf_code: category.darf_code(),
+        category,
+        quantity: input.quantity,
+        price: input.price,
+        sale_total,
+        cost_basis: sale.cost_basis,
+        average_cost: sale.cost_basis / input.quantity,
+        profit_loss: sale.profit_loss,
+        exemption_limit: if eligible {
+            rule.monthly_exemption
+        } else {
+            Decimal::ZERO
+        },
+        exemption_sales,
+        exempt,
+        loses_exemption,
+        loss_offset_before,
+        loss_offset_after,
+        carry_forward_before,
+        carry_forward_after,
+        month_tax_before,
+        month_tax_after,
+        irrf,
+        darf_due: calculate_darf_due_date(year, month)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_simulated_sale_leaves_database_untouched() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'PETR4', 'STOCK');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+             VALUES (1, 'BUY', '2024-05-10', '2000', '20', '40000', '0', 0, 'TEST'),
+                    (1, 'SELL', '2024-08-10', '500', '16', '8000', '0', 0, 'TEST'),
+                    (1, 'SELL', '2025-03-05', '500', '30', '15000', '0', 0, 'TEST');",
+        )
+        .unwrap();
+
+        let input = |quantity, price| SaleInput {
+            ticker: "PETR4".to_string(),
+            quantity,
+            price,
+            fees: Decimal::ZERO,
+            date: NaiveDate::from_ymd_opt(2025, 3, 20).unwrap(),
+            day_trade: false,
+        };
+
+        // 15.000 already sold in March: 4.000 more stays exempt
+        let small = simulate_sale(&conn, &input(dec!(100), dec!(40))).unwrap();
+        assert_eq!(small.profit_loss, dec!(2000));
+        assert!(small.exempt);
+        assert_eq!(small.additional_tax(), Decimal::ZERO);
+
+        // 8.000 more takes March over R$ 20k: both gains are taxed, after the
+        // 2.000 loss of 2024
+        let large = simulate_sale(&conn, &input(dec!(200), dec!(40))).unwrap();
+        assert!(large.loses_exemption);
+        assert_eq!(large.exemption_sales, dec!(23000));
+        assert_eq!(large.loss_offset_after, dec!(2000));
+        assert_eq!(large.carry_forward_before, dec!(2000));
+        assert_eq!(large.carry_forward_after, Decimal::ZERO);
+        // (5.000 + 4.000 - 2.000) × 15%
+        assert_eq!(large.month_tax_after, dec!(1050));
+        assert_eq!(large.irrf, dec!(0.40));
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM transactions", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+
+        assert!(simulate_sale(&conn, &input(dec!(5000), dec!(40))).is_err());
+    }
+}
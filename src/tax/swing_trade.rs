@@ -1,8 +1,8 @@
 use anyhow::Result;
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
 use rusqlite::Connection;
 use rust_decimal::Decimal;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
 
 use super::cost_basis::{AverageCostMatcher, SaleCostBasis};
@@ -11,13 +11,17 @@ use crate::db::{Asset, AssetType, CorporateActionType, Transaction, TransactionT
 /// Tax category for operations
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TaxCategory {
-    StockSwingTrade,  // 15%, R$20k exemption
-    StockDayTrade,    // 20%, no exemption
-    FiiSwingTrade,    // 20%, no exemption
-    FiiDayTrade,      // 20%, no exemption
-    FiagroSwingTrade, // 20%, no exemption
-    FiagroDayTrade,   // 20%, no exemption
-    FiInfra,          // Exempt
+    StockSwingTrade,
+    StockDayTrade,
+    FiiSwingTrade,
+    FiiDayTrade,
+    FiagroSwingTrade,
+    FiagroDayTrade,
+    FiInfra,
+    /// Assets held abroad (quoted in a foreign currency), whatever their type
+    Foreign,
+    /// Crypto-assets, wherever the exchange is
+    Crypto,
 }
 
 impl TaxCategory {
@@ -38,27 +42,28 @@ impl TaxCategory {
             (AssetType::Fiagro, false) => TaxCategory::FiagroSwingTrade,
             (AssetType::Fiagro, true) => TaxCategory::FiagroDayTrade,
             (AssetType::FiInfra, _) => TaxCategory::FiInfra,
+            (AssetType::Crypto, _) => TaxCategory::Crypto,
             _ => TaxCategory::StockSwingTrade, // Default for bonds, etc.
         }
     }
 
-    pub fn tax_rate(&self) -> Decimal {
-        match self {
-            TaxCategory::StockSwingTrade => Decimal::from_str("0.15").unwrap(), // 15%
-            TaxCategory::StockDayTrade => Decimal::from_str("0.20").unwrap(),   // 20%
-            TaxCategory::FiiSwingTrade => Decimal::from_str("0.20").unwrap(),   // 20%
-            TaxCategory::FiiDayTrade => Decimal::from_str("0.20").unwrap(),     // 20%
-            TaxCategory::FiagroSwingTrade => Decimal::from_str("0.20").unwrap(), // 20%
-            TaxCategory::FiagroDayTrade => Decimal::from_str("0.20").unwrap(),  // 20%
-            TaxCategory::FiInfra => Decimal::ZERO,                              // Exempt
+    /// Category of a sale of an asset, held abroad when quoted in a foreign currency
+    pub fn of_sale(asset_type: &AssetType, is_day_trade: bool, abroad: bool) -> Self {
+        if abroad && *asset_type != AssetType::Crypto {
+            TaxCategory::Foreign
+        } else {
+            Self::from_asset_and_trade_type(asset_type, is_day_trade)
         }
     }
 
-    pub fn monthly_exemption_threshold(&self) -> Decimal {
-        match self {
-            TaxCategory::StockSwingTrade => Decimal::from(20000), // R$20,000
-            _ => Decimal::ZERO,                                   // No exemption for others
-        }
+    /// Rate in force in (year, month), see `rules`
+    pub fn tax_rate_in(&self, year: i32, month: u32) -> Decimal {
+        super::rules::category_rule(self, year, month).rate
+    }
+
+    /// Monthly sales exemption in force in (year, month), zero when none
+    pub fn monthly_exemption_in(&self, year: i32, month: u32) -> Decimal {
+        super::rules::category_rule(self, year, month).monthly_exemption
     }
 
     #[allow(dead_code)]
@@ -75,6 +80,8 @@ impl TaxCategory {
             TaxCategory::FiagroSwingTrade => "FIAGRO (Swing Trade)",
             TaxCategory::FiagroDayTrade => "FIAGRO (Day Trade)",
             TaxCategory::FiInfra => "FI-Infra (Isento)",
+            TaxCategory::Foreign => "Exterior",
+            TaxCategory::Crypto => "Criptoativos",
         }
     }
 
@@ -87,6 +94,8 @@ impl TaxCategory {
             TaxCategory::FiagroSwingTrade => "FIAGRO_SWING",
             TaxCategory::FiagroDayTrade => "FIAGRO_DAY",
             TaxCategory::FiInfra => "FI_INFRA",
+            TaxCategory::Foreign => "FOREIGN",
+            TaxCategory::Crypto => "CRYPTO",
         }
     }
 
@@ -94,8 +103,10 @@ impl TaxCategory {
     /// All capital gains use code 6015, but are reported separately
     pub fn darf_code(&self) -> Option<&'static str> {
         match self {
-            TaxCategory::FiInfra => None, // Exempt, no DARF needed
-            _ => Some("6015"),            // Capital gains code
+            TaxCategory::FiInfra => None,         // Exempt, no DARF needed
+            TaxCategory::Foreign => Some("4600"), // GCAP, monthly until 2023
+            TaxCategory::Crypto => Some("4600"),  // GCAP
+            _ => Some("6015"),                    // Capital gains code
         }
     }
 
@@ -109,6 +120,8 @@ impl TaxCategory {
             TaxCategory::FiagroSwingTrade => "FIAGRO - Operações Comuns",
             TaxCategory::FiagroDayTrade => "FIAGRO - Day Trade",
             TaxCategory::FiInfra => "FI-Infra - Isento",
+            TaxCategory::Foreign => "Ganhos de Capital - Bens no Exterior",
+            TaxCategory::Crypto => "Ganhos de Capital - Criptoativos",
         }
     }
 }
@@ -125,6 +138,8 @@ impl FromStr for TaxCategory {
             "FIAGRO_SWING" => Ok(TaxCategory::FiagroSwingTrade),
             "FIAGRO_DAY" => Ok(TaxCategory::FiagroDayTrade),
             "FI_INFRA" => Ok(TaxCategory::FiInfra),
+            "FOREIGN" => Ok(TaxCategory::Foreign),
+            "CRYPTO" => Ok(TaxCategory::Crypto),
             _ => Err(()),
         }
     }
@@ -153,6 +168,22 @@ pub struct MonthlyTaxCalculation {
     pub sales: Vec<SaleCostBasis>,
 }
 
+/// Sales realized in a single month, grouped by tax category
+pub type MonthSales = HashMap<TaxCategory, Vec<SaleCostBasis>>;
+
+/// Per-category sales totals for a month, before loss carryforward is applied
+#[derive(Debug, Clone)]
+pub struct CategorySales {
+    pub category: TaxCategory,
+    pub total_sales: Decimal,
+    pub total_cost_basis: Decimal,
+    pub total_profit: Decimal,
+    pub total_loss: Decimal,
+    pub net_profit: Decimal,
+    pub exemptable_profit: Decimal,
+    pub sales: Vec<SaleCostBasis>,
+}
+
 /// Calculate monthly swing trade tax for a specific month
 pub fn calculate_monthly_tax(
     conn: &Connection,
@@ -160,24 +191,100 @@ pub fn calculate_monthly_tax(
     month: u32,
     carryforward: &mut HashMap<TaxCategory, Decimal>,
 ) -> Result<Vec<MonthlyTaxCalculation>> {
+    let mut months = replay_sales_by_month(conn, year, month, None)?;
+    let month_sales = months.pop().unwrap_or_default();
+
+    Ok(summarize_month_sales(year, month, month_sales)
+        .into_iter()
+        .map(|summary| apply_loss_carryforward(year, month, summary, carryforward))
+        .collect())
+}
+
+/// Calculate swing trade tax for every month of a year.
+///
+/// Positions are replayed once for the whole year and the per-month
+/// aggregation runs on the rayon pool; only the loss carryforward chain is
+/// applied in month order. Entry `i` holds the calculations for month `i + 1`.
+pub fn calculate_annual_tax(
+    conn: &Connection,
+    year: i32,
+    carryforward: &mut HashMap<TaxCategory, Decimal>,
+) -> Result<Vec<Vec<MonthlyTaxCalculation>>> {
+    use rayon::prelude::*;
+
+    let months = replay_sales_by_month(conn, year, 12, None)?;
+    let summaries: Vec<Vec<CategorySales>> = months
+        .into_par_iter()
+        .enumerate()
+        .map(|(i, month_sales)| summarize_month_sales(year, i as u32 + 1, month_sales))
+        .collect();
+
+    Ok(summaries
+        .into_iter()
+        .zip(1u32..)
+        .map(|(month_summaries, month)| {
+            month_summaries
+                .into_iter()
+                .map(|summary| apply_loss_carryforward(year, month, summary, carryforward))
+                .collect()
+        })
+        .collect())
+}
+
+/// Realized result of a single SELL transaction, matched against the
+/// average cost of its asset at the time of the sale.
+pub fn realized_sale(conn: &Connection, tx: &Transaction) -> Result<Option<SaleCostBasis>> {
+    if tx.transaction_type != TransactionType::Sell || tx.id.is_none() {
+        return Ok(None);
+    }
+
+    let year = tx.trade_date.year();
+    let month = tx.trade_date.month();
+    let months = replay_sales_by_month(conn, year, month, Some(tx.asset_id))?;
+
+    Ok(months
+        .into_iter()
+        .last()
+        .into_iter()
+        .flat_map(|sales| sales.into_values().flatten())
+        .find(|sale| sale.transaction_id == tx.id))
+}
+
+/// Every sale realized during `year`, with its tax category, in date order
+pub fn realized_sales_for_year(
+    conn: &Connection,
+    year: i32,
+) -> Result<Vec<(TaxCategory, SaleCostBasis)>> {
+    let mut sales: Vec<(TaxCategory, SaleCostBasis)> = replay_sales_by_month(conn, year, 12, None)?
+        .into_iter()
+        .flat_map(|month| {
+            month
+                .into_iter()
+                .flat_map(|(category, sales)| sales.into_iter().map(move |s| (category.clone(), s)))
+        })
+        .collect();
+    sales.sort_by_key(|(_, s)| (s.sale_date, s.transaction_id));
+    Ok(sales)
+}
+
+/// Replay every asset's position once, up to the end of `through_month`, and
+/// bucket the sales made during `year` by month. Entry `i` holds month `i + 1`.
+fn replay_sales_by_month(
+    conn: &Connection,
+    year: i32,
+    through_month: u32,
+    only_asset: Option<i64>,
+) -> Result<Vec<MonthSales>> {
     // Get all assets
     let assets = crate::db::get_all_assets(conn)?;
+    let abroad = crate::db::get_assets_quoted_abroad(conn)?;
 
-    // Group sales by tax category
-    let mut sales_by_category: HashMap<TaxCategory, Vec<SaleCostBasis>> = HashMap::new();
-
-    let month_start = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
-    let month_end = if month == 12 {
-        NaiveDate::from_ymd_opt(year + 1, 1, 1)
-            .unwrap()
-            .pred_opt()
-            .unwrap()
-    } else {
-        NaiveDate::from_ymd_opt(year, month + 1, 1)
-            .unwrap()
-            .pred_opt()
-            .unwrap()
-    };
+    let month_ends: Vec<NaiveDate> = (1..=through_month)
+        .map(|month| month_end_date(year, month))
+        .collect();
+    let year_start = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+    let period_end = month_end_date(year, through_month);
+    let mut months: Vec<MonthSales> = month_ends.iter().map(|_| HashMap::new()).collect();
 
     let mut assets_by_id = HashMap::new();
     for asset in &assets {
@@ -192,25 +299,31 @@ pub fn calculate_monthly_tax(
             continue;
         }
 
-        if !crate::db::is_supported_portfolio_ticker(&asset.ticker) {
-            continue;
-        }
-
         // Skip FI-Infra entirely
         if asset.asset_type == AssetType::FiInfra {
             continue;
         }
 
         let asset_id = asset.id.unwrap();
+        if only_asset.is_some_and(|id| id != asset_id) {
+            continue;
+        }
 
-        if crate::db::is_rename_source_asset(conn, asset_id, month_end)? {
+        // Once renamed, the source asset's sales are reported under the target
+        let mut reporting = Vec::with_capacity(month_ends.len());
+        for month_end in &month_ends {
+            reporting.push(!crate::db::is_rename_source_asset(
+                conn, asset_id, *month_end,
+            )?);
+        }
+        if !reporting.iter().any(|r| *r) {
             continue;
         }
 
-        // Get all transactions for this asset up to end of month
-        let mut transactions = get_transactions_up_to_month(conn, asset_id, year, month)?;
+        // Get all transactions for this asset up to end of the period
+        let mut transactions = get_transactions_up_to_month(conn, asset_id, year, through_month)?;
 
-        let renames = crate::db::get_asset_renames_as_target_up_to(conn, asset_id, month_end)?;
+        let renames = crate::db::get_asset_renames_as_target_up_to(conn, asset_id, period_end)?;
         for rename in renames {
             if let Some(source_asset) = assets_by_id.get(&rename.from_asset_id) {
                 if let Some(carryover) = build_rename_carryover_transaction(
@@ -224,7 +337,7 @@ pub fn calculate_monthly_tax(
             }
         }
 
-        let exchanges = crate::db::get_asset_exchanges_as_target_up_to(conn, asset_id, month_end)?;
+        let exchanges = crate::db::get_asset_exchanges_as_target_up_to(conn, asset_id, period_end)?;
         for exchange in exchanges {
             if exchange.to_quantity <= Decimal::ZERO {
                 continue;
@@ -267,24 +380,27 @@ pub fn calculate_monthly_tax(
             });
         }
 
-        transactions.sort_by(|a, b| (a.trade_date, a.id).cmp(&(b.trade_date, b.id)));
+        transactions.sort_by_key(|a| (a.trade_date, a.id));
+        let transactions =
+            crate::options::add_expiry_sales(&asset.ticker, asset_id, transactions, period_end);
 
-        // Calculate cost basis for sales in this month using average cost
+        // Calculate cost basis for sales using average cost
         // Separate matchers for swing and day trade flows
         let mut swing_matcher = AverageCostMatcher::new();
         let mut day_trade_matcher = AverageCostMatcher::new();
 
         // Capital return (amortization) events reduce cost basis without changing quantity
         let amortizations =
-            crate::db::get_amortizations_for_asset(conn, asset_id, None, Some(month_end))?;
+            crate::db::get_amortizations_for_asset(conn, asset_id, None, Some(period_end))?;
         let mut amort_idx: usize = 0;
         let exchanges_as_source =
-            crate::db::get_asset_exchanges_as_source_up_to(conn, asset_id, month_end)?;
+            crate::db::get_asset_exchanges_as_source_up_to(conn, asset_id, period_end)?;
         let mut exchange_idx: usize = 0;
 
         // Forward-only corporate action adjustments: apply once at ex-date
         // We only apply quantity adjustments to the swing matcher (persistent holdings)
-        let actions_up_to = crate::corporate_actions::get_actions_up_to(conn, asset_id, month_end)?;
+        let actions_up_to =
+            crate::corporate_actions::get_actions_up_to(conn, asset_id, period_end)?;
         let mut action_idx: usize = 0;
 
         for tx in transactions {
@@ -299,10 +415,21 @@ pub fn calculate_monthly_tax(
             while exchange_idx < exchanges_as_source.len()
                 && exchanges_as_source[exchange_idx].effective_date <= tx.trade_date
             {
-                apply_exchange_source_effect(
+                if let Some(sale) = apply_exchange_source_effect(
                     &mut swing_matcher,
                     &exchanges_as_source[exchange_idx],
-                );
+                    asset_id,
+                ) {
+                    push_year_sale(
+                        &mut months,
+                        &reporting,
+                        year_start,
+                        &asset,
+                        &abroad,
+                        false,
+                        sale,
+                    );
+                }
                 exchange_idx += 1;
             }
 
@@ -310,15 +437,10 @@ pub fn calculate_monthly_tax(
             while action_idx < actions_up_to.len()
                 && actions_up_to[action_idx].ex_date <= tx.trade_date
             {
-                match actions_up_to[action_idx].action_type {
-                    crate::db::CorporateActionType::Split
-                    | crate::db::CorporateActionType::ReverseSplit => {
-                        swing_matcher.apply_quantity_adjustment(
-                            actions_up_to[action_idx].quantity_adjustment,
-                        );
-                    }
-                    // Bonus is auto-applied as a transaction on import; capital return handled elsewhere
-                    _ => {}
+                // Capital return is handled with the amortizations
+                if actions_up_to[action_idx].action_type != CorporateActionType::CapitalReturn {
+                    swing_matcher
+                        .apply_quantity_adjustment(actions_up_to[action_idx].quantity_adjustment);
                 }
                 action_idx += 1;
             }
@@ -332,121 +454,187 @@ pub fn calculate_monthly_tax(
                     }
                 }
                 TransactionType::Sell => {
-                    // Only process sales in the target month
-                    if tx.trade_date >= month_start && tx.trade_date <= month_end {
-                        // Determine category based on asset type and day trade flag
-                        let category = TaxCategory::from_asset_and_trade_type(
-                            &asset.asset_type,
-                            tx.is_day_trade,
-                        );
-
-                        let mut sale = if tx.is_day_trade {
-                            day_trade_matcher.match_sale(&tx, None)?
-                        } else {
-                            swing_matcher.match_sale(&tx, None)?
-                        };
-                        sale.asset_type = asset.asset_type;
-                        sales_by_category.entry(category).or_default().push(sale);
-                    } else if tx.trade_date > month_end {
-                        // We've passed the target month, no need to process further
+                    if tx.trade_date > period_end {
+                        // We've passed the target period, no need to process further
                         break;
-                    } else {
-                        // Sale before target month, still need to process to maintain average cost
-                        if tx.is_day_trade {
-                            let _ = day_trade_matcher.match_sale(&tx, None)?;
-                        } else {
-                            let _ = swing_matcher.match_sale(&tx, None)?;
-                        }
                     }
+
+                    // Earlier sales are still matched to maintain average cost
+                    let sale = if tx.is_day_trade {
+                        day_trade_matcher.match_sale(&tx, None)?
+                    } else {
+                        swing_matcher.match_sale(&tx, None)?
+                    };
+
+                    push_year_sale(
+                        &mut months,
+                        &reporting,
+                        year_start,
+                        &asset,
+                        &abroad,
+                        tx.is_day_trade,
+                        sale,
+                    );
                 }
             }
         }
-    }
 
-    // Now calculate tax for each category
-    let mut results = Vec::new();
-
-    for (category, sales) in sales_by_category {
-        if sales.is_empty() {
-            continue;
+        // Mergers after the last trade still close the position
+        for exchange in &exchanges_as_source[exchange_idx..] {
+            if let Some(sale) = apply_exchange_source_effect(&mut swing_matcher, exchange, asset_id)
+            {
+                push_year_sale(
+                    &mut months,
+                    &reporting,
+                    year_start,
+                    &asset,
+                    &abroad,
+                    false,
+                    sale,
+                );
+            }
         }
+    }
 
-        let mut total_sales = Decimal::ZERO;
-        let mut total_cost_basis = Decimal::ZERO;
-        let mut total_profit = Decimal::ZERO;
-        let mut total_loss = Decimal::ZERO;
+    Ok(months)
+}
 
-        for sale in &sales {
-            total_sales += sale.sale_total;
-            total_cost_basis += sale.cost_basis;
+/// File a sale of `asset` under its month when it falls in the replayed year
+fn push_year_sale(
+    months: &mut [MonthSales],
+    reporting: &[bool],
+    year_start: NaiveDate,
+    asset: &crate::db::Asset,
+    abroad: &HashSet<i64>,
+    is_day_trade: bool,
+    mut sale: SaleCostBasis,
+) {
+    if sale.sale_date < year_start {
+        return;
+    }
+    let idx = sale.sale_date.month0() as usize;
+    if idx < months.len() && reporting[idx] {
+        // Determine category based on asset type, day trade flag and where it is held
+        let category = TaxCategory::of_sale(
+            &asset.asset_type,
+            is_day_trade,
+            asset.id.is_some_and(|id| abroad.contains(&id)),
+        );
+        sale.asset_type = asset.asset_type;
+        months[idx].entry(category).or_default().push(sale);
+    }
+}
 
-            if sale.profit_loss > Decimal::ZERO {
-                total_profit += sale.profit_loss;
-            } else {
-                total_loss += sale.profit_loss.abs();
-            }
+/// Aggregate one month's sales per category. Independent of other months.
+fn summarize_month_sales(year: i32, month: u32, month_sales: MonthSales) -> Vec<CategorySales> {
+    let mut summaries: Vec<CategorySales> = month_sales
+        .into_iter()
+        .filter(|(_, sales)| !sales.is_empty())
+        .map(|(category, sales)| summarize_category_sales(year, month, category, sales))
+        .collect();
+    summaries.sort_by_key(|s| s.category.as_str());
+    summaries
+}
+
+fn summarize_category_sales(
+    year: i32,
+    month: u32,
+    category: TaxCategory,
+    sales: Vec<SaleCostBasis>,
+) -> CategorySales {
+    let mut total_sales = Decimal::ZERO;
+    let mut total_cost_basis = Decimal::ZERO;
+    let mut total_profit = Decimal::ZERO;
+    let mut total_loss = Decimal::ZERO;
+
+    for sale in &sales {
+        total_sales += sale.sale_total;
+        total_cost_basis += sale.cost_basis;
+
+        if sale.profit_loss > Decimal::ZERO {
+            total_profit += sale.profit_loss;
+        } else {
+            total_loss += sale.profit_loss.abs();
         }
+    }
 
-        // Calculate net profit/loss
-        let net_profit = total_profit - total_loss;
+    // Calculate net profit/loss
+    let net_profit = total_profit - total_loss;
+
+    // Determine exemptable portion (sales of the exempt asset types under the
+    // monthly limit, e.g. stock swing trades up to R$20k)
+    let rule = super::rules::category_rule(&category, year, month);
+    let exempt = |sale: &&SaleCostBasis| rule.exempt_asset_types.contains(&sale.asset_type);
+    let exempt_sales_total: Decimal = sales
+        .iter()
+        .filter(exempt)
+        .map(|sale| sale.sale_total)
+        .sum();
+    let exempt_profit_total: Decimal = sales
+        .iter()
+        .filter(exempt)
+        .map(|sale| sale.profit_loss)
+        .sum();
+    let exemptable_profit = if rule.monthly_exemption > Decimal::ZERO
+        && net_profit > Decimal::ZERO
+        && exempt_sales_total <= rule.monthly_exemption
+        && exempt_profit_total > Decimal::ZERO
+    {
+        exempt_profit_total.min(net_profit)
+    } else {
+        Decimal::ZERO
+    };
 
-        // Determine exemptable portion (only stock swing trades under R$20k sales)
-        let exemption_threshold = category.monthly_exemption_threshold();
-        let stock_sales_total: Decimal = sales
-            .iter()
-            .filter(|sale| sale.asset_type == AssetType::Stock)
-            .map(|sale| sale.sale_total)
-            .sum();
-        let stock_profit_total: Decimal = sales
-            .iter()
-            .filter(|sale| sale.asset_type == AssetType::Stock)
-            .map(|sale| sale.profit_loss)
-            .sum();
-        let exemptable_profit = if category == TaxCategory::StockSwingTrade
-            && net_profit > Decimal::ZERO
-            && stock_sales_total <= exemption_threshold
-            && stock_profit_total > Decimal::ZERO
-        {
-            stock_profit_total.min(net_profit)
-        } else {
+    CategorySales {
+        category,
+        total_sales,
+        total_cost_basis,
+        total_profit,
+        total_loss,
+        net_profit,
+        exemptable_profit,
+        sales,
+    }
+}
+
+/// Apply the running loss carryforward to a month's category totals.
+/// Must be called in month order since each month updates the carry.
+fn apply_loss_carryforward(
+    year: i32,
+    month: u32,
+    summary: CategorySales,
+    carryforward: &mut HashMap<TaxCategory, Decimal>,
+) -> MonthlyTaxCalculation {
+    let CategorySales {
+        category,
+        total_sales,
+        total_cost_basis,
+        total_profit,
+        total_loss,
+        net_profit,
+        exemptable_profit,
+        sales,
+    } = summary;
+    let profit_after_exemption = net_profit - exemptable_profit;
+    let rule = super::rules::category_rule(&category, year, month);
+
+    // Gains abroad and on crypto are taxed on their own: GCAP never offset
+    // losses, and since the annual regime the year's result abroad is netted
+    // in `foreign_gains`
+    if matches!(category, TaxCategory::Foreign | TaxCategory::Crypto) {
+        let taxable_amount = if rule.annual {
             Decimal::ZERO
-        };
-        let profit_after_exemption = net_profit - exemptable_profit;
-
-        // Apply loss carryforward only to the taxable portion (after exemption)
-        let starting_carry = carryforward
-            .get(&category)
-            .cloned()
-            .unwrap_or(Decimal::ZERO);
-        let loss_offset_applied = if profit_after_exemption > Decimal::ZERO {
-            profit_after_exemption.min(starting_carry)
         } else {
-            Decimal::ZERO
+            profit_after_exemption.max(Decimal::ZERO)
         };
-        let profit_after_loss_offset = profit_after_exemption - loss_offset_applied;
-
-        let mut new_carry = starting_carry - loss_offset_applied;
-        if profit_after_loss_offset < Decimal::ZERO {
-            new_carry += profit_after_loss_offset.abs();
-        }
-        if new_carry.is_zero() {
-            carryforward.remove(&category);
+        let tax_due = rule.tax(taxable_amount);
+        // Effective rate when the gains span progressive brackets
+        let tax_rate = if rule.gain_brackets.is_empty() || taxable_amount.is_zero() {
+            rule.rate
         } else {
-            carryforward.insert(category.clone(), new_carry);
-        }
-
-        // Taxable amount excludes exempt stock profit; carry is untouched by exempt gains
-        let (exemption_applied, taxable_amount) = if profit_after_loss_offset <= Decimal::ZERO {
-            (exemptable_profit, Decimal::ZERO)
-        } else {
-            (exemptable_profit, profit_after_loss_offset)
+            (tax_due / taxable_amount).round_dp(4)
         };
-
-        // Calculate tax
-        let tax_rate = category.tax_rate();
-        let tax_due = taxable_amount * tax_rate;
-
-        results.push(MonthlyTaxCalculation {
+        return MonthlyTaxCalculation {
             year,
             month,
             category,
@@ -455,17 +643,81 @@ pub fn calculate_monthly_tax(
             total_profit,
             total_loss,
             net_profit,
-            loss_offset_applied,
-            profit_after_loss_offset,
-            exemption_applied,
+            loss_offset_applied: Decimal::ZERO,
+            profit_after_loss_offset: profit_after_exemption,
+            exemption_applied: exemptable_profit,
             taxable_amount,
             tax_rate,
             tax_due,
             sales,
-        });
+        };
     }
 
-    Ok(results)
+    // Apply loss carryforward only to the taxable portion (after exemption)
+    let starting_carry = carryforward
+        .get(&category)
+        .cloned()
+        .unwrap_or(Decimal::ZERO);
+    let loss_offset_applied = if profit_after_exemption > Decimal::ZERO {
+        profit_after_exemption.min(starting_carry)
+    } else {
+        Decimal::ZERO
+    };
+    let profit_after_loss_offset = profit_after_exemption - loss_offset_applied;
+
+    let mut new_carry = starting_carry - loss_offset_applied;
+    if profit_after_loss_offset < Decimal::ZERO {
+        new_carry += profit_after_loss_offset.abs();
+    }
+    if new_carry.is_zero() {
+        carryforward.remove(&category);
+    } else {
+        carryforward.insert(category.clone(), new_carry);
+    }
+
+    // Taxable amount excludes exempt stock profit; carry is untouched by exempt gains
+    let (exemption_applied, taxable_amount) = if profit_after_loss_offset <= Decimal::ZERO {
+        (exemptable_profit, Decimal::ZERO)
+    } else {
+        (exemptable_profit, profit_after_loss_offset)
+    };
+
+    // Calculate tax
+    let tax_rate = category.tax_rate_in(year, month);
+    let tax_due = taxable_amount * tax_rate;
+
+    MonthlyTaxCalculation {
+        year,
+        month,
+        category,
+        total_sales,
+        total_cost_basis,
+        total_profit,
+        total_loss,
+        net_profit,
+        loss_offset_applied,
+        profit_after_loss_offset,
+        exemption_applied,
+        taxable_amount,
+        tax_rate,
+        tax_due,
+        sales,
+    }
+}
+
+/// Last calendar day of a month
+fn month_end_date(year: i32, month: u32) -> NaiveDate {
+    if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+            .unwrap()
+            .pred_opt()
+            .unwrap()
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+            .unwrap()
+            .pred_opt()
+            .unwrap()
+    }
 }
 
 fn get_transactions_before(
@@ -473,14 +725,15 @@ fn get_transactions_before(
     asset_id: i64,
     before_date: NaiveDate,
 ) -> Result<Vec<Transaction>> {
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT id, asset_id, transaction_type, trade_date, settlement_date,
                 quantity, price_per_unit, total_cost, fees, is_day_trade,
                 quota_issuance_date, notes, source, created_at
          FROM transactions
-         WHERE asset_id = ?1 AND trade_date < ?2
+         WHERE asset_id = ?1 AND trade_date < ?2{}
          ORDER BY trade_date ASC, id ASC",
-    )?;
+        crate::db::portfolio::scope_filter("portfolio_id")
+    ))?;
 
     let transactions = stmt
         .query_map(rusqlite::params![asset_id, before_date], |row| {
@@ -526,6 +779,8 @@ fn build_rename_carryover_transaction(
     let amortizations =
         crate::db::get_amortizations_for_asset(conn, source_id, None, Some(effective_date))?;
     let mut amort_idx: usize = 0;
+    let actions = crate::corporate_actions::get_actions_up_to(conn, source_id, effective_date)?;
+    let mut action_idx: usize = 0;
 
     for tx in transactions {
         if tx.is_day_trade {
@@ -539,35 +794,28 @@ fn build_rename_carryover_transaction(
             amort_idx += 1;
         }
 
-        // Apply corporate action adjustments for rename carryover
-        let actions = crate::corporate_actions::get_applicable_actions(
-            conn,
-            source_id,
-            tx.trade_date,
-            effective_date,
-        )?;
-
-        let adjusted_quantity =
-            crate::corporate_actions::adjust_quantity_for_actions(tx.quantity, &actions);
-
-        let (_adjusted_price, adjusted_cost) =
-            crate::corporate_actions::adjust_price_and_cost_for_actions(
-                tx.quantity,
-                tx.price_per_unit,
-                tx.total_cost,
-                &actions,
-            );
+        // Corporate actions of the source apply forward, once at their ex-date
+        while action_idx < actions.len() && actions[action_idx].ex_date <= tx.trade_date {
+            if actions[action_idx].action_type != CorporateActionType::CapitalReturn {
+                matcher.apply_quantity_adjustment(actions[action_idx].quantity_adjustment);
+            }
+            action_idx += 1;
+        }
 
         match tx.transaction_type {
-            TransactionType::Buy => {
-                matcher.add_purchase(&tx, Some(adjusted_quantity), Some(adjusted_cost))
-            }
+            TransactionType::Buy => matcher.add_purchase(&tx, None, None),
             TransactionType::Sell => {
-                let _ = matcher.match_sale(&tx, Some(adjusted_quantity))?;
+                let _ = matcher.match_sale(&tx, None)?;
             }
         }
     }
 
+    for action in &actions[action_idx..] {
+        if action.action_type != CorporateActionType::CapitalReturn {
+            matcher.apply_quantity_adjustment(action.quantity_adjustment);
+        }
+    }
+
     // Apply any remaining amortizations up to the effective date
     while amort_idx < amortizations.len() && amortizations[amort_idx].event_date <= effective_date {
         matcher.apply_amortization(amortizations[amort_idx].total_amount);
@@ -626,6 +874,7 @@ fn apply_actions_to_carryover(
         "SELECT action_type, quantity_adjustment, ex_date
          FROM corporate_actions
          WHERE asset_id = ?1 AND ex_date >= ?2
+           AND (action_type != 'BONUS' OR applied_at IS NOT NULL)
          ORDER BY ex_date ASC",
     )?;
 
@@ -669,18 +918,25 @@ fn apply_actions_to_carryover(
     Ok(())
 }
 
+/// Effect of a spin-off or merger on the source position; a merger paying
+/// cash returns the sale of the cash portion
 fn apply_exchange_source_effect(
     matcher: &mut AverageCostMatcher,
     exchange: &crate::db::AssetExchange,
-) {
+    asset_id: i64,
+) -> Option<SaleCostBasis> {
     match exchange.event_type {
         crate::db::AssetExchangeType::Spinoff => {
             let reduction = exchange.allocated_cost + exchange.cash_amount;
             matcher.apply_amortization(reduction);
+            None
         }
-        crate::db::AssetExchangeType::Merger => {
-            matcher.clear_position();
-        }
+        crate::db::AssetExchangeType::Merger => matcher.settle_merger(
+            exchange.effective_date,
+            asset_id,
+            exchange.allocated_cost,
+            exchange.cash_amount,
+        ),
     }
 }
 
@@ -691,26 +947,17 @@ fn get_transactions_up_to_month(
     year: i32,
     month: u32,
 ) -> Result<Vec<Transaction>> {
-    let end_date = if month == 12 {
-        NaiveDate::from_ymd_opt(year + 1, 1, 1)
-            .unwrap()
-            .pred_opt()
-            .unwrap()
-    } else {
-        NaiveDate::from_ymd_opt(year, month + 1, 1)
-            .unwrap()
-            .pred_opt()
-            .unwrap()
-    };
+    let end_date = month_end_date(year, month);
 
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT id, asset_id, transaction_type, trade_date, settlement_date,
                 quantity, price_per_unit, total_cost, fees, is_day_trade,
                 quota_issuance_date, notes, source, created_at
          FROM transactions
-         WHERE asset_id = ?1 AND trade_date <= ?2
+         WHERE asset_id = ?1 AND trade_date <= ?2{}
          ORDER BY trade_date ASC, id ASC",
-    )?;
+        crate::db::portfolio::scope_filter("portfolio_id")
+    ))?;
 
     let transactions = stmt
         .query_map([asset_id.to_string(), end_date.to_string()], |row| {
@@ -772,36 +1019,36 @@ mod tests {
     #[test]
     fn test_tax_category_rates() {
         assert_eq!(
-            TaxCategory::StockSwingTrade.tax_rate(),
+            TaxCategory::StockSwingTrade.tax_rate_in(2025, 1),
             Decimal::from_str("0.15").unwrap()
         );
         assert_eq!(
-            TaxCategory::StockDayTrade.tax_rate(),
+            TaxCategory::StockDayTrade.tax_rate_in(2025, 1),
             Decimal::from_str("0.20").unwrap()
         );
         assert_eq!(
-            TaxCategory::FiiSwingTrade.tax_rate(),
+            TaxCategory::FiiSwingTrade.tax_rate_in(2025, 1),
             Decimal::from_str("0.20").unwrap()
         );
-        assert_eq!(TaxCategory::FiInfra.tax_rate(), Decimal::ZERO);
+        assert_eq!(TaxCategory::FiInfra.tax_rate_in(2025, 1), Decimal::ZERO);
     }
 
     #[test]
     fn test_tax_category_exemptions() {
         assert_eq!(
-            TaxCategory::StockSwingTrade.monthly_exemption_threshold(),
+            TaxCategory::StockSwingTrade.monthly_exemption_in(2025, 1),
             Decimal::from(20000)
         );
         assert_eq!(
-            TaxCategory::StockDayTrade.monthly_exemption_threshold(),
+            TaxCategory::StockDayTrade.monthly_exemption_in(2025, 1),
             Decimal::ZERO
         );
         assert_eq!(
-            TaxCategory::FiiSwingTrade.monthly_exemption_threshold(),
+            TaxCategory::FiiSwingTrade.monthly_exemption_in(2025, 1),
             Decimal::ZERO
         );
         assert_eq!(
-            TaxCategory::FiInfra.monthly_exemption_threshold(),
+            TaxCategory::FiInfra.monthly_exemption_in(2025, 1),
             Decimal::ZERO
         );
     }
@@ -846,7 +1093,7 @@ mod tests {
     #[test]
     fn test_tax_calculation_stock_swing() {
         let taxable = Decimal::from(10000);
-        let tax_rate = TaxCategory::StockSwingTrade.tax_rate();
+        let tax_rate = TaxCategory::StockSwingTrade.tax_rate_in(2025, 1);
         let tax_due = taxable * tax_rate;
 
         assert_eq!(tax_due, Decimal::from(1500)); // 15%
@@ -855,7 +1102,7 @@ mod tests {
     #[test]
     fn test_tax_calculation_day_trade() {
         let taxable = Decimal::from(10000);
-        let tax_rate = TaxCategory::StockDayTrade.tax_rate();
+        let tax_rate = TaxCategory::StockDayTrade.tax_rate_in(2025, 1);
         let tax_due = taxable * tax_rate;
 
         assert_eq!(tax_due, Decimal::from(2000)); // 20%
@@ -864,9 +1111,138 @@ mod tests {
     #[test]
     fn test_tax_calculation_fii() {
         let taxable = Decimal::from(10000);
-        let tax_rate = TaxCategory::FiiSwingTrade.tax_rate();
+        let tax_rate = TaxCategory::FiiSwingTrade.tax_rate_in(2025, 1);
         let tax_due = taxable * tax_rate;
 
         assert_eq!(tax_due, Decimal::from(2000)); // 20%
     }
+
+    fn insert_trade(
+        conn: &Connection,
+        asset_id: i64,
+        tx_type: &str,
+        date: &str,
+        qty: &str,
+        total: &str,
+    ) {
+        conn.execute(
+            "INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                price_per_unit, total_cost, fees, is_day_trade, source)
+             VALUES (?1, ?2, ?3, ?4, '0', ?5, '0', 0, 'TEST')",
+            rusqlite::params![asset_id, tx_type, date, qty, total],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_annual_tax_matches_month_by_month() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('PETR4', 'STOCK'), ('HGLG11', 'FII')",
+            [],
+        )
+        .unwrap();
+
+        insert_trade(&conn, 1, "BUY", "2024-11-10", "1000", "30000");
+        insert_trade(&conn, 2, "BUY", "2024-12-10", "100", "16000");
+        insert_trade(&conn, 1, "SELL", "2025-01-15", "400", "10000"); // loss 2000, exempt month
+        insert_trade(&conn, 1, "SELL", "2025-03-15", "600", "24000"); // profit 6000
+        insert_trade(&conn, 2, "SELL", "2025-03-20", "50", "7000"); // FII loss 1000
+        insert_trade(&conn, 2, "SELL", "2025-07-01", "50", "9500"); // FII profit 1500
+
+        let mut annual_carry = HashMap::new();
+        let annual = calculate_annual_tax(&conn, 2025, &mut annual_carry).unwrap();
+        assert_eq!(annual.len(), 12);
+
+        let mut monthly_carry = HashMap::new();
+        for (calcs, month) in annual.iter().zip(1u32..) {
+            let expected = calculate_monthly_tax(&conn, 2025, month, &mut monthly_carry).unwrap();
+            let key =
+                |c: &MonthlyTaxCalculation| (c.category.as_str(), c.tax_due, c.loss_offset_applied);
+            let mut expected: Vec<_> = expected.iter().map(key).collect();
+            expected.sort();
+            let actual: Vec<_> = calcs.iter().map(key).collect();
+            assert_eq!(actual, expected, "month {month}");
+        }
+        assert_eq!(annual_carry, monthly_carry);
+
+        let march = &annual[2];
+        let stock = march
+            .iter()
+            .find(|c| c.category == TaxCategory::StockSwingTrade)
+            .unwrap();
+        assert_eq!(stock.loss_offset_applied, Decimal::from(2000));
+        assert_eq!(stock.tax_due, Decimal::from(600));
+        let fii = &annual[6][0];
+        assert_eq!(fii.loss_offset_applied, Decimal::from(1000));
+        assert_eq!(fii.tax_due, Decimal::from(100));
+    }
+
+    #[test]
+    fn test_crypto_sales_are_exempt_up_to_the_limit_and_never_offset() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('BTC', 'CRYPTO')",
+            [],
+        )
+        .unwrap();
+
+        insert_trade(&conn, 1, "BUY", "2024-01-10", "1", "200000");
+        insert_trade(&conn, 1, "SELL", "2024-02-15", "0.1", "30000"); // profit 10000, exempt
+        insert_trade(&conn, 1, "SELL", "2024-03-15", "0.2", "30000"); // loss 10000
+        insert_trade(&conn, 1, "SELL", "2024-04-15", "0.3", "90000"); // profit 30000
+
+        let mut carry = HashMap::new();
+        let annual = calculate_annual_tax(&conn, 2024, &mut carry).unwrap();
+        let crypto = |month: usize| {
+            annual[month - 1]
+                .iter()
+                .find(|c| c.category == TaxCategory::Crypto)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(crypto(2).exemption_applied, Decimal::from(10000));
+        assert_eq!(crypto(2).tax_due, Decimal::ZERO);
+        assert_eq!(crypto(3).tax_due, Decimal::ZERO);
+        // GCAP: the March loss does not reduce April's gain
+        assert_eq!(crypto(4).loss_offset_applied, Decimal::ZERO);
+        assert_eq!(crypto(4).tax_due, Decimal::from(4500));
+        assert!(carry.is_empty());
+    }
+
+    #[test]
+    fn test_merger_cash_is_a_sale_of_the_source() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES
+                 (1, 'BTOW3', 'STOCK'), (2, 'AMER3', 'STOCK'), (3, 'LAME3', 'STOCK');
+             INSERT INTO asset_exchanges (event_type, from_asset_id, to_asset_id,
+                 effective_date, to_quantity, allocated_cost, cash_amount, source)
+             VALUES ('MERGER', 1, 3, '2025-05-02', '1000', '15000', '8000', 'MANUAL'),
+                    ('MERGER', 2, 3, '2025-05-02', '500', '10000', '0', 'MANUAL');",
+        )
+        .unwrap();
+        insert_trade(&conn, 1, "BUY", "2024-03-10", "1000", "20000");
+        insert_trade(&conn, 2, "BUY", "2024-03-10", "500", "10000");
+        insert_trade(&conn, 3, "SELL", "2025-06-10", "750", "30000");
+
+        let sales = realized_sales_for_year(&conn, 2025).unwrap();
+        assert_eq!(sales.len(), 2);
+        // The cash pays for the basis BTOW3 did not carry: 20000 - 15000
+        let (_, cash) = &sales[0];
+        assert_eq!(cash.asset_id, 1);
+        assert_eq!(cash.sale_total, Decimal::from(8000));
+        assert_eq!(cash.cost_basis, Decimal::from(5000));
+        assert_eq!(cash.profit_loss, Decimal::from(3000));
+        assert_eq!(cash.transaction_id, None);
+        // LAME3 averages the basis carried from both sources: 25000 / 1500
+        let (_, lame) = &sales[1];
+        assert_eq!(lame.cost_basis, Decimal::from(12500));
+    }
 }
@@ -0,0 +1,214 @@
+//! Regressive IR on Tesouro Direto redemptions.
+//!
+//! Each redemption is matched to purchases first in, first out and every lot
+//! is taxed on its own: IOF first on the yield of lots held under 30 days,
+//! then IR at the regressive rate for the days held (22,5% up to 180 days
+//! down to 15% after 720), on the yield net of IOF and of the custody fee
+//! paid on the lot. Lots with a loss pay nothing and do not offset others.
+//! The tax is withheld by the Tesouro, so this is a check of the amounts on
+//! the extract and the figures for "Rendimentos sujeitos à tributação
+//! exclusiva" in the IRPF.
+
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::rules::fixed_income_rule;
+use crate::db::{self, AssetType};
+use crate::tesouro::{self, LotRedemption};
+
+/// Tax on the part of one purchase that was redeemed
+#[derive(Debug, Clone, Serialize)]
+pub struct LotTax {
+    pub purchase_date: NaiveDate,
+    pub days_held: i64,
+    pub quantity: Decimal,
+    pub cost: Decimal,
+    pub proceeds: Decimal,
+    pub gain: Decimal,
+    pub iof: Decimal,
+    pub custody: Decimal,
+    pub taxable: Decimal,
+    pub ir_rate: Decimal,
+    pub ir: Decimal,
+}
+
+/// Tax on one redemption of a bond
+#[derive(Debug, Clone, Serialize)]
+pub struct RedemptionTax {
+    pub ticker: String,
+    pub date: NaiveDate,
+    pub quantity: Decimal,
+    pub proceeds: Decimal,
+    pub cost: Decimal,
+    pub gain: Decimal,
+    pub iof: Decimal,
+    pub custody: Decimal,
+    pub ir: Decimal,
+    /// Proceeds less IOF and IR
+    pub net: Decimal,
+    /// Yield exempt from IR and IOF (LCI, LCA, CRI and CRA held by individuals)
+    pub exempt: bool,
+    pub lots: Vec<LotTax>,
+}
+
+/// IOF and IR due on a lot redeemed on `date`
+pub fn lot_tax(lot: &LotRedemption, date: NaiveDate) -> LotTax {
+    let rule = fixed_income_rule(date.year(), date.month());
+    let days_held = (date - lot.purchase_date).num_days();
+    let gain = lot.proceeds - lot.cost;
+    let iof = (gain.max(Decimal::ZERO) * rule.iof_rate(days_held)).round_dp(2);
+    let custody = lot.custody.round_dp(2);
+    let taxable = (gain - iof - custody).max(Decimal::ZERO);
+    let ir_rate = rule.ir_rate(days_held);
+    LotTax {
+        purchase_date: lot.purchase_date,
+        days_held,
+        quantity: lot.quantity,
+        cost: lot.cost,
+        proceeds: lot.proceeds,
+        gain,
+        iof,
+        custody,
+        taxable,
+        ir_rate,
+        ir: (taxable * ir_rate).round_dp(2),
+    }
+}
+
+/// Sum the lots of one redemption
+pub fn redemption_tax(
+    ticker: &str,
+    date: NaiveDate,
+    lots: &[LotRedemption],
+    recorded_fees: Decimal,
+) -> RedemptionTax {
+    // Custody charged on the redemption itself replaces the estimate
+    let estimated: Decimal = lots.iter().map(|l| l.custody).sum();
+    let lots: Vec<LotTax> = lots
+        .iter()
+        .map(|lot| {
+            let mut lot = lot.clone();
+            if recorded_fees > Decimal::ZERO && estimated > Decimal::ZERO {
+                lot.custody = recorded_fees * lot.custody / estimated;
+            }
+            lot_tax(&lot, date)
+        })
+        .collect();
+    let sum = |f: fn(&LotTax) -> Decimal| lots.iter().map(f).sum::<Decimal>();
+    let proceeds = sum(|l| l.proceeds);
+    let iof = sum(|l| l.iof);
+    let ir = sum(|l| l.ir);
+    RedemptionTax {
+        ticker: ticker.to_string(),
+        date,
+        quantity: sum(|l| l.quantity),
+        proceeds,
+        cost: sum(|l| l.cost),
+        gain: sum(|l| l.gain),
+        iof,
+        custody: sum(|l| l.custody),
+        ir,
+        net: proceeds - iof - ir,
+        exempt: false,
+        lots,
+    }
+}
+
+/// Tesouro Direto redemptions of `year` with their IOF and IR
+pub fn redemption_taxes(conn: &Connection, year: i32) -> Result<Vec<RedemptionTax>> {
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+    let to = NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+
+    let mut taxes = Vec::new();
+    for asset in db::get_all_assets(conn)? {
+        if asset.asset_type != AssetType::GovBond {
+            continue;
+        }
+        let asset_id = asset.id.expect("asset from database must have id");
+        let history = tesouro::replay_bond(conn, asset_id, &asset.ticker, to)?;
+        for redemption in history.redemptions {
+            if redemption.date < from || redemption.lots.is_empty() {
+                continue;
+            }
+            taxes.push(redemption_tax(
+                &asset.ticker,
+                redemption.date,
+                &redemption.lots,
+                redemption.fees,
+            ));
+        }
+    }
+    taxes.sort_by(|a, b| (a.date, &a.ticker).cmp(&(b.date, &b.ticker)));
+    Ok(taxes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Transaction, TransactionType};
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_redemption_taxed_per_lot_by_days_held() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id =
+            db::insert_asset(&conn, "TESOURO_IPCA_2035", &AssetType::GovBond, None).unwrap();
+        let d = |y, m, day| NaiveDate::from_ymd_opt(y, m, day).unwrap();
+        let tx = |transaction_type, date, quantity, total: Decimal| Transaction {
+            id: None,
+            asset_id,
+            transaction_type,
+            trade_date: date,
+            settlement_date: Some(date),
+            quantity,
+            price_per_unit: total / quantity,
+            total_cost: total,
+            fees: Decimal::ZERO,
+            is_day_trade: false,
+            quota_issuance_date: None,
+            notes: None,
+            source: "TEST".to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        db::insert_transaction(
+            &conn,
+            &tx(TransactionType::Buy, d(2023, 1, 2), dec!(1), dec!(1000)),
+        )
+        .unwrap();
+        db::insert_transaction(
+            &conn,
+            &tx(TransactionType::Buy, d(2024, 9, 2), dec!(1), dec!(1100)),
+        )
+        .unwrap();
+        // Sells the 2023 lot (over 720 days) and half of the 2024 one (under 180)
+        db::insert_transaction(
+            &conn,
+            &tx(TransactionType::Sell, d(2025, 1, 10), dec!(1.5), dec!(1800)),
+        )
+        .unwrap();
+
+        let taxes = redemption_taxes(&conn, 2025).unwrap();
+        assert_eq!(taxes.len(), 1);
+        let lots = &taxes[0].lots;
+        assert_eq!(lots.len(), 2);
+        assert_eq!(lots[0].ir_rate, dec!(0.15));
+        assert_eq!(lots[0].gain, dec!(200));
+        assert_eq!(lots[1].ir_rate, dec!(0.225));
+        assert_eq!(lots[1].gain, dec!(50));
+        assert_eq!(lots[1].iof, Decimal::ZERO);
+        // Custody accrued at cost (no PU stored) lowers the taxable yield
+        assert!(lots[0].custody > dec!(5) && lots[0].custody < dec!(7));
+        assert_eq!(
+            lots[0].ir,
+            ((dec!(200) - lots[0].custody) * dec!(0.15)).round_dp(2)
+        );
+        assert!(redemption_taxes(&conn, 2024).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,416 @@
+//! Annual summary of income tax withheld at source (IRRF).
+//!
+//! Two sources are covered:
+//! - income events, using the withholding recorded on each event (JCP,
+//!   dividends, FII/FIAGRO distributions and amortizations);
+//! - the "dedo-duro" IRRF brokers withhold on sales (0,005% of swing-trade
+//!   sales, 1% of day-trade gains), estimated from the year's sales because
+//!   brokerage notes are not imported.
+//!
+//! Sales IRRF can be deducted from the month's capital gains DARF and carried
+//! to later months of the same year; whatever is left goes to the annual
+//! adjustment. It is simulated here against the monthly tax due.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::db::{self, AssetType, IncomeEventType};
+use crate::tax::irpf::generate_annual_report;
+use crate::tax::rules::withholding_rule;
+use crate::tax::swing_trade::TaxCategory;
+
+/// What can be done with withheld tax
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WithholdingTreatment {
+    /// Final taxation at source, nothing to recover
+    Definitive,
+    /// Can be deducted from tax due (monthly DARF or annual adjustment)
+    Compensable,
+    /// Withheld on exempt income; ask the payer for a refund or correction
+    Recoverable,
+}
+
+impl WithholdingTreatment {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WithholdingTreatment::Definitive => "definitive",
+            WithholdingTreatment::Compensable => "compensable",
+            WithholdingTreatment::Recoverable => "recoverable",
+        }
+    }
+}
+
+/// Withholding of one category for the year
+#[derive(Debug, Clone, Serialize)]
+pub struct WithholdingLine {
+    pub category: &'static str,
+    /// Where the income (or the tax) goes in the IRPF declaration
+    pub declaration: &'static str,
+    pub treatment: WithholdingTreatment,
+    pub gross_amount: Decimal,
+    pub withheld: Decimal,
+    /// True for sales IRRF, which is computed rather than recorded
+    pub estimated: bool,
+    pub events: usize,
+    pub tickers: Vec<String>,
+}
+
+/// Sales IRRF of one month and how much of it the month's DARF absorbs
+#[derive(Debug, Clone, Serialize)]
+pub struct SalesWithholdingMonth {
+    pub month: u32,
+    pub withheld: Decimal,
+    pub tax_due: Decimal,
+    pub deducted: Decimal,
+    /// Balance carried to the next month
+    pub balance: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WithholdingReport {
+    pub year: i32,
+    pub lines: Vec<WithholdingLine>,
+    pub sales_months: Vec<SalesWithholdingMonth>,
+    pub total_withheld: Decimal,
+    /// Withheld on exempt income
+    pub recoverable: Decimal,
+    /// Sales IRRF that can be deducted from the year's DARFs
+    pub deductible_from_darf: Decimal,
+    /// Compensable withholding left for the annual adjustment
+    pub annual_adjustment_credit: Decimal,
+    /// JCP events recorded without IRRF (B3 statements list JCP net)
+    pub jcp_without_withholding: usize,
+}
+
+#[derive(Default)]
+struct Accumulator {
+    gross: Decimal,
+    withheld: Decimal,
+    events: usize,
+    tickers: BTreeSet<String>,
+}
+
+type LineKey = (&'static str, &'static str, WithholdingTreatment);
+
+/// Classify an income event: (category, declaration line, treatment of its IRRF).
+/// `withheld` tells taxed fixed income coupons from exempt ones.
+fn classify_income(
+    event_type: &IncomeEventType,
+    asset_type: AssetType,
+    withheld: bool,
+    year: i32,
+) -> LineKey {
+    let fund = matches!(asset_type, AssetType::Fii | AssetType::Fiagro);
+    match event_type {
+        IncomeEventType::Jcp => (
+            "JCP",
+            "Tributação Exclusiva/Definitiva - 10 Juros sobre capital próprio (valor líquido)",
+            WithholdingTreatment::Definitive,
+        ),
+        IncomeEventType::Interest if withheld => (
+            "Juros de renda fixa",
+            "Tributação Exclusiva/Definitiva - 06 Rendimentos de aplicações financeiras",
+            WithholdingTreatment::Definitive,
+        ),
+        IncomeEventType::Interest => (
+            "Juros de debêntures incentivadas",
+            "Rendimentos Isentos - 26 Outros (debêntures incentivadas, Lei 12.431/2011)",
+            WithholdingTreatment::Recoverable,
+        ),
+        IncomeEventType::Dividend if fund => (
+            "Rendimentos FII/FIAGRO",
+            "Rendimentos Isentos - 26 Outros (rendimentos de FII/FIAGRO)",
+            WithholdingTreatment::Recoverable,
+        ),
+        IncomeEventType::Dividend if withholding_rule(year, 12).dividends_compensable => (
+            "Dividendos",
+            "Rendimentos Isentos - 09 Lucros e dividendos; IRRF em Imposto Pago/Retido",
+            WithholdingTreatment::Compensable,
+        ),
+        IncomeEventType::Dividend => (
+            "Dividendos",
+            "Rendimentos Isentos - 09 Lucros e dividendos recebidos",
+            WithholdingTreatment::Recoverable,
+        ),
+        IncomeEventType::Amortization if fund => (
+            "Amortização FII/FIAGRO",
+            "Bens e Direitos (reduz o custo das cotas); IRRF em Imposto Pago/Retido",
+            WithholdingTreatment::Compensable,
+        ),
+        IncomeEventType::Amortization => (
+            "Amortizações",
+            "Bens e Direitos (reduz o custo do ativo); IRRF em Imposto Pago/Retido",
+            WithholdingTreatment::Compensable,
+        ),
+    }
+}
+
+fn is_day_trade(category: &TaxCategory) -> bool {
+    matches!(
+        category,
+        TaxCategory::StockDayTrade | TaxCategory::FiiDayTrade | TaxCategory::FiagroDayTrade
+    )
+}
+
+/// Estimated sales IRRF for one month: (swing sale value, swing IRRF, day gains, day IRRF)
+fn month_sales_withholding(
+    year: i32,
+    month: u32,
+    by_category: &std::collections::HashMap<TaxCategory, crate::tax::irpf::CategoryMonthSummary>,
+) -> (Decimal, Decimal, Decimal, Decimal) {
+    let rule = withholding_rule(year, month);
+    let mut swing_sales = Decimal::ZERO;
+    let mut day_gains = Decimal::ZERO;
+    for (category, summary) in by_category {
+        // No IRRF outside the B3: FI-Infra is exempt, and trades abroad and
+        // on crypto exchanges are not withheld
+        if matches!(
+            category,
+            TaxCategory::FiInfra | TaxCategory::Foreign | TaxCategory::Crypto
+        ) {
+            continue;
+        }
+        if is_day_trade(category) {
+            day_gains += summary.profit_loss;
+        } else {
+            swing_sales += summary.sales;
+        }
+    }
+    let day_gains = day_gains.max(Decimal::ZERO);
+
+    let retention = |amount: Decimal| {
+        let amount = amount.round_dp(2);
+        if amount > rule.min_retention {
+            amount
+        } else {
+            Decimal::ZERO
+        }
+    };
+
+    (
+        swing_sales,
+        retention(swing_sales * rule.swing_sale_rate),
+        day_gains,
+        retention(day_gains * rule.day_trade_rate),
+    )
+}
+
+/// Build the withholding summary for a calendar year
+pub fn withholding_report(conn: &Connection, year: i32) -> Result<WithholdingReport> {
+    let from = NaiveDate::from_ymd_opt(year, 1, 1)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+    let to = NaiveDate::from_ymd_opt(year, 12, 31)
+        .ok_or_else(|| anyhow::anyhow!("Invalid year: {}", year))?;
+
+    let mut income: BTreeMap<LineKey, Accumulator> = BTreeMap::new();
+    let mut jcp_without_withholding = 0;
+    for (event, asset) in db::get_income_events_with_assets(conn, Some(from), Some(to), None)? {
+        if event.event_type == IncomeEventType::Jcp && event.withholding_tax.is_zero() {
+            jcp_without_withholding += 1;
+        }
+        let key = classify_income(
+            &event.event_type,
+            asset.asset_type,
+            !event.withholding_tax.is_zero(),
+            year,
+        );
+        let acc = income.entry(key).or_default();
+        acc.gross += event.total_amount;
+        acc.withheld += event.withholding_tax;
+        acc.events += 1;
+        acc.tickers.insert(asset.ticker);
+    }
+
+    let mut lines: Vec<WithholdingLine> = income
+        .into_iter()
+        .map(
+            |((category, declaration, treatment), acc)| WithholdingLine {
+                category,
+                declaration,
+                treatment,
+                gross_amount: acc.gross,
+                withheld: acc.withheld,
+                estimated: false,
+                events: acc.events,
+                tickers: acc.tickers.into_iter().collect(),
+            },
+        )
+        .collect();
+
+    // Sales IRRF, deducted month by month from the capital gains tax due
+    let annual = generate_annual_report(conn, year)?;
+    let mut swing = Accumulator::default();
+    let mut day = Accumulator::default();
+    let mut sales_months = Vec::new();
+    let mut balance = Decimal::ZERO;
+    let mut deductible_from_darf = Decimal::ZERO;
+    for summary in &annual.monthly_summaries {
+        let (swing_sales, swing_irrf, day_gains, day_irrf) =
+            month_sales_withholding(year, summary.month, &summary.by_category);
+        swing.gross += swing_sales;
+        swing.withheld += swing_irrf;
+        day.gross += day_gains;
+        day.withheld += day_irrf;
+
+        let withheld = swing_irrf + day_irrf;
+        if withheld.is_zero() && balance.is_zero() {
+            continue;
+        }
+        let available = balance + withheld;
+        let deducted = available.min(summary.tax_due.max(Decimal::ZERO));
+        balance = available - deducted;
+        deductible_from_darf += deducted;
+        sales_months.push(SalesWithholdingMonth {
+            month: summary.month,
+            withheld,
+            tax_due: summary.tax_due,
+            deducted,
+            balance,
+        });
+    }
+
+    let sales_declaration =
+        "Renda Variável - IRRF (Lei 11.033/2004) no mês; saldo em Imposto Pago/Retido";
+    if !swing.withheld.is_zero() {
+        lines.push(WithholdingLine {
+            category: "IRRF vendas (0,005%)",
+            declaration: sales_declaration,
+            treatment: WithholdingTreatment::Compensable,
+            gross_amount: swing.gross,
+            withheld: swing.withheld,
+            estimated: true,
+            events: 0,
+            tickers: Vec::new(),
+        });
+    }
+    if !day.withheld.is_zero() {
+        lines.push(WithholdingLine {
+            category: "IRRF day trade (1%)",
+            declaration: sales_declaration,
+            treatment: WithholdingTreatment::Compensable,
+            gross_amount: day.gross,
+            withheld: day.withheld,
+            estimated: true,
+            events: 0,
+            tickers: Vec::new(),
+        });
+    }
+
+    let total_withheld = lines.iter().map(|l| l.withheld).sum();
+    let recoverable = lines
+        .iter()
+        .filter(|l| l.treatment == WithholdingTreatment::Recoverable)
+        .map(|l| l.withheld)
+        .sum();
+    let income_compensable: Decimal = lines
+        .iter()
+        .filter(|l| l.treatment == WithholdingTreatment::Compensable && !l.estimated)
+        .map(|l| l.withheld)
+        .sum();
+
+    Ok(WithholdingReport {
+        year,
+        lines,
+        sales_months,
+        total_withheld,
+        recoverable,
+        deductible_from_darf,
+        annual_adjustment_credit: income_compensable + balance,
+        jcp_without_withholding,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (ticker, asset_type) VALUES ('ITSA4', 'STOCK');
+             INSERT INTO assets (ticker, asset_type) VALUES ('HGLG11', 'FII');
+             INSERT INTO income_events (asset_id, event_date, event_type, amount_per_quota,
+                 total_amount, withholding_tax, source)
+                 VALUES (1, '2025-03-10', 'JCP', '0.1', '100', '15', 'MANUAL'),
+                        (1, '2025-06-10', 'JCP', '0.1', '85', '0', 'MOVIMENTACAO'),
+                        (1, '2025-07-10', 'DIVIDEND', '0.1', '50', '0', 'MANUAL'),
+                        (2, '2025-04-15', 'DIVIDEND', '1', '100', '20', 'MANUAL'),
+                        (2, '2025-05-15', 'AMORTIZATION', '1', '200', '10', 'MANUAL');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, is_day_trade, source)
+                 VALUES (1, 'BUY', '2025-01-10', '10000', '10', '100000', '0', 0, 'TEST'),
+                        (1, 'SELL', '2025-02-10', '5000', '12', '60000', '0', 0, 'TEST'),
+                        (1, 'SELL', '2025-03-10', '1000', '9', '9000', '0', 0, 'TEST');",
+        )
+        .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_withholding_report_classifies_income_and_sales() {
+        let conn = setup();
+        let report = withholding_report(&conn, 2025).unwrap();
+
+        let line = |category: &str| {
+            report
+                .lines
+                .iter()
+                .find(|l| l.category == category)
+                .unwrap_or_else(|| panic!("missing {}", category))
+        };
+
+        let jcp = line("JCP");
+        assert_eq!(jcp.treatment, WithholdingTreatment::Definitive);
+        assert_eq!(jcp.withheld, Decimal::from(15));
+        assert_eq!(jcp.events, 2);
+        assert_eq!(report.jcp_without_withholding, 1);
+
+        assert_eq!(line("Dividendos").withheld, Decimal::ZERO);
+        let fii = line("Rendimentos FII/FIAGRO");
+        assert_eq!(fii.treatment, WithholdingTreatment::Recoverable);
+        assert_eq!(report.recoverable, Decimal::from(20));
+        assert_eq!(
+            line("Amortização FII/FIAGRO").treatment,
+            WithholdingTreatment::Compensable
+        );
+
+        // Feb: 60k sold -> R$3,00 withheld, absorbed by the R$1.500 DARF.
+        // Mar: 9k sold -> R$0,45, under the R$1 minimum, nothing withheld.
+        let sales = line("IRRF vendas (0,005%)");
+        assert!(sales.estimated);
+        assert_eq!(sales.withheld, Decimal::from(3));
+        assert_eq!(report.sales_months.len(), 1);
+        assert_eq!(report.sales_months[0].month, 2);
+        assert_eq!(report.deductible_from_darf, Decimal::from(3));
+
+        // Amortization IRRF is left for the annual adjustment
+        assert_eq!(report.annual_adjustment_credit, Decimal::from(10));
+        assert_eq!(report.total_withheld, Decimal::from(48));
+    }
+
+    #[test]
+    fn test_dividend_withholding_compensable_from_2026() {
+        let dividend =
+            |year| classify_income(&IncomeEventType::Dividend, AssetType::Stock, true, year);
+        assert_eq!(dividend(2025).2, WithholdingTreatment::Recoverable);
+        assert_eq!(dividend(2026).2, WithholdingTreatment::Compensable);
+    }
+
+    #[test]
+    fn test_bond_interest_line_follows_withholding() {
+        let interest =
+            |withheld| classify_income(&IncomeEventType::Interest, AssetType::Bond, withheld, 2024);
+        assert_eq!(interest(true).2, WithholdingTreatment::Definitive);
+        assert!(interest(true)
+            .1
+            .contains("06 Rendimentos de aplicações financeiras"));
+        assert!(interest(false).1.contains("Lei 12.431/2011"));
+    }
+}
@@ -9,8 +9,11 @@
 
 use anyhow::Result;
 use chrono::NaiveDate;
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
 use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
 use std::str::FromStr;
 use tracing::{info, warn};
 
@@ -236,6 +239,191 @@ pub fn process_term_liquidations(conn: &Connection) -> Result<usize> {
     Ok(processed)
 }
 
+/// A term purchase (TICKERT) still waiting for liquidation
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenTermContract {
+    /// The TICKERT buy, used as the key for `terms set`
+    pub transaction_id: i64,
+    pub ticker: String,
+    pub trade_date: NaiveDate,
+    /// Quantity not yet liquidated or sold
+    pub quantity: Decimal,
+    pub price: Decimal,
+    /// Amount owed at expiry (open quantity × term price)
+    pub notional: Decimal,
+    pub expiry_date: Option<NaiveDate>,
+    /// Annual rate (%), as recorded or implied from the spot price
+    pub contracted_rate: Option<Decimal>,
+    /// True when the rate was derived from the spot close on the trade date
+    pub rate_implied: bool,
+}
+
+/// Open term contracts, oldest first
+///
+/// Liquidations (base ticker buys noted "Term contract liquidation") and
+/// TICKERT sales close purchases in date order.
+pub fn open_term_contracts(conn: &Connection) -> Result<Vec<OpenTermContract>> {
+    let scope = crate::db::portfolio::scope_filter("t.portfolio_id");
+
+    let mut closed: HashMap<String, Decimal> = HashMap::new();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT a.ticker, t.transaction_type, t.quantity, t.notes
+         FROM transactions t
+         JOIN assets a ON t.asset_id = a.id
+         WHERE ((t.transaction_type = 'BUY' AND t.notes LIKE '%Term contract liquidation%')
+             OR (t.transaction_type = 'SELL' AND a.ticker LIKE '%T')){}",
+        scope
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            get_decimal_value(row, 2)?,
+        ))
+    })?;
+    for row in rows {
+        let (ticker, tx_type, quantity) = row?;
+        let term_ticker = if tx_type == "BUY" {
+            get_term_ticker(&ticker)
+        } else if is_term_contract(&ticker) {
+            ticker
+        } else {
+            continue;
+        };
+        *closed.entry(term_ticker).or_default() += quantity;
+    }
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.id, a.ticker, t.trade_date, t.quantity, t.price_per_unit,
+                d.expiry_date, d.contracted_rate
+         FROM transactions t
+         JOIN assets a ON t.asset_id = a.id
+         LEFT JOIN term_contract_details d ON d.transaction_id = t.id
+         WHERE t.transaction_type = 'BUY' AND a.ticker LIKE '%T'{}
+         ORDER BY t.trade_date, t.id",
+        scope
+    ))?;
+    let purchases = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, NaiveDate>(2)?,
+                get_decimal_value(row, 3)?,
+                get_decimal_value(row, 4)?,
+                row.get::<_, Option<NaiveDate>>(5)?,
+                crate::db::get_optional_decimal_value(row, 6)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut open = Vec::new();
+    for (id, ticker, trade_date, quantity, price, expiry_date, rate) in purchases {
+        if !is_term_contract(&ticker) {
+            continue;
+        }
+        let already_closed = closed.entry(ticker.clone()).or_default();
+        let consumed = quantity.min(*already_closed);
+        *already_closed -= consumed;
+        let quantity = quantity - consumed;
+        if quantity <= Decimal::ZERO {
+            continue;
+        }
+
+        let (contracted_rate, rate_implied) = match rate {
+            Some(rate) => (Some(rate), false),
+            None => {
+                let implied = expiry_date.and_then(|expiry| {
+                    spot_close(conn, &get_base_ticker(&ticker), trade_date)
+                        .ok()
+                        .flatten()
+                        .and_then(|spot| implied_annual_rate(price, spot, trade_date, expiry))
+                });
+                (implied, implied.is_some())
+            }
+        };
+
+        open.push(OpenTermContract {
+            transaction_id: id,
+            ticker,
+            trade_date,
+            quantity,
+            price,
+            notional: quantity * price,
+            expiry_date,
+            contracted_rate,
+            rate_implied,
+        });
+    }
+    Ok(open)
+}
+
+/// Record the expiry and/or contracted annual rate (%) of a term purchase
+pub fn set_term_details(
+    conn: &Connection,
+    transaction_id: i64,
+    expiry_date: Option<NaiveDate>,
+    contracted_rate: Option<Decimal>,
+) -> Result<()> {
+    let ticker: Option<String> = conn
+        .query_row(
+            "SELECT a.ticker FROM transactions t JOIN assets a ON t.asset_id = a.id
+             WHERE t.id = ?1 AND t.transaction_type = 'BUY'",
+            [transaction_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match ticker {
+        Some(ticker) if is_term_contract(&ticker) => {}
+        Some(ticker) => anyhow::bail!(
+            "Transaction {} ({}) is not a term contract purchase",
+            transaction_id,
+            ticker
+        ),
+        None => anyhow::bail!("Buy transaction {} not found", transaction_id),
+    }
+
+    conn.execute(
+        "INSERT INTO term_contract_details (transaction_id, expiry_date, contracted_rate)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT(transaction_id) DO UPDATE SET
+             expiry_date = COALESCE(excluded.expiry_date, expiry_date),
+             contracted_rate = COALESCE(excluded.contracted_rate, contracted_rate)",
+        rusqlite::params![
+            transaction_id,
+            expiry_date,
+            contracted_rate.map(|r| r.to_string())
+        ],
+    )?;
+    Ok(())
+}
+
+fn spot_close(conn: &Connection, ticker: &str, date: NaiveDate) -> Result<Option<Decimal>> {
+    let Some(asset) = crate::db::get_asset_by_ticker(conn, ticker)? else {
+        return Ok(None);
+    };
+    let Some(asset_id) = asset.id else {
+        return Ok(None);
+    };
+    Ok(crate::db::get_price_on_or_before(conn, asset_id, date)?.map(|p| p.close_price))
+}
+
+/// Annualized rate (%) embedded in a term price over the spot price
+fn implied_annual_rate(
+    term_price: Decimal,
+    spot: Decimal,
+    trade_date: NaiveDate,
+    expiry: NaiveDate,
+) -> Option<Decimal> {
+    let days = (expiry - trade_date).num_days();
+    if days <= 0 || spot <= Decimal::ZERO {
+        return None;
+    }
+    let period = (term_price / spot).to_f64()?;
+    let annual = period.powf(365.0 / days as f64) - 1.0;
+    Decimal::from_f64(annual * 100.0).map(|r| r.round_dp(2))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -264,4 +452,35 @@ mod tests {
         assert_eq!(get_term_ticker("ANIM3"), "ANIM3T");
         assert_eq!(get_term_ticker("PETR4"), "PETR4T");
     }
+
+    #[test]
+    fn test_open_term_contracts_after_partial_liquidation() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("db/schema.sql")).unwrap();
+        conn.execute_batch(
+            "INSERT INTO assets (id, ticker, asset_type) VALUES (1, 'ANIM3T', 'TERM'), (2, 'ANIM3', 'STOCK');
+             INSERT INTO transactions (id, asset_id, transaction_type, trade_date, quantity, price_per_unit, total_cost)
+             VALUES (10, 1, 'BUY', '2024-01-10', '100', '10.20', '1020'),
+                    (11, 1, 'BUY', '2024-02-10', '50', '11.00', '550');
+             INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity, price_per_unit, total_cost, notes)
+             VALUES (2, 'BUY', '2024-03-01', '120', '10.20', '1224',
+                     'Term contract liquidation (original ticker: ANIM3T → ANIM3)');
+             INSERT INTO price_history (asset_id, price_date, close_price, source) VALUES (2, '2024-02-09', '10.00', 'COTAHIST');",
+        )
+        .unwrap();
+        let expiry = NaiveDate::from_ymd_opt(2025, 2, 9).unwrap();
+        set_term_details(&conn, 11, Some(expiry), None).unwrap();
+
+        let open = open_term_contracts(&conn).unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].transaction_id, 11);
+        assert_eq!(open[0].quantity, Decimal::from(30));
+        assert_eq!(open[0].notional, Decimal::from(330));
+        assert_eq!(open[0].expiry_date, Some(expiry));
+        // 11.00 over a 10.00 spot for 365 days
+        assert_eq!(open[0].contracted_rate, Some(Decimal::from(10)));
+        assert!(open[0].rate_implied);
+
+        assert!(set_term_details(&conn, 12, Some(expiry), None).is_err());
+    }
 }
@@ -1,8 +1,12 @@
 use anyhow::{anyhow, Result};
 use chrono::{Datelike, NaiveDate};
+use rusqlite::Connection;
 use rust_decimal::Decimal;
+use serde::Serialize;
 use std::str::FromStr;
 
+use crate::db::{self, TransactionType};
+
 const TESOURO_PREFIX: &str = "TESOURO";
 
 pub fn ticker_from_name(name: &str) -> Option<String> {
@@ -120,6 +124,314 @@ fn normalize_year_token(token: &str) -> Option<&str> {
     }
 }
 
+/// B3 custody fee on Tesouro Direto balances from `since` on
+#[derive(Debug)]
+pub struct CustodyRule {
+    /// First (year, month) the rule applies to
+    pub since: (i32, u32),
+    pub annual_rate: Decimal,
+    /// Tesouro Selic balance exempt from the fee
+    pub selic_exempt_up_to: Decimal,
+}
+
+static CUSTODY_RULES: &[CustodyRule] = &[
+    CustodyRule {
+        since: (2002, 1),
+        annual_rate: Decimal::from_parts(30, 0, 0, false, 4),
+        selic_exempt_up_to: Decimal::ZERO,
+    },
+    CustodyRule {
+        since: (2020, 1),
+        annual_rate: Decimal::from_parts(25, 0, 0, false, 4),
+        selic_exempt_up_to: Decimal::from_parts(10000, 0, 0, false, 0),
+    },
+    CustodyRule {
+        since: (2025, 1),
+        annual_rate: Decimal::from_parts(20, 0, 0, false, 4),
+        selic_exempt_up_to: Decimal::from_parts(10000, 0, 0, false, 0),
+    },
+];
+
+/// Custody rule in force in (year, month)
+pub fn custody_rule(year: i32, month: u32) -> &'static CustodyRule {
+    CUSTODY_RULES
+        .iter()
+        .rev()
+        .find(|r| r.since <= (year, month))
+        .unwrap_or(&CUSTODY_RULES[0])
+}
+
+pub fn is_selic(ticker: &str) -> bool {
+    ticker.starts_with("TESOURO_SELIC")
+}
+
+/// A purchase still held, with the custody fee accrued on it so far
+#[derive(Debug, Clone, Serialize)]
+pub struct BondLot {
+    pub purchase_date: NaiveDate,
+    pub quantity: Decimal,
+    pub unit_cost: Decimal,
+    pub custody: Decimal,
+}
+
+/// The part of a purchase consumed by a redemption
+#[derive(Debug, Clone, Serialize)]
+pub struct LotRedemption {
+    pub purchase_date: NaiveDate,
+    pub quantity: Decimal,
+    pub cost: Decimal,
+    pub proceeds: Decimal,
+    pub custody: Decimal,
+}
+
+/// A sale, early redemption or maturity of a bond
+#[derive(Debug, Clone, Serialize)]
+pub struct BondRedemption {
+    pub transaction_id: Option<i64>,
+    pub date: NaiveDate,
+    pub quantity: Decimal,
+    pub proceeds: Decimal,
+    /// Fees recorded on the transaction (custody charged on the redemption)
+    pub fees: Decimal,
+    pub lots: Vec<LotRedemption>,
+}
+
+/// Open lots and past redemptions of one bond
+#[derive(Debug, Clone, Default)]
+pub struct BondHistory {
+    pub lots: Vec<BondLot>,
+    pub redemptions: Vec<BondRedemption>,
+}
+
+/// Replay a bond's transactions up to `until`, matching redemptions to
+/// purchases first in, first out (as Tesouro Direto does for IR) and
+/// accruing the custody fee month by month on the marked balance.
+///
+/// The balance is priced at the stored PU of each period end, or at cost
+/// before any PU is known. The Tesouro Selic exemption is applied to the
+/// balance of the bond alone.
+pub fn replay_bond(
+    conn: &Connection,
+    asset_id: i64,
+    ticker: &str,
+    until: NaiveDate,
+) -> Result<BondHistory> {
+    replay(conn, asset_id, Some(is_selic(ticker)), until)
+}
+
+/// Replay a bond's transactions up to `until` first in, first out, without
+/// custody (private bonds are held at the issuer's or broker's expense)
+pub fn replay_lots(conn: &Connection, asset_id: i64, until: NaiveDate) -> Result<BondHistory> {
+    replay(conn, asset_id, None, until)
+}
+
+/// `custody` says whether to accrue the B3 fee and, if so, whether the bond
+/// is a Tesouro Selic
+fn replay(
+    conn: &Connection,
+    asset_id: i64,
+    custody: Option<bool>,
+    until: NaiveDate,
+) -> Result<BondHistory> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id FROM transactions WHERE asset_id = ?1 AND trade_date <= ?2{}
+         ORDER BY trade_date, id",
+        db::portfolio::scope_filter("portfolio_id")
+    ))?;
+    let ids = stmt
+        .query_map(rusqlite::params![asset_id, until], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+
+    let mut history = BondHistory::default();
+    let mut accrued_to: Option<NaiveDate> = None;
+    for id in ids {
+        let Some(tx) = db::get_transaction(conn, id)? else {
+            continue;
+        };
+        if let (Some(selic), Some(from)) = (custody, accrued_to) {
+            accrue_custody(
+                conn,
+                asset_id,
+                selic,
+                &mut history.lots,
+                from,
+                tx.trade_date,
+            )?;
+        }
+        accrued_to = Some(tx.trade_date);
+
+        match tx.transaction_type {
+            TransactionType::Buy if tx.quantity > Decimal::ZERO => {
+                history.lots.push(BondLot {
+                    purchase_date: tx.trade_date,
+                    quantity: tx.quantity,
+                    unit_cost: tx.total_cost / tx.quantity,
+                    custody: Decimal::ZERO,
+                });
+            }
+            TransactionType::Sell if tx.quantity > Decimal::ZERO => {
+                let proceeds = tx.total_cost.abs();
+                let mut remaining = tx.quantity;
+                let mut lots = Vec::new();
+                for lot in history.lots.iter_mut() {
+                    if remaining <= Decimal::ZERO {
+                        break;
+                    }
+                    let quantity = remaining.min(lot.quantity);
+                    let custody = lot.custody * quantity / lot.quantity;
+                    lot.custody -= custody;
+                    lot.quantity -= quantity;
+                    remaining -= quantity;
+                    lots.push(LotRedemption {
+                        purchase_date: lot.purchase_date,
+                        quantity,
+                        cost: lot.unit_cost * quantity,
+                        proceeds: proceeds * quantity / tx.quantity,
+                        custody,
+                    });
+                }
+                history.lots.retain(|lot| lot.quantity > Decimal::ZERO);
+                history.redemptions.push(BondRedemption {
+                    transaction_id: tx.id,
+                    date: tx.trade_date,
+                    quantity: tx.quantity - remaining,
+                    proceeds: lots.iter().map(|l| l.proceeds).sum(),
+                    fees: tx.fees,
+                    lots,
+                });
+            }
+            _ => {}
+        }
+    }
+    if let (Some(selic), Some(from)) = (custody, accrued_to) {
+        accrue_custody(conn, asset_id, selic, &mut history.lots, from, until)?;
+    }
+    Ok(history)
+}
+
+/// Add the custody fee for [from, to) to the open lots, pro rata by quantity
+fn accrue_custody(
+    conn: &Connection,
+    asset_id: i64,
+    selic: bool,
+    lots: &mut [BondLot],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<()> {
+    let held: Decimal = lots.iter().map(|l| l.quantity).sum();
+    if held <= Decimal::ZERO {
+        return Ok(());
+    }
+    let cost: Decimal = lots.iter().map(|l| l.unit_cost * l.quantity).sum();
+
+    let mut start = from;
+    while start < to {
+        let next_month = NaiveDate::from_ymd_opt(start.year(), start.month(), 1)
+            .and_then(|d| d.checked_add_months(chrono::Months::new(1)))
+            .unwrap_or(to);
+        let end = next_month.min(to);
+        let last_day = end.pred_opt().unwrap_or(end);
+        let balance = match db::get_price_on_or_before(conn, asset_id, last_day)? {
+            Some(pu) => pu.close_price * held,
+            None => cost,
+        };
+        let rule = custody_rule(start.year(), start.month());
+        let taxable = if selic {
+            (balance - rule.selic_exempt_up_to).max(Decimal::ZERO)
+        } else {
+            balance
+        };
+        let days = Decimal::from((end - start).num_days());
+        let fee = taxable * rule.annual_rate * days / Decimal::from(365);
+        for lot in lots.iter_mut() {
+            lot.custody += fee * lot.quantity / held;
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+/// A bond held, marked at the latest PU
+#[derive(Debug, Clone, Serialize)]
+pub struct BondPosition {
+    pub ticker: String,
+    pub quantity: Decimal,
+    pub cost: Decimal,
+    /// Latest PU (sell price) and its date
+    pub pu: Option<(NaiveDate, Decimal)>,
+    /// Latest sell rate, in percent a year
+    pub sell_rate: Option<Decimal>,
+    pub market_value: Option<Decimal>,
+    /// Custody fee accrued on the lots held since their purchase
+    pub custody: Decimal,
+    /// IOF and IR if every lot were redeemed at the PU on `as_of`
+    pub iof: Decimal,
+    pub ir: Decimal,
+    pub net_value: Option<Decimal>,
+}
+
+/// Tesouro Direto bonds held on `as_of`
+pub fn open_positions(conn: &Connection, as_of: NaiveDate) -> Result<Vec<BondPosition>> {
+    let mut positions = Vec::new();
+    for asset in db::get_all_assets(conn)? {
+        if asset.asset_type != db::AssetType::GovBond {
+            continue;
+        }
+        let asset_id = asset.id.expect("asset from database must have id");
+        let history = replay_bond(conn, asset_id, &asset.ticker, as_of)?;
+        if history.lots.is_empty() {
+            continue;
+        }
+
+        let quantity: Decimal = history.lots.iter().map(|l| l.quantity).sum();
+        let cost: Decimal = history.lots.iter().map(|l| l.unit_cost * l.quantity).sum();
+        let pu = db::get_price_on_or_before(conn, asset_id, as_of)?
+            .map(|p| (p.price_date, p.close_price));
+        let sell_rate =
+            db::get_gov_bond_rate_on_or_before(conn, asset_id, as_of)?.map(|r| r.sell_rate);
+
+        let (iof, ir) = match pu {
+            Some((_, price)) => {
+                let lots: Vec<LotRedemption> = history
+                    .lots
+                    .iter()
+                    .map(|lot| LotRedemption {
+                        purchase_date: lot.purchase_date,
+                        quantity: lot.quantity,
+                        cost: lot.unit_cost * lot.quantity,
+                        proceeds: price * lot.quantity,
+                        custody: lot.custody,
+                    })
+                    .collect();
+                let tax =
+                    crate::tax::tesouro::redemption_tax(&asset.ticker, as_of, &lots, Decimal::ZERO);
+                (tax.iof, tax.ir)
+            }
+            None => (Decimal::ZERO, Decimal::ZERO),
+        };
+        let market_value = pu.map(|(_, price)| (price * quantity).round_dp(2));
+
+        positions.push(BondPosition {
+            ticker: asset.ticker,
+            quantity,
+            cost: cost.round_dp(2),
+            pu,
+            sell_rate,
+            market_value,
+            custody: history
+                .lots
+                .iter()
+                .map(|l| l.custody)
+                .sum::<Decimal>()
+                .round_dp(2),
+            iof,
+            ir,
+            net_value: market_value.map(|value| value - iof - ir),
+        });
+    }
+    Ok(positions)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
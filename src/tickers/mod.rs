@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Duration, Local, Utc};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
 use encoding_rs::ISO_8859_15;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -11,11 +11,13 @@ use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 
 use crate::db::AssetType;
 pub(crate) mod ambima;
+pub mod renames;
 
 const B3_REQUEST_BASE_URL: &str = "https://arquivos.b3.com.br/api/download/requestname?fileName=InstrumentsConsolidatedFile&date=";
 const B3_API_BASE_URL: &str = "https://arquivos.b3.com.br/api";
 const CACHE_FILENAME: &str = "tickers.csv";
 const META_FILENAME: &str = "tickers.meta.json";
+const VERSIONS_DIR: &str = "versions";
 const CACHE_MAX_AGE_HOURS: i64 = 24;
 
 #[derive(Debug, Clone)]
@@ -24,6 +26,8 @@ pub struct TickerRecord {
     pub security_category: String,
     pub cfi_code: Option<String>,
     pub corporate_name: Option<String>,
+    /// Kept across a ticker change, which is how renames are told apart
+    pub isin: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -51,7 +55,8 @@ struct CachedMap {
     mtime: SystemTime,
 }
 
-static TICKERS_CACHE: OnceLock<Mutex<Option<CachedMap>>> = OnceLock::new();
+/// Parsed ticker files, keyed by path (the latest file plus any dated versions)
+static TICKERS_CACHE: OnceLock<Mutex<HashMap<PathBuf, CachedMap>>> = OnceLock::new();
 
 pub fn get_tickers_cache_dir() -> Result<PathBuf> {
     let cache_dir = std::env::var_os("XDG_CACHE_HOME")
@@ -91,11 +96,12 @@ pub fn refresh_b3_tickers(force: bool) -> Result<PathBuf> {
                 fs::write(&meta_path, serde_json::to_vec_pretty(&meta)?)
                     .context("Failed to write tickers metadata")?;
 
-                {
-                    let mut guard = cache_guard();
-                    *guard = None;
+                if let Err(err) = archive_version(&cache_dir, date, &bytes, false) {
+                    tracing::warn!("Failed to keep dated tickers version: {}", err);
                 }
 
+                cache_guard().remove(&csv_path);
+
                 return Ok(csv_path);
             }
             Err(err) => {
@@ -139,13 +145,153 @@ pub fn read_cache_meta(cache_dir: Option<&Path>) -> Result<Option<TickersMetaInf
     }))
 }
 
-pub fn load_b3_tickers_map(cache_dir: Option<&Path>) -> Result<HashMap<String, TickerRecord>> {
+/// Download the instruments file published on (or just before) `date` and keep
+/// it as a dated version, without touching the latest snapshot.
+pub fn fetch_b3_tickers_version(date: NaiveDate) -> Result<(NaiveDate, PathBuf)> {
+    let cache_dir = get_tickers_cache_dir()?;
+    let max_retries = 5;
+
+    let mut last_err = None;
+    for attempt in 0..max_retries {
+        let day = date - chrono::Duration::days(attempt);
+        match download_b3_tickers(day) {
+            Ok((bytes, _)) => {
+                let path = archive_version(&cache_dir, day, &bytes, true)?
+                    .expect("forced archive always writes");
+                return Ok((day, path));
+            }
+            Err(err) => {
+                tracing::debug!("B3 tickers not available for {}: {}", day, err);
+                last_err = Some(err);
+            }
+        }
+    }
+
+    Err(last_err
+        .unwrap_or_else(|| anyhow::anyhow!("no download attempted"))
+        .context(format!(
+            "No B3 instruments file found for the 5 days up to {}",
+            date
+        )))
+}
+
+/// Dates of the ticker file versions kept in the cache, oldest first
+pub fn list_versions(cache_dir: Option<&Path>) -> Result<Vec<NaiveDate>> {
     let cache_dir = match cache_dir {
         Some(path) => path.to_path_buf(),
         None => get_tickers_cache_dir()?,
     };
-    let csv_path = cache_dir.join(CACHE_FILENAME);
-    let bytes = fs::read(&csv_path).context("Failed to read cached tickers CSV")?;
+    let dir = cache_dir.join(VERSIONS_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut dates = Vec::new();
+    for entry in fs::read_dir(&dir).context("Failed to read tickers versions directory")? {
+        let name = entry?.file_name();
+        let Some(date) = name
+            .to_str()
+            .and_then(|n| n.strip_prefix("tickers-"))
+            .and_then(|n| n.strip_suffix(".csv"))
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        else {
+            continue;
+        };
+        dates.push(date);
+    }
+    dates.sort();
+    Ok(dates)
+}
+
+fn version_path(cache_dir: &Path, date: NaiveDate) -> PathBuf {
+    cache_dir
+        .join(VERSIONS_DIR)
+        .join(format!("tickers-{}.csv", date.format("%Y-%m-%d")))
+}
+
+/// Keep `bytes` as the version for `date`. Unless forced, at most one version
+/// is kept per month, which is enough to follow listings and renames.
+fn archive_version(
+    cache_dir: &Path,
+    date: NaiveDate,
+    bytes: &[u8],
+    force: bool,
+) -> Result<Option<PathBuf>> {
+    if !force {
+        let same_month = list_versions(Some(cache_dir))?
+            .into_iter()
+            .any(|d| d.year() == date.year() && d.month() == date.month());
+        if same_month {
+            return Ok(None);
+        }
+    }
+
+    let path = version_path(cache_dir, date);
+    fs::create_dir_all(cache_dir.join(VERSIONS_DIR))
+        .context("Failed to create tickers versions directory")?;
+    fs::write(&path, bytes).context("Failed to write tickers version")?;
+    cache_guard().remove(&path);
+    Ok(Some(path))
+}
+
+/// The version that was current on `date`: the latest one on or before it,
+/// else the oldest one kept (the closest to that era).
+pub fn version_for_date(cache_dir: &Path, date: NaiveDate) -> Result<Option<(NaiveDate, PathBuf)>> {
+    let versions = list_versions(Some(cache_dir))?;
+    let chosen = versions
+        .iter()
+        .rev()
+        .find(|d| **d <= date)
+        .or_else(|| versions.first())
+        .copied();
+    Ok(chosen.map(|d| (d, version_path(cache_dir, d))))
+}
+
+/// Look a ticker up in the instruments file of its era
+pub fn lookup_record_as_of(
+    ticker: &str,
+    name: Option<&str>,
+    date: NaiveDate,
+) -> Result<Option<TickerRecord>> {
+    let cache_dir = get_tickers_cache_dir()?;
+    let Some((_, path)) = version_for_date(&cache_dir, date)? else {
+        return Ok(None);
+    };
+    let map = get_cached_map_at(&path)?;
+    let normalized = ticker.trim().to_ascii_uppercase();
+    Ok(map
+        .get(&normalized)
+        .or_else(|| find_record_by_name(&map, &normalized, name))
+        .or_else(|| find_record_by_prefix(&map, &normalized))
+        .cloned())
+}
+
+/// Classify a ticker as of `date` (usually its trade date), falling back to
+/// today's list and the other sources when the era's file does not know it.
+pub fn resolve_asset_type_as_of(
+    conn: &rusqlite::Connection,
+    ticker: &str,
+    name: Option<&str>,
+    date: NaiveDate,
+) -> Result<Option<AssetType>> {
+    // The B3 list files option series under their underlying's name
+    if crate::options::is_option_ticker(ticker) {
+        return Ok(Some(AssetType::Option));
+    }
+    match lookup_record_as_of(ticker, name, date) {
+        Ok(Some(record)) => {
+            if let Some(asset_type) = map_record_to_asset_type(&record) {
+                return Ok(Some(asset_type));
+            }
+        }
+        Ok(None) => {}
+        Err(err) => tracing::warn!("Point-in-time ticker lookup failed for {}: {}", ticker, err),
+    }
+    resolve_asset_type_with_name(conn, ticker, name)
+}
+
+fn load_b3_tickers_file(csv_path: &Path) -> Result<HashMap<String, TickerRecord>> {
+    let bytes = fs::read(csv_path).context("Failed to read cached tickers CSV")?;
 
     let (decoded, _, _) = ISO_8859_15.decode(&bytes);
     let content = decoded.into_owned();
@@ -175,6 +321,7 @@ pub fn load_b3_tickers_map(cache_dir: Option<&Path>) -> Result<HashMap<String, T
             .to_string();
         let cfi_code = get_field(&record, &headers, "CFICd");
         let corporate_name = get_field(&record, &headers, "CrpnNm");
+        let isin = get_field(&record, &headers, "ISIN").trim();
 
         map.insert(
             ticker.clone(),
@@ -191,6 +338,7 @@ pub fn load_b3_tickers_map(cache_dir: Option<&Path>) -> Result<HashMap<String, T
                 } else {
                     Some(corporate_name.to_string())
                 },
+                isin: (!isin.is_empty()).then(|| isin.to_ascii_uppercase()),
             },
         );
     }
@@ -198,7 +346,14 @@ pub fn load_b3_tickers_map(cache_dir: Option<&Path>) -> Result<HashMap<String, T
     Ok(map)
 }
 
-pub fn resolve_asset_type_with_name(ticker: &str, name: Option<&str>) -> Result<Option<AssetType>> {
+/// Classify a ticker from the B3 list, the Mais Retorno registry in `conn`
+/// and Ambima. Imports call this inside their write transaction, so the
+/// registry is read and refreshed through the caller's connection.
+pub fn resolve_asset_type_with_name(
+    conn: &rusqlite::Connection,
+    ticker: &str,
+    name: Option<&str>,
+) -> Result<Option<AssetType>> {
     let normalized = ticker.trim().to_ascii_uppercase();
     if normalized.starts_with("TESOURO_") {
         return Ok(Some(AssetType::GovBond));
@@ -208,6 +363,10 @@ pub fn resolve_asset_type_with_name(ticker: &str, name: Option<&str>) -> Result<
         return Ok(Some(AssetType::TermContract));
     }
 
+    if crate::options::is_option_ticker(&normalized) {
+        return Ok(Some(AssetType::Option));
+    }
+
     let lookup_ticker = normalized.clone();
 
     let cache_dir = get_tickers_cache_dir()?;
@@ -236,7 +395,7 @@ pub fn resolve_asset_type_with_name(ticker: &str, name: Option<&str>) -> Result<
         }
     }
 
-    if let Some(asset_type) = registry_asset_type_lookup(&normalized)? {
+    if let Some(asset_type) = registry_asset_type_lookup(conn, &normalized)? {
         return Ok(Some(asset_type));
     }
 
@@ -264,24 +423,19 @@ pub fn resolve_asset_type_with_name(ticker: &str, name: Option<&str>) -> Result<
     Ok(None)
 }
 
-fn registry_asset_type_lookup(ticker: &str) -> Result<Option<AssetType>> {
-    let conn = match crate::db::open_db(None) {
-        Ok(conn) => conn,
-        Err(err) => {
-            tracing::warn!("Registry lookup failed to open DB: {}", err);
-            return Ok(None);
-        }
-    };
-
-    if let Some(entry) = crate::db::get_asset_registry_by_ticker(&conn, "MAIS_RETORNO", ticker)? {
+fn registry_asset_type_lookup(
+    conn: &rusqlite::Connection,
+    ticker: &str,
+) -> Result<Option<AssetType>> {
+    if let Some(entry) = crate::db::get_asset_registry_by_ticker(conn, "MAIS_RETORNO", ticker)? {
         return Ok(Some(entry.asset_type));
     }
 
-    if should_refresh_registry(&conn)? {
-        if let Err(err) = refresh_registry_and_wait() {
+    if should_refresh_registry(conn)? {
+        if let Err(err) = refresh_registry_and_wait(conn) {
             tracing::warn!("Mais Retorno registry refresh failed: {}", err);
         } else if let Some(entry) =
-            crate::db::get_asset_registry_by_ticker(&conn, "MAIS_RETORNO", ticker)?
+            crate::db::get_asset_registry_by_ticker(conn, "MAIS_RETORNO", ticker)?
         {
             return Ok(Some(entry.asset_type));
         }
@@ -304,34 +458,39 @@ fn should_refresh_registry(conn: &rusqlite::Connection) -> Result<bool> {
     Ok(Utc::now().signed_duration_since(last) > Duration::days(1))
 }
 
-fn refresh_registry_and_wait() -> Result<()> {
-    let handle = std::thread::spawn(refresh_registry_blocking);
-    match handle.join() {
-        Ok(result) => result,
-        Err(_) => Err(anyhow::anyhow!("Mais Retorno refresh thread panicked")),
+/// Sync the registry on this thread, through `conn`: a second connection
+/// could not write while the caller's transaction holds the database
+fn refresh_registry_and_wait(conn: &rusqlite::Connection) -> Result<()> {
+    use tokio::runtime::{Handle, RuntimeFlavor};
+
+    let refresh = refresh_registry(conn);
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| handle.block_on(refresh))
+        }
+        Ok(_) => anyhow::bail!("cannot wait for the refresh on a single-threaded runtime"),
+        Err(_) => tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(refresh),
     }
 }
 
-fn refresh_registry_blocking() -> Result<()> {
-    let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(async {
-        let conn = crate::db::open_db(None)?;
-        let sources = crate::scraping::maisretorno::select_sources(None);
-        let printer = crate::ui::progress::ProgressPrinter::new(false);
-        let (tx, mut rx) =
-            tokio::sync::mpsc::unbounded_channel::<crate::ui::progress::ProgressEvent>();
-        let progress_handle = tokio::spawn(async move {
-            while let Some(event) = rx.recv().await {
-                printer.handle_event(&event);
-            }
-        });
+async fn refresh_registry(conn: &rusqlite::Connection) -> Result<()> {
+    let sources = crate::scraping::maisretorno::select_sources(None);
+    let printer = crate::ui::progress::ProgressPrinter::new(false);
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<crate::ui::progress::ProgressEvent>();
+    let progress_handle = tokio::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            printer.handle_event(&event);
+        }
+    });
 
-        let _stats =
-            crate::scraping::maisretorno::sync_registry(&conn, &sources, false, Some(tx)).await?;
-        let _ = progress_handle.await;
-        crate::ui::progress::clear_progress_line();
-        Ok(())
-    })
+    let _stats =
+        crate::scraping::maisretorno::sync_registry(conn, &sources, false, Some(tx)).await?;
+    let _ = progress_handle.await;
+    crate::ui::progress::clear_progress_line();
+    Ok(())
 }
 
 pub fn ambima_debenture_lookup(ticker: &str) -> Result<Option<AssetType>> {
@@ -400,6 +559,77 @@ fn classify_fund_by_name(record: &TickerRecord) -> Option<AssetType> {
     classify_by_cfi_hint(record.cfi_code.as_deref())
 }
 
+/// Ticker of a brokerage-note title such as "PETROBRAS PN N2": the share
+/// class picks the numeric suffix and the trading name must appear in the
+/// company's corporate name. None unless exactly one ticker fits.
+pub fn find_ticker_by_specification(
+    specification: &str,
+    date: NaiveDate,
+) -> Result<Option<String>> {
+    let cache_dir = get_tickers_cache_dir()?;
+    let path = match version_for_date(&cache_dir, date)? {
+        Some((_, path)) => path,
+        None => cache_dir.join(CACHE_FILENAME),
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+    let map = get_cached_map_at(&path)?;
+    Ok(ticker_for_specification(&map, specification))
+}
+
+fn ticker_for_specification(
+    map: &HashMap<String, TickerRecord>,
+    specification: &str,
+) -> Option<String> {
+    let normalized = normalize_name(specification);
+    let tokens: Vec<&str> = normalized.split_whitespace().collect();
+    let class_idx = tokens
+        .iter()
+        .position(|t| share_class_suffix(t).is_some())?;
+    if class_idx == 0 {
+        return None;
+    }
+    let suffix = share_class_suffix(tokens[class_idx])?;
+    let name = format!(" {} ", tokens[..class_idx].join(" "));
+
+    let mut matches: Vec<&str> = map
+        .values()
+        .filter(|record| {
+            record.ticker.len() == 4 + suffix.len()
+                && record.ticker.ends_with(suffix)
+                && record.ticker[..4].chars().all(|c| c.is_ascii_alphabetic())
+        })
+        .filter(|record| {
+            record
+                .corporate_name
+                .as_deref()
+                .is_some_and(|n| format!(" {} ", normalize_name(n)).contains(&name))
+        })
+        .map(|record| record.ticker.as_str())
+        .collect();
+    matches.sort_unstable();
+    matches.dedup();
+    match matches.as_slice() {
+        [ticker] => Some(ticker.to_string()),
+        _ => None,
+    }
+}
+
+/// Ticker suffix of a share class as printed on brokerage notes
+fn share_class_suffix(class: &str) -> Option<&'static str> {
+    match class {
+        "ON" => Some("3"),
+        "PN" => Some("4"),
+        "PNA" => Some("5"),
+        "PNB" => Some("6"),
+        "PNC" => Some("7"),
+        "PND" => Some("8"),
+        "UNT" | "UNIT" => Some("11"),
+        _ => None,
+    }
+}
+
 fn find_record_by_name<'a>(
     map: &'a HashMap<String, TickerRecord>,
     ticker: &str,
@@ -604,33 +834,39 @@ fn download_b3_tickers(date: chrono::NaiveDate) -> Result<(Vec<u8>, String)> {
     .map_err(|_| anyhow::anyhow!("Tickers download thread panicked"))?
 }
 
-fn cache_guard() -> std::sync::MutexGuard<'static, Option<CachedMap>> {
+fn cache_guard() -> std::sync::MutexGuard<'static, HashMap<PathBuf, CachedMap>> {
     TICKERS_CACHE
-        .get_or_init(|| Mutex::new(None))
+        .get_or_init(|| Mutex::new(HashMap::new()))
         .lock()
         .expect("tickers cache mutex poisoned")
 }
 
 fn get_cached_map(cache_dir: &Path) -> Result<Arc<HashMap<String, TickerRecord>>> {
-    let csv_path = cache_dir.join(CACHE_FILENAME);
-    let metadata = fs::metadata(&csv_path).context("Failed to stat tickers cache file")?;
+    get_cached_map_at(&cache_dir.join(CACHE_FILENAME))
+}
+
+fn get_cached_map_at(csv_path: &Path) -> Result<Arc<HashMap<String, TickerRecord>>> {
+    let metadata = fs::metadata(csv_path).context("Failed to stat tickers cache file")?;
     let mtime = metadata
         .modified()
         .context("Failed to read tickers cache modified time")?;
 
     let mut guard = cache_guard();
-    if let Some(cached) = guard.as_ref() {
+    if let Some(cached) = guard.get(csv_path) {
         if cached.mtime == mtime {
             return Ok(cached.map.clone());
         }
     }
 
-    let map = load_b3_tickers_map(Some(cache_dir))?;
+    let map = load_b3_tickers_file(csv_path)?;
     let arc_map = Arc::new(map);
-    *guard = Some(CachedMap {
-        map: arc_map.clone(),
-        mtime,
-    });
+    guard.insert(
+        csv_path.to_path_buf(),
+        CachedMap {
+            map: arc_map.clone(),
+            mtime,
+        },
+    );
     Ok(arc_map)
 }
 
@@ -694,9 +930,46 @@ mod tests {
             security_category: security_category.to_string(),
             cfi_code: cfi_code.map(|v| v.to_string()),
             corporate_name: Some(corporate_name.to_string()),
+            isin: None,
         }
     }
 
+    #[test]
+    fn ticker_for_specification_uses_class_and_name() {
+        let record = |ticker: &str, name: &str| {
+            (
+                ticker.to_string(),
+                TickerRecord {
+                    ticker: ticker.to_string(),
+                    security_category: "SHARES".to_string(),
+                    cfi_code: None,
+                    corporate_name: Some(name.to_string()),
+                    isin: None,
+                },
+            )
+        };
+        let map: HashMap<String, TickerRecord> = [
+            record("PETR3", "PETROLEO BRASILEIRO S.A. PETROBRAS"),
+            record("PETR4", "PETROLEO BRASILEIRO S.A. PETROBRAS"),
+            record("TAEE11", "TRANSMISSORA ALIANÇA DE ENERGIA ELÉTRICA S.A."),
+            record("BRAP4", "BRADESPAR S.A."),
+            record("BBDC4", "BANCO BRADESCO S.A."),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            ticker_for_specification(&map, "PETROBRAS PN N2").as_deref(),
+            Some("PETR4")
+        );
+        assert_eq!(
+            ticker_for_specification(&map, "BRADESCO PN EJ N1").as_deref(),
+            Some("BBDC4")
+        );
+        assert_eq!(ticker_for_specification(&map, "TAESA UNT N2"), None);
+        assert_eq!(ticker_for_specification(&map, "PETROBRAS"), None);
+    }
+
     #[test]
     fn normalize_name_strips_punctuation() {
         assert_eq!(normalize_name("INV. EM INFR."), "INV EM INFR");
@@ -785,10 +1058,49 @@ mod tests {
         ));
         fs::write(cache_dir.join(CACHE_FILENAME), fixture).unwrap();
 
-        let map = load_b3_tickers_map(Some(cache_dir)).unwrap();
+        let map = load_b3_tickers_file(&cache_dir.join(CACHE_FILENAME)).unwrap();
         let record = map.get("2WAV3").unwrap();
         assert_eq!(record.security_category, "SHARES");
         assert_eq!(record.cfi_code.as_deref(), Some("ESVUFR"));
+        assert_eq!(record.isin.as_deref(), Some("BR2WAVACNOR8"));
+    }
+
+    #[test]
+    fn test_versions_pick_file_of_the_era() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path();
+        let d = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        assert!(version_for_date(cache_dir, d("2024-01-01"))
+            .unwrap()
+            .is_none());
+
+        assert!(archive_version(cache_dir, d("2023-03-10"), b"a", false)
+            .unwrap()
+            .is_some());
+        // One version per month unless forced
+        assert!(archive_version(cache_dir, d("2023-03-20"), b"b", false)
+            .unwrap()
+            .is_none());
+        assert!(archive_version(cache_dir, d("2024-06-28"), b"c", false)
+            .unwrap()
+            .is_some());
+        assert!(archive_version(cache_dir, d("2024-06-03"), b"d", true)
+            .unwrap()
+            .is_some());
+        fs::write(cache_dir.join(VERSIONS_DIR).join("notes.txt"), b"x").unwrap();
+
+        assert_eq!(
+            list_versions(Some(cache_dir)).unwrap(),
+            vec![d("2023-03-10"), d("2024-06-03"), d("2024-06-28")]
+        );
+
+        let pick = |date: &str| version_for_date(cache_dir, d(date)).unwrap().unwrap().0;
+        assert_eq!(pick("2023-12-31"), d("2023-03-10"));
+        assert_eq!(pick("2024-06-10"), d("2024-06-03"));
+        assert_eq!(pick("2025-01-01"), d("2024-06-28"));
+        // Older than anything kept: the closest (oldest) version
+        assert_eq!(pick("2019-05-02"), d("2023-03-10"));
     }
 
     #[test]
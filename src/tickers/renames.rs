@@ -0,0 +1,381 @@
+//! Ticker changes found in the history of B3 instrument files. A held ticker
+//! that stops being listed while a ticker of the same class appears with its
+//! ISIN (or, failing that, its company name) is proposed as an
+//! `asset_renames` entry through a TICKER_RENAME inconsistency, instead of
+//! surfacing later as a ticker whose prices no longer update.
+
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{get_cached_map_at, normalize_name, TickerRecord, CACHE_FILENAME};
+use crate::db::{
+    self, AssetType, Inconsistency, InconsistencySeverity, InconsistencyStatus, InconsistencyType,
+};
+
+/// A held ticker and the one that replaced it in the instruments file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProposedRename {
+    pub from: String,
+    pub to: String,
+    /// Last file listing the old ticker
+    pub last_listed: NaiveDate,
+    /// First file listing the new one, used as the effective date
+    pub first_listed: NaiveDate,
+    /// "isin" or "name"
+    pub matched_by: &'static str,
+    pub from_name: Option<String>,
+    pub to_name: Option<String>,
+}
+
+type TickerMap = Arc<HashMap<String, TickerRecord>>;
+
+/// Files to compare, oldest first: the dated versions, then the latest
+/// download when it is newer than all of them
+fn snapshots(cache_dir: &Path) -> Result<Vec<(NaiveDate, PathBuf)>> {
+    let mut files: Vec<_> = super::list_versions(Some(cache_dir))?
+        .into_iter()
+        .map(|d| (d, super::version_path(cache_dir, d)))
+        .collect();
+    let latest = cache_dir.join(CACHE_FILENAME);
+    if let (true, Some(meta)) = (latest.exists(), super::read_cache_meta(Some(cache_dir))?) {
+        let fetched = meta.fetched_at.with_timezone(&Local).date_naive();
+        if files.last().is_none_or(|(d, _)| *d < fetched) {
+            files.push((fetched, latest));
+        }
+    }
+    Ok(files)
+}
+
+/// Ticker suffix after the 4-letter root (3, 4, 11, 34...)
+fn class_suffix(ticker: &str) -> &str {
+    ticker.get(4..).unwrap_or("")
+}
+
+/// Renames of the `held` tickers missing from the newest file. Each one is
+/// looked for between the last file that lists it and the one after.
+pub fn find_renames(cache_dir: &Path, held: &[String]) -> Result<Vec<ProposedRename>> {
+    let files = snapshots(cache_dir)?;
+    if files.len() < 2 {
+        return Ok(Vec::new());
+    }
+    let mut loaded: HashMap<usize, TickerMap> = HashMap::new();
+    let mut load = |i: usize| -> Result<TickerMap> {
+        if let Some(map) = loaded.get(&i) {
+            return Ok(map.clone());
+        }
+        let map = get_cached_map_at(&files[i].1)
+            .with_context(|| format!("Failed to read {}", files[i].1.display()))?;
+        loaded.insert(i, map.clone());
+        Ok(map)
+    };
+
+    let newest = files.len() - 1;
+    let mut proposals = Vec::new();
+    for ticker in held {
+        let ticker = ticker.trim().to_ascii_uppercase();
+        if load(newest)?.contains_key(&ticker) {
+            continue;
+        }
+        let mut last = None;
+        for i in (0..newest).rev() {
+            if load(i)?.contains_key(&ticker) {
+                last = Some(i);
+                break;
+            }
+        }
+        // Never listed in the files kept
+        let Some(i) = last else {
+            continue;
+        };
+        let (before, after) = (load(i)?, load(i + 1)?);
+        let old = &before[&ticker];
+        let appeared: Vec<&TickerRecord> = after
+            .values()
+            .filter(|r| {
+                !before.contains_key(&r.ticker) && class_suffix(&r.ticker) == class_suffix(&ticker)
+            })
+            .collect();
+
+        let by_isin: Vec<_> = appeared
+            .iter()
+            .filter(|r| old.isin.is_some() && r.isin == old.isin)
+            .collect();
+        let old_name = old
+            .corporate_name
+            .as_deref()
+            .map(normalize_name)
+            .unwrap_or_default();
+        let by_name: Vec<_> = appeared
+            .iter()
+            .filter(|r| {
+                !old_name.is_empty()
+                    && r.corporate_name.as_deref().map(normalize_name).as_deref()
+                        == Some(old_name.as_str())
+            })
+            .collect();
+        let (successor, matched_by) = match (by_isin.as_slice(), by_name.as_slice()) {
+            ([only], _) => (only, "isin"),
+            ([], [only]) => (only, "name"),
+            // Nothing alike, or too many to pick from
+            _ => continue,
+        };
+        proposals.push(ProposedRename {
+            from: ticker.clone(),
+            to: successor.ticker.clone(),
+            last_listed: files[i].0,
+            first_listed: files[i + 1].0,
+            matched_by,
+            from_name: old.corporate_name.clone(),
+            to_name: successor.corporate_name.clone(),
+        });
+    }
+    Ok(proposals)
+}
+
+fn has_rename_from(conn: &Connection, asset_id: i64) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM asset_renames WHERE from_asset_id = ?1)",
+        params![asset_id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Open an inconsistency for each rename found for the tickers held, and
+/// resolve the open ones whose ticker got a rename since. Returns (opened, resolved).
+pub fn sync_renames(conn: &Connection) -> Result<(usize, usize)> {
+    sync_renames_with(conn, &super::get_tickers_cache_dir()?)
+}
+
+fn sync_renames_with(conn: &Connection, cache_dir: &Path) -> Result<(usize, usize)> {
+    let mut known: HashSet<String> = HashSet::new();
+    let mut resolved = 0;
+    for issue in db::list_inconsistencies(conn, None, Some(InconsistencyType::TickerRename), None)?
+    {
+        let Some(source_ref) = issue.source_ref else {
+            continue;
+        };
+        if issue.status == InconsistencyStatus::Open {
+            if let Some(asset_id) = issue.asset_id {
+                if has_rename_from(conn, asset_id)? {
+                    db::resolve_inconsistency(conn, issue.id.unwrap_or(0), Some("GONE"), None)?;
+                    resolved += 1;
+                    continue;
+                }
+            }
+        }
+        known.insert(source_ref);
+    }
+
+    let today = Local::now().date_naive();
+    let mut held = HashMap::new();
+    for position in crate::reports::calculate_portfolio_at_date(conn, today, None)?.positions {
+        let Some(asset_id) = position.asset.id else {
+            continue;
+        };
+        // Only what B3 lists; assets quoted abroad are not in its file
+        let listed = matches!(
+            position.asset.asset_type,
+            AssetType::Stock
+                | AssetType::Etf
+                | AssetType::Fii
+                | AssetType::Fiagro
+                | AssetType::FiInfra
+                | AssetType::Bdr
+                | AssetType::Fidc
+                | AssetType::Fip
+        );
+        if position.quantity <= rust_decimal::Decimal::ZERO
+            || !listed
+            || db::get_asset_currency(conn, asset_id)?.is_some()
+            || has_rename_from(conn, asset_id)?
+        {
+            continue;
+        }
+        held.insert(position.asset.ticker.clone(), (asset_id, position.quantity));
+    }
+    let tickers: Vec<String> = held.keys().cloned().collect();
+
+    let mut opened = 0;
+    for proposal in find_renames(cache_dir, &tickers)? {
+        let source_ref = format!("rename:{}:{}", proposal.from, proposal.to);
+        if known.contains(&source_ref) {
+            continue;
+        }
+        let (asset_id, quantity) = held[&proposal.from];
+        db::insert_inconsistency(
+            conn,
+            &Inconsistency {
+                id: None,
+                issue_type: InconsistencyType::TickerRename,
+                status: InconsistencyStatus::Open,
+                severity: InconsistencySeverity::Warn,
+                asset_id: Some(asset_id),
+                transaction_id: None,
+                ticker: Some(proposal.from.clone()),
+                trade_date: Some(proposal.first_listed),
+                quantity: Some(quantity),
+                source: Some("B3".to_string()),
+                source_ref: Some(source_ref),
+                missing_fields_json: None,
+                context_json: Some(
+                    json!({
+                        "notes": format!(
+                            "{} left the B3 instruments file after {}; {} appeared by {} with the same {}",
+                            proposal.from,
+                            proposal.last_listed,
+                            proposal.to,
+                            proposal.first_listed,
+                            if proposal.matched_by == "isin" { "ISIN" } else { "company name" }
+                        ),
+                        "effective_date": proposal.first_listed,
+                        "rename": proposal,
+                    })
+                    .to_string(),
+                ),
+                resolution_action: None,
+                resolution_json: None,
+                created_at: None,
+                resolved_at: None,
+            },
+        )?;
+        opened += 1;
+    }
+    Ok((opened, resolved))
+}
+
+/// Record the rename a TICKER_RENAME issue proposes; `to` and `date` override
+/// the successor and effective date found. Returns the rename id.
+pub fn accept(
+    conn: &Connection,
+    issue: &Inconsistency,
+    to: Option<&str>,
+    date: Option<NaiveDate>,
+) -> Result<i64> {
+    let from_asset_id = issue
+        .asset_id
+        .ok_or_else(|| anyhow::anyhow!("the inconsistency has no asset"))?;
+    let context: serde_json::Value = issue
+        .context_json
+        .as_deref()
+        .and_then(|c| serde_json::from_str(c).ok())
+        .unwrap_or_default();
+    let to = to
+        .map(|t| t.trim().to_ascii_uppercase())
+        .or_else(|| context["rename"]["to"].as_str().map(str::to_string))
+        .ok_or_else(|| anyhow::anyhow!("the new ticker is missing"))?;
+    let effective_date = date
+        .or_else(|| {
+            context["effective_date"]
+                .as_str()
+                .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+        })
+        .or(issue.trade_date)
+        .ok_or_else(|| anyhow::anyhow!("the effective date is missing"))?;
+
+    let to_asset_id = db::upsert_asset(conn, &to, &AssetType::Unknown, None)?;
+    db::insert_asset_rename(
+        conn,
+        &db::AssetRename {
+            id: None,
+            from_asset_id,
+            to_asset_id,
+            effective_date,
+            notes: Some("Detected in the B3 instruments file".to_string()),
+            created_at: chrono::Utc::now(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const HEADER: &str = "RptDt;TckrSymb;SgmtNm;SctyCtgyNm;ISIN;SpcfctnCd;CrpnNm";
+
+    fn write_version(cache_dir: &Path, date: &str, rows: &[&str]) {
+        let dir = cache_dir.join(super::super::VERSIONS_DIR);
+        fs::create_dir_all(&dir).unwrap();
+        let body = std::iter::once(HEADER)
+            .chain(rows.iter().copied())
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(dir.join(format!("tickers-{}.csv", date)), body).unwrap();
+    }
+
+    #[test]
+    fn test_held_ticker_replaced_by_its_isin_is_proposed_once() {
+        let temp_dir = TempDir::new().unwrap();
+        let cache_dir = temp_dir.path();
+        write_version(
+            cache_dir,
+            "2023-08-01",
+            &[
+                "2023-08-01;VIIA3;CASH;SHARES;BRVIIAACNOR8;ON NM;VIA S.A.",
+                "2023-08-01;PETR4;CASH;SHARES;BRPETRACNPR6;PN N2;PETROBRAS",
+            ],
+        );
+        write_version(
+            cache_dir,
+            "2023-09-01",
+            &[
+                "2023-09-01;BHIA3;CASH;SHARES;BRVIIAACNOR8;ON NM;GRUPO CASAS BAHIA S.A.",
+                "2023-09-01;BHIA3F;ODD LOT;SHARES;BRVIIAACNOR8;ON NM;GRUPO CASAS BAHIA S.A.",
+                "2023-09-01;NEWW3;CASH;SHARES;BRNEWWACNOR1;ON NM;NEW CO S.A.",
+                "2023-09-01;PETR4;CASH;SHARES;BRPETRACNPR6;PN N2;PETROBRAS",
+            ],
+        );
+
+        let found = find_renames(cache_dir, &["VIIA3".to_string(), "PETR4".to_string()]).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].to, "BHIA3");
+        assert_eq!(found[0].matched_by, "isin");
+        assert_eq!(
+            found[0].first_listed,
+            NaiveDate::from_ymd_opt(2023, 9, 1).unwrap()
+        );
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../db/schema.sql"))
+            .unwrap();
+        let asset_id = db::upsert_asset(&conn, "VIIA3", &AssetType::Stock, None).unwrap();
+        conn.execute(
+            "UPDATE assets SET asset_type = 'STOCK' WHERE id = ?1",
+            [asset_id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO transactions (asset_id, transaction_type, trade_date, quantity,
+                 price_per_unit, total_cost, fees, source)
+             VALUES (?1, 'BUY', '2023-01-10', '100', '3', '300', '0', 'MANUAL')",
+            [asset_id],
+        )
+        .unwrap();
+
+        assert_eq!(sync_renames_with(&conn, cache_dir).unwrap(), (1, 0));
+        assert_eq!(sync_renames_with(&conn, cache_dir).unwrap(), (0, 0));
+
+        let issue =
+            db::list_inconsistencies(&conn, None, Some(InconsistencyType::TickerRename), None)
+                .unwrap()
+                .remove(0);
+        assert_eq!(issue.source_ref.as_deref(), Some("rename:VIIA3:BHIA3"));
+        let rename_id = accept(&conn, &issue, None, None).unwrap();
+        let rename = db::get_asset_rename(&conn, rename_id).unwrap().unwrap();
+        assert_eq!(rename.from_asset_id, asset_id);
+        assert_eq!(
+            rename.effective_date,
+            NaiveDate::from_ymd_opt(2023, 9, 1).unwrap()
+        );
+
+        // The rename recorded settles the issue
+        assert_eq!(sync_renames_with(&conn, cache_dir).unwrap(), (0, 1));
+    }
+}
@@ -0,0 +1,350 @@
+//! Corporate actions review screen: pending and recently applied actions,
+//! their effect on the position, and approve/apply/unapply keybindings.
+
+use anyhow::Result;
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, terminal};
+use rusqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use std::io::{IsTerminal, Write};
+
+use crate::corporate_actions::review::{self, EffectPreview, ReviewEntry};
+use crate::utils::format_currency;
+
+const KEYS: &str =
+    "↑/↓ move · space approve · a apply approved (or selected) · u unapply · r reload · q quit";
+
+/// Leaves raw mode and the alternate screen however the screen exits
+pub(super) struct TerminalGuard;
+
+impl TerminalGuard {
+    pub(super) fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(
+            std::io::stdout(),
+            terminal::EnterAlternateScreen,
+            cursor::Hide
+        )?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(
+            std::io::stdout(),
+            cursor::Show,
+            terminal::LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Screen {
+    conn: Connection,
+    entries: Vec<ReviewEntry>,
+    selected: usize,
+    approved: HashSet<i64>,
+    previews: HashMap<i64, Result<EffectPreview, String>>,
+    message: Option<String>,
+    /// Applied or unapplied something, so cached reports are stale
+    changed: bool,
+}
+
+impl Screen {
+    fn reload(&mut self) -> Result<()> {
+        let selected_id = self.entries.get(self.selected).map(|e| e.id);
+        self.entries = review::review_entries(&self.conn, chrono::Utc::now())?;
+        self.previews.clear();
+        self.approved
+            .retain(|id| self.entries.iter().any(|e| e.id == *id && !e.is_applied()));
+        self.selected = selected_id
+            .and_then(|id| self.entries.iter().position(|e| e.id == id))
+            .unwrap_or(0)
+            .min(self.entries.len().saturating_sub(1));
+        Ok(())
+    }
+
+    fn preview(&mut self, index: usize) -> Option<&Result<EffectPreview, String>> {
+        let entry = self.entries.get(index)?;
+        let conn = &self.conn;
+        Some(
+            self.previews
+                .entry(entry.id)
+                .or_insert_with(|| review::preview(conn, &entry.action).map_err(|e| e.to_string())),
+        )
+    }
+
+    fn toggle_approved(&mut self) {
+        let Some(entry) = self.entries.get(self.selected) else {
+            return;
+        };
+        if entry.is_applied() {
+            self.message = Some(format!("#{} is already applied", entry.id));
+        } else if !self.approved.remove(&entry.id) {
+            self.approved.insert(entry.id);
+        }
+    }
+
+    fn apply(&mut self) -> Result<()> {
+        let ids: Vec<i64> = if self.approved.is_empty() {
+            self.entries
+                .get(self.selected)
+                .filter(|e| !e.is_applied())
+                .map(|e| vec![e.id])
+                .unwrap_or_default()
+        } else {
+            self.entries
+                .iter()
+                .filter(|e| self.approved.contains(&e.id))
+                .map(|e| e.id)
+                .collect()
+        };
+        if ids.is_empty() {
+            self.message = Some("Nothing pending to apply".to_string());
+            return Ok(());
+        }
+
+        let mut bonuses = 0;
+        let mut failed = Vec::new();
+        for id in &ids {
+            match review::apply(&self.conn, *id) {
+                Ok(n) => bonuses += n,
+                Err(e) => failed.push(format!("#{}: {}", id, e)),
+            }
+        }
+        let applied = ids.len() - failed.len();
+        self.changed |= applied > 0;
+        self.approved.clear();
+        self.message = Some(if failed.is_empty() {
+            format!(
+                "Applied {} action(s), {} bonus(es) added to the position",
+                applied, bonuses
+            )
+        } else {
+            format!("Applied {}; failed {}", applied, failed.join("; "))
+        });
+        self.reload()
+    }
+
+    fn unapply(&mut self) -> Result<()> {
+        let Some(entry) = self.entries.get(self.selected) else {
+            return Ok(());
+        };
+        let id = entry.id;
+        self.message = Some(match review::unapply(&self.conn, id) {
+            Ok(()) => {
+                self.changed = true;
+                format!(
+                    "Unapplied #{}, its shares left the position; it is pending again",
+                    id
+                )
+            }
+            Err(e) => e.to_string(),
+        });
+        self.reload()
+    }
+
+    fn render(&mut self) -> Result<()> {
+        let (_, height) = terminal::size().unwrap_or((100, 30));
+        let pending = self.entries.iter().filter(|e| !e.is_applied()).count();
+        let mut lines = vec![
+            format!(
+                "{} Corporate actions review - {} pending, {} applied in the last {} days",
+                "🏷".cyan().bold(),
+                pending,
+                self.entries.len() - pending,
+                review::RECENT_DAYS
+            ),
+            String::new(),
+        ];
+
+        // Keep the selection visible, leaving room for the detail pane
+        let list_height = (height as usize).saturating_sub(14).max(3);
+        let start = self.selected.saturating_sub(list_height - 1);
+        if self.entries.is_empty() {
+            lines.push("  No pending or recently applied corporate actions".to_string());
+        }
+        for (i, entry) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(list_height)
+        {
+            let mark = if self.approved.contains(&entry.id) {
+                "[x]"
+            } else if entry.is_applied() {
+                "   "
+            } else {
+                "[ ]"
+            };
+            let status = match entry.applied_at {
+                Some(at) => format!("applied {}", at.format("%d/%m")).green(),
+                None => "pending".yellow(),
+            };
+            let conflict = if entry.conflicts.is_empty() {
+                String::new()
+            } else {
+                format!("⚠ {} other source(s)", entry.conflicts.len())
+                    .red()
+                    .to_string()
+            };
+            let line = format!(
+                "{} {} #{:<5} {:<8} {:<14} {}  {:>12}  {:<12} {:<13} {}",
+                if i == self.selected { ">" } else { " " },
+                mark,
+                entry.id,
+                entry.ticker,
+                entry.action_type,
+                entry.ex_date.format("%d/%m/%Y"),
+                format!("{:+}", entry.quantity_adjustment),
+                entry.source,
+                status,
+                conflict
+            );
+            lines.push(if i == self.selected {
+                line.bold().to_string()
+            } else {
+                line
+            });
+        }
+
+        lines.push(String::new());
+        if !self.entries.is_empty() {
+            lines.extend(self.detail_lines());
+        }
+        lines.push(String::new());
+        if let Some(message) = &self.message {
+            lines.push(message.cyan().to_string());
+        }
+        lines.push(KEYS.dimmed().to_string());
+
+        let mut out = std::io::stdout();
+        execute!(
+            out,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        for line in lines {
+            write!(out, "{}\r\n", line)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    fn detail_lines(&mut self) -> Vec<String> {
+        let selected = self.selected;
+        let entry = self.entries[selected].clone();
+        let mut lines = vec![format!(
+            "  {} {} on {} ({} {:+})",
+            entry.ticker.bold(),
+            entry.action_type,
+            entry.ex_date.format("%d/%m/%Y"),
+            entry.source,
+            entry.quantity_adjustment
+        )];
+        if let Some(notes) = entry.action.notes.as_deref().filter(|n| !n.is_empty()) {
+            lines.push(format!("  Notes: {}", notes));
+        }
+        match self.preview(selected) {
+            Some(Ok(p)) => {
+                lines.push(format!(
+                    "  Held before: {} at {} average ({} cost)",
+                    p.held_before,
+                    format_currency(p.average_before()),
+                    format_currency(p.cost_before)
+                ));
+                lines.push(format!(
+                    "  After:       {} at {} average",
+                    p.held_after,
+                    format_currency(p.average_after())
+                ));
+                lines.push(
+                    if p.applies_on_review {
+                        "  Applying adds the bonus shares at zero cost; unapplying takes them out"
+                    } else {
+                        "  Takes effect at query time; applying records it as reviewed"
+                    }
+                    .dimmed()
+                    .to_string(),
+                );
+            }
+            Some(Err(e)) => lines.push(format!("  Preview unavailable: {}", e).red().to_string()),
+            None => {}
+        }
+        for c in &entry.conflicts {
+            lines.push(
+                format!(
+                    "  ⚠ {} reports #{} {} {:+} on {}",
+                    c.source,
+                    c.id,
+                    c.action_type,
+                    c.quantity_adjustment,
+                    c.ex_date.format("%d/%m/%Y")
+                )
+                .red()
+                .to_string(),
+            );
+        }
+        lines
+    }
+}
+
+/// Run the review screen until the user quits
+pub fn review_corporate_actions() -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        anyhow::bail!("actions review needs an interactive terminal; use --json to list the queue");
+    }
+    crate::db::init_database(None)?;
+    let mut screen = Screen {
+        conn: crate::db::open_db(None)?,
+        entries: Vec::new(),
+        selected: 0,
+        approved: HashSet::new(),
+        previews: HashMap::new(),
+        message: None,
+        changed: false,
+    };
+    screen.reload()?;
+
+    {
+        let _guard = TerminalGuard::enter()?;
+        loop {
+            screen.render()?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            screen.message = None;
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => break,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+                KeyCode::Up | KeyCode::Char('k') => {
+                    screen.selected = screen.selected.saturating_sub(1)
+                }
+                KeyCode::Down | KeyCode::Char('j')
+                    if screen.selected + 1 < screen.entries.len() =>
+                {
+                    screen.selected += 1
+                }
+                KeyCode::Char(' ') => screen.toggle_approved(),
+                KeyCode::Char('a') => screen.apply()?,
+                KeyCode::Char('u') => screen.unapply()?,
+                KeyCode::Char('r') => screen.reload()?,
+                _ => {}
+            }
+        }
+    }
+
+    if screen.changed {
+        println!(
+            "{} Corporate actions changed; reports will be recalculated",
+            "✓".green().bold()
+        );
+    }
+    Ok(())
+}
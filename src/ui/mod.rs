@@ -5,12 +5,25 @@
 
 pub mod crossterm_engine;
 pub mod progress;
+pub mod refresh;
 
+#[cfg(feature = "tui")]
+mod actions_review;
+#[cfg(feature = "tui")]
+mod portfolio_browser;
+#[cfg(feature = "tui")]
+mod price_watch;
 #[cfg(feature = "tui")]
 mod readline;
 #[cfg(feature = "tui")]
 mod tui;
 
+#[cfg(feature = "tui")]
+pub use actions_review::review_corporate_actions;
+#[cfg(feature = "tui")]
+pub use portfolio_browser::browse_portfolio;
+#[cfg(feature = "tui")]
+pub use price_watch::watch_prices;
 #[cfg(feature = "tui")]
 pub use tui::launch_tui;
 
@@ -23,3 +36,24 @@ pub async fn launch_tui() -> Result<()> {
         "Interactive TUI is disabled; rebuild with --features tui"
     ))
 }
+
+#[cfg(not(feature = "tui"))]
+pub fn review_corporate_actions() -> Result<()> {
+    Err(anyhow::anyhow!(
+        "The review screen is disabled; rebuild with --features tui or use --json"
+    ))
+}
+
+#[cfg(not(feature = "tui"))]
+pub async fn watch_prices(_interval_secs: u64) -> Result<()> {
+    Err(anyhow::anyhow!(
+        "The live quotes screen is disabled; rebuild with --features tui or use --once"
+    ))
+}
+
+#[cfg(not(feature = "tui"))]
+pub async fn browse_portfolio() -> Result<()> {
+    Err(anyhow::anyhow!(
+        "The portfolio browser is disabled; rebuild with --features tui or use portfolio show"
+    ))
+}
@@ -0,0 +1,292 @@
+//! Portfolio browser: open positions in a navigable table with row shortcuts
+//! for the asset detail, a new trade, a fresh quote and the income history.
+
+use anyhow::Result;
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, terminal};
+use rusqlite::Connection;
+use std::future::Future;
+use std::io::{IsTerminal, Write};
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use super::actions_review::TerminalGuard;
+use crate::cli::{AssetsCommands, Commands, IncomeCommands};
+use crate::pricing::coverage::{self, YAHOO};
+use crate::reports::portfolio::PositionSummary;
+use crate::utils::format_currency;
+
+const KEYS: &str =
+    "↑/↓ move · enter detail · t add transaction · p refresh price · i income · q quit";
+
+/// Line the interactive prompt starts with after `t`
+static NEXT_PROMPT: Mutex<Option<String>> = Mutex::new(None);
+
+/// Take the line left for the next prompt, if any
+pub(super) fn take_next_prompt() -> Option<String> {
+    NEXT_PROMPT.lock().ok()?.take()
+}
+
+/// What a row shortcut asks for once the screen is left
+enum RowAction {
+    Detail(String),
+    Income(String),
+    AddTransaction(String),
+}
+
+struct Screen {
+    conn: Connection,
+    positions: Vec<PositionSummary>,
+    selected: usize,
+    message: Option<String>,
+}
+
+impl Screen {
+    fn reload(&mut self) -> Result<()> {
+        let selected_ticker = self.selected_ticker();
+        self.positions = crate::reports::calculate_portfolio(&self.conn, None)?.positions;
+        self.selected = selected_ticker
+            .and_then(|t| self.positions.iter().position(|p| p.asset.ticker == t))
+            .unwrap_or(0)
+            .min(self.positions.len().saturating_sub(1));
+        Ok(())
+    }
+
+    fn selected_ticker(&self) -> Option<String> {
+        self.positions
+            .get(self.selected)
+            .map(|p| p.asset.ticker.clone())
+    }
+
+    /// Fetch a live quote for the selected asset and revalue the portfolio
+    async fn refresh_price(&mut self) -> Result<()> {
+        let Some(position) = self.positions.get(self.selected) else {
+            return Ok(());
+        };
+        let ticker = position.asset.ticker.clone();
+        let (Some(asset_id), true) = (
+            position.asset.id,
+            coverage::eligible_providers(&position.asset).contains(&YAHOO),
+        ) else {
+            self.message = Some(format!("No live quote source for {}", ticker));
+            return Ok(());
+        };
+
+        self.message = Some(format!("Fetching {}...", ticker));
+        self.render()?;
+        let today = chrono::Local::now().date_naive();
+        self.message = Some(
+            match crate::pricing::watch::quote_now(&self.conn, asset_id, &ticker, today).await {
+                Ok(quote) => format!(
+                    "{} at {} ({})",
+                    ticker,
+                    format_currency(quote.price),
+                    quote
+                        .quoted_at
+                        .with_timezone(&chrono::Local)
+                        .format("%H:%M")
+                ),
+                Err(e) => format!("Could not quote {}: {}", ticker, e),
+            },
+        );
+        self.reload()
+    }
+
+    fn render(&self) -> Result<()> {
+        let (_, height) = terminal::size().unwrap_or((100, 30));
+        let total_value: rust_decimal::Decimal =
+            self.positions.iter().filter_map(|p| p.current_value).sum();
+        let mut lines = vec![
+            format!(
+                "{} Portfolio - {} positions, {}",
+                "📊".cyan().bold(),
+                self.positions.len(),
+                format_currency(total_value)
+            ),
+            String::new(),
+            format!(
+                "  {:<10} {:<9} {:>12} {:>14} {:>14} {:>16} {:>24}",
+                "Ticker", "Type", "Quantity", "Avg Cost", "Price", "Value", "P&L"
+            )
+            .bold()
+            .to_string(),
+        ];
+
+        let list_height = (height as usize).saturating_sub(8).max(3);
+        let start = self.selected.saturating_sub(list_height - 1);
+        for (i, p) in self
+            .positions
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(list_height)
+        {
+            let dash = || "-".to_string();
+            let pnl = match (p.unrealized_pl, p.unrealized_pl_pct) {
+                (Some(amount), Some(pct)) => {
+                    let text = format!("{} ({:+.2}%)", format_currency(amount), pct.round_dp(2));
+                    if amount.is_sign_negative() {
+                        text.red().to_string()
+                    } else {
+                        text.green().to_string()
+                    }
+                }
+                _ => dash(),
+            };
+            let line = format!(
+                "{} {:<10} {:<9} {:>12} {:>14} {:>14} {:>16} {:>24}",
+                if i == self.selected { ">" } else { " " },
+                p.asset.ticker,
+                p.asset.asset_type.as_str(),
+                p.quantity.normalize().to_string(),
+                format_currency(p.average_cost),
+                p.current_price.map(format_currency).unwrap_or_else(dash),
+                p.current_value.map(format_currency).unwrap_or_else(dash),
+                pnl
+            );
+            lines.push(if i == self.selected {
+                line.bold().to_string()
+            } else {
+                line
+            });
+        }
+
+        lines.push(String::new());
+        if let Some(message) = &self.message {
+            lines.push(message.cyan().to_string());
+        }
+        lines.push(KEYS.dimmed().to_string());
+
+        let mut out = std::io::stdout();
+        execute!(
+            out,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        for line in lines {
+            write!(out, "{}\r\n", line)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Handle keys until the user quits or picks a shortcut that leaves the screen
+    async fn run(&mut self) -> Result<Option<RowAction>> {
+        loop {
+            self.render()?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            self.message = None;
+            let ticker = self.selected_ticker();
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(None)
+                }
+                KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') if self.selected + 1 < self.positions.len() => {
+                    self.selected += 1
+                }
+                KeyCode::Enter => return Ok(ticker.map(RowAction::Detail)),
+                KeyCode::Char('i') => return Ok(ticker.map(RowAction::Income)),
+                KeyCode::Char('t') => return Ok(ticker.map(RowAction::AddTransaction)),
+                KeyCode::Char('p') => self.refresh_price().await?,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Run a command below the screen; boxed since the dispatcher got us here
+async fn run_command(command: Commands) {
+    let dispatch: Pin<Box<dyn Future<Output = Result<()>> + '_>> =
+        Box::pin(crate::dispatcher::dispatch_command(&command, false));
+    if let Err(e) = dispatch.await {
+        eprintln!("{} {}", "Error:".red().bold(), e);
+    }
+}
+
+/// Wait for a key after a command's output; false when it was q/Esc
+fn back_to_browser() -> Result<bool> {
+    println!(
+        "\n{}",
+        "Press any key to return to the portfolio, q to leave".dimmed()
+    );
+    terminal::enable_raw_mode()?;
+    let key = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break Ok(key),
+            Ok(_) => continue,
+            Err(e) => break Err(e),
+        }
+    };
+    terminal::disable_raw_mode()?;
+    Ok(!matches!(key?.code, KeyCode::Char('q') | KeyCode::Esc))
+}
+
+/// Run the portfolio browser until the user quits
+pub async fn browse_portfolio() -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        anyhow::bail!("portfolio browse needs an interactive terminal; use portfolio show");
+    }
+    crate::db::init_database(None)?;
+    let mut screen = Screen {
+        conn: crate::db::open_db(None)?,
+        positions: Vec::new(),
+        selected: 0,
+        message: None,
+    };
+    screen.reload()?;
+    if screen.positions.is_empty() {
+        println!("{} No open positions", "ℹ".blue().bold());
+        return Ok(());
+    }
+
+    loop {
+        let action = {
+            let _guard = TerminalGuard::enter()?;
+            screen.run().await?
+        };
+        match action {
+            None => break,
+            Some(RowAction::AddTransaction(ticker)) => {
+                let line = format!("transactions add {} ", ticker);
+                println!(
+                    "{} {}<buy|sell> <quantity> <price> <date>",
+                    "Add the trade with:".dimmed(),
+                    line
+                );
+                if let Ok(mut next) = NEXT_PROMPT.lock() {
+                    *next = Some(line);
+                }
+                break;
+            }
+            Some(RowAction::Detail(ticker)) => {
+                run_command(Commands::Assets {
+                    action: AssetsCommands::Show { ticker },
+                })
+                .await
+            }
+            Some(RowAction::Income(ticker)) => {
+                run_command(Commands::Income {
+                    action: IncomeCommands::Detail {
+                        year: None,
+                        asset: Some(ticker),
+                        table: Default::default(),
+                    },
+                })
+                .await
+            }
+        }
+        if !back_to_browser()? {
+            break;
+        }
+        screen.reload()?;
+    }
+    Ok(())
+}
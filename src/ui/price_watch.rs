@@ -0,0 +1,184 @@
+//! Live quotes screen: held assets with price, day change and P&L, refreshed
+//! on an interval until the user quits.
+
+use anyhow::Result;
+use colored::Colorize;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, terminal};
+use rust_decimal::Decimal;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use super::actions_review::TerminalGuard;
+use crate::pricing::watch::{self, RoundOutcome, WatchRow, MIN_INTERVAL_SECS};
+use crate::utils::format_currency;
+
+const KEYS: &str = "r refresh now · +/- interval · q quit";
+/// How often the countdown is redrawn and keys are read
+const TICK: Duration = Duration::from_millis(250);
+
+fn signed(value: Decimal, text: String) -> String {
+    if value > Decimal::ZERO {
+        text.green().to_string()
+    } else if value < Decimal::ZERO {
+        text.red().to_string()
+    } else {
+        text
+    }
+}
+
+fn row_line(row: &WatchRow) -> String {
+    let price = match (row.price, &row.error) {
+        (Some(price), None) => format_currency(price),
+        (Some(price), Some(_)) => format!("{} !", format_currency(price)),
+        (None, _) => "-".to_string(),
+    };
+    let day = match row.day_change_pct() {
+        Some(pct) => signed(pct, format!("{:>+8.2}%", pct)),
+        None => format!("{:>9}", "-"),
+    };
+    let value = row
+        .market_value()
+        .map(format_currency)
+        .unwrap_or_else(|| "-".to_string());
+    let pnl = match row.pnl().zip(row.pnl_pct()) {
+        Some((amount, pct)) => signed(
+            amount,
+            format!("{:>16} {:>+8.2}%", format_currency(amount), pct),
+        ),
+        None => format!("{:>26}", "-"),
+    };
+    format!(
+        "  {:<10} {:>14} {} {:>16} {}",
+        row.ticker, price, day, value, pnl
+    )
+}
+
+fn render(rows: &[WatchRow], interval: u64, status: &str, next_in: Option<Duration>) -> Result<()> {
+    let totals = watch::totals(rows);
+    let countdown = match next_in {
+        Some(wait) => format!("next refresh in {}s", wait.as_secs()),
+        None => "refreshing...".to_string(),
+    };
+    let mut lines = vec![
+        format!(
+            "{} Live quotes - every {}s, {}",
+            "📈".cyan().bold(),
+            interval,
+            countdown
+        ),
+        String::new(),
+        format!(
+            "  {:<10} {:>14} {:>9} {:>16} {:>26}",
+            "Ticker", "Price", "Day", "Value", "P&L"
+        )
+        .bold()
+        .to_string(),
+    ];
+    lines.extend(rows.iter().map(row_line));
+    lines.push(String::new());
+    lines.push(format!(
+        "  Value: {}   Day: {}   P&L: {}",
+        format_currency(totals.market_value),
+        signed(totals.day_change, format_currency(totals.day_change)),
+        signed(totals.pnl, format_currency(totals.pnl))
+    ));
+    let failed: Vec<String> = rows
+        .iter()
+        .filter_map(|r| r.error.as_ref().map(|e| format!("{}: {}", r.ticker, e)))
+        .collect();
+    if !failed.is_empty() {
+        lines.push(format!("  ! last quote failed - {}", failed.join("; ")));
+    }
+    lines.push(String::new());
+    if !status.is_empty() {
+        lines.push(status.cyan().to_string());
+    }
+    lines.push(KEYS.dimmed().to_string());
+
+    let mut out = std::io::stdout();
+    execute!(
+        out,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(0, 0)
+    )?;
+    for line in lines {
+        write!(out, "{}\r\n", line)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn round_status(outcome: &RoundOutcome) -> String {
+    let at = chrono::Local::now().format("%H:%M:%S");
+    match outcome.cooldown {
+        Some(cooldown) => format!(
+            "Updated {} at {}; Yahoo Finance is rate limiting, waiting {}s",
+            outcome.quoted,
+            at,
+            cooldown.as_secs()
+        ),
+        None if outcome.failed > 0 => format!(
+            "Updated {} at {}, {} failed",
+            outcome.quoted, at, outcome.failed
+        ),
+        None => format!("Updated {} at {}", outcome.quoted, at),
+    }
+}
+
+/// Run the live quotes screen until the user quits
+pub async fn watch_prices(interval_secs: u64) -> Result<()> {
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let mut rows = watch::held_rows(&conn)?;
+    if rows.is_empty() {
+        println!("{} No held assets with live quotes", "ℹ".blue().bold());
+        return Ok(());
+    }
+
+    let mut interval = interval_secs.max(MIN_INTERVAL_SECS);
+    let mut status = String::new();
+    let mut next = Instant::now();
+    let _guard = TerminalGuard::enter()?;
+    loop {
+        if Instant::now() >= next {
+            render(&rows, interval, &status, None)?;
+            let outcome = watch::refresh(&conn, &mut rows).await?;
+            status = round_status(&outcome);
+            next = Instant::now() + watch::next_wait(interval, &outcome);
+        }
+        render(
+            &rows,
+            interval,
+            &status,
+            Some(next.saturating_duration_since(Instant::now())),
+        )?;
+
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Char('r') => next = Instant::now(),
+            KeyCode::Char('+') => {
+                interval += MIN_INTERVAL_SECS;
+                next += Duration::from_secs(MIN_INTERVAL_SECS);
+            }
+            KeyCode::Char('-') if interval > MIN_INTERVAL_SECS => {
+                interval -= MIN_INTERVAL_SECS;
+                next = next
+                    .checked_sub(Duration::from_secs(MIN_INTERVAL_SECS))
+                    .unwrap_or_else(Instant::now);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
@@ -5,6 +5,9 @@ use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::Duration;
 
+/// Cells in a progress bar
+const BAR_WIDTH: usize = 30;
+
 /// Progress tracking data for operations with countable steps
 #[derive(Debug, Clone)]
 pub struct ProgressData {
@@ -16,16 +19,31 @@ impl ProgressData {
     /// Format progress data as string: "(N/M)" or "(N)" if total unknown
     fn format(&self) -> String {
         if let Some(total) = self.total {
-            let percentage = if total > 0 {
-                (self.current * 100) / total
-            } else {
-                0
-            };
+            let percentage = (self.current * 100).checked_div(total).unwrap_or(0);
             format!("({}/{} {}%)", self.current, total, percentage)
         } else {
             format!("({})", self.current)
         }
     }
+
+    /// Format as a bar of `width` cells followed by the counts: "[████░░] 2/3 66%"
+    fn bar(&self, width: usize) -> String {
+        let Some(total) = self.total else {
+            return self.format();
+        };
+        let filled = (self.current * width)
+            .checked_div(total)
+            .unwrap_or(0)
+            .min(width);
+        format!(
+            "[{}{}] {}/{} {}%",
+            "█".repeat(filled),
+            "░".repeat(width - filled),
+            self.current,
+            total,
+            (self.current * 100).checked_div(total).unwrap_or(0)
+        )
+    }
 }
 
 /// Semantic progress events for UI rendering
@@ -66,6 +84,12 @@ pub enum ProgressEvent {
         total: usize,
     },
 
+    /// Aggregate progress of many items - transient bar
+    Bar {
+        label: String,
+        progress: ProgressData,
+    },
+
     /// Generic transient spinner message (fallback for uncategorized updates)
     Spinner { message: String },
 }
@@ -115,6 +139,11 @@ impl ProgressPrinter {
         }
     }
 
+    /// Whether events are drawn (an interactive terminal without --json)
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
     pub fn handle_event(&self, event: &ProgressEvent) {
         if !self.enabled {
             return;
@@ -168,6 +197,9 @@ impl ProgressPrinter {
                 };
                 self.persist_line(&result_str);
             }
+            ProgressEvent::Bar { label, progress } => {
+                self.update_spinner(&format!("{} {}", label, progress.bar(BAR_WIDTH)));
+            }
             ProgressEvent::Spinner { message } => {
                 self.update_spinner(message);
             }
@@ -240,12 +272,19 @@ fn start_spinner_thread(state: Arc<Mutex<SpinnerState>>) -> JoinHandle<()> {
 }
 
 fn render_spinner_line(frame: &str, message: &str) {
-    print!("\r\x1B[2K{} {}", frame, message);
+    clear_line();
+    print!("{} {}", frame, message);
     let _ = io::stdout().flush();
 }
 
+/// Return to the start of the line and blank it; crossterm uses the console
+/// API on Windows consoles that do not understand ANSI escapes
 fn clear_line() {
-    print!("\r\x1B[2K");
+    let _ = crossterm::queue!(
+        io::stdout(),
+        crossterm::cursor::MoveToColumn(0),
+        crossterm::terminal::Clear(crossterm::terminal::ClearType::CurrentLine)
+    );
 }
 
 #[cfg(test)]
@@ -261,6 +300,15 @@ mod tests {
         assert_eq!(data.format(), "(25/100 25%)");
     }
 
+    #[test]
+    fn progress_data_formats_as_bar() {
+        let data = ProgressData {
+            current: 2,
+            total: Some(4),
+        };
+        assert_eq!(data.bar(4), "[██░░] 2/4 50%");
+    }
+
     #[test]
     fn progress_data_formats_without_total() {
         let data = ProgressData {
@@ -8,7 +8,7 @@ use rustyline::highlight::Highlighter;
 use rustyline::hint::{Hinter, HistoryHinter};
 use rustyline::history::DefaultHistory;
 use rustyline::validate::Validator;
-use rustyline::{CompletionType, Config, Context, Editor, Helper};
+use rustyline::{CompletionType, Config, Context, Editor, ExternalPrinter, Helper};
 
 #[allow(dead_code)] // Kept for Phase 3+ TUI implementation
 pub struct CommandHelper {
@@ -140,8 +140,10 @@ impl Readline {
         editor.set_helper(Some(helper));
 
         let history_path = history_path.unwrap_or_else(|| {
-            let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-            PathBuf::from(home).join(".interest/.history")
+            crate::utils::home_dir()
+                .unwrap_or_else(|_| PathBuf::from("."))
+                .join(".interest")
+                .join(".history")
         });
 
         let _ = editor.load_history(&history_path);
@@ -152,8 +154,13 @@ impl Readline {
         })
     }
 
-    pub fn readline(&mut self, prompt: &str) -> Result<String, ReadlineError> {
-        let line = self.editor.readline(prompt)?;
+    /// Read a line that starts out as `initial`, cursor at its end
+    pub fn readline_with_initial(
+        &mut self,
+        prompt: &str,
+        initial: &str,
+    ) -> Result<String, ReadlineError> {
+        let line = self.editor.readline_with_initial(prompt, (initial, ""))?;
         if !line.trim().is_empty() {
             let _ = self.editor.add_history_entry(line.as_str());
             let _ = self.editor.append_history(&self.history_path);
@@ -161,6 +168,18 @@ impl Readline {
         Ok(line)
     }
 
+    /// Printer that writes above the prompt while a line is being edited.
+    /// None when the terminal does not support it (e.g. piped input).
+    pub fn external_printer(&mut self) -> Option<Box<dyn ExternalPrinter + Send>> {
+        match self.editor.create_external_printer() {
+            Ok(printer) => Some(Box::new(printer)),
+            Err(e) => {
+                tracing::debug!("External printer unavailable: {}", e);
+                None
+            }
+        }
+    }
+
     /// Utility for tests to inspect completions without invoking terminal input.
     #[allow(dead_code)] // Kept for Phase 3+ TUI implementation
     pub fn completions(&self, line: &str) -> Vec<String> {
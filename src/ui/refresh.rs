@@ -0,0 +1,373 @@
+//! Cache-first loading and background refresh for the interactive mode.
+//!
+//! In interactive mode, screens render from what is already stored in the
+//! database while a background thread fetches current prices and warms the
+//! current year's tax snapshot and the month-end valuation snapshots. Each
+//! panel shows when its data was last brought up to date.
+
+#![cfg_attr(not(feature = "tui"), allow(dead_code))]
+
+use chrono::{DateTime, Datelike, Local, Utc};
+use colored::Colorize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use crate::ui::crossterm_engine::Spinner;
+
+static CACHE_FIRST: AtomicBool = AtomicBool::new(false);
+static STATE: LazyLock<RefreshState> = LazyLock::new(RefreshState::default);
+
+/// Render from stored data instead of fetching before display
+pub fn set_cache_first(enabled: bool) {
+    CACHE_FIRST.store(enabled, Ordering::Relaxed);
+}
+
+pub fn cache_first() -> bool {
+    CACHE_FIRST.load(Ordering::Relaxed)
+}
+
+/// Shared refresh state for the current session
+pub fn state() -> &'static RefreshState {
+    &STATE
+}
+
+/// Data kept fresh by the background refresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Panel {
+    Prices,
+    Tax,
+}
+
+impl Panel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Panel::Prices => "Prices",
+            Panel::Tax => "Tax snapshot",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct RefreshInner {
+    running: Option<Panel>,
+    rerun: bool,
+    as_of: HashMap<Panel, DateTime<Utc>>,
+    errors: HashMap<Panel, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RefreshState {
+    inner: Arc<Mutex<RefreshInner>>,
+    spinner: Arc<Mutex<Spinner>>,
+}
+
+impl RefreshState {
+    fn lock(&self) -> std::sync::MutexGuard<'_, RefreshInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Claim the refresh worker; if one is already running, ask it to run again
+    fn try_begin(&self, first: Panel) -> bool {
+        let mut inner = self.lock();
+        if inner.running.is_some() {
+            inner.rerun = true;
+            return false;
+        }
+        inner.running = Some(first);
+        true
+    }
+
+    fn set_running(&self, panel: Panel) {
+        self.lock().running = Some(panel);
+    }
+
+    fn finish(&self, panel: Panel, result: Result<bool, String>) {
+        let mut inner = self.lock();
+        match result {
+            Ok(true) => {
+                inner.as_of.insert(panel, Utc::now());
+                inner.errors.remove(&panel);
+            }
+            Ok(false) => {}
+            Err(e) => {
+                inner.errors.insert(panel, e);
+            }
+        }
+    }
+
+    /// Release the worker, unless a rerun was requested meanwhile
+    fn end_cycle(&self) -> bool {
+        let mut inner = self.lock();
+        if std::mem::take(&mut inner.rerun) {
+            inner.running = Some(Panel::Prices);
+            return false;
+        }
+        inner.running = None;
+        true
+    }
+
+    pub fn running(&self) -> Option<Panel> {
+        self.lock().running
+    }
+
+    /// Latest of the session refresh and the stored timestamp
+    pub fn as_of(&self, panel: Panel, stored: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        let refreshed = self.lock().as_of.get(&panel).copied();
+        refreshed.max(stored)
+    }
+
+    pub fn error(&self, panel: Panel) -> Option<String> {
+        self.lock().errors.get(&panel).cloned()
+    }
+
+    /// One-line summary for the prompt; None when idle with nothing to report
+    pub fn status_line(&self) -> Option<String> {
+        let running = self.running()?;
+        let frame = self
+            .spinner
+            .lock()
+            .map(|s| s.tick().to_string())
+            .unwrap_or_default();
+        Some(
+            format!(
+                "{} Refreshing {} in background...",
+                frame,
+                running.label().to_lowercase()
+            )
+            .dimmed()
+            .to_string(),
+        )
+    }
+}
+
+/// "as of" label relative to today
+pub fn format_as_of(ts: DateTime<Utc>, today: chrono::NaiveDate) -> String {
+    let local = ts.with_timezone(&Local);
+    if local.date_naive() == today {
+        format!("as of today {}", local.format("%H:%M"))
+    } else {
+        format!("as of {}", local.format("%Y-%m-%d %H:%M"))
+    }
+}
+
+/// Print a panel's "as of" footer when running cache-first
+pub fn print_as_of(panel: Panel, stored: Option<DateTime<Utc>>) {
+    if !cache_first() {
+        return;
+    }
+    let state = state();
+    let today = Local::now().date_naive();
+    let mut line = match state.as_of(panel, stored) {
+        Some(ts) => format!("{} {}", panel.label(), format_as_of(ts, today)),
+        None => format!("{} not refreshed yet", panel.label()),
+    };
+    if state.running() == Some(panel) {
+        line.push_str(" (refreshing in background)");
+    } else if let Some(err) = state.error(panel) {
+        line.push_str(&format!(" (last refresh failed: {})", err));
+    }
+    println!("\n  {}", line.dimmed());
+}
+
+/// Start the background refresh; `notify` receives one message per finished panel.
+///
+/// Returns false when a refresh was already running (it will run once more).
+pub fn spawn_refresh<F>(notify: F) -> bool
+where
+    F: Fn(String) + Send + 'static,
+{
+    let state = state().clone();
+    if !state.try_begin(Panel::Prices) {
+        return false;
+    }
+
+    std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(rt) => rt,
+            Err(e) => {
+                state.finish(Panel::Prices, Err(e.to_string()));
+                state.end_cycle();
+                return;
+            }
+        };
+
+        loop {
+            state.set_running(Panel::Prices);
+            let (prices, tax) =
+                if crate::daemon::can_delegate() && crate::daemon::status().is_some() {
+                    // The daemon refreshes both panels, one task for every session
+                    let result = crate::daemon::run_task(&crate::daemon::Task::Refresh, |line| {
+                        notify(line.to_string())
+                    })
+                    .map(|_| true)
+                    .map_err(|e| e.to_string());
+                    (result.clone(), result)
+                } else {
+                    refresh_panels(&runtime, |panel| state.set_running(panel), &notify)
+                };
+            state.finish(Panel::Prices, prices);
+            state.finish(Panel::Tax, tax);
+
+            if state.end_cycle() {
+                break;
+            }
+        }
+    });
+
+    true
+}
+
+/// Refresh prices, then the tax snapshot; `on_panel` is told which one is running
+fn refresh_panels<F>(
+    runtime: &tokio::runtime::Runtime,
+    on_panel: impl Fn(Panel),
+    notify: &F,
+) -> (Result<bool, String>, Result<bool, String>)
+where
+    F: Fn(String),
+{
+    on_panel(Panel::Prices);
+    let prices = runtime
+        .block_on(refresh_prices())
+        .map_err(|e| e.to_string());
+    if let Err(e) = &prices {
+        tracing::warn!("Background price refresh failed: {}", e);
+        notify(format!("{} Price refresh failed: {}", "✗".red(), e));
+    } else if prices == Ok(true) {
+        notify(format!(
+            "{} Prices refreshed; rerun the command to see them",
+            "✓".green()
+        ));
+    }
+
+    on_panel(Panel::Tax);
+    let tax = refresh_tax(Local::now().year()).map_err(|e| e.to_string());
+    if let Err(e) = &tax {
+        tracing::warn!("Background tax refresh failed: {}", e);
+    }
+    // Not a panel of its own: it only spares later reports the valuation
+    if let Err(e) = refresh_valuations() {
+        tracing::warn!("Background valuation refresh failed: {}", e);
+    }
+    (prices, tax)
+}
+
+/// One refresh of every panel, outside an interactive session (the daemon's
+/// refresh task)
+pub fn refresh_once<F>(notify: F) -> anyhow::Result<()>
+where
+    F: Fn(String),
+{
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    match refresh_panels(&runtime, |_| {}, &notify) {
+        (Ok(_), Ok(_)) => Ok(()),
+        (Err(e), _) | (_, Err(e)) => Err(anyhow::anyhow!(e)),
+    }
+}
+
+/// Fetch today's prices for currently held assets. Ok(false) when nothing was written.
+async fn refresh_prices() -> anyhow::Result<bool> {
+    let skip_price_fetch = std::env::var("INTEREST_SKIP_PRICE_FETCH")
+        .map(|v| v != "0")
+        .unwrap_or(false);
+    if skip_price_fetch {
+        return Ok(false);
+    }
+
+    crate::db::init_database(None)?;
+    let mut conn = crate::db::open_db(None)?;
+    let report = crate::reports::calculate_portfolio(&conn, None)?;
+    let assets: Vec<_> = report.positions.iter().map(|p| p.asset.clone()).collect();
+    if assets.is_empty() {
+        return Ok(false);
+    }
+
+    let before = crate::db::get_latest_price_update(&conn)?;
+    let today = Local::now().date_naive();
+    crate::pricing::resolver::ensure_prices_available(&mut conn, &assets, (today, today)).await?;
+    // Tickers a `prices update` failed on are retried once their backoff is over
+    crate::pricing::retry_queue::retry_due(&conn).await?;
+    // The resolver tolerates per-ticker failures; only report a refresh when prices were written
+    Ok(crate::db::get_latest_price_update(&conn)? != before)
+}
+
+/// Bring the loss carryforward snapshots up to date so tax reports hit the cache
+fn refresh_tax(year: i32) -> anyhow::Result<bool> {
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    if crate::db::get_earliest_transaction_date(&conn)?.is_none() {
+        return Ok(false);
+    }
+    crate::tax::irpf::generate_annual_report(&conn, year)?;
+    Ok(true)
+}
+
+/// Value every month end since the first trade, so history and performance
+/// read stored snapshots; only months whose data changed are valued again
+fn refresh_valuations() -> anyhow::Result<()> {
+    if crate::db::portfolio::is_scoped() {
+        return Ok(());
+    }
+    crate::db::init_database(None)?;
+    let conn = crate::db::open_db(None)?;
+    let Some(first) = crate::db::get_earliest_transaction_date(&conn)? else {
+        return Ok(());
+    };
+    let today = Local::now().date_naive();
+    let month_ends = crate::reports::history::period_ends(
+        first,
+        today,
+        crate::reports::history::Granularity::Monthly,
+    );
+    crate::reports::snapshots::day_values(&conn, &month_ends, None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_refresh_state_rerun_and_as_of() {
+        let state = RefreshState::default();
+        assert!(state.status_line().is_none());
+
+        assert!(state.try_begin(Panel::Prices));
+        // A second request while running is folded into one more cycle
+        assert!(!state.try_begin(Panel::Prices));
+        assert!(state.status_line().unwrap().contains("prices"));
+
+        state.finish(Panel::Prices, Ok(true));
+        assert!(!state.end_cycle());
+        assert!(state.end_cycle());
+        assert_eq!(state.running(), None);
+
+        let stored = Utc.with_ymd_and_hms(2020, 1, 1, 12, 0, 0).unwrap();
+        assert!(state.as_of(Panel::Prices, Some(stored)).unwrap() > stored);
+        assert_eq!(state.as_of(Panel::Tax, Some(stored)), Some(stored));
+
+        state.finish(Panel::Tax, Err("locked".to_string()));
+        assert_eq!(state.error(Panel::Tax).as_deref(), Some("locked"));
+        state.finish(Panel::Tax, Ok(true));
+        assert!(state.error(Panel::Tax).is_none());
+    }
+
+    #[test]
+    fn test_format_as_of() {
+        let ts = Utc.with_ymd_and_hms(2025, 3, 10, 15, 30, 0).unwrap();
+        let local = ts.with_timezone(&Local);
+        assert!(format_as_of(ts, local.date_naive()).starts_with("as of today "));
+        let later = local.date_naive() + chrono::Duration::days(1);
+        assert_eq!(
+            format_as_of(ts, later),
+            format!("as of {}", local.format("%Y-%m-%d %H:%M"))
+        );
+    }
+}
@@ -3,9 +3,11 @@
 use anyhow::Result;
 use colored::Colorize;
 use rustyline::error::ReadlineError;
+use rustyline::ExternalPrinter;
+use std::sync::{Arc, Mutex};
 
 use crate::dispatcher::dispatch_command;
-use crate::ui::readline;
+use crate::ui::{portfolio_browser, readline, refresh};
 
 /// Parse TUI-style command input into clap Commands
 fn parse_tui_command(input: &str) -> Result<crate::cli::Commands> {
@@ -42,25 +44,47 @@ fn parse_tui_command(input: &str) -> Result<crate::cli::Commands> {
 
 const COMMAND_PATTERNS: &[&[&str]] = &[
     // View & inspect
+    &["whatsnew"],
     &["portfolio", "show"],
+    &["portfolio", "browse"],
+    &["portfolio", "exposure"],
     &["performance", "show"],
+    &["performance", "risk"],
+    &["performance", "history"],
+    &["compare"],
+    &["reports", "annual"],
     &["income", "show"],
     &["income", "detail"],
     &["income", "summary"],
     &["income", "add"],
+    &["income", "reconcile"],
+    &["income", "forecast"],
     &["assets", "show"],
     &["inspect"],
     // Import & sync
     &["import"],
+    &["import", "list"],
+    &["import", "undo"],
     &["import-irpf"],
+    &["watch-imports"],
+    &["sync-b3"],
+    &["daemon"],
     &["prices", "update"],
     &["prices", "import-b3"],
     &["prices", "import-b3-file"],
+    &["prices", "backfill"],
+    &["prices", "update-benchmarks"],
+    &["prices", "update-fx"],
     &["prices", "history"],
+    &["prices", "update-nav"],
+    &["prices", "pvp"],
+    &["prices", "coverage"],
+    &["prices", "watch"],
     &["assets", "sync-maisretorno"],
     // Resolve & reconcile
     &["inconsistencies", "list"],
     &["inconsistencies", "resolve"],
+    &["inconsistencies", "positions"],
     &["tickers", "list-unknown"],
     &["tickers", "resolve"],
     // Manage & maintain
@@ -68,23 +92,285 @@ const COMMAND_PATTERNS: &[&[&str]] = &[
     &["assets", "add"],
     &["assets", "set-type"],
     &["assets", "set-name"],
+    &["assets", "set-cnpj"],
+    &["assets", "set-currency"],
+    &["assets", "enrich-cnpj"],
+    &["assets", "set-value"],
+    &["rebalance", "suggest"],
+    &["assets", "tag"],
+    &["assets", "untag"],
+    &["assets", "tags"],
     &["transactions", "add"],
     &["transactions", "list"],
+    &["transactions", "show"],
+    &["transactions", "edit"],
+    &["transactions", "delete"],
+    &["journal", "add"],
+    &["journal", "list"],
+    &["journal", "link"],
+    &["db", "export-json"],
+    &["db", "import-json"],
+    &["db", "encrypt"],
+    &["db", "decrypt"],
     &["process-terms"],
+    &["recalculate"],
+    &["terms", "show"],
+    &["terms", "set"],
+    &["options", "show"],
+    &["options", "process"],
+    &["options", "set"],
+    &["subscriptions"],
+    &["tesouro", "show"],
+    &["tesouro", "redemptions"],
+    &["fixed-income", "show"],
+    &["fixed-income", "set"],
     &["actions", "split"],
     &["actions", "apply"],
+    &["actions", "unapply"],
+    &["actions", "detect"],
+    &["actions", "review"],
     // Reports & tax
     &["tax", "report"],
     &["tax", "summary"],
     &["tax", "calculate"],
+    &["tax", "preview"],
+    &["tax", "exemption"],
+    &["tax", "withholding"],
+    &["tax", "gcap"],
+    &["tax", "rules"],
+    &["tax", "ledger"],
+    &["tax", "darf"],
+    &["tax", "mark-paid"],
+    &["tax", "simulate"],
+    &["tax", "fixed-income"],
     // Utilities & session
     &["prices", "clear-cache"],
     &["tickers", "status"],
+    &["tickers", "versions"],
+    &["portfolios", "list"],
+    &["portfolios", "add"],
+    &["portfolios", "assign"],
+    &["portfolios", "set-declarant"],
+    &["sandbox", "status"],
+    &["sandbox", "promote"],
+    &["sandbox", "discard"],
+    &["version"],
     &["help"],
+    &["refresh"],
     &["exit"],
     &["quit"],
 ];
 
+/// Status bar line with this month's sales vs each category's exemption
+fn sales_status_line() -> Option<String> {
+    use crate::tax::sales_monitor::ExemptionStatus;
+    use crate::utils::format_currency;
+    use chrono::Datelike;
+
+    let conn = crate::db::open_db(None).ok()?;
+    let today = chrono::Local::now().date_naive();
+    let usage = crate::tax::sales_monitor::month_usage(&conn, today.year(), today.month()).ok()?;
+
+    let parts: Vec<String> = usage
+        .categories
+        .iter()
+        .filter_map(|c| {
+            let text = format!(
+                "{} {:02}/{}: {} of {}",
+                c.category.display_name(),
+                usage.month,
+                usage.year,
+                format_currency(c.exemption_sales),
+                format_currency(c.limit)
+            );
+            Some(match c.status? {
+                ExemptionStatus::Ok => {
+                    format!("{} ({} left)", text, format_currency(c.remaining()))
+                        .dimmed()
+                        .to_string()
+                }
+                ExemptionStatus::Approaching => format!("⚠ {} (approaching exemption limit)", text)
+                    .yellow()
+                    .to_string(),
+                ExemptionStatus::Exceeded => {
+                    format!("✗ {} (exemption exceeded)", text).red().to_string()
+                }
+            })
+        })
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("\n"))
+}
+
+/// Home panel: what changed since the last run and what is due soon.
+/// Opening the interactive mode counts as a run.
+fn whatsnew_panel() -> Option<String> {
+    use crate::reports::whatsnew;
+    use crate::utils::format_currency;
+
+    let conn = crate::db::open_db(None).ok()?;
+    let now = chrono::Utc::now().naive_utc();
+    let today = chrono::Local::now().date_naive();
+    let (since, first_run) = whatsnew::last_run(&conn, now).ok()?;
+    let briefing = whatsnew::briefing(&conn, since, first_run, today).ok()?;
+    whatsnew::mark_run(&conn, now).ok()?;
+    if briefing.is_quiet() {
+        return None;
+    }
+
+    let since = since.and_utc().with_timezone(&chrono::Local);
+    let mut panel = vec![format!(
+        "{}",
+        format!("What's new since {}:", since.format("%d/%m %H:%M")).bold()
+    )];
+    let prices = &briefing.prices;
+    if prices.prices > 0 {
+        panel.push(format!(
+            "  {} prices for {} assets{}",
+            prices.prices,
+            prices.assets,
+            prices
+                .latest_date
+                .map(|d| format!(" (latest {})", d.format("%d/%m/%Y")))
+                .unwrap_or_default()
+        ));
+    }
+    if !briefing.income.is_empty() {
+        panel.push(format!(
+            "  {} income payments, {}",
+            briefing.income.len(),
+            format_currency(briefing.income_total)
+        ));
+    }
+    if !briefing.corporate_actions.is_empty() {
+        let pending = briefing
+            .corporate_actions
+            .iter()
+            .filter(|a| a.pending_review)
+            .count();
+        panel.push(format!(
+            "  {} corporate actions ({} pending review)",
+            briefing.corporate_actions.len(),
+            pending
+        ));
+    }
+    if !briefing.inconsistencies.is_empty() {
+        panel.push(format!(
+            "  {} new inconsistencies ({} open)",
+            briefing.inconsistencies.len(),
+            briefing.open_inconsistencies
+        ));
+    }
+    for o in &briefing.obligations {
+        let line = format!(
+            "  {} {} on {}{}",
+            o.kind.label(),
+            o.subject,
+            o.date.format("%d/%m/%Y"),
+            o.amount
+                .map(|a| format!(", {}", format_currency(a)))
+                .unwrap_or_default()
+        );
+        panel.push(if o.overdue {
+            format!("{} (overdue)", line).red().to_string()
+        } else {
+            line
+        });
+    }
+    Some(panel.join("\n") + "\n")
+}
+
+/// Status bar line with the income expected over the next 12 months
+fn income_status_line() -> Option<String> {
+    use crate::utils::format_currency;
+
+    let conn = crate::db::open_db(None).ok()?;
+    let today = chrono::Local::now().date_naive();
+    let forecast = crate::reports::income_forecast::forecast(&conn, today, 12, None).ok()?;
+    if forecast.payments.is_empty() {
+        return None;
+    }
+    let (low, high) = forecast.total_range();
+    Some(
+        format!(
+            "Income next 12 months: ~{} ({} – {}, see income forecast)",
+            format_currency(forecast.total()),
+            format_currency(low),
+            format_currency(high)
+        )
+        .dimmed()
+        .to_string(),
+    )
+}
+
+/// Commands after which cached prices and tax snapshots may be stale
+fn changes_data(cmd: &crate::cli::Commands) -> bool {
+    use crate::cli::Commands;
+    matches!(
+        cmd,
+        Commands::Import { .. }
+            | Commands::ImportIrpf { .. }
+            | Commands::WatchImports { .. }
+            | Commands::SyncB3 { .. }
+            | Commands::Transactions { .. }
+            | Commands::Actions { .. }
+            | Commands::Income { .. }
+            | Commands::Prices { .. }
+            | Commands::Db { .. }
+            | Commands::ProcessTerms
+            | Commands::Recalculate { .. }
+            | Commands::Terms { .. }
+            | Commands::Options { .. }
+            | Commands::FixedIncome { .. }
+            | Commands::Inconsistencies { .. }
+    )
+}
+
+/// Commands that fetch or sync data, run by the daemon when one is listening
+fn runs_in_daemon(cmd: &crate::cli::Commands) -> bool {
+    use crate::cli::{Commands, PriceCommands};
+    match cmd {
+        Commands::SyncB3 { .. } => true,
+        Commands::Prices { action } => matches!(
+            action,
+            PriceCommands::Update { .. }
+                | PriceCommands::ImportB3 { .. }
+                | PriceCommands::ImportB3File { .. }
+                | PriceCommands::Backfill { .. }
+                | PriceCommands::UpdateBenchmarks { .. }
+                | PriceCommands::UpdateFx { .. }
+                | PriceCommands::UpdateNav { .. }
+        ),
+        _ => false,
+    }
+}
+
+/// Tasks the daemon is working on, for the prompt; None when it is idle or absent
+fn daemon_status_line() -> Option<String> {
+    let tasks = crate::daemon::status()?;
+    let running = tasks.iter().find(|t| t.started_at.is_some())?;
+    let mut line = format!("⚙ Daemon: {}", running.task);
+    if let Some(last) = &running.last_line {
+        line.push_str(&format!(" - {}", last));
+    }
+    let queued = tasks.len() - 1;
+    if queued > 0 {
+        line.push_str(&format!(" (+{} queued)", queued));
+    }
+    Some(line.dimmed().to_string())
+}
+
+/// Kick off a background refresh, reporting each finished panel above the prompt
+fn start_refresh(printer: &Arc<Mutex<Option<Box<dyn ExternalPrinter + Send>>>>) {
+    let printer = Arc::clone(printer);
+    refresh::spawn_refresh(move |msg| {
+        if let Ok(mut guard) = printer.lock() {
+            if let Some(p) = guard.as_mut() {
+                let _ = p.print(msg);
+            }
+        }
+    });
+}
+
 /// Launch the interactive TUI REPL.
 pub async fn launch_tui() -> Result<()> {
     println!("{}", "Interest - Interactive Mode".bold());
@@ -94,10 +380,51 @@ pub async fn launch_tui() -> Result<()> {
         "/exit".cyan()
     );
 
+    if let Some(panel) = whatsnew_panel() {
+        println!("{}", panel);
+    }
+
     let mut rl = readline::Readline::new(COMMAND_PATTERNS, None)?;
+    let mut last_status: Option<String> = None;
+    // The forecast walks the whole income history: only redone after changes
+    let mut income_line = income_status_line();
+
+    // Screens render from stored data; prices and tax snapshots refresh behind them
+    refresh::set_cache_first(true);
+    let printer = Arc::new(Mutex::new(rl.external_printer()));
+    let daemon = crate::daemon::can_delegate() && crate::daemon::status().is_some();
+    if daemon {
+        println!(
+            "{}",
+            "⚙ Connected to the daemon: price updates, syncs and refreshes run there\n".dimmed()
+        );
+    }
+    start_refresh(&printer);
 
     loop {
-        match rl.readline("interest> ") {
+        if let Some(line) = refresh::state().status_line() {
+            println!("{}", line);
+        }
+        if daemon {
+            if let Some(line) = daemon_status_line() {
+                println!("{}", line);
+            }
+        }
+
+        // Only reprint the status bar when it changes (e.g., after an import)
+        let lines: Vec<String> = sales_status_line()
+            .into_iter()
+            .chain(income_line.clone())
+            .collect();
+        let status = (!lines.is_empty()).then(|| lines.join("\n"));
+        if status.is_some() && status != last_status {
+            println!("{}", status.as_deref().unwrap_or_default());
+        }
+        last_status = status;
+
+        // A row shortcut in the portfolio browser may leave a command to finish
+        let initial = portfolio_browser::take_next_prompt().unwrap_or_default();
+        match rl.readline_with_initial("interest> ", &initial) {
             Ok(line) => {
                 let trimmed = line.trim();
                 if trimmed.is_empty() {
@@ -110,11 +437,37 @@ pub async fn launch_tui() -> Result<()> {
                     break;
                 }
 
+                if trimmed == "/refresh" || trimmed == "refresh" {
+                    start_refresh(&printer);
+                    continue;
+                }
+
                 match parse_tui_command(trimmed) {
                     Ok(cmd) => {
-                        if let Err(e) = dispatch_command(&cmd, false).await {
+                        let delegated = runs_in_daemon(&cmd)
+                            && crate::daemon::can_delegate()
+                            && crate::daemon::status().is_some();
+                        let result = if delegated {
+                            let args = trimmed
+                                .strip_prefix('/')
+                                .unwrap_or(trimmed)
+                                .split_whitespace()
+                                .map(str::to_string)
+                                .collect();
+                            crate::daemon::run_task(
+                                &crate::daemon::Task::Command { args },
+                                |line| println!("{}", line),
+                            )
+                        } else {
+                            dispatch_command(&cmd, false).await
+                        };
+                        if let Err(e) = result {
                             eprintln!("{} {}", "Error:".red().bold(), e);
                         }
+                        if changes_data(&cmd) {
+                            income_line = income_status_line();
+                            start_refresh(&printer);
+                        }
                     }
                     Err(e) => {
                         eprintln!("{} {}", "Parse error:".yellow().bold(), e);
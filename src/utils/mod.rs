@@ -4,6 +4,17 @@
 //! display of currency and decimal values throughout the application.
 
 use rust_decimal::Decimal;
+use std::path::PathBuf;
+
+/// The user's home directory: `HOME` when set, else the platform's own
+/// (`%USERPROFILE%` on Windows, where `HOME` usually does not exist)
+pub fn home_dir() -> anyhow::Result<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .or_else(dir_spec::home)
+        .ok_or_else(|| anyhow::anyhow!("Could not find the home directory; set HOME"))
+}
 
 /// Currency symbol options for formatting
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
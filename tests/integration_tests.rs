@@ -24,7 +24,7 @@ use tempfile::TempDir;
 mod cli_helpers;
 use cli_helpers::{
     add_asset, add_income, add_transaction, base_cmd, cache_root_for_home, list_transactions_json,
-    portfolio_json, run_cmd, setup_test_tickers_cache, tax_report_json,
+    portfolio_json, run_cmd, run_cmd_json, setup_test_tickers_cache, tax_report_json,
 };
 mod sqlite_helpers;
 use sqlite_helpers::{
@@ -927,7 +927,7 @@ fn test_11b_split_then_bonus_calculates_correctly() -> Result<()> {
         .assert()
         .success();
 
-    // Apply corporate actions (only bonus creates transactions)
+    // Apply corporate actions (a bonus counts once applied)
     base_cmd(&home)
         .arg("actions")
         .arg("apply")
@@ -935,27 +935,17 @@ fn test_11b_split_then_bonus_calculates_correctly() -> Result<()> {
         .assert()
         .success();
 
+    // Applying leaves the recorded trades as they were
     let transactions = load_transactions(&home, "TEST11")?;
-    assert_eq!(transactions.len(), 2, "Should have original buy + bonus");
-    let bonus_tx = transactions
-        .iter()
-        .find(|tx| {
-            tx.notes
-                .as_ref()
-                .map(|n| n.contains("Bonus"))
-                .unwrap_or(false)
-        })
-        .context("missing bonus transaction")?;
-    let bonus_qty = bonus_tx.quantity;
-
-    // If split was correctly applied before bonus calculation:
-    // Position = 100 shares, after 1:2 split = 200 shares
-    // Bonus 10:11 on 200 shares = 200 * 11/10 - 200 = 20 bonus shares
-    assert_eq!(
-        bonus_qty,
-        dec!(20),
-        "Bonus should be 20 shares (based on 200 split-adjusted shares, not 100 raw)"
-    );
+    assert_eq!(transactions.len(), 1, "Should have only the original buy");
+    assert_eq!(transactions[0].quantity, dec!(100));
+    let conn = open_conn(&home)?;
+    let pending: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM corporate_actions WHERE applied_at IS NULL",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(pending, 0, "Both actions should be applied");
 
     // Verify portfolio snapshot BEFORE split (2025-02-09): 100 shares
     let mut cmd_before_split = base_cmd(&home);
@@ -1039,6 +1029,47 @@ fn test_11b_split_then_bonus_calculates_correctly() -> Result<()> {
     Ok(())
 }
 
+/// A wrongly applied bonus can be taken back out without touching trades
+#[test]
+fn test_11c_unapply_bonus() -> Result<()> {
+    let home = TempDir::new()?;
+    add_transaction(&home, "TEST11", "buy", "100", "10", "2024-01-15", false)?;
+    run_cmd(
+        &home,
+        &["actions", "bonus", "add", "TEST11", "20", "2024-03-15"],
+    )?;
+    run_cmd(
+        &home,
+        &["actions", "split", "add", "TEST11", "120", "2024-05-10"],
+    )?;
+    run_cmd(&home, &["actions", "apply", "TEST11"])?;
+
+    let held = || -> Result<f64> {
+        let conn = open_conn(&home)?;
+        Ok(conn.query_row(
+            "SELECT quantity FROM current_positions WHERE ticker = 'TEST11'",
+            [],
+            |row| row.get(0),
+        )?)
+    };
+    assert_eq!(held()?, 240.0);
+
+    let unapplied = run_cmd_json(&home, &["--json", "actions", "unapply", "1"])?;
+    assert_eq!(unapplied["type"], "BONUS");
+    assert_eq!(held()?, 220.0);
+    assert!(run_cmd(&home, &["actions", "unapply", "1"]).is_err());
+    // Splits act at query time: removing them is the undo
+    assert!(run_cmd(&home, &["actions", "unapply", "2"]).is_err());
+
+    let transactions = load_transactions(&home, "TEST11")?;
+    assert_eq!(transactions.len(), 1);
+    assert_eq!(transactions[0].quantity, dec!(100));
+
+    run_cmd(&home, &["actions", "apply", "TEST11"])?;
+    assert_eq!(held()?, 240.0);
+    Ok(())
+}
+
 #[test]
 fn test_12_desdobro_absolute_adjustment() -> Result<()> {
     let home = TempDir::new()?;
@@ -1163,6 +1194,77 @@ fn test_14_atualizacao_absolute_adjustment() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_14_atualizacao_confirmed_position_adjustment() -> Result<()> {
+    let home = TempDir::new()?;
+
+    let import_result = run_import_json(&home, "tests/data/14_atualizacao_inference.xlsx");
+    assert!(assert_json_success(&import_result));
+
+    // Unmatched Atualização is recorded for confirmation, not applied
+    let issues = run_cmd_json(
+        &home,
+        &[
+            "--json",
+            "inconsistencies",
+            "list",
+            "--type",
+            "POSITION_ADJUSTMENT",
+        ],
+    )?;
+    let issues = issues.as_array().context("issues array")?;
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0]["ticker"], "BRCR11");
+    let id = issues[0]["id"].as_i64().context("issue id")?.to_string();
+
+    // The shares are never assumed to be free: the user says where they came from
+    let unspecified = base_cmd(&home)
+        .args(["inconsistencies", "resolve", &id, "--set", "quantity=22"])
+        .output()?;
+    assert!(!unspecified.status.success());
+    assert!(String::from_utf8_lossy(&unspecified.stderr).contains("kind is required"));
+    let without_price = base_cmd(&home)
+        .args(["inconsistencies", "resolve", &id])
+        .args(["--set", "quantity=22", "--set", "kind=trade"])
+        .output()?;
+    assert!(!without_price.status.success());
+
+    run_cmd(
+        &home,
+        &[
+            "inconsistencies",
+            "resolve",
+            &id,
+            "--set",
+            "quantity=22",
+            "--set",
+            "kind=trade",
+            "--set",
+            "price_per_unit=11",
+        ],
+    )?;
+
+    let portfolio = portfolio_json(&home)?;
+    let position = portfolio["positions"]
+        .as_array()
+        .context("positions missing")?
+        .iter()
+        .find(|p| p["ticker"] == "BRCR11")
+        .context("BRCR11 position missing")?;
+    assert_eq!(decimal_from_value(&position["quantity"])?, dec!(400));
+    assert_eq!(decimal_from_value(&position["total_cost"])?, dec!(4022));
+
+    // A missing trade is a transaction, not a made-up split
+    let transactions = load_transactions(&home, "BRCR11")?;
+    assert_eq!(transactions.len(), 2);
+    assert_eq!(transactions[1].transaction_type, "BUY");
+    assert_eq!(transactions[1].quantity, dec!(22));
+    let actions = run_cmd_json(&home, &["--json", "actions", "split", "list", "BRCR11"])?;
+    assert_eq!(actions.as_array().map(Vec::len), Some(0));
+
+    Ok(())
+}
+
 #[test]
 fn test_03_term_contract_sold_before_expiry() -> Result<()> {
     let home = TempDir::new()?;
@@ -1924,6 +2026,55 @@ fn test_07_capital_return() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_import_keeps_asset_type_from_registry() -> Result<()> {
+    let home = TempDir::new()?;
+    run_cmd(&home, &["assets", "list"])?;
+
+    // MXRF11 is missing from the B3 list; only the Mais Retorno registry,
+    // read inside the import's write transaction, knows it is a FII
+    let cache = TempDir::new()?;
+    setup_test_tickers_cache(cache.path());
+    let tickers = cache
+        .path()
+        .join("interest")
+        .join("tickers")
+        .join("tickers.csv");
+    let listed = std::fs::read(&tickers)?;
+    let kept: Vec<&[u8]> = listed
+        .split(|b| *b == b'\n')
+        .filter(|line| !line.starts_with(b"MXRF11;"))
+        .collect();
+    std::fs::write(&tickers, kept.join(&b'\n'))?;
+    open_conn(&home)?.execute(
+        "INSERT INTO asset_registry (source, ticker, asset_type, name)
+         VALUES ('MAIS_RETORNO', 'MXRF11', 'FII', 'MAXI RENDA')",
+        [],
+    )?;
+
+    let output = base_cmd(&home)
+        .env("XDG_CACHE_HOME", cache.path())
+        .env("INTEREST_SKIP_AMBIMA", "1")
+        .args(["import", "tests/data/07_capital_return.xlsx"])
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    // A second connection cannot open while the import holds the database
+    assert!(
+        !stderr.contains("Failed to apply database schema"),
+        "{}",
+        stderr
+    );
+
+    let asset_type: String = open_conn(&home)?.query_row(
+        "SELECT asset_type FROM assets WHERE ticker = 'MXRF11'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert_eq!(asset_type, "FII");
+    Ok(())
+}
+
 #[test]
 fn test_10_day_trade_detection() -> Result<()> {
     let home = TempDir::new()?;
@@ -2513,3 +2664,396 @@ fn test_portfolio_show_fetches_cached_cotahist_and_shows_prices() -> Result<()>
 
     Ok(())
 }
+
+#[test]
+fn test_prices_replay_recorded_yahoo_responses() -> Result<()> {
+    let home = TempDir::new()?;
+    add_asset(&home, "PETR4", "STOCK")?;
+
+    let cassette = "tests/fixtures/cassettes/yahoo_PETR4.json";
+    let out = base_cmd(&home)
+        .env("INTEREST_CASSETTE", cassette)
+        .args(["prices", "update"])
+        .output()?;
+    assert!(out.status.success());
+
+    let conn = open_conn(&home)?;
+    let (price, source): (String, String) = conn.query_row(
+        "SELECT CAST(close_price AS TEXT), source FROM price_history ph
+         JOIN assets a ON ph.asset_id = a.id WHERE a.ticker = 'PETR4'",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!(Decimal::from_str(&price)?, dec!(34.75));
+    assert_eq!(source, "YAHOO");
+
+    let out = base_cmd(&home)
+        .env("INTEREST_CASSETTE", cassette)
+        .args(["prices", "history", "PETR4"])
+        .args(["--from", "2025-01-01", "--to", "2025-01-10"])
+        .output()?;
+    assert!(String::from_utf8_lossy(&out.stdout).contains("Total: 7 price points"));
+
+    // Recorded failures replay too, and nothing falls through to the network
+    for (ticker, error) in [
+        ("XXXX3", "404 Not Found"),
+        ("VALE3", "No recorded Yahoo Finance response"),
+    ] {
+        let out = base_cmd(&home)
+            .env("INTEREST_CASSETTE", cassette)
+            .args(["prices", "history", ticker])
+            .args(["--from", "2025-01-01", "--to", "2025-01-10"])
+            .output()?;
+        assert!(!out.status.success());
+        assert!(String::from_utf8_lossy(&out.stderr).contains(error));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_transactions_edit_and_delete_rescale_splits() -> Result<()> {
+    let home = TempDir::new()?;
+    add_transaction(&home, "ITSA4", "buy", "100", "10", "2024-01-10", false)?;
+    add_transaction(&home, "ITSA4", "buy", "50", "10", "2024-02-10", false)?;
+    add_transaction(&home, "PETR4", "buy", "10", "30", "2024-02-10", false)?;
+    run_cmd(
+        &home,
+        &["actions", "split", "add", "ITSA4", "150", "2024-03-01"],
+    )?;
+
+    let listed = run_cmd(
+        &home,
+        &[
+            "--json",
+            "transactions",
+            "list",
+            "--from",
+            "2024-02-01",
+            "--source",
+            "manual",
+        ],
+    )?;
+    let listed: Value = serde_json::from_slice(&listed.stdout)?;
+    let ids: Vec<i64> = listed
+        .as_array()
+        .context("list is an array")?
+        .iter()
+        .filter_map(|row| row["id"].as_i64())
+        .collect();
+    assert_eq!(ids, vec![2, 3]);
+
+    let split_adjustment = || -> Result<Decimal> {
+        let conn = open_conn(&home)?;
+        let value: String = conn.query_row(
+            "SELECT quantity_adjustment FROM corporate_actions",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(Decimal::from_str(&value)?)
+    };
+
+    // Without a terminal to ask on, the choice has to be given
+    let mut cmd = base_cmd(&home);
+    cmd.args(["--json", "transactions", "edit", "2", "--quantity", "55"]);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "--rescale-actions or --keep-actions",
+    ));
+    assert_eq!(split_adjustment()?, dec!(150));
+    assert_eq!(load_transactions(&home, "ITSA4")?[1].quantity, dec!(50));
+
+    let edited = run_cmd(
+        &home,
+        &[
+            "--json",
+            "transactions",
+            "edit",
+            "2",
+            "--quantity",
+            "55",
+            "--rescale-actions",
+        ],
+    )?;
+    let edited: Value = serde_json::from_slice(&edited.stdout)?;
+    assert_eq!(edited["transaction"]["total_cost"], "550");
+    assert_eq!(edited["rescaled_actions"][0]["action_id"], 1);
+    assert_eq!(edited["rescaled_actions"][0]["from"], "150");
+    assert_eq!(edited["rescaled_actions"][0]["to"], "155");
+    assert_eq!(split_adjustment()?, dec!(155));
+
+    run_cmd(&home, &["transactions", "delete", "1", "--rescale-actions"])?;
+    assert_eq!(split_adjustment()?, dec!(55));
+    let txs = load_transactions(&home, "ITSA4")?;
+    assert_eq!(txs.len(), 1);
+
+    let kept = run_cmd(
+        &home,
+        &[
+            "--json",
+            "transactions",
+            "edit",
+            "2",
+            "--quantity",
+            "60",
+            "--keep-actions",
+        ],
+    )?;
+    let kept: Value = serde_json::from_slice(&kept.stdout)?;
+    assert_eq!(kept["rescaled_actions"], serde_json::json!([]));
+    assert_eq!(kept["kept_actions"][0]["to"], "60");
+    assert_eq!(split_adjustment()?, dec!(55));
+
+    let mut cmd = base_cmd(&home);
+    cmd.args(["transactions", "show", "1"]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Transaction 1 not found"));
+
+    Ok(())
+}
+
+#[test]
+fn test_import_ofx_statement_trades_and_income() -> Result<()> {
+    let home = TempDir::new()?;
+    let ofx = "OFXHEADER:100\nDATA:OFXSGML\nVERSION:102\n\n<OFX>\n\
+        <SIGNONMSGSRSV1><SONRS><FI><ORG>CORRETORA X</FI></SONRS></SIGNONMSGSRSV1>\n\
+        <INVSTMTMSGSRSV1><INVSTMTTRNRS><INVSTMTRS><INVTRANLIST>\n\
+        <BUYSTOCK><INVBUY><INVTRAN><FITID>1<DTTRADE>20240102</INVTRAN>\n\
+        <SECID><UNIQUEID>BRBBASACNOR3<UNIQUEIDTYPE>ISIN</SECID>\n\
+        <UNITS>200<UNITPRICE>27.10<COMMISSION>2.00<TOTAL>-5422.00</INVBUY><BUYTYPE>BUY</BUYSTOCK>\n\
+        <INCOME><INVTRAN><FITID>2<DTTRADE>20240301<MEMO>DIVIDENDOS</INVTRAN>\n\
+        <SECID><UNIQUEID>BRBBASACNOR3<UNIQUEIDTYPE>ISIN</SECID>\n\
+        <INCOMETYPE>DIV<TOTAL>84.00</INCOME>\n\
+        </INVTRANLIST></INVSTMTRS></INVSTMTTRNRS></INVSTMTMSGSRSV1>\n\
+        <SECLISTMSGSRSV1><SECLIST><STOCKINFO><SECINFO>\n\
+        <SECID><UNIQUEID>BRBBASACNOR3<UNIQUEIDTYPE>ISIN</SECID>\n\
+        <SECNAME>BANCO DO BRASIL ON<TICKER>BBAS3</SECINFO></STOCKINFO></SECLIST></SECLISTMSGSRSV1>\n\
+        </OFX>\n";
+    let path = home.path().join("extrato.ofx");
+    std::fs::write(&path, ofx)?;
+
+    let output = run_cmd(&home, &["--json", "import", path.to_str().unwrap()])?;
+    let value: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(value["data"]["imported"], 1);
+    assert_eq!(value["data"]["imported_income"], 1);
+
+    let txs = load_transactions(&home, "BBAS3")?;
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0].quantity, dec!(200));
+    assert_eq!(txs[0].price_per_unit, dec!(27.10));
+
+    let conn = open_conn(&home)?;
+    let (source, total): (String, String) = conn.query_row(
+        "SELECT source, CAST(total_amount AS TEXT) FROM income_events",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    assert_eq!(source, "OFX");
+    assert_eq!(Decimal::from_str(&total)?, dec!(84));
+
+    // The same statement again records nothing twice
+    let output = run_cmd(&home, &["--json", "import", path.to_str().unwrap()])?;
+    let value: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(value["data"]["imported"], 0);
+    assert_eq!(value["data"]["skipped_income"], 1);
+    assert_eq!(load_transactions(&home, "BBAS3")?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_inconsistencies_positions_finds_first_divergent_month() -> Result<()> {
+    let home = TempDir::new()?;
+    for args in [
+        ["PETR4", "buy", "100", "30", "2024-01-10"],
+        ["PETR4", "buy", "100", "32", "2024-03-05"],
+    ] {
+        let mut cmd = vec!["transactions", "add"];
+        cmd.extend(args);
+        run_cmd(&home, &cmd)?;
+    }
+
+    // B3 already reported the second lot at the end of February
+    let conn = open_conn(&home)?;
+    for (date, quantity) in [
+        ("2024-01-31", "100"),
+        ("2024-02-29", "200"),
+        ("2024-03-28", "200"),
+    ] {
+        conn.execute(
+            "INSERT INTO position_statements (asset_id, statement_date, quantity, source)
+             SELECT id, ?1, ?2, 'POSICAO' FROM assets WHERE ticker = 'PETR4'",
+            rusqlite::params![date, quantity],
+        )?;
+    }
+
+    let output = run_cmd(&home, &["--json", "inconsistencies", "positions"])?;
+    let value: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(value["months"].as_array().unwrap().len(), 3);
+    let petr4 = &value["assets"][0];
+    assert_eq!(petr4["ticker"], "PETR4");
+    assert_eq!(petr4["first_divergence"]["statement_date"], "2024-02-29");
+    assert_eq!(
+        decimal_from_value(&petr4["first_divergence"]["difference"])?,
+        dec!(-100)
+    );
+    assert!(decimal_from_value(&petr4["cells"][2]["difference"])?.is_zero());
+
+    let output = run_cmd(&home, &["inconsistencies", "positions"])?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("First divergence per asset"));
+    assert!(text.contains("29/02/2024"));
+
+    Ok(())
+}
+
+#[test]
+fn test_import_utf16_crlf_csv_as_saved_by_excel_on_windows() -> Result<()> {
+    let home = TempDir::new()?;
+    let csv = "Data Negociação;Código de Negociação;C/V;Quantidade;Preço;Valor Total;Taxa\r\n\
+               02/01/2024;WINC3;C;10;10,50;105,00;0,00\r\n";
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(csv.encode_utf16().flat_map(u16::to_le_bytes));
+    let path = home.path().join("negociacao.csv");
+    std::fs::write(&path, bytes)?;
+
+    run_cmd(&home, &["import", path.to_str().unwrap()])?;
+    let txs = load_transactions(&home, "WINC3")?;
+    assert_eq!(txs.len(), 1);
+    assert_eq!(txs[0].quantity, dec!(10));
+    assert_eq!(txs[0].price_per_unit, dec!(10.50));
+
+    Ok(())
+}
+
+#[test]
+fn test_tax_report_by_declarant_splits_gains_income_and_holdings() -> Result<()> {
+    let home = TempDir::new()?;
+    add_asset(&home, "PETR4", "STOCK")?;
+    add_asset(&home, "VALE3", "STOCK")?;
+    run_cmd(
+        &home,
+        &["portfolios", "add", "bruno", "--declarant", "Bruno"],
+    )?;
+    run_cmd(&home, &["portfolios", "add", "ana", "--declarant", "Ana"])?;
+
+    // PETR4 is held in both portfolios and sold from both in March: R$ 45,000
+    // together, but each declarant stays under the R$ 20,000 exemption only
+    // on their own sales
+    for (portfolio, sold) in [("bruno", "500"), ("ana", "1000")] {
+        let trades = [
+            ("buy", "1000", "20", "2025-01-10"),
+            ("sell", sold, "30", "2025-03-10"),
+        ];
+        for (tx_type, quantity, price, date) in trades {
+            run_cmd(
+                &home,
+                &[
+                    "--portfolio",
+                    portfolio,
+                    "transactions",
+                    "add",
+                    "PETR4",
+                    tx_type,
+                    quantity,
+                    price,
+                    date,
+                ],
+            )?;
+        }
+    }
+    run_cmd(
+        &home,
+        &[
+            "--portfolio",
+            "bruno",
+            "income",
+            "add",
+            "PETR4",
+            "DIVIDEND",
+            "100",
+            "2025-05-20",
+        ],
+    )?;
+    run_cmd(
+        &home,
+        &[
+            "--portfolio",
+            "ana",
+            "income",
+            "add",
+            "PETR4",
+            "JCP",
+            "200",
+            "2025-06-20",
+        ],
+    )?;
+    // The default portfolio has no declarant
+    add_transaction(&home, "VALE3", "buy", "10", "50", "2025-02-03", false)?;
+
+    let reports = run_cmd_json(
+        &home,
+        &["--json", "tax", "report", "2025", "--by-declarant"],
+    )?;
+    let reports = reports.as_array().context("expected an array")?;
+    let by_declarant = |name: Option<&str>| {
+        reports
+            .iter()
+            .find(|r| r["declarant"].as_str() == name)
+            .with_context(|| format!("no report for {:?}", name))
+    };
+    let amount = |report: &Value, key: &str| decimal_from_value(&report[key]);
+    assert_eq!(reports.len(), 3);
+
+    let bruno = by_declarant(Some("Bruno"))?;
+    assert_eq!(bruno["portfolios"], serde_json::json!(["bruno"]));
+    assert_eq!(amount(bruno, "total_sales")?, dec!(15000));
+    assert_eq!(amount(bruno, "total_profit")?, dec!(5000));
+    assert_eq!(amount(bruno, "tax_due")?, dec!(0));
+    assert_eq!(amount(bruno, "dividends")?, dec!(100));
+    assert_eq!(amount(bruno, "jcp")?, dec!(0));
+    let holdings = bruno["holdings"].as_array().context("holdings")?;
+    assert_eq!(holdings.len(), 1);
+    assert_eq!(holdings[0]["ticker"], "PETR4");
+    assert_eq!(decimal_from_value(&holdings[0]["quantity"])?, dec!(500));
+    assert_eq!(decimal_from_value(&holdings[0]["total_cost"])?, dec!(10000));
+
+    let ana = by_declarant(Some("Ana"))?;
+    assert_eq!(amount(ana, "total_sales")?, dec!(30000));
+    assert_eq!(amount(ana, "tax_due")?, dec!(1500));
+    assert_eq!(amount(ana, "dividends")?, dec!(0));
+    assert_eq!(amount(ana, "jcp")?, dec!(200));
+    assert!(ana["holdings"].as_array().context("holdings")?.is_empty());
+
+    let unassigned = by_declarant(None)?;
+    assert_eq!(unassigned["portfolios"], serde_json::json!(["default"]));
+    assert_eq!(amount(unassigned, "total_sales")?, dec!(0));
+    let holdings = unassigned["holdings"].as_array().context("holdings")?;
+    assert_eq!(holdings.len(), 1);
+    assert_eq!(holdings[0]["ticker"], "VALE3");
+    assert_eq!(decimal_from_value(&holdings[0]["total_cost"])?, dec!(500));
+
+    // Together the sales would have been taxed above the exemption
+    let combined = tax_report_json(&home, "2025")?;
+    assert!(decimal_from_value(&combined["annual_total_tax"])? > dec!(1500));
+
+    Ok(())
+}
+
+#[test]
+fn test_tax_report_recomputed_after_asset_type_changes() -> Result<()> {
+    let home = TempDir::new()?;
+    add_asset(&home, "PETR4", "UNKNOWN")?;
+    add_transaction(&home, "PETR4", "buy", "100", "20", "2025-01-10", false)?;
+    add_transaction(&home, "PETR4", "sell", "100", "28", "2025-03-10", false)?;
+
+    // Unclassified, the R$ 2,800 of sales get no exemption
+    let report = tax_report_json(&home, "2025")?;
+    assert!(decimal_from_value(&report["annual_total_tax"])? > dec!(0));
+
+    // As a stock they fall under the R$ 20,000 monthly exemption
+    run_cmd(&home, &["assets", "set-type", "PETR4", "STOCK"])?;
+    let report = tax_report_json(&home, "2025")?;
+    assert_eq!(decimal_from_value(&report["annual_total_tax"])?, dec!(0));
+    Ok(())
+}